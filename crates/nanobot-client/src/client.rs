@@ -0,0 +1,286 @@
+//! HTTP client for the chatweb API.
+
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+
+use crate::error::{ClientError, Result};
+use crate::sse::SseParser;
+use crate::types::{ChatRequest, ChatResponse, Session, SessionSummary, StreamEvent};
+
+/// Default API base URL.
+pub const DEFAULT_BASE_URL: &str = "https://chatweb.ai";
+
+/// Builder for [`Client`].
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    base_url: String,
+    token: Option<String>,
+    timeout: Duration,
+    max_retries: u32,
+    retry_backoff: Duration,
+    user_agent: String,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            token: None,
+            timeout: Duration::from_secs(120),
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(500),
+            user_agent: format!("nanobot-client/{}", env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+impl ClientBuilder {
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Bearer token: an API key (`cw_...`), a login token or a gateway API token.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Per-request timeout (streams are only bounded until the first byte).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Retries for connection errors, 5xx and 429 responses. Streams are
+    /// only retried before any event has been received.
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Delay before the first retry; doubles on each further attempt.
+    pub fn retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let http = reqwest::Client::builder()
+            .user_agent(&self.user_agent)
+            .connect_timeout(Duration::from_secs(10))
+            .build()?;
+        Ok(Client {
+            http,
+            base_url: self.base_url,
+            token: self.token,
+            timeout: self.timeout,
+            max_retries: self.max_retries,
+            retry_backoff: self.retry_backoff,
+        })
+    }
+}
+
+/// Typed client for the chatweb / nanobot HTTP API.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+    timeout: Duration,
+    max_retries: u32,
+    retry_backoff: Duration,
+}
+
+impl Client {
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    /// Client for `base_url` without authentication.
+    pub fn new(base_url: impl Into<String>) -> Result<Self> {
+        Self::builder().base_url(base_url).build()
+    }
+
+    /// Client using `NANOBOT_API_URL` (default [`DEFAULT_BASE_URL`]) and
+    /// `NANOBOT_API_TOKEN` from the environment.
+    pub fn from_env() -> Result<Self> {
+        let mut builder = Self::builder();
+        if let Ok(url) = std::env::var("NANOBOT_API_URL") {
+            builder = builder.base_url(url);
+        }
+        if let Ok(token) = std::env::var("NANOBOT_API_TOKEN") {
+            builder = builder.token(token);
+        }
+        builder.build()
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    fn authed(&self, req: RequestBuilder) -> RequestBuilder {
+        match self.token {
+            Some(ref token) => req.bearer_auth(token),
+            None => req,
+        }
+    }
+
+    /// Send a request built by `make`, retrying retryable failures.
+    async fn send(&self, make: impl Fn() -> RequestBuilder) -> Result<reqwest::Response> {
+        let mut attempt = 0;
+        loop {
+            let err = match self.authed(make()).send().await {
+                Ok(resp) if resp.status().is_success() => return Ok(resp),
+                Ok(resp) => {
+                    let status = resp.status();
+                    let body = resp.text().await.unwrap_or_default();
+                    ClientError::from_status(status.as_u16(), &body)
+                }
+                Err(e) => ClientError::Http(e),
+            };
+            if attempt >= self.max_retries || !err.is_retryable() {
+                return Err(err);
+            }
+            tokio::time::sleep(self.retry_backoff * 2u32.saturating_pow(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let url = self.url(path);
+        let resp = self.send(|| self.http.get(&url).timeout(self.timeout)).await?;
+        Ok(serde_json::from_slice(&resp.bytes().await?)?)
+    }
+
+    /// `POST /api/v1/chat` — one complete reply.
+    pub async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        let url = self.url("/api/v1/chat");
+        let resp = self
+            .send(|| self.http.post(&url).timeout(self.timeout).json(request))
+            .await?;
+        Ok(serde_json::from_slice(&resp.bytes().await?)?)
+    }
+
+    /// `POST /api/v1/chat/stream` — reply as a stream of [`StreamEvent`]s.
+    pub async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+    ) -> Result<impl Stream<Item = Result<StreamEvent>> + Send + 'static> {
+        let url = self.url("/api/v1/chat/stream");
+        let resp = self
+            .send(|| self.http.post(&url).header("Accept", "text/event-stream").json(request))
+            .await?;
+        let mut parser = SseParser::default();
+        let stream = resp.bytes_stream().flat_map(move |chunk| {
+            let items: Vec<Result<StreamEvent>> = match chunk {
+                Ok(bytes) => parser.push(&bytes).into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(ClientError::Stream(e.to_string()))],
+            };
+            futures::stream::iter(items)
+        });
+        Ok(stream)
+    }
+
+    /// Stream a reply and collect its text. `on_event` sees every event
+    /// (tool progress, chunks) as it arrives.
+    pub async fn chat_stream_text(
+        &self,
+        request: &ChatRequest,
+        mut on_event: impl FnMut(&StreamEvent),
+    ) -> Result<String> {
+        let stream = self.chat_stream(request).await?;
+        futures::pin_mut!(stream);
+        let mut text = String::new();
+        while let Some(event) = stream.next().await {
+            let event = event?;
+            on_event(&event);
+            match event {
+                StreamEvent::ContentChunk(ref s) => text.push_str(s),
+                StreamEvent::Content(s) => text = s,
+                StreamEvent::Done { content: Some(s), .. } if text.is_empty() => text = s,
+                StreamEvent::Error(e) => return Err(ClientError::Stream(e)),
+                _ => {}
+            }
+        }
+        Ok(text)
+    }
+
+    /// `GET /api/v1/sessions`
+    pub async fn sessions(&self) -> Result<Vec<SessionSummary>> {
+        self.get_json("/api/v1/sessions").await
+    }
+
+    /// `GET /api/v1/sessions/{id}`
+    pub async fn session(&self, id: &str) -> Result<Session> {
+        self.get_json(&format!("/api/v1/sessions/{}", encode_path(id))).await
+    }
+
+    /// `DELETE /api/v1/sessions/{id}`. Returns false when it did not exist.
+    pub async fn delete_session(&self, id: &str) -> Result<bool> {
+        let url = self.url(&format!("/api/v1/sessions/{}", encode_path(id)));
+        match self.send(|| self.http.delete(&url).timeout(self.timeout)).await {
+            Ok(_) => Ok(true),
+            Err(ClientError::Api { status, .. }) if status == StatusCode::NOT_FOUND.as_u16() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// `GET /health`
+    pub async fn health(&self) -> Result<serde_json::Value> {
+        self.get_json("/health").await
+    }
+}
+
+/// Percent-encode a path segment (session ids contain `:`).
+fn encode_path(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_and_paths() {
+        let client = Client::builder().base_url("http://localhost:3000/").token("t").build().unwrap();
+        assert_eq!(client.url("/api/v1/chat"), "http://localhost:3000/api/v1/chat");
+        assert_eq!(encode_path("api:abc 1"), "api%3Aabc%201");
+    }
+
+    #[test]
+    fn test_error_mapping() {
+        let err = ClientError::from_status(402, r#"{"error":"Out of credits"}"#);
+        assert!(matches!(err, ClientError::InsufficientCredits(ref m) if m == "Out of credits"));
+        assert!(!err.is_retryable());
+        assert!(ClientError::from_status(503, "busy").is_retryable());
+        assert!(ClientError::from_status(429, "{}").is_retryable());
+        assert!(matches!(ClientError::from_status(401, ""), ClientError::Unauthorized(_)));
+    }
+
+    #[tokio::test]
+    async fn test_connection_error_is_reported() {
+        let client = Client::builder()
+            .base_url("http://127.0.0.1:9")
+            .max_retries(1)
+            .retry_backoff(Duration::from_millis(1))
+            .build()
+            .unwrap();
+        let err = client.chat(&ChatRequest::new("hi")).await.unwrap_err();
+        assert!(matches!(err, ClientError::Http(_)));
+    }
+}
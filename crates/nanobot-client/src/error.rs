@@ -0,0 +1,57 @@
+/// Errors returned by [`crate::Client`].
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("API error ({status}): {message}")]
+    Api { status: u16, message: String },
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Insufficient credits: {0}")]
+    InsufficientCredits(String),
+
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+
+    #[error("Invalid response: {0}")]
+    Decode(#[from] serde_json::Error),
+
+    #[error("Stream error: {0}")]
+    Stream(String),
+}
+
+impl ClientError {
+    /// Build an error from a non-success response body.
+    pub(crate) fn from_status(status: u16, body: &str) -> Self {
+        let message = serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|v| {
+                v.get("error")
+                    .or_else(|| v.get("message"))
+                    .and_then(|m| m.as_str())
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| body.trim().to_string());
+        match status {
+            401 | 403 => Self::Unauthorized(message),
+            402 => Self::InsufficientCredits(message),
+            429 => Self::RateLimited(message),
+            _ => Self::Api { status, message },
+        }
+    }
+
+    /// Whether retrying the same request may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Http(e) => e.is_timeout() || e.is_connect(),
+            Self::Api { status, .. } => *status >= 500,
+            Self::RateLimited(_) => true,
+            _ => false,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
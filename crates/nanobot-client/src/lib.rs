@@ -0,0 +1,34 @@
+//! Typed Rust client for the chatweb / nanobot HTTP API.
+//!
+//! ```no_run
+//! use futures::StreamExt;
+//! use nanobot_client::{ChatRequest, Client, StreamEvent};
+//!
+//! # async fn run() -> nanobot_client::Result<()> {
+//! let client = Client::builder()
+//!     .base_url("http://localhost:3000")
+//!     .token("cw_...")
+//!     .build()?;
+//!
+//! let reply = client.chat(&ChatRequest::new("こんにちは").session("api:demo")).await?;
+//! println!("{}", reply.response);
+//!
+//! let stream = client.chat_stream(&ChatRequest::new("Summarize today's news")).await?;
+//! futures::pin_mut!(stream);
+//! while let Some(event) = stream.next().await {
+//!     if let StreamEvent::ContentChunk(text) = event? {
+//!         print!("{}", text);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod client;
+pub mod error;
+mod sse;
+pub mod types;
+
+pub use client::{Client, ClientBuilder, DEFAULT_BASE_URL};
+pub use error::{ClientError, Result};
+pub use types::{ChatRequest, ChatResponse, Session, SessionMessage, SessionSummary, StreamEvent};
@@ -0,0 +1,53 @@
+//! Incremental parser for the server-sent events of `/api/v1/chat/stream`.
+
+use crate::types::StreamEvent;
+
+/// Buffers response chunks and yields complete events.
+#[derive(Debug, Default)]
+pub(crate) struct SseParser {
+    buf: String,
+}
+
+impl SseParser {
+    /// Feed one chunk; returns the events completed by it. Lines that are
+    /// not `data:` JSON (comments, keep-alives) are skipped, and a data line
+    /// holding a JSON array yields one event per element.
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Vec<StreamEvent> {
+        self.buf.push_str(&String::from_utf8_lossy(chunk));
+        let mut events = Vec::new();
+        while let Some(pos) = self.buf.find('\n') {
+            let line: String = self.buf.drain(..=pos).collect();
+            let Some(data) = line.trim_end().strip_prefix("data:") else {
+                continue;
+            };
+            let data = data.trim();
+            if data.is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<serde_json::Value>(data) {
+                Ok(serde_json::Value::Array(items)) => {
+                    events.extend(items.into_iter().map(StreamEvent::from_json));
+                }
+                Ok(value) => events.push(StreamEvent::from_json(value)),
+                Err(_) => continue,
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_split_across_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"data: {\"type\":\"content_chunk\",").is_empty());
+        let events = parser.push(b"\"content\":\"he\"}\r\n\r\n: ping\n\ndata: [{\"type\":\"content_chunk\",\"content\":\"llo\"},{\"type\":\"done\"}]\n\n");
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].text(), Some("he"));
+        assert_eq!(events[1].text(), Some("llo"));
+        assert!(matches!(events[2], StreamEvent::Done { .. }));
+    }
+}
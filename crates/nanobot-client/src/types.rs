@@ -0,0 +1,233 @@
+//! Request and response types of the HTTP API.
+
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /api/v1/chat` and `/api/v1/chat/stream`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChatRequest {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// `pc` | `mobile` | `voice`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// `local` | `cloud` | `auto`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    /// UI language (`ja`, `en`, ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_system_prompt: Option<String>,
+}
+
+impl ChatRequest {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn session(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    pub fn channel(mut self, channel: impl Into<String>) -> Self {
+        self.channel = Some(channel.into());
+        self
+    }
+
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+}
+
+/// Response of `POST /api/v1/chat`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatResponse {
+    pub response: String,
+    pub session_id: String,
+    #[serde(default)]
+    pub agent: Option<String>,
+    #[serde(default)]
+    pub tools_used: Option<Vec<String>>,
+    #[serde(default)]
+    pub credits_used: Option<i64>,
+    #[serde(default)]
+    pub credits_remaining: Option<i64>,
+    #[serde(default)]
+    pub model_used: Option<String>,
+    /// Client action hint (e.g. `upgrade` when credits are exhausted).
+    #[serde(default)]
+    pub action: Option<String>,
+    #[serde(default)]
+    pub input_tokens: Option<u32>,
+    #[serde(default)]
+    pub output_tokens: Option<u32>,
+    #[serde(default)]
+    pub estimated_cost_usd: Option<f64>,
+    #[serde(default)]
+    pub mode: Option<String>,
+    /// Why the request failed (`rate_limited`, `timeout`, ...) when `response` is an error message.
+    #[serde(default)]
+    pub error_code: Option<String>,
+}
+
+/// One event of the `/api/v1/chat/stream` SSE stream.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    Start {
+        session_id: Option<String>,
+        agent: Option<String>,
+    },
+    Thinking {
+        content: String,
+    },
+    ToolStart {
+        tool: String,
+    },
+    ToolResult {
+        tool: String,
+        success: bool,
+        result: Option<String>,
+    },
+    /// Incremental text.
+    ContentChunk(String),
+    /// Complete text (sent instead of chunks by some paths).
+    Content(String),
+    Done {
+        content: Option<String>,
+        credits_used: Option<i64>,
+        credits_remaining: Option<i64>,
+    },
+    Error(String),
+    /// Any other event type, kept as raw JSON.
+    Other(serde_json::Value),
+}
+
+impl StreamEvent {
+    pub fn from_json(value: serde_json::Value) -> Self {
+        let str_field = |key: &str| value.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        let int_field = |key: &str| value.get(key).and_then(|v| v.as_i64());
+        match value.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+            "start" => Self::Start {
+                session_id: str_field("session_id"),
+                agent: str_field("agent"),
+            },
+            "thinking" => Self::Thinking {
+                content: str_field("content").unwrap_or_default(),
+            },
+            "tool_start" => Self::ToolStart {
+                tool: str_field("tool").unwrap_or_default(),
+            },
+            "tool_result" => Self::ToolResult {
+                tool: str_field("tool").unwrap_or_default(),
+                success: value.get("success").and_then(|v| v.as_bool()).unwrap_or(true),
+                result: str_field("result").or_else(|| str_field("summary")),
+            },
+            "content_chunk" => Self::ContentChunk(str_field("content").unwrap_or_default()),
+            "content" => Self::Content(str_field("content").unwrap_or_default()),
+            "done" => Self::Done {
+                content: str_field("content"),
+                credits_used: int_field("credits_used"),
+                credits_remaining: int_field("credits_remaining"),
+            },
+            "error" => Self::Error(str_field("content").or_else(|| str_field("error")).unwrap_or_default()),
+            _ => Self::Other(value),
+        }
+    }
+
+    /// Text carried by content events.
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            Self::ContentChunk(s) | Self::Content(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Entry of `GET /api/v1/sessions`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionSummary {
+    pub key: String,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+/// One message of a session history.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionMessage {
+    pub role: String,
+    pub content: String,
+    #[serde(default)]
+    pub timestamp: Option<String>,
+}
+
+/// Response of `GET /api/v1/sessions/{id}`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Session {
+    pub key: String,
+    /// Unified key when the session is linked across channels.
+    #[serde(default)]
+    pub resolved_key: Option<String>,
+    #[serde(default)]
+    pub messages: Vec<SessionMessage>,
+    #[serde(default)]
+    pub total_message_count: usize,
+    #[serde(default)]
+    pub linked_channels: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_event_from_json() {
+        let e = StreamEvent::from_json(serde_json::json!({"type": "tool_start", "tool": "web_search"}));
+        assert_eq!(e, StreamEvent::ToolStart { tool: "web_search".into() });
+
+        let e = StreamEvent::from_json(serde_json::json!({"type": "done", "credits_used": 3, "credits_remaining": 97}));
+        assert_eq!(e, StreamEvent::Done { content: None, credits_used: Some(3), credits_remaining: Some(97) });
+
+        let e = StreamEvent::from_json(serde_json::json!({"type": "content_chunk", "content": "hi"}));
+        assert_eq!(e.text(), Some("hi"));
+
+        let e = StreamEvent::from_json(serde_json::json!({"type": "purchase_confirm", "amount": 1}));
+        assert!(matches!(e, StreamEvent::Other(_)));
+    }
+
+    #[test]
+    fn test_chat_request_skips_unset_fields() {
+        let body = serde_json::to_value(ChatRequest::new("hello").session("api:1").temperature(0.2)).unwrap();
+        assert_eq!(body, serde_json::json!({"message": "hello", "session_id": "api:1", "temperature": 0.2}));
+    }
+}
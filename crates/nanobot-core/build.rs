@@ -37,6 +37,13 @@ fn main() {
 
     println!("cargo:rustc-env=REPO_URL={}", repo_url);
 
+    // gRPC service definitions (requires `protoc`)
+    #[cfg(feature = "grpc")]
+    {
+        tonic_build::compile_protos("proto/nanobot.proto").expect("Failed to compile proto/nanobot.proto");
+        println!("cargo:rerun-if-changed=proto/nanobot.proto");
+    }
+
     println!("cargo:rerun-if-changed=../../.git/HEAD");
     println!("cargo:rerun-if-changed=../../.git/refs/heads/");
     println!("cargo:rerun-if-env-changed=BUILD_NUMBER");
@@ -0,0 +1,526 @@
+//! Embedding API: build an agent in-process without the CLI or gateway.
+//!
+//! ```no_run
+//! # async fn run(provider: std::sync::Arc<dyn nanobot_core::provider::LlmProvider>) -> anyhow::Result<()> {
+//! use nanobot_core::agent::builder::{AgentBuilder, AgentEvent};
+//!
+//! let agent = AgentBuilder::new(provider, "/tmp/my-app/workspace")
+//!     .model("claude-sonnet-4-5")
+//!     .without_tool("exec")
+//!     .build()?;
+//!
+//! let mut events = agent.subscribe();
+//! tokio::spawn(async move {
+//!     while let Ok(AgentEvent::ToolStarted { name, .. }) = events.recv().await {
+//!         println!("using {name}");
+//!     }
+//! });
+//! let reply = agent.ask("user-42", "What's in my notes?").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tokio::sync::{broadcast, mpsc, Mutex};
+
+use crate::config::{Config, ExecToolConfig};
+use crate::hooks::{ResponseHook, ResponseHooks};
+use crate::memory::backend::MemoryBackend;
+use crate::provider::{self, LlmProvider};
+use crate::service::cron::CronService;
+use crate::session::store::SessionStore;
+use crate::tool::Tool;
+use crate::types::{InboundMessage, OutboundMessage};
+use crate::webhook::WebhookOutbox;
+
+use super::stream::StreamEvent;
+use super::{AgentLoop, CodeVerificationReport};
+
+/// Channel name used by [`Agent::ask`].
+pub const EMBED_CHANNEL: &str = "embed";
+
+/// Progress of an embedded agent, delivered to subscribers (see [`Agent::subscribe`]).
+#[derive(Debug, Clone)]
+pub enum AgentEvent {
+    ToolStarted {
+        name: String,
+        arguments: HashMap<String, serde_json::Value>,
+    },
+    ToolFinished {
+        name: String,
+        duration_ms: u64,
+        result: String,
+    },
+    /// Final reply to a message.
+    Response {
+        channel: String,
+        chat_id: String,
+        content: String,
+    },
+}
+
+/// Builder for an in-process [`Agent`].
+pub struct AgentBuilder {
+    provider: Arc<dyn LlmProvider>,
+    workspace: PathBuf,
+    model: Option<String>,
+    max_iterations: u32,
+    brave_api_key: Option<String>,
+    exec_config: ExecToolConfig,
+    restrict_to_workspace: bool,
+    timezone: Option<String>,
+    sessions: Option<Box<dyn SessionStore>>,
+    memory: Option<Box<dyn MemoryBackend>>,
+    tools: Vec<Arc<dyn Tool>>,
+    without_tools: Vec<String>,
+    only_tools: Option<Vec<String>>,
+    cron: Option<Arc<Mutex<CronService>>>,
+    webhooks: Option<Arc<Mutex<WebhookOutbox>>>,
+    response_hooks: ResponseHooks,
+    queue_size: usize,
+    event_capacity: usize,
+}
+
+impl AgentBuilder {
+    /// Agent using `provider`, keeping sessions and memory under `workspace`.
+    pub fn new(provider: Arc<dyn LlmProvider>, workspace: impl Into<PathBuf>) -> Self {
+        Self {
+            provider,
+            workspace: workspace.into(),
+            model: None,
+            max_iterations: 20,
+            brave_api_key: None,
+            exec_config: ExecToolConfig::default(),
+            restrict_to_workspace: true,
+            timezone: None,
+            sessions: None,
+            memory: None,
+            tools: Vec::new(),
+            without_tools: Vec::new(),
+            only_tools: None,
+            cron: None,
+            webhooks: None,
+            response_hooks: ResponseHooks::default(),
+            queue_size: 64,
+            event_capacity: 256,
+        }
+    }
+
+    /// Builder preset from a loaded config: provider, model, workspace and
+    /// tool settings as the gateway would use them.
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let model = config.agents.defaults.model.clone();
+        let api_key = config.get_api_key(None);
+        if api_key.is_none() && !model.starts_with("bedrock/") {
+            anyhow::bail!("No API key configured for model {}", model);
+        }
+        let provider: Arc<dyn LlmProvider> = Arc::from(provider::create_provider(
+            api_key.unwrap_or(""),
+            config.get_api_base(None),
+            &model,
+        ));
+        let search_key = &config.tools.web.search.api_key;
+        Ok(Self::new(provider, config.workspace_path())
+            .model(model)
+            .max_iterations(config.agents.defaults.max_tool_iterations)
+            .brave_api_key((!search_key.is_empty()).then(|| search_key.clone()))
+            .exec_config(config.tools.exec_config.clone())
+            .restrict_to_workspace(config.tools.restrict_to_workspace)
+            .timezone(config.agents.defaults.timezone.clone())
+            .response_hooks(ResponseHooks::from_config(&config.agents.hooks)))
+    }
+
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Maximum LLM → tool rounds per message.
+    pub fn max_iterations(mut self, max_iterations: u32) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    pub fn brave_api_key(mut self, key: Option<String>) -> Self {
+        self.brave_api_key = key;
+        self
+    }
+
+    pub fn exec_config(mut self, exec_config: ExecToolConfig) -> Self {
+        self.exec_config = exec_config;
+        self
+    }
+
+    /// Confine file and shell tools to the workspace (default: on).
+    pub fn restrict_to_workspace(mut self, restrict: bool) -> Self {
+        self.restrict_to_workspace = restrict;
+        self
+    }
+
+    pub fn timezone(mut self, tz: Option<String>) -> Self {
+        self.timezone = tz;
+        self
+    }
+
+    /// Session storage (default: JSONL files in the workspace).
+    pub fn session_store(mut self, store: Box<dyn SessionStore>) -> Self {
+        self.sessions = Some(store);
+        self
+    }
+
+    /// Long-term memory backend (default: files in the workspace).
+    pub fn memory(mut self, memory: Box<dyn MemoryBackend>) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Register an additional tool (replaces a built-in of the same name).
+    pub fn tool(mut self, tool: Arc<dyn Tool>) -> Self {
+        self.tools.push(tool);
+        self
+    }
+
+    /// Drop a built-in tool (`exec`, `write_file`, `web_search`, ...).
+    pub fn without_tool(mut self, name: impl Into<String>) -> Self {
+        self.without_tools.push(name.into());
+        self
+    }
+
+    /// Keep only these tools (applied after [`Self::tool`] registrations).
+    pub fn only_tools<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.only_tools = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Enable reminders backed by `cron`.
+    pub fn cron_service(mut self, cron: Arc<Mutex<CronService>>) -> Self {
+        self.cron = Some(cron);
+        self
+    }
+
+    pub fn webhooks(mut self, outbox: Arc<Mutex<WebhookOutbox>>) -> Self {
+        self.webhooks = Some(outbox);
+        self
+    }
+
+    /// Replace the post-processing hooks (see [`crate::hooks`]).
+    pub fn response_hooks(mut self, hooks: ResponseHooks) -> Self {
+        self.response_hooks = hooks;
+        self
+    }
+
+    /// Run `hook` over final replies, after any already registered.
+    pub fn response_hook(mut self, hook: Arc<dyn ResponseHook>) -> Self {
+        self.response_hooks.register(hook);
+        self
+    }
+
+    /// Events buffered per subscriber before slow ones start lagging.
+    pub fn event_capacity(mut self, capacity: usize) -> Self {
+        self.event_capacity = capacity.max(1);
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<Agent> {
+        std::fs::create_dir_all(&self.workspace)?;
+        let (inbound_tx, inbound_rx) = mpsc::channel(self.queue_size);
+        let (outbound_tx, outbound_rx) = mpsc::channel(self.queue_size);
+        let (events, _) = broadcast::channel(self.event_capacity);
+
+        let mut inner = AgentLoop::from_senders(
+            inbound_tx.clone(),
+            outbound_tx,
+            self.provider,
+            self.workspace,
+            self.model,
+            self.max_iterations,
+            self.brave_api_key,
+            self.exec_config,
+            self.restrict_to_workspace,
+            None,
+        )
+        .with_timezone(self.timezone)
+        .with_response_hooks(self.response_hooks);
+        if let Some(memory) = self.memory {
+            inner.context = inner.context.with_memory_backend(memory);
+        }
+        if let Some(sessions) = self.sessions {
+            inner.sessions = sessions;
+        }
+        if let Some(cron) = self.cron {
+            inner = inner.with_cron_service(cron);
+        }
+        if let Some(outbox) = self.webhooks {
+            inner = inner.with_webhooks(outbox);
+        }
+        for tool in self.tools {
+            inner.tools.register(tool);
+        }
+        for name in &self.without_tools {
+            inner.tools.unregister(name);
+        }
+        if let Some(ref keep) = self.only_tools {
+            for name in inner.tools.tool_names() {
+                if !keep.contains(&name) {
+                    inner.tools.unregister(&name);
+                }
+            }
+        }
+        inner.events = Some(events.clone());
+
+        Ok(Agent {
+            inner: Mutex::new(inner),
+            events,
+            inbound_tx,
+            inbound_rx: Mutex::new(inbound_rx),
+            outbound_rx: Mutex::new(outbound_rx),
+        })
+    }
+}
+
+/// Handle to an in-process agent. Messages are processed one at a time.
+pub struct Agent {
+    inner: Mutex<AgentLoop>,
+    events: broadcast::Sender<AgentEvent>,
+    inbound_tx: mpsc::Sender<InboundMessage>,
+    inbound_rx: Mutex<mpsc::Receiver<InboundMessage>>,
+    outbound_rx: Mutex<mpsc::Receiver<OutboundMessage>>,
+}
+
+impl Agent {
+    /// Process one message and return the reply (`None` for messages that
+    /// need no reply, such as feedback reactions).
+    pub async fn send(&self, msg: InboundMessage) -> anyhow::Result<Option<OutboundMessage>> {
+        self.inner.lock().await.process_message(&msg).await
+    }
+
+    /// Ask on behalf of `user_id` (session `embed:<user_id>`) and return the reply text.
+    pub async fn ask(&self, user_id: &str, content: &str) -> anyhow::Result<String> {
+        let msg = InboundMessage::new(EMBED_CHANNEL, user_id, user_id, content);
+        Ok(self.send(msg).await?.map(|r| r.content).unwrap_or_default())
+    }
+
+    /// [`Agent::ask`], streaming the reply to `events` as it is written
+    /// (see [`super::stream`]).
+    pub async fn ask_stream(
+        &self,
+        user_id: &str,
+        content: &str,
+        events: mpsc::UnboundedSender<StreamEvent>,
+    ) -> anyhow::Result<String> {
+        let session_key = format!("{}:{}", EMBED_CHANNEL, user_id);
+        self.inner
+            .lock()
+            .await
+            .process_direct_stream(content, &session_key, EMBED_CHANNEL, user_id, events)
+            .await
+    }
+
+    /// Subscribe to tool and response events.
+    pub fn subscribe(&self) -> broadcast::Receiver<AgentEvent> {
+        self.events.subscribe()
+    }
+
+    /// Next message the agent sent on its own (e.g. via the `message` tool).
+    pub async fn next_outbound(&self) -> Option<OutboundMessage> {
+        self.outbound_rx.lock().await.recv().await
+    }
+
+    /// Queue a message for [`Self::run`] instead of processing it now.
+    pub async fn enqueue(&self, msg: InboundMessage) -> anyhow::Result<()> {
+        self.inbound_tx.send(msg).await.map_err(|e| anyhow::anyhow!("agent queue closed: {e}"))
+    }
+
+    /// Process messages queued with [`Self::enqueue`] (and subagent
+    /// announces), passing each reply to `on_reply`. Runs until cancelled.
+    pub async fn run(&self, mut on_reply: impl FnMut(OutboundMessage)) {
+        let mut inbound = self.inbound_rx.lock().await;
+        while let Some(msg) = inbound.recv().await {
+            match self.send(msg).await {
+                Ok(Some(reply)) => on_reply(reply),
+                Ok(None) => {}
+                Err(e) => tracing::error!("Embedded agent error: {}", e),
+            }
+        }
+    }
+
+    /// Run one of the agent's tools directly, without the LLM.
+    pub async fn call_tool(&self, name: &str, args: HashMap<String, serde_json::Value>) -> String {
+        let tools = self.inner.lock().await.tools.clone();
+        tools.execute(name, args).await
+    }
+
+    /// Names of the tools the agent can call.
+    pub async fn tool_names(&self) -> Vec<String> {
+        self.inner.lock().await.tools.tool_names()
+    }
+
+    /// Run `run_linter` and `run_tests` for the project at `path`.
+    pub async fn verify_code(&self, language: &str, path: &str) -> anyhow::Result<CodeVerificationReport> {
+        self.inner.lock().await.verify_code(language, path).await
+    }
+
+    /// Check and let the LLM fix failures, up to `max_iterations` rounds.
+    /// Returns the last report and the number of rounds.
+    pub async fn self_correct(
+        &self,
+        language: &str,
+        path: &str,
+        max_iterations: u32,
+    ) -> anyhow::Result<(CodeVerificationReport, u32)> {
+        self.inner
+            .lock()
+            .await
+            .self_correct_report(language, path, max_iterations)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ProviderError;
+    use crate::types::{CompletionResponse, FinishReason, Message, Role, TokenUsage, ToolCall};
+    use async_trait::async_trait;
+
+    /// Calls `echo` once, then answers with the tool result.
+    struct ScriptedProvider;
+
+    #[async_trait]
+    impl LlmProvider for ScriptedProvider {
+        async fn chat(
+            &self,
+            messages: &[Message],
+            _tools: Option<&[serde_json::Value]>,
+            _model: &str,
+            _max_tokens: u32,
+            _temperature: f64,
+        ) -> Result<CompletionResponse, ProviderError> {
+            let tool_result = messages.iter().rev()
+                .find(|m| matches!(m.role, Role::Tool))
+                .and_then(|m| m.content.clone());
+            let (content, tool_calls) = match tool_result {
+                Some(result) => (Some(format!("echoed {}", result)), vec![]),
+                None => (None, vec![ToolCall {
+                    id: "call_1".into(),
+                    name: "echo".into(),
+                    arguments: HashMap::from([("text".to_string(), serde_json::json!("hi"))]),
+                }]),
+            };
+            Ok(CompletionResponse {
+                content,
+                finish_reason: if tool_calls.is_empty() { FinishReason::Stop } else { FinishReason::ToolCalls },
+                tool_calls,
+                usage: TokenUsage::default(),
+                model: None,
+            })
+        }
+
+        fn default_model(&self) -> &str {
+            "scripted"
+        }
+    }
+
+    struct EchoTool;
+
+    #[async_trait]
+    impl Tool for EchoTool {
+        fn name(&self) -> &str {
+            "echo"
+        }
+        fn description(&self) -> &str {
+            "Echo the text back"
+        }
+        fn parameters(&self) -> serde_json::Value {
+            serde_json::json!({"type": "object", "properties": {"text": {"type": "string"}}})
+        }
+        async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+            params.get("text").and_then(|v| v.as_str()).unwrap_or_default().to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embedded_agent_runs_tools_and_emits_events() {
+        let tmp = tempfile::tempdir().unwrap();
+        let agent = AgentBuilder::new(Arc::new(ScriptedProvider), tmp.path())
+            .tool(Arc::new(EchoTool))
+            .only_tools(["echo", "read_file"])
+            .build()
+            .unwrap();
+
+        let mut names = agent.tool_names().await;
+        names.sort();
+        assert_eq!(names, vec!["echo", "read_file"]);
+
+        let mut events = agent.subscribe();
+        let reply = agent.ask("u1", "say hi").await.unwrap();
+        assert_eq!(reply, "echoed hi");
+
+        assert!(matches!(events.recv().await.unwrap(), AgentEvent::ToolStarted { ref name, .. } if name == "echo"));
+        assert!(matches!(events.recv().await.unwrap(), AgentEvent::ToolFinished { ref result, .. } if result == "hi"));
+        match events.recv().await.unwrap() {
+            AgentEvent::Response { channel, chat_id, content } => {
+                assert_eq!((channel.as_str(), chat_id.as_str()), (EMBED_CHANNEL, "u1"));
+                assert_eq!(content, "echoed hi");
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ask_stream() {
+        let tmp = tempfile::tempdir().unwrap();
+        let agent = AgentBuilder::new(Arc::new(ScriptedProvider), tmp.path())
+            .tool(Arc::new(EchoTool))
+            .build()
+            .unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        assert_eq!(agent.ask_stream("u1", "say hi", tx).await.unwrap(), "echoed hi");
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        assert!(matches!(&events[0], StreamEvent::ToolStart { tool, iteration: 1, .. } if tool == "echo"));
+        assert!(matches!(&events[1], StreamEvent::ToolResult { result, is_error: false, .. } if result == "hi"));
+        assert!(matches!(&events[2], StreamEvent::Thinking { tool_count: 1, .. }));
+        assert_eq!(
+            events[3..],
+            [
+                StreamEvent::ContentChunk { text: "echoed hi".into() },
+                StreamEvent::Content { content: "echoed hi".into() },
+                StreamEvent::Done,
+            ]
+        );
+    }
+
+    struct Signature;
+
+    #[async_trait]
+    impl ResponseHook for Signature {
+        fn name(&self) -> &str {
+            "signature"
+        }
+        async fn process(&self, reply: String, ctx: &crate::hooks::HookContext<'_>) -> String {
+            format!("{} — via {}", reply, ctx.channel)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_response_hook() {
+        let tmp = tempfile::tempdir().unwrap();
+        let agent = AgentBuilder::new(Arc::new(ScriptedProvider), tmp.path())
+            .tool(Arc::new(EchoTool))
+            .response_hook(Arc::new(Signature))
+            .build()
+            .unwrap();
+        assert_eq!(agent.ask("u1", "say hi").await.unwrap(), "echoed hi — via embed");
+    }
+}
@@ -8,18 +8,29 @@ use crate::types::Message;
 
 #[cfg(feature = "dynamodb-backend")]
 use crate::agent::personality::PersonalityBackend;
+#[cfg(feature = "code-intel")]
+use crate::code_intel::SymbolIndex;
 
 /// Builds the context (system prompt + messages) for the agent.
 pub struct ContextBuilder {
     workspace: PathBuf,
     memory: Box<dyn MemoryBackend>,
     skills: SkillsLoader,
+    /// User timezone for the identity clock (server local time when unset).
+    timezone: Option<String>,
     #[cfg(feature = "dynamodb-backend")]
     personality_backend: Option<Arc<dyn PersonalityBackend>>,
     #[cfg(feature = "dynamodb-backend")]
     user_id: Option<String>,
+    /// Source of the repo map; `None` leaves it out of the prompt.
+    #[cfg(feature = "code-intel")]
+    symbols: Option<Arc<SymbolIndex>>,
 }
 
+/// Size budget for the repo map section of the system prompt.
+#[cfg(feature = "code-intel")]
+const REPO_MAP_CHARS: usize = 4000;
+
 const BOOTSTRAP_FILES: &[&str] = &["AGENTS.md", "SOUL.md", "USER.md", "TOOLS.md", "IDENTITY.md"];
 
 impl ContextBuilder {
@@ -28,10 +39,13 @@ impl ContextBuilder {
             workspace: workspace.to_path_buf(),
             memory: Box::new(MemoryStore::new(workspace)),
             skills: SkillsLoader::new(workspace, None),
+            timezone: None,
             #[cfg(feature = "dynamodb-backend")]
             personality_backend: None,
             #[cfg(feature = "dynamodb-backend")]
             user_id: None,
+            #[cfg(feature = "code-intel")]
+            symbols: None,
         }
     }
 
@@ -41,13 +55,38 @@ impl ContextBuilder {
             workspace: workspace.to_path_buf(),
             memory,
             skills: SkillsLoader::new(workspace, None),
+            timezone: None,
             #[cfg(feature = "dynamodb-backend")]
             personality_backend: None,
             #[cfg(feature = "dynamodb-backend")]
             user_id: None,
+            #[cfg(feature = "code-intel")]
+            symbols: None,
         }
     }
 
+    /// Use `tz` for the current time in the prompt and for the daily memory
+    /// rollover. Replaces the memory backend with a timezone-aware file store.
+    pub fn with_timezone(mut self, tz: Option<String>) -> Self {
+        self.memory = Box::new(MemoryStore::new(&self.workspace).with_timezone(tz.clone()));
+        self.timezone = tz;
+        self
+    }
+
+    /// Swap the memory backend, keeping the timezone and skills.
+    pub fn with_memory_backend(mut self, memory: Box<dyn MemoryBackend>) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Add a map of the workspace's source files (definitions per file) to
+    /// the system prompt. Omitted while the workspace has no source files.
+    #[cfg(feature = "code-intel")]
+    pub fn with_symbol_index(mut self, symbols: Arc<SymbolIndex>) -> Self {
+        self.symbols = Some(symbols);
+        self
+    }
+
     /// Set personality backend and user ID for behavioral learning
     #[cfg(feature = "dynamodb-backend")]
     pub fn with_personality(
@@ -73,6 +112,20 @@ impl ContextBuilder {
             parts.push(bootstrap);
         }
 
+        // Repository map for multi-file code work
+        #[cfg(feature = "code-intel")]
+        if let Some(ref symbols) = self.symbols {
+            symbols.refresh();
+            let map = symbols.repo_map(REPO_MAP_CHARS);
+            if !map.is_empty() {
+                parts.push(format!(
+                    "# Repository Map\n\n\
+                    Definitions per source file in the workspace. Use find_definition or list_symbols \
+                    to locate code before reading whole files.\n\n{map}"
+                ));
+            }
+        }
+
         // Learned personality preferences (DynamoDB backend only)
         #[cfg(feature = "dynamodb-backend")]
         if let (Some(backend), Some(user_id)) = (&self.personality_backend, &self.user_id) {
@@ -120,7 +173,9 @@ impl ContextBuilder {
     }
 
     fn get_identity(&self) -> String {
-        let now = chrono::Local::now().format("%Y-%m-%d %H:%M (%A)").to_string();
+        let now = crate::util::now_in(self.timezone.as_deref())
+            .format("%Y-%m-%d %H:%M (%A, UTC%:z)")
+            .to_string();
         let workspace_path = self
             .workspace
             .canonicalize()
@@ -0,0 +1,240 @@
+//! Confidence-based escalation between model tiers.
+//!
+//! With `agents.escalation.enabled`, a request is answered by the economy
+//! model first, which is asked to end its reply with a `[confidence: N]`
+//! line. If N is below `minConfidence`, or the optional verifier model flags
+//! a problem, the request is re-run on the powerful model and its answer
+//! replaces the first one. The marker is always stripped before a reply is
+//! shown. Each escalation is recorded with the cost of both attempts.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::EscalationConfig;
+use crate::provider::LlmProvider;
+use crate::types::{Message, Role, TokenUsage};
+
+/// File (under the data dir) where escalations are appended.
+pub const ESCALATION_FILE: &str = "escalations.jsonl";
+
+const CONFIDENCE_INSTRUCTION: &str = "\n\n## Confidence\n\
+After your answer, add a last line `[confidence: N]` where N (0-100) is how sure you are \
+that the answer is correct and complete. Be honest: use a low number when guessing, \
+when facts may be outdated or when the question is outside what you can do well.";
+
+const VERIFIER_PROMPT: &str = "You review answers written by another assistant. \
+Reply `OK` if the answer is correct and addresses the question. Otherwise reply \
+`ISSUE: ` followed by the main problem in one sentence.";
+
+static CONFIDENCE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\[\s*confidence\s*[:：=]\s*(\d{1,3})\s*%?\s*\]").unwrap());
+
+/// Remove `[confidence: N]` markers; returns the text and the last N as 0.0–1.0.
+pub fn split_confidence(text: &str) -> (String, Option<f64>) {
+    let confidence = CONFIDENCE_RE
+        .captures_iter(text)
+        .last()
+        .and_then(|c| c[1].parse::<f64>().ok())
+        .map(|n| (n / 100.0).clamp(0.0, 1.0));
+    let clean = CONFIDENCE_RE.replace_all(text, "").trim_end().to_string();
+    (clean, confidence)
+}
+
+/// Why a reply was escalated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EscalationReason {
+    LowConfidence { confidence: f64 },
+    /// The economy model didn't report a confidence.
+    NoConfidence,
+    Verifier { issue: String },
+}
+
+/// The economy model's reply, ready to show unless `escalate` is set.
+#[derive(Debug, Clone)]
+pub struct Assessment {
+    pub answer: String,
+    pub confidence: Option<f64>,
+    pub escalate: Option<EscalationReason>,
+}
+
+/// One escalated request and what both attempts cost.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EscalationRecord {
+    pub timestamp: String,
+    pub channel: String,
+    pub reason: EscalationReason,
+    pub economy_model: String,
+    pub economy_cost_usd: f64,
+    pub powerful_model: String,
+    pub powerful_cost_usd: f64,
+}
+
+impl EscalationRecord {
+    pub fn new(
+        channel: &str,
+        reason: EscalationReason,
+        (economy_model, economy_usage): (&str, &TokenUsage),
+        (powerful_model, powerful_usage): (&str, &TokenUsage),
+    ) -> Self {
+        let cost = |model: &str, usage: &TokenUsage| {
+            crate::provider::pricing::calculate_cost(model, usage.prompt_tokens, usage.completion_tokens)
+        };
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            channel: channel.to_string(),
+            reason,
+            economy_model: economy_model.to_string(),
+            economy_cost_usd: cost(economy_model, economy_usage),
+            powerful_model: powerful_model.to_string(),
+            powerful_cost_usd: cost(powerful_model, powerful_usage),
+        }
+    }
+
+    pub fn total_cost_usd(&self) -> f64 {
+        self.economy_cost_usd + self.powerful_cost_usd
+    }
+}
+
+pub fn default_log_path() -> PathBuf {
+    crate::config::get_data_dir().join(ESCALATION_FILE)
+}
+
+pub fn append_record(path: &Path, record: &EscalationRecord) -> std::io::Result<()> {
+    let line = serde_json::to_string(record)?;
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{line}")
+}
+
+/// Thresholds and models from `agents.escalation`.
+#[derive(Debug, Clone)]
+pub struct EscalationPolicy {
+    config: EscalationConfig,
+}
+
+impl EscalationPolicy {
+    /// `None` unless escalation is enabled.
+    pub fn from_config(config: &EscalationConfig) -> Option<Self> {
+        config.enabled.then(|| Self { config: config.clone() })
+    }
+
+    /// Configured economy model; `None` means the caller's economy tier.
+    pub fn economy_model(&self) -> Option<&str> {
+        Some(self.config.economy_model.trim()).filter(|m| !m.is_empty())
+    }
+
+    /// Configured powerful model; `None` means the caller's powerful tier.
+    pub fn powerful_model(&self) -> Option<&str> {
+        Some(self.config.powerful_model.trim()).filter(|m| !m.is_empty())
+    }
+
+    /// Ask for a confidence line by extending the system prompt.
+    pub fn instruct(&self, messages: &mut Vec<Message>) {
+        match messages.iter_mut().find(|m| m.role == Role::System) {
+            Some(system) => {
+                let content = system.content.get_or_insert_with(String::new);
+                content.push_str(CONFIDENCE_INSTRUCTION);
+            }
+            None => messages.insert(0, Message::system(CONFIDENCE_INSTRUCTION.trim_start())),
+        }
+    }
+
+    /// Strip the confidence marker from the economy reply and decide whether
+    /// to escalate. The verifier (if configured) only runs when the reported
+    /// confidence is high enough on its own; its failures are ignored.
+    pub async fn assess(&self, provider: &dyn LlmProvider, question: &str, reply: &str) -> Assessment {
+        let (answer, confidence) = split_confidence(reply);
+        let mut escalate = match confidence {
+            Some(c) if c < self.config.min_confidence => Some(EscalationReason::LowConfidence { confidence: c }),
+            None if self.config.escalate_without_confidence => Some(EscalationReason::NoConfidence),
+            _ => None,
+        };
+        if escalate.is_none() {
+            if let Some(model) = self.config.verifier_model.as_deref().filter(|m| !m.trim().is_empty()) {
+                if let Some(issue) = verify(provider, model, question, &answer).await {
+                    escalate = Some(EscalationReason::Verifier { issue });
+                }
+            }
+        }
+        Assessment { answer, confidence, escalate }
+    }
+}
+
+/// Ask `model` to check `answer`; the issue it found, if any.
+async fn verify(provider: &dyn LlmProvider, model: &str, question: &str, answer: &str) -> Option<String> {
+    let messages = vec![
+        Message::system(VERIFIER_PROMPT),
+        Message::user(format!("Question:\n{}\n\nAnswer:\n{}", question, answer)),
+    ];
+    match provider.chat(&messages, None, model, 200, 0.0).await {
+        Ok(resp) => parse_verdict(&resp.content.unwrap_or_default()),
+        Err(e) => {
+            tracing::debug!("Verifier {} failed: {}", model, e);
+            None
+        }
+    }
+}
+
+fn parse_verdict(verdict: &str) -> Option<String> {
+    let verdict = verdict.trim();
+    let rest = verdict.strip_prefix("ISSUE").or_else(|| verdict.strip_prefix("Issue"))?;
+    let issue = rest.trim_start_matches([':', '：', ' ']).trim();
+    Some(if issue.is_empty() { "unspecified".to_string() } else { issue.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> EscalationPolicy {
+        EscalationPolicy::from_config(&EscalationConfig { enabled: true, ..Default::default() }).unwrap()
+    }
+
+    #[test]
+    fn test_split_confidence() {
+        assert_eq!(
+            split_confidence("Canberra is the capital.\n[confidence: 92]"),
+            ("Canberra is the capital.".to_string(), Some(0.92))
+        );
+        assert_eq!(split_confidence("たぶん晴れです\n[Confidence：40%]").1, Some(0.4));
+        assert_eq!(split_confidence("no marker"), ("no marker".to_string(), None));
+        assert_eq!(split_confidence("[confidence: 250]").1, Some(1.0));
+    }
+
+    #[test]
+    fn test_parse_verdict() {
+        assert_eq!(parse_verdict("OK"), None);
+        assert_eq!(parse_verdict("ISSUE: the date is wrong").as_deref(), Some("the date is wrong"));
+        assert_eq!(parse_verdict("ISSUE").as_deref(), Some("unspecified"));
+    }
+
+    #[test]
+    fn test_instruct_extends_system_prompt() {
+        let mut messages = vec![Message::system("You are helpful."), Message::user("hi")];
+        policy().instruct(&mut messages);
+        assert!(messages[0].content.as_deref().unwrap().contains("[confidence: N]"));
+        assert_eq!(messages.len(), 2);
+
+        let mut bare = vec![Message::user("hi")];
+        policy().instruct(&mut bare);
+        assert_eq!(bare[0].role, Role::System);
+    }
+
+    #[test]
+    fn test_record_costs() {
+        let usage = TokenUsage { prompt_tokens: 1_000, completion_tokens: 500, total_tokens: 1_500 };
+        let record = EscalationRecord::new(
+            "telegram",
+            EscalationReason::LowConfidence { confidence: 0.3 },
+            ("deepseek-chat", &usage),
+            ("claude-opus-4-6", &usage),
+        );
+        assert!(record.powerful_cost_usd > record.economy_cost_usd);
+        assert_eq!(record.total_cost_usd(), record.economy_cost_usd + record.powerful_cost_usd);
+    }
+}
@@ -0,0 +1,363 @@
+//! Handing conversations to a human operator.
+//!
+//! With `agents.handoff.enabled`, a user can ask for a person with `/human`
+//! (or one of the configured trigger phrases), and the model can hand off a
+//! conversation it can't resolve by ending its reply with [`HANDOFF_MARKER`].
+//! The session is then paused: the bot stops answering, the operator chat
+//! (`agents.handoff.operator`) is told and gets every further user message,
+//! and the operator's replies are relayed back to the user. `/resume` from
+//! either side gives the conversation back to the bot.
+//!
+//! Operator commands: `/reply <session> <text>`, `/resume [session]` and
+//! `/handoffs`; plain text goes to the session the operator last dealt with.
+//! The handoff itself lives in the user's session metadata (see
+//! [`crate::session::handoff`]); the operator's session keeps the list of
+//! open handoffs and which one has focus.
+
+use serde_json::json;
+
+use crate::config::HandoffConfig;
+use crate::session::locale::Locale;
+use crate::session::store::SessionStore;
+use crate::types::{InboundMessage, Message, OutboundMessage, Role};
+
+/// Reply marker the model uses to hand off, followed by a short reason.
+pub const HANDOFF_MARKER: &str = "[[HANDOFF]]";
+
+/// Operator session metadata: open handoffs, oldest first.
+const OPEN_KEY: &str = "handoffsOpen";
+/// Operator session metadata: session plain-text replies go to.
+const FOCUS_KEY: &str = "handoffFocus";
+
+/// Messages of the conversation shown to the operator on handoff.
+const CONTEXT_MESSAGES: usize = 6;
+
+const HANDOFF_INSTRUCTION: &str = "\n\n## Human handoff\n\
+A human operator is available. If you cannot resolve the user's request, are not confident in \
+your answer, or the user is upset, write a short message telling them a person will follow up, \
+then end your reply with a last line `[[HANDOFF]] <reason in a few words>`.";
+
+/// Handoff routing for one operator chat.
+#[derive(Debug, Clone)]
+pub struct HandoffDesk {
+    operator_channel: String,
+    operator_chat: String,
+    triggers: Vec<String>,
+    on_low_confidence: bool,
+    channels: Vec<String>,
+}
+
+impl HandoffDesk {
+    /// `None` unless handoff is enabled with a valid operator address.
+    pub fn from_config(config: &HandoffConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let Some((channel, chat)) = config.operator.split_once(':').filter(|(c, id)| !c.is_empty() && !id.is_empty())
+        else {
+            tracing::warn!("agents.handoff.operator must be channel:chat_id; handoff disabled");
+            return None;
+        };
+        Some(Self {
+            operator_channel: channel.to_string(),
+            operator_chat: chat.to_string(),
+            triggers: config.triggers.iter().map(|t| t.to_lowercase()).filter(|t| !t.is_empty()).collect(),
+            on_low_confidence: config.on_low_confidence,
+            channels: config.channels.clone(),
+        })
+    }
+
+    fn operator_key(&self) -> String {
+        format!("{}:{}", self.operator_channel, self.operator_chat)
+    }
+
+    fn to_operator(&self, text: impl Into<String>) -> OutboundMessage {
+        OutboundMessage::new(&self.operator_channel, &self.operator_chat, text)
+    }
+
+    /// Whether users on `channel` can be handed off.
+    pub fn applies(&self, channel: &str) -> bool {
+        self.channels.is_empty() || self.channels.iter().any(|c| c == channel)
+    }
+
+    /// Let the model hand off by extending the system prompt.
+    pub fn instruct(&self, messages: &mut Vec<Message>) {
+        if !self.on_low_confidence {
+            return;
+        }
+        match messages.iter_mut().find(|m| m.role == Role::System) {
+            Some(system) => system.content.get_or_insert_with(String::new).push_str(HANDOFF_INSTRUCTION),
+            None => messages.insert(0, Message::system(HANDOFF_INSTRUCTION.trim_start())),
+        }
+    }
+
+    /// Route `msg` if handoff concerns it: operator traffic, messages of a
+    /// paused session, and requests for a person. Returns the messages to
+    /// send, or `None` to let the agent answer.
+    pub fn handle(&self, sessions: &mut dyn SessionStore, msg: &InboundMessage) -> Option<Vec<OutboundMessage>> {
+        if msg.channel == self.operator_channel && msg.chat_id == self.operator_chat {
+            return self.handle_operator(sessions, msg);
+        }
+        if !self.applies(&msg.channel) {
+            return None;
+        }
+        let key = msg.session_key();
+        let command = crate::command::parse(&msg.content);
+        let session = sessions.get_or_create(&key);
+        let locale = session.locale().or_else(|| Locale::detect(&msg.content)).unwrap_or_default();
+
+        if session.handoff().is_some() {
+            if command.as_ref().is_some_and(|c| c.name() == "resume") {
+                return Some(self.resume(sessions, &key, "user"));
+            }
+            session.add_message_from_channel("user", &msg.content, &msg.channel);
+            sessions.save_by_key(&key);
+            return Some(vec![self.to_operator(format!("💬 {}: {}", key, msg.content))]);
+        }
+
+        let reason = match &command {
+            Some(c) if c.name() == "human" => Some(match c.args.trim() {
+                "" => "requested by the user".to_string(),
+                why => why.to_string(),
+            }),
+            Some(c) if c.name() == "resume" => {
+                let text = locale.pick("現在ボットが対応しています。", "You're already talking to the bot.");
+                return Some(vec![OutboundMessage::new(&msg.channel, &msg.chat_id, text)]);
+            }
+            Some(_) => None,
+            None => {
+                let content = msg.content.to_lowercase();
+                self.triggers.iter().any(|t| content.contains(t.as_str())).then(|| "asked for a person".to_string())
+            }
+        }?;
+        session.add_message_from_channel("user", &msg.content, &msg.channel);
+        let (notice, operator) = self.start(sessions, &key, &reason, locale);
+        Some(vec![OutboundMessage::new(&msg.channel, &msg.chat_id, notice), operator])
+    }
+
+    /// Pause the bot in session `key` and announce it to the operator.
+    /// Returns the notice for the user and the operator message.
+    pub fn start(&self, sessions: &mut dyn SessionStore, key: &str, reason: &str, locale: Locale) -> (String, OutboundMessage) {
+        let session = sessions.get_or_create(key);
+        session.start_handoff(reason);
+        let start = session.messages.len().saturating_sub(CONTEXT_MESSAGES);
+        let recent: Vec<String> = session.messages[start..]
+            .iter()
+            .map(|m| format!("{}: {}", m.role, crate::util::truncate_string(&m.content, 300, "…")))
+            .collect();
+        sessions.save_by_key(key);
+        self.update_desk(sessions, |open, focus| {
+            if !open.iter().any(|k| k == key) {
+                open.push(key.to_string());
+            }
+            *focus = Some(key.to_string());
+        });
+
+        let notice = locale.pick(
+            "担当者におつなぎします。少々お待ちください。（ボットに戻すには /resume）",
+            "I'm connecting you with a person — please hold on. (Send /resume to go back to the bot.)",
+        );
+        let mut announcement = format!("🙋 Handoff: {}\nReason: {}\n", key, reason);
+        if !recent.is_empty() {
+            announcement.push_str(&format!("\n{}\n", recent.join("\n")));
+        }
+        announcement.push_str(&format!(
+            "\nReply here to answer (or /reply {} <text>) · /resume {} to hand back · /handoffs",
+            key, key
+        ));
+        (notice.to_string(), self.to_operator(announcement))
+    }
+
+    fn handle_operator(&self, sessions: &mut dyn SessionStore, msg: &InboundMessage) -> Option<Vec<OutboundMessage>> {
+        let (open, focus) = self.desk(sessions);
+        match crate::command::parse(&msg.content) {
+            Some(c) if c.name() == "handoffs" => {
+                let text = if open.is_empty() {
+                    "No open handoffs.".to_string()
+                } else {
+                    let lines: Vec<String> = open
+                        .iter()
+                        .map(|key| {
+                            let reason = sessions.get_or_create(key).handoff().map(|h| h.reason).unwrap_or_default();
+                            let mark = if focus.as_deref() == Some(key.as_str()) { "▶" } else { "・" };
+                            format!("{} {} — {}", mark, key, reason)
+                        })
+                        .collect();
+                    format!("Open handoffs:\n{}", lines.join("\n"))
+                };
+                Some(vec![self.to_operator(text)])
+            }
+            Some(c) if c.name() == "reply" => {
+                let (key, text) = c.args.trim().split_once(char::is_whitespace).unwrap_or((c.args.trim(), ""));
+                Some(self.relay(sessions, &open, key, text.trim()))
+            }
+            Some(c) if c.name() == "resume" => {
+                let key = match c.args.trim() {
+                    "" => focus?,
+                    key => key.to_string(),
+                };
+                if !open.contains(&key) {
+                    return Some(vec![self.to_operator(format!("{} is not handed off.", key))]);
+                }
+                Some(self.resume(sessions, &key, "operator"))
+            }
+            Some(_) => None,
+            None => {
+                let key = focus.filter(|k| open.contains(k))?;
+                Some(self.relay(sessions, &open, &key, msg.content.trim()))
+            }
+        }
+    }
+
+    /// Send an operator reply to the user of session `key`.
+    fn relay(&self, sessions: &mut dyn SessionStore, open: &[String], key: &str, text: &str) -> Vec<OutboundMessage> {
+        if !open.iter().any(|k| k == key) {
+            return vec![self.to_operator(format!("{} is not handed off. Open: /handoffs", key))];
+        }
+        if text.is_empty() {
+            return vec![self.to_operator("Usage: /reply <session> <text>")];
+        }
+        let Some((channel, chat_id)) = key.split_once(':') else {
+            return vec![self.to_operator(format!("Can't reach {}", key))];
+        };
+        let session = sessions.get_or_create(key);
+        session.add_message_from_channel("assistant", text, &self.operator_channel);
+        sessions.save_by_key(key);
+        self.update_desk(sessions, |_, focus| *focus = Some(key.to_string()));
+        let mut out = OutboundMessage::new(channel, chat_id, text);
+        out.metadata.insert("operator".to_string(), json!(true));
+        vec![out]
+    }
+
+    /// Give session `key` back to the bot, telling both sides.
+    fn resume(&self, sessions: &mut dyn SessionStore, key: &str, by: &str) -> Vec<OutboundMessage> {
+        let session = sessions.get_or_create(key);
+        session.end_handoff();
+        let locale = session.locale().unwrap_or_default();
+        sessions.save_by_key(key);
+        self.update_desk(sessions, |open, focus| {
+            open.retain(|k| k != key);
+            if focus.as_deref() == Some(key) {
+                *focus = open.last().cloned();
+            }
+        });
+        let mut out = vec![self.to_operator(format!("✅ {} is back with the bot (resumed by {}).", key, by))];
+        if let Some((channel, chat_id)) = key.split_once(':') {
+            let text = locale.pick("ボットとの会話に戻りました。", "You're back with the bot.");
+            out.insert(0, OutboundMessage::new(channel, chat_id, text));
+        }
+        out
+    }
+
+    /// Open handoffs and the focused one, from the operator's session.
+    fn desk(&self, sessions: &mut dyn SessionStore) -> (Vec<String>, Option<String>) {
+        let session = sessions.get_or_create(&self.operator_key());
+        let open = session
+            .metadata
+            .get(OPEN_KEY)
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default();
+        let focus = session.metadata.get(FOCUS_KEY).and_then(|v| v.as_str()).map(str::to_string);
+        (open, focus)
+    }
+
+    fn update_desk(&self, sessions: &mut dyn SessionStore, update: impl FnOnce(&mut Vec<String>, &mut Option<String>)) {
+        let (mut open, mut focus) = self.desk(sessions);
+        update(&mut open, &mut focus);
+        let key = self.operator_key();
+        let session = sessions.get_or_create(&key);
+        session.metadata.insert(OPEN_KEY.to_string(), json!(open));
+        match focus {
+            Some(focus) => session.metadata.insert(FOCUS_KEY.to_string(), json!(focus)),
+            None => session.metadata.remove(FOCUS_KEY),
+        };
+        sessions.save_by_key(&key);
+    }
+}
+
+/// Remove [`HANDOFF_MARKER`] from a model reply; returns the text and the
+/// reason when the model handed off.
+pub fn split_marker(reply: &str) -> (String, Option<String>) {
+    let Some(at) = reply.find(HANDOFF_MARKER) else {
+        return (reply.to_string(), None);
+    };
+    let rest = &reply[at + HANDOFF_MARKER.len()..];
+    let reason = rest.lines().next().map(str::trim).filter(|r| !r.is_empty()).unwrap_or("the assistant couldn't help");
+    let text = format!("{}{}", &reply[..at], rest.split_once('\n').map(|(_, after)| after).unwrap_or(""));
+    (text.trim().to_string(), Some(reason.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Session;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct MemStore {
+        sessions: HashMap<String, Session>,
+    }
+
+    impl SessionStore for MemStore {
+        fn get_or_create(&mut self, key: &str) -> &mut Session {
+            self.sessions.entry(key.to_string()).or_insert_with(|| Session::new(key))
+        }
+        fn refresh(&mut self, key: &str) -> &mut Session {
+            self.get_or_create(key)
+        }
+        fn save(&self, _session: &Session) {}
+        fn save_by_key(&self, _key: &str) {}
+        fn delete(&mut self, key: &str) -> bool {
+            self.sessions.remove(key).is_some()
+        }
+        fn list_sessions(&self) -> Vec<serde_json::Value> {
+            Vec::new()
+        }
+    }
+
+    fn desk() -> HandoffDesk {
+        let config = HandoffConfig { enabled: true, operator: "telegram:ops".to_string(), ..Default::default() };
+        HandoffDesk::from_config(&config).unwrap()
+    }
+
+    fn contents(out: &[OutboundMessage]) -> Vec<(String, String)> {
+        out.iter().map(|m| (format!("{}:{}", m.channel, m.chat_id), m.content.clone())).collect()
+    }
+
+    #[test]
+    fn test_split_marker() {
+        assert_eq!(split_marker("Sure, here it is."), ("Sure, here it is.".to_string(), None));
+        let (text, reason) = split_marker("A person will follow up.\n[[HANDOFF]] refund request");
+        assert_eq!(text, "A person will follow up.");
+        assert_eq!(reason.as_deref(), Some("refund request"));
+    }
+
+    #[test]
+    fn test_handoff_round_trip() {
+        let mut sessions = MemStore::default();
+        let desk = desk();
+        let user = |text: &str| InboundMessage::new("line", "U1", "U1", text);
+        let operator = |text: &str| InboundMessage::new("telegram", "ops", "ops", text);
+        sessions.get_or_create("line:U1").set_locale(Some(crate::session::locale::Locale::En));
+
+        assert!(desk.handle(&mut sessions, &user("What are your hours?")).is_none());
+        assert!(desk.handle(&mut sessions, &operator("hello bot")).is_none(), "no open handoffs");
+
+        let out = desk.handle(&mut sessions, &user("I want to talk to a human")).unwrap();
+        assert_eq!(out[0].chat_id, "U1");
+        assert!(out[1].content.starts_with("🙋 Handoff: line:U1"));
+        assert!(sessions.get_or_create("line:U1").handoff().is_some());
+
+        // Paused: user messages go to the operator, operator replies to the user
+        let out = desk.handle(&mut sessions, &user("My order is late")).unwrap();
+        assert_eq!(contents(&out), [("telegram:ops".to_string(), "💬 line:U1: My order is late".to_string())]);
+        let out = desk.handle(&mut sessions, &operator("Sorry! Checking now.")).unwrap();
+        assert_eq!(contents(&out), [("line:U1".to_string(), "Sorry! Checking now.".to_string())]);
+        assert_eq!(sessions.get_or_create("line:U1").last_assistant_message(), Some("Sorry! Checking now."));
+
+        let out = desk.handle(&mut sessions, &operator("/resume")).unwrap();
+        assert_eq!(contents(&out)[0], ("line:U1".to_string(), "You're back with the bot.".to_string()));
+        assert!(sessions.get_or_create("line:U1").handoff().is_none());
+        assert!(desk.handle(&mut sessions, &operator("anyone?")).is_none());
+    }
+}
@@ -0,0 +1,339 @@
+//! Response length and tone profiles.
+//!
+//! The effective limit for a reply is resolved from (highest priority first)
+//! the session's `/length` preference, the channel profile, the device
+//! profile and finally the agent's built-in limit. The prompt asks the model
+//! to stay within it; with `response.trimRatio` set, [`trim_response`] also
+//! cuts what runs far over afterwards without breaking code blocks or
+//! markdown lines.
+
+use crate::config::ResponseConfig;
+use crate::session::locale::Locale;
+use crate::session::Session;
+
+/// Smallest limit a `/length` preference can produce.
+pub const MIN_MAX_CHARS: usize = 40;
+
+/// Marker appended where prose was cut.
+const ELLIPSIS: char = '…';
+
+/// A user's length preference, set per session with `/length`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthPreference {
+    Brief,
+    Normal,
+    Long,
+    Chars(usize),
+}
+
+impl LengthPreference {
+    /// Parse `brief`/`long`/`normal` (or 短く/詳しく/普通) or an explicit character count.
+    pub fn parse(s: &str) -> Option<Self> {
+        let lower = s.trim().to_lowercase();
+        match lower.as_str() {
+            "brief" | "short" | "簡潔" | "短く" | "みじかく" => Some(Self::Brief),
+            "normal" | "default" | "普通" | "ふつう" => Some(Self::Normal),
+            "long" | "detailed" | "詳しく" | "くわしく" | "長め" => Some(Self::Long),
+            _ => lower
+                .trim_end_matches("文字")
+                .trim_end_matches("chars")
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|&n| n >= MIN_MAX_CHARS)
+                .map(Self::Chars),
+        }
+    }
+
+    /// Storage form, accepted again by [`LengthPreference::parse`].
+    pub fn as_string(&self) -> String {
+        match self {
+            Self::Brief => "brief".to_string(),
+            Self::Normal => "normal".to_string(),
+            Self::Long => "long".to_string(),
+            Self::Chars(n) => n.to_string(),
+        }
+    }
+
+    /// Apply the preference to a base limit.
+    pub fn apply(&self, base: usize) -> usize {
+        match *self {
+            Self::Brief => (base / 2).max(MIN_MAX_CHARS),
+            Self::Normal => base,
+            Self::Long => base * 2,
+            Self::Chars(n) => n,
+        }
+    }
+}
+
+/// Resolved length/tone for one reply.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseStyle {
+    /// Character limit given to the model.
+    pub max_chars: usize,
+    /// Extra tone instruction, if any.
+    pub tone: Option<String>,
+    /// Responses longer than this are trimmed (0 = never).
+    pub trim_at: usize,
+}
+
+impl ResponseStyle {
+    /// Resolve the style for a reply on `channel` from `device`.
+    /// `agent_max_chars` is the agent profile's built-in limit for the device.
+    pub fn resolve(
+        config: &ResponseConfig,
+        channel: &str,
+        device: &str,
+        agent_max_chars: usize,
+        session: Option<&Session>,
+    ) -> Self {
+        let channel_profile = config.channels.get(channel);
+        let device_profile = config.devices.get(device);
+        let base = channel_profile
+            .and_then(|p| p.max_chars)
+            .or_else(|| device_profile.and_then(|p| p.max_chars))
+            .unwrap_or(agent_max_chars);
+        let max_chars = session
+            .and_then(|s| s.length_override())
+            .map(|pref| pref.apply(base))
+            .unwrap_or(base);
+        let tone = session
+            .and_then(|s| s.tone_override())
+            .or_else(|| channel_profile.and_then(|p| p.tone.as_deref()))
+            .or_else(|| device_profile.and_then(|p| p.tone.as_deref()))
+            .map(str::to_string);
+        let trim_at = if config.trim_ratio > 0.0 {
+            (max_chars as f64 * config.trim_ratio).ceil() as usize
+        } else {
+            0
+        };
+        Self { max_chars, tone, trim_at }
+    }
+
+    /// System prompt block describing the limit (and tone).
+    pub fn instruction(&self, locale: Locale) -> String {
+        let mut s = if locale.is_english() {
+            format!("\n\n[Length limit] Keep your answer within {} characters. Be concise and to the point.", self.max_chars)
+        } else {
+            format!("\n\n【応答制約】回答は{}文字以内に収めてください。簡潔に要点を伝えてください。", self.max_chars)
+        };
+        if let Some(tone) = &self.tone {
+            s.push_str(&format!("\n{}{}", locale.pick("【口調】", "[Tone] "), tone));
+        }
+        s
+    }
+
+    /// Trim `text` to `trim_at` characters (see [`trim_response`]).
+    pub fn enforce(&self, text: &str) -> String {
+        trim_response(text, self.trim_at)
+    }
+}
+
+enum Block<'a> {
+    Code(Vec<&'a str>),
+    Prose(Vec<&'a str>),
+}
+
+/// Split markdown into fenced code blocks and blank-line separated prose blocks.
+fn split_blocks(text: &str) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    let mut prose: Vec<&str> = Vec::new();
+    let mut fence: Option<(&str, Vec<&str>)> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if let Some((marker, code)) = fence.as_mut() {
+            code.push(line);
+            if trimmed.starts_with(*marker) {
+                blocks.push(Block::Code(std::mem::take(code)));
+                fence = None;
+            }
+            continue;
+        }
+        let marker = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m));
+        if let Some(marker) = marker {
+            if !prose.is_empty() {
+                blocks.push(Block::Prose(std::mem::take(&mut prose)));
+            }
+            fence = Some((marker, vec![line]));
+        } else if trimmed.is_empty() {
+            if !prose.is_empty() {
+                blocks.push(Block::Prose(std::mem::take(&mut prose)));
+            }
+        } else {
+            prose.push(line);
+        }
+    }
+    if let Some((_, code)) = fence {
+        blocks.push(Block::Code(code));
+    }
+    if !prose.is_empty() {
+        blocks.push(Block::Prose(prose));
+    }
+    blocks
+}
+
+fn is_heading(line: &str) -> bool {
+    line.trim_start().starts_with('#')
+}
+
+/// Cut a prose block to `budget` characters: whole lines first (so list items
+/// and headings stay intact), then the next line at a sentence break if
+/// nothing but headings fit.
+fn cut_prose(lines: &[&str], budget: usize) -> String {
+    let mut kept: Vec<String> = Vec::new();
+    let mut used = 0usize;
+    for line in lines {
+        let cost = line.chars().count() + 1;
+        if used + cost > budget {
+            break;
+        }
+        used += cost;
+        kept.push(line.to_string());
+    }
+    if kept.iter().all(|l| is_heading(l)) {
+        if let Some(next) = lines.get(kept.len()) {
+            let part = cut_line(next, budget.saturating_sub(used));
+            if !part.is_empty() {
+                kept.push(part);
+            }
+        }
+    }
+    // A heading with nothing under it is noise.
+    while kept.last().is_some_and(|l| is_heading(l)) {
+        kept.pop();
+    }
+    kept.join("\n")
+}
+
+/// Cut a single line to `budget` characters, preferring a sentence end or a space.
+fn cut_line(line: &str, budget: usize) -> String {
+    let prefix: String = line.chars().take(budget).collect();
+    let min = prefix.len() / 3;
+    let cut = prefix
+        .char_indices()
+        .rev()
+        .filter(|&(_, c)| matches!(c, '。' | '！' | '？' | '.' | '!' | '?'))
+        .map(|(i, c)| i + c.len_utf8())
+        .find(|&i| i > min)
+        .or_else(|| prefix.rfind(char::is_whitespace).filter(|&i| i > min));
+    match cut {
+        Some(i) => prefix[..i].trim_end().to_string(),
+        None => prefix,
+    }
+}
+
+/// Trim a response to roughly `max_chars` characters of prose.
+///
+/// Fenced code blocks are never cut and do not count against the limit;
+/// prose is cut at line or sentence boundaries and the cut is marked with `…`.
+/// `max_chars == 0` disables trimming.
+pub fn trim_response(text: &str, max_chars: usize) -> String {
+    if max_chars == 0 || text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut budget = max_chars;
+    let mut truncated = false;
+    let mut out: Vec<String> = Vec::new();
+    for block in split_blocks(text) {
+        match block {
+            Block::Code(lines) => out.push(lines.join("\n")),
+            Block::Prose(_) if truncated => {}
+            Block::Prose(lines) => {
+                let len: usize = lines.iter().map(|l| l.chars().count() + 1).sum();
+                if len <= budget {
+                    budget -= len;
+                    out.push(lines.join("\n"));
+                } else {
+                    let mut cut = cut_prose(&lines, budget);
+                    cut.push(ELLIPSIS);
+                    out.push(cut);
+                    truncated = true;
+                }
+            }
+        }
+    }
+    if !truncated {
+        return text.to_string();
+    }
+    out.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ResponseProfile;
+
+    #[test]
+    fn test_length_preference() {
+        assert_eq!(LengthPreference::parse("Brief"), Some(LengthPreference::Brief));
+        assert_eq!(LengthPreference::parse("詳しく"), Some(LengthPreference::Long));
+        assert_eq!(LengthPreference::parse("150文字"), Some(LengthPreference::Chars(150)));
+        assert_eq!(LengthPreference::parse("5"), None);
+        assert_eq!(LengthPreference::parse("huge"), None);
+        assert_eq!(LengthPreference::Brief.apply(400), 200);
+        assert_eq!(LengthPreference::Brief.apply(60), MIN_MAX_CHARS);
+        assert_eq!(LengthPreference::Long.apply(400), 800);
+    }
+
+    #[test]
+    fn test_resolve_priority() {
+        let mut config = ResponseConfig::default();
+        config.devices.insert("mobile".into(), ResponseProfile { max_chars: Some(150), ..Default::default() });
+        config.channels.insert(
+            "line".into(),
+            ResponseProfile { max_chars: Some(200), tone: Some("親しみやすく".into()), ..Default::default() },
+        );
+
+        assert_eq!(ResponseStyle::resolve(&config, "web", "pc", 400, None).max_chars, 400);
+        assert_eq!(ResponseStyle::resolve(&config, "web", "mobile", 400, None).max_chars, 150);
+        let line = ResponseStyle::resolve(&config, "line", "mobile", 400, None);
+        assert_eq!(line.max_chars, 200);
+        assert_eq!(line.tone.as_deref(), Some("親しみやすく"));
+        assert_eq!(line.trim_at, 0);
+        config.trim_ratio = 1.5;
+        assert_eq!(ResponseStyle::resolve(&config, "line", "mobile", 400, None).trim_at, 300);
+
+        let mut session = Session::new("line:U1");
+        session.set_length_override(Some(LengthPreference::Brief));
+        session.set_tone_override(Some("敬語で"));
+        let line = ResponseStyle::resolve(&config, "line", "pc", 400, Some(&session));
+        assert_eq!(line.max_chars, 100);
+        assert_eq!(line.tone.as_deref(), Some("敬語で"));
+        assert!(line.instruction(Locale::Ja).contains("100文字以内"));
+        assert!(line.instruction(Locale::En).contains("[Tone] 敬語で"));
+    }
+
+    #[test]
+    fn test_trim_short_text_untouched() {
+        assert_eq!(trim_response("hello", 100), "hello");
+        assert_eq!(trim_response(&"x".repeat(500), 0).len(), 500);
+    }
+
+    #[test]
+    fn test_trim_keeps_code_blocks() {
+        let text = format!(
+            "Intro line.\n\n```rust\nfn main() {{\n    println!(\"{}\");\n}}\n```\n\n{}\n\nTail paragraph.",
+            "a".repeat(200),
+            "Second paragraph. ".repeat(10)
+        );
+        let out = trim_response(&text, 60);
+        assert!(out.starts_with("Intro line."));
+        assert!(out.contains(&"a".repeat(200)));
+        assert_eq!(out.matches("```").count(), 2);
+        assert!(out.contains('…'));
+        assert!(!out.contains("Tail paragraph."));
+    }
+
+    #[test]
+    fn test_trim_at_line_and_sentence_boundaries() {
+        let list = "## 手順\n- 一つ目の項目です\n- 二つ目の項目です\n- 三つ目の項目です";
+        let out = trim_response(list, 25);
+        assert_eq!(out, "## 手順\n- 一つ目の項目です…");
+
+        let prose = "最初の文です。次の文はとても長くて制限を超えてしまうかもしれません。";
+        assert_eq!(trim_response(prose, 20), "最初の文です。…");
+
+        let heading_only = "# Title\nA very long line that will not fit into the budget at all";
+        assert_eq!(trim_response(heading_only, 20), "# Title\nA very long…");
+    }
+}
@@ -1,29 +1,74 @@
+pub mod builder;
 pub mod context;
+pub mod escalation;
+pub mod handoff;
+pub mod length;
 pub mod ooda;
 pub mod personality;
+pub mod prompt;
+pub mod speculative;
+pub mod stream;
 pub mod subagent;
 
+use serde::Serialize;
 use serde_json::json;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tracing::{debug, error, info};
 
 use crate::bus::MessageBus;
-use crate::config::ExecToolConfig;
-use crate::provider::LlmProvider;
+use crate::channel::presence::{PresenceStore, NO_ROUTE_KEY};
+use crate::config::{EscalationConfig, ExecToolConfig, FilePolicyConfig, HandoffConfig, ProactiveConfig, ResponseConfig, ToolPolicyConfig};
+use crate::expense::FileLedger;
+use crate::feedback::{append_feedback, reaction_action, FeedbackRecord, ReactionAction};
+use crate::provider::{ChatExtra, LlmProvider};
+use crate::service::cron::CronService;
+use crate::service::followup::FollowupPolicy;
+use crate::service::reminder;
+use crate::session::incognito::{self, INCOGNITO_KEY};
+use crate::session::locale::Locale;
 use crate::session::file_store::FileSessionStore;
 use crate::session::store::SessionStore;
+use crate::session::turn::TurnMeta;
+use crate::tool::code::CodeExecuteTool;
+use crate::tool::file_policy::FilePolicy;
+use crate::tool::policy::{Approver, ToolPolicy};
+use crate::tool::followup::FollowupTool;
 use crate::tool::filesystem::{EditFileTool, ListDirTool, ReadFileTool, WriteFileTool};
 use crate::tool::message::MessageTool;
+use crate::tool::forward::ForwardMessageTool;
+use crate::tool::patch::ApplyPatchTool;
+use crate::tool::quality::{QualityReport, RunLinterTool, RunTestsTool};
+use crate::tool::crawl::WebCrawlTool;
+use crate::tool::extract::ExtractTool;
+use crate::tool::expense::ExpenseTool;
+use crate::tool::youtube::YouTubeTranscriptTool;
+use crate::tool::ocr::{Ocr, OcrTool};
+use crate::tool::search::GrepWorkspaceTool;
+#[cfg(feature = "code-intel")]
+use crate::tool::symbols::{FindDefinitionTool, ListSymbolsTool};
 use crate::tool::shell::ExecTool;
+use crate::tool::shell_session::ShellSessionTool;
 use crate::tool::spawn::{SpawnCallback, SpawnTool};
+use crate::tool::stats::ToolStatsStore;
 use crate::tool::web::{WebFetchTool, WebSearchTool};
 use crate::tool::ToolRegistry;
 use crate::types::{InboundMessage, Message, OutboundMessage};
-
+use crate::webhook::{WebhookEvent, WebhookOutbox};
+use crate::faq::{Faq, FAQ_KEY};
+use crate::onboarding::Onboarding;
+use crate::hooks::{HookContext, ResponseHooks};
+use crate::rules::RulesEngine;
+use crate::workflow::WorkflowEngine;
+
+use self::builder::AgentEvent;
 use self::context::ContextBuilder;
+use self::escalation::{EscalationPolicy, EscalationRecord};
+use self::handoff::HandoffDesk;
+use self::speculative::{Draft, SpeculativePolicy, DRAFT_KEY};
+use self::stream::StreamEvent;
 use self::subagent::SubagentManager;
 
 /// The agent loop is the core processing engine.
@@ -31,15 +76,57 @@ use self::subagent::SubagentManager;
 pub struct AgentLoop {
     provider: Arc<dyn LlmProvider>,
     workspace: PathBuf,
+    /// Directory the file tools are confined to (`tools.restrictToWorkspace`).
+    allowed_dir: Option<PathBuf>,
     model: String,
     max_iterations: u32,
     context: ContextBuilder,
     sessions: Box<dyn SessionStore>,
     tools: Arc<ToolRegistry>,
     message_tool: Arc<MessageTool>,
+    /// `forward_message`; its deliveries are recorded in both sessions.
+    forward_tool: Arc<ForwardMessageTool>,
+    expense_tool: Arc<ExpenseTool>,
     inbound_rx: mpsc::Receiver<InboundMessage>,
     outbound_tx: mpsc::Sender<OutboundMessage>,
     inbound_tx: mpsc::Sender<InboundMessage>,
+    /// Cron service backing `/remind` and friends (gateway mode).
+    cron: Option<Arc<tokio::sync::Mutex<CronService>>>,
+    /// Configured default timezone (`agents.defaults.timezone`).
+    timezone: Option<String>,
+    /// `schedule_followup`, registered by [`AgentLoop::with_followups`].
+    followup_tool: Option<Arc<FollowupTool>>,
+    /// Per-channel activity of linked users, and `/route`.
+    presence: Option<Arc<tokio::sync::Mutex<PresenceStore>>>,
+    /// Outbox for `message.processed` / `tool.executed` webhooks.
+    webhooks: Option<Arc<tokio::sync::Mutex<WebhookOutbox>>>,
+    /// Workflows startable with `/workflow <name>`.
+    workflows: Option<Arc<WorkflowEngine>>,
+    /// Event rules matched against every inbound message.
+    rules: Option<Arc<RulesEngine>>,
+    /// Progress events for embedders (see [`builder::Agent::subscribe`]).
+    events: Option<tokio::sync::broadcast::Sender<AgentEvent>>,
+    /// Draft replies from a fast model, set by [`AgentLoop::with_speculative`].
+    speculative: Option<SpeculativePolicy>,
+    /// Economy-first answering, set by [`AgentLoop::with_escalation`].
+    escalation: Option<EscalationPolicy>,
+    /// Human operator handoff, set by [`AgentLoop::with_handoff`].
+    handoff: Option<HandoffDesk>,
+    /// Canned answers, set by [`AgentLoop::with_faq`].
+    faq: Option<Faq>,
+    /// First-contact onboarding, set by [`AgentLoop::with_onboarding`].
+    onboarding: Option<Onboarding>,
+    /// OCR of image attachments, set by [`AgentLoop::with_ocr`].
+    media_ocr: Option<Arc<Ocr>>,
+    /// Tool call analytics, set by [`AgentLoop::with_tool_stats`].
+    tool_stats: Option<Arc<ToolStatsStore>>,
+    /// Session the current message belongs to, for `tool_stats`.
+    stats_user: String,
+    /// Receiver of the current message's [`StreamEvent`]s, set by
+    /// [`AgentLoop::process_direct_stream`].
+    stream: Option<mpsc::UnboundedSender<StreamEvent>>,
+    /// Post-processing of final replies, set by [`AgentLoop::with_response_hooks`].
+    response_hooks: ResponseHooks,
 }
 
 impl AgentLoop {
@@ -53,15 +140,41 @@ impl AgentLoop {
         exec_config: ExecToolConfig,
         restrict_to_workspace: bool,
         subagent_manager: Option<Arc<SubagentManager>>,
+    ) -> Self {
+        Self::from_senders(
+            bus.inbound_sender(),
+            bus.outbound_sender(),
+            provider,
+            workspace,
+            model,
+            max_iterations,
+            brave_api_key,
+            exec_config,
+            restrict_to_workspace,
+            subagent_manager,
+        )
+    }
+
+    /// Construct with explicit bus senders; [`AgentLoop::new`] takes them
+    /// from a [`MessageBus`], [`builder::AgentBuilder`] keeps the receivers.
+    #[allow(clippy::too_many_arguments)]
+    fn from_senders(
+        inbound_tx: mpsc::Sender<InboundMessage>,
+        outbound_tx: mpsc::Sender<OutboundMessage>,
+        provider: Arc<dyn LlmProvider>,
+        workspace: PathBuf,
+        model: Option<String>,
+        max_iterations: u32,
+        brave_api_key: Option<String>,
+        exec_config: ExecToolConfig,
+        restrict_to_workspace: bool,
+        subagent_manager: Option<Arc<SubagentManager>>,
     ) -> Self {
         let model = model.unwrap_or_else(|| provider.default_model().to_string());
         let context = ContextBuilder::new(&workspace);
         let sessions: Box<dyn SessionStore> = Box::new(FileSessionStore::new(&workspace));
         let tools = Arc::new(ToolRegistry::new());
 
-        let inbound_tx = bus.inbound_sender();
-        let outbound_tx = bus.outbound_sender();
-
         // Register default tools
         let allowed_dir = if restrict_to_workspace {
             Some(workspace.clone())
@@ -69,22 +182,54 @@ impl AgentLoop {
             None
         };
 
-        tools.register(Arc::new(ReadFileTool::new(allowed_dir.clone())));
-        tools.register(Arc::new(WriteFileTool::new(allowed_dir.clone())));
-        tools.register(Arc::new(EditFileTool::new(allowed_dir.clone())));
-        tools.register(Arc::new(ListDirTool::new(allowed_dir)));
+        let policy = Arc::new(FilePolicy::new(&workspace, &FilePolicyConfig::default()));
+        register_file_tools(&tools, &workspace, allowed_dir.clone(), policy);
+        tools.register(Arc::new(ListDirTool::new(allowed_dir.clone())));
+        tools.register(Arc::new(GrepWorkspaceTool::new(workspace.clone())));
+
+        // Symbol index shared by the code navigation tools and the repo map
+        #[cfg(feature = "code-intel")]
+        let context = {
+            let symbols = Arc::new(crate::code_intel::SymbolIndex::new(workspace.clone()));
+            tools.register(Arc::new(ListSymbolsTool::new(symbols.clone())));
+            tools.register(Arc::new(FindDefinitionTool::new(symbols.clone())));
+            context.with_symbol_index(symbols)
+        };
 
         tools.register(Arc::new(ExecTool::new(
             workspace.display().to_string(),
             exec_config.timeout,
             restrict_to_workspace,
         )));
+        tools.register(Arc::new(ShellSessionTool::new(
+            workspace.clone(),
+            exec_config.clone(),
+            restrict_to_workspace,
+        )));
+
+        tools.register(Arc::new(CodeExecuteTool::default()));
+        tools.register(Arc::new(RunLinterTool::new(workspace.clone())));
+        tools.register(Arc::new(RunTestsTool::new(workspace.clone())));
 
         tools.register(Arc::new(WebSearchTool::new(brave_api_key, 5)));
         tools.register(Arc::new(WebFetchTool::new(50000)));
+        tools.register(Arc::new(WebCrawlTool::new(30000)));
+        tools.register(Arc::new(ExtractTool::new(provider.clone(), model.clone())));
+        tools.register(Arc::new(YouTubeTranscriptTool));
+        let ocr = Arc::new(Ocr::new(workspace.clone(), &Default::default()));
+        tools.register(Arc::new(OcrTool::new(ocr.clone())));
+        let expense_tool = Arc::new(ExpenseTool::new(
+            Arc::new(FileLedger::new(FileLedger::default_path())),
+            provider.clone(),
+            model.clone(),
+            ocr,
+        ));
+        tools.register(expense_tool.clone());
 
         let message_tool = Arc::new(MessageTool::new(outbound_tx.clone()));
         tools.register(message_tool.clone());
+        let forward_tool = Arc::new(ForwardMessageTool::new(outbound_tx.clone()));
+        tools.register(forward_tool.clone());
 
         // Spawn tool
         if let Some(mgr) = subagent_manager {
@@ -101,20 +246,194 @@ impl AgentLoop {
             tools.register(Arc::new(SpawnTool::new(spawn_fn)));
         }
 
-        let (_inbound_tx, _outbound_tx) = (bus.inbound_sender(), bus.outbound_sender());
-
         Self {
             provider,
             workspace,
+            allowed_dir,
             model,
             max_iterations,
             context,
             sessions,
             tools,
             message_tool,
+            forward_tool,
+            expense_tool,
             inbound_rx: tokio::sync::mpsc::channel(1).1, // placeholder, set in run_with_receiver
             outbound_tx,
             inbound_tx,
+            cron: None,
+            timezone: None,
+            followup_tool: None,
+            presence: None,
+            webhooks: None,
+            workflows: None,
+            rules: None,
+            events: None,
+            speculative: None,
+            escalation: None,
+            handoff: None,
+            faq: None,
+            onboarding: None,
+            media_ocr: None,
+            tool_stats: None,
+            stats_user: String::new(),
+            stream: None,
+            response_hooks: ResponseHooks::default(),
+        }
+    }
+
+    /// Enable reminders backed by `cron`.
+    pub fn with_cron_service(mut self, cron: Arc<tokio::sync::Mutex<CronService>>) -> Self {
+        self.cron = Some(cron);
+        self
+    }
+
+    /// Default timezone for the clock, reminders and daily memory when a
+    /// session hasn't set one with `/timezone`.
+    pub fn with_timezone(mut self, tz: Option<String>) -> Self {
+        self.context = self.context.with_timezone(tz.clone());
+        self.timezone = tz;
+        self
+    }
+
+    /// Let the agent schedule its own follow-ups (`schedule_followup`). Needs
+    /// [`Self::with_cron_service`], and times follow-ups in the timezone set
+    /// with [`Self::with_timezone`].
+    pub fn with_followups(mut self, config: &ProactiveConfig) -> Self {
+        if let (true, Some(cron)) = (config.enabled, self.cron.clone()) {
+            let policy = FollowupPolicy::new(config, self.timezone.as_deref());
+            let tool = Arc::new(FollowupTool::new(cron, policy));
+            self.tools.register(tool.clone());
+            self.followup_tool = Some(tool);
+        }
+        self
+    }
+
+    /// Queue webhook events for processed messages and executed tools.
+    pub fn with_webhooks(mut self, outbox: Arc<tokio::sync::Mutex<WebhookOutbox>>) -> Self {
+        self.webhooks = Some(outbox);
+        self
+    }
+
+    /// Enable `/workflow`; runs are queued in `engine` for the gateway's workflow runner.
+    pub fn with_workflows(mut self, engine: Arc<WorkflowEngine>) -> Self {
+        self.workflows = Some(engine);
+        self
+    }
+
+    /// Match inbound messages against `engine`'s rules and drop messages
+    /// from channels a rule has disabled.
+    pub fn with_rules(mut self, engine: Arc<RulesEngine>) -> Self {
+        self.rules = Some(engine);
+        self
+    }
+
+    /// Show a draft from `config.draft_model` on channels whose response
+    /// profile enables it (see [`speculative`]).
+    pub fn with_speculative(mut self, config: &ResponseConfig) -> Self {
+        self.speculative = SpeculativePolicy::from_config(config);
+        self
+    }
+
+    /// Answer with `config.economy_model` first and re-run on the default
+    /// (or `powerful_model`) when unsure (see [`escalation`]).
+    pub fn with_escalation(mut self, config: &EscalationConfig) -> Self {
+        self.escalation = EscalationPolicy::from_config(config);
+        self
+    }
+
+    /// Search with the engines and fallbacks from `tools.web.search`.
+    pub fn with_web_search(self, config: &crate::config::WebSearchConfig) -> Self {
+        self.tools.register(Arc::new(WebSearchTool::from_config(config)));
+        self
+    }
+
+    /// Hand conversations to a human operator on request or when the model
+    /// can't help (see [`handoff`]).
+    pub fn with_handoff(mut self, config: &HandoffConfig) -> Self {
+        self.handoff = HandoffDesk::from_config(config);
+        self
+    }
+
+    /// Answer messages matching `faq`'s questions without calling the model
+    /// (see [`crate::faq`]).
+    pub fn with_faq(mut self, faq: Faq) -> Self {
+        self.faq = Some(faq);
+        self
+    }
+
+    /// Greet new users with the steps of `onboarding` (see [`crate::onboarding`]).
+    pub fn with_onboarding(mut self, onboarding: Onboarding) -> Self {
+        self.onboarding = Some(onboarding);
+        self
+    }
+
+    /// OCR settings from `tools.ocr`; with `media` on, text found in image
+    /// attachments is added to the user's message.
+    pub fn with_ocr(mut self, config: &crate::config::OcrConfig) -> Self {
+        let ocr = Arc::new(Ocr::new(self.workspace.clone(), config));
+        self.tools.register(Arc::new(OcrTool::new(ocr.clone())));
+        self.expense_tool = Arc::new(self.expense_tool.with_ocr(ocr.clone()));
+        self.tools.register(self.expense_tool.clone());
+        self.media_ocr = config.media.then_some(ocr);
+        self
+    }
+
+    /// Safe mode for the file tools from `tools.files` (see
+    /// [`crate::tool::file_policy`]).
+    pub fn with_file_policy(self, config: &FilePolicyConfig) -> Self {
+        let policy = Arc::new(FilePolicy::new(&self.workspace, config));
+        register_file_tools(&self.tools, &self.workspace, self.allowed_dir.clone(), policy);
+        self
+    }
+
+    /// Allow, deny or ask per tool from `tools.policy` (see
+    /// [`crate::tool::policy`]); `approver` answers the asks, if anyone can.
+    pub fn with_tool_policy(self, config: &ToolPolicyConfig, approver: Option<Approver>) -> Self {
+        self.tools.set_policy(ToolPolicy::from_config(config).with_workspace(&self.workspace));
+        if let Some(approver) = approver {
+            self.tools.set_approver(approver);
+        }
+        self
+    }
+
+    /// Count tool calls, failures, latency and result tokens per session and
+    /// day (see [`crate::tool::stats`]).
+    pub fn with_tool_stats(mut self, store: Arc<ToolStatsStore>) -> Self {
+        self.tool_stats = Some(store);
+        self
+    }
+
+    /// Record where linked users are active and handle `/route`.
+    pub fn with_presence(mut self, presence: Arc<tokio::sync::Mutex<PresenceStore>>) -> Self {
+        self.presence = Some(presence);
+        self
+    }
+
+    /// Run `hooks` over each final reply before delivery; the session keeps
+    /// the reply as the model wrote it.
+    pub fn with_response_hooks(mut self, hooks: ResponseHooks) -> Self {
+        self.response_hooks = hooks;
+        self
+    }
+
+    fn emit_event(&self, event: AgentEvent) {
+        if let Some(ref events) = self.events {
+            // No subscribers is fine
+            let _ = events.send(event);
+        }
+    }
+
+    fn emit_stream(&self, event: StreamEvent) {
+        if let Some(ref stream) = self.stream {
+            // The caller may stop listening
+            let _ = stream.send(event);
+        }
+    }
+
+    async fn emit_webhook(&self, event: WebhookEvent, data: serde_json::Value) {
+        if let Some(ref outbox) = self.webhooks {
+            outbox.lock().await.emit(event, data);
         }
     }
 
@@ -155,22 +474,190 @@ impl AgentLoop {
             return self.process_system_message(msg).await;
         }
 
+        // Event rules: queue matching firings; disabled channels are ignored
+        if let Some(ref rules) = self.rules {
+            if rules.is_channel_disabled(&msg.channel) {
+                info!("Dropping message from disabled channel {}", msg.channel);
+                return Ok(None);
+            }
+            rules.observe_message(msg);
+        }
+
+        // Emoji reactions: 👍/👎 are recorded as feedback, 🔁 re-runs the last turn
+        let regenerate;
+        let msg = match crate::feedback::reaction_of(msg) {
+            Some(emoji) => match self.handle_reaction(msg, emoji) {
+                Some(retry) => {
+                    regenerate = retry;
+                    &regenerate
+                }
+                None => return Ok(None),
+            },
+            None => msg,
+        };
+
+        // `/incognito <message>`: answer just this message without saving it
+        let private;
+        let msg = match incognito::one_off(&msg.content) {
+            Some(text) => {
+                let mut one_off = msg.clone();
+                one_off.content = text.to_string();
+                one_off.metadata.insert(INCOGNITO_KEY.to_string(), json!(true));
+                private = one_off;
+                &private
+            }
+            None => msg,
+        };
+
         info!(
             "Processing message from {}:{}",
             msg.channel, msg.sender_id
         );
 
         let session_key = msg.session_key();
+        self.stats_user.clone_from(&session_key);
+        let incognito = msg.metadata.contains_key(INCOGNITO_KEY) || self.sessions.get_or_create(&session_key).incognito();
+
+        if let Some(ref presence) = self.presence {
+            presence.lock().await.touch(&msg.channel, &msg.chat_id);
+        }
+
+        // Human handoff: paused sessions, operator replies, /human and /resume
+        if let Some(ref desk) = self.handoff {
+            if let Some(out) = desk.handle(self.sessions.as_mut(), msg) {
+                for mut message in out {
+                    let key = format!("{}:{}", message.channel, message.chat_id);
+                    message.content = outgoing(&self.response_hooks, message.content, &message.channel, &key).await;
+                    if let Err(e) = self.outbound_tx.send(message).await {
+                        error!("Failed to send handoff message: {}", e);
+                    }
+                }
+                return Ok(None);
+            }
+        }
+
+        // Session-level slash commands (/reset, /usage, /pin, ...) from the shared registry
+        if let Some(inv) = crate::command::parse(&msg.content) {
+            let env = crate::command::CommandEnv {
+                channel: &msg.channel,
+                model: &self.model,
+                agent: None,
+                agents: &[],
+            };
+            let session = self.sessions.get_or_create(&session_key);
+            if let Some(reply) = inv.execute(session, &env) {
+                self.sessions.save_by_key(&session_key);
+                return Ok(Some(OutboundMessage::new(&msg.channel, &msg.chat_id, &reply)));
+            }
+        }
+
+        // First contact: welcome, language choice, linking and privacy notes
+        if let Some(ref onboarding) = self.onboarding {
+            let session = self.sessions.get_or_create(&session_key);
+            if let Some(onboarded) = onboarding.handle(session, &msg.channel, &msg.content) {
+                self.sessions.save_by_key(&session_key);
+                for text in onboarded.messages {
+                    if let Err(e) = self.outbound_tx.send(OutboundMessage::new(&msg.channel, &msg.chat_id, &text)).await {
+                        error!("Failed to send onboarding message: {}", e);
+                    }
+                }
+                if onboarded.consumed {
+                    return Ok(None);
+                }
+            }
+        }
+
+        // Reminders: /remind, /reminders, /snooze and "remind me to ..." messages
+        if let (Some(cron), Some(request)) = (self.cron.clone(), reminder::parse_request(&msg.content)) {
+            let session = self.sessions.get_or_create(&session_key);
+            let locale = session.locale().or_else(|| Locale::detect(&msg.content)).unwrap_or_default();
+            let tz = reminder::session_timezone(session, self.timezone.as_deref(), &msg.channel, locale);
+            let mut cron = cron.lock().await;
+            if let Some(reply) = reminder::handle_request(&mut cron, request, &msg.channel, &msg.chat_id, &tz, locale) {
+                return Ok(Some(OutboundMessage::new(&msg.channel, &msg.chat_id, &reply)));
+            }
+        }
+
+        // Everything scheduled for this chat: /upcoming [cancel <id> | move <id> <when>]
+        if let (Some(cron), Some(inv)) = (self.cron.clone(), crate::command::parse(&msg.content)) {
+            if inv.name() == "upcoming" {
+                let session = self.sessions.get_or_create(&session_key);
+                let locale = session.locale().unwrap_or_default();
+                let tz = reminder::session_timezone(session, self.timezone.as_deref(), &msg.channel, locale);
+                let mut cron = cron.lock().await;
+                let reply = crate::service::upcoming::handle_command(&mut cron, inv.args, &msg.channel, &msg.chat_id, &tz, locale);
+                return Ok(Some(OutboundMessage::new(&msg.channel, &msg.chat_id, &reply)));
+            }
+        }
+
+        // Workflows: /workflow [name] [input]
+        if let (Some(engine), Some(inv)) = (self.workflows.clone(), crate::command::parse(&msg.content)) {
+            if inv.name() == "workflow" {
+                let locale = self.sessions.get_or_create(&session_key).locale().unwrap_or_default();
+                let reply = crate::workflow::handle_command(&engine, inv.args, &msg.channel, &msg.chat_id, locale);
+                return Ok(Some(OutboundMessage::new(&msg.channel, &msg.chat_id, &reply)));
+            }
+        }
+
+        // Presence routing: /route [channel|auto]
+        if let (Some(presence), Some(inv)) = (self.presence.clone(), crate::command::parse(&msg.content)) {
+            if inv.name() == "route" {
+                let locale = self.sessions.get_or_create(&session_key).locale().unwrap_or_default();
+                let mut presence = presence.lock().await;
+                let reply = crate::channel::presence::handle_command(&mut presence, inv.args, &msg.channel, &msg.chat_id, locale);
+                let mut out = OutboundMessage::new(&msg.channel, &msg.chat_id, &reply);
+                out.metadata.insert(NO_ROUTE_KEY.to_string(), json!(true));
+                return Ok(Some(out));
+            }
+        }
+
+        // Canned answers from <workspace>/faq.yaml, without calling the model
+        let faq_hit = match &self.faq {
+            Some(faq) if msg.media.is_empty() => faq.answer(&msg.channel, &msg.content).await,
+            _ => None,
+        };
+        if let Some(hit) = faq_hit {
+            info!("FAQ answer '{}' ({:?}, score {:.2})", hit.id, hit.kind, hit.score);
+            let session = self.sessions.get_or_create(&session_key);
+            let locale = session.locale().unwrap_or_default();
+            if !incognito {
+                session.add_message("user", &msg.content);
+                session.add_message("assistant", &hit.answer);
+                self.sessions.save_by_key(&session_key);
+            }
+            let answer = outgoing(&self.response_hooks, hit.answer.clone(), &msg.channel, &session_key).await;
+            let answer = if incognito { incognito::label(&answer, locale) } else { answer };
+            let mut out = OutboundMessage::new(&msg.channel, &msg.chat_id, &answer);
+            out.metadata.insert(FAQ_KEY.to_string(), json!(hit.id));
+            return Ok(Some(out));
+        }
 
         // Update tool contexts
         self.message_tool.set_context(&msg.channel, &msg.chat_id).await;
+        if let Some(ref followups) = self.followup_tool {
+            followups.set_context(&msg.channel, &msg.chat_id).await;
+        }
+        let contacts = self.sessions.get_or_create(&session_key).contacts();
+        let linked = match &self.presence {
+            Some(presence) => presence.lock().await.linked_addresses(&msg.channel, &msg.chat_id),
+            None => Vec::new(),
+        };
+        self.forward_tool.set_context(&msg.channel, &msg.chat_id, &msg.content, contacts, linked).await;
+        self.expense_tool.set_context(&session_key).await;
+
+        // Text of photographed documents and screenshots
+        let content = match &self.media_ocr {
+            Some(ocr) if !msg.media.is_empty() => ocr.annotate_media(&msg.content, &msg.media).await,
+            _ => msg.content.clone(),
+        };
 
         // Build initial messages
         let session = self.sessions.get_or_create(&session_key);
+        let model = session.model_override().unwrap_or(&self.model).to_string();
         let history = session.get_history(50);
-        let messages = self.context.build_messages(
+        let mut messages = self.context.build_messages(
             &history,
-            &msg.content,
+            &content,
             if msg.media.is_empty() {
                 None
             } else {
@@ -180,29 +667,144 @@ impl AgentLoop {
             Some(&msg.chat_id),
         );
 
-        // Agent loop
-        let final_content = self
-            .run_agent_loop(messages)
-            .await?;
+        // Addresses of the contacts this message mentions
+        let contacts = session.contacts_context(&msg.content);
+        if let Some(system) = messages.first_mut().filter(|m| m.role == crate::types::Role::System && !contacts.is_empty()) {
+            system.content = Some(format!("{}\n\n{}", system.content.as_deref().unwrap_or_default(), contacts));
+        }
+
+        let handoff = self.handoff.as_ref().filter(|desk| desk.applies(&msg.channel));
+        if let Some(desk) = handoff {
+            desk.instruct(&mut messages);
+        }
+
+        // Speculative draft from the fast model while the agent loop runs
+        let locale = session.locale().or_else(|| Locale::detect(&msg.content)).unwrap_or_default();
+        let device = msg.metadata.get("device").and_then(|v| v.as_str());
+        let draft = self
+            .speculative
+            .as_ref()
+            .filter(|policy| policy.applies(&msg.channel, device))
+            .map(|policy| {
+                let (outbound, hooks) = (self.outbound_tx.clone(), self.response_hooks.clone());
+                let (channel, chat_id, key) = (msg.channel.clone(), msg.chat_id.clone(), session_key.clone());
+                Draft::spawn(self.provider.clone(), policy.draft_model(), messages.clone(), move |text| async move {
+                    let text = speculative::label_draft(&outgoing(&hooks, text, &channel, &key).await, locale);
+                    let text = if incognito { incognito::label(&text, locale) } else { text };
+                    let mut out = OutboundMessage::new(&channel, &chat_id, text);
+                    out.metadata.insert(DRAFT_KEY.to_string(), json!(true));
+                    outbound.send(out).await.is_ok()
+                })
+            });
+
+        // Agent loop (economy model first when escalation is on and no /model is set)
+        let escalation = self.escalation.as_ref().filter(|_| session.model_override().is_none());
+        let turn_start = std::time::Instant::now();
+        let mut turn = TurnMeta::default();
+        let final_content = match escalation {
+            Some(policy) => self.run_with_escalation(policy, messages, &msg.channel, &msg.content, &mut turn).await,
+            None => self.run_agent_loop(messages, &model, &mut turn, true).await,
+        };
+        turn.latency_ms = Some(turn_start.elapsed().as_millis() as u64);
+        let shown_draft = match draft {
+            Some(draft) => draft.finish().await,
+            None => None,
+        };
 
-        let final_content = final_content
+        let mut final_content = final_content?
             .unwrap_or_else(|| "I've completed processing but have no response to give.".to_string());
+        let handoff_reason = match handoff {
+            Some(_) => {
+                let (text, reason) = handoff::split_marker(&final_content);
+                final_content = text;
+                reason
+            }
+            None => None,
+        };
 
-        // Save to session
-        {
+        // Save to session (incognito turns are only ever in memory)
+        if !incognito {
             let session = self.sessions.get_or_create(&session_key);
-            session.add_message("user", &msg.content);
+            session.add_message("user", &content);
             session.add_message("assistant", &final_content);
+            session.set_turn_meta(&turn);
+            self.sessions.save_by_key(&session_key);
         }
-        self.sessions.save_by_key(&session_key);
+
+        // The model handed off: pause the session and tell the operator
+        if let (Some(desk), Some(reason)) = (handoff, handoff_reason) {
+            let (notice, mut operator) = desk.start(self.sessions.as_mut(), &session_key, &reason, locale);
+            final_content = format!("{}\n\n{}", final_content, notice).trim().to_string();
+            let key = format!("{}:{}", operator.channel, operator.chat_id);
+            operator.content = outgoing(&self.response_hooks, operator.content, &operator.channel, &key).await;
+            if let Err(e) = self.outbound_tx.send(operator).await {
+                error!("Failed to notify the handoff operator: {}", e);
+            }
+        }
+        for forward in self.forward_tool.take_sent().await {
+            if incognito {
+                forward.record_received(self.sessions.as_mut());
+            } else {
+                forward.record(self.sessions.as_mut());
+            }
+        }
+        let delivered = outgoing(&self.response_hooks, final_content.clone(), &msg.channel, &session_key).await;
+
+        self.emit_event(AgentEvent::Response {
+            channel: msg.channel.clone(),
+            chat_id: msg.chat_id.clone(),
+            content: delivered.clone(),
+        });
+        if !incognito {
+            self.emit_webhook(WebhookEvent::MessageProcessed, json!({
+                "channel": msg.channel,
+                "chatId": msg.chat_id,
+                "senderId": msg.sender_id,
+                "session": session_key,
+                "model": model,
+                "responseLength": final_content.len(),
+            })).await;
+        }
+
+        // A draft the reply agrees with stands; otherwise correct it
+        let reply = match (shown_draft, &self.speculative) {
+            (Some(draft), Some(policy)) if !policy.needs_correction(&draft, &final_content) => return Ok(None),
+            (Some(_), _) => speculative::label_correction(&delivered, locale),
+            (None, _) => delivered,
+        };
+        let reply = if incognito { incognito::label(&reply, locale) } else { reply };
 
         Ok(Some(OutboundMessage::new(
             &msg.channel,
             &msg.chat_id,
-            &final_content,
+            &reply,
         )))
     }
 
+    /// Handle a reaction on a bot message. Ratings are appended to the
+    /// workspace feedback log; a regenerate reaction drops the last turn and
+    /// returns the user message to run again.
+    fn handle_reaction(&mut self, msg: &InboundMessage, emoji: &str) -> Option<InboundMessage> {
+        let session_key = msg.session_key();
+        let session = self.sessions.get_or_create(&session_key);
+        match reaction_action(emoji)? {
+            ReactionAction::Rate(rating) => {
+                let reply = session.last_assistant_message()?;
+                let record = FeedbackRecord::from_reaction(rating, &msg.channel, &session_key, &msg.sender_id, reply);
+                match append_feedback(&self.workspace, &record) {
+                    Ok(()) => info!("Recorded {} feedback via {} reaction", rating.as_str(), msg.channel),
+                    Err(e) => error!("Failed to record feedback: {}", e),
+                }
+                None
+            }
+            ReactionAction::Regenerate => {
+                let content = session.pop_last_turn()?;
+                info!("Regenerating last reply for {}", session_key);
+                Some(InboundMessage::new(&msg.channel, &msg.sender_id, &msg.chat_id, content))
+            }
+        }
+    }
+
     /// Process a system message (e.g., subagent announce).
     async fn process_system_message(
         &mut self,
@@ -217,9 +819,20 @@ impl AgentLoop {
         };
 
         let session_key = format!("{origin_channel}:{origin_chat_id}");
+        self.stats_user.clone_from(&session_key);
         self.message_tool
             .set_context(&origin_channel, &origin_chat_id)
             .await;
+        if let Some(ref followups) = self.followup_tool {
+            followups.set_context(&origin_channel, &origin_chat_id).await;
+        }
+        let contacts = self.sessions.get_or_create(&session_key).contacts();
+        let linked = match &self.presence {
+            Some(presence) => presence.lock().await.linked_addresses(&origin_channel, &origin_chat_id),
+            None => Vec::new(),
+        };
+        self.forward_tool.set_context(&origin_channel, &origin_chat_id, "", contacts, linked).await;
+        self.expense_tool.set_context(&session_key).await;
 
         let session = self.sessions.get_or_create(&session_key);
         let history = session.get_history(50);
@@ -231,7 +844,8 @@ impl AgentLoop {
             Some(&origin_chat_id),
         );
 
-        let final_content = self.run_agent_loop(messages).await?.unwrap_or_else(|| {
+        let mut turn = TurnMeta::default();
+        let final_content = self.run_agent_loop(messages, &self.model, &mut turn, false).await?.unwrap_or_else(|| {
             "Background task completed.".to_string()
         });
 
@@ -241,6 +855,10 @@ impl AgentLoop {
             &format!("[System: {}] {}", msg.sender_id, msg.content),
         );
         session.add_message("assistant", &final_content);
+        session.set_turn_meta(&turn);
+        for forward in self.forward_tool.take_sent().await {
+            forward.record(self.sessions.as_mut());
+        }
 
         Ok(Some(OutboundMessage::new(
             &origin_channel,
@@ -249,30 +867,89 @@ impl AgentLoop {
         )))
     }
 
-    /// Run the LLM -> tool -> loop cycle.
+    /// Answer on the economy model and re-run on the powerful one if the
+    /// first answer isn't confident (see [`escalation`]). `turn` covers both
+    /// attempts.
+    async fn run_with_escalation(
+        &self,
+        policy: &EscalationPolicy,
+        messages: Vec<Message>,
+        channel: &str,
+        question: &str,
+        turn: &mut TurnMeta,
+    ) -> anyhow::Result<Option<String>> {
+        let economy = policy.economy_model().unwrap_or(&self.model);
+        let powerful = policy.powerful_model().unwrap_or(&self.model);
+        if economy == powerful {
+            return self.run_agent_loop(messages, powerful, turn, true).await;
+        }
+
+        let mut instructed = messages.clone();
+        policy.instruct(&mut instructed);
+        let mut economy_turn = TurnMeta::default();
+        // Its text isn't streamed: it may still be replaced
+        let first = self.run_agent_loop(instructed, economy, &mut economy_turn, false).await?;
+        turn.merge(economy_turn.clone());
+        let Some(first) = first else {
+            return Ok(None);
+        };
+        let assessment = policy.assess(self.provider.as_ref(), question, &first).await;
+        let Some(reason) = assessment.escalate else {
+            return Ok(Some(assessment.answer));
+        };
+
+        info!("Escalating from {} to {}: {:?}", economy, powerful, reason);
+        let mut powerful_turn = TurnMeta::default();
+        let second = self.run_agent_loop(messages, powerful, &mut powerful_turn, true).await?;
+        let record = EscalationRecord::new(
+            channel,
+            reason,
+            (economy, &economy_turn.usage()),
+            (powerful, &powerful_turn.usage()),
+        );
+        turn.merge(powerful_turn);
+        info!(
+            "Escalation cost: {} ${:.5} + {} ${:.5}",
+            economy, record.economy_cost_usd, powerful, record.powerful_cost_usd
+        );
+        if let Err(e) = escalation::append_record(&escalation::default_log_path(), &record) {
+            error!("Failed to record escalation: {}", e);
+        }
+        Ok(second.or(Some(assessment.answer)))
+    }
+
+    /// Run the LLM -> tool -> loop cycle, recording the model that answered,
+    /// the tokens of every call and the tools called in `turn`. When
+    /// streaming, `stream_text` sends the model's text as it is written.
     async fn run_agent_loop(
         &self,
         mut messages: Vec<Message>,
+        model: &str,
+        turn: &mut TurnMeta,
+        stream_text: bool,
     ) -> anyhow::Result<Option<String>> {
         for iteration in 0..self.max_iterations {
             debug!("Agent loop iteration {}", iteration + 1);
 
             let tools_defs = self.tools.get_definitions();
-            let response = self
-                .provider
-                .chat(
-                    &messages,
-                    if tools_defs.is_empty() {
-                        None
-                    } else {
-                        Some(&tools_defs)
-                    },
-                    &self.model,
-                    8192,
-                    0.7,
-                )
-                .await
-                .map_err(|e| anyhow::anyhow!("{e}"))?;
+            let tools = if tools_defs.is_empty() { None } else { Some(&tools_defs[..]) };
+            let response = match self.stream.as_ref().filter(|_| stream_text) {
+                Some(events) => {
+                    let (chunk_tx, mut chunk_rx) = mpsc::unbounded_channel();
+                    let forward = async {
+                        while let Some(text) = chunk_rx.recv().await {
+                            let _ = events.send(StreamEvent::ContentChunk { text });
+                        }
+                    };
+                    let extra = ChatExtra::default();
+                    let chat = self.provider.chat_stream(&messages, tools, model, 8192, 0.7, &extra, chunk_tx);
+                    tokio::join!(chat, forward).0
+                }
+                None => self.provider.chat(&messages, tools, model, 8192, 0.7).await,
+            }
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+            turn.add_usage(&response.usage);
+            turn.model = Some(response.model_or(model).to_string());
 
             if response.has_tool_calls() {
                 // Build tool_calls JSON for message history
@@ -301,12 +978,31 @@ impl AgentLoop {
                     let tc = &response.tool_calls[0];
                     let args_preview = Self::format_tool_args(&tc.arguments);
                     info!("🔧 Executing: {}({})", tc.name, args_preview);
+                    self.emit_event(AgentEvent::ToolStarted { name: tc.name.clone(), arguments: tc.arguments.clone() });
+                    self.emit_stream(StreamEvent::ToolStart {
+                        tool: tc.name.clone(),
+                        iteration: iteration + 1,
+                        args_preview: args_preview.clone(),
+                    });
 
                     let start = std::time::Instant::now();
                     let result = self.tools.execute(&tc.name, tc.arguments.clone()).await;
                     let elapsed = start.elapsed();
+                    self.record_tool_stats(&tc.name, elapsed, &result, model);
+                    turn.tools.push(tc.name.clone());
 
                     info!("✅ {} completed in {:.2}s", tc.name, elapsed.as_secs_f64());
+                    self.emit_stream(StreamEvent::tool_result(&tc.name, &result, iteration + 1, elapsed.as_millis() as u64));
+                    self.emit_event(AgentEvent::ToolFinished {
+                        name: tc.name.clone(),
+                        duration_ms: elapsed.as_millis() as u64,
+                        result: result.clone(),
+                    });
+                    self.emit_webhook(WebhookEvent::ToolExecuted, json!({
+                        "tool": tc.name,
+                        "durationMs": elapsed.as_millis() as u64,
+                        "resultLength": result.len(),
+                    })).await;
                     messages.push(Message::tool_result(&tc.id, &tc.name, &result));
                 } else {
                     // Parallel execution with join_all
@@ -318,6 +1014,14 @@ impl AgentLoop {
                         tool_names.join(", ")
                     );
 
+                    for tc in &response.tool_calls {
+                        self.emit_event(AgentEvent::ToolStarted { name: tc.name.clone(), arguments: tc.arguments.clone() });
+                        self.emit_stream(StreamEvent::ToolStart {
+                            tool: tc.name.clone(),
+                            iteration: iteration + 1,
+                            args_preview: Self::format_tool_args(&tc.arguments),
+                        });
+                    }
                     let start = std::time::Instant::now();
                     let futures: Vec<_> = response
                         .tool_calls
@@ -328,8 +1032,9 @@ impl AgentLoop {
                             let args = tc.arguments.clone();
                             let id = tc.id.clone();
                             async move {
+                                let started = std::time::Instant::now();
                                 let result = tools.execute(&name, args).await;
-                                (id, name, result)
+                                (id, name, result, started.elapsed())
                             }
                         })
                         .collect();
@@ -338,10 +1043,28 @@ impl AgentLoop {
                     let elapsed = start.elapsed();
                     info!("✅ All tools completed in {:.2}s", elapsed.as_secs_f64());
 
-                    for (id, name, result) in results {
+                    for (id, name, result, tool_elapsed) in results {
+                        self.record_tool_stats(&name, tool_elapsed, &result, model);
+                        turn.tools.push(name.clone());
+                        self.emit_stream(StreamEvent::tool_result(&name, &result, iteration + 1, tool_elapsed.as_millis() as u64));
+                        self.emit_event(AgentEvent::ToolFinished {
+                            name: name.clone(),
+                            duration_ms: elapsed.as_millis() as u64,
+                            result: result.clone(),
+                        });
+                        self.emit_webhook(WebhookEvent::ToolExecuted, json!({
+                            "tool": name,
+                            "durationMs": elapsed.as_millis() as u64,
+                            "resultLength": result.len(),
+                        })).await;
                         messages.push(Message::tool_result(&id, &name, &result));
                     }
                 }
+                self.emit_stream(StreamEvent::Thinking {
+                    iteration: iteration + 1,
+                    max_iter: self.max_iterations,
+                    tool_count: response.tool_calls.len(),
+                });
             } else {
                 // No tool calls, we're done
                 return Ok(response.content);
@@ -351,6 +1074,12 @@ impl AgentLoop {
         Ok(None)
     }
 
+    fn record_tool_stats(&self, tool: &str, elapsed: std::time::Duration, result: &str, model: &str) {
+        if let Some(ref stats) = self.tool_stats {
+            stats.record(&self.stats_user, tool, elapsed, result, model);
+        }
+    }
+
     /// Format tool arguments for logging (abbreviated to avoid clutter).
     fn format_tool_args(args: &HashMap<String, serde_json::Value>) -> String {
         if args.is_empty() {
@@ -396,6 +1125,28 @@ impl AgentLoop {
         Ok(response.map(|r| r.content).unwrap_or_default())
     }
 
+    /// [`AgentLoop::process_direct`], sending the reply text, tool calls
+    /// and thinking steps to `events` as they happen, then the final
+    /// `content` and `done`. A failure is sent as an `error` event too.
+    pub async fn process_direct_stream(
+        &mut self,
+        content: &str,
+        session_key: &str,
+        channel: &str,
+        chat_id: &str,
+        events: mpsc::UnboundedSender<StreamEvent>,
+    ) -> anyhow::Result<String> {
+        self.stream = Some(events.clone());
+        let result = self.process_direct(content, session_key, channel, chat_id).await;
+        self.stream = None;
+        let _ = match &result {
+            Ok(reply) => events.send(StreamEvent::Content { content: reply.clone() }),
+            Err(e) => events.send(StreamEvent::Error { content: e.to_string() }),
+        };
+        let _ = events.send(StreamEvent::Done);
+        result
+    }
+
     /// Self-correction loop: verify code with linter/tests, auto-fix if errors found.
     /// Returns (success: bool, final_output: String, iterations: u32)
     pub async fn self_correct(
@@ -404,54 +1155,49 @@ impl AgentLoop {
         path: &str,
         max_iterations: u32,
     ) -> anyhow::Result<(bool, String, u32)> {
+        let (report, iterations) = self.self_correct_report(language, path, max_iterations).await?;
+        let output = format!("Linter:\n{}\n\nTests:\n{}", report.lint_output, report.test_output);
+        Ok((report.overall_passed, output, iterations))
+    }
+
+    /// [`Self::self_correct`] returning the structured report of the last check.
+    pub async fn self_correct_report(
+        &mut self,
+        language: &str,
+        path: &str,
+        max_iterations: u32,
+    ) -> anyhow::Result<(CodeVerificationReport, u32)> {
         info!("🔄 Starting self-correction loop (max {} iterations)", max_iterations);
 
         let mut iteration = 0;
-        let mut last_output = String::new();
 
-        while iteration < max_iterations {
+        loop {
             iteration += 1;
             info!("🔄 Self-correction iteration {}/{}", iteration, max_iterations);
 
-            // Step 1: Run linter
-            info!("🔍 Running linter...");
-            let mut lint_args = HashMap::new();
-            lint_args.insert("language".to_string(), serde_json::json!(language));
-            lint_args.insert("path".to_string(), serde_json::json!(path));
-            let lint_result = self.tools.execute("run_linter", lint_args).await;
-
-            // Step 2: Run tests
-            info!("🧪 Running tests...");
-            let mut test_args = HashMap::new();
-            test_args.insert("language".to_string(), serde_json::json!(language));
-            test_args.insert("path".to_string(), serde_json::json!(path));
-            let test_result = self.tools.execute("run_tests", test_args).await;
-
-            last_output = format!("Linter:\n{}\n\nTests:\n{}", lint_result, test_result);
-
-            // Step 3: Check if passed
-            let lint_passed = lint_result.contains("✅") || lint_result.contains("no issues");
-            let tests_passed = test_result.contains("✅") || test_result.contains("passed");
+            // Step 1: Run linter and tests
+            let report = self.verify_code(language, path).await?;
 
-            if lint_passed && tests_passed {
+            // Step 2: Check if passed
+            if report.overall_passed {
                 info!("✅ All checks passed!");
-                return Ok((true, last_output, iteration));
+                return Ok((report, iteration));
             }
 
-            // Step 4: If last iteration, return failure
-            if iteration >= max_iterations {
+            // Step 3: If last iteration, return failure
+            if iteration >= max_iterations.max(1) {
                 info!("❌ Max iterations reached, checks still failing");
-                return Ok((false, last_output, iteration));
+                return Ok((report, iteration));
             }
 
-            // Step 5: Ask LLM to fix errors
+            // Step 4: Ask LLM to fix errors
             info!("🤖 Asking LLM to fix errors...");
             let fix_prompt = format!(
-                "The following linter and test errors were found:\n\n{}\n\n\
+                "The following linter and test errors were found:\n\nLinter:\n{}\n\nTests:\n{}\n\n\
                 Please analyze these errors and fix the code. Use file operations tools \
                 (read_file, write_file, edit_file) to make the necessary changes. \
                 Focus on fixing the actual errors, not refactoring or adding features.",
-                last_output
+                report.lint_output, report.test_output
             );
 
             let msg = InboundMessage::new("cli", "system", "self-correction", &fix_prompt);
@@ -461,14 +1207,12 @@ impl AgentLoop {
                 }
                 None => {
                     error!("❌ LLM failed to respond");
-                    return Ok((false, "LLM failed to respond".to_string(), iteration));
+                    return Ok((report, iteration));
                 }
             }
 
             // Loop continues to re-check
         }
-
-        Ok((false, last_output, iteration))
     }
 
     /// Verify code quality: run linter + tests, return detailed report.
@@ -479,37 +1223,62 @@ impl AgentLoop {
     ) -> anyhow::Result<CodeVerificationReport> {
         info!("🔍 Verifying code quality for {} at {}", language, path);
 
-        // Run linter
-        let mut lint_args = HashMap::new();
-        lint_args.insert("language".to_string(), serde_json::json!(language));
-        lint_args.insert("path".to_string(), serde_json::json!(path));
-        let lint_result = self.tools.execute("run_linter", lint_args).await;
-
-        // Run tests
-        let mut test_args = HashMap::new();
-        test_args.insert("language".to_string(), serde_json::json!(language));
-        test_args.insert("path".to_string(), serde_json::json!(path));
-        let test_result = self.tools.execute("run_tests", test_args).await;
-
-        let lint_passed = lint_result.contains("✅") || lint_result.contains("no issues");
-        let tests_passed = test_result.contains("✅") || test_result.contains("passed");
-
-        Ok(CodeVerificationReport {
-            lint_passed,
-            lint_output: lint_result,
-            tests_passed,
-            test_output: test_result,
-            overall_passed: lint_passed && tests_passed,
-        })
+        let mut args = HashMap::new();
+        args.insert("language".to_string(), serde_json::json!(language));
+        args.insert("path".to_string(), serde_json::json!(path));
+
+        info!("🔍 Running linter...");
+        let lint_output = self.tools.execute("run_linter", args.clone()).await;
+        info!("🧪 Running tests...");
+        let test_output = self.tools.execute("run_tests", args).await;
+
+        Ok(CodeVerificationReport::new(lint_output, test_output))
     }
 }
 
+/// `text` after the deployer's hooks, on its way to `channel`. Replies,
+/// drafts, FAQ answers and handoff messages all go out through here.
+async fn outgoing(hooks: &ResponseHooks, text: String, channel: &str, session_key: &str) -> String {
+    hooks.apply(text, &HookContext { channel, agent: None, session_key }).await
+}
+
+/// (Re-)register the tools that read and write files, guarded by `policy`.
+fn register_file_tools(tools: &ToolRegistry, workspace: &Path, allowed_dir: Option<PathBuf>, policy: Arc<FilePolicy>) {
+    tools.register(Arc::new(ReadFileTool::new(allowed_dir.clone()).with_policy(policy.clone())));
+    tools.register(Arc::new(WriteFileTool::new(allowed_dir.clone()).with_policy(policy.clone())));
+    tools.register(Arc::new(EditFileTool::new(allowed_dir.clone()).with_policy(policy.clone())));
+    tools.register(Arc::new(ApplyPatchTool::new(workspace.to_path_buf(), allowed_dir).with_policy(policy)));
+}
+
 /// Code verification report from linter + tests
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CodeVerificationReport {
     pub lint_passed: bool,
+    #[serde(skip)]
     pub lint_output: String,
     pub tests_passed: bool,
+    #[serde(skip)]
     pub test_output: String,
     pub overall_passed: bool,
+    /// Parsed `run_linter` result.
+    pub lint: QualityReport,
+    /// Parsed `run_tests` result.
+    pub tests: QualityReport,
+}
+
+impl CodeVerificationReport {
+    /// Build from the raw `run_linter`/`run_tests` tool results.
+    pub fn new(lint_output: String, test_output: String) -> Self {
+        let lint = QualityReport::from_tool_output(&lint_output);
+        let tests = QualityReport::from_tool_output(&test_output);
+        Self {
+            lint_passed: lint.passed,
+            lint_output,
+            tests_passed: tests.passed,
+            test_output,
+            overall_passed: lint.passed && tests.passed,
+            lint,
+            tests,
+        }
+    }
 }
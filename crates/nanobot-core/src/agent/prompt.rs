@@ -0,0 +1,280 @@
+//! System prompt compiler.
+//!
+//! Assembles the system prompt from named sections instead of one giant
+//! `format!`. Each section can be toggled off or capped via `PromptConfig`,
+//! lines repeated across sections (e.g. agent profile vs. meta context) are
+//! dropped, and optional sections are shed when the total budget is exceeded.
+
+use std::collections::HashSet;
+
+use tracing::debug;
+
+use crate::config::PromptConfig;
+
+/// Lines shorter than this are never deduplicated (headings, separators, etc.).
+const DEDUP_MIN_LINE_CHARS: usize = 12;
+
+/// A named chunk of the system prompt.
+#[derive(Debug, Clone)]
+pub struct PromptSection {
+    pub name: String,
+    pub content: String,
+    /// Required sections are never dropped to satisfy the total budget.
+    pub required: bool,
+}
+
+impl PromptSection {
+    pub fn new(name: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            content: content.into(),
+            required: false,
+        }
+    }
+
+    pub fn required(name: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            content: content.into(),
+            required: true,
+        }
+    }
+}
+
+/// Result of compiling a prompt.
+#[derive(Debug, Clone)]
+pub struct CompiledPrompt {
+    pub text: String,
+    pub tokens: usize,
+    /// Names of sections removed (disabled, empty after dedup, or over budget).
+    pub dropped: Vec<String>,
+}
+
+/// Builds a system prompt from sections according to a `PromptConfig`.
+pub struct PromptCompiler<'a> {
+    config: &'a PromptConfig,
+    sections: Vec<PromptSection>,
+}
+
+impl<'a> PromptCompiler<'a> {
+    pub fn new(config: &'a PromptConfig) -> Self {
+        Self {
+            config,
+            sections: Vec::new(),
+        }
+    }
+
+    /// Append an optional section. Empty content is ignored.
+    pub fn section(mut self, name: &str, content: impl Into<String>) -> Self {
+        self.sections.push(PromptSection::new(name, content));
+        self
+    }
+
+    /// Append a section that survives total-budget trimming.
+    pub fn required(mut self, name: &str, content: impl Into<String>) -> Self {
+        self.sections.push(PromptSection::required(name, content));
+        self
+    }
+
+    /// Assemble the final prompt.
+    pub fn compile(self) -> CompiledPrompt {
+        let mut dropped = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut kept: Vec<(PromptSection, usize)> = Vec::new();
+
+        for mut section in self.sections {
+            if !self.config.is_enabled(&section.name) {
+                dropped.push(section.name);
+                continue;
+            }
+
+            let content = if self.config.dedupe {
+                dedupe_lines(&section.content, &mut seen)
+            } else {
+                section.content.trim().to_string()
+            };
+            if content.is_empty() {
+                if !section.content.trim().is_empty() {
+                    dropped.push(section.name);
+                }
+                continue;
+            }
+
+            section.content = match self.config.section_budget(&section.name) {
+                Some(budget) => truncate_to_tokens(&content, budget),
+                None => content,
+            };
+            let tokens = estimate_tokens(&section.content);
+            kept.push((section, tokens));
+        }
+
+        // Shed optional sections from the end until we fit the total budget.
+        if self.config.max_tokens > 0 {
+            let mut total: usize = kept.iter().map(|(_, t)| *t).sum();
+            let mut i = kept.len();
+            while total > self.config.max_tokens && i > 0 {
+                i -= 1;
+                if !kept[i].0.required {
+                    let (section, tokens) = kept.remove(i);
+                    total -= tokens;
+                    dropped.push(section.name);
+                }
+            }
+        }
+
+        let text = kept
+            .iter()
+            .map(|(s, _)| s.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let tokens = estimate_tokens(&text);
+
+        debug!(
+            "System prompt compiled: ~{} tokens, {} sections{}",
+            tokens,
+            kept.len(),
+            if dropped.is_empty() {
+                String::new()
+            } else {
+                format!(", dropped [{}]", dropped.join(", "))
+            }
+        );
+
+        CompiledPrompt {
+            text,
+            tokens,
+            dropped,
+        }
+    }
+}
+
+/// Rough token estimate: ~4 ASCII chars per token, ~1 token per CJK/other char.
+pub fn estimate_tokens(text: &str) -> usize {
+    let (ascii, other) = text.chars().fold((0usize, 0usize), |(a, o), c| {
+        if c.is_ascii() { (a + 1, o) } else { (a, o + 1) }
+    });
+    ascii.div_ceil(4) + other
+}
+
+/// Remove lines already emitted by an earlier section.
+fn dedupe_lines(content: &str, seen: &mut HashSet<String>) -> String {
+    let mut out = Vec::new();
+    for line in content.trim().lines() {
+        let key = line.trim();
+        if key.chars().count() >= DEDUP_MIN_LINE_CHARS
+            && !key.starts_with('#')
+            && !seen.insert(key.to_string())
+        {
+            continue;
+        }
+        out.push(line);
+    }
+    out.join("\n").trim().to_string()
+}
+
+/// Cut `content` so its estimated size fits in `budget` tokens.
+/// Prefers cutting at a line boundary.
+fn truncate_to_tokens(content: &str, budget: usize) -> String {
+    if estimate_tokens(content) <= budget {
+        return content.to_string();
+    }
+    let mut used = 0usize;
+    let mut ascii_run = 0usize;
+    let mut end = 0usize;
+    for (i, c) in content.char_indices() {
+        if c.is_ascii() {
+            ascii_run += 1;
+            if ascii_run % 4 == 1 {
+                used += 1;
+            }
+        } else {
+            used += 1;
+        }
+        if used > budget {
+            break;
+        }
+        end = i + c.len_utf8();
+    }
+    let cut = &content[..end];
+    let cut = match cut.rfind('\n') {
+        Some(pos) if pos > end / 2 => &cut[..pos],
+        _ => cut,
+    };
+    format!("{}…", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("こんにちは"), 5);
+    }
+
+    #[test]
+    fn test_compile_joins_sections() {
+        let cfg = PromptConfig::default();
+        let out = PromptCompiler::new(&cfg)
+            .required("agent", "You are helpful.")
+            .section("meta", "Time: morning")
+            .section("empty", "")
+            .compile();
+        assert_eq!(out.text, "You are helpful.\n\nTime: morning");
+        assert!(out.dropped.is_empty());
+        assert!(out.tokens > 0);
+    }
+
+    #[test]
+    fn test_disabled_section_dropped() {
+        let mut cfg = PromptConfig::default();
+        cfg.disabled.push("wow".to_string());
+        let out = PromptCompiler::new(&cfg)
+            .required("agent", "base")
+            .section("wow", "be amazing")
+            .compile();
+        assert_eq!(out.text, "base");
+        assert_eq!(out.dropped, vec!["wow"]);
+    }
+
+    #[test]
+    fn test_dedupe_across_sections() {
+        let cfg = PromptConfig::default();
+        let out = PromptCompiler::new(&cfg)
+            .required("agent", "## SOUL\n- Prefer English unless asked otherwise.")
+            .section("common", "## SOUL\n- Prefer English unless asked otherwise.\n- Be concise and kind.")
+            .compile();
+        assert_eq!(out.text.matches("Prefer English").count(), 1);
+        assert_eq!(out.text.matches("## SOUL").count(), 2);
+        assert!(out.text.contains("Be concise"));
+    }
+
+    #[test]
+    fn test_total_budget_sheds_optional_from_end() {
+        let cfg = PromptConfig {
+            max_tokens: 10,
+            ..Default::default()
+        };
+        let out = PromptCompiler::new(&cfg)
+            .required("agent", "a".repeat(32))
+            .section("skills", "b".repeat(32))
+            .section("length", "c".repeat(8))
+            .compile();
+        assert!(out.text.contains("aaaa"));
+        assert!(!out.text.contains("bbbb"));
+        assert_eq!(out.dropped, vec!["length", "skills"]);
+    }
+
+    #[test]
+    fn test_section_budget_truncates() {
+        let mut cfg = PromptConfig::default();
+        cfg.section_max_tokens.insert("memory".to_string(), 3);
+        let out = PromptCompiler::new(&cfg)
+            .section("memory", "一二三四五六七八")
+            .compile();
+        assert_eq!(out.text, "一二三…");
+    }
+}
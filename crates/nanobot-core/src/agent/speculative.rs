@@ -0,0 +1,251 @@
+//! Speculative drafts.
+//!
+//! On channels and devices whose response profile sets `speculative`, a fast
+//! model (`agents.response.draftModel`) answers right away without tools and
+//! its reply is shown labeled as preliminary while the configured model works.
+//! A draft is only shown if it beats the real reply; once the real reply is
+//! in, it is sent as a correction when it differs materially from the draft,
+//! and dropped otherwise. Good for voice, where silence feels broken; off by
+//! default elsewhere, since a wrong first guess costs more than it saves
+//! when the answer is code.
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use crate::config::ResponseConfig;
+use crate::provider::LlmProvider;
+use crate::session::locale::Locale;
+use crate::types::Message;
+
+/// Outbound metadata key set on draft messages.
+pub const DRAFT_KEY: &str = "draft";
+/// Drafts are meant to be short.
+const DRAFT_MAX_TOKENS: u32 = 300;
+
+/// When to draft and when a draft needs correcting.
+#[derive(Debug, Clone)]
+pub struct SpeculativePolicy {
+    config: ResponseConfig,
+}
+
+impl SpeculativePolicy {
+    /// `None` when no draft model is configured.
+    pub fn from_config(config: &ResponseConfig) -> Option<Self> {
+        if config.draft_model.trim().is_empty() {
+            return None;
+        }
+        Some(Self { config: config.clone() })
+    }
+
+    pub fn draft_model(&self) -> &str {
+        self.config.draft_model.trim()
+    }
+
+    /// Whether replies on `channel` from `device` get a draft; the channel
+    /// profile takes priority over the device profile.
+    pub fn applies(&self, channel: &str, device: Option<&str>) -> bool {
+        self.config
+            .channels
+            .get(channel)
+            .and_then(|p| p.speculative)
+            .or_else(|| device.and_then(|d| self.config.devices.get(d)).and_then(|p| p.speculative))
+            .unwrap_or(false)
+    }
+
+    /// Whether `final_reply` differs enough from `draft` to send a correction.
+    pub fn needs_correction(&self, draft: &str, final_reply: &str) -> bool {
+        1.0 - similarity(draft, final_reply) >= self.config.draft_min_change.clamp(0.0, 1.0)
+    }
+}
+
+fn bigrams(text: &str) -> HashSet<(char, char)> {
+    let chars: Vec<char> = text
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    chars.windows(2).map(|w| (w[0], w[1])).collect()
+}
+
+/// Character-bigram overlap (Dice coefficient) of two texts, ignoring case,
+/// whitespace and punctuation: 1.0 for the same wording, 0.0 for nothing in
+/// common. Bigrams rather than words so Japanese compares as well as English.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (bigrams(a), bigrams(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let shared = a.intersection(&b).count();
+    2.0 * shared as f64 / (a.len() + b.len()) as f64
+}
+
+/// The draft as shown to the user.
+pub fn label_draft(text: &str, locale: Locale) -> String {
+    match locale {
+        Locale::Ja => format!("⏳ 速報（確認中）\n{}", text),
+        Locale::En => format!("⏳ Preliminary (still checking)\n{}", text),
+    }
+}
+
+/// The final reply when it replaces a draft.
+pub fn label_correction(text: &str, locale: Locale) -> String {
+    match locale {
+        Locale::Ja => format!("✏️ 訂正\n{}", text),
+        Locale::En => format!("✏️ Correction\n{}", text),
+    }
+}
+
+#[derive(Default)]
+struct DraftState {
+    /// The real reply has started; a draft arriving now is dropped.
+    closed: bool,
+    shown: Option<String>,
+}
+
+/// A draft being generated next to the real reply.
+pub struct Draft {
+    state: Arc<Mutex<DraftState>>,
+    task: JoinHandle<()>,
+}
+
+impl Draft {
+    /// Ask `model` for a quick answer to `messages` and pass it to `show`
+    /// unless the real reply got there first. `show` returns whether the
+    /// draft reached the user.
+    pub fn spawn<F, Fut>(provider: Arc<dyn LlmProvider>, model: &str, messages: Vec<Message>, show: F) -> Self
+    where
+        F: FnOnce(String) -> Fut + Send + 'static,
+        Fut: Future<Output = bool> + Send,
+    {
+        let state = Arc::new(Mutex::new(DraftState::default()));
+        let shared = state.clone();
+        let model = model.to_string();
+        let task = tokio::spawn(async move {
+            let text = match provider.chat(&messages, None, &model, DRAFT_MAX_TOKENS, 0.5).await {
+                Ok(resp) => resp.content.unwrap_or_default(),
+                Err(e) => {
+                    tracing::debug!("Draft from {} failed: {}", model, e);
+                    return;
+                }
+            };
+            let text = text.trim().to_string();
+            if text.is_empty() {
+                return;
+            }
+            // Hold the lock while showing, so the draft can't land after `preempt`.
+            let mut state = shared.lock().await;
+            if !state.closed && show(text.clone()).await {
+                state.shown = Some(text);
+            }
+        });
+        Self { state, task }
+    }
+
+    /// The real reply is starting to stream. Returns `true` if a draft is
+    /// already showing (hold the stream back and settle with [`Self::finish`]);
+    /// otherwise no draft will be shown anymore.
+    pub async fn preempt(&self) -> bool {
+        let mut state = self.state.lock().await;
+        state.closed = true;
+        state.shown.is_some()
+    }
+
+    /// Stop drafting (dropping a `Draft` does too); the draft the user saw, if any.
+    pub async fn finish(self) -> Option<String> {
+        let mut state = self.state.lock().await;
+        state.closed = true;
+        state.shown.take()
+    }
+}
+
+impl Drop for Draft {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ResponseProfile;
+    use crate::error::ProviderError;
+    use crate::types::{CompletionResponse, FinishReason, TokenUsage};
+    use async_trait::async_trait;
+    use std::time::Duration;
+
+    #[test]
+    fn test_applies_per_channel_and_device() {
+        assert!(SpeculativePolicy::from_config(&ResponseConfig::default()).is_none());
+
+        let mut config = ResponseConfig {
+            draft_model: "gpt-4o-mini".into(),
+            ..Default::default()
+        };
+        config.channels.insert(
+            "cli".into(),
+            ResponseProfile { speculative: Some(false), ..Default::default() },
+        );
+        let policy = SpeculativePolicy::from_config(&config).unwrap();
+        assert!(policy.applies("web", Some("voice")));
+        assert!(!policy.applies("web", Some("pc")));
+        assert!(!policy.applies("telegram", None));
+        assert!(!policy.applies("cli", Some("voice")));
+    }
+
+    #[test]
+    fn test_material_difference() {
+        let policy = SpeculativePolicy::from_config(&ResponseConfig {
+            draft_model: "fast".into(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(similarity("東京の天気は晴れです", "東京の天気は晴れです。"), 1.0);
+        assert!(!policy.needs_correction("The capital of Australia is Canberra.", "Australia's capital is Canberra."));
+        assert!(policy.needs_correction("The capital of Australia is Sydney.", "It's Canberra, not Sydney — a common mix-up."));
+        assert!(policy.needs_correction("明日は雨です", "明日は晴れの予報で、降水確率は10%です"));
+    }
+
+    struct Slow(Duration);
+
+    #[async_trait]
+    impl LlmProvider for Slow {
+        async fn chat(
+            &self,
+            _messages: &[Message],
+            _tools: Option<&[serde_json::Value]>,
+            _model: &str,
+            _max_tokens: u32,
+            _temperature: f64,
+        ) -> Result<CompletionResponse, ProviderError> {
+            tokio::time::sleep(self.0).await;
+            Ok(CompletionResponse {
+                content: Some("draft".into()),
+                tool_calls: vec![],
+                finish_reason: FinishReason::Stop,
+                usage: TokenUsage::default(),
+                model: None,
+            })
+        }
+
+        fn default_model(&self) -> &str {
+            "fast"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_draft_only_shown_before_the_reply() {
+        let fast = Draft::spawn(Arc::new(Slow(Duration::ZERO)), "fast", vec![], |_| async { true });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(fast.preempt().await);
+        assert_eq!(fast.finish().await.as_deref(), Some("draft"));
+
+        let slow = Draft::spawn(Arc::new(Slow(Duration::from_millis(50))), "fast", vec![], |_| async { true });
+        assert!(!slow.preempt().await);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(slow.finish().await, None);
+    }
+}
@@ -0,0 +1,62 @@
+//! Incremental output of [`AgentLoop::process_direct_stream`](super::AgentLoop::process_direct_stream).
+//!
+//! Events serialize to the same JSON as the hosted `/api/v1/chat/stream`
+//! SSE events (`{"type":"content_chunk","text":...}` and so on), so a
+//! client that renders one renders the other, and an HTTP front-end can
+//! forward them as `data:` lines unchanged.
+
+use serde::Serialize;
+
+/// Characters of a tool result carried by [`StreamEvent::ToolResult`].
+pub const RESULT_PREVIEW_CHARS: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// A piece of reply text as the model writes it.
+    ContentChunk { text: String },
+    /// Tool results are in; the model is called again.
+    Thinking { iteration: u32, max_iter: u32, tool_count: usize },
+    ToolStart { tool: String, iteration: u32, args_preview: String },
+    ToolResult {
+        tool: String,
+        /// The first [`RESULT_PREVIEW_CHARS`] characters.
+        result: String,
+        iteration: u32,
+        duration_ms: u64,
+        is_error: bool,
+    },
+    /// The final reply. It can differ from the streamed chunks (an
+    /// escalated answer, a handoff notice) and replaces them.
+    Content { content: String },
+    Error { content: String },
+    Done,
+}
+
+impl StreamEvent {
+    pub fn tool_result(tool: &str, result: &str, iteration: u32, duration_ms: u64) -> Self {
+        Self::ToolResult {
+            tool: tool.to_string(),
+            result: result.chars().take(RESULT_PREVIEW_CHARS).collect(),
+            iteration,
+            duration_ms,
+            is_error: crate::tool::stats::is_failure(result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_matches_hosted_events() {
+        let chunk = serde_json::to_value(StreamEvent::ContentChunk { text: "Hi".into() }).unwrap();
+        assert_eq!(chunk, json!({"type": "content_chunk", "text": "Hi"}));
+        let result = serde_json::to_value(StreamEvent::tool_result("web_search", &"x".repeat(600), 1, 42)).unwrap();
+        assert_eq!(result["type"], "tool_result");
+        assert_eq!(result["result"].as_str().unwrap().len(), RESULT_PREVIEW_CHARS);
+        assert_eq!(serde_json::to_value(StreamEvent::Done).unwrap(), json!({"type": "done"}));
+    }
+}
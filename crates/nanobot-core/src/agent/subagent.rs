@@ -4,8 +4,9 @@ use std::sync::Arc;
 use tokio::sync::mpsc;
 use tracing::{debug, error, info};
 
-use crate::config::ExecToolConfig;
+use crate::config::{ExecToolConfig, FilePolicyConfig};
 use crate::provider::LlmProvider;
+use crate::tool::file_policy::FilePolicy;
 use crate::tool::filesystem::{ListDirTool, ReadFileTool, WriteFileTool};
 use crate::tool::shell::ExecTool;
 use crate::tool::web::{WebFetchTool, WebSearchTool};
@@ -145,8 +146,9 @@ async fn run_subagent(
         None
     };
 
-    tools.register(Arc::new(ReadFileTool::new(allowed_dir.clone())));
-    tools.register(Arc::new(WriteFileTool::new(allowed_dir.clone())));
+    let policy = Arc::new(FilePolicy::new(workspace, &FilePolicyConfig::default()));
+    tools.register(Arc::new(ReadFileTool::new(allowed_dir.clone()).with_policy(policy.clone())));
+    tools.register(Arc::new(WriteFileTool::new(allowed_dir.clone()).with_policy(policy)));
     tools.register(Arc::new(ListDirTool::new(allowed_dir)));
     tools.register(Arc::new(ExecTool::new(
         workspace.display().to_string(),
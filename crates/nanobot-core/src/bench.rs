@@ -0,0 +1,274 @@
+//! Load testing for the HTTP chat API (`chatweb bench`).
+//!
+//! [`run`] keeps `concurrency` requests in flight against `POST /api/v1/chat`
+//! for a fixed duration and summarizes them in a [`BenchReport`]: latency
+//! percentiles, throughput and errors by kind. Each worker talks in its own
+//! session. A reply carrying an `error_code` counts as an error even though
+//! it came back as 200, so outages show up in the numbers.
+//!
+//! [`MockProvider`] answers instantly (or after a fixed delay) without calling
+//! a model, for measuring the server itself.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use crate::error::ProviderError;
+use crate::provider::LlmProvider;
+use crate::types::{CompletionResponse, FinishReason, Message, TokenUsage};
+
+/// What to send, where, and how hard.
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Base URL of the server, e.g. `https://chatweb.ai`.
+    pub endpoint: String,
+    pub concurrency: usize,
+    pub duration: Duration,
+    pub message: String,
+    /// Sent as `Authorization: Bearer`.
+    pub token: Option<String>,
+    /// Per-request timeout.
+    pub timeout: Duration,
+}
+
+/// How one request ended.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Outcome {
+    Ok,
+    /// Non-success HTTP status.
+    Status(u16),
+    /// A 200 whose body reported a failure (`error_code`).
+    Code(String),
+    Timeout,
+    Network,
+}
+
+impl Outcome {
+    fn label(&self) -> String {
+        match self {
+            Outcome::Ok => "ok".to_string(),
+            Outcome::Status(status) => format!("http {}", status),
+            Outcome::Code(code) => code.clone(),
+            Outcome::Timeout => "timeout".to_string(),
+            Outcome::Network => "network".to_string(),
+        }
+    }
+}
+
+/// Results of a run.
+#[derive(Debug, Default)]
+pub struct BenchReport {
+    /// Latencies of successful requests, ascending.
+    latencies: Vec<Duration>,
+    /// Failed requests by kind.
+    pub errors: BTreeMap<String, usize>,
+    pub elapsed: Duration,
+}
+
+impl BenchReport {
+    fn from_samples(mut samples: Vec<(Duration, Outcome)>, elapsed: Duration) -> Self {
+        samples.sort();
+        let mut report = BenchReport { elapsed, ..Default::default() };
+        for (latency, outcome) in samples {
+            match outcome {
+                Outcome::Ok => report.latencies.push(latency),
+                other => *report.errors.entry(other.label()).or_default() += 1,
+            }
+        }
+        report
+    }
+
+    pub fn requests(&self) -> usize {
+        self.latencies.len() + self.failures()
+    }
+
+    pub fn failures(&self) -> usize {
+        self.errors.values().sum()
+    }
+
+    /// Share of requests that failed, `0.0..=1.0`.
+    pub fn error_rate(&self) -> f64 {
+        match self.requests() {
+            0 => 0.0,
+            n => self.failures() as f64 / n as f64,
+        }
+    }
+
+    /// Successful requests per second.
+    pub fn throughput(&self) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            secs if secs > 0.0 => self.latencies.len() as f64 / secs,
+            _ => 0.0,
+        }
+    }
+
+    /// Latency below which `p` percent of successful requests finished.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let rank = (p / 100.0 * self.latencies.len() as f64).ceil() as usize;
+        self.latencies.get(rank.clamp(1, self.latencies.len()) - 1).copied()
+    }
+
+    pub fn render(&self) -> String {
+        let ms = |d: Option<Duration>| d.map(|d| format!("{} ms", d.as_millis())).unwrap_or_else(|| "-".to_string());
+        let mut out = format!(
+            "Requests:   {} in {:.1}s ({:.1} req/s)\nErrors:     {} ({:.1}%)\n",
+            self.requests(),
+            self.elapsed.as_secs_f64(),
+            self.throughput(),
+            self.failures(),
+            self.error_rate() * 100.0,
+        );
+        for (kind, count) in &self.errors {
+            out.push_str(&format!("  {:<14} {}\n", kind, count));
+        }
+        out.push_str(&format!(
+            "Latency:    p50 {}  p90 {}  p99 {}  max {}\n",
+            ms(self.percentile(50.0)),
+            ms(self.percentile(90.0)),
+            ms(self.percentile(99.0)),
+            ms(self.latencies.last().copied()),
+        ));
+        out
+    }
+}
+
+/// Drive the chat API as described by `opts`.
+pub async fn run(opts: &BenchOptions) -> anyhow::Result<BenchReport> {
+    anyhow::ensure!(opts.concurrency > 0, "concurrency must be at least 1");
+    let client = reqwest::Client::builder().timeout(opts.timeout).build()?;
+    let url = format!("{}/api/v1/chat", opts.endpoint.trim_end_matches('/'));
+    let run_id = uuid::Uuid::new_v4().to_string()[..8].to_string();
+    let started = Instant::now();
+    let deadline = started + opts.duration;
+
+    let workers: Vec<_> = (0..opts.concurrency)
+        .map(|worker| {
+            let (client, url, opts) = (client.clone(), url.clone(), opts.clone());
+            let session_id = format!("api:bench-{}-{}", run_id, worker);
+            tokio::spawn(async move {
+                let mut samples = Vec::new();
+                while Instant::now() < deadline {
+                    let sent = Instant::now();
+                    let outcome = send(&client, &url, &opts, &session_id).await;
+                    samples.push((sent.elapsed(), outcome));
+                }
+                samples
+            })
+        })
+        .collect();
+
+    let mut samples = Vec::new();
+    for worker in workers {
+        samples.extend(worker.await?);
+    }
+    Ok(BenchReport::from_samples(samples, started.elapsed()))
+}
+
+async fn send(client: &reqwest::Client, url: &str, opts: &BenchOptions, session_id: &str) -> Outcome {
+    let mut req = client.post(url).json(&serde_json::json!({
+        "message": opts.message,
+        "session_id": session_id,
+    }));
+    if let Some(token) = &opts.token {
+        req = req.bearer_auth(token);
+    }
+    let resp = match req.send().await {
+        Ok(resp) => resp,
+        Err(e) if e.is_timeout() => return Outcome::Timeout,
+        Err(_) => return Outcome::Network,
+    };
+    let status = resp.status();
+    if !status.is_success() {
+        return Outcome::Status(status.as_u16());
+    }
+    match resp.json::<serde_json::Value>().await {
+        Ok(body) => match body["error_code"].as_str() {
+            Some(code) => Outcome::Code(code.to_string()),
+            None => Outcome::Ok,
+        },
+        Err(e) if e.is_timeout() => Outcome::Timeout,
+        Err(_) => Outcome::Network,
+    }
+}
+
+/// Parse `500ms`, `30s`, `2m` or `1h` (a bare number is seconds).
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(split);
+    let n: u64 = n.parse().map_err(|_| format!("invalid duration '{}'", s))?;
+    match unit.trim() {
+        "ms" => Ok(Duration::from_millis(n)),
+        "" | "s" => Ok(Duration::from_secs(n)),
+        "m" => Ok(Duration::from_secs(n * 60)),
+        "h" => Ok(Duration::from_secs(n * 3600)),
+        _ => Err(format!("invalid duration '{}' (use ms, s, m or h)", s)),
+    }
+}
+
+/// Provider that replies with a canned answer after `latency`, for
+/// benchmarking the server without calling (or paying for) a model.
+pub struct MockProvider {
+    pub latency: Duration,
+}
+
+#[async_trait]
+impl LlmProvider for MockProvider {
+    async fn chat(
+        &self,
+        _messages: &[Message],
+        _tools: Option<&[serde_json::Value]>,
+        _model: &str,
+        _max_tokens: u32,
+        _temperature: f64,
+    ) -> Result<CompletionResponse, ProviderError> {
+        tokio::time::sleep(self.latency).await;
+        Ok(CompletionResponse {
+            content: Some("This is a benchmark reply.".to_string()),
+            tool_calls: vec![],
+            finish_reason: FinishReason::Stop,
+            usage: TokenUsage { prompt_tokens: 10, completion_tokens: 6, total_tokens: 16 },
+            model: None,
+        })
+    }
+
+    fn default_model(&self) -> &str {
+        "mock"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let ms = Duration::from_millis;
+        let mut samples: Vec<(Duration, Outcome)> = (1..=100).map(|i| (ms(i), Outcome::Ok)).collect();
+        samples.push((ms(5), Outcome::Status(503)));
+        samples.push((ms(5), Outcome::Code("rate_limited".to_string())));
+        let report = BenchReport::from_samples(samples, Duration::from_secs(10));
+
+        assert_eq!(report.requests(), 102);
+        assert_eq!(report.percentile(50.0), Some(ms(50)));
+        assert_eq!(report.percentile(99.0), Some(ms(99)));
+        assert_eq!(report.percentile(100.0), Some(ms(100)));
+        assert!((report.throughput() - 10.0).abs() < 1e-9);
+        assert_eq!(report.errors["http 503"], 1);
+        assert!(report.render().contains("rate_limited"));
+        assert_eq!(BenchReport::default().percentile(50.0), None);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("60s"), Ok(Duration::from_secs(60)));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("15"), Ok(Duration::from_secs(15)));
+        assert!(parse_duration("fast").is_err());
+    }
+}
@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
 use async_trait::async_trait;
 use futures::stream::StreamExt;
 use serde_json::json;
@@ -5,18 +9,123 @@ use tokio::sync::mpsc;
 use tracing::{error, info, warn};
 
 use crate::config::DiscordConfig;
+use crate::feedback::{reaction_action, reaction_message};
 use crate::types::{InboundMessage, OutboundMessage};
+use crate::util::http::{self, ClientClass};
 
 use super::{is_allowed, Channel};
 
 const DISCORD_API_BASE: &str = "https://discord.com/api/v10";
 
+/// Interaction tokens stop working after 15 minutes; keep a margin.
+const INTERACTION_TOKEN_TTL: Duration = Duration::from_secs(14 * 60);
+
 /// Discord channel using Gateway WebSocket.
 pub struct DiscordChannel {
     config: DiscordConfig,
     inbound_tx: mpsc::Sender<InboundMessage>,
     running: bool,
     client: reqwest::Client,
+    started_at: Option<Instant>,
+    /// Deferred slash commands per channel id, answered by the next reply there.
+    pending: Mutex<HashMap<String, Vec<PendingReply>>>,
+}
+
+/// A slash command (APPLICATION_COMMAND interaction) from the gateway.
+#[derive(Debug, Clone, PartialEq)]
+struct Interaction {
+    id: String,
+    token: String,
+    application_id: String,
+    channel_id: String,
+    user_id: String,
+    command: String,
+    /// The `question` option of `/ask`.
+    question: String,
+    locale: String,
+}
+
+impl Interaction {
+    fn parse(payload: &serde_json::Value) -> Option<Self> {
+        // 2 = APPLICATION_COMMAND; autocomplete and components are not used
+        if payload.get("type").and_then(|v| v.as_u64()) != Some(2) {
+            return None;
+        }
+        let str_at = |pointer: &str| payload.pointer(pointer).and_then(|v| v.as_str()).map(str::to_string);
+        let question = payload
+            .pointer("/data/options")
+            .and_then(|v| v.as_array())
+            .and_then(|options| options.iter().find(|o| o.get("name").and_then(|v| v.as_str()) == Some("question")))
+            .and_then(|o| o.get("value"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .trim()
+            .to_string();
+        Some(Self {
+            id: str_at("/id")?,
+            token: str_at("/token")?,
+            application_id: str_at("/application_id")?,
+            channel_id: str_at("/channel_id")?,
+            // `member.user` in servers, `user` in DMs
+            user_id: str_at("/member/user/id").or_else(|| str_at("/user/id"))?,
+            command: str_at("/data/name")?,
+            question,
+            locale: str_at("/locale").unwrap_or_default(),
+        })
+    }
+
+    fn is_ja(&self) -> bool {
+        self.locale.starts_with("ja")
+    }
+}
+
+/// A deferred interaction waiting for the agent's reply.
+#[derive(Debug, Clone)]
+struct PendingReply {
+    application_id: String,
+    token: String,
+    expires_at: Instant,
+}
+
+/// The commands registered with Discord; descriptions come from the shared
+/// command registry where it has them.
+fn command_definitions() -> serde_json::Value {
+    let spec = |name: &str| crate::command::COMMANDS.iter().find(|c| c.name == name);
+    let described = |name: &str| {
+        let (en, ja) = spec(name).map(|c| (c.help_en, c.help)).unwrap_or(("", ""));
+        json!({
+            "name": name,
+            "type": 1,
+            "description": en,
+            "description_localizations": {"ja": ja},
+        })
+    };
+    json!([
+        {
+            "name": "ask",
+            "type": 1,
+            "description": "Ask the assistant",
+            "description_localizations": {"ja": "アシスタントに質問する"},
+            "options": [{
+                "type": 3,
+                "name": "question",
+                "description": "What do you want to know?",
+                "description_localizations": {"ja": "質問内容"},
+                "required": true,
+            }],
+        },
+        described("reset"),
+        described("status"),
+    ])
+}
+
+fn format_uptime(elapsed: Duration) -> String {
+    let minutes = elapsed.as_secs() / 60;
+    match (minutes / 1440, minutes / 60 % 24, minutes % 60) {
+        (0, 0, m) => format!("{}m", m),
+        (0, h, m) => format!("{}h {}m", h, m),
+        (d, h, _) => format!("{}d {}h", d, h),
+    }
 }
 
 impl DiscordChannel {
@@ -25,7 +134,9 @@ impl DiscordChannel {
             config,
             inbound_tx,
             running: false,
-            client: reqwest::Client::new(),
+            client: http::client_for(ClientClass::Webhook),
+            started_at: None,
+            pending: Mutex::new(HashMap::new()),
         }
     }
 
@@ -37,6 +148,7 @@ impl DiscordChannel {
         let (mut write, mut read) = ws_stream.split();
 
         let mut _seq: Option<i64> = None;
+        let mut bot_user_id: Option<String> = None;
 
         while let Some(msg) = read.next().await {
             let msg = msg?;
@@ -79,13 +191,37 @@ impl DiscordChannel {
                             .await?;
                     }
                     0 if event_type == Some("READY") => {
+                        bot_user_id = payload
+                            .and_then(|p| p.pointer("/user/id"))
+                            .and_then(|v| v.as_str())
+                            .map(|s| s.to_string());
                         info!("Discord gateway READY");
+                        let application_id = payload
+                            .and_then(|p| p.pointer("/application/id"))
+                            .and_then(|v| v.as_str());
+                        if let Some(application_id) = application_id.filter(|_| self.config.slash_commands) {
+                            if let Err(e) = self.register_commands(application_id).await {
+                                warn!("Failed to register Discord slash commands: {}", e);
+                            }
+                        }
                     }
                     0 if event_type == Some("MESSAGE_CREATE") => {
                         if let Some(payload) = payload {
                             self.handle_message_create(payload).await;
                         }
                     }
+                    0 if event_type == Some("INTERACTION_CREATE") && self.config.slash_commands => {
+                        if let Some(interaction) = payload.and_then(Interaction::parse) {
+                            if let Err(e) = self.handle_interaction(interaction).await {
+                                warn!("Failed to answer Discord interaction: {}", e);
+                            }
+                        }
+                    }
+                    0 if event_type == Some("MESSAGE_REACTION_ADD") => {
+                        if let Some(payload) = payload {
+                            self.handle_reaction_add(payload, bot_user_id.as_deref()).await;
+                        }
+                    }
                     7 | 9 => {
                         info!("Discord gateway requested reconnect");
                         break;
@@ -138,6 +274,150 @@ impl DiscordChannel {
             error!("Failed to send Discord message to bus: {}", e);
         }
     }
+
+    /// Replace the bot's slash commands with [`command_definitions`].
+    async fn register_commands(&self, application_id: &str) -> anyhow::Result<()> {
+        let url = if self.config.command_guild_id.is_empty() {
+            format!("{}/applications/{}/commands", DISCORD_API_BASE, application_id)
+        } else {
+            format!(
+                "{}/applications/{}/guilds/{}/commands",
+                DISCORD_API_BASE, application_id, self.config.command_guild_id
+            )
+        };
+        self.client
+            .put(&url)
+            .header("Authorization", format!("Bot {}", self.config.token))
+            .json(&command_definitions())
+            .send()
+            .await?
+            .error_for_status()?;
+        info!("Registered Discord slash commands");
+        Ok(())
+    }
+
+    /// Answer `/status` right away; acknowledge `/ask` and `/reset` with a
+    /// "thinking..." response and pass them to the agent, whose reply then
+    /// replaces it (see [`Self::take_pending`]).
+    async fn handle_interaction(&self, interaction: Interaction) -> anyhow::Result<()> {
+        let ja = interaction.is_ja();
+        if !is_allowed(&interaction.user_id, &self.config.allow_from) {
+            let text = if ja { "このボットを使う権限がありません。" } else { "You are not allowed to use this bot." };
+            return self.respond(&interaction, ephemeral(text)).await;
+        }
+
+        let content = match interaction.command.as_str() {
+            "ask" if !interaction.question.is_empty() => interaction.question.clone(),
+            "reset" => "/reset".to_string(),
+            "status" => return self.respond(&interaction, ephemeral(&self.status_text(ja))).await,
+            _ => {
+                let text = if ja { "不明なコマンドです。" } else { "Unknown command." };
+                return self.respond(&interaction, ephemeral(text)).await;
+            }
+        };
+
+        // 5 = DEFERRED_CHANNEL_MESSAGE_WITH_SOURCE; the reset notice is only for the caller
+        let flags = if interaction.command == "reset" { 64 } else { 0 };
+        self.respond(&interaction, json!({"type": 5, "data": {"flags": flags}})).await?;
+        self.pending
+            .lock()
+            .unwrap()
+            .entry(interaction.channel_id.clone())
+            .or_default()
+            .push(PendingReply {
+                application_id: interaction.application_id.clone(),
+                token: interaction.token.clone(),
+                expires_at: Instant::now() + INTERACTION_TOKEN_TTL,
+            });
+
+        let mut msg = InboundMessage::new("discord", &interaction.user_id, &interaction.channel_id, &content);
+        msg.metadata.insert("discord_interaction_id".to_string(), json!(interaction.id));
+        if let Err(e) = self.inbound_tx.send(msg).await {
+            error!("Failed to send Discord interaction to bus: {}", e);
+        }
+        Ok(())
+    }
+
+    async fn respond(&self, interaction: &Interaction, body: serde_json::Value) -> anyhow::Result<()> {
+        let url = format!(
+            "{}/interactions/{}/{}/callback",
+            DISCORD_API_BASE, interaction.id, interaction.token
+        );
+        self.client.post(&url).json(&body).send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    fn status_text(&self, ja: bool) -> String {
+        let uptime = format_uptime(self.started_at.map(|t| t.elapsed()).unwrap_or_default());
+        if ja {
+            format!("nanobot v{}\nDiscord: 接続中（稼働 {}）", crate::VERSION, uptime)
+        } else {
+            format!("nanobot v{}\nDiscord: connected (up {})", crate::VERSION, uptime)
+        }
+    }
+
+    /// The oldest unexpired deferred interaction in `channel_id`, if any.
+    fn take_pending(&self, channel_id: &str) -> Option<PendingReply> {
+        let mut pending = self.pending.lock().unwrap();
+        let queue = pending.get_mut(channel_id)?;
+        queue.retain(|p| p.expires_at > Instant::now());
+        let reply = (!queue.is_empty()).then(|| queue.remove(0));
+        if queue.is_empty() {
+            pending.remove(channel_id);
+        }
+        reply
+    }
+
+    /// Replace the "thinking..." response of a deferred interaction.
+    async fn edit_interaction(&self, reply: &PendingReply, content: &str) -> anyhow::Result<Option<String>> {
+        let url = format!(
+            "{}/webhooks/{}/{}/messages/@original",
+            DISCORD_API_BASE, reply.application_id, reply.token
+        );
+        let sent: serde_json::Value = self
+            .client
+            .patch(&url)
+            .json(&json!({"content": content}))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .unwrap_or_default();
+        Ok(sent.get("id").and_then(|v| v.as_str()).map(str::to_string))
+    }
+
+    /// Forward a reaction on one of the bot's messages as feedback.
+    async fn handle_reaction_add(&self, payload: &serde_json::Value, bot_user_id: Option<&str>) {
+        let str_field = |key| payload.get(key).and_then(|v: &serde_json::Value| v.as_str()).unwrap_or("");
+        let user_id = str_field("user_id");
+        let channel_id = str_field("channel_id");
+        let message_id = str_field("message_id");
+        let emoji = payload
+            .pointer("/emoji/name")
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+
+        if user_id.is_empty() || channel_id.is_empty() || emoji.is_empty() {
+            return;
+        }
+        // Only reactions by users on messages the bot wrote
+        if Some(user_id) == bot_user_id {
+            return;
+        }
+        let author = str_field("message_author_id");
+        if !author.is_empty() && bot_user_id.is_some_and(|bot| bot != author) {
+            return;
+        }
+        if reaction_action(emoji).is_none() || !is_allowed(user_id, &self.config.allow_from) {
+            return;
+        }
+
+        let msg = reaction_message("discord", user_id, channel_id, emoji, message_id);
+        if let Err(e) = self.inbound_tx.send(msg).await {
+            error!("Failed to send Discord reaction to bus: {}", e);
+        }
+    }
 }
 
 #[async_trait]
@@ -152,6 +432,7 @@ impl Channel for DiscordChannel {
         }
 
         self.running = true;
+        self.started_at = Some(Instant::now());
         info!("Starting Discord gateway...");
 
         while self.running {
@@ -177,6 +458,17 @@ impl Channel for DiscordChannel {
     }
 
     async fn send(&self, msg: &OutboundMessage) -> anyhow::Result<()> {
+        self.send_tracked(msg).await.map(|_| ())
+    }
+
+    async fn send_tracked(&self, msg: &OutboundMessage) -> anyhow::Result<Option<String>> {
+        if let Some(reply) = self.take_pending(&msg.chat_id) {
+            match self.edit_interaction(&reply, &msg.content).await {
+                Ok(id) => return Ok(id),
+                Err(e) => warn!("Failed to answer Discord interaction, posting instead: {}", e),
+            }
+        }
+
         let url = format!("{}/channels/{}/messages", DISCORD_API_BASE, msg.chat_id);
         let mut payload = json!({"content": msg.content});
 
@@ -205,14 +497,92 @@ impl Channel for DiscordChannel {
                 continue;
             }
 
-            response.error_for_status()?;
-            return Ok(());
+            let sent: serde_json::Value = response.error_for_status()?.json().await.unwrap_or_default();
+            return Ok(sent.get("id").and_then(|v| v.as_str()).map(str::to_string));
         }
 
-        Ok(())
+        Err(anyhow::anyhow!("Discord rate limit retries exhausted"))
     }
 
     fn is_running(&self) -> bool {
         self.running
     }
 }
+
+/// An immediate reply only the caller sees (4 = CHANNEL_MESSAGE_WITH_SOURCE, 64 = EPHEMERAL).
+fn ephemeral(text: &str) -> serde_json::Value {
+    json!({"type": 4, "data": {"content": text, "flags": 64}})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interaction() {
+        let payload = json!({
+            "type": 2,
+            "id": "111",
+            "token": "tok",
+            "application_id": "222",
+            "channel_id": "333",
+            "locale": "ja",
+            "member": {"user": {"id": "444"}},
+            "data": {"name": "ask", "options": [{"name": "question", "type": 3, "value": " What time is it? "}]},
+        });
+        let interaction = Interaction::parse(&payload).unwrap();
+        assert_eq!(interaction.user_id, "444");
+        assert_eq!(interaction.command, "ask");
+        assert_eq!(interaction.question, "What time is it?");
+        assert!(interaction.is_ja());
+
+        // DMs carry `user` instead of `member`
+        let dm = json!({
+            "type": 2, "id": "1", "token": "t", "application_id": "2", "channel_id": "3",
+            "user": {"id": "5"}, "data": {"name": "reset"},
+        });
+        let interaction = Interaction::parse(&dm).unwrap();
+        assert_eq!((interaction.user_id.as_str(), interaction.question.as_str()), ("5", ""));
+
+        // Pings and component clicks are not commands
+        assert!(Interaction::parse(&json!({"type": 1, "id": "1", "token": "t"})).is_none());
+    }
+
+    #[test]
+    fn test_command_definitions() {
+        let commands = command_definitions();
+        let names: Vec<&str> = commands.as_array().unwrap().iter().filter_map(|c| c["name"].as_str()).collect();
+        assert_eq!(names, vec!["ask", "reset", "status"]);
+        for command in commands.as_array().unwrap() {
+            let description = command["description"].as_str().unwrap();
+            assert!(!description.is_empty() && description.len() <= 100);
+        }
+        assert_eq!(commands[0]["options"][0]["required"], true);
+    }
+
+    #[test]
+    fn test_take_pending() {
+        let (tx, _rx) = mpsc::channel(1);
+        let channel = DiscordChannel::new(DiscordConfig::default(), tx);
+        let reply = |token: &str, expires_at| PendingReply { application_id: "1".into(), token: token.into(), expires_at };
+        channel.pending.lock().unwrap().insert(
+            "c".into(),
+            vec![
+                reply("old", Instant::now() - Duration::from_secs(1)),
+                reply("a", Instant::now() + INTERACTION_TOKEN_TTL),
+                reply("b", Instant::now() + INTERACTION_TOKEN_TTL),
+            ],
+        );
+        assert_eq!(channel.take_pending("c").unwrap().token, "a");
+        assert_eq!(channel.take_pending("c").unwrap().token, "b");
+        assert!(channel.take_pending("c").is_none());
+        assert!(channel.pending.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_format_uptime() {
+        assert_eq!(format_uptime(Duration::from_secs(59)), "0m");
+        assert_eq!(format_uptime(Duration::from_secs(3 * 3600 + 120)), "3h 2m");
+        assert_eq!(format_uptime(Duration::from_secs(2 * 86400 + 5 * 3600)), "2d 5h");
+    }
+}
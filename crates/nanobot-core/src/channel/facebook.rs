@@ -4,6 +4,7 @@ use serde_json::json;
 use tracing::{error, info};
 
 use crate::types::OutboundMessage;
+use crate::util::http::{self, ClientClass};
 
 use super::Channel;
 
@@ -63,7 +64,7 @@ impl FacebookChannel {
     pub fn new() -> Self {
         Self {
             running: false,
-            client: reqwest::Client::new(),
+            client: http::client_for(ClientClass::Webhook),
         }
     }
 
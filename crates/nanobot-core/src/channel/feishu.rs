@@ -5,6 +5,7 @@ use tracing::{error, info};
 
 use crate::config::FeishuConfig;
 use crate::types::{InboundMessage, OutboundMessage};
+use crate::util::http::{self, ClientClass};
 
 use super::Channel;
 
@@ -29,7 +30,7 @@ impl FeishuChannel {
             config,
             inbound_tx,
             running: false,
-            client: reqwest::Client::new(),
+            client: http::client_for(ClientClass::Webhook),
             access_token: None,
         }
     }
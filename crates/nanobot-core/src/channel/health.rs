@@ -0,0 +1,447 @@
+//! Live channel probes for `chatweb channels status`: each enabled channel's
+//! credentials and endpoints are exercised for real and reported with
+//! latency and, on failure, what to change.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::config::ChannelsConfig;
+use crate::util::http::{self, ClientClass};
+
+const TELEGRAM_API_BASE: &str = "https://api.telegram.org";
+const DISCORD_API_BASE: &str = "https://discord.com/api/v10";
+const LINE_API_BASE: &str = "https://api.line.me/v2/bot";
+const SLACK_API_BASE: &str = "https://slack.com/api";
+const FEISHU_API_BASE: &str = "https://open.feishu.cn/open-apis";
+
+/// How long a single probe may take.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Overall state of one channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelState {
+    Healthy,
+    Failing,
+    /// Enabled but missing credentials.
+    Unconfigured,
+    Disabled,
+}
+
+/// One probe against a channel's API.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Check {
+    pub name: &'static str,
+    pub ok: bool,
+    pub latency_ms: u64,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+/// Probe results for one channel.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelHealth {
+    pub channel: &'static str,
+    pub state: ChannelState,
+    pub checks: Vec<Check>,
+}
+
+impl ChannelHealth {
+    fn probed(channel: &'static str, checks: Vec<Check>) -> Self {
+        let state = if checks.iter().all(|c| c.ok) { ChannelState::Healthy } else { ChannelState::Failing };
+        Self { channel, state, checks }
+    }
+
+    fn idle(channel: &'static str, state: ChannelState) -> Self {
+        Self { channel, state, checks: Vec::new() }
+    }
+}
+
+/// Probe every supported channel concurrently. Disabled channels and
+/// channels without credentials are reported without any network calls.
+pub async fn probe_all(channels: &ChannelsConfig) -> Vec<ChannelHealth> {
+    let telegram = async {
+        let c = &channels.telegram;
+        match (c.enabled, c.token.is_empty()) {
+            (false, _) => ChannelHealth::idle("telegram", ChannelState::Disabled),
+            (true, true) => ChannelHealth::idle("telegram", ChannelState::Unconfigured),
+            (true, false) => ChannelHealth::probed("telegram", probe_telegram(&c.token).await),
+        }
+    };
+    let discord = async {
+        let c = &channels.discord;
+        match (c.enabled, c.token.is_empty()) {
+            (false, _) => ChannelHealth::idle("discord", ChannelState::Disabled),
+            (true, true) => ChannelHealth::idle("discord", ChannelState::Unconfigured),
+            (true, false) => ChannelHealth::probed("discord", probe_discord(&c.token, &c.gateway_url).await),
+        }
+    };
+    let line = async {
+        let c = &channels.line;
+        match (c.enabled, c.channel_access_token.is_empty()) {
+            (false, _) => ChannelHealth::idle("line", ChannelState::Disabled),
+            (true, true) => ChannelHealth::idle("line", ChannelState::Unconfigured),
+            (true, false) => ChannelHealth::probed("line", probe_line(&c.channel_access_token).await),
+        }
+    };
+    let slack = async {
+        let c = &channels.slack;
+        match (c.enabled, c.bot_token.is_empty()) {
+            (false, _) => ChannelHealth::idle("slack", ChannelState::Disabled),
+            (true, true) => ChannelHealth::idle("slack", ChannelState::Unconfigured),
+            (true, false) => ChannelHealth::probed("slack", probe_slack(&c.bot_token, &c.app_token).await),
+        }
+    };
+    let whatsapp = async {
+        let c = &channels.whatsapp;
+        match c.enabled {
+            false => ChannelHealth::idle("whatsapp", ChannelState::Disabled),
+            true => ChannelHealth::probed("whatsapp", vec![probe_bridge(&c.bridge_url).await]),
+        }
+    };
+    let feishu = async {
+        let c = &channels.feishu;
+        match (c.enabled, c.app_id.is_empty() || c.app_secret.is_empty()) {
+            (false, _) => ChannelHealth::idle("feishu", ChannelState::Disabled),
+            (true, true) => ChannelHealth::idle("feishu", ChannelState::Unconfigured),
+            (true, false) => ChannelHealth::probed("feishu", vec![probe_feishu(&c.app_id, &c.app_secret).await]),
+        }
+    };
+    let (telegram, discord, line, slack, whatsapp, feishu) =
+        tokio::join!(telegram, discord, line, slack, whatsapp, feishu);
+    vec![telegram, discord, line, slack, whatsapp, feishu]
+}
+
+/// Text report, one line per check.
+pub fn render(reports: &[ChannelHealth]) -> String {
+    let mut out = String::from("Channel Status\n");
+    for report in reports {
+        let (mark, note) = match report.state {
+            ChannelState::Healthy => ("✓", ""),
+            ChannelState::Failing => ("✗", ""),
+            ChannelState::Unconfigured => ("!", "enabled but not configured"),
+            ChannelState::Disabled => ("-", "disabled"),
+        };
+        let heading = format!("  {} {:<9} {}", mark, report.channel, note);
+        out.push('\n');
+        out.push_str(heading.trim_end());
+        out.push('\n');
+        for check in &report.checks {
+            out.push_str(&format!(
+                "      {} {:<8} {:>5}ms  {}\n",
+                if check.ok { "✓" } else { "✗" },
+                check.name,
+                check.latency_ms,
+                check.detail
+            ));
+            if let Some(hint) = &check.hint {
+                out.push_str(&format!("                         → {}\n", hint));
+            }
+        }
+    }
+    out
+}
+
+/// Run `probe` under [`PROBE_TIMEOUT`], timing it. `probe` yields the
+/// detail line, or an error with an optional hint.
+async fn check<F>(name: &'static str, probe: F) -> Check
+where
+    F: Future<Output = Result<String, (String, Option<String>)>>,
+{
+    let started = Instant::now();
+    let result = tokio::time::timeout(PROBE_TIMEOUT, probe)
+        .await
+        .unwrap_or_else(|_| Err((format!("no response within {}s", PROBE_TIMEOUT.as_secs()), None)));
+    let latency_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(detail) => Check { name, ok: true, latency_ms, detail, hint: None },
+        Err((detail, hint)) => Check { name, ok: false, latency_ms, detail, hint },
+    }
+}
+
+/// Send `request` and read a JSON body, whatever the status.
+async fn fetch(request: reqwest::RequestBuilder) -> Result<(u16, Value), (String, Option<String>)> {
+    let response = request.send().await.map_err(|e| (network_error(&e), network_hint(&e)))?;
+    let status = response.status().as_u16();
+    let body = response.json::<Value>().await.unwrap_or(Value::Null);
+    Ok((status, body))
+}
+
+fn network_error(e: &reqwest::Error) -> String {
+    if e.is_timeout() {
+        "timed out".to_string()
+    } else if e.is_connect() {
+        format!("could not connect: {}", e)
+    } else {
+        e.to_string()
+    }
+}
+
+fn network_hint(e: &reqwest::Error) -> Option<String> {
+    e.is_connect().then(|| "check DNS, proxy settings and outbound firewall rules".to_string())
+}
+
+/// What to do about a rejected credential or an API error.
+fn credential_hint(channel: &str, status: u16, error: &str) -> Option<String> {
+    let rejected = matches!(status, 401 | 403)
+        || matches!(error, "invalid_auth" | "not_authed" | "token_revoked" | "token_expired" | "account_inactive");
+    if !rejected {
+        return match status {
+            429 => Some("rate limited; try again in a minute".to_string()),
+            500..=599 => Some("the platform is having trouble; check its status page".to_string()),
+            _ => None,
+        };
+    }
+    Some(
+        match channel {
+            "telegram" => "get a new token from @BotFather and set channels.telegram.token",
+            "discord" => "reset the bot token in the Developer Portal and set channels.discord.token",
+            "line" => "issue a new channel access token in the LINE Developers console and set channels.line.channelAccessToken",
+            "slack" => "reinstall the app to the workspace and set channels.slack.botToken",
+            "slack-app" => "create an app-level token with connections:write and set channels.slack.appToken",
+            "feishu" => "check channels.feishu.appId and appSecret in the Feishu developer console",
+            _ => "check the channel's credentials",
+        }
+        .to_string(),
+    )
+}
+
+fn api_error(channel: &str, status: u16, body: &Value, message: &str) -> (String, Option<String>) {
+    let detail = body[message].as_str().filter(|m| !m.is_empty()).unwrap_or("request failed");
+    let code = body["error"].as_str().unwrap_or_default();
+    (format!("HTTP {}: {}", status, detail), credential_hint(channel, status, code))
+}
+
+async fn probe_telegram(token: &str) -> Vec<Check> {
+    let base = format!("{}/bot{}", TELEGRAM_API_BASE, token);
+    let me = check("getMe", async {
+        let (status, body) = fetch(http::client_for(ClientClass::Webhook).get(format!("{}/getMe", base))).await?;
+        match body["result"]["username"].as_str() {
+            Some(username) if body["ok"] == true => Ok(format!("@{}", username)),
+            _ => Err(api_error("telegram", status, &body, "description")),
+        }
+    });
+    let webhook = async {
+        let info = check("webhook", async {
+            let (status, body) = fetch(http::client_for(ClientClass::Webhook).get(format!("{}/getWebhookInfo", base))).await?;
+            if body["ok"] != true {
+                return Err(api_error("telegram", status, &body, "description"));
+            }
+            let result = &body["result"];
+            let url = result["url"].as_str().unwrap_or_default();
+            if url.is_empty() {
+                return Ok("none set (long polling)".to_string());
+            }
+            match result["last_error_message"].as_str() {
+                Some(error) => Err((
+                    format!("{}: Telegram's last delivery failed: {}", url, error),
+                    Some("make sure the webhook URL is public and serves HTTPS with a valid certificate".to_string()),
+                )),
+                None => Ok(format!("{} ({} pending)", url, result["pending_update_count"].as_u64().unwrap_or(0))),
+            }
+        })
+        .await;
+        let url = info.detail.split_whitespace().next().filter(|u| u.starts_with("https://")).map(str::to_string);
+        match url {
+            Some(url) if info.ok => vec![info, probe_reachable(&url).await],
+            _ => vec![info],
+        }
+    };
+    let (me, mut webhook) = tokio::join!(me, webhook);
+    webhook.insert(0, me);
+    webhook
+}
+
+async fn probe_discord(token: &str, gateway_url: &str) -> Vec<Check> {
+    let auth = check("token", async {
+        let request = http::client_for(ClientClass::Webhook)
+            .get(format!("{}/users/@me", DISCORD_API_BASE))
+            .header("Authorization", format!("Bot {}", token));
+        let (status, body) = fetch(request).await?;
+        match body["username"].as_str() {
+            Some(username) if status == 200 => Ok(username.to_string()),
+            _ => Err(api_error("discord", status, &body, "message")),
+        }
+    });
+    let gateway = check("gateway", async {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+        let (mut ws, _) = tokio_tungstenite::connect_async(gateway_url)
+            .await
+            .map_err(|e| (format!("could not connect to {}: {}", gateway_url, e), None))?;
+        while let Some(message) = ws.next().await {
+            let message = message.map_err(|e| (e.to_string(), None))?;
+            if let WsMessage::Text(text) = message {
+                let hello: Value = serde_json::from_str(text.as_str()).unwrap_or(Value::Null);
+                let _ = ws.close(None).await;
+                return match hello["op"].as_i64() {
+                    Some(10) => Ok(format!("hello (heartbeat {}ms)", hello["d"]["heartbeat_interval"].as_u64().unwrap_or(0))),
+                    _ => Err((format!("expected HELLO, got: {}", text.as_str()), None)),
+                };
+            }
+        }
+        Err(("gateway closed before HELLO".to_string(), Some("check channels.discord.gatewayUrl".to_string())))
+    });
+    let (auth, gateway) = tokio::join!(auth, gateway);
+    vec![auth, gateway]
+}
+
+async fn probe_line(token: &str) -> Vec<Check> {
+    let bearer = format!("Bearer {}", token);
+    let info = check("token", async {
+        let request = http::client_for(ClientClass::Webhook).get(format!("{}/info", LINE_API_BASE)).header("Authorization", &bearer);
+        let (status, body) = fetch(request).await?;
+        match body["displayName"].as_str() {
+            Some(name) if status == 200 => Ok(name.to_string()),
+            _ => Err(api_error("line", status, &body, "message")),
+        }
+    });
+    // LINE sends a test event to the registered endpoint and reports what
+    // it got back, which checks reachability from LINE's side.
+    let webhook = check("webhook", async {
+        let request = http::client_for(ClientClass::Webhook)
+            .get(format!("{}/channel/webhook/endpoint", LINE_API_BASE))
+            .header("Authorization", &bearer);
+        let (status, body) = fetch(request).await?;
+        let Some(endpoint) = body["endpoint"].as_str().filter(|e| !e.is_empty()) else {
+            return match status {
+                200 | 404 => Err(("no webhook URL set".to_string(), Some("set the webhook URL in the LINE Developers console".to_string()))),
+                _ => Err(api_error("line", status, &body, "message")),
+            };
+        };
+        if body["active"] == false {
+            return Err((format!("{} (disabled)", endpoint), Some("turn on \"Use webhook\" in the LINE Developers console".to_string())));
+        }
+        let request = http::client_for(ClientClass::Webhook)
+            .post(format!("{}/channel/webhook/test", LINE_API_BASE))
+            .header("Authorization", &bearer)
+            .json(&json!({}));
+        let (_, test) = fetch(request).await?;
+        if test["success"] == true {
+            Ok(format!("{} (LINE got HTTP {})", endpoint, test["statusCode"].as_u64().unwrap_or(200)))
+        } else {
+            Err((
+                format!(
+                    "{}: LINE could not deliver: {} {}",
+                    endpoint,
+                    test["reason"].as_str().unwrap_or("unknown"),
+                    test["detail"].as_str().unwrap_or_default()
+                )
+                .trim_end()
+                .to_string(),
+                Some("make sure the server is running and the webhook URL is public and answers 200".to_string()),
+            ))
+        }
+    });
+    let (info, webhook) = tokio::join!(info, webhook);
+    vec![info, webhook]
+}
+
+async fn probe_slack(bot_token: &str, app_token: &str) -> Vec<Check> {
+    let slack_call = |name: &'static str, method: &'static str, token: String, hint_for: &'static str| {
+        check(name, async move {
+            let request = http::client_for(ClientClass::Webhook).post(format!("{}/{}", SLACK_API_BASE, method)).bearer_auth(token);
+            let (status, body) = fetch(request).await?;
+            if body["ok"] == true {
+                return Ok(match body["team"].as_str() {
+                    Some(team) => format!("{} in {}", body["user"].as_str().unwrap_or("bot"), team),
+                    None => "socket mode available".to_string(),
+                });
+            }
+            let error = body["error"].as_str().unwrap_or("request failed");
+            Err((format!("HTTP {}: {}", status, error), credential_hint(hint_for, status, error)))
+        })
+    };
+    let auth = slack_call("auth", "auth.test", bot_token.to_string(), "slack");
+    if app_token.is_empty() {
+        return vec![auth.await];
+    }
+    let socket = slack_call("socket", "apps.connections.open", app_token.to_string(), "slack-app");
+    let (auth, socket) = tokio::join!(auth, socket);
+    vec![auth, socket]
+}
+
+async fn probe_bridge(bridge_url: &str) -> Check {
+    check("bridge", async {
+        let (mut ws, _) = tokio_tungstenite::connect_async(bridge_url).await.map_err(|e| {
+            (
+                format!("could not connect to {}: {}", bridge_url, e),
+                Some("start the WhatsApp bridge or fix channels.whatsapp.bridgeUrl".to_string()),
+            )
+        })?;
+        let _ = ws.close(None).await;
+        Ok(format!("connected to {}", bridge_url))
+    })
+    .await
+}
+
+async fn probe_feishu(app_id: &str, app_secret: &str) -> Check {
+    check("token", async {
+        let request = http::client_for(ClientClass::Webhook)
+            .post(format!("{}/auth/v3/tenant_access_token/internal", FEISHU_API_BASE))
+            .json(&json!({"app_id": app_id, "app_secret": app_secret}));
+        let (status, body) = fetch(request).await?;
+        match body["code"].as_i64() {
+            Some(0) => Ok(format!("tenant token issued (expires in {}s)", body["expire"].as_u64().unwrap_or(0))),
+            _ => Err((
+                format!("HTTP {}: {}", status, body["msg"].as_str().unwrap_or("request failed")),
+                credential_hint("feishu", 401, ""),
+            )),
+        }
+    })
+    .await
+}
+
+/// Whether `url` answers at all; any HTTP status counts as reachable.
+async fn probe_reachable(url: &str) -> Check {
+    check("reach", async {
+        let response = http::client_for(ClientClass::Webhook).get(url).send().await.map_err(|e| {
+            (network_error(&e), Some("the webhook URL must be reachable from the internet".to_string()))
+        })?;
+        Ok(format!("HTTP {}", response.status().as_u16()))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credential_hint() {
+        assert!(credential_hint("telegram", 401, "").unwrap().contains("@BotFather"));
+        assert!(credential_hint("slack", 200, "invalid_auth").unwrap().contains("botToken"));
+        assert!(credential_hint("line", 429, "").unwrap().contains("rate limited"));
+        assert_eq!(credential_hint("discord", 400, ""), None);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_channels_are_not_probed() {
+        let mut channels = ChannelsConfig::default();
+        channels.telegram.enabled = true;
+        let reports = probe_all(&channels).await;
+        let telegram = reports.iter().find(|r| r.channel == "telegram").unwrap();
+        assert_eq!(telegram.state, ChannelState::Unconfigured);
+        assert!(reports.iter().filter(|r| r.channel != "telegram").all(|r| r.state == ChannelState::Disabled));
+
+        let text = render(&reports);
+        assert!(text.contains("! telegram  enabled but not configured"));
+        let json = serde_json::to_value(&reports).unwrap();
+        assert_eq!(json[0]["state"], "unconfigured");
+    }
+
+    #[tokio::test]
+    async fn test_check_reports_failure_with_hint() {
+        let failed = check("token", async { Err(("HTTP 401: Unauthorized".to_string(), credential_hint("line", 401, ""))) }).await;
+        assert!(!failed.ok);
+        assert!(failed.hint.as_deref().unwrap().contains("channelAccessToken"));
+        let report = ChannelHealth::probed("line", vec![failed]);
+        assert_eq!(report.state, ChannelState::Failing);
+    }
+}
@@ -0,0 +1,224 @@
+//! Per-channel working hours.
+//!
+//! `channels.hours` maps a channel name to the times it is attended. While a
+//! channel is off hours, [`ChannelManager`](super::ChannelManager) holds
+//! non-urgent replies instead of sending them, optionally answering each chat
+//! once with an away note, and sends the held replies in order when the hours
+//! resume. Replies marked [`URGENT_KEY`] or containing one of the channel's
+//! urgent keywords go out immediately. Held replies are kept in a JSON file
+//! under the data directory so a restart doesn't lose them.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+
+use chrono::{DateTime, Datelike, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::config::ChannelHoursConfig;
+use crate::service::followup::QuietHours;
+use crate::types::OutboundMessage;
+use crate::util::tz::Timezone;
+
+/// Outbound metadata key (`true`) for replies that ignore working hours.
+pub const URGENT_KEY: &str = "urgent";
+
+/// One channel's working hours, resolved from its config.
+#[derive(Debug, Clone)]
+pub struct WorkingHours {
+    quiet: Option<QuietHours>,
+    quiet_days: Vec<Weekday>,
+    zone: Timezone,
+    away_message: Option<String>,
+    urgent_keywords: Vec<String>,
+}
+
+impl WorkingHours {
+    pub fn from_config(config: &ChannelHoursConfig, default_tz: Option<&str>) -> Self {
+        let quiet = config.quiet_hours.as_deref().and_then(|s| {
+            let parsed = QuietHours::parse(s);
+            if parsed.is_none() {
+                warn!("Ignoring invalid quietHours '{}' (expected HH:MM-HH:MM)", s);
+            }
+            parsed
+        });
+        let quiet_days = config
+            .quiet_days
+            .iter()
+            .filter_map(|d| {
+                let parsed = d.parse::<Weekday>().ok();
+                if parsed.is_none() {
+                    warn!("Ignoring invalid quietDays entry '{}'", d);
+                }
+                parsed
+            })
+            .collect();
+        let tz = config.timezone.as_deref().or(default_tz);
+        Self {
+            quiet,
+            quiet_days,
+            zone: crate::util::tz::parse_or_local(tz),
+            away_message: config.away_message.clone().filter(|m| !m.trim().is_empty()),
+            urgent_keywords: config.urgent_keywords.iter().map(|k| k.to_lowercase()).collect(),
+        }
+    }
+
+    /// Whether the channel is off hours at `now`.
+    pub fn is_off(&self, now: DateTime<Utc>) -> bool {
+        let local = now.with_timezone(&self.zone);
+        self.quiet_days.contains(&local.weekday()) || self.quiet.is_some_and(|q| q.contains(local.time()))
+    }
+
+    fn is_urgent(&self, msg: &OutboundMessage) -> bool {
+        if msg.metadata.get(URGENT_KEY).and_then(|v| v.as_bool()) == Some(true) {
+            return true;
+        }
+        let content = msg.content.to_lowercase();
+        self.urgent_keywords.iter().any(|k| content.contains(k.as_str()))
+    }
+}
+
+/// What to do with an outbound message.
+#[derive(Debug)]
+pub enum Screened {
+    Send(OutboundMessage),
+    /// Held until the hours resume; send `note` now if there is one.
+    Held { note: Option<OutboundMessage> },
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HeldFile {
+    held: Vec<OutboundMessage>,
+    /// `channel:chat_id` already sent the away note in this off period.
+    notified: BTreeSet<String>,
+}
+
+/// Working hours of all configured channels and the replies held for them.
+pub struct ChannelHours {
+    hours: HashMap<String, WorkingHours>,
+    store_path: PathBuf,
+    state: HeldFile,
+}
+
+impl ChannelHours {
+    pub fn new(config: &HashMap<String, ChannelHoursConfig>, default_tz: Option<&str>, store_path: PathBuf) -> Self {
+        let hours = config
+            .iter()
+            .map(|(channel, c)| (channel.clone(), WorkingHours::from_config(c, default_tz)))
+            .collect();
+        let state = crate::util::read_json(&store_path, "held replies");
+        Self { hours, store_path, state }
+    }
+
+    pub fn default_path() -> PathBuf {
+        crate::config::get_data_dir().join("held_replies.json")
+    }
+
+    /// Whether any channel has working hours configured.
+    pub fn is_enabled(&self) -> bool {
+        !self.hours.is_empty()
+    }
+
+    /// Replies waiting for their channel's hours.
+    pub fn held(&self) -> &[OutboundMessage] {
+        &self.state.held
+    }
+
+    /// Send `msg` now, or hold it because its channel is off hours.
+    pub fn screen(&mut self, msg: OutboundMessage, now: DateTime<Utc>) -> Screened {
+        let Some(hours) = self.hours.get(&msg.channel) else {
+            return Screened::Send(msg);
+        };
+        if !hours.is_off(now) || hours.is_urgent(&msg) {
+            return Screened::Send(msg);
+        }
+        let note = hours
+            .away_message
+            .as_ref()
+            .filter(|_| self.state.notified.insert(format!("{}:{}", msg.channel, msg.chat_id)))
+            .map(|text| OutboundMessage::new(&msg.channel, &msg.chat_id, text));
+        self.state.held.push(msg);
+        self.save();
+        Screened::Held { note }
+    }
+
+    /// Held replies whose channels are back in hours, oldest first.
+    pub fn release(&mut self, now: DateTime<Utc>) -> Vec<OutboundMessage> {
+        let hours = &self.hours;
+        let off = |channel: &str| hours.get(channel).is_some_and(|h| h.is_off(now));
+        let before = (self.state.held.len(), self.state.notified.len());
+        let (ready, held): (Vec<_>, Vec<_>) = std::mem::take(&mut self.state.held)
+            .into_iter()
+            .partition(|m| !off(&m.channel));
+        self.state.held = held;
+        self.state
+            .notified
+            .retain(|key| key.split_once(':').is_some_and(|(channel, _)| off(channel)));
+        if (self.state.held.len(), self.state.notified.len()) != before {
+            self.save();
+        }
+        ready
+    }
+
+    fn save(&self) {
+        crate::util::write_json(&self.store_path, &self.state, "held replies");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        // 2026-03-02 is a Monday
+        Utc.with_ymd_and_hms(2026, 3, day, hour, 0, 0).unwrap()
+    }
+
+    fn line_hours(dir: &std::path::Path) -> ChannelHours {
+        let config = ChannelHoursConfig {
+            quiet_hours: Some("19:00-09:00".to_string()),
+            quiet_days: vec!["sun".to_string()],
+            timezone: Some("UTC".to_string()),
+            away_message: Some("Back at 9:00.".to_string()),
+            urgent_keywords: vec!["Outage".to_string()],
+        };
+        ChannelHours::new(&HashMap::from([("line".to_string(), config)]), None, dir.join("held.json"))
+    }
+
+    #[test]
+    fn test_working_hours() {
+        let dir = tempfile::tempdir().unwrap();
+        let hours = line_hours(dir.path());
+        let line = &hours.hours["line"];
+        assert!(!line.is_off(at(2, 12)));
+        assert!(line.is_off(at(2, 20)));
+        assert!(line.is_off(at(3, 8)));
+        assert!(line.is_off(at(8, 12)), "Sunday is off all day");
+    }
+
+    #[test]
+    fn test_hold_and_release() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut hours = line_hours(dir.path());
+        let reply = |text: &str| OutboundMessage::new("line", "U1", text);
+
+        assert!(matches!(hours.screen(reply("now"), at(2, 12)), Screened::Send(_)));
+        assert!(matches!(hours.screen(OutboundMessage::new("telegram", "1", "x"), at(2, 20)), Screened::Send(_)));
+        assert!(matches!(hours.screen(reply("outage on prod"), at(2, 20)), Screened::Send(_)));
+
+        let Screened::Held { note: Some(note) } = hours.screen(reply("first"), at(2, 20)) else { panic!("expected a note") };
+        assert_eq!(note.content, "Back at 9:00.");
+        assert!(matches!(hours.screen(reply("second"), at(2, 21)), Screened::Held { note: None }));
+
+        // Kept across restarts
+        let mut hours = line_hours(dir.path());
+        assert!(hours.release(at(3, 8)).is_empty());
+        let released: Vec<String> = hours.release(at(3, 9)).into_iter().map(|m| m.content).collect();
+        assert_eq!(released, ["first", "second"]);
+        assert!(hours.held().is_empty());
+
+        // The next off period sends the note again
+        assert!(matches!(hours.screen(reply("third"), at(3, 22)), Screened::Held { note: Some(_) }));
+    }
+}
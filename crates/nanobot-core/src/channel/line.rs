@@ -6,11 +6,14 @@ use tracing::{debug, error, info, warn};
 use crate::channel::{is_allowed, Channel};
 use crate::config::LineConfig;
 use crate::types::{InboundMessage, OutboundMessage};
-use crate::util::http::client;
+use crate::util::http::{client_for, ClientClass};
 
 const LINE_REPLY_API: &str = "https://api.line.me/v2/bot/message/reply";
 const LINE_PUSH_API: &str = "https://api.line.me/v2/bot/message/push";
 
+/// Longest text a LINE message can hold.
+pub const MAX_TEXT_CHARS: usize = 5000;
+
 /// LINE Messaging API channel.
 pub struct LineChannel {
     config: LineConfig,
@@ -63,7 +66,7 @@ impl LineChannel {
     /// Reply to a LINE message using the reply token.
     /// Must be called within 1 minute of receiving the webhook.
     pub async fn reply(access_token: &str, reply_token: &str, text: &str) -> anyhow::Result<()> {
-        let client = client();
+        let client = client_for(ClientClass::Webhook);
         let body = serde_json::json!({
             "replyToken": reply_token,
             "messages": [{
@@ -92,7 +95,7 @@ impl LineChannel {
 
     /// Push a message to a LINE user/group (no reply token needed).
     pub async fn push_message(access_token: &str, to: &str, text: &str) -> anyhow::Result<()> {
-        let client = client();
+        let client = client_for(ClientClass::Webhook);
         let body = serde_json::json!({
             "to": to,
             "messages": [{
@@ -176,6 +179,60 @@ impl LineChannel {
     }
 }
 
+/// Splits a streamed answer into LINE messages at paragraph boundaries
+/// (see [`LineProgressiveConfig`](crate::config::LineProgressiveConfig)).
+pub struct ProgressiveChunker {
+    chunk_chars: usize,
+    max_messages: usize,
+    buffer: String,
+    emitted: usize,
+}
+
+impl ProgressiveChunker {
+    pub fn new(chunk_chars: usize, max_messages: usize) -> Self {
+        Self {
+            chunk_chars: chunk_chars.clamp(1, MAX_TEXT_CHARS / 2),
+            max_messages: max_messages.max(1),
+            buffer: String::new(),
+            emitted: 0,
+        }
+    }
+
+    /// Add streamed text; returns the messages that are ready to send.
+    pub fn push(&mut self, delta: &str) -> Vec<String> {
+        self.buffer.push_str(delta);
+        let mut ready = Vec::new();
+        // The last message is left to `finish` so it can carry the rest
+        while self.emitted + 1 < self.max_messages {
+            let Some(end) = self.boundary() else { break };
+            let chunk: String = self.buffer.drain(..end).collect();
+            let chunk = chunk.trim();
+            if !chunk.is_empty() {
+                self.emitted += 1;
+                ready.push(chunk.to_string());
+            }
+        }
+        ready
+    }
+
+    /// Everything not sent yet, as the final message.
+    pub fn finish(self) -> Option<String> {
+        let rest = self.buffer.trim();
+        (!rest.is_empty()).then(|| crate::util::truncate_string(rest, MAX_TEXT_CHARS, "…"))
+    }
+
+    /// Byte offset ending the next message: the first paragraph break after
+    /// `chunk_chars`, or a line break when a paragraph outgrows a message.
+    fn boundary(&self) -> Option<usize> {
+        let start = self.buffer.char_indices().nth(self.chunk_chars)?.0;
+        if let Some(i) = self.buffer[start..].find("\n\n") {
+            return Some(start + i + 2);
+        }
+        let limit = self.buffer.char_indices().nth(MAX_TEXT_CHARS)?.0;
+        Some(self.buffer[..limit].rfind('\n').map_or(limit, |i| i + 1))
+    }
+}
+
 #[async_trait]
 impl Channel for LineChannel {
     fn name(&self) -> &str {
@@ -298,6 +355,25 @@ mod tests {
         assert_eq!(events[0].event_type, "follow");
     }
 
+    #[test]
+    fn test_progressive_chunker() {
+        let mut chunker = ProgressiveChunker::new(10, 3);
+        assert!(chunker.push("First para").is_empty());
+        assert!(chunker.push("graph here.\n").is_empty(), "no paragraph break yet");
+        assert_eq!(chunker.push("\nSecond"), ["First paragraph here."]);
+        assert_eq!(chunker.push(" paragraph.\n\nThird.\n\nFourth.\n\n"), ["Second paragraph."]);
+        // The last allowed message carries the rest
+        assert!(chunker.push("Fifth paragraph is long.\n\n").is_empty());
+        assert_eq!(chunker.finish().unwrap(), "Third.\n\nFourth.\n\nFifth paragraph is long.");
+
+        let mut chunker = ProgressiveChunker::new(10, 5);
+        let long = "x".repeat(MAX_TEXT_CHARS + 10);
+        let sent = chunker.push(&long);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].chars().count(), MAX_TEXT_CHARS);
+        assert_eq!(chunker.finish().unwrap().len(), 10);
+    }
+
     #[test]
     fn test_parse_webhook_empty() {
         let body = r#"{"events": []}"#;
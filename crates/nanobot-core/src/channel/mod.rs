@@ -11,13 +11,33 @@ pub mod google_chat;
 pub mod matrix;
 pub mod zalo;
 pub mod facebook;
+pub mod registry;
+pub mod health;
+pub mod webhooks;
+pub mod presence;
+pub mod hours;
+
+use std::sync::Arc;
 
 use async_trait::async_trait;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Mutex};
 
+use crate::delivery::DeliveryTracker;
+use self::hours::{ChannelHours, Screened};
+use self::presence::PresenceStore;
 use crate::types::OutboundMessage;
 
+/// Version of the [`Channel`] trait contract. Bumped only for breaking
+/// changes; the gateway skips channels reporting a different version.
+pub const CHANNEL_API_VERSION: u32 = 1;
+
 /// Trait for chat channel implementations.
+///
+/// This is the extension point for custom channels (see
+/// [`registry::ChannelRegistry`]). Stability: within one
+/// [`CHANNEL_API_VERSION`], existing methods keep their signatures and
+/// semantics, and new methods are only added with default implementations,
+/// so downstream implementations keep compiling across minor releases.
 #[async_trait]
 pub trait Channel: Send + Sync {
     /// Channel name (e.g., "telegram", "discord").
@@ -32,8 +52,19 @@ pub trait Channel: Send + Sync {
     /// Send a message through this channel.
     async fn send(&self, msg: &OutboundMessage) -> anyhow::Result<()>;
 
+    /// Send a message and return the platform message id, if the channel
+    /// reports one (used for delivery tracking and receipts).
+    async fn send_tracked(&self, msg: &OutboundMessage) -> anyhow::Result<Option<String>> {
+        self.send(msg).await.map(|_| None)
+    }
+
     /// Check if the channel is running.
     fn is_running(&self) -> bool;
+
+    /// Channel API version this implementation was written against.
+    fn api_version(&self) -> u32 {
+        CHANNEL_API_VERSION
+    }
 }
 
 /// Check if a sender is allowed based on the allow list.
@@ -59,16 +90,45 @@ pub fn is_allowed(sender_id: &str, allow_from: &[String]) -> bool {
 pub struct ChannelManager {
     channels: Vec<Box<dyn Channel>>,
     outbound_rx: Option<mpsc::Receiver<OutboundMessage>>,
+    deliveries: Option<Arc<Mutex<DeliveryTracker>>>,
+    presence: Option<Arc<Mutex<PresenceStore>>>,
+    hours: Option<ChannelHours>,
 }
 
+/// How often held replies are checked against their channels' hours.
+const HOURS_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
 impl ChannelManager {
     pub fn new(outbound_rx: mpsc::Receiver<OutboundMessage>) -> Self {
         Self {
             channels: Vec::new(),
             outbound_rx: Some(outbound_rx),
+            deliveries: None,
+            presence: None,
+            hours: None,
         }
     }
 
+    /// Record the outcome of every dispatched message in `tracker`.
+    pub fn with_delivery_tracker(mut self, tracker: Arc<Mutex<DeliveryTracker>>) -> Self {
+        self.deliveries = Some(tracker);
+        self
+    }
+
+    /// Send messages for linked users to the channel they are active on
+    /// (see [`presence`]).
+    pub fn with_presence(mut self, presence: Arc<Mutex<PresenceStore>>) -> Self {
+        self.presence = Some(presence);
+        self
+    }
+
+    /// Hold non-urgent replies while their channel is off hours (see
+    /// [`hours`]).
+    pub fn with_hours(mut self, hours: ChannelHours) -> Self {
+        self.hours = hours.is_enabled().then_some(hours);
+        self
+    }
+
     /// Add a channel.
     pub fn add_channel(&mut self, channel: Box<dyn Channel>) {
         self.channels.push(channel);
@@ -107,21 +167,65 @@ impl ChannelManager {
             Some(rx) => rx,
             None => return,
         };
-
-        while let Some(msg) = rx.recv().await {
-            let mut sent = false;
-            for channel in &self.channels {
-                if channel.name() == msg.channel {
-                    if let Err(e) = channel.send(&msg).await {
-                        tracing::error!("Error sending to {}: {}", msg.channel, e);
+        let mut hours_check = tokio::time::interval(HOURS_CHECK_INTERVAL);
+
+        loop {
+            let mut msg = tokio::select! {
+                msg = rx.recv() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+                _ = hours_check.tick() => {
+                    let released = match &mut self.hours {
+                        Some(hours) => hours.release(chrono::Utc::now()),
+                        None => Vec::new(),
+                    };
+                    for msg in released {
+                        self.deliver(msg).await;
                     }
-                    sent = true;
-                    break;
+                    continue;
                 }
+            };
+            if let Some(presence) = &self.presence {
+                let running = |name: &str| self.channels.iter().any(|c| c.name() == name);
+                presence.lock().await.route(&mut msg, running);
             }
-            if !sent {
-                tracing::warn!("Unknown channel: {}", msg.channel);
-            }
+            let screened = match &mut self.hours {
+                Some(hours) => hours.screen(msg, chrono::Utc::now()),
+                None => Screened::Send(msg),
+            };
+            let msg = match screened {
+                Screened::Send(msg) => msg,
+                Screened::Held { note } => {
+                    if let Some(note) = note {
+                        self.deliver(note).await;
+                    }
+                    continue;
+                }
+            };
+            self.deliver(msg).await;
+        }
+    }
+
+    /// Send `msg` on its channel, recording the outcome.
+    async fn deliver(&self, mut msg: OutboundMessage) {
+        let tracked = match &self.deliveries {
+            Some(tracker) => Some((tracker, tracker.lock().await.enqueue(&mut msg, None))),
+            None => None,
+        };
+        let result = match self.channels.iter().find(|c| c.name() == msg.channel) {
+            Some(channel) => channel.send_tracked(&msg).await,
+            None => Err(anyhow::anyhow!("Unknown channel: {}", msg.channel)),
+        };
+        if let Err(e) = &result {
+            tracing::error!("Error sending to {}: {}", msg.channel, e);
+        }
+        if let Some((tracker, id)) = tracked {
+            let mut tracker = tracker.lock().await;
+            match result {
+                Ok(message_id) => tracker.mark_sent(&id, message_id),
+                Err(e) => tracker.mark_failed(&id, &e.to_string()),
+            };
         }
     }
 
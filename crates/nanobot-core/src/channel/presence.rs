@@ -0,0 +1,297 @@
+//! Presence-aware routing between linked channels.
+//!
+//! A person reachable on several channels (CLI, LINE, web, Telegram, ...) is
+//! linked with `channels.links` in the config: a user name mapped to the
+//! `channel:chat_id` addresses that belong to them. Every inbound message
+//! updates that user's [`Presence`], the last activity per channel, and
+//! [`ChannelManager`](super::ChannelManager) sends replies and proactive
+//! messages for any of their addresses to the channel they used last.
+//! `/route <channel>` pins a channel instead; `/route auto` goes back to
+//! following activity. Presence is kept in a JSON file under the data
+//! directory.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::session::locale::Locale;
+use crate::types::OutboundMessage;
+use crate::util::{now_ms, read_json, write_json};
+
+/// Outbound metadata key that keeps a message on its addressed channel
+/// (set on `/route` replies so the confirmation shows up where it was typed).
+pub const NO_ROUTE_KEY: &str = "no_route";
+
+/// Last message seen from a user on one channel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Activity {
+    pub chat_id: String,
+    pub at_ms: u64,
+}
+
+/// Where a linked user is active, kept with their profile.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Presence {
+    /// Last activity per channel.
+    pub last_active: BTreeMap<String, Activity>,
+    /// Channel pinned with `/route`, overriding activity.
+    pub route: Option<String>,
+}
+
+impl Presence {
+    /// The `(channel, chat_id)` to reach the user on: the pinned channel if it
+    /// is usable, else the most recently active one. Only channels accepted by
+    /// `usable` are considered.
+    pub fn target(&self, usable: impl Fn(&str) -> bool) -> Option<(&str, &str)> {
+        let pinned = self
+            .route
+            .as_deref()
+            .and_then(|ch| self.last_active.get_key_value(ch))
+            .filter(|(ch, _)| usable(ch));
+        pinned
+            .or_else(|| {
+                self.last_active
+                    .iter()
+                    .filter(|(ch, _)| usable(ch))
+                    .max_by_key(|(_, a)| a.at_ms)
+            })
+            .map(|(ch, a)| (ch.as_str(), a.chat_id.as_str()))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PresenceFile {
+    users: BTreeMap<String, Presence>,
+}
+
+/// Linked users and their presence.
+pub struct PresenceStore {
+    store_path: PathBuf,
+    /// `channel:chat_id` → user name, from `channels.links`.
+    links: HashMap<String, String>,
+    store: Option<PresenceFile>,
+}
+
+impl PresenceStore {
+    pub fn new(store_path: PathBuf, links: &HashMap<String, Vec<String>>) -> Self {
+        let links = links
+            .iter()
+            .flat_map(|(user, addresses)| addresses.iter().map(move |a| (a.clone(), user.clone())))
+            .collect();
+        Self {
+            store_path,
+            links,
+            store: None,
+        }
+    }
+
+    pub fn default_path() -> PathBuf {
+        crate::config::get_data_dir().join("presence.json")
+    }
+
+    /// Whether any channels are linked at all.
+    pub fn is_enabled(&self) -> bool {
+        !self.links.is_empty()
+    }
+
+    /// The linked user owning `channel:chat_id`.
+    pub fn user_of(&self, channel: &str, chat_id: &str) -> Option<&str> {
+        self.links.get(&format!("{}:{}", channel, chat_id)).map(String::as_str)
+    }
+
+    /// Every address of the linked user owning `channel:chat_id`; empty
+    /// when it isn't linked.
+    pub fn linked_addresses(&self, channel: &str, chat_id: &str) -> Vec<String> {
+        let Some(user) = self.user_of(channel, chat_id) else {
+            return Vec::new();
+        };
+        self.links.iter().filter(|(_, u)| u.as_str() == user).map(|(address, _)| address.clone()).collect()
+    }
+
+    fn load_store(&mut self) -> &mut PresenceFile {
+        if self.store.is_none() {
+            self.store = Some(read_json(&self.store_path, "presence"));
+        }
+        self.store.as_mut().unwrap()
+    }
+
+    fn save_store(&mut self) {
+        if let Some(ref store) = self.store {
+            write_json(&self.store_path, store, "presence");
+        }
+    }
+
+    /// Presence of a linked user.
+    pub fn get(&mut self, user: &str) -> Option<Presence> {
+        self.load_store().users.get(user).cloned()
+    }
+
+    /// Record a message from `channel:chat_id`; ignored for unlinked chats.
+    pub fn touch(&mut self, channel: &str, chat_id: &str) {
+        self.touch_at(channel, chat_id, now_ms());
+    }
+
+    fn touch_at(&mut self, channel: &str, chat_id: &str, at_ms: u64) {
+        let Some(user) = self.user_of(channel, chat_id).map(str::to_string) else {
+            return;
+        };
+        let activity = Activity {
+            chat_id: chat_id.to_string(),
+            at_ms,
+        };
+        let presence = self.load_store().users.entry(user).or_default();
+        presence.last_active.insert(channel.to_string(), activity);
+        self.save_store();
+    }
+
+    /// Pin (`Some`) or unpin (`None`) the channel for the user owning
+    /// `channel:chat_id`. Returns the user, or `None` if the chat isn't linked.
+    pub fn set_route(&mut self, channel: &str, chat_id: &str, route: Option<&str>) -> Option<String> {
+        let user = self.user_of(channel, chat_id)?.to_string();
+        let presence = self.load_store().users.entry(user.clone()).or_default();
+        presence.route = route.map(str::to_string);
+        self.save_store();
+        Some(user)
+    }
+
+    /// Readdress `msg` to where its user is active. Returns whether it was
+    /// moved; messages for unlinked chats or marked [`NO_ROUTE_KEY`] stay put.
+    pub fn route(&mut self, msg: &mut OutboundMessage, usable: impl Fn(&str) -> bool) -> bool {
+        if msg.metadata.get(NO_ROUTE_KEY).and_then(|v| v.as_bool()) == Some(true) {
+            return false;
+        }
+        let Some(user) = self.user_of(&msg.channel, &msg.chat_id).map(str::to_string) else {
+            return false;
+        };
+        let Some(presence) = self.load_store().users.get(&user) else {
+            return false;
+        };
+        match presence.target(usable) {
+            Some((channel, chat_id)) if (channel, chat_id) != (msg.channel.as_str(), msg.chat_id.as_str()) => {
+                debug!("Routing message for {} from {} to {}", user, msg.channel, channel);
+                msg.channel = channel.to_string();
+                msg.chat_id = chat_id.to_string();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Handle `/route [channel|auto]` from `channel:chat_id`.
+pub fn handle_command(store: &mut PresenceStore, args: &str, channel: &str, chat_id: &str, locale: Locale) -> String {
+    let Some(user) = store.user_of(channel, chat_id).map(str::to_string) else {
+        return locale
+            .pick(
+                "このチャットは他のチャネルとリンクされていません（設定の channels.links）。",
+                "This chat isn't linked to other channels (channels.links in the config).",
+            )
+            .to_string();
+    };
+    let presence = store.get(&user).unwrap_or_default();
+    match args.trim() {
+        "" => {
+            let mut out = match (&presence.route, presence.target(|_| true)) {
+                (Some(route), _) if locale.is_english() => format!("Messages go to {} (pinned).\n", route),
+                (Some(route), _) => format!("送信先: {}（固定）\n", route),
+                (None, Some((ch, _))) if locale.is_english() => format!("Messages follow you; now {}.\n", ch),
+                (None, Some((ch, _))) => format!("送信先: 最後に使ったチャネル（現在 {}）\n", ch),
+                (None, None) => locale.pick("送信先: 最後に使ったチャネル\n", "Messages follow you.\n").to_string(),
+            };
+            for (ch, activity) in &presence.last_active {
+                let when = chrono::DateTime::<chrono::Utc>::from_timestamp_millis(activity.at_ms as i64)
+                    .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+                    .unwrap_or_default();
+                out.push_str(&format!("・{} {}\n", ch, when));
+            }
+            out.push_str(locale.pick(
+                "変更: /route <チャネル> ・ 自動に戻す: /route auto",
+                "Change: /route <channel> · back to automatic: /route auto",
+            ));
+            out
+        }
+        "auto" => {
+            store.set_route(channel, chat_id, None);
+            locale
+                .pick("最後に使ったチャネルに送信します。", "Messages will follow the channel you used last.")
+                .to_string()
+        }
+        route if presence.last_active.contains_key(route) => {
+            store.set_route(channel, chat_id, Some(route));
+            if locale.is_english() {
+                format!("Messages will go to {} until you send /route auto.", route)
+            } else {
+                format!("{} に送信します（/route auto で自動に戻ります）。", route)
+            }
+        }
+        route if locale.is_english() => format!("No activity on '{}' yet; send a message there first.", route),
+        route => format!("「{}」での利用履歴がありません。先にそのチャネルでメッセージを送ってください。", route),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linked_store(tmp: &tempfile::TempDir) -> PresenceStore {
+        let links = HashMap::from([(
+            "yuki".to_string(),
+            vec!["line:U1".to_string(), "telegram:42".to_string(), "cli:direct".to_string()],
+        )]);
+        PresenceStore::new(tmp.path().join("presence.json"), &links)
+    }
+
+    #[test]
+    fn test_routes_to_most_recent_channel() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut store = linked_store(&tmp);
+        store.touch_at("line", "U1", 1_000);
+        store.touch_at("telegram", "42", 2_000);
+        store.touch_at("discord", "7", 3_000);
+
+        let mut msg = OutboundMessage::new("line", "U1", "build finished");
+        assert!(store.route(&mut msg, |_| true));
+        assert_eq!((msg.channel.as_str(), msg.chat_id.as_str()), ("telegram", "42"));
+
+        // Channels that aren't running are skipped.
+        let mut msg = OutboundMessage::new("line", "U1", "build finished");
+        assert!(!store.route(&mut msg, |ch| ch == "line"));
+        assert_eq!(msg.channel, "line");
+
+        // Unlinked chats are left alone.
+        let mut msg = OutboundMessage::new("discord", "7", "hi");
+        assert!(!store.route(&mut msg, |_| true));
+
+        // Persisted across instances.
+        assert_eq!(linked_store(&tmp).get("yuki").unwrap().last_active.len(), 2);
+    }
+
+    #[test]
+    fn test_route_command_pins_and_unpins() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut store = linked_store(&tmp);
+        store.touch_at("line", "U1", 1_000);
+        store.touch_at("telegram", "42", 2_000);
+
+        let reply = handle_command(&mut store, "line", "telegram", "42", Locale::En);
+        assert!(reply.contains("go to line"), "{}", reply);
+        let mut msg = OutboundMessage::new("telegram", "42", "done");
+        assert!(store.route(&mut msg, |_| true));
+        assert_eq!(msg.channel, "line");
+
+        let mut reply = OutboundMessage::new("telegram", "42", "ok");
+        reply.metadata.insert(NO_ROUTE_KEY.to_string(), serde_json::json!(true));
+        assert!(!store.route(&mut reply, |_| true));
+
+        assert!(handle_command(&mut store, "", "line", "U1", Locale::En).contains("pinned"));
+        assert!(handle_command(&mut store, "slack", "line", "U1", Locale::En).contains("No activity"));
+        handle_command(&mut store, "auto", "line", "U1", Locale::En);
+        assert_eq!(store.get("yuki").unwrap().route, None);
+
+        assert!(handle_command(&mut store, "", "discord", "7", Locale::En).contains("isn't linked"));
+    }
+}
@@ -0,0 +1,278 @@
+//! Channel registry: how the gateway decides which channels to run.
+//!
+//! Every channel is created by a named factory. [`ChannelRegistry::with_builtins`]
+//! registers the bundled channels (each returns `None` unless enabled in
+//! `channels.*` config); downstream crates add their own with
+//! [`ChannelRegistry::register`] or [`ChannelRegistry::add`] and pass the
+//! registry to [`crate::service::gateway::run_gateway_with`]. Registering a
+//! name that already exists replaces the previous factory, so a bundled
+//! channel can be swapped for a custom implementation.
+//!
+//! Channels are linked in at compile time: Rust has no stable ABI, so
+//! loading `Box<dyn Channel>` from shared libraries is not supported.
+
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use super::discord::DiscordChannel;
+use super::feishu::FeishuChannel;
+use super::google_chat::GoogleChatChannel;
+use super::imessage::IMessageChannel;
+use super::line::LineChannel;
+use super::matrix::MatrixChannel;
+use super::signal::SignalChannel;
+use super::slack::SlackChannel;
+use super::teams::TeamsChannel;
+use super::telegram::TelegramChannel;
+use super::whatsapp::WhatsAppChannel;
+use super::zalo::ZaloChannel;
+use super::{Channel, CHANNEL_API_VERSION};
+use crate::config::Config;
+use crate::types::InboundMessage;
+
+/// What a factory gets to build its channel.
+pub struct ChannelContext<'a> {
+    pub config: &'a Config,
+    /// Where the channel publishes received messages.
+    pub inbound_tx: mpsc::Sender<InboundMessage>,
+}
+
+/// Builds a channel, or returns `None` when it is not configured.
+pub type ChannelFactory =
+    Box<dyn Fn(&ChannelContext<'_>) -> anyhow::Result<Option<Box<dyn Channel>>> + Send + Sync>;
+
+/// Lifecycle notifications from [`ChannelRegistry::build`], `start` and `stop`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelEvent {
+    Created { name: String },
+    Started { name: String },
+    StartFailed { name: String, error: String },
+    Stopped { name: String },
+}
+
+type ChannelHook = Box<dyn Fn(&ChannelEvent) + Send + Sync>;
+
+/// Named channel factories plus lifecycle hooks.
+#[derive(Default)]
+pub struct ChannelRegistry {
+    factories: Vec<(String, ChannelFactory)>,
+    hooks: Vec<ChannelHook>,
+}
+
+macro_rules! builtin {
+    ($registry:ident, $name:literal, $field:ident, $channel:ty) => {
+        $registry.register($name, |ctx| {
+            let cfg = &ctx.config.channels.$field;
+            Ok(cfg.enabled.then(|| {
+                Box::new(<$channel>::new(cfg.clone(), ctx.inbound_tx.clone())) as Box<dyn Channel>
+            }))
+        });
+    };
+}
+
+impl ChannelRegistry {
+    /// Empty registry (no bundled channels).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with every bundled channel.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        builtin!(registry, "telegram", telegram, TelegramChannel);
+        builtin!(registry, "discord", discord, DiscordChannel);
+        builtin!(registry, "whatsapp", whatsapp, WhatsAppChannel);
+        builtin!(registry, "feishu", feishu, FeishuChannel);
+        builtin!(registry, "line", line, LineChannel);
+        builtin!(registry, "slack", slack, SlackChannel);
+        builtin!(registry, "signal", signal, SignalChannel);
+        builtin!(registry, "imessage", imessage, IMessageChannel);
+        builtin!(registry, "teams", teams, TeamsChannel);
+        builtin!(registry, "google_chat", google_chat, GoogleChatChannel);
+        builtin!(registry, "matrix", matrix, MatrixChannel);
+        builtin!(registry, "zalo", zalo, ZaloChannel);
+        registry
+    }
+
+    /// Register (or replace) the factory for `name`.
+    pub fn register<F>(&mut self, name: impl Into<String>, factory: F) -> &mut Self
+    where
+        F: Fn(&ChannelContext<'_>) -> anyhow::Result<Option<Box<dyn Channel>>> + Send + Sync + 'static,
+    {
+        let name = name.into();
+        self.factories.retain(|(n, _)| *n != name);
+        self.factories.push((name, Box::new(factory)));
+        self
+    }
+
+    /// Register an already-built channel under its own name. It is handed
+    /// out once; later builds skip it.
+    pub fn add(&mut self, channel: Box<dyn Channel>) -> &mut Self {
+        let name = channel.name().to_string();
+        let slot = std::sync::Mutex::new(Some(channel));
+        self.register(name, move |_| Ok(slot.lock().unwrap().take()))
+    }
+
+    /// Remove a channel (e.g. a bundled one you don't want even if configured).
+    pub fn unregister(&mut self, name: &str) -> &mut Self {
+        self.factories.retain(|(n, _)| n != name);
+        self
+    }
+
+    /// Call `hook` on every lifecycle event.
+    pub fn on_event(&mut self, hook: impl Fn(&ChannelEvent) + Send + Sync + 'static) -> &mut Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.factories.iter().map(|(n, _)| n.as_str()).collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.iter().any(|(n, _)| n == name)
+    }
+
+    fn emit(&self, event: ChannelEvent) {
+        for hook in &self.hooks {
+            hook(&event);
+        }
+    }
+
+    /// Run every factory. A failing factory is logged and skipped so one
+    /// broken plugin can't take the gateway down; so is a channel built
+    /// against a different [`CHANNEL_API_VERSION`].
+    pub fn build(&self, ctx: &ChannelContext<'_>) -> Vec<Box<dyn Channel>> {
+        let mut channels = Vec::new();
+        for (name, factory) in &self.factories {
+            match factory(ctx) {
+                Ok(Some(channel)) if channel.api_version() != CHANNEL_API_VERSION => {
+                    warn!(
+                        "Skipping channel {}: built for channel API v{}, gateway is v{}",
+                        name, channel.api_version(), CHANNEL_API_VERSION
+                    );
+                }
+                Ok(Some(channel)) => {
+                    info!("{} channel enabled", name);
+                    self.emit(ChannelEvent::Created { name: name.clone() });
+                    channels.push(channel);
+                }
+                Ok(None) => {}
+                Err(e) => error!("Failed to create {} channel: {}", name, e),
+            }
+        }
+        channels
+    }
+
+    /// Start one channel, reporting the outcome to the hooks.
+    pub async fn start(&self, channel: &mut dyn Channel) -> anyhow::Result<()> {
+        let name = channel.name().to_string();
+        match channel.start().await {
+            Ok(()) => {
+                self.emit(ChannelEvent::Started { name });
+                Ok(())
+            }
+            Err(e) => {
+                self.emit(ChannelEvent::StartFailed { name, error: e.to_string() });
+                Err(e)
+            }
+        }
+    }
+
+    /// Stop one channel, reporting it to the hooks.
+    pub async fn stop(&self, channel: &mut dyn Channel) -> anyhow::Result<()> {
+        let result = channel.stop().await;
+        self.emit(ChannelEvent::Stopped { name: channel.name().to_string() });
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::OutboundMessage;
+    use async_trait::async_trait;
+    use std::sync::{Arc, Mutex};
+
+    struct TestChannel {
+        name: &'static str,
+        version: u32,
+        running: bool,
+    }
+
+    #[async_trait]
+    impl Channel for TestChannel {
+        fn name(&self) -> &str {
+            self.name
+        }
+        async fn start(&mut self) -> anyhow::Result<()> {
+            self.running = true;
+            Ok(())
+        }
+        async fn stop(&mut self) -> anyhow::Result<()> {
+            self.running = false;
+            Ok(())
+        }
+        async fn send(&self, _msg: &OutboundMessage) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn is_running(&self) -> bool {
+            self.running
+        }
+        fn api_version(&self) -> u32 {
+            self.version
+        }
+    }
+
+    fn channel(name: &'static str) -> Box<dyn Channel> {
+        Box::new(TestChannel { name, version: CHANNEL_API_VERSION, running: false })
+    }
+
+    #[test]
+    fn test_builtins_follow_config() {
+        let registry = ChannelRegistry::with_builtins();
+        assert!(registry.contains("telegram") && registry.contains("zalo"));
+
+        let mut config = Config::default();
+        let (tx, _rx) = mpsc::channel(1);
+        assert!(registry.build(&ChannelContext { config: &config, inbound_tx: tx.clone() }).is_empty());
+
+        config.channels.zalo.enabled = true;
+        let built = registry.build(&ChannelContext { config: &config, inbound_tx: tx });
+        assert_eq!(built.iter().map(|c| c.name()).collect::<Vec<_>>(), vec!["zalo"]);
+    }
+
+    #[tokio::test]
+    async fn test_custom_channels_and_hooks() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+
+        let mut registry = ChannelRegistry::with_builtins();
+        registry
+            .add(channel("acme"))
+            .register("telegram", |_| Ok(Some(channel("telegram"))))
+            .register("stale", |_| Ok(Some(Box::new(TestChannel { name: "stale", version: 0, running: false }) as Box<dyn Channel>)))
+            .register("broken", |_| anyhow::bail!("missing credentials"))
+            .unregister("zalo")
+            .on_event(move |e| seen.lock().unwrap().push(e.clone()));
+        assert!(!registry.contains("zalo"));
+
+        let config = Config::default();
+        let (tx, _rx) = mpsc::channel(1);
+        let ctx = ChannelContext { config: &config, inbound_tx: tx };
+        let mut built = registry.build(&ctx);
+        let mut names: Vec<_> = built.iter().map(|c| c.name().to_string()).collect();
+        names.sort();
+        assert_eq!(names, vec!["acme", "telegram"]);
+        // A pre-built channel is only handed out once
+        assert_eq!(registry.build(&ctx).len(), 1);
+
+        for ch in built.iter_mut() {
+            registry.start(ch.as_mut()).await.unwrap();
+            assert!(ch.is_running());
+        }
+        let events = events.lock().unwrap();
+        assert!(events.contains(&ChannelEvent::Started { name: "acme".into() }));
+        assert_eq!(events.iter().filter(|e| matches!(e, ChannelEvent::Created { .. })).count(), 3);
+    }
+}
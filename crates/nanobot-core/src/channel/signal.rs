@@ -6,6 +6,7 @@ use tracing::{debug, error, info, warn};
 
 use crate::config::SignalConfig;
 use crate::types::{InboundMessage, OutboundMessage};
+use crate::util::http::{self, ClientClass};
 
 use super::{is_allowed, Channel};
 
@@ -23,7 +24,7 @@ impl SignalChannel {
             config,
             inbound_tx,
             running: false,
-            client: reqwest::Client::new(),
+            client: http::client_for(ClientClass::Webhook),
         }
     }
 
@@ -6,7 +6,9 @@ use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 
 use crate::config::SlackConfig;
+use crate::feedback::{reaction_action, reaction_message};
 use crate::types::{InboundMessage, OutboundMessage};
+use crate::util::http::{self, ClientClass};
 
 use super::{is_allowed, Channel};
 
@@ -26,7 +28,7 @@ impl SlackChannel {
             config,
             inbound_tx,
             running: false,
-            client: reqwest::Client::new(),
+            client: http::client_for(ClientClass::Webhook),
         }
     }
 
@@ -113,6 +115,10 @@ impl SlackChannel {
         };
 
         let event_type = event.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        if event_type == "reaction_added" {
+            self.handle_reaction(payload, event).await;
+            return;
+        }
         if event_type != "message" {
             return;
         }
@@ -152,8 +158,42 @@ impl SlackChannel {
         }
     }
 
+    /// Forward a `reaction_added` event on one of the bot's messages as feedback.
+    async fn handle_reaction(&self, payload: &serde_json::Value, event: &serde_json::Value) {
+        let Ok(event) = serde_json::from_value::<SlackEvent>(event.clone()) else {
+            return;
+        };
+        let (Some(user), Some(reaction), Some(item)) = (event.user, event.reaction, event.item) else {
+            return;
+        };
+        if item.item_type != "message" || reaction_action(&reaction).is_none() {
+            return;
+        }
+        // The bot's own user id is listed in the envelope's authorizations
+        let bot_user = payload
+            .pointer("/authorizations/0/user_id")
+            .and_then(|v| v.as_str());
+        if let (Some(bot), Some(author)) = (bot_user, event.item_user.as_deref()) {
+            if bot != author {
+                return;
+            }
+        }
+        let (Some(channel), Some(ts)) = (item.channel, item.ts) else {
+            return;
+        };
+        if !is_allowed(&user, &self.config.allow_from) {
+            return;
+        }
+
+        let msg = reaction_message("slack", &user, &channel, &reaction, &ts);
+        if let Err(e) = self.inbound_tx.send(msg).await {
+            error!("Failed to send Slack reaction to bus: {}", e);
+        }
+    }
+
     /// Send a message via Slack Web API chat.postMessage.
-    async fn post_message(&self, channel: &str, text: &str) -> anyhow::Result<()> {
+    /// Post a message and return its `ts` (Slack's message id).
+    async fn post_message(&self, channel: &str, text: &str) -> anyhow::Result<Option<String>> {
         let url = format!("{SLACK_API_BASE}/chat.postMessage");
 
         for _attempt in 0..3 {
@@ -188,10 +228,10 @@ impl SlackChannel {
                     .unwrap_or("unknown");
                 return Err(anyhow::anyhow!("Slack chat.postMessage error: {err}"));
             }
-            return Ok(());
+            return Ok(body.get("ts").and_then(|v| v.as_str()).map(str::to_string));
         }
 
-        Ok(())
+        Err(anyhow::anyhow!("Slack rate limit retries exhausted"))
     }
 
     /// Parse a Slack Events API webhook payload.
@@ -239,6 +279,10 @@ impl Channel for SlackChannel {
     }
 
     async fn send(&self, msg: &OutboundMessage) -> anyhow::Result<()> {
+        self.post_message(&msg.chat_id, &msg.content).await.map(|_| ())
+    }
+
+    async fn send_tracked(&self, msg: &OutboundMessage) -> anyhow::Result<Option<String>> {
         self.post_message(&msg.chat_id, &msg.content).await
     }
 
@@ -267,6 +311,18 @@ pub struct SlackEvent {
     pub ts: Option<String>,
     pub subtype: Option<String>,
     pub bot_id: Option<String>,
+    /// `reaction_added`: emoji name (e.g. "+1"), reacted item and its author.
+    pub reaction: Option<String>,
+    pub item: Option<SlackReactionItem>,
+    pub item_user: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SlackReactionItem {
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub channel: Option<String>,
+    pub ts: Option<String>,
 }
 
 #[cfg(test)]
@@ -334,4 +390,30 @@ mod tests {
         let event = payload.event.unwrap();
         assert!(event.bot_id.is_some());
     }
+
+    #[test]
+    fn test_parse_event_reaction_added() {
+        let body = r#"{
+            "type": "event_callback",
+            "event": {
+                "type": "reaction_added",
+                "user": "U12345",
+                "reaction": "thumbsup",
+                "item_user": "UBOT",
+                "item": {
+                    "type": "message",
+                    "channel": "C12345",
+                    "ts": "1625000002.000300"
+                }
+            }
+        }"#;
+
+        let payload = SlackChannel::parse_event(body).unwrap();
+        let event = payload.event.unwrap();
+        assert_eq!(event.reaction.as_deref(), Some("thumbsup"));
+        assert_eq!(event.item_user.as_deref(), Some("UBOT"));
+        let item = event.item.unwrap();
+        assert_eq!(item.item_type, "message");
+        assert_eq!(item.ts.as_deref(), Some("1625000002.000300"));
+    }
 }
@@ -6,6 +6,7 @@ use tracing::{debug, error, info, warn};
 
 use crate::config::TeamsConfig;
 use crate::types::{InboundMessage, OutboundMessage};
+use crate::util::http::{self, ClientClass};
 
 use super::{is_allowed, Channel};
 
@@ -27,7 +28,7 @@ impl TeamsChannel {
             config,
             inbound_tx,
             running: false,
-            client: reqwest::Client::new(),
+            client: http::client_for(ClientClass::Webhook),
             access_token: None,
         }
     }
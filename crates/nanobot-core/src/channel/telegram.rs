@@ -5,10 +5,19 @@ use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 
 use crate::config::TelegramConfig;
-use crate::types::{InboundMessage, OutboundMessage};
+use crate::feedback::{reaction_action, reaction_message};
+use crate::types::{Attachment, Button, ButtonAction, InboundMessage, MediaKind, OutboundMessage};
+use crate::util::http::{self, ClientClass};
 
 use super::{is_allowed, Channel};
 
+/// Updates the bot asks Telegram for, polling or by webhook.
+const ALLOWED_UPDATES: [&str; 3] = ["message", "message_reaction", "callback_query"];
+/// Longest photo, voice or file caption Telegram accepts, in characters.
+const CAPTION_LIMIT: usize = 1024;
+/// Longest `callback_data` of an inline keyboard button, in bytes.
+const CALLBACK_DATA_LIMIT: usize = 64;
+
 // ====== Webhook Types ======
 
 /// Telegram Update object (subset for webhook use).
@@ -16,6 +25,8 @@ use super::{is_allowed, Channel};
 pub struct TelegramUpdate {
     pub update_id: i64,
     pub message: Option<TelegramMessage>,
+    pub message_reaction: Option<TelegramMessageReaction>,
+    pub callback_query: Option<TelegramCallbackQuery>,
 }
 
 /// Telegram Message object.
@@ -29,6 +40,58 @@ pub struct TelegramMessage {
     pub date: i64,
 }
 
+/// Telegram MessageReactionUpdated object (a user changed their reaction).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TelegramMessageReaction {
+    pub chat: TelegramChat,
+    pub message_id: i64,
+    pub user: Option<TelegramUser>,
+    pub date: i64,
+    #[serde(default)]
+    pub new_reaction: Vec<TelegramReactionType>,
+}
+
+impl TelegramMessageReaction {
+    /// The first emoji reaction that was added, if any.
+    pub fn emoji(&self) -> Option<&str> {
+        self.new_reaction.iter().find_map(|r| r.emoji.as_deref())
+    }
+}
+
+/// Telegram ReactionType object (only `emoji` reactions carry an emoji).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TelegramReactionType {
+    #[serde(rename = "type")]
+    pub reaction_type: String,
+    pub emoji: Option<String>,
+}
+
+/// Telegram CallbackQuery object (a user pressed an inline keyboard button).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TelegramCallbackQuery {
+    pub id: String,
+    pub from: TelegramUser,
+    /// The message the button was under.
+    pub message: Option<TelegramMessage>,
+    pub data: Option<String>,
+}
+
+impl TelegramCallbackQuery {
+    /// The button press as a message from the user with the button's text,
+    /// in the chat the button was shown in.
+    pub fn as_message(&self) -> Option<TelegramMessage> {
+        let shown_in = self.message.as_ref()?;
+        Some(TelegramMessage {
+            message_id: shown_in.message_id,
+            from: Some(self.from.clone()),
+            chat: shown_in.chat.clone(),
+            text: Some(self.data.clone()?),
+            caption: None,
+            date: shown_in.date,
+        })
+    }
+}
+
 /// Telegram Chat object.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TelegramChat {
@@ -58,11 +121,12 @@ pub struct TelegramChannel {
 
 impl TelegramChannel {
     pub fn new(config: TelegramConfig, inbound_tx: mpsc::Sender<InboundMessage>) -> Self {
+        let client = http::client_via(ClientClass::Webhook, config.proxy.as_deref());
         Self {
             config,
             inbound_tx,
             running: false,
-            client: reqwest::Client::new(),
+            client,
         }
     }
 
@@ -85,7 +149,7 @@ impl TelegramChannel {
             .json(&json!({
                 "offset": offset,
                 "timeout": 30,
-                "allowed_updates": ["message"],
+                "allowed_updates": ALLOWED_UPDATES,
             }))
             .send()
             .await?
@@ -144,19 +208,20 @@ impl TelegramChannel {
         Ok(())
     }
 
-    /// Register a webhook URL with the Telegram Bot API.
-    pub async fn set_webhook(token: &str, webhook_url: &str) -> anyhow::Result<()> {
-        let client = reqwest::Client::new();
+    /// Register a webhook URL with the Telegram Bot API. With `secret`,
+    /// Telegram sends it in `X-Telegram-Bot-Api-Secret-Token` on every update.
+    pub async fn set_webhook(token: &str, webhook_url: &str, secret: Option<&str>) -> anyhow::Result<()> {
+        let client = http::client_for(ClientClass::Webhook);
         let url = Self::api_url_with_token(token, "setWebhook");
 
-        let resp = client
-            .post(&url)
-            .json(&json!({
-                "url": webhook_url,
-                "allowed_updates": ["message"],
-            }))
-            .send()
-            .await?;
+        let mut params = json!({
+            "url": webhook_url,
+            "allowed_updates": ALLOWED_UPDATES,
+        });
+        if let Some(secret) = secret {
+            params["secret_token"] = json!(secret);
+        }
+        let resp = client.post(&url).json(&params).send().await?;
 
         let status = resp.status();
         let body: serde_json::Value = resp.json().await?;
@@ -174,7 +239,7 @@ impl TelegramChannel {
 
     /// Remove the webhook URL from the Telegram Bot API.
     pub async fn delete_webhook(token: &str) -> anyhow::Result<()> {
-        let client = reqwest::Client::new();
+        let client = http::client_for(ClientClass::Webhook);
         let url = Self::api_url_with_token(token, "deleteWebhook");
 
         let resp = client.post(&url).send().await?;
@@ -192,10 +257,173 @@ impl TelegramChannel {
         Ok(())
     }
 
+    /// Stop the spinner on a pressed inline keyboard button.
+    pub async fn answer_callback_query(client: &reqwest::Client, token: &str, query_id: &str) -> anyhow::Result<()> {
+        client
+            .post(Self::api_url_with_token(token, "answerCallbackQuery"))
+            .json(&json!({ "callback_query_id": query_id }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Send an outbound message with its attachments as native photos,
+    /// voice notes and files and its buttons as an inline keyboard. Media
+    /// links in the text (such as `image_generate` results) are sent as
+    /// media too. Returns the id of the last message sent.
+    pub async fn deliver(client: &reqwest::Client, token: &str, msg: &OutboundMessage) -> anyhow::Result<Option<String>> {
+        let mut msg = msg.clone();
+        msg.lift_media();
+        let mut attachments = msg.attachments.clone();
+        attachments.extend(msg.media.iter().map(Attachment::guess));
+        let keyboard = (!msg.buttons.is_empty()).then(|| inline_keyboard(&msg.buttons));
+
+        let mut text = msg.content.clone();
+        let mut reply_to = msg.reply_to.clone();
+        let mut last_id = None;
+        let last = attachments.len().saturating_sub(1);
+        for (i, attachment) in attachments.iter().enumerate() {
+            // The last attachment carries the text when it fits as a caption
+            let captioned = i == last && text.chars().count() <= CAPTION_LIMIT;
+            let caption = if captioned { text.as_str() } else { "" };
+            let markup = if captioned { keyboard.as_ref() } else { None };
+            match Self::send_media(client, token, &msg.chat_id, attachment, caption, markup, reply_to.as_deref()).await {
+                Ok(id) => {
+                    last_id = id;
+                    reply_to = None;
+                    if captioned {
+                        return Ok(last_id);
+                    }
+                }
+                Err(e) => {
+                    // Telegram couldn't fetch or take it; the link still helps
+                    warn!("Sending {:?} to Telegram failed, sending the link instead: {}", attachment.kind, e);
+                    text = format!("{}\n{}", text, attachment.source).trim().to_string();
+                }
+            }
+        }
+        if text.is_empty() && keyboard.is_none() {
+            return Ok(last_id);
+        }
+        Self::send_text(client, token, &msg.chat_id, &text, reply_to.as_deref(), keyboard.as_ref()).await
+    }
+
+    /// `sendMessage` as HTML, falling back to plain text when Telegram
+    /// can't parse it.
+    async fn send_text(
+        client: &reqwest::Client,
+        token: &str,
+        chat_id: &str,
+        text: &str,
+        reply_to: Option<&str>,
+        keyboard: Option<&serde_json::Value>,
+    ) -> anyhow::Result<Option<String>> {
+        let url = Self::api_url_with_token(token, "sendMessage");
+        let mut payload = json!({
+            "chat_id": chat_id,
+            "text": text,
+        });
+        if let Some(message_id) = reply_to.and_then(|id| id.parse::<i64>().ok()) {
+            payload["reply_parameters"] = json!({ "message_id": message_id });
+        }
+        if let Some(keyboard) = keyboard {
+            payload["reply_markup"] = keyboard.clone();
+        }
+        let mut html = payload.clone();
+        html["parse_mode"] = json!("HTML");
+
+        let response = client.post(&url).json(&html).send().await?;
+        let response = if response.status().is_success() {
+            response
+        } else {
+            warn!("HTML parse failed, falling back to plain text");
+            client.post(&url).json(&payload).send().await?.error_for_status()?
+        };
+
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        Ok(body
+            .pointer("/result/message_id")
+            .and_then(|v| v.as_i64())
+            .map(|id| id.to_string()))
+    }
+
+    /// Send one attachment: by URL, or uploaded when it is a local file.
+    async fn send_media(
+        client: &reqwest::Client,
+        token: &str,
+        chat_id: &str,
+        attachment: &Attachment,
+        caption: &str,
+        keyboard: Option<&serde_json::Value>,
+        reply_to: Option<&str>,
+    ) -> anyhow::Result<Option<String>> {
+        let (method, field) = media_method(attachment.kind);
+        let url = Self::api_url_with_token(token, method);
+        let reply_parameters = reply_to
+            .and_then(|id| id.parse::<i64>().ok())
+            .map(|message_id| json!({ "message_id": message_id }));
+
+        let request = if attachment.source.starts_with("http://") || attachment.source.starts_with("https://") {
+            let mut payload = json!({ "chat_id": chat_id, field: attachment.source });
+            if !caption.is_empty() {
+                payload["caption"] = json!(caption);
+            }
+            if let Some(keyboard) = keyboard {
+                payload["reply_markup"] = keyboard.clone();
+            }
+            if let Some(reply_parameters) = reply_parameters {
+                payload["reply_parameters"] = reply_parameters;
+            }
+            client.post(&url).json(&payload)
+        } else {
+            let path = std::path::Path::new(&attachment.source);
+            let bytes = tokio::fs::read(path).await?;
+            let file_name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| field.to_string());
+            let mut form = reqwest::multipart::Form::new()
+                .text("chat_id", chat_id.to_string())
+                .part(field, reqwest::multipart::Part::bytes(bytes).file_name(file_name));
+            if !caption.is_empty() {
+                form = form.text("caption", caption.to_string());
+            }
+            if let Some(keyboard) = keyboard {
+                form = form.text("reply_markup", keyboard.to_string());
+            }
+            if let Some(reply_parameters) = reply_parameters {
+                form = form.text("reply_parameters", reply_parameters.to_string());
+            }
+            client.post(&url).multipart(form)
+        };
+
+        let body: serde_json::Value = request.send().await?.json().await?;
+        if body.get("ok").and_then(|v| v.as_bool()) != Some(true) {
+            anyhow::bail!("{}", body.get("description").and_then(|v| v.as_str()).unwrap_or("unknown error"));
+        }
+        Ok(body
+            .pointer("/result/message_id")
+            .and_then(|v| v.as_i64())
+            .map(|id| id.to_string()))
+    }
+
     async fn handle_update(&self, update: &serde_json::Value) -> anyhow::Result<()> {
-        let message = match update.get("message") {
-            Some(m) => m,
-            None => return Ok(()),
+        if let Some(reaction) = update.get("message_reaction") {
+            let reaction: TelegramMessageReaction = serde_json::from_value(reaction.clone())?;
+            return self.handle_reaction(&reaction).await;
+        }
+
+        let pressed;
+        let message = match (update.get("message"), update.get("callback_query")) {
+            (Some(m), _) => m,
+            (None, Some(query)) => {
+                let query: TelegramCallbackQuery = serde_json::from_value(query.clone())?;
+                Self::answer_callback_query(&self.client, &self.config.token, &query.id).await?;
+                pressed = match query.as_message() {
+                    Some(message) => serde_json::to_value(message)?,
+                    None => return Ok(()),
+                };
+                &pressed
+            }
+            (None, None) => return Ok(()),
         };
 
         let from = match message.get("from") {
@@ -235,6 +463,38 @@ impl TelegramChannel {
 
         Ok(())
     }
+
+    /// Forward a feedback reaction (👍/👎/🔁) as an inbound reaction message.
+    async fn handle_reaction(&self, reaction: &TelegramMessageReaction) -> anyhow::Result<()> {
+        let (Some(user), Some(emoji)) = (reaction.user.as_ref(), reaction.emoji()) else {
+            return Ok(());
+        };
+        if reaction_action(emoji).is_none() {
+            return Ok(());
+        }
+        let sender_id = Self::sender_id(user);
+        if !is_allowed(&sender_id, &self.config.allow_from) {
+            return Ok(());
+        }
+
+        let msg = reaction_message(
+            "telegram",
+            &sender_id,
+            &reaction.chat.id.to_string(),
+            emoji,
+            &reaction.message_id.to_string(),
+        );
+        self.inbound_tx.send(msg).await?;
+        Ok(())
+    }
+
+    /// Sender id used for allow-lists and session keys: `id` or `id|username`.
+    pub fn sender_id(user: &TelegramUser) -> String {
+        match &user.username {
+            Some(username) => format!("{}|{}", user.id, username),
+            None => user.id.to_string(),
+        }
+    }
 }
 
 #[async_trait]
@@ -281,53 +541,11 @@ impl Channel for TelegramChannel {
     }
 
     async fn send(&self, msg: &OutboundMessage) -> anyhow::Result<()> {
-        let url = Self::api_url_with_token(&self.config.token, "sendMessage");
-
-        let mut payload = json!({
-            "chat_id": msg.chat_id,
-            "text": msg.content,
-            "parse_mode": "HTML",
-        });
-
-        // Add reply_to_message_id if specified
-        if let Some(reply_to) = &msg.reply_to {
-            if let Ok(message_id) = reply_to.parse::<i64>() {
-                payload["reply_parameters"] = json!({
-                    "message_id": message_id
-                });
-            }
-        }
-
-        let response = self.client
-            .post(&url)
-            .json(&payload)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            // Fallback to plain text without HTML parsing
-            warn!("HTML parse failed, falling back to plain text");
-            let mut fallback_payload = json!({
-                "chat_id": msg.chat_id,
-                "text": msg.content,
-            });
-
-            if let Some(reply_to) = &msg.reply_to {
-                if let Ok(message_id) = reply_to.parse::<i64>() {
-                    fallback_payload["reply_parameters"] = json!({
-                        "message_id": message_id
-                    });
-                }
-            }
-
-            self.client
-                .post(&url)
-                .json(&fallback_payload)
-                .send()
-                .await?;
-        }
+        self.send_tracked(msg).await.map(|_| ())
+    }
 
-        Ok(())
+    async fn send_tracked(&self, msg: &OutboundMessage) -> anyhow::Result<Option<String>> {
+        Self::deliver(&self.client, &self.config.token, msg).await
     }
 
     fn is_running(&self) -> bool {
@@ -335,6 +553,40 @@ impl Channel for TelegramChannel {
     }
 }
 
+/// Bot API method and file field for a kind of media.
+fn media_method(kind: MediaKind) -> (&'static str, &'static str) {
+    match kind {
+        MediaKind::Photo => ("sendPhoto", "photo"),
+        MediaKind::Voice => ("sendVoice", "voice"),
+        MediaKind::Audio => ("sendAudio", "audio"),
+        MediaKind::Video => ("sendVideo", "video"),
+        MediaKind::Document => ("sendDocument", "document"),
+    }
+}
+
+/// `reply_markup` showing `rows` as an inline keyboard. Reply texts are
+/// cut to fit Telegram's 64-byte `callback_data`.
+fn inline_keyboard(rows: &[Vec<Button>]) -> serde_json::Value {
+    let rows: Vec<Vec<serde_json::Value>> = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|button| match &button.action {
+                    ButtonAction::Reply(text) => {
+                        let mut end = text.len().min(CALLBACK_DATA_LIMIT);
+                        while !text.is_char_boundary(end) {
+                            end -= 1;
+                        }
+                        json!({ "text": button.label, "callback_data": &text[..end] })
+                    }
+                    ButtonAction::Url(url) => json!({ "text": button.label, "url": url }),
+                })
+                .collect()
+        })
+        .collect();
+    json!({ "inline_keyboard": rows })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -437,4 +689,73 @@ mod tests {
         let url = TelegramChannel::api_url_with_token("TOKEN123", "sendMessage");
         assert_eq!(url, "https://api.telegram.org/botTOKEN123/sendMessage");
     }
+
+    #[test]
+    fn test_parse_webhook_update_callback_query() {
+        let body = r#"{
+            "update_id": 123461,
+            "callback_query": {
+                "id": "4382bfdwdsb323b2d9",
+                "from": {"id": 99999, "is_bot": false, "first_name": "Test"},
+                "message": {
+                    "message_id": 43,
+                    "chat": {"id": 99999, "type": "private"},
+                    "text": "Pick one",
+                    "date": 1700000004
+                },
+                "chat_instance": "-1234",
+                "data": "yes"
+            }
+        }"#;
+
+        let update = TelegramChannel::parse_webhook_update(body).unwrap();
+        let query = update.callback_query.unwrap();
+        let msg = query.as_message().unwrap();
+        assert_eq!(msg.text.as_deref(), Some("yes"));
+        assert_eq!(msg.chat.id, 99999);
+        assert_eq!(msg.from.unwrap().id, 99999);
+    }
+
+    #[test]
+    fn test_inline_keyboard() {
+        let long = "あ".repeat(30);
+        let keyboard = inline_keyboard(&[
+            vec![Button::reply("Yes", "yes"), Button::reply("Long", long.as_str())],
+            vec![Button::link("Open", "https://chatweb.ai")],
+        ]);
+        assert_eq!(keyboard["inline_keyboard"][0][0], json!({"text": "Yes", "callback_data": "yes"}));
+        assert_eq!(keyboard["inline_keyboard"][0][1]["callback_data"].as_str().unwrap().len(), 63);
+        assert_eq!(keyboard["inline_keyboard"][1][0]["url"], "https://chatweb.ai");
+        assert_eq!(media_method(MediaKind::Voice), ("sendVoice", "voice"));
+    }
+
+    #[test]
+    fn test_parse_webhook_update_message_reaction() {
+        let body = r#"{
+            "update_id": 123460,
+            "message_reaction": {
+                "chat": {
+                    "id": 99999,
+                    "type": "private"
+                },
+                "message_id": 42,
+                "user": {
+                    "id": 99999,
+                    "is_bot": false,
+                    "first_name": "Test",
+                    "username": "testuser"
+                },
+                "date": 1700000003,
+                "old_reaction": [],
+                "new_reaction": [{"type": "emoji", "emoji": "👍"}]
+            }
+        }"#;
+
+        let update = TelegramChannel::parse_webhook_update(body).unwrap();
+        assert!(update.message.is_none());
+        let reaction = update.message_reaction.unwrap();
+        assert_eq!(reaction.message_id, 42);
+        assert_eq!(reaction.emoji(), Some("👍"));
+        assert_eq!(TelegramChannel::sender_id(reaction.user.as_ref().unwrap()), "99999|testuser");
+    }
 }
@@ -0,0 +1,147 @@
+//! Webhook registration for the HTTP API. With a public base URL
+//! (`gateway.publicUrl`), the gateway points Telegram and LINE at its own
+//! `/webhooks/...` routes on startup and checks that Messenger's verification
+//! handshake works, instead of each being set by hand in a console.
+
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::config::ChannelsConfig;
+use crate::util::http::{self, ClientClass};
+
+use super::telegram::TelegramChannel;
+
+const LINE_API_BASE: &str = "https://api.line.me/v2/bot";
+
+/// What happened to one channel's webhook.
+#[derive(Debug, Clone)]
+pub struct Registration {
+    pub channel: &'static str,
+    pub url: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Registration {
+    fn new(channel: &'static str, url: String, result: anyhow::Result<String>) -> Self {
+        match result {
+            Ok(detail) => Self { channel, url, ok: true, detail },
+            Err(e) => Self { channel, url, ok: false, detail: e.to_string() },
+        }
+    }
+
+    pub fn log(&self) {
+        if self.ok {
+            info!("{} webhook {}: {}", self.channel, self.url, self.detail);
+        } else {
+            warn!("{} webhook {}: {}", self.channel, self.url, self.detail);
+        }
+    }
+}
+
+/// The route `channel` receives webhooks on under `base`.
+pub fn webhook_url(base: &str, channel: &str) -> String {
+    format!("{}/webhooks/{}", base.trim_end_matches('/'), channel)
+}
+
+/// Register (or refresh) the webhooks of the configured channels. The HTTP
+/// API must already be listening: LINE and Messenger call back to check it.
+pub async fn register_all(channels: &ChannelsConfig, base: &str) -> Vec<Registration> {
+    let mut registrations = Vec::new();
+
+    let telegram = &channels.telegram;
+    if telegram.enabled && !telegram.token.is_empty() {
+        let url = webhook_url(base, "telegram");
+        let secret = std::env::var("TELEGRAM_WEBHOOK_SECRET").ok().filter(|s| !s.is_empty());
+        let result = TelegramChannel::set_webhook(&telegram.token, &url, secret.as_deref())
+            .await
+            .map(|()| "registered".to_string());
+        registrations.push(Registration::new("telegram", url, result));
+    }
+
+    let line = &channels.line;
+    if line.enabled && !line.channel_access_token.is_empty() {
+        let url = webhook_url(base, "line");
+        let result = register_line(&line.channel_access_token, &url).await;
+        registrations.push(Registration::new("line", url, result));
+    }
+
+    // Messenger webhooks can only be subscribed in the Meta app dashboard
+    if let Some(verify_token) = std::env::var("FACEBOOK_VERIFY_TOKEN").ok().filter(|t| !t.is_empty()) {
+        let url = webhook_url(base, "facebook");
+        let result = verify_facebook(&url, &verify_token).await;
+        registrations.push(Registration::new("facebook", url, result));
+    }
+
+    registrations
+}
+
+/// Remove the Telegram webhook. LINE and Messenger have nothing to fall back
+/// to, so their endpoints are left as they are.
+pub async fn unregister_all(channels: &ChannelsConfig) {
+    let telegram = &channels.telegram;
+    if telegram.enabled && !telegram.token.is_empty() {
+        if let Err(e) = TelegramChannel::delete_webhook(&telegram.token).await {
+            warn!("Could not remove the Telegram webhook: {}", e);
+        }
+    }
+}
+
+/// Set the LINE endpoint to `url`, then have LINE send it a test event.
+async fn register_line(token: &str, url: &str) -> anyhow::Result<String> {
+    let client = http::client_for(ClientClass::Webhook);
+    let resp = client
+        .put(format!("{}/channel/webhook/endpoint", LINE_API_BASE))
+        .bearer_auth(token)
+        .json(&json!({ "endpoint": url }))
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        let status = resp.status().as_u16();
+        let body: Value = resp.json().await.unwrap_or_default();
+        anyhow::bail!("LINE rejected the endpoint (HTTP {}): {}", status, body["message"].as_str().unwrap_or("unknown error"));
+    }
+
+    let test: Value = client
+        .post(format!("{}/channel/webhook/test", LINE_API_BASE))
+        .bearer_auth(token)
+        .json(&json!({ "endpoint": url }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    if test["success"] == true {
+        Ok(format!("registered and verified (LINE got HTTP {})", test["statusCode"].as_u64().unwrap_or(200)))
+    } else {
+        anyhow::bail!(
+            "registered, but LINE could not deliver: {} {}",
+            test["reason"].as_str().unwrap_or("unknown"),
+            test["detail"].as_str().unwrap_or_default()
+        )
+    }
+}
+
+/// Go through Messenger's subscribe handshake against our own public URL.
+async fn verify_facebook(url: &str, verify_token: &str) -> anyhow::Result<String> {
+    let challenge = uuid::Uuid::new_v4().simple().to_string();
+    let body = http::client_for(ClientClass::Webhook)
+        .get(url)
+        .query(&[("hub.mode", "subscribe"), ("hub.verify_token", verify_token), ("hub.challenge", challenge.as_str())])
+        .send()
+        .await?
+        .text()
+        .await?;
+    anyhow::ensure!(body == challenge, "verification failed; make sure the URL is public and FACEBOOK_VERIFY_TOKEN matches");
+    Ok("verification works; subscribe this URL in the Meta app dashboard".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webhook_url() {
+        assert_eq!(webhook_url("https://bot.example.com/", "telegram"), "https://bot.example.com/webhooks/telegram");
+        assert_eq!(webhook_url("https://x.ngrok.app", "line"), "https://x.ngrok.app/webhooks/line");
+    }
+}
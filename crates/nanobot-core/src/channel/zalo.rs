@@ -6,6 +6,7 @@ use tracing::{debug, error, info, warn};
 
 use crate::config::ZaloConfig;
 use crate::types::{InboundMessage, OutboundMessage};
+use crate::util::http::{self, ClientClass};
 
 use super::{is_allowed, Channel};
 
@@ -25,7 +26,7 @@ impl ZaloChannel {
             config,
             inbound_tx,
             running: false,
-            client: reqwest::Client::new(),
+            client: http::client_for(ClientClass::Webhook),
         }
     }
 
@@ -0,0 +1,225 @@
+//! Source tracking and citations for answers built from web tools.
+//!
+//! Pages the fetch tools actually read are numbered as they come in, and
+//! each such tool result is headed with its number so the model can cite
+//! `[1]` inline. Search hits only become sources when the answer links
+//! them. Once the answer is final, [`Sources::render`] swaps bare source
+//! URLs for their number and appends the numbered list; the same list goes
+//! out as SSE `citation` events so clients can render footnotes.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::factcheck::{find_urls, SOURCE_TOOLS};
+use crate::session::locale::Locale;
+use crate::tool::stats::is_failure;
+
+/// Tools that read a page: the URL they were given is a source.
+pub const FETCH_TOOLS: &[&str] = &["web_fetch", "read_webpage", "web_crawl"];
+
+/// One numbered source.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Citation {
+    /// 1-based number used in `[n]` markers.
+    pub index: usize,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Tool that produced the source.
+    pub tool: String,
+}
+
+/// Sources of one answer.
+#[derive(Debug, Default)]
+pub struct Sources {
+    cited: Vec<Citation>,
+    /// URLs search tools returned; numbered only if the answer links them.
+    hits: Vec<(String, String)>,
+}
+
+impl Sources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a tool result. A fetched page is numbered and its result comes
+    /// back headed with `[n] <url>`; anything else is returned unchanged.
+    pub fn track(&mut self, tool: &str, args: &HashMap<String, serde_json::Value>, result: String) -> String {
+        if result.starts_with("[NO_RESULTS]") || is_failure(&result) {
+            return result;
+        }
+        if FETCH_TOOLS.contains(&tool) {
+            let Some((url, title)) = fetched_page(args, &result) else { return result };
+            let index = self.add(tool, &url, title);
+            return format!("[{}] {}\n{}", index, url, result);
+        }
+        if SOURCE_TOOLS.contains(&tool) {
+            for url in find_urls(&result) {
+                if !self.hits.iter().any(|(known, _)| known == url) {
+                    self.hits.push((url.to_string(), tool.to_string()));
+                }
+            }
+        }
+        result
+    }
+
+    fn add(&mut self, tool: &str, url: &str, title: Option<String>) -> usize {
+        if let Some(existing) = self.cited.iter().find(|c| c.url == url) {
+            return existing.index;
+        }
+        let index = self.cited.len() + 1;
+        self.cited.push(Citation { index, url: url.to_string(), title, tool: tool.to_string() });
+        index
+    }
+
+    /// Make sure the answer gets a source list (researcher answers): when
+    /// no page was fetched, the first `max` search hits are numbered.
+    pub fn require(&mut self, max: usize) {
+        if self.cited.is_empty() {
+            let hits: Vec<(String, String)> = self.hits.iter().take(max).cloned().collect();
+            for (url, tool) in hits {
+                self.add(&tool, &url, None);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cited.is_empty()
+    }
+
+    /// The numbered sources, in order.
+    pub fn citations(&self) -> &[Citation] {
+        &self.cited
+    }
+
+    /// Number the search hits `answer` links, replace bare source URLs with
+    /// their `[n]` marker and append the source list. Answers without
+    /// sources come back unchanged.
+    pub fn render(&mut self, answer: &str, locale: Locale) -> String {
+        let linked: Vec<(String, String)> =
+            self.hits.iter().filter(|(url, _)| answer.contains(url.as_str())).cloned().collect();
+        for (url, tool) in linked {
+            self.add(&tool, &url, None);
+        }
+        if self.cited.is_empty() {
+            return answer.to_string();
+        }
+
+        let mut out = answer.to_string();
+        for citation in &self.cited {
+            out = replace_bare_url(&out, &citation.url, &format!("[{}]", citation.index));
+        }
+        let list: Vec<String> = self
+            .cited
+            .iter()
+            .map(|c| match &c.title {
+                Some(title) => format!("[{}] {} — {}", c.index, title, c.url),
+                None => format!("[{}] {}", c.index, c.url),
+            })
+            .collect();
+        format!("{}\n\n{}\n{}", out.trim_end(), locale.pick("📚 出典:", "📚 Sources:"), list.join("\n"))
+    }
+}
+
+/// URL and title of the page a fetch tool read. `web_fetch` answers with
+/// JSON (`url`, `finalUrl`, `title`); other fetchers return plain text, in
+/// which case the requested URL is used.
+fn fetched_page(args: &HashMap<String, serde_json::Value>, result: &str) -> Option<(String, Option<String>)> {
+    let requested = args.get("url").and_then(|v| v.as_str());
+    let parsed = serde_json::from_str::<serde_json::Value>(result).ok();
+    if parsed.as_ref().is_some_and(|v| v.get("error").is_some()) {
+        return None;
+    }
+    let field = |key: &str| {
+        parsed
+            .as_ref()
+            .and_then(|v| v.get(key))
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty())
+            .map(str::to_string)
+    };
+    let url = field("finalUrl").or_else(|| field("url")).or_else(|| requested.map(str::to_string))?;
+    url.starts_with("http").then(|| (url, field("title")))
+}
+
+/// Replace standalone occurrences of `url` with `marker`. Markdown link
+/// targets are kept, and `(url)` loses its parentheses.
+fn replace_bare_url(text: &str, url: &str, marker: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find(url) {
+        let (before, after) = (&rest[..pos], &rest[pos + url.len()..]);
+        let mut next = after.chars();
+        let continues = match (next.next(), next.next()) {
+            (Some('.'), Some(c)) => c.is_ascii_alphanumeric(),
+            (Some(c), _) => c.is_ascii_alphanumeric() || "/?#&=%-_~".contains(c),
+            _ => false,
+        };
+        if before.ends_with("](") || continues {
+            out.push_str(&rest[..pos + url.len()]);
+        } else if let (Some(open), Some(close)) = (before.strip_suffix('('), after.strip_prefix(')')) {
+            out.push_str(open.trim_end());
+            out.push(' ');
+            out.push_str(marker);
+            rest = close;
+            continue;
+        } else {
+            out.push_str(before);
+            out.push_str(marker);
+        }
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn url_args(url: &str) -> HashMap<String, serde_json::Value> {
+        HashMap::from([("url".to_string(), json!(url))])
+    }
+
+    #[test]
+    fn test_track_and_render() {
+        let mut sources = Sources::new();
+        let page = json!({"url": "https://example.com/a", "finalUrl": "https://example.com/a", "title": "Skytree"}).to_string();
+        let labelled = sources.track("web_fetch", &url_args("https://example.com/a"), page);
+        assert!(labelled.starts_with("[1] https://example.com/a\n{"));
+        let failed = json!({"error": "404", "url": "https://example.com/gone"}).to_string();
+        assert_eq!(sources.track("web_fetch", &url_args("https://example.com/gone"), failed.clone()), failed);
+        sources.track("web_search", &HashMap::new(), "1. Tokyo https://example.org/b\n2. Other https://example.org/c".into());
+        assert_eq!(sources.track("calculator", &HashMap::new(), "42".into()), "42");
+
+        let answer = "Skytree is 634 m [1]. Opened in 2012 (https://example.org/b). See [docs](https://example.com/a).";
+        let out = sources.render(answer, Locale::En);
+        assert_eq!(
+            out,
+            "Skytree is 634 m [1]. Opened in 2012 [2]. See [docs](https://example.com/a).\n\n\
+             📚 Sources:\n[1] Skytree — https://example.com/a\n[2] https://example.org/b"
+        );
+        let urls: Vec<&str> = sources.citations().iter().map(|c| c.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://example.com/a", "https://example.org/b"]);
+    }
+
+    #[test]
+    fn test_no_sources() {
+        let mut sources = Sources::new();
+        sources.track("web_search", &HashMap::new(), "https://example.org/b".into());
+        assert_eq!(sources.render("No links here.", Locale::Ja), "No links here.");
+        assert!(sources.is_empty());
+
+        sources.require(3);
+        assert!(sources.render("No links here.", Locale::Ja).ends_with("📚 出典:\n[1] https://example.org/b"));
+    }
+
+    #[test]
+    fn test_replace_bare_url() {
+        assert_eq!(replace_bare_url("see https://a.com/x now", "https://a.com/x", "[1]"), "see [1] now");
+        assert_eq!(replace_bare_url("https://a.com/xy", "https://a.com/x", "[1]"), "https://a.com/xy");
+        assert_eq!(replace_bare_url("end https://a.com/x.", "https://a.com/x", "[1]"), "end [1].");
+    }
+}
@@ -0,0 +1,582 @@
+//! Lightweight code intelligence: a tree-sitter symbol index of the workspace.
+//!
+//! [`SymbolIndex`] parses Rust, Python, JavaScript/TypeScript and Go files
+//! and records their definitions (functions, types, methods with their
+//! enclosing impl/class). Files are re-parsed only when their mtime or size
+//! changes, so [`SymbolIndex::refresh`] is cheap enough to run before every
+//! prompt. The index backs the `list_symbols`/`find_definition` tools and the
+//! compact repo map added to the system prompt.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tracing::debug;
+
+use crate::util::truncate_chars;
+
+/// Files beyond this count are not indexed.
+pub const MAX_FILES: usize = 5000;
+
+/// Larger files (usually generated) are skipped.
+const MAX_FILE_BYTES: u64 = 512 * 1024;
+
+const MAX_SIGNATURE_CHARS: usize = 160;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolKind {
+    Function,
+    Method,
+    Struct,
+    Enum,
+    Trait,
+    Interface,
+    Class,
+    Type,
+    Const,
+    Module,
+    Macro,
+}
+
+impl SymbolKind {
+    /// Short keyword used in listings and the repo map.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Function => "fn",
+            Self::Method => "method",
+            Self::Struct => "struct",
+            Self::Enum => "enum",
+            Self::Trait => "trait",
+            Self::Interface => "interface",
+            Self::Class => "class",
+            Self::Type => "type",
+            Self::Const => "const",
+            Self::Module => "mod",
+            Self::Macro => "macro",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Some(match s.to_lowercase().as_str() {
+            "fn" | "function" | "func" | "def" => Self::Function,
+            "method" => Self::Method,
+            "struct" => Self::Struct,
+            "enum" => Self::Enum,
+            "trait" => Self::Trait,
+            "interface" => Self::Interface,
+            "class" => Self::Class,
+            "type" => Self::Type,
+            "const" | "static" => Self::Const,
+            "mod" | "module" => Self::Module,
+            "macro" => Self::Macro,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Path relative to the workspace.
+    pub file: String,
+    /// 1-based first and last line of the definition.
+    pub line: usize,
+    pub end_line: usize,
+    /// Enclosing impl/class/receiver type, for methods.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// First line of the definition.
+    pub signature: String,
+}
+
+impl Symbol {
+    /// `Parent::name` for methods, `name` otherwise.
+    pub fn qualified_name(&self) -> String {
+        match self.parent {
+            Some(ref parent) => format!("{}::{}", parent, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Lang {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+}
+
+impl Lang {
+    fn from_path(path: &Path) -> Option<Self> {
+        Some(match path.extension()?.to_str()? {
+            "rs" => Self::Rust,
+            "py" | "pyi" => Self::Python,
+            "js" | "jsx" | "mjs" | "cjs" => Self::JavaScript,
+            "ts" | "mts" | "cts" => Self::TypeScript,
+            "tsx" => Self::Tsx,
+            "go" => Self::Go,
+            _ => return None,
+        })
+    }
+
+    fn language(&self) -> tree_sitter::Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::Go => tree_sitter_go::LANGUAGE.into(),
+        }
+    }
+}
+
+/// Symbols defined in `source`. `file` is only copied into the results.
+pub fn extract_symbols(path: &Path, source: &str, file: &str) -> Vec<Symbol> {
+    let Some(lang) = Lang::from_path(path) else {
+        return Vec::new();
+    };
+    let mut parser = tree_sitter::Parser::new();
+    if parser.set_language(&lang.language()).is_err() {
+        return Vec::new();
+    }
+    let Some(tree) = parser.parse(source, None) else {
+        return Vec::new();
+    };
+    let mut symbols = Vec::new();
+    collect(lang, tree.root_node(), source.as_bytes(), file, None, &mut symbols, 0);
+    symbols
+}
+
+fn text<'a>(node: tree_sitter::Node<'_>, source: &'a [u8]) -> &'a str {
+    node.utf8_text(source).unwrap_or_default()
+}
+
+fn field_text<'a>(node: tree_sitter::Node<'_>, field: &str, source: &'a [u8]) -> Option<&'a str> {
+    node.child_by_field_name(field).map(|n| text(n, source))
+}
+
+fn signature(node: tree_sitter::Node<'_>, source: &[u8]) -> String {
+    let first = text(node, source).lines().next().unwrap_or_default().trim();
+    let first = first.trim_end_matches('{').trim_end();
+    match truncate_chars(first, MAX_SIGNATURE_CHARS) {
+        (head, true) => format!("{}…", head),
+        (head, false) => head.to_string(),
+    }
+}
+
+/// Strip generics and references: `&'a mut Foo<T>` -> `Foo`.
+fn base_type_name(ty: &str) -> String {
+    let ty = ty.trim_start_matches(['&', '*', '(']).trim();
+    let ty = ty.strip_prefix("mut ").unwrap_or(ty);
+    let ty = ty.split(['<', '(', ' ', ')']).next().unwrap_or(ty);
+    ty.rsplit("::").next().unwrap_or(ty).to_string()
+}
+
+/// What a node defines: (kind, name, parent for its children).
+fn classify(
+    lang: Lang,
+    node: tree_sitter::Node<'_>,
+    source: &[u8],
+    parent: Option<&str>,
+) -> (Option<(SymbolKind, String)>, Option<String>) {
+    let name = || field_text(node, "name", source).map(String::from);
+    let in_type = parent.is_some();
+    let def = |kind: SymbolKind| name().map(|n| (kind, n));
+    match (lang, node.kind()) {
+        (Lang::Rust, "function_item" | "function_signature_item") => {
+            (def(if in_type { SymbolKind::Method } else { SymbolKind::Function }), None)
+        }
+        (Lang::Rust, "struct_item" | "union_item") => (def(SymbolKind::Struct), None),
+        (Lang::Rust, "enum_item") => (def(SymbolKind::Enum), None),
+        (Lang::Rust, "trait_item") => (def(SymbolKind::Trait), name()),
+        (Lang::Rust, "type_item") => (def(SymbolKind::Type), None),
+        (Lang::Rust, "const_item" | "static_item") if !in_type => (def(SymbolKind::Const), None),
+        (Lang::Rust, "mod_item") => (def(SymbolKind::Module), None),
+        (Lang::Rust, "macro_definition") => (def(SymbolKind::Macro), None),
+        (Lang::Rust, "impl_item") => (None, field_text(node, "type", source).map(base_type_name)),
+
+        (Lang::Python, "function_definition") => {
+            (def(if in_type { SymbolKind::Method } else { SymbolKind::Function }), None)
+        }
+        (Lang::Python, "class_definition") => (def(SymbolKind::Class), name()),
+
+        (_, "function_declaration" | "generator_function_declaration") if lang != Lang::Go => {
+            (def(SymbolKind::Function), None)
+        }
+        (_, "class_declaration" | "abstract_class_declaration") => (def(SymbolKind::Class), name()),
+        (_, "method_definition" | "method_signature" | "abstract_method_signature") => {
+            (def(SymbolKind::Method), None)
+        }
+        (_, "interface_declaration") => (def(SymbolKind::Interface), name()),
+        (_, "type_alias_declaration") => (def(SymbolKind::Type), None),
+        (_, "enum_declaration") => (def(SymbolKind::Enum), None),
+        (Lang::JavaScript | Lang::TypeScript | Lang::Tsx, "variable_declarator") if !in_type => {
+            let is_fn = node
+                .child_by_field_name("value")
+                .is_some_and(|v| matches!(v.kind(), "arrow_function" | "function_expression" | "function"));
+            (if is_fn { def(SymbolKind::Function) } else { None }, None)
+        }
+
+        (Lang::Go, "function_declaration") => (def(SymbolKind::Function), None),
+        (Lang::Go, "method_declaration") => {
+            // func (s *Server) Start() -> parent "Server"
+            let receiver = node.child_by_field_name("receiver").and_then(|r| {
+                let mut cursor = r.walk();
+                let param = r.named_children(&mut cursor).next();
+                param.and_then(|p| field_text(p, "type", source)).map(base_type_name)
+            });
+            (name().map(|n| (SymbolKind::Method, n)), receiver)
+        }
+        (Lang::Go, "type_spec") => {
+            let kind = match node.child_by_field_name("type").map(|t| t.kind()) {
+                Some("struct_type") => SymbolKind::Struct,
+                Some("interface_type") => SymbolKind::Interface,
+                _ => SymbolKind::Type,
+            };
+            (def(kind), None)
+        }
+        (Lang::Go, "const_spec") if !in_type => (def(SymbolKind::Const), None),
+        _ => (None, None),
+    }
+}
+
+fn collect(
+    lang: Lang,
+    node: tree_sitter::Node<'_>,
+    source: &[u8],
+    file: &str,
+    parent: Option<&str>,
+    out: &mut Vec<Symbol>,
+    depth: usize,
+) {
+    if depth > 64 {
+        return;
+    }
+    let (def, scope) = classify(lang, node, source, parent);
+    if let Some((kind, name)) = def {
+        // Go methods carry their receiver as the scope instead of a container
+        let symbol_parent = match kind {
+            SymbolKind::Method if lang == Lang::Go => scope.clone(),
+            SymbolKind::Method => parent.map(String::from),
+            _ => None,
+        };
+        out.push(Symbol {
+            name,
+            kind,
+            file: file.to_string(),
+            line: node.start_position().row + 1,
+            end_line: node.end_position().row + 1,
+            parent: symbol_parent,
+            signature: signature(node, source),
+        });
+        // Don't descend into function bodies: local helpers are noise
+        if matches!(kind, SymbolKind::Function | SymbolKind::Method) {
+            return;
+        }
+    }
+    let child_parent = match scope {
+        Some(ref s) if lang != Lang::Go => Some(s.as_str()),
+        _ => parent,
+    };
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect(lang, child, source, file, child_parent, out, depth + 1);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FileEntry {
+    modified: Option<SystemTime>,
+    size: u64,
+    symbols: Vec<Symbol>,
+}
+
+/// Symbols of every supported source file in a workspace, refreshed incrementally.
+pub struct SymbolIndex {
+    workspace: PathBuf,
+    files: Mutex<HashMap<String, FileEntry>>,
+}
+
+impl SymbolIndex {
+    pub fn new(workspace: PathBuf) -> Self {
+        Self {
+            workspace,
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn workspace(&self) -> &Path {
+        &self.workspace
+    }
+
+    /// Re-scan the workspace (respecting .gitignore) and re-parse changed
+    /// files. Returns the number of files parsed.
+    pub fn refresh(&self) -> usize {
+        let walker = ignore::WalkBuilder::new(&self.workspace)
+            .require_git(false)
+            .build();
+        let mut seen = Vec::new();
+        let mut parsed = 0;
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+
+        for entry in walker.flatten() {
+            if seen.len() >= MAX_FILES {
+                break;
+            }
+            let path = entry.path();
+            if !entry.file_type().is_some_and(|t| t.is_file()) || Lang::from_path(path).is_none() {
+                continue;
+            }
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.len() > MAX_FILE_BYTES {
+                continue;
+            }
+            let rel = path
+                .strip_prefix(&self.workspace)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let modified = meta.modified().ok();
+            seen.push(rel.clone());
+            if files
+                .get(&rel)
+                .is_some_and(|f| f.modified == modified && f.size == meta.len())
+            {
+                continue;
+            }
+            let Ok(source) = std::fs::read_to_string(path) else {
+                continue;
+            };
+            let symbols = extract_symbols(path, &source, &rel);
+            files.insert(
+                rel,
+                FileEntry {
+                    modified,
+                    size: meta.len(),
+                    symbols,
+                },
+            );
+            parsed += 1;
+        }
+
+        let seen: std::collections::HashSet<String> = seen.into_iter().collect();
+        files.retain(|path, _| seen.contains(path));
+        if parsed > 0 {
+            debug!("Symbol index: parsed {} file(s), {} indexed", parsed, files.len());
+        }
+        parsed
+    }
+
+    /// Number of indexed files.
+    pub fn file_count(&self) -> usize {
+        self.files.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Symbols in files under `path` (a file or directory relative to the
+    /// workspace; `None` for everything), sorted by file and line.
+    pub fn symbols(&self, path: Option<&str>) -> Vec<Symbol> {
+        let prefix = path
+            .map(|p| p.trim_start_matches("./").trim_end_matches('/'))
+            .filter(|p| !p.is_empty() && *p != ".");
+        let files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        let mut symbols: Vec<Symbol> = files
+            .iter()
+            .filter(|(file, _)| {
+                prefix.is_none_or(|p| file.as_str() == p || file.starts_with(&format!("{}/", p)))
+            })
+            .flat_map(|(_, entry)| entry.symbols.iter().cloned())
+            .collect();
+        symbols.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
+        symbols
+    }
+
+    /// Definitions of `name`, which may be qualified (`Type::method`,
+    /// `Class.method`). Exact matches first; falls back to a case-insensitive match.
+    pub fn find(&self, name: &str, kind: Option<SymbolKind>) -> Vec<Symbol> {
+        let (parent, name) = match name.rsplit_once("::").or_else(|| name.rsplit_once('.')) {
+            Some((parent, name)) => (Some(base_type_name(parent)), name),
+            None => (None, name),
+        };
+        let all = self.symbols(None);
+        let matches = |exact: bool| -> Vec<Symbol> {
+            let eq = |a: &str, b: &str| if exact { a == b } else { a.eq_ignore_ascii_case(b) };
+            all.iter()
+                .filter(|s| eq(&s.name, name))
+                .filter(|s| kind.is_none_or(|k| s.kind == k))
+                .filter(|s| match (&parent, &s.parent) {
+                    (Some(want), Some(have)) => eq(have, want),
+                    (Some(_), None) => false,
+                    (None, _) => true,
+                })
+                .cloned()
+                .collect()
+        };
+        let exact = matches(true);
+        if exact.is_empty() {
+            matches(false)
+        } else {
+            exact
+        }
+    }
+
+    /// Compact outline of the indexed files, at most `max_chars` long.
+    ///
+    /// One line per file listing its top-level definitions, with methods
+    /// grouped under their type: `src/lib.rs: struct Config{new, load}, fn run`.
+    pub fn repo_map(&self, max_chars: usize) -> String {
+        let symbols = self.symbols(None);
+        let mut by_file: Vec<(String, Vec<Symbol>)> = Vec::new();
+        for symbol in symbols {
+            match by_file.last_mut() {
+                Some((file, list)) if *file == symbol.file => list.push(symbol),
+                _ => by_file.push((symbol.file.clone(), vec![symbol])),
+            }
+        }
+
+        let total = by_file.len();
+        let mut out = String::new();
+        for (written, (file, list)) in by_file.into_iter().enumerate() {
+            let mut methods: HashMap<&str, Vec<&str>> = HashMap::new();
+            for s in &list {
+                if let (SymbolKind::Method, Some(parent)) = (s.kind, &s.parent) {
+                    methods.entry(parent.as_str()).or_default().push(&s.name);
+                }
+            }
+            let mut items: Vec<String> = Vec::new();
+            let mut listed_parents = std::collections::HashSet::new();
+            for s in &list {
+                if s.kind == SymbolKind::Method {
+                    continue;
+                }
+                let mut item = format!("{} {}", s.kind.label(), s.name);
+                if let Some(ms) = methods.get(s.name.as_str()) {
+                    if listed_parents.insert(s.name.as_str()) {
+                        item.push_str(&format!("{{{}}}", ms.join(", ")));
+                    }
+                }
+                items.push(item);
+            }
+            // Methods whose type is defined elsewhere (e.g. impl blocks in another file)
+            let mut orphans: Vec<_> = methods.iter().filter(|(p, _)| !listed_parents.contains(*p)).collect();
+            orphans.sort();
+            for (parent, ms) in orphans {
+                items.push(format!("impl {}{{{}}}", parent, ms.join(", ")));
+            }
+            if items.is_empty() {
+                continue;
+            }
+            let line = format!("{}: {}\n", file, items.join(", "));
+            if out.len() + line.len() > max_chars {
+                out.push_str(&format!("… ({} more files)\n", total - written));
+                break;
+            }
+            out.push_str(&line);
+        }
+        out.trim_end().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST: &str = r#"
+pub struct Config {
+    name: String,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        fn local_helper() {}
+        Self { name: String::new() }
+    }
+}
+
+pub trait Store {
+    fn get(&self, key: &str) -> Option<String>;
+}
+
+pub fn run(config: &Config) {}
+"#;
+
+    #[test]
+    fn test_extract_rust_symbols() {
+        let symbols = extract_symbols(Path::new("lib.rs"), RUST, "lib.rs");
+        let found: Vec<_> = symbols
+            .iter()
+            .map(|s| (s.kind, s.qualified_name(), s.line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (SymbolKind::Struct, "Config".to_string(), 2),
+                (SymbolKind::Method, "Config::new".to_string(), 7),
+                (SymbolKind::Trait, "Store".to_string(), 13),
+                (SymbolKind::Method, "Store::get".to_string(), 14),
+                (SymbolKind::Function, "run".to_string(), 17),
+            ]
+        );
+        assert_eq!(symbols[1].signature, "pub fn new() -> Self");
+        assert_eq!(symbols[1].end_line, 10);
+    }
+
+    #[test]
+    fn test_extract_other_languages() {
+        let py = "class Greeter:\n    def hello(self):\n        pass\n\ndef main():\n    pass\n";
+        let names: Vec<_> = extract_symbols(Path::new("a.py"), py, "a.py")
+            .iter()
+            .map(|s| s.qualified_name())
+            .collect();
+        assert_eq!(names, vec!["Greeter", "Greeter::hello", "main"]);
+
+        let ts = "export interface Props { id: string }\nexport class App {\n  render() {}\n}\nconst handler = async () => {};\n";
+        let names: Vec<_> = extract_symbols(Path::new("a.ts"), ts, "a.ts")
+            .iter()
+            .map(|s| format!("{} {}", s.kind.label(), s.qualified_name()))
+            .collect();
+        assert_eq!(names, vec!["interface Props", "class App", "method App::render", "fn handler"]);
+
+        let go = "package main\n\ntype Server struct{}\n\nfunc (s *Server) Start() error { return nil }\n\nfunc main() {}\n";
+        let names: Vec<_> = extract_symbols(Path::new("a.go"), go, "a.go")
+            .iter()
+            .map(|s| format!("{} {}", s.kind.label(), s.qualified_name()))
+            .collect();
+        assert_eq!(names, vec!["struct Server", "method Server::Start", "fn main"]);
+    }
+
+    #[test]
+    fn test_index_find_and_repo_map() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join("src")).unwrap();
+        std::fs::write(tmp.path().join("src/lib.rs"), RUST).unwrap();
+        std::fs::write(tmp.path().join("notes.txt"), "fn ignored() {}").unwrap();
+        let index = SymbolIndex::new(tmp.path().to_path_buf());
+
+        assert_eq!(index.refresh(), 1);
+        assert_eq!(index.refresh(), 0, "unchanged files are not re-parsed");
+        assert_eq!(index.find("Config::new", None).len(), 1);
+        assert_eq!(index.find("config", Some(SymbolKind::Struct))[0].name, "Config");
+        assert!(index.find("Store::new", None).is_empty());
+        assert_eq!(index.symbols(Some("src")).len(), 5);
+        assert!(index.symbols(Some("tests")).is_empty());
+
+        assert_eq!(
+            index.repo_map(1000),
+            "src/lib.rs: struct Config{new}, trait Store{get}, fn run"
+        );
+        assert!(index.repo_map(10).starts_with("… (1 more files)"));
+
+        std::fs::remove_file(tmp.path().join("src/lib.rs")).unwrap();
+        index.refresh();
+        assert_eq!(index.file_count(), 0);
+    }
+}
@@ -0,0 +1,993 @@
+//! Slash-command registry shared by the CLI, HTTP API and channel adapters.
+//!
+//! Every command is declared once in [`COMMANDS`] with its help text, the
+//! channels it is offered on, and a handler. Session handlers only touch the
+//! `Session`, so `/reset`, `/usage`, `/model`, `/agent` and the pin commands
+//! behave the same wherever they are typed. Front-end handlers (`/link`,
+//! `/share`, CLI screen commands, ...) are dispatched by the front-end that
+//! owns them.
+
+use crate::agent::length::LengthPreference;
+use crate::agent::prompt::estimate_tokens;
+use crate::provider::pricing::lookup_model;
+use crate::session::locale::Locale;
+use crate::session::contacts::{normalize_identity, Contact};
+use crate::session::incognito::Switch;
+use crate::session::pins::MAX_PINS;
+use crate::session::Session;
+use crate::util::truncate_string;
+
+/// Front-end state available to session handlers.
+pub struct CommandEnv<'a> {
+    /// Channel id the command arrived on (`cli`, `web`, `line`, `telegram`, ...).
+    pub channel: &'a str,
+    /// Front-end default model (used when the session has no `/model` override).
+    pub model: &'a str,
+    /// Agent profile that will answer the next message, if the front-end has one.
+    pub agent: Option<&'a str>,
+    /// Agent ids accepted by `/agent`; empty if the front-end has no agent profiles.
+    pub agents: &'a [&'a str],
+}
+
+/// Handler signature for commands that only operate on the session.
+pub type SessionHandler = fn(args: &str, session: &mut Session, env: &CommandEnv<'_>) -> String;
+
+/// How a command is executed.
+#[derive(Debug, Clone, Copy)]
+pub enum Handler {
+    /// Runs against the session; identical on every front-end.
+    Session(SessionHandler),
+    /// Implemented by the front-end (needs network, UI or backend access).
+    Frontend,
+}
+
+/// Static description of a slash command.
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub help_en: &'static str,
+    /// Channel ids where the command is offered; empty means everywhere.
+    pub channels: &'static [&'static str],
+    pub handler: Handler,
+}
+
+impl CommandSpec {
+    pub fn is_available(&self, channel: &str) -> bool {
+        self.channels.is_empty() || self.channels.contains(&channel)
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name) || self.aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+    }
+}
+
+/// All known slash commands, in help order.
+pub static COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "help",
+        aliases: &["h"],
+        usage: "/help",
+        help: "このヘルプを表示",
+        help_en: "Show this help",
+        channels: &[],
+        handler: Handler::Session(cmd_help),
+    },
+    CommandSpec {
+        name: "status",
+        aliases: &["s"],
+        usage: "/status",
+        help: "システム状態を表示",
+        help_en: "Show system status",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "reset",
+        aliases: &["new"],
+        usage: "/reset",
+        help: "会話履歴をリセット（ピン留めは保持）",
+        help_en: "Reset conversation history (pins are kept)",
+        channels: &[],
+        handler: Handler::Session(cmd_reset),
+    },
+    CommandSpec {
+        name: "model",
+        aliases: &[],
+        usage: "/model [名前|reset]",
+        help: "このセッションのモデルを表示・切替",
+        help_en: "Show or switch the model for this session",
+        channels: &[],
+        handler: Handler::Session(cmd_model),
+    },
+    CommandSpec {
+        name: "agent",
+        aliases: &[],
+        usage: "/agent [ID|reset]",
+        help: "このセッションのエージェントを表示・切替",
+        help_en: "Show or switch the agent for this session",
+        channels: &[],
+        handler: Handler::Session(cmd_agent),
+    },
+    CommandSpec {
+        name: "usage",
+        aliases: &[],
+        usage: "/usage",
+        help: "このセッションの使用状況を表示",
+        help_en: "Show usage for this session",
+        channels: &[],
+        handler: Handler::Session(cmd_usage),
+    },
+    CommandSpec {
+        name: "history",
+        aliases: &[],
+        usage: "/history [件数]",
+        help: "最近の応答をモデル・トークン・所要時間つきで表示",
+        help_en: "Show recent answers with their model, tokens and timing",
+        channels: &[],
+        handler: Handler::Session(cmd_history),
+    },
+    CommandSpec {
+        name: "lang",
+        aliases: &["language"],
+        usage: "/lang [ja|en|auto]",
+        help: "応答言語を表示・切替",
+        help_en: "Show or switch the response language",
+        channels: &[],
+        handler: Handler::Session(cmd_lang),
+    },
+    CommandSpec {
+        name: "length",
+        aliases: &[],
+        usage: "/length [brief|normal|long|文字数|reset]",
+        help: "応答の長さを表示・切替",
+        help_en: "Show or set the reply length",
+        channels: &[],
+        handler: Handler::Session(cmd_length),
+    },
+    CommandSpec {
+        name: "tone",
+        aliases: &[],
+        usage: "/tone [口調|reset]",
+        help: "応答の口調を表示・指定",
+        help_en: "Show or set the reply tone",
+        channels: &[],
+        handler: Handler::Session(cmd_tone),
+    },
+    CommandSpec {
+        name: "timezone",
+        aliases: &["tz"],
+        usage: "/timezone [Asia/Tokyo|+9|reset]",
+        help: "リマインダーのタイムゾーンを表示・設定",
+        help_en: "Show or set the timezone used for reminders",
+        channels: &[],
+        handler: Handler::Session(cmd_timezone),
+    },
+    CommandSpec {
+        name: "incognito",
+        aliases: &["private"],
+        usage: "/incognito [on|off|メッセージ]",
+        help: "シークレットモード（会話を保存しない）",
+        help_en: "Incognito mode: don't save the conversation",
+        channels: &[],
+        handler: Handler::Session(cmd_incognito),
+    },
+    CommandSpec {
+        name: "remind",
+        aliases: &[],
+        usage: "/remind <いつ・内容>",
+        help: "リマインダーを設定（例: /remind 明日9時に会議）",
+        help_en: "Set a reminder (e.g. /remind me to call mom at 5pm)",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "reminders",
+        aliases: &[],
+        usage: "/reminders [cancel <ID>]",
+        help: "リマインダー一覧・解除",
+        help_en: "List or cancel reminders",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "snooze",
+        aliases: &[],
+        usage: "/snooze [ID] [10分]",
+        help: "リマインダーをスヌーズ",
+        help_en: "Snooze a reminder",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "upcoming",
+        aliases: &[],
+        usage: "/upcoming [cancel <ID> | move <ID> <日時>]",
+        help: "リマインダー・定期実行などの予定一覧・取消・変更",
+        help_en: "List, cancel or move reminders, follow-ups and other scheduled items",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "workflow",
+        aliases: &["wf"],
+        usage: "/workflow [名前] [入力]",
+        help: "ワークフロー一覧・実行",
+        help_en: "List or run workflows",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "pin",
+        aliases: &[],
+        usage: "/pin <内容>",
+        help: "事実・ファイル・URLをピン留め",
+        help_en: "Pin a fact, file path or URL",
+        channels: &[],
+        handler: Handler::Session(cmd_pin),
+    },
+    CommandSpec {
+        name: "pins",
+        aliases: &[],
+        usage: "/pins",
+        help: "ピン留め一覧",
+        help_en: "List pinned items",
+        channels: &[],
+        handler: Handler::Session(cmd_pins),
+    },
+    CommandSpec {
+        name: "unpin",
+        aliases: &[],
+        usage: "/unpin <番号|all>",
+        help: "ピン留めを解除",
+        help_en: "Remove pinned items",
+        channels: &[],
+        handler: Handler::Session(cmd_unpin),
+    },
+    CommandSpec {
+        name: "contacts",
+        aliases: &["contact"],
+        usage: "/contacts [名前|add|link|unlink|alias|note|remove]",
+        help: "連絡先（名前とチャネルID）の管理",
+        help_en: "Manage contacts (names and their channel ids)",
+        channels: &[],
+        handler: Handler::Session(cmd_contacts),
+    },
+    CommandSpec {
+        name: "share",
+        aliases: &[],
+        usage: "/share",
+        help: "会話の共有リンクを生成",
+        help_en: "Create a share link for this conversation",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "link",
+        aliases: &[],
+        usage: "/link [CODE]",
+        help: "チャネル連携コードを生成 / 別チャネルとリンク",
+        help_en: "Get a link code / link another channel",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "route",
+        aliases: &[],
+        usage: "/route [チャネル|auto]",
+        help: "送信先チャネルを表示・固定",
+        help_en: "Show or pin the channel messages go to",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "human",
+        aliases: &["operator"],
+        usage: "/human [用件]",
+        help: "担当者（人）に代わる",
+        help_en: "Talk to a person instead of the bot",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "resume",
+        aliases: &[],
+        usage: "/resume [セッション]",
+        help: "ボットとの会話に戻る",
+        help_en: "Go back to the bot after a handoff",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "reply",
+        aliases: &[],
+        usage: "/reply <セッション> <内容>",
+        help: "引き継いだ会話に返信（オペレーター用）",
+        help_en: "Answer a handed-off conversation (operators)",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "handoffs",
+        aliases: &[],
+        usage: "/handoffs",
+        help: "引き継ぎ中の会話一覧（オペレーター用）",
+        help_en: "List handed-off conversations (operators)",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "improve",
+        aliases: &[],
+        usage: "/improve <説明>",
+        help: "改善PRを作成（管理者のみ）",
+        help_en: "Open an improvement PR (admin only)",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "keys",
+        aliases: &[],
+        usage: "/keys",
+        help: "APIキー管理（管理者のみ）",
+        help_en: "Manage API keys (admin only)",
+        channels: &[],
+        handler: Handler::Frontend,
+    },
+    // CLI-only screen commands
+    CommandSpec {
+        name: "q",
+        aliases: &["menu"],
+        usage: "/q",
+        help: "クイックメニュー",
+        help_en: "Quick menu",
+        channels: &["cli"],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "c",
+        aliases: &["clear"],
+        usage: "/c",
+        help: "画面クリア",
+        help_en: "Clear screen",
+        channels: &["cli"],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: ".",
+        aliases: &["again"],
+        usage: "/.",
+        help: "前回メッセージ再送",
+        help_en: "Resend last message",
+        channels: &["cli"],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "m",
+        aliases: &["phrases"],
+        usage: "/m",
+        help: "よく使うフレーズ",
+        help_en: "Frequently used phrases",
+        channels: &["cli"],
+        handler: Handler::Frontend,
+    },
+    CommandSpec {
+        name: "omikuji",
+        aliases: &["fortune"],
+        usage: "/omikuji",
+        help: "毎日おみくじ（運試し）",
+        help_en: "Daily fortune",
+        channels: &["cli"],
+        handler: Handler::Frontend,
+    },
+];
+
+/// A parsed command: the matching spec plus its (trimmed) argument string.
+#[derive(Debug, Clone, Copy)]
+pub struct Invocation<'a> {
+    pub spec: &'static CommandSpec,
+    pub args: &'a str,
+}
+
+impl PartialEq for Invocation<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.spec.name == other.spec.name && self.args == other.args
+    }
+}
+
+impl Invocation<'_> {
+    pub fn name(&self) -> &'static str {
+        self.spec.name
+    }
+
+    /// Run a session handler. Returns `None` for front-end commands.
+    pub fn execute(&self, session: &mut Session, env: &CommandEnv<'_>) -> Option<String> {
+        match self.spec.handler {
+            Handler::Session(f) => Some(f(self.args, session, env)),
+            Handler::Frontend => None,
+        }
+    }
+}
+
+/// Look up a command by name or alias (without the leading `/`).
+pub fn find(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|c| c.matches(name))
+}
+
+/// Parse `/name args` into an invocation. Accepts Telegram-style `/name@bot`.
+pub fn parse(text: &str) -> Option<Invocation<'_>> {
+    let rest = text.trim().strip_prefix('/')?;
+    let (name, args) = match rest.find(char::is_whitespace) {
+        Some(pos) => (&rest[..pos], rest[pos..].trim()),
+        None => (rest, ""),
+    };
+    let name = name.split('@').next().unwrap_or(name);
+    if name.is_empty() {
+        return None;
+    }
+    find(name).map(|spec| Invocation { spec, args })
+}
+
+/// Commands offered on `channel`, in help order.
+pub fn available_in(channel: &str) -> impl Iterator<Item = &'static CommandSpec> + '_ {
+    COMMANDS.iter().filter(move |c| c.is_available(channel))
+}
+
+/// Help text listing the commands available on `channel`.
+pub fn help_text(channel: &str, locale: Locale) -> String {
+    let mut lines = vec![locale.pick("📋 利用可能なコマンド:", "📋 Available commands:").to_string(), String::new()];
+    for spec in available_in(channel) {
+        lines.push(format!("{} — {}", spec.usage, locale.pick(spec.help, spec.help_en)));
+    }
+    lines.join("\n")
+}
+
+// ---------------------------------------------------------------------------
+// Session handlers
+// ---------------------------------------------------------------------------
+
+fn cmd_help(_args: &str, session: &mut Session, env: &CommandEnv<'_>) -> String {
+    help_text(env.channel, session.locale().unwrap_or_default())
+}
+
+fn cmd_reset(_args: &str, session: &mut Session, _env: &CommandEnv<'_>) -> String {
+    let n = session.messages.len();
+    session.clear();
+    format!("🔄 会話をリセットしました（{}件のメッセージを削除）。", n)
+}
+
+/// Model that will answer the next message (session override first).
+fn active_model<'a>(session: &'a Session, env: &CommandEnv<'a>) -> &'a str {
+    session.model_override().unwrap_or(env.model)
+}
+
+fn cmd_model(args: &str, session: &mut Session, env: &CommandEnv<'_>) -> String {
+    if args.is_empty() {
+        return match session.model_override() {
+            Some(model) => format!("🤖 使用中のモデル: {}（このセッションで固定中。/model reset で解除）", model),
+            None => format!("🤖 使用中のモデル: {}（デフォルト）", env.model),
+        };
+    }
+    if args.eq_ignore_ascii_case("reset") {
+        session.set_model_override(None);
+        return format!("🤖 モデル指定を解除しました。デフォルト（{}）に戻ります。", env.model);
+    }
+    // Canonicalize known names ("claude-sonnet" → full id); unknown ids pass through as-is.
+    let model = lookup_model(args).map(|p| p.model).unwrap_or(args);
+    session.set_model_override(Some(model));
+    format!("🤖 このセッションのモデルを {} に切り替えました。", model)
+}
+
+fn cmd_agent(args: &str, session: &mut Session, env: &CommandEnv<'_>) -> String {
+    if args.is_empty() {
+        return match (session.agent_override(), env.agent) {
+            (Some(agent), _) => format!("🧭 使用中のエージェント: {}（このセッションで固定中。/agent reset で解除）", agent),
+            (None, Some(agent)) => format!("🧭 使用中のエージェント: {}", agent),
+            (None, None) => "🧭 エージェント: 自動選択".to_string(),
+        };
+    }
+    if args.eq_ignore_ascii_case("reset") {
+        session.set_agent_override(None);
+        return "🧭 エージェント指定を解除しました。自動選択に戻ります。".to_string();
+    }
+    let id = args.trim_start_matches('@').to_lowercase();
+    if env.agents.is_empty() {
+        return "このチャネルではエージェントを切り替えられません。".to_string();
+    }
+    if !env.agents.contains(&id.as_str()) {
+        return format!("不明なエージェントです: {}\n利用可能: {}", id, env.agents.join(", "));
+    }
+    session.set_agent_override(Some(&id));
+    format!("🧭 このセッションのエージェントを {} に切り替えました。", id)
+}
+
+fn cmd_usage(_args: &str, session: &mut Session, env: &CommandEnv<'_>) -> String {
+    let tokens: usize = session.messages.iter().map(|m| estimate_tokens(&m.content)).sum();
+    let user_turns = session.messages.iter().filter(|m| m.role == "user").count();
+    let mut lines = vec![
+        "📈 セッション使用状況:".to_string(),
+        format!("  メッセージ: {}件（ユーザー発言 {}件）", session.messages.len(), user_turns),
+        format!("  履歴トークン（推定）: ~{}", tokens),
+        format!("  ピン留め: {}/{}", session.pins().len(), MAX_PINS),
+        format!("  モデル: {}", active_model(session, env)),
+    ];
+    let recorded = session.recorded_usage();
+    if recorded.total_tokens > 0 {
+        lines.push(format!(
+            "  LLMトークン（記録済み）: 入力 {} / 出力 {}",
+            recorded.prompt_tokens, recorded.completion_tokens
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Default and maximum number of answers `/history` shows.
+const HISTORY_DEFAULT: usize = 5;
+const HISTORY_MAX: usize = 20;
+
+fn cmd_history(args: &str, session: &mut Session, _env: &CommandEnv<'_>) -> String {
+    let count = args.parse::<usize>().unwrap_or(HISTORY_DEFAULT).clamp(1, HISTORY_MAX);
+    let answers: Vec<usize> = session
+        .messages
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.role == "assistant")
+        .map(|(i, _)| i)
+        .collect();
+    if answers.is_empty() {
+        return "📜 まだ応答はありません。".to_string();
+    }
+
+    let mut lines = vec!["📜 最近の応答:".to_string()];
+    for &i in &answers[answers.len().saturating_sub(count)..] {
+        let answer = &session.messages[i];
+        if let Some(question) = session.messages[..i].iter().rev().find(|m| m.role == "user") {
+            lines.push(format!("  Q: {}", truncate_string(&question.content.replace('\n', " "), 60, "…")));
+        }
+        lines.push(format!("  A: {}", truncate_string(&answer.content.replace('\n', " "), 80, "…")));
+        if let Some(meta) = answer.turn_meta() {
+            lines.push(format!("     ⓘ {}", meta.summary()));
+        }
+    }
+    lines.join("\n")
+}
+
+fn cmd_lang(args: &str, session: &mut Session, _env: &CommandEnv<'_>) -> String {
+    if args.is_empty() {
+        let locale = session.locale().unwrap_or_default();
+        let mode = if session.locale_is_manual() {
+            locale.pick("固定", "fixed")
+        } else {
+            locale.pick("自動検出", "auto-detected")
+        };
+        return match locale {
+            Locale::Ja => format!("🌐 応答言語: 日本語（{}）", mode),
+            Locale::En => format!("🌐 Response language: English ({})", mode),
+        };
+    }
+    if args.eq_ignore_ascii_case("auto") {
+        session.set_locale(None);
+        return session
+            .locale()
+            .unwrap_or_default()
+            .pick("🌐 言語の自動検出に戻しました。", "🌐 Language auto-detection is back on.")
+            .to_string();
+    }
+    match Locale::parse(args) {
+        Some(locale) => {
+            session.set_locale(Some(locale));
+            locale
+                .pick("🌐 これから日本語で応答します。", "🌐 I'll reply in English from now on.")
+                .to_string()
+        }
+        None => "使い方 / Usage: /lang [ja|en|auto]".to_string(),
+    }
+}
+
+fn cmd_length(args: &str, session: &mut Session, _env: &CommandEnv<'_>) -> String {
+    if args.is_empty() {
+        return match session.length_override() {
+            Some(LengthPreference::Chars(n)) => format!("📏 応答の長さ: {}文字以内（/length reset で解除）", n),
+            Some(pref) => format!("📏 応答の長さ: {}（/length reset で解除）", pref.as_string()),
+            None => "📏 応答の長さ: デフォルト（デバイス・チャネルに応じて自動）".to_string(),
+        };
+    }
+    if args.eq_ignore_ascii_case("reset") {
+        session.set_length_override(None);
+        return "📏 応答の長さをデフォルトに戻しました。".to_string();
+    }
+    match LengthPreference::parse(args) {
+        Some(pref) => {
+            session.set_length_override(Some(pref));
+            match pref {
+                LengthPreference::Brief => "📏 これから短めに答えます。".to_string(),
+                LengthPreference::Normal => "📏 標準の長さで答えます。".to_string(),
+                LengthPreference::Long => "📏 これから詳しめに答えます。".to_string(),
+                LengthPreference::Chars(n) => format!("📏 これから{}文字以内で答えます。", n),
+            }
+        }
+        None => "使い方: /length [brief|normal|long|文字数|reset]".to_string(),
+    }
+}
+
+fn cmd_tone(args: &str, session: &mut Session, _env: &CommandEnv<'_>) -> String {
+    if args.is_empty() {
+        return match session.tone_override() {
+            Some(tone) => format!("🎭 口調: {}（/tone reset で解除）", tone),
+            None => "🎭 口調: デフォルト".to_string(),
+        };
+    }
+    if args.eq_ignore_ascii_case("reset") {
+        session.set_tone_override(None);
+        return "🎭 口調をデフォルトに戻しました。".to_string();
+    }
+    session.set_tone_override(Some(args));
+    format!("🎭 これから「{}」の口調で答えます。", args)
+}
+
+fn cmd_incognito(args: &str, session: &mut Session, _env: &CommandEnv<'_>) -> String {
+    let locale = session.locale().unwrap_or_default();
+    match Switch::parse(args) {
+        Switch::On => {
+            session.set_incognito(true);
+            locale
+                .pick(
+                    "🕶 シークレットモードをオンにしました。これからの会話は会話履歴に保存されません（/incognito off で解除）。",
+                    "🕶 Incognito is on. From now on the conversation isn't saved to the chat history (/incognito off to stop).",
+                )
+                .to_string()
+        }
+        Switch::Off => {
+            session.set_incognito(false);
+            locale.pick("🕶 シークレットモードをオフにしました。", "🕶 Incognito is off.").to_string()
+        }
+        // Front-ends answer `/incognito <message>` themselves; this is the fallback
+        Switch::Message(_) => locale
+            .pick("🕶 使い方: /incognito [on|off|メッセージ]", "🕶 Usage: /incognito [on|off|message]")
+            .to_string(),
+    }
+}
+
+fn cmd_timezone(args: &str, session: &mut Session, _env: &CommandEnv<'_>) -> String {
+    let locale = session.locale().unwrap_or_default();
+    if args.is_empty() {
+        return match session.timezone() {
+            Some(tz) => format!("{}{}", locale.pick("🕘 タイムゾーン: ", "🕘 Timezone: "), tz),
+            None => locale
+                .pick("🕘 タイムゾーン: 未設定（Asia/Tokyo として扱います）", "🕘 Timezone: not set (UTC is used)")
+                .to_string(),
+        };
+    }
+    if args.eq_ignore_ascii_case("reset") {
+        session.set_timezone(None);
+        return locale.pick("🕘 タイムゾーン設定を解除しました。", "🕘 Timezone cleared.").to_string();
+    }
+    match crate::util::tz::parse(args) {
+        Some(zone) => {
+            session.set_timezone(Some(args));
+            let now = chrono::Utc::now().with_timezone(&zone);
+            format!(
+                "{}{} (UTC{})",
+                locale.pick("🕘 タイムゾーンを設定しました: ", "🕘 Timezone set: "),
+                args,
+                now.format("%:z")
+            )
+        }
+        None => format!("{}: {}", locale.pick("不明なタイムゾーンです", "Unknown timezone"), args),
+    }
+}
+
+fn cmd_pin(args: &str, session: &mut Session, _env: &CommandEnv<'_>) -> String {
+    if args.is_empty() {
+        return "使い方: /pin <覚えておいてほしい内容・ファイルパス・URL>".to_string();
+    }
+    match session.add_pin(args) {
+        Some(pin) => format!("📌 ピン留めしました [{}] {}", pin.id, pin.content),
+        None => format!("ピン留めできませんでした（上限{}件）。/unpin で整理してください。", MAX_PINS),
+    }
+}
+
+fn cmd_pins(_args: &str, session: &mut Session, _env: &CommandEnv<'_>) -> String {
+    let pins = session.pins();
+    if pins.is_empty() {
+        return "ピン留めはありません。/pin <内容> で追加できます。".to_string();
+    }
+    let mut lines = vec!["📌 ピン留め一覧:".to_string()];
+    for pin in &pins {
+        lines.push(format!("  [{}] ({}) {}", pin.id, pin.kind.label(), pin.content));
+    }
+    lines.push("解除: /unpin <番号> または /unpin all".to_string());
+    lines.join("\n")
+}
+
+fn cmd_unpin(args: &str, session: &mut Session, _env: &CommandEnv<'_>) -> String {
+    if args.eq_ignore_ascii_case("all") {
+        return format!("{}件のピン留めを解除しました。", session.clear_pins());
+    }
+    match args.trim_start_matches('#').parse::<u32>() {
+        Ok(id) if session.remove_pin(id) => format!("ピン留め [{}] を解除しました。", id),
+        Ok(id) => format!("ピン留め [{}] は見つかりません。/pins で確認してください。", id),
+        Err(_) => "使い方: /unpin <番号|all>".to_string(),
+    }
+}
+
+/// Split `rest` into a known contact's name (longest match) and what follows.
+fn split_contact<'a>(session: &Session, rest: &'a str) -> Option<(Contact, &'a str)> {
+    let mut ends: Vec<usize> = rest.match_indices(char::is_whitespace).map(|(i, _)| i).collect();
+    ends.push(rest.len());
+    ends.into_iter().rev().find_map(|end| session.find_contact(&rest[..end]).map(|c| (c, rest[end..].trim())))
+}
+
+fn cmd_contacts(args: &str, session: &mut Session, _env: &CommandEnv<'_>) -> String {
+    let locale = session.locale().unwrap_or_default();
+    let usage = locale.pick(
+        "使い方: /contacts add <名前> [line:ID tg:ID メール…] ・ /contacts link <名前> <ID> ・ /contacts unlink <名前> <ID> ・ \
+         /contacts alias <名前> <別名> ・ /contacts note <名前> <メモ> ・ /contacts remove <名前>",
+        "Usage: /contacts add <name> [line:ID tg:ID email…] · /contacts link <name> <id> · /contacts unlink <name> <id> · \
+         /contacts alias <name> <alias> · /contacts note <name> <text> · /contacts remove <name>",
+    );
+    let (sub, rest) = args.split_once(char::is_whitespace).map(|(a, b)| (a, b.trim())).unwrap_or((args, ""));
+    let sub = sub.to_lowercase();
+    let not_found = |name: &str| format!("{}: {}", locale.pick("連絡先が見つかりません", "No such contact"), name);
+    let result = match sub.as_str() {
+        "" => {
+            let contacts = session.contacts();
+            if contacts.is_empty() {
+                return format!("{}\n{}", locale.pick("連絡先はありません。", "No contacts yet."), usage);
+            }
+            let mut lines = vec![locale.pick("📇 連絡先:", "📇 Contacts:").to_string()];
+            lines.extend(contacts.iter().map(|c| format!("  {}", c.summary())));
+            return lines.join("\n");
+        }
+        "add" => {
+            let (identities, name): (Vec<&str>, Vec<&str>) =
+                rest.split_whitespace().partition(|w| normalize_identity(w).is_some());
+            let identities: Vec<String> = identities.into_iter().map(str::to_string).collect();
+            session.upsert_contact(&name.join(" "), &identities, &[], None)
+        }
+        "link" | "unlink" | "alias" => {
+            let Some((name, value)) = rest.rsplit_once(char::is_whitespace) else {
+                return usage.to_string();
+            };
+            match sub.as_str() {
+                "link" => session.upsert_contact(name, &[value.to_string()], &[], None),
+                "alias" => match session.find_contact(name) {
+                    Some(c) => session.upsert_contact(&c.name, &[], &[value.to_string()], None),
+                    None => return not_found(name),
+                },
+                _ => session.unlink_contact(name, value),
+            }
+        }
+        "note" => match split_contact(session, rest) {
+            Some((c, note)) => session.upsert_contact(&c.name, &[], &[], Some(note)),
+            None => return not_found(rest),
+        },
+        "remove" | "rm" | "delete" => {
+            return if session.remove_contact(rest) {
+                format!("{}: {}", locale.pick("連絡先を削除しました", "Contact removed"), rest)
+            } else {
+                not_found(rest)
+            };
+        }
+        _ => {
+            return match session.find_contact(args) {
+                Some(c) => format!("📇 {}", c.summary()),
+                None => format!("{}\n{}", not_found(args), usage),
+            };
+        }
+    };
+    match result {
+        Ok(contact) => format!("📇 {}", contact.summary()),
+        Err(e) => format!("{}: {}", locale.pick("連絡先を更新できませんでした", "Couldn't update the contact"), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::turn::TurnMeta;
+
+    fn env() -> CommandEnv<'static> {
+        CommandEnv { channel: "web", model: "test-model", agent: None, agents: &["assistant", "coder"] }
+    }
+
+    #[test]
+    fn test_parse() {
+        let inv = parse("  /pin https://docs.rs  ").unwrap();
+        assert_eq!(inv.name(), "pin");
+        assert_eq!(inv.args, "https://docs.rs");
+        assert_eq!(parse("/HELP").unwrap().name(), "help");
+        assert_eq!(parse("/h").unwrap().name(), "help");
+        assert_eq!(parse("/usage@chatweb_bot").unwrap().name(), "usage");
+        assert!(parse("/unknown").is_none());
+        assert!(parse("/").is_none());
+        assert!(parse("hello /reset").is_none());
+    }
+
+    #[test]
+    fn test_channel_availability() {
+        let clear = find("c").unwrap();
+        assert!(clear.is_available("cli"));
+        assert!(!clear.is_available("line"));
+        assert!(find("reset").unwrap().is_available("line"));
+        assert!(help_text("cli", Locale::Ja).contains("/omikuji"));
+        assert!(!help_text("telegram", Locale::Ja).contains("/omikuji"));
+        assert!(help_text("telegram", Locale::Ja).contains("/reset"));
+        assert!(help_text("web", Locale::En).contains("Pin a fact"));
+    }
+
+    #[test]
+    fn test_reset_and_usage() {
+        let mut session = Session::new("test");
+        session.add_message("user", "hello");
+        session.add_message("assistant", "hi");
+        session.add_pin("keep me");
+
+        let usage = parse("/usage").unwrap().execute(&mut session, &env()).unwrap();
+        assert!(usage.contains("メッセージ: 2件"));
+
+        let reply = parse("/reset").unwrap().execute(&mut session, &env()).unwrap();
+        assert!(reply.contains("2件"));
+        assert!(session.messages.is_empty());
+        assert_eq!(session.pins().len(), 1);
+    }
+
+    #[test]
+    fn test_history() {
+        let mut session = Session::new("test");
+        let e = env();
+        assert!(parse("/history").unwrap().execute(&mut session, &e).unwrap().contains("まだ応答はありません"));
+
+        session.add_message("user", "What's the weather?");
+        session.add_message("assistant", "Sunny in Tokyo.");
+        let mut meta = TurnMeta::new("gemini-2.5-flash");
+        meta.prompt_tokens = 300;
+        meta.completion_tokens = 20;
+        meta.tools = vec!["web_search".into()];
+        session.set_turn_meta(&meta);
+        session.add_message("user", "Thanks");
+        session.add_message("assistant", "You're welcome!");
+
+        let shown = parse("/history").unwrap().execute(&mut session, &e).unwrap();
+        assert!(shown.contains("Q: What's the weather?"), "{}", shown);
+        assert!(shown.contains("ⓘ gemini-2.5-flash · 300→20 tokens · web_search"), "{}", shown);
+        let last = parse("/history 1").unwrap().execute(&mut session, &e).unwrap();
+        assert!(!last.contains("weather") && last.contains("You're welcome!"));
+        assert!(parse("/usage").unwrap().execute(&mut session, &e).unwrap().contains("入力 300 / 出力 20"));
+    }
+
+    #[test]
+    fn test_pin_commands() {
+        let mut session = Session::new("test");
+        let e = env();
+        assert!(parse("/pin").unwrap().execute(&mut session, &e).unwrap().contains("使い方"));
+        assert!(parse("/pin ./Cargo.toml").unwrap().execute(&mut session, &e).unwrap().contains("[1]"));
+        assert!(parse("/pins").unwrap().execute(&mut session, &e).unwrap().contains("(file) ./Cargo.toml"));
+        assert!(parse("/unpin 1").unwrap().execute(&mut session, &e).unwrap().contains("解除しました"));
+        assert!(parse("/unpin x").unwrap().execute(&mut session, &e).unwrap().contains("使い方"));
+    }
+
+    #[test]
+    fn test_contacts_command() {
+        let mut session = Session::new("test");
+        let e = env();
+        let run = |s: &mut Session, text: &str| parse(text).unwrap().execute(s, &e).unwrap();
+        assert!(run(&mut session, "/contacts").contains("連絡先はありません"));
+        assert!(run(&mut session, "/contacts add 山田 太郎 line:U1 taro@example.com").contains("山田 太郎 — line:U1, email:taro@example.com"));
+        run(&mut session, "/contacts alias 山田 太郎 太郎");
+        run(&mut session, "/contacts link 太郎さん tg:42");
+        run(&mut session, "/contacts note 山田 太郎 同僚、平日のみ");
+        let shown = run(&mut session, "/contacts 太郎");
+        assert!(shown.contains("(太郎)") && shown.contains("telegram:42") && shown.contains("同僚、平日のみ"), "{}", shown);
+        assert!(run(&mut session, "/contacts link 花子 line:U1").contains("already belongs to 山田 太郎"));
+        assert!(run(&mut session, "/contacts remove 太郎").contains("削除しました"));
+        assert!(session.contacts().is_empty());
+    }
+
+    #[test]
+    fn test_model_override() {
+        let mut session = Session::new("test");
+        let e = env();
+        let run = |s: &mut Session, text: &str| parse(text).unwrap().execute(s, &e).unwrap();
+        assert!(run(&mut session, "/model").contains("test-model（デフォルト）"));
+        run(&mut session, "/model claude-sonnet-4-6");
+        assert_eq!(session.model_override(), Some("claude-sonnet-4-6"));
+        assert!(run(&mut session, "/model").contains("固定中"));
+        assert!(run(&mut session, "/usage").contains("モデル: claude-sonnet-4-6"));
+        run(&mut session, "/model my-local-model");
+        assert_eq!(session.model_override(), Some("my-local-model"));
+        run(&mut session, "/model reset");
+        assert_eq!(session.model_override(), None);
+    }
+
+    #[test]
+    fn test_agent_override() {
+        let mut session = Session::new("test");
+        let e = env();
+        let run = |s: &mut Session, text: &str| parse(text).unwrap().execute(s, &e).unwrap();
+        assert!(run(&mut session, "/agent").contains("自動選択"));
+        assert!(run(&mut session, "/agent pirate").contains("不明なエージェント"));
+        assert_eq!(session.agent_override(), None);
+        run(&mut session, "/agent @Coder");
+        assert_eq!(session.agent_override(), Some("coder"));
+        run(&mut session, "/agent reset");
+        assert_eq!(session.agent_override(), None);
+
+        let no_agents = CommandEnv { agents: &[], ..env() };
+        assert!(parse("/agent coder").unwrap().execute(&mut session, &no_agents).unwrap().contains("切り替えられません"));
+    }
+
+    #[test]
+    fn test_lang_command() {
+        let mut session = Session::new("test");
+        let e = env();
+        let run = |s: &mut Session, text: &str| parse(text).unwrap().execute(s, &e).unwrap();
+        assert!(run(&mut session, "/lang").contains("自動検出"));
+        assert!(run(&mut session, "/lang en").contains("English"));
+        assert_eq!(session.locale(), Some(Locale::En));
+        assert!(session.locale_is_manual());
+        assert!(run(&mut session, "/help").contains("Available commands"));
+        run(&mut session, "/lang auto");
+        assert!(!session.locale_is_manual());
+        assert!(run(&mut session, "/lang xx").contains("Usage"));
+    }
+
+    #[test]
+    fn test_length_and_tone_commands() {
+        let mut session = Session::new("test");
+        let e = env();
+        let run = |s: &mut Session, text: &str| parse(text).unwrap().execute(s, &e).unwrap();
+        assert!(run(&mut session, "/length").contains("デフォルト"));
+        assert!(run(&mut session, "/length brief").contains("短め"));
+        assert_eq!(session.length_override(), Some(LengthPreference::Brief));
+        assert!(run(&mut session, "/length 150").contains("150文字以内"));
+        assert!(run(&mut session, "/length huge").contains("使い方"));
+        assert_eq!(session.length_override(), Some(LengthPreference::Chars(150)));
+        run(&mut session, "/length reset");
+        assert_eq!(session.length_override(), None);
+
+        run(&mut session, "/tone 関西弁で");
+        assert_eq!(session.tone_override(), Some("関西弁で"));
+        run(&mut session, "/tone reset");
+        assert_eq!(session.tone_override(), None);
+    }
+
+    #[test]
+    fn test_timezone_command() {
+        let mut session = Session::new("test");
+        let e = env();
+        let run = |s: &mut Session, text: &str| parse(text).unwrap().execute(s, &e).unwrap();
+        assert!(run(&mut session, "/timezone").contains("未設定"));
+        // EST or EDT depending on today's date
+        assert!(run(&mut session, "/tz America/New_York").contains("UTC-0"));
+        assert!(run(&mut session, "/tz UTC+9").contains("UTC+09:00"));
+        assert_eq!(session.timezone(), Some("UTC+9"));
+        run(&mut session, "/tz America/New_York");
+        assert_eq!(session.timezone(), Some("America/New_York"));
+        assert!(run(&mut session, "/timezone Mars/Base").contains("不明"));
+        run(&mut session, "/timezone reset");
+        assert_eq!(session.timezone(), None);
+    }
+
+    #[test]
+    fn test_incognito_command() {
+        let mut session = Session::new("test");
+        let e = env();
+        let run = |s: &mut Session, text: &str| parse(text).unwrap().execute(s, &e).unwrap();
+        assert!(run(&mut session, "/incognito").contains("オン"));
+        assert!(session.incognito());
+        run(&mut session, "/private off");
+        assert!(!session.incognito());
+        assert!(run(&mut session, "/incognito hello").contains("/incognito"));
+        assert!(!session.incognito());
+    }
+
+    #[test]
+    fn test_frontend_commands_not_executed() {
+        let mut session = Session::new("test");
+        assert!(parse("/share").unwrap().execute(&mut session, &env()).is_none());
+        assert!(parse("/reminders").unwrap().execute(&mut session, &env()).is_none());
+    }
+}
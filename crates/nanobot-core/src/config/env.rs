@@ -0,0 +1,347 @@
+//! Environment variable overrides for the config.
+//!
+//! [`ENV_VARS`] maps the well-known variables (`TELEGRAM_BOT_TOKEN`,
+//! `GROQ_API_KEY`, ...) to config fields; it drives both
+//! [`super::load_config_from_env`] and `chatweb config env-vars`. Any other
+//! field can be set as `NANOBOT__<PATH>`, the camelCase path written in
+//! SCREAMING_SNAKE_CASE with `__` between levels, e.g.
+//! `NANOBOT__HTTP__LLM_TIMEOUT_SECS=300`.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::Config;
+use Transform::{Flag, List, Number, Text};
+
+/// Prefix of the generic per-field overrides.
+pub const FIELD_PREFIX: &str = "NANOBOT__";
+
+/// How a variable's text becomes a config value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    Text,
+    /// Comma-separated list.
+    List,
+    Number,
+    /// `1`/`true` or `0`/`false`.
+    Flag,
+}
+
+/// One well-known variable.
+#[derive(Debug, Clone, Copy)]
+pub struct EnvVar {
+    pub name: &'static str,
+    /// Dotted camelCase path, as in config.json.
+    pub field: &'static str,
+    pub transform: Transform,
+    /// Flag set to `true` when the variable is present (e.g. the channel's `enabled`).
+    pub enables: Option<&'static str>,
+    pub description: &'static str,
+}
+
+const fn var(name: &'static str, field: &'static str, transform: Transform, description: &'static str) -> EnvVar {
+    EnvVar { name, field, transform, enables: None, description }
+}
+
+const fn enabling(name: &'static str, field: &'static str, enables: &'static str, description: &'static str) -> EnvVar {
+    EnvVar { name, field, transform: Transform::Text, enables: Some(enables), description }
+}
+
+pub const ENV_VARS: &[EnvVar] = &[
+    // Providers
+    var("ANTHROPIC_API_KEY", "providers.anthropic.apiKey", Text, "Anthropic API key"),
+    var("OPENAI_API_KEY", "providers.openai.apiKey", Text, "OpenAI API key"),
+    var("OPENAI_API_BASE", "providers.openai.apiBase", Text, "OpenAI-compatible endpoint"),
+    var("OPENROUTER_API_KEY", "providers.openrouter.apiKey", Text, "OpenRouter API key"),
+    var("DEEPSEEK_API_KEY", "providers.deepseek.apiKey", Text, "DeepSeek API key"),
+    var("GROQ_API_KEY", "providers.groq.apiKey", Text, "Groq API key"),
+    var("GEMINI_API_KEY", "providers.gemini.apiKey", Text, "Google Gemini API key"),
+    var("ZHIPU_API_KEY", "providers.zhipu.apiKey", Text, "Zhipu (GLM) API key"),
+    var("MOONSHOT_API_KEY", "providers.moonshot.apiKey", Text, "Moonshot (Kimi) API key"),
+    var("KIMI_API_KEY", "providers.moonshot.apiKey", Text, "Alias of MOONSHOT_API_KEY"),
+    var("VLLM_API_KEY", "providers.vllm.apiKey", Text, "vLLM server API key"),
+    var("VLLM_API_BASE", "providers.vllm.apiBase", Text, "vLLM server URL"),
+    // Agent
+    var("NANOBOT_MODEL", "agents.defaults.model", Text, "Default model"),
+    var("NANOBOT_WORKSPACE", "agents.defaults.workspace", Text, "Workspace directory"),
+    var("NANOBOT_MAX_TOKENS", "agents.defaults.maxTokens", Number, "Max tokens per reply"),
+    var("NANOBOT_TEMPERATURE", "agents.defaults.temperature", Number, "Sampling temperature"),
+    var("NANOBOT_TIMEZONE", "agents.defaults.timezone", Text, "IANA timezone for schedules"),
+    var("NANOBOT_PROMPT_MAX_TOKENS", "agents.prompt.maxTokens", Number, "System prompt token budget"),
+    var("NANOBOT_PROMPT_DISABLE", "agents.prompt.disabled", List, "System prompt sections to leave out"),
+    // Channels
+    enabling("TELEGRAM_BOT_TOKEN", "channels.telegram.token", "channels.telegram.enabled", "Telegram bot token"),
+    var("TELEGRAM_ALLOW_FROM", "channels.telegram.allowFrom", List, "Allowed Telegram user IDs"),
+    var("TELEGRAM_PROXY", "channels.telegram.proxy", Text, "Proxy for the Telegram API"),
+    var("LINE_CHANNEL_SECRET", "channels.line.channelSecret", Text, "LINE channel secret"),
+    enabling("LINE_CHANNEL_ACCESS_TOKEN", "channels.line.channelAccessToken", "channels.line.enabled", "LINE channel access token"),
+    var("LINE_ALLOW_FROM", "channels.line.allowFrom", List, "Allowed LINE user IDs"),
+    enabling("DISCORD_BOT_TOKEN", "channels.discord.token", "channels.discord.enabled", "Discord bot token"),
+    var("DISCORD_ALLOW_FROM", "channels.discord.allowFrom", List, "Allowed Discord user IDs"),
+    var("SLACK_APP_TOKEN", "channels.slack.appToken", Text, "Slack app-level token (socket mode)"),
+    enabling("SLACK_BOT_TOKEN", "channels.slack.botToken", "channels.slack.enabled", "Slack bot token"),
+    var("SLACK_ALLOW_FROM", "channels.slack.allowFrom", List, "Allowed Slack user IDs"),
+    var("FEISHU_APP_ID", "channels.feishu.appId", Text, "Feishu app ID"),
+    enabling("FEISHU_APP_SECRET", "channels.feishu.appSecret", "channels.feishu.enabled", "Feishu app secret"),
+    var("FEISHU_ENCRYPT_KEY", "channels.feishu.encryptKey", Text, "Feishu event encrypt key"),
+    var("FEISHU_VERIFICATION_TOKEN", "channels.feishu.verificationToken", Text, "Feishu verification token"),
+    enabling("WHATSAPP_BRIDGE_URL", "channels.whatsapp.bridgeUrl", "channels.whatsapp.enabled", "WhatsApp bridge WebSocket URL"),
+    var("SIGNAL_ENDPOINT", "channels.signal.endpoint", Text, "signal-cli REST endpoint"),
+    enabling("SIGNAL_PHONE", "channels.signal.phoneNumber", "channels.signal.enabled", "Signal phone number"),
+    enabling("IMESSAGE_BRIDGE_URL", "channels.imessage.bridgeUrl", "channels.imessage.enabled", "iMessage bridge URL"),
+    var("TEAMS_APP_ID", "channels.teams.appId", Text, "Microsoft Teams app ID"),
+    enabling("TEAMS_APP_PASSWORD", "channels.teams.appPassword", "channels.teams.enabled", "Microsoft Teams app password"),
+    enabling(
+        "GOOGLE_CHAT_SERVICE_ACCOUNT_KEY",
+        "channels.googleChat.serviceAccountKey",
+        "channels.googleChat.enabled",
+        "Google Chat service account key",
+    ),
+    var("GOOGLE_CHAT_WEBHOOK_TOKEN", "channels.googleChat.webhookToken", Text, "Google Chat webhook token"),
+    var("MATRIX_HOMESERVER", "channels.matrix.homeserver", Text, "Matrix homeserver URL"),
+    var("MATRIX_USER_ID", "channels.matrix.userId", Text, "Matrix user ID"),
+    enabling("MATRIX_ACCESS_TOKEN", "channels.matrix.accessToken", "channels.matrix.enabled", "Matrix access token"),
+    enabling("ZALO_BOT_TOKEN", "channels.zalo.botToken", "channels.zalo.enabled", "Zalo bot token"),
+    var("ZALO_SECRET_TOKEN", "channels.zalo.secretToken", Text, "Zalo webhook secret"),
+    // Gateway
+    var("GATEWAY_API_TOKENS", "gateway.apiTokens", List, "Bearer tokens for the HTTP API"),
+    var("GATEWAY_ALLOWED_IPS", "gateway.allowedIps", List, "Allowed client IPs/CIDRs"),
+    var("GATEWAY_LISTEN", "gateway.listen", List, "Listen addresses"),
+    var("GATEWAY_TLS_CERT", "gateway.tlsCert", Text, "TLS certificate path"),
+    var("GATEWAY_TLS_KEY", "gateway.tlsKey", Text, "TLS private key path"),
+    var("GATEWAY_ACME_DOMAINS", "gateway.acme.domains", List, "Domains for Let's Encrypt certificates"),
+    var("GATEWAY_ACME_EMAIL", "gateway.acme.email", Text, "Let's Encrypt contact email"),
+    var("GATEWAY_PUBLIC_URL", "gateway.publicUrl", Text, "Public base URL for webhooks"),
+    // Tools and networking
+    var("BRAVE_API_KEY", "tools.web.search.apiKey", Text, "Brave Search API key"),
+    var("TAVILY_API_KEY", "tools.web.search.tavilyApiKey", Text, "Tavily API key"),
+    var("SEARXNG_URL", "tools.web.search.searxngUrl", Text, "SearxNG base URL"),
+    var("NANOBOT_HTTP_PROXY", "http.proxy", Text, "Proxy for all outbound requests"),
+    var("NANOBOT_CONFIG_STRICT", "strict", Flag, "Reject config files with unknown keys"),
+];
+
+/// `cfg` with the overrides found in `vars` applied: first [`ENV_VARS`],
+/// then `NANOBOT__*` fields. A value that doesn't fit its field is skipped
+/// with a warning; the other overrides still apply.
+pub fn apply(cfg: Config, vars: &HashMap<String, String>) -> Config {
+    let Ok(mut root) = serde_json::to_value(&cfg) else { return cfg };
+
+    for var in ENV_VARS {
+        let Some(raw) = vars.get(var.name) else { continue };
+        let Some(value) = convert(var.transform, raw) else {
+            tracing::warn!("Ignoring {}: expected {:?}, got {:?}", var.name, var.transform, raw);
+            continue;
+        };
+        let mut updated = root.clone();
+        set_path(&mut updated, &split(var.field), value);
+        if let Some(flag) = var.enables {
+            set_path(&mut updated, &split(flag), Value::Bool(true));
+        }
+        accept(&mut root, updated, var.name);
+    }
+
+    let mut fields: Vec<_> = vars.iter().filter(|(name, _)| name.starts_with(FIELD_PREFIX)).collect();
+    fields.sort();
+    for (name, raw) in fields {
+        let Some(path) = resolve_path(&root, &name[FIELD_PREFIX.len()..]) else {
+            tracing::warn!("Ignoring {}: no such config field", name);
+            continue;
+        };
+        let current = path.iter().try_fold(&root, |v, key| v.get(key.as_str()));
+        let value = field_value(current, raw);
+        let mut updated = root.clone();
+        set_path(&mut updated, &path.iter().map(String::as_str).collect::<Vec<_>>(), value);
+        accept(&mut root, updated, name);
+    }
+
+    serde_json::from_value(root).unwrap_or(cfg)
+}
+
+/// Keep `updated` if it still deserializes into a [`Config`].
+fn accept(root: &mut Value, updated: Value, name: &str) {
+    match serde_json::from_value::<Config>(updated.clone()) {
+        Ok(_) => *root = updated,
+        Err(e) => tracing::warn!("Ignoring {}: {}", name, e),
+    }
+}
+
+fn split(path: &str) -> Vec<&str> {
+    path.split('.').collect()
+}
+
+fn convert(transform: Transform, raw: &str) -> Option<Value> {
+    match transform {
+        Text => Some(Value::String(raw.to_string())),
+        List => Some(Value::Array(
+            raw.split(',').map(str::trim).filter(|s| !s.is_empty()).map(|s| Value::String(s.to_string())).collect(),
+        )),
+        Number => serde_json::from_str::<serde_json::Number>(raw.trim()).ok().map(Value::Number),
+        Flag => match raw.trim().to_lowercase().as_str() {
+            "1" | "true" | "yes" => Some(Value::Bool(true)),
+            "0" | "false" | "no" | "" => Some(Value::Bool(false)),
+            _ => None,
+        },
+    }
+}
+
+/// A generic override's value, shaped like the field's current one.
+fn field_value(current: Option<&Value>, raw: &str) -> Value {
+    match current {
+        Some(Value::String(_)) => Value::String(raw.to_string()),
+        Some(Value::Array(_)) if !raw.trim_start().starts_with('[') => convert(List, raw).unwrap_or_default(),
+        Some(Value::Bool(_)) => convert(Flag, raw).unwrap_or_else(|| Value::String(raw.to_string())),
+        _ => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+    }
+}
+
+/// Match `SOME__FIELD_NAME` segments to the keys of `root`, ignoring case
+/// and underscores.
+fn resolve_path(root: &Value, name: &str) -> Option<Vec<String>> {
+    let mut node = root;
+    let mut path = Vec::new();
+    for segment in name.split("__") {
+        let wanted = segment.replace('_', "").to_lowercase();
+        let (key, child) = node.as_object()?.iter().find(|(key, _)| key.to_lowercase() == wanted)?;
+        path.push(key.clone());
+        node = child;
+    }
+    Some(path)
+}
+
+fn set_path(root: &mut Value, path: &[&str], value: Value) {
+    let Some((last, parents)) = path.split_last() else { return };
+    let mut node = root;
+    for key in parents {
+        if !node[*key].is_object() {
+            node[*key] = Value::Object(Default::default());
+        }
+        node = &mut node[*key];
+    }
+    node[*last] = value;
+}
+
+/// `NANOBOT__...` name for a camelCase config path.
+pub fn field_var_name(path: &str) -> String {
+    let segments: Vec<String> = path
+        .split('.')
+        .map(|segment| {
+            let mut out = String::new();
+            for (i, c) in segment.chars().enumerate() {
+                if c.is_uppercase() && i > 0 {
+                    out.push('_');
+                }
+                out.push(c.to_ascii_uppercase());
+            }
+            out
+        })
+        .collect();
+    format!("{}{}", FIELD_PREFIX, segments.join("__"))
+}
+
+/// Dotted paths of every setting in `cfg` (leaves of its JSON form).
+pub fn field_paths(cfg: &Config) -> Vec<String> {
+    fn walk(value: &Value, path: String, out: &mut Vec<String>) {
+        match value.as_object() {
+            Some(map) if !map.is_empty() => {
+                for (key, child) in map {
+                    let child_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                    walk(child, child_path, out);
+                }
+            }
+            _ => out.push(path),
+        }
+    }
+    let mut out = Vec::new();
+    walk(&serde_json::to_value(cfg).unwrap_or_default(), String::new(), &mut out);
+    out.sort();
+    out
+}
+
+/// Listing for `chatweb config env-vars`: the well-known variables, and with
+/// `all` every `NANOBOT__` field override. `markdown` renders tables.
+pub fn render(all: bool, markdown: bool) -> String {
+    let mut out = String::new();
+    if markdown {
+        out.push_str("| Variable | Config field | Description |\n|----------|--------------|-------------|\n");
+    }
+    for var in ENV_VARS {
+        let description = match var.enables {
+            Some(flag) => format!("{} (also sets `{}`)", var.description, flag),
+            None => var.description.to_string(),
+        };
+        if markdown {
+            out.push_str(&format!("| `{}` | `{}` | {} |\n", var.name, var.field, description));
+        } else {
+            out.push_str(&format!("{:<34} {:<38} {}\n", var.name, var.field, description));
+        }
+    }
+    if all {
+        out.push_str(if markdown { "\n| Variable | Config field |\n|----------|--------------|\n" } else { "\n" });
+        for path in field_paths(&Config::default()) {
+            if markdown {
+                out.push_str(&format!("| `{}` | `{}` |\n", field_var_name(&path), path));
+            } else {
+                out.push_str(&format!("{:<60} {}\n", field_var_name(&path), path));
+            }
+        }
+    } else {
+        out.push_str(&format!(
+            "\nAny other setting: {}<PATH>, e.g. {}=300 (list them with --all)\n",
+            FIELD_PREFIX,
+            field_var_name("http.llmTimeoutSecs")
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_table_fields_exist() {
+        let paths = field_paths(&Config::default());
+        for var in ENV_VARS {
+            for field in [Some(var.field), var.enables].into_iter().flatten() {
+                assert!(paths.iter().any(|p| p == field), "{} targets unknown field {}", var.name, field);
+            }
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let cfg = apply(
+            Config::default(),
+            &vars(&[
+                ("GROQ_API_KEY", "gsk-1"),
+                ("TELEGRAM_BOT_TOKEN", "tg"),
+                ("GATEWAY_LISTEN", "0.0.0.0, ::"),
+                ("NANOBOT_PROMPT_MAX_TOKENS", "not a number"),
+                ("NANOBOT_MAX_TOKENS", "1.5"),
+                ("NANOBOT__HTTP__LLM_TIMEOUT_SECS", "300"),
+                ("NANOBOT__TOOLS__WEB__FETCH__RESPECT_ROBOTS", "false"),
+                ("NANOBOT__CHANNELS__SLACK__ALLOW_FROM", "U1,U2"),
+                ("NANOBOT__NO_SUCH__FIELD", "x"),
+            ]),
+        );
+        assert_eq!(cfg.providers.groq.api_key, "gsk-1");
+        assert!(cfg.channels.telegram.enabled);
+        assert_eq!(cfg.gateway.listen, vec!["0.0.0.0", "::"]);
+        assert_eq!(cfg.agents.prompt.max_tokens, Config::default().agents.prompt.max_tokens);
+        assert_eq!(cfg.agents.defaults.max_tokens, Config::default().agents.defaults.max_tokens);
+        assert_eq!(cfg.http.llm_timeout_secs, 300);
+        assert!(!cfg.tools.web.fetch.respect_robots);
+        assert_eq!(cfg.channels.slack.allow_from, vec!["U1", "U2"]);
+    }
+
+    #[test]
+    fn test_field_var_name() {
+        assert_eq!(field_var_name("http.llmTimeoutSecs"), "NANOBOT__HTTP__LLM_TIMEOUT_SECS");
+        assert_eq!(field_var_name("channels.googleChat.enabled"), "NANOBOT__CHANNELS__GOOGLE_CHAT__ENABLED");
+    }
+}
@@ -1,9 +1,14 @@
+pub mod env;
+pub mod profile;
+pub mod project;
 pub mod provider;
+pub mod validate;
 
 #[cfg(feature = "dynamodb-backend")]
 pub mod dynamo_provider;
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use crate::error::ConfigError;
@@ -18,6 +23,15 @@ pub struct Config {
     pub providers: ProvidersConfig,
     pub gateway: GatewayConfig,
     pub tools: ToolsConfig,
+    pub webhooks: WebhooksConfig,
+    pub voice: VoiceConfig,
+    pub termux: TermuxConfig,
+    pub kiosk: KioskConfig,
+    pub http: HttpConfig,
+    pub sync: SyncConfig,
+    /// Refuse to load a config with unknown keys instead of warning about
+    /// them (also `NANOBOT_CONFIG_STRICT=1`).
+    pub strict: bool,
 }
 
 
@@ -31,6 +45,11 @@ impl Config {
             return PathBuf::from("/tmp").join(".nanobot/workspace");
         }
 
+        // Profiles made by hand keep the default setting; give them their own workspace
+        if *path == AgentDefaults::default().workspace && profile::active() != profile::DEFAULT_PROFILE {
+            return profile::active_dir().join("workspace");
+        }
+
         if path.starts_with("~/") || path.starts_with("~\\") {
             if let Some(home) = dirs::home_dir() {
                 return home.join(&path[2..]);
@@ -40,7 +59,7 @@ impl Config {
     }
 
     /// Match a provider based on model name.
-    fn match_provider(&self, model: Option<&str>) -> Option<&ProviderConfig> {
+    pub(crate) fn match_provider(&self, model: Option<&str>) -> Option<&ProviderConfig> {
         let model = model
             .unwrap_or(&self.agents.defaults.model)
             .to_lowercase();
@@ -123,6 +142,15 @@ impl Config {
 #[derive(Default)]
 pub struct AgentsConfig {
     pub defaults: AgentDefaults,
+    pub prompt: PromptConfig,
+    pub response: ResponseConfig,
+    pub proactive: ProactiveConfig,
+    pub routing: RoutingConfig,
+    pub escalation: EscalationConfig,
+    pub handoff: HandoffConfig,
+    pub memory: MemoryConfig,
+    /// Post-processing of final replies, run in order (see `hooks`).
+    pub hooks: Vec<ResponseHookConfig>,
 }
 
 
@@ -134,6 +162,9 @@ pub struct AgentDefaults {
     pub max_tokens: u32,
     pub temperature: f64,
     pub max_tool_iterations: u32,
+    /// User timezone (`Asia/Tokyo`, `UTC+9`, ...) for the clock, reminders and
+    /// daily memory rollover; server local time when unset.
+    pub timezone: Option<String>,
 }
 
 impl Default for AgentDefaults {
@@ -144,10 +175,290 @@ impl Default for AgentDefaults {
             max_tokens: 8192,
             temperature: 0.7,
             max_tool_iterations: 20,
+            timezone: None,
+        }
+    }
+}
+
+/// System prompt size guard (see `agent::prompt::PromptCompiler`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PromptConfig {
+    /// Total token budget for the system prompt (0 = unlimited).
+    pub max_tokens: usize,
+    /// Per-section token caps, keyed by section name (e.g. "memory", "skills").
+    pub section_max_tokens: HashMap<String, usize>,
+    /// Section names to omit entirely.
+    pub disabled: Vec<String>,
+    /// Drop lines that already appeared in an earlier section.
+    pub dedupe: bool,
+}
+
+impl Default for PromptConfig {
+    fn default() -> Self {
+        Self {
+            max_tokens: 0,
+            section_max_tokens: HashMap::new(),
+            disabled: Vec::new(),
+            dedupe: true,
+        }
+    }
+}
+
+impl PromptConfig {
+    pub fn is_enabled(&self, section: &str) -> bool {
+        !self.disabled.iter().any(|d| d == section)
+    }
+
+    pub fn section_budget(&self, section: &str) -> Option<usize> {
+        self.section_max_tokens.get(section).copied().filter(|&n| n > 0)
+    }
+}
+
+/// Response length/tone profiles (see `agent::length`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResponseConfig {
+    /// Profiles keyed by device ("pc", "mobile", "voice"); override the agent's built-in limits.
+    pub devices: HashMap<String, ResponseProfile>,
+    /// Profiles keyed by channel id ("line", "telegram", ...); take priority over `devices`.
+    pub channels: HashMap<String, ResponseProfile>,
+    /// Cut replies longer than `max_chars × trim_ratio` after the model
+    /// answers, e.g. 1.5; 0 (the default) only asks the model to keep within
+    /// `max_chars`. Streamed replies are never cut.
+    pub trim_ratio: f64,
+    /// Fast model for speculative drafts on profiles with `speculative`
+    /// set (see `agent::speculative`); empty disables drafts.
+    pub draft_model: String,
+    /// Send a correction when the final reply differs from the draft by at
+    /// least this much (0.0–1.0).
+    pub draft_min_change: f64,
+}
+
+impl Default for ResponseConfig {
+    fn default() -> Self {
+        let mut channels = HashMap::new();
+        channels.insert(
+            "line".to_string(),
+            ResponseProfile { max_chars: Some(200), ..Default::default() },
+        );
+        let mut devices = HashMap::new();
+        devices.insert(
+            "voice".to_string(),
+            ResponseProfile { speculative: Some(true), ..Default::default() },
+        );
+        Self {
+            devices,
+            channels,
+            trim_ratio: 0.0,
+            draft_model: String::new(),
+            draft_min_change: 0.4,
+        }
+    }
+}
+
+/// Follow-ups the agent schedules for itself (see [`crate::service::followup`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProactiveConfig {
+    /// Offer the `schedule_followup` tool.
+    pub enabled: bool,
+    /// Local time range with no proactive messages, e.g. "22:00-07:00";
+    /// follow-ups due inside it are sent when it ends.
+    pub quiet_hours: Option<String>,
+    /// Proactive messages per day, across all chats.
+    pub max_per_day: u32,
+}
+
+impl Default for ProactiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            quiet_hours: Some("22:00-07:00".to_string()),
+            max_per_day: 5,
+        }
+    }
+}
+
+/// Learned agent routing (see [`crate::service::router`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RoutingConfig {
+    /// "keyword" (default), "shadow" (log the model's pick only) or "learned".
+    pub mode: String,
+    /// Model written by `chatweb routing train` (default:
+    /// `~/.nanobot/routing/model.json`).
+    pub model_path: Option<String>,
+    /// In learned mode, keyword scoring still decides below this confidence.
+    pub min_confidence: f64,
+    /// Days routing log entries are kept before they expire.
+    pub log_retention_days: u32,
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            mode: "keyword".to_string(),
+            model_path: None,
+            min_confidence: 0.6,
+            log_retention_days: 90,
+        }
+    }
+}
+
+/// Consolidating daily memory notes into long-term memory (see
+/// [`crate::memory::consolidate`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MemoryConfig {
+    pub consolidate: bool,
+    /// Cron expression of the gateway's consolidation run, in
+    /// `agents.defaults.timezone`; empty = no scheduled run.
+    pub schedule: String,
+    /// The API server also consolidates after every this many daily
+    /// entries; 0 = never.
+    pub every_entries: usize,
+    /// Model to consolidate with; `local-…` names run on the local model.
+    /// Empty: the chat model (gpt-4o-mini on the API server).
+    pub model: String,
+    /// Instructions with `{long_term}` and `{daily}` placeholders; empty =
+    /// the built-in prompt.
+    pub prompt: String,
+    /// Further attempts after a failed model call, with growing delays.
+    pub retries: u32,
+    /// Days of daily notes each run reads.
+    pub days: u32,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            consolidate: true,
+            schedule: "30 3 * * *".to_string(),
+            every_entries: 10,
+            model: String::new(),
+            prompt: String::new(),
+            retries: 2,
+            days: 2,
+        }
+    }
+}
+
+/// Handing a conversation to a human operator (see `agent::handoff`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HandoffConfig {
+    pub enabled: bool,
+    /// Operator chat as `channel:chat_id`; handoffs are announced there and
+    /// operator replies are relayed from it.
+    pub operator: String,
+    /// Phrases in a user message that ask for a person.
+    pub triggers: Vec<String>,
+    /// Let the model hand off conversations it can't resolve.
+    pub on_low_confidence: bool,
+    /// Channels offering handoff; empty = all.
+    pub channels: Vec<String>,
+}
+
+impl Default for HandoffConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            operator: String::new(),
+            triggers: vec![
+                "オペレーター".to_string(),
+                "担当者".to_string(),
+                "人と話したい".to_string(),
+                "talk to a human".to_string(),
+                "real person".to_string(),
+            ],
+            on_low_confidence: true,
+            channels: Vec::new(),
+        }
+    }
+}
+
+/// A post-processing step for final replies (see `hooks`). Redaction runs
+/// first, then the command, then the prepended and appended text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResponseHookConfig {
+    /// Name for logs.
+    pub name: String,
+    /// Channels the hook runs on ("web", "line", ...); empty = all.
+    pub channels: Vec<String>,
+    /// Agents the hook runs for ("researcher", ...); empty = all.
+    pub agents: Vec<String>,
+    /// Text put before the reply, e.g. branding.
+    pub prepend: Option<String>,
+    /// Text put after the reply, e.g. a disclaimer.
+    pub append: Option<String>,
+    /// Regexes whose matches are replaced with `redact_with`.
+    pub redact: Vec<String>,
+    pub redact_with: String,
+    /// Shell command given the reply on stdin; its stdout replaces the
+    /// reply. A failing command leaves the reply unchanged.
+    pub command: Option<String>,
+}
+
+impl Default for ResponseHookConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            channels: Vec::new(),
+            agents: Vec::new(),
+            prepend: None,
+            append: None,
+            redact: Vec::new(),
+            redact_with: "[redacted]".to_string(),
+            command: None,
+        }
+    }
+}
+
+/// Economy-first answers re-run on a stronger model when unsure
+/// (see `agent::escalation`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EscalationConfig {
+    pub enabled: bool,
+    /// First model to try; empty = the economy tier (HTTP API) or the
+    /// default model (gateway).
+    pub economy_model: String,
+    /// Model for escalated requests; empty = the powerful tier (HTTP API)
+    /// or the default model (gateway).
+    pub powerful_model: String,
+    /// Escalate when the self-reported confidence (0.0–1.0) is below this.
+    pub min_confidence: f64,
+    /// Also escalate replies that report no confidence at all.
+    pub escalate_without_confidence: bool,
+    /// Model that double-checks confident answers; unset = no verifier.
+    pub verifier_model: Option<String>,
+}
+
+impl Default for EscalationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            economy_model: String::new(),
+            powerful_model: String::new(),
+            min_confidence: 0.6,
+            escalate_without_confidence: false,
+            verifier_model: None,
         }
     }
 }
 
+/// Length, tone and drafting for one device or channel. Unset fields fall through.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResponseProfile {
+    pub max_chars: Option<usize>,
+    pub tone: Option<String>,
+    /// Show a draft from `draft_model` while the reply is generated.
+    pub speculative: Option<bool>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", default)]
 #[derive(Default)]
@@ -164,6 +475,29 @@ pub struct ChannelsConfig {
     pub google_chat: GoogleChatConfig,
     pub matrix: MatrixConfig,
     pub zalo: ZaloConfig,
+    /// Linked identities: user name → `channel:chat_id` addresses of the same
+    /// person. Their messages are routed to the channel they used last.
+    pub links: HashMap<String, Vec<String>>,
+    /// Working hours per channel name (see [`crate::channel::hours`]).
+    pub hours: HashMap<String, ChannelHoursConfig>,
+}
+
+/// When a channel is attended. Outside working hours non-urgent replies are
+/// held and sent when the hours resume.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChannelHoursConfig {
+    /// Local time range that is off hours, e.g. "19:00-09:00".
+    pub quiet_hours: Option<String>,
+    /// Weekdays that are off all day ("sat", "sun").
+    pub quiet_days: Vec<String>,
+    /// Timezone of the hours; `agents.defaults.timezone` when unset.
+    pub timezone: Option<String>,
+    /// Sent once per chat when a reply is held; replies are held silently
+    /// when unset.
+    pub away_message: Option<String>,
+    /// Replies containing one of these go out immediately.
+    pub urgent_keywords: Vec<String>,
 }
 
 
@@ -175,6 +509,34 @@ pub struct LineConfig {
     pub channel_secret: String,
     pub channel_access_token: String,
     pub allow_from: Vec<String>,
+    /// Deliver answers as they are generated instead of all at once.
+    pub progressive: LineProgressiveConfig,
+}
+
+/// LINE can't edit messages, so a streamed answer is delivered as a quick
+/// acknowledgment (with the reply token) followed by push messages split at
+/// paragraph boundaries. Push messages count against the LINE plan's quota.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LineProgressiveConfig {
+    pub enabled: bool,
+    /// Sent as soon as the message arrives; empty sends none.
+    pub ack_message: String,
+    /// Characters after which a paragraph boundary ends a message.
+    pub chunk_chars: usize,
+    /// Most push messages per answer; the last one carries the rest.
+    pub max_messages: usize,
+}
+
+impl Default for LineProgressiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ack_message: "💭 考えています…".to_string(),
+            chunk_chars: 500,
+            max_messages: 4,
+        }
+    }
 }
 
 
@@ -215,6 +577,11 @@ pub struct DiscordConfig {
     pub allow_from: Vec<String>,
     pub gateway_url: String,
     pub intents: u32,
+    /// Register and answer `/ask`, `/reset` and `/status`.
+    pub slash_commands: bool,
+    /// Register the commands in this server only, where they show up at
+    /// once; global commands can take up to an hour to appear.
+    pub command_guild_id: String,
 }
 
 impl Default for DiscordConfig {
@@ -224,7 +591,11 @@ impl Default for DiscordConfig {
             token: String::new(),
             allow_from: Vec::new(),
             gateway_url: "wss://gateway.discord.gg/?v=10&encoding=json".to_string(),
-            intents: 37377,
+            // GUILDS, GUILD_MESSAGES, GUILD_MESSAGE_REACTIONS, DIRECT_MESSAGES,
+            // DIRECT_MESSAGE_REACTIONS, MESSAGE_CONTENT
+            intents: 46593,
+            slash_commands: true,
+            command_guild_id: String::new(),
         }
     }
 }
@@ -374,6 +745,26 @@ pub struct GatewayConfig {
     pub tls_cert: Option<String>,
     /// TLS private key path
     pub tls_key: Option<String>,
+    /// Addresses the HTTP API listens on, e.g. `["0.0.0.0", "::"]` for IPv4
+    /// and IPv6 or `["127.0.0.1:8080"]`. Entries without a port use the HTTP
+    /// port. Empty = `host`.
+    pub listen: Vec<String>,
+    /// Let's Encrypt certificates (takes precedence over `tls_cert`/`tls_key`).
+    pub acme: AcmeConfig,
+    /// Public base URL of the HTTP API, e.g. `https://bot.example.com`. When
+    /// set (or implied by `acme.domains`), channel webhooks are registered
+    /// against it on startup.
+    pub public_url: Option<String>,
+    /// Remove the Telegram webhook again on shutdown, so the bot can go back
+    /// to long polling.
+    pub unregister_webhooks: bool,
+    /// Parallel chat requests per user (see [`crate::service::chat_queue`]).
+    pub concurrency: ConcurrencyConfig,
+    /// Serve the built-in dashboard at `/admin` (see
+    /// `crate::service::admin_ui`). Its data needs one of `api_tokens`.
+    pub admin_ui: bool,
+    /// Built-in chat page at `/` (`webui` feature).
+    pub webui: WebUiConfig,
 }
 
 impl Default for GatewayConfig {
@@ -385,6 +776,216 @@ impl Default for GatewayConfig {
             allowed_ips: Vec::new(),
             tls_cert: None,
             tls_key: None,
+            listen: Vec::new(),
+            acme: AcmeConfig::default(),
+            public_url: None,
+            unregister_webhooks: false,
+            concurrency: ConcurrencyConfig::default(),
+            admin_ui: false,
+            webui: WebUiConfig::default(),
+        }
+    }
+}
+
+/// The chat page served by the gateway (see `crate::service::webui`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WebUiConfig {
+    /// Serve the page at `/`. Needs a build with the `webui` feature.
+    pub enabled: bool,
+    /// Page title and header.
+    pub title: String,
+    /// Shown before the first message of a conversation.
+    pub greeting: String,
+    pub placeholder: String,
+    /// `dark`, `light` or `auto` (follow the browser).
+    pub theme: String,
+    /// Accent color as CSS, e.g. `#6366f1`.
+    pub accent: String,
+    /// Show a microphone button (browser speech recognition, or a
+    /// recognizer set by a script in `head`).
+    pub voice: bool,
+    /// Speech recognition language like `ja-JP`; empty = the browser's.
+    pub voice_lang: String,
+    /// Extra HTML for `<head>`: styles, or scripts that set the page's
+    /// `window.nanobotChat` hooks.
+    pub head: String,
+}
+
+impl Default for WebUiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            title: "nanobot".to_string(),
+            greeting: "How can I help?".to_string(),
+            placeholder: "Message".to_string(),
+            theme: "auto".to_string(),
+            accent: "#6366f1".to_string(),
+            voice: true,
+            voice_lang: String::new(),
+            head: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ConcurrencyConfig {
+    /// Chat requests one user may have running at once.
+    pub max_parallel: u32,
+    /// The same for users on a paid plan.
+    pub paid_max_parallel: u32,
+    /// Requests over the limit wait in a queue of up to this many; more
+    /// are refused.
+    pub max_queued: u32,
+    /// Queued requests give up after this long.
+    pub queue_timeout_secs: u64,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            max_parallel: 10,
+            paid_max_parallel: 1000,
+            max_queued: 20,
+            queue_timeout_secs: 20,
+        }
+    }
+}
+
+impl GatewayConfig {
+    /// `public_url` without a trailing slash, falling back to the first ACME domain.
+    pub fn public_base_url(&self) -> Option<String> {
+        match self.public_url.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+            Some(url) => Some(url.trim_end_matches('/').to_string()),
+            None => self.acme.domains.first().map(|domain| format!("https://{}", domain)),
+        }
+    }
+
+    /// Socket addresses for the HTTP API (see `listen`), `port` filling in
+    /// entries that have none.
+    pub fn listen_addrs(&self, port: u16) -> Result<Vec<std::net::SocketAddr>, ConfigError> {
+        use std::net::ToSocketAddrs;
+
+        let entries = if self.listen.is_empty() { std::slice::from_ref(&self.host) } else { &self.listen[..] };
+        let mut addrs = Vec::new();
+        for entry in entries {
+            let entry = entry.trim();
+            let bare = entry.trim_start_matches('[').trim_end_matches(']');
+            let resolved: std::io::Result<Vec<_>> = if let Ok(addr) = entry.parse::<std::net::SocketAddr>() {
+                Ok(vec![addr])
+            } else if let Ok(ip) = bare.parse::<std::net::IpAddr>() {
+                Ok(vec![std::net::SocketAddr::new(ip, port)])
+            } else if entry.contains(':') {
+                entry.to_socket_addrs().map(Iterator::collect)
+            } else {
+                (entry, port).to_socket_addrs().map(Iterator::collect)
+            };
+            match resolved {
+                Ok(found) if !found.is_empty() => {
+                    for addr in found {
+                        if !addrs.contains(&addr) {
+                            addrs.push(addr);
+                        }
+                    }
+                }
+                _ => return Err(ConfigError::Invalid(format!("invalid listen address '{}'", entry))),
+            }
+        }
+        Ok(addrs)
+    }
+}
+
+/// Automatic HTTPS: certificates are obtained from Let's Encrypt over
+/// TLS-ALPN-01 (the HTTP API must be reachable on port 443) and renewed in
+/// the background. Requires the `acme` feature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+#[derive(Default)]
+pub struct AcmeConfig {
+    /// Domains to certify, e.g. "bot.example.com". Empty disables ACME.
+    pub domains: Vec<String>,
+    /// Contact address for expiry notices.
+    pub email: Option<String>,
+    /// Use the staging directory (untrusted certificates, generous rate limits).
+    pub staging: bool,
+    /// Account keys and certificates; default `~/.nanobot/tls`.
+    pub cache_dir: Option<String>,
+}
+
+impl AcmeConfig {
+    pub fn enabled(&self) -> bool {
+        !self.domains.is_empty()
+    }
+
+    /// Expanded cache directory.
+    pub fn cache_path(&self) -> PathBuf {
+        match &self.cache_dir {
+            Some(dir) if dir.starts_with("~/") => dirs::home_dir()
+                .map(|home| home.join(&dir[2..]))
+                .unwrap_or_else(|| PathBuf::from(dir)),
+            Some(dir) => PathBuf::from(dir),
+            None => get_data_dir().join("tls"),
+        }
+    }
+}
+
+/// Outbound HTTP clients (see [`crate::util::http::client_for`]). Without a
+/// `proxy`, the standard `HTTPS_PROXY`/`HTTP_PROXY`/`NO_PROXY` variables apply.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HttpConfig {
+    /// Proxy URL for all outbound requests, e.g. `http://proxy.local:3128`.
+    pub proxy: Option<String>,
+    pub connect_timeout_secs: u64,
+    /// Overall timeout for model API calls.
+    pub llm_timeout_secs: u64,
+    /// Overall timeout for channel APIs and webhook deliveries.
+    pub webhook_timeout_secs: u64,
+    /// Overall timeout for everything else.
+    pub default_timeout_secs: u64,
+    /// Idle connections kept open per host.
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout_secs: u64,
+    /// HTTP/2 keep-alive ping interval (0 disables pings).
+    pub keep_alive_secs: u64,
+    pub dns: DnsConfig,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            connect_timeout_secs: 5,
+            llm_timeout_secs: 120,
+            webhook_timeout_secs: 60,
+            default_timeout_secs: 120,
+            pool_max_idle_per_host: 50,
+            pool_idle_timeout_secs: 90,
+            keep_alive_secs: 30,
+            dns: DnsConfig::default(),
+        }
+    }
+}
+
+/// Name resolution for outbound requests. By default the system resolver is used.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DnsConfig {
+    /// Nameservers to ask instead, e.g. "1.1.1.1" or "[2606:4700:4700::1111]:53".
+    pub servers: Vec<String>,
+    /// Addresses to connect to: "any", "ipv4", "ipv6", "prefer-ipv4" or "prefer-ipv6".
+    pub ip_family: String,
+    /// Fixed addresses for hostnames, like /etc/hosts ("api.internal" -> "10.0.0.5").
+    pub hosts: HashMap<String, String>,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            servers: Vec::new(),
+            ip_family: "any".to_string(),
+            hosts: HashMap::new(),
         }
     }
 }
@@ -394,9 +995,12 @@ impl Default for GatewayConfig {
 #[derive(Default)]
 pub struct ToolsConfig {
     pub web: WebToolsConfig,
+    pub ocr: OcrConfig,
     #[serde(rename = "exec")]
     pub exec_config: ExecToolConfig,
     pub restrict_to_workspace: bool,
+    pub files: FilePolicyConfig,
+    pub policy: ToolPolicyConfig,
 }
 
 
@@ -405,14 +1009,132 @@ pub struct ToolsConfig {
 #[derive(Default)]
 pub struct WebToolsConfig {
     pub search: WebSearchConfig,
+    pub fetch: WebFetchConfig,
+}
+
+/// How web tools behave towards the sites they read (see [`crate::util::http::configure_web`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WebFetchConfig {
+    /// User-Agent sent by read_webpage, web_crawl and search scraping.
+    pub user_agent: String,
+    /// Skip URLs that the site's robots.txt disallows for this agent.
+    pub respect_robots: bool,
+    /// Minimum time between two requests to the same host.
+    pub min_interval_ms: u64,
+    /// Cache fetched pages under `<workspace>/.cache/web`.
+    pub cache: bool,
+    /// Pages younger than this are served from the cache without revalidation.
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for WebFetchConfig {
+    fn default() -> Self {
+        Self {
+            user_agent: "nanobot/0.1 (+https://chatweb.ai/bot)".to_string(),
+            respect_robots: true,
+            min_interval_ms: 1000,
+            cache: true,
+            cache_ttl_secs: 3600,
+        }
+    }
 }
 
 
+/// Text recognition for the `ocr` tool and image attachments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OcrConfig {
+    /// Languages expected in images ("ja", "en", or tesseract codes like "jpn").
+    pub languages: Vec<String>,
+    /// Run OCR on images attached to inbound messages and add the text to the message.
+    pub media: bool,
+    /// Ask a vision model when tesseract is missing or finds no text.
+    pub vision_fallback: bool,
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self {
+            languages: vec!["ja".to_string(), "en".to_string()],
+            media: true,
+            vision_fallback: true,
+        }
+    }
+}
+
+/// Safe mode for the file tools (see [`crate::tool::file_policy`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FilePolicyConfig {
+    /// Gitignore-style patterns, relative to the workspace, of files the
+    /// tools must not modify.
+    pub protected: Vec<String>,
+    /// Extensions the tools neither read nor write (keys, keystores).
+    pub denied_extensions: Vec<String>,
+    /// Largest file the tools read or write.
+    pub max_file_bytes: u64,
+    /// Keep a copy of overwritten and deleted files in `<workspace>/.trash`.
+    pub trash: bool,
+    /// Trash older than this is purged.
+    pub trash_retention_days: u32,
+}
+
+impl Default for FilePolicyConfig {
+    fn default() -> Self {
+        Self {
+            protected: [".git/", ".env", ".env.*", ".ssh/", "id_rsa*", "id_ecdsa*", "id_ed25519*", ".trash/"]
+                .map(String::from)
+                .to_vec(),
+            denied_extensions: ["pem", "key", "p12", "pfx", "jks", "keystore", "ppk"]
+                .map(String::from)
+                .to_vec(),
+            max_file_bytes: 10 * 1024 * 1024,
+            trash: true,
+            trash_retention_days: 7,
+        }
+    }
+}
+
+/// Which tools may run (see [`crate::tool::policy`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ToolPolicyConfig {
+    /// Tool name (or prefix ending in `*`) to "allow", "deny" or "ask".
+    pub rules: HashMap<String, String>,
+    /// Rule for tools no entry matches.
+    pub default: String,
+    /// Ask before default-allowed calls that delete or overwrite data. Unset,
+    /// that happens where someone can answer (`chatweb agent`) and the calls
+    /// run elsewhere; `true` also refuses them where nobody can (gateway,
+    /// `mcp-serve`); `false` never asks.
+    pub confirm_destructive: Option<bool>,
+}
+
+impl Default for ToolPolicyConfig {
+    fn default() -> Self {
+        Self {
+            rules: HashMap::new(),
+            default: "allow".to_string(),
+            confirm_destructive: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", default)]
 pub struct WebSearchConfig {
+    /// Brave Search API key (also read from `BRAVE_API_KEY`).
     pub api_key: String,
     pub max_results: u32,
+    /// Engine tried first: "brave", "searxng", "tavily" or "duckduckgo".
+    pub provider: String,
+    /// Engines tried in order when the previous one errors or rate-limits.
+    pub fallback: Vec<String>,
+    /// Base URL of a self-hosted SearxNG instance (JSON format enabled).
+    pub searxng_url: String,
+    /// Tavily API key (also read from `TAVILY_API_KEY`).
+    pub tavily_api_key: String,
 }
 
 impl Default for WebSearchConfig {
@@ -420,6 +1142,10 @@ impl Default for WebSearchConfig {
         Self {
             api_key: String::new(),
             max_results: 5,
+            provider: "brave".to_string(),
+            fallback: vec!["duckduckgo".to_string()],
+            searxng_url: String::new(),
+            tavily_api_key: String::new(),
         }
     }
 }
@@ -428,210 +1154,317 @@ impl Default for WebSearchConfig {
 #[serde(rename_all = "camelCase", default)]
 pub struct ExecToolConfig {
     pub timeout: u64,
+    /// Persistent shell sessions (`shell_session` tool) idle longer than this are closed.
+    pub session_idle_secs: u64,
+    pub max_sessions: usize,
+    /// Address-space limit for each session's processes (0 = unlimited).
+    pub session_memory_mb: u64,
+    /// CPU-time limit for each session's processes (0 = unlimited).
+    pub session_cpu_secs: u64,
 }
 
 impl Default for ExecToolConfig {
     fn default() -> Self {
-        Self { timeout: 60 }
+        Self {
+            timeout: 60,
+            session_idle_secs: 30 * 60,
+            max_sessions: 4,
+            session_memory_mb: 2048,
+            session_cpu_secs: 3600,
+        }
     }
 }
 
-// ====== Config loading/saving ======
+/// Hands-free voice mode (see [`crate::voice`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VoiceConfig {
+    /// Listen continuously instead of push-to-talk.
+    pub hands_free: bool,
+    /// 0 (lets the most through) to 3 (filters the most noise).
+    pub vad_aggressiveness: u8,
+    /// Silence that ends an utterance.
+    pub silence_ms: u32,
+    /// Phrase that must open each request, e.g. "ねぇチャット".
+    pub wake_word: Option<String>,
+    /// Pause TTS playback when the user starts speaking.
+    pub barge_in: bool,
+    /// Models and voice for the realtime endpoint (`/api/v1/voice/ws`),
+    /// served by the `openai` provider; the `openai` TTS backend uses the
+    /// same model and voice.
+    pub stt_model: String,
+    pub tts_model: String,
+    pub tts_voice: String,
+    /// Read replies aloud in push-to-talk voice mode (`--no-tts` turns it off).
+    pub tts: bool,
+    /// Speech synthesis (see [`crate::voice::tts`]): `auto`, `chatweb`,
+    /// `elevenlabs`, `openai` or `local`.
+    pub tts_backend: String,
+    /// Local synthesizer for the `local` backend, reading the text on stdin
+    /// and writing `{wav}`; `say` on macOS and espeak-ng elsewhere if unset.
+    pub tts_command: Option<String>,
+    /// ElevenLabs voice and model (the key comes from `ELEVENLABS_API_KEY`).
+    pub elevenlabs_voice_id: String,
+    pub elevenlabs_model: String,
+    /// Push-to-talk speech recognition (see [`crate::voice::stt`]):
+    /// `auto`, `chatweb`, `openai` or `local`.
+    pub stt_backend: String,
+    /// Local recognizer for the `local` backend; `{wav}` is replaced by the
+    /// audio file and stdout is the transcript, e.g.
+    /// `whisper-cli -m ggml-base.bin -l ja -nt -np -f {wav}`.
+    pub stt_command: Option<String>,
+    /// Spoken language hint for the `openai` backend (ISO-639-1, e.g. "ja").
+    pub stt_language: Option<String>,
+    /// Recognize enrolled speakers by voice (see [`crate::voice::speaker`]).
+    /// Off by default: voiceprints are biometric data.
+    pub speaker_id: bool,
+    /// Cosine similarity needed to match an enrolled voiceprint.
+    pub speaker_threshold: f32,
+    /// Keep "remember that ..." requests as notes on the speaker.
+    pub remember_preferences: bool,
+}
 
-/// Load configuration from environment variables.
-///
-/// Priority:
-/// 1. `NANOBOT_CONFIG` env var — full JSON config
-/// 2. Individual env vars (merged on top of defaults)
-/// 3. File fallback (`~/.nanobot/config.json`)
-pub fn load_config_from_env() -> Config {
-    // 1. Full JSON from NANOBOT_CONFIG
-    if let Ok(json) = std::env::var("NANOBOT_CONFIG") {
-        match serde_json::from_str::<Config>(&json) {
-            Ok(config) => return config,
-            Err(e) => {
-                tracing::warn!("Failed to parse NANOBOT_CONFIG: {}", e);
-            }
+impl Default for VoiceConfig {
+    fn default() -> Self {
+        Self {
+            hands_free: false,
+            vad_aggressiveness: 2,
+            silence_ms: 700,
+            wake_word: None,
+            barge_in: true,
+            stt_model: "whisper-1".to_string(),
+            tts_model: "gpt-4o-mini-tts".to_string(),
+            tts_voice: "alloy".to_string(),
+            tts: true,
+            tts_backend: "auto".to_string(),
+            tts_command: None,
+            elevenlabs_voice_id: "pNInz6obpgDQGcFmaJgB".to_string(),
+            elevenlabs_model: "eleven_multilingual_v2".to_string(),
+            stt_backend: "auto".to_string(),
+            stt_command: None,
+            stt_language: None,
+            speaker_id: false,
+            speaker_threshold: 0.85,
+            remember_preferences: true,
         }
     }
+}
 
-    // 2. Start with file fallback, then overlay individual env vars
-    let mut cfg = load_config(None);
-
-    // Provider keys
-    if let Ok(v) = std::env::var("ANTHROPIC_API_KEY") {
-        cfg.providers.anthropic.api_key = v;
-    }
-    if let Ok(v) = std::env::var("OPENAI_API_KEY") {
-        cfg.providers.openai.api_key = v;
-    }
-    if let Ok(v) = std::env::var("OPENROUTER_API_KEY") {
-        cfg.providers.openrouter.api_key = v;
-    }
-    if let Ok(v) = std::env::var("DEEPSEEK_API_KEY") {
-        cfg.providers.deepseek.api_key = v;
-    }
-
-    // Telegram
-    if let Ok(v) = std::env::var("TELEGRAM_BOT_TOKEN") {
-        cfg.channels.telegram.token = v;
-        cfg.channels.telegram.enabled = true;
-    }
-    if let Ok(v) = std::env::var("TELEGRAM_ALLOW_FROM") {
-        cfg.channels.telegram.allow_from = v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
-    }
-
-    // LINE
-    if let Ok(v) = std::env::var("LINE_CHANNEL_SECRET") {
-        cfg.channels.line.channel_secret = v;
-    }
-    if let Ok(v) = std::env::var("LINE_CHANNEL_ACCESS_TOKEN") {
-        cfg.channels.line.channel_access_token = v;
-        cfg.channels.line.enabled = true;
-    }
-    if let Ok(v) = std::env::var("LINE_ALLOW_FROM") {
-        cfg.channels.line.allow_from = v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
-    }
+/// Android/Termux profile (see [`crate::termux`]). Detected automatically;
+/// these only override what detection picks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TermuxConfig {
+    /// Force the profile on or off; detected from the environment when unset.
+    pub enabled: Option<bool>,
+    /// Post Android notifications (needs the Termux:API app).
+    pub notifications: bool,
+    /// Read replies aloud in voice mode.
+    pub speak_replies: bool,
+}
 
-    // Discord
-    if let Ok(v) = std::env::var("DISCORD_BOT_TOKEN") {
-        cfg.channels.discord.token = v;
-        cfg.channels.discord.enabled = true;
+impl Default for TermuxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: None,
+            notifications: true,
+            speak_replies: true,
+        }
     }
+}
 
-    // Slack
-    if let Ok(v) = std::env::var("SLACK_APP_TOKEN") {
-        cfg.channels.slack.app_token = v;
-    }
-    if let Ok(v) = std::env::var("SLACK_BOT_TOKEN") {
-        cfg.channels.slack.bot_token = v;
-        cfg.channels.slack.enabled = true;
-    }
+/// Voice appliance mode, `chatweb kiosk` (see [`crate::kiosk`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct KioskConfig {
+    /// Shell command per state (`listening`, `thinking`, `speaking`,
+    /// `offline`, `muted`, `error`, or `*` for any), e.g. to drive LEDs.
+    pub hooks: HashMap<String, String>,
+    /// Local model answering while chatweb.ai is unreachable; none when empty.
+    pub fallback_model: String,
+    /// OpenAI-compatible endpoint serving `fallback_model` (Ollama by default).
+    pub fallback_api_base: String,
+    pub fallback_api_key: String,
+    /// Offline speech recognition; `{wav}` is replaced by the audio file and
+    /// stdout is the transcript (e.g. a whisper.cpp command line).
+    pub offline_stt_command: Option<String>,
+    /// Offline speech synthesis, reading the text on stdin.
+    pub offline_tts_command: String,
+    /// Sets the output volume; `{percent}` is replaced by 0-100.
+    pub volume_command: String,
+    /// Heartbeat (and remote command poll) interval.
+    pub heartbeat_secs: u64,
+}
 
-    // Signal
-    if let Ok(v) = std::env::var("SIGNAL_ENDPOINT") {
-        cfg.channels.signal.endpoint = v;
-    }
-    if let Ok(v) = std::env::var("SIGNAL_PHONE") {
-        cfg.channels.signal.phone_number = v;
-        cfg.channels.signal.enabled = true;
+impl Default for KioskConfig {
+    fn default() -> Self {
+        Self {
+            hooks: HashMap::new(),
+            fallback_model: String::new(),
+            fallback_api_base: "http://localhost:11434/v1".to_string(),
+            fallback_api_key: "ollama".to_string(),
+            offline_stt_command: None,
+            offline_tts_command: "espeak-ng --stdin".to_string(),
+            volume_command: "amixer -q set Master {percent}%".to_string(),
+            heartbeat_secs: 60,
+        }
     }
+}
 
-    // iMessage
-    if let Ok(v) = std::env::var("IMESSAGE_BRIDGE_URL") {
-        cfg.channels.imessage.bridge_url = v;
-        cfg.channels.imessage.enabled = true;
-    }
+/// Encrypted sync of CLI settings and sessions between devices, `chatweb
+/// sync now` (see [`crate::service::config_sync`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncConfig {
+    /// `chatweb` (the hosted API), `s3://bucket/key` or a WebDAV file URL;
+    /// sync is off when empty.
+    pub target: String,
+    /// Basic auth for a WebDAV target.
+    pub webdav_user: String,
+    pub webdav_password: String,
+    /// Key prefixes of the sessions to sync.
+    pub sessions: Vec<String>,
+    /// Also sync API keys and tokens (encrypted like everything else).
+    pub include_secrets: bool,
+    /// Name of this device in conflict copies; the hostname when empty.
+    pub device: String,
+}
 
-    // MS Teams
-    if let Ok(v) = std::env::var("TEAMS_APP_ID") {
-        cfg.channels.teams.app_id = v;
-    }
-    if let Ok(v) = std::env::var("TEAMS_APP_PASSWORD") {
-        cfg.channels.teams.app_password = v;
-        cfg.channels.teams.enabled = true;
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            target: String::new(),
+            webdav_user: String::new(),
+            webdav_password: String::new(),
+            sessions: vec!["cli:".to_string()],
+            include_secrets: false,
+            device: String::new(),
+        }
     }
+}
 
-    // Google Chat
-    if let Ok(v) = std::env::var("GOOGLE_CHAT_SERVICE_ACCOUNT_KEY") {
-        cfg.channels.google_chat.service_account_key = v;
-        cfg.channels.google_chat.enabled = true;
-    }
-    if let Ok(v) = std::env::var("GOOGLE_CHAT_WEBHOOK_TOKEN") {
-        cfg.channels.google_chat.webhook_token = v;
-    }
+/// Outbound webhooks fired on agent events (see [`crate::webhook`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WebhooksConfig {
+    pub endpoints: Vec<WebhookEndpoint>,
+    /// Attempts per delivery before it is marked failed.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles on each further attempt.
+    pub initial_backoff_secs: u64,
+    pub timeout_secs: u64,
+}
 
-    // Matrix
-    if let Ok(v) = std::env::var("MATRIX_HOMESERVER") {
-        cfg.channels.matrix.homeserver = v;
-    }
-    if let Ok(v) = std::env::var("MATRIX_USER_ID") {
-        cfg.channels.matrix.user_id = v;
-    }
-    if let Ok(v) = std::env::var("MATRIX_ACCESS_TOKEN") {
-        cfg.channels.matrix.access_token = v;
-        cfg.channels.matrix.enabled = true;
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            max_attempts: 5,
+            initial_backoff_secs: 10,
+            timeout_secs: 10,
+        }
     }
+}
 
-    // Zalo
-    if let Ok(v) = std::env::var("ZALO_BOT_TOKEN") {
-        cfg.channels.zalo.bot_token = v;
-        cfg.channels.zalo.enabled = true;
-    }
-    if let Ok(v) = std::env::var("ZALO_SECRET_TOKEN") {
-        cfg.channels.zalo.secret_token = v;
-    }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WebhookEndpoint {
+    pub id: String,
+    pub url: String,
+    /// HMAC-SHA256 signing secret. Unsigned when empty.
+    pub secret: String,
+    /// Event names to send (e.g. `message.processed`). Empty means all.
+    pub events: Vec<String>,
+    pub enabled: bool,
+}
 
-    // Agent defaults
-    if let Ok(v) = std::env::var("NANOBOT_MODEL") {
-        cfg.agents.defaults.model = v;
+impl Default for WebhookEndpoint {
+    fn default() -> Self {
+        Self {
+            id: String::new(),
+            url: String::new(),
+            secret: String::new(),
+            events: Vec::new(),
+            enabled: true,
+        }
     }
+}
 
-    // Gateway API tokens
-    if let Ok(v) = std::env::var("GATEWAY_API_TOKENS") {
-        cfg.gateway.api_tokens = v.split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect();
+impl WebhookEndpoint {
+    pub fn wants(&self, event: &str) -> bool {
+        self.enabled && !self.url.is_empty()
+            && (self.events.is_empty() || self.events.iter().any(|e| e == event || e == "*"))
     }
+}
 
-    // Gateway allowed IPs
-    if let Ok(v) = std::env::var("GATEWAY_ALLOWED_IPS") {
-        cfg.gateway.allowed_ips = v.split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect();
-    }
+// ====== Config loading/saving ======
 
-    // Gateway TLS
-    if let Ok(v) = std::env::var("GATEWAY_TLS_CERT") {
-        cfg.gateway.tls_cert = Some(v);
-    }
-    if let Ok(v) = std::env::var("GATEWAY_TLS_KEY") {
-        cfg.gateway.tls_key = Some(v);
+/// Load configuration from environment variables.
+///
+/// Priority:
+/// 1. `NANOBOT_CONFIG` env var — full JSON config
+/// 2. Individual env vars (see [`env::ENV_VARS`]) and `NANOBOT__<PATH>`
+///    overrides, merged on top of the file or defaults
+/// 3. File fallback (`~/.nanobot/config.json`)
+pub fn load_config_from_env() -> Config {
+    // 1. Full JSON from NANOBOT_CONFIG
+    if let Ok(json) = std::env::var("NANOBOT_CONFIG") {
+        match serde_json::from_str::<Config>(&json) {
+            Ok(config) => return config,
+            Err(e) => {
+                tracing::warn!("Failed to parse NANOBOT_CONFIG: {}", e);
+            }
+        }
     }
 
-    cfg
+    // 2. Start with file fallback, then overlay individual env vars
+    let vars: HashMap<String, String> = std::env::vars().collect();
+    env::apply(load_config(None), &vars)
 }
 
-/// Get the default configuration file path.
+/// Get the default configuration file path (of the active profile).
 pub fn get_config_path() -> PathBuf {
-    // In Lambda, use /tmp as it's the only writable directory
-    let base_dir = if std::env::var("AWS_LAMBDA_FUNCTION_NAME").is_ok() {
-        PathBuf::from("/tmp")
-    } else {
-        dirs::home_dir().unwrap_or_else(|| PathBuf::from("."))
-    };
-
-    base_dir
-        .join(".nanobot")
-        .join("config.json")
+    profile::active_dir().join("config.json")
 }
 
-/// Get the nanobot data directory.
+/// Get the nanobot data directory (of the active profile).
 pub fn get_data_dir() -> PathBuf {
-    // In Lambda, use /tmp as it's the only writable directory
-    let base_dir = if std::env::var("AWS_LAMBDA_FUNCTION_NAME").is_ok() {
-        PathBuf::from("/tmp")
-    } else {
-        dirs::home_dir().unwrap_or_else(|| PathBuf::from("."))
-    };
-
-    let path = base_dir.join(".nanobot");
+    let path = profile::active_dir();
     std::fs::create_dir_all(&path).ok();
     path
 }
 
-/// Load configuration from file or create default.
+/// Load configuration from file or create default. Without an explicit
+/// path, a project-local `.nanobot/` found by [`project::detect`] is
+/// overlaid on top.
 pub fn load_config(config_path: Option<&Path>) -> Config {
-    let path = config_path
-        .map(|p| p.to_path_buf())
-        .unwrap_or_else(get_config_path);
+    match config_path {
+        Some(path) => read_config(path),
+        None => {
+            let config = read_config(&get_config_path());
+            match project::active() {
+                Some(dir) => project::overlay(config, dir),
+                None => config,
+            }
+        }
+    }
+}
 
+fn read_config(path: &Path) -> Config {
     if path.exists() {
-        match std::fs::read_to_string(&path) {
+        match std::fs::read_to_string(path) {
             Ok(content) => match serde_json::from_str::<Config>(&content) {
-                Ok(config) => return config,
+                Ok(config) => {
+                    let report = validate::check_keys(&content, strict_from_env());
+                    for issue in &report.issues {
+                        tracing::warn!("{} {}: {}", path.display(), issue.path, issue.message);
+                    }
+                    if report.is_ok() {
+                        return config;
+                    }
+                    tracing::warn!("Strict config: fix the keys above (or run `chatweb config validate`).");
+                    tracing::warn!("Using default configuration.");
+                }
                 Err(e) => {
                     tracing::warn!("Failed to parse config from {}: {}", path.display(), e);
                     tracing::warn!("Using default configuration.");
@@ -647,6 +1480,11 @@ pub fn load_config(config_path: Option<&Path>) -> Config {
     Config::default()
 }
 
+/// `NANOBOT_CONFIG_STRICT` is set to `1` or `true`.
+pub fn strict_from_env() -> bool {
+    std::env::var("NANOBOT_CONFIG_STRICT").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
 /// Save configuration to file.
 pub fn save_config(config: &Config, config_path: Option<&Path>) -> std::result::Result<(), ConfigError> {
     let path = config_path
@@ -686,6 +1524,25 @@ mod tests {
         assert_eq!(parsed.gateway.port, cfg.gateway.port);
     }
 
+    #[test]
+    fn test_gateway_listen_addrs() {
+        let mut gateway = GatewayConfig::default();
+        assert_eq!(gateway.listen_addrs(3000).unwrap(), vec!["0.0.0.0:3000".parse().unwrap()]);
+
+        gateway.listen = vec!["0.0.0.0".into(), "::".into(), "[::1]:8080".into(), "127.0.0.1:9000".into()];
+        let addrs: Vec<String> = gateway.listen_addrs(3000).unwrap().iter().map(|a| a.to_string()).collect();
+        assert_eq!(addrs, vec!["0.0.0.0:3000", "[::]:3000", "[::1]:8080", "127.0.0.1:9000"]);
+
+        gateway.listen = vec!["not an address".into()];
+        assert!(gateway.listen_addrs(3000).is_err());
+
+        assert_eq!(gateway.public_base_url(), None);
+        gateway.acme.domains = vec!["bot.example.com".into()];
+        assert_eq!(gateway.public_base_url().as_deref(), Some("https://bot.example.com"));
+        gateway.public_url = Some("https://abc.trycloudflare.com/".into());
+        assert_eq!(gateway.public_base_url().as_deref(), Some("https://abc.trycloudflare.com"));
+    }
+
     #[test]
     fn test_config_camelcase_compat() {
         let json = r#"{
@@ -772,6 +1629,27 @@ mod tests {
         assert_eq!(cfg.agents.defaults.model, "anthropic/claude-opus-4-5");
     }
 
+    #[test]
+    fn test_prompt_config_from_json() {
+        let json = r#"{
+            "agents": {
+                "prompt": {
+                    "maxTokens": 3000,
+                    "sectionMaxTokens": { "memory": 500 },
+                    "disabled": ["wow"]
+                }
+            }
+        }"#;
+        let cfg: Config = serde_json::from_str(json).unwrap();
+        let p = &cfg.agents.prompt;
+        assert_eq!(p.max_tokens, 3000);
+        assert_eq!(p.section_budget("memory"), Some(500));
+        assert_eq!(p.section_budget("skills"), None);
+        assert!(!p.is_enabled("wow"));
+        assert!(p.is_enabled("meta"));
+        assert!(p.dedupe);
+    }
+
     #[test]
     fn test_load_config_from_env_full_json() {
         // Clear individual vars to test JSON config takes precedence
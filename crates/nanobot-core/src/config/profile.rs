@@ -0,0 +1,176 @@
+//! Named profiles (`chatweb --profile work ...`).
+//!
+//! A profile is a separate `~/.nanobot/profiles/<name>/` holding its own
+//! `config.json`, workspace, sessions and auth token, so personal and work
+//! assistants on one machine never see each other's data. Without a profile
+//! everything lives directly in `~/.nanobot/` as before ("default").
+//!
+//! The active profile is, in order: the one passed to [`select`] (the
+//! `--profile` flag), `NANOBOT_PROFILE`, or the one saved by
+//! `chatweb profile switch`.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::error::ConfigError;
+
+/// Name of the profile that lives directly in `~/.nanobot`.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// File in the root directory remembering `chatweb profile switch`.
+const CURRENT_FILE: &str = "current_profile";
+
+static SELECTED: OnceLock<Option<String>> = OnceLock::new();
+
+/// Use `name` for the rest of the process. Call before anything reads the
+/// config or data directory; later calls are ignored.
+pub fn select(name: Option<String>) -> Result<(), ConfigError> {
+    if let Some(name) = &name {
+        validate_name(name)?;
+    }
+    let _ = SELECTED.set(name);
+    Ok(())
+}
+
+/// `~/.nanobot` (or `/tmp/.nanobot` on Lambda), regardless of profile.
+pub fn root_dir() -> PathBuf {
+    // In Lambda, use /tmp as it's the only writable directory
+    let base_dir = if std::env::var("AWS_LAMBDA_FUNCTION_NAME").is_ok() {
+        PathBuf::from("/tmp")
+    } else {
+        dirs::home_dir().unwrap_or_else(|| PathBuf::from("."))
+    };
+    base_dir.join(".nanobot")
+}
+
+/// The active profile's name, [`DEFAULT_PROFILE`] when none is selected.
+pub fn active() -> String {
+    SELECTED
+        .get()
+        .cloned()
+        .flatten()
+        .or_else(|| std::env::var("NANOBOT_PROFILE").ok())
+        .or_else(|| std::fs::read_to_string(root_dir().join(CURRENT_FILE)).ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty() && validate_name(name).is_ok())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+/// Directory holding `name`'s config and data.
+pub fn dir(name: &str) -> PathBuf {
+    if name == DEFAULT_PROFILE {
+        root_dir()
+    } else {
+        root_dir().join("profiles").join(name)
+    }
+}
+
+/// Directory of the active profile.
+pub fn active_dir() -> PathBuf {
+    dir(&active())
+}
+
+/// All profiles, `default` first.
+pub fn list() -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(root_dir().join("profiles"))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| e.path().is_dir())
+                .filter_map(|e| e.file_name().into_string().ok())
+                .filter(|name| validate_name(name).is_ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    names
+}
+
+/// Create profile `name` with a config whose workspace is inside the
+/// profile. With `from`, that profile's config is copied (the workspace
+/// still points at the new profile).
+pub fn create(name: &str, from: Option<&str>) -> Result<PathBuf, ConfigError> {
+    validate_name(name)?;
+    if name == DEFAULT_PROFILE {
+        return Err(ConfigError::Invalid("the default profile always exists".to_string()));
+    }
+    let path = dir(name);
+    if path.exists() {
+        return Err(ConfigError::Invalid(format!("profile '{}' already exists", name)));
+    }
+
+    let mut config = match from {
+        Some(from) => {
+            let source = dir(from).join("config.json");
+            if !source.exists() {
+                return Err(ConfigError::NotFound(source));
+            }
+            super::load_config(Some(&source))
+        }
+        None => super::Config::default(),
+    };
+    config.agents.defaults.workspace = workspace_setting(name);
+    super::save_config(&config, Some(&path.join("config.json")))?;
+    std::fs::create_dir_all(path.join("workspace")).map_err(|e| ConfigError::Invalid(e.to_string()))?;
+    Ok(path)
+}
+
+/// Make `name` the profile used when neither `--profile` nor
+/// `NANOBOT_PROFILE` is given.
+pub fn switch(name: &str) -> Result<(), ConfigError> {
+    validate_name(name)?;
+    let current = root_dir().join(CURRENT_FILE);
+    if name == DEFAULT_PROFILE {
+        return match std::fs::remove_file(&current) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(ConfigError::Invalid(e.to_string())),
+            _ => Ok(()),
+        };
+    }
+    if !dir(name).exists() {
+        return Err(ConfigError::NotFound(dir(name)));
+    }
+    std::fs::write(&current, name).map_err(|e| ConfigError::Invalid(e.to_string()))
+}
+
+/// `agents.defaults.workspace` for a new profile, in `~/` form so the
+/// config stays portable.
+fn workspace_setting(name: &str) -> String {
+    format!("~/.nanobot/profiles/{}/workspace", name)
+}
+
+/// Profile names become directory names: letters, digits, `-` and `_`.
+pub fn validate_name(name: &str) -> Result<(), ConfigError> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(ConfigError::Invalid(format!(
+            "invalid profile name '{}' (use letters, digits, '-' and '_')",
+            name
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("work").is_ok());
+        assert!(validate_name("client_a-2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("../etc").is_err());
+        assert!(validate_name("a b").is_err());
+    }
+
+    #[test]
+    fn test_dir() {
+        assert_eq!(dir(DEFAULT_PROFILE), root_dir());
+        assert_eq!(dir("work"), root_dir().join("profiles").join("work"));
+        assert_eq!(workspace_setting("work"), "~/.nanobot/profiles/work/workspace");
+    }
+}
@@ -0,0 +1,358 @@
+//! Checks on `config.json` beyond what serde enforces: keys that match no
+//! setting (serde's `default` silently drops typos like `"telgram"`), values
+//! out of range, and settings that contradict each other, such as an enabled
+//! channel without a token. Used by `chatweb config validate` and, as
+//! warnings, whenever the config is loaded.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::Config;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// One problem, located by its JSON path and, when known, line.
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    /// Dotted path like `channels.telegram.token`.
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Issue {
+    fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Error, path: path.into(), line: None, message: message.into() }
+    }
+
+    fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Warning, path: path.into(), line: None, message: message.into() }
+    }
+}
+
+/// Everything wrong with one config file.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    pub fn errors(&self) -> usize {
+        self.issues.iter().filter(|i| i.severity == Severity::Error).count()
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.issues.len() - self.errors()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.errors() == 0
+    }
+
+    /// Text report, one line per issue.
+    pub fn render(&self, source: &str) -> String {
+        if self.issues.is_empty() {
+            return format!("{}: no problems found\n", source);
+        }
+        let mut out = format!("{}: {} error(s), {} warning(s)\n", source, self.errors(), self.warnings());
+        for issue in &self.issues {
+            let mark = match issue.severity {
+                Severity::Error => "✗",
+                Severity::Warning => "!",
+            };
+            let line = issue.line.map(|l| format!("line {}", l)).unwrap_or_default();
+            out.push_str(&format!("  {} {:<9} {}: {}\n", mark, line, issue.path, issue.message));
+        }
+        out
+    }
+}
+
+/// Validate the text of a config file: [`check_keys`] plus [`Config::validate`].
+pub fn validate_str(content: &str, strict: bool) -> Report {
+    let (mut report, config) = check(content, strict);
+    for mut issue in config.map(|c| c.validate()).unwrap_or_default() {
+        let key = issue.path.rsplit('.').next().unwrap_or(&issue.path).to_string();
+        issue.line = key_line(content, &key);
+        report.issues.push(issue);
+    }
+    report
+}
+
+/// Syntax errors and unknown keys only; settings that may still be filled
+/// in from the environment are not checked. In `strict` mode (or with
+/// `"strict": true` in the file) unknown keys are errors, not warnings.
+pub fn check_keys(content: &str, strict: bool) -> Report {
+    check(content, strict).0
+}
+
+fn check(content: &str, strict: bool) -> (Report, Option<Config>) {
+    let mut report = Report::default();
+    let parsed = serde_json::from_str::<Value>(content)
+        .map_err(|e| (format!("not valid JSON: {}", e), e.line()))
+        .and_then(|input| {
+            let config = serde_json::from_str::<Config>(content).map_err(|e| (e.to_string(), e.line()))?;
+            Ok((input, config))
+        });
+    let (input, config) = match parsed {
+        Ok(parsed) => parsed,
+        Err((message, line)) => {
+            let mut issue = Issue::error("(file)", message);
+            issue.line = Some(line);
+            report.issues.push(issue);
+            return (report, None);
+        }
+    };
+
+    let strict = strict || config.strict;
+    let known = serde_json::to_value(&config).unwrap_or(Value::Null);
+    let mut unknown = Vec::new();
+    unknown_keys(&input, &known, "", &mut unknown);
+    for (path, suggestion) in unknown {
+        let message = match suggestion {
+            Some(s) => format!("unknown key (did you mean \"{}\"?)", s),
+            None => "unknown key".to_string(),
+        };
+        let key = path.rsplit('.').next().unwrap_or(&path).to_string();
+        let mut issue = if strict { Issue::error(path, message) } else { Issue::warning(path, message) };
+        issue.line = key_line(content, &key);
+        report.issues.push(issue);
+    }
+    (report, Some(config))
+}
+
+impl Config {
+    /// Range and cross-field checks on loaded settings.
+    pub fn validate(&self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+
+        let gateway = &self.gateway;
+        if gateway.port == 0 {
+            issues.push(Issue::error("gateway.port", "must be between 1 and 65535"));
+        }
+        if let Err(e) = gateway.listen_addrs(gateway.port.max(1)) {
+            issues.push(Issue::error("gateway.listen", e.to_string()));
+        }
+        if gateway.tls_cert.is_some() != gateway.tls_key.is_some() {
+            issues.push(Issue::error("gateway.tlsCert", "tlsCert and tlsKey must be set together"));
+        }
+        if let Some(url) = &gateway.public_url {
+            if !url.starts_with("https://") {
+                issues.push(Issue::warning("gateway.publicUrl", "Telegram and LINE only deliver webhooks to https:// URLs"));
+            }
+        }
+        if gateway.admin_ui && gateway.api_tokens.is_empty() {
+            issues.push(Issue::warning("gateway.adminUi", "the dashboard shows no data until gateway.apiTokens is set"));
+        }
+        if !matches!(gateway.webui.theme.as_str(), "dark" | "light" | "auto") {
+            issues.push(Issue::error("gateway.webui.theme", "must be \"dark\", \"light\" or \"auto\""));
+        }
+
+        let http = &self.http;
+        for (name, secs) in [
+            ("connectTimeoutSecs", http.connect_timeout_secs),
+            ("llmTimeoutSecs", http.llm_timeout_secs),
+            ("webhookTimeoutSecs", http.webhook_timeout_secs),
+            ("defaultTimeoutSecs", http.default_timeout_secs),
+        ] {
+            if secs == 0 || secs > 3600 {
+                issues.push(Issue::error(format!("http.{}", name), "must be between 1 and 3600 seconds"));
+            }
+        }
+        if let Some(proxy) = http.proxy.as_deref().filter(|p| !p.is_empty()) {
+            if reqwest::Url::parse(proxy).is_err() {
+                issues.push(Issue::error("http.proxy", format!("\"{}\" is not a URL", proxy)));
+            }
+        }
+        if !matches!(http.dns.ip_family.as_str(), "any" | "ipv4" | "ipv6" | "prefer-ipv4" | "prefer-ipv6") {
+            issues.push(Issue::error(
+                "http.dns.ipFamily",
+                "must be \"any\", \"ipv4\", \"ipv6\", \"prefer-ipv4\" or \"prefer-ipv6\"",
+            ));
+        }
+
+        let policy = &self.tools.policy;
+        let rules = policy.rules.iter().map(|(name, rule)| (format!("tools.policy.rules.{}", name), rule));
+        for (path, rule) in std::iter::once(("tools.policy.default".to_string(), &policy.default)).chain(rules) {
+            if !matches!(rule.as_str(), "allow" | "deny" | "ask") {
+                issues.push(Issue::error(path, "must be \"allow\", \"deny\" or \"ask\""));
+            }
+        }
+
+        let target = self.sync.target.as_str();
+        let known_target = target.is_empty()
+            || target == "chatweb"
+            || target.starts_with("s3://")
+            || target.starts_with("https://")
+            || target.starts_with("http://");
+        if !known_target {
+            issues.push(Issue::error("sync.target", "must be \"chatweb\", s3://bucket/key or a WebDAV URL"));
+        } else if target.starts_with("http://") {
+            issues.push(Issue::warning("sync.target", "WebDAV credentials are sent unencrypted over http://"));
+        }
+
+        let defaults = &self.agents.defaults;
+        if defaults.max_tokens == 0 {
+            issues.push(Issue::error("agents.defaults.maxTokens", "must be at least 1"));
+        }
+        if !(0.0..=2.0).contains(&defaults.temperature) {
+            issues.push(Issue::error("agents.defaults.temperature", "must be between 0 and 2"));
+        }
+        let memory = &self.agents.memory;
+        if !memory.schedule.is_empty() && !crate::service::cron::is_valid_expr(&memory.schedule) {
+            issues.push(Issue::error("agents.memory.schedule", format!("\"{}\" is not a cron expression", memory.schedule)));
+        }
+        if !memory.prompt.is_empty() && !memory.prompt.contains("{daily}") {
+            issues.push(Issue::error("agents.memory.prompt", "must contain {daily}"));
+        }
+        if memory.days == 0 {
+            issues.push(Issue::error("agents.memory.days", "must be at least 1"));
+        }
+        if self.agents.routing.log_retention_days == 0 {
+            issues.push(Issue::error("agents.routing.logRetentionDays", "must be at least 1"));
+        }
+        for (i, hook) in self.agents.hooks.iter().enumerate() {
+            for pattern in &hook.redact {
+                if let Err(e) = regex::Regex::new(pattern) {
+                    issues.push(Issue::error(format!("agents.hooks[{}].redact", i), format!("\"{}\": {}", pattern, e)));
+                }
+            }
+        }
+
+        // Channel, whether it is enabled, and the (key, value) fields it needs
+        type Required<'a> = (&'a str, bool, &'a [(&'a str, &'a str)]);
+        let c = &self.channels;
+        let required: [Required; 11] = [
+            ("telegram", c.telegram.enabled, &[("token", c.telegram.token.as_str())]),
+            ("discord", c.discord.enabled, &[("token", c.discord.token.as_str())]),
+            ("line", c.line.enabled, &[("channelAccessToken", c.line.channel_access_token.as_str()), ("channelSecret", c.line.channel_secret.as_str())]),
+            ("slack", c.slack.enabled, &[("botToken", c.slack.bot_token.as_str())]),
+            ("feishu", c.feishu.enabled, &[("appId", c.feishu.app_id.as_str()), ("appSecret", c.feishu.app_secret.as_str())]),
+            ("whatsapp", c.whatsapp.enabled, &[("bridgeUrl", c.whatsapp.bridge_url.as_str())]),
+            ("imessage", c.imessage.enabled, &[("bridgeUrl", c.imessage.bridge_url.as_str())]),
+            ("teams", c.teams.enabled, &[("appId", c.teams.app_id.as_str()), ("appPassword", c.teams.app_password.as_str())]),
+            ("googleChat", c.google_chat.enabled, &[("serviceAccountKey", c.google_chat.service_account_key.as_str())]),
+            ("matrix", c.matrix.enabled, &[("homeserver", c.matrix.homeserver.as_str()), ("accessToken", c.matrix.access_token.as_str())]),
+            ("zalo", c.zalo.enabled, &[("botToken", c.zalo.bot_token.as_str())]),
+        ];
+        for (channel, enabled, fields) in required {
+            if !enabled {
+                continue;
+            }
+            for (field, value) in fields {
+                if value.trim().is_empty() {
+                    issues.push(Issue::error(format!("channels.{}.{}", channel, field), "required when the channel is enabled"));
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+/// Keys in `input` that have no counterpart in `known` (the config as serde
+/// understood it), with the closest known key as a suggestion.
+fn unknown_keys(input: &Value, known: &Value, path: &str, out: &mut Vec<(String, Option<String>)>) {
+    match (input, known) {
+        (Value::Object(input), Value::Object(known)) => {
+            for (key, value) in input {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                match known.get(key) {
+                    Some(known_value) => unknown_keys(value, known_value, &child, out),
+                    None => out.push((child, closest(key, known.keys()))),
+                }
+            }
+        }
+        (Value::Array(input), Value::Array(known)) => {
+            for (i, (value, known_value)) in input.iter().zip(known).enumerate() {
+                unknown_keys(value, known_value, &format!("{}[{}]", path, i), out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The candidate within edit distance 2 of `key`, if any.
+fn closest<'a>(key: &str, candidates: impl Iterator<Item = &'a String>) -> Option<String> {
+    candidates
+        .map(|c| (edit_distance(&key.to_lowercase(), &c.to_lowercase()), c))
+        .filter(|(d, _)| *d <= 2)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c.clone())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = if ca == *cb { prev } else { 1 + prev.min(row[j]).min(cur) };
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
+
+/// Line (1-based) where `"key":` first appears.
+fn key_line(content: &str, key: &str) -> Option<usize> {
+    let quoted = format!("\"{}\"", key);
+    content
+        .lines()
+        .position(|line| {
+            line.find(&quoted)
+                .is_some_and(|at| line[at + quoted.len()..].trim_start().starts_with(':'))
+        })
+        .map(|i| i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+  "channels": {
+    "telgram": { "enabled": true },
+    "discord": { "enabled": true, "token": "" }
+  },
+  "gateway": { "port": 0 },
+  "http": { "llmTimeoutSecs": 0 }
+}"#;
+
+    #[test]
+    fn test_validate_report() {
+        let report = validate_str(CONFIG, false);
+        let unknown = report.issues.iter().find(|i| i.path == "channels.telgram").unwrap();
+        assert_eq!(unknown.severity, Severity::Warning);
+        assert_eq!(unknown.line, Some(3));
+        assert!(unknown.message.contains("\"telegram\""));
+
+        let paths: Vec<&str> = report.issues.iter().filter(|i| i.severity == Severity::Error).map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, vec!["gateway.port", "http.llmTimeoutSecs", "channels.discord.token"]);
+
+        assert_eq!(validate_str(CONFIG, true).errors(), 4);
+        assert!(validate_str("{}", true).issues.is_empty());
+    }
+
+    #[test]
+    fn test_syntax_error_line() {
+        let report = validate_str("{\n  \"agents\": {,\n}", false);
+        assert_eq!(report.issues[0].line, Some(2));
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("telgram", "telegram"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
@@ -0,0 +1,303 @@
+//! Credit ledger: an append-only log of every change to a user's credits.
+//!
+//! The balance itself stays on the user profile (`credits_remaining`) and is
+//! what requests are checked against; the ledger itemizes how it got there —
+//! grants, coupons, referrals, purchases, subscription renewals and usage.
+//! Reading the history reconciles the two (see [`history`]): a difference left
+//! by changes that weren't recorded, such as balances from before the ledger
+//! existed or plan downgrades, is appended as an `adjustment`. Reconciling is
+//! skipped while a change recorded after the balance was read shows it stale.
+//!
+//! Transactions are stored by a [`CreditLedger`]:
+//!
+//! - [`DbLedger`] — the `credit_transactions` table of a [`DbBackend`] (libSQL/Turso)
+//! - `DynamoLedger` — `CREDITS#<user>` items in the config table
+//!   (feature `dynamodb-backend`)
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::db::DbBackend;
+
+/// Transactions read when reconciling, however few are shown.
+const RECONCILE_WINDOW: usize = 50;
+
+/// What a transaction was for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CreditKind {
+    /// Free credits: sign-up, apologies, daily bonuses.
+    Grant,
+    Purchase,
+    /// Monthly allowance of a paid plan.
+    Subscription,
+    Coupon,
+    Referral,
+    Usage,
+    Refund,
+    /// Correction found by reconciliation.
+    Adjustment,
+}
+
+impl CreditKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CreditKind::Grant => "grant",
+            CreditKind::Purchase => "purchase",
+            CreditKind::Subscription => "subscription",
+            CreditKind::Coupon => "coupon",
+            CreditKind::Referral => "referral",
+            CreditKind::Usage => "usage",
+            CreditKind::Refund => "refund",
+            CreditKind::Adjustment => "adjustment",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::json!(s)).ok()
+    }
+}
+
+/// One change to a user's credits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreditTransaction {
+    pub id: String,
+    /// RFC 3339.
+    pub at: String,
+    pub kind: CreditKind,
+    /// Credits added (positive) or spent (negative).
+    pub amount: i64,
+    /// Balance right after the change, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<i64>,
+    /// What it was for: the model, coupon code, payment...
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+}
+
+impl CreditTransaction {
+    pub fn new(kind: CreditKind, amount: i64, balance: Option<i64>, note: impl Into<String>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string()[..8].to_string(),
+            at: crate::util::timestamp(),
+            kind,
+            amount,
+            balance,
+            note: note.into(),
+        }
+    }
+
+    /// Whether this was recorded at or after `t`. Unparseable times count as
+    /// older.
+    pub fn recorded_since(&self, t: chrono::DateTime<chrono::Utc>) -> bool {
+        chrono::DateTime::parse_from_rfc3339(&self.at).is_ok_and(|at| at >= t)
+    }
+
+    /// `2026-03-02 10:15  usage        -12  (988)  claude-sonnet`
+    pub fn line(&self) -> String {
+        let at = self.at.get(..16).unwrap_or(&self.at).replace('T', " ");
+        let balance = self.balance.map(|b| format!("({})", b)).unwrap_or_default();
+        format!("{}  {:<12} {:>+7}  {:<8} {}", at, self.kind.as_str(), self.amount, balance, self.note)
+            .trim_end()
+            .to_string()
+    }
+}
+
+/// Storage for credit transactions. Entries are only ever appended.
+#[async_trait]
+pub trait CreditLedger: Send + Sync {
+    async fn append(&self, user_id: &str, tx: &CreditTransaction) -> anyhow::Result<()>;
+
+    /// The newest `limit` transactions, newest first.
+    async fn recent(&self, user_id: &str, limit: usize) -> anyhow::Result<Vec<CreditTransaction>>;
+}
+
+/// Record `tx`, logging failures: the balance has already changed, so the
+/// caller carries on either way.
+pub async fn record(ledger: &dyn CreditLedger, user_id: &str, tx: CreditTransaction) {
+    if let Err(e) = ledger.append(user_id, &tx).await {
+        error!("Failed to record credit transaction for {}: {} ({:?} {})", user_id, e, tx.kind, tx.amount);
+    }
+}
+
+/// How far `balance` is from what `recent` (newest first) accounts for: the
+/// newest known balance plus the amounts recorded after it. An empty ledger
+/// accounts for nothing; without any known balance there's nothing to compare.
+pub fn discrepancy(recent: &[CreditTransaction], balance: i64) -> Option<i64> {
+    if recent.is_empty() {
+        return (balance != 0).then_some(balance);
+    }
+    let mut newer = 0;
+    for tx in recent {
+        if let Some(after) = tx.balance {
+            let expected = after + newer;
+            return (expected != balance).then_some(balance - expected);
+        }
+        newer += tx.amount;
+    }
+    None
+}
+
+/// The newest `limit` transactions of `user_id`, newest first, after
+/// reconciling the ledger with the profile's `balance`, read at `read_at`.
+///
+/// The balance must be read before the ledger. A transaction recorded since
+/// then means the balance is already stale, so nothing is reconciled.
+pub async fn history(
+    ledger: &dyn CreditLedger,
+    user_id: &str,
+    balance: i64,
+    read_at: chrono::DateTime<chrono::Utc>,
+    limit: usize,
+) -> anyhow::Result<Vec<CreditTransaction>> {
+    let mut recent = ledger.recent(user_id, limit.max(RECONCILE_WINDOW)).await?;
+    let stale = recent.first().is_some_and(|tx| tx.recorded_since(read_at));
+    if let Some(diff) = discrepancy(&recent, balance).filter(|_| !stale) {
+        let note = if recent.is_empty() { "opening balance" } else { "reconciliation" };
+        let tx = CreditTransaction::new(CreditKind::Adjustment, diff, Some(balance), note);
+        ledger.append(user_id, &tx).await?;
+        recent.insert(0, tx);
+    }
+    recent.truncate(limit);
+    Ok(recent)
+}
+
+/// The `credit_transactions` table of a database backend.
+pub struct DbLedger(pub Arc<dyn DbBackend>);
+
+#[async_trait]
+impl CreditLedger for DbLedger {
+    async fn append(&self, user_id: &str, tx: &CreditTransaction) -> anyhow::Result<()> {
+        self.0.append_credit_transaction(user_id, tx).await
+    }
+
+    async fn recent(&self, user_id: &str, limit: usize) -> anyhow::Result<Vec<CreditTransaction>> {
+        self.0.list_credit_transactions(user_id, limit).await
+    }
+}
+
+/// `pk = CREDITS#<user>`, `sk = <at>#<id>` items in the config table.
+#[cfg(feature = "dynamodb-backend")]
+pub struct DynamoLedger {
+    client: aws_sdk_dynamodb::Client,
+    table: String,
+}
+
+#[cfg(feature = "dynamodb-backend")]
+impl DynamoLedger {
+    pub fn new(client: aws_sdk_dynamodb::Client, table: impl Into<String>) -> Self {
+        Self { client, table: table.into() }
+    }
+}
+
+#[cfg(feature = "dynamodb-backend")]
+#[async_trait]
+impl CreditLedger for DynamoLedger {
+    async fn append(&self, user_id: &str, tx: &CreditTransaction) -> anyhow::Result<()> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+        self.client
+            .put_item()
+            .table_name(&self.table)
+            .item("pk", AttributeValue::S(format!("CREDITS#{}", user_id)))
+            .item("sk", AttributeValue::S(format!("{}#{}", tx.at, tx.id)))
+            .item("data", AttributeValue::S(serde_json::to_string(tx)?))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn recent(&self, user_id: &str, limit: usize) -> anyhow::Result<Vec<CreditTransaction>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+        let resp = self
+            .client
+            .query()
+            .table_name(&self.table)
+            .key_condition_expression("pk = :pk")
+            .expression_attribute_values(":pk", AttributeValue::S(format!("CREDITS#{}", user_id)))
+            .scan_index_forward(false)
+            .limit(limit.min(i32::MAX as usize) as i32)
+            .send()
+            .await?;
+        Ok(resp
+            .items()
+            .iter()
+            .filter_map(|item| item.get("data")?.as_s().ok())
+            .filter_map(|data| serde_json::from_str(data).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keeps transactions in memory.
+    #[derive(Default)]
+    struct MemLedger(tokio::sync::Mutex<Vec<CreditTransaction>>);
+
+    #[async_trait]
+    impl CreditLedger for MemLedger {
+        async fn append(&self, _user_id: &str, tx: &CreditTransaction) -> anyhow::Result<()> {
+            self.0.lock().await.push(tx.clone());
+            Ok(())
+        }
+
+        async fn recent(&self, _user_id: &str, limit: usize) -> anyhow::Result<Vec<CreditTransaction>> {
+            Ok(self.0.lock().await.iter().rev().take(limit).cloned().collect())
+        }
+    }
+
+    #[test]
+    fn test_discrepancy() {
+        let usage = |amount, balance| CreditTransaction::new(CreditKind::Usage, amount, balance, "");
+        assert_eq!(discrepancy(&[], 0), None);
+        assert_eq!(discrepancy(&[], 1000), Some(1000));
+        // Newest first: a coupon without a known balance after usage that left 980
+        let recent = [CreditTransaction::new(CreditKind::Coupon, 500, None, "WELCOME"), usage(-20, Some(980))];
+        assert_eq!(discrepancy(&recent, 1480), None);
+        assert_eq!(discrepancy(&recent, 1000), Some(-480));
+        assert_eq!(discrepancy(&[usage(-5, None)], 10), None);
+    }
+
+    #[tokio::test]
+    async fn test_history_reconciles() {
+        let ledger = MemLedger::default();
+        let opening = history(&ledger, "u1", 1000, chrono::Utc::now(), 10).await.unwrap();
+        assert_eq!(opening.len(), 1);
+        assert_eq!((opening[0].kind, opening[0].amount), (CreditKind::Adjustment, 1000));
+
+        record(&ledger, "u1", CreditTransaction::new(CreditKind::Usage, -30, Some(970), "gpt-4o")).await;
+        record(&ledger, "u1", CreditTransaction::new(CreditKind::Referral, 100, None, "")).await;
+        let settled = history(&ledger, "u1", 1070, chrono::Utc::now(), 10).await.unwrap();
+        assert_eq!(settled.len(), 3, "nothing to reconcile");
+        assert_eq!(settled[0].kind, CreditKind::Referral);
+
+        // A plan downgrade reset the balance without a record
+        let reset = history(&ledger, "u1", 100, chrono::Utc::now(), 2).await.unwrap();
+        assert_eq!(reset.len(), 2);
+        assert_eq!((reset[0].amount, reset[0].balance), (-970, Some(100)));
+        assert!(reset[0].line().contains("adjustment"));
+    }
+
+    #[tokio::test]
+    async fn test_history_skips_stale_balance() {
+        let ledger = MemLedger::default();
+        record(&ledger, "u1", CreditTransaction::new(CreditKind::Grant, 1000, Some(1000), "sign-up")).await;
+
+        // The balance is read, then usage is deducted and recorded before the ledger is read
+        let read_at = chrono::Utc::now();
+        let balance = 1000;
+        record(&ledger, "u1", CreditTransaction::new(CreditKind::Usage, -30, Some(970), "gpt-4o")).await;
+        let listed = history(&ledger, "u1", balance, read_at, 10).await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].kind, CreditKind::Usage);
+
+        // Read again afterwards, the balance agrees and nothing was appended
+        let settled = history(&ledger, "u1", 970, chrono::Utc::now(), 10).await.unwrap();
+        assert_eq!(settled, listed);
+    }
+}
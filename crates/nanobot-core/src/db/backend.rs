@@ -478,6 +478,56 @@ pub trait DbBackend: Send + Sync {
         tokens: i64,
     ) -> anyhow::Result<()>;
 
+    // -----------------------------------------------------------------------
+    // Expenses (see `crate::expense`)
+    // -----------------------------------------------------------------------
+
+    /// Record an expense entry.
+    async fn add_expense(&self, user_id: &str, expense: &crate::expense::Expense) -> anyhow::Result<()>;
+
+    /// Entries dated `from..=to` (`YYYY-MM-DD`), oldest first.
+    async fn list_expenses(
+        &self,
+        user_id: &str,
+        from: &str,
+        to: &str,
+    ) -> anyhow::Result<Vec<crate::expense::Expense>>;
+
+    /// Delete an entry. Returns `true` if it existed.
+    async fn delete_expense(&self, user_id: &str, id: &str) -> anyhow::Result<bool>;
+
+    // -----------------------------------------------------------------------
+    // Credit ledger (see `crate::credits`)
+    // -----------------------------------------------------------------------
+
+    /// Append a credit transaction.
+    async fn append_credit_transaction(
+        &self,
+        user_id: &str,
+        tx: &crate::credits::CreditTransaction,
+    ) -> anyhow::Result<()>;
+
+    /// The newest `limit` transactions, newest first.
+    async fn list_credit_transactions(
+        &self,
+        user_id: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<crate::credits::CreditTransaction>>;
+
+    // -----------------------------------------------------------------------
+    // Omikuji (see `crate::omikuji`)
+    // -----------------------------------------------------------------------
+
+    /// Record a user's draw for `date` (`YYYY-MM-DD`).
+    /// Returns `false` if they already drew that day.
+    async fn record_omikuji_draw(
+        &self,
+        user_id: &str,
+        date: &str,
+        fortune: &str,
+        credits: i64,
+    ) -> anyhow::Result<bool>;
+
     // -----------------------------------------------------------------------
     // Migrations (run once on startup)
     // -----------------------------------------------------------------------
@@ -1352,6 +1352,158 @@ impl DbBackend for LibSqlBackend {
         Ok(())
     }
 
+    // -----------------------------------------------------------------------
+    // Expenses
+    // -----------------------------------------------------------------------
+
+    async fn add_expense(&self, user_id: &str, expense: &crate::expense::Expense) -> anyhow::Result<()> {
+        let conn = self.conn().await?;
+        conn.execute(
+            "INSERT INTO expenses (user_id, id, date, amount, currency, vendor, category, note, created_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            libsql::params![
+                user_id,
+                expense.id.as_str(),
+                expense.date.as_str(),
+                expense.amount,
+                expense.currency.as_str(),
+                expense.vendor.as_str(),
+                expense.category.as_str(),
+                expense.note.as_str(),
+                expense.created_at.as_str()
+            ],
+        )
+        .await
+        .context("add_expense")?;
+        Ok(())
+    }
+
+    async fn list_expenses(
+        &self,
+        user_id: &str,
+        from: &str,
+        to: &str,
+    ) -> anyhow::Result<Vec<crate::expense::Expense>> {
+        let conn = self.conn().await?;
+        let mut rows = conn
+            .query(
+                "SELECT id, date, amount, currency, vendor, category, note, created_at FROM expenses \
+                 WHERE user_id = ?1 AND date >= ?2 AND date <= ?3 ORDER BY date, created_at",
+                libsql::params![user_id, from, to],
+            )
+            .await
+            .context("list_expenses")?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next().await? {
+            result.push(crate::expense::Expense {
+                id: row.get(0)?,
+                date: row.get(1)?,
+                amount: row.get(2)?,
+                currency: row.get(3)?,
+                vendor: row.get(4)?,
+                category: row.get(5)?,
+                note: row.get(6)?,
+                created_at: row.get(7)?,
+            });
+        }
+        Ok(result)
+    }
+
+    async fn delete_expense(&self, user_id: &str, id: &str) -> anyhow::Result<bool> {
+        let conn = self.conn().await?;
+        let deleted = conn
+            .execute(
+                "DELETE FROM expenses WHERE user_id = ?1 AND id = ?2",
+                libsql::params![user_id, id],
+            )
+            .await
+            .context("delete_expense")?;
+        Ok(deleted > 0)
+    }
+
+    // -----------------------------------------------------------------------
+    // Credit ledger
+    // -----------------------------------------------------------------------
+
+    async fn append_credit_transaction(
+        &self,
+        user_id: &str,
+        tx: &crate::credits::CreditTransaction,
+    ) -> anyhow::Result<()> {
+        let conn = self.conn().await?;
+        conn.execute(
+            "INSERT INTO credit_transactions (user_id, id, at, kind, amount, balance, note) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            libsql::params![
+                user_id,
+                tx.id.as_str(),
+                tx.at.as_str(),
+                tx.kind.as_str(),
+                tx.amount,
+                tx.balance,
+                tx.note.as_str()
+            ],
+        )
+        .await
+        .context("append_credit_transaction")?;
+        Ok(())
+    }
+
+    async fn list_credit_transactions(
+        &self,
+        user_id: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<crate::credits::CreditTransaction>> {
+        let conn = self.conn().await?;
+        let mut rows = conn
+            .query(
+                "SELECT id, at, kind, amount, balance, note FROM credit_transactions \
+                 WHERE user_id = ?1 ORDER BY at DESC, rowid DESC LIMIT ?2",
+                libsql::params![user_id, limit as i64],
+            )
+            .await
+            .context("list_credit_transactions")?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let kind: String = row.get(2)?;
+            result.push(crate::credits::CreditTransaction {
+                id: row.get(0)?,
+                at: row.get(1)?,
+                kind: crate::credits::CreditKind::parse(&kind).unwrap_or(crate::credits::CreditKind::Adjustment),
+                amount: row.get(3)?,
+                balance: row.get(4)?,
+                note: row.get(5)?,
+            });
+        }
+        Ok(result)
+    }
+
+    // -----------------------------------------------------------------------
+    // Omikuji
+    // -----------------------------------------------------------------------
+
+    async fn record_omikuji_draw(
+        &self,
+        user_id: &str,
+        date: &str,
+        fortune: &str,
+        credits: i64,
+    ) -> anyhow::Result<bool> {
+        let conn = self.conn().await?;
+        let now = now_rfc3339();
+        let inserted = conn
+            .execute(
+                "INSERT INTO omikuji_draws (user_id, date, fortune, credits, drawn_at) \
+                 VALUES (?1, ?2, ?3, ?4, ?5) ON CONFLICT (user_id, date) DO NOTHING",
+                libsql::params![user_id, date, fortune, credits, now.as_str()],
+            )
+            .await
+            .context("record_omikuji_draw")?;
+        Ok(inserted > 0)
+    }
+
     // -----------------------------------------------------------------------
     // Migrations
     // -----------------------------------------------------------------------
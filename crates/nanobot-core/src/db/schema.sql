@@ -258,3 +258,49 @@ CREATE TABLE IF NOT EXISTS sokora_nodes (
 );
 
 CREATE INDEX IF NOT EXISTS idx_sokora_nodes_last_seen ON sokora_nodes (last_seen);
+
+-- ---------------------------------------------------------------------------
+-- Expense ledger (crate::expense)
+-- ---------------------------------------------------------------------------
+CREATE TABLE IF NOT EXISTS expenses (
+    user_id     TEXT    NOT NULL,
+    id          TEXT    NOT NULL,
+    date        TEXT    NOT NULL,
+    amount      REAL    NOT NULL,
+    currency    TEXT    NOT NULL,
+    vendor      TEXT    NOT NULL DEFAULT '',
+    category    TEXT    NOT NULL DEFAULT 'other',
+    note        TEXT    NOT NULL DEFAULT '',
+    created_at  TEXT    NOT NULL,
+    PRIMARY KEY (user_id, id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_expenses_user_date ON expenses (user_id, date);
+
+-- ---------------------------------------------------------------------------
+-- Credit ledger, append-only (crate::credits)
+-- ---------------------------------------------------------------------------
+CREATE TABLE IF NOT EXISTS credit_transactions (
+    user_id     TEXT    NOT NULL,
+    id          TEXT    NOT NULL,
+    at          TEXT    NOT NULL,
+    kind        TEXT    NOT NULL,
+    amount      INTEGER NOT NULL,
+    balance     INTEGER,
+    note        TEXT    NOT NULL DEFAULT '',
+    PRIMARY KEY (user_id, id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_credit_transactions_user_at ON credit_transactions (user_id, at);
+
+-- ---------------------------------------------------------------------------
+-- Omikuji daily draws (crate::omikuji)
+-- ---------------------------------------------------------------------------
+CREATE TABLE IF NOT EXISTS omikuji_draws (
+    user_id     TEXT    NOT NULL,
+    date        TEXT    NOT NULL,
+    fortune     TEXT    NOT NULL,
+    credits     INTEGER NOT NULL,
+    drawn_at    TEXT    NOT NULL,
+    PRIMARY KEY (user_id, date)
+);
@@ -0,0 +1,349 @@
+//! Delivery tracking for outbound messages.
+//!
+//! Every tracked [`OutboundMessage`] gets a delivery id (stored in its
+//! metadata) and a [`DeliveryRecord`] that moves through
+//! `queued → sent → delivered → read`, or ends in `failed`. Channels report
+//! the platform message id on send; receipts (e.g. WhatsApp `statuses`
+//! webhooks) are matched back to the record by that id. Records are kept in
+//! a JSON file under the data directory, like cron jobs.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::types::OutboundMessage;
+use crate::util::{now_ms, read_json, write_json};
+
+/// Outbound metadata key holding the delivery id.
+pub const DELIVERY_ID_KEY: &str = "delivery_id";
+
+/// Oldest records beyond this count are dropped on save.
+pub const MAX_RECORDS: usize = 2000;
+
+/// Lifecycle state of an outbound message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Queued,
+    Sent,
+    Delivered,
+    Read,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "queued" => Some(Self::Queued),
+            "sent" => Some(Self::Sent),
+            "delivered" => Some(Self::Delivered),
+            "read" => Some(Self::Read),
+            "failed" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Sent => "sent",
+            Self::Delivered => "delivered",
+            Self::Read => "read",
+            Self::Failed => "failed",
+        }
+    }
+
+    /// Whether a record may move from `self` to `next`. Statuses only move
+    /// forward (receipts can arrive out of order and skip steps); a failed
+    /// message can only be re-queued for a retry.
+    pub fn can_transition(&self, next: DeliveryStatus) -> bool {
+        use DeliveryStatus::*;
+        match (self, next) {
+            (Failed, Queued) => true,
+            (Failed, _) | (Read, _) => false,
+            (_, Failed) => matches!(self, Queued | Sent),
+            (from, to) => to.rank() > from.rank(),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Queued => 0,
+            Self::Sent => 1,
+            Self::Delivered => 2,
+            Self::Read => 3,
+            Self::Failed => 4,
+        }
+    }
+}
+
+/// One status change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryEvent {
+    pub status: DeliveryStatus,
+    pub at_ms: u64,
+}
+
+/// Tracked state of one outbound message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryRecord {
+    pub id: String,
+    pub channel: String,
+    pub chat_id: String,
+    pub status: DeliveryStatus,
+    /// Platform message id reported by the channel (Telegram message_id, Slack ts, ...).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    /// What produced the message, e.g. `cron:<job id>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub events: Vec<DeliveryEvent>,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+impl DeliveryRecord {
+    /// Apply a transition. Returns false (and leaves the record unchanged)
+    /// when the transition is not allowed.
+    fn transition(&mut self, next: DeliveryStatus) -> bool {
+        if !self.status.can_transition(next) {
+            return false;
+        }
+        let now = now_ms();
+        self.status = next;
+        self.updated_at_ms = now;
+        self.events.push(DeliveryEvent { status: next, at_ms: now });
+        true
+    }
+}
+
+/// Filter for [`DeliveryTracker::list`].
+#[derive(Debug, Clone, Default)]
+pub struct DeliveryFilter {
+    pub channel: Option<String>,
+    pub status: Option<DeliveryStatus>,
+    pub source: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DeliveryStore {
+    #[serde(default)]
+    records: Vec<DeliveryRecord>,
+}
+
+/// File-backed store of delivery records.
+pub struct DeliveryTracker {
+    store_path: PathBuf,
+    store: Option<DeliveryStore>,
+}
+
+impl DeliveryTracker {
+    pub fn new(store_path: PathBuf) -> Self {
+        Self {
+            store_path,
+            store: None,
+        }
+    }
+
+    /// `<data dir>/delivery/outbound.json`.
+    pub fn default_path() -> PathBuf {
+        crate::config::get_data_dir().join("delivery").join("outbound.json")
+    }
+
+    fn load_store(&mut self) -> &mut DeliveryStore {
+        if self.store.is_none() {
+            self.store = Some(read_json(&self.store_path, "delivery store"));
+        }
+        self.store.as_mut().unwrap()
+    }
+
+    fn save_store(&mut self) {
+        if let Some(ref mut store) = self.store {
+            if store.records.len() > MAX_RECORDS {
+                let excess = store.records.len() - MAX_RECORDS;
+                store.records.drain(..excess);
+            }
+            write_json(&self.store_path, store, "delivery store");
+        }
+    }
+
+    /// Start tracking `msg`: assigns a delivery id (kept in its metadata) and
+    /// records it as queued. A message that already carries an id is re-queued
+    /// as a retry instead.
+    pub fn enqueue(&mut self, msg: &mut OutboundMessage, source: Option<&str>) -> String {
+        if let Some(id) = delivery_id(msg).map(str::to_string) {
+            if self.update(&id, |r| r.transition(DeliveryStatus::Queued) || r.status == DeliveryStatus::Queued) {
+                return id;
+            }
+        }
+        let id = Uuid::new_v4().to_string();
+        let now = now_ms();
+        let record = DeliveryRecord {
+            id: id.clone(),
+            channel: msg.channel.clone(),
+            chat_id: msg.chat_id.clone(),
+            status: DeliveryStatus::Queued,
+            message_id: None,
+            source: source.map(str::to_string),
+            error: None,
+            attempts: 0,
+            events: vec![DeliveryEvent { status: DeliveryStatus::Queued, at_ms: now }],
+            created_at_ms: now,
+            updated_at_ms: now,
+        };
+        self.load_store().records.push(record);
+        self.save_store();
+        msg.metadata
+            .insert(DELIVERY_ID_KEY.to_string(), serde_json::json!(id));
+        id
+    }
+
+    /// The channel accepted the message.
+    pub fn mark_sent(&mut self, id: &str, message_id: Option<String>) -> bool {
+        self.update(id, |r| {
+            r.attempts += 1;
+            if message_id.is_some() {
+                r.message_id = message_id;
+            }
+            r.error = None;
+            r.transition(DeliveryStatus::Sent)
+        })
+    }
+
+    /// Sending failed.
+    pub fn mark_failed(&mut self, id: &str, error: &str) -> bool {
+        self.update(id, |r| {
+            r.attempts += 1;
+            r.error = Some(error.to_string());
+            r.transition(DeliveryStatus::Failed)
+        })
+    }
+
+    /// Apply a receipt reported by a platform for one of its message ids.
+    pub fn mark_receipt(&mut self, channel: &str, message_id: &str, status: DeliveryStatus, error: Option<&str>) -> bool {
+        let id = self.load_store().records.iter().rev().find(|r| {
+            r.channel == channel && r.message_id.as_deref() == Some(message_id)
+        });
+        let Some(id) = id.map(|r| r.id.clone()) else {
+            return false;
+        };
+        self.update(&id, |r| {
+            if let Some(e) = error {
+                r.error = Some(e.to_string());
+            }
+            r.transition(status)
+        })
+    }
+
+    fn update(&mut self, id: &str, f: impl FnOnce(&mut DeliveryRecord) -> bool) -> bool {
+        let changed = match self.load_store().records.iter_mut().find(|r| r.id == id) {
+            Some(record) => f(record),
+            None => return false,
+        };
+        self.save_store();
+        changed
+    }
+
+    pub fn get(&mut self, id: &str) -> Option<DeliveryRecord> {
+        self.load_store().records.iter().find(|r| r.id == id).cloned()
+    }
+
+    /// Records matching `filter`, newest first.
+    pub fn list(&mut self, filter: &DeliveryFilter) -> Vec<DeliveryRecord> {
+        self.load_store()
+            .records
+            .iter()
+            .rev()
+            .filter(|r| filter.channel.as_ref().is_none_or(|c| &r.channel == c))
+            .filter(|r| filter.status.is_none_or(|s| r.status == s))
+            .filter(|r| filter.source.as_ref().is_none_or(|s| r.source.as_ref() == Some(s)))
+            .take(filter.limit.unwrap_or(100))
+            .cloned()
+            .collect()
+    }
+}
+
+/// The delivery id carried by an outbound message, if it is tracked.
+pub fn delivery_id(msg: &OutboundMessage) -> Option<&str> {
+    msg.metadata.get(DELIVERY_ID_KEY).and_then(|v| v.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_tracker() -> (tempfile::TempDir, DeliveryTracker) {
+        let tmp = tempfile::tempdir().unwrap();
+        let tracker = DeliveryTracker::new(tmp.path().join("delivery").join("outbound.json"));
+        (tmp, tracker)
+    }
+
+    #[test]
+    fn test_transitions() {
+        use DeliveryStatus::*;
+        assert!(Queued.can_transition(Sent));
+        assert!(Sent.can_transition(Read));
+        assert!(Delivered.can_transition(Read));
+        assert!(!Delivered.can_transition(Sent));
+        assert!(!Read.can_transition(Delivered));
+        assert!(Sent.can_transition(Failed));
+        assert!(!Delivered.can_transition(Failed));
+        assert!(Failed.can_transition(Queued));
+        assert!(!Failed.can_transition(Sent));
+    }
+
+    #[test]
+    fn test_lifecycle_and_receipts() {
+        let (tmp, mut tracker) = temp_tracker();
+        let mut msg = OutboundMessage::new("whatsapp", "8190", "リマインダー");
+        let id = tracker.enqueue(&mut msg, Some("cron:abc"));
+        assert_eq!(delivery_id(&msg), Some(id.as_str()));
+        assert_eq!(tracker.get(&id).unwrap().status, DeliveryStatus::Queued);
+
+        assert!(tracker.mark_sent(&id, Some("wamid.1".into())));
+        assert!(tracker.mark_receipt("whatsapp", "wamid.1", DeliveryStatus::Read, None));
+        // A late "delivered" receipt does not move the record backwards.
+        assert!(!tracker.mark_receipt("whatsapp", "wamid.1", DeliveryStatus::Delivered, None));
+        assert!(!tracker.mark_receipt("whatsapp", "unknown", DeliveryStatus::Read, None));
+
+        let record = tracker.get(&id).unwrap();
+        assert_eq!(record.status, DeliveryStatus::Read);
+        assert_eq!(record.attempts, 1);
+        assert_eq!(record.events.len(), 3);
+
+        // Persisted across instances.
+        let mut reloaded = DeliveryTracker::new(tmp.path().join("delivery").join("outbound.json"));
+        assert_eq!(reloaded.get(&id).unwrap().status, DeliveryStatus::Read);
+        let filter = DeliveryFilter { source: Some("cron:abc".into()), ..Default::default() };
+        assert_eq!(reloaded.list(&filter).len(), 1);
+    }
+
+    #[test]
+    fn test_failure_and_retry() {
+        let (_tmp, mut tracker) = temp_tracker();
+        let mut msg = OutboundMessage::new("telegram", "42", "hi");
+        let id = tracker.enqueue(&mut msg, None);
+        assert!(tracker.mark_failed(&id, "timeout"));
+        assert_eq!(tracker.get(&id).unwrap().error.as_deref(), Some("timeout"));
+
+        // Re-enqueueing the same message retries the same record.
+        assert_eq!(tracker.enqueue(&mut msg, None), id);
+        assert!(tracker.mark_sent(&id, None));
+        let record = tracker.get(&id).unwrap();
+        assert_eq!(record.status, DeliveryStatus::Sent);
+        assert_eq!(record.attempts, 2);
+        assert!(record.error.is_none());
+
+        let failed = DeliveryFilter { status: Some(DeliveryStatus::Failed), ..Default::default() };
+        assert!(tracker.list(&failed).is_empty());
+    }
+}
@@ -1,5 +1,9 @@
 use std::path::PathBuf;
 
+use serde::Serialize;
+
+use crate::session::locale::Locale;
+
 /// Core error types for nanobot.
 #[derive(Debug, thiserror::Error)]
 pub enum NanobotError {
@@ -54,10 +58,57 @@ pub enum ProviderError {
     #[error("No API key configured for provider")]
     NoApiKey,
 
+    #[error("Request timed out after {0} seconds")]
+    Timeout(u64),
+
+    #[error("All {attempts} providers failed; last error: {last}")]
+    AllFailed { attempts: usize, last: Box<ProviderError> },
+
     #[error("{0}")]
     Other(String),
 }
 
+impl ProviderError {
+    /// The error after `attempts` providers failed, `last` being the most recent failure.
+    pub fn all_failed(attempts: usize, last: Option<ProviderError>) -> Self {
+        match last {
+            Some(last) => ProviderError::AllFailed { attempts, last: Box::new(last) },
+            None => ProviderError::Other("All providers failed".to_string()),
+        }
+    }
+
+    /// HTTP status returned by the provider, if any.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ProviderError::Api { status, .. } => Some(*status),
+            ProviderError::Http(e) => e.status().map(|s| s.as_u16()),
+            ProviderError::AllFailed { last, .. } => last.status(),
+            _ => None,
+        }
+    }
+
+    /// A 4xx other than the persistent 401/402/429: the request was at fault, not the provider.
+    pub fn is_client_error(&self) -> bool {
+        matches!(self.status(), Some(s) if (400..500).contains(&s) && !matches!(s, 401 | 402 | 429))
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ProviderError::Http(e) if e.is_timeout() => ErrorCode::Timeout,
+            ProviderError::Http(e) => match e.status() {
+                Some(status) => ErrorCode::from_status(status.as_u16(), ""),
+                None => ErrorCode::Network,
+            },
+            ProviderError::Api { status, message } => ErrorCode::from_status(*status, message),
+            ProviderError::Parse(_) => ErrorCode::Unavailable,
+            ProviderError::NoApiKey => ErrorCode::Auth,
+            ProviderError::Timeout(_) => ErrorCode::Timeout,
+            ProviderError::AllFailed { last, .. } => last.code(),
+            ProviderError::Other(_) => ErrorCode::Unavailable,
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ToolError {
     #[error("Tool not found: {0}")]
@@ -76,6 +127,16 @@ pub enum ToolError {
     Timeout(u64),
 }
 
+impl ToolError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ToolError::Timeout(_) => ErrorCode::Timeout,
+            ToolError::InvalidParams(_) => ErrorCode::InvalidRequest,
+            _ => ErrorCode::ToolFailed,
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ChannelError {
     #[error("Connection error: {0}")]
@@ -103,4 +164,170 @@ pub enum SessionError {
     InvalidKey(String),
 }
 
+impl NanobotError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            NanobotError::Provider(e) => e.code(),
+            NanobotError::Tool(e) => e.code(),
+            NanobotError::Config(ConfigError::NoApiKey) => ErrorCode::Auth,
+            NanobotError::Channel(ChannelError::Connection(_)) => ErrorCode::Network,
+            NanobotError::Channel(ChannelError::Auth(_)) => ErrorCode::Auth,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+/// What kind of failure an error is, as reported to users and API clients.
+///
+/// API error responses carry it as `code` next to the message, so clients can
+/// tell a rate limit from an outage without parsing text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    RateLimited,
+    /// Credentials were missing or rejected.
+    Auth,
+    /// Credits or a provider's billing quota ran out.
+    Quota,
+    Network,
+    Timeout,
+    ToolFailed,
+    InvalidRequest,
+    /// No provider could answer.
+    Unavailable,
+    Internal,
+}
+
+impl ErrorCode {
+    /// Classify a provider's HTTP status; `message` tells quota exhaustion
+    /// apart from plain rate limiting, which share 429.
+    pub fn from_status(status: u16, message: &str) -> Self {
+        let message = message.to_lowercase();
+        match status {
+            429 if ["quota", "billing", "credit"].iter().any(|w| message.contains(w)) => ErrorCode::Quota,
+            429 => ErrorCode::RateLimited,
+            401 | 403 => ErrorCode::Auth,
+            402 => ErrorCode::Quota,
+            408 | 504 => ErrorCode::Timeout,
+            400..=499 => ErrorCode::InvalidRequest,
+            _ => ErrorCode::Unavailable,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::Auth => "auth",
+            ErrorCode::Quota => "quota",
+            ErrorCode::Network => "network",
+            ErrorCode::Timeout => "timeout",
+            ErrorCode::ToolFailed => "tool_failed",
+            ErrorCode::InvalidRequest => "invalid_request",
+            ErrorCode::Unavailable => "unavailable",
+            ErrorCode::Internal => "internal",
+        }
+    }
+
+    /// Status of API responses failing with this code. Failures of upstream
+    /// providers are gateway errors: a provider rejecting our key says nothing
+    /// about the caller's credentials.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            ErrorCode::RateLimited => 429,
+            ErrorCode::Auth | ErrorCode::Network => 502,
+            ErrorCode::Quota | ErrorCode::Unavailable => 503,
+            ErrorCode::Timeout => 504,
+            ErrorCode::InvalidRequest => 400,
+            ErrorCode::ToolFailed | ErrorCode::Internal => 500,
+        }
+    }
+
+    /// Whether trying again later may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ErrorCode::RateLimited | ErrorCode::Network | ErrorCode::Timeout | ErrorCode::Unavailable)
+    }
+
+    /// What to tell the user.
+    pub fn user_message(&self, locale: Locale) -> &'static str {
+        match self {
+            ErrorCode::RateLimited => locale.pick(
+                "混み合っているため、少し時間をおいてからもう一度お試しください。",
+                "We're getting a lot of requests right now. Please try again in a moment.",
+            ),
+            ErrorCode::Auth => locale.pick(
+                "AIプロバイダーの認証に失敗しました。管理者に設定の確認を依頼してください。",
+                "The AI provider rejected our credentials. Please ask the administrator to check the configuration.",
+            ),
+            ErrorCode::Quota => locale.pick(
+                "利用上限に達しました。しばらくしてから再度お試しください。",
+                "The usage limit has been reached. Please try again later.",
+            ),
+            ErrorCode::Network => locale.pick(
+                "AIプロバイダーに接続できませんでした。もう一度お試しください。",
+                "Couldn't reach the AI provider. Please try again.",
+            ),
+            ErrorCode::Timeout => locale.pick(
+                "応答に時間がかかりすぎたため中断しました。短い質問でもう一度お試しください。",
+                "The response took too long and was stopped. Please try again, perhaps with a shorter request.",
+            ),
+            ErrorCode::ToolFailed => locale.pick(
+                "ツールの実行中にエラーが発生しました。",
+                "A tool failed while working on your request.",
+            ),
+            ErrorCode::InvalidRequest => locale.pick(
+                "リクエストを処理できませんでした。内容を変えてもう一度お試しください。",
+                "The request couldn't be processed. Please rephrase and try again.",
+            ),
+            ErrorCode::Unavailable => locale.pick(
+                "AIが一時的に利用できません。しばらくしてから再度お試しください。",
+                "The AI is temporarily unavailable. Please try again shortly.",
+            ),
+            ErrorCode::Internal => locale.pick(
+                "申し訳ありません。エラーが発生しました。",
+                "Sorry, something went wrong.",
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 pub type Result<T> = std::result::Result<T, NanobotError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_error_codes() {
+        let api = |status, message: &str| ProviderError::Api { status, message: message.to_string() };
+        assert_eq!(api(429, "Too many requests").code(), ErrorCode::RateLimited);
+        assert_eq!(api(429, "You exceeded your current quota").code(), ErrorCode::Quota);
+        assert_eq!(api(401, "invalid x-api-key").code(), ErrorCode::Auth);
+        assert_eq!(api(400, "bad tool schema").code(), ErrorCode::InvalidRequest);
+        assert_eq!(api(529, "overloaded").code(), ErrorCode::Unavailable);
+        assert_eq!(ProviderError::Timeout(45).code(), ErrorCode::Timeout);
+
+        let all = ProviderError::all_failed(3, Some(api(429, "slow down")));
+        assert_eq!(all.code(), ErrorCode::RateLimited);
+        assert_eq!(all.status(), Some(429));
+        assert!(!all.is_client_error());
+        assert!(api(404, "model not found").is_client_error());
+        assert_eq!(ProviderError::all_failed(2, None).code(), ErrorCode::Unavailable);
+    }
+
+    #[test]
+    fn test_error_code_mapping() {
+        assert_eq!(ErrorCode::RateLimited.http_status(), 429);
+        assert_eq!(ErrorCode::Auth.http_status(), 502, "a provider's auth failure isn't the caller's");
+        assert_eq!(serde_json::to_value(ErrorCode::ToolFailed).unwrap(), "tool_failed");
+        assert_eq!(ErrorCode::ToolFailed.to_string(), "tool_failed");
+        assert_eq!(NanobotError::from(ToolError::Timeout(30)).code(), ErrorCode::Timeout);
+        assert!(ErrorCode::Timeout.user_message(Locale::En).contains("too long"));
+        assert!(!ErrorCode::Quota.is_retryable());
+    }
+}
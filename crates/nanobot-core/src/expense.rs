@@ -0,0 +1,496 @@
+//! Expense ledger: spending entries per user, read from receipts or text by
+//! the `expense` tool (see [`crate::tool::expense`]).
+//!
+//! Entries are stored by an [`ExpenseLedger`]:
+//!
+//! - [`FileLedger`] — one JSON file per user under `<data dir>/expenses/`
+//!   (CLI, gateway and self-hosted servers)
+//! - [`DbLedger`] — the `expenses` table of a [`DbBackend`] (libSQL/Turso)
+//! - `DynamoLedger` — `EXPENSE#<user>` items in the config table
+//!   (feature `dynamodb-backend`)
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::db::DbBackend;
+
+/// Categories entries are filed under.
+pub const CATEGORIES: &[&str] = &[
+    "food",
+    "groceries",
+    "transport",
+    "shopping",
+    "utilities",
+    "housing",
+    "entertainment",
+    "health",
+    "travel",
+    "education",
+    "other",
+];
+
+/// Words mapped onto [`CATEGORIES`] ("食費" → food).
+const CATEGORY_ALIASES: &[(&str, &[&str])] = &[
+    ("food", &["食費", "食事", "外食", "ランチ", "飲食", "カフェ", "meal", "meals", "dining", "restaurant", "cafe", "lunch", "dinner"]),
+    ("groceries", &["食料品", "スーパー", "grocery", "supermarket"]),
+    ("transport", &["交通", "交通費", "電車", "タクシー", "バス", "ガソリン", "taxi", "train", "bus", "fuel", "gas", "transportation"]),
+    ("shopping", &["買い物", "日用品", "服", "clothes", "clothing"]),
+    ("utilities", &["光熱費", "電気", "水道", "通信", "携帯", "utility", "electricity", "phone", "internet"]),
+    ("housing", &["家賃", "住居", "rent"]),
+    ("entertainment", &["娯楽", "趣味", "映画", "fun", "movies", "games"]),
+    ("health", &["医療", "病院", "薬", "medical", "pharmacy", "medicine"]),
+    ("travel", &["旅行", "宿泊", "ホテル", "hotel", "flight"]),
+    ("education", &["教育", "本", "書籍", "books", "course"]),
+];
+
+/// Longest bar in summary charts.
+const CHART_WIDTH: usize = 16;
+
+/// One spending entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Expense {
+    pub id: String,
+    /// `YYYY-MM-DD`.
+    pub date: String,
+    pub amount: f64,
+    /// ISO 4217 code (`JPY`, `USD`).
+    pub currency: String,
+    #[serde(default)]
+    pub vendor: String,
+    pub category: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+    pub created_at: String,
+}
+
+impl Expense {
+    pub fn new(date: NaiveDate, amount: f64, currency: &str, vendor: &str, category: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string()[..8].to_string(),
+            date: date.format("%Y-%m-%d").to_string(),
+            amount,
+            currency: currency.trim().to_uppercase(),
+            vendor: vendor.trim().to_string(),
+            category: normalize_category(category),
+            note: String::new(),
+            created_at: crate::util::timestamp(),
+        }
+    }
+
+    /// "2026-10-18  ¥1,280  food  Starbucks (id: a1b2c3d4)".
+    pub fn line(&self) -> String {
+        let vendor = if self.vendor.is_empty() { "-" } else { &self.vendor };
+        format!("{}  {}  {}  {} (id: {})", self.date, format_amount(self.amount, &self.currency), self.category, vendor, self.id)
+    }
+}
+
+/// One of [`CATEGORIES`] for a user- or model-supplied category name.
+pub fn normalize_category(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    if CATEGORIES.contains(&name.as_str()) {
+        return name;
+    }
+    CATEGORY_ALIASES
+        .iter()
+        .find(|(_, words)| words.iter().any(|w| *w == name))
+        .map(|(category, _)| category.to_string())
+        .unwrap_or_else(|| "other".to_string())
+}
+
+/// `¥1,280`, `$12.50`, `€3.00`, or `1,280 KRW`.
+pub fn format_amount(amount: f64, currency: &str) -> String {
+    let (symbol, decimals) = match currency {
+        "JPY" => (Some("¥"), 0),
+        "KRW" => (None, 0),
+        "USD" => (Some("$"), 2),
+        "EUR" => (Some("€"), 2),
+        "GBP" => (Some("£"), 2),
+        _ => (None, 2),
+    };
+    let fixed = format!("{:.*}", decimals, amount.abs());
+    let (int, frac) = fixed.split_once('.').map(|(i, f)| (i, Some(f))).unwrap_or((fixed.as_str(), None));
+    let mut grouped = String::new();
+    for (i, c) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    if let Some(frac) = frac {
+        grouped = format!("{}.{}", grouped, frac);
+    }
+    let sign = if amount < 0.0 { "-" } else { "" };
+    match symbol {
+        Some(symbol) => format!("{}{}{}", sign, symbol, grouped),
+        None => format!("{}{} {}", sign, grouped, currency),
+    }
+}
+
+/// Inclusive date range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Period {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl Period {
+    /// `today`, `this_week`, `this_month` (default), `last_month`,
+    /// `this_year`, `YYYY-MM`, `YYYY-MM-DD` or `YYYY-MM-DD..YYYY-MM-DD`;
+    /// 今日・今週・今月・先月・今年 work too.
+    pub fn parse(s: &str, today: NaiveDate) -> Option<Self> {
+        let month_start = |d: NaiveDate| d.with_day(1).unwrap_or(d);
+        let month_end = |d: NaiveDate| {
+            let next = if d.month() == 12 {
+                NaiveDate::from_ymd_opt(d.year() + 1, 1, 1)
+            } else {
+                NaiveDate::from_ymd_opt(d.year(), d.month() + 1, 1)
+            };
+            next.map(|n| n - Duration::days(1)).unwrap_or(d)
+        };
+        let s = s.trim().to_lowercase();
+        let period = match s.as_str() {
+            "" | "this_month" | "this month" | "month" | "今月" => Period { from: month_start(today), to: today },
+            "today" | "今日" => Period { from: today, to: today },
+            "this_week" | "this week" | "week" | "今週" => Period {
+                from: today - Duration::days(today.weekday().num_days_from_monday() as i64),
+                to: today,
+            },
+            "last_month" | "last month" | "先月" => {
+                let end = month_start(today) - Duration::days(1);
+                Period { from: month_start(end), to: end }
+            }
+            "this_year" | "this year" | "year" | "今年" => Period { from: NaiveDate::from_ymd_opt(today.year(), 1, 1)?, to: today },
+            _ => {
+                if let Some((from, to)) = s.split_once("..") {
+                    let from = NaiveDate::parse_from_str(from.trim(), "%Y-%m-%d").ok()?;
+                    let to = NaiveDate::parse_from_str(to.trim(), "%Y-%m-%d").ok()?;
+                    Period { from: from.min(to), to: from.max(to) }
+                } else if let Ok(day) = NaiveDate::parse_from_str(&s, "%Y-%m-%d") {
+                    Period { from: day, to: day }
+                } else {
+                    let first = NaiveDate::parse_from_str(&format!("{}-01", s), "%Y-%m-%d").ok()?;
+                    Period { from: first, to: month_end(first) }
+                }
+            }
+        };
+        Some(period)
+    }
+
+    pub fn label(&self) -> String {
+        if self.from == self.to {
+            self.from.format("%Y-%m-%d").to_string()
+        } else {
+            format!("{} – {}", self.from.format("%Y-%m-%d"), self.to.format("%Y-%m-%d"))
+        }
+    }
+}
+
+/// Totals of a set of entries.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    /// Currency the breakdown is in (the one with the most entries).
+    pub currency: String,
+    pub total: f64,
+    pub count: usize,
+    /// Largest first.
+    pub by_category: Vec<(String, f64)>,
+    /// Totals in other currencies, not included in the breakdown.
+    pub other_currencies: Vec<(String, f64)>,
+}
+
+impl Summary {
+    pub fn of(entries: &[Expense]) -> Self {
+        let mut per_currency: BTreeMap<&str, (usize, f64)> = BTreeMap::new();
+        for e in entries {
+            let slot = per_currency.entry(e.currency.as_str()).or_default();
+            slot.0 += 1;
+            slot.1 += e.amount;
+        }
+        let Some(currency) = per_currency.iter().max_by_key(|(_, (count, _))| *count).map(|(c, _)| c.to_string()) else {
+            return Self::default();
+        };
+        let mut by_category: BTreeMap<&str, f64> = BTreeMap::new();
+        for e in entries.iter().filter(|e| e.currency == currency) {
+            *by_category.entry(e.category.as_str()).or_default() += e.amount;
+        }
+        let mut by_category: Vec<(String, f64)> = by_category.into_iter().map(|(c, t)| (c.to_string(), t)).collect();
+        by_category.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Self {
+            total: per_currency[currency.as_str()].1,
+            count: per_currency[currency.as_str()].0,
+            other_currencies: per_currency
+                .iter()
+                .filter(|(c, _)| **c != currency.as_str())
+                .map(|(c, (_, total))| (c.to_string(), *total))
+                .collect(),
+            by_category,
+            currency,
+        }
+    }
+
+    /// Totals with a bar chart per category.
+    pub fn render(&self, heading: &str) -> String {
+        if self.count == 0 {
+            return format!("{}\nNo expenses recorded.", heading);
+        }
+        let mut lines = vec![
+            heading.to_string(),
+            format!("Total: {} ({} entries)", format_amount(self.total, &self.currency), self.count),
+        ];
+        let max = self.by_category.first().map(|(_, t)| *t).unwrap_or(0.0);
+        let width = self.by_category.iter().map(|(c, _)| c.len()).max().unwrap_or(0);
+        for (category, total) in &self.by_category {
+            let bar = if max > 0.0 { ((total / max) * CHART_WIDTH as f64).round().max(1.0) as usize } else { 1 };
+            let share = if self.total > 0.0 { total / self.total * 100.0 } else { 0.0 };
+            lines.push(format!(
+                "{:<width$} {} {} ({:.0}%)",
+                category,
+                "█".repeat(bar),
+                format_amount(*total, &self.currency),
+                share,
+                width = width
+            ));
+        }
+        for (currency, total) in &self.other_currencies {
+            lines.push(format!("+ {}", format_amount(*total, currency)));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Storage for expense entries.
+#[async_trait]
+pub trait ExpenseLedger: Send + Sync {
+    async fn add(&self, user_id: &str, expense: &Expense) -> anyhow::Result<()>;
+
+    /// Entries dated within `period`, oldest first.
+    async fn list(&self, user_id: &str, period: Period) -> anyhow::Result<Vec<Expense>>;
+
+    /// Delete an entry. Returns `true` if it existed.
+    async fn remove(&self, user_id: &str, id: &str) -> anyhow::Result<bool>;
+}
+
+fn in_period(e: &Expense, period: Period) -> bool {
+    NaiveDate::parse_from_str(&e.date, "%Y-%m-%d").is_ok_and(|d| d >= period.from && d <= period.to)
+}
+
+/// One JSON file per user.
+pub struct FileLedger {
+    dir: PathBuf,
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl FileLedger {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, lock: tokio::sync::Mutex::new(()) }
+    }
+
+    pub fn default_path() -> PathBuf {
+        crate::config::get_data_dir().join("expenses")
+    }
+
+    fn path(&self, user_id: &str) -> PathBuf {
+        let name: String = user_id.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect();
+        self.dir.join(format!("{}.json", name))
+    }
+
+    async fn read(&self, user_id: &str) -> Vec<Expense> {
+        tokio::fs::read_to_string(self.path(user_id))
+            .await
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    async fn write(&self, user_id: &str, entries: &[Expense]) -> anyhow::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(self.path(user_id), serde_json::to_string_pretty(entries)?).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExpenseLedger for FileLedger {
+    async fn add(&self, user_id: &str, expense: &Expense) -> anyhow::Result<()> {
+        let _guard = self.lock.lock().await;
+        let mut entries = self.read(user_id).await;
+        entries.push(expense.clone());
+        self.write(user_id, &entries).await
+    }
+
+    async fn list(&self, user_id: &str, period: Period) -> anyhow::Result<Vec<Expense>> {
+        let mut entries: Vec<Expense> = self.read(user_id).await.into_iter().filter(|e| in_period(e, period)).collect();
+        entries.sort_by(|a, b| a.date.cmp(&b.date));
+        Ok(entries)
+    }
+
+    async fn remove(&self, user_id: &str, id: &str) -> anyhow::Result<bool> {
+        let _guard = self.lock.lock().await;
+        let mut entries = self.read(user_id).await;
+        let before = entries.len();
+        entries.retain(|e| e.id != id);
+        if entries.len() == before {
+            return Ok(false);
+        }
+        self.write(user_id, &entries).await?;
+        Ok(true)
+    }
+}
+
+/// The `expenses` table of a database backend.
+pub struct DbLedger(pub Arc<dyn DbBackend>);
+
+#[async_trait]
+impl ExpenseLedger for DbLedger {
+    async fn add(&self, user_id: &str, expense: &Expense) -> anyhow::Result<()> {
+        self.0.add_expense(user_id, expense).await
+    }
+
+    async fn list(&self, user_id: &str, period: Period) -> anyhow::Result<Vec<Expense>> {
+        let (from, to) = (period.from.format("%Y-%m-%d").to_string(), period.to.format("%Y-%m-%d").to_string());
+        self.0.list_expenses(user_id, &from, &to).await
+    }
+
+    async fn remove(&self, user_id: &str, id: &str) -> anyhow::Result<bool> {
+        self.0.delete_expense(user_id, id).await
+    }
+}
+
+/// `pk = EXPENSE#<user>`, `sk = <date>#<id>` items in the config table.
+#[cfg(feature = "dynamodb-backend")]
+pub struct DynamoLedger {
+    client: aws_sdk_dynamodb::Client,
+    table: String,
+}
+
+#[cfg(feature = "dynamodb-backend")]
+impl DynamoLedger {
+    pub fn new(client: aws_sdk_dynamodb::Client, table: impl Into<String>) -> Self {
+        Self { client, table: table.into() }
+    }
+
+    async fn query(&self, user_id: &str, from: &str, to: &str) -> anyhow::Result<Vec<Expense>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+        let resp = self
+            .client
+            .query()
+            .table_name(&self.table)
+            .key_condition_expression("pk = :pk AND sk BETWEEN :from AND :to")
+            .expression_attribute_values(":pk", AttributeValue::S(format!("EXPENSE#{}", user_id)))
+            .expression_attribute_values(":from", AttributeValue::S(from.to_string()))
+            .expression_attribute_values(":to", AttributeValue::S(format!("{}#~", to)))
+            .send()
+            .await?;
+        Ok(resp
+            .items()
+            .iter()
+            .filter_map(|item| item.get("data")?.as_s().ok())
+            .filter_map(|data| serde_json::from_str(data).ok())
+            .collect())
+    }
+}
+
+#[cfg(feature = "dynamodb-backend")]
+#[async_trait]
+impl ExpenseLedger for DynamoLedger {
+    async fn add(&self, user_id: &str, expense: &Expense) -> anyhow::Result<()> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+        self.client
+            .put_item()
+            .table_name(&self.table)
+            .item("pk", AttributeValue::S(format!("EXPENSE#{}", user_id)))
+            .item("sk", AttributeValue::S(format!("{}#{}", expense.date, expense.id)))
+            .item("data", AttributeValue::S(serde_json::to_string(expense)?))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn list(&self, user_id: &str, period: Period) -> anyhow::Result<Vec<Expense>> {
+        let (from, to) = (period.from.format("%Y-%m-%d").to_string(), period.to.format("%Y-%m-%d").to_string());
+        self.query(user_id, &from, &to).await
+    }
+
+    async fn remove(&self, user_id: &str, id: &str) -> anyhow::Result<bool> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+        let Some(entry) = self.query(user_id, "0000", "9999").await?.into_iter().find(|e| e.id == id) else {
+            return Ok(false);
+        };
+        self.client
+            .delete_item()
+            .table_name(&self.table)
+            .key("pk", AttributeValue::S(format!("EXPENSE#{}", user_id)))
+            .key("sk", AttributeValue::S(format!("{}#{}", entry.date, entry.id)))
+            .send()
+            .await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_period_parse() {
+        let today = day("2026-03-18");
+        assert_eq!(Period::parse("今月", today), Some(Period { from: day("2026-03-01"), to: today }));
+        assert_eq!(Period::parse("last_month", today), Some(Period { from: day("2026-02-01"), to: day("2026-02-28") }));
+        assert_eq!(Period::parse("this_week", today), Some(Period { from: day("2026-03-16"), to: today }));
+        assert_eq!(Period::parse("2025-12", today), Some(Period { from: day("2025-12-01"), to: day("2025-12-31") }));
+        assert_eq!(Period::parse("2026-01-10..2026-01-01", today), Some(Period { from: day("2026-01-01"), to: day("2026-01-10") }));
+        assert_eq!(Period::parse("someday", today), None);
+    }
+
+    #[test]
+    fn test_categories_and_amounts() {
+        assert_eq!(normalize_category("食費"), "food");
+        assert_eq!(normalize_category("Taxi"), "transport");
+        assert_eq!(normalize_category("???"), "other");
+        assert_eq!(format_amount(1280.0, "JPY"), "¥1,280");
+        assert_eq!(format_amount(1234567.5, "USD"), "$1,234,567.50");
+        assert_eq!(format_amount(900.0, "KRW"), "900 KRW");
+    }
+
+    #[test]
+    fn test_summary_chart() {
+        let entries = vec![
+            Expense::new(day("2026-03-01"), 1000.0, "JPY", "Cafe", "food"),
+            Expense::new(day("2026-03-02"), 3000.0, "jpy", "Taxi", "transport"),
+            Expense::new(day("2026-03-03"), 1000.0, "JPY", "Ramen", "外食"),
+            Expense::new(day("2026-03-04"), 12.5, "USD", "App", "shopping"),
+        ];
+        let summary = Summary::of(&entries);
+        assert_eq!((summary.currency.as_str(), summary.total, summary.count), ("JPY", 5000.0, 3));
+        assert_eq!(summary.by_category, vec![("transport".to_string(), 3000.0), ("food".to_string(), 2000.0)]);
+        let chart = summary.render("March");
+        assert!(chart.contains("Total: ¥5,000 (3 entries)"), "{}", chart);
+        assert!(chart.contains(&format!("transport {} ¥3,000 (60%)", "█".repeat(CHART_WIDTH))), "{}", chart);
+        assert!(chart.ends_with("+ $12.50"));
+    }
+
+    #[tokio::test]
+    async fn test_file_ledger() {
+        let dir = tempfile::tempdir().unwrap();
+        let ledger = FileLedger::new(dir.path().to_path_buf());
+        let lunch = Expense::new(day("2026-03-05"), 980.0, "JPY", "Lunch", "food");
+        ledger.add("line:U1", &lunch).await.unwrap();
+        ledger.add("line:U1", &Expense::new(day("2026-02-05"), 500.0, "JPY", "Bus", "transport")).await.unwrap();
+
+        let march = Period::parse("2026-03", day("2026-03-18")).unwrap();
+        assert_eq!(ledger.list("line:U1", march).await.unwrap(), vec![lunch.clone()]);
+        assert!(ledger.list("line:U2", march).await.unwrap().is_empty());
+        assert!(ledger.remove("line:U1", &lunch.id).await.unwrap());
+        assert!(!ledger.remove("line:U1", &lunch.id).await.unwrap());
+    }
+}
@@ -0,0 +1,351 @@
+//! Checking research answers against the pages they came from.
+//!
+//! After a researcher answer, every sentence that states something
+//! ("claims": numbers, names, dates) is matched against the text the web
+//! tools returned. A claim is supported when most of its words or
+//! character bigrams appear in one source and every number it mentions
+//! does too; with an embeddings key, claims that fail the string match get
+//! a second chance by cosine similarity against source passages. Unsupported
+//! claims are marked inline and a one-line confidence note is appended.
+
+use std::collections::HashSet;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::provider::embeddings::{cosine_similarity, EmbeddingsProvider};
+use crate::session::locale::Locale;
+
+/// Tools whose results count as sources.
+pub const SOURCE_TOOLS: &[&str] = &[
+    "web_search",
+    "web_fetch",
+    "read_webpage",
+    "news_search",
+    "tavily_search",
+    "wikipedia",
+];
+
+/// Share of a claim's terms that must appear in one source.
+const STRING_THRESHOLD: f64 = 0.6;
+/// Cosine similarity for a semantic match.
+const SEMANTIC_THRESHOLD: f32 = 0.82;
+/// Passage size for semantic matching, and how many passages are embedded.
+const PASSAGE_CHARS: usize = 600;
+const MAX_PASSAGES: usize = 24;
+const MIN_CLAIM_CHARS: usize = 12;
+
+static URL_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"https?://[^\s)\]>"'、。]+"#).unwrap());
+static NUMBER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d[\d,]*(?:\.\d+)?").unwrap());
+
+/// Text a web tool returned.
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub tool: String,
+    pub urls: Vec<String>,
+    pub text: String,
+}
+
+impl Source {
+    /// `None` for tools that don't fetch from the web.
+    pub fn from_tool_result(tool: &str, result: &str) -> Option<Self> {
+        if !SOURCE_TOOLS.contains(&tool) || result.trim().is_empty() {
+            return None;
+        }
+        let urls = find_urls(result).into_iter().map(str::to_string).collect();
+        Some(Self { tool: tool.to_string(), urls, text: result.to_string() })
+    }
+}
+
+/// The http(s) URLs in `text`, without trailing punctuation.
+pub fn find_urls(text: &str) -> Vec<&str> {
+    URL_RE.find_iter(text).map(|m| trim_url(m.as_str())).collect()
+}
+
+fn trim_url(url: &str) -> &str {
+    url.trim_end_matches(['.', ',', ';', ':'])
+}
+
+/// How one claim fared.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaimCheck {
+    pub text: String,
+    pub supported: bool,
+    /// Best match against any source (0.0–1.0).
+    pub score: f64,
+    /// Index into the sources of the best match.
+    pub source: Option<usize>,
+    /// A URL the claim cites that no tool fetched or returned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unknown_citation: Option<String>,
+}
+
+/// All claims of an answer.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    pub claims: Vec<ClaimCheck>,
+}
+
+impl Report {
+    pub fn supported(&self) -> usize {
+        self.claims.iter().filter(|c| c.supported).count()
+    }
+
+    /// Share of supported claims; 1.0 when there is nothing to check.
+    pub fn confidence(&self) -> f64 {
+        if self.claims.is_empty() {
+            1.0
+        } else {
+            self.supported() as f64 / self.claims.len() as f64
+        }
+    }
+}
+
+/// Words (Latin scripts) and character bigrams (CJK), lowercased.
+fn terms(text: &str) -> HashSet<String> {
+    let mut out = HashSet::new();
+    let mut word = String::new();
+    let mut prev: Option<char> = None;
+    for c in text.to_lowercase().chars().chain(std::iter::once(' ')) {
+        if c.is_ascii_alphanumeric() {
+            word.push(c);
+            prev = None;
+            continue;
+        }
+        if word.len() > 2 {
+            out.insert(word.clone());
+        }
+        word.clear();
+        if c.is_alphanumeric() {
+            if let Some(p) = prev {
+                out.insert(format!("{p}{c}"));
+            }
+            prev = Some(c);
+        } else {
+            prev = None;
+        }
+    }
+    out
+}
+
+fn numbers(text: &str) -> Vec<String> {
+    NUMBER_RE.find_iter(text).map(|m| m.as_str().replace(',', "")).collect()
+}
+
+/// Sentences of `answer` worth checking: long enough, not code, not headings
+/// or bare link lists, and containing a number, a name or a citation.
+pub fn claims(answer: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut in_code = false;
+    for line in answer.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        let line = line.trim().trim_start_matches(['-', '*', '•', '>']).trim();
+        if in_code || line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        for sentence in split_sentences(line) {
+            let sentence = sentence.trim();
+            let without_urls = URL_RE.replace_all(sentence, "");
+            if without_urls.trim().chars().count() < MIN_CLAIM_CHARS {
+                continue;
+            }
+            let factual = sentence.chars().any(|c| c.is_ascii_digit())
+                || URL_RE.is_match(sentence)
+                || sentence.split_whitespace().skip(1).any(|w| w.starts_with(|c: char| c.is_uppercase()))
+                || sentence.chars().any(|c| ('\u{30A0}'..='\u{30FF}').contains(&c));
+            if factual {
+                out.push(sentence.to_string());
+            }
+        }
+    }
+    out
+}
+
+fn split_sentences(line: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    for (i, &(pos, c)) in chars.iter().enumerate() {
+        let next_is_space = chars.get(i + 1).is_none_or(|(_, n)| n.is_whitespace());
+        let end = matches!(c, '。' | '！' | '？') || (matches!(c, '.' | '!' | '?') && next_is_space);
+        if end {
+            let stop = pos + c.len_utf8();
+            out.push(&line[start..stop]);
+            start = stop;
+        }
+    }
+    if start < line.len() {
+        out.push(&line[start..]);
+    }
+    out
+}
+
+fn check_claim(claim: &str, sources: &[Source], source_terms: &[HashSet<String>]) -> ClaimCheck {
+    let claim_without_urls = URL_RE.replace_all(claim, "");
+    let claim_terms = terms(&claim_without_urls);
+    let claim_numbers = numbers(&claim_without_urls);
+
+    let mut best = (0.0, None);
+    for (i, (source, st)) in sources.iter().zip(source_terms).enumerate() {
+        if claim_terms.is_empty() {
+            break;
+        }
+        let shared = claim_terms.iter().filter(|t| st.contains(*t)).count();
+        let mut score = shared as f64 / claim_terms.len() as f64;
+        let source_text = source.text.replace(',', "");
+        if !claim_numbers.iter().all(|n| source_text.contains(n.as_str())) {
+            score = score.min(STRING_THRESHOLD - 0.01);
+        }
+        if score > best.0 {
+            best = (score, Some(i));
+        }
+    }
+
+    let known: HashSet<&str> = sources.iter().flat_map(|s| s.urls.iter().map(String::as_str)).collect();
+    let unknown_citation = URL_RE
+        .find_iter(claim)
+        .map(|m| trim_url(m.as_str()))
+        .find(|url| !known.contains(url))
+        .map(str::to_string);
+
+    ClaimCheck {
+        text: claim.to_string(),
+        supported: best.0 >= STRING_THRESHOLD && unknown_citation.is_none(),
+        score: best.0,
+        source: best.1,
+        unknown_citation,
+    }
+}
+
+/// String-match every claim of `answer` against `sources`.
+pub fn check(answer: &str, sources: &[Source]) -> Report {
+    let source_terms: Vec<HashSet<String>> = sources.iter().map(|s| terms(&s.text)).collect();
+    Report {
+        claims: claims(answer).iter().map(|c| check_claim(c, sources, &source_terms)).collect(),
+    }
+}
+
+fn passages(sources: &[Source]) -> Vec<(usize, String)> {
+    let mut out = Vec::new();
+    for (i, source) in sources.iter().enumerate() {
+        let chars: Vec<char> = source.text.chars().collect();
+        for chunk in chars.chunks(PASSAGE_CHARS) {
+            out.push((i, chunk.iter().collect()));
+            if out.len() >= MAX_PASSAGES {
+                return out;
+            }
+        }
+    }
+    out
+}
+
+/// Give claims without a string match a second chance by embedding
+/// similarity. Embedding failures leave the report as it was.
+pub async fn recheck_semantic(report: &mut Report, sources: &[Source], embedder: &EmbeddingsProvider) {
+    let pending: Vec<usize> = (0..report.claims.len())
+        .filter(|&i| !report.claims[i].supported && report.claims[i].unknown_citation.is_none())
+        .collect();
+    if pending.is_empty() {
+        return;
+    }
+    let mut embedded = Vec::new();
+    for (source, passage) in passages(sources) {
+        match embedder.embed(&passage).await {
+            Ok(v) => embedded.push((source, v)),
+            Err(e) => {
+                tracing::debug!("Fact check embeddings failed: {}", e);
+                return;
+            }
+        }
+    }
+    for i in pending {
+        let Ok(claim) = embedder.embed(&report.claims[i].text).await else {
+            return;
+        };
+        let best = embedded
+            .iter()
+            .map(|(source, v)| (*source, cosine_similarity(&claim, v)))
+            .fold((None, 0.0f32), |acc, (s, sim)| if sim > acc.1 { (Some(s), sim) } else { acc });
+        if best.1 >= SEMANTIC_THRESHOLD {
+            let check = &mut report.claims[i];
+            check.supported = true;
+            check.score = check.score.max(best.1 as f64);
+            check.source = best.0;
+        }
+    }
+}
+
+/// Mark unsupported claims in `answer` and append a confidence note.
+pub fn annotate(answer: &str, report: &Report, locale: Locale) -> String {
+    if report.claims.is_empty() {
+        return answer.to_string();
+    }
+    let marker = locale.pick(" ⚠️[出典で未確認]", " ⚠️[not found in sources]");
+    let mut out = answer.to_string();
+    for claim in report.claims.iter().filter(|c| !c.supported) {
+        if let Some(pos) = out.find(&claim.text) {
+            out.insert_str(pos + claim.text.len(), marker);
+        }
+    }
+    let (total, ok) = (report.claims.len(), report.supported());
+    let level = match report.confidence() {
+        c if c >= 0.8 => locale.pick("高", "high"),
+        c if c >= 0.5 => locale.pick("中", "medium"),
+        _ => locale.pick("低", "low"),
+    };
+    let note = match locale {
+        Locale::Ja => format!("🔎 出典との照合: {total}件中{ok}件を確認（信頼度: {level}）"),
+        Locale::En => format!("🔎 Checked against sources: {ok} of {total} claims supported (confidence: {level})"),
+    };
+    format!("{}\n\n{}", out.trim_end(), note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources() -> Vec<Source> {
+        vec![
+            Source::from_tool_result(
+                "read_webpage",
+                "https://example.com/tokyo\nTokyo Skytree opened in 2012 and is 634 metres tall. \
+                 東京スカイツリーの高さは634メートルです。",
+            )
+            .unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_claims_skip_code_and_chatter() {
+        let answer = "## Summary\nSure, here you go!\nTokyo Skytree opened in 2012.\n```\nlet x = 2012;\n```\n東京スカイツリーは634メートルです。";
+        assert_eq!(claims(answer), vec!["Tokyo Skytree opened in 2012.", "東京スカイツリーは634メートルです。"]);
+        assert!(Source::from_tool_result("calculator", "42").is_none());
+    }
+
+    #[test]
+    fn test_check_numbers_and_citations() {
+        let answer = "Tokyo Skytree opened in 2012 and is 634 metres tall. \
+                      Tokyo Skytree opened in 2015 with 700 metres. \
+                      Tokyo Skytree is 634 metres tall (https://example.com/tokyo). \
+                      See https://made-up.example/skytree for Tokyo Skytree facts.\n\
+                      東京スカイツリーの高さは634メートルです。";
+        let report = check(answer, &sources());
+        let supported: Vec<bool> = report.claims.iter().map(|c| c.supported).collect();
+        assert_eq!(supported, vec![true, false, true, false, true]);
+        assert_eq!(report.claims[3].unknown_citation.as_deref(), Some("https://made-up.example/skytree"));
+    }
+
+    #[test]
+    fn test_annotate() {
+        let answer = "Tokyo Skytree opened in 2012. Tokyo Skytree opened in 1999.";
+        let report = check(answer, &sources());
+        let out = annotate(answer, &report, Locale::En);
+        assert!(out.starts_with("Tokyo Skytree opened in 2012. Tokyo Skytree opened in 1999. ⚠️[not found in sources]"));
+        assert!(out.ends_with("1 of 2 claims supported (confidence: medium)"));
+        assert_eq!(annotate("ok", &Report::default(), Locale::Ja), "ok");
+    }
+}
@@ -0,0 +1,313 @@
+//! Canned answers to frequent questions.
+//!
+//! Questions listed in `<workspace>/faq.yaml` are answered straight from the
+//! file, before the model is called, so support bots don't pay for the same
+//! "what are your opening hours?" over and over:
+//!
+//! ```yaml
+//! threshold: 0.85          # lexical confidence needed to answer
+//! semanticThreshold: 0.8   # embedding similarity needed to answer
+//! channels: [line, web]    # empty = every channel
+//! entries:
+//!   - id: hours
+//!     questions: ["営業時間は？", "What are your opening hours?"]
+//!     answer: "毎日10:00〜19:00に営業しています。"
+//!   - id: refund
+//!     questions: ["返品できますか", "Can I get a refund?"]
+//!     answer: "購入から30日以内であれば返品できます。"
+//! ```
+//!
+//! Messages are compared after normalization (case, full-width forms,
+//! punctuation and spacing are ignored). An exact hit scores 1.0; otherwise
+//! the score is the character-bigram overlap, which works for Japanese as
+//! well as English. When no question is close enough and `OPENAI_API_KEY` is
+//! set, embeddings are compared as well so paraphrases are answered too;
+//! question embeddings are cached in memory.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::provider::embeddings::{cosine_similarity, EmbeddingsProvider};
+
+/// Outbound metadata key holding the id of the FAQ entry that answered.
+pub const FAQ_KEY: &str = "faq";
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_threshold() -> f32 {
+    0.85
+}
+
+fn default_semantic_threshold() -> f32 {
+    0.8
+}
+
+/// `faq.yaml`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaqFile {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_threshold")]
+    pub threshold: f32,
+    #[serde(default = "default_semantic_threshold")]
+    pub semantic_threshold: f32,
+    /// Channels answered from the FAQ; empty means all.
+    #[serde(default)]
+    pub channels: Vec<String>,
+    #[serde(default)]
+    pub entries: Vec<FaqEntry>,
+}
+
+impl FaqFile {
+    pub fn applies(&self, channel: &str) -> bool {
+        self.enabled && (self.channels.is_empty() || self.channels.iter().any(|c| c == channel))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaqEntry {
+    /// Shown in logs and outbound metadata; defaults to the entry's position.
+    #[serde(default)]
+    pub id: String,
+    pub questions: Vec<String>,
+    pub answer: String,
+}
+
+/// How a message matched an FAQ question.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    Exact,
+    Lexical,
+    Semantic,
+}
+
+/// An FAQ answer for a message.
+#[derive(Debug, Clone, Serialize)]
+pub struct FaqMatch {
+    pub id: String,
+    pub question: String,
+    pub answer: String,
+    pub score: f32,
+    pub kind: MatchKind,
+}
+
+/// Lowercased text without punctuation, symbols or spaces; full-width ASCII
+/// is folded to half-width.
+pub fn normalize(text: &str) -> String {
+    text.chars()
+        .map(|c| match c as u32 {
+            0xFF01..=0xFF5E => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn bigrams(text: &str) -> Vec<(char, char)> {
+    let chars: Vec<char> = text.chars().collect();
+    match chars.len() {
+        0 => Vec::new(),
+        1 => vec![(chars[0], chars[0])],
+        _ => chars.windows(2).map(|w| (w[0], w[1])).collect(),
+    }
+}
+
+/// Dice coefficient of the character bigrams of two normalized strings.
+pub fn similarity(a: &str, b: &str) -> f32 {
+    if a == b {
+        return if a.is_empty() { 0.0 } else { 1.0 };
+    }
+    let (a, mut b) = (bigrams(a), bigrams(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let total = a.len() + b.len();
+    let mut shared = 0;
+    for gram in &a {
+        if let Some(i) = b.iter().position(|g| g == gram) {
+            b.swap_remove(i);
+            shared += 1;
+        }
+    }
+    2.0 * shared as f32 / total as f32
+}
+
+fn entry_id(entry: &FaqEntry, index: usize) -> String {
+    if entry.id.is_empty() {
+        (index + 1).to_string()
+    } else {
+        entry.id.clone()
+    }
+}
+
+/// The question closest to `text` by normalized spelling.
+pub fn best_lexical(file: &FaqFile, text: &str) -> Option<FaqMatch> {
+    let text = normalize(text);
+    let mut best: Option<FaqMatch> = None;
+    for (index, entry) in file.entries.iter().enumerate() {
+        for question in &entry.questions {
+            let score = similarity(&text, &normalize(question));
+            if best.as_ref().is_some_and(|b| b.score >= score) || score == 0.0 {
+                continue;
+            }
+            best = Some(FaqMatch {
+                id: entry_id(entry, index),
+                question: question.clone(),
+                answer: entry.answer.clone(),
+                score,
+                kind: if score >= 1.0 { MatchKind::Exact } else { MatchKind::Lexical },
+            });
+        }
+    }
+    best
+}
+
+/// The FAQ of a workspace.
+pub struct Faq {
+    path: PathBuf,
+    embedder: Option<EmbeddingsProvider>,
+    embeddings: std::sync::Mutex<HashMap<String, Vec<f32>>>,
+}
+
+impl Faq {
+    pub fn new(path: PathBuf, embedder: Option<EmbeddingsProvider>) -> Self {
+        Self {
+            path,
+            embedder,
+            embeddings: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// `<workspace>/faq.yaml`, with semantic matching when `OPENAI_API_KEY` is set.
+    pub fn for_workspace(workspace: &Path) -> Self {
+        let embedder = std::env::var("OPENAI_API_KEY").ok().filter(|k| !k.is_empty()).map(EmbeddingsProvider::new);
+        Self::new(workspace.join("faq.yaml"), embedder)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The FAQ file; a missing file means no FAQ.
+    pub fn load(&self) -> anyhow::Result<Option<FaqFile>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(content) => Ok(Some(serde_yaml::from_str(&content)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The canned answer for `text` on `channel`, if one is confident enough.
+    pub async fn answer(&self, channel: &str, text: &str) -> Option<FaqMatch> {
+        let file = match self.load() {
+            Ok(file) => file?,
+            Err(e) => {
+                warn!("Invalid {}: {}", self.path.display(), e);
+                return None;
+            }
+        };
+        if !file.applies(channel) || file.entries.is_empty() || normalize(text).is_empty() {
+            return None;
+        }
+        if let Some(hit) = best_lexical(&file, text).filter(|m| m.score >= file.threshold) {
+            return Some(hit);
+        }
+        let embedder = self.embedder.as_ref()?;
+        self.best_semantic(embedder, &file, text)
+            .await
+            .filter(|m| m.score >= file.semantic_threshold)
+    }
+
+    async fn embedding(&self, embedder: &EmbeddingsProvider, question: &str) -> anyhow::Result<Vec<f32>> {
+        let cached = self.embeddings.lock().unwrap_or_else(|e| e.into_inner()).get(question).cloned();
+        if let Some(cached) = cached {
+            return Ok(cached);
+        }
+        let embedding = embedder.embed(question).await?;
+        self.embeddings
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(question.to_string(), embedding.clone());
+        Ok(embedding)
+    }
+
+    async fn best_semantic(&self, embedder: &EmbeddingsProvider, file: &FaqFile, text: &str) -> Option<FaqMatch> {
+        let result: anyhow::Result<Option<FaqMatch>> = async {
+            let target = embedder.embed(text).await?;
+            let mut best: Option<FaqMatch> = None;
+            for (index, entry) in file.entries.iter().enumerate() {
+                for question in &entry.questions {
+                    let score = cosine_similarity(&target, &self.embedding(embedder, question).await?);
+                    if best.as_ref().is_some_and(|b| b.score >= score) {
+                        continue;
+                    }
+                    best = Some(FaqMatch {
+                        id: entry_id(entry, index),
+                        question: question.clone(),
+                        answer: entry.answer.clone(),
+                        score,
+                        kind: MatchKind::Semantic,
+                    });
+                }
+            }
+            Ok(best)
+        }
+        .await;
+        result.unwrap_or_else(|e| {
+            warn!("FAQ semantic match failed: {}", e);
+            None
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAQ: &str = r#"
+threshold: 0.7
+channels: [line]
+entries:
+  - id: hours
+    questions: ["営業時間は？", "What are your opening hours?"]
+    answer: "10:00-19:00"
+  - questions: ["返品できますか"]
+    answer: "30日以内なら可能です"
+"#;
+
+    #[test]
+    fn test_normalize_and_similarity() {
+        assert_eq!(normalize("ＷＨＡＴ are your  hours？"), "whatareyourhours");
+        assert_eq!(similarity("abc", "abc"), 1.0);
+        assert_eq!(similarity("abc", "xyz"), 0.0);
+        assert!(similarity(&normalize("営業時間は何時ですか"), &normalize("営業時間は？")) > 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_answer() {
+        let dir = tempfile::tempdir().unwrap();
+        let faq = Faq::new(dir.path().join("faq.yaml"), None);
+        assert!(faq.answer("line", "営業時間は？").await.is_none(), "no file, no FAQ");
+
+        std::fs::write(faq.path(), FAQ).unwrap();
+        let exact = faq.answer("line", "what are your opening hours").await.unwrap();
+        assert_eq!((exact.id.as_str(), exact.kind), ("hours", MatchKind::Exact));
+
+        let close = faq.answer("line", "返品できますか？？").await.unwrap();
+        assert_eq!((close.id.as_str(), close.answer.as_str()), ("2", "30日以内なら可能です"));
+
+        let fuzzy = faq.answer("line", "What are your opening hours today?").await.unwrap();
+        assert_eq!((fuzzy.id.as_str(), fuzzy.kind), ("hours", MatchKind::Lexical));
+        assert!(faq.answer("line", "営業時間を教えて").await.is_none(), "below the threshold");
+        assert!(faq.answer("line", "明日の天気は？").await.is_none());
+        assert!(faq.answer("telegram", "営業時間は？").await.is_none(), "channel not enabled");
+    }
+}
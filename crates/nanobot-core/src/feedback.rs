@@ -0,0 +1,183 @@
+//! Emoji reactions as lightweight feedback.
+//!
+//! Channel adapters that support reactions (Discord, Slack, Telegram) turn a
+//! reaction on a bot message into an [`InboundMessage`] carrying the emoji in
+//! its metadata. 👍/👎 become feedback records; 🔁 regenerates the last reply.
+
+use std::io::Write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::InboundMessage;
+
+/// Inbound metadata key holding the reaction emoji (or Slack emoji name).
+pub const REACTION_KEY: &str = "reaction";
+
+/// Inbound metadata key holding the id of the message that was reacted to.
+pub const REACTION_MESSAGE_KEY: &str = "reaction_message_id";
+
+/// File (under the workspace) where gateway-mode feedback is appended.
+pub const FEEDBACK_FILE: &str = "feedback.jsonl";
+
+/// Thumbs up / thumbs down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+impl Rating {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "up" => Some(Rating::Up),
+            "down" => Some(Rating::Down),
+            _ => None,
+        }
+    }
+
+    /// Wire value used by `/api/v1/feedback` and the feedback table.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rating::Up => "up",
+            Rating::Down => "down",
+        }
+    }
+}
+
+/// What a reaction asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReactionAction {
+    Rate(Rating),
+    Regenerate,
+}
+
+/// Map a reaction to an action. Accepts Unicode emoji (skin tones and
+/// variation selectors are ignored) and Slack emoji names.
+pub fn reaction_action(emoji: &str) -> Option<ReactionAction> {
+    let base: String = emoji
+        .trim()
+        .trim_matches(':')
+        .chars()
+        .filter(|c| !matches!(c, '\u{FE0F}' | '\u{1F3FB}'..='\u{1F3FF}'))
+        .collect();
+    let base = base.split("::skin-tone").next().unwrap_or("");
+    match base {
+        "👍" | "❤" | "🔥" | "🎉" | "+1" | "thumbsup" | "heart" | "fire" | "tada" => {
+            Some(ReactionAction::Rate(Rating::Up))
+        }
+        "👎" | "-1" | "thumbsdown" => Some(ReactionAction::Rate(Rating::Down)),
+        "🔁" | "🔄" | "repeat" | "arrows_counterclockwise" => Some(ReactionAction::Regenerate),
+        _ => None,
+    }
+}
+
+/// Build the inbound message a channel adapter sends for a reaction.
+pub fn reaction_message(
+    channel: &str,
+    sender_id: &str,
+    chat_id: &str,
+    emoji: &str,
+    message_id: &str,
+) -> InboundMessage {
+    let mut msg = InboundMessage::new(channel, sender_id, chat_id, "");
+    msg.metadata.insert(REACTION_KEY.to_string(), serde_json::json!(emoji));
+    msg.metadata
+        .insert(REACTION_MESSAGE_KEY.to_string(), serde_json::json!(message_id));
+    msg
+}
+
+/// The reaction emoji carried by an inbound message, if it is a reaction.
+pub fn reaction_of(msg: &InboundMessage) -> Option<&str> {
+    msg.metadata.get(REACTION_KEY).and_then(|v| v.as_str())
+}
+
+/// A single feedback entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedbackRecord {
+    pub rating: Rating,
+    pub channel: String,
+    pub session_key: String,
+    pub user_id: String,
+    /// The rated assistant message (truncated).
+    pub snippet: String,
+    #[serde(default)]
+    pub conversation_id: String,
+    /// Where the feedback came from: "reaction" or "button".
+    pub source: String,
+    pub timestamp: String,
+}
+
+impl FeedbackRecord {
+    /// Feedback from an emoji reaction on the last reply in `session_key`.
+    pub fn from_reaction(rating: Rating, channel: &str, session_key: &str, user_id: &str, reply: &str) -> Self {
+        Self {
+            rating,
+            channel: channel.to_string(),
+            session_key: session_key.to_string(),
+            user_id: user_id.to_string(),
+            snippet: snippet(reply),
+            conversation_id: String::new(),
+            source: "reaction".to_string(),
+            timestamp: crate::util::timestamp(),
+        }
+    }
+}
+
+/// First 200 characters of a reply, for feedback records.
+pub fn snippet(text: &str) -> String {
+    text.chars().take(200).collect()
+}
+
+/// Append a record to `<workspace>/feedback.jsonl` (gateway mode).
+pub fn append_feedback(workspace: &Path, record: &FeedbackRecord) -> std::io::Result<()> {
+    let line = serde_json::to_string(record)?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(workspace.join(FEEDBACK_FILE))?;
+    writeln!(file, "{line}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reaction_action() {
+        assert_eq!(reaction_action("👍"), Some(ReactionAction::Rate(Rating::Up)));
+        assert_eq!(reaction_action("👍🏽"), Some(ReactionAction::Rate(Rating::Up)));
+        assert_eq!(reaction_action("❤️"), Some(ReactionAction::Rate(Rating::Up)));
+        assert_eq!(reaction_action("👎"), Some(ReactionAction::Rate(Rating::Down)));
+        assert_eq!(reaction_action("thumbsdown"), Some(ReactionAction::Rate(Rating::Down)));
+        assert_eq!(reaction_action("+1::skin-tone-3"), Some(ReactionAction::Rate(Rating::Up)));
+        assert_eq!(reaction_action("🔁"), Some(ReactionAction::Regenerate));
+        assert_eq!(reaction_action(":repeat:"), Some(ReactionAction::Regenerate));
+        assert_eq!(reaction_action("😂"), None);
+    }
+
+    #[test]
+    fn test_reaction_message_roundtrip() {
+        let msg = reaction_message("discord", "u1", "c1", "👎", "m42");
+        assert_eq!(reaction_of(&msg), Some("👎"));
+        assert_eq!(msg.metadata[REACTION_MESSAGE_KEY], "m42");
+        assert!(msg.content.is_empty());
+        assert_eq!(reaction_of(&InboundMessage::new("discord", "u1", "c1", "hi")), None);
+    }
+
+    #[test]
+    fn test_append_feedback() {
+        let dir = std::env::temp_dir().join(format!("nanobot-feedback-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let record = FeedbackRecord::from_reaction(Rating::Down, "slack", "slack:C1", "U1", &"x".repeat(300));
+        append_feedback(&dir, &record).unwrap();
+        append_feedback(&dir, &record).unwrap();
+        let content = std::fs::read_to_string(dir.join(FEEDBACK_FILE)).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        let parsed: FeedbackRecord = serde_json::from_str(content.lines().next().unwrap()).unwrap();
+        assert_eq!(parsed.rating, Rating::Down);
+        assert_eq!(parsed.snippet.len(), 200);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
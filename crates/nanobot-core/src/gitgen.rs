@@ -0,0 +1,390 @@
+//! Commit message and changelog generation from git history.
+//!
+//! `chatweb commit-msg` sends the staged diff to the agent with a
+//! conventional-commit prompt; `chatweb changelog` groups `git log` by
+//! conventional-commit type and asks the agent to write release notes (or
+//! renders the grouping directly with `--raw`).
+//!
+//! Prompts can be overridden with `<workspace>/templates/commit-msg.md` and
+//! `<workspace>/templates/changelog.md`, rendered with
+//! [`crate::workflow::render`]: `{{diff}}` and `{{stat}}` for commit messages,
+//! `{{version}}`, `{{range}}`, `{{date}}` and `{{commits}}` for changelogs.
+
+use anyhow::{bail, Context};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::util::truncate_chars;
+
+/// Staged diffs are cut to this many characters in the prompt.
+const MAX_DIFF_CHARS: usize = 30_000;
+
+const DEFAULT_COMMIT_TEMPLATE: &str = "\
+Write a git commit message for the staged changes below, following Conventional Commits.
+
+Rules:
+- First line: `type(scope): summary` with type one of feat, fix, docs, style, refactor, perf, test, build, ci, chore, revert; scope optional; imperative mood; at most 72 characters; no trailing period.
+- Add `!` after the type/scope and a `BREAKING CHANGE:` footer only for incompatible changes.
+- If the change needs explaining, add a blank line and a short body wrapped at 72 characters saying what changed and why.
+- Reply with ONLY the commit message, no code fences or commentary.
+
+Files changed:
+{{stat}}
+
+Diff:
+{{diff}}
+";
+
+const DEFAULT_CHANGELOG_TEMPLATE: &str = "\
+Write release notes in Markdown for version {{version}} ({{date}}) from the commits below ({{range}}), already grouped by type.
+
+- Start with `## {{version}} - {{date}}`, then `###` sections in this order where non-empty: Breaking Changes, Features, Bug Fixes, Performance, Documentation, Other.
+- One bullet per user-visible change, written for users of the project; merge duplicates and drop purely internal chores, CI and test-only commits.
+- Keep commit hashes in parentheses at the end of each bullet.
+- Reply with ONLY the Markdown.
+
+Commits:
+{{commits}}
+";
+
+/// One commit from `git log`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitInfo {
+    pub hash: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// A parsed `type(scope)!: description` subject.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conventional<'a> {
+    pub kind: &'a str,
+    pub scope: Option<&'a str>,
+    pub breaking: bool,
+    pub description: &'a str,
+}
+
+static CONVENTIONAL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?P<kind>[a-zA-Z]+)(?:\((?P<scope>[^)]+)\))?(?P<bang>!)?: (?P<desc>.+)$").unwrap());
+
+const CONVENTIONAL_TYPES: &[&str] = &[
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert",
+];
+
+pub fn parse_conventional(subject: &str) -> Option<Conventional<'_>> {
+    let caps = CONVENTIONAL.captures(subject.trim())?;
+    let kind = caps.name("kind")?.as_str();
+    if !CONVENTIONAL_TYPES.contains(&kind.to_lowercase().as_str()) {
+        return None;
+    }
+    Some(Conventional {
+        kind,
+        scope: caps.name("scope").map(|m| m.as_str()),
+        breaking: caps.name("bang").is_some(),
+        description: caps.name("desc")?.as_str(),
+    })
+}
+
+/// Whether the first line of `message` is a conventional commit header.
+pub fn is_conventional(message: &str) -> bool {
+    message
+        .lines()
+        .next()
+        .and_then(parse_conventional)
+        .is_some_and(|c| !c.description.trim().is_empty())
+}
+
+/// Changelog section for a commit.
+fn section(commit: &CommitInfo) -> &'static str {
+    match parse_conventional(&commit.subject) {
+        Some(c) if c.breaking => "Breaking Changes",
+        _ if commit.body.contains("BREAKING CHANGE") => "Breaking Changes",
+        Some(c) => match c.kind.to_lowercase().as_str() {
+            "feat" => "Features",
+            "fix" => "Bug Fixes",
+            "perf" => "Performance",
+            "docs" => "Documentation",
+            _ => "Other",
+        },
+        None => "Other",
+    }
+}
+
+const SECTIONS: &[&str] = &[
+    "Breaking Changes",
+    "Features",
+    "Bug Fixes",
+    "Performance",
+    "Documentation",
+    "Other",
+];
+
+/// Commits grouped into changelog sections, in display order.
+pub fn group_commits(commits: &[CommitInfo]) -> Vec<(&'static str, Vec<&CommitInfo>)> {
+    SECTIONS
+        .iter()
+        .map(|&name| (name, commits.iter().filter(|c| section(c) == name).collect::<Vec<_>>()))
+        .filter(|(_, list)| !list.is_empty())
+        .collect()
+}
+
+fn short_hash(hash: &str) -> &str {
+    &hash[..hash.len().min(7)]
+}
+
+/// Description without the `type(scope):` prefix, with the scope in bold.
+fn bullet(commit: &CommitInfo) -> String {
+    let text = match parse_conventional(&commit.subject) {
+        Some(c) => match c.scope {
+            Some(scope) => format!("**{}:** {}", scope, c.description),
+            None => c.description.to_string(),
+        },
+        None => commit.subject.clone(),
+    };
+    format!("- {} ({})", text, short_hash(&commit.hash))
+}
+
+/// Today's date as `YYYY-MM-DD`, for changelog headings.
+pub fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+/// The grouped changelog without the agent.
+pub fn render_changelog(version: &str, date: &str, commits: &[CommitInfo]) -> String {
+    let mut out = format!("## {} - {}\n", version, date);
+    for (name, list) in group_commits(commits) {
+        out.push_str(&format!("\n### {}\n\n", name));
+        for commit in list {
+            out.push_str(&bullet(commit));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn template(workspace: &Path, name: &str, default: &str) -> String {
+    std::fs::read_to_string(workspace.join("templates").join(name)).unwrap_or_else(|_| default.to_string())
+}
+
+fn clip(text: &str, max: usize) -> String {
+    match truncate_chars(text, max) {
+        (head, true) => format!("{}\n... (truncated)", head),
+        (head, false) => head.to_string(),
+    }
+}
+
+/// Prompt for a commit message from the staged `diff` and `stat`.
+pub fn commit_prompt(workspace: &Path, diff: &str, stat: &str) -> String {
+    let mut vars = Map::new();
+    vars.insert("diff".into(), Value::String(clip(diff, MAX_DIFF_CHARS)));
+    vars.insert("stat".into(), Value::String(stat.trim().to_string()));
+    crate::workflow::render(&template(workspace, "commit-msg.md", DEFAULT_COMMIT_TEMPLATE), &vars)
+}
+
+/// Prompt for release notes of `commits`.
+pub fn changelog_prompt(workspace: &Path, version: &str, date: &str, range: &str, commits: &[CommitInfo]) -> String {
+    let mut listing = String::new();
+    for (name, list) in group_commits(commits) {
+        listing.push_str(&format!("[{}]\n", name));
+        for c in list {
+            listing.push_str(&format!("{} {}\n", short_hash(&c.hash), c.subject));
+            let body = c.body.trim();
+            if !body.is_empty() {
+                listing.push_str(&format!("    {}\n", clip(body, 500).replace('\n', "\n    ")));
+            }
+        }
+    }
+    let mut vars = Map::new();
+    vars.insert("version".into(), Value::String(version.to_string()));
+    vars.insert("date".into(), Value::String(date.to_string()));
+    vars.insert("range".into(), Value::String(range.to_string()));
+    vars.insert("commits".into(), Value::String(listing));
+    crate::workflow::render(&template(workspace, "changelog.md", DEFAULT_CHANGELOG_TEMPLATE), &vars)
+}
+
+/// Strip code fences and surrounding quotes the model may add.
+pub fn clean_message(response: &str) -> String {
+    let trimmed = response.trim();
+    let inner = match trimmed.strip_prefix("```") {
+        Some(rest) => {
+            let rest = rest.split_once('\n').map_or("", |(_, body)| body);
+            rest.trim_end().strip_suffix("```").unwrap_or(rest)
+        }
+        None => trimmed,
+    };
+    inner.trim().trim_matches('"').trim().to_string()
+}
+
+fn git(dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        bail!("git {}: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Staged diff and `--stat` summary.
+pub fn staged_changes(dir: &Path) -> anyhow::Result<(String, String)> {
+    Ok((
+        git(dir, &["diff", "--cached", "--no-color"])?,
+        git(dir, &["diff", "--cached", "--stat", "--no-color"])?,
+    ))
+}
+
+/// Commit the staged changes with `message`.
+pub fn commit(dir: &Path, message: &str) -> anyhow::Result<String> {
+    let mut child = Command::new("git")
+        .args(["commit", "-F", "-"])
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to run git")?;
+    child
+        .stdin
+        .take()
+        .context("git stdin unavailable")?
+        .write_all(message.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!("git commit: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Non-merge commits in `since..until`, newest first.
+pub fn git_log(dir: &Path, since: Option<&str>, until: &str) -> anyhow::Result<Vec<CommitInfo>> {
+    let range = match since {
+        Some(since) => format!("{}..{}", since, until),
+        None => until.to_string(),
+    };
+    let log = git(dir, &["log", "--no-merges", "--format=%H%x1f%s%x1f%b%x1e", &range])?;
+    Ok(log
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(3, '\x1f');
+            let hash = fields.next()?.trim();
+            if hash.is_empty() {
+                return None;
+            }
+            Some(CommitInfo {
+                hash: hash.to_string(),
+                subject: fields.next().unwrap_or_default().trim().to_string(),
+                body: fields.next().unwrap_or_default().trim().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Most recent tag reachable from HEAD, if any.
+pub fn latest_tag(dir: &Path) -> Option<String> {
+    git(dir, &["describe", "--tags", "--abbrev=0"])
+        .ok()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+/// Insert `section` at the top of an existing changelog, after a leading
+/// `# Title` line if there is one.
+pub fn prepend_changelog(existing: &str, section: &str) -> String {
+    let section = section.trim_end();
+    if existing.trim().is_empty() {
+        return format!("# Changelog\n\n{}\n", section);
+    }
+    match existing.split_once('\n') {
+        Some((first, rest)) if first.starts_with("# ") => {
+            format!("{}\n\n{}\n\n{}", first, section, rest.trim_start_matches('\n'))
+        }
+        _ => format!("{}\n\n{}", section, existing),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(hash: &str, subject: &str, body: &str) -> CommitInfo {
+        CommitInfo {
+            hash: hash.into(),
+            subject: subject.into(),
+            body: body.into(),
+        }
+    }
+
+    #[test]
+    fn test_conventional_parsing() {
+        let c = parse_conventional("feat(api)!: drop v1 routes").unwrap();
+        assert_eq!((c.kind, c.scope, c.breaking, c.description), ("feat", Some("api"), true, "drop v1 routes"));
+        assert!(is_conventional("fix: handle empty input\n\nDetails."));
+        assert!(!is_conventional("Fixed stuff"));
+        assert!(!is_conventional("wip: something"));
+        assert_eq!(clean_message("```text\nfeat: add x\n```"), "feat: add x");
+        assert_eq!(clean_message("\"docs: fix typo\""), "docs: fix typo");
+    }
+
+    #[test]
+    fn test_grouped_changelog() {
+        let commits = vec![
+            info("aaaaaaa111", "feat(cli): add verify command", ""),
+            info("bbbbbbb222", "fix: crash on empty config", ""),
+            info("ccccccc333", "refactor: split module", "BREAKING CHANGE: renamed API"),
+            info("ddddddd444", "Update README", ""),
+        ];
+        assert_eq!(
+            render_changelog("v1.3.0", "2026-01-02", &commits),
+            "## v1.3.0 - 2026-01-02\n\n\
+             ### Breaking Changes\n\n- split module (ccccccc)\n\n\
+             ### Features\n\n- **cli:** add verify command (aaaaaaa)\n\n\
+             ### Bug Fixes\n\n- crash on empty config (bbbbbbb)\n\n\
+             ### Other\n\n- Update README (ddddddd)\n"
+        );
+        assert_eq!(
+            prepend_changelog("# Changelog\n\n## v1.2.0\n", "## v1.3.0\n"),
+            "# Changelog\n\n## v1.3.0\n\n## v1.2.0\n"
+        );
+    }
+
+    #[test]
+    fn test_workspace_template_override() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(commit_prompt(tmp.path(), "diff", "1 file").contains("Conventional Commits"));
+        std::fs::create_dir(tmp.path().join("templates")).unwrap();
+        std::fs::write(tmp.path().join("templates/commit-msg.md"), "Summarize {{stat}}").unwrap();
+        assert_eq!(commit_prompt(tmp.path(), "diff", " 1 file\n"), "Summarize 1 file");
+    }
+
+    #[test]
+    fn test_git_log_and_commit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let run = |args: &[&str]| git(dir, args).unwrap();
+        run(&["init", "-q"]);
+        run(&["config", "user.email", "dev@example.com"]);
+        run(&["config", "user.name", "Dev"]);
+        std::fs::write(dir.join("a.txt"), "a").unwrap();
+        run(&["add", "a.txt"]);
+        commit(dir, "feat: first\n\nWith a body.").unwrap();
+        run(&["tag", "v0.1.0"]);
+        std::fs::write(dir.join("a.txt"), "b").unwrap();
+        run(&["add", "a.txt"]);
+        let (diff, stat) = staged_changes(dir).unwrap();
+        assert!(diff.contains("+b") && stat.contains("a.txt"));
+        commit(dir, "fix: second").unwrap();
+
+        assert_eq!(latest_tag(dir).as_deref(), Some("v0.1.0"));
+        let log = git_log(dir, Some("v0.1.0"), "HEAD").unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].subject, "fix: second");
+        let all = git_log(dir, None, "HEAD").unwrap();
+        assert_eq!(all[1].body, "With a body.");
+    }
+}
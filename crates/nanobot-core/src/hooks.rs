@@ -0,0 +1,232 @@
+//! Post-processing hooks for final replies.
+//!
+//! Deployers append disclaimers, add branding or redact text on the way
+//! out. A hook sees the final reply right before delivery, after citations
+//! and length limits, and the session keeps the reply as the model wrote
+//! it. Hooks come from `agents.hooks` in the config ([`ConfigHook`]) or are
+//! registered through the library API ([`ResponseHook`]); both can be
+//! limited to channels and agents. A `command` hook can run any program,
+//! including a wasm plugin under a runtime (`wasmtime run redact.wasm`).
+
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use regex::Regex;
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+use crate::config::ResponseHookConfig;
+
+/// How long a `command` hook may run before the reply goes out unchanged.
+pub const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a reply is going.
+#[derive(Debug, Clone, Copy)]
+pub struct HookContext<'a> {
+    pub channel: &'a str,
+    /// Agent that answered ("researcher", ...); `None` for the gateway agent.
+    pub agent: Option<&'a str>,
+    pub session_key: &'a str,
+}
+
+#[async_trait]
+pub trait ResponseHook: Send + Sync {
+    /// Name for logs.
+    fn name(&self) -> &str;
+
+    /// Whether the hook runs for this reply. Defaults to all replies.
+    fn applies(&self, _ctx: &HookContext<'_>) -> bool {
+        true
+    }
+
+    /// The reply to deliver instead of `reply`.
+    async fn process(&self, reply: String, ctx: &HookContext<'_>) -> String;
+}
+
+/// Hooks run in registration order.
+#[derive(Clone, Default)]
+pub struct ResponseHooks {
+    hooks: Vec<Arc<dyn ResponseHook>>,
+}
+
+impl ResponseHooks {
+    /// The hooks in `agents.hooks`; invalid redaction patterns are skipped
+    /// with a warning.
+    pub fn from_config(configs: &[ResponseHookConfig]) -> Self {
+        let mut hooks = Self::default();
+        for config in configs {
+            hooks.register(Arc::new(ConfigHook::new(config)));
+        }
+        hooks
+    }
+
+    pub fn register(&mut self, hook: Arc<dyn ResponseHook>) {
+        self.hooks.push(hook);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Run every hook that applies to `ctx` over `reply`.
+    pub async fn apply(&self, mut reply: String, ctx: &HookContext<'_>) -> String {
+        for hook in self.hooks.iter().filter(|h| h.applies(ctx)) {
+            reply = hook.process(reply, ctx).await;
+        }
+        reply
+    }
+}
+
+/// A hook declared in `agents.hooks`.
+pub struct ConfigHook {
+    name: String,
+    channels: Vec<String>,
+    agents: Vec<String>,
+    prepend: Option<String>,
+    append: Option<String>,
+    redact: Vec<Regex>,
+    redact_with: String,
+    command: Option<String>,
+}
+
+impl ConfigHook {
+    pub fn new(config: &ResponseHookConfig) -> Self {
+        let name = if config.name.is_empty() { "unnamed".to_string() } else { config.name.clone() };
+        let redact = config
+            .redact
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    warn!("Response hook '{}': invalid redact pattern '{}': {}", name, pattern, e);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            name,
+            channels: config.channels.clone(),
+            agents: config.agents.clone(),
+            prepend: config.prepend.clone().filter(|s| !s.is_empty()),
+            append: config.append.clone().filter(|s| !s.is_empty()),
+            redact,
+            redact_with: config.redact_with.clone(),
+            command: config.command.clone().filter(|c| !c.trim().is_empty()),
+        }
+    }
+}
+
+#[async_trait]
+impl ResponseHook for ConfigHook {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn applies(&self, ctx: &HookContext<'_>) -> bool {
+        let channel = self.channels.is_empty() || self.channels.iter().any(|c| c == ctx.channel);
+        let agent = self.agents.is_empty() || ctx.agent.is_some_and(|a| self.agents.iter().any(|name| name == a));
+        channel && agent
+    }
+
+    async fn process(&self, mut reply: String, ctx: &HookContext<'_>) -> String {
+        for re in &self.redact {
+            reply = re.replace_all(&reply, self.redact_with.as_str()).into_owned();
+        }
+        if let Some(ref command) = self.command {
+            match run_command(command, &reply, ctx).await {
+                Ok(out) => reply = out,
+                Err(e) => warn!("Response hook '{}' failed, reply left unchanged: {}", self.name, e),
+            }
+        }
+        if let Some(ref prepend) = self.prepend {
+            reply = format!("{}\n\n{}", prepend, reply);
+        }
+        if let Some(ref append) = self.append {
+            reply = format!("{}\n\n{}", reply, append);
+        }
+        reply
+    }
+}
+
+/// Pipe `reply` through a shell command. The channel, agent and session are
+/// passed as `NANOBOT_CHANNEL`, `NANOBOT_AGENT` and `NANOBOT_SESSION`.
+async fn run_command(command: &str, reply: &str, ctx: &HookContext<'_>) -> Result<String, String> {
+    let mut child = tokio::process::Command::new("sh")
+        .args(["-c", command])
+        .env("NANOBOT_CHANNEL", ctx.channel)
+        .env("NANOBOT_AGENT", ctx.agent.unwrap_or_default())
+        .env("NANOBOT_SESSION", ctx.session_key)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    let mut stdin = child.stdin.take().ok_or("stdin unavailable")?;
+    stdin.write_all(reply.as_bytes()).await.map_err(|e| e.to_string())?;
+    drop(stdin);
+    let output = tokio::time::timeout(COMMAND_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| "timed out".to_string())?
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} {}", output.status, stderr.lines().last().unwrap_or("").trim()));
+    }
+    let out = String::from_utf8_lossy(&output.stdout).trim_end().to_string();
+    if out.is_empty() {
+        return Err("empty output".to_string());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEB: HookContext<'static> = HookContext { channel: "web", agent: Some("researcher"), session_key: "web:1" };
+
+    #[tokio::test]
+    async fn test_config_hooks() {
+        let hooks = ResponseHooks::from_config(&[
+            ResponseHookConfig {
+                name: "pii".into(),
+                redact: vec![r"\d{3}-\d{4}-\d{4}".into(), "(".into()],
+                ..Default::default()
+            },
+            ResponseHookConfig {
+                name: "disclaimer".into(),
+                channels: vec!["web".into()],
+                agents: vec!["researcher".into()],
+                append: Some("※ AIによる回答です".into()),
+                ..Default::default()
+            },
+            ResponseHookConfig {
+                name: "line-only".into(),
+                channels: vec!["line".into()],
+                prepend: Some("[Brand]".into()),
+                ..Default::default()
+            },
+        ]);
+        let reply = hooks.apply("Call 090-1234-5678".into(), &WEB).await;
+        assert_eq!(reply, "Call [redacted]\n\n※ AIによる回答です");
+
+        // Agent-limited hooks skip the gateway agent
+        let line = HookContext { channel: "line", agent: None, session_key: "line:1" };
+        assert_eq!(hooks.apply("Hi".into(), &line).await, "[Brand]\n\nHi");
+    }
+
+    #[tokio::test]
+    async fn test_command_hook() {
+        let upper = ConfigHook::new(&ResponseHookConfig {
+            command: Some("tr a-z A-Z; printf \" ($NANOBOT_CHANNEL)\"".into()),
+            ..Default::default()
+        });
+        assert_eq!(upper.process("hello".into(), &WEB).await, "HELLO (web)");
+
+        let failing = ConfigHook::new(&ResponseHookConfig { command: Some("exit 1".into()), ..Default::default() });
+        assert_eq!(failing.process("hello".into(), &WEB).await, "hello");
+    }
+}
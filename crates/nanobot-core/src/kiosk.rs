@@ -0,0 +1,339 @@
+//! Voice appliance mode (`chatweb kiosk`).
+//!
+//! A kiosk is a small always-on box (typically a Raspberry Pi with a USB
+//! microphone and a speaker) running hands-free voice with no screen. This
+//! module holds the parts that aren't about audio:
+//!
+//! - [`StatusHooks`] run a shell command whenever the [`KioskState`]
+//!   changes, so LEDs or GPIO pins can show listening/thinking/speaking
+//!   (e.g. `gpioset gpiochip0 17=1`).
+//! - [`LocalFallback`] answers with a local model (an Ollama or other
+//!   OpenAI-compatible server) while chatweb.ai can't be reached, and
+//!   [`transcribe_locally`] / [`speak_locally`] do the same for speech.
+//! - [`DeviceCommand`]s are queued on the server by the owner
+//!   (`POST /api/v1/devices/commands`) and handed to the device in the
+//!   reply to its next heartbeat, the daemon's command channel.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::config::KioskConfig;
+use crate::provider::LlmProvider;
+use crate::types::Message;
+use crate::util::{now_ms, read_json, write_json};
+
+/// Commands kept per device until it picks them up.
+pub const MAX_QUEUED_COMMANDS: usize = 50;
+
+/// Exchanges the local fallback model remembers.
+const FALLBACK_HISTORY: usize = 6;
+
+/// What the appliance is doing, as shown by the status hooks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KioskState {
+    Listening,
+    Thinking,
+    Speaking,
+    /// Listening, but chatweb.ai is unreachable and the fallbacks answer.
+    Offline,
+    Muted,
+    Error,
+}
+
+impl KioskState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Listening => "listening",
+            Self::Thinking => "thinking",
+            Self::Speaking => "speaking",
+            Self::Offline => "offline",
+            Self::Muted => "muted",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// Runs `kiosk.hooks[state]` on every state change, with the state in
+/// `CHATWEB_STATE`. A `"*"` hook runs for states without their own.
+pub struct StatusHooks {
+    hooks: HashMap<String, String>,
+    current: Option<KioskState>,
+}
+
+impl StatusHooks {
+    pub fn new(hooks: HashMap<String, String>) -> Self {
+        Self { hooks, current: None }
+    }
+
+    fn hook_for(&self, state: KioskState) -> Option<&str> {
+        self.hooks
+            .get(state.as_str())
+            .or_else(|| self.hooks.get("*"))
+            .map(String::as_str)
+            .filter(|cmd| !cmd.trim().is_empty())
+    }
+
+    pub fn current(&self) -> Option<KioskState> {
+        self.current
+    }
+
+    /// Switch to `state`; returns whether it changed. Hooks run in the
+    /// background so a slow one can't stall the audio loop.
+    pub fn set(&mut self, state: KioskState) -> bool {
+        if self.current == Some(state) {
+            return false;
+        }
+        self.current = Some(state);
+        if let Some(cmd) = self.hook_for(state) {
+            debug!("Kiosk state {}: {}", state.as_str(), cmd);
+            let spawned = Command::new("sh")
+                .args(["-c", cmd])
+                .env("CHATWEB_STATE", state.as_str())
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .spawn();
+            match spawned {
+                // Reap in the background; the exit status doesn't matter.
+                Ok(mut child) => {
+                    std::thread::spawn(move || child.wait());
+                }
+                Err(e) => warn!("Kiosk hook for {} failed: {}", state.as_str(), e),
+            }
+        }
+        true
+    }
+}
+
+/// Local model used while chatweb.ai is unreachable.
+pub struct LocalFallback {
+    provider: Box<dyn LlmProvider>,
+    model: String,
+    history: Vec<Message>,
+}
+
+impl LocalFallback {
+    /// The fallback configured in `kiosk.fallbackModel`, if any.
+    pub fn from_config(config: &KioskConfig) -> Option<Self> {
+        if config.fallback_model.is_empty() {
+            return None;
+        }
+        let provider = crate::provider::create_provider(
+            &config.fallback_api_key,
+            Some(&config.fallback_api_base),
+            &config.fallback_model,
+        );
+        Some(Self {
+            provider,
+            model: config.fallback_model.clone(),
+            history: Vec::new(),
+        })
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Answer `text`, keeping a few exchanges of context.
+    pub async fn reply(&mut self, text: &str) -> anyhow::Result<String> {
+        let mut messages = vec![Message::system(
+            "You are a voice assistant running offline on a small device. \
+             Answer briefly in plain spoken sentences, in the user's language. \
+             You have no internet access; say so if asked for live information.",
+        )];
+        messages.extend(self.history.iter().cloned());
+        messages.push(Message::user(text));
+        let response = self.provider.chat(&messages, None, &self.model, 300, 0.7).await?;
+        let reply = response.content.unwrap_or_default().trim().to_string();
+        self.history.push(Message::user(text));
+        self.history.push(Message::assistant(reply.clone()));
+        let excess = self.history.len().saturating_sub(FALLBACK_HISTORY * 2);
+        self.history.drain(..excess);
+        Ok(reply)
+    }
+}
+
+/// Transcribe a WAV file with a local recognizer command
+/// (`kiosk.offlineSttCommand`, `voice.sttCommand`); `{wav}` in the command
+/// is replaced by the file path and stdout is the transcript.
+pub fn transcribe_locally(command: &str, wav: &[u8]) -> anyhow::Result<String> {
+    let path = std::env::temp_dir().join(format!("chatweb-kiosk-{}.wav", std::process::id()));
+    std::fs::write(&path, wav)?;
+    let output = Command::new("sh")
+        .args(["-c", &command.replace("{wav}", &path.display().to_string())])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+    let _ = std::fs::remove_file(&path);
+    let output = output?;
+    if !output.status.success() {
+        anyhow::bail!("offline speech recognition failed ({})", output.status);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Speak `text` with `kiosk.offlineTtsCommand` (text on stdin); returns when
+/// it finishes.
+pub fn speak_locally(command: &str, text: &str) -> anyhow::Result<()> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("offline speech synthesis failed ({})", status);
+    }
+    Ok(())
+}
+
+/// Remote management command for a device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeviceCommand {
+    /// Speak a message.
+    Say { text: String },
+    /// Set the output volume (0-100) with `kiosk.volumeCommand`.
+    Volume { percent: u8 },
+    /// Stop listening until unmuted.
+    Mute,
+    Unmute,
+    /// Exit so the service manager restarts the process (and picks up a
+    /// new binary or config).
+    Restart,
+}
+
+/// A command waiting for its device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedCommand {
+    pub id: String,
+    pub command: DeviceCommand,
+    pub queued_at_ms: u64,
+}
+
+/// Server-side queue of device commands, keyed by owner and hostname.
+pub struct DeviceCommandQueue {
+    store_path: PathBuf,
+    store: Option<BTreeMap<String, Vec<QueuedCommand>>>,
+}
+
+fn device_key(owner: &str, hostname: &str) -> String {
+    format!("{}/{}", owner, hostname)
+}
+
+impl DeviceCommandQueue {
+    pub fn new(store_path: PathBuf) -> Self {
+        Self { store_path, store: None }
+    }
+
+    pub fn default_path() -> PathBuf {
+        crate::config::get_data_dir().join("device_commands.json")
+    }
+
+    fn load_store(&mut self) -> &mut BTreeMap<String, Vec<QueuedCommand>> {
+        if self.store.is_none() {
+            self.store = Some(read_json(&self.store_path, "device commands"));
+        }
+        self.store.as_mut().unwrap()
+    }
+
+    fn save_store(&mut self) {
+        if let Some(ref store) = self.store {
+            write_json(&self.store_path, store, "device commands");
+        }
+    }
+
+    /// Queue `command` for `owner`'s device `hostname`; returns its id.
+    pub fn push(&mut self, owner: &str, hostname: &str, command: DeviceCommand) -> String {
+        let id = Uuid::new_v4().to_string()[..8].to_string();
+        let queue = self.load_store().entry(device_key(owner, hostname)).or_default();
+        queue.push(QueuedCommand {
+            id: id.clone(),
+            command,
+            queued_at_ms: now_ms(),
+        });
+        let excess = queue.len().saturating_sub(MAX_QUEUED_COMMANDS);
+        queue.drain(..excess);
+        self.save_store();
+        id
+    }
+
+    /// Commands waiting for a device, without removing them.
+    pub fn pending(&mut self, owner: &str, hostname: &str) -> Vec<QueuedCommand> {
+        self.load_store().get(&device_key(owner, hostname)).cloned().unwrap_or_default()
+    }
+
+    /// Hand over (and forget) the commands waiting for a device.
+    pub fn drain(&mut self, owner: &str, hostname: &str) -> Vec<QueuedCommand> {
+        let taken = self.load_store().remove(&device_key(owner, hostname)).unwrap_or_default();
+        if !taken.is_empty() {
+            self.save_store();
+        }
+        taken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_queue_is_per_device() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut queue = DeviceCommandQueue::new(tmp.path().join("commands.json"));
+        queue.push("user:1", "kitchen-pi", DeviceCommand::Volume { percent: 40 });
+        queue.push("user:1", "kitchen-pi", DeviceCommand::Say { text: "dinner!".into() });
+        queue.push("user:1", "office-pi", DeviceCommand::Restart);
+
+        // Persisted, and only the addressed device gets them, in order.
+        let mut reloaded = DeviceCommandQueue::new(tmp.path().join("commands.json"));
+        let commands: Vec<_> = reloaded.drain("user:1", "kitchen-pi").into_iter().map(|c| c.command).collect();
+        assert_eq!(
+            commands,
+            [DeviceCommand::Volume { percent: 40 }, DeviceCommand::Say { text: "dinner!".into() }]
+        );
+        assert!(reloaded.drain("user:1", "kitchen-pi").is_empty());
+        assert_eq!(reloaded.pending("user:1", "office-pi").len(), 1);
+        assert!(reloaded.pending("user:2", "office-pi").is_empty());
+    }
+
+    #[test]
+    fn test_command_wire_format() {
+        let cmd: DeviceCommand = serde_json::from_str(r#"{"type":"say","text":"hi"}"#).unwrap();
+        assert_eq!(cmd, DeviceCommand::Say { text: "hi".into() });
+        assert_eq!(serde_json::to_value(DeviceCommand::Mute).unwrap(), serde_json::json!({ "type": "mute" }));
+    }
+
+    #[test]
+    fn test_hooks_fire_on_change_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        let log = tmp.path().join("states");
+        let hooks = HashMap::from([("*".to_string(), format!("echo $CHATWEB_STATE >> {}", log.display()))]);
+        let mut status = StatusHooks::new(hooks);
+        assert!(status.set(KioskState::Listening));
+        assert!(!status.set(KioskState::Listening));
+        assert!(status.set(KioskState::Thinking));
+        assert_eq!(status.current(), Some(KioskState::Thinking));
+
+        // Hooks run in the background.
+        for _ in 0..50 {
+            if std::fs::read_to_string(&log).is_ok_and(|s| s.lines().count() == 2) {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        let mut states: Vec<String> = std::fs::read_to_string(&log).unwrap().lines().map(String::from).collect();
+        states.sort();
+        assert_eq!(states, ["listening", "thinking"]);
+    }
+}
@@ -7,6 +7,28 @@ pub mod session;
 pub mod memory;
 pub mod skills;
 pub mod agent;
+pub mod command;
+pub mod feedback;
+pub mod expense;
+pub mod credits;
+pub mod omikuji;
+pub mod delivery;
+pub mod offline;
+pub mod termux;
+pub mod kiosk;
+pub mod webhook;
+pub mod workflow;
+pub mod rules;
+pub mod faq;
+pub mod onboarding;
+pub mod review;
+pub mod factcheck;
+pub mod citation;
+pub mod hooks;
+pub mod gitgen;
+pub mod voice;
+#[cfg(feature = "code-intel")]
+pub mod code_intel;
 pub mod provider;
 pub mod tool;
 pub mod channel;
@@ -14,6 +36,7 @@ pub mod service;
 pub mod util;
 pub mod mcp;
 pub mod cache;
+pub mod bench;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const GIT_HASH: &str = env!("GIT_HASH");
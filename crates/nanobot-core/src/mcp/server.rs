@@ -0,0 +1,503 @@
+//! MCP server: nanobot's tools for other MCP clients (Claude Desktop, IDEs).
+//!
+//! The reverse of [`super::client`]. `chatweb mcp-serve` speaks JSON-RPC over
+//! stdio, one message per line. With `--http` it serves Streamable HTTP at
+//! `/mcp` and the older HTTP+SSE transport at `/sse` + `/messages` instead
+//! (`http-api` feature), on a loopback address unless `gateway.apiTokens` is
+//! set. [`standard_tools`] builds the tool groups `--tools` picks from;
+//! `shell` is only served when asked for.
+//!
+//! Claude Desktop (`claude_desktop_config.json`):
+//!
+//! ```json
+//! { "mcpServers": { "nanobot": { "command": "chatweb", "args": ["mcp-serve", "--tools", "fs,web"] } } }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::bail;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::config::Config;
+use crate::service::cron::CronService;
+use crate::tool::crawl::WebCrawlTool;
+use crate::tool::cron_tool::CronTool;
+use crate::tool::file_policy::FilePolicy;
+use crate::tool::filesystem::{EditFileTool, ListDirTool, ReadFileTool, WriteFileTool};
+use crate::tool::patch::ApplyPatchTool;
+use crate::tool::policy::ToolPolicy;
+use crate::tool::search::GrepWorkspaceTool;
+use crate::tool::shell::ExecTool;
+use crate::tool::stats::is_failure;
+use crate::tool::web::{WebFetchTool, WebSearchTool};
+use crate::tool::ToolRegistry;
+
+/// Protocol version offered when the client asks for one we don't know.
+pub const PROTOCOL_VERSION: &str = "2025-11-25";
+const SUPPORTED_VERSIONS: &[&str] = &["2025-11-25", "2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Tool groups for [`standard_tools`].
+pub const TOOL_GROUPS: &[&str] = &["fs", "shell", "web", "cron"];
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Tools for `chatweb mcp-serve`, set up from `config.tools` as for the
+/// agent:
+///
+/// - `fs`: read_file, write_file, edit_file, apply_patch, list_dir,
+///   grep_workspace
+/// - `shell`: exec
+/// - `web`: web_search, read_webpage, web_crawl
+/// - `cron`: cron; a running gateway delivers the reminders to
+///   `cron_target` (`channel:chat_id`)
+pub async fn standard_tools(config: &Config, groups: &[String], cron_target: &str) -> anyhow::Result<ToolRegistry> {
+    let tools = ToolRegistry::new();
+    let workspace = config.workspace_path();
+    let allowed_dir = config.tools.restrict_to_workspace.then(|| workspace.clone());
+    for group in groups {
+        match group.as_str() {
+            "fs" => {
+                let policy = Arc::new(FilePolicy::new(&workspace, &config.tools.files));
+                tools.register(Arc::new(ReadFileTool::new(allowed_dir.clone()).with_policy(policy.clone())));
+                tools.register(Arc::new(WriteFileTool::new(allowed_dir.clone()).with_policy(policy.clone())));
+                tools.register(Arc::new(EditFileTool::new(allowed_dir.clone()).with_policy(policy.clone())));
+                tools.register(Arc::new(ApplyPatchTool::new(workspace.clone(), allowed_dir.clone()).with_policy(policy)));
+                tools.register(Arc::new(ListDirTool::new(allowed_dir.clone())));
+                tools.register(Arc::new(GrepWorkspaceTool::new(workspace.clone())));
+            }
+            "shell" => {
+                tools.register(Arc::new(ExecTool::new(
+                    workspace.display().to_string(),
+                    config.tools.exec_config.timeout,
+                    config.tools.restrict_to_workspace,
+                )));
+            }
+            "web" => {
+                tools.register(Arc::new(WebSearchTool::from_config(&config.tools.web.search)));
+                tools.register(Arc::new(WebFetchTool::new(50000)));
+                tools.register(Arc::new(WebCrawlTool::new(30000)));
+            }
+            "cron" => {
+                let Some((channel, chat_id)) = cron_target.split_once(':') else {
+                    bail!("cron target must be channel:chat_id, got \"{}\"", cron_target);
+                };
+                let cron = CronService::open(crate::config::get_data_dir().join("cron").join("jobs.json"));
+                let tool = CronTool::new(Arc::new(Mutex::new(cron)));
+                tool.set_context(channel, chat_id).await;
+                tools.register(Arc::new(tool));
+            }
+            other => bail!("unknown tool group \"{}\" (expected {})", other, TOOL_GROUPS.join(", ")),
+        }
+    }
+    tools.set_policy(ToolPolicy::from_config(&config.tools.policy).with_workspace(&workspace));
+    Ok(tools)
+}
+
+/// Answers MCP requests with the tools of a [`ToolRegistry`].
+pub struct McpServer {
+    tools: Arc<ToolRegistry>,
+}
+
+impl McpServer {
+    pub fn new(tools: Arc<ToolRegistry>) -> Self {
+        Self { tools }
+    }
+
+    /// The response to one JSON-RPC message; `None` for notifications.
+    pub async fn handle(&self, message: Value) -> Option<Value> {
+        if !message.is_object() {
+            return Some(error(Value::Null, INVALID_REQUEST, "expected a JSON-RPC object"));
+        }
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // A response: this server sends no requests of its own
+            return None;
+        };
+        let id = message.get("id")?.clone();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let result = match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": self.list_tools() })),
+            "tools/call" => self.call_tool(&params).await,
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method {}", method))),
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error(id, code, &message),
+        })
+    }
+
+    /// [`Self::handle`] for a message as text.
+    pub async fn handle_text(&self, text: &str) -> Option<Value> {
+        match serde_json::from_str(text) {
+            Ok(message) => self.handle(message).await,
+            Err(e) => Some(error(Value::Null, PARSE_ERROR, &e.to_string())),
+        }
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        let requested = params.get("protocolVersion").and_then(Value::as_str).unwrap_or_default();
+        let version = SUPPORTED_VERSIONS.iter().copied().find(|v| *v == requested).unwrap_or(PROTOCOL_VERSION);
+        json!({
+            "protocolVersion": version,
+            "capabilities": { "tools": { "listChanged": false } },
+            "serverInfo": { "name": "nanobot", "version": crate::VERSION },
+        })
+    }
+
+    fn list_tools(&self) -> Vec<Value> {
+        let mut names = self.tools.tool_names();
+        names.sort();
+        names
+            .iter()
+            .filter_map(|name| self.tools.get(name))
+            .map(|tool| {
+                json!({
+                    "name": tool.name(),
+                    "description": tool.description(),
+                    "inputSchema": tool.parameters(),
+                })
+            })
+            .collect()
+    }
+
+    async fn call_tool(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or((INVALID_PARAMS, "missing tool name".to_string()))?;
+        if !self.tools.has(name) {
+            return Err((INVALID_PARAMS, format!("unknown tool {}", name)));
+        }
+        let args: HashMap<String, Value> = match params.get("arguments") {
+            None | Some(Value::Null) => HashMap::new(),
+            Some(args) => serde_json::from_value(args.clone())
+                .map_err(|_| (INVALID_PARAMS, "arguments must be an object".to_string()))?,
+        };
+        let text = self.tools.execute(name, args).await;
+        Ok(json!({
+            "content": [{ "type": "text", "text": text }],
+            "isError": is_failure(&text),
+        }))
+    }
+
+    /// Answer JSON-RPC lines from stdin on stdout until stdin closes.
+    /// Requests run concurrently, so a slow tool doesn't hold up `ping`.
+    pub async fn serve_stdio(self: Arc<Self>) -> std::io::Result<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+        let writer = tokio::spawn(async move {
+            let mut stdout = tokio::io::stdout();
+            while let Some(response) = rx.recv().await {
+                let line = response.to_string() + "\n";
+                if stdout.write_all(line.as_bytes()).await.is_err() || stdout.flush().await.is_err() {
+                    break;
+                }
+            }
+        });
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let server = self.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                if let Some(response) = server.handle_text(&line).await {
+                    let _ = tx.send(response);
+                }
+            });
+        }
+        // The writer ends once the requests still running have answered
+        drop(tx);
+        let _ = writer.await;
+        Ok(())
+    }
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+#[cfg(feature = "http-api")]
+pub use self::http::serve_http;
+
+#[cfg(feature = "http-api")]
+mod http {
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::sync::Arc;
+
+    use axum::extract::{Query, State};
+    use axum::http::{header, HeaderMap, StatusCode};
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use serde::Deserialize;
+    use serde_json::Value;
+    use tokio::sync::mpsc;
+
+    use super::McpServer;
+
+    struct HttpState {
+        server: Arc<McpServer>,
+        /// Bearer tokens; empty = no authentication.
+        tokens: Vec<String>,
+        /// Open `/sse` streams by session id.
+        streams: std::sync::Mutex<HashMap<String, mpsc::UnboundedSender<Value>>>,
+    }
+
+    /// Serve Streamable HTTP (`POST /mcp`) and HTTP+SSE (`GET /sse`, then
+    /// `POST /messages?session_id=`) on `addr`. With `tokens`, requests need
+    /// one as a Bearer token; without, `addr` must be a loopback address and
+    /// browsers from other sites are turned away by their `Origin`.
+    pub async fn serve_http(server: Arc<McpServer>, addr: &str, tokens: Vec<String>) -> anyhow::Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let local = listener.local_addr()?;
+        if tokens.is_empty() && !local.ip().is_loopback() {
+            anyhow::bail!(
+                "refusing to serve MCP on {} without authentication: bind to 127.0.0.1 or set gateway.apiTokens",
+                local
+            );
+        }
+        let state = Arc::new(HttpState { server, tokens, streams: Default::default() });
+        let router = Router::new()
+            .route("/mcp", post(handle_mcp))
+            .route("/sse", get(handle_sse))
+            .route("/messages", post(handle_message))
+            .with_state(state);
+        axum::serve(listener, router).await?;
+        Ok(())
+    }
+
+    /// The response refusing a request, if its token or origin isn't allowed.
+    fn check(state: &HttpState, headers: &HeaderMap) -> Option<Response> {
+        if !state.tokens.is_empty() {
+            let bearer = headers
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "));
+            if !bearer.is_some_and(|token| state.tokens.iter().any(|t| t == token)) {
+                return Some((StatusCode::UNAUTHORIZED, "Invalid API token").into_response());
+            }
+            return None;
+        }
+        // Guards against DNS rebinding: a page elsewhere reaching a local server
+        if let Some(origin) = headers.get(header::ORIGIN).and_then(|v| v.to_str().ok()) {
+            if !is_local_origin(origin) {
+                return Some((StatusCode::FORBIDDEN, "Origin not allowed").into_response());
+            }
+        }
+        None
+    }
+
+    pub(super) fn is_local_origin(origin: &str) -> bool {
+        let host = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+        let host = host.rsplit_once(':').filter(|(_, port)| port.parse::<u16>().is_ok()).map_or(host, |(h, _)| h);
+        matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+    }
+
+    /// POST /mcp: one message in, its response (or 202) out.
+    async fn handle_mcp(State(state): State<Arc<HttpState>>, headers: HeaderMap, body: String) -> Response {
+        if let Some(denied) = check(&state, &headers) {
+            return denied;
+        }
+        match state.server.handle_text(&body).await {
+            Some(response) => Json(response).into_response(),
+            None => StatusCode::ACCEPTED.into_response(),
+        }
+    }
+
+    /// Removes its stream from the state when the client goes away.
+    struct StreamGuard {
+        state: Arc<HttpState>,
+        id: String,
+    }
+
+    impl Drop for StreamGuard {
+        fn drop(&mut self) {
+            self.state.streams.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.id);
+        }
+    }
+
+    /// GET /sse: an `endpoint` event to post to, then a `message` event per
+    /// response.
+    async fn handle_sse(State(state): State<Arc<HttpState>>, headers: HeaderMap) -> Response {
+        if let Some(denied) = check(&state, &headers) {
+            return denied;
+        }
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        state.streams.lock().unwrap_or_else(|e| e.into_inner()).insert(id.clone(), tx);
+        let guard = StreamGuard { state: state.clone(), id: id.clone() };
+        let events = async_stream::stream! {
+            let _guard = guard;
+            yield Ok::<_, Infallible>(Event::default().event("endpoint").data(format!("/messages?session_id={}", id)));
+            while let Some(response) = rx.recv().await {
+                yield Ok(Event::default().event("message").data(response.to_string()));
+            }
+        };
+        Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+    }
+
+    #[derive(Deserialize)]
+    struct MessageQuery {
+        session_id: String,
+    }
+
+    /// POST /messages?session_id=: the response goes out on the `/sse` stream.
+    async fn handle_message(
+        State(state): State<Arc<HttpState>>,
+        Query(q): Query<MessageQuery>,
+        headers: HeaderMap,
+        body: String,
+    ) -> Response {
+        if let Some(denied) = check(&state, &headers) {
+            return denied;
+        }
+        let Some(stream) = state.streams.lock().unwrap_or_else(|e| e.into_inner()).get(&q.session_id).cloned() else {
+            return (StatusCode::NOT_FOUND, "Unknown session").into_response();
+        };
+        let server = state.server.clone();
+        tokio::spawn(async move {
+            if let Some(response) = server.handle_text(&body).await {
+                let _ = stream.send(response);
+            }
+        });
+        StatusCode::ACCEPTED.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    struct EchoTool;
+
+    #[async_trait]
+    impl crate::tool::Tool for EchoTool {
+        fn name(&self) -> &str {
+            "echo"
+        }
+        fn description(&self) -> &str {
+            "Echo the text"
+        }
+        fn parameters(&self) -> Value {
+            json!({ "type": "object", "properties": { "text": { "type": "string" } }, "required": ["text"] })
+        }
+        async fn execute(&self, params: HashMap<String, Value>) -> String {
+            match params.get("text").and_then(Value::as_str) {
+                Some(text) => text.to_string(),
+                None => "Error: text is required".to_string(),
+            }
+        }
+    }
+
+    fn server() -> McpServer {
+        let tools = ToolRegistry::new();
+        tools.register(Arc::new(EchoTool));
+        McpServer::new(Arc::new(tools))
+    }
+
+    #[tokio::test]
+    async fn test_requests() {
+        let server = server();
+        let init = server
+            .handle(json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"protocolVersion": "2025-06-18"}}))
+            .await
+            .unwrap();
+        assert_eq!(init["result"]["protocolVersion"], "2025-06-18");
+        assert!(server.handle(json!({"jsonrpc": "2.0", "method": "notifications/initialized"})).await.is_none());
+
+        let list = server.handle(json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"})).await.unwrap();
+        assert_eq!(list["result"]["tools"][0]["name"], "echo");
+        assert_eq!(list["result"]["tools"][0]["inputSchema"]["required"], json!(["text"]));
+
+        let call = json!({"jsonrpc": "2.0", "id": "c", "method": "tools/call", "params": {"name": "echo", "arguments": {"text": "hi"}}});
+        let result = server.handle(call).await.unwrap();
+        assert_eq!(result["id"], "c");
+        assert_eq!(result["result"], json!({"content": [{"type": "text", "text": "hi"}], "isError": false}));
+
+        let failed = server.handle(json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {"name": "echo"}})).await.unwrap();
+        assert_eq!(failed["result"]["isError"], true);
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let server = server();
+        let unknown_tool = json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {"name": "rm"}});
+        assert_eq!(server.handle(unknown_tool).await.unwrap()["error"]["code"], INVALID_PARAMS);
+        let unknown_method = json!({"jsonrpc": "2.0", "id": 2, "method": "resources/list"});
+        assert_eq!(server.handle(unknown_method).await.unwrap()["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(server.handle_text("{not json").await.unwrap()["error"]["code"], PARSE_ERROR);
+        let Err(err) = standard_tools(&Config::default(), &["everything".into()], "cli:mcp").await else {
+            panic!("unknown tool group accepted");
+        };
+        assert!(err.to_string().contains("fs, shell, web, cron"));
+    }
+
+    #[tokio::test]
+    async fn test_policy_for_client_edits() {
+        // An MCP client cannot be asked, so by default its edits just run
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("notes.txt"), "old\n").unwrap();
+        let mut config = Config::default();
+        config.agents.defaults.workspace = tmp.path().display().to_string();
+        let server = McpServer::new(Arc::new(standard_tools(&config, &["fs".into()], "cli:mcp").await.unwrap()));
+        let path = tmp.path().join("notes.txt").display().to_string();
+        let edit = |from: &str, to: &str| {
+            json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {"name": "edit_file",
+                "arguments": {"path": path, "old_text": from, "new_text": to}}})
+        };
+        let result = server.handle(edit("old", "new")).await.unwrap();
+        assert_eq!(result["result"]["isError"], false, "{result}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new\n");
+
+        // With confirmDestructive set they are refused
+        config.tools.policy.confirm_destructive = Some(true);
+        let server = McpServer::new(Arc::new(standard_tools(&config, &["fs".into()], "cli:mcp").await.unwrap()));
+        let result = server.handle(edit("new", "old")).await.unwrap();
+        assert_eq!(result["result"]["isError"], true, "{result}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new\n");
+
+        // An explicit allow rule lets clients edit without confirmation
+        for tool in ["edit_file", "write_file"] {
+            config.tools.policy.rules.insert(tool.to_string(), "allow".to_string());
+        }
+        let server = McpServer::new(Arc::new(standard_tools(&config, &["fs".into()], "cli:mcp").await.unwrap()));
+        let result = server.handle(edit("new", "old")).await.unwrap();
+        assert_eq!(result["result"]["isError"], false, "{result}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old\n");
+
+        let write = json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {"name": "write_file",
+            "arguments": {"path": path, "content": "newer\n"}}});
+        let result = server.handle(write).await.unwrap();
+        assert_eq!(result["result"]["isError"], false, "{result}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "newer\n");
+    }
+
+    #[cfg(feature = "http-api")]
+    #[test]
+    fn test_local_origin() {
+        assert!(http::is_local_origin("http://localhost:6274"));
+        assert!(http::is_local_origin("http://127.0.0.1"));
+        assert!(http::is_local_origin("http://[::1]:8080"));
+        assert!(!http::is_local_origin("https://evil.example"));
+        assert!(!http::is_local_origin("http://localhost.evil.example"));
+    }
+
+    #[cfg(feature = "http-api")]
+    #[tokio::test]
+    async fn test_public_bind_needs_tokens() {
+        let err = serve_http(Arc::new(server()), "0.0.0.0:0", Vec::new()).await.unwrap_err();
+        assert!(err.to_string().contains("without authentication"), "{err}");
+    }
+}
@@ -0,0 +1,304 @@
+//! Consolidating daily memory notes into long-term memory.
+//!
+//! A model reads the long-term memory and the recent daily notes and writes
+//! the merged long-term memory back (`agents.memory`):
+//!
+//! - the gateway runs it on `schedule` as a cron job, the API server after
+//!   every `everyEntries` daily entries and when a conversation is closed,
+//!   and `chatweb memory consolidate` on demand;
+//! - `model` may be a local model (`local-qwen3-0.6b`) so notes stay on the
+//!   machine, and `prompt` replaces the built-in instructions;
+//! - a failed model call is retried `retries` times, waiting longer each
+//!   time;
+//! - API users opt out with the `memory_consolidation` setting.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::warn;
+
+use crate::config::{Config, MemoryConfig};
+use crate::provider::{self, LlmProvider};
+use crate::service::cron::{CronPayload, CronSchedule, CronService};
+use crate::types::Message;
+
+use super::backend::MemoryBackend;
+
+/// Cron payload kind of scheduled consolidations.
+pub const CONSOLIDATE_KIND: &str = "memory_consolidate";
+
+/// Built-in instructions; `{long_term}` and `{daily}` are filled in.
+pub const DEFAULT_PROMPT: &str = "以下はユーザーの既存の長期記憶と最近の会話ログです。\n\
+    ログから重要な事実・好み・決定事項を抽出し、長期記憶を更新してください。\n\
+    箇条書きで簡潔に（最大20項目）。重複は統合。古い情報は最新で上書き。\n\n\
+    ## 既存の長期記憶\n{long_term}\n\n## 最近の会話ログ\n{daily}\n\n\
+    ## 更新後の長期記憶（箇条書きのみ出力）:";
+
+/// Wait before the first retry; doubled for each further one.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// `agents.memory`, ready to run.
+#[derive(Debug, Clone)]
+pub struct Consolidator {
+    model: String,
+    prompt: String,
+    retries: u32,
+    days: u32,
+    retry_delay: Duration,
+}
+
+impl Consolidator {
+    pub fn from_config(config: &MemoryConfig) -> Self {
+        Self {
+            model: config.model.clone(),
+            prompt: if config.prompt.is_empty() { DEFAULT_PROMPT.to_string() } else { config.prompt.clone() },
+            retries: config.retries,
+            days: config.days.max(1),
+            retry_delay: RETRY_DELAY,
+        }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// The configured model, or `default` when none is.
+    pub fn model_or<'a>(&'a self, default: &'a str) -> &'a str {
+        if self.model.is_empty() {
+            default
+        } else {
+            &self.model
+        }
+    }
+
+    /// Days of daily notes a run reads.
+    pub fn days(&self) -> u32 {
+        self.days
+    }
+
+    pub fn is_local(&self) -> bool {
+        self.model.starts_with("local-")
+    }
+
+    /// The provider and model to consolidate with: the local model for
+    /// `local-…` names, a provider for the model's configured key, else
+    /// `chat` (the chat model when none is configured).
+    pub fn provider_for(&self, config: &Config, chat: Arc<dyn LlmProvider>) -> anyhow::Result<(Arc<dyn LlmProvider>, String)> {
+        let model = self.model_or(&config.agents.defaults.model).to_string();
+        if self.is_local() {
+            return Ok((local_provider(&model)?, model));
+        }
+        match config.match_provider(Some(&model)) {
+            Some(p) if !self.model.is_empty() => {
+                let provider = provider::create_provider(&p.api_key, config.get_api_base(Some(&model)), &model);
+                Ok((Arc::from(provider), model))
+            }
+            _ => Ok((chat, model)),
+        }
+    }
+
+    pub fn prompt(&self, long_term: &str, daily: &str) -> String {
+        let long_term = if long_term.trim().is_empty() { "（なし）" } else { long_term };
+        self.prompt.replace("{long_term}", long_term).replace("{daily}", daily)
+    }
+
+    /// The merged long-term memory, or `None` when there are no daily notes.
+    /// Failed or empty replies are retried.
+    pub async fn summarize(
+        &self,
+        provider: &dyn LlmProvider,
+        model: &str,
+        long_term: &str,
+        daily: &str,
+    ) -> anyhow::Result<Option<String>> {
+        if daily.trim().is_empty() {
+            return Ok(None);
+        }
+        let messages = vec![Message::user(self.prompt(long_term, daily))];
+        let mut delay = self.retry_delay;
+        let mut attempt = 0;
+        loop {
+            let error = match provider.chat(&messages, None, model, 1024, 0.3).await {
+                Ok(resp) => match resp.content.as_deref().map(str::trim) {
+                    Some(content) if !content.is_empty() => return Ok(Some(content.to_string())),
+                    _ => "empty reply".to_string(),
+                },
+                Err(e) => e.to_string(),
+            };
+            if attempt >= self.retries {
+                anyhow::bail!("memory consolidation with {} failed after {} attempts: {}", model, attempt + 1, error);
+            }
+            attempt += 1;
+            warn!("Memory consolidation attempt {} failed ({}); retrying in {:?}", attempt, error, delay);
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+
+    /// Consolidate `memory` in place. Returns the new long-term memory,
+    /// `None` when there was nothing to consolidate. With `dry_run` nothing
+    /// is written.
+    pub async fn run(
+        &self,
+        provider: &dyn LlmProvider,
+        model: &str,
+        memory: &dyn MemoryBackend,
+        dry_run: bool,
+    ) -> anyhow::Result<Option<String>> {
+        let daily = memory.get_recent_memories(self.days);
+        let merged = self.summarize(provider, model, &memory.read_long_term(), &daily).await?;
+        if let (Some(merged), false) = (&merged, dry_run) {
+            memory.write_long_term(merged);
+        }
+        Ok(merged)
+    }
+}
+
+#[cfg(feature = "local-fallback")]
+fn local_provider(model: &str) -> anyhow::Result<Arc<dyn LlmProvider>> {
+    let name = model.strip_prefix("local-").unwrap_or(model);
+    match provider::local::LocalProvider::for_model(name) {
+        Some(local) => Ok(Arc::new(local)),
+        None => anyhow::bail!("No local model {}: set LOCAL_MODEL_URL (or LOCAL_MODEL_URL_<NAME>)", model),
+    }
+}
+
+#[cfg(not(feature = "local-fallback"))]
+fn local_provider(model: &str) -> anyhow::Result<Arc<dyn LlmProvider>> {
+    anyhow::bail!("{} needs the local-fallback feature", model)
+}
+
+/// Keep one cron job for `agents.memory.schedule`, or none when
+/// consolidation or the schedule is off.
+pub fn sync_cron(config: &MemoryConfig, cron: &mut CronService, tz: Option<&str>) {
+    let wanted = (config.consolidate && !config.schedule.is_empty())
+        .then(|| CronSchedule::Cron { expr: config.schedule.clone(), tz: tz.map(str::to_string) });
+    let mut kept = false;
+    for job in cron.list_jobs(true).into_iter().filter(|j| j.payload.kind == CONSOLIDATE_KIND) {
+        let current = match (&job.schedule, &wanted) {
+            (CronSchedule::Cron { expr, tz }, Some(CronSchedule::Cron { expr: e, tz: t })) => expr == e && tz == t,
+            _ => false,
+        };
+        if current && !kept {
+            kept = true;
+            continue;
+        }
+        cron.remove_job(&job.id);
+    }
+    if let (Some(schedule), false) = (wanted, kept) {
+        let payload = CronPayload {
+            kind: CONSOLIDATE_KIND.to_string(),
+            message: String::new(),
+            deliver: false,
+            channel: None,
+            to: None,
+        };
+        cron.add_job_with_payload("memory consolidation", schedule, payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ProviderError;
+    use crate::memory::MemoryStore;
+    use crate::types::{CompletionResponse, FinishReason, TokenUsage};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails `failures` times, then answers with the prompt's first line.
+    struct Flaky {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl LlmProvider for Flaky {
+        async fn chat(
+            &self,
+            messages: &[Message],
+            _tools: Option<&[serde_json::Value]>,
+            _model: &str,
+            _max_tokens: u32,
+            _temperature: f64,
+        ) -> Result<CompletionResponse, ProviderError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(ProviderError::Api { status: 529, message: "overloaded".into() });
+            }
+            let prompt = messages[0].content.clone().unwrap_or_default();
+            Ok(CompletionResponse {
+                content: Some(format!("- {}", prompt.lines().next().unwrap_or(""))),
+                tool_calls: vec![],
+                finish_reason: FinishReason::Stop,
+                usage: TokenUsage::default(),
+                model: None,
+            })
+        }
+
+        fn default_model(&self) -> &str {
+            "flaky"
+        }
+    }
+
+    fn consolidator(prompt: &str, retries: u32) -> Consolidator {
+        let config = MemoryConfig { prompt: prompt.into(), retries, ..Default::default() };
+        Consolidator::from_config(&config).with_retry_delay(Duration::ZERO)
+    }
+
+    #[test]
+    fn test_prompt() {
+        let c = consolidator("Merge {daily} into {long_term}", 0);
+        assert_eq!(c.prompt("", "- Q: hi"), "Merge - Q: hi into （なし）");
+        assert!(consolidator("", 0).prompt("- likes tea", "- Q: hi").contains("## 既存の長期記憶\n- likes tea"));
+        assert_eq!(c.model_or("claude"), "claude");
+        assert!(c.with_model("local-qwen3-0.6b").is_local());
+    }
+
+    #[tokio::test]
+    async fn test_run_retries_and_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let memory = MemoryStore::new(dir.path());
+        let flaky = Flaky { failures: 2, calls: AtomicU32::new(0) };
+
+        // Nothing to consolidate without daily notes
+        let c = consolidator("Facts: {daily}", 2);
+        assert_eq!(c.run(&flaky, "m", &memory, false).await.unwrap(), None);
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 0);
+
+        memory.append_today("- Q: I moved to Osaka");
+        let merged = c.run(&flaky, "m", &memory, true).await.unwrap().unwrap();
+        assert!(merged.starts_with("- Facts: #"));
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(memory.read_long_term(), "");
+
+        c.run(&flaky, "m", &memory, false).await.unwrap();
+        assert_eq!(memory.read_long_term(), merged);
+
+        let failing = Flaky { failures: 5, calls: AtomicU32::new(0) };
+        let err = consolidator("{daily}", 1).run(&failing, "m", &memory, false).await.unwrap_err();
+        assert!(err.to_string().contains("after 2 attempts"));
+        assert_eq!(failing.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_sync_cron() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cron = CronService::new(dir.path().join("jobs.json"));
+        let jobs = |cron: &CronService| cron.list_jobs(true).into_iter().filter(|j| j.payload.kind == CONSOLIDATE_KIND).count();
+
+        let mut config = MemoryConfig::default();
+        sync_cron(&config, &mut cron, Some("Asia/Tokyo"));
+        sync_cron(&config, &mut cron, Some("Asia/Tokyo"));
+        assert_eq!(jobs(&cron), 1);
+
+        config.consolidate = false;
+        sync_cron(&config, &mut cron, None);
+        assert_eq!(jobs(&cron), 0);
+    }
+}
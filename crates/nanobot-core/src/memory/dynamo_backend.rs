@@ -11,6 +11,8 @@ pub struct DynamoMemoryBackend {
     client: Client,
     table_name: String,
     tenant_id: String,
+    /// Timezone for the daily note rollover (server local time when unset).
+    timezone: Option<String>,
 }
 
 impl DynamoMemoryBackend {
@@ -19,9 +21,16 @@ impl DynamoMemoryBackend {
             client,
             table_name,
             tenant_id,
+            timezone: None,
         }
     }
 
+    /// Roll daily notes over at midnight in `tz` instead of server local time.
+    pub fn with_timezone(mut self, tz: Option<String>) -> Self {
+        self.timezone = tz;
+        self
+    }
+
     fn get_item_sync(&self, sk: &str) -> Option<String> {
         let rt = tokio::runtime::Handle::try_current().ok()?;
         let client = self.client.clone();
@@ -91,7 +100,7 @@ impl DynamoMemoryBackend {
     }
 
     fn today_key(&self) -> String {
-        format!("memory:daily:{}", crate::util::today_date())
+        format!("memory:daily:{}", crate::util::today_date_in(self.timezone.as_deref()))
     }
 }
 
@@ -104,7 +113,7 @@ impl MemoryBackend for DynamoMemoryBackend {
         let key = self.today_key();
         let existing = self.get_item_sync(&key).unwrap_or_default();
         let new_content = if existing.is_empty() {
-            format!("# {}\n\n{}", crate::util::today_date(), content)
+            format!("# {}\n\n{}", crate::util::today_date_in(self.timezone.as_deref()), content)
         } else {
             format!("{}\n{}", existing, content)
         };
@@ -121,7 +130,7 @@ impl MemoryBackend for DynamoMemoryBackend {
     }
 
     fn get_recent_memories(&self, days: u32) -> String {
-        let today = chrono::Local::now().date_naive();
+        let today = crate::util::now_in(self.timezone.as_deref()).date_naive();
         let mut memories = Vec::new();
 
         for i in 0..days {
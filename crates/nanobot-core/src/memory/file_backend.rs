@@ -1,6 +1,6 @@
 use std::path::{Path, PathBuf};
 
-use crate::util::{ensure_dir, today_date};
+use crate::util::{ensure_dir, now_in, today_date_in};
 
 use super::backend::MemoryBackend;
 
@@ -8,6 +8,8 @@ use super::backend::MemoryBackend;
 pub struct FileMemoryBackend {
     memory_dir: PathBuf,
     memory_file: PathBuf,
+    /// Timezone for the daily file rollover (server local time when unset).
+    timezone: Option<String>,
 }
 
 impl FileMemoryBackend {
@@ -18,12 +20,23 @@ impl FileMemoryBackend {
         Self {
             memory_dir,
             memory_file,
+            timezone: None,
         }
     }
 
+    /// Roll daily notes over at midnight in `tz` instead of server local time.
+    pub fn with_timezone(mut self, tz: Option<String>) -> Self {
+        self.timezone = tz;
+        self
+    }
+
+    fn today(&self) -> String {
+        today_date_in(self.timezone.as_deref())
+    }
+
     /// Get path to today's memory file.
     pub fn today_file(&self) -> PathBuf {
-        self.memory_dir.join(format!("{}.md", today_date()))
+        self.memory_dir.join(format!("{}.md", self.today()))
     }
 }
 
@@ -43,7 +56,7 @@ impl MemoryBackend for FileMemoryBackend {
             let existing = std::fs::read_to_string(&path).unwrap_or_default();
             format!("{existing}\n{content}")
         } else {
-            format!("# {}\n\n{}", today_date(), content)
+            format!("# {}\n\n{}", self.today(), content)
         };
         std::fs::write(&path, new_content).ok();
     }
@@ -61,7 +74,7 @@ impl MemoryBackend for FileMemoryBackend {
     }
 
     fn get_recent_memories(&self, days: u32) -> String {
-        let today = chrono::Local::now().date_naive();
+        let today = now_in(self.timezone.as_deref()).date_naive();
         let mut memories = Vec::new();
 
         for i in 0..days {
@@ -124,6 +137,7 @@ impl MemoryBackend for FileMemoryBackend {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::util::today_date;
 
     #[test]
     fn test_file_memory_backend_new() {
@@ -174,6 +188,14 @@ mod tests {
         assert_eq!(filename.len(), 13); // YYYY-MM-DD.md
     }
 
+    #[test]
+    fn test_today_file_follows_timezone() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = FileMemoryBackend::new(tmp.path()).with_timezone(Some("UTC+14".to_string()));
+        let expected = format!("{}.md", today_date_in(Some("UTC+14")));
+        assert_eq!(store.today_file().file_name().unwrap().to_str().unwrap(), expected);
+    }
+
     #[test]
     fn test_get_memory_context() {
         let tmp = tempfile::tempdir().unwrap();
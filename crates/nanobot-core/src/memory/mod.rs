@@ -1,4 +1,5 @@
 pub mod backend;
+pub mod consolidate;
 pub mod file_backend;
 
 #[cfg(feature = "dynamodb-backend")]
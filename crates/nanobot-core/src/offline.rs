@@ -0,0 +1,179 @@
+//! Offline queue for CLI chat messages.
+//!
+//! When `chatweb chat` can't reach the server, the message is kept in a
+//! JSON file under the data directory instead of being lost. Queued
+//! messages are replayed oldest first against the session they were typed
+//! in: on the next `chatweb chat` invocation, before the next message of an
+//! interactive session, or by `chatweb daemon` in the background. Replay
+//! stops at the first failure so the conversation keeps its order. The file
+//! is re-read on every operation because the daemon and the chat may run at
+//! the same time.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::util::{now_ms, read_json, write_json};
+
+/// Messages beyond this count are dropped oldest first.
+pub const MAX_QUEUED: usize = 500;
+
+/// A chat message waiting for the network.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedMessage {
+    pub id: String,
+    pub session_id: String,
+    /// Chat endpoint the message was meant for.
+    pub api_url: String,
+    pub message: String,
+    pub queued_at_ms: u64,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+impl QueuedMessage {
+    /// Local "HH:MM" the message was queued at.
+    pub fn queued_at_local(&self) -> String {
+        chrono::DateTime::<chrono::Utc>::from_timestamp_millis(self.queued_at_ms as i64)
+            .map(|t| t.with_timezone(&chrono::Local).format("%H:%M").to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Whether `e` means the server couldn't be reached at all (as opposed to
+/// an error response), i.e. the message is worth queueing.
+pub fn is_offline_error(e: &anyhow::Error) -> bool {
+    e.chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(|e| e.is_connect())
+}
+
+/// File-backed FIFO of unsent messages.
+pub struct OfflineQueue {
+    path: PathBuf,
+}
+
+impl OfflineQueue {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn default_path() -> PathBuf {
+        crate::config::get_data_dir().join("offline").join("queue.json")
+    }
+
+    fn load(&self) -> Vec<QueuedMessage> {
+        read_json(&self.path, "offline queue")
+    }
+
+    fn save(&self, mut queue: Vec<QueuedMessage>) {
+        if queue.len() > MAX_QUEUED {
+            let excess = queue.len() - MAX_QUEUED;
+            queue.drain(..excess);
+        }
+        write_json(&self.path, &queue, "offline queue");
+    }
+
+    /// Queue a message; returns its entry.
+    pub fn push(&self, session_id: &str, api_url: &str, message: &str) -> QueuedMessage {
+        let entry = QueuedMessage {
+            id: Uuid::new_v4().to_string()[..8].to_string(),
+            session_id: session_id.to_string(),
+            api_url: api_url.to_string(),
+            message: message.to_string(),
+            queued_at_ms: now_ms(),
+            attempts: 0,
+            last_error: None,
+        };
+        let mut queue = self.load();
+        queue.push(entry.clone());
+        self.save(queue);
+        entry
+    }
+
+    /// Queued messages, oldest first; only those for `session_id` if given.
+    pub fn pending(&self, session_id: Option<&str>) -> Vec<QueuedMessage> {
+        self.load()
+            .into_iter()
+            .filter(|m| session_id.is_none_or(|s| m.session_id == s))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.load().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop a message once it was sent.
+    pub fn remove(&self, id: &str) {
+        let mut queue = self.load();
+        let before = queue.len();
+        queue.retain(|m| m.id != id);
+        if queue.len() != before {
+            self.save(queue);
+        }
+    }
+
+    /// Record a failed replay attempt.
+    pub fn mark_failed(&self, id: &str, error: &str) {
+        let mut queue = self.load();
+        if let Some(m) = queue.iter_mut().find(|m| m.id == id) {
+            m.attempts += 1;
+            m.last_error = Some(error.to_string());
+            self.save(queue);
+        }
+    }
+
+    /// Remove every queued message; returns how many there were.
+    pub fn clear(&self) -> usize {
+        let count = self.len();
+        if count > 0 {
+            self.save(Vec::new());
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_is_fifo_per_session() {
+        let tmp = tempfile::tempdir().unwrap();
+        let queue = OfflineQueue::new(tmp.path().join("queue.json"));
+        assert!(queue.is_empty());
+
+        let first = queue.push("cli:a", "https://chatweb.ai/api/v1/chat", "first");
+        queue.push("cli:b", "https://chatweb.ai/api/v1/chat", "other session");
+        let second = queue.push("cli:a", "https://chatweb.ai/api/v1/chat", "second");
+
+        let pending: Vec<_> = queue.pending(Some("cli:a")).into_iter().map(|m| m.message).collect();
+        assert_eq!(pending, ["first", "second"]);
+        assert_eq!(queue.pending(None).len(), 3);
+
+        queue.mark_failed(&first.id, "connection refused");
+        let retried = &queue.pending(Some("cli:a"))[0];
+        assert_eq!((retried.attempts, retried.last_error.as_deref()), (1, Some("connection refused")));
+
+        queue.remove(&first.id);
+        queue.remove(&second.id);
+        assert_eq!(queue.pending(Some("cli:a")), vec![]);
+        assert_eq!(queue.clear(), 1);
+        assert!(queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_only_connection_failures_are_offline() {
+        let refused = reqwest::Client::new().get("http://127.0.0.1:9/").send().await.unwrap_err();
+        assert!(is_offline_error(&anyhow::Error::from(refused).context("chat request")));
+        assert!(!is_offline_error(&anyhow::anyhow!("HTTP 500")));
+    }
+}
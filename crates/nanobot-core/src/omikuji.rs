@@ -0,0 +1,104 @@
+//! Omikuji (おみくじ): a daily fortune that grants a few credits.
+//!
+//! Each user may draw once per UTC day. Fortunes are weighted so that most
+//! draws are small and 大吉 is rare; the credits of a draw never take the
+//! balance past [`CREDIT_CAP`]. `POST /api/v1/omikuji` serves draws from the
+//! libSQL backend on self-hosted instances and from DynamoDB on chatweb.ai.
+
+use serde::Serialize;
+
+/// Balance above which draws stop granting credits.
+pub const CREDIT_CAP: i64 = 100_000;
+
+/// One outcome of a draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Fortune {
+    pub name: &'static str,
+    /// Chance out of 100.
+    pub weight: u32,
+    pub credits: i64,
+    pub message: &'static str,
+}
+
+/// Every fortune, best first. Weights add up to 100.
+pub const FORTUNES: [Fortune; 5] = [
+    Fortune {
+        name: "大吉",
+        weight: 5,
+        credits: 500,
+        message: "素晴らしい運気！今日は何をしても上手くいきそうです。新しいことに挑戦してみましょう！",
+    },
+    Fortune {
+        name: "吉",
+        weight: 15,
+        credits: 200,
+        message: "とても良い運勢です。積極的に行動すれば良い結果が得られるでしょう。",
+    },
+    Fortune {
+        name: "中吉",
+        weight: 30,
+        credits: 100,
+        message: "まずまずの運気です。落ち着いて物事に取り組めば順調に進みます。",
+    },
+    Fortune {
+        name: "小吉",
+        weight: 30,
+        credits: 50,
+        message: "穏やかな一日になりそうです。小さな幸せを見つけてみてください。",
+    },
+    Fortune {
+        name: "末吉",
+        weight: 20,
+        credits: 10,
+        message: "慎重に過ごしましょう。焦らず、一歩ずつ進むことが大切です。",
+    },
+];
+
+/// The fortune for `roll`, a number in `0.0..1.0`.
+pub fn pick(roll: f64) -> &'static Fortune {
+    let mut upper = 0.0;
+    for fortune in &FORTUNES {
+        upper += fortune.weight as f64 / 100.0;
+        if roll < upper {
+            return fortune;
+        }
+    }
+    &FORTUNES[FORTUNES.len() - 1]
+}
+
+/// Draw a fortune at random.
+pub fn draw() -> &'static Fortune {
+    pick(rand::random::<f64>())
+}
+
+/// The day a draw counts against (UTC, `YYYY-MM-DD`).
+pub fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// Credits `fortune` grants to a user holding `balance`.
+pub fn grant(fortune: &Fortune, balance: i64) -> i64 {
+    fortune.credits.min(CREDIT_CAP - balance).max(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_follows_weights() {
+        assert_eq!(FORTUNES.iter().map(|f| f.weight).sum::<u32>(), 100);
+        assert_eq!(pick(0.0).name, "大吉");
+        assert_eq!(pick(0.049).name, "大吉");
+        assert_eq!(pick(0.05).name, "吉");
+        assert_eq!(pick(0.5).name, "小吉");
+        assert_eq!(pick(0.999).name, "末吉");
+    }
+
+    #[test]
+    fn test_grant_respects_cap() {
+        assert_eq!(grant(&FORTUNES[0], 0), 500);
+        assert_eq!(grant(&FORTUNES[0], CREDIT_CAP - 30), 30);
+        assert_eq!(grant(&FORTUNES[4], CREDIT_CAP + 5), 0);
+    }
+}
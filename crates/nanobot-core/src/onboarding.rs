@@ -0,0 +1,268 @@
+//! First-contact onboarding.
+//!
+//! When a new user writes for the first time, the steps in
+//! `<workspace>/onboarding.yaml` are sent before the conversation starts:
+//!
+//! ```yaml
+//! channels: []   # empty = every channel
+//! steps:
+//!   - text:
+//!       ja: "はじめまして！なんでも聞いてください。"
+//!       en: "Hi! Ask me anything."
+//!   - id: language
+//!     text: "どちらの言語で話しますか？ / Which language do you prefer?"
+//!     choices: ["日本語", "English"]
+//!     set: language
+//!   - text:
+//!       ja: "/link でほかのチャネルと会話を同期できます。"
+//!       en: "Use /link to continue this chat on other channels."
+//!   - id: privacy
+//!     text: "会話はサービス改善のために保存されます。/ Conversations are stored to improve the service."
+//! ```
+//!
+//! Steps are sent in order until one with `choices` is reached; the user's
+//! next message answers it (by number or label) and the rest follow. A reply
+//! that isn't one of the choices skips the question and is answered as a
+//! normal message. `set: language` fixes the session language to the chosen
+//! one; other answers are kept in the session under the step id. Progress is
+//! tracked per session, so the sequence runs once per user and channel, and
+//! conversations that already had messages when onboarding was configured
+//! are left alone.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::session::locale::Locale;
+use crate::session::onboarding::OnboardingState;
+use crate::session::Session;
+
+/// Text of a step, either for every language or per language.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StepText {
+    Plain(String),
+    Localized {
+        #[serde(default)]
+        ja: Option<String>,
+        #[serde(default)]
+        en: Option<String>,
+    },
+}
+
+impl StepText {
+    pub fn render(&self, locale: Locale) -> &str {
+        match self {
+            StepText::Plain(text) => text,
+            StepText::Localized { ja, en } => {
+                let (preferred, other) = if locale.is_english() { (en, ja) } else { (ja, en) };
+                preferred.as_deref().or(other.as_deref()).unwrap_or_default()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    /// Key of the answer for questions; defaults to the step's position.
+    #[serde(default)]
+    pub id: String,
+    pub text: StepText,
+    /// Makes the step a question answered with one of these.
+    #[serde(default)]
+    pub choices: Vec<String>,
+    /// `language` applies the answer as the session language.
+    #[serde(default)]
+    pub set: Option<String>,
+}
+
+impl Step {
+    fn is_question(&self) -> bool {
+        !self.choices.is_empty()
+    }
+
+    fn render(&self, locale: Locale) -> String {
+        let text = self.text.render(locale);
+        if !self.is_question() {
+            return text.to_string();
+        }
+        let choices: Vec<String> = self.choices.iter().enumerate().map(|(i, c)| format!("{}. {}", i + 1, c)).collect();
+        format!("{}\n{}", text, choices.join("\n"))
+    }
+
+    /// The choice `reply` picks, by number, label or (for `set: language`) language name.
+    fn choice(&self, reply: &str) -> Option<&str> {
+        let reply = reply.trim();
+        if let Some(choice) = reply.parse::<usize>().ok().and_then(|n| n.checked_sub(1)).and_then(|i| self.choices.get(i)) {
+            return Some(choice);
+        }
+        if let Some(choice) = self.choices.iter().find(|c| c.trim().eq_ignore_ascii_case(reply)) {
+            return Some(choice);
+        }
+        if self.set.as_deref() == Some("language") {
+            let locale = Locale::parse(reply)?;
+            return self.choices.iter().find(|c| Locale::parse(c) == Some(locale)).map(String::as_str);
+        }
+        None
+    }
+}
+
+/// `onboarding.yaml`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnboardingFile {
+    /// Channels that onboard new users; empty means all.
+    #[serde(default)]
+    pub channels: Vec<String>,
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+/// What onboarding did with a message.
+#[derive(Debug, Default)]
+pub struct Onboarded {
+    /// Messages to send, in order.
+    pub messages: Vec<String>,
+    /// Whether the message was an answer (or onboarding waits for one), so
+    /// it shouldn't be answered as a normal message.
+    pub consumed: bool,
+}
+
+/// The onboarding sequence of a workspace.
+pub struct Onboarding {
+    path: PathBuf,
+}
+
+impl Onboarding {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// `<workspace>/onboarding.yaml`.
+    pub fn for_workspace(workspace: &Path) -> Self {
+        Self::new(workspace.join("onboarding.yaml"))
+    }
+
+    /// The onboarding file; a missing file means no onboarding.
+    pub fn load(&self) -> anyhow::Result<Option<OnboardingFile>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(content) => Ok(Some(serde_yaml::from_str(&content)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Advance `session`'s onboarding with the user's message `text`.
+    /// Returns `None` once the session is onboarded (or has nothing to do).
+    pub fn handle(&self, session: &mut Session, channel: &str, text: &str) -> Option<Onboarded> {
+        let file = match self.load() {
+            Ok(file) => file?,
+            Err(e) => {
+                warn!("Invalid {}: {}", self.path.display(), e);
+                return None;
+            }
+        };
+        if !file.channels.is_empty() && !file.channels.iter().any(|c| c == channel) {
+            return None;
+        }
+        let mut out = Onboarded::default();
+        let state = match session.onboarding() {
+            Some(state) if state.done => return None,
+            // Existing conversations aren't onboarded after the fact
+            None if !session.messages.is_empty() => {
+                session.set_onboarding(&OnboardingState { done: true, ..Default::default() });
+                return None;
+            }
+            None => OnboardingState::default(),
+            // The message answers the pending question
+            Some(mut state) => {
+                if let Some(step) = file.steps.get(state.step) {
+                    if let Some(choice) = step.choice(text) {
+                        if step.set.as_deref() == Some("language") {
+                            session.set_locale(Locale::parse(choice));
+                        }
+                        let id = if step.id.is_empty() { (state.step + 1).to_string() } else { step.id.clone() };
+                        state.answers.insert(id, choice.to_string());
+                        out.consumed = true;
+                    }
+                    state.step += 1;
+                }
+                state
+            }
+        };
+        Self::advance(&file, session, state, text, &mut out);
+        Some(out)
+    }
+
+    /// Send steps from `state.step` up to the next question, or to the end.
+    fn advance(file: &OnboardingFile, session: &mut Session, mut state: OnboardingState, text: &str, out: &mut Onboarded) {
+        let locale = session.locale().or_else(|| Locale::detect(text)).unwrap_or_default();
+        while let Some(step) = file.steps.get(state.step) {
+            out.messages.push(step.render(locale));
+            if step.is_question() {
+                out.consumed = true;
+                session.set_onboarding(&state);
+                return;
+            }
+            state.step += 1;
+        }
+        state.done = true;
+        session.set_onboarding(&state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONBOARDING: &str = r#"
+steps:
+  - text: { ja: "ようこそ", en: "Welcome" }
+  - id: language
+    text: "言語 / Language?"
+    choices: ["日本語", "English"]
+    set: language
+  - text: { ja: "/link で同期", en: "Use /link to sync" }
+"#;
+
+    fn onboarding() -> (tempfile::TempDir, Onboarding) {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("onboarding.yaml"), ONBOARDING).unwrap();
+        let onboarding = Onboarding::for_workspace(dir.path());
+        (dir, onboarding)
+    }
+
+    #[test]
+    fn test_runs_once() {
+        let (_dir, onboarding) = onboarding();
+        let mut session = Session::new("line:U1");
+
+        let first = onboarding.handle(&mut session, "line", "こんにちは").unwrap();
+        assert_eq!(first.messages, ["ようこそ", "言語 / Language?\n1. 日本語\n2. English"]);
+        assert!(first.consumed);
+
+        let answer = onboarding.handle(&mut session, "line", "english").unwrap();
+        assert_eq!(answer.messages, ["Use /link to sync"]);
+        assert!(answer.consumed);
+        assert_eq!(session.locale(), Some(Locale::En));
+        assert_eq!(session.onboarding().unwrap().answers["language"], "English");
+
+        assert!(onboarding.handle(&mut session, "line", "hello").is_none());
+    }
+
+    #[test]
+    fn test_skip_and_existing_sessions() {
+        let (_dir, onboarding) = onboarding();
+        let mut session = Session::new("telegram:1");
+        onboarding.handle(&mut session, "telegram", "hi there");
+        let skipped = onboarding.handle(&mut session, "telegram", "What's the weather?").unwrap();
+        assert!(!skipped.consumed, "an unrelated reply is answered normally");
+        assert_eq!(skipped.messages, ["Use /link to sync"]);
+        assert!(session.onboarding().unwrap().done);
+
+        let mut existing = Session::new("telegram:2");
+        existing.add_message("user", "hi");
+        assert!(onboarding.handle(&mut existing, "telegram", "hello").is_none());
+        assert!(existing.onboarding().unwrap().done);
+    }
+}
@@ -321,6 +321,7 @@ impl LlmProvider for AnthropicProvider {
         let mut tool_calls: Vec<ToolCall> = Vec::new();
         let mut current_tool: Option<(String, String, String)> = None; // (id, name, input_json)
         let mut usage = TokenUsage::default();
+        let mut served_model: Option<String> = None;
 
         let mut stream = response.bytes_stream();
         let mut buf = String::new();
@@ -342,6 +343,7 @@ impl LlmProvider for AnthropicProvider {
 
                     match event_type {
                         "message_start" => {
+                            served_model = parsed.pointer("/message/model").and_then(|v| v.as_str()).map(|s| s.to_string());
                             if let Some(u) = parsed.get("message").and_then(|m| m.get("usage")) {
                                 usage.prompt_tokens = u.get("input_tokens").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
                             }
@@ -411,6 +413,7 @@ impl LlmProvider for AnthropicProvider {
             tool_calls,
             finish_reason,
             usage,
+            model: served_model,
         })
     }
 
@@ -490,6 +493,7 @@ impl AnthropicProvider {
             tool_calls,
             finish_reason,
             usage,
+            model: data.get("model").and_then(|v| v.as_str()).map(|s| s.to_string()),
         })
     }
 }
@@ -0,0 +1,193 @@
+//! Consensus mode: one answer from several.
+//!
+//! [`LoadBalancedProvider::chat_explore`](super::LoadBalancedProvider::chat_explore)
+//! collects an answer from every parallel model. A judge model reads them
+//! all and writes a single merged answer, noting where the models disagreed
+//! and which view it went with.
+
+use serde::{Deserialize, Serialize};
+
+use super::{ExploreResult, LlmProvider};
+use crate::session::locale::Locale;
+use crate::types::{Message, TokenUsage};
+use crate::util::truncate_chars;
+
+const JUDGE_PROMPT: &str = "You are the judge in a panel of AI assistants. You get a question \
+and the answers of several models. Write the best single answer: keep what the answers agree \
+on, resolve conflicts by reasoning about which answer is right, and drop anything that looks \
+made up. Answer in the language of the question.\n\n\
+Reply with JSON only: {\"answer\": \"<merged answer>\", \"disagreements\": [\"<one line per \
+point the models disagreed on, saying which view you chose and why>\"]}";
+
+/// Answers are cut to this many characters before judging.
+const MAX_ANSWER_CHARS: usize = 4_000;
+
+/// The merged answer.
+#[derive(Debug, Clone, Serialize)]
+pub struct Consensus {
+    pub answer: String,
+    pub disagreements: Vec<String>,
+    /// Models whose answers were merged.
+    pub models: Vec<String>,
+    pub judge_model: String,
+    pub judge_usage: TokenUsageSummary,
+}
+
+/// Serializable token counts of the judge call.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TokenUsageSummary {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+impl From<&TokenUsage> for TokenUsageSummary {
+    fn from(usage: &TokenUsage) -> Self {
+        Self { input_tokens: usage.prompt_tokens, output_tokens: usage.completion_tokens }
+    }
+}
+
+impl Consensus {
+    /// The answer with disagreement notes appended.
+    pub fn render(&self, locale: Locale) -> String {
+        if self.disagreements.is_empty() {
+            return self.answer.clone();
+        }
+        let heading = locale.pick("⚖️ モデル間の見解の相違", "⚖️ Where the models disagreed");
+        let notes: Vec<String> = self.disagreements.iter().map(|d| format!("- {}", d)).collect();
+        format!("{}\n\n---\n{}\n{}", self.answer.trim_end(), heading, notes.join("\n"))
+    }
+}
+
+#[derive(Deserialize)]
+struct Verdict {
+    answer: String,
+    #[serde(default)]
+    disagreements: Vec<String>,
+}
+
+/// The judge's input: the question and every non-empty answer.
+pub fn judge_messages(question: &str, results: &[ExploreResult]) -> Vec<Message> {
+    let answers: Vec<String> = results
+        .iter()
+        .filter(|r| !r.response.trim().is_empty())
+        .enumerate()
+        .map(|(i, r)| {
+            let (answer, cut) = truncate_chars(r.response.trim(), MAX_ANSWER_CHARS);
+            format!("### Answer {} ({})\n{}{}", i + 1, r.model, answer, if cut { "…" } else { "" })
+        })
+        .collect();
+    vec![
+        Message::system(JUDGE_PROMPT),
+        Message::user(format!("## Question\n{}\n\n## Answers\n{}", question, answers.join("\n\n"))),
+    ]
+}
+
+/// Parse the judge's reply; anything that isn't the requested JSON is
+/// taken as the answer itself.
+pub fn parse_verdict(reply: &str) -> (String, Vec<String>) {
+    let trimmed = reply.trim();
+    let json = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|s| s.strip_suffix("```"))
+        .unwrap_or(trimmed)
+        .trim();
+    let json = match (json.find('{'), json.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &json[start..=end],
+        _ => json,
+    };
+    match serde_json::from_str::<Verdict>(json) {
+        Ok(v) if !v.answer.trim().is_empty() => (
+            v.answer,
+            v.disagreements.into_iter().filter(|d| !d.trim().is_empty()).collect(),
+        ),
+        _ => (trimmed.to_string(), vec![]),
+    }
+}
+
+/// Merge `results` into one answer with `judge`. With a single answer there
+/// is nothing to judge and it is returned as is.
+pub async fn synthesize(
+    judge: &dyn LlmProvider,
+    judge_model: &str,
+    question: &str,
+    results: &[ExploreResult],
+) -> anyhow::Result<Consensus> {
+    let usable: Vec<&ExploreResult> = results.iter().filter(|r| !r.response.trim().is_empty()).collect();
+    let models = usable.iter().map(|r| r.model.clone()).collect();
+    match usable.as_slice() {
+        [] => anyhow::bail!("no model produced an answer"),
+        [only] => {
+            return Ok(Consensus {
+                answer: only.response.clone(),
+                disagreements: vec![],
+                models,
+                judge_model: String::new(),
+                judge_usage: TokenUsageSummary::default(),
+            })
+        }
+        _ => {}
+    }
+
+    let messages = judge_messages(question, results);
+    let resp = judge
+        .chat(&messages, None, judge_model, 4096, 0.2)
+        .await
+        .map_err(|e| anyhow::anyhow!("judge {} failed: {}", judge_model, e))?;
+    let (answer, disagreements) = parse_verdict(resp.content.as_deref().unwrap_or_default());
+    Ok(Consensus {
+        answer,
+        disagreements,
+        models,
+        judge_model: judge_model.to_string(),
+        judge_usage: (&resp.usage).into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(model: &str, response: &str) -> ExploreResult {
+        ExploreResult {
+            model: model.into(),
+            response: response.into(),
+            response_time_ms: 100,
+            input_tokens: 10,
+            output_tokens: 20,
+            is_fallback: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_verdict() {
+        let (answer, notes) = parse_verdict(
+            "```json\n{\"answer\": \"Canberra\", \"disagreements\": [\"One model said Sydney; Canberra is correct.\", \"\"]}\n```",
+        );
+        assert_eq!(answer, "Canberra");
+        assert_eq!(notes, vec!["One model said Sydney; Canberra is correct."]);
+
+        assert_eq!(parse_verdict("Just Canberra."), ("Just Canberra.".to_string(), vec![]));
+    }
+
+    #[test]
+    fn test_judge_messages_skip_empty_answers() {
+        let messages = judge_messages("capital?", &[result("a", "Canberra"), result("b", "  "), result("c", "Sydney")]);
+        let prompt = messages[1].content.as_deref().unwrap();
+        assert!(prompt.contains("### Answer 1 (a)\nCanberra"));
+        assert!(prompt.contains("### Answer 2 (c)\nSydney"));
+        assert!(!prompt.contains("(b)"));
+    }
+
+    #[test]
+    fn test_render() {
+        let consensus = Consensus {
+            answer: "Canberra".into(),
+            disagreements: vec!["Sydney vs Canberra".into()],
+            models: vec!["a".into(), "b".into()],
+            judge_model: "judge".into(),
+            judge_usage: TokenUsageSummary::default(),
+        };
+        assert_eq!(consensus.render(Locale::En), "Canberra\n\n---\n⚖️ Where the models disagreed\n- Sydney vs Canberra");
+    }
+}
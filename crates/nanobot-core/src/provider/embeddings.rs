@@ -1,6 +1,8 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::util::http::{self, ClientClass};
+
 /// OpenAI text-embedding-3-small API client
 #[derive(Clone)]
 pub struct EmbeddingsProvider {
@@ -27,7 +29,7 @@ struct EmbeddingData {
 impl EmbeddingsProvider {
     pub fn new(api_key: String) -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client: http::client_for(ClientClass::Llm),
             api_key,
         }
     }
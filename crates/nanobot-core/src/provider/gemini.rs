@@ -264,6 +264,7 @@ impl GeminiProvider {
             tool_calls,
             finish_reason,
             usage,
+            model: data.get("modelVersion").and_then(|v| v.as_str()).map(|s| s.to_string()),
         })
     }
 }
@@ -1,10 +1,14 @@
 //! Local LLM fallback provider using candle for CPU inference.
 //!
 //! Runs Qwen3-0.6B-Instruct (Q4_K_M GGUF) directly on Lambda ARM64.
-//! Used as a last-resort fallback when all remote providers fail.
+//! Used as a last-resort fallback when all remote providers fail, and by
+//! `chatweb earn` workers. Further models are configured per name with
+//! `LOCAL_MODEL_URL_<NAME>`; loaded models live in a [`ModelPool`] (see
+//! [`super::pool`]) so only the first request pays the load time.
 
 use std::path::PathBuf;
-use std::sync::{Mutex, OnceLock};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use candle_core::{quantized::gguf_file, Device, Tensor};
@@ -15,19 +19,42 @@ use tokenizers::Tokenizer;
 use crate::error::ProviderError;
 use crate::types::{CompletionResponse, FinishReason, Message, Role, TokenUsage};
 
+use super::pool::{ModelPool, PoolPolicy, PoolStatus};
 use super::LlmProvider;
 
-/// Loaded model + tokenizer singleton (Lambda = 1 request/instance).
+/// Name of the model configured by `LOCAL_MODEL_URL`.
+pub const DEFAULT_MODEL: &str = "local-qwen3-0.6b";
+
+/// Loaded model + tokenizer.
 struct LoadedModel {
     model: ModelWeights,
     tokenizer: Tokenizer,
     device: Device,
 }
 
-static MODEL: OnceLock<Mutex<LoadedModel>> = OnceLock::new();
+static POOL: OnceLock<Mutex<ModelPool<Mutex<LoadedModel>>>> = OnceLock::new();
+
+/// Serializes loads so concurrent first requests don't load a model twice.
+static LOADING: OnceLock<tokio::sync::Mutex<()>> = OnceLock::new();
+
+static KEEP_ALIVE: OnceLock<()> = OnceLock::new();
+
+fn pool() -> MutexGuard<'static, ModelPool<Mutex<LoadedModel>>> {
+    POOL.get_or_init(|| Mutex::new(ModelPool::new(PoolPolicy::from_env())))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// `qwen3-1.7b` → `QWEN3_1_7B`.
+fn env_suffix(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect()
+}
 
 /// Local LLM provider for fallback inference.
 pub struct LocalProvider {
+    name: String,
     model_url: String,
     tokenizer_url: String,
 }
@@ -43,14 +70,70 @@ impl LocalProvider {
             return None;
         }
         Some(Self {
+            name: DEFAULT_MODEL.to_string(),
             model_url,
             tokenizer_url,
         })
     }
 
-    /// Check if the local model is loaded in memory.
+    /// A model by name, from `LOCAL_MODEL_URL_<NAME>` (and optionally
+    /// `LOCAL_TOKENIZER_URL_<NAME>`), e.g. `LOCAL_MODEL_URL_QWEN3_1_7B` for
+    /// `qwen3-1.7b`. `qwen3-0.6b` falls back to `LOCAL_MODEL_URL`.
+    pub fn for_model(name: &str) -> Option<Self> {
+        let suffix = env_suffix(name);
+        match std::env::var(format!("LOCAL_MODEL_URL_{}", suffix)) {
+            Ok(model_url) if !model_url.is_empty() => Some(Self {
+                name: name.to_string(),
+                model_url,
+                tokenizer_url: std::env::var(format!("LOCAL_TOKENIZER_URL_{}", suffix)).unwrap_or_default(),
+            }),
+            _ if name == DEFAULT_MODEL || DEFAULT_MODEL.strip_prefix("local-") == Some(name) => Self::from_env(),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Check if any local model is loaded in memory.
     pub fn is_loaded() -> bool {
-        MODEL.get().is_some()
+        pool().loaded_count() > 0
+    }
+
+    /// Loaded models, limits and load-time metrics.
+    pub fn pool_status() -> PoolStatus {
+        pool().status()
+    }
+
+    /// Load the model now so the first request doesn't wait for it.
+    /// Returns the load time (zero if it was already loaded).
+    pub async fn warm_up(&self) -> Result<Duration, ProviderError> {
+        let started = Instant::now();
+        if pool().is_loaded(&self.name) {
+            return Ok(Duration::ZERO);
+        }
+        self.ensure_loaded().await?;
+        Ok(started.elapsed())
+    }
+
+    /// Unload idle models in the background when `LOCAL_KEEP_ALIVE_SECS` is
+    /// set. Safe to call more than once.
+    pub fn spawn_keep_alive() {
+        let Some(keep_alive) = pool().policy().keep_alive else {
+            return;
+        };
+        if KEEP_ALIVE.set(()).is_err() {
+            return;
+        }
+        let every = (keep_alive / 2).clamp(Duration::from_secs(1), Duration::from_secs(30));
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(every);
+            loop {
+                tick.tick().await;
+                pool().evict_idle(Instant::now());
+            }
+        });
     }
 
     /// Check if a local model is configured (env vars set).
@@ -60,10 +143,20 @@ impl LocalProvider {
             .unwrap_or(false)
     }
 
-    /// Estimate memory usage of the loaded model in MB.
-    /// Qwen3-0.6B Q4_K_M is approximately 350-400 MB.
+    /// Estimate memory usage of the loaded models in MB (their GGUF file
+    /// sizes; Qwen3-0.6B Q4_K_M is approximately 350-400 MB).
     pub fn estimated_memory_mb() -> u64 {
-        if Self::is_loaded() { 350 } else { 0 }
+        pool().loaded_mb()
+    }
+
+    /// Cache file names; the default model keeps the original ones.
+    fn cache_names(&self) -> (String, String) {
+        if self.name == DEFAULT_MODEL {
+            ("local-model.gguf".to_string(), "tokenizer.json".to_string())
+        } else {
+            let suffix = env_suffix(&self.name).to_lowercase();
+            (format!("local-model-{}.gguf", suffix), format!("tokenizer-{}.json", suffix))
+        }
     }
 
     /// Download a file from URL to /tmp if not already cached.
@@ -95,13 +188,21 @@ impl LocalProvider {
         Ok(path)
     }
 
-    /// Ensure the model is loaded into memory (lazy singleton).
-    async fn ensure_loaded(&self) -> Result<(), ProviderError> {
-        if MODEL.get().is_some() {
-            return Ok(());
+    /// The model from the pool, loading it first if needed. The recorded
+    /// load time includes the download on first use.
+    async fn ensure_loaded(&self) -> Result<Arc<Mutex<LoadedModel>>, ProviderError> {
+        if let Some(model) = pool().get(&self.name) {
+            return Ok(model);
+        }
+        let _loading = LOADING.get_or_init(|| tokio::sync::Mutex::new(())).lock().await;
+        // Loaded by another request while this one waited
+        if let Some(model) = pool().get(&self.name) {
+            return Ok(model);
         }
+        let started = Instant::now();
 
-        let model_path = Self::download_to_tmp(&self.model_url, "local-model.gguf").await?;
+        let (model_file, tokenizer_file) = self.cache_names();
+        let model_path = Self::download_to_tmp(&self.model_url, &model_file).await?;
 
         let tokenizer = if self.tokenizer_url.is_empty() {
             // If no tokenizer URL, try loading from HuggingFace Hub
@@ -118,12 +219,16 @@ impl LocalProvider {
             })?
         } else {
             let tokenizer_path =
-                Self::download_to_tmp(&self.tokenizer_url, "tokenizer.json").await?;
+                Self::download_to_tmp(&self.tokenizer_url, &tokenizer_file).await?;
             Tokenizer::from_file(tokenizer_path).map_err(|e| {
                 ProviderError::Other(format!("Failed to load tokenizer: {}", e))
             })?
         };
 
+        // Unload before loading so peak memory stays within the pool limit
+        pool().make_room();
+        let size_mb = std::fs::metadata(&model_path).map(|m| m.len() / (1024 * 1024)).unwrap_or(0);
+
         // Load GGUF model on CPU (blocking operation)
         let model_path_clone = model_path.clone();
         let loaded = tokio::task::spawn_blocking(move || {
@@ -142,14 +247,12 @@ impl LocalProvider {
         .await
         .map_err(|e| ProviderError::Other(format!("spawn_blocking join error: {}", e)))??;
 
-        let _ = MODEL.set(Mutex::new(LoadedModel {
+        let model = Mutex::new(LoadedModel {
             model: loaded.0,
             tokenizer,
             device: loaded.1,
-        }));
-
-        tracing::info!("Local model loaded successfully");
-        Ok(())
+        });
+        Ok(pool().insert(&self.name, model, size_mb, started.elapsed()))
     }
 
     /// Format messages into ChatML format for Qwen3.
@@ -278,7 +381,7 @@ impl LlmProvider for LocalProvider {
         temperature: f64,
     ) -> Result<CompletionResponse, ProviderError> {
         // Ensure model is loaded
-        self.ensure_loaded().await?;
+        let loaded_model = self.ensure_loaded().await?;
 
         let prompt = Self::format_chatml(messages);
         let max_tokens = max_tokens.min(512); // Cap for speed on CPU
@@ -287,10 +390,7 @@ impl LlmProvider for LocalProvider {
         // Run inference in a blocking task
         let (text, prompt_tokens, completion_tokens) =
             tokio::task::spawn_blocking(move || {
-                let guard = MODEL
-                    .get()
-                    .ok_or_else(|| ProviderError::Other("Model not loaded".to_string()))?;
-                let mut loaded = guard.lock().map_err(|e| {
+                let mut loaded = loaded_model.lock().map_err(|e| {
                     ProviderError::Other(format!("Model lock poisoned: {}", e))
                 })?;
                 let LoadedModel { ref mut model, ref tokenizer, ref device } = *loaded;
@@ -307,9 +407,11 @@ impl LlmProvider for LocalProvider {
             .map_err(|e| ProviderError::Other(format!("Inference task error: {}", e)))??;
 
         // Add fallback disclaimer
+        let label = if self.name == DEFAULT_MODEL { "Qwen3-0.6B" } else { self.name.as_str() };
         let response_text = format!(
-            "{}\n\n---\n⚡ ローカルフォールバックモデル (Qwen3-0.6B) による応答です",
-            text.trim()
+            "{}\n\n---\n⚡ ローカルフォールバックモデル ({}) による応答です",
+            text.trim(),
+            label
         );
 
         Ok(CompletionResponse {
@@ -321,10 +423,11 @@ impl LlmProvider for LocalProvider {
                 completion_tokens,
                 total_tokens: prompt_tokens + completion_tokens,
             },
+            model: Some(self.name.clone()),
         })
     }
 
     fn default_model(&self) -> &str {
-        "local-qwen3-0.6b"
+        &self.name
     }
 }
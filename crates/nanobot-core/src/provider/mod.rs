@@ -3,6 +3,8 @@ pub mod anthropic;
 pub mod gemini;
 pub mod pricing;
 pub mod embeddings;
+pub mod pool;
+pub mod consensus;
 #[cfg(feature = "local-fallback")]
 pub mod local;
 
@@ -112,6 +114,9 @@ pub fn create_provider(
 const CIRCUIT_BREAKER_THRESHOLD: u32 = 3;
 const CIRCUIT_BREAKER_COOLDOWN_SECS: u64 = 300;
 
+/// How long a single provider may take to finish a streamed response.
+const STREAM_TIMEOUT_SECS: u64 = 600;
+
 /// Load-balanced provider that distributes requests across multiple providers
 /// with automatic failover and per-provider circuit breakers.
 pub struct LoadBalancedProvider {
@@ -204,6 +209,16 @@ impl LoadBalancedProvider {
         }
     }
 
+    /// Record a failed parallel fallback, returning the error. Only server errors
+    /// count toward the circuit breaker here (not 4xx client errors or timeouts).
+    fn record_parallel_failure(&self, idx: usize, err: ProviderError) -> ProviderError {
+        let is_server_error = !matches!(err.status(), Some(s) if s < 500) && !matches!(err, ProviderError::Timeout(_));
+        if is_server_error {
+            self.record_failure(idx);
+        }
+        err
+    }
+
     /// Record a success for the provider at `idx` — resets failure count.
     pub fn record_success(&self, idx: usize) {
         if idx >= self.providers.len() { return; }
@@ -897,6 +912,18 @@ pub struct RaceResult {
     pub is_fallback: bool,
 }
 
+/// Failovers can hand a claude request to gemini; say so instead of
+/// switching silently.
+fn warn_if_switched(requested: &str, resp: &CompletionResponse) {
+    if resp.switched_family(requested) {
+        tracing::warn!(
+            "Model fallback: {} was requested but {} answered",
+            requested,
+            resp.model_or(requested)
+        );
+    }
+}
+
 #[async_trait]
 impl LlmProvider for LoadBalancedProvider {
     async fn chat(
@@ -926,19 +953,24 @@ impl LlmProvider for LoadBalancedProvider {
             primary.chat(messages, tools, model, max_tokens, temperature),
         ).await;
 
-        match primary_result {
-            Ok(Ok(resp)) => {
+        // The latest failure, reported if every provider fails
+        let mut last_err = match primary_result {
+            Ok(Ok(mut resp)) => {
                 self.record_success(primary_idx);
+                resp.model.get_or_insert_with(|| model.to_string());
                 return Ok(resp);
             }
             Ok(Err(e)) => {
                 self.record_failure_if_server_error(primary_idx, &e);
                 tracing::warn!("Primary provider failed for model {}: {}, trying parallel fallback", model, e);
+                e
             }
             Err(_) => {
                 tracing::warn!("Primary provider slow for model {} (>{}s), racing all fallbacks", model, primary_head_start.as_secs());
+                ProviderError::Timeout(primary_head_start.as_secs())
             }
-        }
+        };
+        let mut attempts = 1;
 
         // Phase 2: Race ALL remaining available providers in parallel, return first success
         if total > 1 {
@@ -947,7 +979,7 @@ impl LlmProvider for LoadBalancedProvider {
             let tools_owned: Option<Vec<serde_json::Value>> = tools.map(|t| t.to_vec());
 
             let (tx, mut rx) = tokio::sync::mpsc::channel::<(CompletionResponse, usize)>(total);
-            let (fail_tx, mut fail_rx) = tokio::sync::mpsc::channel::<usize>(total);
+            let (fail_tx, mut fail_rx) = tokio::sync::mpsc::channel::<(usize, ProviderError)>(total);
 
             let mut spawned = 0;
             for i in 1..total {
@@ -966,20 +998,18 @@ impl LlmProvider for LoadBalancedProvider {
                         parallel_timeout,
                         provider.chat(&msgs, tools_ref, &converted_model, max_tokens, temperature),
                     ).await {
-                        Ok(Ok(resp)) => {
+                        Ok(Ok(mut resp)) => {
                             tracing::info!("Parallel fallback succeeded with model {}", converted_model);
+                            resp.model.get_or_insert(converted_model);
                             let _ = tx.send((resp, idx)).await;
                         }
                         Ok(Err(e)) => {
                             tracing::warn!("Parallel fallback {} failed: {}", converted_model, e);
-                            // Only count server errors for circuit breaker (not 4xx client errors)
-                            let is_server_error = !matches!(&e, crate::error::ProviderError::Api { status, .. } if *status < 500);
-                            if is_server_error {
-                                let _ = fail_tx.send(idx).await;
-                            }
+                            let _ = fail_tx.send((idx, e)).await;
                         }
                         Err(_) => {
                             tracing::warn!("Parallel fallback {} timed out ({}s)", converted_model, parallel_timeout.as_secs());
+                            let _ = fail_tx.send((idx, ProviderError::Timeout(parallel_timeout.as_secs()))).await;
                         }
                     }
                 });
@@ -989,21 +1019,23 @@ impl LlmProvider for LoadBalancedProvider {
             drop(fail_tx);
 
             if spawned > 0 {
+                attempts += spawned;
                 // Collect failures and first success
-                while let Ok(idx) = fail_rx.try_recv() {
-                    self.record_failure(idx);
+                while let Ok((idx, e)) = fail_rx.try_recv() {
+                    last_err = self.record_parallel_failure(idx, e);
                 }
                 if let Some((resp, success_idx)) = rx.recv().await {
                     self.record_success(success_idx);
                     // Drain remaining failures
-                    while let Ok(idx) = fail_rx.try_recv() {
-                        self.record_failure(idx);
+                    while let Ok((idx, e)) = fail_rx.try_recv() {
+                        self.record_parallel_failure(idx, e);
                     }
+                    warn_if_switched(model, &resp);
                     return Ok(resp);
                 }
                 // All failed — drain remaining failures
-                while let Ok(idx) = fail_rx.try_recv() {
-                    self.record_failure(idx);
+                while let Ok((idx, e)) = fail_rx.try_recv() {
+                    last_err = self.record_parallel_failure(idx, e);
                 }
             }
         }
@@ -1016,6 +1048,7 @@ impl LlmProvider for LoadBalancedProvider {
                 match local_provider.chat(messages, tools, "local-qwen3-0.6b", max_tokens.min(512), temperature).await {
                     Ok(resp) => {
                         tracing::info!("Local fallback succeeded");
+                        warn_if_switched(model, &resp);
                         return Ok(resp);
                     }
                     Err(e) => tracing::error!("Local fallback also failed: {}", e),
@@ -1023,7 +1056,7 @@ impl LlmProvider for LoadBalancedProvider {
             }
         }
 
-        Err(ProviderError::Other("All providers failed".to_string()))
+        Err(ProviderError::all_failed(attempts, Some(last_err)))
     }
 
     async fn chat_stream(
@@ -1044,7 +1077,8 @@ impl LlmProvider for LoadBalancedProvider {
         // Sequential failover for streaming (can't race — each provider writes to the same chunk_tx)
         // Start from the best matching provider for the requested model
         let start = self.select_provider_idx(model);
-        let mut last_err = String::new();
+        let mut last_err = None;
+        let mut attempts = 0;
 
         for i in 0..total {
             let idx = (start + i) % total;
@@ -1054,31 +1088,34 @@ impl LlmProvider for LoadBalancedProvider {
             }
             let provider = &*self.providers[idx];
             let converted_model = Self::convert_model_for_provider(provider, model);
+            attempts += 1;
 
             match tokio::time::timeout(
-                std::time::Duration::from_secs(600),
+                std::time::Duration::from_secs(STREAM_TIMEOUT_SECS),
                 provider.chat_stream(messages, tools, &converted_model, max_tokens, temperature, extra, chunk_tx.clone()),
             ).await {
-                Ok(Ok(resp)) => {
+                Ok(Ok(mut resp)) => {
                     self.record_success(idx);
                     if i > 0 {
                         tracing::info!("Stream failover succeeded with provider #{} model {}", idx, converted_model);
                     }
+                    resp.model.get_or_insert(converted_model);
+                    warn_if_switched(model, &resp);
                     return Ok(resp);
                 }
                 Ok(Err(e)) => {
                     self.record_failure_if_server_error(idx, &e);
                     tracing::warn!("Stream provider #{} ({}) failed: {}, trying next", idx, converted_model, e);
-                    last_err = format!("{}", e);
+                    last_err = Some(e);
                 }
                 Err(_) => {
-                    tracing::warn!("Stream provider #{} ({}) timed out (10s), trying next", idx, converted_model);
-                    last_err = "timeout".to_string();
+                    tracing::warn!("Stream provider #{} ({}) timed out ({}s), trying next", idx, converted_model, STREAM_TIMEOUT_SECS);
+                    last_err = Some(ProviderError::Timeout(STREAM_TIMEOUT_SECS));
                 }
             }
         }
 
-        Err(ProviderError::Other(format!("All {} stream providers failed: {}", total, last_err)))
+        Err(ProviderError::all_failed(attempts, last_err))
     }
 
     fn default_model(&self) -> &str {
@@ -535,6 +535,7 @@ impl LlmProvider for OpenAiCompatProvider {
         let mut finish_reason = FinishReason::Stop;
         let mut tool_calls_map: std::collections::BTreeMap<usize, (String, String, String)> = std::collections::BTreeMap::new(); // index -> (id, name, args)
         let mut usage = TokenUsage::default();
+        let mut served_model: Option<String> = None;
 
         // enable_thinking: false is sent to RunPod, so no </think> tag appears.
         // Always forward content directly (think_done = true).
@@ -558,6 +559,9 @@ impl LlmProvider for OpenAiCompatProvider {
                 if data == "[DONE]" { continue; }
 
                 if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(data) {
+                    if served_model.is_none() {
+                        served_model = parsed.get("model").and_then(|v| v.as_str()).map(|s| s.to_string());
+                    }
                     // Usage info (from stream_options.include_usage)
                     if let Some(u) = parsed.get("usage") {
                         usage.prompt_tokens = u.get("prompt_tokens").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
@@ -639,6 +643,7 @@ impl LlmProvider for OpenAiCompatProvider {
             tool_calls,
             finish_reason,
             usage,
+            model: served_model,
         })
     }
 
@@ -718,5 +723,6 @@ pub fn parse_openai_response(data: &serde_json::Value) -> Result<CompletionRespo
         tool_calls,
         finish_reason,
         usage,
+        model: data.get("model").and_then(|v| v.as_str()).map(|s| s.to_string()),
     })
 }
@@ -0,0 +1,248 @@
+//! Loaded-model pool for the local provider.
+//!
+//! Loading a GGUF model takes seconds, so loaded models are kept in a pool
+//! shared by every request: at most `max_loaded` at a time (the least
+//! recently used one is dropped to make room), and, with a keep-alive set,
+//! unloaded after sitting idle that long. Load times and evictions are
+//! recorded for `/api/v1/local/status`. The pool is generic over the model
+//! type so the policy doesn't depend on candle.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Pool limits, from `LOCAL_MAX_LOADED_MODELS` and `LOCAL_KEEP_ALIVE_SECS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolPolicy {
+    pub max_loaded: usize,
+    /// Unload models idle this long; `None` keeps them until evicted.
+    pub keep_alive: Option<Duration>,
+}
+
+impl Default for PoolPolicy {
+    fn default() -> Self {
+        Self {
+            max_loaded: 1,
+            keep_alive: None,
+        }
+    }
+}
+
+impl PoolPolicy {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        Self {
+            max_loaded: var("LOCAL_MAX_LOADED_MODELS").map_or(1, |n| n.max(1) as usize),
+            keep_alive: var("LOCAL_KEEP_ALIVE_SECS").filter(|&s| s > 0).map(Duration::from_secs),
+        }
+    }
+}
+
+/// Load metrics for one model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LoadStats {
+    pub loads: u32,
+    pub last_load_ms: u64,
+    pub max_load_ms: u64,
+    pub total_load_ms: u64,
+    pub evictions: u32,
+    pub requests: u64,
+}
+
+/// A model in the pool, as reported in the status.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoadedInfo {
+    pub name: String,
+    pub size_mb: u64,
+    pub idle_secs: u64,
+}
+
+/// Snapshot of the pool for the status endpoint.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PoolStatus {
+    pub max_loaded: usize,
+    pub keep_alive_secs: Option<u64>,
+    pub loaded: Vec<LoadedInfo>,
+    pub stats: HashMap<String, LoadStats>,
+}
+
+struct Entry<T> {
+    model: Arc<T>,
+    size_mb: u64,
+    last_used: Instant,
+}
+
+pub struct ModelPool<T> {
+    policy: PoolPolicy,
+    entries: HashMap<String, Entry<T>>,
+    stats: HashMap<String, LoadStats>,
+}
+
+impl<T> ModelPool<T> {
+    pub fn new(policy: PoolPolicy) -> Self {
+        Self {
+            policy,
+            entries: HashMap::new(),
+            stats: HashMap::new(),
+        }
+    }
+
+    pub fn policy(&self) -> PoolPolicy {
+        self.policy
+    }
+
+    /// The loaded model `name`, marked as just used.
+    pub fn get(&mut self, name: &str) -> Option<Arc<T>> {
+        let entry = self.entries.get_mut(name)?;
+        entry.last_used = Instant::now();
+        self.stats.entry(name.to_string()).or_default().requests += 1;
+        Some(entry.model.clone())
+    }
+
+    pub fn is_loaded(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    pub fn loaded_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Memory held by loaded models (their file sizes).
+    pub fn loaded_mb(&self) -> u64 {
+        self.entries.values().map(|e| e.size_mb).sum()
+    }
+
+    fn evict(&mut self, name: &str) {
+        if self.entries.remove(name).is_some() {
+            tracing::info!("Unloaded local model {}", name);
+            self.stats.entry(name.to_string()).or_default().evictions += 1;
+        }
+    }
+
+    /// Make room for one more model; returns the models unloaded.
+    pub fn make_room(&mut self) -> Vec<String> {
+        let mut evicted = Vec::new();
+        while self.entries.len() >= self.policy.max_loaded {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(name, _)| name.clone())
+            else {
+                break;
+            };
+            self.evict(&oldest);
+            evicted.push(oldest);
+        }
+        evicted
+    }
+
+    /// Add a freshly loaded model, evicting as needed, and record how long
+    /// loading took.
+    pub fn insert(&mut self, name: &str, model: T, size_mb: u64, load_time: Duration) -> Arc<T> {
+        self.make_room();
+        let model = Arc::new(model);
+        self.entries.insert(
+            name.to_string(),
+            Entry {
+                model: model.clone(),
+                size_mb,
+                last_used: Instant::now(),
+            },
+        );
+        let ms = load_time.as_millis() as u64;
+        let stats = self.stats.entry(name.to_string()).or_default();
+        stats.loads += 1;
+        stats.last_load_ms = ms;
+        stats.max_load_ms = stats.max_load_ms.max(ms);
+        stats.total_load_ms += ms;
+        tracing::info!("Loaded local model {} in {} ms ({} MB)", name, ms, size_mb);
+        model
+    }
+
+    /// Unload models idle longer than the keep-alive; returns their names.
+    pub fn evict_idle(&mut self, now: Instant) -> Vec<String> {
+        let Some(keep_alive) = self.policy.keep_alive else {
+            return Vec::new();
+        };
+        let idle: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, e)| now.saturating_duration_since(e.last_used) >= keep_alive)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in &idle {
+            self.evict(name);
+        }
+        idle
+    }
+
+    pub fn status(&self) -> PoolStatus {
+        let now = Instant::now();
+        let mut loaded: Vec<LoadedInfo> = self
+            .entries
+            .iter()
+            .map(|(name, e)| LoadedInfo {
+                name: name.clone(),
+                size_mb: e.size_mb,
+                idle_secs: now.saturating_duration_since(e.last_used).as_secs(),
+            })
+            .collect();
+        loaded.sort_by(|a, b| a.name.cmp(&b.name));
+        PoolStatus {
+            max_loaded: self.policy.max_loaded,
+            keep_alive_secs: self.policy.keep_alive.map(|d| d.as_secs()),
+            loaded,
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_eviction_and_load_stats() {
+        let mut pool = ModelPool::new(PoolPolicy {
+            max_loaded: 2,
+            keep_alive: None,
+        });
+        pool.insert("a", "model a", 300, Duration::from_millis(1200));
+        pool.insert("b", "model b", 900, Duration::from_millis(3000));
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(pool.get("a").as_deref(), Some(&"model a"));
+
+        // "b" is the least recently used now.
+        pool.insert("c", "model c", 300, Duration::from_millis(800));
+        assert!(pool.is_loaded("a") && pool.is_loaded("c") && !pool.is_loaded("b"));
+        assert_eq!(pool.loaded_mb(), 600);
+
+        pool.insert("b", "model b", 900, Duration::from_millis(1000));
+        let status = pool.status();
+        let b = &status.stats["b"];
+        assert_eq!((b.loads, b.evictions, b.last_load_ms, b.max_load_ms, b.total_load_ms), (2, 1, 1000, 3000, 4000));
+        assert_eq!(status.stats["a"].requests, 1);
+        assert_eq!(status.loaded.len(), 2);
+    }
+
+    #[test]
+    fn test_keep_alive_unloads_idle_models() {
+        let mut pool = ModelPool::new(PoolPolicy {
+            max_loaded: 3,
+            keep_alive: Some(Duration::from_secs(60)),
+        });
+        pool.insert("a", 1, 10, Duration::ZERO);
+        pool.insert("b", 2, 10, Duration::ZERO);
+        let now = Instant::now();
+        assert!(pool.evict_idle(now).is_empty());
+        assert_eq!(pool.evict_idle(now + Duration::from_secs(61)).len(), 2);
+        assert_eq!(pool.loaded_count(), 0);
+
+        let mut forever = ModelPool::new(PoolPolicy::default());
+        forever.insert("a", 1, 10, Duration::ZERO);
+        assert!(forever.evict_idle(now + Duration::from_secs(86_400)).is_empty());
+    }
+}
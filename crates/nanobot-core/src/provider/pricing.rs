@@ -159,10 +159,31 @@ pub fn calculate_cost(model: &str, input_tokens: u32, output_tokens: u32) -> f64
     }
 }
 
+/// Most output tokens `model` can produce after `input_tokens` of input
+/// without costing more than `budget_usd` (0 when the input alone exceeds it).
+pub fn max_output_tokens_within(model: &str, input_tokens: u32, budget_usd: f64) -> u32 {
+    let left = budget_usd - calculate_cost(model, input_tokens, 0);
+    if left <= 0.0 {
+        return 0;
+    }
+    let per_token = calculate_cost(model, 0, 1_000_000) / 1_000_000.0;
+    if per_token <= 0.0 {
+        return u32::MAX;
+    }
+    (left / per_token).floor().min(u32::MAX as f64) as u32
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_max_output_tokens_within() {
+        // gpt-4o: $10 per 1M output tokens
+        assert!((999..=1_000).contains(&max_output_tokens_within("gpt-4o", 0, 0.01)));
+        assert_eq!(max_output_tokens_within("gpt-4o", 10_000_000, 0.01), 0);
+    }
+
     #[test]
     fn test_lookup_model() {
         let p = lookup_model("gpt-4o").unwrap();
@@ -0,0 +1,396 @@
+//! Code review of a diff: rubric prompt, parsing the agent's JSON answer into
+//! inline comments, rendering, and posting to a GitHub pull request.
+//!
+//! Used by `chatweb review` (local ranges, a pre-push hook, or `--pr <url>`).
+//! Comments are anchored to `file:line` on the new side of the diff; those
+//! that don't land on a changed line are kept but marked `in_diff: false`,
+//! since GitHub only accepts inline comments on lines present in the diff.
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::util::truncate_chars;
+
+/// Diffs are cut to this many characters before review.
+pub const MAX_DIFF_CHARS: usize = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn parse(s: &str) -> Option<Self> {
+        Some(match s.to_lowercase().as_str() {
+            "info" | "nit" | "suggestion" => Self::Info,
+            "warning" | "warn" | "minor" => Self::Warning,
+            "error" | "critical" | "major" | "blocker" => Self::Error,
+            _ => return None,
+        })
+    }
+
+    fn icon(&self) -> &'static str {
+        match self {
+            Self::Info => "💡",
+            Self::Warning => "⚠️",
+            Self::Error => "🛑",
+        }
+    }
+}
+
+fn default_severity() -> Severity {
+    Severity::Warning
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewComment {
+    pub file: String,
+    /// Line in the new version of the file.
+    pub line: usize,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+    /// Rubric category: correctness, security, performance, readability, tests.
+    #[serde(default)]
+    pub category: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+    /// Whether `file:line` is a changed line of the diff.
+    #[serde(default = "default_true")]
+    pub in_diff: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Review {
+    pub summary: String,
+    /// approve, comment or request_changes.
+    #[serde(default)]
+    pub verdict: String,
+    #[serde(default)]
+    pub comments: Vec<ReviewComment>,
+}
+
+impl Review {
+    /// Highest severity among the comments.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.comments.iter().map(|c| c.severity).max()
+    }
+}
+
+/// The review rubric and output contract given to the agent.
+pub fn build_prompt(diff: &str, context: Option<&str>) -> String {
+    let (diff, truncated) = truncate_chars(diff, MAX_DIFF_CHARS);
+    let mut prompt = String::from(
+        "Review the following code change as a senior engineer.\n\n\
+         ## Rubric\n\
+         - correctness: bugs, edge cases, error handling, concurrency\n\
+         - security: injection, secrets, unsafe input handling, permissions\n\
+         - performance: needless allocations, N+1 calls, blocking in async code\n\
+         - readability: naming, dead code, consistency with the surrounding code\n\
+         - tests: missing or weakened tests for the changed behaviour\n\n\
+         Only comment on problems introduced or touched by this diff; skip praise and style \
+         nits a formatter would fix. You may read files in the workspace for context.\n\n\
+         ## Output\n\
+         Reply with ONLY a JSON object, no prose around it:\n\
+         {\"summary\": \"one paragraph\", \"verdict\": \"approve|comment|request_changes\", \
+         \"comments\": [{\"file\": \"path/in/diff\", \"line\": 42, \"severity\": \"info|warning|error\", \
+         \"category\": \"correctness|security|performance|readability|tests\", \"message\": \"...\", \
+         \"suggestion\": \"optional replacement code\"}]}\n\
+         `line` is the line number in the NEW version of the file (the + side of the hunk).\n",
+    );
+    if let Some(context) = context {
+        prompt.push_str(&format!("\n## Context\n{}\n", context));
+    }
+    prompt.push_str(&format!("\n## Diff\n```diff\n{}\n```\n", diff));
+    if truncated {
+        prompt.push_str("\n(The diff was truncated; review what is shown.)\n");
+    }
+    prompt
+}
+
+/// New-side line numbers of added lines, per file.
+pub fn changed_lines(diff: &str) -> HashMap<String, BTreeSet<usize>> {
+    let mut files: HashMap<String, BTreeSet<usize>> = HashMap::new();
+    let mut current: Option<String> = None;
+    let mut line = 0usize;
+    for text in diff.lines() {
+        if let Some(path) = text.strip_prefix("+++ ") {
+            let path = path.split('\t').next().unwrap_or_default().trim();
+            current = (path != "/dev/null").then(|| path.strip_prefix("b/").unwrap_or(path).to_string());
+        } else if text.starts_with("--- ") {
+            continue;
+        } else if let Some(header) = text.strip_prefix("@@ ") {
+            // @@ -a,b +c,d @@
+            line = header
+                .split_whitespace()
+                .find_map(|p| p.strip_prefix('+'))
+                .and_then(|p| p.split(',').next())
+                .and_then(|n| n.parse().ok())
+                .unwrap_or(0);
+        } else if let Some(ref file) = current {
+            match text.chars().next() {
+                Some('+') => {
+                    files.entry(file.clone()).or_default().insert(line);
+                    line += 1;
+                }
+                Some('-') | Some('\\') => {}
+                _ => line += 1,
+            }
+        }
+    }
+    files
+}
+
+/// Pull the JSON object out of the agent's reply (which may be fenced or
+/// wrapped in prose) and mark comments that are not on changed lines.
+pub fn parse_review(response: &str, diff: &str) -> anyhow::Result<Review> {
+    let start = response.find('{').context("no JSON object in the review response")?;
+    let end = response.rfind('}').context("no JSON object in the review response")?;
+    if end < start {
+        bail!("no JSON object in the review response");
+    }
+    let value: serde_json::Value =
+        serde_json::from_str(&response[start..=end]).context("review response is not valid JSON")?;
+
+    // Be lenient with severities the model invents ("major", "nit")
+    let mut value = value;
+    if let Some(comments) = value.get_mut("comments").and_then(|c| c.as_array_mut()) {
+        for comment in comments {
+            if let Some(sev) = comment.get("severity").and_then(|s| s.as_str()) {
+                let sev = Severity::parse(sev).unwrap_or(Severity::Warning);
+                comment["severity"] = serde_json::to_value(sev)?;
+            }
+        }
+    }
+    let mut review: Review = serde_json::from_value(value).context("unexpected review JSON shape")?;
+
+    let changed = changed_lines(diff);
+    for comment in &mut review.comments {
+        let file = comment.file.trim_start_matches("b/").trim_start_matches("./").to_string();
+        comment.in_diff = changed.get(&file).is_some_and(|lines| lines.contains(&comment.line));
+        comment.file = file;
+    }
+    review
+        .comments
+        .sort_by(|a, b| b.severity.cmp(&a.severity).then(a.file.cmp(&b.file)).then(a.line.cmp(&b.line)));
+    Ok(review)
+}
+
+/// Markdown rendering for terminals and PR bodies.
+pub fn to_markdown(review: &Review) -> String {
+    let mut out = format!("## Review: {}\n\n{}\n", review.verdict.replace('_', " "), review.summary);
+    if review.comments.is_empty() {
+        out.push_str("\nNo issues found.\n");
+        return out;
+    }
+    out.push('\n');
+    for c in &review.comments {
+        out.push_str(&format!(
+            "- {} **{}:{}** [{}] {}{}\n",
+            c.severity.icon(),
+            c.file,
+            c.line,
+            if c.category.is_empty() { "general" } else { &c.category },
+            c.message,
+            if c.in_diff { "" } else { " _(outside the diff)_" }
+        ));
+        if let Some(ref suggestion) = c.suggestion {
+            out.push_str(&format!("  ```\n  {}\n  ```\n", suggestion.replace('\n', "\n  ")));
+        }
+    }
+    out
+}
+
+/// Diff of the local repository at `dir`: `range` if given, otherwise staged
+/// changes, falling back to unstaged ones.
+pub fn git_diff(dir: &Path, range: Option<&str>) -> anyhow::Result<String> {
+    let run = |args: &[&str]| -> anyhow::Result<String> {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .context("failed to run git")?;
+        if !output.status.success() {
+            bail!("git {}: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    match range {
+        Some(range) => run(&["diff", "--no-color", range]),
+        None => {
+            let staged = run(&["diff", "--no-color", "--cached"])?;
+            if staged.trim().is_empty() {
+                run(&["diff", "--no-color"])
+            } else {
+                Ok(staged)
+            }
+        }
+    }
+}
+
+/// `https://github.com/<owner>/<repo>/pull/<number>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PullRequestRef {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+}
+
+impl PullRequestRef {
+    pub fn parse(url: &str) -> anyhow::Result<Self> {
+        let path = url
+            .trim()
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_start_matches("github.com/")
+            .trim_end_matches('/');
+        let parts: Vec<&str> = path.split('/').collect();
+        match parts.as_slice() {
+            [owner, repo, "pull" | "pulls", number, ..] => Ok(Self {
+                owner: owner.to_string(),
+                repo: repo.to_string(),
+                number: number.parse().context("invalid pull request number")?,
+            }),
+            _ => bail!("expected a pull request URL like https://github.com/owner/repo/pull/123"),
+        }
+    }
+
+    fn api_url(&self) -> String {
+        format!(
+            "https://api.github.com/repos/{}/{}/pulls/{}",
+            self.owner, self.repo, self.number
+        )
+    }
+}
+
+fn github_client(token: Option<&str>) -> anyhow::Result<reqwest::Client> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("User-Agent", "chatweb-ai/1.0".parse()?);
+    if let Some(token) = token {
+        headers.insert("Authorization", format!("Bearer {}", token).parse()?);
+    }
+    Ok(reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .default_headers(headers)
+        .build()?)
+}
+
+/// The unified diff of a pull request. Public repositories work without a token.
+pub async fn fetch_pr_diff(pr: &PullRequestRef, token: Option<&str>) -> anyhow::Result<String> {
+    let resp = github_client(token)?
+        .get(pr.api_url())
+        .header("Accept", "application/vnd.github.v3.diff")
+        .send()
+        .await?;
+    let status = resp.status();
+    let body = resp.text().await?;
+    if !status.is_success() {
+        bail!("GitHub returned HTTP {}: {}", status, body.chars().take(200).collect::<String>());
+    }
+    Ok(body)
+}
+
+/// Post `review` as a pull request review: in-diff comments inline, the rest
+/// in the review body. Returns the review URL.
+pub async fn post_review(pr: &PullRequestRef, review: &Review, token: &str) -> anyhow::Result<String> {
+    let inline: Vec<serde_json::Value> = review
+        .comments
+        .iter()
+        .filter(|c| c.in_diff)
+        .map(|c| {
+            let mut body = format!("{} **{}** {}", c.severity.icon(), c.category, c.message);
+            if let Some(ref s) = c.suggestion {
+                body.push_str(&format!("\n\n```suggestion\n{}\n```", s));
+            }
+            serde_json::json!({ "path": c.file, "line": c.line, "side": "RIGHT", "body": body })
+        })
+        .collect();
+    let mut body = format!("{}\n\n*Automated review by chatweb*", review.summary);
+    let outside: Vec<&ReviewComment> = review.comments.iter().filter(|c| !c.in_diff).collect();
+    if !outside.is_empty() {
+        body.push_str("\n\n**Other notes**\n");
+        for c in outside {
+            body.push_str(&format!("- `{}:{}` {}\n", c.file, c.line, c.message));
+        }
+    }
+    // Never approve or block on behalf of a human reviewer
+    let payload = serde_json::json!({ "event": "COMMENT", "body": body, "comments": inline });
+
+    let resp = github_client(Some(token))?
+        .post(format!("{}/reviews", pr.api_url()))
+        .header("Accept", "application/vnd.github.v3+json")
+        .json(&payload)
+        .send()
+        .await?;
+    let status = resp.status();
+    let data: serde_json::Value = resp.json().await.unwrap_or_default();
+    if !status.is_success() {
+        let msg = data.get("message").and_then(|v| v.as_str()).unwrap_or("unknown error");
+        bail!("GitHub returned HTTP {}: {}", status, msg);
+    }
+    Ok(data
+        .get("html_url")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -10,3 +10,4 @@ fn main() {
+ let a = 1;
+-let b = 2;
++let b = a.unwrap();
++let c = 3;
+ done();
+";
+
+    #[test]
+    fn test_changed_lines() {
+        let changed = changed_lines(DIFF);
+        assert_eq!(changed["src/lib.rs"].iter().copied().collect::<Vec<_>>(), vec![11, 12]);
+    }
+
+    #[test]
+    fn test_parse_review_marks_out_of_diff_comments() {
+        let response = "Here you go:\n```json\n{\"summary\": \"Risky unwrap.\", \"verdict\": \"request_changes\", \
+            \"comments\": [\
+              {\"file\": \"src/lib.rs\", \"line\": 30, \"severity\": \"nit\", \"message\": \"rename\"},\
+              {\"file\": \"b/src/lib.rs\", \"line\": 11, \"severity\": \"major\", \"category\": \"correctness\", \
+               \"message\": \"unwrap panics\", \"suggestion\": \"let b = a?;\"}]}\n```";
+        let review = parse_review(response, DIFF).unwrap();
+        assert_eq!(review.max_severity(), Some(Severity::Error));
+        assert_eq!(review.comments[0].file, "src/lib.rs");
+        assert!(review.comments[0].in_diff);
+        assert!(!review.comments[1].in_diff);
+
+        let md = to_markdown(&review);
+        assert!(md.starts_with("## Review: request changes"));
+        assert!(md.contains("🛑 **src/lib.rs:11** [correctness] unwrap panics"));
+        assert!(md.contains("_(outside the diff)_"));
+
+        assert!(parse_review("LGTM!", DIFF).is_err());
+    }
+
+    #[test]
+    fn test_pull_request_ref() {
+        let pr = PullRequestRef::parse("https://github.com/yukihamada/nanobot/pull/42/files").unwrap();
+        assert_eq!((pr.owner.as_str(), pr.repo.as_str(), pr.number), ("yukihamada", "nanobot", 42));
+        assert!(PullRequestRef::parse("https://github.com/yukihamada/nanobot").is_err());
+    }
+}
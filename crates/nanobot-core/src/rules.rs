@@ -0,0 +1,782 @@
+//! Event rules: "when X happens, do Y".
+//!
+//! Rules live in `<workspace>/rules.yaml` and can also be edited through
+//! `/api/v1/rules`:
+//!
+//! ```yaml
+//! rules:
+//!   - id: urgent
+//!     when:
+//!       event: message
+//!       pattern: "(?i)urgent|緊急"
+//!       channel: telegram
+//!     do:
+//!       - notify: { channel: telegram, to: "123456", text: "Urgent from {{sender}}: {{content}}" }
+//!       - run_workflow: { workflow: triage, input: "{{content}}" }
+//!     cooldown_secs: 300
+//!   - id: line-silent
+//!     when: { event: heartbeat_missing, source: line, after_secs: 3600 }
+//!     do:
+//!       - disable_channel: { channel: line }
+//!   - id: low-credits
+//!     when: { event: credits_low, below: 50 }
+//!     do:
+//!       - notify: { channel: slack, to: C0123, text: "{{user_id}} has {{remaining}} credits left" }
+//! ```
+//!
+//! Events are matched as they happen (inbound messages in the agent loop,
+//! credit deductions in the HTTP API, missing heartbeats on each check) and
+//! every match is queued as a [`RuleFiring`] with its actions already
+//! templated (see [`crate::workflow::render`]). The gateway executes pending
+//! firings, so events raised by the HTTP server are handled the same way.
+//! Firings, heartbeats and disabled channels are kept in
+//! `<data dir>/rules/state.json`.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::types::{InboundMessage, OutboundMessage};
+use crate::util::{now_ms, read_json, write_json};
+use crate::workflow::{self, WorkflowEngine};
+
+/// Oldest finished firings beyond this count are dropped on save.
+pub const MAX_FIRINGS: usize = 500;
+
+/// Heartbeats closer together than this are not written to disk.
+const HEARTBEAT_RESOLUTION_MS: u64 = 30_000;
+
+/// What a rule reacts to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RuleTrigger {
+    /// An inbound message whose content matches `pattern` (a regex; empty matches all).
+    Message {
+        #[serde(default)]
+        pattern: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        channel: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sender: Option<String>,
+    },
+    /// No heartbeat from `source` for `after_secs`. Every inbound message is
+    /// a heartbeat for its channel; other sources ping
+    /// `POST /api/v1/rules/heartbeat/{source}`. Fires once per outage.
+    HeartbeatMissing { source: String, after_secs: u64 },
+    /// A user's credits drop below `below`.
+    CreditsLow { below: i64 },
+}
+
+impl RuleTrigger {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Message { .. } => "message",
+            Self::HeartbeatMissing { .. } => "heartbeat_missing",
+            Self::CreditsLow { .. } => "credits_low",
+        }
+    }
+}
+
+/// What a rule does. String fields are templates over the event variables.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    Notify { channel: String, to: String, text: String },
+    /// Queue a run of a workflow; `input` becomes its `{{input}}`.
+    RunWorkflow {
+        workflow: String,
+        #[serde(default)]
+        input: String,
+    },
+    /// Drop inbound messages from `channel` until it is re-enabled.
+    DisableChannel { channel: String },
+}
+
+impl RuleAction {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Notify { .. } => "notify",
+            Self::RunWorkflow { .. } => "run_workflow",
+            Self::DisableChannel { .. } => "disable_channel",
+        }
+    }
+
+    fn render(&self, vars: &Map<String, Value>) -> Self {
+        let r = |s: &str| workflow::render(s, vars);
+        match self {
+            Self::Notify { channel, to, text } => Self::Notify {
+                channel: r(channel),
+                to: r(to),
+                text: r(text),
+            },
+            Self::RunWorkflow { workflow, input } => Self::RunWorkflow {
+                workflow: r(workflow),
+                input: r(input),
+            },
+            Self::DisableChannel { channel } => Self::DisableChannel { channel: r(channel) },
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Rule {
+    /// Defaults to `rule<N>`.
+    #[serde(default)]
+    pub id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub when: RuleTrigger,
+    #[serde(rename = "do", with = "serde_yaml::with::singleton_map_recursive")]
+    pub actions: Vec<RuleAction>,
+    /// Minimum time between two firings of this rule.
+    #[serde(default)]
+    pub cooldown_secs: u64,
+}
+
+impl Rule {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.actions.is_empty() {
+            anyhow::bail!("rule '{}' has no actions", self.id);
+        }
+        if let RuleTrigger::Message { ref pattern, .. } = self.when {
+            regex::Regex::new(pattern).map_err(|e| anyhow::anyhow!("rule '{}': {}", self.id, e))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    rules: Vec<Rule>,
+}
+
+/// Parse a rules file and validate every rule.
+pub fn parse_rules(content: &str) -> anyhow::Result<Vec<Rule>> {
+    let mut rules = serde_yaml::from_str::<Option<RulesFile>>(content)?.unwrap_or_default().rules;
+    for (i, rule) in rules.iter_mut().enumerate() {
+        if rule.id.trim().is_empty() {
+            rule.id = format!("rule{}", i + 1);
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    for rule in &rules {
+        if !seen.insert(rule.id.as_str()) {
+            anyhow::bail!("rule id '{}' is used twice", rule.id);
+        }
+        rule.validate()?;
+    }
+    Ok(rules)
+}
+
+/// Something that happened, to be matched against the rules.
+#[derive(Debug, Clone)]
+pub enum RuleEvent<'a> {
+    Message(&'a InboundMessage),
+    HeartbeatMissing { source: &'a str, missing_secs: u64 },
+    /// Credits went from `before` (unknown for new users) to `remaining`.
+    CreditsLow {
+        user_id: Option<&'a str>,
+        before: Option<i64>,
+        remaining: i64,
+    },
+}
+
+impl RuleEvent<'_> {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Message(_) => "message",
+            Self::HeartbeatMissing { .. } => "heartbeat_missing",
+            Self::CreditsLow { .. } => "credits_low",
+        }
+    }
+
+    /// Template variables when `trigger` matches this event.
+    fn matches(&self, trigger: &RuleTrigger) -> Option<Map<String, Value>> {
+        let mut vars = Map::new();
+        match (self, trigger) {
+            (Self::Message(msg), RuleTrigger::Message { pattern, channel, sender }) => {
+                if channel.as_ref().is_some_and(|c| *c != msg.channel)
+                    || sender.as_ref().is_some_and(|s| *s != msg.sender_id)
+                {
+                    return None;
+                }
+                let re = regex::Regex::new(pattern).ok()?;
+                let caps = re.captures(&msg.content)?;
+                vars.insert("channel".into(), msg.channel.clone().into());
+                vars.insert("sender".into(), msg.sender_id.clone().into());
+                vars.insert("chat_id".into(), msg.chat_id.clone().into());
+                vars.insert("content".into(), msg.content.clone().into());
+                vars.insert("match".into(), caps.get(0).map_or("", |m| m.as_str()).into());
+                for name in re.capture_names().flatten() {
+                    if let Some(m) = caps.name(name) {
+                        vars.insert(name.into(), m.as_str().into());
+                    }
+                }
+            }
+            (Self::HeartbeatMissing { source, missing_secs }, RuleTrigger::HeartbeatMissing { source: s, after_secs }) => {
+                if source != s || missing_secs < after_secs {
+                    return None;
+                }
+                vars.insert("source".into(), (*source).into());
+                vars.insert("missing_secs".into(), (*missing_secs).into());
+            }
+            (Self::CreditsLow { user_id, before, remaining }, RuleTrigger::CreditsLow { below }) => {
+                // Only when crossing the threshold, not on every message below it
+                if remaining >= below || before.is_some_and(|b| b < *below) {
+                    return None;
+                }
+                vars.insert("user_id".into(), user_id.unwrap_or_default().into());
+                vars.insert("remaining".into(), (*remaining).into());
+                vars.insert("threshold".into(), (*below).into());
+            }
+            _ => return None,
+        }
+        Some(vars)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FiringStatus {
+    Pending,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionResult {
+    pub action: String,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// One match of a rule, with its actions templated for the event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleFiring {
+    pub id: String,
+    pub rule_id: String,
+    pub event: String,
+    pub vars: Map<String, Value>,
+    pub actions: Vec<RuleAction>,
+    pub status: FiringStatus,
+    #[serde(default)]
+    pub results: Vec<ActionResult>,
+    pub created_at_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleState {
+    #[serde(default)]
+    firings: Vec<RuleFiring>,
+    #[serde(default)]
+    disabled_channels: Vec<String>,
+    /// Source -> last heartbeat.
+    #[serde(default)]
+    heartbeats: BTreeMap<String, u64>,
+    /// Rule id -> last firing (for cooldowns).
+    #[serde(default)]
+    last_fired: BTreeMap<String, u64>,
+    /// Heartbeat rules that already fired for the current outage.
+    #[serde(default)]
+    outages: Vec<String>,
+}
+
+/// Where rule actions go: channel sends and workflow runs.
+#[async_trait]
+pub trait RuleHost: Send + Sync {
+    async fn notify(&self, channel: &str, to: &str, text: &str) -> anyhow::Result<()>;
+    /// Queue a workflow run and return its id.
+    async fn run_workflow(&self, name: &str, input: &str) -> anyhow::Result<String>;
+}
+
+/// [`RuleHost`] for the gateway: notifications go to the outbound queue,
+/// workflow runs to the workflow runner.
+pub struct GatewayRuleHost {
+    outbound: mpsc::Sender<OutboundMessage>,
+    workflows: std::sync::Arc<WorkflowEngine>,
+}
+
+impl GatewayRuleHost {
+    pub fn new(outbound: mpsc::Sender<OutboundMessage>, workflows: std::sync::Arc<WorkflowEngine>) -> Self {
+        Self { outbound, workflows }
+    }
+}
+
+#[async_trait]
+impl RuleHost for GatewayRuleHost {
+    async fn notify(&self, channel: &str, to: &str, text: &str) -> anyhow::Result<()> {
+        self.outbound
+            .send(OutboundMessage::new(channel, to, text))
+            .await
+            .map_err(|e| anyhow::anyhow!("outbound queue: {e}"))
+    }
+
+    async fn run_workflow(&self, name: &str, input: &str) -> anyhow::Result<String> {
+        let run = self
+            .workflows
+            .enqueue(name, workflow::TRIGGER_RULE, Value::String(input.to_string()), None)?;
+        Ok(run.id)
+    }
+}
+
+/// Loads rules, matches events and keeps the firing history.
+///
+/// Like [`WorkflowEngine`], both files are re-read on every call so the HTTP
+/// API and the gateway can share them.
+pub struct RulesEngine {
+    rules_path: PathBuf,
+    state_path: PathBuf,
+    lock: std::sync::Mutex<()>,
+}
+
+impl RulesEngine {
+    pub fn new(rules_path: PathBuf, state_path: PathBuf) -> Self {
+        Self {
+            rules_path,
+            state_path,
+            lock: std::sync::Mutex::new(()),
+        }
+    }
+
+    /// Rules in `<workspace>/rules.yaml`, state in `<data dir>/rules/state.json`.
+    pub fn for_workspace(workspace: &Path) -> Self {
+        Self::new(
+            workspace.join("rules.yaml"),
+            crate::config::get_data_dir().join("rules").join("state.json"),
+        )
+    }
+
+    pub fn rules_path(&self) -> &Path {
+        &self.rules_path
+    }
+
+    /// All rules; a missing file means none.
+    pub fn load(&self) -> anyhow::Result<Vec<Rule>> {
+        match std::fs::read_to_string(&self.rules_path) {
+            Ok(content) => parse_rules(&content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save_rules(&self, rules: Vec<Rule>) -> anyhow::Result<()> {
+        if let Some(parent) = self.rules_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.rules_path, serde_yaml::to_string(&RulesFile { rules })?)?;
+        Ok(())
+    }
+
+    /// Add `rule`, or replace the rule with the same id.
+    pub fn upsert(&self, mut rule: Rule) -> anyhow::Result<Rule> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut rules = self.load()?;
+        if rule.id.trim().is_empty() {
+            rule.id = (rules.len() + 1..)
+                .map(|n| format!("rule{}", n))
+                .find(|id| rules.iter().all(|r| r.id != *id))
+                .unwrap_or_default();
+        }
+        rule.validate()?;
+        match rules.iter_mut().find(|r| r.id == rule.id) {
+            Some(existing) => *existing = rule.clone(),
+            None => rules.push(rule.clone()),
+        }
+        self.save_rules(rules)?;
+        Ok(rule)
+    }
+
+    /// Delete a rule. Returns false if there was none with that id.
+    pub fn remove(&self, id: &str) -> anyhow::Result<bool> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut rules = self.load()?;
+        let before = rules.len();
+        rules.retain(|r| r.id != id);
+        if rules.len() == before {
+            return Ok(false);
+        }
+        self.save_rules(rules)?;
+        Ok(true)
+    }
+
+    fn read_state(&self) -> RuleState {
+        read_json(&self.state_path, "rule state")
+    }
+
+    fn write_state(&self, state: &mut RuleState) {
+        if state.firings.len() > MAX_FIRINGS {
+            let mut excess = state.firings.len() - MAX_FIRINGS;
+            state.firings.retain(|f| {
+                if excess > 0 && f.status != FiringStatus::Pending {
+                    excess -= 1;
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        write_json(&self.state_path, state, "rule state");
+    }
+
+    /// Read-modify-write the state under the engine lock.
+    fn with_state<T>(&self, f: impl FnOnce(&mut RuleState) -> T) -> T {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut state = self.read_state();
+        let result = f(&mut state);
+        self.write_state(&mut state);
+        result
+    }
+
+    /// Match `event` against the enabled rules and queue a firing for each
+    /// match that is not cooling down.
+    pub fn fire(&self, event: &RuleEvent<'_>) -> Vec<RuleFiring> {
+        let rules = match self.load() {
+            Ok(rules) => rules,
+            Err(e) => {
+                warn!("Rules not evaluated: {}", e);
+                return Vec::new();
+            }
+        };
+        let matched: Vec<(Rule, Map<String, Value>)> = rules
+            .into_iter()
+            .filter(|r| r.enabled)
+            .filter_map(|r| event.matches(&r.when).map(|vars| (r, vars)))
+            .collect();
+        if matched.is_empty() {
+            return Vec::new();
+        }
+        let now = now_ms();
+        self.with_state(|state| {
+            let mut fired = Vec::new();
+            for (rule, vars) in matched {
+                let cooling = state
+                    .last_fired
+                    .get(&rule.id)
+                    .is_some_and(|t| now < t + rule.cooldown_secs * 1000);
+                if cooling {
+                    continue;
+                }
+                state.last_fired.insert(rule.id.clone(), now);
+                let firing = RuleFiring {
+                    id: Uuid::new_v4().to_string()[..8].to_string(),
+                    rule_id: rule.id.clone(),
+                    event: event.kind().to_string(),
+                    actions: rule.actions.iter().map(|a| a.render(&vars)).collect(),
+                    vars,
+                    status: FiringStatus::Pending,
+                    results: Vec::new(),
+                    created_at_ms: now,
+                    finished_at_ms: None,
+                };
+                info!("Rule '{}' fired on {} ({})", rule.id, firing.event, firing.id);
+                state.firings.push(firing.clone());
+                fired.push(firing);
+            }
+            fired
+        })
+    }
+
+    /// Record a heartbeat from `source` (ends its outage, if any).
+    pub fn beat(&self, source: &str) {
+        let now = now_ms();
+        let state = self.read_state();
+        let recent = state
+            .heartbeats
+            .get(source)
+            .is_some_and(|t| now.saturating_sub(*t) < HEARTBEAT_RESOLUTION_MS);
+        let prefix = format!("{}:", source);
+        if recent && !state.outages.iter().any(|o| o.starts_with(&prefix)) {
+            return;
+        }
+        self.with_state(|state| {
+            state.heartbeats.insert(source.to_string(), now);
+            state.outages.retain(|o| !o.starts_with(&prefix));
+        });
+    }
+
+    /// Heartbeat and `message` rules for an inbound message.
+    pub fn observe_message(&self, msg: &InboundMessage) -> Vec<RuleFiring> {
+        self.beat(&msg.channel);
+        self.fire(&RuleEvent::Message(msg))
+    }
+
+    /// Fire `heartbeat_missing` rules whose source has been silent too long.
+    /// A source that never sent a heartbeat counts from its first check.
+    pub fn check_heartbeats(&self) -> Vec<RuleFiring> {
+        let rules = self.load().unwrap_or_default();
+        let now = now_ms();
+        let mut missing = Vec::new();
+        self.with_state(|state| {
+            for rule in rules.iter().filter(|r| r.enabled) {
+                let RuleTrigger::HeartbeatMissing { ref source, after_secs } = rule.when else {
+                    continue;
+                };
+                let last = *state.heartbeats.entry(source.clone()).or_insert(now);
+                let silent_secs = now.saturating_sub(last) / 1000;
+                let outage = format!("{}:{}", source, rule.id);
+                if silent_secs >= after_secs && !state.outages.contains(&outage) {
+                    state.outages.push(outage);
+                    missing.push((source.clone(), silent_secs));
+                }
+            }
+        });
+        missing.sort();
+        missing.dedup();
+        missing
+            .iter()
+            .flat_map(|(source, missing_secs)| {
+                self.fire(&RuleEvent::HeartbeatMissing {
+                    source,
+                    missing_secs: *missing_secs,
+                })
+            })
+            .collect()
+    }
+
+    /// Execute pending firings. Returns the number executed.
+    pub async fn run_pending(&self, host: &dyn RuleHost) -> usize {
+        let pending: Vec<RuleFiring> = self
+            .read_state()
+            .firings
+            .into_iter()
+            .filter(|f| f.status == FiringStatus::Pending)
+            .collect();
+        let count = pending.len();
+        for mut firing in pending {
+            for action in &firing.actions {
+                let outcome = match action {
+                    RuleAction::Notify { channel, to, text } => {
+                        if self.is_channel_disabled(channel) {
+                            Err(anyhow::anyhow!("channel '{}' is disabled", channel))
+                        } else {
+                            host.notify(channel, to, text).await.map(|_| None)
+                        }
+                    }
+                    RuleAction::RunWorkflow { workflow, input } => {
+                        host.run_workflow(workflow, input).await.map(|id| Some(format!("run {}", id)))
+                    }
+                    RuleAction::DisableChannel { channel } => {
+                        self.set_channel_disabled(channel, true);
+                        Ok(None)
+                    }
+                };
+                if let Err(ref e) = outcome {
+                    warn!("Rule '{}' action {} failed: {}", firing.rule_id, action.kind(), e);
+                }
+                firing.results.push(ActionResult {
+                    action: action.kind().to_string(),
+                    ok: outcome.is_ok(),
+                    detail: outcome.unwrap_or_else(|e| Some(e.to_string())),
+                });
+            }
+            firing.status = if firing.results.iter().all(|r| r.ok) {
+                FiringStatus::Done
+            } else {
+                FiringStatus::Failed
+            };
+            firing.finished_at_ms = Some(now_ms());
+            self.with_state(|state| {
+                if let Some(existing) = state.firings.iter_mut().find(|f| f.id == firing.id) {
+                    *existing = firing;
+                }
+            });
+        }
+        count
+    }
+
+    /// Firings newest first.
+    pub fn firings(&self, rule_id: Option<&str>, limit: usize) -> Vec<RuleFiring> {
+        self.read_state()
+            .firings
+            .into_iter()
+            .rev()
+            .filter(|f| rule_id.is_none_or(|id| f.rule_id == id))
+            .take(limit)
+            .collect()
+    }
+
+    pub fn heartbeats(&self) -> BTreeMap<String, u64> {
+        self.read_state().heartbeats
+    }
+
+    pub fn disabled_channels(&self) -> Vec<String> {
+        self.read_state().disabled_channels
+    }
+
+    pub fn is_channel_disabled(&self, channel: &str) -> bool {
+        self.read_state().disabled_channels.iter().any(|c| c == channel)
+    }
+
+    /// Disable or re-enable a channel. Returns whether anything changed.
+    pub fn set_channel_disabled(&self, channel: &str, disabled: bool) -> bool {
+        self.with_state(|state| {
+            let present = state.disabled_channels.iter().any(|c| c == channel);
+            match (disabled, present) {
+                (true, false) => {
+                    warn!("Channel '{}' disabled by rule", channel);
+                    state.disabled_channels.push(channel.to_string());
+                    true
+                }
+                (false, true) => {
+                    state.disabled_channels.retain(|c| c != channel);
+                    true
+                }
+                _ => false,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct FakeHost {
+        calls: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl RuleHost for FakeHost {
+        async fn notify(&self, channel: &str, to: &str, text: &str) -> anyhow::Result<()> {
+            self.calls.lock().unwrap().push(format!("notify {}:{} {}", channel, to, text));
+            Ok(())
+        }
+
+        async fn run_workflow(&self, name: &str, input: &str) -> anyhow::Result<String> {
+            self.calls.lock().unwrap().push(format!("workflow {} {}", name, input));
+            Ok("abc".to_string())
+        }
+    }
+
+    const RULES: &str = r#"
+rules:
+  - id: urgent
+    when:
+      event: message
+      pattern: "(?i)urgent: (?P<topic>.+)"
+      channel: telegram
+    do:
+      - notify: { channel: slack, to: ops, text: "{{sender}}: {{topic}}" }
+      - run_workflow: { workflow: triage, input: "{{content}}" }
+    cooldown_secs: 600
+  - when: { event: heartbeat_missing, source: line, after_secs: 0 }
+    do:
+      - disable_channel: { channel: line }
+  - when: { event: credits_low, below: 50 }
+    do:
+      - notify: { channel: telegram, to: admin, text: "{{user_id}} has {{remaining}}" }
+"#;
+
+    fn engine() -> (tempfile::TempDir, RulesEngine) {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("rules.yaml"), RULES).unwrap();
+        let engine = RulesEngine::new(tmp.path().join("rules.yaml"), tmp.path().join("state.json"));
+        (tmp, engine)
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = parse_rules(RULES).unwrap();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[1].id, "rule2");
+        assert!(matches!(rules[2].when, RuleTrigger::CreditsLow { below: 50 }));
+        assert!(parse_rules("").unwrap().is_empty());
+
+        let bad_regex = "rules:\n  - when: { event: message, pattern: '(' }\n    do:\n      - disable_channel: { channel: x }\n";
+        assert!(parse_rules(bad_regex).is_err());
+        let no_actions = "rules:\n  - when: { event: credits_low, below: 1 }\n    do: []\n";
+        assert!(parse_rules(no_actions).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_message_rule_fires_with_cooldown() {
+        let (_tmp, engine) = engine();
+        let host = FakeHost::default();
+
+        let other = InboundMessage::new("discord", "u1", "c1", "urgent: db down");
+        assert!(engine.observe_message(&other).is_empty());
+
+        let msg = InboundMessage::new("telegram", "u1", "c1", "URGENT: db down");
+        let fired = engine.observe_message(&msg);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].vars["topic"], "db down");
+        // Cooling down
+        assert!(engine.observe_message(&msg).is_empty());
+
+        assert_eq!(engine.run_pending(&host).await, 1);
+        assert_eq!(engine.run_pending(&host).await, 0);
+        let calls = host.calls.lock().unwrap();
+        assert_eq!(calls[0], "notify slack:ops u1: db down");
+        assert_eq!(calls[1], "workflow triage URGENT: db down");
+        let firing = &engine.firings(Some("urgent"), 10)[0];
+        assert_eq!(firing.status, FiringStatus::Done);
+        assert_eq!(firing.results[1].detail.as_deref(), Some("run abc"));
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_missing_disables_channel_once_per_outage() {
+        let (_tmp, engine) = engine();
+        let host = FakeHost::default();
+
+        assert_eq!(engine.check_heartbeats().len(), 1);
+        assert!(engine.check_heartbeats().is_empty());
+        engine.run_pending(&host).await;
+        assert!(engine.is_channel_disabled("line"));
+        assert_eq!(engine.disabled_channels(), vec!["line".to_string()]);
+
+        engine.beat("line");
+        assert_eq!(engine.check_heartbeats().len(), 1);
+        assert!(engine.set_channel_disabled("line", false));
+        assert!(!engine.is_channel_disabled("line"));
+    }
+
+    #[test]
+    fn test_credits_low_fires_on_crossing_and_upsert() {
+        let (_tmp, engine) = engine();
+        let event = |before, remaining| RuleEvent::CreditsLow {
+            user_id: Some("user:1"),
+            before,
+            remaining,
+        };
+        assert!(engine.fire(&event(Some(100), 60)).is_empty());
+        assert!(engine.fire(&event(Some(40), 30)).is_empty());
+        let fired = engine.fire(&event(Some(60), 40));
+        assert_eq!(fired.len(), 1);
+        assert!(matches!(fired[0].actions[0], RuleAction::Notify { ref text, .. } if text == "user:1 has 40"));
+
+        let rule = Rule {
+            id: String::new(),
+            description: "new".into(),
+            enabled: false,
+            when: RuleTrigger::CreditsLow { below: 10 },
+            actions: vec![RuleAction::DisableChannel { channel: "web".into() }],
+            cooldown_secs: 0,
+        };
+        assert_eq!(engine.upsert(rule).unwrap().id, "rule4");
+        assert_eq!(engine.load().unwrap().len(), 4);
+        assert!(engine.remove("rule4").unwrap());
+        assert!(!engine.remove("rule4").unwrap());
+    }
+}
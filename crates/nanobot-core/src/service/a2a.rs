@@ -7,6 +7,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tracing::{error, info, warn};
 
+use crate::util::http::{self, ClientClass};
+
 // ---------------------------------------------------------------------------
 // Agent Card
 // ---------------------------------------------------------------------------
@@ -36,7 +38,7 @@ pub struct AgentSkill {
 /// Fetch the Agent Card from `{base_url}/.well-known/agent.json`.
 pub async fn fetch_agent_card(base_url: &str) -> Result<AgentCard, String> {
     let url = format!("{}/.well-known/agent.json", base_url.trim_end_matches('/'));
-    let client = reqwest::Client::new();
+    let client = http::client_for(ClientClass::Llm);
     let resp = client
         .get(&url)
         .timeout(std::time::Duration::from_secs(10))
@@ -122,7 +124,7 @@ pub async fn send_task(
         }),
     };
 
-    let client = reqwest::Client::new();
+    let client = http::client_for(ClientClass::Llm);
     let resp = client
         .post(&url)
         .header("Content-Type", "application/json")
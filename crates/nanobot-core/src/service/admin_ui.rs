@@ -0,0 +1,238 @@
+//! Built-in admin dashboard for self-hosted gateways (`gateway.adminUi`).
+//!
+//! `/admin` serves a single page that reads the JSON endpoints below:
+//!
+//! - `GET /api/v1/admin/gateway/providers`: default model and the health of
+//!   each load-balanced provider (failures, open circuit breakers).
+//! - `GET /api/v1/admin/gateway/channels`: channels, whether their required
+//!   settings are filled in, and when each last saw a conversation.
+//! - `GET /api/v1/admin/gateway/sessions`: most recently updated sessions.
+//! - `GET /api/v1/admin/gateway/cron`: scheduled jobs and their last runs.
+//! - `GET /api/v1/admin/gateway/usage?days=14`: tool calls per day and per tool.
+//! - `GET /api/v1/admin/gateway/config`: the running config with secrets
+//!   blanked, and what `chatweb config validate` would report.
+//!
+//! The page asks for one of `gateway.apiTokens` and sends it as a Bearer
+//! token; with no tokens configured, nobody gets data. Being on the same
+//! machine doesn't count, since requests through a tunnel or a reverse proxy
+//! come from there too. The hosted admin panel is served instead when the
+//! dashboard is off.
+
+use std::sync::Arc;
+
+use axum::extract::{Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::backup::redact_secrets;
+use super::cron::CronService;
+use super::http::AppState;
+use crate::config::Config;
+
+/// Sessions listed by the sessions endpoint.
+const RECENT_SESSIONS: usize = 50;
+
+/// Days of usage when the request doesn't say.
+const USAGE_DAYS: u32 = 14;
+
+pub fn page() -> Html<&'static str> {
+    Html(include_str!("../../../../web/gateway-admin.html"))
+}
+
+/// Whether a request may read dashboard data: a Bearer token from `tokens`.
+fn authorized(tokens: &[String], bearer: Option<&str>) -> bool {
+    bearer.is_some_and(|token| tokens.iter().any(|t| t == token))
+}
+
+/// The response refusing `request`, if it may not see the dashboard.
+fn check(state: &AppState, request: &Request) -> Option<Response> {
+    if !state.config.gateway.admin_ui {
+        return Some(StatusCode::NOT_FOUND.into_response());
+    }
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let tokens = &state.config.gateway.api_tokens;
+    if authorized(tokens, bearer) {
+        None
+    } else if tokens.is_empty() {
+        let error = "The dashboard needs gateway.apiTokens; add one and sign in with it";
+        Some((StatusCode::UNAUTHORIZED, Json(json!({ "error": error }))).into_response())
+    } else {
+        Some((StatusCode::UNAUTHORIZED, Json(json!({ "error": "Admin token required" }))).into_response())
+    }
+}
+
+/// GET /api/v1/admin/gateway/providers
+pub async fn handle_providers(State(state): State<Arc<AppState>>, request: Request) -> Response {
+    if let Some(denied) = check(&state, &request) {
+        return denied;
+    }
+    let pool = state
+        .lb_raw
+        .read()
+        .ok()
+        .and_then(|lb| lb.as_ref().map(|lb| lb.provider_status()))
+        .unwrap_or_default();
+    Json(json!({
+        "default": {
+            "model": state.config.agents.defaults.model,
+            "configured": state.provider.is_some(),
+        },
+        "pool": pool,
+    }))
+    .into_response()
+}
+
+/// GET /api/v1/admin/gateway/channels
+pub async fn handle_channels(State(state): State<Arc<AppState>>, request: Request) -> Response {
+    if let Some(denied) = check(&state, &request) {
+        return denied;
+    }
+    let sessions = state.sessions.lock().await.list_sessions();
+    Json(json!({ "channels": channel_status(&state.config, &sessions) })).into_response()
+}
+
+/// Every channel with its `enabled` flag, the config problems under it and
+/// the newest `updated_at` of its sessions.
+fn channel_status(config: &Config, sessions: &[Value]) -> Vec<Value> {
+    let issues = config.validate();
+    let Ok(Value::Object(channels)) = serde_json::to_value(&config.channels) else {
+        return Vec::new();
+    };
+    channels
+        .iter()
+        .filter_map(|(name, channel)| Some((name, channel.get("enabled")?.as_bool()?)))
+        .map(|(name, enabled)| {
+            let path = format!("channels.{}.", name);
+            let problems: Vec<String> = issues
+                .iter()
+                .filter_map(|issue| Some(format!("{}: {}", issue.path.strip_prefix(&path)?, issue.message)))
+                .collect();
+            // Session keys use the channel's runtime name (`google_chat`), and
+            // the file store lists them with `_` read back as `:`
+            let session_prefix = format!("{}:", snake_case(name)).replace('_', ":");
+            let last_active = sessions
+                .iter()
+                .filter(|s| s["key"].as_str().is_some_and(|key| key.replace('_', ":").starts_with(&session_prefix)))
+                .filter_map(|s| s["updated_at"].as_str())
+                .max();
+            json!({
+                "name": name,
+                "enabled": enabled,
+                "problems": problems,
+                "last_active": last_active,
+            })
+        })
+        .collect()
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('_');
+        }
+        out.push(c.to_ascii_lowercase());
+    }
+    out
+}
+
+/// GET /api/v1/admin/gateway/sessions
+pub async fn handle_sessions(State(state): State<Arc<AppState>>, request: Request) -> Response {
+    if let Some(denied) = check(&state, &request) {
+        return denied;
+    }
+    let sessions = state.sessions.lock().await.list_sessions();
+    Json(json!({
+        "total": sessions.len(),
+        "sessions": sessions.into_iter().take(RECENT_SESSIONS).collect::<Vec<_>>(),
+    }))
+    .into_response()
+}
+
+/// GET /api/v1/admin/gateway/cron
+pub async fn handle_cron(State(state): State<Arc<AppState>>, request: Request) -> Response {
+    if let Some(denied) = check(&state, &request) {
+        return denied;
+    }
+    let cron = CronService::open(crate::config::get_data_dir().join("cron").join("jobs.json"));
+    Json(json!({ "jobs": cron.list_jobs(true) })).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    days: Option<u32>,
+}
+
+/// GET /api/v1/admin/gateway/usage
+pub async fn handle_usage(State(state): State<Arc<AppState>>, Query(q): Query<UsageQuery>, request: Request) -> Response {
+    if let Some(denied) = check(&state, &request) {
+        return denied;
+    }
+    let days = q.days.unwrap_or(USAGE_DAYS).clamp(1, 90);
+    Json(json!({
+        "days": days,
+        "daily": state.tool_stats.daily(days),
+        "tools": state.tool_stats.summary(None, days),
+    }))
+    .into_response()
+}
+
+/// GET /api/v1/admin/gateway/config
+pub async fn handle_config(State(state): State<Arc<AppState>>, request: Request) -> Response {
+    if let Some(denied) = check(&state, &request) {
+        return denied;
+    }
+    let mut config = serde_json::to_value(&state.config).unwrap_or_default();
+    redact_secrets(&mut config);
+    Json(json!({
+        "version": crate::VERSION,
+        "config": config,
+        "issues": state.config.validate(),
+    }))
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorized() {
+        // No tokens, no data, wherever the request comes from
+        assert!(!authorized(&[], None));
+        assert!(!authorized(&[], Some("")));
+
+        let tokens = vec!["s3cret".to_string()];
+        assert!(authorized(&tokens, Some("s3cret")));
+        assert!(!authorized(&tokens, Some("guess")));
+        assert!(!authorized(&tokens, None));
+    }
+
+    #[test]
+    fn test_channel_status() {
+        let mut config = Config::default();
+        config.channels.telegram.enabled = true;
+        config.channels.google_chat.enabled = true;
+        let sessions = vec![
+            json!({"key": "telegram:42", "updated_at": "2026-10-17T09:00:00+00:00"}),
+            json!({"key": "telegram:7", "updated_at": "2026-10-18T08:00:00+00:00"}),
+            json!({"key": "google:chat:spaces/a", "updated_at": "2026-10-16T12:00:00+00:00"}),
+        ];
+        let status = channel_status(&config, &sessions);
+        let telegram = status.iter().find(|c| c["name"] == "telegram").unwrap();
+        assert_eq!(telegram["enabled"], true);
+        assert_eq!(telegram["problems"], json!(["token: required when the channel is enabled"]));
+        assert_eq!(telegram["last_active"], "2026-10-18T08:00:00+00:00");
+        let google_chat = status.iter().find(|c| c["name"] == "googleChat").unwrap();
+        assert_eq!(google_chat["last_active"], "2026-10-16T12:00:00+00:00");
+        let line = status.iter().find(|c| c["name"] == "line").unwrap();
+        assert_eq!((line["enabled"].clone(), line["last_active"].clone()), (json!(false), Value::Null));
+    }
+}
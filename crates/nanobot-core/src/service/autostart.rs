@@ -0,0 +1,368 @@
+//! Run `chatweb daemon` in the background at login (`chatweb service`).
+//!
+//! Each platform uses its own service manager, without extra dependencies:
+//! a systemd user unit on Linux, a launchd agent on macOS, a Task Scheduler
+//! task on Windows (a real Windows service would need a service control
+//! dispatcher in the binary; a logon task gives the same result for a
+//! per-user daemon), and a Termux:Boot script on Android.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Unit / task name.
+pub const SERVICE_NAME: &str = "chatweb-daemon";
+
+/// launchd label.
+pub const LAUNCHD_LABEL: &str = "ai.chatweb.daemon";
+
+/// Shell Termux:Boot scripts run with.
+const TERMUX_SH: &str = "/data/data/com.termux/files/usr/bin/sh";
+
+/// Service manager used to start the daemon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceManager {
+    Systemd,
+    Launchd,
+    TaskScheduler,
+    TermuxBoot,
+}
+
+impl ServiceManager {
+    /// The manager for the platform this binary runs on.
+    pub fn current() -> Option<Self> {
+        if crate::termux::is_termux() {
+            Some(Self::TermuxBoot)
+        } else if cfg!(target_os = "linux") {
+            Some(Self::Systemd)
+        } else if cfg!(target_os = "macos") {
+            Some(Self::Launchd)
+        } else if cfg!(windows) {
+            Some(Self::TaskScheduler)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Systemd => "systemd (user)",
+            Self::Launchd => "launchd",
+            Self::TaskScheduler => "Task Scheduler",
+            Self::TermuxBoot => "Termux:Boot",
+        }
+    }
+}
+
+/// The daemon command and how it is registered.
+#[derive(Debug, Clone)]
+pub struct DaemonService {
+    pub manager: ServiceManager,
+    pub exe: PathBuf,
+    pub args: Vec<String>,
+}
+
+/// Run a service manager command; its output, or an error with stderr.
+fn run(program: &str, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new(program).args(args).output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        anyhow::bail!("{} {} failed: {}", program, args.join(" "), if stderr.is_empty() { stdout } else { stderr });
+    }
+    Ok(stdout)
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Quote an argument for a systemd `ExecStart=` or a Windows command line.
+fn quote(arg: &str) -> String {
+    if arg.is_empty() || arg.contains([' ', '\t', '"']) {
+        format!("\"{}\"", arg.replace('"', "\\\""))
+    } else {
+        arg.to_string()
+    }
+}
+
+impl DaemonService {
+    pub fn new(manager: ServiceManager, exe: PathBuf, args: Vec<String>) -> Self {
+        Self { manager, exe, args }
+    }
+
+    fn command_line(&self) -> String {
+        std::iter::once(self.exe.display().to_string())
+            .chain(self.args.iter().cloned())
+            .map(|a| quote(&a))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// File the service is defined in (none for Task Scheduler).
+    pub fn unit_path(&self) -> Option<PathBuf> {
+        let home = dirs::home_dir()?;
+        match self.manager {
+            ServiceManager::Systemd => Some(
+                home.join(".config/systemd/user")
+                    .join(format!("{}.service", SERVICE_NAME)),
+            ),
+            ServiceManager::Launchd => Some(
+                home.join("Library/LaunchAgents")
+                    .join(format!("{}.plist", LAUNCHD_LABEL)),
+            ),
+            ServiceManager::TaskScheduler => None,
+            ServiceManager::TermuxBoot => Some(home.join(".termux/boot").join(SERVICE_NAME)),
+        }
+    }
+
+    /// The unit file, plist, task command line, or boot script.
+    pub fn definition(&self) -> String {
+        match self.manager {
+            ServiceManager::Systemd => format!(
+                "[Unit]\nDescription=chatweb.ai daemon\nAfter=network-online.target\n\n\
+                 [Service]\nExecStart={}\nRestart=on-failure\nRestartSec=10\n\n\
+                 [Install]\nWantedBy=default.target\n",
+                self.command_line()
+            ),
+            ServiceManager::Launchd => {
+                let args: String = std::iter::once(self.exe.display().to_string())
+                    .chain(self.args.iter().cloned())
+                    .map(|a| format!("        <string>{}</string>\n", xml_escape(&a)))
+                    .collect();
+                let log = crate::config::get_data_dir().join("daemon.log");
+                let log = xml_escape(&log.display().to_string());
+                format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LAUNCHD_LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+{args}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <true/>
+    <key>StandardOutPath</key>
+    <string>{log}</string>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#
+                )
+            }
+            ServiceManager::TaskScheduler => self.command_line(),
+            ServiceManager::TermuxBoot => {
+                let log = crate::config::get_data_dir().join("daemon.log");
+                // The wake lock keeps Android from suspending the daemon.
+                format!(
+                    "#!{}\ntermux-wake-lock\nexec {} >> {} 2>&1\n",
+                    TERMUX_SH,
+                    self.command_line(),
+                    quote(&log.display().to_string())
+                )
+            }
+        }
+    }
+
+    fn write_unit(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.definition())?;
+        Ok(())
+    }
+
+    /// Register the daemon and start it now. Returns where it was installed.
+    pub fn install(&self) -> anyhow::Result<String> {
+        match (self.manager, self.unit_path()) {
+            (ServiceManager::Systemd, Some(path)) => {
+                self.write_unit(&path)?;
+                run("systemctl", &["--user", "daemon-reload"])?;
+                run("systemctl", &["--user", "enable", "--now", &format!("{}.service", SERVICE_NAME)])?;
+                Ok(path.display().to_string())
+            }
+            (ServiceManager::Launchd, Some(path)) => {
+                self.write_unit(&path)?;
+                let path_str = path.display().to_string();
+                // Reinstall: unload a previous version first (fails harmlessly if absent)
+                let _ = run("launchctl", &["unload", &path_str]);
+                run("launchctl", &["load", "-w", &path_str])?;
+                Ok(path_str)
+            }
+            (ServiceManager::TermuxBoot, Some(path)) => {
+                self.write_unit(&path)?;
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+                }
+                // Termux:Boot starts it on the next boot; start it now as well.
+                let _ = run("pkill", &["-f", &self.command_line()]);
+                Command::new("nohup")
+                    .args(["sh", &path.display().to_string()])
+                    .stdin(std::process::Stdio::null())
+                    .stdout(std::process::Stdio::null())
+                    .stderr(std::process::Stdio::null())
+                    .spawn()?;
+                Ok(path.display().to_string())
+            }
+            (ServiceManager::TaskScheduler, _) => {
+                let command = self.definition();
+                run(
+                    "schtasks",
+                    &["/Create", "/TN", SERVICE_NAME, "/TR", &command, "/SC", "ONLOGON", "/RL", "LIMITED", "/F"],
+                )?;
+                run("schtasks", &["/Run", "/TN", SERVICE_NAME])?;
+                Ok(format!("Task Scheduler task '{}'", SERVICE_NAME))
+            }
+            (_, None) => anyhow::bail!("home directory not found"),
+        }
+    }
+
+    /// Stop the daemon and remove its registration.
+    pub fn uninstall(&self) -> anyhow::Result<()> {
+        match (self.manager, self.unit_path()) {
+            (ServiceManager::Systemd, Some(path)) => {
+                let _ = run("systemctl", &["--user", "disable", "--now", &format!("{}.service", SERVICE_NAME)]);
+                if path.exists() {
+                    std::fs::remove_file(&path)?;
+                }
+                run("systemctl", &["--user", "daemon-reload"])?;
+            }
+            (ServiceManager::Launchd, Some(path)) => {
+                let _ = run("launchctl", &["unload", "-w", &path.display().to_string()]);
+                if path.exists() {
+                    std::fs::remove_file(&path)?;
+                }
+            }
+            (ServiceManager::TermuxBoot, Some(path)) => {
+                let _ = run("pkill", &["-f", &self.command_line()]);
+                if path.exists() {
+                    std::fs::remove_file(&path)?;
+                }
+            }
+            (ServiceManager::TaskScheduler, _) => {
+                let _ = run("schtasks", &["/End", "/TN", SERVICE_NAME]);
+                run("schtasks", &["/Delete", "/TN", SERVICE_NAME, "/F"])?;
+            }
+            (_, None) => anyhow::bail!("home directory not found"),
+        }
+        Ok(())
+    }
+
+    /// The service manager's view of the daemon.
+    pub fn status(&self) -> anyhow::Result<String> {
+        match self.manager {
+            ServiceManager::Systemd => {
+                // is-active exits non-zero for inactive units; that's an answer, not an error
+                let output = Command::new("systemctl")
+                    .args(["--user", "is-active", &format!("{}.service", SERVICE_NAME)])
+                    .output()?;
+                Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            }
+            ServiceManager::Launchd => match run("launchctl", &["list", LAUNCHD_LABEL]) {
+                Ok(_) => Ok("loaded".to_string()),
+                Err(_) => Ok("not loaded".to_string()),
+            },
+            ServiceManager::TaskScheduler => run("schtasks", &["/Query", "/TN", SERVICE_NAME, "/FO", "LIST"]),
+            ServiceManager::TermuxBoot => match run("pgrep", &["-f", &self.command_line()]) {
+                Ok(_) => Ok("running".to_string()),
+                Err(_) => Ok("not running".to_string()),
+            },
+        }
+    }
+}
+
+/// Seconds since boot, or 0 when unknown.
+pub fn system_uptime() -> u64 {
+    #[cfg(target_os = "macos")]
+    {
+        if let Ok(output) = Command::new("sysctl").arg("-n").arg("kern.boottime").output() {
+            let s = String::from_utf8_lossy(&output.stdout);
+            // Parse "{ sec = 1234567890, usec = 0 } ..."
+            if let Some(sec_start) = s.find("sec = ") {
+                let rest = &s[sec_start + 6..];
+                if let Some(comma) = rest.find(',') {
+                    if let Ok(boot_sec) = rest[..comma].trim().parse::<u64>() {
+                        let now = std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map(|d| d.as_secs())
+                            .unwrap_or(0);
+                        return now.saturating_sub(boot_sec);
+                    }
+                }
+            }
+        }
+        0
+    }
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_to_string("/proc/uptime")
+            .ok()
+            .and_then(|s| s.split_whitespace().next().map(|s| s.to_string()))
+            .and_then(|s| s.parse::<f64>().ok())
+            .map(|f| f as u64)
+            .unwrap_or(0)
+    }
+    #[cfg(windows)]
+    {
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn GetTickCount64() -> u64;
+        }
+        // SAFETY: GetTickCount64 takes no arguments and cannot fail.
+        unsafe { GetTickCount64() / 1000 }
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
+    {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(manager: ServiceManager, exe: &str) -> DaemonService {
+        let args = ["daemon", "--interval", "60"].map(String::from).to_vec();
+        DaemonService::new(manager, PathBuf::from(exe), args)
+    }
+
+    #[test]
+    fn test_definitions() {
+        let unit = service(ServiceManager::Systemd, "/home/me/.cargo/bin/chatweb").definition();
+        assert!(unit.contains("ExecStart=/home/me/.cargo/bin/chatweb daemon --interval 60\n"), "{unit}");
+        assert!(unit.contains("WantedBy=default.target"));
+
+        let plist = service(ServiceManager::Launchd, "/Applications/chat & web/chatweb").definition();
+        assert!(plist.contains("<string>/Applications/chat &amp; web/chatweb</string>"), "{plist}");
+        assert!(plist.contains("<string>--interval</string>"));
+        assert!(plist.contains(&format!("<string>{}</string>", LAUNCHD_LABEL)));
+
+        let task = service(ServiceManager::TaskScheduler, r"C:\Program Files\chatweb\chatweb.exe").definition();
+        assert_eq!(task, r#""C:\Program Files\chatweb\chatweb.exe" daemon --interval 60"#);
+
+        let boot = service(ServiceManager::TermuxBoot, "/data/data/com.termux/files/usr/bin/chatweb").definition();
+        assert!(boot.starts_with("#!/data/data/com.termux/files/usr/bin/sh\ntermux-wake-lock\n"), "{boot}");
+        assert!(boot.contains("exec /data/data/com.termux/files/usr/bin/chatweb daemon --interval 60 >> "));
+    }
+
+    #[test]
+    fn test_unit_paths() {
+        let unit = service(ServiceManager::Systemd, "chatweb").unit_path().unwrap();
+        assert!(unit.ends_with(".config/systemd/user/chatweb-daemon.service"));
+        assert!(service(ServiceManager::TaskScheduler, "chatweb").unit_path().is_none());
+        let boot = service(ServiceManager::TermuxBoot, "chatweb").unit_path().unwrap();
+        assert!(boot.ends_with(".termux/boot/chatweb-daemon"));
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "macos", windows))]
+    fn test_system_uptime() {
+        assert!(system_uptime() > 0);
+    }
+}
@@ -0,0 +1,644 @@
+//! Workspace snapshots for `chatweb backup create` / `backup restore`.
+//!
+//! A backup is a gzipped tarball whose first entry is `manifest.json`,
+//! listing every other entry with its size and SHA-256:
+//!
+//! - `workspace/…` — the agent workspace (memory, skills, bootstrap files)
+//! - `data/sessions/…`, `data/cron/…` — sessions and cron jobs from the data dir
+//! - `config.json` — API keys and tokens are blanked unless secrets are included
+//!
+//! Restores verify the whole archive before writing anything. Backups can
+//! also run on a schedule as cron jobs with payload kind [`BACKUP_KIND`].
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::config::{self, Config};
+
+/// Archive format version written to the manifest.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Cron payload kind for scheduled backups.
+pub const BACKUP_KIND: &str = "backup";
+
+const MANIFEST_NAME: &str = "manifest.json";
+const CONFIG_NAME: &str = "config.json";
+/// Data-dir subdirectories included in a backup.
+const DATA_DIRS: &[&str] = &["sessions", "cron"];
+
+/// Where the backed-up state lives on this machine.
+#[derive(Debug, Clone)]
+pub struct BackupSources {
+    pub workspace: PathBuf,
+    pub data_dir: PathBuf,
+    pub config_path: PathBuf,
+}
+
+impl BackupSources {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            workspace: config.workspace_path(),
+            data_dir: config::get_data_dir(),
+            config_path: config::get_config_path(),
+        }
+    }
+
+    /// Map an archive path to its location on disk.
+    fn target(&self, archive_path: &str) -> Option<PathBuf> {
+        let path = Path::new(archive_path);
+        if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+            return None;
+        }
+        if archive_path == CONFIG_NAME {
+            return Some(self.config_path.clone());
+        }
+        if let Ok(rest) = path.strip_prefix("workspace") {
+            return Some(self.workspace.join(rest));
+        }
+        let rest = path.strip_prefix("data").ok()?;
+        let top = rest.components().next()?.as_os_str().to_str()?;
+        DATA_DIRS.contains(&top).then(|| self.data_dir.join(rest))
+    }
+}
+
+/// One file in the archive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Archive index, stored as the first tar entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub format: u32,
+    pub created_at: String,
+    /// nanobot version that wrote the archive.
+    pub version: String,
+    pub secrets_included: bool,
+    pub files: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+}
+
+/// Backup destination or restore source: a local path or `s3://bucket/key`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    Local(PathBuf),
+    S3 { bucket: String, key: String },
+}
+
+impl Location {
+    pub fn parse(s: &str) -> Self {
+        match s.strip_prefix("s3://") {
+            Some(rest) => {
+                let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+                Location::S3 {
+                    bucket: bucket.to_string(),
+                    key: key.trim_matches('/').to_string(),
+                }
+            }
+            None => Location::Local(PathBuf::from(s)),
+        }
+    }
+}
+
+/// Options for a scheduled backup, stored as the cron job's message.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackupPlan {
+    /// Destination (`None` = [`default_dir`]).
+    pub to: Option<String>,
+    pub include_secrets: bool,
+    /// Local archives to keep in the destination directory (0 = all).
+    pub keep: usize,
+}
+
+impl BackupPlan {
+    pub fn to_message(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub fn from_message(message: &str) -> Self {
+        serde_json::from_str(message).unwrap_or_default()
+    }
+}
+
+/// Default directory for local archives (`~/.nanobot/backups`).
+pub fn default_dir() -> PathBuf {
+    config::get_data_dir().join("backups")
+}
+
+/// Timestamped archive file name.
+pub fn archive_name(now: chrono::DateTime<chrono::Utc>) -> String {
+    format!("nanobot-backup-{}.tar.gz", now.format("%Y%m%d-%H%M%S"))
+}
+
+fn is_archive_name(name: &str) -> bool {
+    name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+/// Resolve a local destination: an archive path is used as-is, anything else
+/// is treated as a directory to put a timestamped archive in.
+pub fn local_archive_path(dest: &Path) -> PathBuf {
+    let is_file = dest.file_name().and_then(|n| n.to_str()).is_some_and(is_archive_name);
+    if is_file {
+        dest.to_path_buf()
+    } else {
+        dest.join(archive_name(chrono::Utc::now()))
+    }
+}
+
+/// Write a backup of `sources` to `archive`.
+pub fn create(sources: &BackupSources, archive: &Path, include_secrets: bool) -> Result<Manifest> {
+    let mut entries: Vec<(String, Payload)> = Vec::new();
+    collect_dir(&sources.workspace, "workspace", &mut entries)?;
+    for dir in DATA_DIRS {
+        collect_dir(&sources.data_dir.join(dir), &format!("data/{dir}"), &mut entries)?;
+    }
+    if sources.config_path.exists() {
+        let raw = std::fs::read(&sources.config_path)
+            .with_context(|| format!("reading {}", sources.config_path.display()))?;
+        let bytes = if include_secrets {
+            raw
+        } else {
+            let mut value: serde_json::Value =
+                serde_json::from_slice(&raw).context("parsing config.json")?;
+            redact_secrets(&mut value);
+            serde_json::to_vec_pretty(&value)?
+        };
+        entries.push((CONFIG_NAME.to_string(), Payload::Bytes(bytes)));
+    }
+
+    let mut files = Vec::with_capacity(entries.len());
+    for (path, payload) in &entries {
+        let (size, sha256) = payload.digest()?;
+        files.push(ManifestEntry { path: path.clone(), size, sha256 });
+    }
+    let manifest = Manifest {
+        format: FORMAT_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        version: crate::VERSION.to_string(),
+        secrets_included: include_secrets,
+        files,
+    };
+
+    if let Some(parent) = archive.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = File::create(archive).with_context(|| format!("creating {}", archive.display()))?;
+    let mut tar = tar::Builder::new(GzEncoder::new(file, flate2::Compression::default()));
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    append(&mut tar, MANIFEST_NAME, manifest_json.len() as u64, manifest_json.as_slice())?;
+    for ((path, payload), entry) in entries.iter().zip(&manifest.files) {
+        match payload {
+            Payload::File(src) => append(&mut tar, path, entry.size, File::open(src)?)?,
+            Payload::Bytes(bytes) => append(&mut tar, path, entry.size, bytes.as_slice())?,
+        }
+    }
+    tar.into_inner()?.finish()?.flush()?;
+
+    info!("Backup written to {} ({} files)", archive.display(), manifest.files.len());
+    Ok(manifest)
+}
+
+/// Check an archive's format version and every file against its manifest.
+pub fn verify(archive: &Path) -> Result<Manifest> {
+    let mut tar = open(archive)?;
+    let mut entries = tar.entries()?;
+    let manifest = read_manifest(&mut entries)?;
+
+    let mut expected = manifest.files.iter();
+    for entry in entries {
+        let mut entry = entry?;
+        let path = entry_path(&entry)?;
+        let want = expected
+            .next()
+            .ok_or_else(|| anyhow!("unexpected file in archive: {path}"))?;
+        if want.path != path {
+            bail!("archive order mismatch: expected {}, found {path}", want.path);
+        }
+        let mut hasher = Sha256::new();
+        let size = std::io::copy(&mut entry, &mut hasher)?;
+        let sha256 = format!("{:x}", hasher.finalize());
+        if size != want.size || sha256 != want.sha256 {
+            bail!("checksum mismatch for {path}");
+        }
+    }
+    if let Some(missing) = expected.next() {
+        bail!("archive is truncated: {} is missing", missing.path);
+    }
+    Ok(manifest)
+}
+
+/// Verify `archive`, then write its files back to `sources`. Files not in the
+/// archive are left alone. When the archive was made without secrets, the
+/// blanked keys are filled from the current config.
+pub fn restore(archive: &Path, sources: &BackupSources) -> Result<Manifest> {
+    let manifest = verify(archive)?;
+    for file in &manifest.files {
+        if sources.target(&file.path).is_none() {
+            bail!("refusing to restore unexpected path: {}", file.path);
+        }
+    }
+
+    let mut tar = open(archive)?;
+    let mut entries = tar.entries()?;
+    read_manifest(&mut entries)?;
+    for entry in entries {
+        let mut entry = entry?;
+        let path = entry_path(&entry)?;
+        let target = sources
+            .target(&path)
+            .ok_or_else(|| anyhow!("unexpected path: {path}"))?;
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+
+        if path == CONFIG_NAME && !manifest.secrets_included {
+            let mut restored: serde_json::Value = serde_json::from_slice(&bytes)?;
+            if let Some(current) = std::fs::read(&target)
+                .ok()
+                .and_then(|raw| serde_json::from_slice::<serde_json::Value>(&raw).ok())
+            {
+                merge_secrets(&mut restored, &current);
+            }
+            bytes = serde_json::to_vec_pretty(&restored)?;
+        }
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&target, bytes).with_context(|| format!("writing {}", target.display()))?;
+    }
+
+    info!("Restored {} files from {}", manifest.files.len(), archive.display());
+    Ok(manifest)
+}
+
+/// Delete all but the newest `keep` archives in `dir`. Returns the removed paths.
+pub fn prune(dir: &Path, keep: usize) -> Vec<PathBuf> {
+    let Ok(read) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut archives: Vec<PathBuf> = read
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("nanobot-backup-") && is_archive_name(n))
+        })
+        .collect();
+    // Timestamped names sort chronologically
+    archives.sort();
+    let excess = archives.len().saturating_sub(keep);
+    archives
+        .into_iter()
+        .take(excess)
+        .filter(|p| std::fs::remove_file(p).is_ok())
+        .collect()
+}
+
+/// Run a scheduled backup: archive locally, upload when the plan targets S3,
+/// and prune old local archives. Returns where the backup went.
+pub async fn run_plan(sources: BackupSources, plan: &BackupPlan) -> Result<String> {
+    let location = plan.to.as_deref().map(Location::parse);
+    let dir = match &location {
+        Some(Location::Local(path)) => path.clone(),
+        _ => default_dir(),
+    };
+    let archive = local_archive_path(&dir);
+    let include_secrets = plan.include_secrets;
+    let path = archive.clone();
+    tokio::task::spawn_blocking(move || create(&sources, &path, include_secrets)).await??;
+
+    let dest = match location {
+        Some(Location::S3 { bucket, key }) => upload(&archive, &bucket, &key).await?,
+        _ => archive.display().to_string(),
+    };
+    if plan.keep > 0 {
+        if let Some(parent) = archive.parent() {
+            prune(parent, plan.keep);
+        }
+    }
+    Ok(dest)
+}
+
+/// Upload `archive` to S3. A `key` that isn't an archive name is used as a
+/// prefix. Returns the `s3://` URL.
+#[cfg(feature = "dynamodb-backend")]
+pub async fn upload(archive: &Path, bucket: &str, key: &str) -> Result<String> {
+    let name = archive.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let key = if is_archive_name(key) {
+        key.to_string()
+    } else if key.is_empty() {
+        name.to_string()
+    } else {
+        format!("{key}/{name}")
+    };
+    let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+    let s3 = aws_sdk_s3::Client::new(&config);
+    let body = aws_sdk_s3::primitives::ByteStream::from_path(archive).await?;
+    s3.put_object()
+        .bucket(bucket)
+        .key(&key)
+        .body(body)
+        .content_type("application/gzip")
+        .send()
+        .await
+        .map_err(|e| anyhow!("S3 upload failed: {e}"))?;
+    Ok(format!("s3://{bucket}/{key}"))
+}
+
+/// Download `s3://bucket/key` to `dest`.
+#[cfg(feature = "dynamodb-backend")]
+pub async fn download(bucket: &str, key: &str, dest: &Path) -> Result<()> {
+    let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+    let s3 = aws_sdk_s3::Client::new(&config);
+    let object = s3
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|e| anyhow!("S3 download failed: {e}"))?;
+    let bytes = object.body.collect().await?.into_bytes();
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(dest, bytes)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "dynamodb-backend"))]
+pub async fn upload(_archive: &Path, _bucket: &str, _key: &str) -> Result<String> {
+    bail!("S3 backups require the dynamodb-backend feature")
+}
+
+#[cfg(not(feature = "dynamodb-backend"))]
+pub async fn download(_bucket: &str, _key: &str, _dest: &Path) -> Result<()> {
+    bail!("S3 backups require the dynamodb-backend feature")
+}
+
+// ---------------------------------------------------------------------------
+// Internals
+// ---------------------------------------------------------------------------
+
+enum Payload {
+    File(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+impl Payload {
+    fn digest(&self) -> Result<(u64, String)> {
+        let mut hasher = Sha256::new();
+        let size = match self {
+            Payload::File(path) => std::io::copy(&mut File::open(path)?, &mut hasher)?,
+            Payload::Bytes(bytes) => {
+                hasher.update(bytes);
+                bytes.len() as u64
+            }
+        };
+        Ok((size, format!("{:x}", hasher.finalize())))
+    }
+}
+
+fn collect_dir(dir: &Path, prefix: &str, out: &mut Vec<(String, Payload)>) -> Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    let mut files: Vec<(String, Payload)> = Vec::new();
+    for entry in walkdir::WalkDir::new(dir).follow_links(false) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel = entry.path().strip_prefix(dir)?;
+        let rel: Vec<&str> = rel.components().filter_map(|c| c.as_os_str().to_str()).collect();
+        files.push((format!("{prefix}/{}", rel.join("/")), Payload::File(entry.into_path())));
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    out.extend(files);
+    Ok(())
+}
+
+fn append<W: Write, R: Read>(tar: &mut tar::Builder<W>, path: &str, size: u64, data: R) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o600);
+    header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    tar.append_data(&mut header, path, data.take(size))?;
+    Ok(())
+}
+
+fn open(archive: &Path) -> Result<tar::Archive<GzDecoder<File>>> {
+    let file = File::open(archive).with_context(|| format!("opening {}", archive.display()))?;
+    Ok(tar::Archive::new(GzDecoder::new(file)))
+}
+
+fn entry_path<R: Read>(entry: &tar::Entry<'_, R>) -> Result<String> {
+    Ok(entry.path()?.to_string_lossy().replace('\\', "/"))
+}
+
+fn read_manifest<R: Read>(entries: &mut tar::Entries<'_, R>) -> Result<Manifest> {
+    let mut first = entries
+        .next()
+        .ok_or_else(|| anyhow!("empty archive"))??;
+    if entry_path(&first)? != MANIFEST_NAME {
+        bail!("not a nanobot backup (missing {MANIFEST_NAME})");
+    }
+    let mut raw = Vec::new();
+    first.read_to_end(&mut raw)?;
+    let manifest: Manifest = serde_json::from_slice(&raw).context("parsing manifest")?;
+    if manifest.format > FORMAT_VERSION {
+        bail!(
+            "backup format v{} is newer than this build supports (v{FORMAT_VERSION})",
+            manifest.format
+        );
+    }
+    Ok(manifest)
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    ["key", "token", "secret", "password"].iter().any(|s| key.contains(s))
+}
+
+/// Blank every non-empty string under a secret-looking key.
+pub(crate) fn redact_secrets(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                match v {
+                    serde_json::Value::String(s) if is_secret_key(k) => s.clear(),
+                    _ => redact_secrets(v),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
+
+/// Fill secrets blanked by [`redact_secrets`] from `current`.
+pub(crate) fn merge_secrets(restored: &mut serde_json::Value, current: &serde_json::Value) {
+    let (serde_json::Value::Object(map), serde_json::Value::Object(cur)) = (restored, current) else {
+        return;
+    };
+    for (k, v) in map.iter_mut() {
+        let Some(c) = cur.get(k) else { continue };
+        match v {
+            serde_json::Value::String(s) if s.is_empty() && is_secret_key(k) => {
+                if let Some(existing) = c.as_str() {
+                    *s = existing.to_string();
+                }
+            }
+            _ => merge_secrets(v, c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources(root: &Path) -> BackupSources {
+        BackupSources {
+            workspace: root.join("workspace"),
+            data_dir: root.join("data"),
+            config_path: root.join("config.json"),
+        }
+    }
+
+    fn seed(src: &BackupSources) {
+        std::fs::create_dir_all(src.workspace.join("memory")).unwrap();
+        std::fs::write(src.workspace.join("memory/MEMORY.md"), "likes cats").unwrap();
+        std::fs::write(src.workspace.join("SOUL.md"), "be kind").unwrap();
+        std::fs::create_dir_all(src.data_dir.join("sessions")).unwrap();
+        std::fs::write(src.data_dir.join("sessions/cli_default.jsonl"), "{}\n").unwrap();
+        std::fs::create_dir_all(src.data_dir.join("cron")).unwrap();
+        std::fs::write(src.data_dir.join("cron/jobs.json"), "{\"jobs\":[]}").unwrap();
+        std::fs::create_dir_all(src.data_dir.join("backups")).unwrap();
+        std::fs::write(src.data_dir.join("backups/old.tar.gz"), "x").unwrap();
+        std::fs::write(
+            &src.config_path,
+            r#"{"providers":{"openai":{"apiKey":"sk-live"}},"agents":{"defaults":{"maxTokens":8192}}}"#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_create_verify_restore_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = sources(&tmp.path().join("src"));
+        seed(&src);
+        let archive = tmp.path().join("out/b.tar.gz");
+
+        let manifest = create(&src, &archive, false).unwrap();
+        assert!(!manifest.secrets_included);
+        let paths: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert!(paths.contains(&"workspace/memory/MEMORY.md"));
+        assert!(paths.contains(&"data/sessions/cli_default.jsonl"));
+        assert!(paths.contains(&"data/cron/jobs.json"));
+        assert!(paths.contains(&"config.json"));
+        assert!(!paths.iter().any(|p| p.contains("backups")));
+        assert_eq!(verify(&archive).unwrap().files, manifest.files);
+
+        // Restore elsewhere: secrets come from the existing config there
+        let dst = sources(&tmp.path().join("dst"));
+        std::fs::create_dir_all(tmp.path().join("dst")).unwrap();
+        std::fs::write(&dst.config_path, r#"{"providers":{"openai":{"apiKey":"sk-other"}}}"#).unwrap();
+        restore(&archive, &dst).unwrap();
+        assert_eq!(std::fs::read_to_string(dst.workspace.join("memory/MEMORY.md")).unwrap(), "likes cats");
+        assert_eq!(std::fs::read_to_string(dst.data_dir.join("cron/jobs.json")).unwrap(), "{\"jobs\":[]}");
+        let config: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&dst.config_path).unwrap()).unwrap();
+        assert_eq!(config["providers"]["openai"]["apiKey"], "sk-other");
+        assert_eq!(config["agents"]["defaults"]["maxTokens"], 8192);
+    }
+
+    #[test]
+    fn test_verify_detects_tampering() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = sources(tmp.path());
+        seed(&src);
+        let archive = tmp.path().join("b.tar.gz");
+        let mut manifest = create(&src, &archive, true).unwrap();
+
+        // Rewrite the archive with a manifest whose checksum no longer matches
+        manifest.files[0].sha256 = "0".repeat(64);
+        let file = File::create(&archive).unwrap();
+        let mut tar = tar::Builder::new(GzEncoder::new(file, flate2::Compression::default()));
+        let json = serde_json::to_vec(&manifest).unwrap();
+        append(&mut tar, MANIFEST_NAME, json.len() as u64, json.as_slice()).unwrap();
+        for f in &manifest.files {
+            let disk = src.target(&f.path).unwrap();
+            append(&mut tar, &f.path, f.size, File::open(disk).unwrap()).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let err = verify(&archive).unwrap_err().to_string();
+        assert!(err.contains("checksum mismatch"), "{err}");
+        assert!(restore(&archive, &sources(&tmp.path().join("dst"))).is_err());
+        assert!(!tmp.path().join("dst").exists());
+    }
+
+    #[test]
+    fn test_redact_secrets() {
+        let mut v = serde_json::json!({
+            "channels": {"telegram": {"token": "123:abc", "allowFrom": ["me"]}},
+            "providers": {"anthropic": {"apiKey": "sk-ant", "apiBase": null}},
+            "agents": {"prompt": {"sectionMaxTokens": {"memory": 500}}},
+        });
+        redact_secrets(&mut v);
+        assert_eq!(v["channels"]["telegram"]["token"], "");
+        assert_eq!(v["channels"]["telegram"]["allowFrom"][0], "me");
+        assert_eq!(v["providers"]["anthropic"]["apiKey"], "");
+        assert_eq!(v["agents"]["prompt"]["sectionMaxTokens"]["memory"], 500);
+    }
+
+    #[test]
+    fn test_target_and_location() {
+        let src = sources(Path::new("/r"));
+        assert_eq!(src.target("workspace/a/b.md"), Some(PathBuf::from("/r/workspace/a/b.md")));
+        assert_eq!(src.target("data/cron/jobs.json"), Some(PathBuf::from("/r/data/cron/jobs.json")));
+        assert_eq!(src.target("data/backups/x.tar.gz"), None);
+        assert_eq!(src.target("workspace/../etc/passwd"), None);
+        assert_eq!(src.target("/etc/passwd"), None);
+
+        assert_eq!(
+            Location::parse("s3://bucket/nightly/"),
+            Location::S3 { bucket: "bucket".into(), key: "nightly".into() }
+        );
+        assert_eq!(Location::parse("./b"), Location::Local(PathBuf::from("./b")));
+        assert_eq!(local_archive_path(Path::new("/tmp/x.tar.gz")), PathBuf::from("/tmp/x.tar.gz"));
+        assert!(local_archive_path(Path::new("/tmp/dir")).starts_with("/tmp/dir"));
+    }
+
+    #[test]
+    fn test_prune_keeps_newest() {
+        let tmp = tempfile::tempdir().unwrap();
+        for ts in ["20260101-000000", "20260102-000000", "20260103-000000"] {
+            std::fs::write(tmp.path().join(format!("nanobot-backup-{ts}.tar.gz")), "x").unwrap();
+        }
+        std::fs::write(tmp.path().join("notes.txt"), "x").unwrap();
+        let removed = prune(tmp.path(), 2);
+        assert_eq!(removed.len(), 1);
+        assert!(removed[0].ends_with("nanobot-backup-20260101-000000.tar.gz"));
+        assert!(tmp.path().join("notes.txt").exists());
+    }
+}
@@ -0,0 +1,330 @@
+//! Per-user concurrency for the chat API.
+//!
+//! A user may have `gateway.concurrency.maxParallel` chat requests running
+//! at once. Further requests wait in a queue of at most `maxQueued` and give
+//! up after `queueTimeoutSecs`; beyond that they are refused. Waiting
+//! requests are served round-robin across the channels they came from, so
+//! a burst from one channel (a script on the API, say) doesn't hold up the
+//! same user's messages from LINE. Streaming clients are told their queue
+//! position as it changes.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use tokio::sync::{oneshot, watch};
+
+use crate::config::ConcurrencyConfig;
+
+pub struct ChatQueue {
+    config: ConcurrencyConfig,
+    users: Mutex<HashMap<String, UserQueue>>,
+    next_id: AtomicU64,
+}
+
+#[derive(Default)]
+struct UserQueue {
+    active: u32,
+    /// Limit of the user's plan, as of the latest request.
+    limit: u32,
+    /// Waiting requests per channel, oldest first.
+    waiting: BTreeMap<String, VecDeque<Waiter>>,
+    /// Channel served last; the next one after it goes first.
+    last_served: Option<String>,
+}
+
+struct Waiter {
+    id: u64,
+    grant: oneshot::Sender<Permit>,
+    position: watch::Sender<usize>,
+}
+
+/// Outcome of [`ChatQueue::enqueue`].
+pub enum Ticket {
+    /// Run now.
+    Ready(Permit),
+    /// Wait with [`Queued::wait`].
+    Queued(Queued),
+    /// The user's queue is full.
+    Full,
+}
+
+/// A running request's slot; dropping it lets the next request in.
+pub struct Permit {
+    queue: Arc<ChatQueue>,
+    user: String,
+    armed: bool,
+}
+
+/// A request waiting for a slot; dropping it leaves the queue.
+pub struct Queued {
+    queue: Arc<ChatQueue>,
+    user: String,
+    id: u64,
+    grant: oneshot::Receiver<Permit>,
+    position: watch::Receiver<usize>,
+}
+
+impl ChatQueue {
+    pub fn new(config: ConcurrencyConfig) -> Arc<Self> {
+        Arc::new(Self {
+            config,
+            users: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    /// Parallel requests allowed for a user on `plan`.
+    pub fn limit_for(&self, plan: Option<&str>) -> u32 {
+        match plan {
+            Some("starter" | "pro" | "business") => self.config.paid_max_parallel,
+            _ => self.config.max_parallel,
+        }
+    }
+
+    pub fn queue_timeout(&self) -> Duration {
+        Duration::from_secs(self.config.queue_timeout_secs)
+    }
+
+    /// Ask for a slot for `user`'s request from `channel`.
+    pub fn enqueue(self: &Arc<Self>, user: &str, channel: &str, limit: u32) -> Ticket {
+        let mut users = self.lock();
+        let queue = users.entry(user.to_string()).or_default();
+        queue.limit = limit.max(1);
+        let waiting = queue.waiting_len();
+        if queue.active < queue.limit && waiting == 0 {
+            queue.active += 1;
+            return Ticket::Ready(Permit { queue: self.clone(), user: user.to_string(), armed: true });
+        }
+        if waiting >= self.config.max_queued as usize {
+            return Ticket::Full;
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (grant_tx, grant_rx) = oneshot::channel();
+        let (position_tx, position_rx) = watch::channel(waiting + 1);
+        queue
+            .waiting
+            .entry(channel.to_string())
+            .or_default()
+            .push_back(Waiter { id, grant: grant_tx, position: position_tx });
+        queue.update_positions();
+        Ticket::Queued(Queued {
+            queue: self.clone(),
+            user: user.to_string(),
+            id,
+            grant: grant_rx,
+            position: position_rx,
+        })
+    }
+
+    /// Requests of `user` running and waiting.
+    pub fn load(&self, user: &str) -> (u32, usize) {
+        self.lock()
+            .get(user)
+            .map_or((0, 0), |queue| (queue.active, queue.waiting_len()))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, UserQueue>> {
+        self.users.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// A slot of `user` came free: hand it to the next waiter.
+    fn release(self: &Arc<Self>, user: &str) {
+        let mut users = self.lock();
+        let Some(queue) = users.get_mut(user) else { return };
+        queue.active = queue.active.saturating_sub(1);
+        while queue.active < queue.limit {
+            let Some(waiter) = queue.pop_next() else { break };
+            queue.active += 1;
+            let permit = Permit { queue: self.clone(), user: user.to_string(), armed: true };
+            if let Err(mut permit) = waiter.grant.send(permit) {
+                // The waiter gave up in the meantime
+                permit.armed = false;
+                queue.active -= 1;
+            }
+        }
+        queue.update_positions();
+        if queue.active == 0 && queue.waiting.is_empty() {
+            users.remove(user);
+        }
+    }
+
+    fn cancel(&self, user: &str, id: u64) {
+        let mut users = self.lock();
+        let Some(queue) = users.get_mut(user) else { return };
+        for waiters in queue.waiting.values_mut() {
+            waiters.retain(|w| w.id != id);
+        }
+        queue.waiting.retain(|_, waiters| !waiters.is_empty());
+        queue.update_positions();
+        if queue.active == 0 && queue.waiting.is_empty() {
+            users.remove(user);
+        }
+    }
+}
+
+impl UserQueue {
+    fn waiting_len(&self) -> usize {
+        self.waiting.values().map(VecDeque::len).sum()
+    }
+
+    /// Channels in serving order: the one after `last_served` first.
+    fn channels(&self) -> Vec<String> {
+        let mut channels: Vec<String> = self.waiting.keys().cloned().collect();
+        let start = self
+            .last_served
+            .as_ref()
+            .and_then(|last| channels.iter().position(|c| c > last))
+            .unwrap_or(0);
+        channels.rotate_left(start);
+        channels
+    }
+
+    fn pop_next(&mut self) -> Option<Waiter> {
+        let channel = self.channels().into_iter().next()?;
+        let waiters = self.waiting.get_mut(&channel)?;
+        let waiter = waiters.pop_front();
+        if waiters.is_empty() {
+            self.waiting.remove(&channel);
+        }
+        self.last_served = Some(channel);
+        waiter
+    }
+
+    /// Tell every waiter its 1-based place in round-robin order.
+    fn update_positions(&self) {
+        let channels = self.channels();
+        let mut position = 0;
+        for round in 0.. {
+            let mut served = false;
+            for channel in &channels {
+                if let Some(waiter) = self.waiting.get(channel).and_then(|w| w.get(round)) {
+                    position += 1;
+                    waiter.position.send_if_modified(|p| std::mem::replace(p, position) != position);
+                    served = true;
+                }
+            }
+            if !served {
+                break;
+            }
+        }
+    }
+}
+
+impl Queued {
+    /// Current 1-based queue position.
+    pub fn position(&self) -> usize {
+        *self.position.borrow()
+    }
+
+    /// Wait for a slot, calling `on_position` whenever the position
+    /// changes. `None` when the queue timeout passes first.
+    pub async fn wait(mut self, timeout: Duration, mut on_position: impl FnMut(usize)) -> Option<Permit> {
+        let deadline = tokio::time::sleep(timeout);
+        tokio::pin!(deadline);
+        loop {
+            tokio::select! {
+                permit = &mut self.grant => return permit.ok(),
+                changed = self.position.changed() => {
+                    if changed.is_err() {
+                        // Dropped from the queue; the grant (if any) is already sent
+                        return (&mut self.grant).await.ok();
+                    }
+                    on_position(*self.position.borrow_and_update());
+                }
+                _ = &mut deadline => return None,
+            }
+        }
+    }
+}
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        self.queue.cancel(&self.user, self.id);
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if self.armed {
+            self.queue.release(&self.user);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(max_parallel: u32, max_queued: u32) -> Arc<ChatQueue> {
+        ChatQueue::new(ConcurrencyConfig { max_parallel, max_queued, ..Default::default() })
+    }
+
+    fn ready(ticket: Ticket) -> Permit {
+        match ticket {
+            Ticket::Ready(permit) => permit,
+            _ => panic!("expected a free slot"),
+        }
+    }
+
+    fn queued(ticket: Ticket) -> Queued {
+        match ticket {
+            Ticket::Queued(queued) => queued,
+            _ => panic!("expected to wait"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queue_and_release() {
+        let q = queue(1, 2);
+        let first = ready(q.enqueue("u1", "web", 1));
+        let second = queued(q.enqueue("u1", "web", 1));
+        let third = queued(q.enqueue("u1", "web", 1));
+        assert!(matches!(q.enqueue("u1", "web", 1), Ticket::Full));
+        // Other users are not affected
+        let _other = ready(q.enqueue("u2", "web", 1));
+        assert_eq!((second.position(), third.position()), (1, 2));
+        assert_eq!(q.load("u1"), (1, 2));
+
+        let mut positions = Vec::new();
+        let waiting = tokio::spawn(async move { third.wait(Duration::from_secs(5), |p| positions.push(p)).await.map(|_| positions) });
+        drop(first);
+        let second = second.wait(Duration::from_secs(5), |_| {}).await.unwrap();
+        tokio::task::yield_now().await;
+        drop(second);
+        assert_eq!(waiting.await.unwrap(), Some(vec![1]));
+        assert_eq!(q.load("u1"), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_between_channels() {
+        let q = queue(1, 10);
+        let running = ready(q.enqueue("u1", "api", 1));
+        let api: Vec<Queued> = (0..3).map(|_| queued(q.enqueue("u1", "api", 1))).collect();
+        let line = queued(q.enqueue("u1", "line", 1));
+        // The LINE message goes ahead of the API burst after one API request
+        assert_eq!(api.iter().map(Queued::position).collect::<Vec<_>>(), vec![1, 3, 4]);
+        assert_eq!(line.position(), 2);
+
+        let mut api = api.into_iter();
+        let first = api.next().unwrap();
+        drop(running);
+        let permit = first.wait(Duration::from_secs(1), |_| {}).await.unwrap();
+        drop(permit);
+        assert!(line.wait(Duration::from_secs(1), |_| {}).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_and_cancel() {
+        let q = queue(1, 5);
+        let _running = ready(q.enqueue("u1", "web", 1));
+        let first = queued(q.enqueue("u1", "web", 1));
+        let second = queued(q.enqueue("u1", "telegram", 1));
+        assert!(first.wait(Duration::from_millis(10), |_| {}).await.is_none());
+        assert_eq!(second.position(), 1);
+        drop(second);
+        assert_eq!(q.load("u1"), (1, 0));
+    }
+}
@@ -5,6 +5,7 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 
 use crate::provider::LlmProvider;
+use crate::session::locale::Locale;
 use crate::session::store::SessionStore;
 use crate::service::integrations::ToolRegistry;
 
@@ -27,6 +28,8 @@ pub enum SlashCommand<'a> {
     Improve(&'a str),
     /// `/keys` or `/keys <subcommand>` — admin-only API key management
     Keys(Option<&'a str>),
+    /// Session-level built-in from the shared registry (`/reset`, `/model`, `/pin`, ...)
+    Builtin(crate::command::Invocation<'a>),
 }
 
 /// Result of executing a slash command.
@@ -44,6 +47,8 @@ pub struct CommandContext<'a> {
     pub user_id: Option<&'a str>,
     pub conv_id: Option<&'a str>,
     pub sessions: &'a Mutex<Box<dyn SessionStore>>,
+    /// Default model for this front-end (shown by `/model`, `/usage`).
+    pub model: &'a str,
     pub provider: Option<&'a Arc<dyn LlmProvider>>,
     pub tool_registry: Option<&'a ToolRegistry>,
     #[cfg(feature = "dynamodb-backend")]
@@ -57,49 +62,30 @@ pub struct CommandContext<'a> {
 // ---------------------------------------------------------------------------
 
 /// Parse user text into a slash command, or `None` if not a command.
+///
+/// Command names come from the shared registry in `crate::command`; CLI-only
+/// entries are ignored here.
 pub fn parse_command(text: &str) -> Option<SlashCommand<'_>> {
     let trimmed = text.trim();
 
-    // /help
-    if trimmed.eq_ignore_ascii_case("/help") {
-        return Some(SlashCommand::Help);
-    }
-
-    // /status
-    if trimmed.eq_ignore_ascii_case("/status") {
-        return Some(SlashCommand::Status);
-    }
-
-    // /share
-    if trimmed.eq_ignore_ascii_case("/share") {
-        return Some(SlashCommand::Share);
-    }
-
-    // /improve <description>
-    if let Some(rest) = strip_prefix_ci(trimmed, "/improve ") {
-        let desc = rest.trim();
-        if !desc.is_empty() {
-            return Some(SlashCommand::Improve(desc));
-        }
-    }
-    if trimmed.eq_ignore_ascii_case("/improve") {
-        // bare /improve with no description — still parse it so we can reply with usage hint
-        return Some(SlashCommand::Improve(""));
-    }
-
-    // /keys [subcommand]
-    if trimmed.eq_ignore_ascii_case("/keys") {
-        return Some(SlashCommand::Keys(None));
-    }
-    if let Some(rest) = strip_prefix_ci(trimmed, "/keys ") {
-        let args = rest.trim();
-        if !args.is_empty() {
-            return Some(SlashCommand::Keys(Some(args)));
+    if let Some(inv) = crate::command::parse(trimmed) {
+        let args = if inv.args.is_empty() { None } else { Some(inv.args) };
+        match inv.name() {
+            "help" => return Some(SlashCommand::Help),
+            "status" => return Some(SlashCommand::Status),
+            "share" => return Some(SlashCommand::Share),
+            // bare /improve still parses so we can reply with usage hint
+            "improve" => return Some(SlashCommand::Improve(inv.args)),
+            "keys" => return Some(SlashCommand::Keys(args)),
+            _ => {
+                if let crate::command::Handler::Session(_) = inv.spec.handler {
+                    return Some(SlashCommand::Builtin(inv));
+                }
+            }
         }
-        return Some(SlashCommand::Keys(None));
     }
 
-    // /link [CODE] — must come last because of the embedded-code search
+    // /link [CODE] — also matches codes embedded in pasted text
     if let Some(link) = parse_link(trimmed) {
         return Some(link);
     }
@@ -134,20 +120,6 @@ fn parse_link(trimmed: &str) -> Option<SlashCommand<'_>> {
     None
 }
 
-/// Case-insensitive prefix strip (returns the remainder with original casing).
-/// Safe for multibyte strings — uses `.get()` to avoid panics on non-char-boundary indices.
-fn strip_prefix_ci<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
-    let plen = prefix.len();
-    if text.len() >= plen {
-        if let Some(slice) = text.get(..plen) {
-            if slice.eq_ignore_ascii_case(prefix) {
-                return Some(&text[plen..]);
-            }
-        }
-    }
-    None
-}
-
 // ---------------------------------------------------------------------------
 // Execution
 // ---------------------------------------------------------------------------
@@ -158,12 +130,13 @@ pub async fn execute_command(
     ctx: &CommandContext<'_>,
 ) -> CommandResult {
     match cmd {
-        SlashCommand::Help => CommandResult::Reply(help_text()),
+        SlashCommand::Help => execute_help(ctx).await,
         SlashCommand::Status => execute_status(ctx).await,
         SlashCommand::Share => execute_share(ctx).await,
         SlashCommand::Link(code) => execute_link(code, ctx).await,
         SlashCommand::Improve(desc) => execute_improve(desc, ctx).await,
         SlashCommand::Keys(args) => execute_keys(args, ctx).await,
+        SlashCommand::Builtin(inv) => execute_builtin(inv, ctx).await,
     }
 }
 
@@ -171,25 +144,104 @@ pub async fn execute_command(
 // /help
 // ---------------------------------------------------------------------------
 
-fn help_text() -> String {
-    "\
-📋 利用可能なコマンド:\n\
-\n\
-/help — このヘルプを表示\n\
-/status — システム状態を表示\n\
-/share — 会話の共有リンクを生成\n\
-/link — チャネル連携コードを生成\n\
-/link CODE — 別チャネルとリンク\n\
-/improve <説明> — 改善PRを作成（管理者のみ）\n\
-/keys — APIキー管理（管理者のみ）"
-        .to_string()
+async fn execute_help(ctx: &CommandContext<'_>) -> CommandResult {
+    let locale = {
+        let mut sessions = ctx.sessions.lock().await;
+        sessions.get_or_create(ctx.session_key).locale().unwrap_or_default()
+    };
+    CommandResult::Reply(help_text(channel_id(ctx.channel_key), locale))
+}
+
+fn help_text(channel: &str, locale: Locale) -> String {
+    crate::command::help_text(channel, locale)
+}
+
+/// Map a channel key (`line:U123`, `tg:456|bot`, `webchat:abc`) to a registry channel id.
+pub fn channel_id(channel_key: &str) -> &'static str {
+    match channel_key.split(':').next().unwrap_or("") {
+        "cli" => "cli",
+        "line" => "line",
+        "tg" => "telegram",
+        "fb" => "facebook",
+        "discord" => "discord",
+        "slack" => "slack",
+        "wa" | "whatsapp" => "whatsapp",
+        _ => "web",
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Session built-ins (/reset, /model, /agent, /usage, /pin, ...)
+// ---------------------------------------------------------------------------
+
+async fn execute_builtin(inv: crate::command::Invocation<'_>, ctx: &CommandContext<'_>) -> CommandResult {
+    let agents = super::http::agent_ids();
+    let env = crate::command::CommandEnv {
+        channel: channel_id(ctx.channel_key),
+        model: ctx.model,
+        agent: None,
+        agents: &agents,
+    };
+    let mut sessions = ctx.sessions.lock().await;
+    let session = sessions.get_or_create(ctx.session_key);
+    let reply = inv.execute(session, &env);
+    // /timezone and /lang are mirrored onto the user profile so linked channels share them
+    let profile_setting = match inv.name() {
+        "timezone" => Some(("timezone", session.timezone().map(str::to_string))),
+        "lang" => Some((
+            "locale",
+            session.locale().filter(|_| session.locale_is_manual()).map(|l| l.code().to_string()),
+        )),
+        _ => None,
+    };
+    sessions.save_by_key(ctx.session_key);
+    drop(sessions);
+    match reply {
+        Some(text) => {
+            if let Some((attr, value)) = profile_setting {
+                save_profile_setting(ctx, attr, value.as_deref()).await;
+            }
+            CommandResult::Reply(text)
+        }
+        None => CommandResult::NotACommand,
+    }
+}
+
+/// Store a session setting on the user's profile (`None` removes it).
+async fn save_profile_setting(ctx: &CommandContext<'_>, attr: &str, value: Option<&str>) {
+    #[cfg(feature = "dynamodb-backend")]
+    {
+        let (Some(dynamo), Some(table), Some(user_id)) = (ctx.dynamo, ctx.config_table, ctx.user_id) else {
+            return;
+        };
+        let update = dynamo
+            .update_item()
+            .table_name(table)
+            .key("pk", AttributeValue::S(format!("USER#{user_id}")))
+            .key("sk", AttributeValue::S("PROFILE".to_string()))
+            .expression_attribute_names("#a", attr);
+        let update = match value {
+            Some(v) => update
+                .update_expression("SET #a = :v")
+                .expression_attribute_values(":v", AttributeValue::S(v.to_string())),
+            None => update.update_expression("REMOVE #a"),
+        };
+        if let Err(e) = update.send().await {
+            tracing::warn!("Failed to save profile {} for {}: {}", attr, user_id, e);
+        }
+    }
+
+    #[cfg(not(feature = "dynamodb-backend"))]
+    {
+        let _ = (ctx, attr, value);
+    }
 }
 
 // ---------------------------------------------------------------------------
 // /status
 // ---------------------------------------------------------------------------
 
-async fn execute_status(_ctx: &CommandContext<'_>) -> CommandResult {
+async fn execute_status(ctx: &CommandContext<'_>) -> CommandResult {
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(5))
         .build()
@@ -265,6 +317,20 @@ async fn execute_status(_ctx: &CommandContext<'_>) -> CommandResult {
 
     let _ = openai_ok; // suppress unused warning
 
+    // Session-scoped /model and /agent overrides
+    {
+        let mut sessions = ctx.sessions.lock().await;
+        let session = sessions.get_or_create(ctx.session_key);
+        lines.push(format!(
+            "  モデル: {}",
+            session.model_override().map(|m| format!("{m}（/model で固定中）")).unwrap_or_else(|| ctx.model.to_string())
+        ));
+        lines.push(format!(
+            "  エージェント: {}",
+            session.agent_override().map(|a| format!("{a}（/agent で固定中）")).unwrap_or_else(|| "自動選択".to_string())
+        ));
+    }
+
     CommandResult::Reply(lines.join("\n"))
 }
 
@@ -1108,7 +1174,7 @@ async fn query_recent_feedback(ctx: &CommandContext<'_>) -> String {
 // ---------------------------------------------------------------------------
 
 #[cfg(feature = "dynamodb-backend")]
-enum LinkResult {
+pub(crate) enum LinkResult {
     CodeGenerated(String),
     Linked(String),
     Error(String),
@@ -1149,7 +1215,7 @@ async fn resolve_session_key(
 /// e.g. "line:U12345" → "LINE", "tg:123|yukibot" → "Telegram (@yukibot)", "webchat:xxx" → "Web"
 /// Returns (display_name, identifier).
 #[allow(dead_code)]
-fn channel_display_name(channel_key: &str) -> (String, String) {
+pub(crate) fn channel_display_name(channel_key: &str) -> (String, String) {
     if let Some(rest) = channel_key.strip_prefix("line:") {
         ("LINE".to_string(), rest.to_string())
     } else if let Some(rest) = channel_key.strip_prefix("tg:") {
@@ -1162,13 +1228,15 @@ fn channel_display_name(channel_key: &str) -> (String, String) {
         ("Web".to_string(), String::new())
     } else if let Some(rest) = channel_key.strip_prefix("fb:") {
         ("Facebook".to_string(), rest.to_string())
+    } else if channel_key.starts_with("cli:") {
+        ("CLI".to_string(), String::new())
     } else {
         ("Unknown".to_string(), channel_key.to_string())
     }
 }
 
 #[cfg(feature = "dynamodb-backend")]
-async fn handle_link_command(
+pub(crate) async fn handle_link_command(
     dynamo: &aws_sdk_dynamodb::Client,
     config_table: &str,
     channel_key: &str,
@@ -1333,6 +1401,20 @@ async fn handle_link_command(
                 }
             }
 
+            // Leave a short-lived receipt so the device that generated the
+            // code (e.g. `chatweb link --qr`) can tell who consumed it.
+            let done_ttl = (chrono::Utc::now().timestamp() + 600).to_string();
+            let _ = dynamo
+                .put_item()
+                .table_name(config_table)
+                .item("pk", AttributeValue::S(format!("LINKCODE#{}", code)))
+                .item("sk", AttributeValue::S("DONE".to_string()))
+                .item("channel_key", AttributeValue::S(channel_key.to_string()))
+                .item("channel_name", AttributeValue::S(this_display.clone()))
+                .item("ttl", AttributeValue::N(done_ttl))
+                .send()
+                .await;
+
             let _ = dynamo
                 .delete_item()
                 .table_name(config_table)
@@ -1436,15 +1518,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_builtins() {
+        let builtin = |text| match parse_command(text) {
+            Some(SlashCommand::Builtin(inv)) => Some((inv.name(), inv.args)),
+            _ => None,
+        };
+        assert_eq!(builtin("/pin https://docs.rs/axum"), Some(("pin", "https://docs.rs/axum")));
+        assert_eq!(builtin("/PINS"), Some(("pins", "")));
+        assert_eq!(builtin("/unpin all"), Some(("unpin", "all")));
+        assert_eq!(builtin("/reset"), Some(("reset", "")));
+        assert_eq!(builtin("/usage"), Some(("usage", "")));
+        assert_eq!(parse_command("/pinned"), None);
+        // CLI-only screen commands are not handled server-side
+        assert_eq!(parse_command("/omikuji"), None);
+    }
+
+    #[test]
+    fn test_channel_id() {
+        assert_eq!(channel_id("line:U123"), "line");
+        assert_eq!(channel_id("tg:1|bot"), "telegram");
+        assert_eq!(channel_id("cli:abc"), "cli");
+        assert_eq!(channel_id("webchat:abc"), "web");
+    }
+
     #[test]
     fn test_help_text_contains_commands() {
-        let text = help_text();
+        let text = help_text("web", Locale::Ja);
         assert!(text.contains("/help"));
         assert!(text.contains("/status"));
         assert!(text.contains("/share"));
         assert!(text.contains("/link"));
         assert!(text.contains("/improve"));
         assert!(text.contains("/keys"));
+        assert!(text.contains("/pin"));
     }
 
     #[test]
@@ -1486,5 +1593,8 @@ mod tests {
 
         let (name, _) = channel_display_name("fb:12345");
         assert_eq!(name, "Facebook");
+
+        let (name, _) = channel_display_name("cli:0b6e");
+        assert_eq!(name, "CLI");
     }
 }
@@ -0,0 +1,782 @@
+//! End-to-end encrypted sync of CLI state between a user's devices
+//! (`chatweb sync now` / `sync status`, configured under `sync`).
+//!
+//! A [`Snapshot`] holds `config.json` and the sessions matching
+//! `sync.sessions` (named `chatweb agent` sessions by default), pins
+//! included. API keys and tokens are blanked unless `sync.includeSecrets`,
+//! and the `sync` section itself stays on each device. The snapshot is
+//! sealed with AES-256-GCM under a key derived from the user's passphrase
+//! (PBKDF2-SHA256), so the target — the hosted API, an S3 object or a
+//! WebDAV file — only ever stores ciphertext.
+//!
+//! Each item is merged three-way against the hashes recorded at the last
+//! sync: a side that didn't change takes the other side's version, edits
+//! beat deletions, and when both sides changed the newer edit wins. The
+//! losing version is kept under `sync/conflicts/` in the data dir.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use super::backup::{merge_secrets, redact_secrets};
+use crate::config::{self, SyncConfig};
+use crate::session::archive::SessionArchive;
+use crate::session::store::SessionStore;
+
+/// Snapshot and envelope format version written by this build.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// `sync.target` value for the hosted API.
+pub const HOSTED_TARGET: &str = "chatweb";
+
+/// Passphrase source for non-interactive use.
+pub const PASSPHRASE_ENV: &str = "NANOBOT_SYNC_PASSPHRASE";
+
+/// Object name used when an S3 target names a prefix rather than a key.
+const DEFAULT_OBJECT: &str = "nanobot-sync.json";
+const CONFIG_ITEM: &str = "config";
+const SESSION_PREFIX: &str = "session:";
+/// PBKDF2 rounds for new envelopes (OWASP's recommendation for SHA-256).
+const KDF_ITERATIONS: u32 = 600_000;
+/// Push attempts when another device writes in between.
+const MAX_ATTEMPTS: usize = 3;
+
+/// One synced item: the config document or a session archive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Item {
+    pub updated_at: DateTime<Utc>,
+    /// Device that last changed the item.
+    pub device: String,
+    pub content: serde_json::Value,
+}
+
+impl Item {
+    fn hash(&self) -> String {
+        format!("{:x}", Sha256::digest(self.content.to_string().as_bytes()))
+    }
+}
+
+/// Everything synced, keyed by `config` or `session:<key>`.
+pub type Items = BTreeMap<String, Item>;
+
+/// The decrypted contents of the remote copy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    pub format: u32,
+    pub device: String,
+    pub created_at: DateTime<Utc>,
+    pub items: Items,
+}
+
+/// What is stored at the target: a sealed [`Snapshot`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    format: u32,
+    kdf: String,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Encrypt `snapshot` under `passphrase`.
+pub fn seal(snapshot: &Snapshot, passphrase: &str) -> Result<Vec<u8>> {
+    seal_with(snapshot, passphrase, KDF_ITERATIONS)
+}
+
+fn seal_with(snapshot: &Snapshot, passphrase: &str, iterations: u32) -> Result<Vec<u8>> {
+    use aes_gcm::aead::OsRng;
+    use aes_gcm::AeadCore;
+    use rand::RngCore;
+
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt, iterations).into());
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, serde_json::to_vec(snapshot)?.as_slice())
+        .map_err(|e| anyhow!("encryption failed: {e}"))?;
+
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let envelope = Envelope {
+        format: FORMAT_VERSION,
+        kdf: "pbkdf2-sha256".to_string(),
+        iterations,
+        salt: b64.encode(salt),
+        nonce: b64.encode(nonce),
+        ciphertext: b64.encode(ciphertext),
+    };
+    Ok(serde_json::to_vec(&envelope)?)
+}
+
+/// Decrypt what [`seal`] wrote.
+pub fn open(data: &[u8], passphrase: &str) -> Result<Snapshot> {
+    let envelope: Envelope = serde_json::from_slice(data).context("not a nanobot sync file")?;
+    if envelope.format > FORMAT_VERSION {
+        bail!(
+            "sync format v{} is newer than this build supports (v{FORMAT_VERSION}); update chatweb",
+            envelope.format
+        );
+    }
+    if envelope.kdf != "pbkdf2-sha256" {
+        bail!("unsupported key derivation: {}", envelope.kdf);
+    }
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let salt = b64.decode(&envelope.salt).context("bad salt")?;
+    let nonce = b64.decode(&envelope.nonce).context("bad nonce")?;
+    let ciphertext = b64.decode(&envelope.ciphertext).context("bad ciphertext")?;
+    if nonce.len() != 12 {
+        bail!("nonce must be 12 bytes, got {}", nonce.len());
+    }
+
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt, envelope.iterations).into());
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| anyhow!("wrong sync passphrase (or the remote copy is damaged)"))?;
+    serde_json::from_slice(&plaintext).context("parsing sync snapshot")
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+// ---------------------------------------------------------------------------
+// Merge
+// ---------------------------------------------------------------------------
+
+/// Which side's version an item now has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Local,
+    Remote,
+}
+
+/// An item changed on both sides; `lost` is the version that was replaced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub key: String,
+    pub kept: Side,
+    pub lost: Item,
+}
+
+/// Result of [`merge`].
+#[derive(Debug, Default)]
+pub struct Merge {
+    /// The new shared state.
+    pub items: Items,
+    /// Keys whose remote version must be written locally.
+    pub pull: Vec<String>,
+    /// Keys deleted on the other device.
+    pub remove: Vec<String>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Merge {
+    /// Whether the remote copy differs from the merged state.
+    pub fn needs_push(&self, remote: &Items) -> bool {
+        hashes(&self.items) != hashes(remote)
+    }
+}
+
+/// Merge `local` and `remote` against `base`, the item hashes of the last
+/// successful sync.
+pub fn merge(local: &Items, remote: &Items, base: &BTreeMap<String, String>) -> Merge {
+    let keys: BTreeSet<&String> = local.keys().chain(remote.keys()).collect();
+    let mut out = Merge::default();
+    for key in keys {
+        let (l, r) = (local.get(key), remote.get(key));
+        let (lh, rh) = (l.map(Item::hash), r.map(Item::hash));
+        let b = base.get(key);
+
+        let side = if lh == rh || rh.as_ref() == b {
+            Side::Local
+        } else if lh.as_ref() == b {
+            Side::Remote
+        } else {
+            // Changed on both sides: an edit beats a deletion, else the newer edit wins
+            let side = match (l, r) {
+                (Some(l), Some(r)) if r.updated_at > l.updated_at => Side::Remote,
+                (None, Some(_)) => Side::Remote,
+                _ => Side::Local,
+            };
+            let lost = if side == Side::Local { r } else { l };
+            if let Some(lost) = lost {
+                out.conflicts.push(Conflict { key: key.clone(), kept: side, lost: lost.clone() });
+            }
+            side
+        };
+
+        match (side, l, r) {
+            (Side::Local, Some(item), _) => {
+                out.items.insert(key.clone(), item.clone());
+            }
+            (Side::Remote, _, Some(item)) => {
+                out.items.insert(key.clone(), item.clone());
+                if lh != rh {
+                    out.pull.push(key.clone());
+                }
+            }
+            (Side::Remote, Some(_), None) => out.remove.push(key.clone()),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn hashes(items: &Items) -> BTreeMap<String, String> {
+    items.iter().map(|(k, v)| (k.clone(), v.hash())).collect()
+}
+
+// ---------------------------------------------------------------------------
+// Local state
+// ---------------------------------------------------------------------------
+
+/// Where the synced state lives on this machine.
+#[derive(Debug, Clone)]
+pub struct SyncSources {
+    pub config_path: PathBuf,
+    /// Holds `sync/state.json` and `sync/conflicts/`.
+    pub data_dir: PathBuf,
+}
+
+impl SyncSources {
+    pub fn active() -> Self {
+        Self {
+            config_path: config::get_config_path(),
+            data_dir: config::get_data_dir(),
+        }
+    }
+
+    fn state_path(&self) -> PathBuf {
+        self.data_dir.join("sync").join("state.json")
+    }
+
+    pub fn conflicts_dir(&self) -> PathBuf {
+        self.data_dir.join("sync").join("conflicts")
+    }
+}
+
+/// What this device remembers between syncs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncState {
+    pub last_sync_at: Option<DateTime<Utc>>,
+    /// Target of the last sync; a different target starts from scratch.
+    pub target: String,
+    /// Item hashes after the last sync (the merge base).
+    pub hashes: BTreeMap<String, String>,
+    /// Conflicts from the last sync, as `(key, saved copy)`.
+    pub conflicts: Vec<(String, String)>,
+}
+
+impl SyncState {
+    pub fn load(sources: &SyncSources) -> Self {
+        std::fs::read(sources.state_path())
+            .ok()
+            .and_then(|raw| serde_json::from_slice(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, sources: &SyncSources) -> Result<()> {
+        let path = sources.state_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// Keys whose local version differs from the last sync.
+    pub fn pending(&self, local: &Items) -> Vec<String> {
+        let current = hashes(local);
+        let keys: BTreeSet<&String> = current.keys().chain(self.hashes.keys()).collect();
+        keys.into_iter()
+            .filter(|k| current.get(*k) != self.hashes.get(*k))
+            .cloned()
+            .collect()
+    }
+}
+
+/// The config document as synced: without the device-local `sync`
+/// section, and with secrets blanked unless they are included.
+fn config_content(raw: &serde_json::Value, include_secrets: bool) -> serde_json::Value {
+    let mut value = raw.clone();
+    if let Some(map) = value.as_object_mut() {
+        map.remove("sync");
+    }
+    if !include_secrets {
+        redact_secrets(&mut value);
+    }
+    value
+}
+
+/// The synced config written over `current`: this device's `sync` section
+/// and any secrets the snapshot left blank are kept.
+fn config_to_write(synced: &serde_json::Value, current: &serde_json::Value) -> serde_json::Value {
+    let mut value = synced.clone();
+    merge_secrets(&mut value, current);
+    if let (Some(map), Some(sync)) = (value.as_object_mut(), current.get("sync")) {
+        map.insert("sync".to_string(), sync.clone());
+    }
+    value
+}
+
+fn read_config(path: &Path) -> Result<Option<serde_json::Value>> {
+    match std::fs::read(path) {
+        Ok(raw) => Ok(Some(serde_json::from_slice(&raw).with_context(|| format!("parsing {}", path.display()))?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("reading {}", path.display())),
+    }
+}
+
+/// This device's current items.
+pub fn collect(sources: &SyncSources, settings: &SyncConfig, device: &str, sessions: &mut dyn SessionStore) -> Result<Items> {
+    let mut items = Items::new();
+    if let Some(raw) = read_config(&sources.config_path)? {
+        let updated_at = std::fs::metadata(&sources.config_path)
+            .and_then(|m| m.modified())
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+        let content = config_content(&raw, settings.include_secrets);
+        items.insert(CONFIG_ITEM.to_string(), Item { updated_at, device: device.to_string(), content });
+    }
+
+    let keys: Vec<String> = sessions
+        .list_sessions()
+        .iter()
+        .filter_map(|s| s.get("key").and_then(|k| k.as_str()).map(str::to_string))
+        .filter(|k| settings.sessions.iter().any(|p| k.starts_with(p.as_str())))
+        .collect();
+    for key in keys {
+        if let Some(archive) = sessions.export(&key) {
+            items.insert(
+                format!("{SESSION_PREFIX}{key}"),
+                Item { updated_at: archive.updated_at, device: device.to_string(), content: serde_json::to_value(&archive)? },
+            );
+        }
+    }
+    Ok(items)
+}
+
+/// Write the pulled and removed items of `merge` to this device.
+fn apply(sources: &SyncSources, merge: &Merge, sessions: &mut dyn SessionStore) -> Result<()> {
+    for key in &merge.pull {
+        let item = &merge.items[key];
+        if key == CONFIG_ITEM {
+            let current = read_config(&sources.config_path)?.unwrap_or_default();
+            let value = config_to_write(&item.content, &current);
+            serde_json::from_value::<config::Config>(value.clone()).context("synced config.json is invalid")?;
+            std::fs::write(&sources.config_path, serde_json::to_vec_pretty(&value)?)
+                .with_context(|| format!("writing {}", sources.config_path.display()))?;
+        } else if key.starts_with(SESSION_PREFIX) {
+            let archive: SessionArchive = serde_json::from_value(item.content.clone())
+                .with_context(|| format!("{key}: not a session archive"))?;
+            sessions.import(archive.into_session(None))?;
+        }
+    }
+    // A config.json deleted elsewhere is left alone here
+    for session in merge.remove.iter().filter_map(|k| k.strip_prefix(SESSION_PREFIX)) {
+        sessions.delete(session);
+    }
+    Ok(())
+}
+
+/// Keep the losing side of a conflict; returns where it was saved.
+fn save_conflict(sources: &SyncSources, conflict: &Conflict) -> Result<PathBuf> {
+    let dir = sources.conflicts_dir();
+    std::fs::create_dir_all(&dir)?;
+    let name = format!(
+        "{}.{}.{}.json",
+        crate::util::safe_filename(&conflict.key.replace(':', "_")),
+        crate::util::safe_filename(&conflict.lost.device),
+        Utc::now().format("%Y%m%d-%H%M%S"),
+    );
+    let path = dir.join(name);
+    std::fs::write(&path, serde_json::to_vec_pretty(&conflict.lost.content)?)?;
+    Ok(path)
+}
+
+// ---------------------------------------------------------------------------
+// Targets
+// ---------------------------------------------------------------------------
+
+/// Where the sealed snapshot is kept.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    /// `PUT/GET /api/v1/sync/config` on the hosted API.
+    Hosted { api: String, token: String },
+    S3 { bucket: String, key: String },
+    /// A file on a WebDAV server, read and written with plain GET/PUT.
+    WebDav { url: String, user: String, password: String },
+}
+
+/// The remote copy and the revision to write against.
+pub struct Remote {
+    pub data: Vec<u8>,
+    pub rev: String,
+}
+
+impl Target {
+    /// Resolve `sync.target`. The hosted API needs the CLI's auth token.
+    pub fn parse(settings: &SyncConfig, api: &str, token: Option<String>) -> Result<Self> {
+        let target = settings.target.trim();
+        if target.is_empty() {
+            bail!("sync is not set up: set sync.target to \"chatweb\", s3://bucket/key or a WebDAV URL");
+        }
+        if target == HOSTED_TARGET {
+            let token = token.ok_or_else(|| anyhow!("syncing through chatweb.ai needs an account; run `chatweb link` to sign in first"))?;
+            return Ok(Target::Hosted { api: api.trim_end_matches('/').to_string(), token });
+        }
+        if let Some(rest) = target.strip_prefix("s3://") {
+            let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+            let key = key.trim_matches('/');
+            let key = if key.is_empty() {
+                DEFAULT_OBJECT.to_string()
+            } else if rest.ends_with('/') {
+                format!("{key}/{DEFAULT_OBJECT}")
+            } else {
+                key.to_string()
+            };
+            return Ok(Target::S3 { bucket: bucket.to_string(), key });
+        }
+        if target.starts_with("https://") || target.starts_with("http://") {
+            return Ok(Target::WebDav {
+                url: target.to_string(),
+                user: settings.webdav_user.clone(),
+                password: settings.webdav_password.clone(),
+            });
+        }
+        bail!("unknown sync.target {target:?}: use \"chatweb\", s3://bucket/key or a WebDAV URL")
+    }
+
+    /// Shown in `sync status`, without credentials.
+    pub fn describe(&self) -> String {
+        match self {
+            Target::Hosted { api, .. } => api.clone(),
+            Target::S3 { bucket, key } => format!("s3://{bucket}/{key}"),
+            Target::WebDav { url, .. } => url.clone(),
+        }
+    }
+
+    /// The remote copy, or `None` before the first push.
+    pub async fn fetch(&self, client: &reqwest::Client) -> Result<Option<Remote>> {
+        match self {
+            Target::S3 { bucket, key } => s3_fetch(bucket, key).await,
+            _ => {
+                let resp = self.request(client, reqwest::Method::GET).send().await?;
+                if resp.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+                if !resp.status().is_success() {
+                    bail!("fetching {} failed: HTTP {}", self.describe(), resp.status());
+                }
+                let rev = etag(resp.headers());
+                Ok(Some(Remote { data: resp.bytes().await?.to_vec(), rev }))
+            }
+        }
+    }
+
+    /// Replace the remote copy if it is still at `rev` (`None`: if there is
+    /// none). Returns `false` when another device wrote first.
+    pub async fn store(&self, client: &reqwest::Client, data: Vec<u8>, rev: Option<&str>) -> Result<bool> {
+        match self {
+            Target::S3 { bucket, key } => s3_store(bucket, key, data, rev).await,
+            _ => {
+                let mut req = self.request(client, reqwest::Method::PUT).body(data);
+                req = match rev {
+                    Some(rev) if !rev.is_empty() => req.header(reqwest::header::IF_MATCH, rev),
+                    // Servers without ETags get a last-writer-wins PUT
+                    Some(_) => req,
+                    None => req.header(reqwest::header::IF_NONE_MATCH, "*"),
+                };
+                let resp = req.send().await?;
+                if resp.status() == reqwest::StatusCode::PRECONDITION_FAILED {
+                    return Ok(false);
+                }
+                if !resp.status().is_success() {
+                    bail!("uploading to {} failed: HTTP {}", self.describe(), resp.status());
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    fn request(&self, client: &reqwest::Client, method: reqwest::Method) -> reqwest::RequestBuilder {
+        match self {
+            Target::Hosted { api, token } => client
+                .request(method, format!("{api}/api/v1/sync/config"))
+                .bearer_auth(token),
+            Target::WebDav { url, user, password } => {
+                let req = client.request(method, url);
+                if user.is_empty() { req } else { req.basic_auth(user, Some(password)) }
+            }
+            Target::S3 { .. } => unreachable!("S3 goes through the AWS SDK"),
+        }
+    }
+}
+
+fn etag(headers: &reqwest::header::HeaderMap) -> String {
+    headers
+        .get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(feature = "dynamodb-backend")]
+async fn s3_client() -> aws_sdk_s3::Client {
+    let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+    aws_sdk_s3::Client::new(&config)
+}
+
+#[cfg(feature = "dynamodb-backend")]
+async fn s3_fetch(bucket: &str, key: &str) -> Result<Option<Remote>> {
+    let s3 = s3_client().await;
+    match s3.get_object().bucket(bucket).key(key).send().await {
+        Ok(object) => {
+            let rev = object.e_tag().unwrap_or_default().to_string();
+            let data = object.body.collect().await?.into_bytes().to_vec();
+            Ok(Some(Remote { data, rev }))
+        }
+        Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => Ok(None),
+        Err(e) => Err(anyhow!("S3 download failed: {e}")),
+    }
+}
+
+#[cfg(feature = "dynamodb-backend")]
+async fn s3_store(bucket: &str, key: &str, data: Vec<u8>, rev: Option<&str>) -> Result<bool> {
+    let s3 = s3_client().await;
+    // Best effort: S3 has no compare-and-swap on every provider, so check first
+    let current = match s3.head_object().bucket(bucket).key(key).send().await {
+        Ok(head) => Some(head.e_tag().unwrap_or_default().to_string()),
+        Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => None,
+        Err(e) => return Err(anyhow!("S3 check failed: {e}")),
+    };
+    if current.as_deref() != rev {
+        return Ok(false);
+    }
+    s3.put_object()
+        .bucket(bucket)
+        .key(key)
+        .body(aws_sdk_s3::primitives::ByteStream::from(data))
+        .content_type("application/json")
+        .send()
+        .await
+        .map_err(|e| anyhow!("S3 upload failed: {e}"))?;
+    Ok(true)
+}
+
+#[cfg(not(feature = "dynamodb-backend"))]
+async fn s3_fetch(_bucket: &str, _key: &str) -> Result<Option<Remote>> {
+    bail!("S3 sync targets require the dynamodb-backend feature")
+}
+
+#[cfg(not(feature = "dynamodb-backend"))]
+async fn s3_store(_bucket: &str, _key: &str, _data: Vec<u8>, _rev: Option<&str>) -> Result<bool> {
+    bail!("S3 sync targets require the dynamodb-backend feature")
+}
+
+// ---------------------------------------------------------------------------
+// Sync
+// ---------------------------------------------------------------------------
+
+/// Outcome of [`run`].
+#[derive(Debug, Default)]
+pub struct Report {
+    pub pulled: Vec<String>,
+    pub removed: Vec<String>,
+    pub pushed: bool,
+    /// `(key, saved copy of the losing version)`.
+    pub conflicts: Vec<(String, PathBuf)>,
+}
+
+/// Sync this device with `target` once.
+pub async fn run(
+    target: &Target,
+    settings: &SyncConfig,
+    passphrase: &str,
+    device: &str,
+    sources: &SyncSources,
+    sessions: &mut dyn SessionStore,
+) -> Result<Report> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(60))
+        .build()
+        .unwrap_or_default();
+    let mut state = SyncState::load(sources);
+    if state.target != target.describe() {
+        state.hashes.clear();
+    }
+
+    for _ in 0..MAX_ATTEMPTS {
+        let local = collect(sources, settings, device, sessions)?;
+        let remote = target.fetch(&client).await?;
+        let remote_items = match &remote {
+            Some(r) => open(&r.data, passphrase)?.items,
+            None => Items::new(),
+        };
+
+        let merge = merge(&local, &remote_items, &state.hashes);
+        let pushed = merge.needs_push(&remote_items) || remote.is_none();
+        if pushed {
+            let snapshot = Snapshot {
+                format: FORMAT_VERSION,
+                device: device.to_string(),
+                created_at: Utc::now(),
+                items: merge.items.clone(),
+            };
+            let data = seal(&snapshot, passphrase)?;
+            if !target.store(&client, data, remote.as_ref().map(|r| r.rev.as_str())).await? {
+                info!("Sync target changed while syncing; retrying");
+                continue;
+            }
+        }
+
+        // Keep the losing versions before anything local is overwritten
+        let mut report = Report { pushed, ..Default::default() };
+        for conflict in &merge.conflicts {
+            let path = save_conflict(sources, conflict)?;
+            report.conflicts.push((conflict.key.clone(), path));
+        }
+        apply(sources, &merge, sessions)?;
+
+        report.pulled = merge.pull.clone();
+        report.removed = merge.remove.clone();
+        state = SyncState {
+            last_sync_at: Some(Utc::now()),
+            target: target.describe(),
+            hashes: hashes(&merge.items),
+            conflicts: report.conflicts.iter().map(|(k, p)| (k.clone(), p.display().to_string())).collect(),
+        };
+        state.save(sources)?;
+        return Ok(report);
+    }
+    bail!("{} kept changing while syncing; try again", target.describe())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(content: serde_json::Value, minutes: i64, device: &str) -> Item {
+        Item {
+            updated_at: DateTime::from_timestamp(1_760_000_000 + minutes * 60, 0).unwrap(),
+            device: device.to_string(),
+            content,
+        }
+    }
+
+    fn items(entries: &[(&str, &Item)]) -> Items {
+        entries.iter().map(|(k, v)| (k.to_string(), (*v).clone())).collect()
+    }
+
+    #[test]
+    fn test_seal_open() {
+        let snapshot = Snapshot {
+            format: FORMAT_VERSION,
+            device: "laptop".into(),
+            created_at: Utc::now(),
+            items: items(&[("config", &item(serde_json::json!({"agents": {}}), 0, "laptop"))]),
+        };
+        let sealed = seal_with(&snapshot, "correct horse", 1000).unwrap();
+        assert!(!String::from_utf8_lossy(&sealed).contains("agents"));
+
+        let opened = open(&sealed, "correct horse").unwrap();
+        assert_eq!(opened.items, snapshot.items);
+        assert!(open(&sealed, "wrong").unwrap_err().to_string().contains("passphrase"));
+        assert!(open(b"{}", "correct horse").is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let v1 = item(serde_json::json!(1), 0, "laptop");
+        let local_edit = item(serde_json::json!(2), 10, "laptop");
+        let remote_edit = item(serde_json::json!(3), 20, "phone");
+        let base: BTreeMap<String, String> = [("a".to_string(), v1.hash())].into();
+
+        // Only one side changed
+        let m = merge(&items(&[("a", &local_edit)]), &items(&[("a", &v1)]), &base);
+        assert_eq!(m.items["a"], local_edit);
+        assert!(m.pull.is_empty() && m.conflicts.is_empty());
+        assert!(m.needs_push(&items(&[("a", &v1)])));
+
+        let m = merge(&items(&[("a", &v1)]), &items(&[("a", &remote_edit)]), &base);
+        assert_eq!(m.pull, vec!["a"]);
+        assert!(!m.needs_push(&items(&[("a", &remote_edit)])));
+
+        // Both changed: the newer edit wins, the other is reported
+        let m = merge(&items(&[("a", &local_edit)]), &items(&[("a", &remote_edit)]), &base);
+        assert_eq!(m.items["a"], remote_edit);
+        assert_eq!(m.conflicts, vec![Conflict { key: "a".into(), kept: Side::Remote, lost: local_edit.clone() }]);
+
+        // Deleted remotely and unchanged here: delete; edited here: keep
+        let m = merge(&items(&[("a", &v1)]), &Items::new(), &base);
+        assert_eq!(m.remove, vec!["a"]);
+        assert!(m.items.is_empty());
+        let m = merge(&items(&[("a", &local_edit)]), &Items::new(), &base);
+        assert_eq!(m.items["a"], local_edit);
+        assert_eq!(m.conflicts.len(), 0);
+
+        // First sync with no base: new items on either side are kept
+        let m = merge(&items(&[("a", &v1)]), &items(&[("b", &remote_edit)]), &BTreeMap::new());
+        assert_eq!(m.items.len(), 2);
+        assert_eq!(m.pull, vec!["b"]);
+    }
+
+    #[test]
+    fn test_config_content() {
+        let raw = serde_json::json!({
+            "providers": {"anthropic": {"apiKey": "sk-1", "apiBase": "https://x"}},
+            "sync": {"target": "chatweb"},
+        });
+        let synced = config_content(&raw, false);
+        assert_eq!(synced, serde_json::json!({"providers": {"anthropic": {"apiKey": "", "apiBase": "https://x"}}}));
+        assert_eq!(config_content(&raw, true)["providers"]["anthropic"]["apiKey"], "sk-1");
+
+        let current = serde_json::json!({
+            "providers": {"anthropic": {"apiKey": "sk-local"}},
+            "sync": {"target": "s3://b/k"},
+        });
+        let written = config_to_write(&synced, &current);
+        assert_eq!(written["providers"]["anthropic"]["apiKey"], "sk-local");
+        assert_eq!(written["providers"]["anthropic"]["apiBase"], "https://x");
+        assert_eq!(written["sync"]["target"], "s3://b/k");
+    }
+
+    #[test]
+    fn test_target_parse() {
+        let settings = |target: &str| SyncConfig { target: target.into(), ..Default::default() };
+        assert_eq!(
+            Target::parse(&settings("chatweb"), "https://chatweb.ai/", Some("t".into())).unwrap(),
+            Target::Hosted { api: "https://chatweb.ai".into(), token: "t".into() }
+        );
+        assert!(Target::parse(&settings("chatweb"), "https://chatweb.ai", None).is_err());
+        assert_eq!(
+            Target::parse(&settings("s3://bucket/devices/"), "", None).unwrap(),
+            Target::S3 { bucket: "bucket".into(), key: "devices/nanobot-sync.json".into() }
+        );
+        assert_eq!(
+            Target::parse(&settings("s3://bucket/me.json"), "", None).unwrap().describe(),
+            "s3://bucket/me.json"
+        );
+        assert!(matches!(
+            Target::parse(&settings("https://dav.example.com/nanobot.json"), "", None).unwrap(),
+            Target::WebDav { .. }
+        ));
+        assert!(Target::parse(&settings(""), "", None).is_err());
+        assert!(Target::parse(&settings("ftp://x"), "", None).is_err());
+    }
+}
@@ -1,15 +1,9 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tracing::{error, info, warn};
+use tracing::{info, warn};
 use uuid::Uuid;
 
-fn now_ms() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_millis() as u64
-}
+use crate::util::{now_ms, write_json};
 
 /// Schedule definition for a cron job.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,24 +47,16 @@ impl CronSchedule {
                     None
                 }
             }
-            CronSchedule::Cron { expr, .. } => {
+            CronSchedule::Cron { expr, tz } => {
                 // Use the cron crate to compute next run
                 use cron::Schedule;
                 use std::str::FromStr;
-                // cron crate expects 6 or 7 fields, standard crontab has 5
-                // Prepend seconds field "0" if only 5 fields
-                let cron_expr = if expr.split_whitespace().count() == 5 {
-                    format!("0 {expr}")
-                } else {
-                    expr.clone()
-                };
-                match Schedule::from_str(&cron_expr) {
-                    Ok(schedule) => {
-                        schedule
-                            .upcoming(chrono::Utc)
-                            .next()
-                            .map(|dt| dt.timestamp_millis() as u64)
-                    }
+                match Schedule::from_str(&with_seconds(expr)) {
+                    // Evaluate in the job's timezone when one is set
+                    Ok(schedule) => match tz.as_deref().and_then(crate::util::tz::parse) {
+                        Some(zone) => schedule.upcoming(zone).next().map(|dt| dt.timestamp_millis() as u64),
+                        None => schedule.upcoming(chrono::Utc).next().map(|dt| dt.timestamp_millis() as u64),
+                    },
                     Err(e) => {
                         warn!("Invalid cron expression '{}': {}", expr, e);
                         None
@@ -81,6 +67,22 @@ impl CronSchedule {
     }
 }
 
+/// The cron crate expects 6 or 7 fields, standard crontab has 5:
+/// prepend a seconds field "0" if only 5 fields.
+fn with_seconds(expr: &str) -> String {
+    if expr.split_whitespace().count() == 5 {
+        format!("0 {expr}")
+    } else {
+        expr.to_string()
+    }
+}
+
+/// Whether `expr` is a cron expression (`0 8 * * *`, or with seconds).
+pub fn is_valid_expr(expr: &str) -> bool {
+    use std::str::FromStr;
+    cron::Schedule::from_str(&with_seconds(expr)).is_ok()
+}
+
 /// What to do when the job runs.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -108,9 +110,12 @@ pub struct CronJobState {
     pub last_run_at_ms: Option<u64>,
     pub last_status: Option<String>,
     pub last_error: Option<String>,
+    /// Delivery id of the last message sent for a `deliver` job
+    /// (see [`crate::delivery`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_delivery_id: Option<String>,
 }
 
-
 /// A scheduled job.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -150,9 +155,15 @@ fn default_version() -> u32 {
 }
 
 /// Service for managing and executing scheduled jobs.
+///
+/// The store is cached in memory and re-read when the file changes, so jobs
+/// cancelled or moved by another process (`chatweb upcoming`, the HTTP API)
+/// are picked up by a running gateway.
 pub struct CronService {
     store_path: PathBuf,
     store: Option<CronStore>,
+    /// Modification time of the file when `store` was loaded or saved.
+    store_modified: Option<std::time::SystemTime>,
 }
 
 impl CronService {
@@ -160,11 +171,29 @@ impl CronService {
         Self {
             store_path,
             store: None,
+            store_modified: None,
         }
     }
 
+    /// Open the store as it is on disk, keeping the next runs a running
+    /// gateway computed (unlike [`CronService::init`]).
+    pub fn open(store_path: PathBuf) -> Self {
+        let mut service = Self::new(store_path);
+        service.load_store();
+        service
+    }
+
+    fn file_modified(&self) -> Option<std::time::SystemTime> {
+        std::fs::metadata(&self.store_path).and_then(|m| m.modified()).ok()
+    }
+
     fn load_store(&mut self) -> &mut CronStore {
+        let modified = self.file_modified();
+        if self.store.is_some() && modified != self.store_modified {
+            self.store = None;
+        }
         if self.store.is_none() {
+            self.store_modified = modified;
             let store = if self.store_path.exists() {
                 match std::fs::read_to_string(&self.store_path) {
                     Ok(content) => serde_json::from_str(&content).unwrap_or(CronStore {
@@ -190,16 +219,10 @@ impl CronService {
         self.store.as_mut().unwrap()
     }
 
-    fn save_store(&self) {
+    fn save_store(&mut self) {
         if let Some(ref store) = self.store {
-            if let Some(parent) = self.store_path.parent() {
-                std::fs::create_dir_all(parent).ok();
-            }
-            if let Ok(json) = serde_json::to_string_pretty(store) {
-                if let Err(e) = std::fs::write(&self.store_path, json) {
-                    error!("Failed to save cron store: {}", e);
-                }
-            }
+            write_json(&self.store_path, store, "cron store");
+            self.store_modified = self.file_modified();
         }
     }
 
@@ -229,19 +252,25 @@ impl CronService {
         channel: Option<&str>,
         to: Option<&str>,
     ) -> CronJob {
+        let payload = CronPayload {
+            kind: "agent_turn".to_string(),
+            message: message.to_string(),
+            deliver,
+            channel: channel.map(|s| s.to_string()),
+            to: to.map(|s| s.to_string()),
+        };
+        self.add_job_with_payload(name, schedule, payload)
+    }
+
+    /// Add a new job with an explicit payload (e.g. a `reminder`).
+    pub fn add_job_with_payload(&mut self, name: &str, schedule: CronSchedule, payload: CronPayload) -> CronJob {
         let now = now_ms();
         let job = CronJob {
             id: Uuid::new_v4().to_string()[..8].to_string(),
             name: name.to_string(),
             enabled: true,
             schedule: schedule.clone(),
-            payload: CronPayload {
-                kind: "agent_turn".to_string(),
-                message: message.to_string(),
-                deliver,
-                channel: channel.map(|s| s.to_string()),
-                to: to.map(|s| s.to_string()),
-            },
+            payload,
             state: CronJobState {
                 next_run_at_ms: schedule.next_run(now),
                 ..Default::default()
@@ -258,6 +287,20 @@ impl CronService {
         job
     }
 
+    /// Replace a job's schedule and re-enable it.
+    pub fn reschedule_job(&mut self, job_id: &str, schedule: CronSchedule) -> Option<CronJob> {
+        let store = self.load_store();
+        let now = now_ms();
+        let job = store.jobs.iter_mut().find(|j| j.id == job_id)?;
+        job.state.next_run_at_ms = schedule.next_run(now);
+        job.schedule = schedule;
+        job.enabled = true;
+        job.updated_at_ms = now;
+        let result = job.clone();
+        self.save_store();
+        Some(result)
+    }
+
     /// Remove a job by ID.
     pub fn remove_job(&mut self, job_id: &str) -> bool {
         let store = self.load_store();
@@ -344,6 +387,15 @@ impl CronService {
         self.save_store();
     }
 
+    /// Remember the delivery id of the message a job run produced.
+    pub fn record_delivery(&mut self, job_id: &str, delivery_id: &str) {
+        let store = self.load_store();
+        if let Some(job) = store.jobs.iter_mut().find(|j| j.id == job_id) {
+            job.state.last_delivery_id = Some(delivery_id.to_string());
+            self.save_store();
+        }
+    }
+
     /// Get service status.
     pub fn status(&self) -> serde_json::Value {
         let jobs_count = self
@@ -534,6 +586,25 @@ mod tests {
         assert!(jobs[0].state.last_run_at_ms.is_some());
     }
 
+    #[test]
+    fn test_record_delivery() {
+        let (_tmp, mut svc) = temp_cron_service();
+        let job = svc.add_job(
+            "reminder",
+            CronSchedule::Every { every_ms: 60000 },
+            "薬を飲む時間です",
+            true,
+            Some("telegram"),
+            Some("42"),
+        );
+
+        svc.record_delivery(&job.id, "d-1");
+        svc.record_delivery("missing", "d-2");
+
+        let jobs = svc.list_jobs(true);
+        assert_eq!(jobs[0].state.last_delivery_id.as_deref(), Some("d-1"));
+    }
+
     #[test]
     fn test_persistence() {
         let tmp = tempfile::tempdir().unwrap();
@@ -556,6 +627,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_reloads_changes_from_other_processes() {
+        let (tmp, mut gateway) = temp_cron_service();
+        let mut cli = CronService::new(tmp.path().join("cron").join("jobs.json"));
+        cli.init();
+        let job = cli.add_job("elsewhere", CronSchedule::Every { every_ms: 1000 }, "msg", false, None, None);
+
+        // The gateway's cached store picks up the new job before changing it
+        assert!(gateway.remove_job(&job.id));
+        cli.init();
+        assert!(cli.list_jobs(true).is_empty());
+    }
+
     #[test]
     fn test_cron_status() {
         let (_tmp, svc) = temp_cron_service();
@@ -0,0 +1,264 @@
+//! Follow-ups the agent schedules for itself ("check back in 2 hours about
+//! the build").
+//!
+//! The `schedule_followup` tool ([`crate::tool::followup`]) creates one-shot
+//! cron `At` jobs of kind [`FOLLOWUP_KIND`], bound to the chat the request came
+//! from. When one is due the gateway hands its note back to the agent as a
+//! system message, and the reply goes to that chat. [`FollowupPolicy`] keeps
+//! them out of the user's quiet hours and under a daily cap.
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+use crate::config::ProactiveConfig;
+use crate::service::cron::{CronJob, CronPayload, CronSchedule, CronService};
+use crate::util::tz::Timezone;
+
+/// `CronPayload::kind` of follow-up jobs.
+pub const FOLLOWUP_KIND: &str = "followup";
+
+/// Sender id of the system messages that run due follow-ups.
+pub const FOLLOWUP_SENDER: &str = "followup";
+
+/// A daily time range, possibly wrapping past midnight ("22:00-07:00").
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn parse(s: &str) -> Option<Self> {
+        let (start, end) = s.split_once('-')?;
+        let time = |s: &str| NaiveTime::parse_from_str(s.trim(), "%H:%M").ok();
+        Some(Self { start: time(start)?, end: time(end)? }).filter(|q| q.start != q.end)
+    }
+
+    pub fn contains(&self, t: NaiveTime) -> bool {
+        if self.start < self.end {
+            t >= self.start && t < self.end
+        } else {
+            t >= self.start || t < self.end
+        }
+    }
+
+    /// `at`, or the end of the quiet hours it falls in.
+    pub fn defer(&self, at: DateTime<Timezone>) -> DateTime<Timezone> {
+        if !self.contains(at.time()) {
+            return at;
+        }
+        let date = if at.time() < self.end {
+            at.date_naive()
+        } else {
+            at.date_naive() + Duration::days(1)
+        };
+        at.timezone()
+            .from_local_datetime(&date.and_time(self.end))
+            .single()
+            .unwrap_or(at)
+    }
+}
+
+/// What the gateway should do with a due follow-up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Due {
+    Send,
+    /// Quiet hours: run it at this time (ms) instead.
+    Defer(u64),
+    /// The day's cap is used up.
+    Skip,
+}
+
+/// Quiet hours and the daily cap, evaluated in the user's timezone.
+#[derive(Debug, Clone)]
+pub struct FollowupPolicy {
+    quiet: Option<QuietHours>,
+    max_per_day: u32,
+    zone: Timezone,
+}
+
+impl FollowupPolicy {
+    pub fn new(config: &ProactiveConfig, tz: Option<&str>) -> Self {
+        Self {
+            quiet: config.quiet_hours.as_deref().and_then(QuietHours::parse),
+            max_per_day: config.max_per_day,
+            zone: crate::util::tz::parse_or_local(tz),
+        }
+    }
+
+    fn local(&self, ms: u64) -> DateTime<Timezone> {
+        DateTime::<Utc>::from_timestamp_millis(ms as i64)
+            .unwrap_or_default()
+            .with_timezone(&self.zone)
+    }
+
+    /// Follow-ups sent on `day` (local), plus those still scheduled for it
+    /// when `pending` is set.
+    fn count_on(&self, cron: &CronService, day: NaiveDate, pending: bool) -> u32 {
+        cron.list_jobs(true)
+            .iter()
+            .filter(|j| j.payload.kind == FOLLOWUP_KIND)
+            .filter_map(|j| match j.state.last_status.as_deref() {
+                Some("ok") => j.state.last_run_at_ms,
+                None if pending && j.enabled => j.state.next_run_at_ms,
+                _ => None,
+            })
+            .filter(|&ms| self.local(ms).date_naive() == day)
+            .count() as u32
+    }
+
+    /// When a follow-up wanted at `at_ms` will run: pushed past quiet hours,
+    /// or an error when that day already has its share.
+    pub fn plan(&self, cron: &CronService, at_ms: u64) -> Result<u64, String> {
+        let mut at = self.local(at_ms);
+        if let Some(quiet) = self.quiet {
+            at = quiet.defer(at);
+        }
+        let day = at.date_naive();
+        if self.count_on(cron, day, true) >= self.max_per_day {
+            return Err(format!("the limit of {} follow-ups on {} is reached", self.max_per_day, day));
+        }
+        Ok(at.timestamp_millis() as u64)
+    }
+
+    /// Decide on a follow-up that is due at `now_ms`.
+    pub fn check_due(&self, cron: &CronService, now_ms: u64) -> Due {
+        let now = self.local(now_ms);
+        if let Some(quiet) = self.quiet {
+            let later = quiet.defer(now);
+            if later != now {
+                return Due::Defer(later.timestamp_millis() as u64);
+            }
+        }
+        if self.count_on(cron, now.date_naive(), false) >= self.max_per_day {
+            Due::Skip
+        } else {
+            Due::Send
+        }
+    }
+
+    /// Parse a local time: "YYYY-MM-DD HH:MM", or "HH:MM" for its next
+    /// occurrence after `now_ms`.
+    pub fn parse_at(&self, s: &str, now_ms: u64) -> Option<u64> {
+        let s = s.trim();
+        if let Ok(at) = DateTime::parse_from_rfc3339(s) {
+            return Some(at.timestamp_millis() as u64);
+        }
+        let naive = ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"]
+            .iter()
+            .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+            .or_else(|| {
+                let time = NaiveTime::parse_from_str(s, "%H:%M").ok()?;
+                let now = self.local(now_ms);
+                let today = now.date_naive().and_time(time);
+                Some(if today > now.naive_local() { today } else { today + Duration::days(1) })
+            })?;
+        let at = self.zone.from_local_datetime(&naive).single()?;
+        Some(at.timestamp_millis() as u64)
+    }
+
+    /// "10/17 15:00" in the user's timezone.
+    pub fn format(&self, ms: u64) -> String {
+        self.local(ms).format("%m/%d %H:%M").to_string()
+    }
+}
+
+/// Create a follow-up job that reports back to `channel`/`chat_id`.
+pub fn schedule(cron: &mut CronService, note: &str, at_ms: u64, channel: &str, chat_id: &str) -> CronJob {
+    let payload = CronPayload {
+        kind: FOLLOWUP_KIND.to_string(),
+        message: note.to_string(),
+        deliver: true,
+        channel: Some(channel.to_string()),
+        to: Some(chat_id.to_string()),
+    };
+    let name: String = note.chars().take(30).collect();
+    cron.add_job_with_payload(&name, CronSchedule::At { at_ms }, payload)
+}
+
+/// The system message that runs a due follow-up.
+pub fn prompt(job: &CronJob) -> String {
+    format!(
+        "Follow-up you scheduled earlier: {}\nCheck on it now and tell the user what they need to know.",
+        job.payload.message
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(quiet: Option<&str>, max_per_day: u32) -> FollowupPolicy {
+        let config = ProactiveConfig {
+            enabled: true,
+            quiet_hours: quiet.map(String::from),
+            max_per_day,
+        };
+        FollowupPolicy::new(&config, Some("UTC+9"))
+    }
+
+    fn jst(s: &str) -> u64 {
+        DateTime::parse_from_rfc3339(&format!("{}+09:00", s)).unwrap().timestamp_millis() as u64
+    }
+
+    fn temp_cron() -> (tempfile::TempDir, CronService) {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut cron = CronService::new(tmp.path().join("jobs.json"));
+        cron.init();
+        (tmp, cron)
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let quiet = QuietHours::parse("22:00-07:00").unwrap();
+        let t = |s| NaiveTime::parse_from_str(s, "%H:%M").unwrap();
+        assert!(quiet.contains(t("23:30")) && quiet.contains(t("06:59")));
+        assert!(!quiet.contains(t("07:00")) && !quiet.contains(t("12:00")));
+        assert!(QuietHours::parse("13:00-14:00").unwrap().contains(t("13:30")));
+        assert_eq!(QuietHours::parse("22:00"), None);
+        assert_eq!(QuietHours::parse("09:00-09:00"), None);
+    }
+
+    #[test]
+    fn test_plan_defers_past_quiet_hours_and_caps_the_day() {
+        let (_tmp, mut cron) = temp_cron();
+        let policy = policy(Some("22:00-07:00"), 2);
+
+        assert_eq!(policy.plan(&cron, jst("2026-10-17T15:00:00")), Ok(jst("2026-10-17T15:00:00")));
+        // Late at night and early morning both move to 07:00 the next morning.
+        assert_eq!(policy.plan(&cron, jst("2026-10-17T23:10:00")), Ok(jst("2026-10-18T07:00:00")));
+        assert_eq!(policy.plan(&cron, jst("2026-10-18T05:00:00")), Ok(jst("2026-10-18T07:00:00")));
+
+        schedule(&mut cron, "check the build", jst("2099-10-17T15:00:00"), "telegram", "42");
+        schedule(&mut cron, "ask about the trip", jst("2099-10-17T18:00:00"), "telegram", "42");
+        assert!(policy.plan(&cron, jst("2099-10-17T20:00:00")).is_err());
+        assert!(policy.plan(&cron, jst("2099-10-18T09:00:00")).is_ok());
+    }
+
+    #[test]
+    fn test_check_due() {
+        let (_tmp, mut cron) = temp_cron();
+        let policy = policy(Some("22:00-07:00"), 1);
+        assert_eq!(
+            policy.check_due(&cron, jst("2026-10-17T23:00:00")),
+            Due::Defer(jst("2026-10-18T07:00:00"))
+        );
+        assert_eq!(policy.check_due(&cron, jst("2026-10-17T12:00:00")), Due::Send);
+
+        let job = schedule(&mut cron, "check the build", jst("2099-01-01T12:00:00"), "telegram", "42");
+        cron.mark_executed(&job.id, "ok", None);
+        let now = cron.list_jobs(true)[0].state.last_run_at_ms.unwrap();
+        // Without quiet hours, so the real clock can't land in them.
+        assert_eq!(self::policy(None, 1).check_due(&cron, now), Due::Skip);
+    }
+
+    #[test]
+    fn test_parse_at() {
+        let policy = policy(None, 5);
+        let now = jst("2026-10-17T15:00:00");
+        assert_eq!(policy.parse_at("2026-10-18 09:30", now), Some(jst("2026-10-18T09:30:00")));
+        assert_eq!(policy.parse_at("16:00", now), Some(jst("2026-10-17T16:00:00")));
+        assert_eq!(policy.parse_at("09:00", now), Some(jst("2026-10-18T09:00:00")));
+        assert_eq!(policy.parse_at("soon", now), None);
+        assert_eq!(policy.format(now), "10/17 15:00");
+    }
+}
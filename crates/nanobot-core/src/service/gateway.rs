@@ -2,37 +2,52 @@ use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex};
 use tracing::{error, info, warn};
 
+use crate::agent::builder::AgentBuilder;
 use crate::agent::subagent::SubagentManager;
 use crate::agent::AgentLoop;
 use crate::bus::MessageBus;
-use crate::channel::telegram::TelegramChannel;
-use crate::channel::discord::DiscordChannel;
-use crate::channel::whatsapp::WhatsAppChannel;
-use crate::channel::feishu::FeishuChannel;
-use crate::channel::line::LineChannel;
-use crate::channel::slack::SlackChannel;
-use crate::channel::signal::SignalChannel;
-use crate::channel::imessage::IMessageChannel;
-use crate::channel::teams::TeamsChannel;
-use crate::channel::google_chat::GoogleChatChannel;
-use crate::channel::matrix::MatrixChannel;
-use crate::channel::zalo::ZaloChannel;
-use crate::channel::Channel;
+use crate::channel::presence::PresenceStore;
+use crate::channel::registry::{ChannelContext, ChannelRegistry};
 use crate::config::Config;
+use crate::delivery::DeliveryTracker;
+use crate::faq::Faq;
+use crate::hooks::ResponseHooks;
+use crate::memory::consolidate::{self, Consolidator};
+use crate::memory::MemoryStore;
+use crate::onboarding::Onboarding;
 use crate::provider;
-use crate::service::cron::CronService;
+use crate::rules::{GatewayRuleHost, RulesEngine};
+use crate::service::backup::{self, BackupPlan, BackupSources};
+use crate::service::cron::{CronSchedule, CronService};
+use crate::service::followup::{self, Due, FollowupPolicy};
 use crate::service::heartbeat;
+use crate::service::reminder;
+use crate::service::supervisor::{self, supervise};
+use crate::service::triage::{self, Triage};
+use crate::tool::stats::ToolStatsStore;
 use crate::types::{InboundMessage, OutboundMessage};
+use crate::webhook::{self, WebhookEvent, WebhookOutbox};
+use crate::workflow::{self, AgentHost, WorkflowEngine};
+use crate::util::http::{self, ClientClass};
 
 /// Start the full nanobot gateway with all components.
 pub async fn run_gateway(config: Config) -> anyhow::Result<()> {
+    run_gateway_with(config, ChannelRegistry::with_builtins()).await
+}
+
+/// Start the gateway with the channels from `registry` (bundled channels
+/// plus any registered by the embedding application).
+pub async fn run_gateway_with(config: Config, registry: ChannelRegistry) -> anyhow::Result<()> {
+    supervisor::install_panic_hook();
     let workspace = config.workspace_path();
     std::fs::create_dir_all(&workspace)?;
+    crate::util::http::configure_clients(&config.http);
+    crate::util::http::configure_web(&config.tools.web.fetch, &workspace);
 
     // Create message bus
     let bus = MessageBus::new(256);
     let inbound_tx = bus.inbound_sender();
-    let _outbound_tx = bus.outbound_sender();
+    let outbound_tx = bus.outbound_sender();
 
     // Create provider
     let model = config.agents.defaults.model.clone();
@@ -53,12 +68,72 @@ pub async fn run_gateway(config: Config) -> anyhow::Result<()> {
         &model,
     ));
 
+    // Load the local fallback model now instead of on the first failover
+    #[cfg(feature = "local-fallback")]
+    if let Some(local) = provider::local::LocalProvider::from_env() {
+        provider::local::LocalProvider::spawn_keep_alive();
+        tokio::spawn(async move {
+            match local.warm_up().await {
+                Ok(took) => info!("Local model {} warm ({} ms)", local.name(), took.as_millis()),
+                Err(e) => warn!("Local model warm-up failed: {}", e),
+            }
+        });
+    }
+
     // Create cron service
     let cron_store_path = crate::config::get_data_dir().join("cron").join("jobs.json");
     let mut cron_service = CronService::new(cron_store_path);
     cron_service.init();
     let cron_service = Arc::new(Mutex::new(cron_service));
 
+    // Delivery tracking for outbound messages (cron reminders, replies)
+    let deliveries = Arc::new(Mutex::new(DeliveryTracker::new(DeliveryTracker::default_path())));
+
+    // Outbound webhooks for external automation
+    let webhooks = Arc::new(Mutex::new(WebhookOutbox::new(
+        WebhookOutbox::default_path(),
+        config.webhooks.clone(),
+    )));
+
+    // Workflows: cron triggers become cron jobs, runs left mid-step by a crash can be resumed
+    let workflows = Arc::new(WorkflowEngine::for_workspace(&workspace));
+    workflows.sync_cron(&mut *cron_service.lock().await, config.agents.defaults.timezone.as_deref());
+    let interrupted = workflows.recover_interrupted();
+    if interrupted > 0 {
+        warn!("{} workflow run(s) were interrupted; resume them with `nanobot workflow resume`", interrupted);
+    }
+
+    // Inbox triage (<workspace>/triage.yaml) on its daily schedule
+    let triage = Arc::new(Triage::for_workspace(&workspace));
+    triage.sync_cron(&mut *cron_service.lock().await, config.agents.defaults.timezone.as_deref());
+
+    // Daily memory notes merged into long-term memory on agents.memory.schedule
+    consolidate::sync_cron(&config.agents.memory, &mut *cron_service.lock().await, config.agents.defaults.timezone.as_deref());
+    let consolidator = Consolidator::from_config(&config.agents.memory);
+    let memory_job = match consolidator.provider_for(&config, llm_provider.clone()) {
+        Ok((provider, model)) if config.agents.memory.consolidate => {
+            let memory = MemoryStore::new(&workspace).with_timezone(config.agents.defaults.timezone.clone());
+            Some(Arc::new((consolidator, provider, model, memory)))
+        }
+        Ok(_) => None,
+        Err(e) => {
+            warn!("Memory consolidation disabled: {}", e);
+            None
+        }
+    };
+
+    // Presence of users linked across channels (channels.links)
+    let presence = Arc::new(Mutex::new(PresenceStore::new(
+        PresenceStore::default_path(),
+        &config.channels.links,
+    )));
+
+    // Event rules (<workspace>/rules.yaml)
+    let rules = Arc::new(RulesEngine::for_workspace(&workspace));
+    if let Err(e) = rules.load() {
+        warn!("Invalid {}: {}", rules.rules_path().display(), e);
+    }
+
     // Create subagent manager
     let subagent_manager = Arc::new(SubagentManager::new(
         llm_provider.clone(),
@@ -91,109 +166,34 @@ pub async fn run_gateway(config: Config) -> anyhow::Result<()> {
         config.tools.exec_config.clone(),
         config.tools.restrict_to_workspace,
         Some(subagent_manager),
-    );
+    )
+    .with_cron_service(cron_service.clone())
+    .with_timezone(config.agents.defaults.timezone.clone())
+    .with_webhooks(webhooks.clone())
+    .with_workflows(workflows.clone())
+    .with_rules(rules.clone())
+    .with_followups(&config.agents.proactive)
+    .with_speculative(&config.agents.response)
+    .with_escalation(&config.agents.escalation)
+    .with_handoff(&config.agents.handoff)
+    .with_response_hooks(ResponseHooks::from_config(&config.agents.hooks))
+    .with_faq(Faq::for_workspace(&workspace))
+    .with_onboarding(Onboarding::for_workspace(&workspace))
+    .with_web_search(&config.tools.web.search)
+    .with_ocr(&config.tools.ocr)
+    .with_file_policy(&config.tools.files)
+    .with_tool_policy(&config.tools.policy, None)
+    .with_tool_stats(Arc::new(ToolStatsStore::new(ToolStatsStore::default_path())))
+    .with_presence(presence);
 
     // Create channels
     let (_channel_inbound_tx, _channel_inbound_rx) = mpsc::channel::<InboundMessage>(256);
     let (_agent_outbound_tx, _agent_outbound_rx) = mpsc::channel::<OutboundMessage>(256);
 
-    let mut channels: Vec<Box<dyn Channel>> = Vec::new();
-
-    if config.channels.telegram.enabled {
-        info!("Telegram channel enabled");
-        channels.push(Box::new(TelegramChannel::new(
-            config.channels.telegram.clone(),
-            inbound_tx.clone(),
-        )));
-    }
-
-    if config.channels.discord.enabled {
-        info!("Discord channel enabled");
-        channels.push(Box::new(DiscordChannel::new(
-            config.channels.discord.clone(),
-            inbound_tx.clone(),
-        )));
-    }
-
-    if config.channels.whatsapp.enabled {
-        info!("WhatsApp channel enabled");
-        channels.push(Box::new(WhatsAppChannel::new(
-            config.channels.whatsapp.clone(),
-            inbound_tx.clone(),
-        )));
-    }
-
-    if config.channels.feishu.enabled {
-        info!("Feishu channel enabled");
-        channels.push(Box::new(FeishuChannel::new(
-            config.channels.feishu.clone(),
-            inbound_tx.clone(),
-        )));
-    }
-
-    if config.channels.line.enabled {
-        info!("LINE channel enabled");
-        channels.push(Box::new(LineChannel::new(
-            config.channels.line.clone(),
-            inbound_tx.clone(),
-        )));
-    }
-
-    if config.channels.slack.enabled {
-        info!("Slack channel enabled");
-        channels.push(Box::new(SlackChannel::new(
-            config.channels.slack.clone(),
-            inbound_tx.clone(),
-        )));
-    }
-
-    if config.channels.signal.enabled {
-        info!("Signal channel enabled");
-        channels.push(Box::new(SignalChannel::new(
-            config.channels.signal.clone(),
-            inbound_tx.clone(),
-        )));
-    }
-
-    if config.channels.imessage.enabled {
-        info!("iMessage channel enabled");
-        channels.push(Box::new(IMessageChannel::new(
-            config.channels.imessage.clone(),
-            inbound_tx.clone(),
-        )));
-    }
-
-    if config.channels.teams.enabled {
-        info!("MS Teams channel enabled");
-        channels.push(Box::new(TeamsChannel::new(
-            config.channels.teams.clone(),
-            inbound_tx.clone(),
-        )));
-    }
-
-    if config.channels.google_chat.enabled {
-        info!("Google Chat channel enabled");
-        channels.push(Box::new(GoogleChatChannel::new(
-            config.channels.google_chat.clone(),
-            inbound_tx.clone(),
-        )));
-    }
-
-    if config.channels.matrix.enabled {
-        info!("Matrix channel enabled");
-        channels.push(Box::new(MatrixChannel::new(
-            config.channels.matrix.clone(),
-            inbound_tx.clone(),
-        )));
-    }
-
-    if config.channels.zalo.enabled {
-        info!("Zalo channel enabled");
-        channels.push(Box::new(ZaloChannel::new(
-            config.channels.zalo.clone(),
-            inbound_tx.clone(),
-        )));
-    }
+    let channels = registry.build(&ChannelContext {
+        config: &config,
+        inbound_tx: inbound_tx.clone(),
+    });
 
     let enabled_names: Vec<&str> = channels.iter().map(|c| c.name()).collect();
     if !enabled_names.is_empty() {
@@ -205,32 +205,209 @@ pub async fn run_gateway(config: Config) -> anyhow::Result<()> {
     // Start heartbeat in background
     let hb_workspace = workspace.clone();
     let hb_interval = 30 * 60; // 30 minutes
-    tokio::spawn(async move {
-        loop {
-            tokio::time::sleep(std::time::Duration::from_secs(hb_interval)).await;
-            if heartbeat::should_trigger(&hb_workspace) {
-                info!("Heartbeat: checking for tasks...");
-                // Would trigger agent.process_direct here
-            } else {
-                tracing::debug!("Heartbeat: no tasks");
+    supervise("heartbeat", move || {
+        let hb_workspace = hb_workspace.clone();
+        async move {
+            loop {
+                tokio::time::sleep(std::time::Duration::from_secs(hb_interval)).await;
+                if heartbeat::should_trigger(&hb_workspace) {
+                    info!("Heartbeat: checking for tasks...");
+                    // Would trigger agent.process_direct here
+                } else {
+                    tracing::debug!("Heartbeat: no tasks");
+                }
             }
         }
     });
 
     // Start cron scheduler in background
     let cron_clone = cron_service.clone();
-    tokio::spawn(async move {
-        loop {
-            tokio::time::sleep(std::time::Duration::from_secs(10)).await;
-            let due_jobs = {
-                let mut cron = cron_clone.lock().await;
-                cron.get_due_jobs()
-            };
-            for job in due_jobs {
-                info!("Cron: executing job '{}' ({})", job.name, job.id);
-                // Would trigger agent.process_direct here
-                let mut cron = cron_clone.lock().await;
-                cron.mark_executed(&job.id, "ok", None);
+    let cron_deliveries = deliveries.clone();
+    let cron_outbound = outbound_tx.clone();
+    let cron_webhooks = webhooks.clone();
+    let cron_workflows = workflows.clone();
+    let cron_inbound = inbound_tx.clone();
+    let cron_provider = llm_provider.clone();
+    let cron_model = model.clone();
+    let followup_policy = FollowupPolicy::new(&config.agents.proactive, config.agents.defaults.timezone.as_deref());
+    let backup_sources = BackupSources::from_config(&config);
+    supervise("cron scheduler", move || {
+        let (cron_clone, cron_deliveries, cron_outbound) = (cron_clone.clone(), cron_deliveries.clone(), cron_outbound.clone());
+        let (cron_webhooks, cron_workflows, cron_inbound) = (cron_webhooks.clone(), cron_workflows.clone(), cron_inbound.clone());
+        let (cron_provider, cron_model, triage) = (cron_provider.clone(), cron_model.clone(), triage.clone());
+        let memory_job = memory_job.clone();
+        let (followup_policy, backup_sources) = (followup_policy.clone(), backup_sources.clone());
+        async move {
+            loop {
+                tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+                let due_jobs = {
+                    let mut cron = cron_clone.lock().await;
+                    cron.get_due_jobs()
+                };
+                for job in due_jobs {
+                    info!("Cron: executing job '{}' ({})", job.name, job.id);
+                    let payload = &job.payload;
+                    let target = payload.channel.as_deref().zip(payload.to.as_deref());
+                    let (status, error, delivery_id) = match target {
+                        _ if payload.kind == backup::BACKUP_KIND => {
+                            let plan = BackupPlan::from_message(&payload.message);
+                            match backup::run_plan(backup_sources.clone(), &plan).await {
+                                Ok(dest) => {
+                                    info!("Cron: backup written to {}", dest);
+                                    ("ok", None, None)
+                                }
+                                Err(e) => ("error", Some(format!("backup: {e}")), None),
+                            }
+                        }
+                        _ if payload.kind == workflow::WORKFLOW_KIND => {
+                            match cron_workflows.enqueue(&payload.message, workflow::TRIGGER_CRON, serde_json::Value::Null, None) {
+                                Ok(_) => ("ok", None, None),
+                                Err(e) => ("error", Some(format!("workflow: {e}")), None),
+                            }
+                        }
+                        // Triage takes a while (mail API + model); post the summary when done
+                        _ if payload.kind == triage::TRIAGE_KIND => {
+                            let (triage, provider, model) = (triage.clone(), cron_provider.clone(), cron_model.clone());
+                            let outbound = cron_outbound.clone();
+                            tokio::spawn(async move {
+                                match triage.run_scheduled(provider.as_ref(), &model).await {
+                                    Ok(Some((target, summary))) => {
+                                        if let Err(e) = outbound.send(OutboundMessage::new(target.channel, target.to, summary)).await {
+                                            warn!("Triage: summary not sent: {}", e);
+                                        }
+                                    }
+                                    Ok(None) => {}
+                                    Err(e) => error!("Triage failed: {}", e),
+                                }
+                            });
+                            ("ok", None, None)
+                        }
+                        _ if payload.kind == consolidate::CONSOLIDATE_KIND => match memory_job.clone() {
+                            Some(job) => {
+                                tokio::spawn(async move {
+                                    let (consolidator, provider, model, memory) = &*job;
+                                    match consolidator.run(provider.as_ref(), model, memory, false).await {
+                                        Ok(Some(_)) => info!("Cron: long-term memory consolidated with {}", model),
+                                        Ok(None) => {}
+                                        Err(e) => error!("Memory consolidation failed: {}", e),
+                                    }
+                                });
+                                ("ok", None, None)
+                            }
+                            None => ("error", Some("memory consolidation is disabled".to_string()), None),
+                        },
+                        // Follow-ups go back to the agent, whose reply is sent to the chat
+                        _ if payload.kind == followup::FOLLOWUP_KIND => {
+                            let now = chrono::Utc::now().timestamp_millis() as u64;
+                            let due = followup_policy.check_due(&*cron_clone.lock().await, now);
+                            match (due, target) {
+                                (Due::Defer(at_ms), _) => {
+                                    info!("Cron: follow-up {} deferred past quiet hours", job.id);
+                                    cron_clone.lock().await.reschedule_job(&job.id, CronSchedule::At { at_ms });
+                                    continue;
+                                }
+                                (Due::Skip, _) => ("skipped", Some("daily follow-up limit reached".to_string()), None),
+                                (Due::Send, Some((channel, to))) => {
+                                    let msg = InboundMessage::new(
+                                        "system",
+                                        followup::FOLLOWUP_SENDER,
+                                        format!("{}:{}", channel, to),
+                                        followup::prompt(&job),
+                                    );
+                                    match cron_inbound.try_send(msg) {
+                                        Ok(()) => ("ok", None, None),
+                                        Err(e) => ("error", Some(format!("inbound queue: {e}")), None),
+                                    }
+                                }
+                                (Due::Send, None) => ("error", Some("follow-up has no chat".to_string()), None),
+                            }
+                        }
+                        Some((channel, to)) if payload.deliver => {
+                            let text = if payload.kind == reminder::REMINDER_KIND {
+                                reminder::notification_text(&job)
+                            } else {
+                                payload.message.clone()
+                            };
+                            let mut msg = OutboundMessage::new(channel, to, text);
+                            let source = format!("cron:{}", job.id);
+                            let id = cron_deliveries.lock().await.enqueue(&mut msg, Some(&source));
+                            match cron_outbound.try_send(msg) {
+                                Ok(()) => ("ok", None, Some(id)),
+                                Err(e) => {
+                                    let err = format!("outbound queue: {e}");
+                                    cron_deliveries.lock().await.mark_failed(&id, &err);
+                                    ("error", Some(err), Some(id))
+                                }
+                            }
+                        }
+                        // Would trigger agent.process_direct here
+                        _ => ("ok", None, None),
+                    };
+                    {
+                        let mut cron = cron_clone.lock().await;
+                        cron.mark_executed(&job.id, status, error.as_deref());
+                        if let Some(ref id) = delivery_id {
+                            cron.record_delivery(&job.id, id);
+                        }
+                    }
+                    cron_webhooks.lock().await.emit(WebhookEvent::CronCompleted, serde_json::json!({
+                        "jobId": job.id,
+                        "name": job.name,
+                        "kind": payload.kind,
+                        "status": status,
+                        "error": error,
+                        "deliveryId": delivery_id,
+                    }));
+                }
+            }
+        }
+    });
+
+    // Deliver queued webhooks, retrying failures with backoff
+    if webhooks.lock().await.is_enabled() {
+        let flush_webhooks = webhooks.clone();
+        let client = http::client_for(ClientClass::Webhook);
+        supervise("webhook delivery", move || {
+            let (flush_webhooks, client) = (flush_webhooks.clone(), client.clone());
+            async move {
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    webhook::flush(&flush_webhooks, &client).await;
+                }
+            }
+        });
+    }
+
+    // Execute queued workflow runs (cron, webhook, /workflow) and resume delays.
+    // Steps run on a dedicated agent so a long workflow doesn't block chat.
+    match AgentBuilder::from_config(&config).and_then(|b| b.cron_service(cron_service.clone()).build()) {
+        Ok(workflow_agent) => {
+            let host = Arc::new(AgentHost::new(workflow_agent, outbound_tx.clone()));
+            let runner = workflows.clone();
+            supervise("workflow runner", move || {
+                let (host, runner) = (host.clone(), runner.clone());
+                async move {
+                    loop {
+                        runner.run_due(&*host).await;
+                        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    }
+                }
+            });
+        }
+        Err(e) => warn!("Workflow runner disabled: {}", e),
+    }
+
+    // Check heartbeats and execute queued rule firings (including those
+    // raised by the HTTP server, e.g. credits running low)
+    let rule_host = Arc::new(GatewayRuleHost::new(outbound_tx.clone(), workflows.clone()));
+    let rule_engine = rules.clone();
+    supervise("rules", move || {
+        let (rule_host, rule_engine) = (rule_host.clone(), rule_engine.clone());
+        async move {
+            loop {
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                rule_engine.check_heartbeats();
+                rule_engine.run_pending(&*rule_host).await;
             }
         }
     });
@@ -242,12 +419,15 @@ pub async fn run_gateway(config: Config) -> anyhow::Result<()> {
     // For now, we create the necessary channels and run the agent
     let (_agent_inbound_tx, agent_inbound_rx) = mpsc::channel::<InboundMessage>(256);
 
-    // Start channel tasks
-    for mut channel in channels {
-        tokio::spawn(async move {
-            if let Err(e) = channel.start().await {
-                error!("Channel {} error: {}", channel.name(), e);
-            }
+    // Start channel tasks; a channel that fails or panics is started again
+    let registry = Arc::new(registry);
+    for channel in channels {
+        let name = format!("Channel {}", channel.name());
+        let channel = Arc::new(Mutex::new(channel));
+        let registry = registry.clone();
+        supervise(name, move || {
+            let (registry, channel) = (registry.clone(), channel.clone());
+            async move { registry.start(channel.lock().await.as_mut()).await }
         });
     }
 
@@ -0,0 +1,293 @@
+//! gRPC API (`grpc` feature) for programmatic embedding.
+//!
+//! Exposes the `Chat`, `Sessions` and `Tools` services from
+//! `proto/nanobot.proto` on top of the same [`AppState`] as the HTTP API.
+//! `Chat.Stream` is served by the HTTP router in-process (the
+//! `/api/v1/chat/stream` handler), so routing, credits and auth behave
+//! exactly as over REST; its SSE events are re-emitted as [`pb::ChatEvent`]s.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+use tower::ServiceExt;
+use tracing::info;
+
+use super::http::{authenticate_admin, create_router, drain_sse_frames, AppState};
+
+pub mod pb {
+    tonic::include_proto!("nanobot.v1");
+}
+
+use pb::chat_server::{Chat, ChatServer};
+use pb::sessions_server::{Sessions, SessionsServer};
+use pb::tools_server::{Tools, ToolsServer};
+
+/// Metadata forwarded to the HTTP handlers as headers.
+const FORWARDED_METADATA: &[&str] = &["authorization", "x-session-id", "accept-language", "user-agent"];
+
+/// Copy forwarded request metadata into an HTTP header map.
+fn forwarded_headers(metadata: &tonic::metadata::MetadataMap) -> axum::http::HeaderMap {
+    let mut headers = axum::http::HeaderMap::new();
+    for name in FORWARDED_METADATA {
+        if let Some(value) = metadata.get(*name).and_then(|v| v.to_str().ok()) {
+            if let Ok(value) = axum::http::HeaderValue::from_str(value) {
+                headers.insert(*name, value);
+            }
+        }
+    }
+    headers
+}
+
+/// Map a JSON SSE event (`{"type": ..., ...}`) to a [`pb::ChatEvent`].
+fn chat_event(data: &str) -> pb::ChatEvent {
+    let value: serde_json::Value = serde_json::from_str(data).unwrap_or_else(|_| {
+        serde_json::json!({ "type": "content_chunk", "content": data })
+    });
+    let field = |key: &str| value.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    pb::ChatEvent {
+        r#type: field("type"),
+        content: field("content"),
+        tool: value.get("tool").or_else(|| value.get("name"))
+            .and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+        json: data.to_string(),
+    }
+}
+
+fn status_for(code: axum::http::StatusCode, message: String) -> Status {
+    use axum::http::StatusCode;
+    match code {
+        StatusCode::UNAUTHORIZED => Status::unauthenticated(message),
+        StatusCode::FORBIDDEN => Status::permission_denied(message),
+        StatusCode::PAYMENT_REQUIRED => Status::resource_exhausted(message),
+        StatusCode::TOO_MANY_REQUESTS => Status::resource_exhausted(message),
+        StatusCode::BAD_REQUEST | StatusCode::PAYLOAD_TOO_LARGE => Status::invalid_argument(message),
+        StatusCode::NOT_FOUND => Status::not_found(message),
+        _ => Status::internal(message),
+    }
+}
+
+pub struct ChatService {
+    router: axum::Router,
+}
+
+type ChatEventStream = Pin<Box<dyn Stream<Item = Result<pb::ChatEvent, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Chat for ChatService {
+    type StreamStream = ChatEventStream;
+
+    async fn stream(&self, request: Request<pb::ChatRequest>) -> Result<Response<Self::StreamStream>, Status> {
+        let headers = forwarded_headers(request.metadata());
+        let req = request.into_inner();
+        if req.message.trim().is_empty() {
+            return Err(Status::invalid_argument("message is required"));
+        }
+        let body = serde_json::json!({
+            "message": req.message,
+            "session_id": if req.session_id.is_empty() { "api:default".to_string() } else { req.session_id },
+            "channel": if req.channel.is_empty() { "grpc".to_string() } else { req.channel },
+            "model": req.model,
+            "device": req.device,
+            "mode": req.mode,
+            "language": req.language,
+            "temperature": req.temperature,
+            "max_tokens": req.max_tokens,
+        });
+
+        let mut http_req = axum::http::Request::builder()
+            .method("POST")
+            .uri("/api/v1/chat/stream")
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .map_err(|e| Status::internal(e.to_string()))?;
+        http_req.headers_mut().extend(headers);
+
+        let resp = self.router.clone().oneshot(http_req).await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        if !resp.status().is_success() {
+            let code = resp.status();
+            let bytes = axum::body::to_bytes(resp.into_body(), 64 * 1024).await.unwrap_or_default();
+            return Err(status_for(code, String::from_utf8_lossy(&bytes).into_owned()));
+        }
+
+        let mut body = resp.into_body().into_data_stream();
+        let stream = async_stream::stream! {
+            let mut buf = String::new();
+            while let Some(chunk) = body.next().await {
+                match chunk {
+                    Ok(bytes) => {
+                        buf.push_str(&String::from_utf8_lossy(&bytes).replace("\r\n", "\n"));
+                        for data in drain_sse_frames(&mut buf) {
+                            yield Ok(chat_event(&data));
+                        }
+                    }
+                    Err(e) => {
+                        yield Err(Status::internal(e.to_string()));
+                        break;
+                    }
+                }
+            }
+        };
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+pub struct SessionsService {
+    state: Arc<AppState>,
+}
+
+#[tonic::async_trait]
+impl Sessions for SessionsService {
+    async fn list(&self, _request: Request<pb::ListSessionsRequest>) -> Result<Response<pb::ListSessionsResponse>, Status> {
+        let list = self.state.sessions.lock().await.list_sessions();
+        let field = |v: &serde_json::Value, key: &str| v.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        let sessions = list.iter().map(|s| pb::SessionSummary {
+            key: field(s, "key"),
+            created_at: field(s, "created_at"),
+            updated_at: field(s, "updated_at"),
+        }).collect();
+        Ok(Response::new(pb::ListSessionsResponse { sessions }))
+    }
+
+    async fn get(&self, request: Request<pb::GetSessionRequest>) -> Result<Response<pb::Session>, Status> {
+        let req = request.into_inner();
+        if req.session_id.is_empty() {
+            return Err(Status::invalid_argument("session_id is required"));
+        }
+        let mut sessions = self.state.sessions.lock().await;
+        let session = sessions.refresh(&req.session_id);
+        let skip = match req.limit as usize {
+            0 => 0,
+            n => session.messages.len().saturating_sub(n),
+        };
+        Ok(Response::new(pb::Session {
+            key: session.key.clone(),
+            messages: session.messages[skip..].iter().map(|m| pb::SessionMessage {
+                role: m.role.clone(),
+                content: m.content.clone(),
+                timestamp: m.timestamp.clone().unwrap_or_default(),
+            }).collect(),
+            created_at: session.created_at.to_rfc3339(),
+            updated_at: session.updated_at.to_rfc3339(),
+            metadata_json: serde_json::to_string(&session.metadata).unwrap_or_default(),
+        }))
+    }
+
+    async fn delete(&self, request: Request<pb::DeleteSessionRequest>) -> Result<Response<pb::DeleteSessionResponse>, Status> {
+        let req = request.into_inner();
+        let deleted = self.state.sessions.lock().await.delete(&req.session_id);
+        if !deleted {
+            return Err(Status::not_found("Session not found"));
+        }
+        Ok(Response::new(pb::DeleteSessionResponse { deleted }))
+    }
+}
+
+pub struct ToolsService {
+    state: Arc<AppState>,
+}
+
+#[tonic::async_trait]
+impl Tools for ToolsService {
+    async fn list(&self, _request: Request<pb::ListToolsRequest>) -> Result<Response<pb::ListToolsResponse>, Status> {
+        let tools = self.state.tool_registry.get_definitions().iter().map(|def| {
+            let f = &def["function"];
+            pb::ToolDefinition {
+                name: f["name"].as_str().unwrap_or_default().to_string(),
+                description: f["description"].as_str().unwrap_or_default().to_string(),
+                parameters_json: f["parameters"].to_string(),
+            }
+        }).collect();
+        Ok(Response::new(pb::ListToolsResponse { tools }))
+    }
+
+    async fn execute(&self, request: Request<pb::ExecuteToolRequest>) -> Result<Response<pb::ExecuteToolResponse>, Status> {
+        let headers = forwarded_headers(request.metadata());
+        if authenticate_admin(&self.state, &headers).await.is_none() {
+            return Err(Status::permission_denied("Admin only"));
+        }
+        let req = request.into_inner();
+        let arguments: HashMap<String, serde_json::Value> = if req.arguments_json.trim().is_empty() {
+            HashMap::new()
+        } else {
+            serde_json::from_str(&req.arguments_json)
+                .map_err(|e| Status::invalid_argument(format!("arguments_json: {}", e)))?
+        };
+        info!("gRPC tool execute: {}", req.name);
+        let result = self.state.tool_registry.execute(&req.name, &arguments).await;
+        Ok(Response::new(pb::ExecuteToolResponse {
+            is_error: result.starts_with("[TOOL_ERROR]"),
+            result,
+        }))
+    }
+}
+
+/// Bearer-token check against `gateway.apiTokens`, as for the HTTP API.
+fn check_token(state: &AppState, metadata: &tonic::metadata::MetadataMap) -> Result<(), Status> {
+    let token = metadata
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(|| Status::unauthenticated("Missing or invalid authorization metadata"))?;
+    if !state.config.gateway.api_tokens.iter().any(|t| t == token) {
+        return Err(Status::unauthenticated("Invalid API token"));
+    }
+    Ok(())
+}
+
+/// Serve the gRPC API on `addr`. With `require_auth` and configured
+/// `gateway.apiTokens`, every call needs a valid bearer token.
+pub async fn serve(addr: &str, state: Arc<AppState>, require_auth: bool) -> anyhow::Result<()> {
+    let addr: std::net::SocketAddr = addr.parse()?;
+    let enforce = require_auth && !state.config.gateway.api_tokens.is_empty();
+    let auth_state = state.clone();
+    let interceptor = move |req: Request<()>| -> Result<Request<()>, Status> {
+        if enforce {
+            check_token(&auth_state, req.metadata())?;
+        }
+        Ok(req)
+    };
+
+    let chat = ChatService { router: create_router(state.clone()) };
+    let sessions = SessionsService { state: state.clone() };
+    let tools = ToolsService { state };
+
+    info!("gRPC server listening on {}", addr);
+    tonic::transport::Server::builder()
+        .add_service(ChatServer::with_interceptor(chat, interceptor.clone()))
+        .add_service(SessionsServer::with_interceptor(sessions, interceptor.clone()))
+        .add_service(ToolsServer::with_interceptor(tools, interceptor))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drain_sse_frames() {
+        let mut buf = String::from("data: {\"type\":\"start\"}\n\ndata: {\"type\":\"content_chunk\",\"content\":\"hi\"}\n\ndata: {\"ty");
+        let frames = drain_sse_frames(&mut buf);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(buf, "data: {\"ty");
+
+        let event = chat_event(&frames[1]);
+        assert_eq!(event.r#type, "content_chunk");
+        assert_eq!(event.content, "hi");
+    }
+
+    #[test]
+    fn test_chat_event_tool_and_plain_text() {
+        let event = chat_event(r#"{"type":"tool_start","tool":"web_search"}"#);
+        assert_eq!(event.tool, "web_search");
+
+        let event = chat_event("plain text");
+        assert_eq!(event.r#type, "content_chunk");
+        assert_eq!(event.content, "plain text");
+    }
+}
@@ -25,18 +25,29 @@ use crate::channel::feishu::FeishuChannel;
 use crate::channel::google_chat::GoogleChatChannel;
 use crate::channel::line::LineChannel;
 use crate::channel::teams::TeamsChannel;
-use crate::channel::telegram::TelegramChannel;
+use crate::channel::telegram::{TelegramChannel, TelegramMessageReaction};
 #[allow(unused_imports)]
 use crate::channel::whatsapp::WhatsAppChannel;
 #[allow(unused_imports)]
 use crate::channel::zalo::ZaloChannel;
+use crate::agent::length::ResponseStyle;
+use crate::agent::prompt::PromptCompiler;
 use crate::config::Config;
+use crate::error::ErrorCode;
+use crate::feedback::{reaction_action, FeedbackRecord, Rating, ReactionAction};
+#[cfg(feature = "dynamodb-backend")]
+use crate::memory::consolidate::Consolidator;
 use crate::provider::{self, LlmProvider};
+use crate::session::locale::Locale;
 use crate::session::store::SessionStore;
-use crate::types::Message;
+use crate::session::turn::TurnMeta;
+use crate::types::{ChatMode, ChatOptions, CompletionResponse, Message};
+use crate::util::http::{client_for, ClientClass};
 #[cfg(feature = "stripe")]
 use crate::service::stripe::{process_webhook_event, verify_webhook_signature};
 use crate::service::a2a;
+use crate::service::chat_queue::{Permit, Queued, Ticket};
+use crate::service::stream_resume::{parse_event_id, StreamBuffers};
 
 #[cfg(feature = "dynamodb-backend")]
 use aws_sdk_dynamodb::types::AttributeValue;
@@ -64,6 +75,9 @@ const ERR_MESSAGE_EMPTY: &str = "メッセージを入力してください / Me
 const ERR_RATE_LIMIT: &str = "レート制限に達しました。1時間後に再度お試しください。/ Rate limit exceeded. Please try again in 1 hour.";
 const ERR_LOCAL_NOT_CONFIGURED: &str = "Local mode requested but local model is not configured. Set LOCAL_MODEL_URL environment variable.";
 
+/// Search hits listed as sources of a researcher answer that read no page.
+const MAX_RESEARCH_SOURCES: usize = 5;
+
 /// Safely truncate a string to at most `max_bytes`, respecting UTF-8 char boundaries.
 fn truncate_str(s: &str, max_bytes: usize) -> &str {
     if s.len() <= max_bytes {
@@ -76,16 +90,35 @@ fn truncate_str(s: &str, max_bytes: usize) -> &str {
     &s[..end]
 }
 
+/// Model to report and bill for a completion of `requested`: the one that
+/// actually answered when a failover switched model families, otherwise the
+/// requested name (providers report dated variants of the same model).
+fn served_model(requested: &str, completion: &CompletionResponse) -> String {
+    if completion.switched_family(requested) {
+        completion.model_or(requested).to_string()
+    } else {
+        requested.to_string()
+    }
+}
+
 /// Loving fallback messages for when LLM takes too long.
-fn timeout_fallback_message() -> String {
-    let messages = [
-        "ごめんなさい、応答に時間がかかりすぎました。もう一度お試しください！",
-        "サーバーが混み合っているようです。少し待ってからもう一度送ってみてね！",
-        "応答がタイムアウトしました。ネットワークが混雑しているかも。もう一度お願いします！",
-        "すみません、処理に時間がかかってしまいました。再度お試しいただけますか？",
-        "一時的に応答が遅延しています。もう一度メッセージを送ってくれると嬉しいです！",
-        "ごめんね、ちょっと時間がかかっちゃった。もう一回試してみて！",
-    ];
+fn timeout_fallback_message(locale: Locale) -> String {
+    let messages: &[&str] = match locale {
+        Locale::Ja => &[
+            "ごめんなさい、応答に時間がかかりすぎました。もう一度お試しください！",
+            "サーバーが混み合っているようです。少し待ってからもう一度送ってみてね！",
+            "応答がタイムアウトしました。ネットワークが混雑しているかも。もう一度お願いします！",
+            "すみません、処理に時間がかかってしまいました。再度お試しいただけますか？",
+            "一時的に応答が遅延しています。もう一度メッセージを送ってくれると嬉しいです！",
+            "ごめんね、ちょっと時間がかかっちゃった。もう一回試してみて！",
+        ],
+        Locale::En => &[
+            "Sorry, that took too long to answer. Please try again!",
+            "The servers seem busy right now. Give it a moment and send it again!",
+            "The response timed out — the network may be congested. Please try once more!",
+            "Sorry, processing took longer than expected. Could you try again?",
+        ],
+    };
     let idx = (std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
@@ -93,6 +126,12 @@ fn timeout_fallback_message() -> String {
     messages[idx].to_string()
 }
 
+/// Reply to a channel user whose message `text` failed with `e`, in the
+/// message's language (`default` when it can't be told).
+fn llm_error_reply(e: &crate::error::ProviderError, text: &str, default: Locale) -> String {
+    e.code().user_message(Locale::detect(text).unwrap_or(default)).to_string()
+}
+
 /// Friendly error fallback (when all providers fail, not just timeout).
 /// 100 patterns — honest about the cause, reassuring about data safety, with humor.
 fn error_fallback_message() -> String {
@@ -361,7 +400,7 @@ pub fn is_admin(key: &str) -> bool {
 /// Authenticate admin from Bearer token. Returns (user_id, email) if admin, None otherwise.
 /// Uses the same auth flow as /api/v1/auth/me: Bearer token → resolve user → check is_admin.
 #[cfg(feature = "dynamodb-backend")]
-async fn authenticate_admin(state: &AppState, headers: &axum::http::HeaderMap) -> Option<(String, String)> {
+pub(crate) async fn authenticate_admin(state: &AppState, headers: &axum::http::HeaderMap) -> Option<(String, String)> {
     use aws_sdk_dynamodb::types::AttributeValue;
 
     let token = headers.get("authorization")
@@ -409,7 +448,7 @@ async fn authenticate_admin(state: &AppState, headers: &axum::http::HeaderMap) -
 }
 
 #[cfg(not(feature = "dynamodb-backend"))]
-async fn authenticate_admin(_state: &AppState, _headers: &axum::http::HeaderMap) -> Option<(String, String)> {
+pub(crate) async fn authenticate_admin(_state: &AppState, _headers: &axum::http::HeaderMap) -> Option<(String, String)> {
     None
 }
 
@@ -439,6 +478,10 @@ pub struct UserProfile {
     pub created_at: String,
     pub dev_mode: bool,
     pub solana_wallet: Option<String>,
+    /// Timezone set with `/timezone` (`Asia/Tokyo`, `UTC+9`, ...).
+    pub timezone: Option<String>,
+    /// Language set with `/lang` (`ja` / `en`).
+    pub locale: Option<String>,
 }
 
 /// Cached user profile with expiration timestamp
@@ -474,6 +517,10 @@ pub struct AppState {
     pub tool_registry: crate::service::integrations::ToolRegistry,
     /// Per-user concurrent request tracker: session_key -> active count
     pub concurrent_requests: dashmap::DashMap<String, AtomicU32>,
+    /// Per-user limit and queue for chat requests
+    pub chat_queue: Arc<crate::service::chat_queue::ChatQueue>,
+    /// Buffered `/chat/stream` events, for resuming with `Last-Event-ID`
+    pub stream_buffers: Arc<StreamBuffers>,
     /// User profile cache: user_id -> CachedUserProfile (TTL: 5 minutes)
     pub user_profile_cache: dashmap::DashMap<String, CachedUserProfile>,
     /// Pluggable database backend (libSQL for Fly.io/self-host; None when using DynamoDB)
@@ -484,11 +531,32 @@ pub struct AppState {
     pub config_table: Option<String>,
     /// Cached status ping result (timestamp, json value)
     pub ping_cache: Mutex<Option<(std::time::Instant, serde_json::Value)>>,
+    /// Outbound delivery tracking (status queried via /api/v1/deliveries)
+    pub deliveries: Mutex<crate::delivery::DeliveryTracker>,
+    /// Outbound webhook outbox (log queried via /api/v1/webhooks/deliveries)
+    pub webhooks: Arc<Mutex<crate::webhook::WebhookOutbox>>,
+    /// Workflow definitions and run history (runs are executed by the gateway)
+    pub workflows: crate::workflow::WorkflowEngine,
+    /// Event rules (firings are executed by the gateway)
+    pub rules: crate::rules::RulesEngine,
+    /// Canned answers from `<workspace>/faq.yaml`
+    pub faq: crate::faq::Faq,
+    /// Remote commands waiting for devices (handed out on heartbeat)
+    pub device_commands: Mutex<crate::kiosk::DeviceCommandQueue>,
+    /// Learned agent router (None = keyword scoring only)
+    pub router: Option<crate::service::router::Router>,
+    /// Tool call analytics (queried via /api/v1/usage/tools)
+    pub tool_stats: crate::tool::stats::ToolStatsStore,
+    /// Post-processing of final replies (`agents.hooks`, plus any an
+    /// embedder registers before serving)
+    pub response_hooks: crate::hooks::ResponseHooks,
 }
 
 impl AppState {
     /// Create AppState with an LLM provider auto-configured from config.
     pub fn with_provider(config: Config, sessions: Box<dyn SessionStore>) -> Self {
+        crate::util::http::configure_clients(&config.http);
+        crate::util::http::configure_web(&config.tools.web.fetch, &config.workspace_path());
         let provider = config.get_api_key(None).map(|key| {
             let api_base = config.get_api_base(None).map(|s| s.to_string());
             let model = &config.agents.defaults.model;
@@ -510,9 +578,25 @@ impl AppState {
             if let Some(ref p) = improve_prov {
                 tool_registry.add_improve_tool(p.clone());
                 tool_registry.add_multi_agent_tool(p.clone());
+                tool_registry.add_extract_tool(p.clone());
+                tool_registry.add_expense_tool(
+                    p.clone(),
+                    Arc::new(crate::expense::FileLedger::new(crate::expense::FileLedger::default_path())),
+                );
             }
         }
 
+        let webhooks = Arc::new(Mutex::new(crate::webhook::WebhookOutbox::new(
+            crate::webhook::WebhookOutbox::default_path(),
+            config.webhooks.clone(),
+        )));
+        let workflows = crate::workflow::WorkflowEngine::for_workspace(&config.workspace_path());
+        let rules = crate::rules::RulesEngine::for_workspace(&config.workspace_path());
+        let faq = crate::faq::Faq::for_workspace(&config.workspace_path());
+        let router = crate::service::router::Router::from_config(&config.agents.routing);
+        let chat_queue = crate::service::chat_queue::ChatQueue::new(config.gateway.concurrency.clone());
+        let response_hooks = crate::hooks::ResponseHooks::from_config(&config.agents.hooks);
+
         Self {
             config,
             sessions: Mutex::new(sessions),
@@ -521,6 +605,8 @@ impl AppState {
             lb_raw: std::sync::RwLock::new(lb_raw),
             tool_registry,
             concurrent_requests: dashmap::DashMap::new(),
+            chat_queue,
+            stream_buffers: StreamBuffers::new(),
             user_profile_cache: dashmap::DashMap::new(),
             db: None,
             #[cfg(feature = "dynamodb-backend")]
@@ -528,6 +614,27 @@ impl AppState {
             #[cfg(feature = "dynamodb-backend")]
             config_table: None,
             ping_cache: Mutex::new(None),
+            deliveries: Mutex::new(crate::delivery::DeliveryTracker::new(
+                crate::delivery::DeliveryTracker::default_path(),
+            )),
+            webhooks,
+            workflows,
+            rules,
+            faq,
+            device_commands: Mutex::new(crate::kiosk::DeviceCommandQueue::new(
+                crate::kiosk::DeviceCommandQueue::default_path(),
+            )),
+            router,
+            tool_stats: crate::tool::stats::ToolStatsStore::new(crate::tool::stats::ToolStatsStore::default_path()),
+            response_hooks,
+        }
+    }
+
+    /// Keep expenses in `ledger` (the database or DynamoDB) instead of the
+    /// local files the registry starts with.
+    pub fn set_expense_ledger(&mut self, ledger: Arc<dyn crate::expense::ExpenseLedger>) {
+        if let Some(provider) = self.get_provider() {
+            self.tool_registry.add_expense_tool(provider, ledger);
         }
     }
 
@@ -910,17 +1017,25 @@ struct RoutingLogEntry {
     completion_tokens: u32,
     timed_out: bool,
     error: bool,
+    // Learned router (see service::router): hashed features, and the
+    // model's pick in shadow/learned mode
+    route_features: Vec<u32>,
+    learned_agent: Option<String>,
+    learned_confidence: Option<f32>,
+    learned_override: bool,
     // Meta
     timestamp: String,
     session_hash: String,
 }
 
 /// Write a routing log entry to DynamoDB (fire-and-forget).
-/// pk: ROUTING_LOG#{YYYY-MM-DD}, sk: {timestamp}#{random_6}
+/// pk: ROUTING_LOG#{YYYY-MM-DD}, sk: {timestamp}#{random_6}; expires after
+/// `agents.routing.logRetentionDays`.
 #[cfg(feature = "dynamodb-backend")]
 fn log_routing_data(
     dynamo: aws_sdk_dynamodb::Client,
     config_table: String,
+    retention_days: u32,
     entry: RoutingLogEntry,
 ) {
     tokio::spawn(async move {
@@ -929,7 +1044,7 @@ fn log_routing_data(
         let ts = now.timestamp_millis().to_string();
         let rand_suffix = &uuid::Uuid::new_v4().to_string()[..6];
         let sk = format!("{}#{}", ts, rand_suffix);
-        let ttl = (now.timestamp() + 90 * 24 * 3600).to_string(); // 90 days
+        let ttl = (now.timestamp() + retention_days as i64 * 24 * 3600).to_string();
 
         let json_str = serde_json::to_string(&entry).unwrap_or_default();
 
@@ -950,6 +1065,19 @@ fn log_routing_data(
     });
 }
 
+/// Mark claims of a research answer that its sources don't back and append
+/// a confidence note. Uses embeddings for a semantic second pass when
+/// `OPENAI_API_KEY` is set.
+async fn check_against_sources(answer: &str, sources: &[crate::factcheck::Source], locale: Locale) -> String {
+    let mut report = crate::factcheck::check(answer, sources);
+    if let Ok(key) = std::env::var("OPENAI_API_KEY") {
+        let embedder = crate::provider::embeddings::EmbeddingsProvider::new(key);
+        crate::factcheck::recheck_semantic(&mut report, sources, &embedder).await;
+    }
+    info!("Fact check: {}/{} claims supported by {} sources", report.supported(), report.claims.len(), sources.len());
+    crate::factcheck::annotate(answer, &report, locale)
+}
+
 /// Detect language from message text (simple heuristic).
 fn detect_language(text: &str) -> &'static str {
     let has_ja = text.chars().any(|c| {
@@ -1330,68 +1458,82 @@ async fn append_daily_memory(
     entry_count
 }
 
-/// Consolidate daily memory into long-term memory using a cheap LLM call.
-/// Triggered when daily entries exceed threshold (fire-and-forget).
+/// What `spawn_consolidate_memory` runs with (`agents.memory`).
+#[cfg(feature = "dynamodb-backend")]
+struct MemoryConsolidation {
+    consolidator: Consolidator,
+    provider: Arc<dyn LlmProvider>,
+    model: String,
+}
+
+/// `None` when consolidation is off or its model is unavailable. Without a
+/// configured model the cheapest one is used.
+#[cfg(feature = "dynamodb-backend")]
+fn memory_consolidation(config: &Config, provider: Option<Arc<dyn LlmProvider>>) -> Option<MemoryConsolidation> {
+    if !config.agents.memory.consolidate {
+        return None;
+    }
+    let consolidator = Consolidator::from_config(&config.agents.memory);
+    let (provider, model) = match provider {
+        Some(provider) if consolidator.is_local() => match consolidator.provider_for(config, provider) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                tracing::warn!("Memory consolidation skipped: {}", e);
+                return None;
+            }
+        },
+        Some(provider) => (provider, consolidator.model_or("gpt-4o-mini").to_string()),
+        None => return None,
+    };
+    Some(MemoryConsolidation { consolidator, provider, model })
+}
+
+/// Consolidate recent daily memory into long-term memory (fire-and-forget).
+/// Skipped for users who turned `memory_consolidation` off.
 #[cfg(feature = "dynamodb-backend")]
 fn spawn_consolidate_memory(
     dynamo: aws_sdk_dynamodb::Client,
     config_table: String,
     user_id: String,
-    provider: Arc<dyn LlmProvider>,
+    job: MemoryConsolidation,
 ) {
     tokio::spawn(async move {
+        if get_user_settings(&dynamo, &config_table, &user_id).await.memory_consolidation == Some(false) {
+            return;
+        }
         let pk = format!("MEMORY#{}", user_id);
-
-        // Read current long-term memory and today's daily log
-        let (lt_result, daily_result) = tokio::join!(
-            dynamo.get_item()
+        let read = |sk: String| {
+            let request = dynamo.get_item()
                 .table_name(&config_table)
                 .key("pk", AttributeValue::S(pk.clone()))
-                .key("sk", AttributeValue::S(SK_LONG_TERM.to_string()))
-                .send(),
-            dynamo.get_item()
-                .table_name(&config_table)
-                .key("pk", AttributeValue::S(pk.clone()))
-                .key("sk", AttributeValue::S(format!("DAILY#{}", chrono::Utc::now().format("%Y-%m-%d"))))
-                .send()
-        );
-
-        let existing_lt = lt_result.ok()
-            .and_then(|o| o.item)
-            .and_then(|item| item.get("content").and_then(|v| v.as_s().ok()).cloned())
-            .unwrap_or_default();
-        let daily = daily_result.ok()
-            .and_then(|o| o.item)
-            .and_then(|item| item.get("content").and_then(|v| v.as_s().ok()).cloned())
-            .unwrap_or_default();
-
-        if daily.is_empty() { return; }
-
-        let prompt = format!(
-            "以下はユーザーの既存の長期記憶と今日の会話ログです。\n\
-             今日のログから重要な事実・好み・決定事項を抽出し、長期記憶を更新してください。\n\
-             箇条書きで簡潔に（最大20項目）。重複は統合。古い情報は最新で上書き。\n\n\
-             ## 既存の長期記憶\n{}\n\n## 今日の会話ログ\n{}\n\n\
-             ## 更新後の長期記憶（箇条書きのみ出力）:",
-            if existing_lt.is_empty() { "（なし）".to_string() } else { existing_lt },
-            daily
-        );
+                .key("sk", AttributeValue::S(sk))
+                .send();
+            async move {
+                request.await.ok()
+                    .and_then(|o| o.item)
+                    .and_then(|item| item.get("content").and_then(|v| v.as_s().ok()).cloned())
+                    .unwrap_or_default()
+            }
+        };
 
-        let messages = vec![Message::user(&prompt)];
-        // Use cheapest model available
-        let model = "gpt-4o-mini";
-        match provider.chat(&messages, None, model, 1024, 0.3).await {
-            Ok(resp) => {
-                if let Some(content) = resp.content {
-                    if !content.trim().is_empty() {
-                        save_memory(&dynamo, &config_table, &user_id, "long_term", content.trim()).await;
-                        tracing::info!("Long-term memory consolidated for {}", user_id);
-                    }
-                }
+        // Current long-term memory and the daily logs, oldest first
+        let existing_lt = read(SK_LONG_TERM.to_string()).await;
+        let mut daily = Vec::new();
+        for days_ago in (0..job.consolidator.days()).rev() {
+            let date = chrono::Utc::now() - chrono::Duration::days(days_ago as i64);
+            let content = read(format!("DAILY#{}", date.format("%Y-%m-%d"))).await;
+            if !content.is_empty() {
+                daily.push(content);
             }
-            Err(e) => {
-                tracing::warn!("Memory consolidation failed for {}: {}", user_id, e);
+        }
+
+        match job.consolidator.summarize(job.provider.as_ref(), &job.model, &existing_lt, &daily.join("\n\n")).await {
+            Ok(Some(content)) => {
+                save_memory(&dynamo, &config_table, &user_id, "long_term", &content).await;
+                tracing::info!("Long-term memory consolidated for {}", user_id);
             }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Memory consolidation failed for {}: {}", user_id, e),
         }
     });
 }
@@ -1521,7 +1663,13 @@ async fn deduct_credits_via_state(
     #[cfg(feature = "libsql-backend")]
     if let Some(ref db) = state.db {
         return match db.deduct_credits(user_id, credits).await {
-            Ok((deducted, remaining)) => (deducted, remaining),
+            Ok((deducted, remaining)) => {
+                if deducted > 0 {
+                    let tx = crate::credits::CreditTransaction::new(crate::credits::CreditKind::Usage, -deducted, remaining, model);
+                    crate::credits::record(&crate::credits::DbLedger(db.clone()), user_id, tx).await;
+                }
+                (deducted, remaining)
+            }
             Err(e) => {
                 tracing::warn!("deduct_credits_via_state (libSQL) failed for {}: {}", user_id, e);
                 (0, Some(0))
@@ -1818,6 +1966,8 @@ async fn get_or_create_user_cached(
                 created_at: p.created_at,
                 dev_mode: false,
                 solana_wallet: None,
+                timezone: None,
+                locale: None,
             },
             Err(e) => {
                 tracing::error!("get_or_create_user_cached (libSQL) failed for {}: {}", user_id, e);
@@ -1833,6 +1983,8 @@ async fn get_or_create_user_cached(
                     created_at: chrono::Utc::now().to_rfc3339(),
                     dev_mode: false,
                     solana_wallet: None,
+                    timezone: None,
+                    locale: None,
                 }
             }
         };
@@ -1893,6 +2045,8 @@ async fn get_or_create_user(
                 .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
             let dev_mode = item.get("dev_mode").and_then(|v| v.as_bool().ok()).copied().unwrap_or(false);
             let solana_wallet = item.get("solana_wallet").and_then(|v| v.as_s().ok()).cloned();
+            let timezone = item.get("timezone").and_then(|v| v.as_s().ok()).cloned();
+            let locale = item.get("locale").and_then(|v| v.as_s().ok()).cloned();
 
             return UserProfile {
                 user_id: user_id.to_string(),
@@ -1906,6 +2060,8 @@ async fn get_or_create_user(
                 created_at,
                 dev_mode,
                 solana_wallet,
+                timezone,
+                locale,
             };
         }
     }
@@ -1946,6 +2102,8 @@ async fn get_or_create_user(
         created_at: now,
         dev_mode: false,
         solana_wallet: None,
+        timezone: None,
+        locale: None,
     }
 }
 
@@ -2042,13 +2200,39 @@ async fn deduct_credits(
         .expression_attribute_values(":uid_set", AttributeValue::Ss(vec![user_id_str]))
         .send();
 
+    let ledger_fut = {
+        let ledger = crate::credits::DynamoLedger::new(dynamo.clone(), config_table);
+        let tx = crate::credits::CreditTransaction::new(crate::credits::CreditKind::Usage, -credits, remaining, model);
+        let user_id = user_id.to_string();
+        async move {
+            if remaining.is_some() {
+                crate::credits::record(&ledger, &user_id, tx).await;
+            }
+        }
+    };
+
     // Fire-and-forget analytics writes: do not await — blocking here prevents
     // the SSE stream from terminating in lambda_http's body.collect().await.
-    tokio::spawn(async move { let _ = tokio::join!(usage_fut, hourly_fut, uu_fut); });
+    tokio::spawn(async move { let _ = tokio::join!(usage_fut, hourly_fut, uu_fut, ledger_fut); });
 
     (credits, remaining)
 }
 
+/// Append a credit transaction to `user_id`'s ledger in the config table.
+#[cfg(feature = "dynamodb-backend")]
+async fn record_credits(
+    dynamo: &aws_sdk_dynamodb::Client,
+    config_table: &str,
+    user_id: &str,
+    kind: crate::credits::CreditKind,
+    amount: i64,
+    balance: Option<i64>,
+    note: &str,
+) {
+    let ledger = crate::credits::DynamoLedger::new(dynamo.clone(), config_table);
+    crate::credits::record(&ledger, user_id, crate::credits::CreditTransaction::new(kind, amount, balance, note)).await;
+}
+
 /// Link a Stripe customer to a user profile and upgrade their plan.
 #[cfg(feature = "dynamodb-backend")]
 async fn link_stripe_to_user(
@@ -2162,7 +2346,7 @@ async fn add_credits_to_user(
     credits: i64,
     stripe_customer_id: &str,
     email: &str,
-) {
+) -> Option<i64> {
     let pk = format!("USER#{}", user_id);
     let mut expr = "SET credits_remaining = if_not_exists(credits_remaining, :zero) + :cr, updated_at = :now".to_string();
     let mut builder = dynamo
@@ -2183,9 +2367,16 @@ async fn add_credits_to_user(
         builder = builder.expression_attribute_values(":email", AttributeValue::S(email.to_string()));
     }
 
-    match builder.update_expression(expr).send().await {
-        Ok(_) => info!("Added {} credits to user {}", credits, user_id),
-        Err(e) => tracing::error!("BILLING ERROR: Failed to add {} credits to user {}: {}", credits, user_id, e),
+    match builder.update_expression(expr).return_values(aws_sdk_dynamodb::types::ReturnValue::AllNew).send().await {
+        Ok(output) => {
+            info!("Added {} credits to user {}", credits, user_id);
+            output.attributes
+                .and_then(|attrs| attrs.get("credits_remaining").and_then(|v| v.as_n().ok()).and_then(|n| n.parse::<i64>().ok()))
+        }
+        Err(e) => {
+            tracing::error!("BILLING ERROR: Failed to add {} credits to user {}: {}", credits, user_id, e);
+            None
+        }
     }
 }
 
@@ -2343,11 +2534,17 @@ async fn deduct_credits_direct(
         .condition_expression("credits_remaining >= :c")
         .expression_attribute_values(":c", AttributeValue::N(credits.to_string()))
         .expression_attribute_values(":now", AttributeValue::S(chrono::Utc::now().to_rfc3339()))
+        .return_values(aws_sdk_dynamodb::types::ReturnValue::AllNew)
         .send()
         .await;
 
     match result {
-        Ok(_) => Ok(()),
+        Ok(output) => {
+            let balance = output.attributes
+                .and_then(|attrs| attrs.get("credits_remaining").and_then(|v| v.as_n().ok()).and_then(|n| n.parse::<i64>().ok()));
+            record_credits(dynamo, config_table, user_id, crate::credits::CreditKind::Usage, -credits, balance, "media").await;
+            Ok(())
+        }
         Err(e) => {
             let err_str = format!("{}", e);
             if err_str.contains("ConditionalCheckFailedException") {
@@ -2572,6 +2769,19 @@ pub struct AgentProfile {
     pub max_chars_voice: u32,
 }
 
+impl AgentProfile {
+    /// Built-in character limit for a device ("pc", "mobile", "voice").
+    /// `agents.response` config and session `/length` take priority.
+    pub fn max_chars_for(&self, device: &str) -> usize {
+        let n = match device {
+            "mobile" => self.max_chars_mobile,
+            "voice" => self.max_chars_voice,
+            _ => self.max_chars_pc,
+        };
+        n as usize
+    }
+}
+
 // Shared rules appended to all agent prompts (avoid duplication)
 const AGENT_COMMON: &str = "\n\n\
 ## 共通規範\n\
@@ -2657,7 +2867,7 @@ const AGENTS: &[AgentProfile] = &[
              2. read_webpageで有望なURLの詳細を確認\n\
              3. 複数情報源を比較・照合\n\
              4. 実データ（価格・日付・数値）を引用して回答\n\
-             5. 情報源URLを全て明示\n\n\
+             5. 出典は read_webpage 結果の先頭にある [番号] で本文中に示す（出典一覧は自動で付く）\n\n\
              ## 制約\n\
              - 取得できた情報を最大限活用する。見つからなかった場合は正直に伝え、代替の調査方法を提案。\n\
              - 古い情報と最新情報を区別して提示。\n\
@@ -2793,6 +3003,9 @@ const META_INSTRUCTION_JA: &str = "\n\n【メタ認知ガイド】\n\
 - ユーモア: 適度にウィットを混ぜる。真剣な話題では控えめに。";
 
 // Low credits notice (conditionally injected) — informational only, no sales pitch
+/// `credits.low` webhooks fire when a user's balance drops to this level.
+const WEBHOOK_LOW_CREDITS: i64 = 100;
+
 const META_LOW_CREDITS_JA: &str = "\n\
 - クレジット残少: 残りクレジットが少ないことをユーザーに伝える。詳細はアカウント設定で確認できる旨を案内。";
 
@@ -2826,25 +3039,43 @@ fn build_meta_context(
     history_len: usize,
     is_english: bool,
 ) -> String {
-    build_meta_context_with_model(user, channel, device, history_len, is_english, None, 0, 0)
+    build_meta_context_with_model(user, channel, device, history_len, is_english, None, None, 0, 0)
+}
+
+/// Timezone for a request: the session's `/timezone`, then the user's profile,
+/// then the default for the channel and language.
+fn resolve_timezone(
+    session_tz: Option<&str>,
+    user: Option<&UserProfile>,
+    channel: &str,
+    is_english: bool,
+) -> String {
+    let locale = if is_english { Locale::En } else { Locale::Ja };
+    session_tz
+        .or_else(|| user.and_then(|u| u.timezone.as_deref()))
+        .unwrap_or_else(|| locale.default_timezone(channel))
+        .to_string()
 }
 
 /// Build meta-cognition context with model/cost info.
+/// `session_tz` is the session's `/timezone`, if set; see [`resolve_timezone`].
+#[allow(clippy::too_many_arguments)]
 fn build_meta_context_with_model(
     user: Option<&UserProfile>,
     channel: &str,
     device: &str,
     history_len: usize,
     is_english: bool,
+    session_tz: Option<&str>,
     model: Option<&str>,
     session_tokens: u32,
     session_cost_microdollars: u64,
 ) -> String {
-    use chrono::{Utc, FixedOffset, Timelike, Datelike};
+    use chrono::{Timelike, Datelike};
     use crate::provider::pricing;
 
-    let jst = FixedOffset::east_opt(9 * 3600).unwrap();
-    let now = Utc::now().with_timezone(&jst);
+    let tz = resolve_timezone(session_tz, user, channel, is_english);
+    let now = crate::util::now_in(Some(&tz));
     let hour = now.hour();
     let (time_label, time_label_en) = match hour {
         5..=10 => ("朝", "morning"),
@@ -2887,7 +3118,7 @@ fn build_meta_context_with_model(
 
     if is_english {
         let mut parts = vec![
-            format!("Time: {} {} {}", now.format("%Y-%m-%d %H:%M"), time_label_en, weekday_en),
+            format!("Time: {} {} {} ({})", now.format("%Y-%m-%d %H:%M"), time_label_en, weekday_en, tz),
         ];
         if let Some((model_name, p)) = model_info {
             parts.push(format!("Model: {} ({})", model_name, p.provider));
@@ -2919,7 +3150,7 @@ fn build_meta_context_with_model(
         format!("\n{}", parts.join(" | "))
     } else {
         let mut parts = vec![
-            format!("現在時刻: {} {}（{}）", now.format("%Y-%m-%d %H:%M"), time_label, weekday_ja),
+            format!("現在時刻: {} {}（{}・{}）", now.format("%Y-%m-%d %H:%M"), time_label, weekday_ja, tz),
         ];
         if let Some((model_name, p)) = model_info {
             parts.push(format!("モデル: {} ({})", model_name, p.provider));
@@ -2955,6 +3186,106 @@ fn build_meta_context_with_model(
 /// Detect which agent to use from message text.
 /// Supports @agent prefix or weighted keyword scoring.
 /// Returns (agent, clean_message, score) where score=0 means default.
+/// Ids of all agent profiles (accepted by `/agent`).
+pub(crate) fn agent_ids() -> Vec<&'static str> {
+    AGENTS.iter().map(|a| a.id).collect()
+}
+
+/// Like `detect_agent`, but a session `/agent` override wins over keyword
+/// scoring. An explicit `@agent` prefix still takes precedence.
+fn detect_agent_with_override(text: &str, agent_override: Option<&str>) -> (&'static AgentProfile, String, u32) {
+    let detected = detect_agent(text);
+    if detected.2 >= 100 {
+        return detected;
+    }
+    match agent_override.and_then(|id| AGENTS.iter().find(|a| a.id == id)) {
+        Some(agent) => (agent, detected.1, 100),
+        None => detected,
+    }
+}
+
+/// `max_tokens` lowered so one answer stays within `options.max_cost_usd`;
+/// multi-model modes split the budget across the parallel models. 0 means
+/// the input alone is over budget.
+fn cost_capped_max_tokens(
+    state: &AppState,
+    options: &ChatOptions,
+    mode: ChatMode,
+    model: &str,
+    messages: &[Message],
+    max_tokens: u32,
+) -> u32 {
+    let Some(budget) = options.max_cost_usd else { return max_tokens };
+    let input_tokens: usize = messages.iter()
+        .map(|m| crate::agent::prompt::estimate_tokens(m.content.as_deref().unwrap_or("")))
+        .sum();
+    let share = if mode.is_multi_model() {
+        state.get_lb_raw().map(|lb| lb.available_parallel_models().len()).unwrap_or(1).max(1)
+    } else {
+        1
+    };
+    max_tokens.min(provider::pricing::max_output_tokens_within(model, input_tokens as u32, budget / share as f64))
+}
+
+fn over_budget_message(locale: Locale) -> &'static str {
+    locale.pick(
+        "この会話は options.max_cost_usd の上限を超えます。上限を上げるか、短いメッセージでお試しください。",
+        "This conversation exceeds options.max_cost_usd. Raise the limit or send a shorter message.",
+    )
+}
+
+/// [`ChatOptions::validate`] plus a check that the agent exists.
+fn validate_chat_options(options: &ChatOptions) -> Result<(), String> {
+    options.validate()?;
+    match options.agent.as_deref() {
+        Some(id) if !AGENTS.iter().any(|a| a.id == id) => Err(format!("options.agent: unknown agent '{}'", id)),
+        _ => Ok(()),
+    }
+}
+
+/// Features and model pick of one routing decision, for the routing log.
+#[derive(Debug, Clone, Default)]
+struct LearnedRoute {
+    features: Vec<u32>,
+    prediction: Option<crate::service::router::Prediction>,
+    overridden: bool,
+}
+
+/// Let the learned router, if loaded, weigh in on a keyword pick. Explicit
+/// `@agent` and `/agent` choices (score 100) are never overridden.
+fn learned_route(
+    state: &AppState,
+    agent: &'static AgentProfile,
+    agent_score: u32,
+    text: &str,
+    channel: &str,
+    device: &str,
+) -> (&'static AgentProfile, LearnedRoute) {
+    let features = crate::service::router::features(&crate::service::router::RouteInput {
+        text,
+        channel,
+        device,
+        language: detect_language(text),
+    });
+    let mut route = LearnedRoute { features, ..Default::default() };
+    let Some(router) = state.router.as_ref() else {
+        return (agent, route);
+    };
+    let (prediction, takeover) = router.route(&route.features, agent.id);
+    route.prediction = Some(prediction);
+    if agent_score >= 100 {
+        return (agent, route);
+    }
+    match takeover.and_then(|id| AGENTS.iter().find(|a| a.id == id)) {
+        Some(learned) => {
+            info!("Learned router: {} -> {}", agent.id, learned.id);
+            route.overridden = true;
+            (learned, route)
+        }
+        None => (agent, route),
+    }
+}
+
 fn detect_agent(text: &str) -> (&'static AgentProfile, String, u32) {
     let trimmed = text.trim();
 
@@ -3100,8 +3431,14 @@ const TOOL_GROUPS: &[(&[&str], &[&str])] = &[
     // CSV / data
     (&["csv", "CSV", "データ", "表", "スプレッドシート"],
      &["csv_analysis"]),
+    // Whole-site reading
+    (&["ドキュメント", "サイト全体", "documentation", "docs", "crawl", "クロール"],
+     &["web_crawl"]),
+    // Structured extraction
+    (&["抽出", "抜き出", "extract", "json", "schema", "スキーマ", "スクレイプ", "scrape"],
+     &["extract"]),
     // YouTube & arXiv
-    (&["youtube", "YouTube", "動画", "字幕", "transcript"],
+    (&["youtube", "YouTube", "youtu.be", "動画", "字幕", "transcript"],
      &["youtube_transcript"]),
     (&["arxiv", "論文", "paper", "学術"],
      &["arxiv_search"]),
@@ -3204,6 +3541,13 @@ pub struct DeviceHeartbeat {
     pub uptime_secs: Option<u64>,
 }
 
+/// Remote command for a device (see `crate::kiosk::DeviceCommand`).
+#[derive(Debug, Deserialize)]
+pub struct DeviceCommandRequest {
+    pub hostname: String,
+    pub command: crate::kiosk::DeviceCommand,
+}
+
 // ---------------------------------------------------------------------------
 // Worker (compute provider / earn mode)
 // ---------------------------------------------------------------------------
@@ -3302,12 +3646,25 @@ pub struct ChatRequest {
     pub presence_penalty: Option<f64>,
     /// Custom system prompt addition from user settings
     pub custom_system_prompt: Option<String>,
-    /// Inference mode: "local" (on-device only), "cloud" (remote only), "auto" (default: cloud with local fallback)
-    pub mode: Option<String>,
+    /// Inference mode (see [`ChatMode`]); defaults to "auto", or "local" on wisbee.ai
+    pub mode: Option<ChatMode>,
+    /// Per-request options: model tier, cost cap, tools on/off, agent
+    #[serde(default)]
+    pub options: ChatOptions,
     /// UI language from frontend (e.g. "ja", "en") — used for auto-translation
     pub language: Option<String>,
 }
 
+impl ChatRequest {
+    /// The mode to answer in: `mode`, else `race` for the legacy
+    /// `multi_model` flag, else the host default.
+    fn resolve_mode(&self, host: &str) -> ChatMode {
+        self.mode
+            .or(self.multi_model.then_some(ChatMode::Race))
+            .unwrap_or(if host.contains("wisbee.ai") { ChatMode::Local } else { ChatMode::Auto })
+    }
+}
+
 /// User settings stored in DynamoDB
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserSettings {
@@ -3343,6 +3700,8 @@ pub struct UserSettings {
     pub dev_mode: Option<bool>,
     pub solana_wallet: Option<String>,
     pub enai_earned: Option<i64>,
+    // Merge daily memory into long-term memory (on unless false)
+    pub memory_consolidation: Option<bool>,
 }
 
 /// Request body for updating settings (all fields optional for partial update)
@@ -3381,6 +3740,7 @@ pub struct UpdateSettingsRequest {
     // Developer mode settings
     pub dev_mode: Option<bool>,
     pub solana_wallet: Option<String>,
+    pub memory_consolidation: Option<bool>,
 }
 
 /// Request body for email registration.
@@ -3579,15 +3939,20 @@ pub struct ChatResponse {
     /// Estimated cost in USD for this request
     #[serde(skip_serializing_if = "Option::is_none")]
     pub estimated_cost_usd: Option<f64>,
-    /// Inference mode used: "local", "cloud", or "auto"
+    /// Inference mode used (see [`ChatMode`])
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mode: Option<String>,
+    /// Why the request failed, when `response` is an error message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
 }
 
 /// Response body for errors.
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
     pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
 }
 
 /// Session info for listing.
@@ -3777,7 +4142,17 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .route("/api/v1/sessions", get(handle_list_sessions))
         .route("/api/v1/sessions/{id}", get(handle_get_session))
         .route("/api/v1/sessions/{id}", delete(handle_delete_session))
+        .route("/api/v1/sessions/{id}/sync", post(handle_sync_session))
+        .route("/api/v1/sessions/{id}/pins", get(handle_list_pins))
+        .route("/api/v1/sessions/{id}/pins", post(handle_add_pin))
+        .route("/api/v1/sessions/{id}/pins/{pin_id}", delete(handle_remove_pin))
+        .route("/api/v1/sessions/{id}/contacts", get(handle_list_contacts))
+        .route("/api/v1/sessions/{id}/contacts", post(handle_upsert_contact))
+        .route("/api/v1/sessions/{id}/contacts/{name}", delete(handle_remove_contact))
         .route("/api/v1/usage", get(handle_usage))
+        .route("/api/v1/usage/tools", get(handle_usage_tools))
+        .route("/api/v1/credits/history", get(handle_credits_history))
+        .route("/api/v1/sync/config", get(handle_get_sync_config).put(handle_put_sync_config))
         .route("/api/v1/account/{id}", get(handle_account))
         .route("/api/v1/providers", get(handle_providers))
         .route("/api/v1/integrations", get(handle_integrations))
@@ -3796,6 +4171,7 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         // Devices
         .route("/api/v1/devices", get(handle_devices))
         .route("/api/v1/devices/heartbeat", post(handle_device_heartbeat))
+        .route("/api/v1/devices/commands", post(handle_device_command))
         // Smart home device controls (proxy directly to device APIs)
         .route("/api/v1/hue",          post(handle_hue))
         .route("/api/v1/switchbot",    post(handle_switchbot))
@@ -3824,7 +4200,7 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         // Trial
         .route("/api/v1/trial/start", post(handle_trial_start))
         // Omikuji (fortune)
-        //         .route("/api/v1/omikuji", post(handle_omikuji))
+        .route("/api/v1/omikuji", post(handle_omikuji))
         // Referral
         .route("/api/v1/referral/code", get(handle_referral_code))
         .route("/api/v1/referral/apply", post(handle_referral_apply))
@@ -3900,8 +4276,30 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .route("/api/v1/cron/{id}", axum::routing::put(handle_cron_update))
         .route("/api/v1/cron/{id}", delete(handle_cron_delete))
         .route("/api/v1/cron/daily-summary", post(handle_daily_summary))
+        .route("/api/v1/upcoming", get(handle_upcoming_list))
+        .route("/api/v1/upcoming/{id}", axum::routing::put(handle_upcoming_reschedule))
+        .route("/api/v1/upcoming/{id}", delete(handle_upcoming_cancel))
+        // Outbound delivery status
+        .route("/api/v1/deliveries", get(handle_delivery_list))
+        .route("/api/v1/deliveries/{id}", get(handle_delivery_get))
+        .route("/api/v1/webhooks/deliveries", get(handle_webhook_delivery_list))
+        .route("/api/v1/webhooks/deliveries/{id}/retry", post(handle_webhook_delivery_retry))
+        // Workflows (executed by the gateway's workflow runner)
+        .route("/api/v1/workflows", get(handle_workflow_list))
+        .route("/api/v1/workflows/runs", get(handle_workflow_runs))
+        .route("/api/v1/workflows/runs/{id}", get(handle_workflow_run_get))
+        .route("/api/v1/workflows/runs/{id}/resume", post(handle_workflow_run_resume))
+        .route("/api/v1/workflows/{name}/trigger", post(handle_workflow_trigger))
+        // Event rules (firings executed by the gateway)
+        .route("/api/v1/rules", get(handle_rules_list).post(handle_rules_upsert))
+        .route("/api/v1/rules/firings", get(handle_rules_firings))
+        .route("/api/v1/rules/heartbeat/{source}", post(handle_rules_heartbeat))
+        .route("/api/v1/rules/channels/{name}/enable", post(handle_rules_channel_enable))
+        .route("/api/v1/rules/{id}", axum::routing::delete(handle_rules_delete))
         // Speech (TTS) — internal + OpenAI-compatible external API
         .route("/api/v1/speech/synthesize", post(handle_speech_synthesize))
+        // Realtime voice: streaming audio in, transcript + reply + speech out
+        .route("/api/v1/voice/ws", get(super::voice_ws::handle_voice_ws))
         .route("/v1/audio/speech", post(handle_tts_openai_compat))
         // OpenAI-compatible Chat API — drop-in replacement for OpenAI API
         .route("/v1/chat/completions", post(handle_openai_chat_completions))
@@ -3973,8 +4371,16 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .route("/api/v1/admin/keys", axum::routing::put(handle_admin_keys_put))
         .route("/api/v1/admin/keys/test", post(handle_admin_keys_test))
         .route("/api/v1/admin/feedback", get(handle_admin_feedback))
+        .route("/api/v1/admin/routing", get(handle_admin_routing))
         .route("/api/v1/admin/tickets", get(handle_admin_tickets))
         .route("/api/v1/admin/tickets/{ticket_id}/respond", post(handle_admin_ticket_respond))
+        // Self-hosted dashboard data (gateway.adminUi)
+        .route("/api/v1/admin/gateway/providers", get(super::admin_ui::handle_providers))
+        .route("/api/v1/admin/gateway/channels", get(super::admin_ui::handle_channels))
+        .route("/api/v1/admin/gateway/sessions", get(super::admin_ui::handle_sessions))
+        .route("/api/v1/admin/gateway/cron", get(super::admin_ui::handle_cron))
+        .route("/api/v1/admin/gateway/usage", get(super::admin_ui::handle_usage))
+        .route("/api/v1/admin/gateway/config", get(super::admin_ui::handle_config))
         .route("/api/v1/activity", get(handle_activity))
         // Tickets (user-facing)
         .route("/api/v1/tickets", post(handle_create_ticket))
@@ -3999,6 +4405,8 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         // Link code generation and status for QR flow
         .route("/api/v1/link/generate", post(handle_link_generate))
         .route("/api/v1/link/status/{code}", get(handle_link_status))
+        .route("/api/v1/link/confirm", post(handle_link_confirm))
+        .route("/link/{code}", get(handle_link_page))
         // Credential vault
         .route("/api/v1/vault/credentials", post(handle_vault_store))
         .route("/api/v1/vault/credentials", get(handle_vault_list))
@@ -4057,6 +4465,7 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         // Health
         .route("/health", get(handle_health))
         .route("/api/v1/health", get(handle_health))
+        .merge(token_only_routes(&state))
         .fallback(handle_404)
         .layer(RequestBodyLimitLayer::new(1024 * 1024)) // 1MB max body
         .layer(CompressionLayer::new())
@@ -4119,6 +4528,32 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .with_state(state)
 }
 
+/// Canned answer from `<workspace>/faq.yaml` for `text`, saved to the
+/// session like a model reply unless the turn is incognito; the answer
+/// returned has been through the response hooks.
+async fn faq_reply(state: &AppState, session_key: &str, channel: &str, text: &str, incognito: bool) -> Option<crate::faq::FaqMatch> {
+    let mut hit = state.faq.answer(channel, text).await?;
+    info!("FAQ answer '{}' on {} ({:?}, score {:.2})", hit.id, channel, hit.kind, hit.score);
+    let label = {
+        let mut sessions = state.sessions.lock().await;
+        let session = sessions.get_or_create(session_key);
+        if incognito || session.incognito() {
+            Some(session.locale().unwrap_or_default())
+        } else {
+            session.add_message_from_channel("user", text, channel);
+            session.add_message_from_channel("assistant", &hit.answer, channel);
+            sessions.save_by_key(session_key);
+            None
+        }
+    };
+    let hook_ctx = crate::hooks::HookContext { channel, agent: None, session_key };
+    hit.answer = state.response_hooks.apply(hit.answer, &hook_ctx).await;
+    if let Some(locale) = label {
+        hit.answer = crate::session::incognito::label(&hit.answer, locale);
+    }
+    Some(hit)
+}
+
 /// POST /api/v1/chat — Agent conversation
 async fn handle_chat(
     State(state): State<Arc<AppState>>,
@@ -4142,6 +4577,7 @@ async fn handle_chat(
             output_tokens: None,
             estimated_cost_usd: None,
             mode: None,
+            error_code: None,
         });
     }
 
@@ -4161,6 +4597,7 @@ async fn handle_chat(
             output_tokens: None,
             estimated_cost_usd: None,
             mode: None,
+            error_code: None,
         });
     }
 
@@ -4180,9 +4617,20 @@ async fn handle_chat(
             output_tokens: None,
             estimated_cost_usd: None,
             mode: None,
+            error_code: None,
         });
     }
 
+    // `/incognito <message>`: answer just this message without saving it
+    let mut req = req;
+    let incognito_once = match crate::session::incognito::one_off(&req.message).map(str::to_string) {
+        Some(text) => {
+            req.message = text;
+            true
+        }
+        None => false,
+    };
+
     let chat_start = std::time::Instant::now();
 
     // Circuit breaker: reject early if all providers are down
@@ -4204,6 +4652,7 @@ async fn handle_chat(
                 output_tokens: None,
                 estimated_cost_usd: None,
                 mode: None,
+                error_code: None,
             });
         }
     }
@@ -4228,6 +4677,7 @@ async fn handle_chat(
                     output_tokens: None,
                     estimated_cost_usd: None,
                     mode: None,
+                    error_code: None,
                 });
             }
         }
@@ -4253,27 +4703,34 @@ async fn handle_chat(
                 output_tokens: None,
                 estimated_cost_usd: None,
                 mode: None,
+                error_code: Some(ErrorCode::RateLimited),
             });
         }
     }
 
     // Resolve inference mode: request field > host-based default > "auto"
     let chat_host = effective_host(&headers);
-    let is_wisbee = chat_host.contains("wisbee.ai");
-    let resolved_mode = match req.mode.as_deref() {
-        Some("local") => "local",
-        Some("cloud") => "cloud",
-        Some("auto") => "auto",
-        None | Some(_) => {
-            // wisbee.ai hosts default to local mode
-            if is_wisbee { "local" } else { "auto" }
-        }
-    };
+    let resolved_mode = req.resolve_mode(chat_host);
+    if let Err(e) = validate_chat_options(&req.options) {
+        return Json(ChatResponse {
+            response: e,
+            session_id: req.session_id,
+            agent: None, tools_used: None,
+            credits_used: None, credits_remaining: None,
+            model_used: None, models_consulted: None,
+            action: None,
+            input_tokens: None,
+            output_tokens: None,
+            estimated_cost_usd: None,
+            mode: None,
+            error_code: None,
+        });
+    }
     info!("Chat request: session={}, msg_len={}, mode={}, host={}", req.session_id, req.message.len(), resolved_mode, chat_host);
 
     // --- Local mode: route directly to LocalProvider, no cloud ---
     #[cfg(feature = "local-fallback")]
-    if resolved_mode == MODE_LOCAL {
+    if resolved_mode == ChatMode::Local {
         use crate::provider::local::LocalProvider;
         match LocalProvider::from_env() {
             Some(local_provider) => {
@@ -4306,6 +4763,7 @@ async fn handle_chat(
                             output_tokens: Some(resp.usage.completion_tokens),
                             estimated_cost_usd: Some(0.0),
                             mode: Some(MODE_LOCAL.to_string()),
+                            error_code: None,
                         });
                     }
                     Err(e) => {
@@ -4324,6 +4782,7 @@ async fn handle_chat(
                             output_tokens: None,
                             estimated_cost_usd: None,
                             mode: Some(MODE_LOCAL.to_string()),
+                            error_code: None,
                         });
                     }
                 }
@@ -4343,12 +4802,13 @@ async fn handle_chat(
                     output_tokens: None,
                     estimated_cost_usd: None,
                     mode: Some(MODE_LOCAL.to_string()),
+                    error_code: None,
                 });
             }
         }
     }
     #[cfg(not(feature = "local-fallback"))]
-    if resolved_mode == "local" {
+    if resolved_mode == ChatMode::Local {
         return Json(ChatResponse {
             response: "Local mode is not available. This build does not include the local-fallback feature.".to_string(),
             session_id: req.session_id,
@@ -4363,6 +4823,7 @@ async fn handle_chat(
             output_tokens: None,
             estimated_cost_usd: None,
             mode: Some(MODE_LOCAL.to_string()),
+            error_code: None,
         });
     }
 
@@ -4431,6 +4892,7 @@ async fn handle_chat(
             user_id: user_id_opt.as_deref(),
             conv_id: conv_id.as_deref(),
             sessions: &state.sessions,
+            model: &state.config.agents.defaults.model,
             provider: cmd_provider.as_ref(),
             tool_registry: Some(&state.tool_registry),
             #[cfg(feature = "dynamodb-backend")]
@@ -4455,12 +4917,36 @@ async fn handle_chat(
                     output_tokens: None,
                     estimated_cost_usd: None,
                     mode: None,
+                    error_code: None,
                 });
             }
             super::commands::CommandResult::NotACommand => { /* fall through to LLM */ }
         }
     }
 
+    // Incognito turns are not saved to the session, memory or logs
+    let incognito = incognito_once || state.sessions.lock().await.get_or_create(&session_key).incognito();
+
+    // Canned answers from <workspace>/faq.yaml: no model call, no credits
+    if let Some(hit) = faq_reply(&state, &session_key, &req.channel, &req.message, incognito).await {
+        return Json(ChatResponse {
+            response: hit.answer,
+            session_id: req.session_id,
+            agent: Some(crate::faq::FAQ_KEY.to_string()),
+            tools_used: None,
+            credits_used: Some(0),
+            credits_remaining: None,
+            model_used: None,
+            models_consulted: None,
+            action: None,
+            input_tokens: None,
+            output_tokens: None,
+            estimated_cost_usd: Some(0.0),
+            mode: None,
+            error_code: None,
+        });
+    }
+
     let provider = match state.get_provider() {
         Some(p) => p.clone(),
         None => {
@@ -4478,6 +4964,7 @@ async fn handle_chat(
                 output_tokens: None,
                 estimated_cost_usd: None,
                 mode: None,
+                error_code: None,
             });
         }
     };
@@ -4538,6 +5025,7 @@ async fn handle_chat(
                     output_tokens: None,
                     estimated_cost_usd: None,
                     mode: None,
+                    error_code: None,
                 });
             }
         }
@@ -4576,6 +5064,7 @@ async fn handle_chat(
                         output_tokens: None,
                         estimated_cost_usd: None,
                         mode: None,
+                        error_code: None,
                     });
                 }
             }
@@ -4617,6 +5106,7 @@ async fn handle_chat(
                             output_tokens: None,
                             estimated_cost_usd: None,
                             mode: None,
+                            error_code: None,
                         });
                     }
                 }
@@ -4624,40 +5114,16 @@ async fn handle_chat(
         }
     }
 
-    // Check concurrent request limit (10 for free, 1000 for paid) — using cached user
-    let max_concurrent = {
-        #[cfg(feature = "dynamodb-backend")]
-        {
-            let mut limit = 10u32; // free tier default
-            if let Some(ref user) = cached_user {
-                if user.plan == "starter" || user.plan == "pro" {
-                    limit = 1000;
-                }
-            }
-            limit
-        }
-        #[cfg(not(feature = "dynamodb-backend"))]
-        { 10u32 }
+    // Per-user concurrency: over the plan's limit, wait in the user's queue
+    let limit = state.chat_queue.limit_for(cached_user.as_ref().map(|u| u.plan.as_str()));
+    let permit = match state.chat_queue.enqueue(&session_key, &req.channel, limit) {
+        Ticket::Ready(permit) => Some(permit),
+        Ticket::Queued(queued) => queued.wait(state.chat_queue.queue_timeout(), |_| {}).await,
+        Ticket::Full => None,
     };
-    // Increment concurrent count
-    {
-        state.concurrent_requests
-            .entry(session_key.clone())
-            .or_insert_with(|| AtomicU32::new(0));
-    }
-    let current = state.concurrent_requests
-        .get(&session_key)
-        .map(|v| v.value().fetch_add(1, Ordering::SeqCst))
-        .unwrap_or(0);
-    if current >= max_concurrent {
-        if let Some(v) = state.concurrent_requests.get(&session_key) {
-            v.value().fetch_sub(1, Ordering::SeqCst);
-        }
+    let Some(_permit) = permit else {
         return Json(ChatResponse {
-            response: format!(
-                "同時リクエスト数が上限（{}）に達しました。しばらくお待ちください。\nConcurrent request limit ({}) reached. Please wait.",
-                max_concurrent, max_concurrent
-            ),
+            response: concurrency_limit_message(limit),
             session_id: req.session_id,
             agent: None,
             tools_used: None,
@@ -4670,26 +5136,29 @@ async fn handle_chat(
             output_tokens: None,
             estimated_cost_usd: None,
             mode: None,
+            error_code: Some(ErrorCode::RateLimited),
         });
-    }
-    // Decrement on exit via drop guard
-    let guard_key = session_key.clone();
-    let guard_state = state.clone();
-    struct ConcurrencyGuard {
-        key: String,
-        state: Arc<AppState>,
-    }
-    impl Drop for ConcurrencyGuard {
-        fn drop(&mut self) {
-            if let Some(v) = self.state.concurrent_requests.get(&self.key) {
-                v.value().fetch_sub(1, Ordering::SeqCst);
-            }
-        }
-    }
-    let _guard = ConcurrencyGuard { key: guard_key, state: guard_state };
+    };
+
+    // Session-scoped overrides set via /model and /agent, and the response locale
+    // (auto-detected from this message unless fixed with /lang)
+    let (session_model, session_agent, locale) = {
+        let mut sessions = state.sessions.lock().await;
+        let session = sessions.get_or_create(&session_key);
+        let default_locale = if chat_host.contains("teai.io") { Locale::En } else { Locale::Ja };
+        let previous = session.locale();
+        let locale = session.observe_locale(&req.message, default_locale);
+        let overrides = (session.model_override().map(str::to_string), session.agent_override().map(str::to_string), locale);
+        if session.locale() != previous {
+            sessions.save_by_key(&session_key);
+        }
+        overrides
+    };
+    let is_english = locale.is_english();
 
     // Multi-agent orchestration: route to best agent
-    let (agent, clean_message, agent_score) = detect_agent(&req.message);
+    let (agent, clean_message, agent_score) = detect_agent_with_override(&req.message, req.options.agent.as_deref().or(session_agent.as_deref()));
+    let (agent, route) = learned_route(&state, agent, agent_score, &clean_message, &req.channel, req.device.as_deref().unwrap_or("pc"));
     info!("Agent selected: {} (score={}) for message", agent.id, agent_score);
 
     // Build conversation with session history — include current date + memory + meta context in system prompt
@@ -4723,24 +5192,29 @@ async fn handle_chat(
         agent.system_prompt.to_string()
     };
 
-    // Device-based character limit
+    // Device/channel-based length and tone (config + session /length, /tone)
     let device = req.device.as_deref().unwrap_or("pc");
-    let max_chars = match device {
-        "mobile" => agent.max_chars_mobile,
-        "voice" => agent.max_chars_voice,
-        _ => agent.max_chars_pc,
-    };
-    let char_instruction = format!(
-        "\n\n【応答制約】回答は{}文字以内に収めてください。簡潔に要点を伝えてください。",
-        max_chars
-    );
 
     // Get session history first (need history_len for meta context)
     // Use fewer history messages for small-context models (Nemotron 8K)
     let history_messages: Vec<(String, String)>;
+    let pins_block: String;
+    let contacts_block: String;
+    let response_style: ResponseStyle;
+    let session_tz: Option<String>;
     {
         let mut sessions = state.sessions.lock().await;
         let session = sessions.refresh(&session_key);
+        pins_block = session.pins_context(pin_budget(&state.config));
+        contacts_block = session.contacts_context(&req.message);
+        session_tz = session.timezone().map(str::to_string);
+        response_style = ResponseStyle::resolve(
+            &state.config.agents.response,
+            super::commands::channel_id(&session_key),
+            device,
+            agent.max_chars_for(device),
+            Some(session),
+        );
         let history = session.get_history_with_summary(4);
         history_messages = history.iter().filter_map(|msg| {
             let role = msg.get("role").and_then(|v| v.as_str())?;
@@ -4791,7 +5265,19 @@ async fn handle_chat(
         false
     };
 
-    let model = if is_adult_mode_on && contains_adult_content && req.model.is_none() && user_settings.as_ref().and_then(|s| s.preferred_model.as_deref()).is_none() {
+    // Confidence-based escalation: the economy tier answers first unless a
+    // model was chosen explicitly (see agent::escalation)
+    let escalation = crate::agent::escalation::EscalationPolicy::from_config(&state.config.agents.escalation)
+        .filter(|_| !resolved_mode.is_multi_model() && req.model.is_none() && req.options.tier.is_none() && session_model.is_none())
+        .filter(|_| user_settings.as_ref().and_then(|s| s.preferred_model.as_deref()).is_none());
+    let escalation_economy: Option<String> = escalation.as_ref().and_then(|policy| {
+        policy.economy_model().map(str::to_string)
+            .or_else(|| state.get_lb_raw().and_then(|lb| lb.get_tier_model("economy")).map(|(_, m)| m))
+    });
+    let tier_model: Option<String> = req.options.tier
+        .and_then(|tier| state.get_lb_raw().and_then(|lb| lb.get_tier_model(tier.as_str())).map(|(_, m)| m));
+
+    let model = if is_adult_mode_on && contains_adult_content && req.model.is_none() && session_model.is_none() && user_settings.as_ref().and_then(|s| s.preferred_model.as_deref()).is_none() {
         // Adult content detected: use Midnight Miqu or Euryale from OpenRouter
         use rand::Rng;
         let mut rng = rand::thread_rng();
@@ -4801,9 +5287,12 @@ async fn handle_chat(
             "openrouter/sao10k/euryale-2.1-l2-70b"
         }
     } else {
-        req.model
+        session_model
             .as_deref()
+            .or(req.model.as_deref())
+            .or(tier_model.as_deref())
             .or(user_settings.as_ref().and_then(|s| s.preferred_model.as_deref()))
+            .or(escalation_economy.as_deref())
             .or(agent.preferred_model)
             .unwrap_or_else(|| {
                 // Model A/B test: rotate between models based on session hash
@@ -4811,6 +5300,7 @@ async fn handle_chat(
             })
     };
     let model = model.to_string();
+    let escalation = escalation.filter(|_| escalation_economy.as_deref() == Some(model.as_str()));
 
     // Build meta-cognition context (now includes model/cost info)
     let meta_context = build_meta_context_with_model(
@@ -4818,7 +5308,8 @@ async fn handle_chat(
         &req.channel,
         device,
         history_messages.len(),
-        is_teai,
+        is_english,
+        session_tz.as_deref(),
         Some(&model),
         0, // session tokens (updated per-session in future)
         0, // session cost microdollars
@@ -4832,12 +5323,12 @@ async fn handle_chat(
         .unwrap_or(false);
 
     let meta_instruction = {
-        let base = if is_teai { META_INSTRUCTION_EN } else { META_INSTRUCTION_JA };
+        let base = if is_english { META_INSTRUCTION_EN } else { META_INSTRUCTION_JA };
         let low_credits_part = if is_low_credits {
-            if is_teai { META_LOW_CREDITS_EN } else { META_LOW_CREDITS_JA }
+            if is_english { META_LOW_CREDITS_EN } else { META_LOW_CREDITS_JA }
         } else { "" };
         let adult_part = if is_adult {
-            if is_teai { META_ADULT_EN } else { META_ADULT_JA }
+            if is_english { META_ADULT_EN } else { META_ADULT_JA }
         } else { "" };
         format!("{}{}{}", base, low_credits_part, adult_part)
     };
@@ -4894,11 +5385,27 @@ async fn handle_chat(
         ""
     };
 
-    let system_prompt = if memory_context.is_empty() {
-        format!("{}{}{}\n\n今日の日付: {}{}{}{}{}{}{}{}", base_prompt, AGENT_COMMON, model_identity_block, today, meta_context, meta_instruction, adult_prompt, wow_prompt, custom_sys_block, skills_block, char_instruction)
+    let memory_block = if memory_context.is_empty() {
+        String::new()
     } else {
-        format!("{}{}{}\n\n今日の日付: {}{}{}{}{}{}{}\n\n---\n{}{}", base_prompt, AGENT_COMMON, model_identity_block, today, meta_context, meta_instruction, adult_prompt, wow_prompt, custom_sys_block, skills_block, memory_context, char_instruction)
+        format!("---\n{}", memory_context)
     };
+    let system_prompt = PromptCompiler::new(&state.config.agents.prompt)
+        .required("agent", base_prompt)
+        .required("common", AGENT_COMMON)
+        .section("model_identity", model_identity_block)
+        .section("meta", format!("今日の日付: {}{}", today, meta_context))
+        .section("meta_instruction", meta_instruction)
+        .section("adult", adult_prompt)
+        .section("wow", wow_prompt)
+        .required("custom", custom_sys_block)
+        .required("pins", pins_block)
+        .section("contacts", contacts_block)
+        .section("skills", skills_block.as_str())
+        .section("memory", memory_block)
+        .required("length", response_style.instruction(locale))
+        .compile()
+        .text;
     let mut messages = vec![
         Message::system(&system_prompt),
     ];
@@ -4914,11 +5421,33 @@ async fn handle_chat(
 
     // Always provide the user message as-is; tool guidance is in AGENT_COMMON system prompt
     messages.push(Message::user(&clean_message));
+    // The economy model also reports its confidence; an escalated re-run doesn't
+    let unassessed_messages = escalation.as_ref().map(|policy| {
+        let plain = messages.clone();
+        policy.instruct(&mut messages);
+        plain
+    });
     // Resolve LLM parameters: request > user settings > defaults
     let max_tokens = req.max_tokens.unwrap_or(state.config.agents.defaults.max_tokens);
     let temperature = req.temperature
         .or(user_settings.as_ref().and_then(|s| s.temperature))
         .unwrap_or(state.config.agents.defaults.temperature);
+    let max_tokens = cost_capped_max_tokens(&state, &req.options, resolved_mode, &model, &messages, max_tokens);
+    if max_tokens == 0 {
+        return Json(ChatResponse {
+            response: over_budget_message(locale).to_string(),
+            session_id: req.session_id,
+            agent: Some(agent.id.to_string()), tools_used: None,
+            credits_used: None, credits_remaining: None,
+            model_used: None, models_consulted: None,
+            action: None,
+            input_tokens: None,
+            output_tokens: None,
+            estimated_cost_usd: None,
+            mode: Some(resolved_mode.as_str().to_string()),
+            error_code: None,
+        });
+    }
 
     // If user has custom API keys, create per-request provider
     let custom_provider: Option<Arc<dyn LlmProvider>> = user_settings.as_ref()
@@ -4964,7 +5493,7 @@ async fn handle_chat(
     if agent.id == "multi_agent" {
         relevant_tool_names.insert("multi_agent");
     }
-    let tools = if agent.tools_enabled {
+    let tools = if agent.tools_enabled && req.options.tools_enabled() {
         let all_tools = state.tool_registry.get_definitions();
         let mut filtered: Vec<serde_json::Value> = all_tools.into_iter()
             .filter(|t| {
@@ -4997,7 +5526,7 @@ async fn handle_chat(
     info!("Calling LLM: model={}, tools={}/{} (dynamic), agent={}", model, tools.len(), state.tool_registry.len(), agent.id);
 
     // --- Parallel multi-model race path ---
-    if req.multi_model {
+    if resolved_mode == ChatMode::Race {
         // Free plan cannot use parallel mode (cost 3-4x)
         #[cfg(feature = "dynamodb-backend")]
         {
@@ -5014,6 +5543,7 @@ async fn handle_chat(
                         output_tokens: None,
                         estimated_cost_usd: None,
                         mode: None,
+                        error_code: None,
                     });
                 }
             }
@@ -5024,7 +5554,7 @@ async fn handle_chat(
             info!("Parallel multi-model race: starting");
             match lb.chat_parallel(&messages, tools_ref, max_tokens, temperature).await {
                 Ok((resp, winning_model, all_usage)) => {
-                    let response_text = resp.content.unwrap_or_default();
+                    let response_text = response_style.enforce(&resp.content.unwrap_or_default());
                     let models_consulted: Vec<String> = all_usage.iter().map(|(m, _, _)| m.clone()).collect();
 
                     // Deduct credits for all successful calls
@@ -5074,7 +5604,8 @@ async fn handle_chat(
                         input_tokens: None,
                         output_tokens: None,
                         estimated_cost_usd: None,
-                        mode: None,
+                        mode: Some(resolved_mode.as_str().to_string()),
+                        error_code: None,
                     });
                 }
                 Err(e) => {
@@ -5085,35 +5616,160 @@ async fn handle_chat(
         }
     }
 
-    #[allow(unused_mut)]
-    let mut total_credits_used: i64 = 0;
-    #[allow(unused_mut)]
-    let mut last_remaining_credits: Option<i64> = None;
-    let mut total_input_tokens: u32 = 0;
-    let mut total_output_tokens: u32 = 0;
-
-    // Build extra LLM parameters from request
-    let chat_extra = provider::ChatExtra {
-        top_p: req.top_p.or(user_settings.as_ref().and_then(|s| s.top_p)),
-        frequency_penalty: req.frequency_penalty.or(user_settings.as_ref().and_then(|s| s.frequency_penalty)),
-        presence_penalty: req.presence_penalty.or(user_settings.as_ref().and_then(|s| s.presence_penalty)),
-    };
+    // --- Explore / consensus paths: every model answers; all answers are
+    // returned side by side, or a judge merges them ---
+    if matches!(resolved_mode, ChatMode::Explore | ChatMode::Consensus) {
+        // Same cost profile as multi-model, so the same plan restriction
+        #[cfg(feature = "dynamodb-backend")]
+        {
+            if let Some(ref user) = cached_user {
+                if user.plan == "free" {
+                    return Json(ChatResponse {
+                        response: format!("The {} mode is not available on the free plan. Please upgrade.", resolved_mode),
+                        session_id: req.session_id,
+                        agent: None, tools_used: None,
+                        credits_used: None, credits_remaining: None,
+                        model_used: None, models_consulted: None,
+                        action: None,
+                        input_tokens: None,
+                        output_tokens: None,
+                        estimated_cost_usd: None,
+                        mode: None,
+                        error_code: None,
+                    });
+                }
+            }
+        }
 
-    // LLM call with hard deadline (failover handled by LoadBalancedProvider)
+        if let Some(ref lb) = state.get_lb_raw() {
+            info!("{}: collecting answers", resolved_mode);
+            let results = lb.chat_explore(&messages, None, max_tokens, temperature).await;
+            let mut usage: Vec<(String, u32, u32)> = results.iter()
+                .map(|r| (r.model.clone(), r.input_tokens, r.output_tokens))
+                .collect();
+            let answered = if resolved_mode == ChatMode::Consensus {
+                let (judge, judge_model) = lb.get_tier_model("powerful")
+                    .unwrap_or_else(|| (lb.clone() as Arc<dyn LlmProvider>, model.clone()));
+                match provider::consensus::synthesize(judge.as_ref(), &judge_model, &clean_message, &results).await {
+                    Ok(consensus) => {
+                        info!("Consensus of {} models by {}, {} disagreements",
+                            consensus.models.len(), consensus.judge_model, consensus.disagreements.len());
+                        if consensus.judge_model.is_empty() {
+                            Some((consensus.render(locale), consensus.models.first().cloned(), consensus.models))
+                        } else {
+                            usage.push((
+                                consensus.judge_model.clone(),
+                                consensus.judge_usage.input_tokens,
+                                consensus.judge_usage.output_tokens,
+                            ));
+                            Some((consensus.render(locale), Some(consensus.judge_model.clone()), consensus.models))
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Consensus failed: {}, falling back to single", e);
+                        None
+                    }
+                }
+            } else {
+                let answers: Vec<&provider::ExploreResult> = results.iter().filter(|r| !r.response.trim().is_empty()).collect();
+                if answers.is_empty() {
+                    tracing::error!("Explore: no model answered, falling back to single");
+                    None
+                } else {
+                    let text = answers.iter()
+                        .map(|r| format!("**{}** ({} ms)\n{}", r.model, r.response_time_ms, r.response.trim()))
+                        .collect::<Vec<_>>()
+                        .join("\n\n---\n\n");
+                    let models: Vec<String> = answers.iter().map(|r| r.model.clone()).collect();
+                    Some((text, models.first().cloned(), models))
+                }
+            };
+
+            if let Some((text, model_used, models_consulted)) = answered {
+                let response_text = response_style.enforce(&text);
+                let estimated_cost: f64 = usage.iter()
+                    .map(|(m, i, o)| provider::pricing::calculate_cost(m, *i, *o))
+                    .sum();
+
+                #[allow(unused_mut)]
+                let mut total_credits: i64 = 0;
+                #[allow(unused_mut)]
+                let mut last_remaining: Option<i64> = None;
+                #[cfg(feature = "dynamodb-backend")]
+                {
+                    for (m, input_t, output_t) in &usage {
+                        let (credits, remaining) = deduct_credits_via_state(&state, &session_key, m, *input_t, *output_t).await;
+                        total_credits += credits;
+                        if remaining.is_some() { last_remaining = remaining; }
+                    }
+                    state.user_profile_cache.remove(&session_key);
+                }
+
+                {
+                    let mut sessions = state.sessions.lock().await;
+                    let session = sessions.get_or_create(&session_key);
+                    session.add_message_from_channel("user", &req.message, "web");
+                    session.add_message_from_channel("assistant", &response_text, "web");
+                    sessions.save_by_key(&session_key);
+                }
+                #[cfg(feature = "dynamodb-backend")]
+                {
+                    if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+                        increment_sync_version(dynamo, table, &session_key, "web").await;
+                    }
+                }
+
+                info!("{}: {} models consulted, {} total credits", resolved_mode, models_consulted.len(), total_credits);
+
+                return Json(ChatResponse {
+                    response: response_text,
+                    session_id: req.session_id,
+                    agent: Some(agent.id.to_string()),
+                    tools_used: None,
+                    credits_used: if total_credits > 0 { Some(total_credits) } else { None },
+                    credits_remaining: last_remaining,
+                    model_used,
+                    models_consulted: Some(models_consulted),
+                    action: None,
+                    input_tokens: Some(usage.iter().map(|(_, i, _)| *i).sum()),
+                    output_tokens: Some(usage.iter().map(|(_, _, o)| *o).sum()),
+                    estimated_cost_usd: Some(estimated_cost),
+                    mode: Some(resolved_mode.as_str().to_string()),
+                    error_code: None,
+                });
+            }
+        }
+    }
+
+    #[allow(unused_mut)]
+    let mut total_credits_used: i64 = 0;
+    #[allow(unused_mut)]
+    let mut last_remaining_credits: Option<i64> = None;
+    let mut total_input_tokens: u32 = 0;
+    let mut total_output_tokens: u32 = 0;
+
+    // Build extra LLM parameters from request
+    let chat_extra = provider::ChatExtra {
+        top_p: req.top_p.or(user_settings.as_ref().and_then(|s| s.top_p)),
+        frequency_penalty: req.frequency_penalty.or(user_settings.as_ref().and_then(|s| s.frequency_penalty)),
+        presence_penalty: req.presence_penalty.or(user_settings.as_ref().and_then(|s| s.presence_penalty)),
+    };
+
+    // LLM call with hard deadline (failover handled by LoadBalancedProvider)
     let deadline = std::time::Duration::from_secs(RESPONSE_DEADLINE_SECS);
     let llm_result = tokio::time::timeout(
         deadline,
         active_provider.chat_with_extra(&messages, tools_ref, &model, max_tokens, temperature, &chat_extra),
     ).await;
     let (used_model, first_completion) = match llm_result {
-        Ok(Ok(c)) => (model.clone(), Ok(c)),
+        Ok(Ok(c)) => (served_model(&model, &c), Ok(c)),
         Ok(Err(e)) => {
             tracing::error!("LLM call failed: {}", e);
             (model.clone(), Err(e))
         }
         Err(_) => {
             tracing::warn!("LLM call timed out after {}s, returning fallback", RESPONSE_DEADLINE_SECS);
-            let fallback = timeout_fallback_message();
+            let fallback = timeout_fallback_message(locale);
             // Deduct minimum 1 credit for timeout (input tokens were consumed)
             #[cfg(feature = "dynamodb-backend")]
             {
@@ -5151,11 +5807,13 @@ async fn handle_chat(
                 output_tokens: None,
                 estimated_cost_usd: None,
                 mode: None,
+                error_code: Some(ErrorCode::Timeout),
             });
         }
     };
 
-    let mut had_provider_error = false;
+    let mut provider_error: Option<ErrorCode> = None;
+    let mut sources = crate::citation::Sources::new();
     let (response_text, tools_used) = match first_completion {
         Ok(completion) => {
             record_outage_end();
@@ -5265,6 +5923,8 @@ async fn handle_chat(
 
                 // Execute tool calls in parallel
                 let registry = &state.tool_registry;
+                let tool_stats = &state.tool_stats;
+                let stats_model = used_model.as_str();
                 let sandbox_dir_ref = &sandbox_dir;
                 let futures: Vec<_> = tool_calls_to_run.iter().map(|tc| {
                     let name = tc.name.clone();
@@ -5288,12 +5948,14 @@ async fn handle_chat(
                     if name == "phone_call" || name == "web_deploy" {
                         args.insert("_session_key".to_string(), serde_json::Value::String(session_key.clone()));
                     }
-                    // Inject user_id for browser tools that need vault access
-                    if name.starts_with("browser_") {
+                    // Inject user_id for browser tools (vault access) and the expense ledger
+                    if name.starts_with("browser_") || name == "expense" {
                         args.insert("_user_id".to_string(), serde_json::Value::String(session_key.clone()));
                     }
+                    let stats_user = session_key.clone();
                     async move {
                         info!("Tool call [iter {}]: {} args={:?}", iteration, name, args);
+                        let started = std::time::Instant::now();
                         let raw_result = if name.starts_with(a2a::STAYFLOW_TOOL_PREFIX) {
                             a2a::execute_stayflow_tool(&name, &args).await
                         } else if let Some(url) = webhook_url {
@@ -5315,13 +5977,22 @@ async fn handle_chat(
                         } else {
                             raw_result
                         };
+                        tool_stats.record(&stats_user, &name, started.elapsed(), &result, stats_model);
                         let preview_end = result.char_indices().nth(300).map(|(i, _)| i).unwrap_or(result.len());
                         let preview = &result[..preview_end];
                         info!("Tool result ({}): {} chars — {}", name, result.len(), preview);
                         (id, name, result)
                     }
                 }).collect();
-                let tool_results: Vec<_> = futures::future::join_all(futures).await;
+                // Number fetched pages so the answer can cite them
+                let tool_results: Vec<_> = futures::future::join_all(futures).await
+                    .into_iter()
+                    .zip(&tool_calls_to_run)
+                    .map(|((id, name, result), tc)| {
+                        let result = sources.track(&name, &tc.arguments, result);
+                        (id, name, result)
+                    })
+                    .collect();
                 all_tool_results.extend(tool_results.iter().cloned());
 
                 // Log tool usage to DynamoDB
@@ -5418,6 +6089,7 @@ async fn handle_chat(
                             tool_calls: vec![],
                             finish_reason: crate::types::FinishReason::Stop,
                             usage: crate::types::TokenUsage::default(),
+                            model: None,
                         };
                         break; // Stop iterating on error
                     }
@@ -5443,35 +6115,127 @@ async fn handle_chat(
             } else {
                 content
             };
+
+            // Researcher answers: check claims against what the web tools returned
+            let sources: Vec<crate::factcheck::Source> = if agent.id == "researcher" {
+                all_tool_results.iter()
+                    .filter_map(|(_, name, result)| crate::factcheck::Source::from_tool_result(name, result))
+                    .collect()
+            } else {
+                vec![]
+            };
+            let text = if sources.is_empty() { text } else { check_against_sources(&text, &sources, locale).await };
             (text, tools_used)
         }
         Err(e) => {
             tracing::error!("LLM error (all providers failed): {}", e);
-            had_provider_error = true;
+            let code = e.code();
+            provider_error = Some(code);
             let should_give_apology = record_outage_start();
             #[cfg(feature = "dynamodb-backend")]
             if should_give_apology {
                 if let (Some(dynamo), Some(table), Some(user)) =
                     (state.dynamo_client.as_ref(), state.config_table.as_ref(), cached_user.as_ref())
                 {
-                    add_credits_to_user(dynamo, table, &user.user_id, 1000, "", "").await;
+                    let balance = add_credits_to_user(dynamo, table, &user.user_id, 1000, "", "").await;
+                    record_credits(dynamo, table, &user.user_id, crate::credits::CreditKind::Grant, 1000, balance, "outage apology").await;
                     info!("Outage apology: gave 1000 credits to user {} (outage ≥1h)", user.user_id);
                 }
             }
-            (error_fallback_message(), None)
+            let text = if code == ErrorCode::Unavailable { error_fallback_message() } else { code.user_message(locale).to_string() };
+            (text, None)
+        }
+    };
+
+    // Escalation: drop the confidence line, and re-run on the powerful tier
+    // when the economy answer (without tools) isn't confident
+    let mut used_model = used_model;
+    let mut escalation_record: Option<crate::agent::escalation::EscalationRecord> = None;
+    let response_text = match (escalation.as_ref(), unassessed_messages) {
+        (Some(policy), Some(plain)) if provider_error.is_none() => {
+            let assessment = policy.assess(active_provider.as_ref(), &clean_message, &response_text).await;
+            let powerful = policy.powerful_model()
+                .map(|m| (active_provider.clone(), m.to_string()))
+                .or_else(|| state.get_lb_raw().and_then(|lb| lb.get_tier_model("powerful")));
+            match (assessment.escalate, powerful) {
+                (Some(reason), Some((powerful_provider, powerful_model))) if tools_used.is_none() => {
+                    info!("Escalating {} -> {}: {:?}", used_model, powerful_model, reason);
+                    let rerun = tokio::time::timeout(
+                        std::time::Duration::from_secs(RESPONSE_DEADLINE_SECS),
+                        powerful_provider.chat_with_extra(&plain, None, &powerful_model, max_tokens, temperature, &chat_extra),
+                    ).await;
+                    match rerun {
+                        Ok(Ok(completion)) if completion.content.as_deref().is_some_and(|c| !c.trim().is_empty()) => {
+                            let economy_usage = crate::types::TokenUsage {
+                                prompt_tokens: total_input_tokens,
+                                completion_tokens: total_output_tokens,
+                                total_tokens: total_input_tokens + total_output_tokens,
+                            };
+                            total_input_tokens += completion.usage.prompt_tokens;
+                            total_output_tokens += completion.usage.completion_tokens;
+                            #[cfg(feature = "dynamodb-backend")]
+                            {
+                                let (credits, remaining) = deduct_credits_via_state(
+                                    &state, &session_key, &powerful_model,
+                                    completion.usage.prompt_tokens, completion.usage.completion_tokens,
+                                ).await;
+                                total_credits_used += credits;
+                                if remaining.is_some() { last_remaining_credits = remaining; }
+                            }
+                            escalation_record = Some(crate::agent::escalation::EscalationRecord::new(
+                                &req.channel, reason,
+                                (&used_model, &economy_usage),
+                                (&powerful_model, &completion.usage),
+                            ));
+                            used_model = powerful_model;
+                            completion.content.unwrap_or_default()
+                        }
+                        Ok(Ok(_)) => assessment.answer,
+                        Ok(Err(e)) => {
+                            warn!("Escalation to {} failed, keeping economy answer: {}", powerful_model, e);
+                            assessment.answer
+                        }
+                        Err(_) => {
+                            warn!("Escalation to {} timed out, keeping economy answer", powerful_model);
+                            assessment.answer
+                        }
+                    }
+                }
+                _ => assessment.answer,
+            }
         }
+        _ => response_text,
     };
+    if let Some(ref record) = escalation_record {
+        info!("Escalation cost: {} ${:.5} + {} ${:.5}",
+            record.economy_model, record.economy_cost_usd, record.powerful_model, record.powerful_cost_usd);
+    }
+    let response_text = response_style.enforce(&response_text);
+    // Numbered source list after the length limit so it is never cut off;
+    // researcher answers always carry one when web tools ran
+    if agent.id == "researcher" {
+        sources.require(MAX_RESEARCH_SOURCES);
+    }
+    let response_text = sources.render(&response_text, locale);
 
     // Save to session
-    {
+    if !incognito {
         let mut sessions = state.sessions.lock().await;
         let session = sessions.get_or_create(&session_key);
         session.add_message_from_channel("user", &req.message, "web");
         session.add_message_from_channel("assistant", &response_text, "web");
+        session.set_turn_meta(&TurnMeta {
+            model: Some(used_model.clone()),
+            prompt_tokens: total_input_tokens,
+            completion_tokens: total_output_tokens,
+            latency_ms: Some(chat_start.elapsed().as_millis() as u64),
+            agent: Some(agent.id.to_string()),
+            tools: tools_used.clone().unwrap_or_default(),
+        });
         sessions.save_by_key(&session_key);
     }
     #[cfg(feature = "dynamodb-backend")]
-    {
+    if !incognito {
         if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
             increment_sync_version(dynamo, table, &session_key, "web").await;
         }
@@ -5480,7 +6244,7 @@ async fn handle_chat(
     // Auto-update conversation title & message count (fire-and-forget)
     // Use auth user_id as the DynamoDB PK so /api/v1/conversations can find it.
     #[cfg(feature = "dynamodb-backend")]
-    {
+    if !incognito {
         if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
             if let Some(conv_id) = req.session_id.strip_prefix("webchat:") {
                 let msg_count = {
@@ -5499,11 +6263,12 @@ async fn handle_chat(
 
     // Auto-save to daily memory log + trigger consolidation (fire-and-forget)
     #[cfg(feature = "dynamodb-backend")]
-    {
+    if !incognito {
         let sk = session_key.clone();
         let user_msg = req.message.clone();
         let bot_msg = response_text.clone();
-        let provider_for_mem = state.get_lb_provider().or_else(|| state.provider.clone());
+        let consolidation = memory_consolidation(&state.config, state.get_lb_provider().or_else(|| state.provider.clone()));
+        let every = state.config.agents.memory.every_entries;
         let summary = format!("- Q: {} → A: {}",
             if user_msg.len() > 80 { let mut i = 80; while i > 0 && !user_msg.is_char_boundary(i) { i -= 1; } format!("{}...", &user_msg[..i]) } else { user_msg },
             if bot_msg.len() > 120 { let mut i = 120; while i > 0 && !bot_msg.is_char_boundary(i) { i -= 1; } format!("{}...", &bot_msg[..i]) } else { bot_msg },
@@ -5522,9 +6287,9 @@ async fn handle_chat(
             let table = table.clone();
             tokio::spawn(async move {
                 let entry_count = append_daily_memory(&dynamo, &table, &sk, &summary).await;
-                if entry_count > 0 && entry_count % 10 == 0 {
-                    if let Some(provider) = provider_for_mem {
-                        spawn_consolidate_memory(dynamo, table, sk, provider);
+                if every > 0 && entry_count > 0 && entry_count % every == 0 {
+                    if let Some(job) = consolidation {
+                        spawn_consolidate_memory(dynamo, table, sk, job);
                     }
                 }
             });
@@ -5535,9 +6300,9 @@ async fn handle_chat(
             let table = table.clone();
             tokio::spawn(async move {
                 let entry_count = append_daily_memory(&dynamo, &table, &sk, &summary).await;
-                if entry_count > 0 && entry_count % 10 == 0 {
-                    if let Some(provider) = provider_for_mem {
-                        spawn_consolidate_memory(dynamo, table, sk, provider);
+                if every > 0 && entry_count > 0 && entry_count % every == 0 {
+                    if let Some(job) = consolidation {
+                        spawn_consolidate_memory(dynamo, table, sk, job);
                     }
                 }
             });
@@ -5549,7 +6314,7 @@ async fn handle_chat(
     // When a Bearer token is present, load the auth user's profile directly; fall back to
     // cached_user (which is session-key-based and may be a different key).
     #[cfg(feature = "dynamodb-backend")]
-    {
+    if !incognito {
         let is_dev_mode = {
             // Bypass cache: dev_mode may have been set after this Lambda container cached the profile.
             if let (Some(auth_uid), Some(dynamo), Some(table)) = (
@@ -5590,6 +6355,14 @@ async fn handle_chat(
             response_text = translated;
         }
     }
+    // Deployer post-processing last; the session keeps the unhooked reply
+    let hook_ctx = crate::hooks::HookContext { channel: &req.channel, agent: Some(agent.id), session_key: &session_key };
+    let response_text = state.response_hooks.apply(response_text, &hook_ctx).await;
+    let response_text = if incognito {
+        crate::session::incognito::label(&response_text, locale)
+    } else {
+        response_text
+    };
 
     // Use remaining credits from deduct_credits (no extra DynamoDB call needed)
     let remaining_credits: Option<i64> = last_remaining_credits;
@@ -5601,7 +6374,7 @@ async fn handle_chat(
         tools_used.as_ref().map(|t| t.len()).unwrap_or(0),
         latency_ms, response_text.len());
     #[cfg(feature = "dynamodb-backend")]
-    {
+    if !incognito {
         if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
             emit_audit_log(dynamo.clone(), table.clone(), "chat", &session_key, "",
                 &format!("model={} credits={} tools={} latency={}ms",
@@ -5617,7 +6390,7 @@ async fn handle_chat(
                 session_key.hash(&mut h);
                 format!("{:x}", h.finish())
             };
-            log_routing_data(dynamo.clone(), table.clone(), RoutingLogEntry {
+            log_routing_data(dynamo.clone(), table.clone(), state.config.agents.routing.log_retention_days, RoutingLogEntry {
                 message_len: req.message.len(),
                 language: detect_language(&req.message).to_string(),
                 channel: req.channel.clone(),
@@ -5634,13 +6407,72 @@ async fn handle_chat(
                 completion_tokens: 0,
                 timed_out: used_model == "timeout",
                 error: false,
+                route_features: route.features.clone(),
+                learned_agent: route.prediction.as_ref().map(|p| p.agent.clone()),
+                learned_confidence: route.prediction.as_ref().map(|p| p.confidence),
+                learned_override: route.overridden,
                 timestamp: chrono::Utc::now().to_rfc3339(),
                 session_hash,
             });
         }
     }
 
-    let estimated_cost = crate::provider::pricing::calculate_cost(&used_model, total_input_tokens, total_output_tokens);
+    // Outbound webhooks: message processed, tools executed, credits crossing the low mark
+    {
+        let mut webhooks = state.webhooks.lock().await;
+        if webhooks.is_enabled() {
+            use crate::webhook::WebhookEvent;
+            let user_id = cached_user.as_ref().map(|u| u.user_id.clone());
+            for tool in tools_used.iter().flatten() {
+                webhooks.emit(WebhookEvent::ToolExecuted, serde_json::json!({
+                    "tool": tool,
+                    "session": session_key,
+                    "channel": req.channel,
+                }));
+            }
+            webhooks.emit(WebhookEvent::MessageProcessed, serde_json::json!({
+                "session": session_key,
+                "userId": user_id,
+                "channel": req.channel,
+                "agent": agent.id,
+                "model": used_model,
+                "creditsUsed": total_credits_used,
+                "latencyMs": latency_ms as u64,
+                "responseLength": response_text.len(),
+            }));
+            let before = cached_user.as_ref().map(|u| u.credits_remaining);
+            if let Some(remaining) = remaining_credits {
+                if remaining <= WEBHOOK_LOW_CREDITS && before.is_none_or(|b| b > WEBHOOK_LOW_CREDITS) {
+                    webhooks.emit(WebhookEvent::CreditsLow, serde_json::json!({
+                        "userId": user_id,
+                        "session": session_key,
+                        "creditsRemaining": remaining,
+                        "threshold": WEBHOOK_LOW_CREDITS,
+                    }));
+                }
+            }
+            drop(webhooks);
+            let outbox = state.webhooks.clone();
+            tokio::spawn(async move {
+                crate::webhook::flush(&outbox, &client_for(ClientClass::Webhook)).await;
+            });
+        }
+    }
+
+    // Event rules: credits crossing a rule's threshold
+    if let Some(remaining) = remaining_credits {
+        state.rules.fire(&crate::rules::RuleEvent::CreditsLow {
+            user_id: cached_user.as_ref().map(|u| u.user_id.as_str()),
+            before: cached_user.as_ref().map(|u| u.credits_remaining),
+            remaining,
+        });
+    }
+
+    // Escalated requests cost both attempts, each at its own model's price
+    let estimated_cost = match escalation_record.as_ref() {
+        Some(record) => record.total_cost_usd(),
+        None => crate::provider::pricing::calculate_cost(&used_model, total_input_tokens, total_output_tokens),
+    };
     Json(ChatResponse {
         response: response_text,
         session_id: req.session_id,
@@ -5649,12 +6481,13 @@ async fn handle_chat(
         credits_used: if total_credits_used > 0 { Some(total_credits_used) } else { None },
         credits_remaining: remaining_credits,
         model_used: Some(used_model),
-        models_consulted: None,
-        action: if had_provider_error { Some("retry_scheduled".to_string()) } else { None },
+        models_consulted: escalation_record.map(|r| vec![r.economy_model, r.powerful_model]),
+        action: provider_error.filter(|c| c.is_retryable()).map(|_| "retry_scheduled".to_string()),
         input_tokens: if total_input_tokens > 0 { Some(total_input_tokens) } else { None },
         output_tokens: if total_output_tokens > 0 { Some(total_output_tokens) } else { None },
         estimated_cost_usd: if estimated_cost > 0.0 { Some(estimated_cost) } else { None },
-        mode: Some(resolved_mode.to_string()),
+        mode: Some(resolved_mode.as_str().to_string()),
+        error_code: provider_error,
     })
 }
 
@@ -5792,9 +6625,11 @@ async fn handle_device_heartbeat(
 
             match item_builder.send().await {
                 Ok(_) => {
+                    let commands = state.device_commands.lock().await.drain(&user_key, &req.hostname);
                     return (StatusCode::OK, Json(serde_json::json!({
                         "status": "ok",
                         "next_heartbeat_secs": 60,
+                        "commands": commands,
                     })));
                 }
                 Err(e) => {
@@ -5807,15 +6642,46 @@ async fn handle_device_heartbeat(
         }
     }
 
-    #[cfg(not(feature = "dynamodb-backend"))]
-    let _ = &state;
-
+    let commands = state.device_commands.lock().await.drain(&req.session_id, &req.hostname);
     (StatusCode::OK, Json(serde_json::json!({
         "status": "ok",
         "next_heartbeat_secs": 60,
+        "commands": commands,
     })))
 }
 
+/// POST /api/v1/devices/commands — Queue a remote command for one of the
+/// caller's devices; it is delivered with the device's next heartbeat.
+async fn handle_device_command(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<DeviceCommandRequest>,
+) -> impl IntoResponse {
+    let Some(session_id) = headers.get("x-session-id").and_then(|v| v.to_str().ok()) else {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "x-session-id required" })));
+    };
+
+    // Heartbeats are filed under the unified user key; match them
+    let owner = {
+        #[cfg(feature = "dynamodb-backend")]
+        {
+            if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+                resolve_session_key(dynamo, table, session_id).await
+            } else {
+                session_id.to_string()
+            }
+        }
+        #[cfg(not(feature = "dynamodb-backend"))]
+        {
+            session_id.to_string()
+        }
+    };
+
+    let id = state.device_commands.lock().await.push(&owner, &req.hostname, req.command);
+    info!("Queued device command {} for {}", id, req.hostname);
+    (StatusCode::OK, Json(serde_json::json!({ "id": id, "status": "queued" })))
+}
+
 // ---------------------------------------------------------------------------
 // Workers API (compute provider / earn mode)
 // ---------------------------------------------------------------------------
@@ -6212,6 +7078,278 @@ async fn handle_delete_session(
     }
 }
 
+/// Token budget for the pinned-context block (`agents.prompt.sectionMaxTokens.pins`).
+fn pin_budget(config: &Config) -> usize {
+    config.agents.prompt.section_budget("pins")
+        .unwrap_or(crate::session::pins::DEFAULT_PIN_BUDGET_TOKENS)
+}
+
+#[derive(Debug, Deserialize)]
+struct AddPinRequest {
+    content: String,
+}
+
+/// POST /api/v1/sessions/:id/sync — Exchange message deltas with a client
+/// (see `session::sync`). Answers 304 when the client is up to date.
+async fn handle_sync_session(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<crate::session::sync::SyncRequest>,
+) -> axum::response::Response {
+    use crate::session::sync::{etag, SyncResponse};
+
+    let session_key = linked_session_key(&state, &id).await;
+
+    let mut sessions = state.sessions.lock().await;
+    let session = sessions.refresh(&session_key);
+    let pushed = !req.messages.is_empty();
+    if session.apply_push(req.messages) {
+        sessions.save_by_key(&session_key);
+    }
+    let session = sessions.get_or_create(&session_key);
+    let seq = session.sync_seq();
+    let tag = etag(seq);
+    let if_none_match = headers.get(http::header::IF_NONE_MATCH).and_then(|v| v.to_str().ok());
+    if !pushed && if_none_match == Some(tag.as_str()) {
+        return (StatusCode::NOT_MODIFIED, [(http::header::ETAG, tag)]).into_response();
+    }
+    let response = SyncResponse {
+        seq,
+        messages: session.changes_since(req.since),
+    };
+    (StatusCode::OK, [(http::header::ETAG, tag)], Json(response)).into_response()
+}
+
+/// Session that `id` reads and writes: linked channels share the unified
+/// session, and with it its pins and contacts.
+async fn linked_session_key(state: &AppState, id: &str) -> String {
+    #[cfg(feature = "dynamodb-backend")]
+    if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+        return resolve_session_key(dynamo, table, id).await;
+    }
+    let _ = state;
+    id.to_string()
+}
+
+/// GET /api/v1/sessions/:id/pins — List pinned items
+async fn handle_list_pins(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let key = linked_session_key(&state, &id).await;
+    let mut sessions = state.sessions.lock().await;
+    let pins = sessions.get(&key).map(|s| s.pins()).unwrap_or_default();
+    Json(serde_json::json!({ "pins": pins }))
+}
+
+/// POST /api/v1/sessions/:id/pins — Pin a fact, file path or URL
+async fn handle_add_pin(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(req): Json<AddPinRequest>,
+) -> impl IntoResponse {
+    let key = linked_session_key(&state, &id).await;
+    let mut sessions = state.sessions.lock().await;
+    let pin = sessions.get_or_create(&key).add_pin(&req.content);
+    match pin {
+        Some(pin) => {
+            sessions.save_by_key(&key);
+            (StatusCode::OK, Json(serde_json::json!({ "pin": pin })))
+        }
+        None => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Empty content or pin limit reached" })),
+        ),
+    }
+}
+
+/// DELETE /api/v1/sessions/:id/pins/:pin_id — Unpin an item
+async fn handle_remove_pin(
+    State(state): State<Arc<AppState>>,
+    Path((id, pin_id)): Path<(String, u32)>,
+) -> impl IntoResponse {
+    let key = linked_session_key(&state, &id).await;
+    let mut sessions = state.sessions.lock().await;
+    if sessions.get(&key).is_some_and(|s| s.remove_pin(pin_id)) {
+        sessions.save_by_key(&key);
+        (StatusCode::OK, Json(serde_json::json!({"deleted": true})))
+    } else {
+        (StatusCode::NOT_FOUND, Json(serde_json::json!({"deleted": false, "error": "Pin not found"})))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct UpsertContactRequest {
+    name: String,
+    #[serde(default)]
+    identities: Vec<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    notes: Option<String>,
+}
+
+/// GET /api/v1/sessions/:id/contacts — List contacts
+async fn handle_list_contacts(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let key = linked_session_key(&state, &id).await;
+    let mut sessions = state.sessions.lock().await;
+    let contacts = sessions.get(&key).map(|s| s.contacts()).unwrap_or_default();
+    Json(serde_json::json!({ "contacts": contacts }))
+}
+
+/// POST /api/v1/sessions/:id/contacts — Create or update a contact
+async fn handle_upsert_contact(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(req): Json<UpsertContactRequest>,
+) -> impl IntoResponse {
+    let key = linked_session_key(&state, &id).await;
+    let mut sessions = state.sessions.lock().await;
+    let result = sessions
+        .get_or_create(&key)
+        .upsert_contact(&req.name, &req.identities, &req.aliases, req.notes.as_deref());
+    match result {
+        Ok(contact) => {
+            sessions.save_by_key(&key);
+            (StatusCode::OK, Json(serde_json::json!({ "contact": contact })))
+        }
+        Err(e @ crate::session::contacts::ContactError::Taken { .. }) => {
+            (StatusCode::CONFLICT, Json(serde_json::json!({ "error": e.to_string() })))
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e.to_string() }))),
+    }
+}
+
+/// DELETE /api/v1/sessions/:id/contacts/:name — Remove a contact
+async fn handle_remove_contact(
+    State(state): State<Arc<AppState>>,
+    Path((id, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    let key = linked_session_key(&state, &id).await;
+    let mut sessions = state.sessions.lock().await;
+    if sessions.get(&key).is_some_and(|s| s.remove_contact(&name)) {
+        sessions.save_by_key(&key);
+        (StatusCode::OK, Json(serde_json::json!({"deleted": true})))
+    } else {
+        (StatusCode::NOT_FOUND, Json(serde_json::json!({"deleted": false, "error": "Contact not found"})))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ForwardMessageRequest {
+    /// Contact name or alias, or `channel:id`.
+    to: String,
+    content: String,
+    #[serde(default)]
+    channel: Option<String>,
+}
+
+/// Routes that act on someone's behalf and so are only served when
+/// `gateway.apiTokens` is set.
+fn token_only_routes(state: &AppState) -> Router<Arc<AppState>> {
+    if state.config.gateway.api_tokens.is_empty() {
+        return Router::new();
+    }
+    Router::new().route("/api/v1/sessions/{id}/forward", post(handle_forward_message))
+}
+
+/// Whether the Bearer token may act for the session `key`: a
+/// `gateway.apiTokens` token, or a signed-in user whose session it is.
+async fn owns_session(state: &AppState, headers: &axum::http::HeaderMap, key: &str) -> bool {
+    let bearer = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if bearer.is_some_and(|token| state.config.gateway.api_tokens.iter().any(|t| t == token)) {
+        return true;
+    }
+    auth_user_id(state, headers).await.is_some_and(|user| user == key)
+}
+
+/// POST /api/v1/sessions/:id/forward — Send a message to a contact or a
+/// linked channel (LINE, Telegram) and record it in both sessions
+async fn handle_forward_message(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<ForwardMessageRequest>,
+) -> impl IntoResponse {
+    use crate::session::contacts::normalize_identity;
+    use crate::tool::forward::{linked_identities, resolve_target, Forward};
+
+    let content = req.content.trim();
+    if content.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": "Empty content" })));
+    }
+    let key = linked_session_key(&state, &id).await;
+    if !owns_session(&state, &headers, &key).await {
+        return (StatusCode::FORBIDDEN, Json(serde_json::json!({ "error": "Forbidden" })));
+    }
+    let contacts = state.sessions.lock().await.get(&key).map(|s| s.contacts()).unwrap_or_default();
+    // Channels linked in the config or, with accounts, to the same user
+    let mut linked = linked_identities(&state.config.channels.links, &id);
+    if let Some(identity) = normalize_identity(&req.to) {
+        if identity != id && linked_session_key(&state, &identity).await == key {
+            linked.push(identity);
+        }
+    }
+    let (contact, channel, chat_id) = match resolve_target(&contacts, &linked, &req.to, req.channel.as_deref()) {
+        Ok(target) => target,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))),
+    };
+    let forward = Forward { from: key, contact, channel, chat_id, content: content.to_string() };
+
+    let mut outbound = crate::types::OutboundMessage::new(forward.channel.as_str(), forward.chat_id.as_str(), content);
+    let result = match forward.channel.as_str() {
+        "line" => {
+            let token = std::env::var("LINE_CHANNEL_ACCESS_TOKEN").unwrap_or_default();
+            if token.is_empty() {
+                None
+            } else {
+                let delivery_id = state.deliveries.lock().await.enqueue(&mut outbound, Some("forward"));
+                Some((delivery_id, crate::channel::line::LineChannel::push_message(&token, &forward.chat_id, content).await))
+            }
+        }
+        "telegram" => {
+            let token = std::env::var("TELEGRAM_BOT_TOKEN").unwrap_or_default();
+            if token.is_empty() {
+                None
+            } else {
+                let delivery_id = state.deliveries.lock().await.enqueue(&mut outbound, Some("forward"));
+                let client = client_for(ClientClass::General);
+                Some((
+                    delivery_id,
+                    crate::channel::telegram::TelegramChannel::send_message_static(&client, &token, &forward.chat_id, content).await,
+                ))
+            }
+        }
+        _ => None,
+    };
+    match result {
+        None => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": format!("Can't deliver to {} from the API", forward.channel) })),
+        ),
+        Some((delivery_id, Err(e))) => {
+            warn!("forward: send to {} failed: {}", forward.target_key(), e);
+            state.deliveries.lock().await.mark_failed(&delivery_id, &e.to_string());
+            (StatusCode::BAD_GATEWAY, Json(serde_json::json!({ "error": e.to_string(), "delivery_id": delivery_id })))
+        }
+        Some((delivery_id, Ok(()))) => {
+            state.deliveries.lock().await.mark_sent(&delivery_id, None);
+            forward.record(state.sessions.lock().await.as_mut());
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({ "forwarded": true, "to": forward.target_label(), "delivery_id": delivery_id })),
+            )
+        }
+    }
+}
+
 /// GET /api/v1/usage — Usage info (supports Bearer token or x-user-id header)
 async fn handle_usage(
     State(state): State<Arc<AppState>>,
@@ -6252,10 +7390,271 @@ async fn handle_usage(
     })
 }
 
-/// POST /webhooks/line — LINE webhook
-async fn handle_line_webhook(
-    State(state): State<Arc<AppState>>,
-    headers: axum::http::HeaderMap,
+/// Query parameters for `GET /api/v1/usage/tools`.
+#[derive(Debug, Deserialize)]
+struct ToolUsageQuery {
+    days: Option<u32>,
+    /// One session's calls (admins, or anyone on a self-hosted gateway)
+    session: Option<String>,
+}
+
+/// GET /api/v1/usage/tools — calls, failures, latency and downstream token
+/// cost per tool. Signed-in users see their own calls; admins and
+/// self-hosted gateways (already behind API tokens) see everyone's.
+async fn handle_usage_tools(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Query(q): Query<ToolUsageQuery>,
+) -> impl IntoResponse {
+    let days = q.days.unwrap_or(7).clamp(1, 90);
+
+    #[cfg(feature = "dynamodb-backend")]
+    let user = if authenticate_admin(&state, &headers).await.is_some() {
+        q.session
+    } else {
+        let user_id = auth_user_id(&state, &headers).await
+            .or_else(|| headers.get("x-user-id").and_then(|v| v.to_str().ok()).map(|s| s.to_string()))
+            .unwrap_or_else(|| "anonymous".to_string());
+        Some(match (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+            (Some(dynamo), Some(table)) => resolve_session_key(dynamo, table, &user_id).await,
+            _ => user_id,
+        })
+    };
+    #[cfg(not(feature = "dynamodb-backend"))]
+    let user = {
+        let _ = &headers;
+        q.session
+    };
+
+    let tools = state.tool_stats.summary(user.as_deref(), days);
+    Json(serde_json::json!({
+        "days": days,
+        "session": user,
+        "tools": tools,
+    }))
+}
+
+/// A credit ledger, the resolved user id, and the balance with when it was read.
+type CreditsLedger = (Box<dyn crate::credits::CreditLedger>, String, i64, chrono::DateTime<chrono::Utc>);
+
+/// The credit ledger of `user_id` with the resolved user id and current balance.
+async fn credits_ledger(state: &AppState, user_id: &str) -> Option<CreditsLedger> {
+    if let Some(ref db) = state.db {
+        let read_at = chrono::Utc::now();
+        return match db.get_or_create_user(user_id).await {
+            Ok(user) => {
+                let ledger: Box<dyn crate::credits::CreditLedger> = Box::new(crate::credits::DbLedger(db.clone()));
+                Some((ledger, user_id.to_string(), user.credits_remaining, read_at))
+            }
+            Err(e) => {
+                tracing::error!("credits: failed to load user {}: {}", user_id, e);
+                None
+            }
+        };
+    }
+
+    #[cfg(feature = "dynamodb-backend")]
+    if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+        let resolved = resolve_session_key(dynamo, table, user_id).await;
+        let read_at = chrono::Utc::now();
+        let user = get_or_create_user(dynamo, table, &resolved).await;
+        let ledger: Box<dyn crate::credits::CreditLedger> = Box::new(crate::credits::DynamoLedger::new(dynamo.clone(), table.as_str()));
+        return Some((ledger, resolved, user.credits_remaining, read_at));
+    }
+
+    None
+}
+
+/// GET /api/v1/credits/history?limit=20 — current balance and the newest credit
+/// transactions, reconciled with the balance.
+async fn handle_credits_history(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> impl IntoResponse {
+    #[cfg(feature = "dynamodb-backend")]
+    let user_id = auth_user_id(&state, &headers).await;
+    #[cfg(not(feature = "dynamodb-backend"))]
+    let user_id: Option<String> = { let _ = &headers; None };
+    let Some(user_id) = user_id else {
+        return (axum::http::StatusCode::UNAUTHORIZED, Json(serde_json::json!({
+            "error": "Authentication required"
+        }))).into_response();
+    };
+    let limit = params.get("limit").and_then(|l| l.parse::<usize>().ok()).unwrap_or(20).clamp(1, 100);
+
+    let Some((ledger, user_id, balance, read_at)) = credits_ledger(&state, &user_id).await else {
+        return (axum::http::StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({
+            "error": "Credits are unavailable"
+        }))).into_response();
+    };
+
+    match crate::credits::history(ledger.as_ref(), &user_id, balance, read_at, limit).await {
+        Ok(transactions) => Json(serde_json::json!({
+            "balance": balance,
+            "transactions": transactions,
+        })).into_response(),
+        Err(e) => {
+            tracing::error!("credits history failed for {}: {}", user_id, e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Failed to load credit history"
+            }))).into_response()
+        }
+    }
+}
+
+/// Largest sealed settings snapshot stored (DynamoDB items are capped at 400 KB).
+#[cfg(feature = "dynamodb-backend")]
+const SYNC_SNAPSHOT_MAX_BYTES: usize = 350_000;
+
+/// GET /api/v1/sync/config — the caller's sealed settings snapshot for
+/// `chatweb sync now`. It is encrypted on the device; the server never sees
+/// the passphrase. The ETag is the revision to send back in `If-Match`.
+async fn handle_get_sync_config(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    let Some(user_id) = auth_user_id(&state, &headers).await else {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Authentication required" }))).into_response();
+    };
+
+    #[cfg(feature = "dynamodb-backend")]
+    if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let resp = dynamo
+            .get_item()
+            .table_name(table)
+            .key("pk", AttributeValue::S(format!("SYNC#{}", user_id)))
+            .key("sk", AttributeValue::S("CONFIG".to_string()))
+            .send()
+            .await;
+        return match resp {
+            Ok(output) => match output.item {
+                Some(item) => {
+                    let field = |name: &str| item.get(name).and_then(|v| v.as_s().ok()).cloned().unwrap_or_default();
+                    (StatusCode::OK, [(http::header::ETAG, format!("\"{}\"", field("rev")))], field("data")).into_response()
+                }
+                None => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Nothing synced yet" }))).into_response(),
+            },
+            Err(e) => {
+                tracing::error!("sync snapshot read failed for {}: {}", user_id, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": "Failed to load sync data" }))).into_response()
+            }
+        };
+    }
+
+    let _ = user_id;
+    (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({ "error": "Sync storage is unavailable" }))).into_response()
+}
+
+/// PUT /api/v1/sync/config — replace the sealed snapshot. `If-Match` (or
+/// `If-None-Match: *` for the first upload) makes a concurrent write from
+/// another device fail with 412, so the client merges again.
+async fn handle_put_sync_config(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    body: String,
+) -> axum::response::Response {
+    let Some(user_id) = auth_user_id(&state, &headers).await else {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Authentication required" }))).into_response();
+    };
+
+    #[cfg(feature = "dynamodb-backend")]
+    if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        if body.len() > SYNC_SNAPSHOT_MAX_BYTES {
+            return (StatusCode::PAYLOAD_TOO_LARGE, Json(serde_json::json!({
+                "error": format!("Sync data is limited to {} KB", SYNC_SNAPSHOT_MAX_BYTES / 1000)
+            }))).into_response();
+        }
+        let header = |name: http::HeaderName| headers.get(name).and_then(|v| v.to_str().ok()).map(|v| v.trim_matches('"').to_string());
+        let rev = uuid::Uuid::new_v4().to_string();
+        let mut put = dynamo
+            .put_item()
+            .table_name(table)
+            .item("pk", AttributeValue::S(format!("SYNC#{}", user_id)))
+            .item("sk", AttributeValue::S("CONFIG".to_string()))
+            .item("data", AttributeValue::S(body))
+            .item("rev", AttributeValue::S(rev.clone()))
+            .item("updated_at", AttributeValue::S(chrono::Utc::now().to_rfc3339()));
+        if let Some(expected) = header(http::header::IF_MATCH) {
+            put = put
+                .condition_expression("rev = :rev")
+                .expression_attribute_values(":rev", AttributeValue::S(expected));
+        } else if header(http::header::IF_NONE_MATCH).as_deref() == Some("*") {
+            put = put.condition_expression("attribute_not_exists(pk)");
+        }
+        return match put.send().await {
+            Ok(_) => (StatusCode::OK, [(http::header::ETAG, format!("\"{}\"", rev))], Json(serde_json::json!({ "rev": rev }))).into_response(),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_conditional_check_failed_exception()) => {
+                (StatusCode::PRECONDITION_FAILED, Json(serde_json::json!({ "error": "Changed by another device" }))).into_response()
+            }
+            Err(e) => {
+                tracing::error!("sync snapshot write failed for {}: {}", user_id, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": "Failed to save sync data" }))).into_response()
+            }
+        };
+    }
+
+    let _ = (user_id, body);
+    (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({ "error": "Sync storage is unavailable" }))).into_response()
+}
+
+/// Answer a LINE message progressively: acknowledge with the reply token at
+/// once, then push the answer as it streams, split at paragraph boundaries.
+#[allow(clippy::too_many_arguments)]
+async fn line_chat_progressive(
+    provider: &dyn LlmProvider,
+    messages: &[Message],
+    model: &str,
+    max_tokens: u32,
+    temperature: f64,
+    access_token: &str,
+    reply_token: &str,
+    to: &str,
+    config: &crate::config::LineProgressiveConfig,
+) -> Result<crate::types::CompletionResponse, crate::error::ProviderError> {
+    use crate::channel::line::ProgressiveChunker;
+
+    if !config.ack_message.is_empty() {
+        if let Err(e) = LineChannel::reply(access_token, reply_token, &config.ack_message).await {
+            warn!("Failed to acknowledge LINE message: {}", e);
+        }
+    }
+    let push_all = move |chunks: Vec<String>| async move {
+        for chunk in chunks {
+            if let Err(e) = LineChannel::push_message(access_token, to, &chunk).await {
+                tracing::error!("Failed to push LINE message: {}", e);
+            }
+        }
+    };
+
+    let mut chunker = ProgressiveChunker::new(config.chunk_chars, config.max_messages);
+    let (chunk_tx, mut chunk_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let extra = provider::ChatExtra::default();
+    let stream = provider.chat_stream(messages, None, model, max_tokens, temperature, &extra, chunk_tx);
+    tokio::pin!(stream);
+    let result = loop {
+        tokio::select! {
+            Some(delta) = chunk_rx.recv() => push_all(chunker.push(&delta)).await,
+            result = &mut stream => break result,
+        }
+    };
+    while let Ok(delta) = chunk_rx.try_recv() {
+        push_all(chunker.push(&delta)).await;
+    }
+    if result.is_ok() {
+        push_all(chunker.finish().into_iter().collect()).await;
+    }
+    result
+}
+
+/// POST /webhooks/line — LINE webhook
+async fn handle_line_webhook(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
     body: String,
 ) -> impl IntoResponse {
     info!("LINE webhook received: {} bytes", body.len());
@@ -6350,6 +7749,12 @@ async fn handle_line_webhook(
                         }
                     };
 
+                    // `/incognito <message>`: answer just this message without saving it
+                    let (text, incognito_once) = match crate::session::incognito::one_off(text) {
+                        Some(message) => (message, true),
+                        None => (text, false),
+                    };
+
                     // Handle slash commands
                     if let Some(cmd) = super::commands::parse_command(text) {
                         let cmd_provider = state.get_provider();
@@ -6359,6 +7764,7 @@ async fn handle_line_webhook(
                             user_id: None,
                             conv_id: None,
                             sessions: &state.sessions,
+                            model: &state.config.agents.defaults.model,
                             provider: cmd_provider.as_ref(),
                             tool_registry: Some(&state.tool_registry),
                             #[cfg(feature = "dynamodb-backend")]
@@ -6375,19 +7781,42 @@ async fn handle_line_webhook(
                         }
                     }
 
-                    let reply = match state.get_provider() {
-                        Some(provider) => {
+                    // Progressive delivery spends the reply token on its acknowledgment
+                    let progressive = &state.config.channels.line.progressive;
+                    let push_to = event.source.as_ref()
+                        .and_then(|s| s.group_id.as_deref().or(s.room_id.as_deref()).or(s.user_id.as_deref()))
+                        .unwrap_or(user_id);
+                    let mut token_spent = false;
+                    let mut delivered = false;
+
+                    let (incognito, locale) = {
+                        let mut sessions = state.sessions.lock().await;
+                        let session = sessions.get_or_create(&session_key);
+                        (incognito_once || session.incognito(), session.locale().unwrap_or_default())
+                    };
+                    let faq = faq_reply(&state, &session_key, "line", text, incognito).await;
+                    let reply = match (faq, state.get_provider()) {
+                        (Some(hit), _) => hit.answer,
+                        (None, Some(provider)) => {
                             let provider = provider.clone();
-                            let mut messages = vec![
-                                Message::system(
-                                    "あなたはChatWeb（chatweb.ai）、音声対応の高速AIアシスタントです。\
-                                     LINEメッセンジャーでの会話です。\
-                                     - 1メッセージ200文字以内で簡潔に。長い説明は箇条書き。\
-                                     - 絵文字を適度に使用して親しみやすく。\
-                                     - URLは短く。コードブロックは使わない。\
-                                     - 日本語で質問されたら日本語で、英語なら英語で答えてください。"
-                                ),
-                            ];
+                            let line_style = {
+                                let mut sessions = state.sessions.lock().await;
+                                let session = sessions.get_or_create(&session_key);
+                                ResponseStyle::resolve(&state.config.agents.response, "line", "mobile", 200, Some(session))
+                            };
+                            let mut system_prompt = format!(
+                                "あなたはChatWeb（chatweb.ai）、音声対応の高速AIアシスタントです。\
+                                 LINEメッセンジャーでの会話です。\
+                                 - 1メッセージ{}文字以内で簡潔に。長い説明は箇条書き。\
+                                 - 絵文字を適度に使用して親しみやすく。\
+                                 - URLは短く。コードブロックは使わない。\
+                                 - 日本語で質問されたら日本語で、英語なら英語で答えてください。",
+                                line_style.max_chars
+                            );
+                            if let Some(tone) = &line_style.tone {
+                                system_prompt.push_str(&format!("\n- 口調: {}", tone));
+                            }
+                            let mut messages = vec![Message::system(&system_prompt)];
 
                             // Get session history (refresh to pick up messages from other channels)
                             {
@@ -6411,7 +7840,14 @@ async fn handle_line_webhook(
                             let max_tokens = state.config.agents.defaults.max_tokens;
                             let temperature = state.config.agents.defaults.temperature;
 
-                            match provider.chat(&messages, None, model, max_tokens, temperature).await {
+                            let completion = if progressive.enabled {
+                                token_spent = !progressive.ack_message.is_empty();
+                                line_chat_progressive(provider.as_ref(), &messages, model, max_tokens, temperature,
+                                    &access_token, reply_token, push_to, progressive).await
+                            } else {
+                                provider.chat(&messages, None, model, max_tokens, temperature).await
+                            };
+                            match completion {
                                 Ok(completion) => {
                                     // Deduct credits
                                     #[cfg(feature = "dynamodb-backend")]
@@ -6421,35 +7857,45 @@ async fn handle_line_webhook(
                                                 completion.usage.prompt_tokens, completion.usage.completion_tokens).await;
                                         }
                                     }
-                                    let resp = completion.content.unwrap_or_default();
-                                    // Save to session
-                                    {
-                                        let mut sessions = state.sessions.lock().await;
-                                        let session = sessions.get_or_create(&session_key);
-                                        session.add_message_from_channel("user", text, "line");
-                                        session.add_message_from_channel("assistant", &resp, "line");
-                                        sessions.save_by_key(&session_key);
-                                    }
-                                    #[cfg(feature = "dynamodb-backend")]
-                                    {
-                                        if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
-                                            increment_sync_version(dynamo, table, &session_key, "line").await;
+                                    // Progressive answers were pushed in full as they streamed
+                                    delivered = progressive.enabled;
+                                    let content = completion.content.unwrap_or_default();
+                                    let resp = if delivered { content } else { line_style.enforce(&content) };
+                                    if incognito {
+                                        crate::session::incognito::label(&resp, locale)
+                                    } else {
+                                        // Save to session
+                                        {
+                                            let mut sessions = state.sessions.lock().await;
+                                            let session = sessions.get_or_create(&session_key);
+                                            session.add_message_from_channel("user", text, "line");
+                                            session.add_message_from_channel("assistant", &resp, "line");
+                                            sessions.save_by_key(&session_key);
                                         }
+                                        #[cfg(feature = "dynamodb-backend")]
+                                        {
+                                            if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+                                                increment_sync_version(dynamo, table, &session_key, "line").await;
+                                            }
+                                        }
+                                        resp
                                     }
-                                    resp
                                 }
                                 Err(e) => {
                                     tracing::error!("LLM error for LINE: {}", e);
-                                    "すみません、エラーが発生しました。もう一度お試しください。".to_string()
+                                    llm_error_reply(&e, text, Locale::Ja)
                                 }
                             }
                         }
-                        None => "AI provider not configured.".to_string(),
+                        (None, None) => "AI provider not configured.".to_string(),
                     };
 
-                    if let Err(e) =
-                        LineChannel::reply(&access_token, reply_token, &reply).await
-                    {
+                    let sent = match (delivered, token_spent) {
+                        (true, _) => Ok(()),
+                        (false, true) => LineChannel::push_message(&access_token, push_to, &reply).await,
+                        (false, false) => LineChannel::reply(&access_token, reply_token, &reply).await,
+                    };
+                    if let Err(e) = sent {
                         tracing::error!("Failed to reply to LINE: {}", e);
                     }
                 }
@@ -6491,7 +7937,20 @@ async fn handle_telegram_webhook(
         }
     };
 
-    let message = match update.message {
+    if let Some(reaction) = &update.message_reaction {
+        handle_telegram_reaction(&state, reaction).await;
+        return StatusCode::OK;
+    }
+
+    // A pressed inline keyboard button reads as the user sending its text
+    if let Some(query) = &update.callback_query {
+        let client = client_for(ClientClass::General);
+        let token = &state.config.channels.telegram.token;
+        if let Err(e) = TelegramChannel::answer_callback_query(&client, token, &query.id).await {
+            tracing::warn!("Failed to answer Telegram callback query: {}", e);
+        }
+    }
+    let message = match update.message.or_else(|| update.callback_query.as_ref().and_then(|q| q.as_message())) {
         Some(m) => m,
         None => return StatusCode::OK,
     };
@@ -6541,7 +8000,7 @@ async fn handle_telegram_webhook(
             if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
                 auto_link_session(dynamo, table, &channel_key, _sid, &state.sessions).await;
                 let reply = "Link complete! Your Web and Telegram conversations are now synced.\nYou can continue the same conversation on any channel.";
-                let client = reqwest::Client::new();
+                let client = client_for(ClientClass::General);
                 if let Err(e) = TelegramChannel::send_message_static(&client, token, &chat_id, reply).await {
                     tracing::error!("Failed to send Telegram link reply: {}", e);
                 }
@@ -6564,7 +8023,7 @@ async fn handle_telegram_webhook(
             /start - Show this message\n\n\
             Open source: github.com/yukihamada\n\
             https://chatweb.ai";
-        let client = reqwest::Client::new();
+        let client = client_for(ClientClass::General);
         if let Err(e) = TelegramChannel::send_message_static(&client, token, &chat_id, welcome).await {
             tracing::error!("Failed to send Telegram welcome: {}", e);
         }
@@ -6578,7 +8037,7 @@ async fn handle_telegram_webhook(
             let web_sid = text.trim();
             auto_link_session(dynamo, table, &channel_key, web_sid, &state.sessions).await;
             let reply = "Link complete! Your Web and Telegram conversations are now synced.";
-            let client = reqwest::Client::new();
+            let client = client_for(ClientClass::General);
             if let Err(e) = TelegramChannel::send_message_static(&client, token, &chat_id, reply).await {
                 tracing::error!("Failed to send Telegram link reply: {}", e);
             }
@@ -6602,6 +8061,12 @@ async fn handle_telegram_webhook(
         }
     };
 
+    // `/incognito <message>`: answer just this message without saving it
+    let (text, incognito_once) = match crate::session::incognito::one_off(text) {
+        Some(message) => (message, true),
+        None => (text, false),
+    };
+
     // Handle slash commands
     if let Some(cmd) = super::commands::parse_command(text) {
         let cmd_provider = state.get_provider();
@@ -6611,6 +8076,7 @@ async fn handle_telegram_webhook(
             user_id: None,
             conv_id: None,
             sessions: &state.sessions,
+            model: &state.config.agents.defaults.model,
             provider: cmd_provider.as_ref(),
             tool_registry: Some(&state.tool_registry),
             #[cfg(feature = "dynamodb-backend")]
@@ -6620,7 +8086,7 @@ async fn handle_telegram_webhook(
         };
         let result = super::commands::execute_command(cmd, &ctx).await;
         if let super::commands::CommandResult::Reply(reply) = result {
-            let client = reqwest::Client::new();
+            let client = client_for(ClientClass::General);
             if let Err(e) = TelegramChannel::send_message_static(&client, token, &chat_id, &reply).await {
                 tracing::error!("Failed to send Telegram reply: {}", e);
             }
@@ -6628,7 +8094,37 @@ async fn handle_telegram_webhook(
         }
     }
 
-    let reply = match state.get_provider() {
+    let (incognito, locale) = {
+        let mut sessions = state.sessions.lock().await;
+        let session = sessions.get_or_create(&session_key);
+        (incognito_once || session.incognito(), session.locale().unwrap_or_default())
+    };
+    let reply = telegram_chat_reply(&state, &session_key, text, incognito).await;
+
+    // Parse reply tags
+    let (clean_reply, reply_tag) = super::tags::parse_reply_tag(&reply);
+    let clean_reply = if incognito { crate::session::incognito::label(&clean_reply, locale) } else { clean_reply };
+    let reply_to_message_id = if reply_tag == Some("current".to_string()) {
+        Some(message.message_id.to_string())
+    } else {
+        None
+    };
+
+    // Send message with optional reply; image links go out as photos
+    let client = client_for(ClientClass::General);
+    let mut out = crate::types::OutboundMessage::new("telegram", &chat_id, clean_reply);
+    out.reply_to = reply_to_message_id;
+    if let Err(e) = TelegramChannel::deliver(&client, token, &out).await {
+        tracing::error!("Failed to send Telegram reply: {}", e);
+    }
+
+    StatusCode::OK
+}
+
+/// Generate a Telegram reply for `text` and append the turn to the session,
+/// unless `incognito`.
+async fn telegram_chat_reply(state: &AppState, session_key: &str, text: &str, incognito: bool) -> String {
+    match state.get_provider() {
         Some(provider) => {
             let provider = provider.clone();
             let mut messages = vec![
@@ -6645,7 +8141,7 @@ async fn handle_telegram_webhook(
 
             {
                 let mut sessions = state.sessions.lock().await;
-                let session = sessions.refresh(&session_key);
+                let session = sessions.refresh(session_key);
                 let history = session.get_history(10);
                 for msg in &history {
                     let role = msg.get("role").and_then(|v| v.as_str()).unwrap_or("");
@@ -6670,62 +8166,105 @@ async fn handle_telegram_webhook(
                     #[cfg(feature = "dynamodb-backend")]
                     {
                         if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
-                            let _ = deduct_credits(dynamo, table, &session_key, model,
+                            let _ = deduct_credits(dynamo, table, session_key, model,
                                 completion.usage.prompt_tokens, completion.usage.completion_tokens).await;
                         }
                     }
                     let resp = completion.content.unwrap_or_default();
+                    if incognito {
+                        return resp;
+                    }
                     {
                         let mut sessions = state.sessions.lock().await;
-                        let session = sessions.get_or_create(&session_key);
+                        let session = sessions.get_or_create(session_key);
                         session.add_message_from_channel("user", text, "telegram");
                         session.add_message_from_channel("assistant", &resp, "telegram");
-                        sessions.save_by_key(&session_key);
+                        sessions.save_by_key(session_key);
                     }
                     #[cfg(feature = "dynamodb-backend")]
                     {
                         if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
-                            increment_sync_version(dynamo, table, &session_key, "telegram").await;
+                            increment_sync_version(dynamo, table, session_key, "telegram").await;
                         }
                     }
                     resp
                 }
                 Err(e) => {
                     tracing::error!("LLM error for Telegram: {}", e);
-                    "Sorry, an error occurred. Please try again.".to_string()
+                    llm_error_reply(&e, text, Locale::En)
                 }
             }
         }
         None => "AI provider not configured.".to_string(),
-    };
+    }
+}
 
-    // Parse reply tags
-    let (clean_reply, reply_tag) = super::tags::parse_reply_tag(&reply);
-    let reply_to_message_id = if reply_tag == Some("current".to_string()) {
-        Some(message.message_id.to_string())
-    } else {
-        None
+/// Handle a reaction on a bot message in Telegram: 👍/👎 are stored as
+/// feedback, 🔁 regenerates the last reply.
+async fn handle_telegram_reaction(state: &AppState, reaction: &TelegramMessageReaction) {
+    let (Some(user), Some(emoji)) = (reaction.user.as_ref(), reaction.emoji()) else {
+        return;
     };
-
-    // Send message with optional reply
-    let client = reqwest::Client::new();
-    let url = format!("https://api.telegram.org/bot{}/sendMessage", token);
-    let mut payload = serde_json::json!({
-        "chat_id": chat_id,
-        "text": clean_reply,
-    });
-
-    if let Some(msg_id) = reply_to_message_id {
-        payload["reply_parameters"] = serde_json::json!({
-            "message_id": msg_id.parse::<i64>().unwrap_or(0)
-        });
+    let Some(action) = reaction_action(emoji) else {
+        return;
+    };
+    let sender_id = TelegramChannel::sender_id(user);
+    if !is_allowed(&sender_id, &state.config.channels.telegram.allow_from) {
+        return;
     }
 
-    if let Err(e) = client.post(&url).json(&payload).send().await {
-        tracing::error!("Failed to send Telegram reply: {}", e);
-    }
+    let channel_key = format!("tg:{}", sender_id);
+    let session_key = {
+        #[cfg(feature = "dynamodb-backend")]
+        {
+            if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+                resolve_session_key(dynamo, table, &channel_key).await
+            } else {
+                channel_key.clone()
+            }
+        }
+        #[cfg(not(feature = "dynamodb-backend"))]
+        {
+            channel_key.clone()
+        }
+    };
 
-    StatusCode::OK
+    match action {
+        ReactionAction::Rate(rating) => {
+            let reply = {
+                let mut sessions = state.sessions.lock().await;
+                sessions.refresh(&session_key).last_assistant_message().map(str::to_string)
+            };
+            if let Some(reply) = reply {
+                info!("Telegram {} reaction recorded for {}", rating.as_str(), session_key);
+                save_feedback(state, FeedbackRecord::from_reaction(rating, "telegram", &session_key, &sender_id, &reply));
+            }
+        }
+        ReactionAction::Regenerate => {
+            let (last, incognito, locale) = {
+                let mut sessions = state.sessions.lock().await;
+                let session = sessions.refresh(&session_key);
+                let (incognito, locale) = (session.incognito(), session.locale().unwrap_or_default());
+                let last = session.pop_last_turn();
+                if last.is_some() {
+                    sessions.save_by_key(&session_key);
+                }
+                (last, incognito, locale)
+            };
+            let Some(text) = last else {
+                return;
+            };
+            let reply = telegram_chat_reply(state, &session_key, &text, incognito).await;
+            let (clean_reply, _) = super::tags::parse_reply_tag(&reply);
+            let clean_reply = if incognito { crate::session::incognito::label(&clean_reply, locale) } else { clean_reply };
+            let client = client_for(ClientClass::General);
+            let chat_id = reaction.chat.id.to_string();
+            let token = &state.config.channels.telegram.token;
+            if let Err(e) = TelegramChannel::send_message_static(&client, token, &chat_id, &clean_reply).await {
+                tracing::error!("Failed to send regenerated Telegram reply: {}", e);
+            }
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -6814,7 +8353,7 @@ async fn handle_facebook_webhook(
                 let provider = match state.get_provider() {
                     Some(p) => p.clone(),
                     None => {
-                        let client = reqwest::Client::new();
+                        let client = client_for(ClientClass::General);
                         let _ = FacebookChannel::send_message_static(&client, &page_token, sender_id, "AI provider not configured.").await;
                         continue;
                     }
@@ -6871,11 +8410,11 @@ async fn handle_facebook_webhook(
                     }
                     Err(e) => {
                         tracing::error!("LLM error for Facebook: {}", e);
-                        "Sorry, an error occurred. Please try again.".to_string()
+                        llm_error_reply(&e, text, Locale::En)
                     }
                 };
 
-                let client = reqwest::Client::new();
+                let client = client_for(ClientClass::General);
                 if let Err(e) = FacebookChannel::send_message_static(&client, &page_token, sender_id, &reply).await {
                     tracing::error!("Failed to send Facebook reply: {}", e);
                 }
@@ -6987,7 +8526,7 @@ async fn handle_teams_webhook(
         }
         Err(e) => {
             tracing::error!("LLM error for Teams: {}", e);
-            "申し訳ありません。エラーが発生しました。".to_string()
+            llm_error_reply(&e, &text, Locale::Ja)
         }
     };
 
@@ -6997,7 +8536,7 @@ async fn handle_teams_webhook(
     let app_password = std::env::var("TEAMS_APP_PASSWORD").unwrap_or_default();
 
     if !app_id.is_empty() && !app_password.is_empty() {
-        let client = reqwest::Client::new();
+        let client = client_for(ClientClass::General);
         if let Ok(token_resp) = client.post("https://login.microsoftonline.com/botframework.com/oauth2/v2.0/token")
             .form(&[
                 ("grant_type", "client_credentials"),
@@ -7119,7 +8658,7 @@ async fn handle_google_chat_webhook(
         }
         Err(e) => {
             tracing::error!("LLM error for Google Chat: {}", e);
-            "申し訳ありません。エラーが発生しました。".to_string()
+            llm_error_reply(&e, &text, Locale::Ja)
         }
     };
 
@@ -7234,7 +8773,7 @@ async fn handle_zalo_webhook(
         }
     };
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let _ = client.post("https://openapi.zalo.me/v3.0/oa/message/cs")
         .header("access_token", &zalo_token)
         .json(&serde_json::json!({
@@ -7368,11 +8907,11 @@ async fn handle_feishu_webhook(
         }
         Err(e) => {
             tracing::error!("LLM error for Feishu: {}", e);
-            "申し訳ありません。エラーが発生しました。".to_string()
+            llm_error_reply(&e, &text, Locale::Ja)
         }
     };
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     if let Ok(token_resp) = client.post("https://open.feishu.cn/open-apis/auth/v3/tenant_access_token/internal")
         .json(&serde_json::json!({ "app_id": app_id, "app_secret": app_secret }))
         .send().await
@@ -7447,6 +8986,19 @@ async fn handle_whatsapp_webhook(
         None => return StatusCode::OK,
     };
 
+    // Delivery/read receipts for messages we sent
+    if let Some(statuses) = value.get("statuses").and_then(|v| v.as_array()) {
+        let mut deliveries = state.deliveries.lock().await;
+        for receipt in statuses {
+            let wamid = receipt.get("id").and_then(|v| v.as_str()).unwrap_or("");
+            let status = receipt.get("status").and_then(|v| v.as_str()).and_then(crate::delivery::DeliveryStatus::parse);
+            let error = receipt.pointer("/errors/0/title").and_then(|v| v.as_str());
+            if let Some(status) = status {
+                deliveries.mark_receipt("whatsapp", wamid, status, error);
+            }
+        }
+    }
+
     let wa_messages = match value.get("messages").and_then(|v| v.as_array()) {
         Some(m) if !m.is_empty() => m,
         _ => return StatusCode::OK,
@@ -7536,12 +9088,15 @@ async fn handle_whatsapp_webhook(
         }
         Err(e) => {
             tracing::error!("LLM error for WhatsApp: {}", e);
-            "Sorry, an error occurred.".to_string()
+            llm_error_reply(&e, &text, Locale::En)
         }
     };
 
-    let client = reqwest::Client::new();
-    let _ = client.post(format!("https://graph.facebook.com/v21.0/{}/messages", phone_number_id))
+    let mut outbound = crate::types::OutboundMessage::new("whatsapp", &sender_phone, &reply);
+    let delivery_id = state.deliveries.lock().await.enqueue(&mut outbound, Some("reply"));
+
+    let client = client_for(ClientClass::General);
+    let sent = client.post(format!("https://graph.facebook.com/v21.0/{}/messages", phone_number_id))
         .header("Authorization", format!("Bearer {}", wa_token))
         .json(&serde_json::json!({
             "messaging_product": "whatsapp",
@@ -7549,7 +9104,25 @@ async fn handle_whatsapp_webhook(
             "type": "text",
             "text": { "body": reply },
         }))
-        .send().await;
+        .send().await
+        .and_then(|r| r.error_for_status());
+    let sent = match sent {
+        Ok(r) => r.json::<serde_json::Value>().await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    {
+        let mut deliveries = state.deliveries.lock().await;
+        match sent {
+            Ok(body) => {
+                let wamid = body.pointer("/messages/0/id").and_then(|v| v.as_str()).map(str::to_string);
+                deliveries.mark_sent(&delivery_id, wamid);
+            }
+            Err(e) => {
+                tracing::error!("WhatsApp send failed: {}", e);
+                deliveries.mark_failed(&delivery_id, &e);
+            }
+        }
+    }
 
     StatusCode::OK
 }
@@ -7582,6 +9155,30 @@ async fn handle_chat_stream(
         return Sse::new(err_stream).into_response();
     }
 
+    // `/incognito <message>`: answer just this message without saving it
+    let mut req = req;
+    let incognito_once = match crate::session::incognito::one_off(&req.message).map(str::to_string) {
+        Some(text) => {
+            req.message = text;
+            true
+        }
+        None => false,
+    };
+
+    // Reconnect after a dropped connection: the rest of the same answer
+    let last_event_id = headers.get("last-event-id").and_then(|v| v.to_str().ok()).and_then(parse_event_id);
+    if let Some((stream_id, after)) = last_event_id {
+        return buffered_sse(&state.stream_buffers, stream_id, &req.session_id, after).unwrap_or_else(|| {
+            let events = vec![
+                Ok::<_, Infallible>(Event::default().data(
+                    serde_json::json!({"type":"error","content":"This response can no longer be resumed","code":ErrorCode::InvalidRequest}).to_string()
+                )),
+                Ok::<_, Infallible>(Event::default().data(serde_json::json!({"type":"done"}).to_string())),
+            ];
+            Sse::new(stream::iter(events)).into_response()
+        });
+    }
+
     // Input validation: empty message
     if req.message.trim().is_empty() {
         let err_stream = stream::once(async {
@@ -7679,16 +9276,157 @@ async fn handle_chat_stream(
         }
     };
 
-    // Parallel initialization: fetch user (cached) + settings + skills + webhook tools concurrently
-    #[cfg(feature = "dynamodb-backend")]
-    let (stream_user, stream_memory, stream_settings, stream_skills, stream_webhook_tools) = {
-        if let Some(ref db) = state.db {
-            // libSQL path
-            let user = get_or_create_user_cached(&*state, &session_key).await;
-            let memory = read_memory_context_db(db, &session_key).await;
-            let skills = load_user_skills_for_prompt_db(db, &session_key).await;
-            let webhook_tools = load_user_webhook_tools_db(db, &session_key).await;
-            (Some(user), memory, None::<UserSettings>, skills, webhook_tools)
+    // Session-level slash commands (/reset, /model, /agent, /pin, ...) reply without the LLM
+    if let Some(super::commands::SlashCommand::Builtin(inv)) = super::commands::parse_command(&req.message) {
+        let agents = agent_ids();
+        let env = crate::command::CommandEnv {
+            channel: super::commands::channel_id(&req.session_id),
+            model: &state.config.agents.defaults.model,
+            agent: None,
+            agents: &agents,
+        };
+        let reply = {
+            let mut sessions = state.sessions.lock().await;
+            let reply = inv.execute(sessions.get_or_create(&session_key), &env);
+            sessions.save_by_key(&session_key);
+            reply
+        };
+        if let Some(reply) = reply {
+            let events = vec![
+                Ok::<_, Infallible>(Event::default().data(
+                    serde_json::json!({"type":"content","content": reply}).to_string()
+                )),
+                Ok::<_, Infallible>(Event::default().data(
+                    serde_json::json!({"type":"done"}).to_string()
+                )),
+            ];
+            return Sse::new(stream::iter(events)).into_response();
+        }
+    }
+
+    // Incognito turns are not saved to the session, memory or logs
+    let incognito = incognito_once || state.sessions.lock().await.get_or_create(&session_key).incognito();
+
+    // Per-user concurrency: over the plan's limit the client gets `queued`
+    // events with its position until a slot frees up
+    #[cfg(feature = "dynamodb-backend")]
+    let plan = if state.db.is_some() || state.dynamo_client.is_some() {
+        Some(get_or_create_user_cached(&state, &session_key).await.plan)
+    } else {
+        None
+    };
+    #[cfg(not(feature = "dynamodb-backend"))]
+    let plan: Option<String> = None;
+    let limit = state.chat_queue.limit_for(plan.as_deref());
+    match state.chat_queue.enqueue(&session_key, &req.channel, limit) {
+        Ticket::Ready(permit) => chat_stream_turn(state, headers, req, session_key, incognito, stream_start, permit).await,
+        Ticket::Queued(queued) => queued_chat_stream(state, headers, req, session_key, incognito, stream_start, queued, limit),
+        Ticket::Full => {
+            let err_stream = stream::once(async move {
+                Ok::<_, Infallible>(Event::default().data(
+                    serde_json::json!({"type":"error","content": concurrency_limit_message(limit), "code": ErrorCode::RateLimited}).to_string()
+                ))
+            });
+            Sse::new(err_stream).into_response()
+        }
+    }
+}
+
+/// Reply when a user's concurrent request limit is reached and the queue is
+/// full or timed out.
+fn concurrency_limit_message(limit: u32) -> String {
+    format!(
+        "同時リクエスト数が上限（{}）に達しました。しばらくお待ちください。\nConcurrent request limit ({}) reached. Please wait.",
+        limit, limit
+    )
+}
+
+/// SSE for a request waiting in the user's queue: `queued` events with the
+/// current position, then the turn itself once it gets a slot.
+#[allow(clippy::too_many_arguments)]
+fn queued_chat_stream(
+    state: Arc<AppState>,
+    headers: axum::http::HeaderMap,
+    req: ChatRequest,
+    session_key: String,
+    incognito: bool,
+    stream_start: std::time::Instant,
+    queued: Queued,
+    limit: u32,
+) -> axum::response::Response {
+    use futures::StreamExt;
+
+    fn frame(data: serde_json::Value) -> Result<axum::body::Bytes, std::convert::Infallible> {
+        Ok(axum::body::Bytes::from(format!("data: {}\n\n", data)))
+    }
+
+    let (tx, rx) = futures::channel::mpsc::unbounded();
+    let _ = tx.unbounded_send(frame(serde_json::json!({"type":"queued","position": queued.position()})));
+    tokio::spawn(async move {
+        let timeout = state.chat_queue.queue_timeout();
+        let progress = tx.clone();
+        let permit = queued
+            .wait(timeout, |position| {
+                let _ = progress.unbounded_send(frame(serde_json::json!({"type":"queued","position": position})));
+            })
+            .await;
+        let Some(permit) = permit else {
+            let _ = tx.unbounded_send(frame(serde_json::json!({
+                "type": "error",
+                "content": concurrency_limit_message(limit),
+                "code": ErrorCode::RateLimited,
+            })));
+            let _ = tx.unbounded_send(frame(serde_json::json!({"type":"done"})));
+            return;
+        };
+        // The client left while waiting: give the slot to the next request
+        if tx.is_closed() {
+            return;
+        }
+        let response = chat_stream_turn(state, headers, req, session_key, incognito, stream_start, permit).await;
+        let mut body = response.into_body().into_data_stream();
+        while let Some(Ok(chunk)) = body.next().await {
+            if tx.unbounded_send(Ok(chunk)).is_err() {
+                break;
+            }
+        }
+    });
+
+    (
+        [
+            (http::header::CONTENT_TYPE, "text/event-stream"),
+            (http::header::CACHE_CONTROL, "no-cache"),
+        ],
+        axum::body::Body::from_stream(rx),
+    )
+        .into_response()
+}
+
+/// The model turn of [`handle_chat_stream`], run while holding one of the
+/// user's concurrency slots; `permit` is released when the stream ends.
+async fn chat_stream_turn(
+    state: Arc<AppState>,
+    headers: axum::http::HeaderMap,
+    req: ChatRequest,
+    session_key: String,
+    incognito: bool,
+    stream_start: std::time::Instant,
+    permit: Permit,
+) -> axum::response::Response {
+    use axum::response::sse::{Event, Sse};
+    use futures::stream;
+    use std::convert::Infallible;
+
+    // Parallel initialization: fetch user (cached) + settings + skills + webhook tools concurrently
+    #[cfg(feature = "dynamodb-backend")]
+    let (stream_user, stream_memory, stream_settings, stream_skills, stream_webhook_tools) = {
+        if let Some(ref db) = state.db {
+            // libSQL path
+            let user = get_or_create_user_cached(&*state, &session_key).await;
+            let memory = read_memory_context_db(db, &session_key).await;
+            let skills = load_user_skills_for_prompt_db(db, &session_key).await;
+            let webhook_tools = load_user_webhook_tools_db(db, &session_key).await;
+            (Some(user), memory, None::<UserSettings>, skills, webhook_tools)
         } else if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
             // Fetch user with cache (separate from tokio::join! to use state)
             let user = get_or_create_user_cached(&*state, &session_key).await;
@@ -7790,17 +9528,34 @@ async fn handle_chat_stream(
 
     // Resolve inference mode for streaming endpoint (same logic as handle_chat)
     let stream_host = effective_host(&headers);
-    let is_wisbee_stream = stream_host.contains("wisbee.ai");
-    let stream_mode = match req.mode.as_deref() {
-        Some("local") => "local",
-        Some("cloud") => "cloud",
-        Some("auto") => "auto",
-        None | Some(_) => if is_wisbee_stream { "local" } else { "auto" },
+    let stream_mode = req.resolve_mode(stream_host);
+    let unsupported = match stream_mode {
+        ChatMode::Race | ChatMode::Consensus => Some(format!("Mode '{}' is only available on /api/v1/chat", stream_mode)),
+        _ => validate_chat_options(&req.options).err(),
     };
+    if let Some(content) = unsupported {
+        let err_stream = stream::once(async move {
+            Ok::<_, Infallible>(Event::default().data(
+                serde_json::json!({"type": "error", "content": content}).to_string()
+            ))
+        });
+        return Sse::new(err_stream).into_response();
+    }
+    // Explore streams each model's answer as it arrives
+    if stream_mode == ChatMode::Explore {
+        let explore = ExploreRequest {
+            message: req.message,
+            session_id: req.session_id,
+            level: None,
+            previous_chunk: None,
+            auto_synthesize: false,
+        };
+        return handle_chat_explore(State(state), Json(explore)).await.into_response();
+    }
 
     // Local mode: run inference locally and return as SSE
     #[cfg(feature = "local-fallback")]
-    if stream_mode == "local" {
+    if stream_mode == ChatMode::Local {
         use crate::provider::local::LocalProvider;
         let result = match LocalProvider::from_env() {
             Some(local_provider) => {
@@ -7832,7 +9587,7 @@ async fn handle_chat_stream(
         return Sse::new(event_stream).into_response();
     }
     #[cfg(not(feature = "local-fallback"))]
-    if stream_mode == "local" {
+    if stream_mode == ChatMode::Local {
         let event_stream = stream::once(async {
             Ok::<_, Infallible>(Event::default().data(
                 serde_json::json!({"type": "error", "content": "Local mode not available in this build", "mode": "local"}).to_string()
@@ -7853,8 +9608,25 @@ async fn handle_chat_stream(
         }
     };
 
+    // Session-scoped overrides set via /model and /agent, and the response locale
+    // (auto-detected from this message unless fixed with /lang)
+    let (session_model, session_agent, locale) = {
+        let mut sessions = state.sessions.lock().await;
+        let session = sessions.get_or_create(&session_key);
+        let default_locale = if stream_host.contains("teai.io") { Locale::En } else { Locale::Ja };
+        let previous = session.locale();
+        let locale = session.observe_locale(&req.message, default_locale);
+        let overrides = (session.model_override().map(str::to_string), session.agent_override().map(str::to_string), locale);
+        if session.locale() != previous {
+            sessions.save_by_key(&session_key);
+        }
+        overrides
+    };
+    let is_english = locale.is_english();
+
     // Agent detection (same as handle_chat)
-    let (agent, clean_message, agent_score) = detect_agent(&req.message);
+    let (agent, clean_message, agent_score) = detect_agent_with_override(&req.message, req.options.agent.as_deref().or(session_agent.as_deref()));
+    let (agent, route) = learned_route(&state, agent, agent_score, &clean_message, &req.channel, req.device.as_deref().unwrap_or("pc"));
     info!("Stream agent: {} (score={}) for message", agent.id, agent_score);
 
     // Build messages — agent-specific + host-aware system prompt + memory + meta context
@@ -7881,23 +9653,28 @@ async fn handle_chat_stream(
         agent.system_prompt.to_string()
     };
 
-    // Device-based character limit
+    // Device/channel-based length and tone (config + session /length, /tone)
     let device = req.device.as_deref().unwrap_or("pc");
-    let max_chars = match device {
-        "mobile" => agent.max_chars_mobile,
-        "voice" => agent.max_chars_voice,
-        _ => agent.max_chars_pc,
-    };
-    let char_instruction = format!(
-        "\n\n【応答制約】回答は{}文字以内に収めてください。簡潔に要点を伝えてください。",
-        max_chars
-    );
 
     // Get session history first (need history_len for meta context)
     let stream_history: Vec<(String, String)>;
+    let stream_pins: String;
+    let stream_contacts: String;
+    let response_style: ResponseStyle;
+    let session_tz: Option<String>;
     {
         let mut sessions = state.sessions.lock().await;
         let session = sessions.refresh(&session_key);
+        stream_pins = session.pins_context(pin_budget(&state.config));
+        stream_contacts = session.contacts_context(&req.message);
+        session_tz = session.timezone().map(str::to_string);
+        response_style = ResponseStyle::resolve(
+            &state.config.agents.response,
+            super::commands::channel_id(&session_key),
+            device,
+            agent.max_chars_for(device),
+            Some(session),
+        );
         let history = session.get_history_with_summary(4);
         stream_history = history.iter().filter_map(|msg| {
             let role = msg.get("role").and_then(|v| v.as_str())?;
@@ -7910,7 +9687,11 @@ async fn handle_chat_stream(
     let user_settings: Option<UserSettings> = stream_settings;
 
     let default_model = state.config.agents.defaults.model.clone();
-    let model = req.model.as_deref()
+    let tier_model: Option<String> = req.options.tier
+        .and_then(|tier| state.get_lb_raw().and_then(|lb| lb.get_tier_model(tier.as_str())).map(|(_, m)| m));
+    let model = session_model.as_deref()
+        .or(req.model.as_deref())
+        .or(tier_model.as_deref())
         .or(user_settings.as_ref().and_then(|s| s.preferred_model.as_deref()))
         .or(agent.preferred_model)
         .unwrap_or_else(|| {
@@ -7923,12 +9704,13 @@ async fn handle_chat_stream(
         &req.channel,
         device,
         stream_history.len(),
-        is_teai,
+        is_english,
+        session_tz.as_deref(),
         Some(&model),
         0,
         0,
     );
-    let stream_meta_instr = if is_teai { META_INSTRUCTION_EN } else { META_INSTRUCTION_JA };
+    let stream_meta_instr = if is_english { META_INSTRUCTION_EN } else { META_INSTRUCTION_JA };
 
     // Adult mode prompt injection (only if age verified and mode enabled)
     let stream_is_adult = user_settings.as_ref()
@@ -7997,11 +9779,26 @@ async fn handle_chat_stream(
     } else {
         ""
     };
-    let stream_system_prompt = if stream_memory.is_empty() {
-        format!("{}\n\n今日の日付: {}{}{}{}{}{}{}{}{}", base_prompt, today, stream_meta, stream_meta_instr, stream_adult_prompt, stream_wow_prompt, stream_custom_block, &stream_skills, admin_improve_block, char_instruction)
+    let stream_memory_block = if stream_memory.is_empty() {
+        String::new()
     } else {
-        format!("{}\n\n今日の日付: {}{}{}{}{}{}{}\n\n---\n{}{}{}", base_prompt, today, stream_meta, stream_meta_instr, stream_adult_prompt, stream_wow_prompt, stream_custom_block, &stream_skills, stream_memory, admin_improve_block, char_instruction)
+        format!("---\n{}", stream_memory)
     };
+    let stream_system_prompt = PromptCompiler::new(&state.config.agents.prompt)
+        .required("agent", base_prompt)
+        .section("meta", format!("今日の日付: {}{}", today, stream_meta))
+        .section("meta_instruction", stream_meta_instr)
+        .section("adult", stream_adult_prompt)
+        .section("wow", stream_wow_prompt)
+        .required("custom", stream_custom_block)
+        .required("pins", stream_pins)
+        .section("contacts", stream_contacts)
+        .section("skills", stream_skills.as_str())
+        .section("memory", stream_memory_block)
+        .required("admin", admin_improve_block)
+        .required("length", response_style.instruction(locale))
+        .compile()
+        .text;
 
     let mut messages = vec![Message::system(&stream_system_prompt)];
 
@@ -8027,6 +9824,16 @@ async fn handle_chat_stream(
         messages.push(Message::user(&clean_message));
     }
     let max_tokens = req.max_tokens.unwrap_or(state.config.agents.defaults.max_tokens);
+    let max_tokens = cost_capped_max_tokens(&state, &req.options, stream_mode, &model, &messages, max_tokens);
+    if max_tokens == 0 {
+        let content = over_budget_message(locale);
+        let err_stream = stream::once(async move {
+            Ok::<_, Infallible>(Event::default().data(
+                serde_json::json!({"type": "error", "content": content}).to_string()
+            ))
+        });
+        return Sse::new(err_stream).into_response();
+    }
     let temperature = req.temperature
         .or(user_settings.as_ref().and_then(|s| s.temperature))
         .unwrap_or(state.config.agents.defaults.temperature);
@@ -8044,12 +9851,16 @@ async fn handle_chat_stream(
     let req_channel = req.channel.clone();
     let req_device = device.to_string();
     let req_session_id = req.session_id.clone();
+    let stream_owner = req.session_id.clone();
+    let stream_buffers = state.stream_buffers.clone();
+    let (stream_id, writer) = stream_buffers.create(&req.session_id);
     let req_language = req.language.clone();
     let state_clone = state.clone();
     let session_key_clone = session_key.clone();
     let agent_id = agent.id;
     let agent_estimated_seconds = agent.estimated_seconds;
     let stream_agent_score = agent_score;
+    let stream_route = route;
     let stream_user_plan = stream_user.as_ref().map(|u| u.plan.clone()).unwrap_or_else(|| "unknown".to_string());
     let stream_user_id = stream_user.as_ref().map(|u| u.user_id.clone());
 
@@ -8074,7 +9885,7 @@ async fn handle_chat_stream(
     if agent.id == "multi_agent" {
         stream_relevant_tools.insert("multi_agent");
     }
-    let tools: Vec<serde_json::Value> = if agent.tools_enabled {
+    let tools: Vec<serde_json::Value> = if agent.tools_enabled && req.options.tools_enabled() {
         let all_defs = state.tool_registry.get_definitions();
         let mut defs: Vec<serde_json::Value> = all_defs.into_iter()
             .filter(|t| {
@@ -8132,16 +9943,20 @@ async fn handle_chat_stream(
     // and admin tool-augmented prompts are larger; API Gateway v2 limit is 30s.
     let stream_deadline_secs: u64 = if stream_user_is_admin { 25 } else { RESPONSE_DEADLINE_SECS };
 
-    // Real-time SSE: send each event individually as it happens via mpsc channel
-    let (tx, rx) = futures::channel::mpsc::unbounded::<Result<Event, Infallible>>();
+    // Speculative draft from a fast model on channels/devices that enable it
+    let speculative = crate::agent::speculative::SpeculativePolicy::from_config(&state.config.agents.response)
+        .filter(|policy| policy.applies(super::commands::channel_id(&session_key), Some(device)));
+
+    // Real-time SSE: send each event individually as it happens via mpsc channel;
+    // events go through a resumable buffer (see `stream_resume`)
+    let (tx, mut rx) = futures::channel::mpsc::unbounded::<String>();
 
     tokio::spawn(async move {
+        let _permit = permit;
         // Helper: send a single SSE event immediately
         macro_rules! send_sse {
             ($data:expr) => {
-                let _ = tx.unbounded_send(Ok(Event::default().data(
-                    serde_json::to_string(&$data).unwrap_or_default()
-                )));
+                let _ = tx.unbounded_send(serde_json::to_string(&$data).unwrap_or_default());
             };
         }
         let mut event_count: usize = 0;
@@ -8157,15 +9972,38 @@ async fn handle_chat_stream(
 
         let tools_ref = if tools.is_empty() { None } else { Some(&tools[..]) };
 
+        // Draft event from the fast model, unless real content gets there first
+        let draft = speculative.as_ref().map(|policy| {
+            let tx_for_draft = tx.clone();
+            let draft_messages = messages.clone();
+            let (hooks, channel, key) = (state_clone.response_hooks.clone(), req_channel.clone(), session_key_clone.clone());
+            crate::agent::speculative::Draft::spawn(provider.clone(), policy.draft_model(), draft_messages, move |text| async move {
+                let hook_ctx = crate::hooks::HookContext { channel: &channel, agent: Some(agent_id), session_key: &key };
+                let text = hooks.apply(text, &hook_ctx).await;
+                tx_for_draft.unbounded_send(serde_json::json!({"type":"draft","text":text}).to_string()).is_ok()
+            })
+        });
+        let draft = draft.map(std::sync::Arc::new);
+
         // LLM call with hard deadline — using streaming to send content_chunk events in real-time
         let deadline = std::time::Duration::from_secs(stream_deadline_secs);
         let (chunk_tx, mut chunk_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
         let tx_for_chunks = tx.clone();
+        let draft_for_chunks = draft.clone();
         let chunk_forwarder = tokio::spawn(async move {
+            // With a draft on screen, chunks are held back; the final content settles it
+            let mut hold_back = None;
             while let Some(chunk) = chunk_rx.recv().await {
-                let _ = tx_for_chunks.unbounded_send(Ok(Event::default().data(
-                    serde_json::json!({"type":"content_chunk","text":chunk}).to_string()
-                )));
+                if hold_back.is_none() {
+                    hold_back = Some(match draft_for_chunks.as_ref() {
+                        Some(draft) => draft.preempt().await,
+                        None => false,
+                    });
+                }
+                if hold_back == Some(true) {
+                    continue;
+                }
+                let _ = tx_for_chunks.unbounded_send(serde_json::json!({"type":"content_chunk","text":chunk}).to_string());
             }
         });
         let llm_result = tokio::time::timeout(
@@ -8174,14 +10012,14 @@ async fn handle_chat_stream(
         ).await;
         let _ = chunk_forwarder.await;
         let (stream_used_model, first_result) = match llm_result {
-            Ok(Ok(c)) => (model.clone(), Ok(c)),
+            Ok(Ok(c)) => (served_model(&model, &c), Ok(c)),
             Ok(Err(e)) => {
                 tracing::error!("Stream LLM call failed: {}", e);
                 (model.clone(), Err(e))
             }
             Err(_) => {
                 tracing::warn!("Stream LLM call timed out after {}s, returning fallback", stream_deadline_secs);
-                let fallback = timeout_fallback_message();
+                let fallback = timeout_fallback_message(locale);
                 // Send content before done so the client renders the message correctly
                 send_sse!(serde_json::json!({"type":"content","content": fallback}));
                 #[cfg(feature = "dynamodb-backend")]
@@ -8204,6 +10042,8 @@ async fn handle_chat_stream(
         let mut stream_had_error = false;
         match first_result {
             Ok(completion) => {
+                // Exact name of the model that answered, for the client
+                let answered_by = completion.model_or(&model).to_string();
                 #[allow(unused_mut)]
                 let mut total_credits_used: i64 = 0;
                 #[allow(unused_mut)]
@@ -8225,6 +10065,7 @@ async fn handle_chat_stream(
                 let mut current = completion;
                 let mut conversation = messages.clone();
                 let mut all_tools_used: Vec<String> = Vec::new();
+                let mut sources = crate::citation::Sources::new();
                 let mut iteration: usize = 0;
 
                 // Create sandbox directory
@@ -8296,8 +10137,8 @@ async fn handle_chat_stream(
                         if name == "phone_call" || name == "web_deploy" {
                             args.insert("_session_key".to_string(), serde_json::Value::String(session_key_clone.clone()));
                         }
-                        // Inject user_id for browser tools that need vault access
-                        if name.starts_with("browser_") {
+                        // Inject user_id for browser tools (vault access) and the expense ledger
+                        if name.starts_with("browser_") || name == "expense" {
                             args.insert("_user_id".to_string(), serde_json::Value::String(session_key_clone.clone()));
                         }
                         async move {
@@ -8326,11 +10167,26 @@ async fn handle_chat_stream(
                             (id, name, result, duration_ms)
                         }
                     }).collect();
-                    let tool_results: Vec<_> = futures::future::join_all(futures_vec).await;
+                    // Number fetched pages so the answer can cite them
+                    let tool_results: Vec<_> = futures::future::join_all(futures_vec).await
+                        .into_iter()
+                        .zip(&tool_calls_to_run)
+                        .map(|((id, name, result, duration_ms), tc)| {
+                            let result = sources.track(&name, &tc.arguments, result);
+                            (id, name, result, duration_ms)
+                        })
+                        .collect();
 
                     // Emit tool_result events (sent immediately)
                     for (_, name, result, duration_ms) in &tool_results {
                         all_tools_used.push(name.clone());
+                        state_clone.tool_stats.record(
+                            &session_key_clone,
+                            name,
+                            std::time::Duration::from_millis(*duration_ms),
+                            result,
+                            &stream_used_model,
+                        );
                         let preview_end = result.char_indices().nth(500).map(|(i, _)| i).unwrap_or(result.len());
                         let is_error = result.starts_with("[TOOL_ERROR]");
                         let is_no_results = result.starts_with("[NO_RESULTS]");
@@ -8433,9 +10289,7 @@ async fn handle_chat_stream(
                     let tx_for_fu = tx.clone();
                     let fu_forwarder = tokio::spawn(async move {
                         while let Some(chunk) = fu_chunk_rx.recv().await {
-                            let _ = tx_for_fu.unbounded_send(Ok(Event::default().data(
-                                serde_json::json!({"type":"content_chunk","text":chunk}).to_string()
-                            )));
+                            let _ = tx_for_fu.unbounded_send(serde_json::json!({"type":"content_chunk","text":chunk}).to_string());
                         }
                     });
 
@@ -8474,6 +10328,7 @@ async fn handle_chat_stream(
                                 tool_calls: vec![],
                                 finish_reason: crate::types::FinishReason::Stop,
                                 usage: crate::types::TokenUsage::default(),
+                                model: None,
                             };
                             let _ = fu_forwarder.await;
                             break;
@@ -8486,6 +10341,7 @@ async fn handle_chat_stream(
                                 tool_calls: vec![],
                                 finish_reason: crate::types::FinishReason::Stop,
                                 usage: crate::types::TokenUsage::default(),
+                                model: None,
                             };
                             let _ = fu_forwarder.await;
                             break;
@@ -8509,17 +10365,32 @@ async fn handle_chat_stream(
                         response_text = translated;
                     }
                 }
+                // No length trim here: the user already saw every chunk, and the
+                // saved reply must match. Researcher answers always carry a
+                // source list when web tools ran
+                if agent_id == "researcher" {
+                    sources.require(MAX_RESEARCH_SOURCES);
+                }
+                response_text = sources.render(&response_text, locale);
 
                 // Save to session
-                {
+                if !incognito {
                     let mut sessions = state_clone.sessions.lock().await;
                     let session = sessions.get_or_create(&session_key_clone);
                     session.add_message("user", &req_message);
                     session.add_message("assistant", &response_text);
+                    session.set_turn_meta(&TurnMeta {
+                        model: Some(stream_used_model.clone()),
+                        prompt_tokens: stream_total_input,
+                        completion_tokens: stream_total_output,
+                        latency_ms: Some(stream_start.elapsed().as_millis() as u64),
+                        agent: Some(agent_id.to_string()),
+                        tools: all_tools_used.clone(),
+                    });
                     sessions.save_by_key(&session_key_clone);
                 }
                 #[cfg(feature = "dynamodb-backend")]
-                {
+                if !incognito {
                     // Fire-and-forget: awaiting directly would block the spawned task holding `tx`,
                     // preventing the SSE stream from terminating in lambda_http's body.collect().
                     if let (Some(dynamo), Some(table)) = (state_clone.dynamo_client.clone(), state_clone.config_table.clone()) {
@@ -8533,7 +10404,7 @@ async fn handle_chat_stream(
                 // Auto-update conversation title & message count
                 // Use auth user_id (stream_user_id) so /api/v1/conversations can find the record.
                 #[cfg(feature = "dynamodb-backend")]
-                {
+                if !incognito {
                     if let (Some(dynamo), Some(table)) = (&state_clone.dynamo_client, &state_clone.config_table) {
                         if let Some(conv_id) = req_session_id.strip_prefix("webchat:") {
                             let msg_count = {
@@ -8552,11 +10423,12 @@ async fn handle_chat_stream(
 
                 // Auto-save to daily memory log + trigger consolidation (fire-and-forget)
                 #[cfg(feature = "dynamodb-backend")]
-                {
+                if !incognito {
                     let sk = session_key_clone.clone();
                     let user_msg = req_message.clone();
                     let bot_msg = response_text.clone();
-                    let provider_for_mem = state_clone.get_lb_provider().or_else(|| state_clone.provider.clone());
+                    let consolidation = memory_consolidation(&state_clone.config, state_clone.get_lb_provider().or_else(|| state_clone.provider.clone()));
+                    let every = state_clone.config.agents.memory.every_entries;
                     let summary = format!("- Q: {} → A: {}",
                         if user_msg.len() > 80 { let mut i = 80; while i > 0 && !user_msg.is_char_boundary(i) { i -= 1; } format!("{}...", &user_msg[..i]) } else { user_msg },
                         if bot_msg.len() > 120 { let mut i = 120; while i > 0 && !bot_msg.is_char_boundary(i) { i -= 1; } format!("{}...", &bot_msg[..i]) } else { bot_msg },
@@ -8574,9 +10446,9 @@ async fn handle_chat_stream(
                         let table = table.clone();
                         tokio::spawn(async move {
                             let entry_count = append_daily_memory(&dynamo, &table, &sk, &summary).await;
-                            if entry_count > 0 && entry_count % 10 == 0 {
-                                if let Some(provider) = provider_for_mem {
-                                    spawn_consolidate_memory(dynamo, table, sk, provider);
+                            if every > 0 && entry_count > 0 && entry_count % every == 0 {
+                                if let Some(job) = consolidation {
+                                    spawn_consolidate_memory(dynamo, table, sk, job);
                                 }
                             }
                         });
@@ -8587,9 +10459,9 @@ async fn handle_chat_stream(
                         let table = table.clone();
                         tokio::spawn(async move {
                             let entry_count = append_daily_memory(&dynamo, &table, &sk, &summary).await;
-                            if entry_count > 0 && entry_count % 10 == 0 {
-                                if let Some(provider) = provider_for_mem {
-                                    spawn_consolidate_memory(dynamo, table, sk, provider);
+                            if every > 0 && entry_count > 0 && entry_count % every == 0 {
+                                if let Some(job) = consolidation {
+                                    spawn_consolidate_memory(dynamo, table, sk, job);
                                 }
                             }
                         });
@@ -8610,21 +10482,59 @@ async fn handle_chat_stream(
                 // Update response_text to clean version (without <think> tags and provider-specific XML)
                 let response_text = super::tags::strip_provider_tags(&clean_response_text);
 
-                // Content event (final answer — sent immediately)
+                // What the client shows, after the deployer's hooks
+                let hook_ctx = crate::hooks::HookContext { channel: &req_channel, agent: Some(agent_id), session_key: &session_key_clone };
+                let delivered = state_clone.response_hooks.apply(response_text.clone(), &hook_ctx).await;
+                let delivered = if incognito {
+                    crate::session::incognito::label(&delivered, locale)
+                } else {
+                    delivered
+                };
+
+                // Settle a draft the user saw: correct it or confirm it
+                let shown_draft = match draft.and_then(std::sync::Arc::into_inner) {
+                    Some(draft) => draft.finish().await,
+                    None => None,
+                };
+                if let (Some(shown), Some(policy)) = (shown_draft, speculative.as_ref()) {
+                    if policy.needs_correction(&shown, &response_text) {
+                        send_sse!(serde_json::json!({"type":"correction","text": delivered}));
+                    } else {
+                        send_sse!(serde_json::json!({"type":"draft_confirmed"}));
+                    }
+                    event_count += 1;
+                }
+
+                // Citation events, one per numbered source, for client-side footnotes
+                for citation in sources.citations() {
+                    send_sse!(serde_json::json!({
+                        "type": "citation",
+                        "index": citation.index,
+                        "url": citation.url,
+                        "title": citation.title,
+                        "tool": citation.tool,
+                    }));
+                    event_count += 1;
+                }
+
+                // Content event (final answer — sent immediately); it replaces
+                // the streamed chunks
                 let stream_cost = crate::provider::pricing::calculate_cost(&stream_used_model, stream_total_input, stream_total_output);
                 record_outage_end();
                 send_sse!(serde_json::json!({
                     "type": "content",
-                    "content": response_text,
+                    "content": delivered,
                     "agent": agent_id,
                     "credits_remaining": last_remaining,
                     "credits_used": if total_credits_used > 0 { Some(total_credits_used) } else { None::<i64> },
                     "tools_used": if all_tools_used.is_empty() { None } else { Some(&all_tools_used) },
                     "iterations": iteration,
                     "model_used": stream_used_model,
+                    "model": answered_by,
                     "input_tokens": stream_total_input,
                     "output_tokens": stream_total_output,
                     "estimated_cost_usd": if stream_cost > 0.0 { Some(stream_cost) } else { None::<f64> },
+                    "incognito": incognito,
                 }));
                 event_count += 1;
             }
@@ -8637,17 +10547,28 @@ async fn handle_chat_stream(
                     if let (Some(dynamo), Some(table), Some(uid)) =
                         (&state_clone.dynamo_client, &state_clone.config_table, &stream_user_id)
                     {
-                        add_credits_to_user(dynamo, table, uid, 1000, "", "").await;
+                        let balance = add_credits_to_user(dynamo, table, uid, 1000, "", "").await;
+                        record_credits(dynamo, table, uid, crate::credits::CreditKind::Grant, 1000, balance, "outage apology").await;
                         info!("Outage apology (stream): gave 1000 credits to user {} (outage ≥1h)", uid);
                     }
                 }
-                let fallback = error_fallback_message();
-                send_sse!(serde_json::json!({
-                    "type": "error",
-                    "content": fallback,
-                    "action": "retry_scheduled",
-                    "auto_retry_after": 300
-                }));
+                let code = e.code();
+                if code == ErrorCode::Unavailable {
+                    send_sse!(serde_json::json!({
+                        "type": "error",
+                        "content": error_fallback_message(),
+                        "code": code,
+                        "action": "retry_scheduled",
+                        "auto_retry_after": 300
+                    }));
+                } else {
+                    send_sse!(serde_json::json!({
+                        "type": "error",
+                        "content": code.user_message(locale),
+                        "code": code,
+                        "retryable": code.is_retryable(),
+                    }));
+                }
                 event_count += 1;
             }
         }
@@ -8657,7 +10578,7 @@ async fn handle_chat_stream(
         info!("Stream response: session={}, model={}, latency={}ms, events={}",
             session_key_clone, model, stream_latency, event_count);
         #[cfg(feature = "dynamodb-backend")]
-        {
+        if !incognito {
             if let (Some(dynamo), Some(table)) = (&state_clone.dynamo_client, &state_clone.config_table) {
                 emit_audit_log(dynamo.clone(), table.clone(), "chat_stream", &session_key_clone, "",
                     &format!("model={} latency={}ms events={}", model, stream_latency, event_count));
@@ -8670,7 +10591,7 @@ async fn handle_chat_stream(
                     session_key_clone.hash(&mut h);
                     format!("{:x}", h.finish())
                 };
-                log_routing_data(dynamo.clone(), table.clone(), RoutingLogEntry {
+                log_routing_data(dynamo.clone(), table.clone(), state_clone.config.agents.routing.log_retention_days, RoutingLogEntry {
                     message_len: req_message.len(),
                     language: detect_language(&req_message).to_string(),
                     channel: req_channel.clone(),
@@ -8687,6 +10608,10 @@ async fn handle_chat_stream(
                     completion_tokens: 0,
                     timed_out: false,
                     error: stream_had_error,
+                    route_features: stream_route.features.clone(),
+                    learned_agent: stream_route.prediction.as_ref().map(|p| p.agent.clone()),
+                    learned_confidence: stream_route.prediction.as_ref().map(|p| p.confidence),
+                    learned_override: stream_route.overridden,
                     timestamp: chrono::Utc::now().to_rfc3339(),
                     session_hash,
                 });
@@ -8698,10 +10623,34 @@ async fn handle_chat_stream(
         // tx is dropped here → stream closes naturally
     });
 
+    // The buffer keeps filling if the client goes away, so it can come back
+    tokio::spawn(async move {
+        use futures::StreamExt;
+        while let Some(data) = rx.next().await {
+            writer.push(data);
+        }
+    });
+
     // Note: .keep_alive() is intentionally omitted — Lambda buffers the entire SSE body
     // before returning it, so keep-alive pings accumulate as noise and provide no benefit.
     // The stream naturally terminates when the spawned task drops `tx`.
-    Sse::new(rx).into_response()
+    buffered_sse(&stream_buffers, &stream_id, &stream_owner, 0)
+        .unwrap_or_else(|| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
+
+/// SSE of a buffered chat stream from event `after` on; each event carries
+/// the id `<stream>:<n>` to resume from. `None` when the stream is unknown,
+/// expired or not `owner`'s.
+fn buffered_sse(buffers: &StreamBuffers, stream_id: &str, owner: &str, after: usize) -> Option<axum::response::Response> {
+    use axum::response::sse::{Event, Sse};
+    use futures::StreamExt;
+
+    let events = buffers.events(stream_id, owner, after)?;
+    let stream_id = stream_id.to_string();
+    let events = events.map(move |(n, data)| {
+        Ok::<_, std::convert::Infallible>(Event::default().id(format!("{}:{}", stream_id, n)).data(data))
+    });
+    Some(Sse::new(events).into_response())
 }
 
 // ============================================================================
@@ -8845,7 +10794,7 @@ async fn handle_chat_explore(
             let err_stream = futures::stream::once(async {
                 Ok::<_, Infallible>(Event::default()
                     .event("error")
-                    .data(serde_json::json!({"type":"error","content":"No providers available","error":"No providers available"}).to_string()))
+                    .data(serde_json::json!({"type":"error","content":"No providers available","error":"No providers available","code":ErrorCode::Unavailable}).to_string()))
             });
             return Sse::new(err_stream).into_response();
         }
@@ -9181,7 +11130,8 @@ async fn handle_explore_synthesize(
                     .data(serde_json::json!({
                         "type": "error",
                         "content": "No providers available",
-                        "error": "No providers available"
+                        "error": "No providers available",
+                        "code": ErrorCode::Unavailable
                     }).to_string()))
             });
             return Sse::new(err_stream).into_response();
@@ -9306,10 +11256,11 @@ async fn handle_first_chunk(
     let lb = match state.get_lb_provider() {
         Some(lb) => lb,
         None => {
-            return Json(serde_json::json!({
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({
                 "error": "No providers available",
+                "code": ErrorCode::Unavailable,
                 "first_chunk": "",
-            })).into_response();
+            }))).into_response();
         }
     };
 
@@ -9536,7 +11487,7 @@ async fn handle_chat_race(
             let err_stream = futures::stream::once(async {
                 Ok::<_, Infallible>(Event::default()
                     .event("error")
-                    .data(serde_json::json!({"type":"error","content":"No providers available","error":"No providers available"}).to_string()))
+                    .data(serde_json::json!({"type":"error","content":"No providers available","error":"No providers available","code":ErrorCode::Unavailable}).to_string()))
             });
             return Sse::new(err_stream).into_response();
         }
@@ -9916,7 +11867,7 @@ async fn handle_billing_checkout(
         ("metadata[session_key]", session_key),
     ];
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     match client.post("https://api.stripe.com/v1/checkout/sessions")
         .header("Authorization", format!("Bearer {}", stripe_key))
         .form(&params)
@@ -9991,7 +11942,7 @@ async fn handle_credit_pack_checkout(
         ("metadata[session_key]", session_key),
     ];
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     match client.post("https://api.stripe.com/v1/checkout/sessions")
         .header("Authorization", format!("Bearer {}", stripe_key))
         .form(&params)
@@ -10081,7 +12032,7 @@ async fn handle_billing_portal(
             let user_key = resolve_session_key(dynamo, table, &session_key).await;
             let user = get_or_create_user(dynamo, table, &user_key).await;
             if let Some(customer_id) = &user.stripe_customer_id {
-                let client = reqwest::Client::new();
+                let client = client_for(ClientClass::General);
                 let params = vec![
                     ("customer", customer_id.as_str()),
                     ("return_url", if effective_host(&headers).contains("teai.io") { "https://teai.io/" } else { "https://chatweb.ai/" }),
@@ -10250,7 +12201,8 @@ async fn handle_stripe_webhook(
                             .and_then(|s| s.parse().ok())
                             .unwrap_or(1000);
 
-                        add_credits_to_user(client, table, &user_id, credits_to_add, customer_id, customer_email).await;
+                        let balance = add_credits_to_user(client, table, &user_id, credits_to_add, customer_id, customer_email).await;
+                        record_credits(client, table, &user_id, crate::credits::CreditKind::Purchase, credits_to_add, balance, "credit pack").await;
                         info!("Credit pack: added {} credits to user {} (customer={})", credits_to_add, user_id, customer_id);
                     } else {
                         // Subscription — upgrade plan + set monthly credits
@@ -10276,7 +12228,8 @@ async fn handle_stripe_webhook(
                             info!("Subscription: upgraded user {} to plan {} (customer={})", user_id, plan_name, customer_id);
                         } else if mode == "payment" {
                             // One-time payment without specific metadata — treat as generic credit pack
-                            add_credits_to_user(client, table, &user_id, 1000, customer_id, customer_email).await;
+                            let balance = add_credits_to_user(client, table, &user_id, 1000, customer_id, customer_email).await;
+                            record_credits(client, table, &user_id, crate::credits::CreditKind::Purchase, 1000, balance, "payment").await;
                             info!("Generic payment: added 1000 credits to user {} (customer={})", user_id, customer_id);
                         } else {
                             // Default to starter
@@ -10318,7 +12271,11 @@ async fn handle_stripe_webhook(
                                     .send()
                                     .await
                                 {
-                                    Ok(_) => info!("Reset credits to {} for user {} (invoice.paid)", monthly_credits, user_id),
+                                    Ok(_) => {
+                                        info!("Reset credits to {} for user {} (invoice.paid)", monthly_credits, user_id);
+                                        record_credits(client, table, &user_id, crate::credits::CreditKind::Subscription,
+                                            monthly_credits - user.credits_remaining, Some(monthly_credits), &user.plan).await;
+                                    }
                                     Err(e) => tracing::error!("BILLING ERROR: Failed to reset credits to {} for user {} (invoice.paid): {}", monthly_credits, user_id, e),
                                 }
                             } else {
@@ -10455,25 +12412,85 @@ async fn handle_omikuji(
 ) -> impl IntoResponse {
     info!("Omikuji request");
 
+    let login_required = || (axum::http::StatusCode::UNAUTHORIZED, Json(serde_json::json!({
+        "error": "Login required"
+    }))).into_response();
+    let already_drawn = || (axum::http::StatusCode::BAD_REQUEST, Json(serde_json::json!({
+        "error": "Already drawn today",
+        "error_ja": "今日はもう引きました。明日また来てください！"
+    }))).into_response();
+
+    // Self-hosted (libSQL): the daily limit is per user, so only the
+    // authenticated user or a session already linked to one may draw; a made-up
+    // session id must not create a user. Claiming today's draw first keeps
+    // concurrent requests from drawing twice.
+    if let Some(ref db) = state.db {
+        let user_id = match auth_user_id(&state, &headers).await {
+            Some(uid) => Some(uid),
+            None => match req.session_id.as_deref().filter(|sid| !sid.is_empty()) {
+                Some(sid) => db.get_channel_map(sid).await.ok().flatten(),
+                None => None,
+            },
+        };
+        let Some(user_id) = user_id else {
+            return login_required();
+        };
+        let fortune = crate::omikuji::draw();
+        let balance = match db.get_or_create_user(&user_id).await {
+            Ok(user) => user.credits_remaining,
+            Err(e) => {
+                tracing::error!("omikuji: failed to load user {}: {}", user_id, e);
+                return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                    "error": "Database error"
+                }))).into_response();
+            }
+        };
+        let grant = crate::omikuji::grant(fortune, balance);
+        match db.record_omikuji_draw(&user_id, &crate::omikuji::today(), fortune.name, grant).await {
+            Ok(true) => {}
+            Ok(false) => return already_drawn(),
+            Err(e) => {
+                tracing::error!("omikuji: failed to record draw for {}: {}", user_id, e);
+                return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                    "error": "Database error"
+                }))).into_response();
+            }
+        }
+        let mut remaining = balance;
+        if grant > 0 {
+            match db.add_credits(&user_id, grant).await {
+                Ok(new_balance) => {
+                    remaining = new_balance;
+                    let tx = crate::credits::CreditTransaction::new(crate::credits::CreditKind::Grant, grant, Some(new_balance),
+                        format!("omikuji {}", fortune.name));
+                    crate::credits::record(&crate::credits::DbLedger(db.clone()), &user_id, tx).await;
+                }
+                Err(e) => tracing::error!("omikuji: failed to grant {} credits to {}: {}", grant, user_id, e),
+            }
+        }
+        return Json(serde_json::json!({
+            "success": true,
+            "fortune": fortune.name,
+            "credits_granted": grant,
+            "credits_remaining": remaining,
+            "message": fortune.message,
+        })).into_response();
+    }
+
     #[cfg(feature = "dynamodb-backend")]
     if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
         // 1. Resolve user
-        let session_key = if let Some(ref sid) = req.session_id {
-            sid.clone()
-        } else {
-            auth_user_id(&state, &headers).await.unwrap_or_default()
+        let session_key = match req.session_id.clone().filter(|sid| !sid.is_empty()) {
+            Some(sid) => sid,
+            None => auth_user_id(&state, &headers).await.unwrap_or_default(),
         };
-
         if session_key.is_empty() {
-            return (axum::http::StatusCode::UNAUTHORIZED, Json(serde_json::json!({
-                "error": "Login required"
-            }))).into_response();
+            return login_required();
         }
-
         let resolved_user = resolve_session_key(dynamo, table, &session_key).await;
 
         // 2. Check if already drawn today
-        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        let today = crate::omikuji::today();
         let check = dynamo
             .get_item()
             .table_name(table)
@@ -10484,38 +12501,16 @@ async fn handle_omikuji(
 
         if let Ok(output) = check {
             if output.item.is_some() {
-                return (axum::http::StatusCode::BAD_REQUEST, Json(serde_json::json!({
-                    "error": "Already drawn today",
-                    "error_ja": "今日はもう引きました。明日また来てください！"
-                }))).into_response();
+                return already_drawn();
             }
         }
 
         // 3. Draw fortune with weighted random
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        let roll: f64 = rng.gen(); // 0.0 to 1.0
-
-        let (fortune, credits, message) = if roll < 0.05 {
-            // 5% - 大吉
-            ("大吉", 500, "素晴らしい運気！今日は何をしても上手くいきそうです。新しいことに挑戦してみましょう！")
-        } else if roll < 0.20 {
-            // 15% - 吉
-            ("吉", 200, "とても良い運勢です。積極的に行動すれば良い結果が得られるでしょう。")
-        } else if roll < 0.50 {
-            // 30% - 中吉
-            ("中吉", 100, "まずまずの運気です。落ち着いて物事に取り組めば順調に進みます。")
-        } else if roll < 0.80 {
-            // 30% - 小吉
-            ("小吉", 50, "穏やかな一日になりそうです。小さな幸せを見つけてみてください。")
-        } else {
-            // 20% - 末吉
-            ("末吉", 10, "慎重に過ごしましょう。焦らず、一歩ずつ進むことが大切です。")
-        };
+        let fortune = crate::omikuji::draw();
 
         // 4. Grant credits
         let user = get_or_create_user(dynamo, table, &resolved_user).await;
-        let grant = credits.min(100_000 - user.credits_remaining);
+        let grant = crate::omikuji::grant(fortune, user.credits_remaining);
 
         if grant > 0 {
             let _ = dynamo
@@ -10528,6 +12523,8 @@ async fn handle_omikuji(
                 .expression_attribute_values(":now", AttributeValue::S(chrono::Utc::now().to_rfc3339()))
                 .send()
                 .await;
+            record_credits(dynamo, table, &resolved_user, crate::credits::CreditKind::Grant, grant, Some(user.credits_remaining + grant),
+                &format!("omikuji {}", fortune.name)).await;
         }
 
         // 5. Record today's draw
@@ -10536,7 +12533,7 @@ async fn handle_omikuji(
             .table_name(table)
             .item("pk", AttributeValue::S(format!("OMIKUJI#{}", resolved_user)))
             .item("sk", AttributeValue::S(format!("DATE#{}", today)))
-            .item("fortune", AttributeValue::S(fortune.to_string()))
+            .item("fortune", AttributeValue::S(fortune.name.to_string()))
             .item("credits", AttributeValue::N(grant.to_string()))
             .item("drawn_at", AttributeValue::S(chrono::Utc::now().to_rfc3339()))
             .send()
@@ -10544,20 +12541,17 @@ async fn handle_omikuji(
 
         let updated = get_or_create_user(dynamo, table, &resolved_user).await;
         emit_audit_log(dynamo.clone(), table.clone(), "omikuji_drawn", &resolved_user, "",
-            &format!("fortune={}, credits={}", fortune, grant));
+            &format!("fortune={}, credits={}", fortune.name, grant));
 
         return Json(serde_json::json!({
             "success": true,
-            "fortune": fortune,
+            "fortune": fortune.name,
             "credits_granted": grant,
             "credits_remaining": updated.credits_remaining,
-            "message": message,
+            "message": fortune.message,
         })).into_response();
     }
 
-    #[cfg(not(feature = "dynamodb-backend"))]
-    let _ = (&state, &headers, &req);
-
     (axum::http::StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({
         "error": "Database not available"
     }))).into_response()
@@ -10716,6 +12710,7 @@ async fn handle_coupon_redeem(
                 .await;
 
             let updated = get_or_create_user(dynamo, table, &resolved_user).await;
+            record_credits(dynamo, table, &resolved_user, crate::credits::CreditKind::Grant, grant, Some(updated.credits_remaining), &code).await;
             emit_audit_log(dynamo.clone(), table.clone(), "easter_egg_redeemed", &resolved_user, "",
                 &format!("code={}, granted={}, new_balance={}", code, grant, updated.credits_remaining));
 
@@ -10831,6 +12826,9 @@ async fn handle_coupon_redeem(
             .expression_attribute_values(":now", AttributeValue::S(now.clone()))
             .send()
             .await;
+        if grant_credits > 0 {
+            record_credits(dynamo, table, &resolved_user, crate::credits::CreditKind::Coupon, grant_credits, None, &code).await;
+        }
 
         // 6. Record redemption (increment count)
         let ttl = (chrono::Utc::now() + chrono::Duration::days(grant_days + 30)).timestamp();
@@ -10976,6 +12974,7 @@ async fn handle_trial_start(
             .expression_attribute_values(":now", AttributeValue::S(now.clone()))
             .send()
             .await;
+        record_credits(dynamo, table, &resolved_user, crate::credits::CreditKind::Grant, TRIAL_CREDITS, None, TRIAL_CODE).await;
 
         // 5. Record redemption
         let ttl = (chrono::Utc::now() + chrono::Duration::days(TRIAL_DAYS + 30)).timestamp();
@@ -11005,7 +13004,7 @@ async fn handle_trial_start(
             let resend_key = std::env::var("RESEND_API_KEY").ok().filter(|k| !k.is_empty());
             if let Some(api_key) = resend_key {
                 tokio::spawn(async move {
-                    let client = reqwest::Client::new();
+                    let client = client_for(ClientClass::General);
                     let (from, brand, color) = email_brand(true);
                     let site_url = if brand == "teai.io" { "https://teai.io/" } else { "https://chatweb.ai/" };
                     let body = serde_json::json!({
@@ -11269,8 +13268,10 @@ async fn handle_referral_apply(
                 .expression_attribute_values(":now", AttributeValue::S(now))
                 .send()
                 .await;
+            record_credits(dynamo, table, &referrer_id, crate::credits::CreditKind::Referral, bonus, None, &format!("referred {}", user_id)).await;
 
             let updated_user = get_or_create_user(dynamo, table, &user_id).await;
+            record_credits(dynamo, table, &user_id, crate::credits::CreditKind::Referral, bonus, Some(updated_user.credits_remaining), &code).await;
             return Json(serde_json::json!({
                 "ok": true,
                 "bonus_credits": bonus,
@@ -11524,7 +13525,7 @@ async fn handle_crypto_initiate(
         })).into_response(),
     };
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let or_resp = client
         .post("https://openrouter.ai/api/v1/credits/coinbase")
         .header("Authorization", format!("Bearer {}", or_key))
@@ -11623,7 +13624,7 @@ async fn handle_crypto_confirm(
         _ => return Json(serde_json::json!({ "error": "Not configured" })).into_response(),
     };
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let credits_resp = client
         .get("https://openrouter.ai/api/v1/credits")
         .header("Authorization", format!("Bearer {}", or_key))
@@ -12520,7 +14521,7 @@ impl WebhookSkillDef {
 
 /// Call an external webhook for a skill tool invocation.
 async fn call_webhook(webhook_url: &str, tool_name: &str, args: &std::collections::HashMap<String, serde_json::Value>) -> String {
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     match client.post(webhook_url)
         .header("Content-Type", "application/json")
         .header("X-Skill-Name", tool_name)
@@ -12665,7 +14666,7 @@ async fn handle_integrations(
 }
 
 /// GET / — Root landing page (host-based routing)
-async fn handle_root(headers: axum::http::HeaderMap) -> impl IntoResponse {
+async fn handle_root(State(state): State<Arc<AppState>>, headers: axum::http::HeaderMap) -> impl IntoResponse {
     // NOTE: WASM SPA disabled — using embedded index.html which has
     // agent-select, multi-agent support, and all latest UI features.
     // To re-enable WASM: uncomment STATIC_DIR check below.
@@ -12682,7 +14683,9 @@ async fn handle_root(headers: axum::http::HeaderMap) -> impl IntoResponse {
 
     let host = effective_host(&headers);
 
-    let html: std::borrow::Cow<'static, str> = if host.starts_with("api.") {
+    let html: std::borrow::Cow<'static, str> = if let Some(page) = webui_page(&state) {
+        std::borrow::Cow::Owned(page)
+    } else if host.starts_with("api.") {
         std::borrow::Cow::Borrowed(include_str!("../../../../web/api-docs.html"))
     } else if host.contains("chatweb-pi") || host.contains(".local") || (!host.contains('.') && !host.contains("chatweb.ai") && !host.contains("teai.io")) {
         std::borrow::Cow::Borrowed(include_str!("../../../../web/pi.html"))
@@ -12770,6 +14773,18 @@ async fn handle_root(headers: axum::http::HeaderMap) -> impl IntoResponse {
     )
 }
 
+/// The built-in chat page, when `gateway.webui` is on (`webui` feature).
+#[cfg(feature = "webui")]
+fn webui_page(state: &AppState) -> Option<String> {
+    let webui = &state.config.gateway.webui;
+    webui.enabled.then(|| super::webui::render(webui))
+}
+
+#[cfg(not(feature = "webui"))]
+fn webui_page(_state: &AppState) -> Option<String> {
+    None
+}
+
 /// GET /api/v1/pricing — Pricing data JSON
 async fn handle_pricing_api() -> impl IntoResponse {
     use crate::provider::pricing::{PRICING_TABLE, MEDIA_PRICING};
@@ -12990,7 +15005,10 @@ async fn handle_contact_submit(
 /// GET /admin — Admin dashboard HTML shell.
 /// The page itself is served to anyone, but all API calls require Bearer token auth.
 /// admin.html reads authToken from localStorage for API authentication.
-async fn handle_admin() -> impl IntoResponse {
+async fn handle_admin(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    if state.config.gateway.admin_ui {
+        return super::admin_ui::page().into_response();
+    }
     axum::response::Html(include_str!("../../../../web/admin.html")).into_response()
 }
 
@@ -14948,7 +16966,8 @@ async fn handle_enai_redeem(
             }
             let credits = enai * 10; // 1 ENAI = 10 credits
             // Add credits to profile
-            add_credits_to_user(dynamo, table, &user_id, credits, "", "").await;
+            let balance = add_credits_to_user(dynamo, table, &user_id, credits, "", "").await;
+            record_credits(dynamo, table, &user_id, crate::credits::CreditKind::Grant, credits, balance, "ENAI redemption").await;
             // Reset enai_earned to 0
             let pk = format!("USER#{}", user_id);
             let _ = dynamo
@@ -15229,7 +17248,8 @@ async fn handle_enai_confirm(
                     .await;
 
                 // Add credits to user
-                add_credits_to_user(dynamo, table, &user_id, credits, "", "").await;
+                let balance = add_credits_to_user(dynamo, table, &user_id, credits, "", "").await;
+                record_credits(dynamo, table, &user_id, crate::credits::CreditKind::Purchase, credits, balance, "ENAI payment").await;
 
                 // Get updated balance
                 let user = get_or_create_user(dynamo, table, &user_id).await;
@@ -15608,6 +17628,9 @@ async fn handle_local_status() -> impl IntoResponse {
         loaded: bool,
         memory_usage_mb: u64,
         capabilities: Vec<&'static str>,
+        /// Loaded models, pool limits and load times
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pool: Option<crate::provider::pool::PoolStatus>,
     }
 
     #[cfg(feature = "local-fallback")]
@@ -15622,6 +17645,7 @@ async fn handle_local_status() -> impl IntoResponse {
             loaded,
             memory_usage_mb: memory,
             capabilities: vec!["text-generation", "japanese", "english"],
+            pool: Some(LocalProvider::pool_status()),
         })
     }
     #[cfg(not(feature = "local-fallback"))]
@@ -15632,6 +17656,7 @@ async fn handle_local_status() -> impl IntoResponse {
             loaded: false,
             memory_usage_mb: 0,
             capabilities: vec!["text-generation", "japanese", "english"],
+            pool: None,
         })
     }
 }
@@ -15865,6 +17890,7 @@ async fn handle_llms_full_txt() -> impl IntoResponse {
          - GET /api/v1/agents — List AI agents\n\
          - GET /api/v1/integrations — List tools\n\
          - GET /api/v1/devices — List connected CLI devices\n\
+         - POST /api/v1/devices/commands — Send a remote command to a device\n\
          - GET /health — Health check\n\
          - GET /api/v1/status/ping — Service status with latencies\n\
          \n\
@@ -15990,9 +18016,10 @@ async fn handle_link_generate(
     }
 }
 
-/// GET /api/v1/link/status/{code} — Check if a link code has been consumed (linked)
-/// Returns { "status": "pending" } if code still exists, { "status": "linked" } if consumed.
-/// Used by the frontend QR modal to detect when linking completes.
+/// GET /api/v1/link/status/{code} — Check whether a link code has been consumed
+/// Returns `pending` with the generating channel while the code is open,
+/// `linked` with the channel that consumed it, or `expired`.
+/// Used by the frontend QR modal and `chatweb link --qr` to detect when linking completes.
 async fn handle_link_status(
     State(state): State<Arc<AppState>>,
     Path(code): Path<String>,
@@ -16005,24 +18032,43 @@ async fn handle_link_status(
         };
 
         let code = code.trim().to_uppercase();
-        let resp = dynamo
-            .get_item()
-            .table_name(table)
-            .key("pk", aws_sdk_dynamodb::types::AttributeValue::S(format!("LINKCODE#{}", code)))
-            .key("sk", aws_sdk_dynamodb::types::AttributeValue::S("PENDING".to_string()))
-            .send()
-            .await;
+        let lookup = |sk: &'static str| {
+            dynamo
+                .get_item()
+                .table_name(table)
+                .key("pk", aws_sdk_dynamodb::types::AttributeValue::S(format!("LINKCODE#{}", code)))
+                .key("sk", aws_sdk_dynamodb::types::AttributeValue::S(sk.to_string()))
+                .send()
+        };
 
-        match resp {
+        // DynamoDB deletes expired items lazily, so check the ttl ourselves
+        let now = chrono::Utc::now().timestamp();
+        let live = |item: &std::collections::HashMap<String, aws_sdk_dynamodb::types::AttributeValue>| {
+            item.get("ttl")
+                .and_then(|v| v.as_n().ok())
+                .and_then(|n| n.parse::<i64>().ok())
+                .is_none_or(|ttl| ttl >= now)
+        };
+        let channel = |item: &std::collections::HashMap<String, aws_sdk_dynamodb::types::AttributeValue>| {
+            item.get("channel_key")
+                .and_then(|v| v.as_s().ok())
+                .map(|k| super::commands::channel_display_name(k).0)
+                .unwrap_or_default()
+        };
+
+        match lookup("PENDING").await {
             Ok(output) => {
-                if output.item.is_some() {
-                    // Code still exists — not yet consumed
-                    Json(serde_json::json!({ "status": "pending" }))
-                } else {
-                    // Code consumed (deleted after linking) — linking completed
-                    Json(serde_json::json!({ "status": "linked" }))
+                if let Some(item) = output.item.filter(|i| live(i)) {
+                    return Json(serde_json::json!({ "status": "pending", "channel": channel(&item) }));
                 }
             }
+            Err(_) => return Json(serde_json::json!({ "status": "error" })),
+        }
+        match lookup("DONE").await {
+            Ok(output) => match output.item.filter(|i| live(i)) {
+                Some(item) => Json(serde_json::json!({ "status": "linked", "channel": channel(&item) })),
+                None => Json(serde_json::json!({ "status": "expired" })),
+            },
             Err(_) => Json(serde_json::json!({ "status": "error" })),
         }
     }
@@ -16034,6 +18080,55 @@ async fn handle_link_status(
     }
 }
 
+#[derive(Deserialize)]
+struct LinkConfirmRequest {
+    code: String,
+}
+
+/// POST /api/v1/link/confirm — Consume a link code from the browser (x-session-id)
+/// Called by the /link/{code} page after the user confirms on their phone.
+async fn handle_link_confirm(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<LinkConfirmRequest>,
+) -> impl IntoResponse {
+    #[cfg(feature = "dynamodb-backend")]
+    {
+        let session_id = headers
+            .get("x-session-id")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("");
+
+        if session_id.is_empty() {
+            return Json(serde_json::json!({ "error": "Missing x-session-id header" }));
+        }
+
+        let (dynamo, table) = match (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+            (Some(d), Some(t)) => (d, t.as_str()),
+            _ => return Json(serde_json::json!({ "error": "DynamoDB not configured" })),
+        };
+
+        use super::commands::LinkResult;
+        match super::commands::handle_link_command(dynamo, table, session_id, Some(&req.code), &state.sessions).await {
+            LinkResult::Linked(message) => Json(serde_json::json!({ "linked": true, "message": message })),
+            LinkResult::CodeGenerated(message) | LinkResult::Error(message) => {
+                Json(serde_json::json!({ "linked": false, "message": message }))
+            }
+        }
+    }
+
+    #[cfg(not(feature = "dynamodb-backend"))]
+    {
+        let _ = (state, headers, req);
+        Json(serde_json::json!({ "error": "DynamoDB backend required" }))
+    }
+}
+
+/// GET /link/{code} — Phone-side confirmation page for a QR link code
+async fn handle_link_page(Path(_code): Path<String>) -> impl IntoResponse {
+    axum::response::Html(include_str!("../../../../web/link.html"))
+}
+
 /// POST /api/v1/results — Save a playground result for sharing
 async fn handle_save_result(
     State(state): State<Arc<AppState>>,
@@ -16695,6 +18790,7 @@ async fn handle_get_settings(
             dev_mode: None,
             solana_wallet: None,
             enai_earned: None,
+            memory_consolidation: None,
         },
         "session_id": id,
     }))
@@ -16820,13 +18916,14 @@ async fn get_user_settings(
             let dev_mode = item.get("dev_mode").and_then(|v| v.as_bool().ok()).copied();
             let solana_wallet = item.get("solana_wallet").and_then(|v| v.as_s().ok()).cloned();
             let enai_earned = item.get("enai_earned").and_then(|v| v.as_n().ok()).and_then(|n| n.parse::<i64>().ok());
+            let memory_consolidation = item.get("memory_consolidation").and_then(|v| v.as_bool().ok()).copied();
             return UserSettings {
                 preferred_model, temperature, enabled_tools, custom_api_keys, language,
                 adult_mode, age_verified, top_p, frequency_penalty, presence_penalty,
                 custom_system_prompt, streaming_enabled, show_thinking, theme, ui_language,
                 font_size, send_method, tts_speed, show_token_info, show_timestamps, compact_mode,
                 preferred_voice, preferred_tts_provider, ai_nickname, user_nickname, onboarding_completed,
-                use_master_key_fallback, dev_mode, solana_wallet, enai_earned,
+                use_master_key_fallback, dev_mode, solana_wallet, enai_earned, memory_consolidation,
             };
         }
     }
@@ -16866,6 +18963,7 @@ async fn get_user_settings(
         dev_mode: None,
         solana_wallet: None,
         enai_earned: None,
+        memory_consolidation: None,
     }
 }
 
@@ -17039,6 +19137,10 @@ async fn save_user_settings(
         update_expr.push("solana_wallet = :sw".to_string());
         expr_values.insert(":sw".to_string(), AttributeValue::S(sw.clone()));
     }
+    if let Some(mc) = req.memory_consolidation {
+        update_expr.push("memory_consolidation = :mc".to_string());
+        expr_values.insert(":mc".to_string(), AttributeValue::Bool(mc));
+    }
 
     let update_expression = update_expr.join(", ");
     let _ = dynamo
@@ -17214,7 +19316,7 @@ async fn handle_google_callback(
     };
 
     // Exchange code for tokens
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let token_resp = client
         .post("https://oauth2.googleapis.com/token")
         .form(&[
@@ -18111,7 +20213,7 @@ fn email_brand(is_teai: bool) -> (&'static str, &'static str, &'static str) {
 #[allow(dead_code)]
 async fn send_verification_email(email: &str, code: &str, resend_api_key: &str) -> Result<(), String> {
     let (from, brand, color) = email_brand(true);
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let body = serde_json::json!({
         "from": from,
         "to": [email],
@@ -18764,7 +20866,7 @@ async fn handle_forgot_password(
             // Send reset email
             let resend_api_key = std::env::var("RESEND_API_KEY").ok().filter(|k| !k.is_empty());
             if let Some(api_key) = resend_api_key {
-                let client = reqwest::Client::new();
+                let client = client_for(ClientClass::General);
                 let (from, brand, color) = email_brand(true);
                 let body = serde_json::json!({
                     "from": from,
@@ -18961,7 +21063,7 @@ async fn handle_delete_account(
             if let Some(cust_id) = &stripe_customer_id {
                 let stripe_key = std::env::var("STRIPE_SECRET_KEY").ok().filter(|k| !k.is_empty());
                 if let Some(key) = stripe_key {
-                    let client = reqwest::Client::new();
+                    let client = client_for(ClientClass::General);
                     // List active subscriptions
                     let subs_url = format!("https://api.stripe.com/v1/customers/{}/subscriptions?status=active", cust_id);
                     if let Ok(resp) = client.get(&subs_url)
@@ -19657,7 +21759,7 @@ async fn handle_speech_recognize(
                 .filter(|k| !k.is_empty());
 
             if let Some(api_key) = openai_key {
-                let client = reqwest::Client::new();
+                let client = client_for(ClientClass::General);
                 let content_type = headers.get("content-type")
                     .and_then(|v| v.to_str().ok())
                     .unwrap_or("audio/webm");
@@ -19793,12 +21895,11 @@ async fn handle_finalize_conversation(
             let session_key = resolve_session_key(dynamo, table, old_session_id).await;
 
             // Force memory consolidation (fire-and-forget)
-            let provider_for_mem = state.get_lb_provider().or_else(|| state.provider.clone());
-            if let Some(provider) = provider_for_mem {
+            if let Some(job) = memory_consolidation(&state.config, state.get_lb_provider().or_else(|| state.provider.clone())) {
                 let dynamo_c = dynamo.clone();
                 let table_c = table.clone();
                 let sk = session_key.clone();
-                spawn_consolidate_memory(dynamo_c, table_c, sk, provider);
+                spawn_consolidate_memory(dynamo_c, table_c, sk, job);
             }
 
             return Json(serde_json::json!({"ok": true}));
@@ -20710,7 +22811,7 @@ async fn try_qwen_tts(text: &str, voice: &str, _speed: f64) -> Result<Vec<u8>, S
         voice
     };
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
 
     // Non-streaming mode: returns a URL valid for 24 hours
     let resp = client
@@ -20793,7 +22894,7 @@ async fn try_openai_tts(text: &str, voice: &str, speed: f64, instructions: Optio
 
     let voice_instructions = instructions.unwrap_or(default_instructions);
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let resp = client
         .post("https://api.openai.com/v1/audio/speech")
         .header("Authorization", format!("Bearer {}", api_key))
@@ -20870,7 +22971,7 @@ async fn try_elevenlabs_tts(text: &str, voice_id: &str, model_id: &str) -> Resul
     let mid = if model_id.is_empty() { "eleven_multilingual_v2" } else { model_id };
 
     let url = format!("https://api.elevenlabs.io/v1/text-to-speech/{}", vid);
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let resp = client
         .post(&url)
         .header("xi-api-key", &api_key)
@@ -20912,7 +23013,7 @@ async fn try_replicate_qwen3_tts(text: &str, voice: &str, language: &str) -> Res
         return Err("Empty REPLICATE_API_TOKEN".to_string());
     }
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
 
     // Create prediction
     let prediction_resp = client
@@ -21022,7 +23123,7 @@ async fn try_replicate_cosyvoice_clone(tts_text: &str, prompt_text: &str, audio_
         format!("data:audio/wav;base64,{}", audio_base64)
     };
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
 
     // Create prediction via Models API
     let prediction_resp = client
@@ -21127,7 +23228,7 @@ async fn try_sbv2_tts(text: &str, model_id: i32, speaker_id: i32, style: &str) -
     }
 
     let url = format!("https://api.runpod.ai/v2/{}/runsync", endpoint_id);
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let resp = client
         .post(&url)
         .header("Authorization", format!("Bearer {}", api_key))
@@ -21195,7 +23296,7 @@ async fn try_modal_qwen_tts(text: &str, voice: &str, speed: f64, reference_audio
     let language = if is_ja { "ja" } else { "en" };
     let voice_name = if voice.is_empty() { "default" } else { voice };
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let url = format!("{}/synthesize", endpoint_url.trim_end_matches('/'));
 
     let mut payload = serde_json::json!({
@@ -21250,7 +23351,7 @@ async fn try_runpod_qwen_tts(text: &str, voice: &str, speed: f64, reference_audi
     let language = if is_ja { "ja" } else { "en" };
     let voice_name = if voice.is_empty() { "default" } else { voice };
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let url = format!("{}/synthesize", endpoint_url.trim_end_matches('/'));
 
     let mut payload = serde_json::json!({
@@ -21305,7 +23406,7 @@ async fn try_modal_cosyvoice_tts(text: &str, mode: &str, prompt_audio: Option<&s
         return Err("Empty MODAL_COSYVOICE_TTS_URL".to_string());
     }
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let url = format!("{}/synthesize", endpoint_url.trim_end_matches('/'));
 
     let mut payload = serde_json::json!({
@@ -21352,7 +23453,7 @@ async fn try_runpod_cosyvoice_tts(text: &str, mode: &str, prompt_audio: Option<&
     }
 
     let url = format!("https://api.runpod.ai/v2/{}/runsync", endpoint_id);
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
 
     let mut input = serde_json::json!({
         "text": text,
@@ -21406,7 +23507,7 @@ async fn try_cosyvoice_tts(text: &str, mode: &str, speaker_id: &str) -> Result<V
     }
 
     let url = format!("https://api.runpod.ai/v2/{}/runsync", endpoint_id);
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
 
     let mode_str = if mode.is_empty() { "sft" } else { mode };
     let mut input = serde_json::json!({
@@ -21766,7 +23867,7 @@ async fn handle_voice_clone(
         let runpod_endpoint = std::env::var("RUNPOD_COSYVOICE_ENDPOINT_ID").unwrap_or_default();
         if !runpod_api_key.is_empty() && !runpod_endpoint.is_empty() {
             let url = format!("https://api.runpod.ai/v2/{}/run", runpod_endpoint);
-            let client = reqwest::Client::new();
+            let client = client_for(ClientClass::General);
             let resp = client
                 .post(&url)
                 .header("Authorization", format!("Bearer {}", runpod_api_key))
@@ -21862,7 +23963,7 @@ async fn handle_voice_clone_status(
     }
 
     let url = format!("https://api.runpod.ai/v2/{}/status/{}", endpoint_id, job_id);
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let resp = client
         .get(&url)
         .header("Authorization", format!("Bearer {}", api_key))
@@ -22078,7 +24179,7 @@ async fn handle_media_image(
     }
 
     // 4. Generate image using appropriate provider
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let images: Vec<serde_json::Value>;
 
     if model == "flux-pro" || model == "flux-schnell" || model == "flux-realism" {
@@ -22389,7 +24490,7 @@ async fn poll_kling_video(
     let api_base = std::env::var("KLING_API_BASE")
         .unwrap_or_else(|_| "https://api.klingai.com".to_string());
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
 
     // First, submit the video generation request
     let body = serde_json::json!({
@@ -22630,7 +24731,7 @@ async fn poll_stable_audio(
         }
     };
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
 
     // First, submit the music generation request to fal.ai queue
     let body = serde_json::json!({
@@ -22808,7 +24909,7 @@ async fn handle_media_ocr(
     let fal_key = get_api_key("fal")
         .ok_or_else(|| (StatusCode::SERVICE_UNAVAILABLE, "FAL_KEY not configured".to_string()))?;
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let body = serde_json::json!({
         "image_url": req.image_url.unwrap_or_else(|| format!("data:image/png;base64,{}", req.image_base64.unwrap_or_default())),
         "language": req.language.clone().unwrap_or_else(|| "jpn+eng".to_string()),
@@ -22880,7 +24981,7 @@ async fn handle_media_remove_bg(
     let fal_key = get_api_key("fal")
         .ok_or_else(|| (StatusCode::SERVICE_UNAVAILABLE, "FAL_KEY not configured".to_string()))?;
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let model = if quality == "hd" { "fal-ai/bria-rmbg" } else { "fal-ai/bria-rmbg" };
 
     let body = serde_json::json!({
@@ -22958,7 +25059,7 @@ async fn handle_media_upscale(
     let fal_key = get_api_key("fal")
         .ok_or_else(|| (StatusCode::SERVICE_UNAVAILABLE, "FAL_KEY not configured".to_string()))?;
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let fal_model = if model == "quality" {
         "fal-ai/ccsr"  // High quality model
     } else {
@@ -23032,7 +25133,7 @@ async fn handle_media_sfx(
     let fal_key = get_api_key("fal")
         .ok_or_else(|| (StatusCode::SERVICE_UNAVAILABLE, "FAL_KEY not configured".to_string()))?;
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let body = serde_json::json!({
         "prompt": req.prompt,
         "seconds_total": duration,
@@ -23244,7 +25345,7 @@ async fn handle_openai_image_generations(
         }
     };
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let body = serde_json::json!({
         "model": model,
         "prompt": prompt,
@@ -23511,7 +25612,7 @@ async fn handle_openai_chat_completions(
                 "max_tokens": max_tokens,
                 "temperature": temperature,
             });
-            let client = reqwest::Client::new();
+            let client = client_for(ClientClass::General);
             match client.post(&api_url)
                 .header("Authorization", format!("Bearer {}", auth_key))
                 .header("Content-Type", "application/json")
@@ -24420,6 +26521,8 @@ struct CronScheduleInput {
     every_minutes: Option<u64>,
     cron: Option<String>,
     at: Option<String>,
+    /// Timezone for `cron`/`at`; defaults to the user's profile timezone.
+    tz: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -24477,6 +26580,7 @@ async fn handle_cron_list(
                             "schedule": format!("{}:{}", schedule_type, schedule_val),
                             "schedule_display": display,
                             "channel": item.get("channel").and_then(|v| v.as_s().ok()).cloned().unwrap_or_default(),
+                            "timezone": item.get("timezone").and_then(|v| v.as_s().ok()).cloned(),
                             "enabled": item.get("enabled").and_then(|v| v.as_s().ok()).map(|s| s == "true").unwrap_or(true),
                             "created_at": item.get("created_at").and_then(|v| v.as_s().ok()).cloned().unwrap_or_default(),
                         })
@@ -24546,7 +26650,17 @@ async fn handle_cron_create(
                 return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": "Invalid schedule" }))).into_response();
             };
 
-            let now = chrono::Utc::now().to_rfc3339();
+            let channel = req.channel.unwrap_or_default();
+            let timezone = req.schedule.tz.clone()
+                .unwrap_or_else(|| {
+                    let is_english = user.locale.as_deref() == Some("en");
+                    resolve_timezone(None, Some(&user), &channel, is_english)
+                });
+            if crate::util::tz::parse(&timezone).is_none() {
+                return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": format!("Unknown timezone: {}", timezone) }))).into_response();
+            }
+
+            let now = chrono::Utc::now().to_rfc3339();
             let mut item = std::collections::HashMap::new();
             item.insert("pk".to_string(), AttributeValue::S(user_pk));
             item.insert("sk".to_string(), AttributeValue::S(format!("JOB#{}", job_id)));
@@ -24555,7 +26669,8 @@ async fn handle_cron_create(
             item.insert("message".to_string(), AttributeValue::S(req.message));
             item.insert("schedule_type".to_string(), AttributeValue::S(schedule_type));
             item.insert("schedule_value".to_string(), AttributeValue::S(schedule_value));
-            item.insert("channel".to_string(), AttributeValue::S(req.channel.unwrap_or_default()));
+            item.insert("channel".to_string(), AttributeValue::S(channel));
+            item.insert("timezone".to_string(), AttributeValue::S(timezone));
             item.insert("enabled".to_string(), AttributeValue::S("true".to_string()));
             item.insert("created_at".to_string(), AttributeValue::S(now));
             item.insert("user_id".to_string(), AttributeValue::S(user_id));
@@ -24571,120 +26686,615 @@ async fn handle_cron_create(
     (StatusCode::NOT_IMPLEMENTED, Json(serde_json::json!({ "error": "DynamoDB backend required" }))).into_response()
 }
 
-async fn handle_cron_update(
+async fn handle_cron_update(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<CronUpdateRequest>,
+) -> impl IntoResponse {
+    let token = headers.get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string())
+        .unwrap_or_default();
+
+    #[cfg(feature = "dynamodb-backend")]
+    {
+        if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+            let user_id = resolve_user_from_token(dynamo, table, &token).await;
+            if user_id.is_empty() {
+                return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Not authenticated" }))).into_response();
+            }
+            let user_pk = format!("CRON#{}", user_id);
+            let sk = format!("JOB#{}", id);
+
+            let mut update_expr_parts = vec![];
+            let mut attr_values = std::collections::HashMap::new();
+            if let Some(enabled) = req.enabled {
+                update_expr_parts.push("enabled = :enabled");
+                attr_values.insert(":enabled".to_string(), AttributeValue::S(enabled.to_string()));
+            }
+            if let Some(ref name) = req.name {
+                update_expr_parts.push("#n = :name");
+                attr_values.insert(":name".to_string(), AttributeValue::S(name.clone()));
+            }
+            if let Some(ref message) = req.message {
+                update_expr_parts.push("message = :msg");
+                attr_values.insert(":msg".to_string(), AttributeValue::S(message.clone()));
+            }
+            if let Some(ref schedule) = req.schedule {
+                let (stype, svalue) = if let Some(mins) = schedule.every_minutes {
+                    ("every".to_string(), mins.to_string())
+                } else if let Some(ref expr) = schedule.cron {
+                    ("cron".to_string(), expr.clone())
+                } else if let Some(ref at_str) = schedule.at {
+                    ("at".to_string(), at_str.clone())
+                } else {
+                    ("every".to_string(), "30".to_string())
+                };
+                update_expr_parts.push("schedule_type = :stype");
+                attr_values.insert(":stype".to_string(), AttributeValue::S(stype));
+                update_expr_parts.push("schedule_value = :svalue");
+                attr_values.insert(":svalue".to_string(), AttributeValue::S(svalue));
+            }
+            if let Some(ref channel) = req.channel {
+                update_expr_parts.push("channel = :channel");
+                attr_values.insert(":channel".to_string(), AttributeValue::S(channel.clone()));
+            }
+            if update_expr_parts.is_empty() {
+                return Json(serde_json::json!({ "ok": true })).into_response();
+            }
+
+            let update_expr = format!("SET {}", update_expr_parts.join(", "));
+            let mut builder = dynamo.update_item()
+                .table_name(table)
+                .key("pk", AttributeValue::S(user_pk))
+                .key("sk", AttributeValue::S(sk))
+                .update_expression(&update_expr);
+            for (k, v) in &attr_values {
+                builder = builder.expression_attribute_values(k, v.clone());
+            }
+            if req.name.is_some() {
+                builder = builder.expression_attribute_names("#n", "name");
+            }
+            match builder.send().await {
+                Ok(_) => return Json(serde_json::json!({ "ok": true })).into_response(),
+                Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": format!("{}", e) }))).into_response(),
+            }
+        }
+    }
+
+    let _ = (&state, &id, &token, &req);
+    (StatusCode::NOT_IMPLEMENTED, Json(serde_json::json!({ "error": "DynamoDB backend required" }))).into_response()
+}
+
+async fn handle_cron_delete(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    let token = headers.get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string())
+        .unwrap_or_default();
+
+    #[cfg(feature = "dynamodb-backend")]
+    {
+        if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+            let user_id = resolve_user_from_token(dynamo, table, &token).await;
+            if user_id.is_empty() {
+                return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Not authenticated" }))).into_response();
+            }
+            let user_pk = format!("CRON#{}", user_id);
+            let sk = format!("JOB#{}", id);
+            match dynamo.delete_item()
+                .table_name(table)
+                .key("pk", AttributeValue::S(user_pk))
+                .key("sk", AttributeValue::S(sk))
+                .send().await
+            {
+                Ok(_) => return Json(serde_json::json!({ "ok": true })).into_response(),
+                Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": format!("{}", e) }))).into_response(),
+            }
+        }
+    }
+
+    let _ = (&state, &id, &token);
+    (StatusCode::NOT_IMPLEMENTED, Json(serde_json::json!({ "error": "DynamoDB backend required" }))).into_response()
+}
+
+// ---------------------------------------------------------------------------
+// Upcoming: one calendar of scheduled items (crate::service::upcoming)
+// ---------------------------------------------------------------------------
+
+/// The gateway's cron store on a personal instance (`chatweb gateway --http`).
+/// Multi-user deployments keep each user's jobs in the database instead.
+fn local_cron(state: &AppState) -> Option<crate::service::cron::CronService> {
+    #[cfg(feature = "dynamodb-backend")]
+    if state.dynamo_client.is_some() {
+        return None;
+    }
+    if state.db.is_some() {
+        return None;
+    }
+    Some(crate::service::cron::CronService::open(crate::config::get_data_dir().join("cron").join("jobs.json")))
+}
+
+fn upcoming_error(e: crate::service::upcoming::ChangeError) -> axum::response::Response {
+    use crate::service::upcoming::ChangeError;
+    let status = match e {
+        ChangeError::NotFound => StatusCode::NOT_FOUND,
+        ChangeError::Synced(_) => StatusCode::CONFLICT,
+        ChangeError::InvalidTime => StatusCode::BAD_REQUEST,
+    };
+    (status, Json(serde_json::json!({ "error": e.to_string() }))).into_response()
+}
+
+/// A user's cloud cron job (`CRON#<user>`) as an upcoming item.
+#[cfg(feature = "dynamodb-backend")]
+fn cloud_upcoming_item(
+    item: &std::collections::HashMap<String, AttributeValue>,
+    now_ms: u64,
+) -> Option<crate::service::upcoming::UpcomingItem> {
+    use crate::service::cron::CronSchedule;
+    let get = |key: &str| item.get(key).and_then(|v| v.as_s().ok()).cloned().unwrap_or_default();
+    if get("enabled") == "false" {
+        return None;
+    }
+    let (schedule_type, value) = (get("schedule_type"), get("schedule_value"));
+    let next_run_at_ms = match schedule_type.as_str() {
+        "every" => now_ms + value.parse::<u64>().ok()? * 60_000,
+        "cron" => CronSchedule::Cron { expr: value, tz: Some(get("timezone")).filter(|tz| !tz.is_empty()) }.next_run(now_ms)?,
+        _ => chrono::DateTime::parse_from_rfc3339(&value).ok().map(|t| t.timestamp_millis() as u64).filter(|&t| t > now_ms)?,
+    };
+    let (name, channel) = (get("name"), get("channel"));
+    Some(crate::service::upcoming::UpcomingItem {
+        id: get("job_id"),
+        kind: crate::service::upcoming::UpcomingKind::Cron,
+        title: if name.is_empty() { get("message") } else { name },
+        next_run_at_ms,
+        recurring: schedule_type != "at",
+        editable: true,
+        channel: Some(channel).filter(|c| !c.is_empty()),
+        chat_id: None,
+    })
+}
+
+/// GET /api/v1/upcoming — everything scheduled for the user, soonest first:
+/// reminders, follow-ups, digests, workflow runs and cron jobs.
+async fn handle_upcoming_list(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    if let Some(cron) = local_cron(&state) {
+        let items = crate::service::upcoming::list(&cron, None);
+        return Json(serde_json::json!({ "items": items })).into_response();
+    }
+
+    #[cfg(feature = "dynamodb-backend")]
+    if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+        let token = headers.get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.trim_start_matches("Bearer ").to_string())
+            .unwrap_or_default();
+        let user_id = resolve_user_from_token(dynamo, table, &token).await;
+        if user_id.is_empty() {
+            return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Not authenticated" }))).into_response();
+        }
+        let resp = dynamo
+            .query()
+            .table_name(table)
+            .key_condition_expression("pk = :pk AND begins_with(sk, :sk)")
+            .expression_attribute_values(":pk", AttributeValue::S(format!("CRON#{}", user_id)))
+            .expression_attribute_values(":sk", AttributeValue::S("JOB#".to_string()))
+            .send()
+            .await;
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+        let mut items: Vec<_> = match resp {
+            Ok(output) => output.items().iter().filter_map(|item| cloud_upcoming_item(item, now_ms)).collect(),
+            Err(e) => {
+                tracing::error!("upcoming: failed to list cron jobs for {}: {}", user_id, e);
+                Vec::new()
+            }
+        };
+        items.sort_by_key(|i| i.next_run_at_ms);
+        return Json(serde_json::json!({ "items": items })).into_response();
+    }
+
+    let _ = &headers;
+    Json(serde_json::json!({ "items": [] })).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct UpcomingRescheduleRequest {
+    /// `30m`, `18:30`, `2026-03-14 09:00`, RFC 3339 or a cron expression.
+    when: String,
+    /// Timezone `when` is read in; defaults to the configured one.
+    tz: Option<String>,
+}
+
+/// PUT /api/v1/upcoming/{id} — move an item to `when`.
+async fn handle_upcoming_reschedule(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<UpcomingRescheduleRequest>,
+) -> impl IntoResponse {
+    let tz = req.tz.clone()
+        .or_else(|| state.config.agents.defaults.timezone.clone())
+        .unwrap_or_else(|| "UTC".to_string());
+
+    if let Some(mut cron) = local_cron(&state) {
+        return match crate::service::upcoming::reschedule(&mut cron, None, &id, &req.when, &tz) {
+            Ok(item) => Json(serde_json::json!({ "ok": true, "item": item })).into_response(),
+            Err(e) => upcoming_error(e),
+        };
+    }
+
+    #[cfg(feature = "dynamodb-backend")]
+    if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+        use crate::service::cron::CronSchedule;
+        let token = headers.get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.trim_start_matches("Bearer ").to_string())
+            .unwrap_or_default();
+        let user_id = resolve_user_from_token(dynamo, table, &token).await;
+        if user_id.is_empty() {
+            return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Not authenticated" }))).into_response();
+        }
+        let zone = crate::util::tz::parse_or_utc(&tz);
+        let (schedule_type, value) = match crate::service::upcoming::parse_when(&req.when, chrono::Utc::now().with_timezone(&zone), &tz) {
+            Some(CronSchedule::At { at_ms }) => match chrono::DateTime::<chrono::Utc>::from_timestamp_millis(at_ms as i64) {
+                Some(t) => ("at", t.with_timezone(&zone).fixed_offset().to_rfc3339()),
+                None => return upcoming_error(crate::service::upcoming::ChangeError::InvalidTime),
+            },
+            Some(CronSchedule::Cron { expr, .. }) => ("cron", expr),
+            _ => return upcoming_error(crate::service::upcoming::ChangeError::InvalidTime),
+        };
+        let result = dynamo.update_item()
+            .table_name(table)
+            .key("pk", AttributeValue::S(format!("CRON#{}", user_id)))
+            .key("sk", AttributeValue::S(format!("JOB#{}", id)))
+            .update_expression("SET schedule_type = :stype, schedule_value = :svalue, timezone = :tz, enabled = :enabled")
+            .condition_expression("attribute_exists(pk)")
+            .expression_attribute_values(":stype", AttributeValue::S(schedule_type.to_string()))
+            .expression_attribute_values(":svalue", AttributeValue::S(value))
+            .expression_attribute_values(":tz", AttributeValue::S(tz))
+            .expression_attribute_values(":enabled", AttributeValue::S("true".to_string()))
+            .return_values(aws_sdk_dynamodb::types::ReturnValue::AllNew)
+            .send()
+            .await;
+        return match result {
+            Ok(output) => {
+                let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+                let item = output.attributes.as_ref().and_then(|attrs| cloud_upcoming_item(attrs, now_ms));
+                Json(serde_json::json!({ "ok": true, "item": item })).into_response()
+            }
+            Err(e) if e.to_string().contains("ConditionalCheckFailed") => upcoming_error(crate::service::upcoming::ChangeError::NotFound),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": format!("{}", e) }))).into_response(),
+        };
+    }
+
+    let _ = &headers;
+    upcoming_error(crate::service::upcoming::ChangeError::NotFound)
+}
+
+/// DELETE /api/v1/upcoming/{id} — cancel an item.
+async fn handle_upcoming_cancel(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    if let Some(mut cron) = local_cron(&state) {
+        return match crate::service::upcoming::cancel(&mut cron, None, &id) {
+            Ok(()) => Json(serde_json::json!({ "ok": true })).into_response(),
+            Err(e) => upcoming_error(e),
+        };
+    }
+
+    #[cfg(feature = "dynamodb-backend")]
+    if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
+        let token = headers.get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.trim_start_matches("Bearer ").to_string())
+            .unwrap_or_default();
+        let user_id = resolve_user_from_token(dynamo, table, &token).await;
+        if user_id.is_empty() {
+            return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Not authenticated" }))).into_response();
+        }
+        let result = dynamo.delete_item()
+            .table_name(table)
+            .key("pk", AttributeValue::S(format!("CRON#{}", user_id)))
+            .key("sk", AttributeValue::S(format!("JOB#{}", id)))
+            .condition_expression("attribute_exists(pk)")
+            .send()
+            .await;
+        return match result {
+            Ok(_) => Json(serde_json::json!({ "ok": true })).into_response(),
+            Err(e) if e.to_string().contains("ConditionalCheckFailed") => upcoming_error(crate::service::upcoming::ChangeError::NotFound),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": format!("{}", e) }))).into_response(),
+        };
+    }
+
+    let _ = &headers;
+    upcoming_error(crate::service::upcoming::ChangeError::NotFound)
+}
+
+/// GET /api/v1/deliveries — list outbound delivery records (admin only).
+/// Query: channel, status (queued|sent|delivered|read|failed), source (e.g. cron:<job id>), limit.
+async fn handle_delivery_list(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Query(q): Query<std::collections::HashMap<String, String>>,
+) -> impl IntoResponse {
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Admin only" }))).into_response();
+    }
+    let status = match q.get("status") {
+        Some(s) => match crate::delivery::DeliveryStatus::parse(s) {
+            Some(status) => Some(status),
+            None => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": format!("Unknown status: {}", s) }))).into_response(),
+        },
+        None => None,
+    };
+    let filter = crate::delivery::DeliveryFilter {
+        channel: q.get("channel").cloned(),
+        status,
+        source: q.get("source").cloned(),
+        limit: q.get("limit").and_then(|l| l.parse().ok()).map(|l: usize| l.min(500)),
+    };
+    let records = state.deliveries.lock().await.list(&filter);
+    Json(serde_json::json!({ "deliveries": records })).into_response()
+}
+
+/// GET /api/v1/deliveries/{id} — status and history of one outbound message (admin only).
+async fn handle_delivery_get(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Admin only" }))).into_response();
+    }
+    match state.deliveries.lock().await.get(&id) {
+        Some(record) => Json(serde_json::json!(record)).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Delivery not found" }))).into_response(),
+    }
+}
+
+/// GET /api/v1/webhooks/deliveries — outbound webhook delivery log (admin only).
+async fn handle_webhook_delivery_list(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Query(q): Query<std::collections::HashMap<String, String>>,
+) -> impl IntoResponse {
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Admin only" }))).into_response();
+    }
+    let status = match q.get("status").map(String::as_str) {
+        Some("pending") => Some(crate::webhook::WebhookStatus::Pending),
+        Some("delivered") => Some(crate::webhook::WebhookStatus::Delivered),
+        Some("failed") => Some(crate::webhook::WebhookStatus::Failed),
+        Some(s) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": format!("Unknown status: {}", s) }))).into_response(),
+        None => None,
+    };
+    let filter = crate::webhook::WebhookFilter {
+        event: q.get("event").cloned(),
+        status,
+        endpoint_id: q.get("endpoint").cloned(),
+        limit: q.get("limit").and_then(|l| l.parse().ok()).map(|l: usize| l.min(500)),
+    };
+    let deliveries = state.webhooks.lock().await.list(&filter);
+    Json(serde_json::json!({ "deliveries": deliveries })).into_response()
+}
+
+/// POST /api/v1/webhooks/deliveries/{id}/retry — re-queue a failed webhook (admin only).
+async fn handle_webhook_delivery_retry(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Admin only" }))).into_response();
+    }
+    if !state.webhooks.lock().await.retry(&id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "No failed delivery with that id" }))).into_response();
+    }
+    let outbox = state.webhooks.clone();
+    tokio::spawn(async move {
+        crate::webhook::flush(&outbox, &client_for(ClientClass::Webhook)).await;
+    });
+    Json(serde_json::json!({ "ok": true, "id": id })).into_response()
+}
+
+/// GET /api/v1/workflows — workflow definitions and files that failed to load (admin only).
+async fn handle_workflow_list(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Admin only" }))).into_response();
+    }
+    let (workflows, errors) = state.workflows.load_all();
+    let errors: Vec<_> = errors
+        .into_iter()
+        .map(|(path, error)| serde_json::json!({ "file": path.display().to_string(), "error": error }))
+        .collect();
+    Json(serde_json::json!({ "workflows": workflows, "errors": errors })).into_response()
+}
+
+/// GET /api/v1/workflows/runs — execution history, newest first (admin only).
+async fn handle_workflow_runs(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Query(q): Query<std::collections::HashMap<String, String>>,
+) -> impl IntoResponse {
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Admin only" }))).into_response();
+    }
+    let status = match q.get("status") {
+        Some(s) => match crate::workflow::RunStatus::parse(s) {
+            Some(status) => Some(status),
+            None => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": format!("Unknown status: {}", s) }))).into_response(),
+        },
+        None => None,
+    };
+    let limit = q.get("limit").and_then(|l| l.parse().ok()).unwrap_or(50usize).min(500);
+    let runs = state.workflows.runs(q.get("workflow").map(String::as_str), status, limit);
+    Json(serde_json::json!({ "runs": runs })).into_response()
+}
+
+/// GET /api/v1/workflows/runs/{id} — one run with its step history (admin only).
+async fn handle_workflow_run_get(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Admin only" }))).into_response();
+    }
+    match state.workflows.get_run(&id) {
+        Some(run) => Json(serde_json::json!(run)).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Run not found" }))).into_response(),
+    }
+}
+
+/// POST /api/v1/workflows/runs/{id}/resume — re-queue a failed or waiting run (admin only).
+async fn handle_workflow_run_resume(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Admin only" }))).into_response();
+    }
+    match state.workflows.requeue(&id) {
+        Some(run) => Json(serde_json::json!(run)).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "No failed or waiting run with that id" }))).into_response(),
+    }
+}
+
+/// POST /api/v1/workflows/{name}/trigger — queue a run of a workflow with a
+/// `webhook` trigger. The request body is passed to the workflow as `{{input}}`.
+/// Authenticated with the workflow's `secret` as a Bearer token, or as an admin.
+async fn handle_workflow_trigger(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> impl IntoResponse {
+    let workflow = match state.workflows.load(&name) {
+        Ok(w) if w.trigger.webhook => w,
+        _ => return (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Workflow not found" }))).into_response(),
+    };
+    let bearer = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let secret_ok = !workflow.trigger.secret.is_empty()
+        && bearer == Some(workflow.trigger.secret.as_str());
+    if !secret_ok && authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Invalid workflow secret" }))).into_response();
+    }
+    let input = if body.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_slice(&body)
+            .unwrap_or_else(|_| serde_json::Value::String(String::from_utf8_lossy(&body).into_owned()))
+    };
+    match state.workflows.enqueue(&workflow.name, crate::workflow::TRIGGER_WEBHOOK, input, None) {
+        Ok(run) => (StatusCode::ACCEPTED, Json(serde_json::json!({ "ok": true, "runId": run.id }))).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+/// GET /api/v1/rules — rules, disabled channels and last heartbeats (admin only).
+async fn handle_rules_list(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Admin only" }))).into_response();
+    }
+    match state.rules.load() {
+        Ok(rules) => Json(serde_json::json!({
+            "rules": rules,
+            "disabledChannels": state.rules.disabled_channels(),
+            "heartbeats": state.rules.heartbeats(),
+        }))
+        .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+/// POST /api/v1/rules — add a rule, or replace the rule with the same id (admin only).
+async fn handle_rules_upsert(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(rule): Json<crate::rules::Rule>,
+) -> impl IntoResponse {
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Admin only" }))).into_response();
+    }
+    match state.rules.upsert(rule) {
+        Ok(rule) => Json(serde_json::json!(rule)).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+/// DELETE /api/v1/rules/{id} (admin only).
+async fn handle_rules_delete(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Admin only" }))).into_response();
+    }
+    match state.rules.remove(&id) {
+        Ok(true) => Json(serde_json::json!({ "ok": true, "id": id })).into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Rule not found" }))).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+/// GET /api/v1/rules/firings — firing history, newest first (admin only).
+async fn handle_rules_firings(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<String>,
     headers: axum::http::HeaderMap,
-    Json(req): Json<CronUpdateRequest>,
+    Query(q): Query<std::collections::HashMap<String, String>>,
 ) -> impl IntoResponse {
-    let token = headers.get("authorization")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.trim_start_matches("Bearer ").to_string())
-        .unwrap_or_default();
-
-    #[cfg(feature = "dynamodb-backend")]
-    {
-        if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
-            let user_id = resolve_user_from_token(dynamo, table, &token).await;
-            if user_id.is_empty() {
-                return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Not authenticated" }))).into_response();
-            }
-            let user_pk = format!("CRON#{}", user_id);
-            let sk = format!("JOB#{}", id);
-
-            let mut update_expr_parts = vec![];
-            let mut attr_values = std::collections::HashMap::new();
-            if let Some(enabled) = req.enabled {
-                update_expr_parts.push("enabled = :enabled");
-                attr_values.insert(":enabled".to_string(), AttributeValue::S(enabled.to_string()));
-            }
-            if let Some(ref name) = req.name {
-                update_expr_parts.push("#n = :name");
-                attr_values.insert(":name".to_string(), AttributeValue::S(name.clone()));
-            }
-            if let Some(ref message) = req.message {
-                update_expr_parts.push("message = :msg");
-                attr_values.insert(":msg".to_string(), AttributeValue::S(message.clone()));
-            }
-            if let Some(ref schedule) = req.schedule {
-                let (stype, svalue) = if let Some(mins) = schedule.every_minutes {
-                    ("every".to_string(), mins.to_string())
-                } else if let Some(ref expr) = schedule.cron {
-                    ("cron".to_string(), expr.clone())
-                } else if let Some(ref at_str) = schedule.at {
-                    ("at".to_string(), at_str.clone())
-                } else {
-                    ("every".to_string(), "30".to_string())
-                };
-                update_expr_parts.push("schedule_type = :stype");
-                attr_values.insert(":stype".to_string(), AttributeValue::S(stype));
-                update_expr_parts.push("schedule_value = :svalue");
-                attr_values.insert(":svalue".to_string(), AttributeValue::S(svalue));
-            }
-            if let Some(ref channel) = req.channel {
-                update_expr_parts.push("channel = :channel");
-                attr_values.insert(":channel".to_string(), AttributeValue::S(channel.clone()));
-            }
-            if update_expr_parts.is_empty() {
-                return Json(serde_json::json!({ "ok": true })).into_response();
-            }
-
-            let update_expr = format!("SET {}", update_expr_parts.join(", "));
-            let mut builder = dynamo.update_item()
-                .table_name(table)
-                .key("pk", AttributeValue::S(user_pk))
-                .key("sk", AttributeValue::S(sk))
-                .update_expression(&update_expr);
-            for (k, v) in &attr_values {
-                builder = builder.expression_attribute_values(k, v.clone());
-            }
-            if req.name.is_some() {
-                builder = builder.expression_attribute_names("#n", "name");
-            }
-            match builder.send().await {
-                Ok(_) => return Json(serde_json::json!({ "ok": true })).into_response(),
-                Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": format!("{}", e) }))).into_response(),
-            }
-        }
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Admin only" }))).into_response();
     }
-
-    let _ = (&state, &id, &token, &req);
-    (StatusCode::NOT_IMPLEMENTED, Json(serde_json::json!({ "error": "DynamoDB backend required" }))).into_response()
+    let limit = q.get("limit").and_then(|l| l.parse().ok()).unwrap_or(50usize).min(500);
+    let firings = state.rules.firings(q.get("rule").map(String::as_str), limit);
+    Json(serde_json::json!({ "firings": firings })).into_response()
 }
 
-async fn handle_cron_delete(
+/// POST /api/v1/rules/heartbeat/{source} — report that an external source is alive (admin only).
+async fn handle_rules_heartbeat(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<String>,
+    Path(source): Path<String>,
     headers: axum::http::HeaderMap,
 ) -> impl IntoResponse {
-    let token = headers.get("authorization")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.trim_start_matches("Bearer ").to_string())
-        .unwrap_or_default();
-
-    #[cfg(feature = "dynamodb-backend")]
-    {
-        if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
-            let user_id = resolve_user_from_token(dynamo, table, &token).await;
-            if user_id.is_empty() {
-                return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Not authenticated" }))).into_response();
-            }
-            let user_pk = format!("CRON#{}", user_id);
-            let sk = format!("JOB#{}", id);
-            match dynamo.delete_item()
-                .table_name(table)
-                .key("pk", AttributeValue::S(user_pk))
-                .key("sk", AttributeValue::S(sk))
-                .send().await
-            {
-                Ok(_) => return Json(serde_json::json!({ "ok": true })).into_response(),
-                Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": format!("{}", e) }))).into_response(),
-            }
-        }
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Admin only" }))).into_response();
     }
+    state.rules.beat(&source);
+    Json(serde_json::json!({ "ok": true, "source": source })).into_response()
+}
 
-    let _ = (&state, &id, &token);
-    (StatusCode::NOT_IMPLEMENTED, Json(serde_json::json!({ "error": "DynamoDB backend required" }))).into_response()
+/// POST /api/v1/rules/channels/{name}/enable — re-enable a channel disabled by a rule (admin only).
+async fn handle_rules_channel_enable(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Admin only" }))).into_response();
+    }
+    let changed = state.rules.set_channel_disabled(&name, false);
+    Json(serde_json::json!({ "ok": true, "channel": name, "changed": changed })).into_response()
 }
 
 /// Daily summary notification — sends usage summary to linked LINE/Telegram channels.
@@ -24701,10 +27311,12 @@ async fn handle_daily_summary(
     #[cfg(feature = "dynamodb-backend")]
     {
         if let (Some(dynamo), Some(table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
-            let yesterday = {
-                let now = chrono::Utc::now() + chrono::Duration::hours(9); // JST
+            // "Yesterday" follows each user's timezone; JST for users who haven't set one
+            let yesterday_in = |tz: Option<&str>| {
+                let now = crate::util::now_in(Some(tz.unwrap_or("Asia/Tokyo")));
                 (now - chrono::Duration::days(1)).format("%Y-%m-%d").to_string()
             };
+            let yesterday = yesterday_in(None);
 
             // Scan all LINK# records to find users with linked channels
             let mut linked_users: std::collections::HashMap<String, Vec<(String, String)>> = std::collections::HashMap::new(); // user_id → [(channel_type, channel_key)]
@@ -24751,9 +27363,23 @@ async fn handle_daily_summary(
             let mut errors = 0u32;
             let line_token = std::env::var("LINE_CHANNEL_ACCESS_TOKEN").unwrap_or_default();
             let tg_token = std::env::var("TELEGRAM_BOT_TOKEN").unwrap_or_default();
-            let client = reqwest::Client::new();
+            let client = client_for(ClientClass::General);
 
             for (user_id, channels) in &linked_users {
+                // Get credits remaining and timezone
+                let profile = dynamo.get_item()
+                    .table_name(table)
+                    .key("pk", AttributeValue::S(format!("USER#{}", user_id)))
+                    .key("sk", AttributeValue::S(SK_PROFILE.to_string()))
+                    .send().await
+                    .ok()
+                    .and_then(|o| o.item);
+                let credits = profile.as_ref()
+                    .and_then(|i| i.get("credits_remaining").and_then(|v| v.as_n().ok()).and_then(|n| n.parse::<i64>().ok()))
+                    .unwrap_or(0);
+                let user_tz = profile.as_ref().and_then(|i| i.get("timezone").and_then(|v| v.as_s().ok()));
+                let yesterday = yesterday_in(user_tz.map(String::as_str));
+
                 // Query yesterday's usage
                 let usage_pk = format!("USAGE#{}#{}", user_id, yesterday);
                 let usage_resp = dynamo.query()
@@ -24764,17 +27390,6 @@ async fn handle_daily_summary(
                     .send().await;
                 let msg_count = usage_resp.as_ref().map(|r| r.count()).unwrap_or(0);
 
-                // Get credits remaining
-                let profile_resp = dynamo.get_item()
-                    .table_name(table)
-                    .key("pk", AttributeValue::S(format!("USER#{}", user_id)))
-                    .key("sk", AttributeValue::S(SK_PROFILE.to_string()))
-                    .send().await;
-                let credits = profile_resp.ok()
-                    .and_then(|o| o.item)
-                    .and_then(|i| i.get("credits_remaining").and_then(|v| v.as_n().ok()).and_then(|n| n.parse::<i64>().ok()))
-                    .unwrap_or(0);
-
                 // Skip users with no activity yesterday
                 if msg_count == 0 { continue; }
 
@@ -24784,21 +27399,28 @@ async fn handle_daily_summary(
                 );
 
                 for (ch_type, ch_key) in channels {
-                    let result = match ch_type.as_str() {
-                        "line" if !line_token.is_empty() => {
-                            let line_uid = ch_key.trim_start_matches("line:");
-                            crate::channel::line::LineChannel::push_message(&line_token, line_uid, &summary).await
-                        }
+                    let chat_id = match ch_type.as_str() {
+                        "line" if !line_token.is_empty() => ch_key.trim_start_matches("line:"),
                         "telegram" if !tg_token.is_empty() => {
-                            let chat_id = ch_key.trim_start_matches("tg:").trim_start_matches("telegram:");
-                            crate::channel::telegram::TelegramChannel::send_message_static(&client, &tg_token, chat_id, &summary).await
+                            ch_key.trim_start_matches("tg:").trim_start_matches("telegram:")
                         }
                         _ => continue,
                     };
+                    let mut outbound = crate::types::OutboundMessage::new(ch_type.as_str(), chat_id, &summary);
+                    let delivery_id = state.deliveries.lock().await.enqueue(&mut outbound, Some("daily_summary"));
+                    let result = if ch_type == "line" {
+                        crate::channel::line::LineChannel::push_message(&line_token, chat_id, &summary).await
+                    } else {
+                        crate::channel::telegram::TelegramChannel::send_message_static(&client, &tg_token, chat_id, &summary).await
+                    };
                     match result {
-                        Ok(_) => sent += 1,
+                        Ok(_) => {
+                            state.deliveries.lock().await.mark_sent(&delivery_id, None);
+                            sent += 1;
+                        }
                         Err(e) => {
                             warn!("daily_summary: send to {} failed: {}", ch_key, e);
+                            state.deliveries.lock().await.mark_failed(&delivery_id, &e.to_string());
                             errors += 1;
                         }
                     }
@@ -24826,8 +27448,33 @@ pub async fn serve(addr: &str, state: Arc<AppState>) -> anyhow::Result<()> {
     serve_with_auth(addr, state, false).await
 }
 
+/// Split complete SSE frames off the front of `buf`, returning their `data:` payloads.
+pub(crate) fn drain_sse_frames(buf: &mut String) -> Vec<String> {
+    let mut out = Vec::new();
+    while let Some(end) = buf.find("\n\n") {
+        let frame: String = buf.drain(..end + 2).collect();
+        let data: Vec<&str> = frame
+            .lines()
+            .filter_map(|l| l.strip_prefix("data:"))
+            .map(|l| l.strip_prefix(' ').unwrap_or(l))
+            .collect();
+        if !data.is_empty() {
+            out.push(data.join("\n"));
+        }
+    }
+    out
+}
+
 /// Serve HTTP API with authentication enabled if tokens are configured.
 pub async fn serve_with_auth(addr: &str, state: Arc<AppState>, require_auth: bool) -> anyhow::Result<()> {
+    let addrs: Vec<std::net::SocketAddr> = tokio::net::lookup_host(addr).await?.collect();
+    serve_on(&addrs, state, require_auth).await
+}
+
+/// Serve HTTP API on every address in `addrs` (see [`crate::config::GatewayConfig::listen_addrs`]).
+/// Stops when any listener fails.
+pub async fn serve_on(addrs: &[std::net::SocketAddr], state: Arc<AppState>, require_auth: bool) -> anyhow::Result<()> {
+    anyhow::ensure!(!addrs.is_empty(), "no address to listen on");
     let mut router = create_router(state.clone());
 
     // Add IP restriction middleware if configured
@@ -24848,9 +27495,16 @@ pub async fn serve_with_auth(addr: &str, state: Arc<AppState>, require_auth: boo
         ));
     }
 
+    // Let's Encrypt, when domains are configured
+    let acme = &state.config.gateway.acme;
+    #[cfg(not(feature = "acme"))]
+    anyhow::ensure!(!acme.enabled(), "ACME certificates are not available. Rebuild with: cargo build --features acme");
+    #[cfg(feature = "acme")]
+    let acme_acceptor = acme.enabled().then(|| acme_acceptor(acme));
+
     // Check for TLS configuration
-    #[cfg(feature = "http-api")]
-    if let (Some(cert_path), Some(key_path)) = (&state.config.gateway.tls_cert, &state.config.gateway.tls_key) {
+    let mut tls = None;
+    if let (false, Some(cert_path), Some(key_path)) = (acme.enabled(), &state.config.gateway.tls_cert, &state.config.gateway.tls_key) {
         info!("Starting HTTPS server with TLS (cert: {}, key: {})", cert_path, key_path);
 
         // Install default crypto provider if not already installed
@@ -24869,27 +27523,92 @@ pub async fn serve_with_auth(addr: &str, state: Arc<AppState>, require_auth: boo
         let cert_expanded = expand_path(cert_path);
         let key_expanded = expand_path(key_path);
 
-        let config = axum_server::tls_rustls::RustlsConfig::from_pem_file(&cert_expanded, &key_expanded).await?;
-        let bind_addr: std::net::SocketAddr = addr.parse()?;
+        tls = Some(axum_server::tls_rustls::RustlsConfig::from_pem_file(&cert_expanded, &key_expanded).await?);
+    }
 
-        info!("HTTPS server listening on {}", addr);
-        axum_server::bind_rustls(bind_addr, config)
-            .serve(router.into_make_service_with_connect_info::<std::net::SocketAddr>())
-            .await?;
+    // IPv6 sockets accept IPv4 too unless told otherwise, which would clash
+    // with an explicit IPv4 listener on the same port.
+    let v6_only = addrs.iter().any(|a| a.is_ipv4());
+    let mut servers = Vec::new();
+    for &addr in addrs {
+        let listener = bind_listener(addr, v6_only).map_err(|e| anyhow::anyhow!("Failed to bind {}: {}", addr, e))?;
+        let app = router.clone().into_make_service_with_connect_info::<std::net::SocketAddr>();
+        #[cfg(feature = "acme")]
+        if let Some(acceptor) = acme_acceptor.clone() {
+            info!("HTTPS server listening on {} ({})", addr, acme.domains.join(", "));
+            servers.push(tokio::spawn(async move { axum_server::from_tcp(listener).acceptor(acceptor).serve(app).await }));
+            continue;
+        }
+        servers.push(match tls.clone() {
+            Some(tls) => {
+                info!("HTTPS server listening on {}", addr);
+                tokio::spawn(async move { axum_server::from_tcp_rustls(listener, tls).serve(app).await })
+            }
+            None => {
+                info!("HTTP server listening on {}", addr);
+                let listener = tokio::net::TcpListener::from_std(listener)?;
+                tokio::spawn(async move { axum::serve(listener, app).await })
+            }
+        });
+    }
 
-        return Ok(());
+    // Now that the listeners are up, point channel webhooks at them
+    if let Some(base) = state.config.gateway.public_base_url() {
+        let channels = state.config.channels.clone();
+        tokio::spawn(async move {
+            for registration in crate::channel::webhooks::register_all(&channels, &base).await {
+                registration.log();
+            }
+        });
     }
 
-    // HTTP mode (no TLS)
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    info!("HTTP server listening on {}", addr);
-    axum::serve(
-        listener,
-        router.into_make_service_with_connect_info::<std::net::SocketAddr>()
-    ).await?;
+    let (result, _, _) = futures::future::select_all(servers).await;
+    result??;
     Ok(())
 }
 
+/// TLS acceptor with certificates for `acme.domains` from Let's Encrypt,
+/// obtained and renewed by a background task and cached on disk.
+#[cfg(feature = "acme")]
+fn acme_acceptor(acme: &crate::config::AcmeConfig) -> rustls_acme::axum::AxumAcceptor {
+    use futures::StreamExt;
+
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    let cache_dir = acme.cache_path();
+    info!("Using Let's Encrypt for {} (cache: {})", acme.domains.join(", "), cache_dir.display());
+    let mut state = rustls_acme::AcmeConfig::new(acme.domains.clone())
+        .contact(acme.email.iter().map(|email| format!("mailto:{}", email)))
+        .cache(rustls_acme::caches::DirCache::new(cache_dir))
+        .directory_lets_encrypt(!acme.staging)
+        .state();
+    // The default config only speaks the challenge protocol; add the real ones.
+    let mut rustls_config = (*state.default_rustls_config()).clone();
+    rustls_config.alpn_protocols.extend([b"h2".to_vec(), b"http/1.1".to_vec()]);
+    let acceptor = state.axum_acceptor(Arc::new(rustls_config));
+    tokio::spawn(async move {
+        while let Some(event) = state.next().await {
+            match event {
+                Ok(event) => info!("ACME: {:?}", event),
+                Err(e) => warn!("ACME: {:?}", e),
+            }
+        }
+    });
+    acceptor
+}
+
+/// A non-blocking listening socket for `addr`.
+fn bind_listener(addr: std::net::SocketAddr, v6_only: bool) -> std::io::Result<std::net::TcpListener> {
+    let socket = socket2::Socket::new(socket2::Domain::for_address(addr), socket2::Type::STREAM, None)?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(v6_only)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    Ok(socket.into())
+}
+
 /// Middleware to validate Bearer token for Gateway HTTP API.
 async fn gateway_auth_middleware(
     State(state): State<Arc<AppState>>,
@@ -24901,12 +27620,30 @@ async fn gateway_auth_middleware(
     if request.uri().path() == "/health" || request.uri().path() == "/status" {
         return Ok(next.run(request).await);
     }
+    // Channel webhooks check their platform's signature or secret instead
+    if request.uri().path().starts_with("/webhooks/") {
+        return Ok(next.run(request).await);
+    }
+    // The dashboard page itself is static; it asks for a token for its data
+    if state.config.gateway.admin_ui && request.uri().path() == "/admin" {
+        return Ok(next.run(request).await);
+    }
+    // So is the chat page, which asks for a token on its first 401
+    if cfg!(feature = "webui") && state.config.gateway.webui.enabled && request.uri().path() == "/" {
+        return Ok(next.run(request).await);
+    }
 
-    // Extract Bearer token
+    // Extract Bearer token; browsers cannot set headers on WebSocket
+    // requests, so the voice socket may pass it as `?token=` instead.
+    let query_token = (request.uri().path() == "/api/v1/voice/ws")
+        .then(|| request.uri().query())
+        .flatten()
+        .and_then(|q| q.split('&').find_map(|kv| kv.strip_prefix("token=")));
     let token = headers
         .get(axum::http::header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok())
         .and_then(|v| v.strip_prefix("Bearer "))
+        .or(query_token)
         .ok_or((StatusCode::UNAUTHORIZED, "Missing or invalid Authorization header".to_string()))?;
 
     // Validate token against configured list
@@ -25459,11 +28196,11 @@ async fn handle_feedback(
     Json(req): Json<FeedbackRequest>,
 ) -> impl IntoResponse {
     // Validate rating
-    if req.rating != "up" && req.rating != "down" {
+    let Some(rating) = Rating::parse(&req.rating) else {
         return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
             "ok": false, "error": "rating must be 'up' or 'down'"
         }))).into_response();
-    }
+    };
 
     let session_id = headers.get("x-session-id")
         .and_then(|v| v.to_str().ok())
@@ -25481,22 +28218,30 @@ async fn handle_feedback(
         else if session_id.starts_with("webchat:") { "web" }
         else { "api" };
 
-    let snippet = req.snippet.unwrap_or_default();
-    let conv_id = req.conversation_id.unwrap_or_default();
+    let record = FeedbackRecord {
+        rating,
+        channel: channel.to_string(),
+        session_key: session_id,
+        user_id: user_id.unwrap_or_default(),
+        snippet: req.snippet.unwrap_or_default(),
+        conversation_id: req.conversation_id.unwrap_or_default(),
+        source: "button".to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+    save_feedback(&state, record);
+
+    Json(serde_json::json!({ "ok": true })).into_response()
+}
 
+/// Write a feedback record (button or reaction) to the config table and bump
+/// the global up/down counter. Fire-and-forget; no-op without DynamoDB.
+fn save_feedback(state: &AppState, record: FeedbackRecord) {
     #[cfg(feature = "dynamodb-backend")]
     {
         if let (Some(dynamo), Some(config_table)) = (state.dynamo_client.as_ref(), state.config_table.as_ref()) {
             let dynamo = dynamo.clone();
             let config_table = config_table.to_string();
-            let rating = req.rating.clone();
-            let session_id_c = session_id.clone();
-            let user_id_c = user_id.clone().unwrap_or_default();
-            let channel_c = channel.to_string();
-            let snippet_c = snippet.clone();
-            let conv_id_c = conv_id.clone();
 
-            // Fire-and-forget: write feedback record
             tokio::spawn(async move {
                 let now = chrono::Utc::now();
                 let date = now.format("%Y-%m-%d").to_string();
@@ -25510,19 +28255,20 @@ async fn handle_feedback(
                     .table_name(&config_table)
                     .item("pk", AttributeValue::S(format!("FEEDBACK#{}", date)))
                     .item("sk", AttributeValue::S(sk))
-                    .item("rating", AttributeValue::S(rating.clone()))
-                    .item("snippet", AttributeValue::S(snippet_c))
-                    .item("session_id", AttributeValue::S(session_id_c))
-                    .item("user_id", AttributeValue::S(user_id_c))
-                    .item("channel", AttributeValue::S(channel_c))
-                    .item("conversation_id", AttributeValue::S(conv_id_c))
-                    .item("timestamp", AttributeValue::S(now.to_rfc3339()))
+                    .item("rating", AttributeValue::S(record.rating.as_str().to_string()))
+                    .item("snippet", AttributeValue::S(record.snippet))
+                    .item("session_id", AttributeValue::S(record.session_key))
+                    .item("user_id", AttributeValue::S(record.user_id))
+                    .item("channel", AttributeValue::S(record.channel))
+                    .item("conversation_id", AttributeValue::S(record.conversation_id))
+                    .item("source", AttributeValue::S(record.source))
+                    .item("timestamp", AttributeValue::S(record.timestamp))
                     .item("ttl", AttributeValue::N(ttl))
                     .send()
                     .await;
 
                 // Atomic increment of aggregate counter
-                let counter_attr = if rating == "up" { "total_up" } else { "total_down" };
+                let counter_attr = if record.rating == Rating::Up { "total_up" } else { "total_down" };
                 let _ = dynamo
                     .update_item()
                     .table_name(&config_table)
@@ -25536,9 +28282,8 @@ async fn handle_feedback(
         }
     }
 
-    let _ = &state; // suppress unused warning in non-dynamo builds
-
-    Json(serde_json::json!({ "ok": true })).into_response()
+    #[cfg(not(feature = "dynamodb-backend"))]
+    let _ = (state, record);
 }
 
 /// GET /api/v1/version — Return version, git hash, build number, and repo URL.
@@ -25657,6 +28402,31 @@ async fn handle_bug_report(
     Json(serde_json::json!({ "ok": true })).into_response()
 }
 
+/// GET /api/v1/admin/routing — Learned router mode and its agreement with
+/// keyword scoring since startup (Bearer token auth)
+async fn handle_admin_routing(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    if authenticate_admin(&state, &headers).await.is_none() {
+        return (StatusCode::FORBIDDEN, Json(serde_json::json!({
+            "error": "Forbidden"
+        }))).into_response();
+    }
+
+    match state.router.as_ref() {
+        Some(router) => {
+            let report = router.shadow_report();
+            Json(serde_json::json!({
+                "mode": format!("{:?}", router.mode()).to_lowercase(),
+                "agreement": report.agreement(),
+                "report": report,
+            })).into_response()
+        }
+        None => Json(serde_json::json!({ "mode": "keyword" })).into_response(),
+    }
+}
+
 /// Query parameters for admin feedback endpoint.
 #[derive(Debug, Deserialize)]
 struct AdminFeedbackQuery {
@@ -26598,6 +29368,10 @@ mod agent_routing_tests {
             stripe_customer_id: None,
             email: None,
             created_at: "2025-01-01".to_string(),
+            dev_mode: false,
+            solana_wallet: None,
+            timezone: None,
+            locale: None,
         };
         let ctx = build_meta_context(Some(&user), "line", "mobile", 6, false);
         assert!(ctx.contains("ユーザー名: 太郎"));
@@ -26665,6 +29439,10 @@ mod agent_routing_tests {
             stripe_customer_id: None,
             email: Some("test@example.com".to_string()),
             created_at: "2025-01-01".to_string(),
+            dev_mode: false,
+            solana_wallet: None,
+            timezone: None,
+            locale: None,
         };
         let json = serde_json::to_string(&profile).unwrap();
         assert!(json.contains("test-user"));
@@ -26689,6 +29467,10 @@ mod agent_routing_tests {
             stripe_customer_id: None,
             email: None,
             created_at: "2025-06-01".to_string(),
+            dev_mode: false,
+            solana_wallet: None,
+            timezone: None,
+            locale: None,
         };
         let json = serde_json::to_string(&profile).unwrap();
         // None fields serialize as null in serde default
@@ -26711,6 +29493,10 @@ mod agent_routing_tests {
             stripe_customer_id: Some("cus_abc123".to_string()),
             email: Some("alice@example.com".to_string()),
             created_at: "2025-03-15T10:00:00Z".to_string(),
+            dev_mode: false,
+            solana_wallet: None,
+            timezone: None,
+            locale: None,
         };
         let json = serde_json::to_string(&profile).unwrap();
         let deser: UserProfile = serde_json::from_str(&json).unwrap();
@@ -26782,6 +29568,25 @@ mod agent_routing_tests {
         assert_eq!(req.device, Some("mobile".to_string()));
     }
 
+    #[test]
+    fn test_chat_request_mode_and_options() {
+        let json = r#"{"message": "hi", "mode": "consensus", "options": {"tier": "powerful", "tools": false}}"#;
+        let req: ChatRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.resolve_mode("chatweb.ai"), ChatMode::Consensus);
+        assert_eq!(req.options.tier, Some(crate::types::ModelTier::Powerful));
+        assert!(!req.options.tools_enabled());
+
+        let legacy: ChatRequest = serde_json::from_str(r#"{"message": "hi", "multi_model": true}"#).unwrap();
+        assert_eq!(legacy.resolve_mode("chatweb.ai"), ChatMode::Race);
+        let plain: ChatRequest = serde_json::from_str(r#"{"message": "hi"}"#).unwrap();
+        assert_eq!(plain.resolve_mode("wisbee.ai"), ChatMode::Local);
+        assert_eq!(plain.resolve_mode("chatweb.ai"), ChatMode::Auto);
+
+        assert!(serde_json::from_str::<ChatRequest>(r#"{"message": "hi", "mode": "fastest"}"#).is_err());
+        let options = ChatOptions { agent: Some("nobody".into()), ..Default::default() };
+        assert!(validate_chat_options(&options).is_err());
+    }
+
     #[test]
     fn test_chat_request_missing_message_fails() {
         let json = r#"{"session_id": "s1"}"#;
@@ -26809,6 +29614,7 @@ mod agent_routing_tests {
             output_tokens: Some(20),
             estimated_cost_usd: Some(0.001),
             mode: None,
+            error_code: None,
         };
         let json = serde_json::to_string(&resp).unwrap();
         assert!(json.contains("\"credits_used\":5"));
@@ -26835,6 +29641,7 @@ mod agent_routing_tests {
             output_tokens: None,
             estimated_cost_usd: None,
             mode: None,
+            error_code: None,
         };
         let json = serde_json::to_string(&resp).unwrap();
         // Only response and session_id should be present
@@ -26865,6 +29672,7 @@ mod agent_routing_tests {
             output_tokens: Some(150),
             estimated_cost_usd: Some(0.005),
             mode: None,
+            error_code: None,
         };
         let json = serde_json::to_string(&resp).unwrap();
         assert!(json.contains("web_search"));
@@ -26992,6 +29800,7 @@ mod agent_routing_tests {
     fn test_error_response_serialization() {
         let err = ErrorResponse {
             error: "not found".to_string(),
+            code: None,
         };
         let json = serde_json::to_string(&err).unwrap();
         assert_eq!(json, r#"{"error":"not found"}"#);
@@ -27001,6 +29810,7 @@ mod agent_routing_tests {
     fn test_error_response_empty_message() {
         let err = ErrorResponse {
             error: "".to_string(),
+            code: None,
         };
         let json = serde_json::to_string(&err).unwrap();
         assert_eq!(json, r#"{"error":""}"#);
@@ -27085,7 +29895,7 @@ mod agent_routing_tests {
     fn test_build_meta_context_with_model_ja() {
         let ctx = build_meta_context_with_model(
             None, "web", "pc", 0, false,
-            Some("unknown-model"), 500, 1500,
+            None, Some("unknown-model"), 500, 1500,
         );
         assert!(ctx.contains("現在時刻:"));
         assert!(ctx.contains("モデル: unknown-model"));
@@ -27098,7 +29908,7 @@ mod agent_routing_tests {
     fn test_build_meta_context_with_model_en() {
         let ctx = build_meta_context_with_model(
             None, "api", "voice", 10, true,
-            Some("unknown-model"), 1000, 5000,
+            None, Some("unknown-model"), 1000, 5000,
         );
         assert!(ctx.contains("Time:"));
         assert!(ctx.contains("Model: unknown-model"));
@@ -27112,7 +29922,7 @@ mod agent_routing_tests {
     fn test_build_meta_context_with_model_no_model() {
         let ctx = build_meta_context_with_model(
             None, "line", "mobile", 0, false,
-            None, 0, 0,
+            None, None, 0, 0,
         );
         assert!(ctx.contains("現在時刻:"));
         assert!(ctx.contains("チャネル: line"));
@@ -27121,6 +29931,38 @@ mod agent_routing_tests {
         assert!(!ctx.contains("この会話:"));
     }
 
+    #[test]
+    fn test_build_meta_context_timezone() {
+        let mut user = UserProfile {
+            user_id: "tz".to_string(),
+            display_name: None,
+            plan: "pro".to_string(),
+            credits_remaining: 5000,
+            credits_used: 0,
+            channels: vec![],
+            stripe_customer_id: None,
+            email: None,
+            created_at: "2025-01-01".to_string(),
+            dev_mode: false,
+            solana_wallet: None,
+            timezone: Some("America/New_York".to_string()),
+            locale: Some("en".to_string()),
+        };
+        let ctx = build_meta_context(Some(&user), "web", "pc", 0, true);
+        assert!(ctx.contains("(America/New_York)"));
+        let ctx = build_meta_context_with_model(
+            Some(&user), "web", "pc", 0, true,
+            Some("UTC+14"), None, 0, 0,
+        );
+        assert!(ctx.contains("(UTC+14)"));
+
+        // No timezone anywhere: inferred from channel/language
+        user.timezone = None;
+        assert!(build_meta_context(Some(&user), "line", "pc", 0, true).contains("(Asia/Tokyo)"));
+        assert!(build_meta_context(Some(&user), "web", "pc", 0, true).contains("(UTC)"));
+        assert!(build_meta_context(None, "web", "pc", 0, false).contains("・Asia/Tokyo）"));
+    }
+
     #[test]
     fn test_build_meta_context_low_credits_warning_ja() {
         let user = UserProfile {
@@ -27133,6 +29975,10 @@ mod agent_routing_tests {
             stripe_customer_id: None,
             email: None,
             created_at: "2025-01-01".to_string(),
+            dev_mode: false,
+            solana_wallet: None,
+            timezone: None,
+            locale: None,
         };
         let ctx = build_meta_context(Some(&user), "web", "pc", 0, false);
         assert!(ctx.contains("クレジット残少"));
@@ -27150,6 +29996,10 @@ mod agent_routing_tests {
             stripe_customer_id: None,
             email: None,
             created_at: "2025-01-01".to_string(),
+            dev_mode: false,
+            solana_wallet: None,
+            timezone: None,
+            locale: None,
         };
         let ctx = build_meta_context(Some(&user), "web", "pc", 0, true);
         assert!(ctx.contains("LOW_CREDITS"));
@@ -27167,6 +30017,10 @@ mod agent_routing_tests {
             stripe_customer_id: None,
             email: None,
             created_at: "2025-01-01".to_string(),
+            dev_mode: false,
+            solana_wallet: None,
+            timezone: None,
+            locale: None,
         };
         let ctx = build_meta_context(Some(&user), "web", "pc", 0, false);
         // Low credits warning only appears for free plan
@@ -27230,6 +30084,11 @@ mod agent_routing_tests {
             ai_nickname: None,
             user_nickname: None,
             onboarding_completed: None,
+            use_master_key_fallback: None,
+            dev_mode: None,
+            solana_wallet: None,
+            enai_earned: None,
+            memory_consolidation: None,
         };
         let json = serde_json::to_string(&settings).unwrap();
         let deser: UserSettings = serde_json::from_str(&json).unwrap();
@@ -27269,6 +30128,11 @@ mod agent_routing_tests {
             ai_nickname: None,
             user_nickname: None,
             onboarding_completed: None,
+            use_master_key_fallback: None,
+            dev_mode: None,
+            solana_wallet: None,
+            enai_earned: None,
+            memory_consolidation: None,
         };
         let json = serde_json::to_string(&settings).unwrap();
         let deser: UserSettings = serde_json::from_str(&json).unwrap();
@@ -27387,3 +30251,123 @@ mod agent_routing_tests {
         }
     }
 }
+
+#[cfg(test)]
+mod incognito_webhook_tests {
+    use super::*;
+    use crate::error::ProviderError;
+    use crate::session::Session;
+    use crate::types::{CompletionResponse, FinishReason, TokenUsage};
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct MemStore {
+        sessions: HashMap<String, Session>,
+    }
+
+    impl SessionStore for MemStore {
+        fn get_or_create(&mut self, key: &str) -> &mut Session {
+            self.sessions.entry(key.to_string()).or_insert_with(|| Session::new(key))
+        }
+        fn refresh(&mut self, key: &str) -> &mut Session {
+            self.get_or_create(key)
+        }
+        fn save(&self, _session: &Session) {}
+        fn save_by_key(&self, _key: &str) {}
+        fn delete(&mut self, key: &str) -> bool {
+            self.sessions.remove(key).is_some()
+        }
+        fn list_sessions(&self) -> Vec<serde_json::Value> {
+            Vec::new()
+        }
+    }
+
+    struct Canned;
+
+    #[async_trait]
+    impl LlmProvider for Canned {
+        async fn chat(
+            &self,
+            _messages: &[Message],
+            _tools: Option<&[serde_json::Value]>,
+            _model: &str,
+            _max_tokens: u32,
+            _temperature: f64,
+        ) -> Result<CompletionResponse, ProviderError> {
+            Ok(CompletionResponse {
+                content: Some("answer".into()),
+                tool_calls: vec![],
+                finish_reason: FinishReason::Stop,
+                usage: TokenUsage::default(),
+                model: None,
+            })
+        }
+
+        fn default_model(&self) -> &str {
+            "canned"
+        }
+    }
+
+    fn state(workspace: &std::path::Path) -> Arc<AppState> {
+        let mut config = Config::default();
+        config.agents.defaults.workspace = workspace.display().to_string();
+        config.channels.line.channel_secret = "line-secret".into();
+        let state = AppState::with_provider(config, Box::new(MemStore::default()));
+        *state.lb_provider.write().unwrap() = Some(Arc::new(Canned));
+        Arc::new(state)
+    }
+
+    async fn history(state: &AppState, key: &str) -> usize {
+        state.sessions.lock().await.get_or_create(key).get_history(50).len()
+    }
+
+    #[tokio::test]
+    async fn test_telegram_incognito_stores_nothing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = state(tmp.path());
+        let update = |text: &str| {
+            serde_json::json!({"update_id": 1, "message": {"message_id": 5, "date": 0, "chat": {"id": 7, "type": "private"},
+                "from": {"id": 7, "is_bot": false, "first_name": "A"}, "text": text}})
+            .to_string()
+        };
+        let send = |text: &str| handle_telegram_webhook(State(state.clone()), Default::default(), update(text));
+
+        send("/incognito hello").await;
+        assert_eq!(history(&state, "tg:7").await, 0);
+        send("/incognito").await;
+        send("hello").await;
+        assert_eq!(history(&state, "tg:7").await, 0);
+        send("/incognito off").await;
+        send("hello").await;
+        assert_eq!(history(&state, "tg:7").await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_line_incognito_stores_nothing() {
+        use hmac::{Hmac, Mac};
+
+        let tmp = tempfile::tempdir().unwrap();
+        let state = state(tmp.path());
+        let send = |text: &str| {
+            let body = serde_json::json!({"events": [{"type": "message", "replyToken": "r", "source": {"type": "user", "userId": "U1"},
+                "message": {"id": "m", "type": "text", "text": text}}]})
+            .to_string();
+            let mut mac = Hmac::<sha2::Sha256>::new_from_slice(b"line-secret").unwrap();
+            mac.update(body.as_bytes());
+            let signature = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, mac.finalize().into_bytes());
+            let mut headers = axum::http::HeaderMap::new();
+            headers.insert("x-line-signature", signature.parse().unwrap());
+            handle_line_webhook(State(state.clone()), headers, body)
+        };
+
+        send("/incognito hello").await;
+        assert_eq!(history(&state, "line:U1").await, 0);
+        send("/incognito on").await;
+        send("hello").await;
+        assert_eq!(history(&state, "line:U1").await, 0);
+        send("/incognito off").await;
+        send("hello").await;
+        assert_eq!(history(&state, "line:U1").await, 2);
+    }
+}
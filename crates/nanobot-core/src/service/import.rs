@@ -0,0 +1,574 @@
+//! Import chat history from other assistants (`chatweb import`).
+//!
+//! Supported exports:
+//! - ChatGPT `conversations.json` (message tree under `mapping`; the branch
+//!   ending at `current_node` is imported)
+//! - Claude `conversations.json` (`chat_messages` with `sender` human/assistant)
+//! - Generic OpenAI-format logs: `{"messages": [...]}`, an array of those,
+//!   a bare message array, or JSONL with one conversation per line
+//!
+//! Each conversation becomes a session keyed `<prefix>:<id>`, and a one-line
+//! summary per conversation is appended to long-term memory.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use serde_json::Value;
+
+use crate::memory::backend::MemoryBackend;
+use crate::session::store::SessionStore;
+
+/// Session metadata key recording where an imported session came from.
+pub const IMPORTED_FROM_KEY: &str = "imported_from";
+
+/// Export format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    ChatGpt,
+    Claude,
+    OpenAi,
+}
+
+impl ImportSource {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "chatgpt" => Some(ImportSource::ChatGpt),
+            "claude" => Some(ImportSource::Claude),
+            "openai" | "generic" => Some(ImportSource::OpenAi),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            ImportSource::ChatGpt => "chatgpt",
+            ImportSource::Claude => "claude",
+            ImportSource::OpenAi => "openai",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImportSource::ChatGpt => "ChatGPT",
+            ImportSource::Claude => "Claude",
+            ImportSource::OpenAi => "OpenAI-format log",
+        }
+    }
+}
+
+/// A message from an export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedMessage {
+    pub role: String,
+    pub content: String,
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// A conversation from an export.
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    pub id: String,
+    pub title: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub messages: Vec<ImportedMessage>,
+}
+
+impl Conversation {
+    /// First user message, for previews.
+    pub fn first_prompt(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .find(|m| m.role == "user")
+            .map(|m| m.content.as_str())
+    }
+}
+
+/// How conversations map onto sessions and memory.
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    /// Session key prefix (default `import:<source>`).
+    pub prefix: String,
+    /// Skip conversations created before this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Import at most this many conversations (newest first).
+    pub limit: Option<usize>,
+    /// Keep system messages (dropped by default; tool messages always are).
+    pub include_system: bool,
+    /// Append a summary of each conversation to long-term memory.
+    pub memory: bool,
+    /// Replace sessions that already exist instead of skipping them.
+    pub overwrite: bool,
+}
+
+impl ImportOptions {
+    pub fn new(source: ImportSource) -> Self {
+        Self {
+            prefix: format!("import:{}", source.id()),
+            since: None,
+            limit: None,
+            include_system: false,
+            memory: true,
+            overwrite: false,
+        }
+    }
+}
+
+/// Outcome of [`import`].
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Session keys written.
+    pub imported: Vec<String>,
+    /// Session keys left alone because they already existed.
+    pub existing: Vec<String>,
+    pub messages: usize,
+}
+
+/// Parse an export file's contents.
+pub fn parse(source: ImportSource, raw: &str) -> Result<Vec<Conversation>> {
+    match source {
+        ImportSource::ChatGpt => parse_chatgpt(&serde_json::from_str(raw).context("invalid JSON")?),
+        ImportSource::Claude => parse_claude(&serde_json::from_str(raw).context("invalid JSON")?),
+        ImportSource::OpenAi => parse_openai(raw),
+    }
+}
+
+/// Apply filters and role mapping: drops tool (and, unless asked, system)
+/// messages and empty conversations, then applies `since` and `limit`.
+/// Newest conversations come first.
+pub fn select(conversations: Vec<Conversation>, options: &ImportOptions) -> Vec<Conversation> {
+    let mut selected: Vec<Conversation> = conversations
+        .into_iter()
+        .filter(|c| match (options.since, c.created_at) {
+            (Some(since), Some(at)) => at >= since,
+            _ => true,
+        })
+        .map(|mut c| {
+            c.messages.retain(|m| match m.role.as_str() {
+                "user" | "assistant" => true,
+                "system" => options.include_system,
+                _ => false,
+            });
+            c
+        })
+        .filter(|c| c.messages.iter().any(|m| m.role == "user"))
+        .collect();
+    selected.sort_by_key(|c| std::cmp::Reverse(c.created_at));
+    if let Some(limit) = options.limit {
+        selected.truncate(limit);
+    }
+    selected
+}
+
+/// Parse a `--since` date (`YYYY-MM-DD`, midnight UTC).
+pub fn parse_date(s: &str) -> Option<DateTime<Utc>> {
+    chrono::NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)
+        .map(|dt| dt.and_utc())
+}
+
+/// Session key for an imported conversation.
+pub fn session_key(options: &ImportOptions, conversation: &Conversation) -> String {
+    format!("{}:{}", options.prefix, conversation.id)
+}
+
+/// Write selected conversations to `sessions` (and a summary to `memory`).
+pub fn import(
+    source: ImportSource,
+    conversations: &[Conversation],
+    options: &ImportOptions,
+    sessions: &mut dyn SessionStore,
+    memory: Option<&dyn MemoryBackend>,
+) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut summary = Vec::new();
+
+    for conversation in conversations {
+        let key = session_key(options, conversation);
+        let session = sessions.get_or_create(&key);
+        if !session.messages.is_empty() && !options.overwrite {
+            report.existing.push(key);
+            continue;
+        }
+
+        session.clear();
+        for m in &conversation.messages {
+            session.add_message(&m.role, &m.content);
+            if let (Some(ts), Some(last)) = (m.timestamp, session.messages.last_mut()) {
+                last.timestamp = Some(ts.to_rfc3339());
+            }
+        }
+        if let Some(created) = conversation.created_at {
+            session.created_at = created;
+        }
+        session
+            .metadata
+            .insert(IMPORTED_FROM_KEY.to_string(), serde_json::json!(source.id()));
+        session
+            .metadata
+            .insert("title".to_string(), serde_json::json!(conversation.title));
+        sessions.save_by_key(&key);
+
+        report.messages += conversation.messages.len();
+        summary.push(format!(
+            "- {}: {} ({} messages, session `{}`)",
+            conversation
+                .created_at
+                .map(|t| t.format("%Y-%m-%d").to_string())
+                .unwrap_or_else(|| "unknown date".to_string()),
+            conversation.title,
+            conversation.messages.len(),
+            key
+        ));
+        report.imported.push(key);
+    }
+
+    if options.memory && !summary.is_empty() {
+        if let Some(memory) = memory {
+            let mut long_term = memory.read_long_term();
+            if !long_term.is_empty() && !long_term.ends_with('\n') {
+                long_term.push('\n');
+            }
+            long_term.push_str(&format!(
+                "\n## Imported from {} ({})\n\n{}\n",
+                source.label(),
+                crate::util::today_date(),
+                summary.join("\n")
+            ));
+            memory.write_long_term(&long_term);
+        }
+    }
+
+    report
+}
+
+// ---------------------------------------------------------------------------
+// Format parsers
+// ---------------------------------------------------------------------------
+
+fn as_array(value: &Value) -> Result<&Vec<Value>> {
+    value
+        .as_array()
+        .context("expected a JSON array of conversations")
+}
+
+/// Unix seconds (possibly fractional) or an RFC 3339 string.
+fn parse_time(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    match value? {
+        Value::Number(n) => {
+            let secs = n.as_f64()?;
+            Utc.timestamp_millis_opt((secs * 1000.0) as i64).single()
+        }
+        Value::String(s) => DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc)),
+        _ => None,
+    }
+}
+
+fn title_or_default(title: Option<&str>, messages: &[ImportedMessage]) -> String {
+    match title.map(str::trim).filter(|t| !t.is_empty()) {
+        Some(t) => t.to_string(),
+        None => messages
+            .iter()
+            .find(|m| m.role == "user")
+            .map(|m| crate::util::truncate_string(m.content.lines().next().unwrap_or(""), 60, "…"))
+            .unwrap_or_else(|| "Untitled".to_string()),
+    }
+}
+
+/// Text of an OpenAI-style `content`: a string or an array of parts.
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| match p {
+                Value::String(s) => Some(s.as_str()),
+                _ if p.get("type").and_then(|t| t.as_str()).unwrap_or("text") == "text" => {
+                    p.get("text").and_then(|t| t.as_str())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn parse_chatgpt(root: &Value) -> Result<Vec<Conversation>> {
+    let mut conversations = Vec::new();
+    for conv in as_array(root)? {
+        let Some(mapping) = conv.get("mapping").and_then(|m| m.as_object()) else {
+            continue;
+        };
+        // Walk from the current node back to the root to get the visible branch
+        let mut node_id = conv
+            .get("current_node")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        let mut chain = Vec::new();
+        while let Some(id) = node_id.take() {
+            let Some(node) = mapping.get(&id) else { break };
+            if chain.len() > mapping.len() {
+                break; // cycle guard
+            }
+            chain.push(node);
+            node_id = node.get("parent").and_then(|p| p.as_str()).map(str::to_string);
+        }
+        chain.reverse();
+
+        let messages: Vec<ImportedMessage> = chain
+            .iter()
+            .filter_map(|node| {
+                let msg = node.get("message").filter(|m| !m.is_null())?;
+                let hidden = msg
+                    .pointer("/metadata/is_visually_hidden_from_conversation")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                if hidden {
+                    return None;
+                }
+                let role = msg.pointer("/author/role").and_then(|r| r.as_str())?;
+                let content = msg.get("content")?;
+                let text = match content.get("content_type").and_then(|t| t.as_str()) {
+                    Some("text") | Some("multimodal_text") | None => {
+                        content.get("parts").map(content_text).unwrap_or_default()
+                    }
+                    Some("code") => content.get("text").and_then(|t| t.as_str()).unwrap_or("").to_string(),
+                    _ => String::new(),
+                };
+                let text = text.trim();
+                (!text.is_empty()).then(|| ImportedMessage {
+                    role: role.to_string(),
+                    content: text.to_string(),
+                    timestamp: parse_time(msg.get("create_time")),
+                })
+            })
+            .collect();
+
+        let id = conv
+            .get("conversation_id")
+            .or_else(|| conv.get("id"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{}", conversations.len() + 1));
+        conversations.push(Conversation {
+            title: title_or_default(conv.get("title").and_then(|t| t.as_str()), &messages),
+            id,
+            created_at: parse_time(conv.get("create_time")),
+            messages,
+        });
+    }
+    Ok(conversations)
+}
+
+fn parse_claude(root: &Value) -> Result<Vec<Conversation>> {
+    let mut conversations = Vec::new();
+    for conv in as_array(root)? {
+        let Some(chat) = conv.get("chat_messages").and_then(|m| m.as_array()) else {
+            continue;
+        };
+        let messages: Vec<ImportedMessage> = chat
+            .iter()
+            .filter_map(|m| {
+                let role = match m.get("sender").and_then(|s| s.as_str())? {
+                    "human" => "user",
+                    "assistant" => "assistant",
+                    _ => return None,
+                };
+                let text = m
+                    .get("text")
+                    .and_then(|t| t.as_str())
+                    .filter(|t| !t.trim().is_empty())
+                    .map(str::to_string)
+                    .or_else(|| m.get("content").map(content_text))?;
+                let text = text.trim();
+                (!text.is_empty()).then(|| ImportedMessage {
+                    role: role.to_string(),
+                    content: text.to_string(),
+                    timestamp: parse_time(m.get("created_at")),
+                })
+            })
+            .collect();
+
+        let id = conv
+            .get("uuid")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{}", conversations.len() + 1));
+        conversations.push(Conversation {
+            title: title_or_default(conv.get("name").and_then(|t| t.as_str()), &messages),
+            id,
+            created_at: parse_time(conv.get("created_at")),
+            messages,
+        });
+    }
+    Ok(conversations)
+}
+
+fn parse_openai(raw: &str) -> Result<Vec<Conversation>> {
+    let docs: Vec<Value> = match serde_json::from_str::<Value>(raw) {
+        Ok(Value::Array(items)) if items.first().is_some_and(|i| i.get("role").is_some()) => {
+            vec![serde_json::json!({ "messages": items })]
+        }
+        Ok(Value::Array(items)) => items,
+        Ok(doc) => vec![doc],
+        // JSONL: one conversation per line
+        Err(_) => raw
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .enumerate()
+            .map(|(i, l)| serde_json::from_str(l).with_context(|| format!("line {}: invalid JSON", i + 1)))
+            .collect::<Result<_>>()?,
+    };
+
+    let mut conversations = Vec::new();
+    for (i, doc) in docs.iter().enumerate() {
+        let Some(items) = doc.get("messages").and_then(|m| m.as_array()) else {
+            bail!("conversation {} has no \"messages\" array", i + 1);
+        };
+        let messages: Vec<ImportedMessage> = items
+            .iter()
+            .filter_map(|m| {
+                let role = m.get("role").and_then(|r| r.as_str())?;
+                let text = m.get("content").map(content_text).unwrap_or_default();
+                let text = text.trim();
+                (!text.is_empty()).then(|| ImportedMessage {
+                    role: role.to_string(),
+                    content: text.to_string(),
+                    timestamp: parse_time(m.get("timestamp").or_else(|| m.get("created_at"))),
+                })
+            })
+            .collect();
+        conversations.push(Conversation {
+            id: doc
+                .get("id")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("{}", i + 1)),
+            title: title_or_default(doc.get("title").and_then(|t| t.as_str()), &messages),
+            created_at: parse_time(doc.get("created_at").or_else(|| doc.get("created"))),
+            messages,
+        });
+    }
+    Ok(conversations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::file_backend::FileMemoryBackend;
+    use crate::session::Session;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct MemStore {
+        sessions: HashMap<String, Session>,
+    }
+
+    impl SessionStore for MemStore {
+        fn get_or_create(&mut self, key: &str) -> &mut Session {
+            self.sessions.entry(key.to_string()).or_insert_with(|| Session::new(key))
+        }
+        fn refresh(&mut self, key: &str) -> &mut Session {
+            self.get_or_create(key)
+        }
+        fn save(&self, _session: &Session) {}
+        fn save_by_key(&self, _key: &str) {}
+        fn delete(&mut self, key: &str) -> bool {
+            self.sessions.remove(key).is_some()
+        }
+        fn list_sessions(&self) -> Vec<Value> {
+            Vec::new()
+        }
+    }
+
+    const CHATGPT: &str = r#"[{
+        "title": "Rust lifetimes",
+        "create_time": 1700000000.5,
+        "conversation_id": "c1",
+        "current_node": "n4",
+        "mapping": {
+            "root": {"id": "root", "message": null, "parent": null, "children": ["n1"]},
+            "n1": {"id": "n1", "parent": "root", "message": {"author": {"role": "system"}, "content": {"content_type": "text", "parts": ["You are ChatGPT"]}}},
+            "n2": {"id": "n2", "parent": "n1", "message": {"author": {"role": "user"}, "create_time": 1700000001, "content": {"content_type": "text", "parts": ["What is 'a?"]}}},
+            "n3": {"id": "n3", "parent": "n2", "message": {"author": {"role": "assistant"}, "content": {"content_type": "text", "parts": ["Old answer"]}}},
+            "n4": {"id": "n4", "parent": "n2", "message": {"author": {"role": "assistant"}, "content": {"content_type": "text", "parts": ["A lifetime."]}}}
+        }
+    }]"#;
+
+    #[test]
+    fn test_parse_chatgpt_follows_current_branch() {
+        let convs = parse(ImportSource::ChatGpt, CHATGPT).unwrap();
+        assert_eq!(convs.len(), 1);
+        let c = &convs[0];
+        assert_eq!(c.id, "c1");
+        assert_eq!(c.title, "Rust lifetimes");
+        let texts: Vec<&str> = c.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(texts, vec!["You are ChatGPT", "What is 'a?", "A lifetime."]);
+        assert!(c.messages[1].timestamp.is_some());
+
+        let selected = select(convs, &ImportOptions::new(ImportSource::ChatGpt));
+        assert_eq!(selected[0].messages.len(), 2); // system dropped
+    }
+
+    #[test]
+    fn test_parse_claude_and_openai() {
+        let claude = r#"[{"uuid": "u1", "name": "", "created_at": "2025-01-02T03:04:05Z",
+            "chat_messages": [
+                {"sender": "human", "text": "Plan a trip to Kyoto", "created_at": "2025-01-02T03:04:05Z"},
+                {"sender": "assistant", "text": "", "content": [{"type": "text", "text": "Sure!"}]}
+            ]}]"#;
+        let convs = parse(ImportSource::Claude, claude).unwrap();
+        assert_eq!(convs[0].title, "Plan a trip to Kyoto");
+        assert_eq!(convs[0].messages[1].content, "Sure!");
+        assert_eq!(convs[0].messages[1].role, "assistant");
+
+        let jsonl = "{\"messages\":[{\"role\":\"user\",\"content\":\"hi\"},{\"role\":\"assistant\",\"content\":[{\"type\":\"text\",\"text\":\"hello\"}]}]}\n\
+                     {\"id\":\"x\",\"messages\":[{\"role\":\"user\",\"content\":\"again\"}]}\n";
+        let convs = parse(ImportSource::OpenAi, jsonl).unwrap();
+        assert_eq!(convs.len(), 2);
+        assert_eq!(convs[0].messages[1].content, "hello");
+        assert_eq!(convs[1].id, "x");
+
+        let bare = r#"[{"role":"user","content":"solo"}]"#;
+        assert_eq!(parse(ImportSource::OpenAi, bare).unwrap()[0].messages.len(), 1);
+        assert!(parse(ImportSource::OpenAi, r#"{"foo": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_import_writes_sessions_and_memory() {
+        let tmp = tempfile::tempdir().unwrap();
+        let memory = FileMemoryBackend::new(tmp.path());
+        let mut store = MemStore::default();
+        let options = ImportOptions::new(ImportSource::ChatGpt);
+        let convs = select(parse(ImportSource::ChatGpt, CHATGPT).unwrap(), &options);
+
+        let report = import(ImportSource::ChatGpt, &convs, &options, &mut store, Some(&memory));
+        assert_eq!(report.imported, vec!["import:chatgpt:c1"]);
+        assert_eq!(report.messages, 2);
+        let session = store.get_or_create("import:chatgpt:c1");
+        assert_eq!(session.messages[0].content, "What is 'a?");
+        assert_eq!(session.metadata[IMPORTED_FROM_KEY], "chatgpt");
+        assert!(memory.read_long_term().contains("Rust lifetimes (2 messages"));
+
+        // Re-running skips what is already there
+        let report = import(ImportSource::ChatGpt, &convs, &options, &mut store, Some(&memory));
+        assert!(report.imported.is_empty());
+        assert_eq!(report.existing.len(), 1);
+    }
+
+    #[test]
+    fn test_select_since_and_limit() {
+        let conv = |id: &str, day: u32| Conversation {
+            id: id.to_string(),
+            title: id.to_string(),
+            created_at: Utc.with_ymd_and_hms(2025, 1, day, 0, 0, 0).single(),
+            messages: vec![ImportedMessage { role: "user".into(), content: "q".into(), timestamp: None }],
+        };
+        let mut options = ImportOptions::new(ImportSource::OpenAi);
+        options.since = parse_date("2025-01-02");
+        options.limit = Some(1);
+        let selected = select(vec![conv("a", 1), conv("b", 2), conv("c", 3)], &options);
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].id, "c");
+    }
+}
@@ -8,6 +8,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::util::http::{client_for, ClientClass};
+
 /// Safely truncate a string to at most `max_bytes`, respecting UTF-8 char boundaries.
 fn truncate_str(s: &str, max_bytes: usize) -> &str {
     if s.len() <= max_bytes {
@@ -62,6 +64,7 @@ impl ToolRegistry {
         let mut tools: Vec<Box<dyn Tool>> = vec![
             Box::new(WebSearchTool),
             Box::new(WebFetchTool),
+            Box::new(WebCrawlTool(crate::tool::crawl::WebCrawlTool::new(30_000))),
             Box::new(CalculatorTool),
             Box::new(WeatherTool),
             Box::new(TranslateTool),
@@ -93,8 +96,8 @@ impl ToolRegistry {
             Box::new(GitDiffTool),
             Box::new(GitCommitTool),
             // Quality assurance tools
-            Box::new(RunLinterTool),
-            Box::new(RunTestsTool),
+            Box::new(RunLinterTool::new()),
+            Box::new(RunTestsTool::new()),
         ];
 
         // Register GitHub tools (read works on public repos without token)
@@ -151,7 +154,7 @@ impl ToolRegistry {
             }
 
             // YouTube transcript and arXiv are always available (no API key needed)
-            tools.push(Box::new(YouTubeTranscriptTool));
+            tools.push(Box::new(YouTubeTranscriptTool(crate::tool::youtube::YouTubeTranscriptTool)));
             tools.push(Box::new(ArxivSearchTool));
 
             // memory_log and knowledge_graph are always available (DynamoDB-backed, user-scoped)
@@ -215,6 +218,26 @@ impl ToolRegistry {
         self.tools.push(Box::new(MultiAgentTool { provider }));
     }
 
+    /// Add the `extract` tool, which asks the provider's default model for
+    /// schema-shaped output (see [`crate::tool::extract`]).
+    pub fn add_extract_tool(&mut self, provider: Arc<dyn crate::provider::LlmProvider>) {
+        let model = provider.default_model().to_string();
+        self.tools.push(Box::new(ExtractTool(crate::tool::extract::ExtractTool::new(provider, model))));
+    }
+
+    /// Add (or replace) the `expense` tool keeping receipts in `ledger`
+    /// (see [`crate::expense`]).
+    pub fn add_expense_tool(
+        &mut self,
+        provider: Arc<dyn crate::provider::LlmProvider>,
+        ledger: Arc<dyn crate::expense::ExpenseLedger>,
+    ) {
+        let model = provider.default_model().to_string();
+        let ocr = Arc::new(crate::tool::ocr::Ocr::new(std::path::PathBuf::from("."), &Default::default()));
+        self.tools.retain(|t| t.name() != "expense");
+        self.tools.push(Box::new(ExpenseTool(crate::tool::expense::ExpenseTool::new(ledger, provider, model, ocr))));
+    }
+
     /// Register multiple tools at once.
     pub fn register_all(&mut self, tools: Vec<Box<dyn Tool>>) {
         self.tools.extend(tools);
@@ -226,9 +249,14 @@ impl ToolRegistry {
     }
 
     /// Execute a tool by name with a timeout.
-    /// Most tools get 25 s; `improve_project` gets 300 s (inner LLM loop).
+    /// Most tools get 25 s; `improve_project` gets 300 s (inner LLM loop),
+    /// `web_crawl` and `extract` 90 s (many pages / retried LLM calls).
     pub async fn execute(&self, name: &str, arguments: &HashMap<String, serde_json::Value>) -> String {
-        let timeout_secs = if name == "improve_project" { 300 } else { 25 };
+        let timeout_secs = match name {
+            "improve_project" => 300,
+            "web_crawl" | "extract" | "youtube_transcript" => 90,
+            _ => 25,
+        };
         for tool in &self.tools {
             if tool.name() == name {
                 match tokio::time::timeout(
@@ -2082,9 +2110,9 @@ pub(crate) async fn execute_web_fetch(url: &str) -> String {
             match resp.text().await {
                 Ok(body) => {
                     tracing::info!("web_fetch: direct fetch got {} bytes", body.len());
-                    let text = strip_html_tags(&body);
+                    let text = crate::util::markdown::readable(&body, reqwest::Url::parse(url).ok().as_ref()).markdown;
                     let cleaned: String = text.lines()
-                        .map(|l| l.trim())
+                        .map(|l| l.trim_end())
                         .filter(|l| !l.is_empty())
                         .collect::<Vec<_>>()
                         .join("\n");
@@ -2115,7 +2143,7 @@ fn execute_calculator(expression: &str) -> String {
 
 /// Weather using Open-Meteo free API (no key required).
 async fn execute_weather(location: &str) -> String {
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let encoded = urlencoding::encode(location);
 
     // Geocode with multiple language attempts for broad coverage:
@@ -2303,36 +2331,12 @@ async fn execute_wikipedia(query: &str, lang: &str) -> String {
 /// Get current datetime in specified timezone.
 fn execute_datetime(tz: &str) -> String {
     let now = chrono::Utc::now();
-    // Map common timezone names to UTC offsets
-    let offset_hours: i32 = match tz.to_lowercase().as_str() {
-        "jst" | "asia/tokyo" | "japan" => 9,
-        "kst" | "asia/seoul" | "korea" => 9,
-        "cst" | "asia/shanghai" | "china" | "asia/taipei" => 8,
-        "ist" | "asia/kolkata" | "india" => 5,  // +5:30 approximated
-        "gmt" | "utc" | "europe/london" => 0,
-        "cet" | "europe/paris" | "europe/berlin" => 1,
-        "eet" | "europe/athens" => 2,
-        "est" | "america/new_york" | "us/eastern" => -5,
-        "cst_us" | "america/chicago" | "us/central" => -6,
-        "mst" | "america/denver" | "us/mountain" => -7,
-        "pst" | "america/los_angeles" | "us/pacific" => -8,
-        "hst" | "pacific/honolulu" | "hawaii" => -10,
-        "aest" | "australia/sydney" => 11,
-        "nzst" | "pacific/auckland" => 13,
-        _ => {
-            // Try to parse as +N or -N
-            tz.parse::<i32>().unwrap_or_default()
-        }
-    };
-
-    let offset = chrono::FixedOffset::east_opt(offset_hours * 3600).unwrap_or_else(|| chrono::FixedOffset::east_opt(0).unwrap());
-    let local = now.with_timezone(&offset);
+    let local = now.with_timezone(&crate::util::tz::parse_or_utc(tz));
 
     format!(
-        "Current time in {} (UTC{}{}):\n\nDate: {}\nTime: {}\nDay: {}\nUnix timestamp: {}",
+        "Current time in {} (UTC{}):\n\nDate: {}\nTime: {}\nDay: {}\nUnix timestamp: {}",
         tz,
-        if offset_hours >= 0 { "+" } else { "" },
-        offset_hours,
+        local.format("%:z"),
         local.format("%Y-%m-%d"),
         local.format("%H:%M:%S"),
         local.format("%A"),
@@ -2653,7 +2657,7 @@ async fn google_refresh_access_token(refresh_token: &str) -> Result<String, Stri
         return Err("Google OAuth not configured".to_string());
     }
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let resp = client
         .post("https://oauth2.googleapis.com/token")
         .form(&[
@@ -2717,7 +2721,7 @@ async fn get_sa_access_token(sa_key_json: &str) -> Result<String, String> {
     let jwt = format!("{unsigned}.{signature}");
 
     // Exchange JWT for access token
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let resp = client
         .post("https://oauth2.googleapis.com/token")
         .form(&[
@@ -2764,7 +2768,7 @@ async fn execute_google_calendar(action: &str, params: &HashMap<String, serde_js
         }
     };
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
 
     match action {
         "list" => {
@@ -2854,22 +2858,27 @@ async fn execute_google_calendar(action: &str, params: &HashMap<String, serde_js
     }
 }
 
+/// Google access token from the user's refresh token, or the service
+/// account when there is none.
+pub(crate) async fn google_access_token(refresh_token: Option<&str>) -> Result<String, String> {
+    match refresh_token.filter(|t| !t.is_empty()) {
+        Some(token) => google_refresh_access_token(token)
+            .await
+            .map_err(|e| format!("Error getting Google access token: {e}")),
+        None => get_google_refresh_token_for_session()
+            .await
+            .map_err(|e| format!("Gmail requires login with Google. Error: {e}")),
+    }
+}
+
 /// Execute Gmail tool.
 async fn execute_gmail(action: &str, params: &HashMap<String, serde_json::Value>, injected_refresh_token: Option<&str>) -> String {
-    let refresh_token = injected_refresh_token.unwrap_or("").to_string();
-    let access_token = if !refresh_token.is_empty() {
-        match google_refresh_access_token(&refresh_token).await {
-            Ok(t) => t,
-            Err(e) => return format!("Error getting Google access token: {e}"),
-        }
-    } else {
-        match get_google_refresh_token_for_session().await {
-            Ok(t) => t,
-            Err(e) => return format!("Gmail requires login with Google. Error: {e}"),
-        }
+    let access_token = match google_access_token(injected_refresh_token).await {
+        Ok(t) => t,
+        Err(e) => return e,
     };
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
 
     match action {
         "search" => {
@@ -3062,19 +3071,10 @@ fn extract_gmail_body(data: &serde_json::Value) -> String {
 // Sandbox tools — code execution and file operations in /tmp/sandbox/
 // ---------------------------------------------------------------------------
 
-/// Code execution tool — runs shell commands (with optional Python/Node.js) in a sandbox.
+/// Code execution tool — runs shell commands, Python or JavaScript in a sandbox.
 pub struct CodeExecuteTool;
 
 impl CodeExecuteTool {
-    /// Check if an interpreter is available on the system.
-    fn interpreter_available(cmd: &str) -> bool {
-        std::process::Command::new("which")
-            .arg(cmd)
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false)
-    }
-
     /// Run a command in the sandbox with timeout.
     async fn run_in_sandbox(cmd: &str, args: &[&str], sandbox_dir: &str) -> String {
         std::fs::create_dir_all(sandbox_dir).ok();
@@ -3126,7 +3126,8 @@ impl Tool for CodeExecuteTool {
         "Execute code in a sandboxed environment. \
          IMPORTANT: Use language='shell' for best compatibility — it works everywhere. \
          Shell supports awk, sed, bc, and standard Unix tools for calculations and text processing. \
-         Python and Node.js are available only if installed on the server. \
+         Python and JavaScript run confined with a 10s limit and no network, writing only inside the sandbox; \
+         the value of the last expression is printed. \
          Use file_write to create script files, then execute them with shell. \
          The sandbox persists files across calls within the same session. \
          Example shell math: echo $((1+2+3)) or echo '1+2+3' | bc or awk 'BEGIN{for(i=1;i<=100;i++)s+=i;print s}'"
@@ -3137,8 +3138,8 @@ impl Tool for CodeExecuteTool {
             "properties": {
                 "language": {
                     "type": "string",
-                    "enum": ["shell", "python", "nodejs"],
-                    "description": "Language to use. 'shell' is always available. 'python' and 'nodejs' may not be installed."
+                    "enum": ["shell", "python", "javascript"],
+                    "description": "Language to use. 'python' needs python3 on a Linux server; 'javascript' needs a build with the js-sandbox feature."
                 },
                 "code": {
                     "type": "string",
@@ -3176,35 +3177,20 @@ impl Tool for CodeExecuteTool {
             "shell" => {
                 Self::run_in_sandbox("sh", &["-c", code], sandbox_dir).await
             }
-            "python" => {
-                // Try python3, then python, then fallback to writing a script and running via sh
-                for interpreter in &["python3", "python"] {
-                    if Self::interpreter_available(interpreter) {
-                        return Self::run_in_sandbox(interpreter, &["-c", code], sandbox_dir).await;
-                    }
-                }
-                // Fallback: write to file and try to run
-                let script_path = format!("{}/_.py", sandbox_dir);
-                std::fs::create_dir_all(sandbox_dir).ok();
-                if let Err(e) = std::fs::write(&script_path, code) {
-                    return format!("[TOOL_ERROR] Failed to write script: {e}");
-                }
-                // Try common Python paths
-                for path in &["/usr/bin/python3", "/usr/local/bin/python3", "/opt/python/bin/python3"] {
-                    if std::path::Path::new(path).exists() {
-                        return Self::run_in_sandbox(path, &[&script_path], sandbox_dir).await;
-                    }
-                }
-                "[TOOL_ERROR] Python is not available on this server. Please use language='shell' instead. \
-                 Shell supports awk for calculations: awk 'BEGIN{for(i=0;i<20;i++){if(i<2)a[i]=i;else a[i]=a[i-1]+a[i-2];print a[i]}}'".to_string()
-            }
-            "nodejs" => {
-                if Self::interpreter_available("node") {
-                    return Self::run_in_sandbox("node", &["-e", code], sandbox_dir).await;
+            "python" | "javascript" | "nodejs" => {
+                // Constrained runtime (see tool::code): files only inside the session sandbox, no network
+                let limits = crate::tool::code::SandboxLimits {
+                    timeout: std::time::Duration::from_secs(10),
+                    workdir: Some(std::path::PathBuf::from(sandbox_dir)),
+                    ..Default::default()
+                };
+                let output = crate::tool::code::evaluate(language, code, &limits).await;
+                match output.error {
+                    Some(ref e) if output.stdout.is_empty() => format!("[TOOL_ERROR] {}", e.trim_end()),
+                    _ => output.render(limits.max_output),
                 }
-                "[TOOL_ERROR] Node.js is not available on this server. Please use language='shell' instead.".to_string()
             }
-            _ => format!("[TOOL_ERROR] Unsupported language: {language}. Use 'shell', 'python', or 'nodejs'."),
+            _ => format!("[TOOL_ERROR] Unsupported language: {language}. Use 'shell', 'python', or 'javascript'."),
         }
     }
 }
@@ -4113,7 +4099,7 @@ async fn execute_music_generate(prompt: &str, style: &str, instrumental: bool) -
         format!("{} (style: {})", prompt, style)
     };
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let body = serde_json::json!({
         "prompt": full_prompt,
         "customMode": false,
@@ -4209,7 +4195,7 @@ impl Tool for ImageAnalyzeTool {
     }
 }
 
-async fn execute_image_analyze(url: &str, question: &str) -> String {
+pub(crate) async fn execute_image_analyze(url: &str, question: &str) -> String {
     let api_key = match std::env::var("OPENAI_API_KEY") {
         Ok(k) if !k.is_empty() => k,
         _ => {
@@ -4220,7 +4206,7 @@ async fn execute_image_analyze(url: &str, question: &str) -> String {
             }
         }
     };
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let body = serde_json::json!({
         "model": "gpt-4o-mini",
         "messages": [{
@@ -4257,7 +4243,7 @@ async fn execute_image_analyze(url: &str, question: &str) -> String {
 }
 
 async fn execute_image_analyze_openrouter(url: &str, question: &str, api_key: &str) -> String {
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let body = serde_json::json!({
         "model": "openai/gpt-4o-mini",
         "messages": [{
@@ -4322,7 +4308,7 @@ impl Tool for PdfAnalyzeTool {
 async fn execute_pdf_analyze(url: &str, question: &str) -> String {
     // Use Jina Reader to extract PDF text (works for most PDFs)
     let jina_url = format!("https://r.jina.ai/{}", url);
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let text = match client.get(&jina_url)
         .header("Accept", "text/plain")
         .header("User-Agent", "Mozilla/5.0")
@@ -4387,7 +4373,7 @@ async fn execute_video_generate(prompt: &str, duration: &str, mode: &str) -> Str
     };
     let api_base = std::env::var("KLING_API_BASE").unwrap_or_else(|_| "https://api.klingai.com".to_string());
 
-    let client = reqwest::Client::new();
+    let client = client_for(ClientClass::General);
     let body = serde_json::json!({
         "prompt": prompt,
         "duration": duration,
@@ -4515,7 +4501,7 @@ impl Tool for WebhookTriggerTool {
         if !value2.is_empty() { body.insert("value2".to_string(), serde_json::json!(value2)); }
         if !value3.is_empty() { body.insert("value3".to_string(), serde_json::json!(value3)); }
 
-        let client = reqwest::Client::new();
+        let client = client_for(ClientClass::General);
         match client.post(&url)
             .header("Content-Type", "application/json")
             .json(&serde_json::Value::Object(body))
@@ -4570,7 +4556,7 @@ impl Tool for SlackTool {
 
         let action = params.get("action").and_then(|v| v.as_str()).unwrap_or("read");
         let channel = params.get("channel").and_then(|v| v.as_str()).unwrap_or("");
-        let client = reqwest::Client::new();
+        let client = client_for(ClientClass::General);
 
         match action {
             "send" => {
@@ -4655,134 +4641,6 @@ impl Tool for SlackTool {
     }
 }
 
-// ─── YouTube Transcript Tool ───
-
-pub struct YouTubeTranscriptTool;
-
-#[async_trait::async_trait]
-impl Tool for YouTubeTranscriptTool {
-    fn name(&self) -> &str { "youtube_transcript" }
-    fn description(&self) -> &str {
-        "Fetch the transcript (subtitles/captions) of a YouTube video for summarization or analysis. \
-         Provide a YouTube URL or video ID."
-    }
-    fn parameters(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "url": { "type": "string", "description": "YouTube video URL or video ID (e.g., 'dQw4w9WgXcQ' or 'https://youtu.be/dQw4w9WgXcQ')" },
-                "language": { "type": "string", "description": "Preferred language (e.g., 'ja', 'en'). Default: auto" }
-            },
-            "required": ["url"]
-        })
-    }
-    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
-        let url = params.get("url").and_then(|v| v.as_str()).unwrap_or("");
-        if url.is_empty() {
-            return "[TOOL_ERROR] url required".to_string();
-        }
-
-        // Extract video ID
-        let video_id = if url.contains("youtube.com") || url.contains("youtu.be") {
-            if url.contains("v=") {
-                url.split("v=").nth(1).unwrap_or("").split('&').next().unwrap_or("")
-            } else if url.contains("youtu.be/") {
-                url.split("youtu.be/").nth(1).unwrap_or("").split('?').next().unwrap_or("")
-            } else {
-                url
-            }
-        } else {
-            url // assume it's a bare video ID
-        };
-
-        if video_id.is_empty() || video_id.len() < 5 {
-            return "[TOOL_ERROR] Could not extract valid video ID".to_string();
-        }
-
-        let lang = params.get("language").and_then(|v| v.as_str()).unwrap_or("ja");
-
-        // Try fetching transcript via YouTube's timedtext API
-        let client = reqwest::Client::new();
-
-        // First get the video page to extract caption tracks
-        let page_url = format!("https://www.youtube.com/watch?v={}", video_id);
-        let page_resp = client.get(&page_url)
-            .header("Accept-Language", format!("{},en;q=0.5", lang))
-            .send().await;
-
-        match page_resp {
-            Ok(resp) => {
-                let body = resp.text().await.unwrap_or_default();
-                // Extract captions URL from page source
-                if let Some(start) = body.find("\"captions\":") {
-                    let captions_json = &body[start..];
-                    if let Some(url_start) = captions_json.find("\"baseUrl\":\"") {
-                        let url_part = &captions_json[url_start + 11..];
-                        if let Some(url_end) = url_part.find('"') {
-                            let caption_url = url_part[..url_end]
-                                .replace("\\u0026", "&")
-                                .replace("\\/", "/");
-
-                            // Add language param
-                            let full_url = if caption_url.contains("lang=") {
-                                caption_url.to_string()
-                            } else {
-                                format!("{}&lang={}", caption_url, lang)
-                            };
-
-                            match client.get(&full_url).send().await {
-                                Ok(r) => {
-                                    let xml = r.text().await.unwrap_or_default();
-                                    // Parse XML transcript: extract text between <text> tags
-                                    let mut transcript = String::new();
-                                    for line in xml.split("<text") {
-                                        if let Some(content_start) = line.find('>') {
-                                            let content = &line[content_start + 1..];
-                                            if let Some(end) = content.find("</text>") {
-                                                let text = &content[..end]
-                                                    .replace("&amp;", "&")
-                                                    .replace("&lt;", "<")
-                                                    .replace("&gt;", ">")
-                                                    .replace("&quot;", "\"")
-                                                    .replace("&#39;", "'")
-                                                    .replace("\n", " ");
-                                                if !text.is_empty() {
-                                                    transcript.push_str(&text);
-                                                    transcript.push(' ');
-                                                }
-                                            }
-                                        }
-                                    }
-                                    if transcript.is_empty() {
-                                        format!("No transcript found for video {} in language '{}'", video_id, lang)
-                                    } else {
-                                        // Truncate if too long
-                                        if transcript.len() > 15000 {
-                                            let mut i = 15000;
-                                            while i > 0 && !transcript.is_char_boundary(i) { i -= 1; }
-                                            format!("{}... [truncated, {} chars total]", &transcript[..i], transcript.len())
-                                        } else {
-                                            transcript
-                                        }
-                                    }
-                                }
-                                Err(e) => format!("[TOOL_ERROR] Failed to fetch captions: {}", e),
-                            }
-                        } else {
-                            format!("No captions available for video {}", video_id)
-                        }
-                    } else {
-                        format!("No captions available for video {}", video_id)
-                    }
-                } else {
-                    format!("No captions found for video {}. The video may not have subtitles.", video_id)
-                }
-            }
-            Err(e) => format!("[TOOL_ERROR] Failed to fetch video page: {}", e),
-        }
-    }
-}
-
 // ─── Notion Integration Tool ───
 
 pub struct NotionTool;
@@ -4816,7 +4674,7 @@ impl Tool for NotionTool {
         };
 
         let action = params.get("action").and_then(|v| v.as_str()).unwrap_or("search");
-        let client = reqwest::Client::new();
+        let client = client_for(ClientClass::General);
         let headers = |c: reqwest::RequestBuilder| -> reqwest::RequestBuilder {
             c.header("Authorization", format!("Bearer {}", token))
              .header("Notion-Version", "2022-06-28")
@@ -4956,7 +4814,7 @@ impl Tool for ArxivSearchTool {
             urlencoding::encode(query), max_results
         );
 
-        let client = reqwest::Client::new();
+        let client = client_for(ClientClass::General);
         match client.get(&url).send().await {
             Ok(resp) => {
                 let xml = resp.text().await.unwrap_or_default();
@@ -5072,7 +4930,7 @@ impl Tool for DiscordTool {
                     body.insert("embeds".to_string(), serde_json::json!([serde_json::Value::Object(embed)]));
                 }
 
-                let client = reqwest::Client::new();
+                let client = client_for(ClientClass::General);
                 match client.post(&webhook_url)
                     .header("Content-Type", "application/json")
                     .json(&serde_json::Value::Object(body))
@@ -5131,7 +4989,7 @@ impl Tool for SpotifyTool {
         };
 
         // Get access token via client credentials flow
-        let client = reqwest::Client::new();
+        let client = client_for(ClientClass::General);
         let token_resp = client.post("https://accounts.spotify.com/api/token")
             .header("Content-Type", "application/x-www-form-urlencoded")
             .basic_auth(&client_id, Some(&client_secret))
@@ -5327,7 +5185,7 @@ impl Tool for PostgresTool {
 
                 // Execute via a lightweight HTTP request to the database URL
                 // If POSTGRES_URL is a REST API endpoint (e.g., PostgREST, Supabase)
-                let client = reqwest::Client::new();
+                let client = client_for(ClientClass::General);
                 if db_url.starts_with("http") {
                     // REST API mode: POST SQL query
                     match client.post(&db_url)
@@ -5379,7 +5237,7 @@ impl Tool for PostgresTool {
                 };
 
                 // Re-use the query path
-                let client = reqwest::Client::new();
+                let client = client_for(ClientClass::General);
                 if db_url.starts_with("http") {
                     match client.post(&db_url)
                         .header("Content-Type", "application/json")
@@ -6346,7 +6204,7 @@ mod tests {
         std::env::remove_var("POSTGRES_URL");
         let registry = ToolRegistry::with_builtins();
         // Count: check actual registered tools dynamically
-        let expected = if cfg!(feature = "http-api") { 32 } else { 29 };
+        let expected = if cfg!(feature = "http-api") { 35 } else { 30 };
         assert_eq!(registry.len(), expected);
         let defs = registry.get_definitions();
         let names: Vec<&str> = defs.iter()
@@ -6389,7 +6247,7 @@ mod tests {
         std::env::remove_var("SPOTIFY_CLIENT_ID");
         std::env::remove_var("POSTGRES_URL");
         let registry = ToolRegistry::with_builtins();
-        assert_eq!(registry.len(), 27); // 24 base + 1 github_read_file + 2 github_write tools
+        assert_eq!(registry.len(), 37); // 35 builtins + 2 github_write tools
         let defs = registry.get_definitions();
         let names: Vec<&str> = defs.iter()
             .filter_map(|t| t.pointer("/function/name").and_then(|v| v.as_str()))
@@ -6892,179 +6750,151 @@ impl Tool for GitCommitTool {
     }
 }
 
-// ---------------------------------------------------------------------------
-// Quality Assurance Tools
-// ---------------------------------------------------------------------------
+/// Adapter over [`crate::tool::crawl::WebCrawlTool`].
+struct WebCrawlTool(crate::tool::crawl::WebCrawlTool);
 
-struct RunLinterTool;
+#[async_trait]
+impl Tool for WebCrawlTool {
+    fn name(&self) -> &str {
+        crate::tool::Tool::name(&self.0)
+    }
+
+    fn description(&self) -> &str {
+        crate::tool::Tool::description(&self.0)
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        crate::tool::Tool::parameters(&self.0)
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        crate::tool::Tool::execute(&self.0, params).await
+    }
+}
+
+/// Adapter over [`crate::tool::youtube::YouTubeTranscriptTool`].
+struct YouTubeTranscriptTool(crate::tool::youtube::YouTubeTranscriptTool);
 
 #[async_trait]
-impl Tool for RunLinterTool {
+impl Tool for YouTubeTranscriptTool {
     fn name(&self) -> &str {
-        "run_linter"
+        crate::tool::Tool::name(&self.0)
     }
 
     fn description(&self) -> &str {
-        "Run code linter to detect syntax errors, style issues, and potential bugs. Supports multiple languages (clippy for Rust, eslint for JS/TS, pylint for Python, etc.). Returns list of issues found."
+        crate::tool::Tool::description(&self.0)
     }
 
     fn parameters(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "language": {
-                    "type": "string",
-                    "enum": ["rust", "javascript", "typescript", "python", "go"],
-                    "description": "Programming language to lint"
-                },
-                "path": {
-                    "type": "string",
-                    "description": "Optional: Path to file or directory to lint (defaults to current directory)"
-                },
-                "fix": {
-                    "type": "boolean",
-                    "description": "Auto-fix issues if possible (default: false)"
-                }
-            },
-            "required": ["language"]
-        })
+        crate::tool::Tool::parameters(&self.0)
     }
 
     async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
-        let language = match params.get("language").and_then(|v| v.as_str()) {
-            Some(l) => l,
-            None => return "Error: language is required".to_string(),
-        };
+        crate::tool::Tool::execute(&self.0, params).await
+    }
+}
 
-        let path = params
-            .get("path")
-            .and_then(|v| v.as_str())
-            .unwrap_or(".");
-        let fix = params
-            .get("fix")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
+/// Adapter over [`crate::tool::extract::ExtractTool`].
+struct ExtractTool(crate::tool::extract::ExtractTool);
 
-        let (cmd, args) = match language {
-            "rust" => {
-                let mut args = vec!["clippy", "--", "-D", "warnings"];
-                if fix {
-                    args = vec!["clippy", "--fix", "--allow-dirty"];
-                }
-                ("cargo", args)
-            }
-            "javascript" | "typescript" => {
-                let mut args = vec!["eslint", path];
-                if fix {
-                    args.push("--fix");
-                }
-                ("npx", args)
-            }
-            "python" => ("pylint", vec![path]),
-            "go" => ("golint", vec![path]),
-            _ => return format!("Unsupported language: {}", language),
-        };
+#[async_trait]
+impl Tool for ExtractTool {
+    fn name(&self) -> &str {
+        crate::tool::Tool::name(&self.0)
+    }
 
-        match tokio::process::Command::new(cmd)
-            .args(&args)
-            .output()
-            .await
-        {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                let combined = format!("{}\n{}", stdout, stderr);
+    fn description(&self) -> &str {
+        crate::tool::Tool::description(&self.0)
+    }
 
-                if output.status.success() {
-                    format!("✅ Linter passed - no issues found\n{}", combined)
-                } else {
-                    format!("⚠️ Linter found issues:\n{}", combined)
-                }
-            }
-            Err(e) => format!("Failed to run linter (is {} installed?): {}", cmd, e),
-        }
+    fn parameters(&self) -> serde_json::Value {
+        crate::tool::Tool::parameters(&self.0)
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        crate::tool::Tool::execute(&self.0, params).await
     }
 }
 
-struct RunTestsTool;
+/// Adapter over [`crate::tool::expense::ExpenseTool`]; callers pass the
+/// ledger owner as `_user_id`.
+struct ExpenseTool(crate::tool::expense::ExpenseTool);
 
 #[async_trait]
-impl Tool for RunTestsTool {
+impl Tool for ExpenseTool {
     fn name(&self) -> &str {
-        "run_tests"
+        crate::tool::Tool::name(&self.0)
     }
 
     fn description(&self) -> &str {
-        "Run test suite to verify code correctness. Supports multiple test frameworks (cargo test for Rust, jest for JS/TS, pytest for Python, etc.). Returns test results with pass/fail status."
+        crate::tool::Tool::description(&self.0)
     }
 
     fn parameters(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "language": {
-                    "type": "string",
-                    "enum": ["rust", "javascript", "typescript", "python", "go"],
-                    "description": "Programming language / test framework"
-                },
-                "path": {
-                    "type": "string",
-                    "description": "Optional: Path to test file or directory"
-                },
-                "test_name": {
-                    "type": "string",
-                    "description": "Optional: Specific test name to run (default: run all tests)"
-                }
-            },
-            "required": ["language"]
-        })
+        crate::tool::Tool::parameters(&self.0)
     }
 
     async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
-        let language = match params.get("language").and_then(|v| v.as_str()) {
-            Some(l) => l,
-            None => return "Error: language is required".to_string(),
-        };
+        crate::tool::Tool::execute(&self.0, params).await
+    }
+}
 
-        let path = params.get("path").and_then(|v| v.as_str());
-        let test_name = params.get("test_name").and_then(|v| v.as_str());
+// ---------------------------------------------------------------------------
+// Quality Assurance Tools
+// ---------------------------------------------------------------------------
 
-        let (cmd, mut args) = match language {
-            "rust" => ("cargo", vec!["test"]),
-            "javascript" | "typescript" => ("npm", vec!["test"]),
-            "python" => ("pytest", vec!["-v"]),
-            "go" => ("go", vec!["test", "-v"]),
-            _ => return format!("Unsupported language: {}", language),
-        };
+/// Adapters over [`crate::tool::quality`], run in the server's working directory.
+struct RunLinterTool(crate::tool::quality::RunLinterTool);
 
-        if let Some(p) = path {
-            args.push(p);
-        }
+impl RunLinterTool {
+    fn new() -> Self {
+        Self(crate::tool::quality::RunLinterTool::new(std::path::PathBuf::from(".")))
+    }
+}
 
-        if let Some(t) = test_name {
-            if language == "rust" {
-                args.push(t);
-            }
-        }
+#[async_trait]
+impl Tool for RunLinterTool {
+    fn name(&self) -> &str {
+        crate::tool::Tool::name(&self.0)
+    }
 
-        match tokio::process::Command::new(cmd)
-            .args(&args)
-            .output()
-            .await
-        {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                let combined = format!("{}\n{}", stdout, stderr);
+    fn description(&self) -> &str {
+        crate::tool::Tool::description(&self.0)
+    }
 
-                if output.status.success() {
-                    format!("✅ Tests passed\n{}", combined)
-                } else {
-                    format!("❌ Tests failed\n{}", combined)
-                }
-            }
-            Err(e) => format!("Failed to run tests (is {} installed?): {}", cmd, e),
-        }
+    fn parameters(&self) -> serde_json::Value {
+        crate::tool::Tool::parameters(&self.0)
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        crate::tool::Tool::execute(&self.0, params).await
+    }
+}
+
+struct RunTestsTool(crate::tool::quality::RunTestsTool);
+
+impl RunTestsTool {
+    fn new() -> Self {
+        Self(crate::tool::quality::RunTestsTool::new(std::path::PathBuf::from(".")))
+    }
+}
+
+#[async_trait]
+impl Tool for RunTestsTool {
+    fn name(&self) -> &str {
+        crate::tool::Tool::name(&self.0)
+    }
+
+    fn description(&self) -> &str {
+        crate::tool::Tool::description(&self.0)
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        crate::tool::Tool::parameters(&self.0)
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        crate::tool::Tool::execute(&self.0, params).await
     }
 }
 
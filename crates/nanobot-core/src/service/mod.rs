@@ -1,9 +1,23 @@
 pub mod a2a;
 pub mod cron;
+pub mod reminder;
+pub mod followup;
+pub mod upcoming;
+pub mod autostart;
+pub mod backup;
+pub mod config_sync;
+pub mod triage;
+pub mod import;
+pub mod router;
+pub mod routing_dataset;
 pub mod heartbeat;
 pub mod gateway;
+pub mod supervisor;
+pub mod tunnel;
 pub mod auth;
 pub mod usage;
+pub mod chat_queue;
+pub mod stream_resume;
 pub mod saas_tools;
 pub mod integrations;
 pub mod tool_permissions;
@@ -13,9 +27,21 @@ pub mod vault;
 #[cfg(feature = "http-api")]
 pub mod http;
 
+#[cfg(feature = "http-api")]
+pub mod voice_ws;
+
+#[cfg(feature = "http-api")]
+pub mod admin_ui;
+
+#[cfg(feature = "webui")]
+pub mod webui;
+
 #[cfg(feature = "http-api")]
 pub mod commands;
 
+#[cfg(feature = "grpc")]
+pub mod grpc;
+
 #[cfg(feature = "http-api")]
 pub mod tags;
 
@@ -0,0 +1,648 @@
+//! User-facing reminders on top of the cron service.
+//!
+//! `remind me to X at 5pm`, `/remind 毎日8時に薬を飲む` and similar requests
+//! become cron jobs of kind [`REMINDER_KIND`], bound to the chat they came
+//! from and evaluated in the user's timezone (session `/timezone`, falling
+//! back to the locale default). `/reminders` lists and cancels them and
+//! `/snooze` pushes one back.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::service::cron::{CronJob, CronPayload, CronSchedule, CronService};
+use crate::session::locale::Locale;
+use crate::session::Session;
+use crate::util::tz::Timezone;
+
+/// `CronPayload::kind` of reminder jobs.
+pub const REMINDER_KIND: &str = "reminder";
+
+/// Snooze length when `/snooze` is given no duration.
+pub const DEFAULT_SNOOZE_MS: u64 = 10 * 60 * 1000;
+
+/// A parsed reminder: what to say and when.
+#[derive(Debug, Clone)]
+pub struct ReminderSpec {
+    pub message: String,
+    pub schedule: CronSchedule,
+}
+
+/// A reminder request recognised in a user message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReminderRequest<'a> {
+    /// `/remind ...` or a natural "remind me ..." / "...リマインドして" message.
+    Create { text: &'a str, explicit: bool },
+    List,
+    Cancel(&'a str),
+    Snooze { id: Option<&'a str>, duration: Option<&'a str> },
+}
+
+/// Recognise reminder commands and natural-language reminder requests.
+pub fn parse_request(text: &str) -> Option<ReminderRequest<'_>> {
+    let trimmed = text.trim();
+    if let Some(inv) = crate::command::parse(trimmed) {
+        return match inv.name() {
+            "remind" => Some(ReminderRequest::Create { text: inv.args, explicit: true }),
+            "reminders" => {
+                let mut parts = inv.args.split_whitespace();
+                match (parts.next(), parts.next()) {
+                    (Some("cancel" | "delete" | "削除"), Some(id)) => Some(ReminderRequest::Cancel(id)),
+                    _ => Some(ReminderRequest::List),
+                }
+            }
+            "snooze" => {
+                let mut parts = inv.args.split_whitespace();
+                let first = parts.next();
+                // `/snooze 30m` snoozes the latest reminder; `/snooze <id> [30m]` a specific one
+                match first {
+                    Some(arg) if parse_duration(arg).is_some() => {
+                        Some(ReminderRequest::Snooze { id: None, duration: Some(arg) })
+                    }
+                    _ => Some(ReminderRequest::Snooze { id: first, duration: parts.next() }),
+                }
+            }
+            _ => None,
+        };
+    }
+    let lower = trimmed.to_lowercase();
+    if lower.starts_with("remind me ") || trimmed.contains("リマインド") {
+        return Some(ReminderRequest::Create { text: trimmed, explicit: false });
+    }
+    None
+}
+
+/// Timezone used for a session's reminders: `/timezone`, else `fallback`
+/// (the configured default), else the default for the channel and locale.
+pub fn session_timezone(session: &Session, fallback: Option<&str>, channel: &str, locale: Locale) -> String {
+    session
+        .timezone()
+        .or(fallback)
+        .unwrap_or_else(|| locale.default_timezone(channel))
+        .to_string()
+}
+
+/// Parse a duration such as `10m`, `2 hours`, `30分`, `1時間`.
+pub fn parse_duration(s: &str) -> Option<u64> {
+    static RE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^(\d+)\s*(m|min|mins|minute|minutes|分|h|hr|hrs|hour|hours|時間|d|day|days|日)$").unwrap()
+    });
+    let s = normalize(s).to_lowercase();
+    let caps = RE.captures(s.trim())?;
+    let n: u64 = caps[1].parse().ok()?;
+    let unit_ms = match &caps[2] {
+        "m" | "min" | "mins" | "minute" | "minutes" | "分" => 60_000,
+        "h" | "hr" | "hrs" | "hour" | "hours" | "時間" => 3_600_000,
+        _ => 86_400_000,
+    };
+    Some(n * unit_ms).filter(|&ms| ms > 0)
+}
+
+/// Full-width digits, colons and spaces to ASCII.
+fn normalize(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '０'..='９' => char::from_u32(c as u32 - '０' as u32 + '0' as u32).unwrap_or(c),
+            '：' => ':',
+            '　' => ' ',
+            _ => c,
+        })
+        .collect()
+}
+
+fn strip_prefix_ci<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    s.get(..prefix.len())
+        .filter(|head| head.eq_ignore_ascii_case(prefix))
+        .map(|_| &s[prefix.len()..])
+}
+
+/// Parse a reminder request into a message and schedule. `now` is in the
+/// user's timezone; `tz` is stored on recurring schedules.
+pub fn parse_reminder(text: &str, now: DateTime<Timezone>, tz: &str) -> Option<ReminderSpec> {
+    let text = normalize(text);
+    let text = text.trim();
+    let body = strip_prefix_ci(text, "remind").unwrap_or(text).trim_start();
+    let body = strip_prefix_ci(body, "me ").unwrap_or(body).trim();
+    parse_english(body, now, tz).or_else(|| parse_japanese(body, now, tz))
+}
+
+// ---------------------------------------------------------------------------
+// English
+// ---------------------------------------------------------------------------
+
+const TIME_KEYWORDS: &[&str] = &["at", "in", "every", "tomorrow", "today", "daily"];
+
+fn parse_english(body: &str, now: DateTime<Timezone>, tz: &str) -> Option<ReminderSpec> {
+    let words: Vec<&str> = body.split_whitespace().collect();
+    let lower: Vec<String> = words.iter().map(|w| w.to_lowercase()).collect();
+    let lower: Vec<&str> = lower.iter().map(String::as_str).collect();
+    // "to X <when>": the leftmost keyword whose remainder is a complete time spec
+    for (i, w) in lower.iter().enumerate().skip(1) {
+        if TIME_KEYWORDS.contains(w) {
+            if let Some(schedule) = parse_when(&lower[i..], now, tz) {
+                let message = strip_to(&words[..i].join(" "));
+                return non_empty(message).map(|message| ReminderSpec { message, schedule });
+            }
+        }
+    }
+    // "<when> to X"
+    let to = lower.iter().position(|w| *w == "to")?;
+    let schedule = parse_when(&lower[..to], now, tz)?;
+    non_empty(words[to + 1..].join(" ")).map(|message| ReminderSpec { message, schedule })
+}
+
+fn strip_to(s: &str) -> String {
+    strip_prefix_ci(s, "to ").unwrap_or(s).trim().to_string()
+}
+
+fn non_empty(s: String) -> Option<String> {
+    let s = s.trim().trim_end_matches(['.', '。']).trim().to_string();
+    (!s.is_empty()).then_some(s)
+}
+
+fn parse_when(words: &[&str], now: DateTime<Timezone>, tz: &str) -> Option<CronSchedule> {
+    match words {
+        ["in", rest @ ..] => {
+            let ms = parse_duration(&rest.join(""))?;
+            Some(CronSchedule::At { at_ms: now.timestamp_millis() as u64 + ms })
+        }
+        ["at", time @ ..] => {
+            let (h, m) = parse_clock(&time.join(""))?;
+            at_next(now, now.date_naive(), h, m, true)
+        }
+        ["today", "at", time @ ..] => {
+            let (h, m) = parse_clock(&time.join(""))?;
+            at_next(now, now.date_naive(), h, m, false)
+        }
+        ["tomorrow"] => at_next(now, now.date_naive().succ_opt()?, 9, 0, false),
+        ["tomorrow", "at", time @ ..] | ["tomorrow", time @ ..] => {
+            let (h, m) = parse_clock(&time.join(""))?;
+            at_next(now, now.date_naive().succ_opt()?, h, m, false)
+        }
+        ["every", "hour"] => Some(CronSchedule::Every { every_ms: 3_600_000 }),
+        ["daily", "at", time @ ..] | ["every", "day", "at", time @ ..] => cron_at(&time.join(""), "*", tz),
+        ["every", "weekday", "at", time @ ..] => cron_at(&time.join(""), "Mon-Fri", tz),
+        ["every", day, "at", time @ ..] if english_weekday(day).is_some() => {
+            cron_at(&time.join(""), english_weekday(day)?, tz)
+        }
+        ["every", rest @ ..] => {
+            let ms = parse_duration(&rest.join(""))?;
+            Some(CronSchedule::Every { every_ms: ms })
+        }
+        _ => None,
+    }
+}
+
+fn english_weekday(day: &str) -> Option<&'static str> {
+    let day = day.trim_end_matches('s');
+    [
+        ("mon", "Mon"),
+        ("tue", "Tue"),
+        ("wed", "Wed"),
+        ("thu", "Thu"),
+        ("fri", "Fri"),
+        ("sat", "Sat"),
+        ("sun", "Sun"),
+    ]
+    .into_iter()
+    .find(|(prefix, _)| day.len() >= 3 && day.starts_with(prefix))
+    .map(|(_, name)| name)
+}
+
+/// `9`, `9am`, `9:30pm`, `21:00`, `noon`, `midnight`.
+fn parse_clock(s: &str) -> Option<(u32, u32)> {
+    match s {
+        "noon" => return Some((12, 0)),
+        "midnight" => return Some((0, 0)),
+        _ => {}
+    }
+    let (clock, meridiem) = if let Some(c) = s.strip_suffix("am") {
+        (c, Some(false))
+    } else if let Some(c) = s.strip_suffix("pm") {
+        (c, Some(true))
+    } else {
+        (s, None)
+    };
+    let (h, m) = clock.split_once(':').unwrap_or((clock, "0"));
+    let (mut h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+    match meridiem {
+        Some(pm) if (1..=12).contains(&h) => h = h % 12 + if pm { 12 } else { 0 },
+        Some(_) => return None,
+        None => {}
+    }
+    (h < 24 && m < 60).then_some((h, m))
+}
+
+/// One-shot schedule at `h:m` on `date` (in `now`'s timezone). With
+/// `roll_over`, a time already past today moves to tomorrow.
+fn at_next(now: DateTime<Timezone>, date: NaiveDate, h: u32, m: u32, roll_over: bool) -> Option<CronSchedule> {
+    let local = |d: NaiveDate| now.timezone().from_local_datetime(&d.and_hms_opt(h, m, 0)?).single();
+    let mut at = local(date)?;
+    if at <= now {
+        if !roll_over {
+            return None;
+        }
+        at = local(date.succ_opt()?)?;
+    }
+    Some(CronSchedule::At { at_ms: at.timestamp_millis() as u64 })
+}
+
+fn cron_at(time: &str, days: &str, tz: &str) -> Option<CronSchedule> {
+    let (h, m) = parse_clock(time)?;
+    Some(CronSchedule::Cron {
+        expr: format!("{m} {h} * * {days}"),
+        tz: Some(tz.to_string()),
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Japanese
+// ---------------------------------------------------------------------------
+
+static JA_AFTER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d+)\s*(分|時間|日)後に?(.+)$").unwrap());
+static JA_EVERY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\d+)\s*(分|時間|日)(?:ごと|おき|毎)に?(.+)$").unwrap());
+static JA_AT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(今日|明日|明後日)?の?(午前|午後)?(\d{1,2})(?:時|:)(?:(\d{1,2})分?|(半))?に(.+)$").unwrap()
+});
+static JA_RECURRING: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^毎(日|朝|晩|平日|週([月火水木金土日])曜?日?)の?(午前|午後)?(\d{1,2})(?:時|:)(?:(\d{1,2})分?|(半))?に(.+)$")
+        .unwrap()
+});
+
+fn parse_japanese(body: &str, now: DateTime<Timezone>, tz: &str) -> Option<ReminderSpec> {
+    let body = body.trim_start_matches(['、', ',', ':']).trim();
+    if let Some(c) = JA_AFTER.captures(body) {
+        let ms = parse_duration(&format!("{}{}", &c[1], &c[2]))?;
+        let message = ja_message(&c[3])?;
+        return Some(ReminderSpec { message, schedule: CronSchedule::At { at_ms: now.timestamp_millis() as u64 + ms } });
+    }
+    if let Some(c) = JA_EVERY.captures(body) {
+        let every_ms = parse_duration(&format!("{}{}", &c[1], &c[2]))?;
+        let message = ja_message(&c[3])?;
+        return Some(ReminderSpec { message, schedule: CronSchedule::Every { every_ms } });
+    }
+    if let Some(c) = JA_RECURRING.captures(body) {
+        let (h, m) = ja_clock(c.get(3).map(|m| m.as_str()), &c[4], c.get(5).map(|m| m.as_str()), c.get(6).is_some())?;
+        let days = match &c[1] {
+            "平日" => "Mon-Fri",
+            _ => match c.get(2).map(|m| m.as_str()) {
+                Some(day) => japanese_weekday(day)?,
+                None => "*",
+            },
+        };
+        let message = ja_message(&c[7])?;
+        let schedule = CronSchedule::Cron { expr: format!("{m} {h} * * {days}"), tz: Some(tz.to_string()) };
+        return Some(ReminderSpec { message, schedule });
+    }
+    if let Some(c) = JA_AT.captures(body) {
+        let (h, m) = ja_clock(c.get(2).map(|m| m.as_str()), &c[3], c.get(4).map(|m| m.as_str()), c.get(5).is_some())?;
+        let (date, roll_over) = match c.get(1).map(|m| m.as_str()) {
+            Some("明日") => (now.date_naive().succ_opt()?, false),
+            Some("明後日") => (now.date_naive().checked_add_signed(Duration::days(2))?, false),
+            Some(_) => (now.date_naive(), false),
+            None => (now.date_naive(), true),
+        };
+        let message = ja_message(&c[6])?;
+        return Some(ReminderSpec { message, schedule: at_next(now, date, h, m, roll_over)? });
+    }
+    None
+}
+
+fn ja_clock(ampm: Option<&str>, h: &str, m: Option<&str>, half: bool) -> Option<(u32, u32)> {
+    let mut h: u32 = h.parse().ok()?;
+    let m: u32 = if half { 30 } else { m.map(|m| m.parse()).transpose().ok()?.unwrap_or(0) };
+    if ampm == Some("午後") && h < 12 {
+        h += 12;
+    }
+    (h < 24 && m < 60).then_some((h, m))
+}
+
+fn japanese_weekday(day: &str) -> Option<&'static str> {
+    Some(match day {
+        "月" => "Mon",
+        "火" => "Tue",
+        "水" => "Wed",
+        "木" => "Thu",
+        "金" => "Fri",
+        "土" => "Sat",
+        "日" => "Sun",
+        _ => return None,
+    })
+}
+
+/// Strip request phrasing ("…ってリマインドして", "…を教えて") from the message.
+fn ja_message(s: &str) -> Option<String> {
+    const SUFFIXES: &[&str] = &[
+        "ってリマインドして", "とリマインドして", "をリマインドして", "リマインドして",
+        "ってリマインド", "とリマインド", "をリマインド", "リマインド",
+        "って教えて", "と教えて", "を教えて", "ください",
+    ];
+    let mut s = s.trim().trim_end_matches(['。', '!', '！']).trim();
+    for suffix in SUFFIXES {
+        if let Some(rest) = s.strip_suffix(suffix) {
+            s = rest.trim();
+        }
+    }
+    non_empty(s.to_string())
+}
+
+// ---------------------------------------------------------------------------
+// Operations
+// ---------------------------------------------------------------------------
+
+fn is_reminder_for(job: &CronJob, channel: &str, chat_id: &str) -> bool {
+    job.payload.kind == REMINDER_KIND
+        && job.payload.channel.as_deref() == Some(channel)
+        && job.payload.to.as_deref() == Some(chat_id)
+}
+
+/// Create a reminder job delivering to `channel`/`chat_id`.
+pub fn create(cron: &mut CronService, spec: ReminderSpec, channel: &str, chat_id: &str) -> CronJob {
+    let name: String = spec.message.chars().take(30).collect();
+    let payload = CronPayload {
+        kind: REMINDER_KIND.to_string(),
+        message: spec.message,
+        deliver: true,
+        channel: Some(channel.to_string()),
+        to: Some(chat_id.to_string()),
+    };
+    cron.add_job_with_payload(&name, spec.schedule, payload)
+}
+
+/// Active reminders for a chat, soonest first.
+pub fn list(cron: &CronService, channel: &str, chat_id: &str) -> Vec<CronJob> {
+    cron.list_jobs(false)
+        .into_iter()
+        .filter(|j| is_reminder_for(j, channel, chat_id))
+        .collect()
+}
+
+/// Cancel one of the chat's reminders.
+pub fn cancel(cron: &mut CronService, channel: &str, chat_id: &str, id: &str) -> bool {
+    let owned = cron.list_jobs(true).iter().any(|j| j.id == id && is_reminder_for(j, channel, chat_id));
+    owned && cron.remove_job(id)
+}
+
+/// Snooze a reminder by `ms`: `id`, or the chat's most recently fired one.
+/// One-shot reminders are re-armed; recurring ones get a one-shot copy.
+pub fn snooze(cron: &mut CronService, channel: &str, chat_id: &str, id: Option<&str>, ms: u64) -> Option<CronJob> {
+    let jobs: Vec<CronJob> = cron
+        .list_jobs(true)
+        .into_iter()
+        .filter(|j| is_reminder_for(j, channel, chat_id))
+        .collect();
+    let target = match id {
+        Some(id) => jobs.into_iter().find(|j| j.id == id)?,
+        None => jobs
+            .into_iter()
+            .filter(|j| j.state.last_run_at_ms.is_some())
+            .max_by_key(|j| j.state.last_run_at_ms)?,
+    };
+    let at = CronSchedule::At { at_ms: Utc::now().timestamp_millis() as u64 + ms };
+    match target.schedule {
+        CronSchedule::At { .. } => cron.reschedule_job(&target.id, at),
+        _ => Some(cron.add_job_with_payload(&target.name, at, target.payload)),
+    }
+}
+
+/// Text sent when a reminder fires.
+pub fn notification_text(job: &CronJob) -> String {
+    format!("⏰ {}", job.payload.message)
+}
+
+/// One line describing a reminder for `/reminders`.
+pub fn describe(job: &CronJob, zone: Timezone, locale: Locale) -> String {
+    let next = job
+        .state
+        .next_run_at_ms
+        .and_then(|ms| zone.timestamp_millis_opt(ms as i64).single());
+    let when = match next {
+        Some(t) if t.date_naive() == Utc::now().with_timezone(&zone).date_naive() => t.format("%H:%M").to_string(),
+        Some(t) => format!("{}/{} {}", t.month(), t.day(), t.format("%H:%M")),
+        None => "-".to_string(),
+    };
+    let repeat = match job.schedule {
+        CronSchedule::At { .. } => "",
+        _ => locale.pick("（繰り返し）", " (repeats)"),
+    };
+    format!("• [{}] {} — {}{}", job.id, job.payload.message, when, repeat)
+}
+
+/// Handle a reminder request for `channel`/`chat_id`. Returns `None` when a
+/// natural-language message turned out not to be a reminder.
+pub fn handle_request(
+    cron: &mut CronService,
+    request: ReminderRequest<'_>,
+    channel: &str,
+    chat_id: &str,
+    tz: &str,
+    locale: Locale,
+) -> Option<String> {
+    let zone = crate::util::tz::parse_or_utc(tz);
+    let now = Utc::now().with_timezone(&zone);
+    match request {
+        ReminderRequest::Create { text, explicit } => match parse_reminder(text, now, tz) {
+            Some(spec) => {
+                let job = create(cron, spec, channel, chat_id);
+                Some(format!(
+                    "{}\n{}",
+                    locale.pick("⏰ リマインダーを設定しました:", "⏰ Reminder set:"),
+                    describe(&job, zone, locale)
+                ))
+            }
+            None if explicit => Some(
+                locale
+                    .pick(
+                        "使い方: /remind 明日9時に会議 / 10分後にストレッチ / 毎日8時に薬を飲む",
+                        "Usage: /remind me to stretch in 10 minutes / to call mom at 5pm / to take medicine every day at 8am",
+                    )
+                    .to_string(),
+            ),
+            None => None,
+        },
+        ReminderRequest::List => {
+            let jobs = list(cron, channel, chat_id);
+            if jobs.is_empty() {
+                return Some(locale.pick("⏰ 設定中のリマインダーはありません。", "⏰ No reminders set.").to_string());
+            }
+            let mut lines = vec![locale.pick("⏰ リマインダー一覧:", "⏰ Reminders:").to_string()];
+            lines.extend(jobs.iter().map(|j| describe(j, zone, locale)));
+            lines.push(locale.pick("解除: /reminders cancel <ID>", "Cancel: /reminders cancel <ID>").to_string());
+            Some(lines.join("\n"))
+        }
+        ReminderRequest::Cancel(id) => Some(if cancel(cron, channel, chat_id, id) {
+            format!("{} [{}]", locale.pick("🗑️ リマインダーを解除しました", "🗑️ Reminder cancelled"), id)
+        } else {
+            format!("{}: {}", locale.pick("リマインダーが見つかりません", "Reminder not found"), id)
+        }),
+        ReminderRequest::Snooze { id, duration } => {
+            let ms = match duration {
+                Some(d) => match parse_duration(d) {
+                    Some(ms) => ms,
+                    None => return Some(locale.pick("使い方: /snooze [ID] [10分]", "Usage: /snooze [ID] [10m]").to_string()),
+                },
+                None => DEFAULT_SNOOZE_MS,
+            };
+            Some(match snooze(cron, channel, chat_id, id, ms) {
+                Some(job) => format!(
+                    "{}\n{}",
+                    locale.pick("😴 スヌーズしました:", "😴 Snoozed:"),
+                    describe(&job, zone, locale)
+                ),
+                None => locale.pick("スヌーズするリマインダーがありません。", "No reminder to snooze.").to_string(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jst_now() -> DateTime<Timezone> {
+        // 2026-03-10 (Tue) 10:00 JST
+        crate::util::tz::parse_or_utc("Asia/Tokyo").with_ymd_and_hms(2026, 3, 10, 10, 0, 0).unwrap()
+    }
+
+    fn at_local(spec: &ReminderSpec) -> DateTime<Timezone> {
+        match spec.schedule {
+            CronSchedule::At { at_ms } => jst_now().timezone().timestamp_millis_opt(at_ms as i64).unwrap(),
+            _ => panic!("expected one-shot schedule, got {:?}", spec.schedule),
+        }
+    }
+
+    fn cron_expr(spec: &ReminderSpec) -> &str {
+        match &spec.schedule {
+            CronSchedule::Cron { expr, tz } => {
+                assert_eq!(tz.as_deref(), Some("Asia/Tokyo"));
+                expr
+            }
+            _ => panic!("expected cron schedule, got {:?}", spec.schedule),
+        }
+    }
+
+    #[test]
+    fn test_parse_english() {
+        let now = jst_now();
+        let spec = parse_reminder("remind me to look at the report at 5pm", now, "Asia/Tokyo").unwrap();
+        assert_eq!(spec.message, "look at the report");
+        assert_eq!(at_local(&spec).format("%d %H:%M").to_string(), "10 17:00");
+
+        // Past time rolls over to tomorrow
+        let spec = parse_reminder("remind me to stretch at 9:30am", now, "Asia/Tokyo").unwrap();
+        assert_eq!(at_local(&spec).format("%d %H:%M").to_string(), "11 09:30");
+
+        let spec = parse_reminder("Remind me in 10 minutes to call Mom", now, "Asia/Tokyo").unwrap();
+        assert_eq!(spec.message, "call Mom");
+        assert_eq!(at_local(&spec).format("%H:%M").to_string(), "10:10");
+
+        let spec = parse_reminder("remind me to take medicine every day at 8am", now, "Asia/Tokyo").unwrap();
+        assert_eq!(spec.message, "take medicine");
+        assert_eq!(cron_expr(&spec), "0 8 * * *");
+
+        // `/remind` arguments
+        let spec = parse_reminder("me to file the report every Friday at 16:30", now, "Asia/Tokyo").unwrap();
+        assert_eq!(spec.message, "file the report");
+        assert_eq!(cron_expr(&spec), "30 16 * * Fri");
+
+        let spec = parse_reminder("remind me to stand up every 2 hours", now, "Asia/Tokyo").unwrap();
+        assert!(matches!(spec.schedule, CronSchedule::Every { every_ms: 7_200_000 }));
+
+        assert!(parse_reminder("remind me to do something", now, "Asia/Tokyo").is_none());
+    }
+
+    #[test]
+    fn test_parse_japanese() {
+        let now = jst_now();
+        let spec = parse_reminder("10分後にストレッチってリマインドして", now, "Asia/Tokyo").unwrap();
+        assert_eq!(spec.message, "ストレッチ");
+        assert_eq!(at_local(&spec).format("%H:%M").to_string(), "10:10");
+
+        let spec = parse_reminder("明日の午後３時半に歯医者を教えて", now, "Asia/Tokyo").unwrap();
+        assert_eq!(spec.message, "歯医者");
+        assert_eq!(at_local(&spec).format("%d %H:%M").to_string(), "11 15:30");
+
+        let spec = parse_reminder("毎日8時に薬を飲む", now, "Asia/Tokyo").unwrap();
+        assert_eq!(spec.message, "薬を飲む");
+        assert_eq!(cron_expr(&spec), "0 8 * * *");
+
+        let spec = parse_reminder("毎週月曜10時にゴミ出しとリマインド", now, "Asia/Tokyo").unwrap();
+        assert_eq!(spec.message, "ゴミ出し");
+        assert_eq!(cron_expr(&spec), "0 10 * * Mon");
+
+        let spec = parse_reminder("3時間ごとに水を飲む", now, "Asia/Tokyo").unwrap();
+        assert!(matches!(spec.schedule, CronSchedule::Every { every_ms: 10_800_000 }));
+
+        // Explicit "today" in the past is rejected rather than silently moved
+        assert!(parse_reminder("今日の9時に会議", now, "Asia/Tokyo").is_none());
+    }
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!(parse_request("/reminders"), Some(ReminderRequest::List));
+        assert_eq!(parse_request("/reminders cancel ab12"), Some(ReminderRequest::Cancel("ab12")));
+        assert_eq!(
+            parse_request("/snooze 30m"),
+            Some(ReminderRequest::Snooze { id: None, duration: Some("30m") })
+        );
+        assert_eq!(
+            parse_request("/snooze ab12"),
+            Some(ReminderRequest::Snooze { id: Some("ab12"), duration: None })
+        );
+        assert_eq!(
+            parse_request("Remind me to call mom at 5pm"),
+            Some(ReminderRequest::Create { text: "Remind me to call mom at 5pm", explicit: false })
+        );
+        assert_eq!(parse_request("what's the weather?"), None);
+        assert_eq!(parse_duration("１５分"), Some(900_000));
+        assert_eq!(parse_duration("2 hours"), Some(7_200_000));
+        assert_eq!(parse_duration("soon"), None);
+    }
+
+    #[test]
+    fn test_create_list_snooze_cancel() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut cron = CronService::new(tmp.path().join("cron").join("jobs.json"));
+        cron.init();
+
+        let reply = handle_request(
+            &mut cron,
+            ReminderRequest::Create { text: "毎日8時に薬を飲む", explicit: true },
+            "telegram",
+            "42",
+            "Asia/Tokyo",
+            Locale::Ja,
+        )
+        .unwrap();
+        assert!(reply.contains("薬を飲む"));
+        assert!(reply.contains("繰り返し"));
+
+        let jobs = list(&cron, "telegram", "42");
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].payload.kind, REMINDER_KIND);
+        assert!(list(&cron, "telegram", "other").is_empty());
+
+        // Nothing has fired yet
+        assert!(snooze(&mut cron, "telegram", "42", None, DEFAULT_SNOOZE_MS).is_none());
+        cron.mark_executed(&jobs[0].id, "ok", None);
+        let snoozed = snooze(&mut cron, "telegram", "42", None, DEFAULT_SNOOZE_MS).unwrap();
+        assert!(matches!(snoozed.schedule, CronSchedule::At { .. }));
+        assert_eq!(list(&cron, "telegram", "42").len(), 2);
+
+        assert!(!cancel(&mut cron, "telegram", "other", &jobs[0].id));
+        assert!(cancel(&mut cron, "telegram", "42", &jobs[0].id));
+        assert_eq!(list(&cron, "telegram", "42").len(), 1);
+
+        // Natural-language text that is not a reminder falls through
+        let reply = handle_request(
+            &mut cron,
+            ReminderRequest::Create { text: "リマインド機能について教えて", explicit: false },
+            "telegram",
+            "42",
+            "Asia/Tokyo",
+            Locale::Ja,
+        );
+        assert!(reply.is_none());
+    }
+}
@@ -0,0 +1,575 @@
+//! Learned agent routing.
+//!
+//! Keyword scoring (`detect_agent` in the HTTP API) picks an agent for every
+//! chat request, and each request is logged as a routing entry with hashed
+//! message features (no raw text). This module turns those entries into a
+//! small multinomial logistic regression over the same features:
+//!
+//! 1. `chatweb routing export` pulls `ROUTING_LOG#<date>` entries into JSONL,
+//!    anonymized by [`routing_dataset`](super::routing_dataset) unless
+//!    `--raw` is given. Entries expire after `agents.routing.logRetentionDays`
+//!    and `chatweb routing purge` deletes older ones.
+//! 2. `chatweb routing train` fits a [`RoutingModel`] and writes it as JSON.
+//! 3. With `agents.routing.mode = "shadow"` the gateway loads the model and
+//!    logs its pick next to the keyword pick without acting on it;
+//!    `chatweb routing eval` and `/api/v1/admin/routing` report agreement.
+//! 4. With `mode = "learned"` the model's pick wins whenever its confidence
+//!    clears `minConfidence`. Explicit `@agent` and `/agent` choices always win.
+//!
+//! Entries where the user chose the agent themselves are the strongest
+//! labels and count [`EXPLICIT_WEIGHT`] times; failed and timed-out requests
+//! are left out.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{bail, Context, Result};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, RoutingConfig};
+
+/// Size of the hashed feature space.
+pub const FEATURE_DIM: usize = 2048;
+/// Training weight of requests where the user picked the agent.
+pub const EXPLICIT_WEIGHT: f32 = 3.0;
+/// Only the start of a message is hashed, like keyword scoring.
+const SCAN_CHARS: usize = 256;
+
+/// How the learned model takes part in routing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoutingMode {
+    /// Keyword scoring only; the model isn't loaded.
+    Keyword,
+    /// The model predicts and its pick is logged, keyword scoring decides.
+    Shadow,
+    /// The model decides when confident enough.
+    Learned,
+}
+
+impl RoutingMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "keyword" | "keywords" | "off" => Some(Self::Keyword),
+            "shadow" => Some(Self::Shadow),
+            "learned" | "model" | "on" => Some(Self::Learned),
+            _ => None,
+        }
+    }
+}
+
+/// What a routing decision is made from.
+#[derive(Debug, Clone, Copy)]
+pub struct RouteInput<'a> {
+    /// The message without an `@agent` prefix.
+    pub text: &'a str,
+    pub channel: &'a str,
+    pub device: &'a str,
+    pub language: &'a str,
+}
+
+fn fnv1a(s: &str) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for b in s.bytes() {
+        hash ^= b as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash
+}
+
+fn bucket(token: &str) -> u32 {
+    fnv1a(token) % FEATURE_DIM as u32
+}
+
+/// Hashed features of a request: words (Latin scripts) or character bigrams
+/// (CJK) of the message, plus channel, device, language and a length band.
+/// Sorted and deduplicated; the text itself can't be recovered from them.
+pub fn features(input: &RouteInput<'_>) -> Vec<u32> {
+    let text: String = input.text.trim().chars().take(SCAN_CHARS).collect::<String>().to_lowercase();
+    let mut out = Vec::new();
+
+    let mut word = String::new();
+    let mut prev_wide: Option<char> = None;
+    for c in text.chars().chain(std::iter::once(' ')) {
+        if c.is_ascii_alphanumeric() {
+            word.push(c);
+            prev_wide = None;
+            continue;
+        }
+        if !word.is_empty() {
+            out.push(bucket(&format!("w:{}", word)));
+            word.clear();
+        }
+        if c.is_alphanumeric() {
+            out.push(bucket(&format!("c:{}", c)));
+            if let Some(p) = prev_wide {
+                out.push(bucket(&format!("b:{}{}", p, c)));
+            }
+            prev_wide = Some(c);
+        } else {
+            prev_wide = None;
+        }
+    }
+
+    let len_band = (input.text.chars().count().max(1) as f64).log2() as u32;
+    out.push(bucket(&format!("ch:{}", input.channel)));
+    out.push(bucket(&format!("dev:{}", input.device)));
+    out.push(bucket(&format!("lang:{}", input.language)));
+    out.push(bucket(&format!("len:{}", len_band)));
+    out.push(bucket("bias"));
+    out.sort_unstable();
+    out.dedup();
+    out
+}
+
+/// The model's pick for one request.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Prediction {
+    pub agent: String,
+    pub confidence: f32,
+}
+
+/// Multinomial logistic regression over hashed features, stored as JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingModel {
+    /// Agent ids, one per row of `weights`.
+    pub labels: Vec<String>,
+    pub weights: Vec<Vec<f32>>,
+    pub bias: Vec<f32>,
+    /// Training set size.
+    pub examples: usize,
+    pub trained_at: String,
+}
+
+impl RoutingModel {
+    pub fn default_path() -> PathBuf {
+        config::get_data_dir().join("routing").join("model.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let model: Self = serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+        if model.labels.is_empty()
+            || model.weights.len() != model.labels.len()
+            || model.bias.len() != model.labels.len()
+            || model.weights.iter().any(|w| w.len() != FEATURE_DIM)
+        {
+            bail!("{} is not a routing model for {} features", path.display(), FEATURE_DIM);
+        }
+        Ok(model)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn probabilities(&self, features: &[u32]) -> Vec<f32> {
+        let scores: Vec<f32> = self
+            .weights
+            .iter()
+            .zip(&self.bias)
+            .map(|(w, b)| b + features.iter().map(|&i| w[i as usize % FEATURE_DIM]).sum::<f32>())
+            .collect();
+        let max = scores.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let exp: Vec<f32> = scores.iter().map(|s| (s - max).exp()).collect();
+        let total: f32 = exp.iter().sum();
+        exp.into_iter().map(|e| e / total).collect()
+    }
+
+    pub fn predict(&self, features: &[u32]) -> Prediction {
+        let probs = self.probabilities(features);
+        let (best, confidence) = probs
+            .iter()
+            .enumerate()
+            .fold((0, 0.0f32), |acc, (i, &p)| if p > acc.1 { (i, p) } else { acc });
+        Prediction { agent: self.labels[best].clone(), confidence }
+    }
+}
+
+/// One labeled request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrainingExample {
+    pub features: Vec<u32>,
+    pub label: String,
+    pub weight: f32,
+}
+
+impl TrainingExample {
+    /// From an exported routing log entry; `None` for failed requests,
+    /// requests the model itself routed (so it doesn't learn from its own
+    /// picks) and entries logged before features were recorded.
+    pub fn from_log(entry: &serde_json::Value) -> Option<Self> {
+        let skip = ["error", "timed_out", "learned_override"];
+        if skip.iter().any(|k| entry[*k].as_bool() == Some(true)) {
+            return None;
+        }
+        let features: Vec<u32> = entry["route_features"]
+            .as_array()?
+            .iter()
+            .filter_map(|v| v.as_u64().map(|n| n as u32))
+            .collect();
+        if features.is_empty() {
+            return None;
+        }
+        let label = entry["agent_selected"].as_str()?.to_string();
+        // Raw entries carry the score, anonymized rows the flag and a count
+        let explicit = entry["agent_score"].as_u64() == Some(100) || entry["explicit"].as_bool() == Some(true);
+        let count = entry["count"].as_u64().unwrap_or(1).max(1) as f32;
+        Some(Self { features, label, weight: count * if explicit { EXPLICIT_WEIGHT } else { 1.0 } })
+    }
+}
+
+/// Read examples from an exported JSONL file, skipping unusable entries.
+pub fn read_examples(path: &Path) -> Result<Vec<TrainingExample>> {
+    let file = std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let mut examples = Vec::new();
+    for line in std::io::BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(example) = serde_json::from_str(&line).ok().as_ref().and_then(TrainingExample::from_log) {
+            examples.push(example);
+        }
+    }
+    Ok(examples)
+}
+
+#[derive(Debug, Clone)]
+pub struct TrainOptions {
+    pub epochs: usize,
+    pub learning_rate: f32,
+    /// L2 penalty per update.
+    pub l2: f32,
+    pub seed: u64,
+}
+
+impl Default for TrainOptions {
+    fn default() -> Self {
+        Self { epochs: 20, learning_rate: 0.1, l2: 1e-5, seed: 42 }
+    }
+}
+
+/// Fit a model with plain SGD on the cross-entropy loss.
+pub fn train(examples: &[TrainingExample], options: &TrainOptions) -> Result<RoutingModel> {
+    let labels: Vec<String> = examples
+        .iter()
+        .map(|e| e.label.clone())
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    if labels.len() < 2 {
+        bail!("need examples of at least two agents to train a router (got {})", labels.len());
+    }
+    let index: HashMap<&str, usize> = labels.iter().enumerate().map(|(i, l)| (l.as_str(), i)).collect();
+    let mut model = RoutingModel {
+        weights: vec![vec![0.0; FEATURE_DIM]; labels.len()],
+        bias: vec![0.0; labels.len()],
+        labels: labels.clone(),
+        examples: examples.len(),
+        trained_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let mut order: Vec<usize> = (0..examples.len()).collect();
+    let mut rng = rand::rngs::StdRng::seed_from_u64(options.seed);
+    for epoch in 0..options.epochs {
+        order.shuffle(&mut rng);
+        let rate = options.learning_rate / (1.0 + epoch as f32 * 0.1);
+        for &i in &order {
+            let example = &examples[i];
+            let target = index[example.label.as_str()];
+            let probs = model.probabilities(&example.features);
+            for (k, p) in probs.iter().enumerate() {
+                let grad = (p - if k == target { 1.0 } else { 0.0 }) * example.weight;
+                model.bias[k] -= rate * grad;
+                for &f in &example.features {
+                    let w = &mut model.weights[k][f as usize % FEATURE_DIM];
+                    *w -= rate * (grad + options.l2 * *w);
+                }
+            }
+        }
+    }
+    Ok(model)
+}
+
+/// How a model's picks compare to the labels of a set of examples.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Evaluation {
+    pub total: usize,
+    pub agreed: usize,
+    /// Labeled agent → predicted agent → count.
+    pub confusion: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+impl Evaluation {
+    pub fn record(&mut self, label: &str, predicted: &str) {
+        self.total += 1;
+        if label == predicted {
+            self.agreed += 1;
+        }
+        *self
+            .confusion
+            .entry(label.to_string())
+            .or_default()
+            .entry(predicted.to_string())
+            .or_default() += 1;
+    }
+
+    pub fn agreement(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.agreed as f64 / self.total as f64
+        }
+    }
+}
+
+/// Compare `model` with the labels of `examples`.
+pub fn evaluate(model: &RoutingModel, examples: &[TrainingExample]) -> Evaluation {
+    let mut eval = Evaluation::default();
+    for example in examples {
+        eval.record(&example.label, &model.predict(&example.features).agent);
+    }
+    eval
+}
+
+/// The routing model as used by the gateway, with shadow-mode statistics.
+pub struct Router {
+    mode: RoutingMode,
+    model: RoutingModel,
+    min_confidence: f32,
+    shadow: Mutex<Evaluation>,
+}
+
+impl Router {
+    /// `None` in keyword mode or when the model can't be loaded (logged).
+    pub fn from_config(config: &RoutingConfig) -> Option<Self> {
+        let mode = match RoutingMode::parse(&config.mode) {
+            Some(RoutingMode::Keyword) => return None,
+            Some(mode) => mode,
+            None => {
+                tracing::warn!("Unknown agents.routing.mode {:?}; using keyword routing", config.mode);
+                return None;
+            }
+        };
+        let path = config.model_path.as_ref().map(PathBuf::from).unwrap_or_else(RoutingModel::default_path);
+        match RoutingModel::load(&path) {
+            Ok(model) => {
+                tracing::info!("Routing model loaded ({:?}, {} agents, {} examples)", mode, model.labels.len(), model.examples);
+                Some(Self { mode, model, min_confidence: config.min_confidence as f32, shadow: Mutex::new(Evaluation::default()) })
+            }
+            Err(e) => {
+                tracing::warn!("Routing model unavailable, using keyword routing: {:#}", e);
+                None
+            }
+        }
+    }
+
+    pub fn mode(&self) -> RoutingMode {
+        self.mode
+    }
+
+    /// Predict for `features` and record it against the keyword pick.
+    /// Returns the prediction, and the agent to use instead of
+    /// `keyword_agent` if the model takes over.
+    pub fn route(&self, features: &[u32], keyword_agent: &str) -> (Prediction, Option<String>) {
+        let prediction = self.model.predict(features);
+        if let Ok(mut shadow) = self.shadow.lock() {
+            shadow.record(keyword_agent, &prediction.agent);
+        }
+        let takeover = (self.mode == RoutingMode::Learned
+            && prediction.confidence >= self.min_confidence
+            && prediction.agent != keyword_agent)
+            .then(|| prediction.agent.clone());
+        (prediction, takeover)
+    }
+
+    /// Agreement with keyword scoring since startup.
+    pub fn shadow_report(&self) -> Evaluation {
+        self.shadow.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+}
+
+/// Write routing log entries from the last `days` days as JSONL.
+/// Returns how many entries were written.
+#[cfg(feature = "dynamodb-backend")]
+pub async fn export(table: &str, days: u32, out: &mut dyn Write) -> Result<usize> {
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    let aws = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+    let dynamo = aws_sdk_dynamodb::Client::new(&aws);
+    let today = chrono::Utc::now().date_naive();
+    let mut written = 0;
+    for back in 0..days {
+        let date = (today - chrono::Duration::days(back as i64)).format("%Y-%m-%d").to_string();
+        let mut start_key = None;
+        loop {
+            let page = dynamo
+                .query()
+                .table_name(table)
+                .key_condition_expression("pk = :pk")
+                .expression_attribute_values(":pk", AttributeValue::S(format!("ROUTING_LOG#{}", date)))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .with_context(|| format!("querying routing log for {}", date))?;
+            for item in page.items() {
+                if let Some(AttributeValue::S(data)) = item.get("data") {
+                    writeln!(out, "{}", data)?;
+                    written += 1;
+                }
+            }
+            start_key = page.last_evaluated_key().cloned();
+            if start_key.is_none() {
+                break;
+            }
+        }
+    }
+    Ok(written)
+}
+
+#[cfg(not(feature = "dynamodb-backend"))]
+pub async fn export(_table: &str, _days: u32, _out: &mut dyn Write) -> Result<usize> {
+    bail!("Exporting the routing log requires the dynamodb-backend feature")
+}
+
+/// Delete routing log entries older than `retention_days`, looking back
+/// `lookback_days` further for partitions DynamoDB's TTL hasn't removed yet.
+/// Returns how many entries were deleted.
+#[cfg(feature = "dynamodb-backend")]
+pub async fn purge(table: &str, retention_days: u32, lookback_days: u32) -> Result<usize> {
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    let aws = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+    let dynamo = aws_sdk_dynamodb::Client::new(&aws);
+    let today = chrono::Utc::now().date_naive();
+    let mut deleted = 0;
+    for back in retention_days..retention_days + lookback_days {
+        let pk = format!("ROUTING_LOG#{}", (today - chrono::Duration::days(back as i64)).format("%Y-%m-%d"));
+        let mut start_key = None;
+        loop {
+            let page = dynamo
+                .query()
+                .table_name(table)
+                .key_condition_expression("pk = :pk")
+                .expression_attribute_values(":pk", AttributeValue::S(pk.clone()))
+                .projection_expression("pk, sk")
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .with_context(|| format!("querying {}", pk))?;
+            for item in page.items() {
+                dynamo
+                    .delete_item()
+                    .table_name(table)
+                    .set_key(Some(item.clone()))
+                    .send()
+                    .await
+                    .with_context(|| format!("deleting from {}", pk))?;
+                deleted += 1;
+            }
+            start_key = page.last_evaluated_key().cloned();
+            if start_key.is_none() {
+                break;
+            }
+        }
+    }
+    Ok(deleted)
+}
+
+#[cfg(not(feature = "dynamodb-backend"))]
+pub async fn purge(_table: &str, _retention_days: u32, _lookback_days: u32) -> Result<usize> {
+    bail!("Purging the routing log requires the dynamodb-backend feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example(text: &str, label: &str) -> TrainingExample {
+        let input = RouteInput { text, channel: "web", device: "pc", language: "ja" };
+        TrainingExample { features: features(&input), label: label.into(), weight: 1.0 }
+    }
+
+    #[test]
+    fn test_features_are_stable_and_text_free() {
+        let input = RouteInput { text: "Rustのバグを直して", channel: "line", device: "mobile", language: "ja" };
+        let a = features(&input);
+        assert_eq!(a, features(&input));
+        assert!(a.windows(2).all(|w| w[0] < w[1]));
+        assert!(a.iter().all(|&f| (f as usize) < FEATURE_DIM));
+        let other = features(&RouteInput { channel: "telegram", ..input });
+        assert_ne!(a, other);
+    }
+
+    #[test]
+    fn test_train_and_predict() {
+        let mut examples = Vec::new();
+        for _ in 0..5 {
+            examples.push(example("このコードのバグを直して", "coder"));
+            examples.push(example("rust compile error in my function", "coder"));
+            examples.push(example("明日の東京の天気を調べて", "researcher"));
+            examples.push(example("latest news about the election", "researcher"));
+        }
+        let model = train(&examples, &TrainOptions::default()).unwrap();
+        assert_eq!(model.predict(&example("関数のバグ", "").features).agent, "coder");
+        assert_eq!(model.predict(&example("大阪の天気は", "").features).agent, "researcher");
+        assert_eq!(evaluate(&model, &examples).agreement(), 1.0);
+
+        assert!(train(&examples[..1], &TrainOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_examples_from_log() {
+        let ok = serde_json::json!({
+            "agent_selected": "coder", "agent_score": 100, "route_features": [1, 7, 42],
+            "error": false, "timed_out": false,
+        });
+        let example = TrainingExample::from_log(&ok).unwrap();
+        assert_eq!(example.features, vec![1, 7, 42]);
+        assert_eq!(example.weight, EXPLICIT_WEIGHT);
+
+        let failed = serde_json::json!({"agent_selected": "coder", "route_features": [1], "error": true});
+        assert!(TrainingExample::from_log(&failed).is_none());
+        let own = serde_json::json!({"agent_selected": "coder", "route_features": [1], "learned_override": true});
+        assert!(TrainingExample::from_log(&own).is_none());
+        let legacy = serde_json::json!({"agent_selected": "coder", "agent_score": 3});
+        assert!(TrainingExample::from_log(&legacy).is_none());
+
+        let row = serde_json::json!({"agent_selected": "coder", "explicit": true, "route_features": [1], "count": 2});
+        assert_eq!(TrainingExample::from_log(&row).unwrap().weight, 2.0 * EXPLICIT_WEIGHT);
+    }
+
+    #[test]
+    fn test_takeover_only_when_learned_and_confident() {
+        let examples: Vec<_> = (0..5)
+            .flat_map(|_| [example("バグを直して", "coder"), example("天気を調べて", "researcher")])
+            .collect();
+        let model = train(&examples, &TrainOptions::default()).unwrap();
+        let bug = example("バグを直して", "").features;
+        let router = |mode, min_confidence| Router {
+            mode,
+            model: model.clone(),
+            min_confidence,
+            shadow: Mutex::new(Evaluation::default()),
+        };
+
+        let shadow = router(RoutingMode::Shadow, 0.0);
+        let (prediction, takeover) = shadow.route(&bug, "assistant");
+        assert_eq!(prediction.agent, "coder");
+        assert_eq!(takeover, None);
+        assert_eq!(shadow.shadow_report().agreed, 0);
+
+        assert_eq!(router(RoutingMode::Learned, 0.5).route(&bug, "assistant").1.as_deref(), Some("coder"));
+        assert_eq!(router(RoutingMode::Learned, 1.01).route(&bug, "assistant").1, None);
+        assert_eq!(router(RoutingMode::Learned, 0.5).route(&bug, "coder").1, None);
+    }
+}
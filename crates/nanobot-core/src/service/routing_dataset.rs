@@ -0,0 +1,374 @@
+//! Anonymized routing datasets.
+//!
+//! Raw routing log entries carry a session hash, a timestamp and exact
+//! lengths and latencies, which together can single out a user. `chatweb
+//! routing export` passes them through [`anonymize`] before anything is
+//! written:
+//!
+//! 1. Failed, timed-out and model-routed requests are dropped (they are not
+//!    training data), and each session contributes at most
+//!    `max_per_session` requests.
+//! 2. Hashed message features seen in fewer than `k` sessions are removed,
+//!    so rare words (names, ids) can't be matched back to a message.
+//! 3. Lengths and latencies become buckets and the timestamp becomes an ISO
+//!    week; the session hash is not kept.
+//! 4. Every combination of channel, device, language, plan, buckets and
+//!    week must cover `k` sessions. Smaller groups are generalized (device,
+//!    plan, latency and week become `*`) and suppressed if still too small.
+//!
+//! The result is [`DatasetRow`]s that `chatweb routing train` reads like
+//! raw entries. Optional [`AggregateRow`] counts per week, channel,
+//! language and agent get Laplace noise calibrated to `epsilon`
+//! (ε-differential privacy, with the per-session cap as sensitivity) and are
+//! only released from `k` up.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Value of a quasi-identifier that was generalized away.
+pub const GENERALIZED: &str = "*";
+
+#[derive(Debug, Clone)]
+pub struct AnonymizeOptions {
+    /// Smallest number of sessions a released group or feature may describe.
+    pub k: usize,
+    /// Requests counted per session; bounds one user's influence.
+    pub max_per_session: usize,
+    /// Privacy budget of the aggregate counts; 0 skips them.
+    pub epsilon: f64,
+    /// Seed for the noise (tests); random otherwise.
+    pub seed: Option<u64>,
+}
+
+impl Default for AnonymizeOptions {
+    fn default() -> Self {
+        Self { k: 5, max_per_session: 20, epsilon: 1.0, seed: None }
+    }
+}
+
+/// One anonymized training example (or several identical ones, `count`).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct DatasetRow {
+    pub agent_selected: String,
+    /// The user picked the agent (`@agent`, `/agent`).
+    pub explicit: bool,
+    pub route_features: Vec<u32>,
+    pub channel: String,
+    pub device: String,
+    pub language: String,
+    pub user_plan: String,
+    /// Message length bucket, e.g. `50-99`.
+    pub length: String,
+    /// Response time bucket, e.g. `1-3s`.
+    pub latency: String,
+    /// ISO week, e.g. `2026-W42`.
+    pub week: String,
+    pub count: u32,
+}
+
+impl DatasetRow {
+    fn quasi_identifiers(&self) -> [&str; 7] {
+        [&self.channel, &self.device, &self.language, &self.user_plan, &self.length, &self.latency, &self.week]
+    }
+
+    fn generalize(&mut self) {
+        for field in [&mut self.device, &mut self.user_plan, &mut self.latency, &mut self.week] {
+            *field = GENERALIZED.to_string();
+        }
+    }
+}
+
+/// Noisy request count for one week, channel, language and agent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggregateRow {
+    pub week: String,
+    pub channel: String,
+    pub language: String,
+    pub agent_selected: String,
+    pub count: u64,
+}
+
+/// What [`anonymize`] kept and dropped.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    pub read: usize,
+    /// Failed, timed-out, model-routed or featureless entries.
+    pub unusable: usize,
+    /// Entries over a session's `max_per_session`.
+    pub over_cap: usize,
+    /// Distinct features removed for being seen in fewer than `k` sessions.
+    pub rare_features: usize,
+    /// Examples whose quasi-identifiers were generalized.
+    pub generalized: usize,
+    /// Examples in groups smaller than `k` even after generalizing.
+    pub suppressed: usize,
+    /// Examples released (sum of row counts).
+    pub released: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Dataset {
+    pub rows: Vec<DatasetRow>,
+    pub aggregates: Vec<AggregateRow>,
+    pub report: Report,
+}
+
+pub fn length_bucket(chars: u64) -> &'static str {
+    match chars {
+        0..=19 => "0-19",
+        20..=49 => "20-49",
+        50..=99 => "50-99",
+        100..=199 => "100-199",
+        200..=499 => "200-499",
+        500..=999 => "500-999",
+        _ => "1000+",
+    }
+}
+
+pub fn latency_bucket(ms: u64) -> &'static str {
+    match ms {
+        0..=999 => "<1s",
+        1000..=2999 => "1-3s",
+        3000..=9999 => "3-10s",
+        10000..=29999 => "10-30s",
+        _ => "30s+",
+    }
+}
+
+/// ISO week of an RFC 3339 timestamp; `*` if it doesn't parse.
+pub fn week_bucket(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.format("%G-W%V").to_string())
+        .unwrap_or_else(|_| GENERALIZED.to_string())
+}
+
+/// A usable raw entry with the session it came from.
+struct Candidate {
+    session: String,
+    row: DatasetRow,
+}
+
+fn candidate(entry: &serde_json::Value) -> Option<Candidate> {
+    if ["error", "timed_out", "learned_override"].iter().any(|k| entry[*k].as_bool() == Some(true)) {
+        return None;
+    }
+    let mut features: Vec<u32> = entry["route_features"]
+        .as_array()?
+        .iter()
+        .filter_map(|v| v.as_u64().map(|n| n as u32))
+        .collect();
+    features.sort_unstable();
+    features.dedup();
+    let text = |key: &str| entry[key].as_str().filter(|s| !s.is_empty()).unwrap_or("unknown").to_string();
+    Some(Candidate {
+        session: entry["session_hash"].as_str().unwrap_or("").to_string(),
+        row: DatasetRow {
+            agent_selected: entry["agent_selected"].as_str()?.to_string(),
+            explicit: entry["agent_score"].as_u64() == Some(100),
+            route_features: features,
+            channel: text("channel"),
+            device: text("device"),
+            language: text("language"),
+            user_plan: text("user_plan"),
+            length: length_bucket(entry["message_len"].as_u64().unwrap_or(0)).to_string(),
+            latency: latency_bucket(entry["response_time_ms"].as_u64().unwrap_or(0)).to_string(),
+            week: week_bucket(entry["timestamp"].as_str().unwrap_or("")),
+            count: 1,
+        },
+    })
+}
+
+/// A Laplace(0, `scale`) sample.
+fn laplace(rng: &mut impl Rng, scale: f64) -> f64 {
+    let u: f64 = rng.gen_range(-0.5..0.5);
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+}
+
+/// Keep the rows whose quasi-identifiers cover at least `k` sessions;
+/// return the others.
+fn split_by_group_size(rows: Vec<Candidate>, k: usize) -> (Vec<Candidate>, Vec<Candidate>) {
+    let mut sessions: HashMap<[String; 7], HashSet<&str>> = HashMap::new();
+    for c in &rows {
+        sessions.entry(c.row.quasi_identifiers().map(str::to_string)).or_default().insert(&c.session);
+    }
+    let large: HashSet<[String; 7]> =
+        sessions.into_iter().filter(|(_, s)| s.len() >= k).map(|(key, _)| key).collect();
+    rows.into_iter().partition(|c| large.contains(&c.row.quasi_identifiers().map(str::to_string)))
+}
+
+/// Anonymize raw routing log entries (see the module docs).
+pub fn anonymize(entries: &[serde_json::Value], options: &AnonymizeOptions) -> Dataset {
+    let k = options.k.max(1);
+    let mut report = Report { read: entries.len(), ..Default::default() };
+
+    // Usable entries, at most `max_per_session` per session
+    let mut per_session: HashMap<String, usize> = HashMap::new();
+    let mut candidates = Vec::new();
+    for entry in entries {
+        let Some(candidate) = candidate(entry) else {
+            report.unusable += 1;
+            continue;
+        };
+        let seen = per_session.entry(candidate.session.clone()).or_default();
+        if *seen >= options.max_per_session {
+            report.over_cap += 1;
+            continue;
+        }
+        *seen += 1;
+        candidates.push(candidate);
+    }
+
+    // Features used in fewer than k sessions
+    let mut feature_sessions: HashMap<u32, HashSet<&str>> = HashMap::new();
+    for c in &candidates {
+        for &f in &c.row.route_features {
+            feature_sessions.entry(f).or_default().insert(&c.session);
+        }
+    }
+    let rare: HashSet<u32> = feature_sessions.iter().filter(|(_, s)| s.len() < k).map(|(&f, _)| f).collect();
+    report.rare_features = rare.len();
+
+    let aggregates = if options.epsilon > 0.0 {
+        noisy_aggregates(&candidates, options, k)
+    } else {
+        Vec::new()
+    };
+
+    let mut rows = Vec::new();
+    for mut c in candidates {
+        c.row.route_features.retain(|f| !rare.contains(f));
+        if c.row.route_features.is_empty() {
+            report.unusable += 1;
+        } else {
+            rows.push(c);
+        }
+    }
+
+    // k-anonymity over the quasi-identifiers, generalizing once before suppressing
+    let (kept, small) = split_by_group_size(rows, k);
+    report.generalized = small.len();
+    let generalized = small
+        .into_iter()
+        .map(|mut c| {
+            c.row.generalize();
+            c
+        })
+        .collect();
+    let (kept_generalized, suppressed) = split_by_group_size(generalized, k);
+    report.suppressed = suppressed.len();
+
+    // Identical examples become one row with a count
+    let mut merged: BTreeMap<DatasetRow, u32> = BTreeMap::new();
+    for c in kept.into_iter().chain(kept_generalized) {
+        *merged.entry(c.row).or_default() += 1;
+    }
+    let rows: Vec<DatasetRow> = merged.into_iter().map(|(row, count)| DatasetRow { count, ..row }).collect();
+    report.released = rows.iter().map(|r| r.count as usize).sum();
+
+    Dataset { rows, aggregates, report }
+}
+
+fn noisy_aggregates(candidates: &[Candidate], options: &AnonymizeOptions, k: usize) -> Vec<AggregateRow> {
+    let mut counts: BTreeMap<[&str; 4], u64> = BTreeMap::new();
+    for c in candidates {
+        let row = &c.row;
+        *counts.entry([&row.week, &row.channel, &row.language, &row.agent_selected]).or_default() += 1;
+    }
+    let mut rng = match options.seed {
+        Some(seed) => rand::rngs::StdRng::seed_from_u64(seed),
+        None => rand::rngs::StdRng::from_entropy(),
+    };
+    let scale = options.max_per_session.max(1) as f64 / options.epsilon;
+    counts
+        .into_iter()
+        .filter_map(|([week, channel, language, agent], count)| {
+            let noisy = (count as f64 + laplace(&mut rng, scale)).round();
+            (noisy >= k as f64).then(|| AggregateRow {
+                week: week.to_string(),
+                channel: channel.to_string(),
+                language: language.to_string(),
+                agent_selected: agent.to_string(),
+                count: noisy as u64,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(session: &str, agent: &str, features: &[u32], channel: &str) -> serde_json::Value {
+        json!({
+            "message_len": 42,
+            "language": "ja",
+            "channel": channel,
+            "device": "pc",
+            "user_plan": "free",
+            "agent_selected": agent,
+            "agent_score": 3,
+            "response_time_ms": 1500,
+            "route_features": features,
+            "timed_out": false,
+            "error": false,
+            "learned_override": false,
+            "timestamp": "2026-10-14T09:30:00Z",
+            "session_hash": session,
+        })
+    }
+
+    #[test]
+    fn test_buckets() {
+        assert_eq!(length_bucket(42), "20-49");
+        assert_eq!(length_bucket(5000), "1000+");
+        assert_eq!(latency_bucket(1500), "1-3s");
+        assert_eq!(week_bucket("2026-10-14T09:30:00Z"), "2026-W42");
+        assert_eq!(week_bucket("yesterday"), GENERALIZED);
+    }
+
+    #[test]
+    fn test_anonymize() {
+        let mut entries: Vec<_> = (0..6).map(|i| entry(&format!("s{}", i), "coder", &[1, 2, 900 + i], "web")).collect();
+        // One session flooding the log
+        entries.extend((0..30).map(|_| entry("s0", "coder", &[1, 2], "web")));
+        // A lone LINE user is a group of one
+        entries.push(entry("s9", "assistant", &[1, 2], "line"));
+        entries.push(json!({"agent_selected": "coder", "error": true, "route_features": [1]}));
+
+        let options = AnonymizeOptions { k: 3, max_per_session: 2, epsilon: 0.0, seed: Some(7) };
+        let dataset = anonymize(&entries, &options);
+        let report = &dataset.report;
+        assert_eq!((report.read, report.unusable, report.over_cap), (38, 1, 29));
+        // 900..905 each appear in a single session
+        assert_eq!(report.rare_features, 6);
+        assert_eq!((report.generalized, report.suppressed), (1, 1));
+        assert_eq!(report.released, 7);
+        assert_eq!(dataset.rows.len(), 1);
+        let row = &dataset.rows[0];
+        assert_eq!((row.route_features.as_slice(), row.count, row.week.as_str()), (&[1, 2][..], 7, "2026-W42"));
+        assert!(dataset.aggregates.is_empty());
+
+        // The rows train like raw entries
+        let value = serde_json::to_value(row).unwrap();
+        assert!(value.get("session_hash").is_none() && value.get("timestamp").is_none());
+        let example = crate::service::router::TrainingExample::from_log(&value).unwrap();
+        assert_eq!((example.label.as_str(), example.weight), ("coder", 7.0));
+    }
+
+    #[test]
+    fn test_noisy_aggregates() {
+        let entries: Vec<_> = (0..200).map(|i| entry(&format!("s{}", i), "coder", &[1], "web")).collect();
+        let options = AnonymizeOptions { k: 5, max_per_session: 1, epsilon: 1.0, seed: Some(1) };
+        let aggregates = anonymize(&entries, &options).aggregates;
+        assert_eq!(aggregates.len(), 1);
+        assert!((180..=220).contains(&aggregates[0].count));
+
+        // Groups below k after noise are not released
+        let few: Vec<_> = (0..2).map(|i| entry(&format!("s{}", i), "coder", &[1], "web")).collect();
+        let options = AnonymizeOptions { k: 50, ..options };
+        assert!(anonymize(&few, &options).aggregates.is_empty());
+    }
+}
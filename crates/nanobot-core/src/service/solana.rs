@@ -5,6 +5,8 @@
 use anyhow::{Result, bail};
 use serde_json::Value;
 
+use crate::util::http::{self, ClientClass};
+
 /// Token-2022 program ID.
 const TOKEN_2022_PROGRAM: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
 /// Legacy SPL Token program ID.
@@ -56,7 +58,7 @@ pub async fn get_enai_balance(wallet_address: &str) -> Result<u64> {
     }
 
     let program = token_program_id();
-    let client = reqwest::Client::new();
+    let client = http::client_for(ClientClass::General);
     let resp = client
         .post(&rpc_url())
         .json(&serde_json::json!({
@@ -91,7 +93,7 @@ pub async fn verify_enai_payment(tx_signature: &str, required_enai_raw: u64) ->
         bail!("SOLANA_TREASURY_WALLET not configured");
     }
 
-    let client = reqwest::Client::new();
+    let client = http::client_for(ClientClass::General);
     let resp = client
         .post(&rpc_url())
         .json(&serde_json::json!({
@@ -0,0 +1,158 @@
+//! Resumable chat streams.
+//!
+//! Every event of a `/api/v1/chat/stream` response is kept in a per-request
+//! buffer and sent with the SSE id `<stream>:<n>`. A client whose connection
+//! drops sends the request again with `Last-Event-ID`, and gets the events
+//! after `n` (then the live rest) instead of a new answer. The response keeps
+//! being produced while nobody listens, and buffers are dropped
+//! [`RESUME_WINDOW`] after the response finished.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use futures::Stream;
+use tokio::sync::watch;
+
+/// How long a finished stream can still be resumed.
+pub const RESUME_WINDOW: Duration = Duration::from_secs(300);
+
+#[derive(Default)]
+pub struct StreamBuffers {
+    streams: Mutex<HashMap<String, Arc<Buffer>>>,
+}
+
+struct Buffer {
+    /// Session that started the stream; only it may resume.
+    owner: String,
+    state: Mutex<BufferState>,
+    /// Number of events so far; the sender goes away when the stream ends.
+    len: watch::Receiver<usize>,
+}
+
+#[derive(Default)]
+struct BufferState {
+    events: Vec<String>,
+    finished: Option<Instant>,
+}
+
+/// Appends a stream's events; dropping it ends the stream.
+pub struct StreamWriter {
+    buffer: Arc<Buffer>,
+    len: watch::Sender<usize>,
+}
+
+impl StreamBuffers {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Arc<Buffer>>> {
+        self.streams.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Start buffering a stream for `owner`; returns its id.
+    pub fn create(&self, owner: &str) -> (String, StreamWriter) {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let (len_tx, len_rx) = watch::channel(0);
+        let buffer = Arc::new(Buffer {
+            owner: owner.to_string(),
+            state: Mutex::new(BufferState::default()),
+            len: len_rx,
+        });
+        let mut streams = self.lock();
+        streams.retain(|_, b| b.state().finished.is_none_or(|at| at.elapsed() < RESUME_WINDOW));
+        streams.insert(id.clone(), buffer.clone());
+        (id, StreamWriter { buffer, len: len_tx })
+    }
+
+    /// Events of stream `id` after event `after` (1-based), live until the
+    /// stream ends, with their numbers. `None` when the stream is unknown,
+    /// expired or belongs to another session.
+    pub fn events(&self, id: &str, owner: &str, after: usize) -> Option<impl Stream<Item = (usize, String)>> {
+        let buffer = self.lock().get(id).filter(|b| b.owner == owner)?.clone();
+        let mut len = buffer.len.clone();
+        Some(async_stream::stream! {
+            let mut next = after;
+            loop {
+                let (events, finished) = {
+                    let state = buffer.state();
+                    (state.events.get(next..).unwrap_or_default().to_vec(), state.finished.is_some())
+                };
+                for data in events {
+                    next += 1;
+                    yield (next, data);
+                }
+                if finished {
+                    break;
+                }
+                // An error means the writer is gone: the next pass sees `finished`
+                let _ = len.changed().await;
+            }
+        })
+    }
+}
+
+impl Buffer {
+    fn state(&self) -> MutexGuard<'_, BufferState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl StreamWriter {
+    pub fn push(&self, data: String) {
+        let len = {
+            let mut state = self.buffer.state();
+            state.events.push(data);
+            state.events.len()
+        };
+        self.len.send_replace(len);
+    }
+}
+
+impl Drop for StreamWriter {
+    fn drop(&mut self) {
+        self.buffer.state().finished = Some(Instant::now());
+    }
+}
+
+/// Parse a `Last-Event-ID` of the form `<stream>:<n>`.
+pub fn parse_event_id(id: &str) -> Option<(&str, usize)> {
+    let (stream, n) = id.trim().rsplit_once(':')?;
+    Some((stream, n.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_resume_after_event() {
+        let buffers = StreamBuffers::new();
+        let (id, writer) = buffers.create("s1");
+        writer.push("a".into());
+        writer.push("b".into());
+        assert!(buffers.events(&id, "someone-else", 0).is_none());
+
+        let resumed = tokio::spawn({
+            let stream = buffers.events(&id, "s1", 1).unwrap();
+            async move { stream.collect::<Vec<_>>().await }
+        });
+        tokio::task::yield_now().await;
+        writer.push("c".into());
+        drop(writer);
+        assert_eq!(resumed.await.unwrap(), vec![(2, "b".to_string()), (3, "c".to_string())]);
+
+        // Finished streams replay in full until they expire
+        let replay: Vec<_> = buffers.events(&id, "s1", 0).unwrap().collect().await;
+        assert_eq!(replay.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_event_id() {
+        assert_eq!(parse_event_id("abc123:7"), Some(("abc123", 7)));
+        assert_eq!(parse_event_id("abc123"), None);
+        assert_eq!(parse_event_id("abc:x"), None);
+    }
+}
@@ -0,0 +1,156 @@
+//! Crash safety for the gateway.
+//!
+//! [`install_panic_hook`] logs every panic with its location and a backtrace,
+//! and reports it to Sentry when built with the `sentry` feature and
+//! `SENTRY_DSN` is set. [`supervise`] runs a long-lived subsystem (a channel,
+//! the cron scheduler, the workflow runner) as a task that is restarted with
+//! backoff when it panics or fails, instead of dying silently. Catching the
+//! panic needs `panic = "unwind"`, which the release profiles set.
+
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::Once;
+use std::time::{Duration, Instant};
+
+use futures::FutureExt;
+use tracing::{error, info};
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// A run lasting this long was healthy: the next failure restarts quickly again.
+const HEALTHY_RUN: Duration = Duration::from_secs(300);
+
+static PANIC_HOOK: Once = Once::new();
+
+/// Log panics through `tracing` with a backtrace (and report them to Sentry).
+/// Installed once; later calls do nothing.
+pub fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        #[cfg(feature = "sentry")]
+        sentry_report::init();
+        std::panic::set_hook(Box::new(|info| {
+            let message = panic_message(info.payload());
+            let location = info
+                .location()
+                .map(|l| format!("{}:{}", l.file(), l.line()))
+                .unwrap_or_else(|| "unknown location".to_string());
+            let thread = std::thread::current();
+            let backtrace = std::backtrace::Backtrace::force_capture();
+            error!(
+                "Panic in thread '{}' at {}: {}\n{}",
+                thread.name().unwrap_or("<unnamed>"),
+                location,
+                message,
+                backtrace
+            );
+            #[cfg(feature = "sentry")]
+            sentry_report::panic(&message, &location);
+        }));
+    });
+}
+
+/// The message a panic was raised with.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+/// Run the subsystem `name` as a background task, starting it again with
+/// `run` whenever it panics or returns an error. Returning `Ok` stops it.
+pub fn supervise<F, Fut>(name: impl Into<String>, mut run: F) -> tokio::task::JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    let name = name.into();
+    tokio::spawn(async move {
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            let started = Instant::now();
+            let failure = match AssertUnwindSafe(run()).catch_unwind().await {
+                Ok(Ok(())) => {
+                    info!("{} stopped", name);
+                    return;
+                }
+                Ok(Err(e)) => format!("failed: {}", e),
+                Err(payload) => format!("panicked: {}", panic_message(payload.as_ref())),
+            };
+            if started.elapsed() >= HEALTHY_RUN {
+                backoff = INITIAL_BACKOFF;
+            }
+            error!("{} {}; restarting in {}s", name, failure, backoff.as_secs());
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    })
+}
+
+/// Panic reports sent to Sentry (`SENTRY_DSN`).
+#[cfg(feature = "sentry")]
+mod sentry_report {
+    use std::sync::OnceLock;
+
+    static GUARD: OnceLock<sentry::ClientInitGuard> = OnceLock::new();
+
+    pub(super) fn init() {
+        let Ok(dsn) = std::env::var("SENTRY_DSN") else { return };
+        let guard = sentry::init((
+            dsn,
+            sentry::ClientOptions {
+                release: sentry::release_name!(),
+                attach_stacktrace: true,
+                ..Default::default()
+            },
+        ));
+        if guard.is_enabled() {
+            let _ = GUARD.set(guard);
+        }
+    }
+
+    pub(super) fn panic(message: &str, location: &str) {
+        if GUARD.get().is_none() {
+            return;
+        }
+        sentry::capture_message(&format!("panic at {}: {}", location, message), sentry::Level::Fatal);
+        if let Some(client) = sentry::Hub::current().client() {
+            client.flush(Some(std::time::Duration::from_secs(2)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_restarts_after_panic() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        let task = supervise("flaky", move || {
+            let runs = counter.clone();
+            async move {
+                match runs.fetch_add(1, Ordering::SeqCst) {
+                    0 => panic!("first run crashes"),
+                    1 => anyhow::bail!("second run fails"),
+                    _ => Ok(()),
+                }
+            }
+        });
+        task.await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload = std::panic::catch_unwind(|| panic!("boom {}", 1)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "boom 1");
+    }
+}
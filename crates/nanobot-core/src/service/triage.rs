@@ -0,0 +1,741 @@
+//! Inbox-zero email triage.
+//!
+//! Configured in `<workspace>/triage.yaml`:
+//!
+//! ```yaml
+//! schedule: "0 8 * * *"          # daily, in `timezone` (agents.defaults.timezone otherwise)
+//! notify: { channel: telegram, to: "123456" }
+//! query: "in:inbox is:unread newer_than:1d"
+//! rules:
+//!   - name: newsletters
+//!     from: "(?i)newsletter|no-?reply"
+//!     priority: low               # low mail is archived unless `archive: false`
+//!   - name: boss
+//!     from: "boss@example\\.com"
+//!     priority: high
+//!   - name: receipts
+//!     subject: "(?i)receipt|領収書"
+//!     priority: normal
+//!     archive: true
+//! ```
+//!
+//! Each run lists new mail, applies the first matching rule, asks the model
+//! to classify the rest and to write replies where one is needed, archives
+//! low-priority mail, saves replies as drafts for the user to approve in
+//! their mail client, and posts a summary to `notify`. The gateway runs it
+//! as a cron job with payload kind [`TRIAGE_KIND`]. Processed message ids
+//! are kept in `<data dir>/triage/state.json` so mail is triaged once.
+
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::provider::LlmProvider;
+use crate::service::cron::{CronPayload, CronSchedule, CronService};
+use crate::session::locale::Locale;
+use crate::types::Message;
+use crate::util::http::{self, ClientClass};
+
+/// Cron payload kind for scheduled triage runs.
+pub const TRIAGE_KIND: &str = "triage";
+
+/// Processed ids remembered beyond this count are dropped, oldest first.
+const MAX_SEEN: usize = 2000;
+
+/// Emails shown per priority in the summary.
+const SUMMARY_PER_GROUP: usize = 10;
+
+fn default_schedule() -> String {
+    "0 8 * * *".to_string()
+}
+
+fn default_query() -> String {
+    "in:inbox is:unread newer_than:1d".to_string()
+}
+
+fn default_max_emails() -> usize {
+    25
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_token_env() -> String {
+    "GOOGLE_REFRESH_TOKEN".to_string()
+}
+
+/// How much an email matters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    High,
+    Normal,
+    Low,
+}
+
+impl Priority {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "high" | "urgent" => Some(Priority::High),
+            "normal" | "medium" => Some(Priority::Normal),
+            "low" => Some(Priority::Low),
+            _ => None,
+        }
+    }
+}
+
+/// Where the summary goes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotifyTarget {
+    pub channel: String,
+    pub to: String,
+}
+
+/// A rule classifying mail without asking the model. Patterns are regexes;
+/// every pattern given must match.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriageRule {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// Matched against the message preview.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    pub priority: Priority,
+    /// Archive matches; defaults to `true` for low priority.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<bool>,
+}
+
+impl TriageRule {
+    fn matches(&self, email: &Email) -> anyhow::Result<bool> {
+        let fields = [(&self.from, &email.from), (&self.subject, &email.subject), (&self.body, &email.snippet)];
+        for (pattern, text) in fields {
+            if let Some(pattern) = pattern {
+                let re = regex::Regex::new(pattern).map_err(|e| anyhow::anyhow!("triage rule '{}': {}", self.name, e))?;
+                if !re.is_match(text) {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(self.from.is_some() || self.subject.is_some() || self.body.is_some())
+    }
+
+    fn archives(&self) -> bool {
+        self.archive.unwrap_or(self.priority == Priority::Low)
+    }
+}
+
+/// Contents of `triage.yaml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriageConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Cron expression for the daily run.
+    #[serde(default = "default_schedule")]
+    pub schedule: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify: Option<NotifyTarget>,
+    /// Mail search query (Gmail syntax).
+    #[serde(default = "default_query")]
+    pub query: String,
+    #[serde(default = "default_max_emails")]
+    pub max_emails: usize,
+    /// Ask the model to draft replies to mail that needs one.
+    #[serde(default = "default_true")]
+    pub draft_replies: bool,
+    /// Language of the summary and drafts.
+    #[serde(default)]
+    pub locale: Locale,
+    /// Environment variable holding the Google OAuth refresh token.
+    #[serde(default = "default_token_env")]
+    pub refresh_token_env: String,
+    /// Model for classification; the agent's model otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub rules: Vec<TriageRule>,
+}
+
+impl TriageConfig {
+    pub fn parse(yaml: &str) -> anyhow::Result<Self> {
+        let config: Self = serde_yaml::from_str(yaml)?;
+        for rule in &config.rules {
+            for pattern in [&rule.from, &rule.subject, &rule.body].into_iter().flatten() {
+                regex::Regex::new(pattern).map_err(|e| anyhow::anyhow!("triage rule '{}': {}", rule.name, e))?;
+            }
+        }
+        Ok(config)
+    }
+
+    /// Cron schedule of the daily run.
+    pub fn cron_schedule(&self, default_tz: Option<&str>) -> CronSchedule {
+        CronSchedule::Cron {
+            expr: self.schedule.clone(),
+            tz: self.timezone.clone().or_else(|| default_tz.map(str::to_string)),
+        }
+    }
+}
+
+/// A message in the inbox.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Email {
+    pub id: String,
+    #[serde(default)]
+    pub thread_id: String,
+    pub from: String,
+    pub subject: String,
+    #[serde(default)]
+    pub snippet: String,
+    /// `Message-ID` header, for threading replies.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub message_id: String,
+}
+
+/// Mail access used by a triage run.
+#[async_trait]
+pub trait Mailbox: Send + Sync {
+    /// Messages matching `query`, newest first.
+    async fn list(&self, query: &str, max: usize) -> anyhow::Result<Vec<Email>>;
+    /// Move a message out of the inbox.
+    async fn archive(&self, id: &str) -> anyhow::Result<()>;
+    /// Save an unsent reply to `email`; returns the draft id.
+    async fn draft_reply(&self, email: &Email, body: &str) -> anyhow::Result<String>;
+}
+
+/// What happened to one email.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Triaged {
+    pub email: Email,
+    pub priority: Priority,
+    /// Rule that classified it; `None` when the model did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reason: String,
+    pub archived: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub draft_id: Option<String>,
+}
+
+/// Result of a triage run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriageReport {
+    pub emails: Vec<Triaged>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+impl TriageReport {
+    /// Chat message summarizing the run.
+    pub fn summary(&self, locale: Locale) -> String {
+        if self.emails.is_empty() {
+            let mut text = locale.pick("📭 新着メールはありません。", "📭 No new email.").to_string();
+            for error in &self.errors {
+                text.push_str(&format!("\n⚠️ {}", error));
+            }
+            return text;
+        }
+        let archived = self.emails.iter().filter(|t| t.archived).count();
+        let drafts = self.emails.iter().filter(|t| t.draft_id.is_some()).count();
+        let mut lines = vec![match locale {
+            Locale::Ja => format!("📬 メール整理: {}件（アーカイブ {}件・返信下書き {}件）", self.emails.len(), archived, drafts),
+            Locale::En => format!("📬 Inbox triage: {} emails ({} archived, {} reply drafts)", self.emails.len(), archived, drafts),
+        }];
+        let groups = [
+            (Priority::High, locale.pick("🔴 重要", "🔴 Important")),
+            (Priority::Normal, locale.pick("🟡 通常", "🟡 Normal")),
+            (Priority::Low, locale.pick("⚪ 低", "⚪ Low")),
+        ];
+        for (priority, heading) in groups {
+            let group: Vec<&Triaged> = self.emails.iter().filter(|t| t.priority == priority).collect();
+            if group.is_empty() {
+                continue;
+            }
+            lines.push(String::new());
+            lines.push(format!("{} ({})", heading, group.len()));
+            for t in group.iter().take(SUMMARY_PER_GROUP) {
+                let mut line = format!("• {} — {}", sender_name(&t.email.from), t.email.subject);
+                if !t.reason.is_empty() {
+                    line.push_str(&format!(": {}", t.reason));
+                }
+                if t.draft_id.is_some() {
+                    line.push_str(locale.pick(" ✍️下書きあり", " ✍️ draft ready"));
+                }
+                if t.archived {
+                    line.push_str(" 📦");
+                }
+                lines.push(line);
+            }
+            if group.len() > SUMMARY_PER_GROUP {
+                lines.push(format!("  …+{}", group.len() - SUMMARY_PER_GROUP));
+            }
+        }
+        if drafts > 0 {
+            lines.push(String::new());
+            lines.push(
+                locale
+                    .pick("返信の下書きはメールの下書きフォルダにあります。確認して送信してください。", "Reply drafts are in your mail drafts — review and send them.")
+                    .to_string(),
+            );
+        }
+        for error in &self.errors {
+            lines.push(format!("⚠️ {}", error));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Display name of a `From` header: `"Yuki <y@example.com>"` → `Yuki`.
+fn sender_name(from: &str) -> &str {
+    match from.split_once('<') {
+        Some((name, _)) if !name.trim().is_empty() => name.trim().trim_matches('"'),
+        _ => from.trim().trim_matches(['<', '>']),
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TriageState {
+    #[serde(default)]
+    seen: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_run_at: Option<String>,
+}
+
+/// Triage configuration and state for one workspace.
+pub struct Triage {
+    config_path: PathBuf,
+    state_path: PathBuf,
+}
+
+impl Triage {
+    pub fn for_workspace(workspace: &Path) -> Self {
+        Self {
+            config_path: workspace.join("triage.yaml"),
+            state_path: crate::config::get_data_dir().join("triage").join("state.json"),
+        }
+    }
+
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
+    }
+
+    /// The workspace configuration; `None` if there is no `triage.yaml`.
+    pub fn load(&self) -> anyhow::Result<Option<TriageConfig>> {
+        match std::fs::read_to_string(&self.config_path) {
+            Ok(content) => TriageConfig::parse(&content).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Keep one cron job for the configured schedule (none when triage is
+    /// not configured or disabled).
+    pub fn sync_cron(&self, cron: &mut CronService, default_tz: Option<&str>) {
+        let wanted = match self.load() {
+            Ok(Some(config)) if config.enabled => Some(config.cron_schedule(default_tz)),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("Invalid {}: {}", self.config_path.display(), e);
+                None
+            }
+        };
+        let mut kept = false;
+        for job in cron.list_jobs(true).into_iter().filter(|j| j.payload.kind == TRIAGE_KIND) {
+            let current = match (&job.schedule, &wanted) {
+                (CronSchedule::Cron { expr, tz }, Some(CronSchedule::Cron { expr: e, tz: t })) => expr == e && tz == t,
+                _ => false,
+            };
+            if current && !kept {
+                kept = true;
+                continue;
+            }
+            cron.remove_job(&job.id);
+        }
+        if let (Some(schedule), false) = (wanted, kept) {
+            let payload = CronPayload {
+                kind: TRIAGE_KIND.to_string(),
+                message: String::new(),
+                deliver: false,
+                channel: None,
+                to: None,
+            };
+            cron.add_job_with_payload("triage", schedule, payload);
+        }
+    }
+
+    fn load_state(&self) -> TriageState {
+        crate::util::read_json(&self.state_path, "triage state")
+    }
+
+    fn save_state(&self, state: &TriageState) {
+        crate::util::write_json(&self.state_path, state, "triage state");
+    }
+
+    /// Triage new mail. Rules are applied first, the model classifies the
+    /// rest; archive and draft failures are reported, not fatal.
+    pub async fn run(
+        &self,
+        config: &TriageConfig,
+        mailbox: &dyn Mailbox,
+        provider: &dyn LlmProvider,
+        model: &str,
+    ) -> anyhow::Result<TriageReport> {
+        let mut state = self.load_state();
+        let emails: Vec<Email> = mailbox
+            .list(&config.query, config.max_emails)
+            .await?
+            .into_iter()
+            .filter(|e| !state.seen.contains(&e.id))
+            .collect();
+
+        let mut report = TriageReport::default();
+        let mut unmatched = Vec::new();
+        for email in emails {
+            match first_match(&config.rules, &email)? {
+                Some(rule) => report.emails.push(Triaged {
+                    priority: rule.priority,
+                    rule: Some(rule.name.clone()),
+                    reason: String::new(),
+                    archived: rule.archives(),
+                    draft_id: None,
+                    email,
+                }),
+                None => unmatched.push(email),
+            }
+        }
+
+        let mut replies = Vec::new();
+        if !unmatched.is_empty() {
+            let model = config.model.as_deref().unwrap_or(model);
+            let verdicts = match classify(provider, model, &unmatched, config).await {
+                Ok(v) => v,
+                Err(e) => {
+                    report.errors.push(format!("classification: {}", e));
+                    Vec::new()
+                }
+            };
+            for email in unmatched {
+                let verdict = verdicts.iter().find(|v| v.id == email.id);
+                let priority = verdict.map(|v| v.priority).unwrap_or(Priority::Normal);
+                if let Some(reply) = verdict.and_then(|v| v.reply.clone()).filter(|_| config.draft_replies) {
+                    replies.push((report.emails.len(), reply));
+                }
+                report.emails.push(Triaged {
+                    priority,
+                    rule: None,
+                    reason: verdict.map(|v| v.reason.clone()).unwrap_or_default(),
+                    archived: priority == Priority::Low,
+                    draft_id: None,
+                    email,
+                });
+            }
+        }
+
+        for (index, reply) in replies {
+            let triaged = &mut report.emails[index];
+            match mailbox.draft_reply(&triaged.email, &reply).await {
+                Ok(id) => triaged.draft_id = Some(id),
+                Err(e) => report.errors.push(format!("draft for '{}': {}", triaged.email.subject, e)),
+            }
+        }
+        for triaged in report.emails.iter_mut().filter(|t| t.archived) {
+            if let Err(e) = mailbox.archive(&triaged.email.id).await {
+                triaged.archived = false;
+                report.errors.push(format!("archive '{}': {}", triaged.email.subject, e));
+            }
+        }
+        report.emails.sort_by_key(|t| t.priority);
+
+        state.seen.extend(report.emails.iter().map(|t| t.email.id.clone()));
+        if state.seen.len() > MAX_SEEN {
+            state.seen.drain(..state.seen.len() - MAX_SEEN);
+        }
+        state.last_run_at = Some(crate::util::timestamp());
+        self.save_state(&state);
+        info!(
+            "Triage: {} emails, {} archived, {} drafts",
+            report.emails.len(),
+            report.emails.iter().filter(|t| t.archived).count(),
+            report.emails.iter().filter(|t| t.draft_id.is_some()).count()
+        );
+        Ok(report)
+    }
+
+    /// Scheduled run against Gmail: the summary and where to post it, or
+    /// `None` when triage isn't configured or has no `notify` target.
+    pub async fn run_scheduled(&self, provider: &dyn LlmProvider, model: &str) -> anyhow::Result<Option<(NotifyTarget, String)>> {
+        let Some(config) = self.load()?.filter(|c| c.enabled) else {
+            return Ok(None);
+        };
+        let mailbox = GmailMailbox::connect(&config).await?;
+        let report = self.run(&config, &mailbox, provider, model).await?;
+        Ok(config.notify.clone().map(|target| (target, report.summary(config.locale))))
+    }
+}
+
+fn first_match<'a>(rules: &'a [TriageRule], email: &Email) -> anyhow::Result<Option<&'a TriageRule>> {
+    for rule in rules {
+        if rule.matches(email)? {
+            return Ok(Some(rule));
+        }
+    }
+    Ok(None)
+}
+
+/// The model's verdict on one email.
+#[derive(Debug, Clone, PartialEq)]
+struct Verdict {
+    id: String,
+    priority: Priority,
+    reason: String,
+    reply: Option<String>,
+}
+
+fn parse_verdicts(reply: &str) -> Vec<Verdict> {
+    let value = crate::tool::extract::parse_json_reply(reply).unwrap_or(Value::Null);
+    let items = value.get("emails").cloned().unwrap_or(value);
+    items
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    let text = |key: &str| item.get(key).and_then(|v| v.as_str()).map(str::trim).unwrap_or("");
+                    let id = text("id");
+                    if id.is_empty() {
+                        return None;
+                    }
+                    let needs_reply = item.get("needsReply").and_then(|v| v.as_bool()).unwrap_or(false);
+                    Some(Verdict {
+                        id: id.to_string(),
+                        priority: Priority::parse(text("priority")).unwrap_or(Priority::Normal),
+                        reason: text("reason").to_string(),
+                        reply: Some(text("reply").to_string()).filter(|r| needs_reply && !r.is_empty()),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+async fn classify(
+    provider: &dyn LlmProvider,
+    model: &str,
+    emails: &[Email],
+    config: &TriageConfig,
+) -> anyhow::Result<Vec<Verdict>> {
+    let listing: Vec<Value> = emails
+        .iter()
+        .map(|e| json!({"id": e.id, "from": e.from, "subject": e.subject, "preview": e.snippet}))
+        .collect();
+    let language = config.locale.pick("Japanese", "English");
+    let drafting = if config.draft_replies {
+        format!(
+            "When a personal reply is expected, set needsReply to true and write a short, polite reply in {} \
+             as \"reply\" (no subject line, no signature placeholder). Never promise anything on the user's behalf.",
+            language
+        )
+    } else {
+        "Set needsReply to false.".to_string()
+    };
+    let system = format!(
+        "You triage the user's inbox. For each email decide its priority: \"high\" (needs the user today: \
+         people they know, deadlines, money, security), \"normal\", or \"low\" (newsletters, promotions, \
+         notifications). {} Give a reason of at most ten words in {}. Reply with JSON only: \
+         {{\"emails\": [{{\"id\": \"...\", \"priority\": \"high|normal|low\", \"reason\": \"...\", \"needsReply\": false, \"reply\": \"\"}}]}}",
+        drafting, language
+    );
+    let messages = vec![Message::system(&system), Message::user(Value::Array(listing).to_string())];
+    let response = provider.chat(&messages, None, model, 4096, 0.0).await?;
+    Ok(parse_verdicts(response.content.as_deref().unwrap_or("")))
+}
+
+/// Gmail through the REST API with an OAuth access token.
+pub struct GmailMailbox {
+    client: reqwest::Client,
+    access_token: String,
+}
+
+const GMAIL_API: &str = "https://www.googleapis.com/gmail/v1/users/me";
+
+impl GmailMailbox {
+    /// Connect with the refresh token in `config.refresh_token_env` (or the
+    /// service account).
+    pub async fn connect(config: &TriageConfig) -> anyhow::Result<Self> {
+        let token = std::env::var(&config.refresh_token_env).ok();
+        let access_token = crate::service::integrations::google_access_token(token.as_deref())
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        Ok(Self { client: http::client_for(ClientClass::General), access_token })
+    }
+
+    async fn call(&self, request: reqwest::RequestBuilder) -> anyhow::Result<Value> {
+        let response = request.bearer_auth(&self.access_token).send().await?;
+        let status = response.status();
+        let data: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            anyhow::bail!("Gmail API {}: {}", status, data.get("error").unwrap_or(&data));
+        }
+        Ok(data)
+    }
+}
+
+#[async_trait]
+impl Mailbox for GmailMailbox {
+    async fn list(&self, query: &str, max: usize) -> anyhow::Result<Vec<Email>> {
+        let url = format!("{}/messages?q={}&maxResults={}", GMAIL_API, urlencoding::encode(query), max);
+        let data = self.call(self.client.get(&url)).await?;
+        let mut emails = Vec::new();
+        for msg in data.get("messages").and_then(|v| v.as_array()).into_iter().flatten() {
+            let Some(id) = msg.get("id").and_then(|v| v.as_str()) else { continue };
+            let url = format!(
+                "{}/messages/{}?format=metadata&metadataHeaders=From&metadataHeaders=Subject&metadataHeaders=Message-ID",
+                GMAIL_API, id
+            );
+            let detail = match self.call(self.client.get(&url)).await {
+                Ok(d) => d,
+                Err(e) => {
+                    warn!("Triage: skipping message {}: {}", id, e);
+                    continue;
+                }
+            };
+            let mut email = Email {
+                id: id.to_string(),
+                thread_id: detail.get("threadId").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                snippet: detail.get("snippet").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                ..Default::default()
+            };
+            let headers = detail.pointer("/payload/headers").and_then(|h| h.as_array());
+            for header in headers.into_iter().flatten() {
+                let value = header.get("value").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+                match header.get("name").and_then(|v| v.as_str()).unwrap_or_default().to_ascii_lowercase().as_str() {
+                    "from" => email.from = value,
+                    "subject" => email.subject = value,
+                    "message-id" => email.message_id = value,
+                    _ => {}
+                }
+            }
+            emails.push(email);
+        }
+        Ok(emails)
+    }
+
+    async fn archive(&self, id: &str) -> anyhow::Result<()> {
+        let url = format!("{}/messages/{}/modify", GMAIL_API, id);
+        self.call(self.client.post(&url).json(&json!({"removeLabelIds": ["INBOX"]}))).await?;
+        Ok(())
+    }
+
+    async fn draft_reply(&self, email: &Email, body: &str) -> anyhow::Result<String> {
+        use base64::Engine;
+        let subject = if email.subject.to_lowercase().starts_with("re:") {
+            email.subject.clone()
+        } else {
+            format!("Re: {}", email.subject)
+        };
+        let mut raw = format!("To: {}\r\nSubject: {}\r\n", email.from, subject);
+        if !email.message_id.is_empty() {
+            raw.push_str(&format!("In-Reply-To: {0}\r\nReferences: {0}\r\n", email.message_id));
+        }
+        raw.push_str(&format!("Content-Type: text/plain; charset=utf-8\r\n\r\n{}", body));
+        let encoded = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(raw.as_bytes());
+        let url = format!("{}/drafts", GMAIL_API);
+        let data = self
+            .call(self.client.post(&url).json(&json!({"message": {"raw": encoded, "threadId": email.thread_id}})))
+            .await?;
+        Ok(data.get("id").and_then(|v| v.as_str()).unwrap_or_default().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+notify: { channel: telegram, to: "42" }
+rules:
+  - name: newsletters
+    from: "(?i)newsletter"
+    priority: low
+  - name: receipts
+    subject: "(?i)receipt"
+    priority: normal
+    archive: true
+"#;
+
+    fn email(id: &str, from: &str, subject: &str) -> Email {
+        Email { id: id.into(), from: from.into(), subject: subject.into(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_parse_config_and_rules() {
+        let config = TriageConfig::parse(CONFIG).unwrap();
+        assert_eq!(config.schedule, "0 8 * * *");
+        assert_eq!(config.notify, Some(NotifyTarget { channel: "telegram".into(), to: "42".into() }));
+        assert!(config.draft_replies);
+
+        let news = first_match(&config.rules, &email("1", "Weekly Newsletter <news@x.com>", "Hi")).unwrap().unwrap();
+        assert_eq!((news.priority, news.archives()), (Priority::Low, true));
+        let receipt = first_match(&config.rules, &email("2", "shop@x.com", "Your receipt")).unwrap().unwrap();
+        assert_eq!((receipt.priority, receipt.archives()), (Priority::Normal, true));
+        assert!(first_match(&config.rules, &email("3", "yuki@x.com", "lunch?")).unwrap().is_none());
+
+        assert!(TriageConfig::parse("rules:\n  - { from: '(', priority: low }").is_err());
+    }
+
+    #[test]
+    fn test_parse_verdicts() {
+        let reply = r#"```json
+{"emails": [
+  {"id": "a", "priority": "high", "reason": "deadline today", "needsReply": true, "reply": "承知しました。"},
+  {"id": "b", "priority": "LOW", "needsReply": false, "reply": "ignored"},
+  {"priority": "high"}
+]}
+```"#;
+        let verdicts = parse_verdicts(reply);
+        assert_eq!(verdicts.len(), 2);
+        assert_eq!(verdicts[0].reply.as_deref(), Some("承知しました。"));
+        assert_eq!((verdicts[1].priority, verdicts[1].reply.clone()), (Priority::Low, None));
+        assert!(parse_verdicts("sorry").is_empty());
+    }
+
+    #[test]
+    fn test_summary() {
+        let triaged = |id: &str, from: &str, priority, archived, draft: Option<&str>| Triaged {
+            email: email(id, from, "Subject"),
+            priority,
+            rule: None,
+            reason: String::new(),
+            archived,
+            draft_id: draft.map(str::to_string),
+        };
+        let report = TriageReport {
+            emails: vec![
+                triaged("1", "\"Yuki\" <y@x.com>", Priority::High, false, Some("d1")),
+                triaged("2", "news@x.com", Priority::Low, true, None),
+            ],
+            errors: Vec::new(),
+        };
+        let text = report.summary(Locale::En);
+        assert!(text.starts_with("📬 Inbox triage: 2 emails (1 archived, 1 reply drafts)"), "{}", text);
+        assert!(text.contains("• Yuki — Subject ✍️ draft ready"));
+        assert!(text.contains("• news@x.com — Subject 📦"));
+        assert_eq!(TriageReport::default().summary(Locale::Ja), "📭 新着メールはありません。");
+    }
+}
@@ -0,0 +1,153 @@
+//! Public tunnels for local development (`chatweb gateway --http --tunnel`).
+//!
+//! Runs `cloudflared` (a free quick tunnel, no account needed) or `ngrok` in
+//! front of the local HTTP port and reads the public HTTPS URL from its log
+//! output. The gateway uses that URL as `gateway.publicUrl`, so channel
+//! webhooks get registered against it (see [`crate::channel::webhooks`]).
+//! The CLI only starts one with token authentication on, unless told
+//! `--tunnel-insecure`.
+
+use std::process::Stdio;
+use std::str::FromStr;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+/// How long the tunnel may take to report its URL.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TunnelKind {
+    Cloudflared,
+    Ngrok,
+}
+
+impl TunnelKind {
+    pub fn program(self) -> &'static str {
+        match self {
+            TunnelKind::Cloudflared => "cloudflared",
+            TunnelKind::Ngrok => "ngrok",
+        }
+    }
+
+    fn command(self, port: u16) -> Command {
+        let mut command = Command::new(self.program());
+        match self {
+            TunnelKind::Cloudflared => {
+                command.args(["tunnel", "--no-autoupdate", "--url", &format!("http://localhost:{}", port)]);
+            }
+            TunnelKind::Ngrok => {
+                command.args(["http", &port.to_string(), "--log", "stdout", "--log-format", "logfmt"]);
+            }
+        }
+        command
+    }
+}
+
+impl FromStr for TunnelKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cloudflared" | "cloudflare" => Ok(TunnelKind::Cloudflared),
+            "ngrok" => Ok(TunnelKind::Ngrok),
+            other => Err(format!("unknown tunnel '{}' (use cloudflared or ngrok)", other)),
+        }
+    }
+}
+
+/// A running tunnel. Dropping it stops the tunnel process.
+pub struct Tunnel {
+    pub kind: TunnelKind,
+    /// Public HTTPS base URL.
+    pub url: String,
+    _child: Child,
+}
+
+impl Tunnel {
+    /// Start a tunnel to `localhost:port` and wait for its public URL.
+    pub async fn start(kind: TunnelKind, port: u16) -> anyhow::Result<Self> {
+        let mut child = kind
+            .command(port)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("could not start {} ({}); is it installed?", kind.program(), e))?;
+
+        // Keep draining both streams for the tunnel's lifetime so it never
+        // blocks on a full pipe; lines go to the debug log.
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let outputs: [Option<Box<dyn AsyncRead + Unpin + Send>>; 2] = [
+            child.stdout.take().map(|s| Box::new(s) as _),
+            child.stderr.take().map(|s| Box::new(s) as _),
+        ];
+        for output in outputs.into_iter().flatten() {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(output).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    debug!("{}: {}", kind.program(), line);
+                    let _ = tx.send(line);
+                }
+            });
+        }
+        drop(tx);
+
+        let url = tokio::time::timeout(STARTUP_TIMEOUT, async {
+            while let Some(line) = rx.recv().await {
+                if let Some(url) = find_public_url(kind, &line) {
+                    return Some(url);
+                }
+            }
+            None
+        })
+        .await;
+        match url {
+            Ok(Some(url)) => Ok(Self { kind, url, _child: child }),
+            Ok(None) => anyhow::bail!("{} exited before reporting a public URL", kind.program()),
+            Err(_) => anyhow::bail!("{} reported no public URL within {}s", kind.program(), STARTUP_TIMEOUT.as_secs()),
+        }
+    }
+}
+
+/// The public URL in a line of the tunnel's log, if this is the line announcing it.
+fn find_public_url(kind: TunnelKind, line: &str) -> Option<String> {
+    let start = line.find("https://")?;
+    let url: String = line[start..]
+        .chars()
+        .take_while(|c| !c.is_whitespace() && !matches!(c, '"' | '\'' | '|'))
+        .collect();
+    let host = url.trim_start_matches("https://");
+    let announced = match kind {
+        TunnelKind::Cloudflared => host.ends_with(".trycloudflare.com") && host != "api.trycloudflare.com",
+        TunnelKind::Ngrok => line.contains("started tunnel"),
+    };
+    announced.then_some(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_public_url() {
+        let cloudflared = "2026-10-18T09:00:00Z INF |  https://quiet-river-lamp.trycloudflare.com                                  |";
+        assert_eq!(
+            find_public_url(TunnelKind::Cloudflared, cloudflared).as_deref(),
+            Some("https://quiet-river-lamp.trycloudflare.com")
+        );
+        assert_eq!(find_public_url(TunnelKind::Cloudflared, "INF Thank you for trying https://www.cloudflare.com/tos"), None);
+
+        let ngrok = r#"t=2026-10-18T09:00:00+0000 lvl=info msg="started tunnel" obj=tunnels name=command_line addr=http://localhost:3000 url=https://1a2b.ngrok-free.app"#;
+        assert_eq!(find_public_url(TunnelKind::Ngrok, ngrok).as_deref(), Some("https://1a2b.ngrok-free.app"));
+        assert_eq!(find_public_url(TunnelKind::Ngrok, "lvl=info msg=\"update available\" url=https://ngrok.com/download"), None);
+
+        assert_eq!("ngrok".parse(), Ok(TunnelKind::Ngrok));
+        assert!("frp".parse::<TunnelKind>().is_err());
+    }
+}
@@ -0,0 +1,326 @@
+//! One calendar of everything scheduled to happen: cron jobs, reminders,
+//! follow-ups the agent planned, mail digests and workflow runs.
+//!
+//! All of them are cron jobs; this module gives them a common shape
+//! ([`UpcomingItem`]) for `/upcoming` in chat, `chatweb upcoming` and
+//! `GET /api/v1/upcoming`, and cancels or moves them. In chat only the jobs
+//! bound to that chat are shown. Digests and workflow runs come from
+//! `triage.yaml` and `workflows/` and are re-created from those files, so
+//! they are listed but changed by editing the file.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::service::backup::BACKUP_KIND;
+use crate::service::cron::{CronJob, CronSchedule, CronService};
+use crate::service::followup::FOLLOWUP_KIND;
+use crate::service::reminder::{self, REMINDER_KIND};
+use crate::service::triage::TRIAGE_KIND;
+use crate::session::locale::Locale;
+use crate::util::tz::{self, Timezone};
+use crate::workflow::WORKFLOW_KIND;
+
+/// What a scheduled item is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpcomingKind {
+    /// A plain cron job (`chatweb cron add`, the `cron` tool).
+    Cron,
+    Reminder,
+    Followup,
+    Digest,
+    Workflow,
+    Backup,
+}
+
+impl UpcomingKind {
+    pub fn of(job: &CronJob) -> Self {
+        match job.payload.kind.as_str() {
+            REMINDER_KIND => Self::Reminder,
+            FOLLOWUP_KIND => Self::Followup,
+            TRIAGE_KIND => Self::Digest,
+            WORKFLOW_KIND => Self::Workflow,
+            BACKUP_KIND => Self::Backup,
+            _ => Self::Cron,
+        }
+    }
+
+    /// Whether the job is re-created from a workspace file.
+    pub fn is_synced(&self) -> bool {
+        matches!(self, Self::Digest | Self::Workflow)
+    }
+
+    fn icon(&self) -> &'static str {
+        match self {
+            Self::Cron => "🔁",
+            Self::Reminder => "⏰",
+            Self::Followup => "👀",
+            Self::Digest => "📬",
+            Self::Workflow => "⚙️",
+            Self::Backup => "💾",
+        }
+    }
+}
+
+/// A scheduled item.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpcomingItem {
+    pub id: String,
+    pub kind: UpcomingKind,
+    pub title: String,
+    /// Next run, epoch milliseconds.
+    pub next_run_at_ms: u64,
+    pub recurring: bool,
+    /// Cancel and reschedule apply (see [`UpcomingKind::is_synced`]).
+    pub editable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<String>,
+}
+
+impl UpcomingItem {
+    pub fn from_job(job: &CronJob) -> Option<Self> {
+        let kind = UpcomingKind::of(job);
+        let title = match job.payload.message.trim() {
+            "" => job.name.clone(),
+            message => message.chars().take(60).collect(),
+        };
+        Some(Self {
+            id: job.id.clone(),
+            kind,
+            title,
+            next_run_at_ms: job.state.next_run_at_ms.filter(|_| job.enabled)?,
+            recurring: !matches!(job.schedule, CronSchedule::At { .. }),
+            editable: !kind.is_synced(),
+            channel: job.payload.channel.clone(),
+            chat_id: job.payload.to.clone(),
+        })
+    }
+
+    /// `• [ab12cd34] ⏰ 3/14 09:00 Call the dentist (repeats)`
+    pub fn line(&self, zone: Timezone, locale: Locale) -> String {
+        let repeat = if self.recurring { locale.pick("（繰り返し）", " (repeats)") } else { "" };
+        format!("• [{}] {} {} {}{}", self.id, self.kind.icon(), when(self.next_run_at_ms, zone), self.title, repeat)
+    }
+}
+
+/// `09:00` today, `3/14 09:00` on other days.
+fn when(ms: u64, zone: Timezone) -> String {
+    match zone.timestamp_millis_opt(ms as i64).single() {
+        Some(t) if t.date_naive() == Utc::now().with_timezone(&zone).date_naive() => t.format("%H:%M").to_string(),
+        Some(t) => format!("{}/{} {}", t.month(), t.day(), t.format("%H:%M")),
+        None => "-".to_string(),
+    }
+}
+
+fn owned_by(job: &CronJob, owner: Option<(&str, &str)>) -> bool {
+    match owner {
+        Some((channel, chat_id)) => {
+            job.payload.channel.as_deref() == Some(channel) && job.payload.to.as_deref() == Some(chat_id)
+        }
+        None => true,
+    }
+}
+
+/// Upcoming items, soonest first: every job, or only `owner`'s
+/// (`(channel, chat_id)`).
+pub fn list(cron: &CronService, owner: Option<(&str, &str)>) -> Vec<UpcomingItem> {
+    let mut items: Vec<UpcomingItem> = cron
+        .list_jobs(false)
+        .iter()
+        .filter(|j| owned_by(j, owner))
+        .filter_map(UpcomingItem::from_job)
+        .collect();
+    items.sort_by_key(|i| i.next_run_at_ms);
+    items
+}
+
+/// Why an item couldn't be changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeError {
+    NotFound,
+    /// Defined in a workspace file; change it there.
+    Synced(UpcomingKind),
+    /// The new time couldn't be understood or is in the past.
+    InvalidTime,
+}
+
+impl std::fmt::Display for ChangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChangeError::NotFound => write!(f, "no such scheduled item"),
+            ChangeError::Synced(UpcomingKind::Digest) => write!(f, "digests are scheduled in triage.yaml"),
+            ChangeError::Synced(_) => write!(f, "workflow runs are scheduled in the workflow's trigger"),
+            ChangeError::InvalidTime => write!(f, "invalid time"),
+        }
+    }
+}
+
+impl std::error::Error for ChangeError {}
+
+fn find(cron: &CronService, owner: Option<(&str, &str)>, id: &str) -> Result<CronJob, ChangeError> {
+    let job = cron
+        .list_jobs(true)
+        .into_iter()
+        .find(|j| j.id == id && owned_by(j, owner))
+        .ok_or(ChangeError::NotFound)?;
+    match UpcomingKind::of(&job) {
+        kind if kind.is_synced() => Err(ChangeError::Synced(kind)),
+        _ => Ok(job),
+    }
+}
+
+/// Cancel an item for good.
+pub fn cancel(cron: &mut CronService, owner: Option<(&str, &str)>, id: &str) -> Result<(), ChangeError> {
+    let job = find(cron, owner, id)?;
+    cron.remove_job(&job.id);
+    Ok(())
+}
+
+/// Move an item to `when` (see [`parse_when`]).
+pub fn reschedule(
+    cron: &mut CronService,
+    owner: Option<(&str, &str)>,
+    id: &str,
+    when: &str,
+    tz: &str,
+) -> Result<UpcomingItem, ChangeError> {
+    let job = find(cron, owner, id)?;
+    let zone = tz::parse_or_utc(tz);
+    let schedule = parse_when(when, Utc::now().with_timezone(&zone), tz).ok_or(ChangeError::InvalidTime)?;
+    cron.reschedule_job(&job.id, schedule)
+        .as_ref()
+        .and_then(UpcomingItem::from_job)
+        .ok_or(ChangeError::InvalidTime)
+}
+
+/// A new time for an item, read in the user's timezone: a delay (`30m`,
+/// `2時間`), a time today or tomorrow (`18:30`), a date and time
+/// (`2026-03-14 09:00`, RFC 3339) or a 5-field cron expression for a
+/// recurring schedule.
+pub fn parse_when(when: &str, now: DateTime<Timezone>, tz: &str) -> Option<CronSchedule> {
+    let when = when.trim();
+    let at = |t: DateTime<Utc>| (t > now).then(|| CronSchedule::At { at_ms: t.timestamp_millis() as u64 });
+    if let Some(ms) = reminder::parse_duration(when) {
+        return at(now.to_utc() + Duration::milliseconds(ms as i64));
+    }
+    if when.split_whitespace().count() == 5 {
+        let schedule = CronSchedule::Cron { expr: when.to_string(), tz: Some(tz.to_string()) };
+        return schedule.next_run(now.timestamp_millis() as u64).map(|_| schedule);
+    }
+    if let Ok(t) = DateTime::parse_from_rfc3339(when) {
+        return at(t.to_utc());
+    }
+    let zone = now.timezone();
+    if let Ok(time) = NaiveTime::parse_from_str(when, "%H:%M") {
+        let today = zone.from_local_datetime(&now.date_naive().and_time(time)).single()?;
+        let next = if today > now { today } else { zone.from_local_datetime(&now.date_naive().succ_opt()?.and_time(time)).single()? };
+        return at(next.to_utc());
+    }
+    let local = NaiveDateTime::parse_from_str(when, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(when, "%Y-%m-%dT%H:%M"))
+        .or_else(|_| NaiveDate::parse_from_str(when, "%Y-%m-%d").map(|d| d.and_time(NaiveTime::default())))
+        .ok()?;
+    at(zone.from_local_datetime(&local).single()?.to_utc())
+}
+
+/// Handle `/upcoming [cancel <id> | move <id> <when>]` for a chat.
+pub fn handle_command(cron: &mut CronService, args: &str, channel: &str, chat_id: &str, tz: &str, locale: Locale) -> String {
+    let owner = Some((channel, chat_id));
+    let zone = tz::parse_or_utc(tz);
+    let mut parts = args.split_whitespace();
+    let error = |e: ChangeError| match e {
+        ChangeError::NotFound => locale.pick("その予定は見つかりません。", "No such scheduled item.").to_string(),
+        ChangeError::Synced(kind) => format!(
+            "{} ({})",
+            locale.pick("この予定はワークスペースの設定から作られています。", "This one comes from the workspace configuration."),
+            ChangeError::Synced(kind)
+        ),
+        ChangeError::InvalidTime => locale
+            .pick("日時が読めません: 30m / 18:30 / 2026-03-14 09:00 / 0 9 * * 1", "Couldn't read the time: 30m / 18:30 / 2026-03-14 09:00 / 0 9 * * 1")
+            .to_string(),
+    };
+    match (parts.next(), parts.next()) {
+        (Some("cancel" | "削除"), Some(id)) => match cancel(cron, owner, id) {
+            Ok(()) => locale.pick("🗑 予定を取り消しました。", "🗑 Cancelled.").to_string(),
+            Err(e) => error(e),
+        },
+        (Some("move" | "変更"), Some(id)) => {
+            let when = parts.collect::<Vec<_>>().join(" ");
+            match reschedule(cron, owner, id, &when, tz) {
+                Ok(item) => format!("{}\n{}", locale.pick("📅 予定を変更しました:", "📅 Rescheduled:"), item.line(zone, locale)),
+                Err(e) => error(e),
+            }
+        }
+        _ => {
+            let items = list(cron, owner);
+            if items.is_empty() {
+                return locale.pick("📅 予定はありません。", "📅 Nothing scheduled.").to_string();
+            }
+            let mut lines = vec![locale.pick("📅 今後の予定:", "📅 Upcoming:").to_string()];
+            lines.extend(items.iter().map(|i| i.line(zone, locale)));
+            lines.push(
+                locale
+                    .pick("取消: /upcoming cancel <ID> ・ 変更: /upcoming move <ID> <日時>", "Cancel: /upcoming cancel <ID> · Move: /upcoming move <ID> <when>")
+                    .to_string(),
+            );
+            lines.join("\n")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::cron::CronPayload;
+
+    fn payload(kind: &str, to: Option<&str>) -> CronPayload {
+        CronPayload {
+            kind: kind.to_string(),
+            message: format!("{} job", kind),
+            deliver: true,
+            channel: to.map(|_| "line".to_string()),
+            to: to.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_list_cancel_and_move() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut cron = CronService::new(tmp.path().join("jobs.json"));
+        let now = Utc::now().timestamp_millis() as u64;
+        let later = cron.add_job_with_payload("r", CronSchedule::At { at_ms: now + 7_200_000 }, payload(REMINDER_KIND, Some("U1")));
+        let soon = cron.add_job_with_payload("f", CronSchedule::At { at_ms: now + 60_000 }, payload(FOLLOWUP_KIND, Some("U1")));
+        cron.add_job_with_payload("r", CronSchedule::At { at_ms: now + 60_000 }, payload(REMINDER_KIND, Some("U2")));
+        let digest = cron.add_job_with_payload("triage", CronSchedule::Every { every_ms: 3_600_000 }, payload(TRIAGE_KIND, None));
+
+        let mine = list(&cron, Some(("line", "U1")));
+        assert_eq!(mine.iter().map(|i| i.id.as_str()).collect::<Vec<_>>(), [soon.id.as_str(), later.id.as_str()]);
+        assert_eq!(list(&cron, None).len(), 4);
+
+        assert_eq!(cancel(&mut cron, Some(("line", "U2")), &soon.id), Err(ChangeError::NotFound));
+        assert_eq!(cancel(&mut cron, None, &digest.id), Err(ChangeError::Synced(UpcomingKind::Digest)));
+        let moved = reschedule(&mut cron, Some(("line", "U1")), &soon.id, "3h", "UTC").unwrap();
+        assert!(moved.next_run_at_ms > later.state.next_run_at_ms.unwrap());
+        cancel(&mut cron, Some(("line", "U1")), &later.id).unwrap();
+        assert_eq!(list(&cron, Some(("line", "U1"))).len(), 1);
+    }
+
+    #[test]
+    fn test_parse_when() {
+        let now = DateTime::parse_from_rfc3339("2026-03-14T10:00:00+09:00").unwrap().with_timezone(&tz::parse_or_utc("Asia/Tokyo"));
+        let at = |s: &str| match parse_when(s, now, "Asia/Tokyo") {
+            Some(CronSchedule::At { at_ms }) => Some(at_ms as i64 - now.timestamp_millis()),
+            _ => None,
+        };
+        assert_eq!(at("30m"), Some(30 * 60_000));
+        assert_eq!(at("18:30"), Some(8 * 3_600_000 + 30 * 60_000));
+        assert_eq!(at("09:00"), Some(23 * 3_600_000), "past times mean tomorrow");
+        assert_eq!(at("2026-03-15 10:00"), Some(24 * 3_600_000));
+        assert_eq!(at("2026-03-13 10:00"), None, "in the past");
+        assert!(matches!(parse_when("0 9 * * 1", now, "Asia/Tokyo"), Some(CronSchedule::Cron { .. })));
+        assert!(parse_when("someday", now, "Asia/Tokyo").is_none());
+    }
+}
@@ -0,0 +1,527 @@
+//! Realtime voice over WebSocket (`GET /api/v1/voice/ws`).
+//!
+//! Clients stream microphone audio and get the agent's reply back as text and
+//! speech, for phone-like conversations with a self-hosted gateway.
+//!
+//! Client → server:
+//! - `{"type":"start", ...}` (optional): `session_id`, `format` (`pcm16`,
+//!   `webm` or `ogg`), `sample_rate`, `language`, `voice`.
+//! - Binary audio frames. With `pcm16` (16-bit little-endian mono) the server
+//!   finds the end of each utterance itself; with Opus in a WebM/Ogg container
+//!   (e.g. `MediaRecorder` chunks) the client sends `{"type":"commit"}` after
+//!   each utterance.
+//! - `{"type":"cancel"}` stops the current reply.
+//! - `{"type":"enroll","name":"..."}`: the next utterance is enrolled as that
+//!   speaker's voice instead of being answered.
+//!
+//! Server → client: `ready` (output audio format), `speech_start` (the user
+//! is talking; stop playback), `transcript` (`final: false` for partial
+//! hypotheses while the user is still speaking), `speaker` (who was
+//! recognized, or `null`), `speaker.enrolled`, `response.text` chunks,
+//! binary 16-bit PCM speech at 24 kHz, `response.done` and `error`.
+//!
+//! With `voice.speakerId` enabled, `pcm16` utterances are matched against the
+//! enrolled voiceprints and the agent is told who is talking and what they
+//! asked it to remember. A client can opt a session out with
+//! `"speaker_id": false` in `start`.
+//!
+//! Replies run through the `/api/v1/chat/stream` handler in-process, so
+//! sessions, memory, tools and credits behave exactly as over REST, and each
+//! sentence is spoken as soon as it has streamed in.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tower::ServiceExt;
+
+use super::http::{create_router, drain_sse_frames, AppState};
+use crate::voice::speaker::{preference_note, voiceprint};
+use crate::voice::speech::{take_sentences, TTS_SAMPLE_RATE};
+use crate::voice::{
+    pcm_from_bytes, pcm_to_wav, SpeakerProfile, SpeakerRegistry, SpeechClient, Vad, VadConfig, VadEvent,
+};
+
+/// New speech needed before another partial transcript is requested.
+const PARTIAL_INTERVAL_MS: u32 = 1200;
+
+/// Container-format utterances are buffered up to this size.
+const MAX_UTTERANCE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Speech is sent in frames of this many bytes (100 ms at 24 kHz).
+const AUDIO_FRAME_BYTES: usize = 4800;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum AudioFormat {
+    #[default]
+    Pcm16,
+    Webm,
+    Ogg,
+}
+
+impl AudioFormat {
+    fn file_name(self) -> &'static str {
+        match self {
+            AudioFormat::Pcm16 => "audio.wav",
+            AudioFormat::Webm => "audio.webm",
+            AudioFormat::Ogg => "audio.ogg",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+struct StartMessage {
+    session_id: Option<String>,
+    format: AudioFormat,
+    sample_rate: u32,
+    language: Option<String>,
+    voice: Option<String>,
+    /// `false` turns speaker identification off for this session.
+    speaker_id: bool,
+}
+
+impl Default for StartMessage {
+    fn default() -> Self {
+        Self {
+            session_id: None,
+            format: AudioFormat::Pcm16,
+            sample_rate: 16_000,
+            language: None,
+            voice: None,
+            speaker_id: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum ClientMessage {
+    Start(StartMessage),
+    Commit,
+    Cancel,
+    Enroll { name: String },
+}
+
+/// GET /api/v1/voice/ws — upgrade to a realtime voice session.
+pub async fn handle_voice_ws(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<HashMap<String, String>>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let Some(speech) = SpeechClient::from_config(&state.config) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Speech is not configured (set providers.openai.apiKey or OPENAI_API_KEY)",
+        )
+            .into_response();
+    };
+    // Browsers cannot set headers on WebSocket requests, so `?token=` stands
+    // in for the bearer token.
+    let authorization = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+        .or_else(|| query.get("token").map(|t| format!("Bearer {}", t)));
+    ws.on_upgrade(move |socket| run_session(socket, state, speech, authorization))
+}
+
+async fn run_session(socket: WebSocket, state: Arc<AppState>, speech: SpeechClient, authorization: Option<String>) {
+    let (mut sink, mut stream) = socket.split();
+    let (out, mut rx) = mpsc::unbounded_channel::<Message>();
+    let writer = tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if sink.send(msg).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut session = VoiceSession::new(state, speech, authorization, out);
+    session.send(json!({ "type": "ready", "format": "pcm16", "sample_rate": TTS_SAMPLE_RATE }));
+    while let Some(Ok(msg)) = stream.next().await {
+        match msg {
+            Message::Text(text) => session.on_control(text.as_str()),
+            Message::Binary(data) => session.on_audio(&data),
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    session.cancel_reply();
+    writer.abort();
+}
+
+struct VoiceSession {
+    state: Arc<AppState>,
+    router: axum::Router,
+    speech: SpeechClient,
+    authorization: Option<String>,
+    out: mpsc::UnboundedSender<Message>,
+    start: StartMessage,
+    session_id: String,
+    vad: Vad,
+    /// WebM/Ogg bytes of the utterance in progress.
+    container: Vec<u8>,
+    reply: Option<JoinHandle<()>>,
+    partial: Option<JoinHandle<()>>,
+    /// Utterance length (samples) at the last partial transcript.
+    partial_at: usize,
+    /// Set when speaker identification is enabled.
+    speakers: Option<Arc<Mutex<SpeakerRegistry>>>,
+    /// Name to enroll the next utterance as.
+    enroll: Option<String>,
+}
+
+fn speaker_registry(state: &AppState) -> Option<Arc<Mutex<SpeakerRegistry>>> {
+    let voice = &state.config.voice;
+    voice.speaker_id.then(|| {
+        Arc::new(Mutex::new(SpeakerRegistry::new(
+            SpeakerRegistry::default_path(),
+            voice.speaker_threshold,
+        )))
+    })
+}
+
+fn vad_for(state: &AppState, sample_rate: u32) -> Vad {
+    let voice = &state.config.voice;
+    Vad::new(VadConfig {
+        sample_rate,
+        aggressiveness: voice.vad_aggressiveness,
+        silence_ms: voice.silence_ms,
+        ..Default::default()
+    })
+}
+
+impl VoiceSession {
+    fn new(
+        state: Arc<AppState>,
+        speech: SpeechClient,
+        authorization: Option<String>,
+        out: mpsc::UnboundedSender<Message>,
+    ) -> Self {
+        let start = StartMessage::default();
+        let speakers = speaker_registry(&state);
+        Self {
+            router: create_router(state.clone()),
+            vad: vad_for(&state, start.sample_rate),
+            state,
+            speech,
+            authorization,
+            out,
+            start,
+            session_id: format!("voice:{}", uuid::Uuid::new_v4()),
+            container: Vec::new(),
+            reply: None,
+            partial: None,
+            partial_at: 0,
+            speakers,
+            enroll: None,
+        }
+    }
+
+    fn send(&self, event: serde_json::Value) {
+        let _ = self.out.send(Message::Text(event.to_string().into()));
+    }
+
+    fn on_control(&mut self, text: &str) {
+        match serde_json::from_str::<ClientMessage>(text) {
+            Ok(ClientMessage::Start(start)) => {
+                if let Some(id) = start.session_id.as_deref().filter(|id| !id.is_empty()) {
+                    self.session_id = id.to_string();
+                }
+                self.vad = vad_for(&self.state, start.sample_rate.clamp(8_000, 48_000));
+                self.container.clear();
+                if !start.speaker_id {
+                    self.speakers = None;
+                }
+                self.start = start;
+            }
+            Ok(ClientMessage::Commit) => self.end_utterance(),
+            Ok(ClientMessage::Cancel) => self.cancel_reply(),
+            Ok(ClientMessage::Enroll { name }) => {
+                if self.speakers.is_none() {
+                    self.send(json!({ "type": "error", "message": "speaker identification is disabled" }));
+                } else {
+                    self.enroll = Some(name.trim().to_string()).filter(|n| !n.is_empty());
+                }
+            }
+            Err(e) => self.send(json!({ "type": "error", "message": format!("invalid message: {}", e) })),
+        }
+    }
+
+    fn on_audio(&mut self, data: &[u8]) {
+        if self.start.format != AudioFormat::Pcm16 {
+            if self.container.len() + data.len() > MAX_UTTERANCE_BYTES {
+                self.container.clear();
+                self.send(json!({ "type": "error", "message": "utterance too long" }));
+                return;
+            }
+            self.container.extend_from_slice(data);
+            return;
+        }
+        for event in self.vad.push(&pcm_from_bytes(data)) {
+            match event {
+                VadEvent::SpeechStart => {
+                    // Barge-in: the user talking over a reply ends it.
+                    self.cancel_reply();
+                    self.partial_at = 0;
+                    self.send(json!({ "type": "speech_start" }));
+                }
+                VadEvent::SpeechEnd(audio) => self.on_utterance(audio),
+            }
+        }
+        if self.vad.is_speaking() {
+            self.maybe_partial();
+        }
+    }
+
+    fn end_utterance(&mut self) {
+        match self.start.format {
+            AudioFormat::Pcm16 => {
+                if let Some(VadEvent::SpeechEnd(audio)) = self.vad.flush() {
+                    self.on_utterance(audio);
+                }
+            }
+            // Compressed audio is not decoded here, so it carries no voiceprint.
+            _ if !self.container.is_empty() => {
+                let audio = std::mem::take(&mut self.container);
+                self.respond(audio, None);
+            }
+            _ => {}
+        }
+    }
+
+    /// Transcribe the utterance so far, at most one request at a time.
+    fn maybe_partial(&mut self) {
+        let utterance = self.vad.current_utterance();
+        let step = (self.start.sample_rate * PARTIAL_INTERVAL_MS / 1000) as usize;
+        if utterance.len() < self.partial_at + step || self.partial.as_ref().is_some_and(|t| !t.is_finished()) {
+            return;
+        }
+        self.partial_at = utterance.len();
+        let wav = pcm_to_wav(utterance, self.start.sample_rate);
+        let speech = self.speech.clone();
+        let language = self.start.language.clone();
+        let out = self.out.clone();
+        self.partial = Some(tokio::spawn(async move {
+            if let Ok(text) = speech.transcribe(wav, "audio.wav", language.as_deref()).await {
+                if !text.is_empty() {
+                    let event = json!({ "type": "transcript", "text": text, "final": false });
+                    let _ = out.send(Message::Text(event.to_string().into()));
+                }
+            }
+        }));
+    }
+
+    /// Enroll or identify the speaker of a PCM utterance, then answer it.
+    fn on_utterance(&mut self, audio: Vec<i16>) {
+        let Some(speakers) = self.speakers.clone() else {
+            return self.respond(pcm_to_wav(&audio, self.start.sample_rate), None);
+        };
+        let print = voiceprint(&audio, self.start.sample_rate);
+        if let Some(name) = self.enroll.take() {
+            match print {
+                Some(print) => {
+                    let profile = speakers.lock().unwrap().enroll(&name, &print);
+                    self.send(json!({
+                        "type": "speaker.enrolled",
+                        "id": profile.id,
+                        "name": profile.name,
+                        "enrollments": profile.enrollments,
+                    }));
+                }
+                None => {
+                    self.enroll = Some(name);
+                    self.send(json!({ "type": "error", "message": "too short to enroll; say a full sentence" }));
+                }
+            }
+            return;
+        }
+        let speaker = print.and_then(|print| speakers.lock().unwrap().identify(&print));
+        self.send(match &speaker {
+            Some((profile, score)) => {
+                json!({ "type": "speaker", "id": profile.id, "name": profile.name, "score": score })
+            }
+            None => json!({ "type": "speaker", "id": null, "name": null }),
+        });
+        self.respond(pcm_to_wav(&audio, self.start.sample_rate), speaker.map(|(profile, _)| profile));
+    }
+
+    fn respond(&mut self, audio: Vec<u8>, speaker: Option<SpeakerProfile>) {
+        self.cancel_reply();
+        if let Some(partial) = self.partial.take() {
+            partial.abort();
+        }
+        self.partial_at = 0;
+        let turn = VoiceTurn {
+            router: self.router.clone(),
+            speech: self.speech.clone(),
+            authorization: self.authorization.clone(),
+            out: self.out.clone(),
+            session_id: self.session_id.clone(),
+            language: self.start.language.clone(),
+            voice: self.start.voice.clone(),
+            file_name: self.start.format.file_name(),
+            speaker,
+            speakers: self.speakers.clone(),
+            remember: self.state.config.voice.remember_preferences,
+        };
+        self.reply = Some(tokio::spawn(async move {
+            if let Err(e) = turn.run(audio).await {
+                turn.send(json!({ "type": "error", "message": e.to_string() }));
+            }
+        }));
+    }
+
+    fn cancel_reply(&mut self) {
+        if let Some(reply) = self.reply.take() {
+            reply.abort();
+        }
+    }
+}
+
+/// One utterance → transcript → agent reply → speech.
+struct VoiceTurn {
+    router: axum::Router,
+    speech: SpeechClient,
+    authorization: Option<String>,
+    out: mpsc::UnboundedSender<Message>,
+    session_id: String,
+    language: Option<String>,
+    voice: Option<String>,
+    file_name: &'static str,
+    speaker: Option<SpeakerProfile>,
+    speakers: Option<Arc<Mutex<SpeakerRegistry>>>,
+    /// Keep "remember that ..." as a note on the speaker.
+    remember: bool,
+}
+
+impl VoiceTurn {
+    fn send(&self, event: serde_json::Value) {
+        let _ = self.out.send(Message::Text(event.to_string().into()));
+    }
+
+    async fn run(&self, audio: Vec<u8>) -> anyhow::Result<()> {
+        let text = self
+            .speech
+            .transcribe(audio, self.file_name, self.language.as_deref())
+            .await?;
+        self.send(json!({ "type": "transcript", "text": text, "final": true }));
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        let mut speaker = self.speaker.clone();
+        if let (Some(profile), Some(speakers), true) = (&speaker, &self.speakers, self.remember) {
+            if let Some(note) = preference_note(&text) {
+                let mut speakers = speakers.lock().unwrap();
+                speakers.remember(&profile.id, &note);
+                speaker = speakers.find(&profile.id);
+            }
+        }
+        let body = json!({
+            "message": text,
+            "session_id": self.session_id,
+            "channel": "voice",
+            "device": "voice",
+            "language": self.language,
+            "custom_system_prompt": speaker.map(|profile| profile.prompt()),
+        });
+        let mut req = axum::http::Request::builder()
+            .method("POST")
+            .uri("/api/v1/chat/stream")
+            .header("content-type", "application/json");
+        if let Some(auth) = &self.authorization {
+            req = req.header("authorization", auth);
+        }
+        let req = req.body(axum::body::Body::from(body.to_string()))?;
+        let resp = self.router.clone().oneshot(req).await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let bytes = axum::body::to_bytes(resp.into_body(), 64 * 1024).await.unwrap_or_default();
+            anyhow::bail!("chat failed ({}): {}", status, String::from_utf8_lossy(&bytes));
+        }
+
+        let mut body = resp.into_body().into_data_stream();
+        let mut buf = String::new();
+        let mut unspoken = String::new();
+        let mut reply = String::new();
+        let mut streamed = false;
+        while let Some(chunk) = body.next().await {
+            buf.push_str(&String::from_utf8_lossy(&chunk?).replace("\r\n", "\n"));
+            for data in drain_sse_frames(&mut buf) {
+                let event: serde_json::Value = serde_json::from_str(&data).unwrap_or_default();
+                let text = match event["type"].as_str() {
+                    Some("content_chunk") => {
+                        streamed = true;
+                        event["text"].as_str().unwrap_or_default()
+                    }
+                    // The final answer; only needed when nothing was streamed.
+                    Some("content") if !streamed => event["content"].as_str().unwrap_or_default(),
+                    Some("error") => anyhow::bail!("{}", event["content"].as_str().unwrap_or("chat failed")),
+                    _ => continue,
+                };
+                reply.push_str(text);
+                unspoken.push_str(text);
+                self.send(json!({ "type": "response.text", "text": text }));
+                for sentence in take_sentences(&mut unspoken) {
+                    self.speak(&sentence).await?;
+                }
+            }
+        }
+        let rest = unspoken.trim();
+        if !rest.is_empty() {
+            self.speak(rest).await?;
+        }
+        self.send(json!({ "type": "response.done", "text": reply }));
+        Ok(())
+    }
+
+    async fn speak(&self, text: &str) -> anyhow::Result<()> {
+        let mut audio = Box::pin(self.speech.synthesize_pcm(text, self.voice.as_deref()).await?);
+        let mut frame = Vec::with_capacity(AUDIO_FRAME_BYTES);
+        while let Some(chunk) = audio.next().await {
+            frame.extend_from_slice(chunk?.as_ref());
+            while frame.len() >= AUDIO_FRAME_BYTES {
+                let rest = frame.split_off(AUDIO_FRAME_BYTES);
+                let _ = self.out.send(Message::Binary(std::mem::replace(&mut frame, rest).into()));
+            }
+        }
+        if !frame.is_empty() {
+            let _ = self.out.send(Message::Binary(frame.into()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_messages() {
+        let msg: ClientMessage =
+            serde_json::from_str(r#"{"type":"start","format":"webm","language":"ja-JP"}"#).unwrap();
+        let ClientMessage::Start(start) = msg else { panic!("expected start") };
+        assert_eq!(start.format, AudioFormat::Webm);
+        assert_eq!(start.sample_rate, 16_000);
+        assert_eq!(start.language.as_deref(), Some("ja-JP"));
+        assert!(start.speaker_id);
+        assert!(matches!(serde_json::from_str(r#"{"type":"commit"}"#), Ok(ClientMessage::Commit)));
+        assert!(matches!(
+            serde_json::from_str(r#"{"type":"enroll","name":"Hanako"}"#),
+            Ok(ClientMessage::Enroll { name }) if name == "Hanako"
+        ));
+        assert!(serde_json::from_str::<ClientMessage>(r#"{"type":"start","format":"flac"}"#).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+//! Built-in chat page for self-hosted gateways (`webui` feature,
+//! `gateway.webui.enabled`).
+//!
+//! `/` serves a single page that streams answers from `/api/v1/chat/stream`
+//! and resumes a dropped stream with `Last-Event-ID`. The session id is kept
+//! in `localStorage`, so a reload continues the conversation, whose history
+//! comes back from `/api/v1/sessions/{id}`. When the gateway has
+//! `apiTokens`, the page asks for one.
+//!
+//! The page exposes `window.nanobotChat` for scripts added through
+//! `gateway.webui.head`:
+//!
+//! - `recognizer`: `async (lang) => text`, replacing the browser's speech
+//!   recognition for the microphone button;
+//! - `send(text)`: send a message as if typed;
+//! - `reply` events (`nanobotChat.addEventListener("reply", e => e.detail)`)
+//!   with each finished answer, e.g. to read it aloud.
+
+use crate::config::WebUiConfig;
+
+const TEMPLATE: &str = include_str!("../../../../web/webui.html");
+
+/// The page with `config`'s title, theme and hooks filled in.
+pub fn render(config: &WebUiConfig) -> String {
+    let settings = serde_json::json!({
+        "title": config.title,
+        "greeting": config.greeting,
+        "placeholder": config.placeholder,
+        "theme": config.theme,
+        "voice": config.voice,
+        "voiceLang": config.voice_lang,
+    });
+    // `<` can't end the script tag when escaped inside JSON strings
+    let settings = settings.to_string().replace('<', "\\u003c");
+    TEMPLATE
+        .replace("__WEBUI_TITLE__", &escape_html(&config.title))
+        .replace("__WEBUI_ACCENT__", &css_color(&config.accent))
+        .replace("/*__WEBUI_SETTINGS__*/null", &settings)
+        .replace("<!--__WEBUI_HEAD__-->", &config.head)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// `color` if it can only be a color value, else the default accent.
+fn css_color(color: &str) -> String {
+    let safe = !color.is_empty() && color.chars().all(|c| c.is_ascii_alphanumeric() || "#(),.% ".contains(c));
+    if safe { color.to_string() } else { WebUiConfig::default().accent }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let config = WebUiConfig {
+            title: "Ada's <bot>".into(),
+            greeting: "</script><script>alert(1)</script>".into(),
+            accent: "red; } body { display: none".into(),
+            head: "<script src=\"/hooks.js\"></script>".into(),
+            ..Default::default()
+        };
+        let page = render(&config);
+        assert!(page.contains("<title>Ada's &lt;bot&gt;</title>"));
+        assert!(page.contains("\\u003c/script>\\u003cscript>alert(1)"));
+        assert!(!page.contains("</script><script>alert(1)"));
+        assert!(page.contains("--accent: #6366f1;"));
+        assert!(page.contains("<script src=\"/hooks.js\"></script>"));
+        assert!(!page.contains("__WEBUI_"));
+
+        let teal = WebUiConfig { accent: "rgb(20, 184, 166)".into(), ..Default::default() };
+        assert!(render(&teal).contains("--accent: rgb(20, 184, 166);"));
+    }
+}
@@ -0,0 +1,171 @@
+//! Portable session archives, for moving a conversation between session
+//! stores (files, DynamoDB) or keeping a backup of it.
+//!
+//! The JSONL form mirrors the file store: a header line with `_type`
+//! `session_archive`, the key, timestamps, metadata (pins, contacts,
+//! overrides, ...) and optionally long-term memory, then one line per
+//! message. The JSON form is the header with a `messages` array.
+//! [`SessionArchive::parse`] reads either.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::memory::backend::MemoryBackend;
+
+use super::{Session, SessionMessage};
+
+/// `_type` of an archive header.
+pub const ARCHIVE_TYPE: &str = "session_archive";
+/// Format version written by this build.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// A session with everything needed to recreate it elsewhere.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionArchive {
+    #[serde(rename = "_type")]
+    pub type_field: String,
+    pub version: u32,
+    pub key: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+    /// Long-term memory at export time, when included.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<SessionMessage>,
+}
+
+impl SessionArchive {
+    pub fn from_session(session: &Session) -> Self {
+        Self {
+            type_field: ARCHIVE_TYPE.to_string(),
+            version: ARCHIVE_VERSION,
+            key: session.key.clone(),
+            created_at: session.created_at,
+            updated_at: session.updated_at,
+            metadata: session.metadata.clone(),
+            memory: None,
+            messages: session.messages.clone(),
+        }
+    }
+
+    /// Include the long-term memory of `memory`, if there is any.
+    pub fn with_memory(mut self, memory: &dyn MemoryBackend) -> Self {
+        let long_term = memory.read_long_term();
+        self.memory = (!long_term.trim().is_empty()).then_some(long_term);
+        self
+    }
+
+    /// The session to store, under `key` (default: the archived key).
+    pub fn into_session(self, key: Option<&str>) -> Session {
+        Session {
+            key: key.map(str::to_string).unwrap_or(self.key),
+            messages: self.messages,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            metadata: self.metadata,
+        }
+    }
+
+    /// Add the archived memory to `memory`'s long-term memory, unless it is
+    /// already there. Returns whether anything was written.
+    pub fn restore_memory(&self, memory: &dyn MemoryBackend) -> bool {
+        let Some(archived) = self.memory.as_deref().map(str::trim).filter(|m| !m.is_empty()) else {
+            return false;
+        };
+        let current = memory.read_long_term();
+        if current.contains(archived) {
+            return false;
+        }
+        if current.trim().is_empty() {
+            memory.write_long_term(archived);
+        } else {
+            memory.write_long_term(&format!("{}\n\n{}", current.trim_end(), archived));
+        }
+        true
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn to_jsonl(&self) -> String {
+        let header = Self { messages: Vec::new(), ..self.clone() };
+        let mut lines = vec![serde_json::to_string(&header).unwrap_or_default()];
+        lines.extend(self.messages.iter().filter_map(|m| serde_json::to_string(m).ok()));
+        lines.join("\n") + "\n"
+    }
+
+    /// Read an archive written by [`Self::to_json`] or [`Self::to_jsonl`].
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let content = content.trim_start();
+        // Pretty JSON spans lines; a JSONL header is a complete first line
+        let first = content.lines().next().unwrap_or_default();
+        let archive: Self = match serde_json::from_str::<Self>(first) {
+            Ok(mut archive) => {
+                for (i, line) in content.lines().enumerate().skip(1).filter(|(_, l)| !l.trim().is_empty()) {
+                    let message = serde_json::from_str(line).with_context(|| format!("line {}: not a message", i + 1))?;
+                    archive.messages.push(message);
+                }
+                archive
+            }
+            Err(_) => serde_json::from_str(content).context("not a session archive")?,
+        };
+        if archive.type_field != ARCHIVE_TYPE {
+            bail!("not a session archive (_type is \"{}\")", archive.type_field);
+        }
+        if archive.version > ARCHIVE_VERSION {
+            bail!("archive version {} is newer than this build supports ({})", archive.version, ARCHIVE_VERSION);
+        }
+        Ok(archive)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryStore;
+
+    fn session() -> Session {
+        let mut session = Session::new("line:U123");
+        session.add_message("user", "明日の天気は？");
+        session.add_message_from_channel("assistant", "晴れです。", "line");
+        session.metadata.insert("locale".into(), serde_json::json!("ja"));
+        session
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let archive = SessionArchive::from_session(&session());
+        for text in [archive.to_jsonl(), archive.to_json()] {
+            let parsed = SessionArchive::parse(&text).unwrap();
+            let restored = parsed.into_session(Some("web:moved"));
+            assert_eq!(restored.key, "web:moved");
+            assert_eq!(restored.messages.len(), 2);
+            assert_eq!(restored.messages[1].extra.get("channel"), Some(&serde_json::json!("line")));
+            assert_eq!(restored.metadata.get("locale"), Some(&serde_json::json!("ja")));
+        }
+        assert_eq!(archive.to_jsonl().lines().count(), 3);
+        assert!(SessionArchive::parse("{\"role\":\"user\",\"content\":\"hi\"}").is_err());
+    }
+
+    #[test]
+    fn test_memory() {
+        let from = tempfile::tempdir().unwrap();
+        let to = tempfile::tempdir().unwrap();
+        let source = MemoryStore::new(from.path());
+        source.write_long_term("- Likes green tea");
+        let archive = SessionArchive::from_session(&session()).with_memory(&source);
+
+        let target = MemoryStore::new(to.path());
+        target.write_long_term("- Lives in Tokyo");
+        assert!(archive.restore_memory(&target));
+        assert!(!archive.restore_memory(&target));
+        assert_eq!(target.read_long_term(), "- Lives in Tokyo\n\n- Likes green tea");
+    }
+}
@@ -0,0 +1,310 @@
+//! Named contacts and the channel identities they are reachable at.
+//!
+//! Contacts live in `Session::metadata["contacts"]`. The HTTP front-end
+//! resolves session keys through the `LINK#` records first, so a user's
+//! LINE, Telegram and web chats share one contact book. `/contacts` manages
+//! them, the API exposes them under `/api/v1/sessions/{id}/contacts`, and
+//! [`Session::contacts_context`] tells the model how to reach the people a
+//! message mentions ("send this to Yuki").
+
+use serde::{Deserialize, Serialize};
+
+use super::Session;
+
+/// Session metadata key holding the contact list.
+pub const CONTACTS_METADATA_KEY: &str = "contacts";
+
+/// Maximum number of contacts per session.
+pub const MAX_CONTACTS: usize = 200;
+
+/// Suffixes ignored when matching names ("ゆきさん" finds "ゆき").
+const HONORIFICS: &[&str] = &["さん", "さま", "様", "ちゃん", "くん", "君", "先生", "氏"];
+
+/// Channel prefixes accepted in identities, with their short forms.
+const CHANNEL_ALIASES: &[(&str, &str)] = &[("tg", "telegram"), ("mail", "email"), ("fb", "facebook"), ("wa", "whatsapp")];
+
+/// A person and the addresses they can be reached at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Contact {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// `channel:id` addresses: `line:U123`, `telegram:42`, `email:a@example.com`.
+    #[serde(default)]
+    pub identities: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+    pub updated_at: String,
+}
+
+impl Contact {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            aliases: Vec::new(),
+            identities: Vec::new(),
+            notes: String::new(),
+            updated_at: crate::util::timestamp(),
+        }
+    }
+
+    /// Whether `name` is this contact's name or one of its aliases.
+    pub fn answers_to(&self, name: &str) -> bool {
+        let name = normalize_name(name);
+        !name.is_empty() && std::iter::once(&self.name).chain(&self.aliases).any(|n| normalize_name(n) == name)
+    }
+
+    /// `(channel, id)` pairs, in the order they were added.
+    pub fn targets(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.identities.iter().filter_map(|i| i.split_once(':'))
+    }
+
+    /// One line for lists and prompts.
+    pub fn summary(&self) -> String {
+        let mut line = self.name.clone();
+        if !self.aliases.is_empty() {
+            line.push_str(&format!(" ({})", self.aliases.join(", ")));
+        }
+        if !self.identities.is_empty() {
+            line.push_str(&format!(" — {}", self.identities.join(", ")));
+        }
+        if !self.notes.is_empty() {
+            line.push_str(&format!(" · {}", self.notes));
+        }
+        line
+    }
+}
+
+/// Why a contact change was rejected.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ContactError {
+    #[error("contact name is empty")]
+    EmptyName,
+    #[error("'{0}' is not a channel identity (use channel:id or an email address)")]
+    BadIdentity(String),
+    #[error("no contact named '{0}'")]
+    NotFound(String),
+    #[error("'{identity}' already belongs to {owner}")]
+    Taken { identity: String, owner: String },
+    #[error("contact limit reached ({MAX_CONTACTS})")]
+    Full,
+}
+
+/// Lowercased name without honorifics or surrounding space.
+pub fn normalize_name(name: &str) -> String {
+    let mut name = name.trim();
+    for suffix in HONORIFICS {
+        if let Some(stripped) = name.strip_suffix(suffix).filter(|s| !s.is_empty()) {
+            name = stripped.trim_end();
+            break;
+        }
+    }
+    name.to_lowercase()
+}
+
+/// Canonical `channel:id` form: `tg:42` → `telegram:42`, a bare address →
+/// `email:…`. `None` if `raw` isn't an identity.
+pub fn normalize_identity(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if !raw.contains(':') && raw.contains('@') && !raw.starts_with('@') {
+        return Some(format!("email:{}", raw.to_lowercase()));
+    }
+    let (channel, id) = raw.split_once(':')?;
+    let channel = channel.to_lowercase();
+    if channel.is_empty() || id.is_empty() || !channel.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    let channel = CHANNEL_ALIASES.iter().find(|(short, _)| *short == channel).map(|(_, long)| long.to_string()).unwrap_or(channel);
+    let id = if channel == "email" { id.to_lowercase() } else { id.to_string() };
+    Some(format!("{}:{}", channel, id))
+}
+
+impl Session {
+    /// All contacts, in the order they were added.
+    pub fn contacts(&self) -> Vec<Contact> {
+        self.metadata
+            .get(CONTACTS_METADATA_KEY)
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    fn set_contacts(&mut self, contacts: &[Contact]) {
+        if contacts.is_empty() {
+            self.metadata.remove(CONTACTS_METADATA_KEY);
+        } else {
+            self.metadata
+                .insert(CONTACTS_METADATA_KEY.to_string(), serde_json::json!(contacts));
+        }
+        self.updated_at = chrono::Utc::now();
+    }
+
+    /// The contact called `name` (or with that alias).
+    pub fn find_contact(&self, name: &str) -> Option<Contact> {
+        self.contacts().into_iter().find(|c| c.answers_to(name))
+    }
+
+    /// The contact owning `identity` (`line:U123`, ...).
+    pub fn contact_for_identity(&self, identity: &str) -> Option<Contact> {
+        let identity = normalize_identity(identity)?;
+        self.contacts().into_iter().find(|c| c.identities.contains(&identity))
+    }
+
+    /// Create `name` or update it: identities and aliases are added, notes
+    /// replaced when given. An identity can belong to one contact only.
+    pub fn upsert_contact(
+        &mut self,
+        name: &str,
+        identities: &[String],
+        aliases: &[String],
+        notes: Option<&str>,
+    ) -> Result<Contact, ContactError> {
+        let name = name.trim();
+        if normalize_name(name).is_empty() {
+            return Err(ContactError::EmptyName);
+        }
+        let identities = identities
+            .iter()
+            .map(|i| normalize_identity(i).ok_or_else(|| ContactError::BadIdentity(i.clone())))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut contacts = self.contacts();
+        for identity in &identities {
+            if let Some(owner) = contacts.iter().find(|c| !c.answers_to(name) && c.identities.contains(identity)) {
+                return Err(ContactError::Taken { identity: identity.clone(), owner: owner.name.clone() });
+            }
+        }
+        let index = match contacts.iter().position(|c| c.answers_to(name)) {
+            Some(i) => i,
+            None if contacts.len() >= MAX_CONTACTS => return Err(ContactError::Full),
+            None => {
+                contacts.push(Contact::new(name));
+                contacts.len() - 1
+            }
+        };
+        let contact = &mut contacts[index];
+        for identity in identities {
+            if !contact.identities.contains(&identity) {
+                contact.identities.push(identity);
+            }
+        }
+        for alias in aliases.iter().map(|a| a.trim()).filter(|a| !a.is_empty()) {
+            if !contact.answers_to(alias) {
+                contact.aliases.push(alias.to_string());
+            }
+        }
+        if let Some(notes) = notes {
+            contact.notes = notes.trim().to_string();
+        }
+        contact.updated_at = crate::util::timestamp();
+        let contact = contact.clone();
+        self.set_contacts(&contacts);
+        Ok(contact)
+    }
+
+    /// Remove `identity` from `name`.
+    pub fn unlink_contact(&mut self, name: &str, identity: &str) -> Result<Contact, ContactError> {
+        let identity = normalize_identity(identity).ok_or_else(|| ContactError::BadIdentity(identity.to_string()))?;
+        let mut contacts = self.contacts();
+        let contact = contacts
+            .iter_mut()
+            .find(|c| c.answers_to(name))
+            .ok_or_else(|| ContactError::NotFound(name.to_string()))?;
+        contact.identities.retain(|i| *i != identity);
+        contact.updated_at = crate::util::timestamp();
+        let contact = contact.clone();
+        self.set_contacts(&contacts);
+        Ok(contact)
+    }
+
+    /// Delete a contact. Returns `true` if it existed.
+    pub fn remove_contact(&mut self, name: &str) -> bool {
+        let mut contacts = self.contacts();
+        let before = contacts.len();
+        contacts.retain(|c| !c.answers_to(name));
+        if contacts.len() == before {
+            return false;
+        }
+        self.set_contacts(&contacts);
+        true
+    }
+
+    /// Prompt block for the contacts `message` mentions by name or alias,
+    /// with their addresses. Empty if none are mentioned.
+    pub fn contacts_context(&self, message: &str) -> String {
+        let text = message.to_lowercase();
+        let mentioned: Vec<String> = self
+            .contacts()
+            .iter()
+            .filter(|c| {
+                std::iter::once(&c.name)
+                    .chain(&c.aliases)
+                    .map(|n| normalize_name(n))
+                    .any(|n| !n.is_empty() && text.contains(&n))
+            })
+            .map(|c| format!("- {}", c.summary()))
+            .collect();
+        if mentioned.is_empty() {
+            return String::new();
+        }
+        format!(
+            "## Contacts\n{}\nTo message a contact, send to the channel and id of one of their identities (channel:id).",
+            mentioned.join("\n")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_normalize_identity() {
+        assert_eq!(normalize_identity("tg:42").as_deref(), Some("telegram:42"));
+        assert_eq!(normalize_identity("Yuki@Example.com").as_deref(), Some("email:yuki@example.com"));
+        assert_eq!(normalize_identity("line:U1a2B").as_deref(), Some("line:U1a2B"));
+        assert_eq!(normalize_identity("just a name"), None);
+        assert_eq!(normalize_identity("@handle"), None);
+    }
+
+    #[test]
+    fn test_upsert_find_and_remove() {
+        let mut s = Session::new("line:U0");
+        s.upsert_contact("ゆき", &ids(&["line:U1"]), &ids(&["妻"]), Some("prefers LINE")).unwrap();
+        let yuki = s.upsert_contact("ゆきさん", &ids(&["tg:42"]), &[], None).unwrap();
+        assert_eq!(yuki.identities, ids(&["line:U1", "telegram:42"]));
+        assert_eq!(yuki.notes, "prefers LINE");
+        assert_eq!(s.contacts().len(), 1);
+
+        assert_eq!(s.find_contact("妻").unwrap().name, "ゆき");
+        assert_eq!(s.contact_for_identity("telegram:42").unwrap().name, "ゆき");
+        assert_eq!(yuki.targets().collect::<Vec<_>>(), vec![("line", "U1"), ("telegram", "42")]);
+
+        assert_eq!(
+            s.upsert_contact("Ken", &ids(&["line:U1"]), &[], None),
+            Err(ContactError::Taken { identity: "line:U1".into(), owner: "ゆき".into() })
+        );
+        assert!(matches!(s.upsert_contact("Ken", &ids(&["nope"]), &[], None), Err(ContactError::BadIdentity(_))));
+
+        assert_eq!(s.unlink_contact("ゆき", "line:U1").unwrap().identities, ids(&["telegram:42"]));
+        assert!(s.remove_contact("妻"));
+        assert!(s.contacts().is_empty());
+        assert!(!s.metadata.contains_key(CONTACTS_METADATA_KEY));
+    }
+
+    #[test]
+    fn test_contacts_context_only_mentions() {
+        let mut s = Session::new("cli:direct");
+        s.upsert_contact("Yuki", &ids(&["line:U1"]), &ids(&["妻"]), None).unwrap();
+        s.upsert_contact("Ken", &ids(&["telegram:7"]), &[], None).unwrap();
+
+        let ctx = s.contacts_context("LINEで妻にこれを送って");
+        assert!(ctx.contains("- Yuki (妻) — line:U1"), "{}", ctx);
+        assert!(!ctx.contains("Ken"));
+        assert_eq!(s.contacts_context("hello"), "");
+    }
+}
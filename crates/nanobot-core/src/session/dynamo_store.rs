@@ -25,6 +25,15 @@ impl DynamoSessionStore {
         }
     }
 
+    /// The store the Lambda uses: `DYNAMODB_SESSIONS_TABLE` (default
+    /// `nanobot-sessions`) and `NANOBOT_TENANT_ID` (default `default`).
+    pub async fn from_env() -> Self {
+        let table_name = std::env::var("DYNAMODB_SESSIONS_TABLE").unwrap_or_else(|_| "nanobot-sessions".to_string());
+        let tenant_id = std::env::var("NANOBOT_TENANT_ID").unwrap_or_else(|_| "default".to_string());
+        let aws = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        Self::new(Client::new(&aws), table_name, tenant_id)
+    }
+
     fn load_from_dynamo(&self, key: &str) -> Option<Session> {
         let rt = tokio::runtime::Handle::try_current().ok()?;
         let client = self.client.clone();
@@ -62,24 +71,25 @@ impl DynamoSessionStore {
         .flatten()
     }
 
-    fn save_to_dynamo(&self, session: &Session) {
-        let rt = match tokio::runtime::Handle::try_current() {
-            Ok(rt) => rt,
-            Err(_) => return,
-        };
+    /// Write `session` on a separate thread; join the handle to wait for
+    /// the write. `None` outside a Tokio runtime.
+    fn save_to_dynamo(&self, session: &Session) -> Option<std::thread::JoinHandle<Result<(), String>>> {
+        let rt = tokio::runtime::Handle::try_current().ok()?;
 
         let client = self.client.clone();
         let table = self.table_name.clone();
         let tenant = self.tenant_id.clone();
         let session_key = session.key.clone();
         let messages_json = serde_json::to_string(&session.messages).unwrap_or_default();
+        let metadata_json = serde_json::to_string(&session.metadata).unwrap_or_default();
         let created_at = session.created_at.to_rfc3339();
         let updated_at = session.updated_at.to_rfc3339();
         let ttl = (chrono::Utc::now().timestamp() + 30 * 24 * 3600).to_string();
 
-        // Fire-and-forget: do NOT call .join() — blocking a Tokio worker thread prevents
-        // the SSE stream from terminating (body.collect().await never completes in lambda_http).
-        let _ = std::thread::spawn(move || {
+        // Fire-and-forget for regular saves: do NOT call .join() — blocking a Tokio worker thread
+        // prevents the SSE stream from terminating (body.collect().await never completes in lambda_http).
+        // Only `import` (CLI) waits.
+        Some(std::thread::spawn(move || {
             rt.block_on(async {
                 let result = client
                     .put_item()
@@ -87,17 +97,19 @@ impl DynamoSessionStore {
                     .item("tenant_id", AttributeValue::S(tenant))
                     .item("session_key", AttributeValue::S(session_key))
                     .item("messages", AttributeValue::S(messages_json))
+                    .item("metadata", AttributeValue::S(metadata_json))
                     .item("created_at", AttributeValue::S(created_at))
                     .item("updated_at", AttributeValue::S(updated_at))
                     .item("ttl", AttributeValue::N(ttl))
                     .send()
                     .await;
 
-                if let Err(e) = result {
+                result.map(|_| ()).map_err(|e| {
                     warn!("DynamoDB put_item error: {}", e);
-                }
+                    e.to_string()
+                })
             })
-        });
+        }))
     }
 }
 
@@ -112,6 +124,14 @@ impl SessionStore for DynamoSessionStore {
         self.cache.get_mut(key).unwrap()
     }
 
+    fn get(&mut self, key: &str) -> Option<&mut Session> {
+        if !self.cache.contains_key(key) {
+            let session = self.load_from_dynamo(key)?;
+            self.cache.insert(key.to_string(), session);
+        }
+        self.cache.get_mut(key)
+    }
+
     fn refresh(&mut self, key: &str) -> &mut Session {
         self.cache.remove(key);
         self.get_or_create(key)
@@ -127,6 +147,18 @@ impl SessionStore for DynamoSessionStore {
         }
     }
 
+    fn import(&mut self, session: Session) -> anyhow::Result<()> {
+        let write = self
+            .save_to_dynamo(&session)
+            .ok_or_else(|| anyhow::anyhow!("DynamoDB session store needs a Tokio runtime"))?;
+        write
+            .join()
+            .map_err(|_| anyhow::anyhow!("DynamoDB write thread panicked"))?
+            .map_err(|e| anyhow::anyhow!("DynamoDB put_item failed: {}", e))?;
+        self.cache.insert(session.key.clone(), session);
+        Ok(())
+    }
+
     fn delete(&mut self, key: &str) -> bool {
         self.cache.remove(key);
 
@@ -228,6 +260,12 @@ fn parse_session_from_item(
     let messages_str = item.get("messages").and_then(|v| v.as_s().ok())?;
     let messages: Vec<SessionMessage> = serde_json::from_str(messages_str).ok()?;
 
+    let metadata: HashMap<String, serde_json::Value> = item
+        .get("metadata")
+        .and_then(|v| v.as_s().ok())
+        .and_then(|s| serde_json::from_str(s).ok())
+        .unwrap_or_default();
+
     let created_at = item
         .get("created_at")
         .and_then(|v| v.as_s().ok())
@@ -247,6 +285,6 @@ fn parse_session_from_item(
         messages,
         created_at,
         updated_at,
-        metadata: HashMap::new(),
+        metadata,
     })
 }
@@ -80,23 +80,8 @@ impl FileSessionStore {
             metadata,
         })
     }
-}
-
-impl SessionStore for FileSessionStore {
-    fn get_or_create(&mut self, key: &str) -> &mut Session {
-        if !self.cache.contains_key(key) {
-            let session = self.load(key).unwrap_or_else(|| Session::new(key));
-            self.cache.insert(key.to_string(), session);
-        }
-        self.cache.get_mut(key).unwrap()
-    }
-
-    fn refresh(&mut self, key: &str) -> &mut Session {
-        self.cache.remove(key);
-        self.get_or_create(key)
-    }
 
-    fn save(&self, session: &Session) {
+    fn write(&self, session: &Session) -> std::io::Result<()> {
         let path = self.session_path(&session.key);
         let mut lines = Vec::with_capacity(session.messages.len() + 1);
 
@@ -117,7 +102,34 @@ impl SessionStore for FileSessionStore {
         }
 
         let content = lines.join("\n") + "\n";
-        if let Err(e) = std::fs::write(&path, content) {
+        std::fs::write(&path, content)
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn get_or_create(&mut self, key: &str) -> &mut Session {
+        if !self.cache.contains_key(key) {
+            let session = self.load(key).unwrap_or_else(|| Session::new(key));
+            self.cache.insert(key.to_string(), session);
+        }
+        self.cache.get_mut(key).unwrap()
+    }
+
+    fn get(&mut self, key: &str) -> Option<&mut Session> {
+        if !self.cache.contains_key(key) {
+            let session = self.load(key)?;
+            self.cache.insert(key.to_string(), session);
+        }
+        self.cache.get_mut(key)
+    }
+
+    fn refresh(&mut self, key: &str) -> &mut Session {
+        self.cache.remove(key);
+        self.get_or_create(key)
+    }
+
+    fn save(&self, session: &Session) {
+        if let Err(e) = self.write(session) {
             warn!("Failed to save session {}: {}", session.key, e);
         }
     }
@@ -138,6 +150,12 @@ impl SessionStore for FileSessionStore {
         }
     }
 
+    fn import(&mut self, session: Session) -> anyhow::Result<()> {
+        self.write(&session)?;
+        self.cache.insert(session.key.clone(), session);
+        Ok(())
+    }
+
     fn list_sessions(&self) -> Vec<serde_json::Value> {
         let mut sessions = Vec::new();
         if let Ok(entries) = std::fs::read_dir(&self.sessions_dir) {
@@ -241,4 +259,38 @@ mod tests {
         assert!(store.delete("del:test"));
         assert!(!path.exists());
     }
+
+    #[test]
+    fn test_file_session_store_get_does_not_create() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut store = FileSessionStore::new(tmp.path());
+        store.sessions_dir = tmp.path().join("sessions");
+        std::fs::create_dir_all(&store.sessions_dir).unwrap();
+
+        assert!(store.get("get:nobody").is_none());
+        assert!(store.cache.is_empty());
+
+        store.get_or_create("get:test").add_message("user", "hi");
+        store.save_by_key("get:test");
+        store.cache.clear();
+        assert_eq!(store.get("get:test").unwrap().messages.len(), 1);
+    }
+
+    #[test]
+    fn test_file_session_store_export_import() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut store = FileSessionStore::new(tmp.path());
+        store.sessions_dir = tmp.path().join("sessions");
+        std::fs::create_dir_all(&store.sessions_dir).unwrap();
+
+        store.get_or_create("line:U1").add_message("user", "moving soon");
+        store.save_by_key("line:U1");
+        assert!(store.export("line:nobody").is_none());
+        let archive = store.export("line:U1").unwrap();
+
+        store.import(archive.into_session(Some("web:U1"))).unwrap();
+        store.cache.clear();
+        let session = store.get_or_create("web:U1");
+        assert_eq!(session.messages[0].content, "moving soon");
+    }
 }
@@ -0,0 +1,48 @@
+//! Handoff state of a conversation (see [`crate::agent::handoff`]).
+//!
+//! While `Session::metadata["handoff"]` is set the bot stays quiet in that
+//! session and an operator answers instead.
+
+use serde::{Deserialize, Serialize};
+
+use super::Session;
+
+/// Session metadata key holding the active handoff.
+pub const HANDOFF_KEY: &str = "handoff";
+
+/// A conversation handed to a human operator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Handoff {
+    pub since: String,
+    pub reason: String,
+}
+
+impl Session {
+    /// The active handoff, if an operator has this conversation.
+    pub fn handoff(&self) -> Option<Handoff> {
+        self.metadata
+            .get(HANDOFF_KEY)
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+    }
+
+    /// Hand the conversation to an operator; `false` if it already is.
+    pub fn start_handoff(&mut self, reason: &str) -> bool {
+        if self.handoff().is_some() {
+            return false;
+        }
+        let handoff = Handoff { since: crate::util::timestamp(), reason: reason.to_string() };
+        self.metadata.insert(HANDOFF_KEY.to_string(), serde_json::json!(handoff));
+        self.updated_at = chrono::Utc::now();
+        true
+    }
+
+    /// Give the conversation back to the bot; `false` if it wasn't handed off.
+    pub fn end_handoff(&mut self) -> bool {
+        let ended = self.metadata.remove(HANDOFF_KEY).is_some();
+        if ended {
+            self.updated_at = chrono::Utc::now();
+        }
+        ended
+    }
+}
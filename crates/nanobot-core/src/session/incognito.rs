@@ -0,0 +1,96 @@
+//! Incognito (privacy) mode set via `/incognito`.
+//!
+//! `/incognito` or `/incognito on` switches the session to incognito until
+//! `/incognito off`; `/incognito <message>` sends just that message
+//! privately. Incognito exchanges see the saved history but are not added
+//! to it; only the flag itself is stored, on every channel including the
+//! LINE and Telegram webhooks. The HTTP API also keeps them out of memory,
+//! routing and audit logs, and the agent loop sends no
+//! `MessageProcessed` webhook and leaves forwards out of the sender's
+//! history. The agent's tools still run, so a file the model writes (a
+//! memory note included) is kept. Replies are labelled so the user can tell.
+
+use super::locale::Locale;
+use super::Session;
+
+/// Session metadata key holding the `/incognito` switch; also set on the
+/// metadata of inbound messages sent with `/incognito <message>`.
+pub const INCOGNITO_KEY: &str = "incognito";
+
+impl Session {
+    /// Whether `/incognito` is on for this session.
+    pub fn incognito(&self) -> bool {
+        self.metadata.get(INCOGNITO_KEY).and_then(|v| v.as_bool()).unwrap_or(false)
+    }
+
+    /// Switch incognito mode on or off.
+    pub fn set_incognito(&mut self, on: bool) {
+        if on {
+            self.metadata.insert(INCOGNITO_KEY.to_string(), serde_json::json!(true));
+        } else {
+            self.metadata.remove(INCOGNITO_KEY);
+        }
+        self.updated_at = chrono::Utc::now();
+    }
+}
+
+/// What `/incognito <args>` asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Switch<'a> {
+    On,
+    Off,
+    /// Send this one message privately.
+    Message(&'a str),
+}
+
+impl<'a> Switch<'a> {
+    pub fn parse(args: &'a str) -> Self {
+        match args.trim().to_lowercase().as_str() {
+            "" | "on" | "オン" => Switch::On,
+            "off" | "オフ" => Switch::Off,
+            _ => Switch::Message(args.trim()),
+        }
+    }
+}
+
+/// The message of a one-off `/incognito <message>`, if `text` is one.
+pub fn one_off(text: &str) -> Option<&str> {
+    match crate::command::parse(text) {
+        Some(inv) if inv.name() == "incognito" => match Switch::parse(inv.args) {
+            Switch::Message(message) => Some(message),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Mark a reply as not saved.
+pub fn label(reply: &str, locale: Locale) -> String {
+    format!("{}\n{}", locale.pick("🕶 シークレット（保存されません）", "🕶 Incognito (not saved)"), reply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incognito_switch() {
+        let mut s = Session::new("test");
+        assert!(!s.incognito());
+        s.set_incognito(true);
+        assert!(s.incognito());
+        s.set_incognito(false);
+        assert!(!s.incognito());
+        assert!(s.metadata.is_empty());
+    }
+
+    #[test]
+    fn test_one_off() {
+        assert_eq!(one_off("/incognito what did my test results mean?"), Some("what did my test results mean?"));
+        assert_eq!(one_off("/incognito"), None);
+        assert_eq!(one_off("/incognito OFF"), None);
+        assert_eq!(one_off("/reset"), None);
+        assert_eq!(one_off("incognito please"), None);
+        assert_eq!(Switch::parse(" on "), Switch::On);
+    }
+}
@@ -0,0 +1,195 @@
+//! Per-session response locale.
+//!
+//! The locale follows the language of the user's latest message until it is
+//! fixed with `/lang`, so users can switch between 日本語 and English
+//! mid-conversation.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::Session;
+
+/// Session metadata key holding the active locale code.
+pub const LOCALE_KEY: &str = "locale";
+
+/// Session metadata key set when the locale was chosen with `/lang`.
+pub const LOCALE_MANUAL_KEY: &str = "locale_manual";
+
+/// Supported UI/response languages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Ja,
+    En,
+}
+
+impl Locale {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::Ja => "ja",
+            Locale::En => "en",
+        }
+    }
+
+    pub fn is_english(&self) -> bool {
+        *self == Locale::En
+    }
+
+    /// Choose between a Japanese and an English string.
+    pub fn pick<'a>(&self, ja: &'a str, en: &'a str) -> &'a str {
+        match self {
+            Locale::Ja => ja,
+            Locale::En => en,
+        }
+    }
+
+    /// Parse a user-supplied language name (`ja`, `en`, `日本語`, `English`, `en_US.UTF-8`, ...).
+    pub fn parse(s: &str) -> Option<Self> {
+        let lower = s.trim().to_lowercase();
+        match lower.as_str() {
+            "ja" | "jp" | "japanese" | "日本語" | "にほんご" => Some(Locale::Ja),
+            "en" | "english" | "英語" | "えいご" => Some(Locale::En),
+            _ if lower.starts_with("ja_") || lower.starts_with("ja-") => Some(Locale::Ja),
+            _ if lower.starts_with("en_") || lower.starts_with("en-") => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    /// Guess the language of a message. Any kana/kanji means Japanese; a few
+    /// Latin words with no CJK means English. Returns `None` when unclear
+    /// (emoji, numbers, very short input).
+    pub fn detect(text: &str) -> Option<Self> {
+        let mut latin = 0usize;
+        for c in text.chars() {
+            match c {
+                '\u{3040}'..='\u{30FF}' | '\u{4E00}'..='\u{9FFF}' | '\u{FF66}'..='\u{FF9F}' => {
+                    return Some(Locale::Ja);
+                }
+                c if c.is_ascii_alphabetic() => latin += 1,
+                _ => {}
+            }
+        }
+        if latin >= 4 {
+            Some(Locale::En)
+        } else {
+            None
+        }
+    }
+
+    /// Timezone assumed for a user who hasn't set one with `/timezone`:
+    /// Japan for Japanese speakers and LINE (a Japan-only channel), UTC otherwise.
+    pub fn default_timezone(&self, channel: &str) -> &'static str {
+        if *self == Locale::Ja || channel == "line" {
+            "Asia/Tokyo"
+        } else {
+            "UTC"
+        }
+    }
+
+    /// Locale from the `LANG` / `LC_ALL` environment (CLI).
+    pub fn from_env() -> Option<Self> {
+        ["LC_ALL", "LANG"]
+            .iter()
+            .filter_map(|k| std::env::var(k).ok())
+            .find_map(|v| Self::parse(&v))
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl Session {
+    /// Locale stored on the session, if any.
+    pub fn locale(&self) -> Option<Locale> {
+        self.metadata
+            .get(LOCALE_KEY)
+            .and_then(|v| v.as_str())
+            .and_then(Locale::parse)
+    }
+
+    /// Whether the locale was fixed with `/lang`.
+    pub fn locale_is_manual(&self) -> bool {
+        self.metadata
+            .get(LOCALE_MANUAL_KEY)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// Fix the locale (`/lang en`), or return to auto-detection with `None`.
+    pub fn set_locale(&mut self, locale: Option<Locale>) {
+        match locale {
+            Some(l) => {
+                self.metadata.insert(LOCALE_KEY.to_string(), serde_json::json!(l.code()));
+                self.metadata.insert(LOCALE_MANUAL_KEY.to_string(), serde_json::json!(true));
+            }
+            None => {
+                self.metadata.remove(LOCALE_MANUAL_KEY);
+            }
+        }
+        self.updated_at = chrono::Utc::now();
+    }
+
+    /// Update the auto-detected locale from a user message and return the
+    /// effective locale (falling back to `default`). A `/lang` choice is kept.
+    pub fn observe_locale(&mut self, text: &str, default: Locale) -> Locale {
+        if !self.locale_is_manual() {
+            if let Some(detected) = Locale::detect(text) {
+                if self.locale() != Some(detected) {
+                    self.metadata
+                        .insert(LOCALE_KEY.to_string(), serde_json::json!(detected.code()));
+                }
+            }
+        }
+        self.locale().unwrap_or(default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Locale::detect("こんにちは"), Some(Locale::Ja));
+        assert_eq!(Locale::detect("Rustで書いて"), Some(Locale::Ja));
+        assert_eq!(Locale::detect("Please write it in Rust"), Some(Locale::En));
+        assert_eq!(Locale::detect("ok"), None);
+        assert_eq!(Locale::detect("👍 123"), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Locale::parse("EN"), Some(Locale::En));
+        assert_eq!(Locale::parse("日本語"), Some(Locale::Ja));
+        assert_eq!(Locale::parse("en_US.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::parse("fr"), None);
+    }
+
+    #[test]
+    fn test_default_timezone() {
+        assert_eq!(Locale::Ja.default_timezone("telegram"), "Asia/Tokyo");
+        assert_eq!(Locale::En.default_timezone("line"), "Asia/Tokyo");
+        assert_eq!(Locale::En.default_timezone("discord"), "UTC");
+    }
+
+    #[test]
+    fn test_observe_and_manual_override() {
+        let mut s = Session::new("test");
+        assert_eq!(s.observe_locale("ok", Locale::Ja), Locale::Ja);
+        assert_eq!(s.observe_locale("Tell me a joke", Locale::Ja), Locale::En);
+        assert_eq!(s.observe_locale("ok", Locale::Ja), Locale::En);
+        assert_eq!(s.observe_locale("日本語でお願い", Locale::En), Locale::Ja);
+
+        s.set_locale(Some(Locale::En));
+        assert_eq!(s.observe_locale("日本語でお願い", Locale::Ja), Locale::En);
+        assert!(s.locale_is_manual());
+
+        s.set_locale(None);
+        assert_eq!(s.observe_locale("日本語でお願い", Locale::En), Locale::Ja);
+    }
+}
@@ -1,5 +1,15 @@
 pub mod store;
+pub mod archive;
 pub mod file_store;
+pub mod locale;
+pub mod overrides;
+pub mod incognito;
+pub mod pins;
+pub mod contacts;
+pub mod handoff;
+pub mod onboarding;
+pub mod sync;
+pub mod turn;
 
 #[cfg(feature = "dynamodb-backend")]
 pub mod dynamo_store;
@@ -137,7 +147,8 @@ impl Session {
         result
     }
 
-    /// Get full message history including channel and timestamp (for API responses).
+    /// Get full message history including channel, timestamp and turn
+    /// metadata (for API responses).
     pub fn get_full_history(&self, max_messages: usize) -> Vec<serde_json::Value> {
         let start = self.messages.len().saturating_sub(max_messages);
         self.messages[start..]
@@ -153,6 +164,9 @@ impl Session {
                 if let Some(ch) = m.extra.get("channel") {
                     v["channel"] = ch.clone();
                 }
+                if let Some(meta) = m.turn_meta() {
+                    v["meta"] = serde_json::json!(meta);
+                }
                 v
             })
             .collect()
@@ -163,6 +177,24 @@ impl Session {
         self.messages.clear();
         self.updated_at = chrono::Utc::now();
     }
+
+    /// Content of the most recent assistant message.
+    pub fn last_assistant_message(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|m| m.role == "assistant")
+            .map(|m| m.content.as_str())
+    }
+
+    /// Remove the last user message and everything after it, returning the
+    /// user message so the reply can be regenerated.
+    pub fn pop_last_turn(&mut self) -> Option<String> {
+        let pos = self.messages.iter().rposition(|m| m.role == "user")?;
+        let user = self.messages.drain(pos..).next().map(|m| m.content);
+        self.updated_at = chrono::Utc::now();
+        user
+    }
 }
 
 // Re-export for backward compat: SessionManager is now FileSessionStore
@@ -191,6 +223,19 @@ mod tests {
         assert_eq!(session.messages[1].role, "assistant");
     }
 
+    #[test]
+    fn test_full_history_meta() {
+        let mut session = Session::new("test");
+        session.add_message_from_channel("user", "Hello", "web");
+        session.add_message_from_channel("assistant", "Hi!", "web");
+        session.set_turn_meta(&turn::TurnMeta::new("gemini-2.5-flash"));
+
+        let history = session.get_full_history(10);
+        assert!(history[0].get("meta").is_none());
+        assert_eq!(history[1]["meta"]["model"], "gemini-2.5-flash");
+        assert_eq!(session.get_history(10)[1].get("meta"), None);
+    }
+
     #[test]
     fn test_session_get_history() {
         let mut session = Session::new("test");
@@ -212,4 +257,19 @@ mod tests {
         session.clear();
         assert!(session.messages.is_empty());
     }
+
+    #[test]
+    fn test_session_pop_last_turn() {
+        let mut session = Session::new("test");
+        assert_eq!(session.pop_last_turn(), None);
+        session.add_message("user", "first");
+        session.add_message("assistant", "one");
+        session.add_message("user", "second");
+        session.add_message("assistant", "two");
+        assert_eq!(session.last_assistant_message(), Some("two"));
+
+        assert_eq!(session.pop_last_turn().as_deref(), Some("second"));
+        assert_eq!(session.messages.len(), 2);
+        assert_eq!(session.last_assistant_message(), Some("one"));
+    }
 }
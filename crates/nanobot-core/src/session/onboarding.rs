@@ -0,0 +1,36 @@
+//! Onboarding progress of a conversation (see [`crate::onboarding`]).
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::Session;
+
+/// Session metadata key holding the onboarding progress.
+pub const ONBOARDING_KEY: &str = "onboarding";
+
+/// How far a conversation got through `onboarding.yaml`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnboardingState {
+    /// Index of the next step; while it is a question, the step being answered.
+    pub step: usize,
+    pub done: bool,
+    /// Answers to questions, by step id.
+    #[serde(default)]
+    pub answers: BTreeMap<String, String>,
+}
+
+impl Session {
+    /// Onboarding progress; `None` before the first contact.
+    pub fn onboarding(&self) -> Option<OnboardingState> {
+        self.metadata
+            .get(ONBOARDING_KEY)
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+    }
+
+    pub fn set_onboarding(&mut self, state: &OnboardingState) {
+        self.metadata.insert(ONBOARDING_KEY.to_string(), serde_json::json!(state));
+        self.updated_at = chrono::Utc::now();
+    }
+}
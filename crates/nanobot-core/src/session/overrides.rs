@@ -0,0 +1,131 @@
+//! Session-scoped overrides set via `/model`, `/agent`, `/length`, `/tone`
+//! and `/timezone`.
+
+use crate::agent::length::LengthPreference;
+
+use super::Session;
+
+/// Session metadata key holding the `/model` override.
+pub const MODEL_OVERRIDE_KEY: &str = "model_override";
+
+/// Session metadata key holding the `/agent` override.
+pub const AGENT_OVERRIDE_KEY: &str = "agent_override";
+
+/// Session metadata key holding the `/length` preference.
+pub const LENGTH_OVERRIDE_KEY: &str = "length_override";
+
+/// Session metadata key holding the `/tone` instruction.
+pub const TONE_OVERRIDE_KEY: &str = "tone_override";
+
+/// Session metadata key holding the `/timezone` setting.
+pub const TIMEZONE_KEY: &str = "timezone";
+
+impl Session {
+    /// Model pinned for this session with `/model`, if any.
+    pub fn model_override(&self) -> Option<&str> {
+        self.metadata_str(MODEL_OVERRIDE_KEY)
+    }
+
+    /// Set or clear (`None`) the session model override.
+    pub fn set_model_override(&mut self, model: Option<&str>) {
+        self.set_metadata_str(MODEL_OVERRIDE_KEY, model);
+    }
+
+    /// Agent profile pinned for this session with `/agent`, if any.
+    pub fn agent_override(&self) -> Option<&str> {
+        self.metadata_str(AGENT_OVERRIDE_KEY)
+    }
+
+    /// Set or clear (`None`) the session agent override.
+    pub fn set_agent_override(&mut self, agent: Option<&str>) {
+        self.set_metadata_str(AGENT_OVERRIDE_KEY, agent);
+    }
+
+    /// Response length preference set with `/length`, if any.
+    pub fn length_override(&self) -> Option<LengthPreference> {
+        self.metadata_str(LENGTH_OVERRIDE_KEY).and_then(LengthPreference::parse)
+    }
+
+    /// Set or clear (`None`) the response length preference.
+    pub fn set_length_override(&mut self, pref: Option<LengthPreference>) {
+        self.set_metadata_str(LENGTH_OVERRIDE_KEY, pref.map(|p| p.as_string()).as_deref());
+    }
+
+    /// Tone instruction set with `/tone`, if any.
+    pub fn tone_override(&self) -> Option<&str> {
+        self.metadata_str(TONE_OVERRIDE_KEY)
+    }
+
+    /// Set or clear (`None`) the tone instruction.
+    pub fn set_tone_override(&mut self, tone: Option<&str>) {
+        self.set_metadata_str(TONE_OVERRIDE_KEY, tone);
+    }
+
+    /// Timezone set with `/timezone` (e.g. `Asia/Tokyo`, `+09:00`), if any.
+    pub fn timezone(&self) -> Option<&str> {
+        self.metadata_str(TIMEZONE_KEY)
+    }
+
+    /// Set or clear (`None`) the session timezone.
+    pub fn set_timezone(&mut self, tz: Option<&str>) {
+        self.set_metadata_str(TIMEZONE_KEY, tz);
+    }
+
+    fn metadata_str(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .get(key)
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+    }
+
+    fn set_metadata_str(&mut self, key: &str, value: Option<&str>) {
+        match value.map(str::trim).filter(|s| !s.is_empty()) {
+            Some(v) => {
+                self.metadata.insert(key.to_string(), serde_json::json!(v));
+            }
+            None => {
+                self.metadata.remove(key);
+            }
+        }
+        self.updated_at = chrono::Utc::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_roundtrip() {
+        let mut s = Session::new("test");
+        assert_eq!(s.model_override(), None);
+        s.set_model_override(Some("claude-sonnet-4-6"));
+        s.set_agent_override(Some("coder"));
+        assert_eq!(s.model_override(), Some("claude-sonnet-4-6"));
+        assert_eq!(s.agent_override(), Some("coder"));
+
+        s.set_model_override(None);
+        s.set_agent_override(Some("  "));
+        assert_eq!(s.model_override(), None);
+        assert_eq!(s.agent_override(), None);
+        assert!(s.metadata.is_empty());
+    }
+
+    #[test]
+    fn test_length_and_tone_overrides() {
+        let mut s = Session::new("test");
+        s.set_length_override(Some(LengthPreference::Chars(120)));
+        s.set_tone_override(Some("敬語で"));
+        assert_eq!(s.length_override(), Some(LengthPreference::Chars(120)));
+        assert_eq!(s.tone_override(), Some("敬語で"));
+
+        s.set_length_override(None);
+        s.set_tone_override(None);
+        assert!(s.metadata.is_empty());
+
+        s.set_timezone(Some("America/New_York"));
+        assert_eq!(s.timezone(), Some("America/New_York"));
+        s.set_timezone(None);
+        assert_eq!(s.timezone(), None);
+    }
+}
@@ -0,0 +1,201 @@
+//! User-pinned context items (facts, file paths, URLs).
+//!
+//! Pins live in `Session::metadata["pins"]` and are injected into every
+//! system prompt within a dedicated token budget, so users get predictable
+//! context instead of relying on automatic memory extraction.
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent::prompt::estimate_tokens;
+
+use super::Session;
+
+/// Session metadata key holding the pin list.
+pub const PINS_METADATA_KEY: &str = "pins";
+
+/// Default token budget for the pinned-context block.
+pub const DEFAULT_PIN_BUDGET_TOKENS: usize = 500;
+
+/// Maximum number of pins per session.
+pub const MAX_PINS: usize = 50;
+
+/// What a pin refers to (only affects how it is labelled in the prompt).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PinKind {
+    Fact,
+    File,
+    Url,
+}
+
+impl PinKind {
+    /// Guess the kind from the pinned text.
+    pub fn detect(content: &str) -> Self {
+        let c = content.trim();
+        if c.starts_with("http://") || c.starts_with("https://") {
+            PinKind::Url
+        } else if !c.contains(char::is_whitespace)
+            && (c.starts_with('/') || c.starts_with("./") || c.starts_with("~/"))
+        {
+            PinKind::File
+        } else {
+            PinKind::Fact
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PinKind::Fact => "fact",
+            PinKind::File => "file",
+            PinKind::Url => "url",
+        }
+    }
+}
+
+/// A single pinned item.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pin {
+    pub id: u32,
+    pub kind: PinKind,
+    pub content: String,
+    pub created_at: String,
+}
+
+impl Session {
+    /// All pins for this session, oldest first.
+    pub fn pins(&self) -> Vec<Pin> {
+        self.metadata
+            .get(PINS_METADATA_KEY)
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    fn set_pins(&mut self, pins: &[Pin]) {
+        if pins.is_empty() {
+            self.metadata.remove(PINS_METADATA_KEY);
+        } else {
+            self.metadata
+                .insert(PINS_METADATA_KEY.to_string(), serde_json::json!(pins));
+        }
+        self.updated_at = chrono::Utc::now();
+    }
+
+    /// Pin a new item. Returns `None` if the content is empty or the session is full.
+    /// Pinning identical content again returns the existing pin.
+    pub fn add_pin(&mut self, content: &str) -> Option<Pin> {
+        let content = content.trim();
+        if content.is_empty() {
+            return None;
+        }
+        let mut pins = self.pins();
+        if let Some(existing) = pins.iter().find(|p| p.content == content) {
+            return Some(existing.clone());
+        }
+        if pins.len() >= MAX_PINS {
+            return None;
+        }
+        let pin = Pin {
+            id: pins.iter().map(|p| p.id).max().unwrap_or(0) + 1,
+            kind: PinKind::detect(content),
+            content: content.to_string(),
+            created_at: crate::util::timestamp(),
+        };
+        pins.push(pin.clone());
+        self.set_pins(&pins);
+        Some(pin)
+    }
+
+    /// Remove a pin by id. Returns `true` if it existed.
+    pub fn remove_pin(&mut self, id: u32) -> bool {
+        let mut pins = self.pins();
+        let before = pins.len();
+        pins.retain(|p| p.id != id);
+        if pins.len() == before {
+            return false;
+        }
+        self.set_pins(&pins);
+        true
+    }
+
+    /// Remove every pin. Returns how many were removed.
+    pub fn clear_pins(&mut self) -> usize {
+        let n = self.pins().len();
+        if n > 0 {
+            self.set_pins(&[]);
+        }
+        n
+    }
+
+    /// Render pins as a system prompt block, keeping within `budget_tokens`.
+    /// Pins that don't fit are counted but omitted. Empty if there are no pins.
+    pub fn pins_context(&self, budget_tokens: usize) -> String {
+        let pins = self.pins();
+        if pins.is_empty() {
+            return String::new();
+        }
+        let header = "## ピン留め情報（ユーザーが常に参照してほしい内容）";
+        let mut used = estimate_tokens(header);
+        let mut lines = vec![header.to_string()];
+        let mut omitted = 0usize;
+        for pin in &pins {
+            let line = format!("- [{}:{}] {}", pin.kind.label(), pin.id, pin.content);
+            let cost = estimate_tokens(&line) + 1;
+            if used + cost > budget_tokens {
+                omitted += 1;
+                continue;
+            }
+            used += cost;
+            lines.push(line);
+        }
+        if omitted > 0 {
+            lines.push(format!("（予算超過のため{}件省略）", omitted));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pin_kind_detect() {
+        assert_eq!(PinKind::detect("https://example.com"), PinKind::Url);
+        assert_eq!(PinKind::detect("/src/main.rs"), PinKind::File);
+        assert_eq!(PinKind::detect("./Cargo.toml"), PinKind::File);
+        assert_eq!(PinKind::detect("My cat is named Tama"), PinKind::Fact);
+    }
+
+    #[test]
+    fn test_add_and_remove_pins() {
+        let mut s = Session::new("test");
+        let a = s.add_pin("Deploy target is fly.io").unwrap();
+        let b = s.add_pin("https://docs.rs").unwrap();
+        assert_eq!((a.id, b.id), (1, 2));
+        assert_eq!(s.add_pin("Deploy target is fly.io").unwrap().id, 1);
+        assert!(s.add_pin("   ").is_none());
+        assert_eq!(s.pins().len(), 2);
+
+        assert!(s.remove_pin(1));
+        assert!(!s.remove_pin(1));
+        assert_eq!(s.add_pin("another").unwrap().id, 3);
+        assert_eq!(s.clear_pins(), 2);
+        assert!(!s.metadata.contains_key(PINS_METADATA_KEY));
+    }
+
+    #[test]
+    fn test_pins_context_budget() {
+        let mut s = Session::new("test");
+        assert_eq!(s.pins_context(100), "");
+        s.add_pin("short fact");
+        s.add_pin(&"x".repeat(400));
+        let ctx = s.pins_context(DEFAULT_PIN_BUDGET_TOKENS);
+        assert!(ctx.contains("[fact:1] short fact"));
+        assert!(ctx.contains("[fact:2]"));
+
+        let small = s.pins_context(40);
+        assert!(small.contains("short fact"));
+        assert!(!small.contains("[fact:2]"));
+        assert!(small.contains("1件省略"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::session::archive::SessionArchive;
 use crate::session::Session;
 
 /// Trait for session storage backends.
@@ -5,6 +6,13 @@ pub trait SessionStore: Send + Sync {
     /// Get an existing session or create a new one.
     fn get_or_create(&mut self, key: &str) -> &mut Session;
 
+    /// The session stored under `key`, if there is one. Unlike
+    /// [`SessionStore::get_or_create`], nothing is created.
+    fn get(&mut self, key: &str) -> Option<&mut Session> {
+        let session = self.get_or_create(key);
+        (!session.messages.is_empty() || !session.metadata.is_empty()).then_some(session)
+    }
+
     /// Force-reload session from storage (invalidate cache).
     fn refresh(&mut self, key: &str) -> &mut Session;
 
@@ -19,4 +27,20 @@ pub trait SessionStore: Send + Sync {
 
     /// List all sessions.
     fn list_sessions(&self) -> Vec<serde_json::Value>;
+
+    /// A portable copy of the stored session (see [`SessionArchive`]);
+    /// `None` when nothing is stored under `key`.
+    fn export(&mut self, key: &str) -> Option<SessionArchive> {
+        let session = self.refresh(key);
+        (!session.messages.is_empty() || !session.metadata.is_empty()).then(|| SessionArchive::from_session(session))
+    }
+
+    /// Store `session`, replacing any session with the same key. Unlike
+    /// [`SessionStore::save`], the write has finished when this returns.
+    fn import(&mut self, session: Session) -> anyhow::Result<()> {
+        let key = session.key.clone();
+        *self.refresh(&key) = session;
+        self.save_by_key(&key);
+        Ok(())
+    }
 }
@@ -0,0 +1,330 @@
+//! Delta sync of linked sessions between the CLI and the server.
+//!
+//! `/link` merges histories once; afterwards each surface keeps writing to
+//! its own copy. Sync keeps them converged: every message gets a stable id
+//! and, once the server has it, a sequence number (both stored in
+//! `SessionMessage::extra`). A client sends a [`SyncRequest`] with the last
+//! sequence number it has seen and the messages the server doesn't have yet;
+//! the server numbers those and answers with everything after the cursor.
+//! Messages written on both sides in the meantime are interleaved by
+//! timestamp. The cursor doubles as the ETag of `POST
+//! /api/v1/sessions/{id}/sync`, so an idle poll costs a 304. Deletions
+//! (`/reset`) are not synced.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Session, SessionMessage};
+
+/// `SessionMessage::extra` key holding the message id.
+pub const MESSAGE_ID_KEY: &str = "id";
+
+/// `SessionMessage::extra` key holding the server sequence number.
+pub const SEQ_KEY: &str = "seq";
+
+/// Session metadata key: highest sequence number assigned (server side).
+pub const SEQ_METADATA_KEY: &str = "sync_seq";
+
+/// Session metadata key: highest sequence number pulled (client side).
+pub const CURSOR_METADATA_KEY: &str = "sync_cursor";
+
+/// A message as exchanged by the sync protocol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncMessage {
+    pub id: String,
+    /// Assigned by the server; 0 for messages the client pushes.
+    #[serde(default)]
+    pub seq: u64,
+    pub role: String,
+    pub content: String,
+    pub timestamp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+}
+
+/// Body of `POST /api/v1/sessions/{id}/sync`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncRequest {
+    /// Highest sequence number the client has.
+    pub since: u64,
+    /// Messages the server hasn't numbered yet.
+    #[serde(default)]
+    pub messages: Vec<SyncMessage>,
+}
+
+/// Reply to a [`SyncRequest`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncResponse {
+    /// Highest sequence number on the server; the client's next `since`.
+    pub seq: u64,
+    /// Messages numbered after `since`, in sequence order.
+    pub messages: Vec<SyncMessage>,
+}
+
+/// Outcome of one client sync.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncStats {
+    pub pushed: usize,
+    pub pulled: usize,
+}
+
+/// The ETag for a session at sequence number `seq`.
+pub fn etag(seq: u64) -> String {
+    format!("\"{}\"", seq)
+}
+
+fn id_of(m: &SessionMessage) -> Option<&str> {
+    m.extra.get(MESSAGE_ID_KEY).and_then(|v| v.as_str())
+}
+
+fn seq_of(m: &SessionMessage) -> Option<u64> {
+    m.extra.get(SEQ_KEY).and_then(|v| v.as_u64())
+}
+
+fn time_of(timestamp: Option<&str>) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+    timestamp.and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+}
+
+impl From<&SessionMessage> for SyncMessage {
+    fn from(m: &SessionMessage) -> Self {
+        Self {
+            id: id_of(m).unwrap_or_default().to_string(),
+            seq: seq_of(m).unwrap_or(0),
+            role: m.role.clone(),
+            content: m.content.clone(),
+            timestamp: m.timestamp.clone().unwrap_or_default(),
+            channel: m.extra.get("channel").and_then(|v| v.as_str()).map(str::to_string),
+        }
+    }
+}
+
+impl From<SyncMessage> for SessionMessage {
+    fn from(m: SyncMessage) -> Self {
+        let mut extra = std::collections::HashMap::new();
+        extra.insert(MESSAGE_ID_KEY.to_string(), serde_json::json!(m.id));
+        if m.seq > 0 {
+            extra.insert(SEQ_KEY.to_string(), serde_json::json!(m.seq));
+        }
+        if let Some(channel) = m.channel {
+            extra.insert("channel".to_string(), serde_json::json!(channel));
+        }
+        Self {
+            role: m.role,
+            content: m.content,
+            timestamp: Some(m.timestamp).filter(|t| !t.is_empty()),
+            extra,
+        }
+    }
+}
+
+impl Session {
+    fn metadata_u64(&self, key: &str) -> u64 {
+        self.metadata.get(key).and_then(|v| v.as_u64()).unwrap_or(0)
+    }
+
+    /// Highest sequence number assigned on the server.
+    pub fn sync_seq(&self) -> u64 {
+        self.metadata_u64(SEQ_METADATA_KEY)
+    }
+
+    /// Highest sequence number a client has pulled.
+    pub fn sync_cursor(&self) -> u64 {
+        self.metadata_u64(CURSOR_METADATA_KEY)
+    }
+
+    fn next_seq(&mut self) -> u64 {
+        let seq = self.sync_seq() + 1;
+        self.metadata.insert(SEQ_METADATA_KEY.to_string(), serde_json::json!(seq));
+        seq
+    }
+
+    fn ensure_ids(&mut self) {
+        for m in &mut self.messages {
+            if id_of(m).is_none() {
+                m.extra.insert(
+                    MESSAGE_ID_KEY.to_string(),
+                    serde_json::json!(uuid::Uuid::new_v4().to_string()),
+                );
+            }
+        }
+    }
+
+    /// Insert `message` after every message that isn't newer than it.
+    fn insert_by_time(&mut self, message: SessionMessage) {
+        let at = time_of(message.timestamp.as_deref());
+        let pos = self
+            .messages
+            .iter()
+            .rposition(|m| at.is_none() || time_of(m.timestamp.as_deref()) <= at)
+            .map_or(0, |i| i + 1);
+        self.messages.insert(pos, message);
+        self.updated_at = chrono::Utc::now();
+    }
+
+    /// Server side: number messages written since the last sync, then add
+    /// the pushed ones the session doesn't have yet. Returns whether the
+    /// session changed.
+    pub fn apply_push(&mut self, pushed: Vec<SyncMessage>) -> bool {
+        let mut changed = false;
+        self.ensure_ids();
+        for i in 0..self.messages.len() {
+            if seq_of(&self.messages[i]).is_none() {
+                let seq = self.next_seq();
+                self.messages[i].extra.insert(SEQ_KEY.to_string(), serde_json::json!(seq));
+                changed = true;
+            }
+        }
+        for mut m in pushed {
+            if m.id.is_empty() || self.messages.iter().any(|e| id_of(e) == Some(m.id.as_str())) {
+                continue;
+            }
+            m.seq = self.next_seq();
+            self.insert_by_time(m.into());
+            changed = true;
+        }
+        changed
+    }
+
+    /// Server side: messages numbered after `since`, in sequence order.
+    pub fn changes_since(&self, since: u64) -> Vec<SyncMessage> {
+        let mut changes: Vec<SyncMessage> = self
+            .messages
+            .iter()
+            .filter(|m| seq_of(m).is_some_and(|seq| seq > since))
+            .map(SyncMessage::from)
+            .collect();
+        changes.sort_by_key(|m| m.seq);
+        changes
+    }
+
+    /// Client side: the request carrying local messages the server hasn't
+    /// numbered yet.
+    pub fn sync_request(&mut self) -> SyncRequest {
+        self.ensure_ids();
+        SyncRequest {
+            since: self.sync_cursor(),
+            messages: self
+                .messages
+                .iter()
+                .filter(|m| seq_of(m).is_none())
+                .map(SyncMessage::from)
+                .collect(),
+        }
+    }
+
+    /// Client side: merge the server's reply. Known messages take their
+    /// sequence number, new ones are interleaved by timestamp. Returns how
+    /// many messages were added.
+    pub fn apply_pull(&mut self, response: SyncResponse) -> usize {
+        let mut added = 0;
+        for m in response.messages {
+            match self.messages.iter_mut().find(|e| id_of(e) == Some(m.id.as_str())) {
+                Some(existing) => {
+                    existing.extra.insert(SEQ_KEY.to_string(), serde_json::json!(m.seq));
+                }
+                None => {
+                    self.insert_by_time(m.into());
+                    added += 1;
+                }
+            }
+        }
+        self.metadata
+            .insert(CURSOR_METADATA_KEY.to_string(), serde_json::json!(response.seq));
+        added
+    }
+}
+
+/// Sync `session` with the server session at `url`
+/// (`.../api/v1/sessions/{id}/sync`).
+pub async fn sync_with(
+    client: &reqwest::Client,
+    url: &str,
+    auth_token: Option<&str>,
+    session: &mut Session,
+) -> anyhow::Result<SyncStats> {
+    let request = session.sync_request();
+    let pushed = request.messages.len();
+    let mut req = client.post(url).json(&request);
+    if pushed == 0 {
+        req = req.header("If-None-Match", etag(request.since));
+    }
+    if let Some(token) = auth_token {
+        req = req.header("Authorization", format!("Bearer {}", token));
+    }
+    let resp = req.send().await?;
+    if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(SyncStats::default());
+    }
+    if !resp.status().is_success() {
+        anyhow::bail!("sync failed: HTTP {}", resp.status());
+    }
+    let response: SyncResponse = resp.json().await?;
+    let pulled = session.apply_pull(response);
+    Ok(SyncStats { pushed, pulled })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(session: &mut Session, role: &str, content: &str, timestamp: &str) {
+        session.add_message(role, content);
+        session.messages.last_mut().unwrap().timestamp = Some(timestamp.to_string());
+    }
+
+    /// One round trip: client pushes, server applies, client pulls.
+    fn sync(client: &mut Session, server: &mut Session) -> SyncStats {
+        let request = client.sync_request();
+        let pushed = request.messages.len();
+        server.apply_push(request.messages);
+        let response = SyncResponse {
+            seq: server.sync_seq(),
+            messages: server.changes_since(request.since),
+        };
+        let pulled = client.apply_pull(response);
+        SyncStats { pushed, pulled }
+    }
+
+    fn contents(session: &Session) -> Vec<&str> {
+        session.messages.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sync_converges_and_orders_by_timestamp() {
+        let mut server = Session::new("user:1");
+        let mut cli = Session::new("cli:default");
+        message(&mut server, "user", "web question", "2026-10-17T10:00:00+00:00");
+        message(&mut server, "assistant", "web answer", "2026-10-17T10:00:05+00:00");
+
+        assert_eq!(sync(&mut cli, &mut server), SyncStats { pushed: 0, pulled: 2 });
+        assert_eq!(cli.sync_cursor(), 2);
+
+        // Both sides keep talking before the next sync.
+        message(&mut cli, "user", "cli question", "2026-10-17T11:00:00+00:00");
+        message(&mut server, "user", "line question", "2026-10-17T10:30:00+00:00");
+        message(&mut cli, "assistant", "cli answer", "2026-10-17T11:00:03+00:00");
+
+        assert_eq!(sync(&mut cli, &mut server), SyncStats { pushed: 2, pulled: 1 });
+        let expected = ["web question", "web answer", "line question", "cli question", "cli answer"];
+        assert_eq!(contents(&cli), expected);
+        assert_eq!(contents(&server), expected);
+        assert_eq!(cli.sync_cursor(), server.sync_seq());
+
+        // Nothing new: nothing moves, pushed messages aren't duplicated.
+        assert_eq!(sync(&mut cli, &mut server), SyncStats::default());
+        assert_eq!(server.messages.len(), 5);
+        assert!(server.changes_since(server.sync_seq()).is_empty());
+    }
+
+    #[test]
+    fn test_sync_message_round_trip() {
+        let mut session = Session::new("cli:default");
+        session.add_message_from_channel("user", "hi", "line");
+        let request = session.sync_request();
+        let m = &request.messages[0];
+        assert!(!m.id.is_empty());
+        assert_eq!(m.channel.as_deref(), Some("line"));
+        let back = SessionMessage::from(m.clone());
+        assert_eq!(SyncMessage::from(&back), *m);
+        assert_eq!(etag(7), "\"7\"");
+    }
+}
@@ -0,0 +1,154 @@
+//! How an assistant turn was produced.
+//!
+//! The model that answered, the tokens it took, how long the turn ran, the
+//! agent profile and the tools it called are kept in the assistant
+//! message's `SessionMessage::extra` (as top-level `model`, `promptTokens`,
+//! `completionTokens`, `latencyMs`, `agent` and `tools` keys), so history
+//! export, `/history` and `/usage` can show how each answer came about.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Session, SessionMessage};
+use crate::types::TokenUsage;
+
+/// Metadata of one assistant turn.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TurnMeta {
+    /// Model that answered (after any provider fallback).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub prompt_tokens: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub completion_tokens: u32,
+    /// Wall time from receiving the message to the final answer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Agent profile that answered, where the front-end has profiles.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    /// Tools called during the turn, in call order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+impl TurnMeta {
+    pub fn new(model: &str) -> Self {
+        Self { model: Some(model.to_string()), ..Self::default() }
+    }
+
+    /// Add the tokens of one LLM call.
+    pub fn add_usage(&mut self, usage: &TokenUsage) {
+        self.prompt_tokens += usage.prompt_tokens;
+        self.completion_tokens += usage.completion_tokens;
+    }
+
+    /// Fold in a later attempt of the same turn (an escalation re-run):
+    /// tokens and tools add up, the later model is the one that answered.
+    pub fn merge(&mut self, later: TurnMeta) {
+        self.prompt_tokens += later.prompt_tokens;
+        self.completion_tokens += later.completion_tokens;
+        self.tools.extend(later.tools);
+        self.model = later.model.or(self.model.take());
+        self.latency_ms = later.latency_ms.or(self.latency_ms);
+        self.agent = later.agent.or(self.agent.take());
+    }
+
+    /// The token counts as a [`TokenUsage`].
+    pub fn usage(&self) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens: self.prompt_tokens,
+            completion_tokens: self.completion_tokens,
+            total_tokens: self.prompt_tokens + self.completion_tokens,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// One line: `gpt-4o · 1.2s · 340→120 tokens · web_search, calculator`.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(model) = &self.model {
+            parts.push(model.clone());
+        }
+        if let Some(agent) = &self.agent {
+            parts.push(format!("@{}", agent));
+        }
+        if let Some(ms) = self.latency_ms {
+            parts.push(format!("{:.1}s", ms as f64 / 1000.0));
+        }
+        if self.prompt_tokens > 0 || self.completion_tokens > 0 {
+            parts.push(format!("{}→{} tokens", self.prompt_tokens, self.completion_tokens));
+        }
+        if !self.tools.is_empty() {
+            parts.push(self.tools.join(", "));
+        }
+        parts.join(" · ")
+    }
+}
+
+impl SessionMessage {
+    /// The turn metadata stored on this message, if any.
+    pub fn turn_meta(&self) -> Option<TurnMeta> {
+        let fields = self.extra.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        serde_json::from_value::<TurnMeta>(serde_json::Value::Object(fields))
+            .ok()
+            .filter(|meta| !meta.is_empty())
+    }
+}
+
+impl Session {
+    /// Attach `meta` to the last message (the assistant reply just added).
+    pub fn set_turn_meta(&mut self, meta: &TurnMeta) {
+        let Some(last) = self.messages.last_mut() else { return };
+        if let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(meta) {
+            last.extra.extend(fields);
+        }
+    }
+
+    /// Token totals over every turn with metadata.
+    pub fn recorded_usage(&self) -> TokenUsage {
+        let mut total = TurnMeta::default();
+        for meta in self.messages.iter().filter_map(|m| m.turn_meta()) {
+            total.add_usage(&meta.usage());
+        }
+        total.usage()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_turn_meta_roundtrip() {
+        let mut session = Session::new("test");
+        session.add_message_from_channel("user", "Weather?", "web");
+        session.add_message_from_channel("assistant", "Sunny.", "web");
+        let mut meta = TurnMeta::new("gemini-2.5-flash");
+        meta.add_usage(&TokenUsage { prompt_tokens: 300, completion_tokens: 20, total_tokens: 320 });
+        meta.add_usage(&TokenUsage { prompt_tokens: 40, completion_tokens: 100, total_tokens: 140 });
+        meta.latency_ms = Some(1234);
+        meta.agent = Some("assistant".into());
+        meta.tools = vec!["web_search".into()];
+        session.set_turn_meta(&meta);
+
+        assert_eq!(session.messages[0].turn_meta(), None);
+        let stored = session.messages[1].turn_meta().unwrap();
+        assert_eq!(stored, meta);
+        assert_eq!(session.messages[1].extra["channel"], "web");
+        assert_eq!(stored.summary(), "gemini-2.5-flash · @assistant · 1.2s · 340→120 tokens · web_search");
+        assert_eq!(session.recorded_usage().total_tokens, 460);
+
+        let line = serde_json::to_string(&session.messages[1]).unwrap();
+        let reread: SessionMessage = serde_json::from_str(&line).unwrap();
+        assert_eq!(reread.turn_meta(), Some(meta));
+    }
+}
@@ -239,6 +239,20 @@ pub const BUNDLED_SKILLS: &[BundledSkill] = &[
             - 気分・ストレスレベル\n\
             - 週次/月次のトレンド分析",
     },
+    BundledSkill {
+        id: "expense-tracker",
+        name: "家計簿",
+        description: "レシート写真や文章から支出を記録し集計",
+        category: "日常",
+        content: "# 家計簿スキル\n\n\
+            レシートの写真や「ランチ 980円」のような文章から支出を記録し、集計してください。\n\n\
+            ## ルール\n\
+            - 記録・集計・削除は必ず expense ツールを使う（action: add / summary / list / delete）\n\
+            - 写真は image、文章は text に渡す。金額がはっきりしていれば amount などを直接指定\n\
+            - 記録後は日付・店名・金額・カテゴリを一行で確認する\n\
+            - 「今月食費いくら？」には period と category を指定して summary を呼ぶ\n\
+            - 集計結果の chart はそのまま見せ、合計と目立つカテゴリを一言添える",
+    },
 ];
 
 /// Look up a bundled skill by ID.
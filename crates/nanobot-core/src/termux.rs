@@ -0,0 +1,167 @@
+//! Android via Termux.
+//!
+//! The CLI runs unchanged in Termux, but a few desktop assumptions don't
+//! hold there: key release events never arrive (so push-to-talk in raw mode
+//! can't work), there is no ALSA device to record from, and no desktop
+//! notification daemon. [`TermuxProfile`] is detected from the environment
+//! and swaps those for the `termux-*` commands of the Termux:API add-on
+//! (`pkg install termux-api` plus the Termux:API app) when they are present:
+//! Android speech recognition for input, Android TTS for replies, and
+//! notifications from the daemon. Nothing needs configuring; `termux` in the
+//! config only overrides what detection picks.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::config::TermuxConfig;
+
+/// Notification id, so a newer notification replaces the previous one.
+pub const NOTIFICATION_ID: &str = "chatweb";
+
+fn is_termux_env(var: impl Fn(&str) -> Option<String>) -> bool {
+    var("TERMUX_VERSION").is_some() || var("PREFIX").is_some_and(|p| p.contains("/com.termux/"))
+}
+
+/// Whether this process runs inside Termux.
+pub fn is_termux() -> bool {
+    is_termux_env(|name| std::env::var(name).ok())
+}
+
+fn has_command(name: &str) -> bool {
+    which::which(name).is_ok()
+}
+
+/// What the device can do, resolved once at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TermuxProfile {
+    /// `termux-notification` is available and enabled.
+    pub notifications: bool,
+    /// `termux-tts-speak` is available and replies should be spoken.
+    pub tts: bool,
+    /// `termux-speech-to-text` is available.
+    pub microphone: bool,
+}
+
+impl TermuxProfile {
+    /// The profile for this environment, or `None` off Termux (or when
+    /// disabled in the config).
+    pub fn detect(config: &TermuxConfig) -> Option<Self> {
+        if !config.enabled.unwrap_or_else(is_termux) {
+            return None;
+        }
+        Some(Self::resolve(config, has_command))
+    }
+
+    fn resolve(config: &TermuxConfig, has: impl Fn(&str) -> bool) -> Self {
+        Self {
+            notifications: config.notifications && has("termux-notification"),
+            tts: config.speak_replies && has("termux-tts-speak"),
+            microphone: has("termux-speech-to-text"),
+        }
+    }
+
+    /// One-line summary for the banner, e.g. "🎤 mic · 🔊 tts".
+    pub fn summary(&self) -> String {
+        let features: Vec<&str> = [
+            (self.microphone, "🎤 mic"),
+            (self.tts, "🔊 tts"),
+            (self.notifications, "🔔 notify"),
+        ]
+        .into_iter()
+        .filter_map(|(on, label)| on.then_some(label))
+        .collect();
+        if features.is_empty() {
+            "termux-api not installed".to_string()
+        } else {
+            features.join(" · ")
+        }
+    }
+}
+
+fn check(status: std::io::Result<std::process::ExitStatus>, program: &str) -> anyhow::Result<()> {
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => anyhow::bail!("{} failed ({})", program, status),
+        Err(e) => anyhow::bail!("{} failed: {}", program, e),
+    }
+}
+
+/// Post (or replace) the chatweb notification.
+pub fn notify(title: &str, content: &str) -> anyhow::Result<()> {
+    let status = Command::new("termux-notification")
+        .args(["--id", NOTIFICATION_ID, "--title", title, "--content", content])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    check(status, "termux-notification")
+}
+
+/// Speak `text` with Android TTS; returns when it has been read out.
+pub fn speak(text: &str) -> anyhow::Result<()> {
+    let mut child = Command::new("termux-tts-speak")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    check(child.wait(), "termux-tts-speak")
+}
+
+/// Listen once with Android speech recognition; the recognized text, empty
+/// if nothing was heard.
+pub fn listen() -> anyhow::Result<String> {
+    let output = Command::new("termux-speech-to-text").stderr(Stdio::null()).output()?;
+    if !output.status.success() {
+        anyhow::bail!("termux-speech-to-text failed ({})", output.status);
+    }
+    // Partial results come one per line; the last line is the final one.
+    let text = String::from_utf8_lossy(&output.stdout);
+    Ok(text.lines().map(str::trim).rfind(|l| !l.is_empty()).unwrap_or_default().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_termux_environment() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string())
+        };
+        assert!(is_termux_env(env(&[("TERMUX_VERSION", "0.118.0")])));
+        assert!(is_termux_env(env(&[("PREFIX", "/data/data/com.termux/files/usr")])));
+        assert!(!is_termux_env(env(&[("PREFIX", "/usr/local")])));
+        assert!(!is_termux_env(env(&[])));
+    }
+
+    #[test]
+    fn test_profile_follows_installed_commands_and_config() {
+        let config = TermuxConfig::default();
+        let only_tts = TermuxProfile::resolve(&config, |c| c == "termux-tts-speak");
+        assert_eq!(
+            only_tts,
+            TermuxProfile {
+                notifications: false,
+                tts: true,
+                microphone: false
+            }
+        );
+        assert_eq!(only_tts.summary(), "🔊 tts");
+
+        let quiet = TermuxConfig {
+            speak_replies: false,
+            ..Default::default()
+        };
+        let profile = TermuxProfile::resolve(&quiet, |_| true);
+        assert!(!profile.tts && profile.notifications && profile.microphone);
+
+        let forced_off = TermuxConfig {
+            enabled: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(TermuxProfile::detect(&forced_off), None);
+        assert_eq!(TermuxProfile::resolve(&config, |_| false).summary(), "termux-api not installed");
+    }
+}
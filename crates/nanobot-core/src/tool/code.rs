@@ -0,0 +1,639 @@
+//! `code_execute`: evaluate short Python or JavaScript snippets.
+//!
+//! JavaScript runs in an embedded QuickJS runtime (no `std`/`os` modules, so
+//! no filesystem or network) with a heap limit and an interrupt deadline;
+//! it needs the `js-sandbox` feature, which the CLI enables.
+//! Python runs in an isolated `python3 -I -S` subprocess under rlimits,
+//! confined by the kernel (Linux only, see [`super::confine`]): it can read
+//! its own installation and the system libraries, write only in
+//! [`SandboxLimits::workdir`], start no other program and open no network
+//! socket unless [`SandboxLimits::allow_network`].
+//! In both, the value of a trailing expression is printed like a REPL, so
+//! `2**64` or `[1,2,3].map(x => x * 2)` needs no `print`.
+
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[cfg(feature = "js-sandbox")]
+use rquickjs::allocator::{Allocator, RustAllocator};
+#[cfg(feature = "js-sandbox")]
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(feature = "js-sandbox")]
+use std::sync::Arc;
+#[cfg(feature = "js-sandbox")]
+use std::time::Instant;
+
+use super::Tool;
+
+/// Snippets larger than this are rejected.
+pub const MAX_CODE_LEN: usize = 32_000;
+
+/// Caps for one evaluation.
+#[derive(Debug, Clone)]
+pub struct SandboxLimits {
+    pub timeout: Duration,
+    pub memory_bytes: usize,
+    /// Output beyond this is truncated.
+    pub max_output: usize,
+    /// Python only: directory the snippet may read and write (the cwd).
+    pub workdir: Option<PathBuf>,
+    /// Python only: allow sockets.
+    pub allow_network: bool,
+}
+
+impl Default for SandboxLimits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            memory_bytes: 128 * 1024 * 1024,
+            max_output: 8000,
+            workdir: None,
+            allow_network: false,
+        }
+    }
+}
+
+/// Captured output of a snippet, and the error that ended it, if any.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EvalOutput {
+    pub stdout: String,
+    pub error: Option<String>,
+}
+
+impl EvalOutput {
+    fn failed(error: impl Into<String>) -> Self {
+        Self {
+            stdout: String::new(),
+            error: Some(error.into()),
+        }
+    }
+
+    /// Tool result text: stdout, then `Error: ...`, truncated to `max_len`.
+    pub fn render(&self, max_len: usize) -> String {
+        let mut result = self.stdout.trim_end().to_string();
+        if let Some(ref error) = self.error {
+            if !result.is_empty() {
+                result.push('\n');
+            }
+            result.push_str(&format!("Error: {}", error.trim_end()));
+        }
+        if result.is_empty() {
+            return "(no output)".to_string();
+        }
+        crate::util::truncate_string(&result, max_len, "\n... (truncated)")
+    }
+}
+
+/// Languages accepted by [`evaluate`] (with aliases).
+pub fn normalize_language(language: &str) -> Option<&'static str> {
+    match language.trim().to_lowercase().as_str() {
+        "python" | "python3" | "py" => Some("python"),
+        "javascript" | "js" | "node" | "nodejs" => Some("javascript"),
+        _ => None,
+    }
+}
+
+/// Evaluate `code` in `language` ("python" or "javascript", see [`normalize_language`]).
+pub async fn evaluate(language: &str, code: &str, limits: &SandboxLimits) -> EvalOutput {
+    if code.len() > MAX_CODE_LEN {
+        return EvalOutput::failed(format!("code too large (max {} bytes)", MAX_CODE_LEN));
+    }
+    match normalize_language(language) {
+        Some("python") => eval_python(code, limits).await,
+        Some(_) => {
+            let (code, limits) = (code.to_string(), limits.clone());
+            tokio::task::spawn_blocking(move || eval_js(&code, &limits))
+                .await
+                .unwrap_or_else(|e| EvalOutput::failed(format!("evaluation panicked: {e}")))
+        }
+        None => EvalOutput::failed(format!("unsupported language '{}' (use python or javascript)", language)),
+    }
+}
+
+/// Heap granted past the JavaScript memory limit once it is hit, so QuickJS
+/// can still build the error it throws.
+#[cfg(feature = "js-sandbox")]
+const JS_OOM_RESERVE: usize = 1024 * 1024;
+
+/// Bytes the JavaScript heap holds against its limit.
+#[cfg(feature = "js-sandbox")]
+struct JsHeap {
+    limit: usize,
+    used: AtomicUsize,
+    exceeded: AtomicBool,
+}
+
+#[cfg(feature = "js-sandbox")]
+impl JsHeap {
+    /// Whether `size` more bytes fit; the first refusal marks the heap as
+    /// exceeded and opens the reserve.
+    fn admit(&self, size: usize) -> bool {
+        let exceeded = self.exceeded.load(Ordering::Relaxed);
+        let cap = if exceeded { self.limit + JS_OOM_RESERVE } else { self.limit };
+        if self.used.load(Ordering::Relaxed).saturating_add(size) <= cap {
+            return true;
+        }
+        self.exceeded.store(true, Ordering::Relaxed);
+        false
+    }
+}
+
+/// QuickJS allocator enforcing [`SandboxLimits::memory_bytes`]. QuickJS's own
+/// memory limit can crash the process when the error object for "out of
+/// memory" can't be allocated either; this one leaves room for it and the
+/// interrupt handler then ends the script.
+#[cfg(feature = "js-sandbox")]
+struct JsAllocator(Arc<JsHeap>);
+
+#[cfg(feature = "js-sandbox")]
+unsafe impl Allocator for JsAllocator {
+    fn alloc(&mut self, size: usize) -> *mut u8 {
+        if !self.0.admit(size) {
+            return std::ptr::null_mut();
+        }
+        let ptr = RustAllocator.alloc(size);
+        if !ptr.is_null() {
+            self.0.used.fetch_add(unsafe { RustAllocator::usable_size(ptr) }, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    fn calloc(&mut self, count: usize, size: usize) -> *mut u8 {
+        match count.checked_mul(size) {
+            Some(total) if self.0.admit(total) => {
+                let ptr = RustAllocator.calloc(count, size);
+                if !ptr.is_null() {
+                    self.0.used.fetch_add(unsafe { RustAllocator::usable_size(ptr) }, Ordering::Relaxed);
+                }
+                ptr
+            }
+            _ => std::ptr::null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&mut self, ptr: *mut u8) {
+        self.0.used.fetch_sub(RustAllocator::usable_size(ptr), Ordering::Relaxed);
+        RustAllocator.dealloc(ptr);
+    }
+
+    unsafe fn realloc(&mut self, ptr: *mut u8, new_size: usize) -> *mut u8 {
+        if ptr.is_null() {
+            return self.alloc(new_size);
+        }
+        let old = RustAllocator::usable_size(ptr);
+        if new_size > old && !self.0.admit(new_size - old) {
+            return std::ptr::null_mut();
+        }
+        let new = RustAllocator.realloc(ptr, new_size);
+        if !new.is_null() {
+            self.0.used.fetch_sub(old, Ordering::Relaxed);
+            self.0.used.fetch_add(RustAllocator::usable_size(new), Ordering::Relaxed);
+        }
+        new
+    }
+
+    unsafe fn usable_size(ptr: *mut u8) -> usize {
+        RustAllocator::usable_size(ptr)
+    }
+}
+
+/// Evaluate JavaScript in a fresh QuickJS runtime. Blocking.
+#[cfg(feature = "js-sandbox")]
+pub fn eval_js(code: &str, limits: &SandboxLimits) -> EvalOutput {
+    use rquickjs::{prelude::Rest, CatchResultExt, CaughtError, Coerced, Context, FromJs, Function, Runtime, Value};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let heap = Arc::new(JsHeap {
+        limit: limits.memory_bytes,
+        used: AtomicUsize::new(0),
+        exceeded: AtomicBool::new(false),
+    });
+    let runtime = match Runtime::new_with_alloc(JsAllocator(heap.clone())) {
+        Ok(rt) => rt,
+        Err(e) => return EvalOutput::failed(format!("failed to start JavaScript runtime: {e}")),
+    };
+    runtime.set_max_stack_size(1024 * 1024);
+    let deadline = Instant::now() + limits.timeout;
+    let interrupted = heap.clone();
+    runtime.set_interrupt_handler(Some(Box::new(move || {
+        Instant::now() > deadline || interrupted.exceeded.load(Ordering::Relaxed)
+    })));
+    let context = match Context::full(&runtime) {
+        Ok(ctx) => ctx,
+        Err(e) => return EvalOutput::failed(format!("failed to start JavaScript runtime: {e}")),
+    };
+
+    let stdout = Rc::new(RefCell::new(String::new()));
+    let max_output = limits.max_output;
+    let error = context.with(|ctx| {
+        let print = {
+            let stdout = stdout.clone();
+            move |args: Rest<Coerced<String>>| {
+                let mut out = stdout.borrow_mut();
+                // Keep collecting only up to the cap; the rest is dropped
+                if out.len() <= max_output {
+                    let line: Vec<&str> = args.0.iter().map(|a| a.0.as_str()).collect();
+                    out.push_str(&line.join(" "));
+                    out.push('\n');
+                }
+            }
+        };
+        let setup = || -> rquickjs::Result<()> {
+            let console = rquickjs::Object::new(ctx.clone())?;
+            for name in ["log", "info", "warn", "error", "debug"] {
+                console.set(name, Function::new(ctx.clone(), print.clone())?)?;
+            }
+            ctx.globals().set("console", console)?;
+            ctx.globals().set("print", Function::new(ctx.clone(), print.clone())?)?;
+            Ok(())
+        };
+        if let Err(e) = setup() {
+            return Some(e.to_string());
+        }
+
+        match ctx.eval::<Value, _>(code).catch(&ctx) {
+            // Even if the script caught the error and went on
+            _ if heap.exceeded.load(Ordering::Relaxed) => {
+                Some(format!("memory limit of {} MB exceeded", limits.memory_bytes / (1024 * 1024)))
+            }
+            Ok(value) if value.is_undefined() => None,
+            Ok(value) => {
+                let text = if value.is_object() && !value.is_function() {
+                    ctx.json_stringify(value.clone()).ok().flatten().and_then(|s| s.to_string().ok())
+                } else {
+                    None
+                };
+                let text = text.or_else(|| Coerced::<String>::from_js(&ctx, value).ok().map(|c| c.0));
+                if let Some(text) = text {
+                    stdout.borrow_mut().push_str(&format!("{}\n", text));
+                }
+                None
+            }
+            Err(_) if Instant::now() > deadline => {
+                Some(format!("timed out after {}s", limits.timeout.as_secs_f32()))
+            }
+            Err(CaughtError::Exception(e)) => Some(e.to_string()),
+            Err(e) => Some(e.to_string()),
+        }
+    });
+
+    let stdout = stdout.borrow().clone();
+    EvalOutput { stdout, error }
+}
+
+#[cfg(not(feature = "js-sandbox"))]
+pub fn eval_js(_code: &str, _limits: &SandboxLimits) -> EvalOutput {
+    EvalOutput::failed("JavaScript is not available in this build (js-sandbox feature); use python")
+}
+
+/// Runs the snippet read from stdin, printing the value of a trailing
+/// expression.
+const PYTHON_PRELUDE: &str = r#"
+import sys, ast
+_src = sys.stdin.read()
+_tree = ast.parse(_src, "<snippet>", "exec")
+_last = None
+if _tree.body and isinstance(_tree.body[-1], ast.Expr):
+    _last = ast.Expression(_tree.body.pop().value)
+_g = {"__name__": "__main__", "__builtins__": __builtins__}
+try:
+    exec(compile(_tree, "<snippet>", "exec"), _g)
+    if _last is not None:
+        _v = eval(compile(_last, "<snippet>", "eval"), _g)
+        if _v is not None:
+            print(repr(_v))
+except SystemExit:
+    raise
+except BaseException as _e:
+    import traceback
+    traceback.print_exception(type(_e), _e, _e.__traceback__.tb_next)
+    sys.exit(1)
+"#;
+
+/// The interpreter binary and installation prefixes of `python3`.
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+struct PythonInstall {
+    executable: PathBuf,
+    prefixes: Vec<PathBuf>,
+}
+
+/// Ask `python3` (through shims such as pyenv's) where it really lives.
+#[cfg(target_os = "linux")]
+async fn python_install() -> Option<&'static PythonInstall> {
+    static INSTALL: tokio::sync::OnceCell<Option<PythonInstall>> = tokio::sync::OnceCell::const_new();
+    INSTALL
+        .get_or_init(|| async {
+            let interpreter = which::which("python3").or_else(|_| which::which("python")).ok()?;
+            let output = tokio::process::Command::new(interpreter)
+                .args(["-I", "-S", "-c", "import sys; print(sys.executable); print(sys.base_prefix); print(sys.prefix)"])
+                .output()
+                .await
+                .ok()?;
+            let text = String::from_utf8(output.stdout).ok()?;
+            let mut lines = text.lines().map(|l| PathBuf::from(l.trim()));
+            let executable = lines.next()?.canonicalize().ok()?;
+            Some(PythonInstall { executable, prefixes: lines.collect() })
+        })
+        .await
+        .as_ref()
+}
+
+/// System paths the interpreter needs to start.
+#[cfg(target_os = "linux")]
+const SYSTEM_READ: &[&str] = &[
+    "/lib", "/lib64", "/usr/lib", "/usr/lib64", "/usr/local/lib", "/etc/ld.so.cache", "/usr/share/zoneinfo", "/dev/null",
+];
+
+#[cfg(target_os = "linux")]
+fn python_confinement(install: &PythonInstall, limits: &SandboxLimits) -> std::io::Result<super::confine::Confinement> {
+    let mut confinement = super::confine::Confinement::new(limits.allow_network)?;
+    for path in install.prefixes.iter().map(PathBuf::as_path).chain(SYSTEM_READ.iter().map(std::path::Path::new)) {
+        confinement = confinement.read(path);
+    }
+    if let Some(ref dir) = limits.workdir {
+        confinement = confinement.write(dir)?;
+    }
+    confinement.exec(&install.executable)
+}
+
+/// Evaluate Python in a confined `python3` subprocess.
+#[cfg(target_os = "linux")]
+pub async fn eval_python(code: &str, limits: &SandboxLimits) -> EvalOutput {
+    use tokio::io::AsyncWriteExt;
+
+    let Some(install) = python_install().await else {
+        return EvalOutput::failed("Python is not installed on this server; use javascript instead");
+    };
+    if let Some(ref dir) = limits.workdir {
+        std::fs::create_dir_all(dir).ok();
+    }
+    let confinement = match python_confinement(install, limits) {
+        Ok(confinement) => confinement,
+        Err(e) => return EvalOutput::failed(format!("Python cannot be sandboxed here ({e}); use javascript instead")),
+    };
+
+    let mut command = tokio::process::Command::new(&install.executable);
+    command
+        .args(["-I", "-S", "-c", PYTHON_PRELUDE])
+        .env_clear()
+        .env("PYTHONIOENCODING", "utf-8")
+        .current_dir(limits.workdir.clone().unwrap_or_else(std::env::temp_dir))
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true);
+    let memory = limits.memory_bytes as libc::rlim_t;
+    let cpu = limits.timeout.as_secs().max(1) as libc::rlim_t + 1;
+    let file_size: libc::rlim_t = if limits.workdir.is_some() { 16 * 1024 * 1024 } else { 0 };
+    // SAFETY: only async-signal-safe system calls between fork and exec.
+    unsafe {
+        command.pre_exec(move || {
+            confinement.apply()?;
+            for (resource, value) in [
+                (libc::RLIMIT_AS, memory),
+                (libc::RLIMIT_CPU, cpu),
+                (libc::RLIMIT_FSIZE, file_size),
+                (libc::RLIMIT_CORE, 0),
+            ] {
+                let limit = libc::rlimit { rlim_cur: value, rlim_max: value };
+                if libc::setrlimit(resource, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return EvalOutput::failed(format!("failed to start Python: {e}")),
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(code.as_bytes()).await.ok();
+    }
+    let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        return EvalOutput::failed("failed to run Python: no output pipes");
+    };
+    // Pipes aren't covered by RLIMIT_FSIZE: stop reading (and the snippet)
+    // once either passes max_output
+    let cap = limits.max_output + 1;
+    let run = async {
+        let (stdout, stderr) = tokio::join!(read_capped(stdout, cap), read_capped(stderr, cap));
+        let flooded = stdout.len() >= cap || stderr.len() >= cap;
+        if flooded {
+            child.start_kill().ok();
+        }
+        child.wait().await.map(|status| (status, stdout, stderr, flooded))
+    };
+    let (status, stdout, stderr, flooded) = match tokio::time::timeout(limits.timeout, run).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return EvalOutput::failed(format!("failed to run Python: {e}")),
+        Err(_) => return EvalOutput::failed(format!("timed out after {}s", limits.timeout.as_secs_f32())),
+    };
+
+    let stdout = String::from_utf8_lossy(&stdout);
+    let stdout = crate::util::truncate_string(&stdout, limits.max_output, "");
+    let stderr = String::from_utf8_lossy(&stderr).into_owned();
+    let error = if flooded {
+        Some(format!("output truncated at {} bytes; the snippet was stopped", limits.max_output))
+    } else if status.success() {
+        None
+    } else if stderr.contains("MemoryError") {
+        Some(format!("memory limit of {} MB exceeded", limits.memory_bytes / (1024 * 1024)))
+    } else if stderr.trim().is_empty() {
+        Some(format!("exit code {}", status.code().unwrap_or(-1)))
+    } else {
+        Some(stderr)
+    };
+    EvalOutput { stdout, error }
+}
+
+/// Up to `cap` bytes from `pipe`, which is closed once that many arrived.
+#[cfg(target_os = "linux")]
+async fn read_capped(pipe: impl tokio::io::AsyncRead + Unpin, cap: usize) -> Vec<u8> {
+    use tokio::io::AsyncReadExt;
+
+    let mut buf = Vec::new();
+    pipe.take(cap as u64).read_to_end(&mut buf).await.ok();
+    buf
+}
+
+/// Python is only offered where it can be confined.
+#[cfg(not(target_os = "linux"))]
+pub async fn eval_python(_code: &str, _limits: &SandboxLimits) -> EvalOutput {
+    EvalOutput::failed("Python snippets are only sandboxed on Linux; use javascript instead")
+}
+
+/// Agent tool for quick calculations and data wrangling in Python or JavaScript.
+pub struct CodeExecuteTool {
+    limits: SandboxLimits,
+}
+
+impl CodeExecuteTool {
+    pub fn new(limits: SandboxLimits) -> Self {
+        Self { limits }
+    }
+}
+
+impl Default for CodeExecuteTool {
+    fn default() -> Self {
+        Self::new(SandboxLimits::default())
+    }
+}
+
+#[async_trait]
+impl Tool for CodeExecuteTool {
+    fn name(&self) -> &str {
+        "code_execute"
+    }
+
+    fn description(&self) -> &str {
+        "Run a short Python or JavaScript snippet for calculations, date math or data \
+         transformation and return its stdout. The value of the last expression is printed. \
+         JavaScript has no file, network or process access; Python (Linux only) can only read its \
+         own libraries and cannot start programs or use the network. A few seconds of runtime at most."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "language": {
+                    "type": "string",
+                    "enum": ["python", "javascript"],
+                    "description": "Language of the snippet"
+                },
+                "code": {
+                    "type": "string",
+                    "description": "The code to evaluate"
+                }
+            },
+            "required": ["language", "code"]
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        let Some(code) = params.get("code").and_then(|v| v.as_str()).filter(|c| !c.trim().is_empty()) else {
+            return "Error: 'code' parameter is required".to_string();
+        };
+        let language = params.get("language").and_then(|v| v.as_str()).unwrap_or("python");
+        evaluate(language, code, &self.limits).await.render(self.limits.max_output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> SandboxLimits {
+        SandboxLimits {
+            timeout: Duration::from_secs(2),
+            memory_bytes: 32 * 1024 * 1024,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "js-sandbox")]
+    #[test]
+    fn test_js_output_and_last_value() {
+        let out = eval_js("console.log('sum', 1 + 2); [1, 2, 3].map(x => x * 2)", &limits());
+        assert_eq!(out.stdout, "sum 3\n[2,4,6]\n");
+        assert_eq!(out.error, None);
+
+        let out = eval_js("throw new Error('boom')", &limits());
+        assert!(out.error.unwrap().contains("boom"));
+    }
+
+    #[cfg(feature = "js-sandbox")]
+    #[test]
+    fn test_js_limits() {
+        let out = eval_js("while (true) {}", &limits());
+        assert!(out.error.unwrap().starts_with("timed out"));
+
+        let out = eval_js("let a = []; while (true) a.push('x'.repeat(1024));", &limits());
+        let error = out.error.unwrap();
+        assert!(error.contains("memory limit"), "{error}");
+        // Catching the error doesn't keep the script going
+        let out = eval_js("try { let a = []; while (true) a.push('x'.repeat(1024)); } catch (e) { 'caught' }", &limits());
+        assert!(out.error.unwrap().contains("memory limit"));
+
+        // No std/os modules, no fetch
+        let out = eval_js("typeof fetch + ' ' + typeof require + ' ' + typeof std", &limits());
+        assert_eq!(out.stdout, "undefined undefined undefined\n");
+    }
+
+    /// Python tests need python3 and a kernel with Landlock.
+    #[cfg(target_os = "linux")]
+    fn python_sandboxed() -> bool {
+        which::which("python3").is_ok() && crate::tool::confine::landlock_abi().is_some()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_python_sandbox() {
+        if !python_sandboxed() {
+            return;
+        }
+        let out = eval_python("import math\nx = math.factorial(20)\nx * 2", &limits()).await;
+        assert_eq!(out.stdout, "4865804016353280000\n", "{:?}", out.error);
+
+        let out = eval_python("open('/etc/passwd').read()", &limits()).await;
+        assert!(out.error.unwrap().contains("PermissionError"));
+        let out = eval_python("import socket\nsocket.create_connection(('1.1.1.1', 80))", &limits()).await;
+        assert!(out.error.unwrap().contains("PermissionError"));
+        let out = eval_python("import subprocess\nsubprocess.run(['/bin/sh', '-c', 'true'])", &limits()).await;
+        assert!(out.error.unwrap().contains("PermissionError"));
+        let out = eval_python("import os\nos.kill(os.getppid(), 0)", &limits()).await;
+        assert!(out.error.unwrap().contains("PermissionError"));
+        let out = eval_python("while True: pass", &limits()).await;
+        assert!(out.error.unwrap().starts_with("timed out"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_python_output_is_capped() {
+        if !python_sandboxed() {
+            return;
+        }
+        let limits = limits();
+        let out = eval_python("while True: print('x' * 65536)", &limits).await;
+        assert!(out.stdout.len() <= limits.max_output);
+        assert!(out.stdout.starts_with("xxx"));
+        assert!(out.error.unwrap().starts_with("output truncated"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_python_sandbox_holds_without_cooperation() {
+        if !python_sandboxed() {
+            return;
+        }
+        // Rebinding interpreter-level state does not lift kernel confinement
+        let code = "import __main__, os, sys\n__main__._inside = lambda p, r: True\nsys.addaudithook = None\n\
+                    print(os.system('echo ESCAPED'))\nopen('/etc/hostname').read()";
+        let out = eval_python(code, &limits()).await;
+        assert!(!out.stdout.contains("ESCAPED"));
+        assert!(out.error.unwrap().contains("PermissionError"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_python_workdir() {
+        if !python_sandboxed() {
+            return;
+        }
+        let tmp = tempfile::tempdir().unwrap();
+        let limits = SandboxLimits {
+            workdir: Some(tmp.path().to_path_buf()),
+            ..limits()
+        };
+        let out = eval_python("open('out.txt', 'w').write('hi')\nopen('out.txt').read()", &limits).await;
+        assert_eq!(out.stdout, "'hi'\n", "{:?}", out.error);
+        assert!(tmp.path().join("out.txt").exists());
+    }
+}
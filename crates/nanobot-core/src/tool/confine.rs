@@ -0,0 +1,376 @@
+//! Kernel-enforced confinement of a child process (Linux).
+//!
+//! Landlock limits the filesystem to read-only trees, one writable directory
+//! and a single executable; a seccomp filter refuses network sockets (unless
+//! allowed), `ptrace`, sending signals and io_uring. Both are applied between
+//! fork and exec and hold for the child and anything it starts, whatever
+//! code it runs.
+//!
+//! The filter needs each architecture's system call numbers and is only
+//! built for x86_64 and aarch64. Elsewhere (armv7, riscv64) confinement is
+//! Landlock alone: TCP is blocked from ABI 4 and signals from ABI 6, and
+//! without ABI 4 a confinement that must block the network fails.
+
+use std::fs::File;
+use std::io;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+const CREATE_RULESET_VERSION: u32 = 1;
+const RULE_PATH_BENEATH: u32 = 1;
+
+const EXECUTE: u64 = 1 << 0;
+const WRITE_FILE: u64 = 1 << 1;
+const READ_FILE: u64 = 1 << 2;
+const READ_DIR: u64 = 1 << 3;
+const REFER: u64 = 1 << 13;
+const TRUNCATE: u64 = 1 << 14;
+const IOCTL_DEV: u64 = 1 << 15;
+/// Rights that apply to a file (as opposed to a directory) in a rule.
+const FILE_RIGHTS: u64 = EXECUTE | WRITE_FILE | READ_FILE | TRUNCATE | IOCTL_DEV;
+
+const NET_BIND_TCP: u64 = 1 << 0;
+const NET_CONNECT_TCP: u64 = 1 << 1;
+
+const SCOPE_ABSTRACT_UNIX_SOCKET: u64 = 1 << 0;
+const SCOPE_SIGNAL: u64 = 1 << 1;
+
+#[repr(C)]
+struct RulesetAttr {
+    handled_access_fs: u64,
+    handled_access_net: u64,
+    scoped: u64,
+}
+
+#[repr(C, packed)]
+struct PathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+}
+
+/// Landlock ABI version of the running kernel, `None` when unsupported.
+pub fn landlock_abi() -> Option<u32> {
+    // SAFETY: querying the version takes no pointers.
+    let abi = unsafe {
+        libc::syscall(libc::SYS_landlock_create_ruleset, std::ptr::null::<RulesetAttr>(), 0usize, CREATE_RULESET_VERSION)
+    };
+    (abi > 0).then_some(abi as u32)
+}
+
+/// A confinement prepared in the parent; [`Confinement::apply`] runs in the
+/// child after fork.
+pub struct Confinement {
+    abi: u32,
+    rules: Vec<(OwnedFd, u64)>,
+    /// Empty where there is no filter for the architecture.
+    filter: Vec<libc::sock_filter>,
+    allow_network: bool,
+}
+
+impl Confinement {
+    /// Nothing readable, writable or executable yet. Fails when the kernel
+    /// has no Landlock, or cannot block the network when it has to.
+    pub fn new(allow_network: bool) -> io::Result<Self> {
+        let abi = landlock_abi().ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "Landlock is not available"))?;
+        let filter = seccomp_filter(allow_network);
+        if filter.is_empty() && !allow_network && abi < 4 {
+            let reason = "no seccomp filter for this architecture and Landlock is too old to block the network";
+            return Err(io::Error::new(io::ErrorKind::Unsupported, reason));
+        }
+        Ok(Self { abi, rules: Vec::new(), filter, allow_network })
+    }
+
+    /// Network rights Landlock refuses: TCP, when there is no filter to
+    /// refuse sockets.
+    fn handled_net(&self) -> u64 {
+        if self.filter.is_empty() && !self.allow_network && self.abi >= 4 {
+            NET_BIND_TCP | NET_CONNECT_TCP
+        } else {
+            0
+        }
+    }
+
+    fn handled(&self) -> u64 {
+        let mut handled = (1 << 13) - 1;
+        if self.abi >= 2 {
+            handled |= REFER;
+        }
+        if self.abi >= 3 {
+            handled |= TRUNCATE;
+        }
+        if self.abi >= 5 {
+            handled |= IOCTL_DEV;
+        }
+        handled
+    }
+
+    fn allow(&mut self, path: &Path, access: u64) -> io::Result<()> {
+        let file = File::options().read(true).custom_flags(libc::O_PATH | libc::O_CLOEXEC).open(path)?;
+        let access = if file.metadata()?.is_dir() { access } else { access & FILE_RIGHTS };
+        self.rules.push((file.into(), access & self.handled()));
+        Ok(())
+    }
+
+    /// Allow reading `path` (a file or a tree); missing paths are skipped.
+    pub fn read(mut self, path: &Path) -> Self {
+        self.allow(path, READ_FILE | READ_DIR).ok();
+        self
+    }
+
+    /// Allow everything but executing inside `dir`.
+    pub fn write(mut self, dir: &Path) -> io::Result<Self> {
+        let access = self.handled() & !EXECUTE;
+        self.allow(dir, access)?;
+        Ok(self)
+    }
+
+    /// Allow executing (and reading) the file `path` and its ELF
+    /// interpreter (the dynamic loader).
+    pub fn exec(mut self, path: &Path) -> io::Result<Self> {
+        self.allow(path, READ_FILE | EXECUTE)?;
+        if let Some(loader) = elf_interpreter(path) {
+            self.allow(&loader, READ_FILE | EXECUTE)?;
+        }
+        Ok(self)
+    }
+
+    /// Restrict the calling process. Only makes system calls, so it is safe
+    /// between fork and exec.
+    pub fn apply(&self) -> io::Result<()> {
+        let check = |ret: libc::c_long| if ret < 0 { Err(io::Error::last_os_error()) } else { Ok(ret) };
+        let attr = RulesetAttr {
+            handled_access_fs: self.handled(),
+            handled_access_net: self.handled_net(),
+            scoped: if self.abi >= 6 { SCOPE_ABSTRACT_UNIX_SOCKET | SCOPE_SIGNAL } else { 0 },
+        };
+        // SAFETY: the attributes outlive the calls; the ruleset fd is closed below.
+        unsafe {
+            check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) as libc::c_long)?;
+            let ruleset = check(libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                std::mem::size_of::<RulesetAttr>(),
+                0u32,
+            ))? as libc::c_int;
+            for (fd, access) in &self.rules {
+                let rule = PathBeneathAttr { allowed_access: *access, parent_fd: fd.as_raw_fd() };
+                if let Err(e) = check(libc::syscall(
+                    libc::SYS_landlock_add_rule,
+                    ruleset,
+                    RULE_PATH_BENEATH,
+                    &rule as *const PathBeneathAttr,
+                    0u32,
+                )) {
+                    libc::close(ruleset);
+                    return Err(e);
+                }
+            }
+            let restricted = check(libc::syscall(libc::SYS_landlock_restrict_self, ruleset, 0u32));
+            libc::close(ruleset);
+            restricted?;
+
+            if self.filter.is_empty() {
+                return Ok(());
+            }
+            let program = libc::sock_fprog { len: self.filter.len() as u16, filter: self.filter.as_ptr() as *mut _ };
+            check(libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &program as *const libc::sock_fprog) as libc::c_long)?;
+        }
+        Ok(())
+    }
+}
+
+/// The `PT_INTERP` path of a little-endian (32- or 64-bit) ELF executable.
+fn elf_interpreter(path: &Path) -> Option<std::path::PathBuf> {
+    use std::io::Read;
+    const PT_INTERP: u32 = 3;
+    let mut head = Vec::new();
+    File::open(path).ok()?.take(64 * 1024).read_to_end(&mut head).ok()?;
+    let wide = match head.get(..6)? {
+        b"\x7fELF\x01\x01" => false,
+        b"\x7fELF\x02\x01" => true,
+        _ => return None,
+    };
+    let u16_at = |at: usize| Some(u16::from_le_bytes(head.get(at..at + 2)?.try_into().ok()?) as usize);
+    let u32_at = |at: usize| Some(u32::from_le_bytes(head.get(at..at + 4)?.try_into().ok()?));
+    let u64_at = |at: usize| Some(u64::from_le_bytes(head.get(at..at + 8)?.try_into().ok()?) as usize);
+    // Word-sized fields of the header and the program headers
+    let word_at = |at: usize| if wide { u64_at(at) } else { u32_at(at).map(|v| v as usize) };
+    let (phoff, phentsize, phnum) = match wide {
+        true => (word_at(0x20)?, u16_at(0x36)?, u16_at(0x38)?),
+        false => (word_at(0x1C)?, u16_at(0x2A)?, u16_at(0x2C)?),
+    };
+    let (offset_at, size_at) = if wide { (8, 32) } else { (4, 16) };
+    (0..phnum).map(|i| phoff + i * phentsize).find(|&ph| u32_at(ph) == Some(PT_INTERP)).and_then(|ph| {
+        let (offset, size) = (word_at(ph + offset_at)?, word_at(ph + size_at)?);
+        let name = head.get(offset..offset + size)?;
+        let name = std::str::from_utf8(name).ok()?.trim_end_matches('\0');
+        Some(std::path::PathBuf::from(name))
+    })
+}
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xC000_003E;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = 0xC000_00B7;
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+fn stmt(code: u32, k: u32) -> libc::sock_filter {
+    libc::sock_filter { code: code as u16, jt: 0, jf: 0, k }
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+fn jump(code: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+    libc::sock_filter { code: code as u16, jt, jf, k }
+}
+
+/// Deny (EPERM) network sockets unless `allow_network`, tracing, signalling
+/// and io_uring (whose operations bypass seccomp).
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+fn seccomp_filter(allow_network: bool) -> Vec<libc::sock_filter> {
+    use libc::{BPF_ABS, BPF_JEQ, BPF_JGE, BPF_JMP, BPF_K, BPF_LD, BPF_RET, BPF_W};
+    const NR: u32 = 0;
+    const ARCH: u32 = 4;
+    const ARG0: u32 = 16;
+    let deny = SECCOMP_RET_ERRNO | libc::EPERM as u32;
+    let denied = [
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_kill,
+        libc::SYS_tkill,
+        libc::SYS_tgkill,
+        libc::SYS_rt_sigqueueinfo,
+        libc::SYS_rt_tgsigqueueinfo,
+        libc::SYS_pidfd_send_signal,
+        libc::SYS_io_uring_setup,
+    ];
+    let mut sockets = vec![libc::AF_UNIX as u32];
+    if allow_network {
+        sockets.extend([libc::AF_INET as u32, libc::AF_INET6 as u32]);
+    }
+
+    let mut f = vec![
+        stmt(BPF_LD | BPF_W | BPF_ABS, ARCH),
+        jump(BPF_JMP | BPF_JEQ | BPF_K, AUDIT_ARCH, 1, 0),
+        stmt(BPF_RET | BPF_K, SECCOMP_RET_KILL_PROCESS),
+        stmt(BPF_LD | BPF_W | BPF_ABS, NR),
+    ];
+    // Jump offsets count from the next instruction; `deny` and `allow` are
+    // the last two instructions.
+    let x32 = usize::from(cfg!(target_arch = "x86_64"));
+    let deny_at = f.len() + x32 + denied.len() + 3 + sockets.len();
+    let to = |target: usize, from: usize| (target - from - 1) as u8;
+    // x32 system calls have their own numbers
+    if x32 == 1 {
+        f.push(jump(BPF_JMP | BPF_JGE | BPF_K, 0x4000_0000, to(deny_at, f.len()), 0));
+    }
+    for nr in denied {
+        f.push(jump(BPF_JMP | BPF_JEQ | BPF_K, nr as u32, to(deny_at, f.len()), 0));
+    }
+    f.push(jump(BPF_JMP | BPF_JEQ | BPF_K, libc::SYS_socket as u32, 1, 0));
+    f.push(stmt(BPF_RET | BPF_K, SECCOMP_RET_ALLOW));
+    f.push(stmt(BPF_LD | BPF_W | BPF_ABS, ARG0));
+    for family in sockets {
+        f.push(jump(BPF_JMP | BPF_JEQ | BPF_K, family, to(deny_at + 1, f.len()), 0));
+    }
+    debug_assert_eq!(f.len(), deny_at);
+    f.push(stmt(BPF_RET | BPF_K, deny));
+    f.push(stmt(BPF_RET | BPF_K, SECCOMP_RET_ALLOW));
+    f
+}
+
+/// No filter: the system call numbers are only known for the architectures
+/// above.
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn seccomp_filter(_allow_network: bool) -> Vec<libc::sock_filter> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    #[test]
+    fn test_seccomp_filter() {
+        let is_jump = |ins: &libc::sock_filter| ins.code & 0x07 == libc::BPF_JMP as u16;
+        for allow_network in [false, true] {
+            let f = seccomp_filter(allow_network);
+            let (deny, allow) = (f.len() - 2, f.len() - 1);
+            assert_eq!(f[deny].k, SECCOMP_RET_ERRNO | libc::EPERM as u32);
+            assert_eq!(f[allow].k, SECCOMP_RET_ALLOW);
+            for (i, ins) in f.iter().enumerate().filter(|(_, ins)| is_jump(ins)) {
+                assert!(i + 1 + (ins.jt.max(ins.jf) as usize) < f.len(), "jump out of the program at {i}");
+            }
+            let jumps_to = |k: u32, target: usize| {
+                f.iter().enumerate().any(|(i, ins)| is_jump(ins) && ins.k == k && i + 1 + ins.jt as usize == target)
+            };
+            for nr in [
+                libc::SYS_ptrace,
+                libc::SYS_kill,
+                libc::SYS_tgkill,
+                libc::SYS_rt_sigqueueinfo,
+                libc::SYS_pidfd_send_signal,
+                libc::SYS_io_uring_setup,
+            ] {
+                assert!(jumps_to(nr as u32, deny), "system call {nr} is not denied");
+            }
+            assert!(jumps_to(libc::AF_UNIX as u32, allow));
+            assert_eq!(jumps_to(libc::AF_INET as u32, allow), allow_network);
+        }
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    #[test]
+    fn test_seccomp_filter() {
+        assert!(seccomp_filter(false).is_empty());
+    }
+
+    #[test]
+    fn test_elf_interpreter() {
+        assert_eq!(elf_interpreter(Path::new("/etc/hostname")), None);
+        if let Some(loader) = elf_interpreter(Path::new("/bin/sh")) {
+            assert!(loader.exists(), "{}", loader.display());
+        }
+    }
+
+    #[test]
+    fn test_write_outside_workspace_fails() {
+        use std::os::unix::process::CommandExt;
+
+        if landlock_abi().is_none() {
+            return;
+        }
+        let workspace = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let mut confinement = Confinement::new(false).unwrap();
+        for path in ["/lib", "/lib64", "/usr/lib", "/usr/lib64", "/etc/ld.so.cache"] {
+            confinement = confinement.read(Path::new(path));
+        }
+        let confinement = confinement.write(workspace.path()).unwrap().exec(Path::new("/bin/sh")).unwrap();
+
+        let script = format!(
+            "echo in > '{}/in.txt'; echo out > '{}/out.txt'",
+            workspace.path().display(),
+            outside.path().display()
+        );
+        let mut command = std::process::Command::new("/bin/sh");
+        command.args(["-c", &script]);
+        // SAFETY: `apply` only makes system calls.
+        unsafe {
+            command.pre_exec(move || confinement.apply());
+        }
+        let output = command.output().unwrap();
+        assert!(workspace.path().join("in.txt").exists(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert!(!outside.path().join("out.txt").exists());
+        assert!(!output.status.success());
+    }
+}
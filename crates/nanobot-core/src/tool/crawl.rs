@@ -0,0 +1,237 @@
+//! `web_crawl`: a page plus the same-site pages it links to.
+//!
+//! Breadth-first from the start URL, following links on the same host up to
+//! `maxDepth` hops and `maxPages` pages. Each page is reduced to its main
+//! content as markdown ([`markdown::readable`]). Lines already seen on an earlier
+//! page (menus, footers) are dropped, pages with nothing new are skipped,
+//! and the rest share a character budget. Each page is numbered so the
+//! answer can cite it as `[n]`. Fetches go through [`http::fetch_page`], so
+//! robots.txt, per-host pacing and the page cache apply.
+
+use async_trait::async_trait;
+use reqwest::Url;
+use scraper::{Html, Selector};
+use serde_json::json;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::Tool;
+use crate::util::{http, markdown, truncate_chars};
+
+const DEFAULT_DEPTH: u64 = 1;
+const MAX_DEPTH: u64 = 3;
+const DEFAULT_PAGES: u64 = 10;
+const MAX_PAGES: u64 = 30;
+
+/// Links to these aren't pages worth reading.
+const SKIP_EXTENSIONS: &[&str] = &[
+    ".png", ".jpg", ".jpeg", ".gif", ".svg", ".webp", ".ico", ".pdf", ".zip", ".gz", ".mp3", ".mp4",
+    ".css", ".js", ".woff", ".woff2", ".xml",
+];
+
+/// One fetched page.
+#[derive(Debug, Clone)]
+pub struct CrawledPage {
+    pub url: String,
+    pub title: String,
+    pub text: String,
+}
+
+/// Same-host http(s) links of `html`, resolved against `base` and without
+/// fragments.
+pub fn same_site_links(html: &str, base: &Url) -> Vec<Url> {
+    let doc = Html::parse_document(html);
+    let sel = Selector::parse("a[href]").unwrap();
+    let mut seen = HashSet::new();
+    doc.select(&sel)
+        .filter_map(|a| a.value().attr("href"))
+        .filter_map(|href| base.join(href).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https") && url.host_str() == base.host_str())
+        .map(|mut url| {
+            url.set_fragment(None);
+            url
+        })
+        .filter(|url| {
+            let path = url.path().to_lowercase();
+            !SKIP_EXTENSIONS.iter().any(|ext| path.ends_with(ext))
+        })
+        .filter(|url| seen.insert(url.to_string()))
+        .collect()
+}
+
+/// Drop lines already seen on earlier pages; `None` if nothing new is left.
+fn dedupe(text: &str, seen: &mut HashSet<String>) -> Option<String> {
+    let fresh: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        // Markdown structure (code fences, table separators) repeats legitimately
+        .filter(|l| !l.is_empty() && (l.starts_with("```") || l.starts_with("| ---") || seen.insert(l.to_string())))
+        .collect();
+    (!fresh.is_empty()).then(|| fresh.join("\n"))
+}
+
+/// Merge pages into one numbered digest within `budget` characters. Short
+/// pages leave their unused share to the pages after them.
+pub fn digest(pages: &[CrawledPage], budget: usize) -> (String, bool) {
+    let mut out = String::new();
+    let mut truncated = false;
+    let mut left = budget;
+    for (i, page) in pages.iter().enumerate() {
+        let share = left / (pages.len() - i);
+        let (text, cut) = truncate_chars(&page.text, share);
+        truncated |= cut;
+        left -= text.chars().count();
+        out.push_str(&format!("## [{}] {}\n{}\n{}\n\n", i + 1, page.title, page.url, text));
+    }
+    (out.trim_end().to_string(), truncated)
+}
+
+async fn fetch(url: &Url) -> Result<(Url, String, String), String> {
+    let page = http::fetch_page(url.as_str()).await.map_err(|e| e.to_string())?;
+    if !(200..300).contains(&page.status) {
+        return Err(format!("HTTP {}", page.status));
+    }
+    let final_url = Url::parse(&page.final_url).map_err(|e| e.to_string())?;
+    Ok((final_url, page.content_type, page.body))
+}
+
+/// Crawl from `start`; pages that fail to load are skipped.
+pub async fn crawl(start: Url, max_depth: u64, max_pages: u64) -> Vec<CrawledPage> {
+    let mut queue = VecDeque::from([(start.clone(), 0u64)]);
+    let mut visited: HashSet<String> = HashSet::from([start.to_string()]);
+    let mut seen_lines = HashSet::new();
+    let mut pages = Vec::new();
+
+    while let Some((url, depth)) = queue.pop_front() {
+        if pages.len() as u64 >= max_pages {
+            break;
+        }
+        let (final_url, content_type, body) = match fetch(&url).await {
+            Ok(page) => page,
+            Err(e) => {
+                tracing::debug!("web_crawl: skipping {}: {}", url, e);
+                continue;
+            }
+        };
+        let is_html = content_type.contains("text/html") || body.trim_start().to_lowercase().starts_with("<!doctype");
+        if is_html && depth < max_depth {
+            for link in same_site_links(&body, &final_url) {
+                if visited.insert(link.to_string()) {
+                    queue.push_back((link, depth + 1));
+                }
+            }
+        }
+        let (title, text) = if is_html {
+            let page = markdown::readable(&body, Some(&final_url));
+            (page.title, page.markdown)
+        } else {
+            (String::new(), body)
+        };
+        if let Some(text) = dedupe(&text, &mut seen_lines) {
+            pages.push(CrawledPage { url: final_url.to_string(), title, text });
+        }
+    }
+    pages
+}
+
+/// Crawl a site and return a digest with per-page citations.
+pub struct WebCrawlTool {
+    max_chars: usize,
+}
+
+impl WebCrawlTool {
+    pub fn new(max_chars: usize) -> Self {
+        Self { max_chars }
+    }
+}
+
+#[async_trait]
+impl Tool for WebCrawlTool {
+    fn name(&self) -> &str {
+        "web_crawl"
+    }
+
+    fn description(&self) -> &str {
+        "Read a web page and the pages it links to on the same site (e.g. a documentation site). \
+         Returns a merged digest with numbered pages; cite them as [n]."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": {"type": "string", "description": "Start URL"},
+                "maxDepth": {"type": "integer", "description": "Link hops from the start page (default 1)", "minimum": 0, "maximum": MAX_DEPTH},
+                "maxPages": {"type": "integer", "description": "Pages to read (default 10)", "minimum": 1, "maximum": MAX_PAGES},
+                "maxChars": {"type": "integer", "minimum": 1000}
+            },
+            "required": ["url"]
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        let Some(url) = params.get("url").and_then(|v| v.as_str()) else {
+            return json!({"error": "'url' parameter is required"}).to_string();
+        };
+        let start = match Url::parse(url) {
+            Ok(u) if matches!(u.scheme(), "http" | "https") => u,
+            _ => return json!({"error": "Only http/https URLs allowed", "url": url}).to_string(),
+        };
+        let max_depth = params.get("maxDepth").and_then(|v| v.as_u64()).unwrap_or(DEFAULT_DEPTH).min(MAX_DEPTH);
+        let max_pages = params.get("maxPages").and_then(|v| v.as_u64()).unwrap_or(DEFAULT_PAGES).clamp(1, MAX_PAGES);
+        let budget = params.get("maxChars").and_then(|v| v.as_u64()).map(|n| n as usize).unwrap_or(self.max_chars);
+
+        let pages = crawl(start, max_depth, max_pages).await;
+        if pages.is_empty() {
+            return json!({"error": "No pages could be fetched", "url": url}).to_string();
+        }
+        let (text, truncated) = digest(&pages, budget);
+        let sources: Vec<serde_json::Value> = pages
+            .iter()
+            .enumerate()
+            .map(|(i, p)| json!({"ref": i + 1, "url": p.url, "title": p.title}))
+            .collect();
+        json!({
+            "url": url,
+            "pages": sources,
+            "truncated": truncated,
+            "text": text,
+        })
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_site_links() {
+        let base = Url::parse("https://docs.example.com/guide/").unwrap();
+        let html = r##"<a href="intro">Intro</a><a href="/api#top">API</a><a href="/api">API again</a>
+            <a href="https://other.com/x">Other</a><a href="logo.png">Logo</a><a href="mailto:a@b.c">Mail</a>"##;
+        let links: Vec<String> = same_site_links(html, &base).iter().map(|u| u.to_string()).collect();
+        assert_eq!(links, vec!["https://docs.example.com/guide/intro", "https://docs.example.com/api"]);
+    }
+
+    #[test]
+    fn test_dedupe_drops_repeated_lines() {
+        let mut seen = HashSet::new();
+        assert_eq!(dedupe("Home\nDocs\nIntro text", &mut seen).as_deref(), Some("Home\nDocs\nIntro text"));
+        assert_eq!(dedupe("Home\nDocs\nAPI text", &mut seen).as_deref(), Some("API text"));
+        assert_eq!(dedupe("Home\nDocs", &mut seen), None);
+    }
+
+    #[test]
+    fn test_digest_shares_budget() {
+        let page = |n: usize, len: usize| CrawledPage {
+            url: format!("https://x/{}", n),
+            title: format!("P{}", n),
+            text: "a".repeat(len),
+        };
+        let (text, truncated) = digest(&[page(1, 10), page(2, 500)], 200);
+        assert!(truncated);
+        assert!(text.starts_with("## [1] P1\nhttps://x/1\naaaaaaaaaa\n\n## [2] P2"));
+        // the short first page leaves its unused share to the second
+        assert_eq!(text.matches('a').count(), 200);
+    }
+}
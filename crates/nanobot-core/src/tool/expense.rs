@@ -0,0 +1,293 @@
+//! `expense` tool: record receipts and spending, and answer "how much did I
+//! spend on food this month?" from the user's ledger.
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use super::extract::extract;
+use super::ocr::Ocr;
+use super::Tool;
+use crate::expense::{normalize_category, Expense, ExpenseLedger, Period, Summary, CATEGORIES};
+use crate::provider::LlmProvider;
+
+/// Currency assumed when a receipt doesn't show one.
+const DEFAULT_CURRENCY: &str = "JPY";
+
+/// Entries returned by `list`.
+const MAX_LISTED: usize = 50;
+
+/// Schema the receipt text is extracted into.
+fn receipt_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "amount": {"type": "number", "description": "Total paid, tax included"},
+            "currency": {"type": "string", "description": "ISO 4217 code, e.g. JPY or USD"},
+            "vendor": {"type": "string", "description": "Shop or payee"},
+            "date": {"type": "string", "description": "Purchase date, YYYY-MM-DD"},
+            "category": {"type": "string", "enum": CATEGORIES}
+        },
+        "required": ["amount", "category"]
+    })
+}
+
+/// Tool for the agent to keep the user's expense ledger.
+pub struct ExpenseTool {
+    ledger: Arc<dyn ExpenseLedger>,
+    provider: Arc<dyn LlmProvider>,
+    model: String,
+    ocr: Arc<Ocr>,
+    /// Ledger owner (session key); without one, the caller's `_user_id`.
+    user: Arc<Mutex<String>>,
+}
+
+impl ExpenseTool {
+    pub fn new(ledger: Arc<dyn ExpenseLedger>, provider: Arc<dyn LlmProvider>, model: impl Into<String>, ocr: Arc<Ocr>) -> Self {
+        Self { ledger, provider, model: model.into(), ocr, user: Arc::new(Mutex::new(String::new())) }
+    }
+
+    /// The same tool reading receipts with `ocr`.
+    pub fn with_ocr(&self, ocr: Arc<Ocr>) -> Self {
+        Self {
+            ledger: self.ledger.clone(),
+            provider: self.provider.clone(),
+            model: self.model.clone(),
+            ocr,
+            user: self.user.clone(),
+        }
+    }
+
+    pub async fn set_context(&self, session_key: &str) {
+        *self.user.lock().await = session_key.to_string();
+    }
+
+    /// An entry from explicit fields, or extracted from `text` / the receipt
+    /// image at `image`.
+    async fn read_entry(&self, params: &HashMap<String, Value>, today: NaiveDate) -> Result<Expense, String> {
+        let str_param = |key: &str| params.get(key).and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty());
+        let mut fields = json!({});
+        if let Some(amount) = params.get("amount").and_then(|v| v.as_f64()) {
+            fields["amount"] = json!(amount);
+        } else {
+            let mut text = str_param("text").unwrap_or_default().to_string();
+            if let Some(image) = str_param("image") {
+                let bytes = self.ocr.load(image, false).await?;
+                let ocr = self.ocr.recognize(&bytes, None, true).await?;
+                text = format!("{}\n\n[Receipt]\n{}", text, ocr.text);
+            }
+            if text.trim().is_empty() {
+                return Err("give 'amount', 'text' or a receipt 'image'".to_string());
+            }
+            let instructions = format!(
+                "Today is {}. Use the total actually paid. If the currency isn't shown, use {}.",
+                today, DEFAULT_CURRENCY
+            );
+            fields = extract(self.provider.as_ref(), &self.model, &text, &receipt_schema(), Some(&instructions), 2)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        for key in ["currency", "vendor", "date", "category"] {
+            if let Some(value) = str_param(key) {
+                fields[key] = json!(value);
+            }
+        }
+
+        let amount = fields["amount"].as_f64().filter(|a| a.is_finite() && *a != 0.0).ok_or("no amount found")?;
+        let date = fields["date"]
+            .as_str()
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            .filter(|d| *d <= today)
+            .unwrap_or(today);
+        let mut entry = Expense::new(
+            date,
+            amount,
+            fields["currency"].as_str().filter(|c| c.len() == 3).unwrap_or(DEFAULT_CURRENCY),
+            fields["vendor"].as_str().unwrap_or_default(),
+            fields["category"].as_str().unwrap_or("other"),
+        );
+        entry.note = str_param("note").unwrap_or_default().to_string();
+        Ok(entry)
+    }
+}
+
+#[async_trait]
+impl Tool for ExpenseTool {
+    fn name(&self) -> &str {
+        "expense"
+    }
+
+    fn description(&self) -> &str {
+        "The user's expense ledger. action='add' records a purchase from a receipt photo ('image'), text, \
+         or explicit fields; 'summary' totals spending for a period with a per-category chart; 'list' shows \
+         entries with their ids; 'delete' removes one by id."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": {"type": "string", "enum": ["add", "summary", "list", "delete"]},
+                "text": {"type": "string", "description": "add: receipt text or a description like 'lunch 980 yen at Matsuya'"},
+                "image": {"type": "string", "description": "add: receipt image path or URL"},
+                "amount": {"type": "number", "description": "add: amount paid (skips extraction)"},
+                "currency": {"type": "string", "description": "add: ISO 4217 code (default JPY)"},
+                "vendor": {"type": "string"},
+                "date": {"type": "string", "description": "add: YYYY-MM-DD (default today)"},
+                "category": {"type": "string", "description": format!("One of: {}", CATEGORIES.join(", "))},
+                "note": {"type": "string"},
+                "period": {
+                    "type": "string",
+                    "description": "summary/list: today, this_week, this_month (default), last_month, this_year, YYYY-MM, or YYYY-MM-DD..YYYY-MM-DD"
+                },
+                "id": {"type": "string", "description": "delete: entry id"}
+            },
+            "required": ["action"]
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, Value>) -> String {
+        // The session context wins so a model can't name another user's ledger.
+        let mut user = self.user.lock().await.clone();
+        if user.is_empty() {
+            user = params.get("_user_id").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        }
+        if user.is_empty() {
+            return json!({"error": "no user context"}).to_string();
+        }
+        let today = chrono::Local::now().date_naive();
+        let action = params.get("action").and_then(|v| v.as_str()).unwrap_or("summary");
+        let period = || {
+            let raw = params.get("period").and_then(|v| v.as_str()).unwrap_or("");
+            Period::parse(raw, today).ok_or_else(|| format!("unknown period '{}'", raw))
+        };
+        let category = params.get("category").and_then(|v| v.as_str()).map(normalize_category);
+
+        let result: Result<Value, String> = async {
+            match action {
+                "add" => {
+                    let entry = self.read_entry(&params, today).await?;
+                    self.ledger.add(&user, &entry).await.map_err(|e| e.to_string())?;
+                    Ok(json!({"added": entry, "line": entry.line()}))
+                }
+                "summary" | "list" => {
+                    let period = period()?;
+                    let mut entries = self.ledger.list(&user, period).await.map_err(|e| e.to_string())?;
+                    if let Some(category) = &category {
+                        entries.retain(|e| &e.category == category);
+                    }
+                    if action == "list" {
+                        let lines: Vec<String> = entries.iter().rev().take(MAX_LISTED).map(Expense::line).collect();
+                        return Ok(json!({"period": period.label(), "count": entries.len(), "entries": lines}));
+                    }
+                    let summary = Summary::of(&entries);
+                    let heading = match &category {
+                        Some(c) => format!("{} · {}", period.label(), c),
+                        None => period.label(),
+                    };
+                    Ok(json!({
+                        "period": period.label(),
+                        "currency": summary.currency,
+                        "total": summary.total,
+                        "count": summary.count,
+                        "byCategory": summary.by_category.iter().map(|(c, t)| json!({"category": c, "total": t})).collect::<Vec<_>>(),
+                        "otherCurrencies": summary.other_currencies.iter().map(|(c, t)| json!({"currency": c, "total": t})).collect::<Vec<_>>(),
+                        "chart": summary.render(&heading),
+                    }))
+                }
+                "delete" => {
+                    let id = params.get("id").and_then(|v| v.as_str()).unwrap_or("").trim();
+                    if id.is_empty() {
+                        return Err("'id' is required".to_string());
+                    }
+                    let deleted = self.ledger.remove(&user, id).await.map_err(|e| e.to_string())?;
+                    Ok(json!({"deleted": deleted, "id": id}))
+                }
+                other => Err(format!("unknown action '{}'", other)),
+            }
+        }
+        .await;
+        match result {
+            Ok(value) => value.to_string(),
+            Err(e) => json!({"error": e}).to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ProviderError;
+    use crate::expense::FileLedger;
+    use crate::tool::extract::EMIT_TOOL;
+    use crate::types::{CompletionResponse, FinishReason, Message, TokenUsage, ToolCall};
+
+    /// Reads every receipt as a 1,280 yen meal at Matsuya.
+    struct Receipts;
+
+    #[async_trait]
+    impl LlmProvider for Receipts {
+        async fn chat(
+            &self,
+            _messages: &[Message],
+            _tools: Option<&[serde_json::Value]>,
+            _model: &str,
+            _max_tokens: u32,
+            _temperature: f64,
+        ) -> Result<CompletionResponse, ProviderError> {
+            Ok(CompletionResponse {
+                content: None,
+                tool_calls: vec![ToolCall {
+                    id: "call_1".into(),
+                    name: EMIT_TOOL.into(),
+                    arguments: HashMap::from([
+                        ("amount".to_string(), json!(1280)),
+                        ("vendor".to_string(), json!("Matsuya")),
+                        ("category".to_string(), json!("food")),
+                    ]),
+                }],
+                finish_reason: FinishReason::ToolCalls,
+                usage: TokenUsage::default(),
+                model: None,
+            })
+        }
+
+        fn default_model(&self) -> &str {
+            "receipts"
+        }
+    }
+
+    fn params(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_and_summarize() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = ExpenseTool::new(
+            Arc::new(FileLedger::new(dir.path().to_path_buf())),
+            Arc::new(Receipts),
+            "receipts",
+            Arc::new(Ocr::new(dir.path().to_path_buf(), &Default::default())),
+        );
+        let denied: Value = serde_json::from_str(&tool.execute(params(json!({"action": "summary"}))).await).unwrap();
+        assert_eq!(denied["error"], "no user context");
+
+        tool.set_context("line:U1").await;
+        let added: Value =
+            serde_json::from_str(&tool.execute(params(json!({"action": "add", "text": "松屋 ¥1,280"}))).await).unwrap();
+        assert_eq!(added["added"]["vendor"], "Matsuya");
+        assert_eq!(added["added"]["currency"], "JPY");
+        tool.execute(params(json!({"action": "add", "amount": 220, "vendor": "Bus", "category": "transport"}))).await;
+
+        let food: Value =
+            serde_json::from_str(&tool.execute(params(json!({"action": "summary", "category": "food"}))).await).unwrap();
+        assert_eq!(food["total"], 1280.0);
+        assert_eq!(food["count"], 1);
+        let all: Value = serde_json::from_str(&tool.execute(params(json!({"action": "summary"}))).await).unwrap();
+        assert_eq!(all["total"], 1500.0);
+    }
+}
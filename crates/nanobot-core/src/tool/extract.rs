@@ -0,0 +1,377 @@
+//! Structured extraction: text (or a web page) + JSON schema -> validated JSON.
+//!
+//! The model is given a single `emit` function whose parameters are the
+//! caller's schema, so providers with function calling return the data in
+//! their structured-output path. The arguments are validated against the
+//! schema; on errors the model gets them back and tries again.
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::Tool;
+use crate::provider::LlmProvider;
+use crate::types::Message;
+use crate::util::truncate_chars;
+
+/// Attempts per extraction (the first try plus retries on invalid output).
+pub const DEFAULT_ATTEMPTS: u32 = 3;
+
+/// Input text is cut to this many characters.
+const MAX_INPUT_CHARS: usize = 50_000;
+
+pub(crate) const EMIT_TOOL: &str = "emit";
+
+const SYSTEM_PROMPT: &str = "You extract structured data from text. Call the `emit` function \
+exactly once with the data found in the text. Use only information present in the text; use \
+null (or leave optional fields out) for anything missing. Do not invent values.";
+
+/// Why an extraction failed.
+#[derive(Debug, thiserror::Error)]
+pub enum ExtractError {
+    #[error("invalid schema: {0}")]
+    Schema(String),
+    #[error("provider error: {0}")]
+    Provider(String),
+    #[error("no valid result after {attempts} attempts: {}", errors.join("; "))]
+    Invalid { attempts: u32, errors: Vec<String> },
+}
+
+/// Check `value` against `schema`. Supports the JSON Schema subset used for
+/// extraction: `type` (also as a list), `properties`, `required`,
+/// `additionalProperties: false`, `items`, `enum`, `minItems`/`maxItems`,
+/// `minimum`/`maximum`. Returns one message per violation.
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(schema, value, "$", &mut errors);
+    errors
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.as_i64().is_some() || value.as_u64().is_some()
+            || value.as_f64().is_some_and(|f| f.fract() == 0.0),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(ts)) => ts.iter().filter_map(|t| t.as_str()).collect(),
+        _ => vec![],
+    };
+    if !types.is_empty() && !types.iter().any(|t| type_matches(t, value)) {
+        errors.push(format!("{}: expected {}, got {}", path, types.join(" or "), value));
+        return;
+    }
+    if let Some(allowed) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !allowed.contains(value) {
+            errors.push(format!("{}: {} is not one of {}", path, value, Value::Array(allowed.clone())));
+        }
+    }
+    if let Some(n) = value.as_f64() {
+        if let Some(min) = schema.get("minimum").and_then(|m| m.as_f64()).filter(|min| n < *min) {
+            errors.push(format!("{}: {} is below the minimum {}", path, n, min));
+        }
+        if let Some(max) = schema.get("maximum").and_then(|m| m.as_f64()).filter(|max| n > *max) {
+            errors.push(format!("{}: {} is above the maximum {}", path, n, max));
+        }
+    }
+    match value {
+        Value::Object(map) => {
+            let properties = schema.get("properties").and_then(|p| p.as_object());
+            for key in schema.get("required").and_then(|r| r.as_array()).into_iter().flatten().filter_map(|k| k.as_str()) {
+                if !map.contains_key(key) {
+                    errors.push(format!("{}: missing required field '{}'", path, key));
+                }
+            }
+            for (key, item) in map {
+                match properties.and_then(|p| p.get(key)) {
+                    Some(sub) => validate_at(sub, item, &format!("{}.{}", path, key), errors),
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        errors.push(format!("{}: unexpected field '{}'", path, key));
+                    }
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(|m| m.as_u64()).filter(|min| (items.len() as u64) < *min) {
+                errors.push(format!("{}: expected at least {} items, got {}", path, min, items.len()));
+            }
+            if let Some(max) = schema.get("maxItems").and_then(|m| m.as_u64()).filter(|max| items.len() as u64 > *max) {
+                errors.push(format!("{}: expected at most {} items, got {}", path, max, items.len()));
+            }
+            if let Some(sub) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(sub, item, &format!("{}[{}]", path, i), errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Function parameters must be an object; other schemas are wrapped in
+/// `{"value": ...}` and unwrapped again after extraction.
+fn emit_parameters(schema: &Value) -> (Value, bool) {
+    if schema.get("type").and_then(|t| t.as_str()) == Some("object") {
+        (schema.clone(), false)
+    } else {
+        (json!({"type": "object", "properties": {"value": schema}, "required": ["value"]}), true)
+    }
+}
+
+/// Extract data matching `schema` from `text` with `model`, retrying up to
+/// `attempts` times while the output doesn't validate.
+pub async fn extract(
+    provider: &dyn LlmProvider,
+    model: &str,
+    text: &str,
+    schema: &Value,
+    instructions: Option<&str>,
+    attempts: u32,
+) -> Result<Value, ExtractError> {
+    if !schema.is_object() {
+        return Err(ExtractError::Schema("schema must be a JSON object".to_string()));
+    }
+    let (parameters, wrapped) = emit_parameters(schema);
+    let tools = vec![json!({
+        "type": "function",
+        "function": {
+            "name": EMIT_TOOL,
+            "description": "Return the extracted data.",
+            "parameters": parameters,
+        }
+    })];
+
+    let mut prompt = format!("Text:\n\"\"\"\n{}\n\"\"\"", truncate_chars(text, MAX_INPUT_CHARS).0);
+    if let Some(extra) = instructions.filter(|i| !i.trim().is_empty()) {
+        prompt.push_str(&format!("\n\nInstructions: {}", extra));
+    }
+    let mut messages = vec![Message::system(SYSTEM_PROMPT), Message::user(prompt)];
+
+    let attempts = attempts.max(1);
+    let mut errors = Vec::new();
+    for attempt in 1..=attempts {
+        let resp = provider
+            .chat(&messages, Some(&tools), model, 4096, 0.0)
+            .await
+            .map_err(|e| ExtractError::Provider(e.to_string()))?;
+
+        // Function call first; models without one may still answer in JSON
+        let (call_id, candidate) = match resp.tool_calls.iter().find(|c| c.name == EMIT_TOOL) {
+            Some(call) => (Some(call.id.clone()), Some(Value::Object(call.arguments.clone().into_iter().collect()))),
+            None => (None, resp.content.as_deref().and_then(parse_json_reply)),
+        };
+        let candidate = candidate.map(|v| if wrapped { v.get("value").cloned().unwrap_or(Value::Null) } else { v });
+
+        errors = match &candidate {
+            Some(value) => validate(schema, value),
+            None => vec!["the reply contained no `emit` call and no JSON".to_string()],
+        };
+        if errors.is_empty() {
+            if let Some(value) = candidate {
+                return Ok(value);
+            }
+        }
+        tracing::debug!("extract attempt {}/{} invalid: {:?}", attempt, attempts, errors);
+
+        let feedback = format!(
+            "The data is invalid:\n- {}\nCall `emit` again with corrected data.",
+            errors.join("\n- ")
+        );
+        match call_id {
+            Some(id) => {
+                let call = json!({
+                    "id": id,
+                    "type": "function",
+                    "function": {
+                        "name": EMIT_TOOL,
+                        "arguments": resp.tool_calls.iter().find(|c| c.name == EMIT_TOOL)
+                            .and_then(|c| serde_json::to_string(&c.arguments).ok())
+                            .unwrap_or_else(|| "{}".to_string()),
+                    }
+                });
+                messages.push(Message::assistant_with_tool_calls(resp.content.clone(), vec![call]));
+                messages.push(Message::tool_result(id, EMIT_TOOL, feedback));
+            }
+            None => {
+                messages.push(Message::assistant(resp.content.clone().unwrap_or_default()));
+                messages.push(Message::user(feedback));
+            }
+        }
+    }
+    Err(ExtractError::Invalid { attempts, errors })
+}
+
+/// JSON object from a plain reply, with or without a code fence.
+pub(crate) fn parse_json_reply(reply: &str) -> Option<Value> {
+    let start = reply.find(['{', '['])?;
+    let end = reply.rfind(['}', ']'])?;
+    (start < end).then(|| serde_json::from_str(&reply[start..=end]).ok()).flatten()
+}
+
+/// `extract` tool: structured data from text or a URL.
+pub struct ExtractTool {
+    provider: Arc<dyn LlmProvider>,
+    model: String,
+}
+
+impl ExtractTool {
+    pub fn new(provider: Arc<dyn LlmProvider>, model: impl Into<String>) -> Self {
+        Self { provider, model: model.into() }
+    }
+}
+
+#[async_trait]
+impl Tool for ExtractTool {
+    fn name(&self) -> &str {
+        "extract"
+    }
+
+    fn description(&self) -> &str {
+        "Extract structured data from text or a web page. Give `text` or `url` and a JSON `schema`; \
+         returns JSON that validates against the schema."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "text": {"type": "string", "description": "Text to extract from"},
+                "url": {"type": "string", "description": "Web page to extract from (instead of text)"},
+                "schema": {"type": "object", "description": "JSON Schema of the result"},
+                "instructions": {"type": "string", "description": "Extra guidance, e.g. units or date format"}
+            },
+            "required": ["schema"]
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        let Some(schema) = params.get("schema") else {
+            return json!({"error": "'schema' parameter is required"}).to_string();
+        };
+        let text = match (params.get("text").and_then(|v| v.as_str()), params.get("url").and_then(|v| v.as_str())) {
+            (Some(text), _) => text.to_string(),
+            (None, Some(url)) => match fetch_text(url).await {
+                Ok(text) => text,
+                Err(e) => return json!({"error": e, "url": url}).to_string(),
+            },
+            (None, None) => return json!({"error": "'text' or 'url' is required"}).to_string(),
+        };
+        let instructions = params.get("instructions").and_then(|v| v.as_str());
+        match extract(self.provider.as_ref(), &self.model, &text, schema, instructions, DEFAULT_ATTEMPTS).await {
+            Ok(data) => json!({"data": data}).to_string(),
+            Err(e) => json!({"error": e.to_string()}).to_string(),
+        }
+    }
+}
+
+/// Readable text of a web page, via `read_webpage`.
+pub async fn fetch_text(url: &str) -> Result<String, String> {
+    let fetch = super::web::WebFetchTool::new(MAX_INPUT_CHARS);
+    let params = HashMap::from([("url".to_string(), json!(url))]);
+    let result: Value = serde_json::from_str(&fetch.execute(params).await).map_err(|e| e.to_string())?;
+    if let Some(error) = result.get("error").and_then(|e| e.as_str()) {
+        return Err(error.to_string());
+    }
+    Ok(result.get("text").and_then(|t| t.as_str()).unwrap_or_default().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ProviderError;
+    use crate::types::{CompletionResponse, FinishReason, Role, TokenUsage, ToolCall};
+
+    /// Whether a message is the `emit` feedback of a retry.
+    fn is_retry(message: &Message) -> bool {
+        message.role == Role::Tool && message.name.as_deref() == Some(EMIT_TOOL)
+    }
+
+    fn person_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "age": {"type": "integer", "minimum": 0},
+                "tags": {"type": "array", "items": {"type": "string"}, "maxItems": 2},
+                "role": {"enum": ["admin", "user"]}
+            },
+            "required": ["name", "age"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = person_schema();
+        assert!(validate(&schema, &json!({"name": "Ann", "age": 30, "tags": ["a"], "role": "user"})).is_empty());
+
+        let errors = validate(&schema, &json!({"name": 1, "age": -1, "tags": ["a", "b", "c"], "role": "root", "x": 1}));
+        assert!(errors.iter().any(|e| e.starts_with("$.name: expected string")));
+        assert!(errors.iter().any(|e| e.contains("below the minimum")));
+        assert!(errors.iter().any(|e| e.contains("at most 2 items")));
+        assert!(errors.iter().any(|e| e.contains("\"root\" is not one of")));
+        assert!(errors.iter().any(|e| e.contains("unexpected field 'x'")));
+
+        assert_eq!(validate(&schema, &json!({"name": "Ann"})), vec!["$: missing required field 'age'"]);
+        assert!(validate(&json!({"type": ["string", "null"]}), &Value::Null).is_empty());
+    }
+
+    #[test]
+    fn test_parse_json_reply() {
+        assert_eq!(parse_json_reply("```json\n{\"a\": 1}\n```"), Some(json!({"a": 1})));
+        assert_eq!(parse_json_reply("no json"), None);
+    }
+
+    /// Emits an invalid age first, then a valid record once corrected.
+    struct Extractor;
+
+    #[async_trait]
+    impl LlmProvider for Extractor {
+        async fn chat(
+            &self,
+            messages: &[Message],
+            _tools: Option<&[serde_json::Value]>,
+            _model: &str,
+            _max_tokens: u32,
+            _temperature: f64,
+        ) -> Result<CompletionResponse, ProviderError> {
+            let age = if messages.iter().any(is_retry) { json!(41) } else { json!("forty-one") };
+            Ok(CompletionResponse {
+                content: None,
+                tool_calls: vec![ToolCall {
+                    id: "call_1".into(),
+                    name: EMIT_TOOL.into(),
+                    arguments: HashMap::from([("name".to_string(), json!("Ann")), ("age".to_string(), age)]),
+                }],
+                finish_reason: FinishReason::ToolCalls,
+                usage: TokenUsage::default(),
+                model: None,
+            })
+        }
+
+        fn default_model(&self) -> &str {
+            "extractor"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extract_retries_invalid_output() {
+        let data = extract(&Extractor, "m", "Ann is 41.", &person_schema(), None, 3).await.unwrap();
+        assert_eq!(data, json!({"name": "Ann", "age": 41}));
+
+        let err = extract(&Extractor, "m", "Ann is 41.", &person_schema(), None, 1).await.unwrap_err();
+        assert!(matches!(err, ExtractError::Invalid { attempts: 1, .. }));
+    }
+}
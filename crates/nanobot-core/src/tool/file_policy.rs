@@ -0,0 +1,220 @@
+//! Safe mode for the file tools.
+//!
+//! A [`FilePolicy`] decides what `read_file`, `write_file`, `edit_file` and
+//! `apply_patch` may touch: protected paths (gitignore syntax, relative to
+//! the workspace) can't be modified, denied extensions can be neither read
+//! nor written, and files over the size limit are refused. Before a file
+//! is overwritten or deleted a copy goes to `<workspace>/.trash/<time>/`,
+//! and batches older than the retention period are purged. Configured by
+//! `tools.files`.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use tracing::warn;
+
+use crate::config::FilePolicyConfig;
+
+/// Trash directory, relative to the workspace.
+pub const TRASH_DIR: &str = ".trash";
+
+pub struct FilePolicy {
+    workspace: PathBuf,
+    protected: Gitignore,
+    /// Lowercase, without the dot.
+    denied_extensions: Vec<String>,
+    max_file_bytes: u64,
+    trash: bool,
+    retention: Duration,
+}
+
+impl FilePolicy {
+    pub fn new(workspace: &Path, config: &FilePolicyConfig) -> Self {
+        let workspace = crate::util::normalize_path(workspace);
+        let mut builder = GitignoreBuilder::new(&workspace);
+        for pattern in &config.protected {
+            if let Err(e) = builder.add_line(None, pattern) {
+                warn!("Ignoring protected file pattern {:?}: {}", pattern, e);
+            }
+        }
+        let protected = builder.build().unwrap_or_else(|e| {
+            warn!("Invalid protected file patterns: {}", e);
+            Gitignore::empty()
+        });
+        Self {
+            workspace,
+            protected,
+            denied_extensions: config
+                .denied_extensions
+                .iter()
+                .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                .collect(),
+            max_file_bytes: config.max_file_bytes,
+            trash: config.trash,
+            retention: Duration::from_secs(config.trash_retention_days as u64 * 24 * 3600),
+        }
+    }
+
+    /// No restrictions and no trash.
+    pub fn off() -> Self {
+        Self {
+            workspace: PathBuf::new(),
+            protected: Gitignore::empty(),
+            denied_extensions: Vec::new(),
+            max_file_bytes: u64::MAX,
+            trash: false,
+            retention: Duration::ZERO,
+        }
+    }
+
+    /// Whether the file at `path` may be read.
+    pub fn check_read(&self, path: &Path) -> Result<(), String> {
+        self.check_extension(path)?;
+        match std::fs::metadata(path) {
+            Ok(meta) if meta.len() > self.max_file_bytes => Err(self.too_large(path, meta.len())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether `path` may be written with `len` bytes, or deleted (`len` 0).
+    pub fn check_write(&self, path: &Path, len: usize) -> Result<(), String> {
+        self.check_extension(path)?;
+        if self.is_protected(path) {
+            return Err(format!("{} is protected (tools.files.protected)", path.display()));
+        }
+        if len as u64 > self.max_file_bytes {
+            return Err(self.too_large(path, len as u64));
+        }
+        Ok(())
+    }
+
+    fn check_extension(&self, path: &Path) -> Result<(), String> {
+        let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        match ext.filter(|e| self.denied_extensions.contains(e)) {
+            Some(ext) => Err(format!(".{} files are off limits (tools.files.deniedExtensions)", ext)),
+            None => Ok(()),
+        }
+    }
+
+    fn too_large(&self, path: &Path, len: u64) -> String {
+        format!(
+            "{} is {} bytes, over the {} byte limit (tools.files.maxFileBytes)",
+            path.display(),
+            len,
+            self.max_file_bytes
+        )
+    }
+
+    fn is_protected(&self, path: &Path) -> bool {
+        if self.protected.is_empty() {
+            return false;
+        }
+        // Files outside the workspace can only match by name.
+        let relative = match path.strip_prefix(&self.workspace) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative.to_path_buf(),
+            _ => match path.file_name() {
+                Some(name) => PathBuf::from(name),
+                None => return false,
+            },
+        };
+        self.protected.matched_path_or_any_parents(&relative, false).is_ignore()
+    }
+
+    pub fn trash_dir(&self) -> PathBuf {
+        self.workspace.join(TRASH_DIR)
+    }
+
+    /// Copy `path` to the trash before it is overwritten or deleted. `None`
+    /// when the trash is off or the file doesn't exist yet.
+    pub fn trash(&self, path: &Path) -> Result<Option<PathBuf>, String> {
+        if !self.trash || !path.is_file() {
+            return Ok(None);
+        }
+        self.purge_trash();
+        let relative = match path.strip_prefix(&self.workspace) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => PathBuf::from(path.file_name().unwrap_or_default()),
+        };
+        let batch = self.trash_dir().join(chrono::Utc::now().format("%Y-%m-%d_%H%M%S").to_string());
+        let mut dest = batch.join(&relative);
+        // The same file saved twice within a second
+        let mut n = 1;
+        while dest.exists() {
+            dest = batch.join(format!("{}.{}", relative.display(), n));
+            n += 1;
+        }
+        dest.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::copy(path, &dest))
+            .map_err(|e| format!("could not copy {} to the trash: {e}", path.display()))?;
+        Ok(Some(dest))
+    }
+
+    /// Remove trash batches older than the retention period; returns how
+    /// many were removed.
+    pub fn purge_trash(&self) -> usize {
+        let Ok(batches) = std::fs::read_dir(self.trash_dir()) else { return 0 };
+        let mut purged = 0;
+        for batch in batches.flatten() {
+            let expired = batch
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > self.retention);
+            if expired && std::fs::remove_dir_all(batch.path()).is_ok() {
+                purged += 1;
+            }
+        }
+        purged
+    }
+
+    /// `path` relative to the workspace, for tool results.
+    pub fn display(&self, path: &Path) -> String {
+        path.strip_prefix(&self.workspace).unwrap_or(path).display().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = FilePolicy::new(dir.path(), &FilePolicyConfig { max_file_bytes: 100, ..Default::default() });
+        let ws = policy.workspace.clone();
+
+        assert!(policy.check_write(&ws.join("src/main.rs"), 10).is_ok());
+        assert!(policy.check_write(&ws.join("src/main.rs"), 101).unwrap_err().contains("maxFileBytes"));
+        assert!(policy.check_write(&ws.join(".git/config"), 10).unwrap_err().contains("protected"));
+        assert!(policy.check_write(&ws.join("app/.env"), 10).is_err());
+        assert!(policy.check_write(Path::new("/home/me/.ssh/id_ed25519"), 10).is_err());
+        assert!(policy.check_read(&ws.join("certs/server.PEM")).unwrap_err().contains("deniedExtensions"));
+        assert!(policy.check_read(&ws.join(".env")).is_ok());
+
+        let off = FilePolicy::off();
+        assert!(off.check_write(&ws.join(".git/config"), 1 << 30).is_ok());
+        assert_eq!(off.trash(&ws.join("src/main.rs")), Ok(None));
+    }
+
+    #[test]
+    fn test_trash() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = FilePolicy::new(dir.path(), &FilePolicyConfig::default());
+        let file = policy.workspace.join("notes/todo.md");
+        assert_eq!(policy.trash(&file), Ok(None));
+
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(&file, "v1").unwrap();
+        let first = policy.trash(&file).unwrap().unwrap();
+        std::fs::write(&file, "v2").unwrap();
+        let second = policy.trash(&file).unwrap().unwrap();
+        assert!(first.starts_with(policy.trash_dir()) && first.ends_with("notes/todo.md"));
+        assert_ne!(first, second);
+        assert_eq!(std::fs::read_to_string(first).unwrap(), "v1");
+        assert_eq!(std::fs::read_to_string(second).unwrap(), "v2");
+        assert_eq!(policy.purge_trash(), 0);
+    }
+}
@@ -2,10 +2,13 @@ use async_trait::async_trait;
 use serde_json::json;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use super::file_policy::FilePolicy;
+use super::patch::EditHistory;
 use super::Tool;
 
-fn resolve_path(path: &str, allowed_dir: Option<&Path>) -> Result<PathBuf, String> {
+pub(crate) fn resolve_path(path: &str, allowed_dir: Option<&Path>) -> Result<PathBuf, String> {
     let expanded = if path.starts_with("~/") || path.starts_with("~\\") {
         if let Some(home) = dirs::home_dir() {
             home.join(&path[2..])
@@ -16,13 +19,11 @@ fn resolve_path(path: &str, allowed_dir: Option<&Path>) -> Result<PathBuf, Strin
         PathBuf::from(path)
     };
 
-    let resolved = expanded
-        .canonicalize()
-        .unwrap_or_else(|_| expanded.clone());
+    // Works for files that don't exist yet and on Windows (`\\?\` prefixes, case)
+    let resolved = crate::util::normalize_path(&expanded);
 
     if let Some(allowed) = allowed_dir {
-        let allowed_resolved = allowed.canonicalize().unwrap_or_else(|_| allowed.to_path_buf());
-        if !resolved.starts_with(&allowed_resolved) {
+        if !crate::util::is_within(&resolved, allowed) {
             return Err(format!(
                 "Path {} is outside allowed directory {}",
                 path,
@@ -38,11 +39,20 @@ fn resolve_path(path: &str, allowed_dir: Option<&Path>) -> Result<PathBuf, Strin
 
 pub struct ReadFileTool {
     allowed_dir: Option<PathBuf>,
+    policy: Arc<FilePolicy>,
 }
 
 impl ReadFileTool {
     pub fn new(allowed_dir: Option<PathBuf>) -> Self {
-        Self { allowed_dir }
+        Self {
+            allowed_dir,
+            policy: Arc::new(FilePolicy::off()),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: Arc<FilePolicy>) -> Self {
+        self.policy = policy;
+        self
     }
 }
 
@@ -83,6 +93,9 @@ impl Tool for ReadFileTool {
                 if !file_path.is_file() {
                     return format!("Error: Not a file: {path}");
                 }
+                if let Err(e) = self.policy.check_read(&file_path) {
+                    return format!("Error: {e}");
+                }
                 match std::fs::read_to_string(&file_path) {
                     Ok(content) => content,
                     Err(e) => format!("Error reading file: {e}"),
@@ -97,11 +110,27 @@ impl Tool for ReadFileTool {
 
 pub struct WriteFileTool {
     allowed_dir: Option<PathBuf>,
+    history: EditHistory,
+    policy: Arc<FilePolicy>,
 }
 
 impl WriteFileTool {
     pub fn new(allowed_dir: Option<PathBuf>) -> Self {
-        Self { allowed_dir }
+        Self {
+            allowed_dir,
+            history: EditHistory::open_default(),
+            policy: Arc::new(FilePolicy::off()),
+        }
+    }
+
+    pub fn with_history(mut self, history: EditHistory) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: Arc<FilePolicy>) -> Self {
+        self.policy = policy;
+        self
     }
 }
 
@@ -112,7 +141,8 @@ impl Tool for WriteFileTool {
     }
 
     fn description(&self) -> &str {
-        "Write content to a file at the given path. Creates parent directories if needed."
+        "Write content to a file at the given path. Creates parent directories if needed. \
+         An overwritten file is kept in the workspace trash. Set dry_run to check first."
     }
 
     fn parameters(&self) -> serde_json::Value {
@@ -126,6 +156,10 @@ impl Tool for WriteFileTool {
                 "content": {
                     "type": "string",
                     "description": "The content to write"
+                },
+                "dry_run": {
+                    "type": "boolean",
+                    "description": "Only check whether the write is allowed and what it would do (default false)"
                 }
             },
             "required": ["path", "content"]
@@ -142,26 +176,26 @@ impl Tool for WriteFileTool {
             None => return "Error: 'content' parameter is required".to_string(),
         };
 
-        // For write, resolve parent to check allowed_dir
-        let file_path = if path.starts_with("~/") || path.starts_with("~\\") {
-            if let Some(home) = dirs::home_dir() {
-                home.join(&path[2..])
-            } else {
-                PathBuf::from(path)
-            }
-        } else {
-            PathBuf::from(path)
-        };
+        let dry_run = params.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false);
 
-        if let Some(ref allowed) = self.allowed_dir {
-            let allowed_resolved = allowed.canonicalize().unwrap_or_else(|_| allowed.clone());
-            let parent = file_path.parent().unwrap_or(&file_path);
-            if parent.exists() {
-                let parent_resolved = parent.canonicalize().unwrap_or_else(|_| parent.to_path_buf());
-                if !parent_resolved.starts_with(&allowed_resolved) {
-                    return format!("Error: Path {path} is outside allowed directory");
-                }
-            }
+        // The file (and its parents) may not exist yet; resolve_path handles that
+        let file_path = match resolve_path(path, self.allowed_dir.as_deref()) {
+            Ok(p) => p,
+            Err(_) => return format!("Error: Path {path} is outside allowed directory"),
+        };
+        if let Err(e) = self.policy.check_write(&file_path, content.len()) {
+            return format!("Error: {e}");
+        }
+        if dry_run {
+            return match std::fs::metadata(&file_path) {
+                Ok(meta) => format!(
+                    "Dry run, nothing written: would overwrite {} ({} -> {} bytes)",
+                    path,
+                    meta.len(),
+                    content.len()
+                ),
+                Err(_) => format!("Dry run, nothing written: would create {} ({} bytes)", path, content.len()),
+            };
         }
 
         if let Some(parent) = file_path.parent() {
@@ -170,8 +204,25 @@ impl Tool for WriteFileTool {
             }
         }
 
+        let trashed = match self.policy.trash(&file_path) {
+            Ok(trashed) => trashed,
+            Err(e) => return format!("Error: {e}"),
+        };
+        let before = std::fs::read_to_string(&file_path).ok();
         match std::fs::write(&file_path, content) {
-            Ok(_) => format!("Successfully wrote {} bytes to {}", content.len(), path),
+            Ok(_) => {
+                self.history
+                    .record(&file_path, self.name(), before, Some(content.to_string()));
+                match trashed {
+                    Some(copy) => format!(
+                        "Successfully wrote {} bytes to {} (previous version kept in {})",
+                        content.len(),
+                        path,
+                        self.policy.display(&copy)
+                    ),
+                    None => format!("Successfully wrote {} bytes to {}", content.len(), path),
+                }
+            }
             Err(e) => format!("Error writing file: {e}"),
         }
     }
@@ -181,11 +232,27 @@ impl Tool for WriteFileTool {
 
 pub struct EditFileTool {
     allowed_dir: Option<PathBuf>,
+    history: EditHistory,
+    policy: Arc<FilePolicy>,
 }
 
 impl EditFileTool {
     pub fn new(allowed_dir: Option<PathBuf>) -> Self {
-        Self { allowed_dir }
+        Self {
+            allowed_dir,
+            history: EditHistory::open_default(),
+            policy: Arc::new(FilePolicy::off()),
+        }
+    }
+
+    pub fn with_history(mut self, history: EditHistory) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: Arc<FilePolicy>) -> Self {
+        self.policy = policy;
+        self
     }
 }
 
@@ -196,7 +263,8 @@ impl Tool for EditFileTool {
     }
 
     fn description(&self) -> &str {
-        "Edit a file by replacing old_text with new_text. The old_text must exist exactly in the file."
+        "Edit a file by replacing old_text with new_text. The old_text must exist exactly in the file. \
+         Set dry_run to check the edit without writing."
     }
 
     fn parameters(&self) -> serde_json::Value {
@@ -214,6 +282,10 @@ impl Tool for EditFileTool {
                 "new_text": {
                     "type": "string",
                     "description": "The text to replace with"
+                },
+                "dry_run": {
+                    "type": "boolean",
+                    "description": "Only check that the edit applies and is allowed (default false)"
                 }
             },
             "required": ["path", "old_text", "new_text"]
@@ -233,12 +305,16 @@ impl Tool for EditFileTool {
             Some(t) => t,
             None => return "Error: 'new_text' parameter is required".to_string(),
         };
+        let dry_run = params.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false);
 
         match resolve_path(path, self.allowed_dir.as_deref()) {
             Ok(file_path) => {
                 if !file_path.exists() {
                     return format!("Error: File not found: {path}");
                 }
+                if let Err(e) = self.policy.check_read(&file_path) {
+                    return format!("Error: {e}");
+                }
                 match std::fs::read_to_string(&file_path) {
                     Ok(content) => {
                         if !content.contains(old_text) {
@@ -251,8 +327,25 @@ impl Tool for EditFileTool {
                             );
                         }
                         let new_content = content.replacen(old_text, new_text, 1);
-                        match std::fs::write(&file_path, new_content) {
-                            Ok(_) => format!("Successfully edited {path}"),
+                        if let Err(e) = self.policy.check_write(&file_path, new_content.len()) {
+                            return format!("Error: {e}");
+                        }
+                        if dry_run {
+                            return format!(
+                                "Dry run, nothing written: the edit applies to {path} ({} -> {} bytes)",
+                                content.len(),
+                                new_content.len()
+                            );
+                        }
+                        if let Err(e) = self.policy.trash(&file_path) {
+                            return format!("Error: {e}");
+                        }
+                        match std::fs::write(&file_path, &new_content) {
+                            Ok(_) => {
+                                self.history
+                                    .record(&file_path, self.name(), Some(content), Some(new_content));
+                                format!("Successfully edited {path}")
+                            }
                             Err(e) => format!("Error writing file: {e}"),
                         }
                     }
@@ -343,3 +436,37 @@ impl Tool for ListDirTool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FilePolicyConfig;
+
+    async fn write(tool: &WriteFileTool, path: &Path, content: &str, dry_run: bool) -> String {
+        tool.execute(HashMap::from([
+            ("path".to_string(), json!(path.display().to_string())),
+            ("content".to_string(), json!(content)),
+            ("dry_run".to_string(), json!(dry_run)),
+        ]))
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_write_with_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = Arc::new(FilePolicy::new(dir.path(), &FilePolicyConfig::default()));
+        let tool = WriteFileTool::new(Some(dir.path().to_path_buf()))
+            .with_history(EditHistory::new(dir.path().join(".history.json")))
+            .with_policy(policy);
+        let notes = dir.path().join("notes.md");
+
+        assert!(write(&tool, &notes, "v1", true).await.starts_with("Dry run, nothing written: would create"));
+        assert!(!notes.exists());
+        assert!(write(&tool, &notes, "v1", false).await.starts_with("Successfully wrote 2 bytes"));
+        let result = write(&tool, &notes, "v2", false).await;
+        assert!(result.contains("previous version kept in .trash/"), "{result}");
+        assert_eq!(std::fs::read_to_string(&notes).unwrap(), "v2");
+        assert!(write(&tool, &dir.path().join(".git/config"), "x", false).await.starts_with("Error:"));
+        assert!(write(&tool, &dir.path().join("server.key"), "x", false).await.starts_with("Error:"));
+    }
+}
@@ -0,0 +1,104 @@
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use super::Tool;
+use crate::service::cron::CronService;
+use crate::service::followup::{self, FollowupPolicy};
+
+/// Tool for the agent to schedule its own follow-ups in the current chat.
+pub struct FollowupTool {
+    cron_service: Arc<Mutex<CronService>>,
+    policy: FollowupPolicy,
+    context: Arc<Mutex<(String, String)>>, // (channel, chat_id)
+}
+
+impl FollowupTool {
+    pub fn new(cron_service: Arc<Mutex<CronService>>, policy: FollowupPolicy) -> Self {
+        Self {
+            cron_service,
+            policy,
+            context: Arc::new(Mutex::new((String::new(), String::new()))),
+        }
+    }
+
+    pub async fn set_context(&self, channel: &str, chat_id: &str) {
+        let mut ctx = self.context.lock().await;
+        *ctx = (channel.to_string(), chat_id.to_string());
+    }
+}
+
+#[async_trait]
+impl Tool for FollowupTool {
+    fn name(&self) -> &str {
+        "schedule_followup"
+    }
+
+    fn description(&self) -> &str {
+        "Schedule a proactive follow-up in this chat, e.g. to check back on a build or a task later. \
+         When it is due you get the note back and your reply is sent to the user. \
+         Follow-ups are held during the user's quiet hours and limited per day."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "note": {
+                    "type": "string",
+                    "description": "What to check or tell the user, written for your future self"
+                },
+                "in_minutes": {
+                    "type": "integer",
+                    "description": "Minutes from now"
+                },
+                "at": {
+                    "type": "string",
+                    "description": "User's local time, 'YYYY-MM-DD HH:MM' or 'HH:MM' (next occurrence)"
+                }
+            },
+            "required": ["note"]
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        let note = params.get("note").and_then(|v| v.as_str()).unwrap_or("").trim();
+        if note.is_empty() {
+            return "Error: note is required".to_string();
+        }
+
+        let ctx = self.context.lock().await;
+        if ctx.0.is_empty() || ctx.1.is_empty() {
+            return "Error: no session context (channel/chat_id)".to_string();
+        }
+        let (channel, chat_id) = ctx.clone();
+        drop(ctx);
+
+        let now = crate::util::now_ms();
+        let wanted = if let Some(minutes) = params.get("in_minutes").and_then(|v| v.as_u64()) {
+            now + minutes.max(1) * 60_000
+        } else if let Some(at) = params.get("at").and_then(|v| v.as_str()) {
+            match self.policy.parse_at(at, now) {
+                Some(ms) if ms > now => ms,
+                Some(_) => return format!("Error: {} is in the past", at),
+                None => return format!("Error: could not parse time '{}'", at),
+            }
+        } else {
+            return "Error: either in_minutes or at is required".to_string();
+        };
+
+        let mut cron = self.cron_service.lock().await;
+        let at = match self.policy.plan(&cron, wanted) {
+            Ok(at) => at,
+            Err(e) => return format!("Not scheduled: {}", e),
+        };
+        let job = followup::schedule(&mut cron, note, at, &channel, &chat_id);
+        let mut reply = format!("Follow-up scheduled for {} (id: {})", self.policy.format(at), job.id);
+        if at != wanted {
+            reply.push_str(", moved out of the user's quiet hours");
+        }
+        reply
+    }
+}
@@ -0,0 +1,361 @@
+//! Forwarding a message or summary to another channel or contact
+//! ("LINEで妻に送って").
+//!
+//! The tool only previews until the agent calls it again with
+//! `mode: "confirm"`, and that only sends what was previewed in an earlier
+//! turn when the user's own reply since then agrees ("yes", "はい",
+//! "送って"); the model cannot confirm on their behalf. Only the session's
+//! contacts and the sender's linked identities (`channels.links`) can be
+//! written to.
+//! Delivery goes through the outbound bus to the `ChannelManager`; each
+//! [`Forward`] is then recorded in both the sending and the receiving session.
+
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+
+use super::Tool;
+use crate::session::contacts::{normalize_identity, Contact};
+use crate::session::store::SessionStore;
+use crate::types::OutboundMessage;
+
+/// Outbound metadata key naming the session a forwarded message came from.
+pub const FORWARDED_FROM_KEY: &str = "forwardedFrom";
+
+/// A message sent from one session to another channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Forward {
+    /// Session key of the conversation that asked for the forward.
+    pub from: String,
+    /// Contact name, when the target was given as one.
+    pub contact: Option<String>,
+    pub channel: String,
+    pub chat_id: String,
+    pub content: String,
+}
+
+impl Forward {
+    /// Session key of the receiving chat.
+    pub fn target_key(&self) -> String {
+        format!("{}:{}", self.channel, self.chat_id)
+    }
+
+    /// "Yuki (line:U1)" or "line:U1".
+    pub fn target_label(&self) -> String {
+        match &self.contact {
+            Some(name) => format!("{} ({})", name, self.target_key()),
+            None => self.target_key(),
+        }
+    }
+
+    /// Note the cross-post in both sessions: the sender's history says what
+    /// went where, the receiver's history holds the message itself.
+    pub fn record(&self, sessions: &mut dyn SessionStore) {
+        let note = format!("[Forwarded to {}] {}", self.target_label(), self.content);
+        sessions.get_or_create(&self.from).add_message("assistant", &note);
+        sessions.save_by_key(&self.from);
+        self.record_received(sessions);
+    }
+
+    /// Note the message in the receiver's history only, for senders in
+    /// incognito.
+    pub fn record_received(&self, sessions: &mut dyn SessionStore) {
+        let target = self.target_key();
+        let origin = self.from.split(':').next().unwrap_or_default();
+        sessions.get_or_create(&target).add_message_from_channel("assistant", &self.content, origin);
+        sessions.save_by_key(&target);
+    }
+}
+
+/// The addresses `channels.links` links to `address`, itself included.
+pub fn linked_identities(links: &HashMap<String, Vec<String>>, address: &str) -> Vec<String> {
+    links
+        .values()
+        .filter(|addresses| addresses.iter().any(|a| a == address))
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+/// Resolve `to` — a contact name or alias, or a `channel:id` — to a
+/// delivery target. `channel` picks among a contact's identities
+/// (their first one otherwise). A `channel:id` must belong to a contact or
+/// be one of the `linked` identities. Returns `(contact name, channel, chat_id)`.
+pub fn resolve_target(
+    contacts: &[Contact],
+    linked: &[String],
+    to: &str,
+    channel: Option<&str>,
+) -> Result<(Option<String>, String, String), String> {
+    let channel = channel.map(str::trim).filter(|c| !c.is_empty()).map(|c| {
+        normalize_identity(&format!("{}:x", c))
+            .and_then(|i| i.split_once(':').map(|(c, _)| c.to_string()))
+            .unwrap_or_else(|| c.to_lowercase())
+    });
+    if let Some(contact) = contacts.iter().find(|c| c.answers_to(to)) {
+        let mut targets = contact.targets();
+        let target = match &channel {
+            Some(wanted) => targets.find(|(c, _)| c == wanted),
+            None => targets.next(),
+        };
+        return match target {
+            Some((c, id)) => Ok((Some(contact.name.clone()), c.to_string(), id.to_string())),
+            None => Err(match channel {
+                Some(wanted) => format!("{} has no {} identity (known: {})", contact.name, wanted, contact.identities.join(", ")),
+                None => format!("{} has no channel identities; add one with /contacts link", contact.name),
+            }),
+        };
+    }
+    let identity = normalize_identity(to).ok_or_else(|| format!("'{}' is neither a contact nor a channel:id", to))?;
+    let (c, id) = identity.split_once(':').unwrap_or_default();
+    let owner = contacts.iter().find(|contact| contact.identities.contains(&identity)).map(|contact| contact.name.clone());
+    if owner.is_none() && !linked.iter().any(|l| normalize_identity(l).as_ref() == Some(&identity)) {
+        return Err(format!("{} is not one of your contacts or linked channels; add it with /contacts first", to));
+    }
+    Ok((owner, c.to_string(), id.to_string()))
+}
+
+/// Whether `reply` is the user agreeing to send, e.g. "yes", "OK!" or
+/// "はい、送って".
+fn agrees(reply: &str) -> bool {
+    const YES: &[&str] = &[
+        "yes", "y", "yeah", "yep", "ok", "okay", "sure", "send", "sendit", "yessend", "yessendit", "yesplease",
+        "okaysend", "goahead", "confirm", "はい", "うん", "ええ", "ok送って", "はい送って", "送って", "送ってください",
+        "はい送ってください", "お願い", "お願いします", "はいお願いします", "いいよ", "いいです", "送信", "送信して",
+    ];
+    let reply: String = reply
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_ascii_punctuation() && !"、。！？…ー〜".contains(*c))
+        .flat_map(char::to_lowercase)
+        .collect();
+    YES.contains(&reply.as_str())
+}
+
+#[derive(Default)]
+struct ForwardContext {
+    channel: String,
+    chat_id: String,
+    /// What the user said this turn (empty for system turns).
+    user_message: String,
+    contacts: Vec<Contact>,
+    linked: Vec<String>,
+    /// Counts `set_context` calls, to tell previews from earlier turns apart.
+    turn: u64,
+    /// Last preview per sending session, with the turn it was shown in.
+    previews: HashMap<String, (Forward, u64)>,
+}
+
+/// Tool for the agent to send a message to another channel or contact.
+pub struct ForwardMessageTool {
+    outbound_tx: mpsc::Sender<OutboundMessage>,
+    context: Arc<Mutex<ForwardContext>>,
+    sent: Arc<Mutex<Vec<Forward>>>,
+}
+
+impl ForwardMessageTool {
+    pub fn new(outbound_tx: mpsc::Sender<OutboundMessage>) -> Self {
+        Self {
+            outbound_tx,
+            context: Arc::new(Mutex::new(ForwardContext::default())),
+            sent: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Current chat, what the user said in it (empty when the turn is not
+    /// theirs), the contacts its session knows and the identities linked to it.
+    pub async fn set_context(&self, channel: &str, chat_id: &str, user_message: &str, contacts: Vec<Contact>, linked: Vec<String>) {
+        let mut ctx = self.context.lock().await;
+        ctx.channel = channel.to_string();
+        ctx.chat_id = chat_id.to_string();
+        ctx.user_message = user_message.to_string();
+        ctx.contacts = contacts;
+        ctx.linked = linked;
+        ctx.turn += 1;
+    }
+
+    /// Forwards delivered since the last call, for recording in sessions.
+    pub async fn take_sent(&self) -> Vec<Forward> {
+        std::mem::take(&mut *self.sent.lock().await)
+    }
+}
+
+#[async_trait]
+impl Tool for ForwardMessageTool {
+    fn name(&self) -> &str {
+        "forward_message"
+    }
+
+    fn description(&self) -> &str {
+        "Send a message or summary to another chat: a contact by name (\"妻\", \"Yuki\") or one of the user's \
+         own linked channels as channel:id (line:U123, telegram:42). Always call with mode='preview' first, show \
+         the user the exact text and recipient, and ask them to reply yes. Call with mode='confirm' only after \
+         their reply agrees, with the same recipient and text; it sends nothing unless the user's own reply to \
+         the preview says yes."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "to": {
+                    "type": "string",
+                    "description": "Contact name or alias, or channel:id"
+                },
+                "content": {
+                    "type": "string",
+                    "description": "The text to send, written for the recipient"
+                },
+                "channel": {
+                    "type": "string",
+                    "description": "Optional: which of the contact's channels to use (line, telegram, email, ...)"
+                },
+                "mode": {
+                    "type": "string",
+                    "enum": ["preview", "confirm"],
+                    "description": "'preview' — resolve the recipient without sending (default). 'confirm' — send what was previewed, once the user replied yes."
+                }
+            },
+            "required": ["to", "content"]
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        let to = params.get("to").and_then(|v| v.as_str()).unwrap_or("").trim();
+        let content = params.get("content").and_then(|v| v.as_str()).unwrap_or("").trim();
+        if to.is_empty() || content.is_empty() {
+            return "Error: 'to' and 'content' are required".to_string();
+        }
+        let confirmed = params.get("mode").and_then(|v| v.as_str()) == Some("confirm");
+
+        let mut ctx = self.context.lock().await;
+        if ctx.channel.is_empty() || ctx.chat_id.is_empty() {
+            return "Error: no session context (channel/chat_id)".to_string();
+        }
+        let target = resolve_target(&ctx.contacts, &ctx.linked, to, params.get("channel").and_then(|v| v.as_str()));
+        let from = format!("{}:{}", ctx.channel, ctx.chat_id);
+
+        let (contact, channel, chat_id) = match target {
+            Ok(target) => target,
+            Err(e) => return format!("Error: {}", e),
+        };
+        let forward = Forward { from, contact, channel, chat_id, content: content.to_string() };
+        if forward.target_key() == forward.from {
+            return "Error: that is the current chat; reply normally instead".to_string();
+        }
+        if !confirmed {
+            let preview = format!(
+                "Preview (not sent) — to {}:\n---\n{}\n---\nShow this to the user and ask them to reply yes; call again \
+                 with mode='confirm' once they have.",
+                forward.target_label(),
+                forward.content
+            );
+            let turn = ctx.turn;
+            ctx.previews.insert(forward.from.clone(), (forward, turn));
+            return preview;
+        }
+        let previewed = matches!(ctx.previews.get(&forward.from), Some((shown, turn)) if *shown == forward && *turn < ctx.turn);
+        if !previewed || !agrees(&ctx.user_message) {
+            return "Error: Not sent. The user has to see this exact preview (mode='preview') and reply yes to it \
+                    first; ask them instead of confirming for them"
+                .to_string();
+        }
+        ctx.previews.remove(&forward.from);
+        drop(ctx);
+
+        let mut msg = OutboundMessage::new(&forward.channel, &forward.chat_id, &forward.content);
+        msg.metadata.insert(FORWARDED_FROM_KEY.to_string(), json!(forward.from));
+        if let Err(e) = self.outbound_tx.send(msg).await {
+            return format!("Error sending message: {e}");
+        }
+        let reply = format!("Forwarded to {}", forward.target_label());
+        self.sent.lock().await.push(forward);
+        reply
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Session;
+
+    fn contacts() -> Vec<Contact> {
+        let mut s = Session::new("line:U0");
+        let ids = |l: &[&str]| l.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        s.upsert_contact("Yuki", &ids(&["telegram:42", "line:U1"]), &ids(&["妻"]), None).unwrap();
+        s.upsert_contact("Ken", &[], &[], None).unwrap();
+        s.contacts()
+    }
+
+    #[test]
+    fn test_resolve_target() {
+        let book = contacts();
+        let linked = ["discord:9".to_string()];
+        let resolve = |to: &str, channel: Option<&str>| resolve_target(&book, &linked, to, channel);
+        assert_eq!(resolve("妻", None), Ok((Some("Yuki".into()), "telegram".into(), "42".into())));
+        assert_eq!(resolve("Yuki", Some("LINE")), Ok((Some("Yuki".into()), "line".into(), "U1".into())));
+        assert_eq!(resolve("tg:42", None), Ok((Some("Yuki".into()), "telegram".into(), "42".into())));
+        assert_eq!(resolve("discord:9", None), Ok((None, "discord".into(), "9".into())));
+        assert!(resolve("telegram:666", None).unwrap_err().contains("not one of your contacts"));
+        assert!(resolve("Yuki", Some("email")).unwrap_err().contains("no email identity"));
+        assert!(resolve("Ken", None).is_err());
+        assert!(resolve("nobody", None).is_err());
+    }
+
+    #[test]
+    fn test_linked_identities() {
+        let links = HashMap::from([
+            ("yuki".to_string(), vec!["line:U1".to_string(), "telegram:42".to_string()]),
+            ("ken".to_string(), vec!["line:U2".to_string()]),
+        ]);
+        assert_eq!(linked_identities(&links, "telegram:42"), vec!["line:U1".to_string(), "telegram:42".to_string()]);
+        assert!(linked_identities(&links, "line:U9").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_preview_then_confirm() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let tool = ForwardMessageTool::new(tx);
+        tool.set_context("telegram", "7", "妻に今夜は遅くなるって送って", contacts(), Vec::new()).await;
+        let params = |mode: &str| {
+            HashMap::from([
+                ("to".to_string(), json!("妻")),
+                ("channel".to_string(), json!("line")),
+                ("content".to_string(), json!("今夜は遅くなります")),
+                ("mode".to_string(), json!(mode)),
+            ])
+        };
+
+        assert!(tool.execute(params("preview")).await.starts_with("Preview (not sent) — to Yuki (line:U1)"));
+        assert!(rx.try_recv().is_err());
+        // The model can't confirm in the turn it previewed, nor without a yes
+        assert!(tool.execute(params("confirm")).await.starts_with("Error: Not sent"));
+        tool.set_context("telegram", "7", "やっぱりやめて", contacts(), Vec::new()).await;
+        assert!(tool.execute(params("confirm")).await.starts_with("Error: Not sent"));
+        assert!(rx.try_recv().is_err());
+
+        tool.set_context("telegram", "7", "はい、送って！", contacts(), Vec::new()).await;
+        assert_eq!(tool.execute(params("confirm")).await, "Forwarded to Yuki (line:U1)");
+        let out = rx.try_recv().unwrap();
+        assert_eq!((out.channel.as_str(), out.chat_id.as_str()), ("line", "U1"));
+        assert_eq!(out.metadata[FORWARDED_FROM_KEY], json!("telegram:7"));
+
+        let sent = tool.take_sent().await;
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].target_key(), "line:U1");
+        assert!(tool.take_sent().await.is_empty());
+        // The preview is used up
+        assert!(tool.execute(params("confirm")).await.starts_with("Error: Not sent"));
+    }
+
+    #[test]
+    fn test_agrees() {
+        assert!(agrees("Yes!"));
+        assert!(agrees(" ok "));
+        assert!(agrees("はい、お願いします。"));
+        assert!(!agrees("no"));
+        assert!(!agrees("yes but change the time"));
+        assert!(!agrees(""));
+    }
+}
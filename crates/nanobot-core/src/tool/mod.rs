@@ -1,16 +1,42 @@
 pub mod filesystem;
+pub mod file_policy;
 pub mod shell;
+pub mod shell_session;
 pub mod web;
+pub mod crawl;
 pub mod message;
+pub mod forward;
 pub mod spawn;
 pub mod cron_tool;
+pub mod followup;
+pub mod code;
+#[cfg(target_os = "linux")]
+pub mod confine;
+pub mod patch;
+pub mod quality;
+pub mod search;
+pub mod search_provider;
+pub mod extract;
+pub mod expense;
+pub mod youtube;
+pub mod ocr;
+pub mod stats;
+pub mod policy;
+#[cfg(feature = "code-intel")]
+pub mod symbols;
 
 use async_trait::async_trait;
 use dashmap::DashMap;
+use futures::FutureExt;
 use serde_json::json;
 use std::collections::HashMap;
-use std::sync::Arc;
-use tracing::debug;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, RwLock};
+use tracing::{debug, error, warn};
+
+use self::policy::{ApprovalRequest, Approver, Decision, ToolPolicy};
+
+use crate::service::supervisor::panic_message;
 
 /// Trait for agent tools.
 #[async_trait]
@@ -47,15 +73,29 @@ impl<T: Tool + ?Sized> ToolSchema for T {}
 /// Lock-free tool registry using DashMap.
 pub struct ToolRegistry {
     tools: DashMap<String, Arc<dyn Tool>>,
+    policy: RwLock<Arc<ToolPolicy>>,
+    approver: RwLock<Option<Approver>>,
 }
 
 impl ToolRegistry {
     pub fn new() -> Self {
         Self {
             tools: DashMap::new(),
+            policy: RwLock::new(Arc::new(ToolPolicy::default())),
+            approver: RwLock::new(None),
         }
     }
 
+    /// Check every call against `policy` before it runs.
+    pub fn set_policy(&self, policy: ToolPolicy) {
+        *self.policy.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(policy);
+    }
+
+    /// Ask `approver` about calls the policy wants confirmed.
+    pub fn set_approver(&self, approver: Approver) {
+        *self.approver.write().unwrap_or_else(|e| e.into_inner()) = Some(approver);
+    }
+
     /// Register a tool.
     pub fn register(&self, tool: Arc<dyn Tool>) {
         self.tools.insert(tool.name().to_string(), tool);
@@ -95,8 +135,48 @@ impl ToolRegistry {
             None => return format!("Error: Tool '{name}' not found"),
         };
 
+        let policy = self.policy.read().unwrap_or_else(|e| e.into_inner()).clone();
+        let decision = policy.decide(name, &params);
+        let approver = self.approver.read().unwrap_or_else(|e| e.into_inner()).clone();
+        match (decision, approver) {
+            (Decision::Allow, _) => {}
+            (Decision::Deny, _) => {
+                warn!("Tool {} denied by policy", name);
+                return format!("Error: Tool '{name}' is disabled by tools.policy");
+            }
+            (Decision::Ask(_), None) => {
+                return format!("Error: Tool '{name}' needs the user's approval, which cannot be asked for here");
+            }
+            (Decision::Confirm(reason), None) if !policy.refuses_unconfirmed() => {
+                debug!("Tool {} {}; nobody to confirm, running it", name, reason);
+            }
+            (Decision::Confirm(reason), None) => {
+                warn!("Tool {} refused: {} and nobody can confirm", name, reason);
+                return format!(
+                    "Error: This '{name}' call {reason} and needs the user's confirmation, which cannot be asked for here \
+                     (tools.policy.confirmDestructive)"
+                );
+            }
+            (Decision::Ask(reason) | Decision::Confirm(reason), Some(approver)) => {
+                let request = ApprovalRequest { tool: name.to_string(), params: params.clone(), reason: reason.clone() };
+                if !approver(request).await {
+                    debug!("Tool {} declined by the user", name);
+                    return format!(
+                        "Error: The user declined to run '{name}' ({reason}); ask them how to proceed instead of retrying"
+                    );
+                }
+            }
+        }
+
         debug!("Executing tool: {}", name);
-        tool.execute(params).await
+        match AssertUnwindSafe(tool.execute(params)).catch_unwind().await {
+            Ok(result) => result,
+            Err(payload) => {
+                let message = panic_message(payload.as_ref());
+                error!("Tool {} panicked: {}", name, message);
+                format!("Error: Tool '{name}' crashed: {message}")
+            }
+        }
     }
 
     /// Execute multiple tools concurrently (join_all).
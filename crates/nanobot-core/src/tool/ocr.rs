@@ -0,0 +1,321 @@
+//! `ocr`: text from images (screenshots, photographed documents).
+//!
+//! Recognition runs the `tesseract` CLI when it is installed; when it isn't,
+//! or finds nothing, a vision model transcribes the image instead. The same
+//! [`Ocr`] also annotates image attachments of inbound messages (see
+//! [`Ocr::annotate_media`]) so a photo of a letter can be answered like text.
+
+use async_trait::async_trait;
+use base64::Engine as _;
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+use super::Tool;
+use crate::config::OcrConfig;
+use crate::util::{self, http::{self, ClientClass}};
+
+/// Images larger than this aren't read.
+const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+const IMAGE_EXTENSIONS: &[&str] = &[".png", ".jpg", ".jpeg", ".gif", ".webp", ".bmp", ".tif", ".tiff"];
+
+/// Recognized text and the engine that produced it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrText {
+    pub text: String,
+    pub engine: &'static str,
+}
+
+/// tesseract language codes (`jpn+eng`) for hints like `ja`, `en`, `zh-TW`.
+pub fn tesseract_languages(hints: &[String]) -> String {
+    let codes: Vec<&str> = hints
+        .iter()
+        .map(|h| match h.to_lowercase().as_str() {
+            "ja" | "jp" | "japanese" => "jpn",
+            "en" | "english" => "eng",
+            "zh" | "zh-cn" | "zh-hans" | "chinese" => "chi_sim",
+            "zh-tw" | "zh-hant" => "chi_tra",
+            "ko" | "korean" => "kor",
+            "fr" => "fra",
+            "de" => "deu",
+            "es" => "spa",
+            "vi" => "vie",
+            "th" => "tha",
+            _ => h.as_str(),
+        })
+        .collect();
+    if codes.is_empty() {
+        "eng".to_string()
+    } else {
+        codes.join("+")
+    }
+}
+
+/// Whether `name` (a path or URL) looks like an image.
+pub fn is_image(name: &str) -> bool {
+    let path = name.split(['?', '#']).next().unwrap_or(name).to_lowercase();
+    IMAGE_EXTENSIONS.iter().any(|ext| path.ends_with(ext))
+}
+
+fn mime_type(bytes: &[u8]) -> &'static str {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => "image/png",
+        [0xFF, 0xD8, ..] => "image/jpeg",
+        [b'G', b'I', b'F', ..] => "image/gif",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+        [b'B', b'M', ..] => "image/bmp",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Drop trailing spaces and runs of blank lines; with `layout` the leading
+/// indentation and column spacing are kept.
+fn clean(text: &str, layout: bool) -> String {
+    let mut out: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        let line = if layout { line } else { line.trim_start() };
+        if line.is_empty() && out.last().is_none_or(|l| l.is_empty()) {
+            continue;
+        }
+        out.push(line);
+    }
+    out.join("\n").trim_end().to_string()
+}
+
+async fn tesseract(image: &[u8], languages: &str, layout: bool) -> Result<String, String> {
+    let mut cmd = tokio::process::Command::new("tesseract");
+    cmd.args(["stdin", "stdout", "-l", languages]);
+    if layout {
+        // Single column of variable-size text, keeping spaces between words
+        cmd.args(["--psm", "4", "-c", "preserve_interword_spaces=1"]);
+    }
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("tesseract not available: {}", e))?;
+    let mut stdin = child.stdin.take().ok_or("tesseract stdin unavailable")?;
+    stdin.write_all(image).await.map_err(|e| e.to_string())?;
+    drop(stdin);
+    let output = tokio::time::timeout(Duration::from_secs(60), child.wait_with_output())
+        .await
+        .map_err(|_| "tesseract timed out".to_string())?
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("tesseract failed: {}", stderr.lines().last().unwrap_or("").trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+async fn vision(image: &[u8], hints: &[String], layout: bool) -> Result<String, String> {
+    let data_url = format!(
+        "data:{};base64,{}",
+        mime_type(image),
+        base64::engine::general_purpose::STANDARD.encode(image)
+    );
+    let mut prompt = "Transcribe all text in this image exactly as written. Output only the text, \
+                      without commentary or translation."
+        .to_string();
+    if layout {
+        prompt.push_str(" Keep the line breaks, and render tables as markdown tables.");
+    }
+    if !hints.is_empty() {
+        prompt.push_str(&format!(" Expected languages: {}.", hints.join(", ")));
+    }
+    let reply = crate::service::integrations::execute_image_analyze(&data_url, &prompt).await;
+    match reply.strip_prefix("[IMAGE_ANALYZE_ERROR]") {
+        Some(err) => Err(format!("vision model: {}", err.trim())),
+        None => Ok(reply),
+    }
+}
+
+/// OCR settings shared by the tool and the media pipeline.
+pub struct Ocr {
+    workspace: PathBuf,
+    languages: Vec<String>,
+    vision_fallback: bool,
+}
+
+impl Ocr {
+    pub fn new(workspace: PathBuf, config: &OcrConfig) -> Self {
+        Self {
+            workspace,
+            languages: config.languages.clone(),
+            vision_fallback: config.vision_fallback,
+        }
+    }
+
+    /// Recognize text in `image`; `languages` overrides the configured hints.
+    pub async fn recognize(&self, image: &[u8], languages: Option<&[String]>, layout: bool) -> Result<OcrText, String> {
+        let hints = languages.unwrap_or(&self.languages);
+        let tesseract_error = match tesseract(image, &tesseract_languages(hints), layout).await {
+            Ok(text) if text.trim().chars().count() >= 3 => {
+                return Ok(OcrText { text: clean(&text, layout), engine: "tesseract" })
+            }
+            Ok(_) => "tesseract found no text".to_string(),
+            Err(e) => e,
+        };
+        if !self.vision_fallback {
+            return Err(tesseract_error);
+        }
+        tracing::debug!("ocr: {}; falling back to a vision model", tesseract_error);
+        let text = vision(image, hints, layout).await.map_err(|e| format!("{}; {}", tesseract_error, e))?;
+        Ok(OcrText { text: clean(&text, layout), engine: "vision" })
+    }
+
+    /// Read an image from a URL, or from a path that must lie inside the
+    /// workspace unless `trusted` (paths channels saved attachments to).
+    pub async fn load(&self, source: &str, trusted: bool) -> Result<Vec<u8>, String> {
+        let bytes = if source.starts_with("http://") || source.starts_with("https://") {
+            let response = http::client_for(ClientClass::General)
+                .get(source)
+                .timeout(Duration::from_secs(30))
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            if !response.status().is_success() {
+                return Err(format!("HTTP {}", response.status()));
+            }
+            response.bytes().await.map_err(|e| e.to_string())?.to_vec()
+        } else {
+            let path = Path::new(source);
+            let path = if path.is_absolute() { path.to_path_buf() } else { self.workspace.join(path) };
+            if !trusted && !util::is_within(&path, &self.workspace) {
+                return Err(format!("{} is outside the workspace", source));
+            }
+            tokio::fs::read(&path).await.map_err(|e| format!("{}: {}", source, e))?
+        };
+        if bytes.len() > MAX_IMAGE_BYTES {
+            return Err(format!("image is larger than {} MB", MAX_IMAGE_BYTES / 1024 / 1024));
+        }
+        Ok(bytes)
+    }
+
+    /// `content` with the text of every image in `media` appended. Images
+    /// without text, or that fail to load, are left out.
+    pub async fn annotate_media(&self, content: &str, media: &[String]) -> String {
+        let mut out = content.to_string();
+        for item in media.iter().filter(|m| is_image(m)) {
+            let text = match self.load(item, true).await {
+                Ok(bytes) => self.recognize(&bytes, None, true).await,
+                Err(e) => Err(e),
+            };
+            match text {
+                Ok(ocr) if !ocr.text.is_empty() => {
+                    let name = item.rsplit(['/', '\\']).next().unwrap_or(item);
+                    out.push_str(&format!("\n\n[Text in attached image {}]\n{}", name, ocr.text));
+                }
+                Ok(_) => {}
+                Err(e) => tracing::debug!("ocr: skipping attachment {}: {}", item, e),
+            }
+        }
+        out
+    }
+}
+
+/// Extract text from an image file or URL.
+pub struct OcrTool {
+    ocr: Arc<Ocr>,
+}
+
+impl OcrTool {
+    pub fn new(ocr: Arc<Ocr>) -> Self {
+        Self { ocr }
+    }
+}
+
+#[async_trait]
+impl Tool for OcrTool {
+    fn name(&self) -> &str {
+        "ocr"
+    }
+
+    fn description(&self) -> &str {
+        "Extract the text of an image (screenshot, photo of a document or receipt) from a workspace path or URL."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string", "description": "Image file in the workspace"},
+                "url": {"type": "string", "description": "Image URL"},
+                "languages": {"type": "array", "items": {"type": "string"}, "description": "Languages in the image, e.g. [\"ja\", \"en\"]"},
+                "layout": {"type": "boolean", "description": "Keep line breaks and column spacing (default true)"}
+            }
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        let source = params.get("path").or_else(|| params.get("url")).and_then(|v| v.as_str());
+        let Some(source) = source.filter(|s| !s.is_empty()) else {
+            return json!({"error": "'path' or 'url' is required"}).to_string();
+        };
+        let languages: Option<Vec<String>> = params.get("languages").and_then(|v| match v {
+            serde_json::Value::Array(items) => Some(items.iter().filter_map(|i| i.as_str().map(str::to_string)).collect()),
+            serde_json::Value::String(s) => Some(s.split([',', '+']).map(|l| l.trim().to_string()).collect()),
+            _ => None,
+        });
+        let layout = params.get("layout").and_then(|v| v.as_bool()).unwrap_or(true);
+
+        let result = match self.ocr.load(source, false).await {
+            Ok(bytes) => self.ocr.recognize(&bytes, languages.as_deref(), layout).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(ocr) => json!({"source": source, "engine": ocr.engine, "text": ocr.text}).to_string(),
+            Err(e) => json!({"error": e, "source": source}).to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tesseract_languages() {
+        let hints = |h: &[&str]| h.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(tesseract_languages(&hints(&["ja", "en"])), "jpn+eng");
+        assert_eq!(tesseract_languages(&hints(&["zh-TW", "kor"])), "chi_tra+kor");
+        assert_eq!(tesseract_languages(&[]), "eng");
+    }
+
+    #[test]
+    fn test_is_image_and_mime() {
+        assert!(is_image("/tmp/line/photo.JPG"));
+        assert!(is_image("https://cdn.example.com/a.png?sig=1"));
+        assert!(!is_image("notes.pdf"));
+        assert_eq!(mime_type(&[0x89, b'P', b'N', b'G', 0x0D]), "image/png");
+        assert_eq!(mime_type(&[0xFF, 0xD8, 0xFF]), "image/jpeg");
+    }
+
+    #[test]
+    fn test_clean() {
+        let raw = "  Invoice   No. 12  \n\n\n  Total     ¥3,000\n\n";
+        assert_eq!(clean(raw, true), "  Invoice   No. 12\n\n  Total     ¥3,000");
+        assert_eq!(clean(raw, false), "Invoice   No. 12\n\nTotal     ¥3,000");
+    }
+
+    #[tokio::test]
+    async fn test_load_stays_in_workspace() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ocr = Ocr::new(tmp.path().join("ws"), &OcrConfig::default());
+        std::fs::create_dir_all(tmp.path().join("ws")).unwrap();
+        std::fs::write(tmp.path().join("ws/scan.png"), b"png").unwrap();
+        std::fs::write(tmp.path().join("secret.png"), b"png").unwrap();
+
+        assert_eq!(ocr.load("scan.png", false).await.unwrap(), b"png");
+        assert!(ocr.load("../secret.png", false).await.unwrap_err().contains("outside the workspace"));
+        // Channel attachments may live elsewhere
+        let outside = tmp.path().join("secret.png");
+        assert!(ocr.load(outside.to_str().unwrap(), true).await.is_ok());
+    }
+}
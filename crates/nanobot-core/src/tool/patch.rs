@@ -0,0 +1,762 @@
+//! Unified-diff editing with dry-run preview and an undo history.
+//!
+//! [`ApplyPatchTool`] parses a (possibly multi-file) unified diff, checks
+//! every hunk against the current file contents and writes nothing unless all
+//! hunks apply. Hunks are located like `patch` does: at the stated line if the
+//! context matches there, otherwise at the nearest place it does.
+//!
+//! Every write is recorded in [`EditHistory`] (`<data dir>/edits/history.json`)
+//! with the file contents before and after, so `chatweb undo` can restore them.
+//! With a [`FilePolicy`], protected files are refused up front and changed
+//! or deleted files are copied to the workspace trash first.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::warn;
+
+use super::file_policy::FilePolicy;
+use super::Tool;
+use crate::util::{now_ms, read_json};
+
+/// Edits kept in the history; older ones can no longer be undone.
+pub const MAX_EDITS: usize = 100;
+
+/// Files larger than this are edited without an undo snapshot.
+const MAX_SNAPSHOT_BYTES: usize = 1024 * 1024;
+
+// ====== Diff parsing ======
+
+#[derive(Debug, Clone, PartialEq)]
+enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+#[derive(Debug, Clone)]
+struct Hunk {
+    /// 1-based line in the old file, as stated in the `@@` header.
+    old_start: usize,
+    lines: Vec<HunkLine>,
+    /// `\ No newline at end of file` followed the last added/context line.
+    no_newline_at_end: bool,
+}
+
+impl Hunk {
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|l| match l {
+                HunkLine::Context(s) | HunkLine::Remove(s) => Some(s.as_str()),
+                HunkLine::Add(_) => None,
+            })
+            .collect()
+    }
+
+    fn new_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|l| match l {
+                HunkLine::Context(s) | HunkLine::Add(s) => Some(s.as_str()),
+                HunkLine::Remove(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// The hunks for one file. `None` paths mean `/dev/null` (file created or deleted).
+#[derive(Debug, Clone)]
+struct FilePatch {
+    old_path: Option<String>,
+    new_path: Option<String>,
+    hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    fn path(&self) -> &str {
+        self.new_path.as_deref().or(self.old_path.as_deref()).unwrap_or_default()
+    }
+}
+
+fn parse_path(header: &str) -> Option<String> {
+    // "--- a/src/lib.rs\t2024-01-01 ..." -> "src/lib.rs"
+    let path = header.split('\t').next().unwrap_or_default().trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// `(old start, old count, new count)` of a hunk header; an omitted count is 1.
+fn parse_hunk_header(line: &str) -> Result<(usize, usize, usize), String> {
+    // "@@ -12,5 +12,7 @@ fn main()"
+    let malformed = || format!("malformed hunk header: {line}");
+    let range = |prefix: char| -> Result<(usize, usize), String> {
+        let part = line
+            .trim_start_matches('@')
+            .split_whitespace()
+            .find_map(|part| part.strip_prefix(prefix))
+            .ok_or_else(malformed)?;
+        let (start, count) = part.split_once(',').unwrap_or((part, "1"));
+        Ok((start.parse().map_err(|_| malformed())?, count.parse().map_err(|_| malformed())?))
+    };
+    let (old_start, old_count) = range('-')?;
+    let (_, new_count) = range('+')?;
+    Ok((old_start, old_count, new_count))
+}
+
+fn parse_patch(patch: &str) -> Result<Vec<FilePatch>, String> {
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut lines = patch.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if let Some(old) = line.strip_prefix("--- ") {
+            let new = lines
+                .next()
+                .and_then(|l| l.strip_prefix("+++ "))
+                .ok_or_else(|| format!("expected '+++' after '{line}'"))?;
+            files.push(FilePatch {
+                old_path: parse_path(old),
+                new_path: parse_path(new),
+                hunks: Vec::new(),
+            });
+        } else if line.starts_with("@@") {
+            let file = files
+                .last_mut()
+                .ok_or_else(|| "hunk before any '---'/'+++' file header".to_string())?;
+            let (old_start, mut old_left, mut new_left) = parse_hunk_header(line)?;
+            let mut hunk = Hunk { old_start, lines: Vec::new(), no_newline_at_end: false };
+            // The header's counts say where the body ends: a removed "-- x"
+            // line reads "--- x" and must not be taken for a file header.
+            while old_left > 0 || new_left > 0 {
+                let next = lines
+                    .next()
+                    .ok_or_else(|| format!("{}: hunk at line {old_start} ends early", file.path()))?;
+                let hunk_line = match next.chars().next() {
+                    Some(' ') => HunkLine::Context(next[1..].to_string()),
+                    Some('-') => HunkLine::Remove(next[1..].to_string()),
+                    Some('+') => HunkLine::Add(next[1..].to_string()),
+                    Some('\\') => {
+                        if !matches!(hunk.lines.last(), Some(HunkLine::Remove(_))) {
+                            hunk.no_newline_at_end = true;
+                        }
+                        continue;
+                    }
+                    // Blank context lines often lose their leading space in transit
+                    None => HunkLine::Context(String::new()),
+                    Some(_) => return Err(format!("{}: unexpected line in hunk at line {old_start}: {next}", file.path())),
+                };
+                let (old, new) = match hunk_line {
+                    HunkLine::Context(_) => (1, 1),
+                    HunkLine::Remove(_) => (1, 0),
+                    HunkLine::Add(_) => (0, 1),
+                };
+                if old > old_left || new > new_left {
+                    return Err(format!("{}: hunk at line {old_start} has more lines than its header states", file.path()));
+                }
+                old_left -= old;
+                new_left -= new;
+                hunk.lines.push(hunk_line);
+            }
+            if lines.next_if(|l| l.starts_with('\\')).is_some()
+                && !matches!(hunk.lines.last(), Some(HunkLine::Remove(_)))
+            {
+                hunk.no_newline_at_end = true;
+            }
+            if hunk.lines.is_empty() {
+                return Err(format!("empty hunk in {}", file.path()));
+            }
+            file.hunks.push(hunk);
+        }
+        // Anything else ("diff --git", "index ...", prose) is ignored
+    }
+
+    files.retain(|f| !f.hunks.is_empty() || (f.old_path.is_some() && f.new_path.is_none()));
+    if files.is_empty() {
+        return Err("no file changes found; expected a unified diff with '---', '+++' and '@@' lines".to_string());
+    }
+    Ok(files)
+}
+
+// ====== Applying ======
+
+/// Index where `needle` occurs in `haystack[from..]`, preferring the position
+/// closest to `hint`. Trailing whitespace is ignored as a fallback.
+fn find_hunk(haystack: &[&str], needle: &[&str], from: usize, hint: usize) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(hint.clamp(from, haystack.len()));
+    }
+    if haystack.len() < needle.len() || from > haystack.len() - needle.len() {
+        return None;
+    }
+    let last = haystack.len() - needle.len();
+    let mut candidates: Vec<usize> = (from..=last).collect();
+    candidates.sort_by_key(|&i| i.abs_diff(hint));
+    let exact = |i: usize| haystack[i..i + needle.len()] == *needle;
+    let loose = |i: usize| {
+        haystack[i..i + needle.len()]
+            .iter()
+            .zip(needle)
+            .all(|(a, b)| a.trim_end() == b.trim_end())
+    };
+    candidates
+        .iter()
+        .copied()
+        .find(|&i| exact(i))
+        .or_else(|| candidates.iter().copied().find(|&i| loose(i)))
+}
+
+/// Result of applying one file's hunks in memory.
+#[derive(Debug, Clone)]
+struct AppliedFile {
+    path: PathBuf,
+    display: String,
+    before: Option<String>,
+    after: Option<String>,
+    /// (new-file line where the hunk starts, added, removed)
+    hunks: Vec<(usize, usize, usize)>,
+}
+
+fn apply_file(file: &FilePatch, path: PathBuf, before: Option<String>) -> Result<AppliedFile, String> {
+    let display = file.path().to_string();
+    if file.old_path.is_some() && before.is_none() {
+        return Err(format!("{display}: file not found"));
+    }
+    if file.old_path.is_none() && before.is_some() {
+        return Err(format!("{display}: patch creates the file but it already exists"));
+    }
+
+    let original = before.clone().unwrap_or_default();
+    let old: Vec<&str> = original.lines().collect();
+    let mut out: Vec<String> = Vec::new();
+    let mut trailing_newline = original.is_empty() || original.ends_with('\n');
+    let mut cursor = 0;
+    let mut offset: isize = 0;
+    let mut summary = Vec::new();
+
+    for (n, hunk) in file.hunks.iter().enumerate() {
+        let needle = hunk.old_lines();
+        let hint = ((hunk.old_start.max(1) - 1) as isize + offset).max(0) as usize;
+        // A pure insertion into an empty/new file states line 0
+        let hint = if needle.is_empty() && hunk.old_start == 0 { 0 } else { hint };
+        let at = find_hunk(&old, &needle, cursor, hint).ok_or_else(|| {
+            let preview = needle.iter().take(3).copied().collect::<Vec<_>>().join("\n  ");
+            format!(
+                "{display}: hunk {} (line {}) does not match the current file; expected:\n  {preview}",
+                n + 1,
+                hunk.old_start
+            )
+        })?;
+        offset = at as isize - (hunk.old_start.max(1) - 1) as isize;
+        out.extend(old[cursor..at].iter().map(|s| s.to_string()));
+        let new_lines = hunk.new_lines();
+        summary.push((
+            out.len() + 1,
+            hunk.lines.iter().filter(|l| matches!(l, HunkLine::Add(_))).count(),
+            hunk.lines.iter().filter(|l| matches!(l, HunkLine::Remove(_))).count(),
+        ));
+        out.extend(new_lines.iter().map(|s| s.to_string()));
+        cursor = at + needle.len();
+        if cursor == old.len() {
+            trailing_newline = !hunk.no_newline_at_end;
+        }
+    }
+    out.extend(old[cursor..].iter().map(|s| s.to_string()));
+
+    let after = if file.new_path.is_none() {
+        if !out.is_empty() {
+            return Err(format!("{display}: patch deletes the file but lines would remain"));
+        }
+        None
+    } else {
+        let mut content = out.join("\n");
+        if trailing_newline && !out.is_empty() {
+            content.push('\n');
+        }
+        Some(content)
+    };
+
+    Ok(AppliedFile {
+        path,
+        display,
+        before,
+        after,
+        hunks: summary,
+    })
+}
+
+fn render_preview(applied: &[AppliedFile]) -> String {
+    let mut lines = Vec::new();
+    for file in applied {
+        let (added, removed) = file
+            .hunks
+            .iter()
+            .fold((0, 0), |(a, r), (_, add, rem)| (a + add, r + rem));
+        let action = match (&file.before, &file.after) {
+            (None, _) => "create",
+            (_, None) => "delete",
+            _ => "modify",
+        };
+        lines.push(format!(
+            "{} {} ({} hunk(s), +{} -{})",
+            action,
+            file.display,
+            file.hunks.len(),
+            added,
+            removed
+        ));
+        if let Some(ref after) = file.after {
+            let new: Vec<&str> = after.lines().collect();
+            for (start, added, removed) in &file.hunks {
+                let first = start.saturating_sub(3).max(1);
+                let end = (start + added + 2).min(new.len());
+                lines.push(format!("  @@ line {} (+{} -{})", start, added, removed));
+                for n in first..=end {
+                    lines.push(format!("  {:>5}| {}", n, new[n - 1]));
+                }
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+// ====== Undo history ======
+
+/// One recorded file write. `None` contents mean the file did not exist.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditRecord {
+    pub id: u64,
+    pub path: PathBuf,
+    pub tool: String,
+    pub at_ms: u64,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryFile {
+    next_id: u64,
+    edits: Vec<EditRecord>,
+}
+
+/// Outcome of undoing one edit.
+#[derive(Debug, Clone)]
+pub struct Undone {
+    pub record: EditRecord,
+    /// Why it was not restored (file changed since), if it wasn't.
+    pub skipped: Option<String>,
+}
+
+/// File-backed history of edits made through the file tools.
+///
+/// Re-read on every call so the agent and the CLI see the same history.
+pub struct EditHistory {
+    path: PathBuf,
+    lock: std::sync::Mutex<()>,
+}
+
+impl EditHistory {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: std::sync::Mutex::new(()),
+        }
+    }
+
+    /// History in `<data dir>/edits/history.json`.
+    pub fn open_default() -> Self {
+        Self::new(crate::config::get_data_dir().join("edits").join("history.json"))
+    }
+
+    fn read(&self) -> HistoryFile {
+        read_json(&self.path, "edit history")
+    }
+
+    fn write(&self, history: &HistoryFile) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string(history)?)?;
+        Ok(())
+    }
+
+    fn with_history<T>(&self, f: impl FnOnce(&mut HistoryFile) -> T) -> anyhow::Result<T> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut history = self.read();
+        let result = f(&mut history);
+        if history.edits.len() > MAX_EDITS {
+            let excess = history.edits.len() - MAX_EDITS;
+            history.edits.drain(..excess);
+        }
+        self.write(&history)?;
+        Ok(result)
+    }
+
+    /// Record a write. Returns false (and records nothing) for files too
+    /// large to snapshot.
+    pub fn record(&self, path: &Path, tool: &str, before: Option<String>, after: Option<String>) -> bool {
+        let size = before.as_ref().map_or(0, String::len).max(after.as_ref().map_or(0, String::len));
+        if size > MAX_SNAPSHOT_BYTES {
+            return false;
+        }
+        let result = self.with_history(|h| {
+            h.next_id += 1;
+            h.edits.push(EditRecord {
+                id: h.next_id,
+                path: path.to_path_buf(),
+                tool: tool.to_string(),
+                at_ms: now_ms(),
+                before,
+                after,
+            });
+        });
+        if let Err(e) = result {
+            warn!("Failed to record edit of {}: {}", path.display(), e);
+            return false;
+        }
+        true
+    }
+
+    /// Most recent edits first, optionally only those of `file`.
+    pub fn list(&self, limit: usize, file: Option<&Path>) -> Vec<EditRecord> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.read()
+            .edits
+            .into_iter()
+            .rev()
+            .filter(|e| file.is_none_or(|f| e.path == f))
+            .take(limit)
+            .collect()
+    }
+
+    /// Revert the last `count` edits (of `file`, if given), newest first.
+    ///
+    /// An edit is only reverted if the file still has the contents the edit
+    /// left behind, unless `force` is set; skipped edits stay in the history
+    /// and stop the walk so older edits are not applied on top of them.
+    pub fn undo(&self, count: usize, file: Option<&Path>, force: bool) -> anyhow::Result<Vec<Undone>> {
+        self.with_history(|h| {
+            let mut results = Vec::new();
+            while results.len() < count {
+                let Some(idx) = h.edits.iter().rposition(|e| file.is_none_or(|f| e.path == f)) else {
+                    break;
+                };
+                let record = h.edits[idx].clone();
+                let current = std::fs::read_to_string(&record.path).ok();
+                if !force && current != record.after {
+                    results.push(Undone {
+                        record,
+                        skipped: Some("file changed since this edit (use --force to overwrite)".to_string()),
+                    });
+                    break;
+                }
+                let restored = match record.before {
+                    Some(ref content) => record
+                        .path
+                        .parent()
+                        .map_or(Ok(()), std::fs::create_dir_all)
+                        .and_then(|_| std::fs::write(&record.path, content)),
+                    None => match std::fs::remove_file(&record.path) {
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                        other => other,
+                    },
+                };
+                if let Err(e) = restored {
+                    results.push(Undone {
+                        record,
+                        skipped: Some(e.to_string()),
+                    });
+                    break;
+                }
+                h.edits.remove(idx);
+                results.push(Undone { record, skipped: None });
+            }
+            results
+        })
+    }
+}
+
+// ====== ApplyPatchTool ======
+
+pub struct ApplyPatchTool {
+    workspace: PathBuf,
+    allowed_dir: Option<PathBuf>,
+    history: EditHistory,
+    policy: Arc<FilePolicy>,
+}
+
+impl ApplyPatchTool {
+    /// Relative paths in the diff resolve against `workspace`.
+    pub fn new(workspace: PathBuf, allowed_dir: Option<PathBuf>) -> Self {
+        Self {
+            workspace,
+            allowed_dir,
+            history: EditHistory::open_default(),
+            policy: Arc::new(FilePolicy::off()),
+        }
+    }
+
+    pub fn with_history(mut self, history: EditHistory) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: Arc<FilePolicy>) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let joined = if Path::new(path).is_absolute() || path.starts_with('~') {
+            PathBuf::from(path)
+        } else {
+            self.workspace.join(path)
+        };
+        if Path::new(path).components().any(|c| c == std::path::Component::ParentDir) && self.allowed_dir.is_some() {
+            return Err(format!("Path {} is outside allowed directory", path));
+        }
+        super::filesystem::resolve_path(&joined.display().to_string(), self.allowed_dir.as_deref())
+    }
+
+    /// Parse and apply `patch` in memory; nothing is written.
+    fn prepare(&self, patch: &str) -> Result<Vec<AppliedFile>, String> {
+        let files = parse_patch(patch)?;
+        let mut applied = Vec::new();
+        for file in &files {
+            let path = self.resolve(file.path())?;
+            if applied.iter().any(|a: &AppliedFile| a.path == path) {
+                return Err(format!("{} appears twice in the patch", file.path()));
+            }
+            self.policy.check_read(&path)?;
+            let before = std::fs::read_to_string(&path).ok();
+            let file = apply_file(file, path, before)?;
+            self.policy
+                .check_write(&file.path, file.after.as_ref().map_or(0, String::len))?;
+            applied.push(file);
+        }
+        Ok(applied)
+    }
+}
+
+#[async_trait]
+impl Tool for ApplyPatchTool {
+    fn name(&self) -> &str {
+        "apply_patch"
+    }
+
+    fn description(&self) -> &str {
+        "Edit files by applying a unified diff (---/+++ headers and @@ hunks; several files \
+         allowed). All hunks are checked against the current contents first and nothing is \
+         written unless every hunk applies. Set dry_run to preview the result. Edits can be \
+         reverted with `chatweb undo`."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "patch": {
+                    "type": "string",
+                    "description": "Unified diff; paths are relative to the workspace"
+                },
+                "dry_run": {
+                    "type": "boolean",
+                    "description": "Only validate and preview the changes (default false)"
+                }
+            },
+            "required": ["patch"]
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        let patch = match params.get("patch").and_then(|v| v.as_str()) {
+            Some(p) => p,
+            None => return "Error: 'patch' parameter is required".to_string(),
+        };
+        let dry_run = params.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false);
+
+        let applied = match self.prepare(patch) {
+            Ok(applied) => applied,
+            Err(e) => return format!("Error: {e}\nNo files were changed."),
+        };
+        let preview = render_preview(&applied);
+        if dry_run {
+            return format!("Dry run, nothing written:\n{preview}");
+        }
+
+        for (n, file) in applied.iter().enumerate() {
+            let written = match (self.policy.trash(&file.path), &file.after) {
+                (Err(e), _) => Err(std::io::Error::other(e)),
+                (Ok(_), Some(content)) => file
+                    .path
+                    .parent()
+                    .map_or(Ok(()), std::fs::create_dir_all)
+                    .and_then(|_| std::fs::write(&file.path, content)),
+                (Ok(_), None) => std::fs::remove_file(&file.path),
+            };
+            if let Err(e) = written {
+                let done: Vec<&str> = applied[..n].iter().map(|f| f.display.as_str()).collect();
+                return format!(
+                    "Error writing {}: {e}\nAlready written: {}",
+                    file.display,
+                    if done.is_empty() { "none".to_string() } else { done.join(", ") }
+                );
+            }
+            if !self.history.record(&file.path, self.name(), file.before.clone(), file.after.clone()) {
+                warn!("Edit of {} not recorded for undo", file.path.display());
+            }
+        }
+        format!("Applied patch:\n{preview}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(dir: &Path) -> ApplyPatchTool {
+        ApplyPatchTool::new(dir.to_path_buf(), Some(dir.to_path_buf()))
+            .with_history(EditHistory::new(dir.join(".history.json")))
+    }
+
+    async fn apply(tool: &ApplyPatchTool, patch: &str, dry_run: bool) -> String {
+        let params = HashMap::from([
+            ("patch".to_string(), json!(patch)),
+            ("dry_run".to_string(), json!(dry_run)),
+        ]);
+        tool.execute(params).await
+    }
+
+    const PATCH: &str = "\
+diff --git a/src/main.rs b/src/main.rs
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,4 +1,4 @@
+ fn main() {
+-    println!(\"hello\");
++    println!(\"hello, world\");
+
+ }
+--- /dev/null
++++ b/NOTES.md
+@@ -0,0 +1,2 @@
++# Notes
++added by patch
+";
+
+    #[tokio::test]
+    async fn test_dry_run_apply_and_undo() {
+        let tmp = tempfile::tempdir().unwrap();
+        let main = tmp.path().join("src/main.rs");
+        std::fs::create_dir_all(main.parent().unwrap()).unwrap();
+        // Extra leading lines: the hunk must be found at an offset
+        let original = "// header\nfn main() {\n    println!(\"hello\");\n\n}\n";
+        std::fs::write(&main, original).unwrap();
+        let tool = tool(tmp.path());
+
+        let preview = apply(&tool, PATCH, true).await;
+        assert!(preview.starts_with("Dry run"), "{preview}");
+        assert!(preview.contains("modify src/main.rs (1 hunk(s), +1 -1)"), "{preview}");
+        assert!(preview.contains("create NOTES.md"), "{preview}");
+        assert_eq!(std::fs::read_to_string(&main).unwrap(), original);
+        assert!(!tmp.path().join("NOTES.md").exists());
+
+        let result = apply(&tool, PATCH, false).await;
+        assert!(result.starts_with("Applied patch"), "{result}");
+        assert_eq!(
+            std::fs::read_to_string(&main).unwrap(),
+            "// header\nfn main() {\n    println!(\"hello, world\");\n\n}\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(tmp.path().join("NOTES.md")).unwrap(),
+            "# Notes\nadded by patch\n"
+        );
+
+        let history = EditHistory::new(tmp.path().join(".history.json"));
+        assert_eq!(history.list(10, Some(&main.canonicalize().unwrap())).len(), 1);
+        let undone = history.undo(2, None, false).unwrap();
+        assert_eq!(undone.len(), 2);
+        assert!(undone.iter().all(|u| u.skipped.is_none()));
+        assert_eq!(std::fs::read_to_string(&main).unwrap(), original);
+        assert!(!tmp.path().join("NOTES.md").exists());
+        assert!(history.list(10, None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_hunk_writes_nothing() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join("src")).unwrap();
+        std::fs::write(tmp.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        let tool = tool(tmp.path());
+
+        let result = apply(&tool, PATCH, false).await;
+        assert!(result.contains("hunk 1 (line 1) does not match"), "{result}");
+        assert!(result.ends_with("No files were changed."));
+        assert!(!tmp.path().join("NOTES.md").exists());
+
+        let escape = "--- a/../outside.txt\n+++ b/../outside.txt\n@@ -1 +1 @@\n-a\n+b\n";
+        assert!(apply(&tool, escape, false).await.contains("outside allowed directory"));
+    }
+
+    #[tokio::test]
+    async fn test_removed_line_looking_like_a_file_header() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("schema.sql"), "CREATE TABLE t (id INT);\n-- drop me\nSELECT 1;\n").unwrap();
+        std::fs::write(tmp.path().join("b.txt"), "old\n").unwrap();
+        let tool = tool(tmp.path());
+
+        // "--- drop me" is the removal of "-- drop me", not a new file
+        let patch = "--- a/schema.sql\n+++ b/schema.sql\n@@ -1,3 +1,2 @@\n CREATE TABLE t (id INT);\n--- drop me\n SELECT 1;\n\
+                     --- a/b.txt\n+++ b/b.txt\n@@ -1 +1 @@\n-old\n+new\n";
+        let result = apply(&tool, patch, false).await;
+        assert!(result.starts_with("Applied patch"), "{result}");
+        assert_eq!(
+            std::fs::read_to_string(tmp.path().join("schema.sql")).unwrap(),
+            "CREATE TABLE t (id INT);\nSELECT 1;\n"
+        );
+        assert_eq!(std::fs::read_to_string(tmp.path().join("b.txt")).unwrap(), "new\n");
+
+        let short = "--- a/b.txt\n+++ b/b.txt\n@@ -1,2 +1,2 @@\n-new\n+newer\n";
+        assert!(apply(&tool, short, false).await.contains("ends early"));
+    }
+
+    #[test]
+    fn test_undo_skips_files_changed_since() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("a.txt");
+        let history = EditHistory::new(tmp.path().join("history.json"));
+        std::fs::write(&file, "two\n").unwrap();
+        history.record(&file, "edit_file", Some("one\n".into()), Some("two\n".into()));
+        std::fs::write(&file, "three\n").unwrap();
+
+        let undone = history.undo(1, Some(&file), false).unwrap();
+        assert!(undone[0].skipped.is_some());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "three\n");
+
+        let undone = history.undo(1, Some(&file), true).unwrap();
+        assert!(undone[0].skipped.is_none());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "one\n");
+    }
+
+    #[test]
+    fn test_no_newline_at_end() {
+        let file = FilePatch {
+            old_path: Some("f".into()),
+            new_path: Some("f".into()),
+            hunks: parse_patch("--- a/f\n+++ b/f\n@@ -1 +1 @@\n-a\n\\ No newline at end of file\n+b\n\\ No newline at end of file\n")
+                .unwrap()
+                .remove(0)
+                .hunks,
+        };
+        let applied = apply_file(&file, PathBuf::from("f"), Some("a".into())).unwrap();
+        assert_eq!(applied.after.as_deref(), Some("b"));
+    }
+}
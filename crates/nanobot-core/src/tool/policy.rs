@@ -0,0 +1,345 @@
+//! Per-tool allow/deny/ask rules (`tools.policy`), checked by
+//! [`ToolRegistry::execute`](super::ToolRegistry::execute) before a tool runs.
+//!
+//! A rule names a tool exactly or by prefix with a trailing `*`
+//! (`"github_*": "deny"`); the longest match wins and `default` covers the
+//! rest. `ask` needs an [`Approver`] — `chatweb agent` prompts on the
+//! terminal — and is refused where nobody can answer. With
+//! `confirmDestructive`, calls that only the default allows are still
+//! confirmed when they look destructive: a shell command (`exec`,
+//! `shell_session`, `code_execute` in shell) that deletes, moves or
+//! overwrites, a `write_file` over an existing file, `edit_file` and
+//! `apply_patch` (`forward_message` asks in the chat itself, see
+//! [`super::forward`]). Without an approver (the gateway, `mcp-serve`)
+//! they run, unless `confirmDestructive` is set to `true`: then they are
+//! refused there and an explicit `allow` rule for the tool lets them run.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::config::ToolPolicyConfig;
+
+/// A tool call waiting for the user's go-ahead.
+#[derive(Debug, Clone)]
+pub struct ApprovalRequest {
+    pub tool: String,
+    pub params: HashMap<String, serde_json::Value>,
+    /// Why it needs approval, e.g. "deletes files".
+    pub reason: String,
+}
+
+impl ApprovalRequest {
+    /// One line describing the call: the command for `exec`, the path for
+    /// file tools, else the arguments.
+    pub fn summary(&self) -> String {
+        let param = |key: &str| self.params.get(key).and_then(|v| v.as_str());
+        match (self.tool.as_str(), param("command"), param("path")) {
+            ("exec", Some(command), _) => format!("exec: {command}"),
+            ("forward_message", _, _) => {
+                format!("forward_message to {}: {}", param("to").unwrap_or_default(), param("content").unwrap_or_default())
+            }
+            (tool, _, Some(path)) => format!("{tool}: {path}"),
+            (tool, _, _) => format!("{tool}: {}", serde_json::to_string(&self.params).unwrap_or_default()),
+        }
+    }
+}
+
+/// Answers [`ApprovalRequest`]s with yes or no, e.g. by asking on the terminal.
+pub type Approver = Arc<dyn Fn(ApprovalRequest) -> Pin<Box<dyn Future<Output = bool> + Send>> + Send + Sync>;
+
+/// A `tools.policy` rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    Allow,
+    Deny,
+    Ask,
+}
+
+impl Rule {
+    /// Parse a config value; anything unknown asks (validation reports it).
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "allow" => Rule::Allow,
+            "deny" => Rule::Deny,
+            _ => Rule::Ask,
+        }
+    }
+}
+
+/// What to do with a tool call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    Deny,
+    /// Run only if an approver agrees.
+    Ask(String),
+    /// Run only if an approver agrees; the call looks destructive.
+    Confirm(String),
+}
+
+/// `tools.policy`, ready to check calls against.
+#[derive(Debug, Clone)]
+pub struct ToolPolicy {
+    rules: Vec<(String, Rule)>,
+    default: Rule,
+    confirm_destructive: bool,
+    /// Refuse what needs confirming when nobody can confirm it.
+    refuse_unconfirmed: bool,
+    /// Where relative file tool paths point.
+    workspace: Option<PathBuf>,
+}
+
+impl Default for ToolPolicy {
+    /// Everything runs, as without a policy.
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            default: Rule::Allow,
+            confirm_destructive: false,
+            refuse_unconfirmed: false,
+            workspace: None,
+        }
+    }
+}
+
+impl ToolPolicy {
+    pub fn from_config(config: &ToolPolicyConfig) -> Self {
+        Self {
+            rules: config.rules.iter().map(|(name, rule)| (name.clone(), Rule::parse(rule))).collect(),
+            default: Rule::parse(&config.default),
+            confirm_destructive: config.confirm_destructive != Some(false),
+            refuse_unconfirmed: config.confirm_destructive == Some(true),
+            workspace: None,
+        }
+    }
+
+    pub fn with_workspace(mut self, workspace: &Path) -> Self {
+        self.workspace = Some(workspace.to_path_buf());
+        self
+    }
+
+    pub fn decide(&self, tool: &str, params: &HashMap<String, serde_json::Value>) -> Decision {
+        let explicit = self.rule(tool);
+        match explicit.unwrap_or(self.default) {
+            Rule::Deny => Decision::Deny,
+            Rule::Ask => Decision::Ask("tools.policy asks first".to_string()),
+            Rule::Allow if explicit.is_none() && self.confirm_destructive => {
+                match destructive(tool, params, self.workspace.as_deref()) {
+                    Some(reason) => Decision::Confirm(reason),
+                    None => Decision::Allow,
+                }
+            }
+            Rule::Allow => Decision::Allow,
+        }
+    }
+
+    /// Whether a [`Decision::Confirm`] is refused without an approver rather
+    /// than run (`confirmDestructive: true`).
+    pub fn refuses_unconfirmed(&self) -> bool {
+        self.refuse_unconfirmed
+    }
+
+    /// The rule naming `tool` exactly, else the one with the longest matching prefix.
+    fn rule(&self, tool: &str) -> Option<Rule> {
+        self.rules
+            .iter()
+            .filter_map(|(pattern, rule)| match pattern.strip_suffix('*') {
+                Some(prefix) if tool.starts_with(prefix) => Some((prefix.len(), *rule)),
+                None if pattern == tool => Some((usize::MAX, *rule)),
+                _ => None,
+            })
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, rule)| rule)
+    }
+}
+
+/// Shell commands that lose data, with what they do.
+static DESTRUCTIVE_COMMANDS: Lazy<Vec<(Regex, &'static str)>> = Lazy::new(|| {
+    [
+        (r"\b(rm|rmdir|unlink|shred)\s", "deletes files"),
+        (r"\bfind\b.*\s-delete\b", "deletes files"),
+        (r"\bmv\s", "moves or renames files"),
+        (r"\b(dd|truncate)\s|\bmkfs", "overwrites data"),
+        (r"(^|[^>&0-9])>\s*[^\s>&]", "overwrites a file"),
+        (r"\bgit\s+(reset\s+--hard|clean\s|checkout\s+--\s|branch\s+-D\b|stash\s+(drop|clear)\b|push\b.*(--force|\s-f\b))", "discards git history or changes"),
+        (r"\b(chmod|chown)\s+-R\b", "changes permissions recursively"),
+        (r"\bsudo\s", "runs as root"),
+        (r"\b(kill|pkill|killall)\s", "stops processes"),
+        (r"(?i)\b(drop\s+(table|database)|truncate\s+table|delete\s+from)\b", "deletes database rows"),
+    ]
+    .into_iter()
+    .filter_map(|(pattern, reason)| Regex::new(pattern).ok().map(|re| (re, reason)))
+    .collect()
+});
+
+/// Why a call would destroy data, if it looks like it would. Relative
+/// file paths are looked up in `workspace`.
+pub fn destructive(tool: &str, params: &HashMap<String, serde_json::Value>, workspace: Option<&Path>) -> Option<String> {
+    let param = |key: &str| params.get(key).and_then(|v| v.as_str());
+    let dry_run = params.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false);
+    let command = |command: &str| {
+        DESTRUCTIVE_COMMANDS
+            .iter()
+            .find(|(re, _)| re.is_match(command))
+            .map(|(_, reason)| reason.to_string())
+    };
+    match tool {
+        "exec" => command(param("command")?),
+        "shell_session" => match param("action").unwrap_or("run") {
+            "run" => command(param("command")?),
+            "input" => command(param("text")?),
+            _ => None,
+        },
+        "code_execute" if param("language") == Some("shell") => command(param("code")?),
+        "write_file" if !dry_run => {
+            let path = param("path")?;
+            // The tool itself resolves against the working directory
+            let mut candidates = vec![super::filesystem::resolve_path(path, None).ok()?];
+            if let Some(workspace) = workspace.filter(|_| Path::new(path).is_relative() && !path.starts_with('~')) {
+                candidates.push(crate::util::normalize_path(&workspace.join(path)));
+            }
+            candidates.iter().any(|p| p.is_file()).then(|| format!("overwrites {path}"))
+        }
+        "edit_file" if !dry_run => Some(format!("edits {}", param("path")?)),
+        "apply_patch" if !dry_run => Some("changes files".to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, serde_json::Value)]) -> HashMap<String, serde_json::Value> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+    }
+
+    fn policy(rules: &[(&str, &str)], default: &str) -> ToolPolicy {
+        ToolPolicy::from_config(&ToolPolicyConfig {
+            rules: rules.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            default: default.to_string(),
+            confirm_destructive: Some(true),
+        })
+    }
+
+    #[test]
+    fn test_decide() {
+        let p = policy(&[("github_*", "deny"), ("github_read_*", "allow"), ("exec", "ask")], "allow");
+        let none = HashMap::new();
+        assert_eq!(p.decide("github_create_pr", &none), Decision::Deny);
+        assert_eq!(p.decide("github_read_file", &none), Decision::Allow);
+        assert!(matches!(p.decide("exec", &params(&[("command", "ls".into())])), Decision::Ask(_)));
+        assert_eq!(p.decide("read_file", &none), Decision::Allow);
+
+        // Only calls the default lets through are checked for damage
+        let p = policy(&[("write_file", "allow")], "allow");
+        assert_eq!(
+            p.decide("exec", &params(&[("command", "rm -r build".into())])),
+            Decision::Confirm("deletes files".into())
+        );
+        assert_eq!(p.decide("exec", &params(&[("command", "ls -la".into())])), Decision::Allow);
+        assert_eq!(policy(&[], "deny").decide("web_search", &none), Decision::Deny);
+        assert_eq!(ToolPolicy::default().decide("exec", &params(&[("command", "rm x".into())])), Decision::Allow);
+    }
+
+    #[test]
+    fn test_destructive() {
+        let exec = |command: &str| destructive("exec", &params(&[("command", command.into())]), None);
+        assert_eq!(exec("rm -f notes.txt").as_deref(), Some("deletes files"));
+        assert_eq!(exec("echo hi > README.md").as_deref(), Some("overwrites a file"));
+        assert_eq!(exec("git push origin main --force").as_deref(), Some("discards git history or changes"));
+        assert_eq!(exec("sqlite3 app.db 'DELETE FROM users'").as_deref(), Some("deletes database rows"));
+        assert_eq!(exec("cargo test 2>&1 | tail"), None);
+        assert_eq!(exec("echo hi >> log.txt"), None);
+        assert_eq!(exec("git status && ls"), None);
+
+        let dir = std::env::temp_dir().join(format!("nanobot-policy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let existing = dir.join("a.txt");
+        std::fs::write(&existing, "x").unwrap();
+        let write = |path: &str, dry_run: bool| {
+            destructive(
+                "write_file",
+                &params(&[("path", path.into()), ("content", "y".into()), ("dry_run", dry_run.into())]),
+                Some(&dir),
+            )
+        };
+        assert!(write(&existing.display().to_string(), false).unwrap().starts_with("overwrites"));
+        assert!(write("a.txt", false).unwrap().starts_with("overwrites"));
+        assert_eq!(write("a.txt", true), None);
+        assert_eq!(write(&dir.join("new.txt").display().to_string(), false), None);
+        std::fs::remove_dir_all(&dir).ok();
+
+        let session = |action: &str, key: &str, value: &str| {
+            destructive("shell_session", &params(&[("action", action.into()), (key, value.into())]), None)
+        };
+        assert_eq!(session("run", "command", "rm -r build").as_deref(), Some("deletes files"));
+        assert_eq!(session("input", "text", "mv a b\n").as_deref(), Some("moves or renames files"));
+        assert_eq!(session("run", "command", "ls"), None);
+        let code = |language: &str| {
+            destructive("code_execute", &params(&[("language", language.into()), ("code", "rm -f x".into())]), None)
+        };
+        assert_eq!(code("shell").as_deref(), Some("deletes files"));
+        assert_eq!(code("python"), None);
+        assert_eq!(destructive("edit_file", &params(&[("path", "a.txt".into())]), None).as_deref(), Some("edits a.txt"));
+        assert!(destructive("apply_patch", &params(&[("patch", "".into())]), None).is_some());
+        assert_eq!(destructive("apply_patch", &params(&[("dry_run", true.into())]), None), None);
+    }
+
+    #[tokio::test]
+    async fn test_confirm_without_approver() {
+        use crate::tool::{Tool, ToolRegistry};
+
+        struct Named(&'static str);
+        #[async_trait::async_trait]
+        impl Tool for Named {
+            fn name(&self) -> &str {
+                self.0
+            }
+            fn description(&self) -> &str {
+                "runs"
+            }
+            fn parameters(&self) -> serde_json::Value {
+                serde_json::json!({"type": "object"})
+            }
+            async fn execute(&self, _params: HashMap<String, serde_json::Value>) -> String {
+                "ran".into()
+            }
+        }
+
+        // By default destructive calls run where nobody can confirm them
+        let tools = ToolRegistry::new();
+        tools.register(Arc::new(Named("exec")));
+        tools.register(Arc::new(Named("edit_file")));
+        tools.set_policy(ToolPolicy::from_config(&ToolPolicyConfig::default()));
+        assert_eq!(tools.execute("edit_file", params(&[("path", "a.txt".into())])).await, "ran");
+        assert_eq!(tools.execute("exec", params(&[("command", "rm -r build".into())])).await, "ran");
+
+        // confirmDestructive: true refuses them there
+        tools.set_policy(policy(&[], "allow"));
+        assert_eq!(tools.execute("exec", params(&[("command", "ls".into())])).await, "ran");
+        let refused = tools.execute("exec", params(&[("command", "rm -r build".into())])).await;
+        assert!(refused.contains("needs the user's confirmation"), "{refused}");
+
+        tools.set_approver(Arc::new(|_| Box::pin(async { false })));
+        assert_eq!(tools.execute("exec", params(&[("command", "ls".into())])).await, "ran");
+        let declined = tools.execute("exec", params(&[("command", "rm -r build".into())])).await;
+        assert!(declined.contains("declined"), "{declined}");
+    }
+
+    #[test]
+    fn test_summary() {
+        let request = ApprovalRequest {
+            tool: "exec".into(),
+            params: params(&[("command", "rm -r build".into())]),
+            reason: "deletes files".into(),
+        };
+        assert_eq!(request.summary(), "exec: rm -r build");
+    }
+}
@@ -0,0 +1,517 @@
+//! `run_linter` and `run_tests`: language-aware quality checks used by
+//! [`crate::agent::AgentLoop::self_correct`] and `verify_code`.
+//!
+//! Each check runs the project's usual tool (cargo clippy/test, ruff/pytest,
+//! eslint/jest, go vet/test) and returns a [`QualityReport`] serialized as
+//! JSON, so callers read `passed` instead of scraping the output.
+
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::Tool;
+
+/// Default time limit for one check.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 600;
+
+/// Output kept in a report (the tail, where summaries are).
+const MAX_OUTPUT: usize = 6000;
+
+/// Failure names kept in a report.
+const MAX_FAILURES: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Go,
+}
+
+impl Language {
+    pub fn parse(s: &str) -> Option<Self> {
+        Some(match s.to_lowercase().as_str() {
+            "rust" | "rs" => Self::Rust,
+            "python" | "py" => Self::Python,
+            "javascript" | "js" | "node" => Self::JavaScript,
+            "typescript" | "ts" => Self::TypeScript,
+            "go" | "golang" => Self::Go,
+            _ => return None,
+        })
+    }
+
+    /// Guess from project files in `dir` (or its ancestors).
+    pub fn detect(dir: &Path) -> Option<Self> {
+        for dir in dir.ancestors() {
+            if dir.join("Cargo.toml").exists() {
+                return Some(Self::Rust);
+            }
+            if dir.join("go.mod").exists() {
+                return Some(Self::Go);
+            }
+            if dir.join("tsconfig.json").exists() {
+                return Some(Self::TypeScript);
+            }
+            if dir.join("package.json").exists() {
+                return Some(Self::JavaScript);
+            }
+            if ["pyproject.toml", "setup.py", "setup.cfg", "requirements.txt"]
+                .iter()
+                .any(|f| dir.join(f).exists())
+            {
+                return Some(Self::Python);
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckKind {
+    Lint,
+    Test,
+}
+
+/// Structured result of one lint or test run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualityReport {
+    pub passed: bool,
+    pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Lint errors, or failed tests.
+    pub errors: usize,
+    pub warnings: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tests_passed: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tests_failed: Option<usize>,
+    /// Failing tests or lint locations (`file:line: message`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<String>,
+    /// Why the check could not run (tool missing, timeout).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// Tail of stdout + stderr.
+    pub output: String,
+}
+
+impl QualityReport {
+    /// Parse a `run_linter`/`run_tests` tool result. Anything that isn't a
+    /// report (e.g. "Unknown tool") counts as not passed.
+    pub fn from_tool_output(output: &str) -> Self {
+        serde_json::from_str(output).unwrap_or_else(|_| Self {
+            passed: false,
+            error: Some(output.lines().next().unwrap_or_default().to_string()),
+            output: output.to_string(),
+            ..Default::default()
+        })
+    }
+}
+
+/// The program and arguments for a check.
+fn command_for(language: Language, kind: CheckKind, fix: bool, filter: Option<&str>) -> (&'static str, Vec<String>) {
+    let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let (program, mut args) = match (language, kind) {
+        (Language::Rust, CheckKind::Lint) if fix => (
+            "cargo",
+            args(&["clippy", "--all-targets", "--fix", "--allow-dirty", "--allow-staged", "--message-format=short"]),
+        ),
+        (Language::Rust, CheckKind::Lint) => (
+            "cargo",
+            args(&["clippy", "--all-targets", "--message-format=short", "--", "-D", "warnings"]),
+        ),
+        (Language::Rust, CheckKind::Test) => ("cargo", args(&["test"])),
+        (Language::Python, CheckKind::Lint) => ("ruff", args(&["check", "--output-format", "concise", "."])),
+        (Language::Python, CheckKind::Test) => ("pytest", args(&["-q", "-rf"])),
+        (Language::JavaScript | Language::TypeScript, CheckKind::Lint) => {
+            ("npx", args(&["--no-install", "eslint", "--format", "unix", "."]))
+        }
+        (Language::JavaScript | Language::TypeScript, CheckKind::Test) => ("npx", args(&["--no-install", "jest", "--ci"])),
+        (Language::Go, CheckKind::Lint) => ("go", args(&["vet", "./..."])),
+        (Language::Go, CheckKind::Test) => ("go", args(&["test", "-v", "./..."])),
+    };
+    if fix && kind == CheckKind::Lint {
+        match language {
+            Language::Python | Language::JavaScript | Language::TypeScript => args.push("--fix".to_string()),
+            _ => {}
+        }
+    }
+    if let (CheckKind::Test, Some(filter)) = (kind, filter) {
+        match language {
+            Language::Rust => args.push(filter.to_string()),
+            Language::Python => args.extend(["-k".to_string(), filter.to_string()]),
+            Language::JavaScript | Language::TypeScript => args.extend(["-t".to_string(), filter.to_string()]),
+            Language::Go => args.extend(["-run".to_string(), filter.to_string()]),
+        }
+    }
+    (program, args)
+}
+
+fn count(re: &Regex, output: &str) -> usize {
+    re.captures_iter(output)
+        .filter_map(|c| c.get(1)?.as_str().parse::<usize>().ok())
+        .sum()
+}
+
+static RUST_TEST_RESULT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"test result: \w+\. (\d+) passed; (\d+) failed").unwrap());
+static RUST_FAILED: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^test (\S+) \.\.\. FAILED").unwrap());
+static PY_PASSED: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+) passed").unwrap());
+static PY_FAILED: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+) (?:failed|errors?)\b").unwrap());
+static PY_FAILURE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^(?:FAILED|ERROR) (\S+)").unwrap());
+static JEST_TESTS: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^Tests:\s+(.*)$").unwrap());
+static JEST_FAILURE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^\s*● (.+)$").unwrap());
+static GO_PASS: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^\s*--- PASS: ").unwrap());
+static GO_FAIL: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^\s*--- FAIL: (\S+)").unwrap());
+/// `path:line:col: message` as printed by clippy (short), ruff (concise), eslint (unix) and go vet.
+static LOCATION: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^(\S+?:\d+:\d+:?\s.*)$").unwrap());
+
+/// Fill the counts and failures of `report` from the tool output.
+fn parse_output(report: &mut QualityReport, language: Language, kind: CheckKind, output: &str) {
+    match kind {
+        CheckKind::Lint => {
+            let locations: Vec<&str> = LOCATION.captures_iter(output).filter_map(|c| c.get(1)).map(|m| m.as_str()).collect();
+            if language == Language::Rust {
+                report.errors = locations.iter().filter(|l| l.contains(" error")).count();
+                report.warnings = locations.iter().filter(|l| l.contains(" warning")).count();
+            } else {
+                report.errors = locations.len();
+            }
+            report.failures = locations.iter().map(|l| l.to_string()).collect();
+        }
+        CheckKind::Test => {
+            let (passed, failed, failures): (usize, usize, Vec<String>) = match language {
+                Language::Rust => {
+                    let (mut passed, mut failed) = (0, 0);
+                    for c in RUST_TEST_RESULT.captures_iter(output) {
+                        passed += c[1].parse::<usize>().unwrap_or(0);
+                        failed += c[2].parse::<usize>().unwrap_or(0);
+                    }
+                    (passed, failed, RUST_FAILED.captures_iter(output).map(|c| c[1].to_string()).collect())
+                }
+                Language::Python => {
+                    // Only the final summary line ("=== 1 failed, 3 passed in 0.1s ===")
+                    let summary = output.lines().rev().find(|l| PY_PASSED.is_match(l) || PY_FAILED.is_match(l));
+                    let summary = summary.unwrap_or_default();
+                    (
+                        count(&PY_PASSED, summary),
+                        count(&PY_FAILED, summary),
+                        PY_FAILURE.captures_iter(output).map(|c| c[1].to_string()).collect(),
+                    )
+                }
+                Language::JavaScript | Language::TypeScript => {
+                    let summary = JEST_TESTS.captures(output).map(|c| c[1].to_string()).unwrap_or_default();
+                    (
+                        count(&PY_PASSED, &summary),
+                        count(&PY_FAILED, &summary),
+                        JEST_FAILURE.captures_iter(output).map(|c| c[1].trim().to_string()).collect(),
+                    )
+                }
+                Language::Go => {
+                    let failures: Vec<String> = GO_FAIL.captures_iter(output).map(|c| c[1].to_string()).collect();
+                    (GO_PASS.find_iter(output).count(), failures.len(), failures)
+                }
+            };
+            report.tests_passed = Some(passed);
+            report.tests_failed = Some(failed);
+            report.errors = failed;
+            report.failures = failures;
+        }
+    }
+    report.failures.truncate(MAX_FAILURES);
+}
+
+fn tail(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let mut start = text.len() - max;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("...{}", &text[start..])
+}
+
+/// Options for [`run_check`].
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+    pub language: Option<Language>,
+    /// Project directory.
+    pub dir: PathBuf,
+    /// Apply linter auto-fixes.
+    pub fix: bool,
+    /// Only tests matching this name.
+    pub filter: Option<String>,
+    pub timeout: Duration,
+}
+
+/// Run one lint or test check and summarize it.
+pub async fn run_check(kind: CheckKind, options: &CheckOptions) -> QualityReport {
+    let started = Instant::now();
+    let Some(language) = options.language.or_else(|| Language::detect(&options.dir)) else {
+        return QualityReport {
+            error: Some(format!(
+                "could not detect the project language in {}; pass language",
+                options.dir.display()
+            )),
+            ..Default::default()
+        };
+    };
+    let (program, args) = command_for(language, kind, options.fix, options.filter.as_deref());
+    let mut report = QualityReport {
+        command: format!("{} {}", program, args.join(" ")),
+        ..Default::default()
+    };
+
+    let mut command = tokio::process::Command::new(program);
+    command.args(&args).current_dir(&options.dir).kill_on_drop(true);
+    if language == Language::Rust {
+        // Stable, uncoloured output for parsing
+        command.env("CARGO_TERM_COLOR", "never");
+    }
+    let output = match tokio::time::timeout(options.timeout, command.output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => {
+            report.error = Some(format!("failed to run {} (is it installed?): {}", program, e));
+            return report;
+        }
+        Err(_) => {
+            report.error = Some(format!("timed out after {}s", options.timeout.as_secs()));
+            return report;
+        }
+    };
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    report.exit_code = output.status.code();
+    let combined = format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    parse_output(&mut report, language, kind, &combined);
+    report.passed = output.status.success() && report.errors == 0;
+    report.output = tail(combined.trim(), MAX_OUTPUT);
+    report
+}
+
+/// Shared parameter handling for both tools.
+fn options_from_params(workspace: &Path, params: &HashMap<String, serde_json::Value>) -> Result<CheckOptions, String> {
+    let language = match params.get("language").and_then(|v| v.as_str()).filter(|l| *l != "auto") {
+        Some(l) => Some(Language::parse(l).ok_or_else(|| format!("Error: unsupported language '{}'", l))?),
+        None => None,
+    };
+    let dir = match params.get("path").and_then(|v| v.as_str()) {
+        Some(p) if Path::new(p).is_absolute() => PathBuf::from(p),
+        Some(p) => workspace.join(p),
+        None => workspace.to_path_buf(),
+    };
+    // A file path runs the check for its project
+    let dir = if dir.is_file() {
+        dir.parent().map(Path::to_path_buf).unwrap_or(dir)
+    } else {
+        dir
+    };
+    if !dir.is_dir() {
+        return Err(format!("Error: directory not found: {}", dir.display()));
+    }
+    Ok(CheckOptions {
+        language,
+        dir,
+        fix: params.get("fix").and_then(|v| v.as_bool()).unwrap_or(false),
+        filter: params.get("test_name").and_then(|v| v.as_str()).map(String::from),
+        timeout: Duration::from_secs(
+            params
+                .get("timeout_secs")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(DEFAULT_TIMEOUT_SECS),
+        ),
+    })
+}
+
+fn language_schema() -> serde_json::Value {
+    json!({
+        "type": "string",
+        "enum": ["auto", "rust", "python", "javascript", "typescript", "go"],
+        "description": "Project language (default: detected from Cargo.toml, pyproject.toml, package.json, go.mod)"
+    })
+}
+
+async fn execute_check(workspace: &Path, kind: CheckKind, params: HashMap<String, serde_json::Value>) -> String {
+    match options_from_params(workspace, &params) {
+        Ok(options) => serde_json::to_string_pretty(&run_check(kind, &options).await).unwrap_or_default(),
+        Err(e) => e,
+    }
+}
+
+// ====== RunLinterTool ======
+
+pub struct RunLinterTool {
+    workspace: PathBuf,
+}
+
+impl RunLinterTool {
+    pub fn new(workspace: PathBuf) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for RunLinterTool {
+    fn name(&self) -> &str {
+        "run_linter"
+    }
+
+    fn description(&self) -> &str {
+        "Run the project's linter (cargo clippy, ruff, eslint, go vet) and return a JSON report: \
+         passed, error/warning counts, issue locations and the output tail."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "language": language_schema(),
+                "path": {
+                    "type": "string",
+                    "description": "Project directory relative to the workspace (default: workspace root)"
+                },
+                "fix": {
+                    "type": "boolean",
+                    "description": "Apply automatic fixes (default false)"
+                },
+                "timeout_secs": {
+                    "type": "integer",
+                    "description": "Time limit (default 600)"
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        execute_check(&self.workspace, CheckKind::Lint, params).await
+    }
+}
+
+// ====== RunTestsTool ======
+
+pub struct RunTestsTool {
+    workspace: PathBuf,
+}
+
+impl RunTestsTool {
+    pub fn new(workspace: PathBuf) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for RunTestsTool {
+    fn name(&self) -> &str {
+        "run_tests"
+    }
+
+    fn description(&self) -> &str {
+        "Run the project's tests (cargo test, pytest, jest, go test) and return a JSON report: \
+         passed, passed/failed counts, failing test names and the output tail."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "language": language_schema(),
+                "path": {
+                    "type": "string",
+                    "description": "Project directory relative to the workspace (default: workspace root)"
+                },
+                "test_name": {
+                    "type": "string",
+                    "description": "Only run tests matching this name"
+                },
+                "timeout_secs": {
+                    "type": "integer",
+                    "description": "Time limit (default 600)"
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        execute_check(&self.workspace, CheckKind::Test, params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(language: Language, kind: CheckKind, output: &str) -> QualityReport {
+        let mut report = QualityReport::default();
+        parse_output(&mut report, language, kind, output);
+        report
+    }
+
+    #[test]
+    fn test_parse_test_output() {
+        let cargo = "running 3 tests\ntest a::works ... ok\ntest a::broken ... FAILED\n\
+                     test result: FAILED. 2 passed; 1 failed; 0 ignored; 0 measured\n\
+                     test result: ok. 4 passed; 0 failed; 0 ignored\n";
+        let r = parsed(Language::Rust, CheckKind::Test, cargo);
+        assert_eq!((r.tests_passed, r.tests_failed), (Some(6), Some(1)));
+        assert_eq!(r.failures, vec!["a::broken"]);
+
+        let pytest = "..F\nFAILED tests/test_x.py::test_div - ZeroDivisionError\n=== 1 failed, 2 passed in 0.12s ===\n";
+        let r = parsed(Language::Python, CheckKind::Test, pytest);
+        assert_eq!((r.tests_passed, r.tests_failed), (Some(2), Some(1)));
+        assert_eq!(r.failures, vec!["tests/test_x.py::test_div"]);
+
+        let jest = "  ● math › adds\n\nTests:       1 failed, 5 passed, 6 total\n";
+        let r = parsed(Language::JavaScript, CheckKind::Test, jest);
+        assert_eq!((r.tests_passed, r.tests_failed), (Some(5), Some(1)));
+        assert_eq!(r.failures, vec!["math › adds"]);
+
+        let go = "=== RUN   TestA\n--- PASS: TestA (0.00s)\n=== RUN   TestB\n--- FAIL: TestB (0.00s)\nFAIL\n";
+        let r = parsed(Language::Go, CheckKind::Test, go);
+        assert_eq!((r.tests_passed, r.tests_failed), (Some(1), Some(1)));
+    }
+
+    #[test]
+    fn test_parse_lint_output() {
+        let clippy = "src/lib.rs:3:5: warning: unused variable: `x`\nsrc/main.rs:10:1: error: mismatched types\n\
+                      error: could not compile `demo`\n";
+        let r = parsed(Language::Rust, CheckKind::Lint, clippy);
+        assert_eq!((r.errors, r.warnings), (1, 1));
+
+        let ruff = "app.py:1:8: F401 [*] `os` imported but unused\nFound 1 error.\n";
+        let r = parsed(Language::Python, CheckKind::Lint, ruff);
+        assert_eq!(r.errors, 1);
+        assert_eq!(r.failures, vec!["app.py:1:8: F401 [*] `os` imported but unused"]);
+    }
+
+    #[test]
+    fn test_detect_and_report_round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("pyproject.toml"), "").unwrap();
+        std::fs::create_dir(tmp.path().join("pkg")).unwrap();
+        assert_eq!(Language::detect(&tmp.path().join("pkg")), Some(Language::Python));
+
+        let report = QualityReport {
+            passed: true,
+            command: "pytest -q".into(),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(QualityReport::from_tool_output(&json).passed);
+        assert!(!QualityReport::from_tool_output("Error: Unknown tool 'run_tests'").passed);
+    }
+}
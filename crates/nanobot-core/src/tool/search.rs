@@ -0,0 +1,351 @@
+//! Workspace-wide regex search, so the agent can navigate a repository without
+//! shelling out to `rg`.
+//!
+//! Walks the workspace with the `ignore` crate (the walker ripgrep uses), so
+//! `.gitignore`, `.ignore` and hidden files are skipped the same way. Results
+//! are returned as JSON with file, line, column and surrounding context.
+
+use async_trait::async_trait;
+use regex::RegexBuilder;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use super::Tool;
+use crate::util::truncate_chars;
+
+const DEFAULT_MAX_RESULTS: usize = 50;
+const MAX_RESULTS: usize = 200;
+const MAX_CONTEXT: usize = 5;
+
+/// Files larger than this are not searched.
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Matched and context lines are cut to this many characters.
+const MAX_LINE_CHARS: usize = 300;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMatch {
+    /// Path relative to the workspace.
+    pub file: String,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column (in characters) of the first match on the line.
+    pub column: usize,
+    pub text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResult {
+    pub matches: Vec<SearchMatch>,
+    pub files_searched: usize,
+    pub files_matched: usize,
+    /// More matches exist than were returned.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub pattern: String,
+    /// Subdirectory or file to search, relative to the workspace.
+    pub path: Option<String>,
+    /// Glob filters such as `*.rs` or `!*_test.go`.
+    pub globs: Vec<String>,
+    pub case_insensitive: bool,
+    pub fixed_strings: bool,
+    pub context: usize,
+    pub max_results: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            pattern: String::new(),
+            path: None,
+            globs: Vec::new(),
+            case_insensitive: false,
+            fixed_strings: false,
+            context: 2,
+            max_results: DEFAULT_MAX_RESULTS,
+        }
+    }
+}
+
+fn clip(line: &str) -> String {
+    match truncate_chars(line, MAX_LINE_CHARS) {
+        (head, true) => format!("{}…", head),
+        (head, false) => head.to_string(),
+    }
+}
+
+/// Search `workspace` for `options.pattern`. Blocking; run it off the async runtime.
+pub fn search(workspace: &Path, options: &SearchOptions) -> Result<SearchResult, String> {
+    let pattern = if options.fixed_strings {
+        regex::escape(&options.pattern)
+    } else {
+        options.pattern.clone()
+    };
+    let re = RegexBuilder::new(&pattern)
+        .case_insensitive(options.case_insensitive)
+        .size_limit(1 << 20)
+        .build()
+        .map_err(|e| format!("invalid regex: {e}"))?;
+
+    let root = match options.path.as_deref().filter(|p| !p.is_empty() && *p != ".") {
+        Some(sub) => {
+            let sub_path = Path::new(sub);
+            if sub_path.is_absolute() || sub_path.components().any(|c| c == Component::ParentDir) {
+                return Err(format!("path must be relative to the workspace: {sub}"));
+            }
+            workspace.join(sub_path)
+        }
+        None => workspace.to_path_buf(),
+    };
+    if !root.exists() {
+        return Err(format!("path not found: {}", root.display()));
+    }
+
+    let mut overrides = ignore::overrides::OverrideBuilder::new(workspace);
+    for glob in &options.globs {
+        overrides.add(glob).map_err(|e| format!("invalid glob {glob}: {e}"))?;
+    }
+    let overrides = overrides.build().map_err(|e| format!("invalid glob: {e}"))?;
+
+    let walker = ignore::WalkBuilder::new(&root)
+        .overrides(overrides)
+        // Honour .gitignore even when the workspace is not a git checkout
+        .require_git(false)
+        .sort_by_file_path(|a, b| a.cmp(b))
+        .build();
+
+    let max_results = options.max_results.clamp(1, MAX_RESULTS);
+    let context = options.context.min(MAX_CONTEXT);
+    let mut result = SearchResult {
+        matches: Vec::new(),
+        files_searched: 0,
+        files_matched: 0,
+        truncated: false,
+    };
+
+    for entry in walker.flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if entry.metadata().map(|m| m.len() > MAX_FILE_BYTES).unwrap_or(true) {
+            continue;
+        }
+        let Ok(bytes) = std::fs::read(entry.path()) else {
+            continue;
+        };
+        // Skip binaries the way grep does: a NUL byte near the start
+        if bytes[..bytes.len().min(8192)].contains(&0) {
+            continue;
+        }
+        let content = String::from_utf8_lossy(&bytes);
+        result.files_searched += 1;
+
+        let lines: Vec<&str> = content.lines().collect();
+        let rel = entry
+            .path()
+            .strip_prefix(workspace)
+            .unwrap_or(entry.path())
+            .display()
+            .to_string();
+        let mut matched = false;
+        for (idx, line) in lines.iter().enumerate() {
+            let Some(m) = re.find(line) else {
+                continue;
+            };
+            if result.matches.len() == max_results {
+                result.truncated = true;
+                return Ok(result);
+            }
+            if !matched {
+                matched = true;
+                result.files_matched += 1;
+            }
+            result.matches.push(SearchMatch {
+                file: rel.clone(),
+                line: idx + 1,
+                column: line[..m.start()].chars().count() + 1,
+                text: clip(line),
+                before: lines[idx.saturating_sub(context)..idx].iter().map(|l| clip(l)).collect(),
+                after: lines[idx + 1..(idx + 1 + context).min(lines.len())]
+                    .iter()
+                    .map(|l| clip(l))
+                    .collect(),
+            });
+        }
+    }
+    Ok(result)
+}
+
+/// `grep_workspace`: regex search across the workspace.
+pub struct GrepWorkspaceTool {
+    workspace: PathBuf,
+}
+
+impl GrepWorkspaceTool {
+    pub fn new(workspace: PathBuf) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for GrepWorkspaceTool {
+    fn name(&self) -> &str {
+        "grep_workspace"
+    }
+
+    fn description(&self) -> &str {
+        "Search files in the workspace with a regular expression (ripgrep-style; .gitignore'd, \
+         hidden and binary files are skipped). Returns JSON matches with file, line, column and \
+         context lines."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "pattern": {
+                    "type": "string",
+                    "description": "Regular expression (Rust regex syntax) to search for"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Directory or file to search, relative to the workspace (default: whole workspace)"
+                },
+                "glob": {
+                    "type": "string",
+                    "description": "Comma-separated file globs, e.g. \"*.rs,*.toml\" or \"!tests/**\""
+                },
+                "case_insensitive": {
+                    "type": "boolean",
+                    "description": "Ignore case (default false)"
+                },
+                "fixed_strings": {
+                    "type": "boolean",
+                    "description": "Treat the pattern as a literal string (default false)"
+                },
+                "context": {
+                    "type": "integer",
+                    "description": "Lines of context before and after each match (default 2, max 5)"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum matches to return (default 50, max 200)"
+                }
+            },
+            "required": ["pattern"]
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        let pattern = match params.get("pattern").and_then(|v| v.as_str()) {
+            Some(p) if !p.is_empty() => p,
+            _ => return "Error: 'pattern' parameter is required".to_string(),
+        };
+        let bool_param = |key: &str| params.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+        let options = SearchOptions {
+            pattern: pattern.to_string(),
+            path: params.get("path").and_then(|v| v.as_str()).map(String::from),
+            globs: params
+                .get("glob")
+                .and_then(|v| v.as_str())
+                .map(|g| g.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+                .unwrap_or_default(),
+            case_insensitive: bool_param("case_insensitive"),
+            fixed_strings: bool_param("fixed_strings"),
+            context: params.get("context").and_then(|v| v.as_u64()).map_or(2, |n| n as usize),
+            max_results: params
+                .get("max_results")
+                .and_then(|v| v.as_u64())
+                .map_or(DEFAULT_MAX_RESULTS, |n| n as usize),
+        };
+
+        let workspace = self.workspace.clone();
+        match tokio::task::spawn_blocking(move || search(&workspace, &options)).await {
+            Ok(Ok(result)) if result.matches.is_empty() => {
+                format!("No matches for /{}/ in {} files", pattern, result.files_searched)
+            }
+            Ok(Ok(result)) => serde_json::to_string(&result).unwrap_or_default(),
+            Ok(Err(e)) => format!("Error: {e}"),
+            Err(e) => format!("Error: search failed: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace() -> tempfile::TempDir {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("src/lib.rs"), "// lib\npub fn parse_config() {}\n\nfn helper() {}\n").unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {\n    parse_config();\n}\n").unwrap();
+        std::fs::write(root.join("target/out.rs"), "fn parse_config() {}\n").unwrap();
+        std::fs::write(root.join("README.md"), "Call Parse_Config first.\n").unwrap();
+        std::fs::write(root.join("blob.bin"), b"parse_config\0\x01").unwrap();
+        tmp
+    }
+
+    #[test]
+    fn test_search_respects_gitignore_and_context() {
+        let tmp = workspace();
+        let result = search(
+            tmp.path(),
+            &SearchOptions {
+                pattern: r"parse_config\(".into(),
+                context: 1,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let found: Vec<_> = result.matches.iter().map(|m| (m.file.as_str(), m.line, m.column)).collect();
+        assert_eq!(found, vec![("src/lib.rs", 2, 8), ("src/main.rs", 2, 5)]);
+        assert_eq!(result.matches[0].before, vec!["// lib"]);
+        assert_eq!(result.matches[0].after, vec![""]);
+        assert_eq!(result.files_matched, 2);
+        assert!(!result.truncated);
+    }
+
+    #[test]
+    fn test_search_filters_and_limits() {
+        let tmp = workspace();
+        let options = SearchOptions {
+            pattern: "parse_config".into(),
+            case_insensitive: true,
+            globs: vec!["*.md".into()],
+            ..Default::default()
+        };
+        let result = search(tmp.path(), &options).unwrap();
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!(result.matches[0].file, "README.md");
+
+        let options = SearchOptions {
+            pattern: "fn ".into(),
+            path: Some("src".into()),
+            max_results: 2,
+            ..Default::default()
+        };
+        let result = search(tmp.path(), &options).unwrap();
+        assert_eq!(result.matches.len(), 2);
+        assert!(result.truncated);
+
+        let escape = SearchOptions {
+            pattern: "x".into(),
+            path: Some("../".into()),
+            ..Default::default()
+        };
+        assert!(search(tmp.path(), &escape).is_err());
+    }
+}
@@ -0,0 +1,346 @@
+//! Web search engines behind one trait.
+//!
+//! `web_search` asks the engines from `tools.web.search` in order: the
+//! configured `provider` first, then each `fallback` engine whenever the
+//! previous one fails or is rate-limited. Every engine returns the same
+//! [`SearchResult`] shape.
+
+use async_trait::async_trait;
+use reqwest::Url;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::WebSearchConfig;
+use crate::util::http::{self, ClientClass};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One normalized search hit.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SearchError {
+    #[error("{engine} is rate-limited")]
+    RateLimited { engine: &'static str },
+    #[error("{engine} returned HTTP {status}")]
+    Status { engine: &'static str, status: u16 },
+    #[error("{engine}: {message}")]
+    Other { engine: &'static str, message: String },
+}
+
+impl SearchError {
+    fn other(engine: &'static str, e: impl std::fmt::Display) -> Self {
+        Self::Other { engine, message: e.to_string() }
+    }
+}
+
+/// A web search engine.
+#[async_trait]
+pub trait SearchProvider: Send + Sync {
+    /// Engine name as used in config.
+    fn name(&self) -> &'static str;
+
+    async fn search(&self, query: &str, count: u32) -> Result<Vec<SearchResult>, SearchError>;
+}
+
+/// Map non-success statuses, treating 429 as rate limiting.
+fn check_status(engine: &'static str, response: &reqwest::Response) -> Result<(), SearchError> {
+    match response.status().as_u16() {
+        429 => Err(SearchError::RateLimited { engine }),
+        s if !(200..300).contains(&s) => Err(SearchError::Status { engine, status: s }),
+        _ => Ok(()),
+    }
+}
+
+fn str_field(item: &serde_json::Value, key: &str) -> String {
+    item.get(key).and_then(|v| v.as_str()).unwrap_or_default().trim().to_string()
+}
+
+/// Results from a JSON array of objects with the given field names.
+fn normalize(items: Option<&Vec<serde_json::Value>>, title: &str, url: &str, snippet: &str, count: u32) -> Vec<SearchResult> {
+    items
+        .into_iter()
+        .flatten()
+        .map(|item| SearchResult {
+            title: str_field(item, title),
+            url: str_field(item, url),
+            snippet: str_field(item, snippet),
+        })
+        .filter(|r| !r.url.is_empty())
+        .take(count as usize)
+        .collect()
+}
+
+/// Brave Search API.
+pub struct BraveSearch {
+    api_key: String,
+}
+
+#[async_trait]
+impl SearchProvider for BraveSearch {
+    fn name(&self) -> &'static str {
+        "brave"
+    }
+
+    async fn search(&self, query: &str, count: u32) -> Result<Vec<SearchResult>, SearchError> {
+        let response = http::client_for(ClientClass::General)
+            .get("https://api.search.brave.com/res/v1/web/search")
+            .query(&[("q", query), ("count", &count.to_string())])
+            .header("Accept", "application/json")
+            .header("X-Subscription-Token", &self.api_key)
+            .timeout(TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| SearchError::other("brave", e))?;
+        check_status("brave", &response)?;
+        let data: serde_json::Value = response.json().await.map_err(|e| SearchError::other("brave", e))?;
+        let results = data.get("web").and_then(|w| w.get("results")).and_then(|r| r.as_array());
+        Ok(normalize(results, "title", "url", "description", count))
+    }
+}
+
+/// Self-hosted SearxNG (`search.formats` must include `json`).
+pub struct SearxngSearch {
+    base_url: String,
+}
+
+#[async_trait]
+impl SearchProvider for SearxngSearch {
+    fn name(&self) -> &'static str {
+        "searxng"
+    }
+
+    async fn search(&self, query: &str, count: u32) -> Result<Vec<SearchResult>, SearchError> {
+        let url = format!("{}/search", self.base_url.trim_end_matches('/'));
+        if let Ok(parsed) = Url::parse(&url) {
+            http::throttle(&parsed).await;
+        }
+        let response = http::client_for(ClientClass::General)
+            .get(url)
+            .query(&[("q", query), ("format", "json")])
+            .header("User-Agent", http::user_agent())
+            .timeout(TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| SearchError::other("searxng", e))?;
+        check_status("searxng", &response)?;
+        let data: serde_json::Value = response.json().await.map_err(|e| SearchError::other("searxng", e))?;
+        Ok(normalize(data.get("results").and_then(|r| r.as_array()), "title", "url", "content", count))
+    }
+}
+
+/// Tavily search API.
+pub struct TavilySearch {
+    api_key: String,
+}
+
+#[async_trait]
+impl SearchProvider for TavilySearch {
+    fn name(&self) -> &'static str {
+        "tavily"
+    }
+
+    async fn search(&self, query: &str, count: u32) -> Result<Vec<SearchResult>, SearchError> {
+        let response = http::client_for(ClientClass::General)
+            .post("https://api.tavily.com/search")
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&serde_json::json!({"query": query, "max_results": count, "include_answer": false}))
+            .timeout(TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| SearchError::other("tavily", e))?;
+        check_status("tavily", &response)?;
+        let data: serde_json::Value = response.json().await.map_err(|e| SearchError::other("tavily", e))?;
+        Ok(normalize(data.get("results").and_then(|r| r.as_array()), "title", "url", "content", count))
+    }
+}
+
+const DUCKDUCKGO_URL: &str = "https://html.duckduckgo.com/html/";
+
+/// DuckDuckGo's HTML endpoint; needs no key.
+pub struct DuckDuckGoSearch;
+
+#[async_trait]
+impl SearchProvider for DuckDuckGoSearch {
+    fn name(&self) -> &'static str {
+        "duckduckgo"
+    }
+
+    async fn search(&self, query: &str, count: u32) -> Result<Vec<SearchResult>, SearchError> {
+        // Keyless scraping endpoint: paced like any other page fetch
+        http::throttle(&Url::parse(DUCKDUCKGO_URL).unwrap()).await;
+        let response = http::client_for(ClientClass::General)
+            .get(DUCKDUCKGO_URL)
+            .query(&[("q", query)])
+            .header("User-Agent", http::user_agent())
+            .timeout(TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| SearchError::other("duckduckgo", e))?;
+        check_status("duckduckgo", &response)?;
+        let body = response.text().await.map_err(|e| SearchError::other("duckduckgo", e))?;
+        // DuckDuckGo answers bot checks with a 200 page that has no results form
+        if body.contains("anomaly-modal") {
+            return Err(SearchError::RateLimited { engine: "duckduckgo" });
+        }
+        Ok(parse_duckduckgo(&body, count))
+    }
+}
+
+/// Results from DuckDuckGo's HTML page; links go through a `uddg=` redirect.
+fn parse_duckduckgo(html: &str, count: u32) -> Vec<SearchResult> {
+    let doc = Html::parse_document(html);
+    let result_sel = Selector::parse(".result").unwrap();
+    let link_sel = Selector::parse("a.result__a").unwrap();
+    let snippet_sel = Selector::parse(".result__snippet").unwrap();
+
+    doc.select(&result_sel)
+        .filter_map(|result| {
+            let link = result.select(&link_sel).next()?;
+            let href = link.value().attr("href")?;
+            let url = href
+                .split_once("uddg=")
+                .map(|(_, rest)| rest.split('&').next().unwrap_or(rest))
+                .and_then(|encoded| urlencoding::decode(encoded).ok().map(|u| u.into_owned()))
+                .unwrap_or_else(|| href.to_string());
+            let snippet = result.select(&snippet_sel).next().map(|s| s.text().collect::<String>()).unwrap_or_default();
+            Some(SearchResult {
+                title: link.text().collect::<String>().trim().to_string(),
+                url,
+                snippet: snippet.trim().to_string(),
+            })
+        })
+        .take(count as usize)
+        .collect()
+}
+
+/// The engine named `name`, if it's known and configured.
+pub fn engine(name: &str, config: &WebSearchConfig) -> Option<Arc<dyn SearchProvider>> {
+    let env_or = |value: &str, var: &str| {
+        Some(value.to_string())
+            .filter(|v| !v.is_empty())
+            .or_else(|| std::env::var(var).ok().filter(|v| !v.is_empty()))
+    };
+    match name.trim().to_lowercase().as_str() {
+        "brave" => env_or(&config.api_key, "BRAVE_API_KEY").map(|api_key| Arc::new(BraveSearch { api_key }) as _),
+        "searxng" | "searx" => env_or(&config.searxng_url, "SEARXNG_URL").map(|base_url| Arc::new(SearxngSearch { base_url }) as _),
+        "tavily" => env_or(&config.tavily_api_key, "TAVILY_API_KEY").map(|api_key| Arc::new(TavilySearch { api_key }) as _),
+        "duckduckgo" | "ddg" => Some(Arc::new(DuckDuckGoSearch)),
+        other => {
+            tracing::warn!("Unknown search provider '{}'", other);
+            None
+        }
+    }
+}
+
+/// Primary engine plus fallbacks, skipping unconfigured and repeated ones.
+pub fn engines(config: &WebSearchConfig) -> Vec<Arc<dyn SearchProvider>> {
+    let mut engines: Vec<Arc<dyn SearchProvider>> = Vec::new();
+    for name in std::iter::once(&config.provider).chain(&config.fallback) {
+        if let Some(engine) = engine(name, config) {
+            if !engines.iter().any(|e| e.name() == engine.name()) {
+                engines.push(engine);
+            }
+        }
+    }
+    engines
+}
+
+/// Ask each engine in turn until one answers. Returns the engine's name and
+/// its results, or every engine's error.
+pub async fn search_with_fallback(
+    engines: &[Arc<dyn SearchProvider>],
+    query: &str,
+    count: u32,
+) -> Result<(&'static str, Vec<SearchResult>), Vec<SearchError>> {
+    let mut errors = Vec::new();
+    for engine in engines {
+        match engine.search(query, count).await {
+            Ok(results) => return Ok((engine.name(), results)),
+            Err(e) => {
+                tracing::warn!("Search via {} failed, trying next engine: {}", engine.name(), e);
+                errors.push(e);
+            }
+        }
+    }
+    Err(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Failing(&'static str);
+
+    #[async_trait]
+    impl SearchProvider for Failing {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+        async fn search(&self, _query: &str, _count: u32) -> Result<Vec<SearchResult>, SearchError> {
+            Err(SearchError::RateLimited { engine: self.0 })
+        }
+    }
+
+    struct Fixed;
+
+    #[async_trait]
+    impl SearchProvider for Fixed {
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+        async fn search(&self, query: &str, _count: u32) -> Result<Vec<SearchResult>, SearchError> {
+            Ok(vec![SearchResult { title: query.into(), url: "https://example.com".into(), snippet: String::new() }])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fallback_after_rate_limit() {
+        let engines: Vec<Arc<dyn SearchProvider>> = vec![Arc::new(Failing("brave")), Arc::new(Fixed)];
+        let (engine, results) = search_with_fallback(&engines, "rust", 5).await.unwrap();
+        assert_eq!(engine, "fixed");
+        assert_eq!(results[0].title, "rust");
+
+        let errors = search_with_fallback(&engines[..1], "rust", 5).await.unwrap_err();
+        assert!(matches!(errors[0], SearchError::RateLimited { engine: "brave" }));
+    }
+
+    #[test]
+    fn test_engines_from_config() {
+        let config = WebSearchConfig {
+            api_key: "key".into(),
+            provider: "brave".into(),
+            fallback: vec!["searxng".into(), "duckduckgo".into(), "brave".into()],
+            ..Default::default()
+        };
+        let names: Vec<&str> = engines(&config).iter().map(|e| e.name()).collect();
+        // searxng has no URL here (unless SEARXNG_URL is set), brave isn't repeated
+        assert_eq!(names.first(), Some(&"brave"));
+        assert_eq!(names.last(), Some(&"duckduckgo"));
+        assert_eq!(names.iter().filter(|n| **n == "brave").count(), 1);
+    }
+
+    #[test]
+    fn test_parse_duckduckgo() {
+        let html = r#"<div class="result"><a class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fwww.rust-lang.org%2F&rut=x">Rust</a>
+            <a class="result__snippet">A language empowering everyone.</a></div>"#;
+        assert_eq!(parse_duckduckgo(html, 5), vec![SearchResult {
+            title: "Rust".into(),
+            url: "https://www.rust-lang.org/".into(),
+            snippet: "A language empowering everyone.".into(),
+        }]);
+    }
+
+    #[test]
+    fn test_normalize_skips_results_without_url() {
+        let items = vec![serde_json::json!({"title": "a", "url": "https://a", "content": "x"}), serde_json::json!({"title": "b"})];
+        assert_eq!(normalize(Some(&items), "title", "url", "content", 5).len(), 1);
+    }
+}
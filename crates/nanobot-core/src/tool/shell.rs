@@ -40,7 +40,7 @@ impl ExecTool {
         }
     }
 
-    fn guard_command(&self, command: &str, cwd: &str) -> Option<String> {
+    pub(crate) fn guard_command(&self, command: &str, cwd: &str) -> Option<String> {
         let lower = command.to_lowercase();
 
         for pattern in &self.deny_patterns {
@@ -59,17 +59,13 @@ impl ExecTool {
                 );
             }
 
-            // Check absolute paths
+            // Check absolute paths (`/etc/...`, and `C:\\...` on Windows)
             let cwd_path = Path::new(cwd);
-            let path_re = Regex::new(r#"/[^\s"']+"#).unwrap_or_else(|_| Regex::new(".^").unwrap());
+            let path_re = Regex::new(r#"(?:\b[A-Za-z]:)?[/\\][^\s"']+"#).unwrap_or_else(|_| Regex::new(".^").unwrap());
             for mat in path_re.find_iter(command) {
                 let p = Path::new(mat.as_str());
-                if let Ok(resolved) = p.canonicalize() {
-                    if let Ok(cwd_resolved) = cwd_path.canonicalize() {
-                        if !resolved.starts_with(&cwd_resolved) {
-                            return Some("Error: Command blocked by safety guard (path outside working dir)".to_string());
-                        }
-                    }
+                if p.is_absolute() && p.exists() && !crate::util::is_within(p, cwd_path) {
+                    return Some("Error: Command blocked by safety guard (path outside working dir)".to_string());
                 }
             }
         }
@@ -78,6 +74,21 @@ impl ExecTool {
     }
 }
 
+/// `sh -c <command>`, or `cmd /C <command>` on Windows.
+fn shell_command(command: &str) -> Command {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    cmd.arg(command);
+    cmd
+}
+
 #[async_trait]
 impl Tool for ExecTool {
     fn name(&self) -> &str {
@@ -121,9 +132,7 @@ impl Tool for ExecTool {
 
         let result = tokio::time::timeout(
             Duration::from_secs(self.timeout),
-            Command::new("sh")
-                .arg("-c")
-                .arg(command)
+            shell_command(command)
                 .current_dir(cwd)
                 .output(),
         )
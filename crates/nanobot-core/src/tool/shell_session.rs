@@ -0,0 +1,739 @@
+//! Persistent shell sessions: `cd`, exported variables and activated
+//! virtualenvs survive between commands, unlike [`super::shell::ExecTool`].
+//!
+//! Each named session is a shell on its own pseudo-terminal, in its own
+//! process group, started under the rlimits from [`ExecToolConfig`]. A reader
+//! thread collects everything the terminal prints into a bounded buffer;
+//! `run` writes the command followed by a marker `printf` and waits for the
+//! marker (or the timeout), `read` returns whatever arrived since the last
+//! read. Sessions idle for `session_idle_secs` are closed.
+
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{debug, info};
+
+use super::shell::ExecTool;
+use super::Tool;
+use crate::config::ExecToolConfig;
+
+/// Output kept per session; older output is dropped.
+const MAX_BUFFER: usize = 256 * 1024;
+
+/// Characters of output returned by one call.
+const MAX_RESULT: usize = 10_000;
+
+/// Default wait for `run`.
+const DEFAULT_RUN_TIMEOUT_SECS: u64 = 30;
+
+const MARKER_PREFIX: &str = "__NANOBOT_DONE_";
+
+/// Everything a session's terminal printed, addressed by absolute offsets.
+#[derive(Debug, Default)]
+struct OutputBuffer {
+    text: String,
+    /// Absolute offset of `text[0]`.
+    start: usize,
+    /// Absolute offset up to which output has been returned.
+    cursor: usize,
+    closed: bool,
+}
+
+impl OutputBuffer {
+    fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    fn push(&mut self, chunk: &str) {
+        self.text.push_str(&chunk.replace('\r', ""));
+        if self.text.len() > MAX_BUFFER {
+            let mut cut = self.text.len() - MAX_BUFFER;
+            while !self.text.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.text.drain(..cut);
+            self.start += cut;
+        }
+    }
+
+    /// Output from absolute offset `from` (clamped to what is still kept).
+    fn since(&self, from: usize) -> &str {
+        &self.text[from.saturating_sub(self.start).min(self.text.len())..]
+    }
+}
+
+/// Remove marker lines a command left behind (e.g. after a timed-out `run`).
+fn strip_markers(text: &str) -> String {
+    text.lines()
+        .filter(|l| !l.starts_with(MARKER_PREFIX))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Locate `marker` followed by an exit code and newline (skipping an echoed
+/// `printf` line). Returns (marker start, end of marker line, exit code).
+fn find_marker(text: &str, marker: &str) -> Option<(usize, usize, i32)> {
+    let mut offset = 0;
+    while let Some(pos) = text[offset..].find(marker) {
+        let at = offset + pos;
+        let rest = &text[at + marker.len()..];
+        let nl = rest.find('\n')?;
+        if let Ok(code) = rest[..nl].trim().parse() {
+            return Some((at, at + marker.len() + nl + 1, code));
+        }
+        offset = at + marker.len();
+    }
+    None
+}
+
+fn truncate(text: &str) -> String {
+    match crate::util::truncate_chars(text, MAX_RESULT) {
+        (head, true) => format!("{}\n... (truncated, {} more chars)", head, text[head.len()..].chars().count()),
+        (head, false) => head.to_string(),
+    }
+}
+
+struct ShellSession {
+    writer: File,
+    pid: i32,
+    output: Arc<StdMutex<OutputBuffer>>,
+    created: Instant,
+    last_used: Instant,
+    runs: u64,
+    /// What `input` typed since the last newline.
+    pending_input: String,
+}
+
+impl ShellSession {
+    #[cfg(unix)]
+    fn spawn(shell: &str, cwd: &Path, config: &ExecToolConfig) -> std::io::Result<Self> {
+        use std::ffi::CStr;
+        use std::os::unix::fs::OpenOptionsExt;
+        use std::os::unix::io::FromRawFd;
+        use std::os::unix::process::CommandExt;
+
+        // ptsname() is not reentrant everywhere
+        static PTSNAME: StdMutex<()> = StdMutex::new(());
+
+        // SAFETY: plain libc pty setup; every fd is checked before use and
+        // owned by a `File` afterwards.
+        let (master, slave) = unsafe {
+            let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let master = File::from_raw_fd(fd);
+            if libc::grantpt(fd) != 0 || libc::unlockpt(fd) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let path = {
+                let _guard = PTSNAME.lock().unwrap_or_else(|e| e.into_inner());
+                let name = libc::ptsname(fd);
+                if name.is_null() {
+                    return Err(std::io::Error::last_os_error());
+                }
+                CStr::from_ptr(name).to_string_lossy().into_owned()
+            };
+            let slave = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .custom_flags(libc::O_NOCTTY)
+                .open(path)?;
+            (master, slave)
+        };
+
+        let mut command = std::process::Command::new(shell);
+        if shell.ends_with("bash") {
+            command.args(["--noprofile", "--norc"]);
+        }
+        command
+            .current_dir(cwd)
+            .env("TERM", "dumb")
+            .env("PS1", "")
+            .env("PS2", "")
+            .env("PAGER", "cat")
+            .env("GIT_PAGER", "cat")
+            .stdin(slave.try_clone()?)
+            .stdout(slave.try_clone()?)
+            .stderr(slave);
+        let memory = config.session_memory_mb * 1024 * 1024;
+        let cpu = config.session_cpu_secs;
+        // SAFETY: only async-signal-safe calls between fork and exec.
+        unsafe {
+            command.pre_exec(move || {
+                // New session with the pty as controlling terminal, so the
+                // whole process group can be killed on close
+                if libc::setsid() < 0 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                for (resource, value) in [(libc::RLIMIT_AS, memory), (libc::RLIMIT_CPU, cpu)] {
+                    if value > 0 {
+                        let limit = libc::rlimit {
+                            rlim_cur: value as libc::rlim_t,
+                            rlim_max: value as libc::rlim_t,
+                        };
+                        if libc::setrlimit(resource, &limit) != 0 {
+                            return Err(std::io::Error::last_os_error());
+                        }
+                    }
+                }
+                Ok(())
+            });
+        }
+        let child = command.spawn()?;
+        let pid = child.id() as i32;
+
+        let output = Arc::new(StdMutex::new(OutputBuffer::default()));
+        let mut reader = master.try_clone()?;
+        let sink = output.clone();
+        std::thread::spawn(move || {
+            let mut child = child;
+            let mut buf = [0u8; 4096];
+            loop {
+                match reader.read(&mut buf) {
+                    // EIO once the shell (the last slave holder) has exited
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => sink
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .push(&String::from_utf8_lossy(&buf[..n])),
+                }
+            }
+            child.wait().ok();
+            sink.lock().unwrap_or_else(|e| e.into_inner()).closed = true;
+        });
+
+        let now = Instant::now();
+        Ok(Self {
+            writer: master,
+            pid,
+            output,
+            created: now,
+            last_used: now,
+            runs: 0,
+            pending_input: String::new(),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn spawn(_shell: &str, _cwd: &Path, _config: &ExecToolConfig) -> std::io::Result<Self> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "shell sessions need a Unix pseudo-terminal",
+        ))
+    }
+
+    fn write(&mut self, input: &str) -> std::io::Result<()> {
+        self.last_used = Instant::now();
+        self.writer.write_all(input.as_bytes())?;
+        self.writer.flush()
+    }
+
+    /// Send raw `text`, but check each line it completes (with what earlier
+    /// input left unfinished) with `guard` first, as `run` does for commands.
+    fn input(&mut self, text: &str, guard: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+        let mut line = self.pending_input.clone();
+        for c in text.chars() {
+            match c {
+                '\n' | '\r' => {
+                    if let Some(error) = guard(line.trim()).filter(|_| !line.trim().is_empty()) {
+                        return Err(error);
+                    }
+                    line.clear();
+                }
+                // Ctrl-C and Ctrl-U drop the line being typed
+                '\u{3}' | '\u{15}' => line.clear(),
+                '\u{8}' | '\u{7f}' => {
+                    line.pop();
+                }
+                c => line.push(c),
+            }
+        }
+        self.write(text).map_err(|e| format!("Error: {e}"))?;
+        self.pending_input = line;
+        Ok(())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.output.lock().unwrap_or_else(|e| e.into_inner()).closed
+    }
+
+    /// Current directory of the shell, where the OS exposes it.
+    fn cwd(&self) -> Option<PathBuf> {
+        std::fs::read_link(format!("/proc/{}/cwd", self.pid)).ok()
+    }
+
+    /// Write `command` and wait up to `timeout` for it to finish. Returns the
+    /// output and the exit code (`None` if still running).
+    async fn run(&mut self, command: &str, timeout: Duration) -> std::io::Result<(String, Option<i32>)> {
+        self.runs += 1;
+        let marker = format!("{}{}_{}__", MARKER_PREFIX, self.pid, self.runs);
+        let from = {
+            let mut out = self.output.lock().unwrap_or_else(|e| e.into_inner());
+            let end = out.end();
+            out.cursor = end;
+            end
+        };
+        self.write(&format!("{}\nprintf '\\n{}%s\\n' \"$?\"\n", command.trim_end(), marker))?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            {
+                let mut out = self.output.lock().unwrap_or_else(|e| e.into_inner());
+                let text = out.since(from);
+                if let Some((at, end, code)) = find_marker(text, &marker) {
+                    let body = text[..at].strip_suffix('\n').unwrap_or(&text[..at]).to_string();
+                    out.cursor = from + end;
+                    return Ok((strip_markers(&body), Some(code)));
+                }
+                if out.closed || Instant::now() >= deadline {
+                    let body = strip_markers(text);
+                    out.cursor = out.end();
+                    return Ok((body, None));
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    /// Output since the last `run`/`read`.
+    fn read(&mut self) -> String {
+        self.last_used = Instant::now();
+        let mut out = self.output.lock().unwrap_or_else(|e| e.into_inner());
+        let text = strip_markers(out.since(out.cursor));
+        out.cursor = out.end();
+        text
+    }
+
+    fn kill(&self) {
+        #[cfg(unix)]
+        // SAFETY: signals the session's own process group.
+        unsafe {
+            libc::kill(-self.pid, libc::SIGKILL);
+        }
+    }
+}
+
+impl Drop for ShellSession {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+/// Sessions by name, each locked on its own so a long `run` in one does not
+/// hold up the others.
+type Sessions = Arc<Mutex<HashMap<String, Arc<Mutex<ShellSession>>>>>;
+
+/// Close sessions idle longer than `idle`. Returns their names. Sessions in
+/// use are not idle.
+async fn reap(sessions: &Sessions, idle: Duration) -> Vec<String> {
+    let mut sessions = sessions.lock().await;
+    let expired: Vec<String> = sessions
+        .iter()
+        .filter(|(_, s)| s.try_lock().is_ok_and(|s| s.last_used.elapsed() > idle || s.is_closed()))
+        .map(|(name, _)| name.clone())
+        .collect();
+    for name in &expired {
+        sessions.remove(name);
+        info!("Shell session '{}' closed (idle or exited)", name);
+    }
+    expired
+}
+
+/// Named persistent shells the agent can create, run commands in, read from and close.
+pub struct ShellSessionTool {
+    sessions: Sessions,
+    guard: ExecTool,
+    workspace: PathBuf,
+    restrict_to_workspace: bool,
+    config: ExecToolConfig,
+    reaper_started: std::sync::atomic::AtomicBool,
+}
+
+impl ShellSessionTool {
+    pub fn new(workspace: PathBuf, config: ExecToolConfig, restrict_to_workspace: bool) -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            guard: ExecTool::new(workspace.display().to_string(), config.timeout, restrict_to_workspace),
+            workspace,
+            restrict_to_workspace,
+            config,
+            reaper_started: std::sync::atomic::AtomicBool::new(false),
+        }
+    }
+
+    fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.config.session_idle_secs.max(1))
+    }
+
+    /// Close idle sessions in the background; stops once the tool is dropped.
+    fn start_reaper(&self) {
+        if self.reaper_started.swap(true, std::sync::atomic::Ordering::SeqCst) {
+            return;
+        }
+        let sessions: Weak<_> = Arc::downgrade(&self.sessions);
+        let idle = self.idle_timeout();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(30).min(idle)).await;
+                let Some(sessions) = sessions.upgrade() else {
+                    break;
+                };
+                reap(&sessions, idle).await;
+            }
+        });
+    }
+
+    fn resolve_cwd(&self, cwd: Option<&str>) -> Result<PathBuf, String> {
+        let Some(cwd) = cwd else {
+            return Ok(self.workspace.clone());
+        };
+        let path = if Path::new(cwd).is_absolute() {
+            PathBuf::from(cwd)
+        } else {
+            self.workspace.join(cwd)
+        };
+        if self.restrict_to_workspace {
+            let inside = path.is_dir() && crate::util::is_within(&path, &self.workspace);
+            if !inside {
+                return Err("Error: cwd must be inside the workspace".to_string());
+            }
+        }
+        Ok(path)
+    }
+
+    async fn create(&self, name: &str, cwd: Option<&str>, shell: Option<&str>) -> Result<String, String> {
+        let cwd = self.resolve_cwd(cwd)?;
+        let mut sessions = self.sessions.lock().await;
+        if sessions.contains_key(name) {
+            return Err(format!("Error: session '{}' already exists", name));
+        }
+        if sessions.len() >= self.config.max_sessions {
+            return Err(format!(
+                "Error: too many shell sessions (max {}); close one first",
+                self.config.max_sessions
+            ));
+        }
+        let shell = shell
+            .map(String::from)
+            .or_else(|| which::which("bash").ok().map(|p| p.display().to_string()))
+            .unwrap_or_else(|| "/bin/sh".to_string());
+        let mut session = ShellSession::spawn(&shell, &cwd, &self.config)
+            .map_err(|e| format!("Error: failed to start shell: {e}"))?;
+        // Quiet the terminal so output is just what commands print
+        session
+            .run("stty -echo -onlcr 2>/dev/null", Duration::from_secs(5))
+            .await
+            .map_err(|e| format!("Error: failed to start shell: {e}"))?;
+        session.read();
+        sessions.insert(name.to_string(), Arc::new(Mutex::new(session)));
+        drop(sessions);
+        self.start_reaper();
+        debug!("Shell session '{}' started in {}", name, cwd.display());
+        Ok(format!("Session '{}' started in {} ({})", name, cwd.display(), shell))
+    }
+
+    async fn run(&self, name: &str, command: &str, timeout_secs: u64) -> Result<String, String> {
+        if let Some(error) = self.guard.guard_command(command, &self.workspace.display().to_string()) {
+            return Err(error);
+        }
+        if !self.sessions.lock().await.contains_key(name) {
+            self.create(name, None, None).await?;
+        }
+        let session = self.session(name).await?;
+        let mut session = session.lock().await;
+        // Unfinished `input` would prefix the command on the shell's line
+        if !session.pending_input.is_empty() {
+            let line = format!("{}{}", session.pending_input, command);
+            if let Some(error) = self.guard.guard_command(&line, &self.workspace.display().to_string()) {
+                return Err(error);
+            }
+            session.pending_input.clear();
+        }
+        if timeout_secs == 0 {
+            session.runs += 1;
+            session
+                .write(&format!("{}\n", command.trim_end()))
+                .map_err(|e| format!("Error: {e}"))?;
+            return Ok("(started; use action=read for output)".to_string());
+        }
+        let (output, code) = session
+            .run(command, Duration::from_secs(timeout_secs))
+            .await
+            .map_err(|e| format!("Error: {e}"))?;
+        let mut result = if output.trim().is_empty() {
+            "(no output)".to_string()
+        } else {
+            truncate(&output)
+        };
+        match code {
+            Some(0) => {}
+            Some(code) => result.push_str(&format!("\n\nExit code: {}", code)),
+            None if session.is_closed() => result.push_str("\n\n(session exited)"),
+            None => result.push_str(&format!(
+                "\n\n(still running after {}s; use action=read for more output or action=input to send Ctrl-C: \"\\u0003\")",
+                timeout_secs
+            )),
+        }
+        Ok(result)
+    }
+
+    async fn session(&self, name: &str) -> Result<Arc<Mutex<ShellSession>>, String> {
+        self.sessions
+            .lock()
+            .await
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Error: no session '{}'", name))
+    }
+
+    async fn with_session<T>(&self, name: &str, f: impl FnOnce(&mut ShellSession) -> T) -> Result<T, String> {
+        let session = self.session(name).await?;
+        let mut session = session.lock().await;
+        Ok(f(&mut session))
+    }
+
+    async fn list(&self) -> String {
+        let sessions = self.sessions.lock().await;
+        if sessions.is_empty() {
+            return "No shell sessions.".to_string();
+        }
+        let mut names: Vec<_> = sessions.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| match sessions[name].try_lock() {
+                Ok(s) => format!(
+                    "{}: {} (up {}s, idle {}s{})",
+                    name,
+                    s.cwd().map(|p| p.display().to_string()).unwrap_or_else(|| "?".to_string()),
+                    s.created.elapsed().as_secs(),
+                    s.last_used.elapsed().as_secs(),
+                    if s.is_closed() { ", exited" } else { "" }
+                ),
+                Err(_) => format!("{}: (running a command)", name),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[async_trait]
+impl Tool for ShellSessionTool {
+    fn name(&self) -> &str {
+        "shell_session"
+    }
+
+    fn description(&self) -> &str {
+        "Persistent shell sessions: `cd`, environment variables and virtualenv activation \
+         persist between commands. Actions: create, run (waits up to timeout_secs; creates the \
+         session if needed), read (new output, e.g. from a long-running command), input (raw \
+         text to the terminal, e.g. answers to prompts; lines are checked like commands), close, list. Idle sessions are closed automatically."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["create", "run", "read", "input", "close", "list"]
+                },
+                "session": {
+                    "type": "string",
+                    "description": "Session name (default: \"default\")"
+                },
+                "command": {
+                    "type": "string",
+                    "description": "Command for action=run"
+                },
+                "timeout_secs": {
+                    "type": "integer",
+                    "description": "How long run waits for the command (default 30; 0 = don't wait)"
+                },
+                "text": {
+                    "type": "string",
+                    "description": "Raw input for action=input (\"\\u0003\" is Ctrl-C)"
+                },
+                "cwd": {
+                    "type": "string",
+                    "description": "Starting directory for action=create"
+                },
+                "shell": {
+                    "type": "string",
+                    "description": "Shell binary for action=create (default bash, else sh)"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        let str_param = |key: &str| params.get(key).and_then(|v| v.as_str());
+        let action = str_param("action").unwrap_or("run");
+        let name = str_param("session").filter(|s| !s.trim().is_empty()).unwrap_or("default");
+
+        reap(&self.sessions, self.idle_timeout()).await;
+
+        let result = match action {
+            "create" => self.create(name, str_param("cwd"), str_param("shell")).await,
+            "run" => match str_param("command") {
+                Some(command) => {
+                    let timeout = params
+                        .get("timeout_secs")
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or(DEFAULT_RUN_TIMEOUT_SECS)
+                        .min(self.config.timeout.max(1));
+                    self.run(name, command, timeout).await
+                }
+                None => Err("Error: 'command' parameter is required".to_string()),
+            },
+            "read" => self
+                .with_session(name, |s| {
+                    let text = s.read();
+                    let closed = if s.is_closed() { "\n(session exited)" } else { "" };
+                    if text.trim().is_empty() {
+                        format!("(no new output){}", closed)
+                    } else {
+                        format!("{}{}", truncate(&text), closed)
+                    }
+                })
+                .await,
+            "input" => match str_param("text") {
+                Some(text) => {
+                    let workspace = self.workspace.display().to_string();
+                    let guard = |line: &str| self.guard.guard_command(line, &workspace);
+                    self.with_session(name, |s| s.input(text, guard).map(|_| "Sent.".to_string()))
+                        .await
+                        .and_then(|r| r)
+                }
+                None => Err("Error: 'text' parameter is required".to_string()),
+            },
+            "close" => match self.sessions.lock().await.remove(name) {
+                Some(_) => Ok(format!("Session '{}' closed", name)),
+                None => Err(format!("Error: no session '{}'", name)),
+            },
+            "list" => Ok(self.list().await),
+            other => Err(format!("Error: unknown action '{}'", other)),
+        };
+        result.unwrap_or_else(|e| e)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, serde_json::Value)]) -> HashMap<String, serde_json::Value> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+    }
+
+    #[test]
+    fn test_truncate_counts_chars() {
+        let text = "あ".repeat(MAX_RESULT + 5);
+        let out = truncate(&text);
+        assert!(out.starts_with(&"あ".repeat(MAX_RESULT)));
+        assert!(out.ends_with("(truncated, 5 more chars)"));
+        assert_eq!(truncate("short"), "short");
+    }
+
+    #[tokio::test]
+    async fn test_state_persists_between_runs() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir(tmp.path().join("sub")).unwrap();
+        let tool = ShellSessionTool::new(tmp.path().to_path_buf(), ExecToolConfig::default(), false);
+
+        let run = |cmd: &str| params(&[("action", json!("run")), ("command", json!(cmd))]);
+        assert_eq!(tool.execute(run("cd sub && export GREETING=hi")).await, "(no output)");
+        let out = tool.execute(run("echo $GREETING; basename $(pwd)")).await;
+        assert_eq!(out, "hi\nsub");
+        let out = tool.execute(run("false")).await;
+        assert!(out.ends_with("Exit code: 1"), "{out}");
+
+        let list = tool.execute(params(&[("action", json!("list"))])).await;
+        assert!(list.starts_with("default: "), "{list}");
+        let closed = tool.execute(params(&[("action", json!("close"))])).await;
+        assert_eq!(closed, "Session 'default' closed");
+        let list = tool.execute(params(&[("action", json!("list"))])).await;
+        assert_eq!(list, "No shell sessions.");
+    }
+
+    #[tokio::test]
+    async fn test_background_output_and_limits() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = ExecToolConfig {
+            max_sessions: 1,
+            ..Default::default()
+        };
+        let tool = ShellSessionTool::new(tmp.path().to_path_buf(), config, false);
+
+        let out = tool
+            .execute(params(&[
+                ("action", json!("run")),
+                ("session", json!("bg")),
+                ("command", json!("echo one; sleep 0.3; echo two")),
+                ("timeout_secs", json!(0)),
+            ]))
+            .await;
+        assert!(out.starts_with("(started"));
+        tokio::time::sleep(Duration::from_millis(800)).await;
+        let out = tool.execute(params(&[("action", json!("read")), ("session", json!("bg"))])).await;
+        assert_eq!(out, "one\ntwo");
+
+        let out = tool.execute(params(&[("action", json!("create")), ("session", json!("other"))])).await;
+        assert!(out.contains("too many shell sessions"), "{out}");
+    }
+
+    #[tokio::test]
+    async fn test_input_is_guarded_and_sessions_run_independently() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tool = ShellSessionTool::new(tmp.path().to_path_buf(), ExecToolConfig::default(), false);
+        let input = |text: &str| params(&[("action", json!("input")), ("text", json!(text))]);
+        tool.execute(params(&[("action", json!("create"))])).await;
+
+        assert!(tool.execute(input("rm -rf ~\n")).await.contains("blocked"));
+        // Split across calls, and finished by `run`
+        assert_eq!(tool.execute(input("rm -r")).await, "Sent.");
+        assert!(tool.execute(input("f ~\n")).await.contains("blocked"));
+        let run = params(&[("action", json!("run")), ("command", json!("f ~"))]);
+        assert!(tool.execute(run).await.contains("blocked"));
+        // Ctrl-C drops the typed line
+        assert_eq!(tool.execute(input("\u{3}echo ok\n")).await, "Sent.");
+
+        // A command waiting in one session does not block another
+        let slow = params(&[("action", json!("run")), ("session", json!("slow")), ("command", json!("sleep 2"))]);
+        let quick = params(&[("action", json!("run")), ("session", json!("quick")), ("command", json!("echo hi"))]);
+        tool.execute(params(&[("action", json!("create")), ("session", json!("slow"))])).await;
+        tool.execute(params(&[("action", json!("create")), ("session", json!("quick"))])).await;
+        let started = Instant::now();
+        let (_, out) = tokio::join!(tool.execute(slow), async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let out = tool.execute(quick).await;
+            (out, started.elapsed())
+        });
+        assert_eq!(out.0, "hi");
+        assert!(out.1 < Duration::from_millis(1500), "{:?}", out.1);
+    }
+
+    #[test]
+    fn test_output_buffer_bounds() {
+        let mut buf = OutputBuffer::default();
+        buf.push("a\r\n");
+        assert_eq!(buf.since(0), "a\n");
+        buf.push(&"x".repeat(MAX_BUFFER));
+        assert_eq!(buf.text.len(), MAX_BUFFER);
+        assert_eq!(buf.start, 2);
+        assert_eq!(buf.since(0).len(), MAX_BUFFER);
+        assert_eq!(strip_markers("out\n__NANOBOT_DONE_1_2__0\nmore"), "out\nmore");
+        let echoed = "printf '\\n__NANOBOT_DONE_1_2__%s\\n' \"$?\"\nok\n__NANOBOT_DONE_1_2__3\n";
+        let (at, end, code) = find_marker(echoed, "__NANOBOT_DONE_1_2__").unwrap();
+        assert!(echoed[..at].ends_with("\nok\n"));
+        assert_eq!((end, code), (echoed.len(), 3));
+    }
+}
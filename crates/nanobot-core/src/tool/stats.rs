@@ -0,0 +1,259 @@
+//! Tool usage analytics.
+//!
+//! Every tool call is counted per user and day: how often it ran, how often
+//! it failed, how long it took, and how many tokens its result added to the
+//! conversation. Those tokens are priced as input to the model that reads
+//! them, so a chatty `web_fetch` shows up as the cost it really causes.
+//! Served by `/api/v1/usage/tools` and `chatweb usage --tools`.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent::prompt::estimate_tokens;
+use crate::provider::pricing::calculate_cost;
+
+/// Totals for one tool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ToolStats {
+    pub calls: u64,
+    pub failures: u64,
+    pub total_latency_ms: u64,
+    pub max_latency_ms: u64,
+    /// Estimated tokens of the results fed back to the model.
+    pub result_tokens: u64,
+    /// Those tokens priced as model input, in USD.
+    pub downstream_cost_usd: f64,
+}
+
+impl ToolStats {
+    fn add(&mut self, other: &ToolStats) {
+        self.calls += other.calls;
+        self.failures += other.failures;
+        self.total_latency_ms += other.total_latency_ms;
+        self.max_latency_ms = self.max_latency_ms.max(other.max_latency_ms);
+        self.result_tokens += other.result_tokens;
+        self.downstream_cost_usd += other.downstream_cost_usd;
+    }
+
+    pub fn avg_latency_ms(&self) -> u64 {
+        self.total_latency_ms.checked_div(self.calls).unwrap_or(0)
+    }
+
+    /// Share of calls that failed, `0.0..=1.0`.
+    pub fn failure_rate(&self) -> f64 {
+        match self.calls {
+            0 => 0.0,
+            n => self.failures as f64 / n as f64,
+        }
+    }
+}
+
+/// One tool's totals in a [`ToolStatsStore::summary`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolUsage {
+    pub tool: String,
+    #[serde(flatten)]
+    pub stats: ToolStats,
+}
+
+/// All tools' totals on one day, see [`ToolStatsStore::daily`].
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyUsage {
+    /// `YYYY-MM-DD` (UTC).
+    pub day: String,
+    #[serde(flatten)]
+    pub stats: ToolStats,
+}
+
+/// A day file: user -> tool -> totals.
+type DayStats = BTreeMap<String, BTreeMap<String, ToolStats>>;
+
+/// Whether a tool result reports a failure.
+pub fn is_failure(result: &str) -> bool {
+    result.starts_with("[TOOL_ERROR]") || result.starts_with("Error")
+}
+
+/// Serializes read-modify-write of the day files; the gateway's agent loop
+/// and HTTP API each have a store on the same directory.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Per-day JSON files (`<dir>/<YYYY-MM-DD>.json`) of tool totals.
+pub struct ToolStatsStore {
+    dir: PathBuf,
+}
+
+impl ToolStatsStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `<data dir>/tool_stats`.
+    pub fn default_path() -> PathBuf {
+        crate::config::get_data_dir().join("tool_stats")
+    }
+
+    /// Count one call of `tool` by `user` whose result was read by `model`.
+    pub fn record(&self, user: &str, tool: &str, latency: Duration, result: &str, model: &str) {
+        let day = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        self.record_on(&day, user, tool, latency, result, model);
+    }
+
+    fn record_on(&self, day: &str, user: &str, tool: &str, latency: Duration, result: &str, model: &str) {
+        let tokens = estimate_tokens(result) as u64;
+        let latency_ms = latency.as_millis() as u64;
+        let call = ToolStats {
+            calls: 1,
+            failures: is_failure(result) as u64,
+            total_latency_ms: latency_ms,
+            max_latency_ms: latency_ms,
+            result_tokens: tokens,
+            downstream_cost_usd: calculate_cost(model, tokens.min(u32::MAX as u64) as u32, 0),
+        };
+
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let path = self.day_path(day);
+        let mut stats = load_day(&path);
+        stats.entry(user.to_string()).or_default().entry(tool.to_string()).or_default().add(&call);
+        crate::util::write_json(&path, &stats, "tool stats");
+    }
+
+    /// Totals per tool over the last `days` days (today included), for one
+    /// user or everyone; most expensive first.
+    pub fn summary(&self, user: Option<&str>, days: u32) -> Vec<ToolUsage> {
+        self.summary_of(user, &last_days(days))
+    }
+
+    /// Totals of all tools and users per day over the last `days` days,
+    /// oldest first, for usage charts.
+    pub fn daily(&self, days: u32) -> Vec<DailyUsage> {
+        let mut days = last_days(days);
+        days.reverse();
+        days.into_iter().map(|day| self.daily_of(day)).collect()
+    }
+
+    fn daily_of(&self, day: String) -> DailyUsage {
+        let mut stats = ToolStats::default();
+        for tools in load_day(&self.day_path(&day)).values() {
+            tools.values().for_each(|s| stats.add(s));
+        }
+        DailyUsage { day, stats }
+    }
+
+    fn summary_of(&self, user: Option<&str>, days: &[String]) -> Vec<ToolUsage> {
+        let mut totals: BTreeMap<String, ToolStats> = BTreeMap::new();
+        for day in days {
+            for (day_user, tools) in load_day(&self.day_path(day)) {
+                if user.is_some_and(|u| u != day_user) {
+                    continue;
+                }
+                for (tool, stats) in tools {
+                    totals.entry(tool).or_default().add(&stats);
+                }
+            }
+        }
+        let mut rows: Vec<ToolUsage> = totals.into_iter().map(|(tool, stats)| ToolUsage { tool, stats }).collect();
+        rows.sort_by(|a, b| {
+            b.stats
+                .downstream_cost_usd
+                .total_cmp(&a.stats.downstream_cost_usd)
+                .then(b.stats.calls.cmp(&a.stats.calls))
+        });
+        rows
+    }
+
+    fn day_path(&self, day: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", day))
+    }
+}
+
+/// The last `days` UTC dates (at least today), newest first.
+fn last_days(days: u32) -> Vec<String> {
+    let today = chrono::Utc::now().date_naive();
+    (0..days.max(1))
+        .filter_map(|back| today.checked_sub_days(chrono::Days::new(back as u64)))
+        .map(|day| day.format("%Y-%m-%d").to_string())
+        .collect()
+}
+
+fn load_day(path: &Path) -> DayStats {
+    crate::util::read_json(path, "tool stats")
+}
+
+/// Table for `chatweb usage --tools`.
+pub fn render(rows: &[ToolUsage]) -> String {
+    if rows.is_empty() {
+        return "No tool calls recorded.\n".to_string();
+    }
+    let mut out = format!(
+        "{:<20} {:>7} {:>7} {:>9} {:>9} {:>11} {:>10}\n",
+        "TOOL", "CALLS", "FAIL %", "AVG MS", "MAX MS", "TOKENS", "COST"
+    );
+    let mut total = ToolStats::default();
+    for row in rows {
+        let s = &row.stats;
+        out.push_str(&format!(
+            "{:<20} {:>7} {:>6.1}% {:>9} {:>9} {:>11} {:>10}\n",
+            row.tool,
+            s.calls,
+            s.failure_rate() * 100.0,
+            s.avg_latency_ms(),
+            s.max_latency_ms,
+            s.result_tokens,
+            format!("${:.4}", s.downstream_cost_usd),
+        ));
+        total.add(s);
+    }
+    out.push_str(&format!(
+        "\n{} calls, {} failed, {} result tokens, ${:.4} downstream\n",
+        total.calls, total.failures, total.result_tokens, total.downstream_cost_usd
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_summary() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ToolStatsStore::new(dir.path());
+        let ms = Duration::from_millis;
+        let page = "x".repeat(40_000);
+        store.record_on("2026-10-17", "line:u1", "web_fetch", ms(800), &page, "gpt-4o");
+        store.record_on("2026-10-18", "line:u1", "web_fetch", ms(200), "Error: timed out", "gpt-4o");
+        store.record_on("2026-10-18", "line:u1", "calculator", ms(5), "42", "gpt-4o");
+        store.record_on("2026-10-18", "telegram:u2", "calculator", ms(7), "7", "gpt-4o");
+
+        let days = ["2026-10-17".to_string(), "2026-10-18".to_string()];
+        let rows = store.summary_of(Some("line:u1"), &days);
+        assert_eq!(rows[0].tool, "web_fetch");
+        assert_eq!(rows[0].stats.calls, 2);
+        assert_eq!(rows[0].stats.failures, 1);
+        assert_eq!(rows[0].stats.avg_latency_ms(), 500);
+        assert_eq!(rows[0].stats.max_latency_ms, 800);
+        assert!(rows[0].stats.result_tokens >= 10_000);
+        assert_eq!(rows[1].stats.calls, 1);
+
+        let everyone = store.summary_of(None, &days[1..]);
+        let calculator = everyone.iter().find(|r| r.tool == "calculator").unwrap();
+        assert_eq!(calculator.stats.calls, 2);
+        assert!(render(&everyone).contains("calculator"));
+
+        let day = store.daily_of("2026-10-18".to_string());
+        assert_eq!((day.stats.calls, day.stats.failures), (3, 1));
+        assert_eq!(store.daily(3).len(), 3);
+    }
+
+    #[test]
+    fn test_is_failure() {
+        assert!(is_failure("[TOOL_ERROR] Unknown tool: x"));
+        assert!(is_failure("Error: Tool 'x' not found"));
+        assert!(!is_failure("[NO_RESULTS] Empty response"));
+    }
+}
@@ -0,0 +1,221 @@
+//! `list_symbols` and `find_definition`, backed by [`SymbolIndex`].
+
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::Tool;
+use crate::code_intel::{Symbol, SymbolIndex, SymbolKind};
+
+const MAX_LISTED: usize = 300;
+const MAX_DEFINITIONS: usize = 5;
+
+/// Lines of a definition shown by `find_definition`.
+const MAX_SNIPPET_LINES: usize = 40;
+
+fn kind_param(params: &HashMap<String, serde_json::Value>) -> Result<Option<SymbolKind>, String> {
+    match params.get("kind").and_then(|v| v.as_str()).filter(|k| !k.is_empty()) {
+        Some(kind) => SymbolKind::parse(kind)
+            .map(Some)
+            .ok_or_else(|| format!("Error: unknown kind '{}'", kind)),
+        None => Ok(None),
+    }
+}
+
+async fn refreshed(index: &Arc<SymbolIndex>) {
+    let index = index.clone();
+    tokio::task::spawn_blocking(move || index.refresh()).await.ok();
+}
+
+fn format_symbol(s: &Symbol) -> String {
+    format!("{}:{} {} {} — {}", s.file, s.line, s.kind.label(), s.qualified_name(), s.signature)
+}
+
+// ====== ListSymbolsTool ======
+
+pub struct ListSymbolsTool {
+    index: Arc<SymbolIndex>,
+}
+
+impl ListSymbolsTool {
+    pub fn new(index: Arc<SymbolIndex>) -> Self {
+        Self { index }
+    }
+}
+
+#[async_trait]
+impl Tool for ListSymbolsTool {
+    fn name(&self) -> &str {
+        "list_symbols"
+    }
+
+    fn description(&self) -> &str {
+        "List functions, types and methods defined in a workspace file or directory \
+         (Rust, Python, JavaScript/TypeScript, Go), with line numbers and signatures."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "File or directory relative to the workspace (default: everything)"
+                },
+                "query": {
+                    "type": "string",
+                    "description": "Only symbols whose name contains this (case-insensitive)"
+                },
+                "kind": {
+                    "type": "string",
+                    "description": "Only this kind: fn, method, struct, enum, trait, interface, class, type, const, mod, macro"
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        let kind = match kind_param(&params) {
+            Ok(kind) => kind,
+            Err(e) => return e,
+        };
+        let path = params.get("path").and_then(|v| v.as_str());
+        let query = params
+            .get("query")
+            .and_then(|v| v.as_str())
+            .map(|q| q.to_lowercase());
+
+        refreshed(&self.index).await;
+        let symbols: Vec<Symbol> = self
+            .index
+            .symbols(path)
+            .into_iter()
+            .filter(|s| kind.is_none_or(|k| s.kind == k))
+            .filter(|s| query.as_ref().is_none_or(|q| s.name.to_lowercase().contains(q)))
+            .collect();
+        if symbols.is_empty() {
+            return format!("No symbols found in {}", path.unwrap_or("the workspace"));
+        }
+        let mut lines: Vec<String> = symbols.iter().take(MAX_LISTED).map(format_symbol).collect();
+        if symbols.len() > MAX_LISTED {
+            lines.push(format!("... ({} more; narrow with path, query or kind)", symbols.len() - MAX_LISTED));
+        }
+        lines.join("\n")
+    }
+}
+
+// ====== FindDefinitionTool ======
+
+pub struct FindDefinitionTool {
+    index: Arc<SymbolIndex>,
+}
+
+impl FindDefinitionTool {
+    pub fn new(index: Arc<SymbolIndex>) -> Self {
+        Self { index }
+    }
+}
+
+#[async_trait]
+impl Tool for FindDefinitionTool {
+    fn name(&self) -> &str {
+        "find_definition"
+    }
+
+    fn description(&self) -> &str {
+        "Find where a function, type or method is defined in the workspace and show its source. \
+         Accepts plain names or Type::method / Class.method."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "description": "Symbol name, e.g. parse_config, Config::new, App.render"
+                },
+                "kind": {
+                    "type": "string",
+                    "description": "Only this kind: fn, method, struct, enum, trait, interface, class, type, const, mod, macro"
+                }
+            },
+            "required": ["name"]
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        let name = match params.get("name").and_then(|v| v.as_str()) {
+            Some(n) if !n.trim().is_empty() => n.trim(),
+            _ => return "Error: 'name' parameter is required".to_string(),
+        };
+        let kind = match kind_param(&params) {
+            Ok(kind) => kind,
+            Err(e) => return e,
+        };
+
+        refreshed(&self.index).await;
+        let found = self.index.find(name, kind);
+        if found.is_empty() {
+            return format!("No definition of '{}' found. Try grep_workspace for usages.", name);
+        }
+
+        let mut out = Vec::new();
+        for symbol in found.iter().take(MAX_DEFINITIONS) {
+            let path = self.index.workspace().join(&symbol.file);
+            let source = std::fs::read_to_string(&path).unwrap_or_default();
+            let last = symbol.end_line.min(symbol.line + MAX_SNIPPET_LINES - 1);
+            let snippet: Vec<String> = source
+                .lines()
+                .enumerate()
+                .skip(symbol.line - 1)
+                .take(last + 1 - symbol.line)
+                .map(|(n, line)| format!("{:>5}| {}", n + 1, line))
+                .collect();
+            let mut entry = format!(
+                "{}:{}-{} ({} {})\n{}",
+                symbol.file,
+                symbol.line,
+                symbol.end_line,
+                symbol.kind.label(),
+                symbol.qualified_name(),
+                snippet.join("\n")
+            );
+            if symbol.end_line > last {
+                entry.push_str(&format!("\n  ... ({} more lines)", symbol.end_line - last));
+            }
+            out.push(entry);
+        }
+        if found.len() > MAX_DEFINITIONS {
+            out.push(format!("... and {} more definitions", found.len() - MAX_DEFINITIONS));
+        }
+        out.join("\n\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_list_and_find() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            tmp.path().join("app.py"),
+            "class App:\n    def render(self):\n        return 1\n\ndef main():\n    App().render()\n",
+        )
+        .unwrap();
+        let index = Arc::new(SymbolIndex::new(tmp.path().to_path_buf()));
+
+        let list = ListSymbolsTool::new(index.clone())
+            .execute(HashMap::from([("kind".to_string(), json!("method"))]))
+            .await;
+        assert_eq!(list, "app.py:2 method App::render — def render(self):");
+
+        let def = FindDefinitionTool::new(index)
+            .execute(HashMap::from([("name".to_string(), json!("App.render"))]))
+            .await;
+        assert_eq!(def, "app.py:2-3 (method App::render)\n    2|     def render(self):\n    3|         return 1");
+    }
+}
@@ -2,25 +2,33 @@ use async_trait::async_trait;
 use scraper::{Html, Selector};
 use serde_json::json;
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use crate::util::http;
+use crate::config::WebSearchConfig;
+use crate::util::{http, markdown};
+use super::search_provider::{self, SearchProvider};
 use super::Tool;
 
-const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_7_2) AppleWebKit/537.36";
-
-/// Search the web using Brave Search API.
+/// Search the web via the configured engines (see [`super::search_provider`]).
 pub struct WebSearchTool {
-    api_key: String,
+    engines: Vec<Arc<dyn SearchProvider>>,
     max_results: u32,
 }
 
 impl WebSearchTool {
+    /// Brave with the given key (or `BRAVE_API_KEY`), falling back to DuckDuckGo.
     pub fn new(api_key: Option<String>, max_results: u32) -> Self {
-        Self {
-            api_key: api_key
-                .or_else(|| std::env::var("BRAVE_API_KEY").ok())
-                .unwrap_or_default(),
+        Self::from_config(&WebSearchConfig {
+            api_key: api_key.unwrap_or_default(),
             max_results,
+            ..Default::default()
+        })
+    }
+
+    pub fn from_config(config: &WebSearchConfig) -> Self {
+        Self {
+            engines: search_provider::engines(config),
+            max_results: config.max_results,
         }
     }
 }
@@ -47,8 +55,8 @@ impl Tool for WebSearchTool {
     }
 
     async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
-        if self.api_key.is_empty() {
-            return "Error: BRAVE_API_KEY not configured".to_string();
+        if self.engines.is_empty() {
+            return "Error: no search engine configured (tools.web.search)".to_string();
         }
 
         let query = match params.get("query").and_then(|v| v.as_str()) {
@@ -60,47 +68,24 @@ impl Tool for WebSearchTool {
             .get("count")
             .and_then(|v| v.as_u64())
             .unwrap_or(self.max_results as u64)
-            .clamp(1, 10);
-
-        match http::client()
-            .get("https://api.search.brave.com/res/v1/web/search")
-            .query(&[("q", query), ("count", &count.to_string())])
-            .header("Accept", "application/json")
-            .header("X-Subscription-Token", &self.api_key)
-            .timeout(std::time::Duration::from_secs(10))
-            .send()
-            .await
-        {
-            Ok(response) => {
-                if !response.status().is_success() {
-                    return format!("Error: Search API returned {}", response.status());
-                }
-                match response.json::<serde_json::Value>().await {
-                    Ok(data) => {
-                        let results = data
-                            .get("web")
-                            .and_then(|w| w.get("results"))
-                            .and_then(|r| r.as_array());
-                        match results {
-                            Some(results) if !results.is_empty() => {
-                                let mut lines = vec![format!("Results for: {}\n", query)];
-                                for (i, item) in results.iter().take(count as usize).enumerate() {
-                                    let title = item.get("title").and_then(|v| v.as_str()).unwrap_or("");
-                                    let url = item.get("url").and_then(|v| v.as_str()).unwrap_or("");
-                                    lines.push(format!("{}. {}\n   {}", i + 1, title, url));
-                                    if let Some(desc) = item.get("description").and_then(|v| v.as_str()) {
-                                        lines.push(format!("   {desc}"));
-                                    }
-                                }
-                                lines.join("\n")
-                            }
-                            _ => format!("No results for: {query}"),
-                        }
+            .clamp(1, 10) as u32;
+
+        match search_provider::search_with_fallback(&self.engines, query, count).await {
+            Ok((_, results)) if results.is_empty() => format!("No results for: {query}"),
+            Ok((engine, results)) => {
+                let mut lines = vec![format!("Results for: {} (via {})\n", query, engine)];
+                for (i, item) in results.iter().enumerate() {
+                    lines.push(format!("{}. {}\n   {}", i + 1, item.title, item.url));
+                    if !item.snippet.is_empty() {
+                        lines.push(format!("   {}", item.snippet));
                     }
-                    Err(e) => format!("Error parsing response: {e}"),
                 }
+                lines.join("\n")
+            }
+            Err(errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                format!("Error: all search engines failed ({})", errors.join("; "))
             }
-            Err(e) => format!("Error: {e}"),
         }
     }
 }
@@ -123,7 +108,8 @@ impl Tool for WebFetchTool {
     }
 
     fn description(&self) -> &str {
-        "Fetch URL and extract readable content (HTML -> text)."
+        "Fetch URL and extract its main content as markdown (links and tables kept). \
+         Set format to \"html\" only when the raw HTML is needed."
     }
 
     fn parameters(&self) -> serde_json::Value {
@@ -131,6 +117,7 @@ impl Tool for WebFetchTool {
             "type": "object",
             "properties": {
                 "url": {"type": "string", "description": "URL to fetch"},
+                "format": {"type": "string", "enum": ["markdown", "text", "html"], "description": "Output for HTML pages (default markdown)"},
                 "maxChars": {"type": "integer", "minimum": 100}
             },
             "required": ["url"]
@@ -153,61 +140,52 @@ impl Tool for WebFetchTool {
             return json!({"error": "Only http/https URLs allowed", "url": url}).to_string();
         }
 
-        match http::client()
-            .get(url)
-            .header("User-Agent", USER_AGENT)
-            .timeout(std::time::Duration::from_secs(30))
-            .send()
-            .await
-        {
-            Ok(response) => {
-                let status = response.status().as_u16();
-                let final_url = response.url().to_string();
-                let content_type = response
-                    .headers()
-                    .get("content-type")
-                    .and_then(|v| v.to_str().ok())
-                    .unwrap_or("")
-                    .to_string();
-
-                match response.text().await {
-                    Ok(body) => {
-                        let (text, extractor) = if content_type.contains("application/json") {
-                            // Try to pretty-print JSON
-                            let formatted = serde_json::from_str::<serde_json::Value>(&body)
-                                .ok()
-                                .and_then(|v| serde_json::to_string_pretty(&v).ok())
-                                .unwrap_or(body);
-                            (formatted, "json")
-                        } else if content_type.contains("text/html")
-                            || body.trim_start().to_lowercase().starts_with("<!doctype")
-                            || body.trim_start().to_lowercase().starts_with("<html")
-                        {
-                            (strip_html_tags(&body), "html_strip")
-                        } else {
-                            (body, "raw")
-                        };
-
-                        let truncated = text.len() > max_chars;
-                        let text = if truncated {
-                            text[..max_chars].to_string()
-                        } else {
-                            text
-                        };
-
-                        json!({
-                            "url": url,
-                            "finalUrl": final_url,
-                            "status": status,
-                            "extractor": extractor,
-                            "truncated": truncated,
-                            "length": text.len(),
-                            "text": text,
-                        })
-                        .to_string()
-                    }
-                    Err(e) => json!({"error": e.to_string(), "url": url}).to_string(),
-                }
+        let format = params.get("format").and_then(|v| v.as_str()).unwrap_or("markdown");
+
+        match http::fetch_page(url).await {
+            Ok(page) => {
+                let body = page.body;
+                let is_html = page.content_type.contains("text/html")
+                    || body.trim_start().to_lowercase().starts_with("<!doctype")
+                    || body.trim_start().to_lowercase().starts_with("<html");
+                let mut title = None;
+                let (text, extractor) = if page.content_type.contains("application/json") {
+                    // Try to pretty-print JSON
+                    let formatted = serde_json::from_str::<serde_json::Value>(&body)
+                        .ok()
+                        .and_then(|v| serde_json::to_string_pretty(&v).ok())
+                        .unwrap_or(body);
+                    (formatted, "json")
+                } else if !is_html || format == "html" {
+                    (body, "raw")
+                } else if format == "text" {
+                    (strip_html_tags(&body), "html_strip")
+                } else {
+                    let base = reqwest::Url::parse(&page.final_url).ok();
+                    let readable = markdown::readable(&body, base.as_ref());
+                    title = Some(readable.title);
+                    (readable.markdown, "readability")
+                };
+
+                let truncated = text.len() > max_chars;
+                let text = if truncated {
+                    text[..max_chars].to_string()
+                } else {
+                    text
+                };
+
+                json!({
+                    "url": url,
+                    "finalUrl": page.final_url,
+                    "status": page.status,
+                    "cached": page.from_cache,
+                    "title": title,
+                    "extractor": extractor,
+                    "truncated": truncated,
+                    "length": text.len(),
+                    "text": text,
+                })
+                .to_string()
             }
             Err(e) => json!({"error": e.to_string(), "url": url}).to_string(),
         }
@@ -216,7 +194,7 @@ impl Tool for WebFetchTool {
 
 /// Extract readable text from HTML using Servo's html5ever parser (scraper crate).
 /// Skips <script>, <style>, <noscript>, <svg> elements for clean text extraction.
-fn strip_html_tags(html: &str) -> String {
+pub(crate) fn strip_html_tags(html: &str) -> String {
     use scraper::node::Node;
 
     let doc = Html::parse_document(html);
@@ -0,0 +1,393 @@
+//! `youtube_transcript`: captions of a YouTube video with timestamps.
+//!
+//! Caption tracks are listed in the watch page's player response; the track
+//! itself comes from the timedtext endpoint (`fmt=json3`, falling back to the
+//! default XML). The Data API's caption download needs the owner's OAuth, so
+//! it isn't an option for arbitrary videos. Long transcripts are split into
+//! chunks of whole paragraphs, fetched one per call.
+
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::Tool;
+use crate::util::http::{self, ClientClass};
+
+/// Characters per returned chunk unless `maxChars` says otherwise.
+const DEFAULT_CHUNK_CHARS: usize = 12_000;
+/// A new timestamped paragraph starts after this much video time.
+const PARAGRAPH_MS: u64 = 30_000;
+
+/// One caption line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start_ms: u64,
+    pub text: String,
+}
+
+/// A caption track listed on the watch page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptionTrack {
+    pub base_url: String,
+    pub language: String,
+    pub name: String,
+    /// Speech-recognition (`kind: asr`) rather than uploaded captions.
+    pub auto_generated: bool,
+}
+
+/// The 11-character video ID of a watch/short/embed/youtu.be URL or a bare ID.
+pub fn video_id(input: &str) -> Option<String> {
+    let input = input.trim();
+    let is_id = |s: &str| s.len() == 11 && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if is_id(input) {
+        return Some(input.to_string());
+    }
+    let url = reqwest::Url::parse(input).ok()?;
+    let host = url.host_str()?.trim_start_matches("www.").trim_start_matches("m.");
+    let candidate = match host {
+        "youtu.be" => url.path_segments()?.next()?.to_string(),
+        "youtube.com" | "music.youtube.com" | "youtube-nocookie.com" => {
+            match url.query_pairs().find(|(k, _)| k == "v") {
+                Some((_, v)) => v.into_owned(),
+                None => {
+                    let mut segments = url.path_segments()?;
+                    match segments.next()? {
+                        "shorts" | "embed" | "live" | "v" => segments.next()?.to_string(),
+                        _ => return None,
+                    }
+                }
+            }
+        }
+        _ => return None,
+    };
+    is_id(&candidate).then_some(candidate)
+}
+
+/// The JSON array that starts right after `key` in `text`.
+fn json_array_after<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    let start = text.find(key)? + key.len();
+    let rest = text[start..].trim_start();
+    if !rest.starts_with('[') {
+        return None;
+    }
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+    for (i, c) in rest.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '[' if !in_string => depth += 1,
+            ']' if !in_string => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&rest[..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Caption tracks from a watch page.
+pub fn caption_tracks(watch_html: &str) -> Vec<CaptionTrack> {
+    let Some(array) = json_array_after(watch_html, "\"captionTracks\":") else {
+        return vec![];
+    };
+    let tracks: Vec<serde_json::Value> = serde_json::from_str(array).unwrap_or_default();
+    tracks
+        .iter()
+        .filter_map(|t| {
+            let name = t["name"]["simpleText"]
+                .as_str()
+                .map(str::to_string)
+                .or_else(|| t["name"]["runs"][0]["text"].as_str().map(str::to_string))
+                .unwrap_or_default();
+            Some(CaptionTrack {
+                base_url: t["baseUrl"].as_str()?.to_string(),
+                language: t["languageCode"].as_str().unwrap_or("").to_string(),
+                name,
+                auto_generated: t["kind"].as_str() == Some("asr"),
+            })
+        })
+        .collect()
+}
+
+/// Best track for `lang`: uploaded captions in that language, then automatic
+/// ones, then uploaded captions in any language, then whatever there is.
+pub fn pick_track<'a>(tracks: &'a [CaptionTrack], lang: Option<&str>) -> Option<&'a CaptionTrack> {
+    let matches = |t: &CaptionTrack| {
+        lang.is_some_and(|l| t.language == l || t.language.split('-').next() == l.split('-').next())
+    };
+    tracks
+        .iter()
+        .find(|t| matches(t) && !t.auto_generated)
+        .or_else(|| tracks.iter().find(|t| matches(t)))
+        .or_else(|| tracks.iter().find(|t| !t.auto_generated))
+        .or_else(|| tracks.first())
+}
+
+fn page_title(watch_html: &str) -> String {
+    let Some(start) = watch_html.find("<title>") else { return String::new() };
+    let rest = &watch_html[start + 7..];
+    let title = rest.find("</title>").map(|end| &rest[..end]).unwrap_or("");
+    decode_entities(title.trim_end_matches(" - YouTube").trim())
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Cues of a `fmt=json3` track.
+pub fn parse_json3(body: &str) -> Vec<Cue> {
+    let Ok(data) = serde_json::from_str::<serde_json::Value>(body) else {
+        return vec![];
+    };
+    data["events"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|event| {
+            let text: String = event["segs"].as_array()?.iter().filter_map(|s| s["utf8"].as_str()).collect();
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            (!text.is_empty()).then(|| Cue { start_ms: event["tStartMs"].as_u64().unwrap_or(0), text })
+        })
+        .collect()
+}
+
+/// Cues of the default XML track (`<text start="1.2" dur="3.4">…</text>`).
+pub fn parse_xml(body: &str) -> Vec<Cue> {
+    body.split("<text")
+        .skip(1)
+        .filter_map(|part| {
+            let (attrs, rest) = part.split_once('>')?;
+            let content = rest.split("</text>").next()?;
+            let start = attrs
+                .split("start=\"")
+                .nth(1)
+                .and_then(|s| s.split('"').next())
+                .and_then(|s| s.parse::<f64>().ok())
+                .unwrap_or(0.0);
+            // Entities can be double-encoded (`&amp;#39;`)
+            let text = decode_entities(&decode_entities(content));
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            (!text.is_empty()).then_some(Cue { start_ms: (start * 1000.0) as u64, text })
+        })
+        .collect()
+}
+
+/// `m:ss`, or `h:mm:ss` past the hour.
+pub fn timestamp(ms: u64) -> String {
+    let secs = ms / 1000;
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+/// Join cues into timestamped paragraphs and split those into chunks of at
+/// most `max_chars` (a single oversized paragraph gets a chunk of its own).
+pub fn chunk(cues: &[Cue], max_chars: usize) -> Vec<String> {
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut para_start: Option<u64> = None;
+    for cue in cues {
+        match para_start {
+            Some(start) if cue.start_ms < start + PARAGRAPH_MS => {
+                let last = paragraphs.last_mut().expect("paragraph started");
+                last.push(' ');
+                last.push_str(&cue.text);
+            }
+            _ => {
+                para_start = Some(cue.start_ms);
+                paragraphs.push(format!("[{}] {}", timestamp(cue.start_ms), cue.text));
+            }
+        }
+    }
+
+    let mut chunks: Vec<String> = Vec::new();
+    for para in paragraphs {
+        match chunks.last_mut() {
+            Some(last) if last.chars().count() + para.chars().count() < max_chars => {
+                last.push('\n');
+                last.push_str(&para);
+            }
+            _ => chunks.push(para),
+        }
+    }
+    chunks
+}
+
+async fn get(url: &str, lang: Option<&str>) -> Result<String, String> {
+    let response = http::client_for(ClientClass::General)
+        .get(url)
+        .header("Accept-Language", format!("{},en;q=0.5", lang.unwrap_or("en")))
+        // Skips the EU consent interstitial
+        .header("Cookie", "CONSENT=YES+1")
+        .timeout(Duration::from_secs(20))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    response.text().await.map_err(|e| e.to_string())
+}
+
+/// A fetched transcript.
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    pub title: String,
+    pub track: CaptionTrack,
+    pub cues: Vec<Cue>,
+}
+
+/// Fetch the transcript of `video_id`, preferring `lang`.
+pub async fn fetch_transcript(video_id: &str, lang: Option<&str>) -> Result<Transcript, String> {
+    let page = get(&format!("https://www.youtube.com/watch?v={}", video_id), lang).await?;
+    let tracks = caption_tracks(&page);
+    let track = pick_track(&tracks, lang).ok_or("this video has no captions")?.clone();
+
+    let mut cues = match get(&format!("{}&fmt=json3", track.base_url), lang).await {
+        Ok(body) => parse_json3(&body),
+        Err(e) => {
+            tracing::debug!("youtube_transcript: json3 failed for {}: {}", video_id, e);
+            vec![]
+        }
+    };
+    if cues.is_empty() {
+        cues = parse_xml(&get(&track.base_url, lang).await?);
+    }
+    if cues.is_empty() {
+        return Err("the caption track is empty".to_string());
+    }
+    Ok(Transcript { title: page_title(&page), track, cues })
+}
+
+/// Fetch a video's captions as timestamped text, one chunk per call.
+pub struct YouTubeTranscriptTool;
+
+#[async_trait]
+impl Tool for YouTubeTranscriptTool {
+    fn name(&self) -> &str {
+        "youtube_transcript"
+    }
+
+    fn description(&self) -> &str {
+        "Fetch the transcript (captions) of a YouTube video with [m:ss] timestamps, e.g. to summarize a talk. \
+         Long transcripts come in chunks: if `chunks` > 1, call again with the next `chunk` number."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": {"type": "string", "description": "YouTube URL or 11-character video ID"},
+                "language": {"type": "string", "description": "Preferred caption language, e.g. 'ja' or 'en'"},
+                "chunk": {"type": "integer", "description": "Chunk to return (1-based, default 1)", "minimum": 1},
+                "maxChars": {"type": "integer", "minimum": 1000}
+            },
+            "required": ["url"]
+        })
+    }
+
+    async fn execute(&self, params: HashMap<String, serde_json::Value>) -> String {
+        let input = params.get("url").and_then(|v| v.as_str()).unwrap_or("");
+        let Some(id) = video_id(input) else {
+            return json!({"error": "Not a YouTube video URL or ID", "url": input}).to_string();
+        };
+        let lang = params.get("language").and_then(|v| v.as_str()).filter(|l| !l.is_empty());
+        let max_chars = params
+            .get("maxChars")
+            .and_then(|v| v.as_u64())
+            .map(|n| n as usize)
+            .unwrap_or(DEFAULT_CHUNK_CHARS);
+
+        let transcript = match fetch_transcript(&id, lang).await {
+            Ok(t) => t,
+            Err(e) => return json!({"error": format!("No transcript: {}", e), "videoId": id}).to_string(),
+        };
+        let chunks = chunk(&transcript.cues, max_chars);
+        let n = params.get("chunk").and_then(|v| v.as_u64()).unwrap_or(1).max(1) as usize;
+        let Some(text) = chunks.get(n - 1) else {
+            return json!({"error": format!("chunk {} out of range (1-{})", n, chunks.len()), "videoId": id}).to_string();
+        };
+        json!({
+            "videoId": id,
+            "title": transcript.title,
+            "language": transcript.track.language,
+            "autoGenerated": transcript.track.auto_generated,
+            "duration": timestamp(transcript.cues.last().map(|c| c.start_ms).unwrap_or(0)),
+            "chunk": n,
+            "chunks": chunks.len(),
+            "text": text,
+        })
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_video_id() {
+        for input in [
+            "dQw4w9WgXcQ",
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42s",
+            "https://youtu.be/dQw4w9WgXcQ?si=abc",
+            "https://m.youtube.com/shorts/dQw4w9WgXcQ",
+            "https://www.youtube.com/embed/dQw4w9WgXcQ",
+        ] {
+            assert_eq!(video_id(input).as_deref(), Some("dQw4w9WgXcQ"), "{input}");
+        }
+        assert_eq!(video_id("https://example.com/watch?v=dQw4w9WgXcQ"), None);
+        assert_eq!(video_id("https://www.youtube.com/@channel"), None);
+    }
+
+    #[test]
+    fn test_caption_tracks_and_pick() {
+        let page = r#"<title>Talk &amp; Demo - YouTube</title>...{"captions":{"playerCaptionsTracklistRenderer":{"captionTracks":[
+            {"baseUrl":"https://www.youtube.com/api/timedtext?v=x&lang=en","name":{"simpleText":"English [auto]"},"languageCode":"en","kind":"asr"},
+            {"baseUrl":"https://www.youtube.com/api/timedtext?v=x&lang=ja","name":{"runs":[{"text":"日本語"}]},"languageCode":"ja"}
+        ],"audioTracks":[]}}}"#;
+        let tracks = caption_tracks(page);
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].base_url, "https://www.youtube.com/api/timedtext?v=x&lang=en");
+        assert!(tracks[0].auto_generated);
+        assert_eq!(tracks[1].name, "日本語");
+        assert_eq!(page_title(page), "Talk & Demo");
+
+        assert_eq!(pick_track(&tracks, Some("en")).unwrap().language, "en");
+        assert_eq!(pick_track(&tracks, Some("ja-JP")).unwrap().language, "ja");
+        // No French: uploaded captions beat automatic ones
+        assert_eq!(pick_track(&tracks, Some("fr")).unwrap().language, "ja");
+    }
+
+    #[test]
+    fn test_parse_tracks() {
+        let json3 = r#"{"events":[{"tStartMs":0,"segs":[{"utf8":"Hello"},{"utf8":" world"}]},{"tStartMs":1500},{"tStartMs":2000,"segs":[{"utf8":"\n"}]}]}"#;
+        assert_eq!(parse_json3(json3), vec![Cue { start_ms: 0, text: "Hello world".into() }]);
+
+        let xml = r#"<?xml version="1.0"?><transcript><text start="1.5" dur="2">It&amp;#39;s
+here</text><text start="65" dur="1">next</text></transcript>"#;
+        assert_eq!(
+            parse_xml(xml),
+            vec![Cue { start_ms: 1500, text: "It's here".into() }, Cue { start_ms: 65000, text: "next".into() }]
+        );
+    }
+
+    #[test]
+    fn test_chunk() {
+        let cue = |s: u64, t: &str| Cue { start_ms: s * 1000, text: t.into() };
+        let cues = [cue(0, "intro"), cue(10, "still intro"), cue(40, "part two"), cue(3700, "late")];
+        assert_eq!(chunk(&cues, 1000), vec!["[0:00] intro still intro\n[0:40] part two\n[1:01:40] late"]);
+        assert_eq!(chunk(&cues, 20).len(), 3);
+    }
+}
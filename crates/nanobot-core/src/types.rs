@@ -1,3 +1,5 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -125,12 +127,42 @@ pub struct CompletionResponse {
     pub tool_calls: Vec<ToolCall>,
     pub finish_reason: FinishReason,
     pub usage: TokenUsage,
+    /// Model that actually answered, as reported by the API or set by a
+    /// failover; `None` when unknown (assume the requested one).
+    pub model: Option<String>,
 }
 
 impl CompletionResponse {
     pub fn has_tool_calls(&self) -> bool {
         !self.tool_calls.is_empty()
     }
+
+    /// The model that answered, falling back to `requested`.
+    pub fn model_or<'a>(&'a self, requested: &'a str) -> &'a str {
+        self.model.as_deref().unwrap_or(requested)
+    }
+
+    /// Whether a different model family answered than `requested` (say
+    /// gemini for a claude request). Versions and vendor prefixes within a
+    /// family don't count.
+    pub fn switched_family(&self, requested: &str) -> bool {
+        self.model.as_deref().is_some_and(|served| model_family(served) != model_family(requested))
+    }
+}
+
+/// Family of a model name: `anthropic/claude-sonnet-4-5` and
+/// `claude-3-5-haiku-20241022` are both `claude`.
+pub fn model_family(model: &str) -> String {
+    let name = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+    const FAMILIES: &[&str] = &[
+        "claude", "gpt", "o1", "o3", "o4", "gemini", "gemma", "llama", "qwen", "deepseek", "mistral", "glm",
+        "kimi", "moonshot", "nemotron", "grok",
+    ];
+    FAMILIES
+        .iter()
+        .find(|family| name.starts_with(*family))
+        .map(|family| family.to_string())
+        .unwrap_or_else(|| name.split(['-', ':', '.']).next().unwrap_or(&name).to_string())
 }
 
 /// Token usage information.
@@ -177,7 +209,7 @@ impl InboundMessage {
 }
 
 /// Message to send to a chat channel.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutboundMessage {
     pub channel: String,
     pub chat_id: String,
@@ -185,6 +217,12 @@ pub struct OutboundMessage {
     pub reply_to: Option<String>,
     pub media: Vec<String>,
     pub metadata: HashMap<String, serde_json::Value>,
+    /// Photos, voice notes and files to send with the message.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    /// Rows of buttons under the message, on channels that have them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buttons: Vec<Vec<Button>>,
 }
 
 impl OutboundMessage {
@@ -200,10 +238,251 @@ impl OutboundMessage {
             reply_to: None,
             media: Vec::new(),
             metadata: HashMap::new(),
+            attachments: Vec::new(),
+            buttons: Vec::new(),
+        }
+    }
+
+    /// Show rows of buttons under the message.
+    pub fn with_buttons(mut self, rows: Vec<Vec<Button>>) -> Self {
+        self.buttons = rows;
+        self
+    }
+
+    pub fn with_attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    /// Move media links out of `content` into `attachments`, for channels
+    /// that send media natively: markdown images (how `image_generate`
+    /// returns its picture) and bare links to image, audio or video files.
+    pub fn lift_media(&mut self) {
+        let mut lifted: Vec<Attachment> = Vec::new();
+        let mut lift = |attachment: Attachment| {
+            if !lifted.iter().any(|a| a.source == attachment.source) {
+                lifted.push(attachment);
+            }
+        };
+        let content = MARKDOWN_IMAGE.replace_all(&self.content, |caps: &regex::Captures| {
+            let kind = MediaKind::of(&caps[1]).unwrap_or(MediaKind::Photo);
+            lift(Attachment { kind, source: caps[1].to_string() });
+            ""
+        });
+
+        let mut text = String::with_capacity(content.len());
+        let mut last = 0;
+        for link in BARE_LINK.find_iter(&content) {
+            if !content[..link.start()].chars().next_back().is_none_or(char::is_whitespace) {
+                continue;
+            }
+            let url = link.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?', ')']);
+            if let Some(kind) = MediaKind::of(url) {
+                text.push_str(&content[last..link.start()]);
+                last = link.start() + url.len();
+                lift(Attachment { kind, source: url.to_string() });
+            }
+        }
+        text.push_str(&content[last..]);
+
+        if lifted.is_empty() {
+            return;
+        }
+        let mut text = text.lines().map(str::trim_end).collect::<Vec<_>>().join("\n");
+        while text.contains("\n\n\n") {
+            text = text.replace("\n\n\n", "\n\n");
+        }
+        self.content = text.trim().to_string();
+        self.attachments.extend(lifted);
+    }
+}
+
+static MARKDOWN_IMAGE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"!\[[^\]]*\]\((https?://[^\s)]+)\)").expect("valid regex"));
+static BARE_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"https?://\S+").expect("valid regex"));
+
+/// A photo, voice note or file sent with an [`OutboundMessage`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub kind: MediaKind,
+    /// An http(s) URL or a local file path.
+    pub source: String,
+}
+
+impl Attachment {
+    /// An attachment whose kind is guessed from the file extension;
+    /// unknown extensions are sent as documents.
+    pub fn guess(source: impl Into<String>) -> Self {
+        let source = source.into();
+        Self { kind: MediaKind::of(&source).unwrap_or(MediaKind::Document), source }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    Photo,
+    /// A voice note (OGG/Opus), played inline.
+    Voice,
+    Audio,
+    Video,
+    Document,
+}
+
+impl MediaKind {
+    /// The kind of media a URL or path points to, by file extension.
+    pub fn of(source: &str) -> Option<Self> {
+        let path = source.split(['?', '#']).next().unwrap_or(source);
+        let file = path.rsplit('/').next().unwrap_or(path);
+        let (_, ext) = file.rsplit_once('.')?;
+        match ext.to_lowercase().as_str() {
+            "png" | "jpg" | "jpeg" | "webp" => Some(Self::Photo),
+            "ogg" | "oga" | "opus" => Some(Self::Voice),
+            "mp3" | "m4a" | "wav" | "flac" | "aac" => Some(Self::Audio),
+            "mp4" | "mov" | "webm" => Some(Self::Video),
+            _ => None,
         }
     }
 }
 
+/// A button under an outbound message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Button {
+    pub label: String,
+    pub action: ButtonAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ButtonAction {
+    /// Pressing the button sends this text as the user's message.
+    Reply(String),
+    /// Pressing the button opens this URL.
+    Url(String),
+}
+
+impl Button {
+    pub fn reply(label: impl Into<String>, text: impl Into<String>) -> Self {
+        Self { label: label.into(), action: ButtonAction::Reply(text.into()) }
+    }
+
+    pub fn link(label: impl Into<String>, url: impl Into<String>) -> Self {
+        Self { label: label.into(), action: ButtonAction::Url(url.into()) }
+    }
+}
+
+/// How a chat API request is answered (`mode` on /api/v1/chat and /chat/stream).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatMode {
+    /// Cloud, with local fallback when available.
+    #[default]
+    Auto,
+    /// On-device model only.
+    Local,
+    /// Remote providers only.
+    Cloud,
+    /// All parallel models answer; the fastest wins.
+    Race,
+    /// All parallel models answer; every answer is returned.
+    Explore,
+    /// All parallel models answer; a judge merges the answers.
+    Consensus,
+}
+
+impl ChatMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Local => "local",
+            Self::Cloud => "cloud",
+            Self::Race => "race",
+            Self::Explore => "explore",
+            Self::Consensus => "consensus",
+        }
+    }
+
+    /// Modes that call every parallel model (and cost accordingly).
+    pub fn is_multi_model(self) -> bool {
+        matches!(self, Self::Race | Self::Explore | Self::Consensus)
+    }
+}
+
+impl std::fmt::Display for ChatMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ChatMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(s.to_string())).map_err(|e| e.to_string())
+    }
+}
+
+/// Model tier, as in `LoadBalancedProvider::get_tier_model`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelTier {
+    Economy,
+    Normal,
+    Powerful,
+}
+
+impl ModelTier {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Economy => "economy",
+            Self::Normal => "normal",
+            Self::Powerful => "powerful",
+        }
+    }
+}
+
+impl std::str::FromStr for ModelTier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(s.to_string())).map_err(|e| e.to_string())
+    }
+}
+
+/// Per-request options of the chat API (`options` next to `mode`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChatOptions {
+    /// Pick the model from this tier (an explicit `model` still wins).
+    pub tier: Option<ModelTier>,
+    /// Upper bound on what one answer may cost; caps the output tokens.
+    pub max_cost_usd: Option<f64>,
+    /// `false` answers without tools.
+    pub tools: Option<bool>,
+    /// Agent id, as with `/agent <id>`.
+    pub agent: Option<String>,
+}
+
+impl ChatOptions {
+    /// Check values serde can't: the budget must be a positive amount and
+    /// the agent id non-empty.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(cost) = self.max_cost_usd {
+            if !cost.is_finite() || cost <= 0.0 {
+                return Err(format!("options.max_cost_usd must be positive, got {}", cost));
+            }
+        }
+        if self.agent.as_deref().is_some_and(|a| a.trim().is_empty()) {
+            return Err("options.agent must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn tools_enabled(&self) -> bool {
+        self.tools != Some(false)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -265,6 +544,7 @@ mod tests {
             tool_calls: vec![],
             finish_reason: FinishReason::Stop,
             usage: TokenUsage::default(),
+            model: None,
         };
         assert!(!resp.has_tool_calls());
 
@@ -277,10 +557,32 @@ mod tests {
             }],
             finish_reason: FinishReason::ToolCalls,
             usage: TokenUsage::default(),
+            model: None,
         };
         assert!(resp2.has_tool_calls());
     }
 
+    #[test]
+    fn test_switched_family() {
+        let mut resp = CompletionResponse {
+            content: Some("hi".into()),
+            tool_calls: vec![],
+            finish_reason: FinishReason::Stop,
+            usage: TokenUsage::default(),
+            model: None,
+        };
+        assert!(!resp.switched_family("anthropic/claude-sonnet-4-5"));
+        assert_eq!(resp.model_or("claude-sonnet-4-5"), "claude-sonnet-4-5");
+
+        resp.model = Some("claude-sonnet-4-5-20250929".into());
+        assert!(!resp.switched_family("anthropic/claude-sonnet-4-5"));
+
+        resp.model = Some("gemini-2.5-flash".into());
+        assert!(resp.switched_family("anthropic/claude-sonnet-4-5"));
+        assert_eq!(model_family("openai/gpt-4o-mini"), "gpt");
+        assert_eq!(model_family("local-qwen3-0.6b"), "local");
+    }
+
     #[test]
     fn test_inbound_message_session_key() {
         let msg = InboundMessage::new("telegram", "user123", "chat456", "hello");
@@ -297,5 +599,62 @@ mod tests {
         assert_eq!(msg.content, "response text");
         assert!(msg.reply_to.is_none());
         assert!(msg.media.is_empty());
+
+        let msg = msg.with_buttons(vec![vec![Button::reply("Yes", "yes"), Button::link("Docs", "https://chatweb.ai")]]);
+        assert_eq!(msg.buttons[0][0].action, ButtonAction::Reply("yes".into()));
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["buttons"][0][1]["action"]["url"], "https://chatweb.ai");
+        assert!(json.get("attachments").is_none());
+    }
+
+    #[test]
+    fn test_lift_media() {
+        let mut msg = OutboundMessage::new(
+            "telegram",
+            "1",
+            "Here you go:\n\n![Generated Image](https://image.pollinations.ai/prompt/cat?width=1024)\n\nListen: https://cdn.example.com/a/hello.ogg\nMore at https://chatweb.ai",
+        );
+        msg.lift_media();
+        assert_eq!(msg.content, "Here you go:\n\nListen:\nMore at https://chatweb.ai");
+        assert_eq!(
+            msg.attachments,
+            vec![
+                Attachment { kind: MediaKind::Photo, source: "https://image.pollinations.ai/prompt/cat?width=1024".into() },
+                Attachment { kind: MediaKind::Voice, source: "https://cdn.example.com/a/hello.ogg".into() },
+            ]
+        );
+
+        let mut plain = OutboundMessage::new("telegram", "1", "See https://example.com/docs.html  ok");
+        plain.lift_media();
+        assert_eq!(plain.content, "See https://example.com/docs.html  ok");
+        assert_eq!(MediaKind::of("/tmp/reply.mp3?x=1"), Some(MediaKind::Audio));
+        assert_eq!(Attachment::guess("report.pdf").kind, MediaKind::Document);
+    }
+
+    #[test]
+    fn test_chat_mode_serde() {
+        let mode: ChatMode = serde_json::from_str("\"consensus\"").unwrap();
+        assert_eq!(mode, ChatMode::Consensus);
+        assert!(mode.is_multi_model());
+        assert_eq!(serde_json::to_string(&ChatMode::Race).unwrap(), "\"race\"");
+        assert!(serde_json::from_str::<ChatMode>("\"fastest\"").is_err());
+        assert!(!ChatMode::default().is_multi_model());
+        assert_eq!("explore".parse::<ChatMode>(), Ok(ChatMode::Explore));
+        assert_eq!("powerful".parse::<ModelTier>(), Ok(ModelTier::Powerful));
+    }
+
+    #[test]
+    fn test_chat_options() {
+        let options: ChatOptions =
+            serde_json::from_str(r#"{"tier": "economy", "max_cost_usd": 0.01, "tools": false}"#).unwrap();
+        assert_eq!(options.tier, Some(ModelTier::Economy));
+        assert!(!options.tools_enabled());
+        assert!(options.validate().is_ok());
+
+        assert!(serde_json::from_str::<ChatOptions>(r#"{"tier": "cheap"}"#).is_err());
+        assert!(serde_json::from_str::<ChatOptions>(r#"{"tool": false}"#).is_err());
+        assert!(ChatOptions { max_cost_usd: Some(-1.0), ..Default::default() }.validate().is_err());
+        assert!(ChatOptions { agent: Some(" ".into()), ..Default::default() }.validate().is_err());
+        assert!(ChatOptions::default().tools_enabled());
     }
 }
@@ -1,23 +1,623 @@
+use dashmap::DashMap;
 use once_cell::sync::Lazy;
-use reqwest::Client;
-use std::time::Duration;
-
-/// Global HTTP client with connection pooling and keep-alive.
-/// Timeout set to 120s to accommodate LLM code generation (30s was too short,
-/// causing "error decoding response body" when responses took >30s).
-static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
-    Client::builder()
-        .timeout(Duration::from_secs(120))
-        .connect_timeout(Duration::from_secs(5))
-        .pool_max_idle_per_host(50)
-        .pool_idle_timeout(Duration::from_secs(90))
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::config::{DnsConfig, HttpConfig, WebFetchConfig};
+
+/// What an outbound client is used for. Each class has its own connection
+/// pool and overall timeout (see [`HttpConfig`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClientClass {
+    /// Model APIs, whose responses can take minutes.
+    Llm,
+    /// Channel APIs (including long polling) and webhooks.
+    Webhook,
+    /// Everything else: integrations, payments, web tools.
+    General,
+}
+
+/// Shared clients built from the current [`HttpConfig`], keyed by class and proxy.
+struct Clients {
+    config: HttpConfig,
+    resolver: Option<Arc<DnsResolver>>,
+    pools: DashMap<(ClientClass, Option<String>), Client>,
+}
+
+impl Clients {
+    fn new(config: HttpConfig) -> Self {
+        let resolver = DnsResolver::from_config(&config.dns).map(Arc::new);
+        Self { config, resolver, pools: DashMap::new() }
+    }
+
+    fn get(&self, class: ClientClass, proxy: Option<&str>) -> Client {
+        let proxy = proxy.filter(|p| !p.is_empty()).map(str::to_string);
+        self.pools
+            .entry((class, proxy.clone()))
+            .or_insert_with(|| build_client(&self.config, self.resolver.clone(), class, proxy.as_deref()))
+            .clone()
+    }
+}
+
+static CLIENTS: Lazy<RwLock<Arc<Clients>>> = Lazy::new(|| RwLock::new(Arc::new(Clients::new(HttpConfig::default()))));
+
+fn build_client(config: &HttpConfig, resolver: Option<Arc<DnsResolver>>, class: ClientClass, proxy: Option<&str>) -> Client {
+    let timeout = match class {
+        ClientClass::Llm => config.llm_timeout_secs,
+        ClientClass::Webhook => config.webhook_timeout_secs,
+        ClientClass::General => config.default_timeout_secs,
+    };
+    let mut builder = Client::builder()
+        .timeout(Duration::from_secs(timeout))
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .pool_max_idle_per_host(config.pool_max_idle_per_host)
+        .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_secs))
         .tcp_keepalive(Duration::from_secs(30))
-        .user_agent("nanobot/0.1.0")
-        .build()
-        .expect("Failed to create HTTP client")
-});
-
-/// Get the global HTTP client.
-pub fn client() -> &'static Client {
-    &HTTP_CLIENT
+        .user_agent(concat!("nanobot/", env!("CARGO_PKG_VERSION")));
+    if config.keep_alive_secs > 0 {
+        builder = builder
+            .http2_keep_alive_interval(Duration::from_secs(config.keep_alive_secs))
+            .http2_keep_alive_timeout(Duration::from_secs(10))
+            .http2_keep_alive_while_idle(true);
+    }
+    if let Some(resolver) = resolver {
+        builder = builder.dns_resolver(resolver);
+    }
+    for (host, ip) in &config.dns.hosts {
+        match ip.parse::<IpAddr>() {
+            // Port 0: connect on the URL's port
+            Ok(ip) => builder = builder.resolve(host, SocketAddr::new(ip, 0)),
+            Err(_) => tracing::warn!("Ignoring invalid address {} for host {}", ip, host),
+        }
+    }
+    // Without an explicit proxy, reqwest applies HTTPS_PROXY/HTTP_PROXY/NO_PROXY
+    if let Some(url) = proxy.or(config.proxy.as_deref()).filter(|p| !p.is_empty()) {
+        match reqwest::Proxy::all(url) {
+            Ok(proxy) => builder = builder.proxy(proxy),
+            Err(e) => tracing::warn!("Ignoring invalid proxy {}: {}", url, e),
+        }
+    }
+    builder.build().unwrap_or_else(|e| {
+        tracing::warn!("Failed to build {:?} HTTP client: {}", class, e);
+        Client::new()
+    })
+}
+
+/// Resolver for [`DnsConfig`]: custom nameservers and/or one address family.
+struct DnsResolver {
+    /// None: the system resolver.
+    nameservers: Option<hickory_resolver::TokioAsyncResolver>,
+    ip_family: String,
+}
+
+impl DnsResolver {
+    /// None when the config asks for plain system resolution.
+    fn from_config(dns: &DnsConfig) -> Option<Self> {
+        use hickory_resolver::config::{NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig, ResolverOpts};
+
+        let mut servers = Vec::new();
+        for server in &dns.servers {
+            match nameserver_addr(server) {
+                Some(addr) => {
+                    servers.push(NameServerConfig::new(addr, Protocol::Udp));
+                    servers.push(NameServerConfig::new(addr, Protocol::Tcp));
+                }
+                None => tracing::warn!("Ignoring invalid DNS server {}", server),
+            }
+        }
+        if servers.is_empty() && matches!(dns.ip_family.as_str(), "" | "any") {
+            return None;
+        }
+        let nameservers = (!servers.is_empty()).then(|| {
+            let config = ResolverConfig::from_parts(None, vec![], NameServerConfigGroup::from(servers));
+            hickory_resolver::TokioAsyncResolver::tokio(config, ResolverOpts::default())
+        });
+        Some(Self { nameservers, ip_family: dns.ip_family.clone() })
+    }
+}
+
+impl reqwest::dns::Resolve for DnsResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let nameservers = self.nameservers.clone();
+        let ip_family = self.ip_family.clone();
+        Box::pin(async move {
+            let ips: Vec<IpAddr> = match nameservers {
+                Some(resolver) => resolver.lookup_ip(name.as_str()).await?.iter().collect(),
+                None => tokio::net::lookup_host((name.as_str(), 0)).await?.map(|addr| addr.ip()).collect(),
+            };
+            let ips = filter_ip_family(ips, &ip_family);
+            if ips.is_empty() {
+                return Err(format!("no {} address for {}", ip_family, name.as_str()).into());
+            }
+            let addrs: reqwest::dns::Addrs = Box::new(ips.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+/// `1.1.1.1`, `1.1.1.1:5353` or `[::1]:53`; port 53 when none is given.
+fn nameserver_addr(server: &str) -> Option<SocketAddr> {
+    let server = server.trim();
+    server.parse().ok().or_else(|| {
+        let ip: IpAddr = server.trim_start_matches('[').trim_end_matches(']').parse().ok()?;
+        Some(SocketAddr::new(ip, 53))
+    })
+}
+
+/// Keep or put first the addresses of `ip_family` (see [`DnsConfig`]).
+fn filter_ip_family(mut ips: Vec<IpAddr>, ip_family: &str) -> Vec<IpAddr> {
+    match ip_family {
+        "ipv4" => ips.retain(IpAddr::is_ipv4),
+        "ipv6" => ips.retain(IpAddr::is_ipv6),
+        "prefer-ipv4" => ips.sort_by_key(IpAddr::is_ipv6),
+        "prefer-ipv6" => ips.sort_by_key(IpAddr::is_ipv4),
+        _ => {}
+    }
+    ips
+}
+
+/// Apply the `http` config section. Clients handed out earlier keep their settings.
+pub fn configure_clients(config: &HttpConfig) {
+    *CLIENTS.write().unwrap() = Arc::new(Clients::new(config.clone()));
+}
+
+/// The shared client for `class`. Clones share one connection pool.
+pub fn client_for(class: ClientClass) -> Client {
+    client_via(class, None)
+}
+
+/// The shared client for `class` going through `proxy` (e.g. a channel's own
+/// proxy setting) instead of the configured one.
+pub fn client_via(class: ClientClass, proxy: Option<&str>) -> Client {
+    let clients = CLIENTS.read().unwrap().clone();
+    clients.get(class, proxy)
+}
+
+/// The shared client for model APIs.
+pub fn client() -> Client {
+    client_for(ClientClass::Llm)
+}
+
+// ---------------------------------------------------------------------------
+// Polite web fetching: robots.txt, per-host pacing and a page cache, shared
+// by read_webpage, web_crawl, extract and the search engines.
+// ---------------------------------------------------------------------------
+
+/// Why a page wasn't fetched.
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("blocked by robots.txt: {0}")]
+    Disallowed(String),
+    #[error("{0}")]
+    Request(String),
+}
+
+/// Allow/Disallow rules of one robots.txt group.
+#[derive(Debug, Clone, Default)]
+pub struct RobotsRules {
+    /// (allow, pattern)
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    /// Rules for `agent` (a product token like `nanobot`): its own group if
+    /// the file has one, otherwise the `*` group.
+    pub fn parse(robots_txt: &str, agent: &str) -> Self {
+        let agent = agent.to_lowercase();
+        let mut groups: Vec<(Vec<String>, RobotsRules)> = Vec::new();
+        let mut in_rules = true;
+        for line in robots_txt.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else { continue };
+            let (key, value) = (key.trim().to_lowercase(), value.trim());
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        groups.push((Vec::new(), RobotsRules::default()));
+                        in_rules = false;
+                    }
+                    if let Some((agents, _)) = groups.last_mut() {
+                        agents.push(value.to_lowercase());
+                    }
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    if let Some((_, rules)) = groups.last_mut() {
+                        if !value.is_empty() {
+                            rules.rules.push((key == "allow", value.to_string()));
+                        }
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    if let (Some((_, rules)), Ok(secs)) = (groups.last_mut(), value.parse::<f64>()) {
+                        rules.crawl_delay = Some(Duration::from_secs_f64(secs.clamp(0.0, 60.0)));
+                    }
+                }
+                _ => {}
+            }
+        }
+        let own = groups.iter().find(|(agents, _)| agents.iter().any(|a| a != "*" && agent.contains(a.as_str())));
+        let any = groups.iter().find(|(agents, _)| agents.iter().any(|a| a == "*"));
+        own.or(any).map(|(_, rules)| rules.clone()).unwrap_or_default()
+    }
+
+    /// Whether `path` (with query) may be fetched: the longest matching
+    /// pattern decides, Allow winning ties.
+    pub fn allows(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| robots_match(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .map(|(allow, _)| *allow)
+            .unwrap_or(true)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// robots.txt pattern match: prefix match with `*` wildcards and a `$` end anchor.
+fn robots_match(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = path.strip_prefix(first) else { return false };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        let last = i + 1 == parts.len();
+        if last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+/// A page as stored in a [`FetchCache`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedPage {
+    pub url: String,
+    pub final_url: String,
+    pub status: u16,
+    pub content_type: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Unix seconds of the last fetch or revalidation.
+    pub fetched_at: i64,
+    pub body: String,
+}
+
+/// Storage for fetched pages. [`DiskCache`] keeps them in the workspace; a
+/// shared store can be plugged in with [`configure_web_cache`].
+pub trait FetchCache: Send + Sync {
+    fn get(&self, url: &str) -> Option<CachedPage>;
+    fn put(&self, page: &CachedPage);
+}
+
+/// One JSON file per URL.
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        use sha2::{Digest, Sha256};
+        self.dir.join(format!("{}.json", hex::encode(Sha256::digest(url.as_bytes()))))
+    }
+}
+
+impl FetchCache for DiskCache {
+    fn get(&self, url: &str) -> Option<CachedPage> {
+        let data = std::fs::read_to_string(self.path(url)).ok()?;
+        serde_json::from_str::<CachedPage>(&data).ok().filter(|p| p.url == url)
+    }
+
+    fn put(&self, page: &CachedPage) {
+        let write = std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(self.path(&page.url), serde_json::to_string(page).unwrap_or_default()));
+        if let Err(e) = write {
+            tracing::debug!("web cache write failed for {}: {}", page.url, e);
+        }
+    }
+}
+
+struct WebPolicy {
+    user_agent: String,
+    respect_robots: bool,
+    min_interval: Duration,
+    cache: Option<Arc<dyn FetchCache>>,
+    cache_ttl: Duration,
+}
+
+impl WebPolicy {
+    fn new(config: &WebFetchConfig, cache: Option<Arc<dyn FetchCache>>) -> Self {
+        Self {
+            user_agent: config.user_agent.clone(),
+            respect_robots: config.respect_robots,
+            min_interval: Duration::from_millis(config.min_interval_ms),
+            cache,
+            cache_ttl: Duration::from_secs(config.cache_ttl_secs),
+        }
+    }
+}
+
+static WEB_POLICY: Lazy<RwLock<Arc<WebPolicy>>> =
+    Lazy::new(|| RwLock::new(Arc::new(WebPolicy::new(&WebFetchConfig::default(), None))));
+static ROBOTS: Lazy<DashMap<String, Arc<RobotsRules>>> = Lazy::new(DashMap::new);
+static NEXT_SLOT: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn policy() -> Arc<WebPolicy> {
+    WEB_POLICY.read().unwrap().clone()
+}
+
+/// Apply `tools.web.fetch`; the page cache goes to `<workspace>/.cache/web`.
+pub fn configure_web(config: &WebFetchConfig, workspace: &Path) {
+    let cache = config
+        .cache
+        .then(|| Arc::new(DiskCache::new(workspace.join(".cache").join("web"))) as Arc<dyn FetchCache>);
+    *WEB_POLICY.write().unwrap() = Arc::new(WebPolicy::new(config, cache));
+}
+
+/// Apply `tools.web.fetch` with a custom page cache.
+pub fn configure_web_cache(config: &WebFetchConfig, cache: Arc<dyn FetchCache>) {
+    *WEB_POLICY.write().unwrap() = Arc::new(WebPolicy::new(config, Some(cache)));
+}
+
+/// User-Agent for requests made on behalf of web tools.
+pub fn user_agent() -> String {
+    policy().user_agent.clone()
+}
+
+/// Wait until `url`'s host may be contacted again, keeping requests to one
+/// host at least `interval` apart. Concurrent callers queue up in order.
+pub async fn wait_turn(url: &Url, interval: Duration) {
+    let Some(host) = url.host_str() else { return };
+    let slot = {
+        let mut next = NEXT_SLOT.lock().unwrap();
+        let now = Instant::now();
+        let slot = next.get(host).copied().filter(|t| *t > now).unwrap_or(now);
+        next.insert(host.to_string(), slot + interval);
+        slot
+    };
+    tokio::time::sleep_until(slot.into()).await;
+}
+
+/// Pace a request to `url` with the configured per-host interval.
+pub async fn throttle(url: &Url) {
+    wait_turn(url, policy().min_interval).await;
+}
+
+async fn robots_for(url: &Url, user_agent: &str) -> Arc<RobotsRules> {
+    let origin = url.origin().ascii_serialization();
+    if let Some(rules) = ROBOTS.get(&origin) {
+        return rules.clone();
+    }
+    let token = user_agent.split(['/', ' ']).next().unwrap_or(user_agent);
+    let rules = match client_for(ClientClass::General)
+        .get(format!("{}/robots.txt", origin))
+        .header("User-Agent", user_agent)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+    {
+        Ok(resp) if resp.status().is_success() => RobotsRules::parse(&resp.text().await.unwrap_or_default(), token),
+        // Missing robots.txt, or one we can't read, allows everything
+        _ => RobotsRules::default(),
+    };
+    let rules = Arc::new(rules);
+    ROBOTS.insert(origin, rules.clone());
+    rules
+}
+
+/// A fetched page.
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    pub final_url: String,
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+    pub from_cache: bool,
+}
+
+impl From<CachedPage> for FetchedPage {
+    fn from(page: CachedPage) -> Self {
+        Self {
+            final_url: page.final_url,
+            status: page.status,
+            content_type: page.content_type,
+            body: page.body,
+            from_cache: true,
+        }
+    }
+}
+
+/// GET `url` the well-behaved way: robots.txt is honoured, requests to one
+/// host are spaced out (robots `Crawl-delay` included), and pages come from
+/// the cache while fresh and are revalidated with ETag/Last-Modified after.
+pub async fn fetch_page(url: &str) -> Result<FetchedPage, FetchError> {
+    let parsed = Url::parse(url).map_err(|e| FetchError::InvalidUrl(e.to_string()))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(FetchError::InvalidUrl("only http/https URLs are allowed".to_string()));
+    }
+    let policy = policy();
+
+    let mut interval = policy.min_interval;
+    if policy.respect_robots {
+        let rules = robots_for(&parsed, &policy.user_agent).await;
+        let path = match parsed.query() {
+            Some(q) => format!("{}?{}", parsed.path(), q),
+            None => parsed.path().to_string(),
+        };
+        if !rules.allows(&path) {
+            return Err(FetchError::Disallowed(url.to_string()));
+        }
+        interval = interval.max(rules.crawl_delay().unwrap_or_default());
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let cached = policy.cache.as_ref().and_then(|c| c.get(url));
+    if let Some(page) = &cached {
+        if now - page.fetched_at < policy.cache_ttl.as_secs() as i64 {
+            return Ok(page.clone().into());
+        }
+    }
+
+    wait_turn(&parsed, interval).await;
+    let mut request = client_for(ClientClass::General)
+        .get(parsed)
+        .header("User-Agent", &policy.user_agent)
+        .timeout(Duration::from_secs(30));
+    if let Some(page) = &cached {
+        if let Some(etag) = &page.etag {
+            request = request.header("If-None-Match", etag);
+        }
+        if let Some(modified) = &page.last_modified {
+            request = request.header("If-Modified-Since", modified);
+        }
+    }
+    let response = request.send().await.map_err(|e| FetchError::Request(e.to_string()))?;
+
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        if let (Some(cache), Some(mut page)) = (policy.cache.as_ref(), cached) {
+            page.fetched_at = now;
+            cache.put(&page);
+            return Ok(page.into());
+        }
+    }
+
+    let header = |name: &str| response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let page = CachedPage {
+        url: url.to_string(),
+        final_url: response.url().to_string(),
+        status: response.status().as_u16(),
+        content_type: header("content-type").unwrap_or_default(),
+        etag: header("etag"),
+        last_modified: header("last-modified"),
+        fetched_at: now,
+        body: String::new(),
+    };
+    let body = response.text().await.map_err(|e| FetchError::Request(e.to_string()))?;
+    let page = CachedPage { body, ..page };
+    if let Some(cache) = policy.cache.as_ref().filter(|_| (200..300).contains(&page.status)) {
+        cache.put(&page);
+    }
+    Ok(FetchedPage { from_cache: false, ..page.into() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS_TXT: &str = "\
+User-agent: *
+Disallow: /private
+Allow: /private/public
+Disallow: /*.pdf$
+
+User-agent: nanobot
+User-agent: otherbot
+Disallow: /nanobot-only
+Crawl-delay: 2
+";
+
+    #[test]
+    fn test_robots_groups() {
+        let any = RobotsRules::parse(ROBOTS_TXT, "somebot");
+        assert!(!any.allows("/private/page"));
+        assert!(any.allows("/private/public/page"));
+        assert!(!any.allows("/docs/manual.pdf"));
+        assert!(any.allows("/docs/manual.pdf.html"));
+        assert!(any.allows("/nanobot-only"));
+
+        let own = RobotsRules::parse(ROBOTS_TXT, "nanobot");
+        assert!(own.allows("/private/page"));
+        assert!(!own.allows("/nanobot-only/x"));
+        assert_eq!(own.crawl_delay(), Some(Duration::from_secs(2)));
+
+        assert!(RobotsRules::parse("", "nanobot").allows("/anything"));
+    }
+
+    #[test]
+    fn test_robots_match() {
+        assert!(robots_match("/a*b", "/axxb/c"));
+        assert!(!robots_match("/a*b$", "/axxb/c"));
+        assert!(robots_match("/a*b$", "/axxb"));
+        assert!(robots_match("/", "/anything"));
+        assert!(robots_match("/page$", "/page"));
+        assert!(!robots_match("/page$", "/page2"));
+    }
+
+    #[test]
+    fn test_clients_pooled_per_class_and_proxy() {
+        let clients = Clients::new(HttpConfig::default());
+        clients.get(ClientClass::Llm, None);
+        clients.get(ClientClass::Llm, Some(""));
+        clients.get(ClientClass::Webhook, None);
+        clients.get(ClientClass::Webhook, Some("http://proxy.test:3128"));
+        clients.get(ClientClass::Webhook, Some("http://proxy.test:3128"));
+        assert_eq!(clients.pools.len(), 3);
+    }
+
+    #[test]
+    fn test_dns_ip_family() {
+        let v4: IpAddr = "93.184.216.34".parse().unwrap();
+        let v6: IpAddr = "2606:2800:220:1::".parse().unwrap();
+        assert_eq!(filter_ip_family(vec![v6, v4], "ipv4"), vec![v4]);
+        assert_eq!(filter_ip_family(vec![v4, v6], "ipv6"), vec![v6]);
+        assert_eq!(filter_ip_family(vec![v6, v4], "prefer-ipv4"), vec![v4, v6]);
+        assert_eq!(filter_ip_family(vec![v4, v6], "any"), vec![v4, v6]);
+
+        assert_eq!(nameserver_addr("1.1.1.1"), Some("1.1.1.1:53".parse().unwrap()));
+        assert_eq!(nameserver_addr("[2606:4700::1111]:5353"), Some("[2606:4700::1111]:5353".parse().unwrap()));
+        assert_eq!(nameserver_addr("dns.example"), None);
+        assert!(DnsResolver::from_config(&DnsConfig::default()).is_none());
+    }
+
+    #[test]
+    fn test_disk_cache_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = DiskCache::new(tmp.path());
+        let page = CachedPage {
+            url: "https://example.com/".into(),
+            final_url: "https://example.com/".into(),
+            status: 200,
+            content_type: "text/html".into(),
+            etag: Some("\"v1\"".into()),
+            last_modified: None,
+            fetched_at: 0,
+            body: "<p>hi</p>".into(),
+        };
+        cache.put(&page);
+        assert_eq!(cache.get("https://example.com/").unwrap().etag.as_deref(), Some("\"v1\""));
+        assert!(cache.get("https://example.com/other").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_wait_turn_spaces_requests() {
+        let url = Url::parse("https://pacing.test/").unwrap();
+        let start = Instant::now();
+        wait_turn(&url, Duration::from_millis(50)).await;
+        wait_turn(&url, Duration::from_millis(50)).await;
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
 }
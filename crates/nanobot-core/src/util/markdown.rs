@@ -1,3 +1,5 @@
+use reqwest::Url;
+use scraper::{ElementRef, Html, Node, Selector};
 use std::collections::HashMap;
 
 /// Parse YAML-like frontmatter from a markdown file.
@@ -38,6 +40,332 @@ pub fn strip_frontmatter(content: &str) -> &str {
     body
 }
 
+// ---------------------------------------------------------------------------
+// HTML → markdown
+// ---------------------------------------------------------------------------
+
+/// Never rendered.
+const SKIP_TAGS: &[&str] = &[
+    "script", "style", "noscript", "svg", "template", "iframe", "button", "form", "nav", "aside", "select",
+];
+/// Site chrome, dropped when no content element was found and the whole body is rendered.
+const CHROME_TAGS: &[&str] = &["header", "footer"];
+/// Preferred content roots, in order.
+const CONTENT_SELECTORS: &[&str] = &["article", "main", "[role=main]"];
+/// A content root with less text than this is ignored.
+const MIN_CONTENT_CHARS: usize = 200;
+
+/// Main content of a page as markdown.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Readable {
+    pub title: String,
+    pub markdown: String,
+}
+
+/// Readability-style extraction: find the element holding the page's main
+/// content (`<article>`/`<main>`, else the block with the most paragraph
+/// text) and convert it to markdown, keeping headings, links, lists, code
+/// and tables. Relative links are resolved against `base`.
+pub fn readable(html: &str, base: Option<&Url>) -> Readable {
+    let doc = Html::parse_document(html);
+    let title = select_text(&doc, "title").or_else(|| select_text(&doc, "h1")).unwrap_or_default();
+
+    let (root, is_body) = match content_root(&doc) {
+        Some(root) => (root, false),
+        None => match Selector::parse("body").ok().and_then(|s| doc.select(&s).next()) {
+            Some(body) => (body, true),
+            None => (doc.root_element(), true),
+        },
+    };
+    let mut writer = Writer::new(base, is_body);
+    writer.children(root);
+    Readable { title, markdown: tidy(&writer.out) }
+}
+
+/// Convert a whole HTML fragment to markdown, without content extraction.
+pub fn html_to_markdown(html: &str, base: Option<&Url>) -> String {
+    let doc = Html::parse_fragment(html);
+    let mut writer = Writer::new(base, false);
+    writer.children(doc.root_element());
+    tidy(&writer.out)
+}
+
+fn select_text(doc: &Html, selector: &str) -> Option<String> {
+    let sel = Selector::parse(selector).ok()?;
+    let text = collapse(&doc.select(&sel).next()?.text().collect::<String>());
+    (!text.is_empty()).then_some(text)
+}
+
+fn text_len(el: ElementRef) -> usize {
+    el.text().map(|t| t.trim().chars().count()).sum()
+}
+
+fn content_root(doc: &Html) -> Option<ElementRef<'_>> {
+    for selector in CONTENT_SELECTORS {
+        let sel = Selector::parse(selector).ok()?;
+        if let Some(el) = doc.select(&sel).max_by_key(|el| text_len(*el)) {
+            if text_len(el) >= MIN_CONTENT_CHARS {
+                return Some(el);
+            }
+        }
+    }
+    // Score containers by the paragraph text directly inside them; a
+    // grandparent gets half, so a wrapper around several sections can win.
+    let p = Selector::parse("p").ok()?;
+    let mut scores = HashMap::new();
+    for para in doc.select(&p) {
+        let len = text_len(para);
+        let mut ancestors = para.ancestors().filter(|n| n.value().is_element());
+        if let Some(parent) = ancestors.next() {
+            *scores.entry(parent.id()).or_insert(0usize) += len;
+        }
+        if let Some(grandparent) = ancestors.next() {
+            *scores.entry(grandparent.id()).or_insert(0usize) += len / 2;
+        }
+    }
+    scores
+        .into_iter()
+        .filter(|(_, score)| *score >= MIN_CONTENT_CHARS)
+        .max_by_key(|(_, score)| *score)
+        .and_then(|(id, _)| doc.tree.get(id))
+        .and_then(ElementRef::wrap)
+        .filter(|el| !matches!(el.value().name(), "body" | "html"))
+}
+
+/// Collapse runs of whitespace to one space.
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Trim trailing spaces and squeeze blank lines.
+fn tidy(markdown: &str) -> String {
+    let mut out = String::new();
+    let mut blank = 0;
+    for line in markdown.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            blank += 1;
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(if blank > 0 { "\n\n" } else { "\n" });
+        }
+        blank = 0;
+        out.push_str(line);
+    }
+    out
+}
+
+struct Writer<'a> {
+    out: String,
+    base: Option<&'a Url>,
+    skip_chrome: bool,
+}
+
+impl<'a> Writer<'a> {
+    fn new(base: Option<&'a Url>, skip_chrome: bool) -> Self {
+        Self { out: String::new(), base, skip_chrome }
+    }
+
+    /// Render `el`'s children with a fresh writer.
+    fn render(&self, el: ElementRef) -> String {
+        let mut sub = Writer::new(self.base, self.skip_chrome);
+        sub.children(el);
+        sub.out
+    }
+
+    fn inline(&self, el: ElementRef) -> String {
+        collapse(&self.render(el))
+    }
+
+    fn block(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push_str(if self.out.ends_with('\n') { "\n" } else { "\n\n" });
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let starts_ws = text.starts_with(char::is_whitespace);
+        let ends_ws = text.ends_with(char::is_whitespace);
+        let text = collapse(text);
+        let at_line_start = self.out.is_empty() || self.out.ends_with('\n');
+        if (starts_ws || text.is_empty()) && !at_line_start && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+        if text.is_empty() {
+            return;
+        }
+        self.out.push_str(&text);
+        if ends_ws {
+            self.out.push(' ');
+        }
+    }
+
+    fn link(&self, href: &str) -> Option<String> {
+        let href = href.trim();
+        if href.is_empty() || href.starts_with('#') || href.starts_with("javascript:") {
+            return None;
+        }
+        Some(match self.base.and_then(|b| b.join(href).ok()) {
+            Some(url) => url.to_string(),
+            None => href.to_string(),
+        })
+    }
+
+    fn children(&mut self, el: ElementRef) {
+        for child in el.children() {
+            match child.value() {
+                Node::Text(text) => self.text(text),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.element(child);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn element(&mut self, el: ElementRef) {
+        let name = el.value().name();
+        if SKIP_TAGS.contains(&name) || (self.skip_chrome && CHROME_TAGS.contains(&name)) || el.value().attr("hidden").is_some() {
+            return;
+        }
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let text = self.inline(el);
+                if !text.is_empty() {
+                    self.block();
+                    let level = name[1..].parse::<usize>().unwrap_or(1);
+                    self.out.push_str(&format!("{} {}", "#".repeat(level), text));
+                    self.block();
+                }
+            }
+            "p" | "div" | "section" | "article" | "main" | "header" | "footer" | "figure" | "figcaption"
+            | "details" | "summary" | "dl" | "dt" | "dd" | "address" => {
+                self.block();
+                self.children(el);
+                self.block();
+            }
+            "br" => self.out.push('\n'),
+            "hr" => {
+                self.block();
+                self.out.push_str("---");
+                self.block();
+            }
+            "a" => {
+                let text = self.inline(el);
+                match el.value().attr("href").and_then(|h| self.link(h)) {
+                    Some(href) if !text.is_empty() => self.out.push_str(&format!("[{}]({})", text, href)),
+                    _ => self.out.push_str(&text),
+                }
+            }
+            "img" => {
+                if let Some(src) = el.value().attr("src").and_then(|s| self.link(s)) {
+                    let alt = collapse(el.value().attr("alt").unwrap_or(""));
+                    self.out.push_str(&format!("![{}]({})", alt, src));
+                }
+            }
+            "strong" | "b" => self.wrap_inline(el, "**"),
+            "em" | "i" => self.wrap_inline(el, "*"),
+            "del" | "s" => self.wrap_inline(el, "~~"),
+            "code" => self.wrap_inline(el, "`"),
+            "pre" => {
+                let code = el.text().collect::<String>();
+                let lang = el
+                    .children()
+                    .filter_map(ElementRef::wrap)
+                    .find(|c| c.value().name() == "code")
+                    .and_then(|c| c.value().classes().find_map(|cls| cls.strip_prefix("language-")))
+                    .unwrap_or("");
+                self.block();
+                self.out.push_str(&format!("```{}\n{}\n```", lang, code.trim_end_matches('\n')));
+                self.block();
+            }
+            "blockquote" => {
+                let inner = tidy(&self.render(el));
+                if !inner.is_empty() {
+                    self.block();
+                    let quoted: Vec<String> =
+                        inner.lines().map(|l| if l.is_empty() { ">".to_string() } else { format!("> {}", l) }).collect();
+                    self.out.push_str(&quoted.join("\n"));
+                    self.block();
+                }
+            }
+            "ul" | "ol" => self.list(el, name == "ol"),
+            "table" => self.table(el),
+            _ => self.children(el),
+        }
+    }
+
+    fn wrap_inline(&mut self, el: ElementRef, mark: &str) {
+        let text = self.inline(el);
+        if !text.is_empty() {
+            self.out.push_str(&format!("{mark}{text}{mark}"));
+        }
+    }
+
+    fn list(&mut self, el: ElementRef, ordered: bool) {
+        let start = el.value().attr("start").and_then(|s| s.parse::<usize>().ok()).unwrap_or(1);
+        self.block();
+        let items = el.children().filter_map(ElementRef::wrap).filter(|c| c.value().name() == "li");
+        for (i, item) in items.enumerate() {
+            let marker = if ordered { format!("{}. ", start + i) } else { "- ".to_string() };
+            let body = tidy(&self.render(item));
+            let indent = " ".repeat(marker.len());
+            let mut lines = body.lines().filter(|l| !l.is_empty());
+            self.out.push_str(&marker);
+            self.out.push_str(lines.next().unwrap_or(""));
+            for line in lines {
+                self.out.push('\n');
+                self.out.push_str(&indent);
+                self.out.push_str(line);
+            }
+            self.out.push('\n');
+        }
+        self.block();
+    }
+
+    fn table(&mut self, el: ElementRef) {
+        let Ok(tr) = Selector::parse("tr") else { return };
+        let rows: Vec<Vec<String>> = el
+            .select(&tr)
+            .map(|row| {
+                row.children()
+                    .filter_map(ElementRef::wrap)
+                    .filter(|c| matches!(c.value().name(), "th" | "td"))
+                    .map(|cell| self.inline(cell).replace('|', "\\|"))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|row| !row.is_empty())
+            .collect();
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        // A one-column "table" is layout, not data
+        if columns == 1 {
+            self.block();
+            let lines: Vec<&str> = rows.iter().map(|r| r[0].as_str()).filter(|c| !c.is_empty()).collect();
+            self.out.push_str(&lines.join("\n\n"));
+            self.block();
+            return;
+        }
+        let line = |row: &[String]| {
+            let cells: Vec<&str> = (0..columns).map(|i| row.get(i).map(String::as_str).unwrap_or("")).collect();
+            format!("| {} |", cells.join(" | "))
+        };
+        self.block();
+        self.out.push_str(&line(&rows[0]));
+        self.out.push('\n');
+        self.out.push_str(&format!("|{}", " --- |".repeat(columns)));
+        for row in &rows[1..] {
+            self.out.push('\n');
+            self.out.push_str(&line(row));
+        }
+        self.block();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -59,4 +387,38 @@ mod tests {
         assert!(meta.is_empty());
         assert_eq!(body, content);
     }
+
+    #[test]
+    fn test_html_to_markdown() {
+        let base = Url::parse("https://example.com/a/").unwrap();
+        let html = "<h2>Intro</h2><p>See <a href=\"/docs\">the <b>docs</b></a>.</p>\
+            <ul><li>one</li><li>two<ul><li>nested</li></ul></li></ul>\
+            <table><tr><th>Name</th><th>Price</th></tr><tr><td>Tea</td><td>3|4</td></tr></table>";
+        assert_eq!(
+            html_to_markdown(html, Some(&base)),
+            "## Intro\n\nSee [the **docs**](https://example.com/docs).\n\n- one\n- two\n  - nested\n\n\
+             | Name | Price |\n| --- | --- |\n| Tea | 3\\|4 |"
+        );
+    }
+
+    #[test]
+    fn test_readable_picks_main_content() {
+        let body = "Rust makes systems programming approachable. ".repeat(6);
+        let html = format!(
+            "<html><head><title>Post</title></head><body><nav><a href=\"/\">Home</a></nav>\
+             <article><h1>Post</h1><p>{body}</p><pre><code class=\"language-rust\">fn main() {{}}\n</code></pre></article>\
+             <footer>© 2026</footer></body></html>"
+        );
+        let page = readable(&html, None);
+        assert_eq!(page.title, "Post");
+        assert_eq!(page.markdown, format!("# Post\n\n{}\n\n```rust\nfn main() {{}}\n```", body.trim_end()));
+
+        // No <article>: the block with the most paragraph text wins
+        let html = format!(
+            "<body><div class=\"menu\"><p>Menu</p></div><div><p>{body}</p><p>{body}</p></div></body>"
+        );
+        let page = readable(&html, None);
+        assert!(!page.markdown.contains("Menu"));
+        assert!(page.markdown.starts_with("Rust makes"));
+    }
 }
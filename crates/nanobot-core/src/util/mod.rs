@@ -1,7 +1,12 @@
 pub mod http;
 pub mod markdown;
+pub mod tz;
 
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 /// Ensure a directory exists, creating it if necessary.
 pub fn ensure_dir(path: &Path) -> std::io::Result<PathBuf> {
@@ -19,6 +24,40 @@ pub fn timestamp() -> String {
     chrono::Utc::now().to_rfc3339()
 }
 
+/// Milliseconds since the Unix epoch.
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Read a JSON file, falling back to the default when it is missing or
+/// can't be parsed. `what` names the file in the warning for other errors.
+pub fn read_json<T: DeserializeOwned + Default>(path: &Path, what: &str) -> T {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => T::default(),
+        Err(e) => {
+            tracing::warn!("Failed to load {}: {}", what, e);
+            T::default()
+        }
+    }
+}
+
+/// Write `value` to `path` as pretty JSON, creating the parent directory.
+/// Failures are logged rather than returned.
+pub fn write_json<T: Serialize>(path: &Path, value: &T, what: &str) {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).ok();
+    }
+    if let Ok(json) = serde_json::to_string_pretty(value) {
+        if let Err(e) = std::fs::write(path, json) {
+            tracing::error!("Failed to save {}: {}", what, e);
+        }
+    }
+}
+
 /// Convert a string to a safe filename.
 pub fn safe_filename(name: &str) -> String {
     const UNSAFE: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
@@ -29,6 +68,83 @@ pub fn safe_filename(name: &str) -> String {
     result.trim().to_string()
 }
 
+/// Resolve `path` to an absolute path without requiring it to exist: `.`
+/// and `..` are applied lexically, then the longest existing prefix is
+/// canonicalized (following symlinks). Used for workspace checks, where a
+/// plain `canonicalize` fails for files about to be created and a raw
+/// `starts_with` would let `ws/new/../../etc` through.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+    let clean = lexical(&absolute);
+    let mut base = clean.as_path();
+    let mut rest = Vec::new();
+    let mut resolved = loop {
+        if let Ok(real) = base.canonicalize() {
+            break strip_verbatim(real);
+        }
+        match (base.parent(), base.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                base = parent;
+            }
+            _ => break base.to_path_buf(),
+        }
+    };
+    for name in rest.into_iter().rev() {
+        resolved.push(name);
+    }
+    resolved
+}
+
+fn lexical(path: &Path) -> PathBuf {
+    use std::path::Component;
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::CurDir => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Drop the `\\?\` prefix `canonicalize` adds on Windows, so the result
+/// compares equal to ordinary paths and works with `cmd.exe` and friends.
+pub fn strip_verbatim(path: PathBuf) -> PathBuf {
+    #[cfg(windows)]
+    {
+        let s = path.to_string_lossy();
+        if let Some(unc) = s.strip_prefix(r"\\?\UNC\") {
+            return PathBuf::from(format!(r"\\{}", unc));
+        }
+        if let Some(local) = s.strip_prefix(r"\\?\") {
+            if local.as_bytes().get(1) == Some(&b':') {
+                return PathBuf::from(local);
+            }
+        }
+    }
+    path
+}
+
+/// Whether `path` lies inside `dir` (both resolved with [`normalize_path`]).
+/// Case-insensitive on Windows.
+pub fn is_within(path: &Path, dir: &Path) -> bool {
+    let (path, dir) = (normalize_path(path), normalize_path(dir));
+    if cfg!(windows) {
+        let lower = |p: &Path| PathBuf::from(p.to_string_lossy().to_lowercase());
+        lower(&path).starts_with(lower(&dir))
+    } else {
+        path.starts_with(dir)
+    }
+}
+
 /// Parse a session key into (channel, chat_id).
 pub fn parse_session_key(key: &str) -> Option<(&str, &str)> {
     key.split_once(':')
@@ -47,10 +163,60 @@ pub fn truncate_string(s: &str, max_len: usize, suffix: &str) -> String {
     format!("{}{}", &s[..end], suffix)
 }
 
+/// The first `max` characters of `s`, and whether anything was cut.
+pub fn truncate_chars(s: &str, max: usize) -> (&str, bool) {
+    match s.char_indices().nth(max) {
+        Some((end, _)) => (&s[..end], true),
+        None => (s, false),
+    }
+}
+
+/// Current time in `tz`, or in the server's local timezone when `tz` is unset
+/// or unrecognised.
+pub fn now_in(tz: Option<&str>) -> chrono::DateTime<chrono::FixedOffset> {
+    chrono::Utc::now().with_timezone(&tz::parse_or_local(tz)).fixed_offset()
+}
+
+/// Today's date (YYYY-MM-DD) in `tz`; see [`now_in`].
+pub fn today_date_in(tz: Option<&str>) -> String {
+    now_in(tz).format("%Y-%m-%d").to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_normalize_path_and_is_within() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws = tmp.path().join("ws");
+        std::fs::create_dir_all(ws.join("src")).unwrap();
+        let ws_real = normalize_path(&ws);
+
+        assert_eq!(normalize_path(&ws.join("src/../new/file.txt")), ws_real.join("new").join("file.txt"));
+        assert!(is_within(&ws.join("src/new/file.txt"), &ws));
+        assert!(is_within(&ws, &ws));
+        // Traversal through directories that don't exist yet
+        assert!(!is_within(&ws.join("new/../../outside.txt"), &ws));
+        assert!(!is_within(&tmp.path().join("wsx/file"), &ws));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("nested/store.json");
+        let missing: Vec<String> = read_json(&path, "test store");
+        assert!(missing.is_empty());
+
+        write_json(&path, &vec!["a".to_string()], "test store");
+        let loaded: Vec<String> = read_json(&path, "test store");
+        assert_eq!(loaded, vec!["a"]);
+
+        std::fs::write(&path, "not json").unwrap();
+        let corrupt: Vec<String> = read_json(&path, "test store");
+        assert!(corrupt.is_empty());
+    }
+
     #[test]
     fn test_safe_filename() {
         assert_eq!(safe_filename("hello"), "hello");
@@ -74,6 +240,14 @@ mod tests {
         assert_eq!(truncate_string("ab", 2, "..."), "ab");
     }
 
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("hello", 5), ("hello", false));
+        assert_eq!(truncate_chars("hello", 3), ("hel", true));
+        assert_eq!(truncate_chars("こんにちは", 2), ("こん", true));
+        assert_eq!(truncate_chars("", 0), ("", false));
+    }
+
     #[test]
     fn test_today_date_format() {
         let date = today_date();
@@ -82,6 +256,13 @@ mod tests {
         assert_eq!(date.chars().nth(7), Some('-'));
     }
 
+    #[test]
+    fn test_now_in() {
+        assert_eq!(now_in(Some("Asia/Tokyo")).offset().local_minus_utc(), 9 * 3600);
+        assert_eq!(now_in(Some("UTC-5")).offset().local_minus_utc(), -5 * 3600);
+        assert_eq!(today_date_in(Some("bogus")), today_date());
+    }
+
     #[test]
     fn test_timestamp_format() {
         let ts = timestamp();
@@ -0,0 +1,181 @@
+//! User timezones: IANA names with their DST rules (`Europe/Madrid`),
+//! common abbreviations (`JST`, `PST`) and fixed offsets (`UTC+9`, `+05:30`).
+//!
+//! [`Timezone`] is a [`chrono::TimeZone`], so schedules and local times are
+//! computed with the offset in effect on that date, not today's.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{FixedOffset, Local, MappedLocalTime, NaiveDate, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+
+/// A timezone a user or config named (see [`parse`]), or the server's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timezone {
+    Zone(Tz),
+    Fixed(FixedOffset),
+    Local,
+}
+
+impl Timezone {
+    pub const UTC: Timezone = Timezone::Zone(Tz::UTC);
+}
+
+/// The offset of a [`Timezone`] at some instant.
+#[derive(Debug, Clone, Copy)]
+pub enum TimezoneOffset {
+    Zone(<Tz as TimeZone>::Offset),
+    Fixed(FixedOffset),
+}
+
+impl Offset for TimezoneOffset {
+    fn fix(&self) -> FixedOffset {
+        match self {
+            TimezoneOffset::Zone(offset) => offset.fix(),
+            TimezoneOffset::Fixed(offset) => *offset,
+        }
+    }
+}
+
+impl fmt::Display for TimezoneOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fix().fmt(f)
+    }
+}
+
+impl TimeZone for Timezone {
+    type Offset = TimezoneOffset;
+
+    fn from_offset(offset: &TimezoneOffset) -> Self {
+        match offset {
+            TimezoneOffset::Zone(offset) => Timezone::Zone(Tz::from_offset(offset)),
+            TimezoneOffset::Fixed(offset) => Timezone::Fixed(*offset),
+        }
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> MappedLocalTime<TimezoneOffset> {
+        match self {
+            Timezone::Zone(tz) => tz.offset_from_local_date(local).map(TimezoneOffset::Zone),
+            Timezone::Fixed(offset) => offset.offset_from_local_date(local).map(TimezoneOffset::Fixed),
+            Timezone::Local => Local.offset_from_local_date(local).map(TimezoneOffset::Fixed),
+        }
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> MappedLocalTime<TimezoneOffset> {
+        match self {
+            Timezone::Zone(tz) => tz.offset_from_local_datetime(local).map(TimezoneOffset::Zone),
+            Timezone::Fixed(offset) => offset.offset_from_local_datetime(local).map(TimezoneOffset::Fixed),
+            Timezone::Local => Local.offset_from_local_datetime(local).map(TimezoneOffset::Fixed),
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> TimezoneOffset {
+        match self {
+            Timezone::Zone(tz) => TimezoneOffset::Zone(tz.offset_from_utc_date(utc)),
+            Timezone::Fixed(offset) => TimezoneOffset::Fixed(offset.offset_from_utc_date(utc)),
+            Timezone::Local => TimezoneOffset::Fixed(Local.offset_from_utc_date(utc)),
+        }
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> TimezoneOffset {
+        match self {
+            Timezone::Zone(tz) => TimezoneOffset::Zone(tz.offset_from_utc_datetime(utc)),
+            Timezone::Fixed(offset) => TimezoneOffset::Fixed(offset.offset_from_utc_datetime(utc)),
+            Timezone::Local => TimezoneOffset::Fixed(Local.offset_from_utc_datetime(utc)),
+        }
+    }
+}
+
+/// Resolve a timezone name: any IANA name (case-insensitive), an
+/// abbreviation people type (`JST`, `IST`, `PST`, ... — US/EU ones follow
+/// their region's DST), or a fixed offset (`UTC+9`, `GMT-3`, `+05:30`).
+pub fn parse(tz: &str) -> Option<Timezone> {
+    let tz = tz.trim();
+    let lower = tz.to_lowercase();
+    let zone = match lower.as_str() {
+        "jst" | "japan" => Some(Tz::Asia__Tokyo),
+        "kst" | "korea" => Some(Tz::Asia__Seoul),
+        "cst" | "china" => Some(Tz::Asia__Shanghai),
+        "ist" | "india" => Some(Tz::Asia__Kolkata),
+        "gmt" | "utc" | "z" => Some(Tz::UTC),
+        "cet" => Some(Tz::Europe__Paris),
+        "eet" => Some(Tz::Europe__Athens),
+        "est" | "edt" => Some(Tz::America__New_York),
+        "cst_us" | "cdt" => Some(Tz::America__Chicago),
+        "mst" | "mdt" => Some(Tz::America__Denver),
+        "pst" | "pdt" => Some(Tz::America__Los_Angeles),
+        "hst" | "hawaii" => Some(Tz::Pacific__Honolulu),
+        "aest" => Some(Tz::Australia__Sydney),
+        "nzst" => Some(Tz::Pacific__Auckland),
+        _ => Tz::from_str(tz)
+            .ok()
+            .or_else(|| chrono_tz::TZ_VARIANTS.iter().copied().find(|zone| zone.name().eq_ignore_ascii_case(tz))),
+    };
+    if let Some(zone) = zone {
+        return Some(Timezone::Zone(zone));
+    }
+
+    // "+9", "-05:30", "UTC+9", "GMT-3"
+    let raw = lower.trim_start_matches("utc").trim_start_matches("gmt");
+    let (sign, rest) = match raw.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, raw.strip_prefix('+').unwrap_or(raw)),
+    };
+    let (h, m) = rest.split_once(':').unwrap_or((rest, "0"));
+    let (h, m) = (h.parse::<i32>().ok()?, m.parse::<i32>().ok()?);
+    if h > 14 || m >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (h * 60 + m) * 60).map(Timezone::Fixed)
+}
+
+/// [`parse`], falling back to UTC for unknown names.
+pub fn parse_or_utc(tz: &str) -> Timezone {
+    parse(tz).unwrap_or(Timezone::UTC)
+}
+
+/// `tz`, or the server's timezone when it is unset or unknown.
+pub fn parse_or_local(tz: Option<&str>) -> Timezone {
+    tz.and_then(parse).unwrap_or(Timezone::Local)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn offset_on(tz: &str, date: &str) -> i32 {
+        let utc = NaiveDate::from_str(date).unwrap().and_hms_opt(12, 0, 0).unwrap().and_utc();
+        utc.with_timezone(&parse(tz).unwrap()).offset().fix().local_minus_utc()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(offset_on("Asia/Tokyo", "2026-07-01"), 9 * 3600);
+        assert_eq!(offset_on("IST", "2026-07-01"), 5 * 3600 + 1800);
+        assert_eq!(offset_on("UTC-5", "2026-07-01"), -5 * 3600);
+        assert_eq!(offset_on("+05:30", "2026-01-01"), 5 * 3600 + 1800);
+        assert_eq!(offset_on("utc", "2026-01-01"), 0);
+        assert_eq!(offset_on("europe/madrid", "2026-01-01"), 3600);
+        assert!(parse("Mars/Olympus").is_none());
+        assert!(parse("+25").is_none());
+    }
+
+    #[test]
+    fn test_dst() {
+        assert_eq!(offset_on("Europe/Madrid", "2026-01-15"), 3600);
+        assert_eq!(offset_on("Europe/Madrid", "2026-07-15"), 2 * 3600);
+        assert_eq!(offset_on("America/New_York", "2026-01-15"), -5 * 3600);
+        assert_eq!(offset_on("PST", "2026-07-15"), -7 * 3600);
+
+        // 09:00 local is 08:00 UTC in winter and 07:00 UTC in summer
+        let madrid = parse("Europe/Madrid").unwrap();
+        let nine = |date: &str| {
+            let local = NaiveDate::from_str(date).unwrap().and_hms_opt(9, 0, 0).unwrap();
+            madrid.from_local_datetime(&local).single().unwrap().with_timezone(&Utc).format("%H:%M").to_string()
+        };
+        assert_eq!(nine("2026-01-15"), "08:00");
+        assert_eq!(nine("2026-07-15"), "07:00");
+    }
+}
@@ -0,0 +1,248 @@
+//! Hands-free voice: voice activity detection, wake words and barge-in.
+//!
+//! [`HandsFree`] decides turn-taking for a continuous microphone stream. The
+//! caller feeds it audio, transcribes the utterances it hands back, and tells
+//! it when the agent is thinking or speaking; in return it says when to pause,
+//! resume or drop TTS playback and which transcripts are requests.
+//! [`speech`] wraps the speech-to-text and text-to-speech APIs used by the
+//! realtime gateway endpoint, [`stt`] transcribes push-to-talk recordings,
+//! [`tts`] reads replies aloud, and [`speaker`] tells enrolled household
+//! members apart by voice.
+
+pub mod speaker;
+pub mod speech;
+pub mod stt;
+pub mod tts;
+pub mod vad;
+pub mod wake;
+
+pub use speaker::{SpeakerProfile, SpeakerRegistry};
+pub use speech::SpeechClient;
+pub use stt::SttProvider;
+pub use tts::TtsProvider;
+pub use vad::{Vad, VadConfig, VadEvent};
+pub use wake::WakeWord;
+
+use crate::config::VoiceConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnState {
+    Listening,
+    Thinking,
+    Speaking,
+}
+
+/// What the caller should do after [`HandsFree::push_audio`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// The user started talking over TTS; pause playback.
+    PauseTts,
+    /// Transcribe this utterance and pass the text to [`HandsFree::on_transcript`].
+    Transcribe(Vec<i16>),
+}
+
+/// Outcome of [`HandsFree::on_transcript`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Turn {
+    /// Send this to the agent. Playback paused by barge-in should be stopped.
+    Request(String),
+    /// Only the wake word was heard; the next utterance needs no wake word.
+    Awake,
+    /// Nothing addressed to the assistant; resume paused playback.
+    ResumeTts,
+    Ignore,
+}
+
+pub struct HandsFree {
+    vad: Vad,
+    wake: Option<WakeWord>,
+    barge_in: bool,
+    state: TurnState,
+    /// Playback is paused by barge-in until the utterance is transcribed.
+    paused: bool,
+    /// The wake word was said on its own; the next utterance is a request.
+    awake: bool,
+}
+
+impl HandsFree {
+    pub fn new(config: &VoiceConfig, sample_rate: u32) -> Self {
+        Self {
+            vad: Vad::new(VadConfig {
+                sample_rate,
+                aggressiveness: config.vad_aggressiveness,
+                silence_ms: config.silence_ms,
+                ..Default::default()
+            }),
+            wake: config
+                .wake_word
+                .as_deref()
+                .filter(|w| !w.trim().is_empty())
+                .map(WakeWord::new),
+            barge_in: config.barge_in,
+            state: TurnState::Listening,
+            paused: false,
+            awake: false,
+        }
+    }
+
+    pub fn state(&self) -> TurnState {
+        self.state
+    }
+
+    pub fn wake_word(&self) -> Option<&str> {
+        self.wake.as_ref().map(|w| w.phrase())
+    }
+
+    /// Record what the caller is doing: thinking once a request is sent,
+    /// speaking while TTS plays, listening when playback finishes.
+    pub fn set_state(&mut self, state: TurnState) {
+        self.state = state;
+        if state != TurnState::Speaking {
+            self.paused = false;
+        }
+    }
+
+    pub fn push_audio(&mut self, samples: &[i16]) -> Vec<Action> {
+        let mut actions = Vec::new();
+        for event in self.vad.push(samples) {
+            match event {
+                VadEvent::SpeechStart => {
+                    if self.state == TurnState::Speaking && self.barge_in && !self.paused {
+                        self.paused = true;
+                        actions.push(Action::PauseTts);
+                    }
+                }
+                VadEvent::SpeechEnd(audio) => {
+                    // Without barge-in, speech during playback is most likely
+                    // the TTS itself; while thinking, the turn is taken.
+                    let listening = match self.state {
+                        TurnState::Listening => true,
+                        TurnState::Speaking => self.paused,
+                        TurnState::Thinking => false,
+                    };
+                    if listening {
+                        actions.push(Action::Transcribe(audio));
+                    }
+                }
+            }
+        }
+        actions
+    }
+
+    pub fn on_transcript(&mut self, text: &str) -> Turn {
+        let request = match (&self.wake, self.awake) {
+            (Some(wake), false) => wake.strip(text),
+            _ => Some(text.trim().to_string()),
+        };
+        match request {
+            Some(request) if !request.is_empty() => {
+                self.awake = false;
+                self.set_state(TurnState::Thinking);
+                Turn::Request(request)
+            }
+            Some(_) if self.wake.is_some() && !self.awake => {
+                self.awake = true;
+                Turn::Awake
+            }
+            _ if self.paused => {
+                self.paused = false;
+                Turn::ResumeTts
+            }
+            _ => Turn::Ignore,
+        }
+    }
+}
+
+/// 16-bit mono PCM as a WAV file, for speech-to-text APIs.
+pub fn pcm_to_wav(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for s in samples {
+        wav.extend_from_slice(&s.to_le_bytes());
+    }
+    wav
+}
+
+/// Little-endian 16-bit PCM bytes as samples; a trailing odd byte is dropped.
+pub fn pcm_from_bytes(bytes: &[u8]) -> Vec<i16> {
+    bytes
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(ms: u32) -> Vec<i16> {
+        (0..16 * ms)
+            .map(|i| ((i as f32 * 0.17).sin() * 8000.0) as i16)
+            .collect()
+    }
+
+    fn hands_free(wake_word: Option<&str>) -> HandsFree {
+        let config = VoiceConfig {
+            wake_word: wake_word.map(String::from),
+            ..Default::default()
+        };
+        let mut hf = HandsFree::new(&config, 16_000);
+        hf.push_audio(&vec![0; 16 * 200]);
+        hf
+    }
+
+    #[test]
+    fn test_barge_in_pauses_then_resumes_or_stops() {
+        let mut hf = hands_free(Some("ねぇチャット"));
+        hf.set_state(TurnState::Speaking);
+
+        let actions = hf.push_audio(&tone(300));
+        assert_eq!(actions, vec![Action::PauseTts]);
+        let actions = hf.push_audio(&vec![0; 16 * 1000]);
+        assert!(matches!(actions.as_slice(), [Action::Transcribe(_)]));
+        // Not addressed to us (e.g. someone else in the room).
+        assert_eq!(hf.on_transcript("うん、そうだね"), Turn::ResumeTts);
+
+        hf.push_audio(&tone(300));
+        hf.push_audio(&vec![0; 16 * 1000]);
+        assert_eq!(hf.on_transcript("ねぇチャット、止めて"), Turn::Request("止めて".into()));
+        assert_eq!(hf.state(), TurnState::Thinking);
+
+        // Speech while thinking is not transcribed.
+        hf.push_audio(&tone(300));
+        assert!(hf.push_audio(&vec![0; 16 * 1000]).is_empty());
+    }
+
+    #[test]
+    fn test_wake_word_alone_arms_next_utterance() {
+        let mut hf = hands_free(Some("Hey Chat"));
+        assert_eq!(hf.on_transcript("what time is it"), Turn::Ignore);
+        assert_eq!(hf.on_transcript("Hey chat."), Turn::Awake);
+        assert_eq!(hf.on_transcript("what time is it"), Turn::Request("what time is it".into()));
+
+        let mut hf = hands_free(None);
+        assert_eq!(hf.on_transcript("  "), Turn::Ignore);
+        assert_eq!(hf.on_transcript("hello"), Turn::Request("hello".into()));
+    }
+
+    #[test]
+    fn test_wav_roundtrip() {
+        let samples = vec![0i16, 1, -1, i16::MAX, i16::MIN];
+        let wav = pcm_to_wav(&samples, 16_000);
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(wav.len(), 44 + 10);
+        assert_eq!(pcm_from_bytes(&wav[44..]), samples);
+    }
+}
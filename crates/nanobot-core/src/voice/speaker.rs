@@ -0,0 +1,468 @@
+//! Speaker identification for shared devices.
+//!
+//! Each utterance is reduced to a voiceprint, the mean and spread of its
+//! mel-cepstrum over voiced frames, and compared by cosine similarity with the
+//! voiceprints enrolled in a [`SpeakerRegistry`]. The registry also keeps
+//! notes on each speaker (preferences they asked to be remembered) for the
+//! agent's prompt. Only voiceprints and notes are stored, never audio, and
+//! [`SpeakerRegistry::forget`] removes both.
+
+use std::f32::consts::{PI, TAU};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::vad::frame_level_db;
+use crate::util::{now_ms, read_json, write_json};
+
+const FRAME_MS: u32 = 25;
+const HOP_MS: u32 = 10;
+const MEL_BANDS: usize = 26;
+const CEPSTRA: usize = 12;
+/// Sinusoidal lifter length, so higher cepstra weigh in against c1.
+const LIFTER: f32 = 22.0;
+/// Frames this far below the loudest one are left out as silence.
+const DYNAMIC_RANGE_DB: f32 = 35.0;
+/// Voiced frames (of 10 ms) needed for a usable voiceprint.
+const MIN_VOICED_FRAMES: usize = 80;
+/// Clips quieter than this overall have no voiceprint.
+const MIN_LEVEL_DB: f32 = -55.0;
+
+/// Notes kept per speaker; the oldest are dropped first.
+pub const MAX_NOTES: usize = 50;
+
+/// Phrases that ask for something to be remembered.
+const REMEMBER_CUES: &[&str] = &["覚えておいて", "覚えといて", "remember that"];
+
+/// Voiceprint of an utterance (16-bit mono PCM), or `None` when it is too
+/// short or quiet to tell who is speaking.
+pub fn voiceprint(samples: &[i16], sample_rate: u32) -> Option<Vec<f32>> {
+    let frame_len = (sample_rate * FRAME_MS / 1000) as usize;
+    let hop = (sample_rate * HOP_MS / 1000) as usize;
+    if frame_len < 2 || samples.len() < frame_len || frame_level_db(samples) < MIN_LEVEL_DB {
+        return None;
+    }
+    let n_fft = frame_len.next_power_of_two();
+    let window: Vec<f32> = (0..frame_len)
+        .map(|i| 0.5 - 0.5 * (TAU * i as f32 / (frame_len - 1) as f32).cos())
+        .collect();
+    let filters = mel_filters(n_fft, sample_rate);
+    let lifter: Vec<f32> = (1..=CEPSTRA)
+        .map(|k| 1.0 + LIFTER / 2.0 * (PI * k as f32 / LIFTER).sin())
+        .collect();
+
+    // (frame energy in dB, liftered cepstrum)
+    let mut frames: Vec<(f32, Vec<f32>)> = Vec::new();
+    let (mut re, mut im) = (vec![0.0f32; n_fft], vec![0.0f32; n_fft]);
+    for start in (0..=samples.len() - frame_len).step_by(hop.max(1)) {
+        re.fill(0.0);
+        im.fill(0.0);
+        let mut prev = if start > 0 { samples[start - 1] as f32 } else { 0.0 };
+        for i in 0..frame_len {
+            let s = samples[start + i] as f32;
+            // Pre-emphasis flattens the spectral tilt every voice shares.
+            re[i] = (s - 0.97 * prev) / 32768.0 * window[i];
+            prev = s;
+        }
+        fft(&mut re, &mut im);
+        let power: Vec<f32> = (0..=n_fft / 2).map(|k| re[k] * re[k] + im[k] * im[k]).collect();
+        let energy: f32 = power.iter().sum();
+        let log_mel: Vec<f32> = filters
+            .iter()
+            .map(|f| f.iter().map(|&(k, w)| power[k] * w).sum::<f32>().max(1e-10).ln())
+            .collect();
+        let cepstrum = (1..=CEPSTRA)
+            .map(|k| {
+                let c: f32 = log_mel
+                    .iter()
+                    .enumerate()
+                    .map(|(m, &e)| e * (PI * k as f32 * (m as f32 + 0.5) / MEL_BANDS as f32).cos())
+                    .sum();
+                c * lifter[k - 1]
+            })
+            .collect();
+        frames.push((10.0 * energy.max(1e-10).log10(), cepstrum));
+    }
+
+    let loudest = frames.iter().map(|(e, _)| *e).fold(f32::MIN, f32::max);
+    let voiced: Vec<&[f32]> = frames
+        .iter()
+        .filter(|(e, _)| *e >= loudest - DYNAMIC_RANGE_DB)
+        .map(|(_, c)| c.as_slice())
+        .collect();
+    if voiced.len() < MIN_VOICED_FRAMES {
+        return None;
+    }
+    let n = voiced.len() as f32;
+    let mean: Vec<f32> = (0..CEPSTRA).map(|k| voiced.iter().map(|c| c[k]).sum::<f32>() / n).collect();
+    let std = (0..CEPSTRA).map(|k| (voiced.iter().map(|c| (c[k] - mean[k]).powi(2)).sum::<f32>() / n).sqrt());
+    let mut print: Vec<f32> = mean.iter().copied().chain(std).collect();
+    normalize(&mut print);
+    Some(print)
+}
+
+/// Cosine similarity of two voiceprints (0 when their lengths differ).
+pub fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    dot / (norm(a) * norm(b)).max(f32::EPSILON)
+}
+
+fn normalize(v: &mut [f32]) {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Triangular mel filters over the bins of an `n_fft`-point spectrum, as
+/// (bin, weight) pairs.
+fn mel_filters(n_fft: usize, sample_rate: u32) -> Vec<Vec<(usize, f32)>> {
+    let to_mel = |hz: f32| 2595.0 * (1.0 + hz / 700.0).log10();
+    let to_bin = |mel: f32| 700.0 * (10f32.powf(mel / 2595.0) - 1.0) * n_fft as f32 / sample_rate as f32;
+    let (lo, hi) = (to_mel(100.0), to_mel((sample_rate as f32 / 2.0).min(7600.0)));
+    let edges: Vec<f32> = (0..MEL_BANDS + 2)
+        .map(|i| to_bin(lo + (hi - lo) * i as f32 / (MEL_BANDS + 1) as f32))
+        .collect();
+    edges
+        .windows(3)
+        .map(|e| {
+            let (l, c, r) = (e[0], e[1], e[2]);
+            (l.ceil() as usize..=(r.floor() as usize).min(n_fft / 2))
+                .filter_map(|k| {
+                    let x = k as f32;
+                    let w = if x <= c { (x - l) / (c - l) } else { (r - x) / (r - c) };
+                    (w > 0.0).then_some((k, w))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// In-place radix-2 FFT; the length must be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -TAU / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let tr = re[b] * cos - im[b] * sin;
+                let ti = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// If `transcript` asks for something to be remembered ("remember that I
+/// take my coffee black", "辛いものが苦手って覚えておいて"), the thing to remember.
+pub fn preference_note(transcript: &str) -> Option<String> {
+    // ASCII-only lowering keeps byte offsets valid for `transcript`.
+    let lower = transcript.to_ascii_lowercase();
+    let (pos, cue) = REMEMBER_CUES
+        .iter()
+        .find_map(|cue| lower.find(cue).map(|pos| (pos, *cue)))?;
+    let punctuation = |c: char| c.is_whitespace() || matches!(c, '、' | '。' | ',' | '.' | ':' | '!' | '！');
+    let before = transcript[..pos].trim_end_matches(punctuation);
+    let before = before
+        .strip_suffix("って")
+        .or_else(|| before.strip_suffix('と'))
+        .unwrap_or(before);
+    let after = transcript[pos + cue.len()..].trim_matches(punctuation);
+    let note = [before.trim(), after]
+        .iter()
+        .filter(|s| !s.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!note.is_empty()).then_some(note)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeakerProfile {
+    pub id: String,
+    pub name: String,
+    pub voiceprint: Vec<f32>,
+    /// Utterances averaged into the voiceprint.
+    pub enrollments: u32,
+    #[serde(default)]
+    pub notes: Vec<String>,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+impl SpeakerProfile {
+    /// System-prompt addition telling the agent who is talking.
+    pub fn prompt(&self) -> String {
+        let mut prompt = format!(
+            "This is a shared voice device. The person speaking now is {}; address them by name when natural.",
+            self.name
+        );
+        if !self.notes.is_empty() {
+            prompt.push_str(&format!("\nWhat {} asked you to remember:", self.name));
+            for note in &self.notes {
+                prompt.push_str(&format!("\n- {}", note));
+            }
+        }
+        prompt
+    }
+
+    fn matches(&self, speaker: &str) -> bool {
+        let speaker = speaker.trim();
+        self.id == speaker || self.name.eq_ignore_ascii_case(speaker)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SpeakerStore {
+    #[serde(default)]
+    speakers: Vec<SpeakerProfile>,
+}
+
+/// File-backed speaker profiles.
+pub struct SpeakerRegistry {
+    store_path: PathBuf,
+    store: Option<SpeakerStore>,
+    threshold: f32,
+}
+
+impl SpeakerRegistry {
+    pub fn new(store_path: PathBuf, threshold: f32) -> Self {
+        Self {
+            store_path,
+            store: None,
+            threshold,
+        }
+    }
+
+    /// `<data dir>/voice/speakers.json`.
+    pub fn default_path() -> PathBuf {
+        crate::config::get_data_dir().join("voice").join("speakers.json")
+    }
+
+    fn load_store(&mut self) -> &mut SpeakerStore {
+        if self.store.is_none() {
+            self.store = Some(read_json(&self.store_path, "speaker profiles"));
+        }
+        self.store.as_mut().unwrap()
+    }
+
+    fn save_store(&mut self) {
+        if let Some(ref store) = self.store {
+            write_json(&self.store_path, store, "speaker profiles");
+        }
+    }
+
+    pub fn list(&mut self) -> Vec<SpeakerProfile> {
+        self.load_store().speakers.clone()
+    }
+
+    /// Look a speaker up by id or (case-insensitive) name.
+    pub fn find(&mut self, speaker: &str) -> Option<SpeakerProfile> {
+        self.load_store().speakers.iter().find(|p| p.matches(speaker)).cloned()
+    }
+
+    /// Add an utterance's voiceprint to `name`'s profile, creating it on first
+    /// enrollment. More enrollments make recognition more reliable.
+    pub fn enroll(&mut self, name: &str, print: &[f32]) -> SpeakerProfile {
+        let now = now_ms();
+        let name = name.trim();
+        let store = self.load_store();
+        let profile = match store.speakers.iter().position(|p| p.name.eq_ignore_ascii_case(name)) {
+            Some(i) => {
+                let profile = &mut store.speakers[i];
+                if profile.voiceprint.len() == print.len() {
+                    let n = profile.enrollments as f32;
+                    for (v, p) in profile.voiceprint.iter_mut().zip(print) {
+                        *v = (*v * n + p) / (n + 1.0);
+                    }
+                    normalize(&mut profile.voiceprint);
+                } else {
+                    profile.voiceprint = print.to_vec();
+                }
+                profile.enrollments += 1;
+                profile.updated_at_ms = now;
+                profile.clone()
+            }
+            None => {
+                let profile = SpeakerProfile {
+                    id: format!("spk_{}", &Uuid::new_v4().simple().to_string()[..12]),
+                    name: name.to_string(),
+                    voiceprint: print.to_vec(),
+                    enrollments: 1,
+                    notes: Vec::new(),
+                    created_at_ms: now,
+                    updated_at_ms: now,
+                };
+                store.speakers.push(profile.clone());
+                profile
+            }
+        };
+        self.save_store();
+        profile
+    }
+
+    /// The enrolled speaker whose voiceprint best matches, with the
+    /// similarity, if any clears the threshold.
+    pub fn identify(&mut self, print: &[f32]) -> Option<(SpeakerProfile, f32)> {
+        let threshold = self.threshold;
+        self.load_store()
+            .speakers
+            .iter()
+            .map(|p| (p, similarity(&p.voiceprint, print)))
+            .filter(|(_, score)| *score >= threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(p, score)| (p.clone(), score))
+    }
+
+    /// Keep a note on the speaker. Returns false if there is no such speaker.
+    pub fn remember(&mut self, speaker: &str, note: &str) -> bool {
+        let note = note.trim();
+        let Some(profile) = self.load_store().speakers.iter_mut().find(|p| p.matches(speaker)) else {
+            return false;
+        };
+        if !note.is_empty() && !profile.notes.iter().any(|n| n == note) {
+            profile.notes.push(note.to_string());
+            if profile.notes.len() > MAX_NOTES {
+                let excess = profile.notes.len() - MAX_NOTES;
+                profile.notes.drain(..excess);
+            }
+            profile.updated_at_ms = now_ms();
+        }
+        self.save_store();
+        true
+    }
+
+    /// Drop a speaker's notes but keep their voiceprint.
+    pub fn clear_notes(&mut self, speaker: &str) -> bool {
+        let Some(profile) = self.load_store().speakers.iter_mut().find(|p| p.matches(speaker)) else {
+            return false;
+        };
+        profile.notes.clear();
+        profile.updated_at_ms = now_ms();
+        self.save_store();
+        true
+    }
+
+    pub fn rename(&mut self, speaker: &str, name: &str) -> bool {
+        let Some(profile) = self.load_store().speakers.iter_mut().find(|p| p.matches(speaker)) else {
+            return false;
+        };
+        profile.name = name.trim().to_string();
+        profile.updated_at_ms = now_ms();
+        self.save_store();
+        true
+    }
+
+    /// Delete a speaker's voiceprint and notes.
+    pub fn forget(&mut self, speaker: &str) -> bool {
+        let store = self.load_store();
+        let before = store.speakers.len();
+        store.speakers.retain(|p| !p.matches(speaker));
+        let removed = store.speakers.len() < before;
+        if removed {
+            self.save_store();
+        }
+        removed
+    }
+
+    /// Delete every profile. Returns how many were removed.
+    pub fn forget_all(&mut self) -> usize {
+        let removed = std::mem::take(&mut self.load_store().speakers).len();
+        self.save_store();
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A crude vowel: harmonics of `f0` shaped by two formants, with a little
+    /// deterministic noise so no two clips are identical.
+    fn voice(f0: f32, formants: [f32; 2], ms: u32, seed: u32) -> Vec<i16> {
+        let mut x = seed;
+        (0..16 * ms)
+            .map(|i| {
+                let t = i as f32 / 16_000.0;
+                let mut s = 0.0;
+                let mut h = f0;
+                while h < 7000.0 {
+                    let gain: f32 = formants.iter().map(|f| 1.0 / (1.0 + ((h - f) / 150.0).powi(2))).sum();
+                    s += gain * (TAU * h * t).sin();
+                    h += f0;
+                }
+                x = x.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                let noise = ((x >> 16) as i16 % 200) as f32;
+                (s * 3000.0 + noise) as i16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_identifies_enrolled_speakers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("speakers.json");
+        let mut registry = SpeakerRegistry::new(path.clone(), 0.85);
+
+        let low = |seed| voiceprint(&voice(110.0, [500.0, 1500.0], 1500, seed), 16_000).unwrap();
+        let high = |seed| voiceprint(&voice(220.0, [900.0, 2600.0], 1500, seed), 16_000).unwrap();
+        registry.enroll("Taro", &low(1));
+        registry.enroll("Hanako", &high(2));
+
+        let (who, score) = registry.identify(&low(3)).unwrap();
+        assert_eq!(who.name, "Taro", "score {}", score);
+        assert_eq!(registry.identify(&high(4)).unwrap().0.name, "Hanako");
+
+        assert!(registry.remember("taro", "Takes his coffee black"));
+        assert!(!registry.remember("Jiro", "anything"));
+        let taro = SpeakerRegistry::new(path.clone(), 0.85).find("Taro").unwrap();
+        assert!(taro.prompt().contains("- Takes his coffee black"));
+
+        assert!(registry.forget(&taro.id));
+        assert!(registry.identify(&low(5)).is_none_or(|(p, _)| p.name != "Taro"));
+        assert_eq!(SpeakerRegistry::new(path, 0.85).list().len(), 1);
+    }
+
+    #[test]
+    fn test_short_or_silent_clips_have_no_voiceprint() {
+        assert!(voiceprint(&voice(110.0, [500.0, 1500.0], 300, 1), 16_000).is_none());
+        assert!(voiceprint(&[0; 32_000], 16_000).is_none());
+    }
+
+    #[test]
+    fn test_preference_note() {
+        assert_eq!(
+            preference_note("Remember that I take my coffee black.").as_deref(),
+            Some("I take my coffee black")
+        );
+        assert_eq!(preference_note("辛いものが苦手って覚えておいて").as_deref(), Some("辛いものが苦手"));
+        assert_eq!(preference_note("what did I ask you to remember"), None);
+        assert_eq!(preference_note("覚えておいて"), None);
+    }
+}
@@ -0,0 +1,145 @@
+//! Speech-to-text and text-to-speech over OpenAI-compatible audio APIs.
+//!
+//! Used by the realtime voice endpoint; the key and base URL come from the
+//! `openai` provider (or `OPENAI_API_KEY`), so any server implementing
+//! `/audio/transcriptions` and `/audio/speech` works.
+
+use anyhow::{bail, Context};
+use futures::Stream;
+
+use crate::config::Config;
+use crate::util::http::{self, ClientClass};
+
+/// Sample rate of `response_format: "pcm"` speech (16-bit mono).
+pub const TTS_SAMPLE_RATE: u32 = 24_000;
+
+const DEFAULT_API_BASE: &str = "https://api.openai.com/v1";
+
+#[derive(Clone)]
+pub struct SpeechClient {
+    client: reqwest::Client,
+    api_base: String,
+    api_key: String,
+    stt_model: String,
+    tts_model: String,
+    tts_voice: String,
+}
+
+impl SpeechClient {
+    /// `None` when no OpenAI-compatible key is configured.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let provider = &config.providers.openai;
+        let api_key = Some(provider.api_key.clone())
+            .filter(|k| !k.is_empty())
+            .or_else(|| std::env::var("OPENAI_API_KEY").ok().filter(|k| !k.is_empty()))?;
+        Some(Self {
+            client: http::client_for(ClientClass::Llm),
+            api_base: provider
+                .api_base
+                .clone()
+                .unwrap_or_else(|| DEFAULT_API_BASE.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            api_key,
+            stt_model: config.voice.stt_model.clone(),
+            tts_model: config.voice.tts_model.clone(),
+            tts_voice: config.voice.tts_voice.clone(),
+        })
+    }
+
+    /// Transcribe an audio file (`file_name`'s extension tells the API the format).
+    pub async fn transcribe(&self, audio: Vec<u8>, file_name: &str, language: Option<&str>) -> anyhow::Result<String> {
+        let mut form = reqwest::multipart::Form::new()
+            .text("model", self.stt_model.clone())
+            .part("file", reqwest::multipart::Part::bytes(audio).file_name(file_name.to_string()));
+        if let Some(language) = language.filter(|l| !l.is_empty()) {
+            // ISO-639-1: "ja-JP" → "ja".
+            let code = language.split(['-', '_']).next().unwrap_or(language);
+            form = form.text("language", code.to_lowercase());
+        }
+        let resp = self
+            .client
+            .post(format!("{}/audio/transcriptions", self.api_base))
+            .bearer_auth(&self.api_key)
+            .multipart(form)
+            .send()
+            .await
+            .context("speech-to-text request failed")?;
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await.unwrap_or_default();
+        if !status.is_success() {
+            bail!("speech-to-text failed ({}): {}", status, body["error"]["message"].as_str().unwrap_or(""));
+        }
+        Ok(body["text"].as_str().unwrap_or_default().trim().to_string())
+    }
+
+    /// Speak `text` as a stream of 16-bit mono PCM at [`TTS_SAMPLE_RATE`].
+    pub async fn synthesize_pcm(
+        &self,
+        text: &str,
+        voice: Option<&str>,
+    ) -> anyhow::Result<impl Stream<Item = reqwest::Result<impl AsRef<[u8]>>>> {
+        let resp = self
+            .client
+            .post(format!("{}/audio/speech", self.api_base))
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({
+                "model": self.tts_model,
+                "voice": voice.filter(|v| !v.is_empty()).unwrap_or(&self.tts_voice),
+                "input": text,
+                "response_format": "pcm",
+            }))
+            .send()
+            .await
+            .context("text-to-speech request failed")?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            bail!("text-to-speech failed ({}): {}", status, body.chars().take(200).collect::<String>());
+        }
+        Ok(resp.bytes_stream())
+    }
+}
+
+/// Split finished sentences off the front of streamed reply text, so speech
+/// can start before the reply is complete. ASCII `.!?` only end a sentence
+/// when followed by whitespace ("3.14", or a reply still streaming in).
+pub fn take_sentences(buf: &mut String) -> Vec<String> {
+    let chars: Vec<(usize, char)> = buf.char_indices().collect();
+    let mut out = Vec::new();
+    let mut cut = 0;
+    for (i, &(idx, c)) in chars.iter().enumerate() {
+        let terminal = match c {
+            '。' | '！' | '？' | '\n' => true,
+            '.' | '!' | '?' => chars.get(i + 1).is_some_and(|(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if terminal {
+            let end = idx + c.len_utf8();
+            let sentence = buf[cut..end].trim();
+            if !sentence.is_empty() {
+                out.push(sentence.to_string());
+            }
+            cut = end;
+        }
+    }
+    buf.drain(..cut);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_sentences() {
+        let mut buf = "こんにちは。今日は晴れです！Pi is 3.14. And".to_string();
+        assert_eq!(take_sentences(&mut buf), vec!["こんにちは。", "今日は晴れです！", "Pi is 3.14."]);
+        assert_eq!(buf, " And");
+        buf.push_str(" more?");
+        assert!(take_sentences(&mut buf).is_empty());
+        buf.push('\n');
+        assert_eq!(take_sentences(&mut buf), vec!["And more?"]);
+        assert_eq!(buf, "");
+    }
+}
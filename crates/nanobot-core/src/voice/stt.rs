@@ -0,0 +1,167 @@
+//! Speech-to-text backends for push-to-talk.
+//!
+//! The CLI records an utterance while the key is held and hands the samples
+//! to an [`SttProvider`]. Three backends exist: the chatweb.ai speech API
+//! (signed-in CLI), any OpenAI-compatible Whisper API ([`SpeechClient`]),
+//! and a local recognizer such as whisper.cpp run as a shell command.
+//! `voice.sttBackend` picks one; `auto` takes the first that is available.
+
+use async_trait::async_trait;
+
+use super::{pcm_to_wav, SpeechClient};
+use crate::config::Config;
+use crate::util::http::{self, ClientClass};
+
+#[async_trait]
+pub trait SttProvider: Send + Sync {
+    /// Short name for status lines ("chatweb", "openai", "local").
+    fn name(&self) -> &str;
+
+    /// Transcribe 16-bit mono samples; empty when nothing was said.
+    async fn transcribe(&self, samples: &[i16], sample_rate: u32) -> anyhow::Result<String>;
+}
+
+/// The chatweb.ai speech API (`/api/v1/speech/recognize`), for signed-in users.
+pub struct ChatwebStt {
+    client: reqwest::Client,
+    base_url: String,
+    auth_token: String,
+}
+
+impl ChatwebStt {
+    pub fn new(base_url: &str, auth_token: &str) -> Self {
+        Self {
+            client: http::client_for(ClientClass::Llm),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth_token: auth_token.to_string(),
+        }
+    }
+
+    /// Transcribe an already encoded WAV file.
+    pub async fn transcribe_wav(&self, wav: Vec<u8>) -> anyhow::Result<String> {
+        let resp = self
+            .client
+            .post(format!("{}/api/v1/speech/recognize", self.base_url))
+            .bearer_auth(&self.auth_token)
+            .header("Content-Type", "audio/wav")
+            .body(wav)
+            .send()
+            .await?;
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await.unwrap_or_default();
+        if !status.is_success() {
+            anyhow::bail!("speech recognition failed ({}): {}", status, body["error"].as_str().unwrap_or(""));
+        }
+        Ok(body["text"].as_str().unwrap_or_default().trim().to_string())
+    }
+}
+
+#[async_trait]
+impl SttProvider for ChatwebStt {
+    fn name(&self) -> &str {
+        "chatweb"
+    }
+
+    async fn transcribe(&self, samples: &[i16], sample_rate: u32) -> anyhow::Result<String> {
+        self.transcribe_wav(pcm_to_wav(samples, sample_rate)).await
+    }
+}
+
+/// An OpenAI-compatible `/audio/transcriptions` endpoint (Whisper).
+pub struct WhisperApiStt {
+    speech: SpeechClient,
+    language: Option<String>,
+}
+
+impl WhisperApiStt {
+    /// `None` when no OpenAI-compatible key is configured.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        Some(Self {
+            speech: SpeechClient::from_config(config)?,
+            language: config.voice.stt_language.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl SttProvider for WhisperApiStt {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    async fn transcribe(&self, samples: &[i16], sample_rate: u32) -> anyhow::Result<String> {
+        let wav = pcm_to_wav(samples, sample_rate);
+        self.speech.transcribe(wav, "speech.wav", self.language.as_deref()).await
+    }
+}
+
+/// A local recognizer run per utterance (`voice.sttCommand`): `{wav}` is
+/// replaced by a temporary WAV file and stdout is the transcript.
+pub struct LocalWhisperStt {
+    command: String,
+}
+
+impl LocalWhisperStt {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self { command: command.into() }
+    }
+}
+
+#[async_trait]
+impl SttProvider for LocalWhisperStt {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    async fn transcribe(&self, samples: &[i16], sample_rate: u32) -> anyhow::Result<String> {
+        let wav = pcm_to_wav(samples, sample_rate);
+        let command = self.command.clone();
+        tokio::task::spawn_blocking(move || crate::kiosk::transcribe_locally(&command, &wav)).await?
+    }
+}
+
+/// The backend `voice.sttBackend` selects. `chatweb` is the signed-in
+/// user's chatweb.ai backend, if any; `auto` prefers it, then an
+/// OpenAI-compatible key, then `voice.sttCommand`.
+pub fn from_config(config: &Config, chatweb: Option<ChatwebStt>) -> Option<Box<dyn SttProvider>> {
+    let local = || {
+        let command = config.voice.stt_command.as_deref().filter(|c| !c.trim().is_empty())?;
+        Some(Box::new(LocalWhisperStt::new(command)) as Box<dyn SttProvider>)
+    };
+    let openai = || WhisperApiStt::from_config(config).map(|stt| Box::new(stt) as Box<dyn SttProvider>);
+    match config.voice.stt_backend.as_str() {
+        "local" => local(),
+        "openai" => openai(),
+        "chatweb" => chatweb.map(|stt| Box::new(stt) as Box<dyn SttProvider>),
+        other => {
+            if other != "auto" {
+                tracing::warn!("Unknown voice.sttBackend '{}', using auto", other);
+            }
+            chatweb
+                .map(|stt| Box::new(stt) as Box<dyn SttProvider>)
+                .or_else(openai)
+                .or_else(local)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_backend() {
+        let mut config = Config::default();
+        config.voice.stt_backend = "local".into();
+        assert!(from_config(&config, None).is_none());
+
+        config.voice.stt_command = Some("test -s {wav} && echo ' こんにちは '".into());
+        let stt = from_config(&config, Some(ChatwebStt::new("https://chatweb.ai", "token"))).unwrap();
+        assert_eq!(stt.name(), "local");
+        assert_eq!(stt.transcribe(&[0; 1600], 16_000).await.unwrap(), "こんにちは");
+
+        config.voice.stt_backend = "auto".into();
+        let stt = from_config(&config, Some(ChatwebStt::new("https://chatweb.ai", "token"))).unwrap();
+        assert_eq!(stt.name(), "chatweb");
+    }
+}
@@ -0,0 +1,286 @@
+//! Text-to-speech backends for reading replies aloud.
+//!
+//! Voice mode hands the finished reply to a [`TtsProvider`] and plays the
+//! audio it returns. Backends: the chatweb.ai speech API (signed-in CLI),
+//! ElevenLabs (`ELEVENLABS_API_KEY`), any OpenAI-compatible `/audio/speech`
+//! endpoint ([`SpeechClient`]), and a local synthesizer such as `say` or
+//! espeak-ng run as a shell command. `voice.ttsBackend` picks one; `auto`
+//! takes the first that is available, ending with the local one.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use async_trait::async_trait;
+use futures::StreamExt;
+
+use super::speech::TTS_SAMPLE_RATE;
+use super::{pcm_from_bytes, pcm_to_wav, SpeechClient};
+use crate::config::Config;
+use crate::util::http::{self, ClientClass};
+
+/// Synthesized speech, ready to be written to a file and played.
+pub struct Speech {
+    pub audio: Vec<u8>,
+    /// File extension of the encoding: "mp3" or "wav".
+    pub format: &'static str,
+}
+
+#[async_trait]
+pub trait TtsProvider: Send + Sync {
+    /// Short name for status lines ("chatweb", "elevenlabs", "openai", "local").
+    fn name(&self) -> &str;
+
+    async fn synthesize(&self, text: &str) -> anyhow::Result<Speech>;
+}
+
+/// The chatweb.ai speech API (`/api/v1/speech/synthesize`), for signed-in
+/// users. The session picks the voice on the server.
+pub struct ChatwebTts {
+    client: reqwest::Client,
+    base_url: String,
+    auth_token: String,
+    session_id: String,
+}
+
+impl ChatwebTts {
+    pub fn new(base_url: &str, auth_token: &str, session_id: &str) -> Self {
+        Self {
+            client: http::client_for(ClientClass::Llm),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth_token: auth_token.to_string(),
+            session_id: session_id.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl TtsProvider for ChatwebTts {
+    fn name(&self) -> &str {
+        "chatweb"
+    }
+
+    async fn synthesize(&self, text: &str) -> anyhow::Result<Speech> {
+        let resp = self
+            .client
+            .post(format!("{}/api/v1/speech/synthesize", self.base_url))
+            .bearer_auth(&self.auth_token)
+            .json(&serde_json::json!({ "text": text, "session_id": self.session_id }))
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            anyhow::bail!("speech synthesis failed ({})", resp.status());
+        }
+        Ok(Speech { audio: resp.bytes().await?.to_vec(), format: "mp3" })
+    }
+}
+
+/// ElevenLabs text-to-speech.
+pub struct ElevenLabsTts {
+    client: reqwest::Client,
+    api_key: String,
+    voice_id: String,
+    model: String,
+}
+
+impl ElevenLabsTts {
+    /// `None` when `ELEVENLABS_API_KEY` is not set.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let api_key = std::env::var("ELEVENLABS_API_KEY").ok().filter(|k| !k.is_empty())?;
+        Some(Self {
+            client: http::client_for(ClientClass::General),
+            api_key,
+            voice_id: config.voice.elevenlabs_voice_id.clone(),
+            model: config.voice.elevenlabs_model.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl TtsProvider for ElevenLabsTts {
+    fn name(&self) -> &str {
+        "elevenlabs"
+    }
+
+    async fn synthesize(&self, text: &str) -> anyhow::Result<Speech> {
+        let resp = self
+            .client
+            .post(format!("https://api.elevenlabs.io/v1/text-to-speech/{}", self.voice_id))
+            .header("xi-api-key", &self.api_key)
+            .header("Accept", "audio/mpeg")
+            .json(&serde_json::json!({ "text": text, "model_id": self.model }))
+            .send()
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            anyhow::bail!("ElevenLabs speech failed ({}): {}", status, body.chars().take(200).collect::<String>());
+        }
+        Ok(Speech { audio: resp.bytes().await?.to_vec(), format: "mp3" })
+    }
+}
+
+/// An OpenAI-compatible `/audio/speech` endpoint, using `voice.ttsModel`
+/// and `voice.ttsVoice`.
+pub struct OpenAiTts {
+    speech: SpeechClient,
+}
+
+impl OpenAiTts {
+    /// `None` when no OpenAI-compatible key is configured.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        Some(Self { speech: SpeechClient::from_config(config)? })
+    }
+}
+
+#[async_trait]
+impl TtsProvider for OpenAiTts {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    async fn synthesize(&self, text: &str) -> anyhow::Result<Speech> {
+        let mut stream = Box::pin(self.speech.synthesize_pcm(text, None).await?);
+        let mut pcm = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            pcm.extend_from_slice(chunk?.as_ref());
+        }
+        Ok(Speech { audio: pcm_to_wav(&pcm_from_bytes(&pcm), TTS_SAMPLE_RATE), format: "wav" })
+    }
+}
+
+/// A local synthesizer (`voice.ttsCommand`): the text goes to stdin and
+/// `{wav}` is replaced by the file to write.
+pub struct LocalTts {
+    command: String,
+}
+
+impl LocalTts {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self { command: command.into() }
+    }
+
+    /// `say` on macOS, espeak-ng elsewhere.
+    pub fn default_command() -> &'static str {
+        if cfg!(target_os = "macos") {
+            "say --file-format=WAVE --data-format=LEI16@22050 -o {wav}"
+        } else {
+            "espeak-ng --stdin -w {wav}"
+        }
+    }
+}
+
+#[async_trait]
+impl TtsProvider for LocalTts {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    async fn synthesize(&self, text: &str) -> anyhow::Result<Speech> {
+        let (command, text) = (self.command.clone(), text.to_string());
+        let audio = tokio::task::spawn_blocking(move || synthesize_locally(&command, &text)).await??;
+        Ok(Speech { audio, format: "wav" })
+    }
+}
+
+fn synthesize_locally(command: &str, text: &str) -> anyhow::Result<Vec<u8>> {
+    let path = std::env::temp_dir().join(format!("chatweb-tts-{}.wav", std::process::id()));
+    let mut child = Command::new("sh")
+        .args(["-c", &command.replace("{wav}", &path.display().to_string())])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    let audio = std::fs::read(&path);
+    let _ = std::fs::remove_file(&path);
+    if !status.success() {
+        anyhow::bail!("local speech synthesis failed ({})", status);
+    }
+    Ok(audio?)
+}
+
+/// The backend `voice.ttsBackend` selects. `chatweb` is the signed-in
+/// user's chatweb.ai backend, if any; `auto` prefers it, then ElevenLabs,
+/// then an OpenAI-compatible key, then the local synthesizer.
+pub fn from_config(config: &Config, chatweb: Option<ChatwebTts>) -> Option<Box<dyn TtsProvider>> {
+    let local = || {
+        let command = config.voice.tts_command.as_deref().filter(|c| !c.trim().is_empty());
+        Box::new(LocalTts::new(command.unwrap_or(LocalTts::default_command()))) as Box<dyn TtsProvider>
+    };
+    let elevenlabs = || ElevenLabsTts::from_config(config).map(|tts| Box::new(tts) as Box<dyn TtsProvider>);
+    let openai = || OpenAiTts::from_config(config).map(|tts| Box::new(tts) as Box<dyn TtsProvider>);
+    match config.voice.tts_backend.as_str() {
+        "local" => Some(local()),
+        "elevenlabs" => elevenlabs(),
+        "openai" => openai(),
+        "chatweb" => chatweb.map(|tts| Box::new(tts) as Box<dyn TtsProvider>),
+        other => {
+            if other != "auto" {
+                tracing::warn!("Unknown voice.ttsBackend '{}', using auto", other);
+            }
+            chatweb
+                .map(|tts| Box::new(tts) as Box<dyn TtsProvider>)
+                .or_else(elevenlabs)
+                .or_else(openai)
+                .or_else(|| Some(local()))
+        }
+    }
+}
+
+/// The part of a reply worth reading aloud: code blocks, the source list
+/// and URLs are dropped, and markdown markup is removed.
+pub fn speakable(reply: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_code = false;
+    for line in reply.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if trimmed.starts_with("📚") {
+            break;
+        }
+        if in_code {
+            continue;
+        }
+        let words: Vec<&str> = trimmed
+            .trim_start_matches(['#', '>', '-', '*', ' '])
+            .split(' ')
+            .filter(|word| !word.contains("://"))
+            .collect();
+        let line: String = words.join(" ").chars().filter(|c| !matches!(c, '*' | '_' | '`' | '#')).collect();
+        if !line.trim().is_empty() {
+            lines.push(line.trim().to_string());
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speakable() {
+        let reply = "## 東京の天気\n**晴れ**です。詳しくは https://example.com/weather を見てください。\n\n```sh\ncurl wttr.in\n```\n- 最高 `25` 度\n\n📚 出典:\n[1] https://example.com/weather";
+        assert_eq!(speakable(reply), "東京の天気\n晴れです。詳しくは を見てください。\n最高 25 度");
+    }
+
+    #[tokio::test]
+    async fn test_local_backend() {
+        let mut config = Config::default();
+        config.voice.tts_backend = "local".into();
+        config.voice.tts_command = Some("cat > {wav}".into());
+        let tts = from_config(&config, Some(ChatwebTts::new("https://chatweb.ai", "token", "s1"))).unwrap();
+        assert_eq!(tts.name(), "local");
+        let speech = tts.synthesize("hello").await.unwrap();
+        assert_eq!((speech.audio.as_slice(), speech.format), (&b"hello"[..], "wav"));
+
+        config.voice.tts_backend = "chatweb".into();
+        assert!(from_config(&config, None).is_none());
+    }
+}
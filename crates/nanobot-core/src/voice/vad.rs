@@ -0,0 +1,263 @@
+//! Energy-based voice activity detection over 16-bit mono PCM.
+//!
+//! Each 20 ms frame is voiced when its level clears an adaptive noise floor by
+//! a margin; the margin, the minimum level and the onset time grow with the
+//! aggressiveness (0-3, as in WebRTC's VAD). [`Vad::push`] turns a stream of
+//! samples into [`VadEvent`]s and buffers each utterance, including some
+//! pre-roll so the first syllable is not clipped.
+
+use std::collections::VecDeque;
+
+const FRAME_MS: u32 = 20;
+
+/// Audio kept from before the onset and prepended to the utterance.
+const PRE_ROLL_MS: u32 = 300;
+
+/// Level of digital silence.
+const MIN_DBFS: f32 = -90.0;
+
+#[derive(Debug, Clone)]
+pub struct VadConfig {
+    pub sample_rate: u32,
+    /// 0 (lets the most through) to 3 (filters the most noise).
+    pub aggressiveness: u8,
+    /// Trailing silence that ends an utterance.
+    pub silence_ms: u32,
+    /// Utterances are cut at this length.
+    pub max_utterance_ms: u32,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 16_000,
+            aggressiveness: 2,
+            silence_ms: 700,
+            max_utterance_ms: 30_000,
+        }
+    }
+}
+
+impl VadConfig {
+    fn level(&self) -> usize {
+        self.aggressiveness.min(3) as usize
+    }
+
+    /// dB above the noise floor a frame needs to count as voiced.
+    fn margin_db(&self) -> f32 {
+        [6.0, 9.0, 12.0, 15.0][self.level()]
+    }
+
+    /// Frames quieter than this are never voiced.
+    fn min_level_db(&self) -> f32 {
+        [-55.0, -50.0, -45.0, -40.0][self.level()]
+    }
+
+    /// Voiced audio needed before an utterance starts.
+    fn onset_ms(&self) -> u32 {
+        [40, 60, 100, 140][self.level()]
+    }
+
+    fn samples(&self, ms: u32) -> usize {
+        (self.sample_rate as u64 * ms as u64 / 1000) as usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VadEvent {
+    SpeechStart,
+    /// The finished utterance, pre-roll included.
+    SpeechEnd(Vec<i16>),
+}
+
+/// RMS level of a frame in dBFS.
+pub fn frame_level_db(frame: &[i16]) -> f32 {
+    if frame.is_empty() {
+        return MIN_DBFS;
+    }
+    let sum: f64 = frame.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    let rms = (sum / frame.len() as f64).sqrt() / 32768.0;
+    if rms <= 0.0 {
+        return MIN_DBFS;
+    }
+    (20.0 * rms.log10() as f32).max(MIN_DBFS)
+}
+
+pub struct Vad {
+    config: VadConfig,
+    frame_len: usize,
+    pending: Vec<i16>,
+    pre_roll: VecDeque<i16>,
+    utterance: Vec<i16>,
+    /// Set from the first frame, then tracked on unvoiced frames.
+    noise_floor: Option<f32>,
+    speaking: bool,
+    voiced_ms: u32,
+    silent_ms: u32,
+}
+
+impl Vad {
+    pub fn new(config: VadConfig) -> Self {
+        let frame_len = config.samples(FRAME_MS).max(1);
+        Self {
+            config,
+            frame_len,
+            pending: Vec::new(),
+            pre_roll: VecDeque::new(),
+            utterance: Vec::new(),
+            noise_floor: None,
+            speaking: false,
+            voiced_ms: 0,
+            silent_ms: 0,
+        }
+    }
+
+    pub fn is_speaking(&self) -> bool {
+        self.speaking
+    }
+
+    /// Audio of the utterance in progress, pre-roll included.
+    pub fn current_utterance(&self) -> &[i16] {
+        &self.utterance
+    }
+
+    pub fn noise_floor_db(&self) -> Option<f32> {
+        self.noise_floor
+    }
+
+    /// Feed samples of any length; returns the events they complete.
+    pub fn push(&mut self, samples: &[i16]) -> Vec<VadEvent> {
+        self.pending.extend_from_slice(samples);
+        let mut events = Vec::new();
+        while self.pending.len() >= self.frame_len {
+            let frame: Vec<i16> = self.pending.drain(..self.frame_len).collect();
+            if let Some(event) = self.process_frame(&frame) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// End the current utterance, if any (e.g. when the stream closes).
+    pub fn flush(&mut self) -> Option<VadEvent> {
+        if !self.speaking {
+            return None;
+        }
+        self.utterance.append(&mut self.pending);
+        Some(self.finish())
+    }
+
+    fn process_frame(&mut self, frame: &[i16]) -> Option<VadEvent> {
+        let level = frame_level_db(frame);
+        let floor = *self.noise_floor.get_or_insert(level);
+        let voiced = level >= self.config.min_level_db() && level >= floor + self.config.margin_db();
+        if !voiced {
+            // Follow the floor down quickly and up slowly, so a burst of
+            // speech that slipped under the margin barely moves it.
+            let rate = if level < floor { 0.3 } else { 0.02 };
+            self.noise_floor = Some(floor + (level - floor) * rate);
+        }
+
+        if !self.speaking {
+            self.pre_roll.extend(frame);
+            let keep = self.config.samples(PRE_ROLL_MS);
+            while self.pre_roll.len() > keep {
+                self.pre_roll.pop_front();
+            }
+            if !voiced {
+                self.voiced_ms = 0;
+                return None;
+            }
+            self.voiced_ms += FRAME_MS;
+            if self.voiced_ms < self.config.onset_ms() {
+                return None;
+            }
+            self.speaking = true;
+            self.silent_ms = 0;
+            self.utterance = self.pre_roll.drain(..).collect();
+            return Some(VadEvent::SpeechStart);
+        }
+
+        self.utterance.extend_from_slice(frame);
+        self.silent_ms = if voiced { 0 } else { self.silent_ms + FRAME_MS };
+        let too_long = self.utterance.len() >= self.config.samples(self.config.max_utterance_ms);
+        if self.silent_ms >= self.config.silence_ms || too_long {
+            return Some(self.finish());
+        }
+        None
+    }
+
+    fn finish(&mut self) -> VadEvent {
+        self.speaking = false;
+        self.voiced_ms = 0;
+        self.silent_ms = 0;
+        VadEvent::SpeechEnd(std::mem::take(&mut self.utterance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `ms` of a 440 Hz tone at `amplitude` (16 kHz).
+    fn tone(ms: u32, amplitude: f32) -> Vec<i16> {
+        (0..16 * ms)
+            .map(|i| ((i as f32 * 440.0 * std::f32::consts::TAU / 16_000.0).sin() * amplitude) as i16)
+            .collect()
+    }
+
+    /// Deterministic low-level noise.
+    fn noise(ms: u32, amplitude: i16) -> Vec<i16> {
+        let mut x: u32 = 12345;
+        (0..16 * ms)
+            .map(|_| {
+                x = x.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (x >> 16) as i16 % amplitude.max(1)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_utterance_over_noise() {
+        let mut vad = Vad::new(VadConfig::default());
+        assert!(vad.push(&noise(500, 100)).is_empty());
+        let floor = vad.noise_floor_db().unwrap();
+        assert!(floor < -45.0, "floor {}", floor);
+
+        let events = vad.push(&tone(400, 8000.0));
+        assert_eq!(events, vec![VadEvent::SpeechStart]);
+        assert!(vad.is_speaking());
+
+        let events = vad.push(&noise(1000, 100));
+        let [VadEvent::SpeechEnd(audio)] = events.as_slice() else {
+            panic!("expected end, got {:?}", events);
+        };
+        // Pre-roll + tone + trailing silence.
+        let ms = audio.len() as u32 / 16;
+        assert!((1000..=1500).contains(&ms), "utterance {} ms", ms);
+        assert!(!vad.is_speaking());
+    }
+
+    #[test]
+    fn test_clicks_and_quiet_audio_are_ignored() {
+        let mut vad = Vad::new(VadConfig { aggressiveness: 3, ..Default::default() });
+        vad.push(&noise(200, 100));
+        // A 40 ms click is shorter than the onset at aggressiveness 3.
+        assert!(vad.push(&tone(40, 8000.0)).is_empty());
+        assert!(vad.push(&noise(200, 100)).is_empty());
+        // Far below the minimum level.
+        assert!(vad.push(&tone(500, 50.0)).is_empty());
+        assert_eq!(vad.flush(), None);
+    }
+
+    #[test]
+    fn test_long_utterances_are_cut() {
+        let mut vad = Vad::new(VadConfig { max_utterance_ms: 1000, ..Default::default() });
+        vad.push(&noise(100, 100));
+        let events = vad.push(&tone(1500, 8000.0));
+        assert_eq!(events[0], VadEvent::SpeechStart);
+        assert!(matches!(&events[1], VadEvent::SpeechEnd(a) if a.len() == 16_000));
+        // The rest of the tone starts a new utterance.
+        assert_eq!(events[2..], [VadEvent::SpeechStart]);
+    }
+}
@@ -0,0 +1,101 @@
+//! Wake-word matching on speech-to-text transcripts.
+//!
+//! Transcripts of the same phrase vary ("ねぇチャット", "ねえ、チャット",
+//! "ネエチャット"), so both sides are normalized before matching: case,
+//! spacing and punctuation are dropped, katakana is folded to hiragana, small
+//! kana to their full-size forms, and the long-vowel mark is ignored.
+
+/// How far into a transcript the wake word may start, in normalized
+/// characters, to allow for fillers like "あ、" before it.
+const MAX_LEAD: usize = 6;
+
+#[derive(Debug, Clone)]
+pub struct WakeWord {
+    phrase: String,
+    normalized: Vec<char>,
+}
+
+fn fold(c: char) -> Option<char> {
+    if !c.is_alphanumeric() || c == 'ー' {
+        return None;
+    }
+    // Katakana → hiragana.
+    let c = match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    };
+    let c = match c {
+        'ぁ' => 'あ',
+        'ぃ' => 'い',
+        'ぅ' => 'う',
+        'ぇ' => 'え',
+        'ぉ' => 'お',
+        'っ' => 'つ',
+        'ゃ' => 'や',
+        'ゅ' => 'ゆ',
+        'ょ' => 'よ',
+        'ゎ' => 'わ',
+        _ => c,
+    };
+    c.to_lowercase().next()
+}
+
+impl WakeWord {
+    pub fn new(phrase: &str) -> Self {
+        Self {
+            phrase: phrase.trim().to_string(),
+            normalized: phrase.chars().filter_map(fold).collect(),
+        }
+    }
+
+    pub fn phrase(&self) -> &str {
+        &self.phrase
+    }
+
+    /// If `transcript` opens with the wake word, what was said after it
+    /// (possibly empty).
+    pub fn strip(&self, transcript: &str) -> Option<String> {
+        if self.normalized.is_empty() {
+            return Some(transcript.trim().to_string());
+        }
+        // Normalized characters with the byte offset just past each one.
+        let folded: Vec<(char, usize)> = transcript
+            .char_indices()
+            .filter_map(|(i, c)| fold(c).map(|f| (f, i + c.len_utf8())))
+            .collect();
+        let n = self.normalized.len();
+        if folded.len() < n {
+            return None;
+        }
+        let start = (0..=MAX_LEAD.min(folded.len() - n)).find(|&s| {
+            folded[s..s + n]
+                .iter()
+                .map(|(c, _)| *c)
+                .eq(self.normalized.iter().copied())
+        })?;
+        let end = folded[start + n - 1].1;
+        let rest = transcript[end..].trim_start_matches(|c: char| !c.is_alphanumeric() || c == 'ー');
+        Some(rest.trim_end().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wake_word_variants() {
+        let wake = WakeWord::new("ねぇチャット");
+        assert_eq!(wake.strip("ねえ、チャット。明日の天気は？").as_deref(), Some("明日の天気は？"));
+        assert_eq!(wake.strip("ネエチャット").as_deref(), Some(""));
+        assert_eq!(wake.strip("あ、ねぇチャット 今何時").as_deref(), Some("今何時"));
+        assert_eq!(wake.strip("明日の天気は？"), None);
+        assert_eq!(wake.strip("チャット"), None);
+
+        let wake = WakeWord::new("Hey Chat");
+        assert_eq!(wake.strip("hey, chat. What's on my calendar?").as_deref(), Some("What's on my calendar?"));
+        assert_eq!(wake.strip("so, hey chat").as_deref(), Some(""));
+        // Mentioned later in a sentence, not addressed.
+        assert_eq!(wake.strip("what does hey chat mean"), None);
+    }
+}
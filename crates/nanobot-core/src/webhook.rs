@@ -0,0 +1,432 @@
+//! Outbound webhooks for external automation.
+//!
+//! Agent events (a message processed, a tool executed, a cron job completed,
+//! credits running low) are written to an outbox as one [`WebhookDelivery`]
+//! per subscribed endpoint from [`WebhooksConfig`]. [`flush`] POSTs due
+//! deliveries, signing each body with the endpoint secret, and reschedules
+//! failures with exponential backoff until `max_attempts` is reached. The
+//! outbox doubles as the delivery log and is kept in a JSON file under the
+//! data directory, like delivery records.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::Mutex;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::config::WebhooksConfig;
+use crate::util::{now_ms, read_json, write_json};
+
+/// Header carrying `t=<unix secs>,v1=<hex hmac>`.
+pub const SIGNATURE_HEADER: &str = "X-Nanobot-Signature";
+pub const EVENT_HEADER: &str = "X-Nanobot-Event";
+pub const DELIVERY_HEADER: &str = "X-Nanobot-Delivery";
+
+/// Oldest records beyond this count are dropped on save.
+pub const MAX_RECORDS: usize = 2000;
+
+/// Upper bound for the retry delay.
+const MAX_BACKOFF_SECS: u64 = 3600;
+
+/// Events an endpoint can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    MessageProcessed,
+    ToolExecuted,
+    CronCompleted,
+    CreditsLow,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 4] = [
+        Self::MessageProcessed,
+        Self::ToolExecuted,
+        Self::CronCompleted,
+        Self::CreditsLow,
+    ];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.as_str() == s)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MessageProcessed => "message.processed",
+            Self::ToolExecuted => "tool.executed",
+            Self::CronCompleted => "cron.completed",
+            Self::CreditsLow => "credits.low",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+/// One event queued for one endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub endpoint_id: String,
+    pub url: String,
+    pub event: String,
+    /// Exact JSON body sent (and signed) on every attempt.
+    pub body: String,
+    pub status: WebhookStatus,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_status_code: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub next_attempt_at_ms: u64,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+/// Filter for [`WebhookOutbox::list`].
+#[derive(Debug, Clone, Default)]
+pub struct WebhookFilter {
+    pub event: Option<String>,
+    pub status: Option<WebhookStatus>,
+    pub endpoint_id: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OutboxStore {
+    #[serde(default)]
+    deliveries: Vec<WebhookDelivery>,
+}
+
+/// File-backed webhook outbox and delivery log.
+pub struct WebhookOutbox {
+    store_path: PathBuf,
+    store: Option<OutboxStore>,
+    config: WebhooksConfig,
+}
+
+impl WebhookOutbox {
+    pub fn new(store_path: PathBuf, config: WebhooksConfig) -> Self {
+        Self {
+            store_path,
+            store: None,
+            config,
+        }
+    }
+
+    /// `<data dir>/webhooks/outbox.json`.
+    pub fn default_path() -> PathBuf {
+        crate::config::get_data_dir().join("webhooks").join("outbox.json")
+    }
+
+    /// Whether any endpoint is configured at all.
+    pub fn is_enabled(&self) -> bool {
+        self.config.endpoints.iter().any(|e| e.enabled)
+    }
+
+    fn load_store(&mut self) -> &mut OutboxStore {
+        if self.store.is_none() {
+            self.store = Some(read_json(&self.store_path, "webhook outbox"));
+        }
+        self.store.as_mut().unwrap()
+    }
+
+    fn save_store(&mut self) {
+        if let Some(ref mut store) = self.store {
+            if store.deliveries.len() > MAX_RECORDS {
+                let excess = store.deliveries.len() - MAX_RECORDS;
+                store.deliveries.drain(..excess);
+            }
+            write_json(&self.store_path, store, "webhook outbox");
+        }
+    }
+
+    /// Queue `event` for every endpoint subscribed to it. Returns the
+    /// delivery ids (empty when nobody listens).
+    pub fn emit(&mut self, event: WebhookEvent, data: serde_json::Value) -> Vec<String> {
+        let endpoints: Vec<_> = self
+            .config
+            .endpoints
+            .iter()
+            .filter(|e| e.wants(event.as_str()))
+            .map(|e| (e.id.clone(), e.url.clone()))
+            .collect();
+        if endpoints.is_empty() {
+            return Vec::new();
+        }
+        let now = now_ms();
+        let mut ids = Vec::new();
+        for (endpoint_id, url) in endpoints {
+            let id = Uuid::new_v4().to_string();
+            let body = serde_json::json!({
+                "id": id,
+                "event": event.as_str(),
+                "createdAt": chrono::Utc::now().to_rfc3339(),
+                "data": data,
+            })
+            .to_string();
+            self.load_store().deliveries.push(WebhookDelivery {
+                id: id.clone(),
+                endpoint_id,
+                url,
+                event: event.as_str().to_string(),
+                body,
+                status: WebhookStatus::Pending,
+                attempts: 0,
+                last_status_code: None,
+                last_error: None,
+                next_attempt_at_ms: now,
+                created_at_ms: now,
+                updated_at_ms: now,
+            });
+            ids.push(id);
+        }
+        self.save_store();
+        ids
+    }
+
+    /// Pending deliveries whose next attempt is due at `now_ms`.
+    pub fn due(&mut self, now_ms: u64) -> Vec<WebhookDelivery> {
+        self.load_store()
+            .deliveries
+            .iter()
+            .filter(|d| d.status == WebhookStatus::Pending && d.next_attempt_at_ms <= now_ms)
+            .cloned()
+            .collect()
+    }
+
+    /// Record the outcome of one attempt: `Ok(status code)` from the
+    /// endpoint or `Err` for a transport error. Non-2xx responses are retried
+    /// like errors. Returns the resulting status.
+    pub fn record_attempt(&mut self, id: &str, outcome: Result<u16, String>) -> Option<WebhookStatus> {
+        let max_attempts = self.config.max_attempts.max(1);
+        let initial = self.config.initial_backoff_secs;
+        let now = now_ms();
+        let delivery = self.load_store().deliveries.iter_mut().find(|d| d.id == id)?;
+        delivery.attempts += 1;
+        delivery.updated_at_ms = now;
+        match outcome {
+            Ok(code) => {
+                delivery.last_status_code = Some(code);
+                delivery.last_error = None;
+                if (200..300).contains(&code) {
+                    delivery.status = WebhookStatus::Delivered;
+                } else {
+                    delivery.last_error = Some(format!("HTTP {}", code));
+                }
+            }
+            Err(e) => delivery.last_error = Some(e),
+        }
+        if delivery.status == WebhookStatus::Pending {
+            if delivery.attempts >= max_attempts {
+                delivery.status = WebhookStatus::Failed;
+            } else {
+                delivery.next_attempt_at_ms = now + backoff_secs(initial, delivery.attempts) * 1000;
+            }
+        }
+        let status = delivery.status;
+        self.save_store();
+        Some(status)
+    }
+
+    /// Put a failed delivery back in the queue for an immediate attempt.
+    pub fn retry(&mut self, id: &str) -> bool {
+        let now = now_ms();
+        let Some(delivery) = self.load_store().deliveries.iter_mut().find(|d| d.id == id) else {
+            return false;
+        };
+        if delivery.status != WebhookStatus::Failed {
+            return false;
+        }
+        delivery.status = WebhookStatus::Pending;
+        delivery.attempts = 0;
+        delivery.next_attempt_at_ms = now;
+        delivery.updated_at_ms = now;
+        self.save_store();
+        true
+    }
+
+    pub fn get(&mut self, id: &str) -> Option<WebhookDelivery> {
+        self.load_store().deliveries.iter().find(|d| d.id == id).cloned()
+    }
+
+    /// Deliveries matching `filter`, newest first.
+    pub fn list(&mut self, filter: &WebhookFilter) -> Vec<WebhookDelivery> {
+        self.load_store()
+            .deliveries
+            .iter()
+            .rev()
+            .filter(|d| filter.event.as_ref().is_none_or(|e| &d.event == e))
+            .filter(|d| filter.status.is_none_or(|s| d.status == s))
+            .filter(|d| filter.endpoint_id.as_ref().is_none_or(|e| &d.endpoint_id == e))
+            .take(filter.limit.unwrap_or(100))
+            .cloned()
+            .collect()
+    }
+
+    fn secret_for(&self, endpoint_id: &str) -> Option<String> {
+        self.config
+            .endpoints
+            .iter()
+            .find(|e| e.id == endpoint_id && !e.secret.is_empty())
+            .map(|e| e.secret.clone())
+    }
+}
+
+/// Delay before attempt `attempts + 1`: `initial * 2^(attempts - 1)`, capped.
+pub fn backoff_secs(initial: u64, attempts: u32) -> u64 {
+    let shift = attempts.saturating_sub(1).min(20);
+    initial.max(1).saturating_mul(1 << shift).min(MAX_BACKOFF_SECS)
+}
+
+/// Signature header value for `body` sent at `timestamp`. Receivers verify
+/// by computing HMAC-SHA256 over `"<t>.<body>"` with the shared secret.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC key");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("t={},v1={}", timestamp, hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Send every due delivery once. The outbox is not locked while requests
+/// are in flight. Returns the number of deliveries attempted.
+pub async fn flush(outbox: &Mutex<WebhookOutbox>, client: &reqwest::Client) -> usize {
+    let (due, timeout) = {
+        let mut outbox = outbox.lock().await;
+        let due: Vec<_> = outbox
+            .due(now_ms())
+            .into_iter()
+            .map(|d| {
+                let secret = outbox.secret_for(&d.endpoint_id);
+                (d, secret)
+            })
+            .collect();
+        (due, Duration::from_secs(outbox.config.timeout_secs.max(1)))
+    };
+    let attempted = due.len();
+    for (delivery, secret) in due {
+        let mut req = client
+            .post(&delivery.url)
+            .timeout(timeout)
+            .header("Content-Type", "application/json")
+            .header(EVENT_HEADER, &delivery.event)
+            .header(DELIVERY_HEADER, &delivery.id)
+            .body(delivery.body.clone());
+        if let Some(secret) = secret {
+            req = req.header(SIGNATURE_HEADER, sign(&secret, chrono::Utc::now().timestamp(), &delivery.body));
+        }
+        let outcome = match req.send().await {
+            Ok(resp) => Ok(resp.status().as_u16()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(ref e) = outcome {
+            warn!("Webhook {} to {} failed: {}", delivery.event, delivery.url, e);
+        }
+        outbox.lock().await.record_attempt(&delivery.id, outcome);
+    }
+    attempted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WebhookEndpoint;
+
+    fn temp_outbox(endpoints: Vec<WebhookEndpoint>) -> (tempfile::TempDir, WebhookOutbox) {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = WebhooksConfig {
+            endpoints,
+            max_attempts: 3,
+            initial_backoff_secs: 10,
+            timeout_secs: 5,
+        };
+        let outbox = WebhookOutbox::new(tmp.path().join("webhooks").join("outbox.json"), config);
+        (tmp, outbox)
+    }
+
+    fn endpoint(id: &str, events: &[&str]) -> WebhookEndpoint {
+        WebhookEndpoint {
+            id: id.to_string(),
+            url: format!("https://example.com/{}", id),
+            secret: "s3cret".to_string(),
+            events: events.iter().map(|e| e.to_string()).collect(),
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_event_names_round_trip() {
+        for event in WebhookEvent::ALL {
+            assert_eq!(WebhookEvent::parse(event.as_str()), Some(event));
+        }
+        assert_eq!(WebhookEvent::parse("nope"), None);
+    }
+
+    #[test]
+    fn test_emit_filters_by_subscription() {
+        let (_tmp, mut outbox) = temp_outbox(vec![
+            endpoint("all", &[]),
+            endpoint("tools", &["tool.executed"]),
+            WebhookEndpoint { enabled: false, ..endpoint("off", &[]) },
+        ]);
+        let ids = outbox.emit(WebhookEvent::MessageProcessed, serde_json::json!({"session": "s"}));
+        assert_eq!(ids.len(), 1);
+        let ids = outbox.emit(WebhookEvent::ToolExecuted, serde_json::json!({"tool": "web_search"}));
+        assert_eq!(ids.len(), 2);
+
+        let d = outbox.get(&ids[0]).unwrap();
+        let body: serde_json::Value = serde_json::from_str(&d.body).unwrap();
+        assert_eq!(body["event"], "tool.executed");
+        assert_eq!(body["data"]["tool"], "web_search");
+        assert_eq!(body["id"], d.id.as_str());
+    }
+
+    #[test]
+    fn test_retry_with_backoff_until_failed() {
+        let (tmp, mut outbox) = temp_outbox(vec![endpoint("a", &[])]);
+        let id = outbox.emit(WebhookEvent::CronCompleted, serde_json::json!({})).remove(0);
+        assert_eq!(outbox.due(now_ms()).len(), 1);
+
+        assert_eq!(outbox.record_attempt(&id, Ok(500)), Some(WebhookStatus::Pending));
+        let d = outbox.get(&id).unwrap();
+        assert_eq!(d.last_error.as_deref(), Some("HTTP 500"));
+        assert!(d.next_attempt_at_ms >= now_ms() + 9_000);
+        assert!(outbox.due(now_ms()).is_empty());
+
+        assert_eq!(outbox.record_attempt(&id, Err("timeout".into())), Some(WebhookStatus::Pending));
+        assert_eq!(outbox.record_attempt(&id, Err("timeout".into())), Some(WebhookStatus::Failed));
+        assert_eq!(outbox.list(&WebhookFilter { status: Some(WebhookStatus::Failed), ..Default::default() }).len(), 1);
+
+        assert!(outbox.retry(&id));
+        assert_eq!(outbox.record_attempt(&id, Ok(204)), Some(WebhookStatus::Delivered));
+
+        // Persisted across instances.
+        let mut reloaded = WebhookOutbox::new(tmp.path().join("webhooks").join("outbox.json"), WebhooksConfig::default());
+        assert_eq!(reloaded.get(&id).unwrap().status, WebhookStatus::Delivered);
+    }
+
+    #[test]
+    fn test_backoff_and_signature() {
+        assert_eq!(backoff_secs(10, 1), 10);
+        assert_eq!(backoff_secs(10, 2), 20);
+        assert_eq!(backoff_secs(10, 4), 80);
+        assert_eq!(backoff_secs(10, 30), MAX_BACKOFF_SECS);
+
+        let sig = sign("key", 1700000000, "{}");
+        assert!(sig.starts_with("t=1700000000,v1="));
+        assert_eq!(sig.len(), "t=1700000000,v1=".len() + 64);
+        assert_eq!(sig, sign("key", 1700000000, "{}"));
+        assert_ne!(sig, sign("other", 1700000000, "{}"));
+    }
+}
@@ -0,0 +1,1077 @@
+//! Declarative workflows: multi-step automations defined in YAML or JSON.
+//!
+//! Workflows live in `<workspace>/workflows/*.{yaml,yml,json}`:
+//!
+//! ```yaml
+//! name: morning-digest
+//! trigger:
+//!   cron: "0 8 * * *"
+//!   command: true          # /workflow morning-digest
+//! vars:
+//!   topic: Rust
+//! steps:
+//!   - id: news
+//!     type: tool
+//!     tool: web_search
+//!     args: { query: "{{topic}} news today" }
+//!   - id: summary
+//!     type: agent
+//!     prompt: "Summarize in three bullets:\n{{news}}"
+//!   - type: condition
+//!     if: "{{summary}} not_contains nothing new"
+//!     else: end
+//!   - type: delay
+//!     seconds: 60
+//!   - type: send
+//!     channel: telegram
+//!     to: "123456"
+//!     text: "{{summary}}"
+//! ```
+//!
+//! Each step's output is stored under its `id` and can be referenced by later
+//! steps as `{{id}}` (or `{{id.field}}` when the output is JSON); trigger
+//! input is available as `{{input}}`. A `condition` jumps to `then` when true
+//! (default: the next step) and to `else` otherwise (default: `end`).
+//!
+//! Runs are recorded in `<data dir>/workflows/runs.json` after every step, so
+//! a `delay` survives a restart (the run waits in the history until
+//! [`WorkflowEngine::run_due`] picks it up) and a failed run can be resumed
+//! from the step that failed with the variables it had.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::agent::builder::Agent;
+use crate::service::cron::{CronPayload, CronSchedule, CronService};
+use crate::session::locale::Locale;
+use crate::types::{InboundMessage, OutboundMessage};
+use crate::util::{now_ms, read_json, write_json};
+
+/// Cron payload kind for scheduled workflows; the payload message is the workflow name.
+pub const WORKFLOW_KIND: &str = "workflow";
+
+pub const TRIGGER_MANUAL: &str = "manual";
+pub const TRIGGER_CRON: &str = "cron";
+pub const TRIGGER_WEBHOOK: &str = "webhook";
+pub const TRIGGER_COMMAND: &str = "command";
+pub const TRIGGER_RULE: &str = "rule";
+
+/// Jump target that finishes the run.
+pub const END: &str = "end";
+
+/// Oldest finished runs beyond this count are dropped on save.
+pub const MAX_RUNS: usize = 500;
+
+/// Steps executed per run before it is failed (guards against condition loops).
+const MAX_STEPS_PER_RUN: usize = 200;
+
+/// Step output kept in the history record (variables keep the full text).
+const MAX_RECORDED_OUTPUT: usize = 2000;
+
+/// How a workflow can be started (manual runs are always allowed).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowTrigger {
+    /// Cron expression (5 or 6 fields); registered as a cron job by the gateway.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+    /// Timezone for `cron` (default: `agents.defaults.timezone`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tz: Option<String>,
+    /// Allow `POST /api/v1/workflows/{name}/trigger`.
+    #[serde(default)]
+    pub webhook: bool,
+    /// Bearer token for the webhook trigger; without one the caller must be an admin.
+    #[serde(default, skip_serializing)]
+    pub secret: String,
+    /// Allow `/workflow <name>` from chat.
+    #[serde(default)]
+    pub command: bool,
+}
+
+/// What a step does.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StepAction {
+    /// Ask the agent. Steps of one run share a session unless `session` is set.
+    Agent {
+        prompt: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        session: Option<String>,
+    },
+    /// Call a tool directly; string arguments are templated.
+    Tool {
+        tool: String,
+        #[serde(default)]
+        args: Map<String, Value>,
+    },
+    /// `if` is `<left> <op> <right>` (`==`, `!=`, `>`, `>=`, `<`, `<=`,
+    /// `contains`, `not_contains`) or a single value that must be non-empty
+    /// and not `false`/`0`/`no`.
+    Condition {
+        #[serde(rename = "if")]
+        condition: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        then: Option<String>,
+        #[serde(default, rename = "else", skip_serializing_if = "Option::is_none")]
+        otherwise: Option<String>,
+    },
+    /// Pause the run; it is resumed by the scheduler.
+    Delay { seconds: u64 },
+    /// Send a message through a channel.
+    Send { channel: String, to: String, text: String },
+}
+
+impl StepAction {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Agent { .. } => "agent",
+            Self::Tool { .. } => "tool",
+            Self::Condition { .. } => "condition",
+            Self::Delay { .. } => "delay",
+            Self::Send { .. } => "send",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowStep {
+    /// Variable name for the output (default: `step<N>`).
+    #[serde(default)]
+    pub id: String,
+    #[serde(flatten)]
+    pub action: StepAction,
+    /// Extra attempts before the step (and the run) fails.
+    #[serde(default)]
+    pub retries: u32,
+    /// Record the error and carry on with an empty output.
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Workflow {
+    /// Defaults to the file name without extension.
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub trigger: WorkflowTrigger,
+    /// Initial variables.
+    #[serde(default)]
+    pub vars: Map<String, Value>,
+    pub steps: Vec<WorkflowStep>,
+    /// Template for the run's output (default: the last step's output).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+impl Workflow {
+    /// Parse a definition (`json` selects JSON; otherwise YAML) and validate it.
+    pub fn parse(content: &str, json: bool, default_name: &str) -> anyhow::Result<Self> {
+        let mut workflow: Workflow = if json {
+            serde_json::from_str(content)?
+        } else {
+            serde_yaml::from_str(content)?
+        };
+        if workflow.name.trim().is_empty() {
+            workflow.name = default_name.to_string();
+        }
+        workflow.validate()?;
+        Ok(workflow)
+    }
+
+    fn validate(&mut self) -> anyhow::Result<()> {
+        if self.steps.is_empty() {
+            anyhow::bail!("workflow '{}' has no steps", self.name);
+        }
+        for (i, step) in self.steps.iter_mut().enumerate() {
+            if step.id.trim().is_empty() {
+                step.id = format!("step{}", i + 1);
+            }
+        }
+        let mut seen = std::collections::HashSet::new();
+        for step in &self.steps {
+            if step.id == END || step.id == "input" || !seen.insert(step.id.as_str()) {
+                anyhow::bail!("step id '{}' is reserved or used twice", step.id);
+            }
+        }
+        for step in &self.steps {
+            if let StepAction::Condition { then, otherwise, .. } = &step.action {
+                for target in [then, otherwise].into_iter().flatten() {
+                    if target != END && !seen.contains(target.as_str()) {
+                        anyhow::bail!("step '{}' jumps to unknown step '{}'", step.id, target);
+                    }
+                }
+            }
+        }
+        if let Some(ref expr) = self.trigger.cron {
+            if self.schedule(None).and_then(|s| s.next_run(0)).is_none() {
+                anyhow::bail!("invalid cron expression '{}'", expr);
+            }
+        }
+        Ok(())
+    }
+
+    /// Cron schedule for the `cron` trigger, evaluated in `trigger.tz` or `default_tz`.
+    pub fn schedule(&self, default_tz: Option<&str>) -> Option<CronSchedule> {
+        self.trigger.cron.as_ref().map(|expr| CronSchedule::Cron {
+            expr: expr.clone(),
+            tz: self.trigger.tz.clone().or_else(|| default_tz.map(String::from)),
+        })
+    }
+
+    fn step_index(&self, id: &str) -> Option<usize> {
+        self.steps.iter().position(|s| s.id == id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    /// Waiting for the scheduler to start it.
+    Queued,
+    Running,
+    /// Paused by a `delay` step until `resume_at_ms`.
+    Waiting,
+    Completed,
+    Failed,
+}
+
+impl RunStatus {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "queued" => Some(Self::Queued),
+            "running" => Some(Self::Running),
+            "waiting" => Some(Self::Waiting),
+            "completed" => Some(Self::Completed),
+            "failed" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Completed | Self::Failed)
+    }
+}
+
+/// One executed step in a run's history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepRecord {
+    pub id: String,
+    pub kind: String,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub attempts: u32,
+    pub started_at_ms: u64,
+    pub finished_at_ms: u64,
+}
+
+/// Where to send the output of a run started from chat.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplyTarget {
+    pub channel: String,
+    pub chat_id: String,
+}
+
+/// One execution of a workflow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowRun {
+    pub id: String,
+    pub workflow: String,
+    pub trigger: String,
+    pub status: RunStatus,
+    /// Index of the step to execute next (the failed step for failed runs).
+    pub next_step: usize,
+    pub vars: Map<String, Value>,
+    #[serde(default)]
+    pub steps: Vec<StepRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_at_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<ReplyTarget>,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at_ms: Option<u64>,
+}
+
+/// What steps run against: the agent, the tool registry and the channels.
+#[async_trait]
+pub trait WorkflowHost: Send + Sync {
+    /// Send `prompt` to the agent in `session` and return its reply.
+    async fn prompt(&self, session: &str, prompt: &str) -> anyhow::Result<String>;
+    async fn call_tool(&self, name: &str, args: HashMap<String, Value>) -> anyhow::Result<String>;
+    async fn send(&self, channel: &str, to: &str, text: &str) -> anyhow::Result<()>;
+}
+
+/// [`WorkflowHost`] backed by an embedded [`Agent`], sending channel
+/// messages to `outbound` (the gateway's outbound queue).
+pub struct AgentHost {
+    agent: Agent,
+    outbound: mpsc::Sender<OutboundMessage>,
+}
+
+impl AgentHost {
+    pub fn new(agent: Agent, outbound: mpsc::Sender<OutboundMessage>) -> Self {
+        Self { agent, outbound }
+    }
+}
+
+#[async_trait]
+impl WorkflowHost for AgentHost {
+    async fn prompt(&self, session: &str, prompt: &str) -> anyhow::Result<String> {
+        let msg = InboundMessage::new("workflow", session, session, prompt);
+        Ok(self.agent.send(msg).await?.map(|r| r.content).unwrap_or_default())
+    }
+
+    async fn call_tool(&self, name: &str, args: HashMap<String, Value>) -> anyhow::Result<String> {
+        let result = self.agent.call_tool(name, args).await;
+        if result.starts_with("Error:") || result.starts_with("[TOOL_ERROR]") {
+            anyhow::bail!("{}", result);
+        }
+        Ok(result)
+    }
+
+    async fn send(&self, channel: &str, to: &str, text: &str) -> anyhow::Result<()> {
+        self.outbound
+            .send(OutboundMessage::new(channel, to, text))
+            .await
+            .map_err(|e| anyhow::anyhow!("outbound queue: {e}"))
+    }
+}
+
+/// Look up `name` or `name.path.to.field` in `vars`. String values are
+/// parsed as JSON when a path into them is requested.
+fn lookup(vars: &Map<String, Value>, key: &str) -> Option<Value> {
+    let mut parts = key.split('.');
+    let mut value = vars.get(parts.next()?)?.clone();
+    for part in parts {
+        if let Value::String(s) = &value {
+            value = serde_json::from_str(s).ok()?;
+        }
+        value = match &value {
+            Value::Object(map) => map.get(part)?.clone(),
+            Value::Array(items) => items.get(part.parse::<usize>().ok()?)?.clone(),
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Replace `{{var}}` placeholders; unknown variables become empty.
+pub fn render(template: &str, vars: &Map<String, Value>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let key = rest[start + 2..start + 2 + len].trim();
+        if let Some(value) = lookup(vars, key) {
+            out.push_str(&value_text(&value));
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn render_value(value: &Value, vars: &Map<String, Value>) -> Value {
+    match value {
+        Value::String(s) => Value::String(render(s, vars)),
+        Value::Array(items) => Value::Array(items.iter().map(|v| render_value(v, vars)).collect()),
+        Value::Object(map) => Value::Object(map.iter().map(|(k, v)| (k.clone(), render_value(v, vars))).collect()),
+        other => other.clone(),
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    let s = s.trim();
+    for q in ['"', '\''] {
+        if let Some(inner) = s.strip_prefix(q).and_then(|s| s.strip_suffix(q)) {
+            return inner;
+        }
+    }
+    s
+}
+
+/// Evaluate a condition expression (see [`StepAction::Condition`]).
+/// Operands are split before templating, so variable values may contain operators.
+pub fn evaluate(expr: &str, vars: &Map<String, Value>) -> bool {
+    const OPS: [&str; 8] = [" not_contains ", " contains ", " == ", " != ", " >= ", " <= ", " > ", " < "];
+    let Some((op, at)) = OPS.iter().find_map(|op| expr.find(op).map(|at| (op.trim(), at))) else {
+        let value = render(expr, vars);
+        let value = unquote(&value);
+        return !value.is_empty() && !["false", "0", "no"].contains(&value.to_lowercase().as_str());
+    };
+    let left = render(&expr[..at], vars);
+    let right = render(&expr[at + op.len() + 2..], vars);
+    let (left, right) = (unquote(&left), unquote(&right));
+    let numbers = left.parse::<f64>().ok().zip(right.parse::<f64>().ok());
+    match op {
+        "contains" => left.contains(right),
+        "not_contains" => !left.contains(right),
+        "==" => numbers.map_or(left == right, |(l, r)| l == r),
+        "!=" => numbers.map_or(left != right, |(l, r)| l != r),
+        ">" => numbers.map_or(left > right, |(l, r)| l > r),
+        ">=" => numbers.map_or(left >= right, |(l, r)| l >= r),
+        "<" => numbers.map_or(left < right, |(l, r)| l < r),
+        _ => numbers.map_or(left <= right, |(l, r)| l <= r),
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RunStore {
+    #[serde(default)]
+    runs: Vec<WorkflowRun>,
+}
+
+/// Loads workflow definitions and executes runs, keeping the run history.
+///
+/// The history file is re-read on every call so the HTTP API and the
+/// gateway can share it.
+pub struct WorkflowEngine {
+    dir: PathBuf,
+    runs_path: PathBuf,
+    lock: std::sync::Mutex<()>,
+}
+
+impl WorkflowEngine {
+    pub fn new(dir: PathBuf, runs_path: PathBuf) -> Self {
+        Self {
+            dir,
+            runs_path,
+            lock: std::sync::Mutex::new(()),
+        }
+    }
+
+    /// Definitions in `<workspace>/workflows`, history in `<data dir>/workflows/runs.json`.
+    pub fn for_workspace(workspace: &Path) -> Self {
+        Self::new(
+            workspace.join("workflows"),
+            crate::config::get_data_dir().join("workflows").join("runs.json"),
+        )
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn definition_files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("yaml" | "yml" | "json")))
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort();
+        files
+    }
+
+    fn load_file(path: &Path) -> anyhow::Result<Workflow> {
+        let content = std::fs::read_to_string(path)?;
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("workflow");
+        let json = path.extension().is_some_and(|e| e == "json");
+        Workflow::parse(&content, json, stem)
+    }
+
+    /// All definitions, with the files that failed to load.
+    pub fn load_all(&self) -> (Vec<Workflow>, Vec<(PathBuf, String)>) {
+        let mut workflows = Vec::new();
+        let mut errors = Vec::new();
+        for path in self.definition_files() {
+            match Self::load_file(&path) {
+                Ok(w) => workflows.push(w),
+                Err(e) => errors.push((path, e.to_string())),
+            }
+        }
+        (workflows, errors)
+    }
+
+    pub fn load(&self, name: &str) -> anyhow::Result<Workflow> {
+        for path in self.definition_files() {
+            match Self::load_file(&path) {
+                Ok(w) if w.name == name => return Ok(w),
+                Ok(_) => {}
+                Err(e) => {
+                    if path.file_stem().is_some_and(|s| s == name) {
+                        return Err(e.context(format!("{}", path.display())));
+                    }
+                }
+            }
+        }
+        anyhow::bail!("workflow '{}' not found in {}", name, self.dir.display())
+    }
+
+    fn read_store(&self) -> RunStore {
+        read_json(&self.runs_path, "workflow runs")
+    }
+
+    fn write_store(&self, store: &mut RunStore) {
+        if store.runs.len() > MAX_RUNS {
+            let mut excess = store.runs.len() - MAX_RUNS;
+            store.runs.retain(|r| {
+                if excess > 0 && r.status.is_finished() {
+                    excess -= 1;
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        write_json(&self.runs_path, store, "workflow runs");
+    }
+
+    /// Read-modify-write the history under the engine lock.
+    fn with_store<T>(&self, f: impl FnOnce(&mut RunStore) -> T) -> T {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut store = self.read_store();
+        let result = f(&mut store);
+        self.write_store(&mut store);
+        result
+    }
+
+    fn save_run(&self, run: &WorkflowRun) {
+        self.with_store(|store| match store.runs.iter_mut().find(|r| r.id == run.id) {
+            Some(existing) => *existing = run.clone(),
+            None => store.runs.push(run.clone()),
+        });
+    }
+
+    pub fn get_run(&self, id: &str) -> Option<WorkflowRun> {
+        self.read_store().runs.into_iter().find(|r| r.id == id)
+    }
+
+    /// Runs newest first, optionally for one workflow or status.
+    pub fn runs(&self, workflow: Option<&str>, status: Option<RunStatus>, limit: usize) -> Vec<WorkflowRun> {
+        self.read_store()
+            .runs
+            .into_iter()
+            .rev()
+            .filter(|r| workflow.is_none_or(|w| r.workflow == w))
+            .filter(|r| status.is_none_or(|s| r.status == s))
+            .take(limit)
+            .collect()
+    }
+
+    /// Record a queued run of `name` for [`Self::run_due`] (or [`Self::execute`]).
+    pub fn enqueue(
+        &self,
+        name: &str,
+        trigger: &str,
+        input: Value,
+        reply_to: Option<ReplyTarget>,
+    ) -> anyhow::Result<WorkflowRun> {
+        let workflow = self.load(name)?;
+        let mut vars = workflow.vars.clone();
+        vars.insert("input".to_string(), input);
+        let now = now_ms();
+        let run = WorkflowRun {
+            id: Uuid::new_v4().to_string()[..8].to_string(),
+            workflow: workflow.name,
+            trigger: trigger.to_string(),
+            status: RunStatus::Queued,
+            next_step: 0,
+            vars,
+            steps: Vec::new(),
+            output: None,
+            error: None,
+            resume_at_ms: None,
+            reply_to,
+            created_at_ms: now,
+            updated_at_ms: now,
+            finished_at_ms: None,
+        };
+        self.save_run(&run);
+        info!("Workflow '{}' queued as run {} ({})", run.workflow, run.id, trigger);
+        Ok(run)
+    }
+
+    /// Queue and execute a run now.
+    pub async fn start(
+        &self,
+        host: &dyn WorkflowHost,
+        name: &str,
+        trigger: &str,
+        input: Value,
+    ) -> anyhow::Result<WorkflowRun> {
+        let run = self.enqueue(name, trigger, input, None)?;
+        Ok(self.execute(host, run).await)
+    }
+
+    /// Put a failed or waiting run back in the queue. A failed run restarts
+    /// at the step that failed.
+    pub fn requeue(&self, id: &str) -> Option<WorkflowRun> {
+        self.with_store(|store| {
+            let run = store.runs.iter_mut().find(|r| r.id == id)?;
+            if !matches!(run.status, RunStatus::Failed | RunStatus::Waiting) {
+                return None;
+            }
+            run.status = RunStatus::Queued;
+            run.error = None;
+            run.resume_at_ms = None;
+            run.finished_at_ms = None;
+            run.updated_at_ms = now_ms();
+            Some(run.clone())
+        })
+    }
+
+    /// Requeue and execute a failed or waiting run now.
+    pub async fn resume(&self, host: &dyn WorkflowHost, id: &str) -> anyhow::Result<WorkflowRun> {
+        let run = self
+            .requeue(id)
+            .ok_or_else(|| anyhow::anyhow!("no failed or waiting run with id {}", id))?;
+        Ok(self.execute(host, run).await)
+    }
+
+    /// Mark runs left `running` by a previous process as failed so they can be resumed.
+    pub fn recover_interrupted(&self) -> usize {
+        self.with_store(|store| {
+            let mut count = 0;
+            for run in store.runs.iter_mut().filter(|r| r.status == RunStatus::Running) {
+                run.status = RunStatus::Failed;
+                run.error = Some("interrupted".to_string());
+                run.updated_at_ms = now_ms();
+                count += 1;
+            }
+            count
+        })
+    }
+
+    /// Execute queued runs and waiting runs whose delay has passed.
+    /// Returns the number of runs executed.
+    pub async fn run_due(&self, host: &dyn WorkflowHost) -> usize {
+        let now = now_ms();
+        let due: Vec<WorkflowRun> = self
+            .read_store()
+            .runs
+            .into_iter()
+            .filter(|r| match r.status {
+                RunStatus::Queued => true,
+                RunStatus::Waiting => r.resume_at_ms.is_none_or(|t| t <= now),
+                _ => false,
+            })
+            .collect();
+        let count = due.len();
+        for run in due {
+            self.execute(host, run).await;
+        }
+        count
+    }
+
+    /// Register a cron job for every workflow with a `cron` trigger and
+    /// drop jobs for workflows that no longer have one.
+    pub fn sync_cron(&self, cron: &mut CronService, default_tz: Option<&str>) {
+        let (workflows, _) = self.load_all();
+        let wanted: HashMap<String, CronSchedule> = workflows
+            .iter()
+            .filter_map(|w| w.schedule(default_tz).map(|s| (w.name.clone(), s)))
+            .collect();
+        let mut registered = std::collections::HashSet::new();
+        for job in cron.list_jobs(true).into_iter().filter(|j| j.payload.kind == WORKFLOW_KIND) {
+            let current = match (&job.schedule, wanted.get(&job.payload.message)) {
+                (CronSchedule::Cron { expr, tz }, Some(CronSchedule::Cron { expr: e, tz: t })) => expr == e && tz == t,
+                _ => false,
+            };
+            if current && registered.insert(job.payload.message.clone()) {
+                continue;
+            }
+            cron.remove_job(&job.id);
+        }
+        for (name, schedule) in wanted {
+            if registered.contains(&name) {
+                continue;
+            }
+            let payload = CronPayload {
+                kind: WORKFLOW_KIND.to_string(),
+                message: name.clone(),
+                deliver: false,
+                channel: None,
+                to: None,
+            };
+            cron.add_job_with_payload(&format!("workflow:{}", name), schedule, payload);
+        }
+    }
+
+    /// Drive `run` until it completes, fails or waits on a delay, saving it
+    /// after every step.
+    pub async fn execute(&self, host: &dyn WorkflowHost, mut run: WorkflowRun) -> WorkflowRun {
+        let workflow = match self.load(&run.workflow) {
+            Ok(w) => w,
+            Err(e) => return self.fail(run, e.to_string()),
+        };
+        run.status = RunStatus::Running;
+        run.resume_at_ms = None;
+        run.error = None;
+        self.touch(&mut run);
+
+        let mut executed = 0;
+        while let Some(step) = workflow.steps.get(run.next_step) {
+            executed += 1;
+            if executed > MAX_STEPS_PER_RUN {
+                return self.fail(run, format!("more than {} steps executed", MAX_STEPS_PER_RUN));
+            }
+            let started = now_ms();
+            let record = |ok: bool, output: Option<String>, error: Option<String>, attempts: u32| StepRecord {
+                id: step.id.clone(),
+                kind: step.action.kind().to_string(),
+                ok,
+                output: output.map(|o| crate::util::truncate_string(&o, MAX_RECORDED_OUTPUT, "…")),
+                error,
+                attempts,
+                started_at_ms: started,
+                finished_at_ms: now_ms(),
+            };
+
+            match &step.action {
+                StepAction::Delay { seconds } => {
+                    run.steps.push(record(true, None, None, 1));
+                    run.next_step += 1;
+                    if *seconds > 0 {
+                        run.status = RunStatus::Waiting;
+                        run.resume_at_ms = Some(now_ms() + seconds * 1000);
+                        self.touch(&mut run);
+                        return run;
+                    }
+                }
+                StepAction::Condition { condition, then, otherwise } => {
+                    let result = evaluate(condition, &run.vars);
+                    run.steps.push(record(true, Some(result.to_string()), None, 1));
+                    run.vars.insert(step.id.clone(), Value::Bool(result));
+                    let target = if result { then.as_deref() } else { Some(otherwise.as_deref().unwrap_or(END)) };
+                    run.next_step = match target {
+                        None => run.next_step + 1,
+                        Some(END) => workflow.steps.len(),
+                        Some(id) => workflow.step_index(id).unwrap_or(workflow.steps.len()),
+                    };
+                }
+                action => {
+                    let mut attempts = 0;
+                    let outcome = loop {
+                        attempts += 1;
+                        match self.perform(host, &run, action).await {
+                            Ok(output) => break Ok(output),
+                            Err(e) if attempts > step.retries => break Err(e),
+                            Err(e) => warn!("Workflow {} step {} attempt {} failed: {}", run.id, step.id, attempts, e),
+                        }
+                    };
+                    match outcome {
+                        Ok(output) => {
+                            run.steps.push(record(true, Some(output.clone()), None, attempts));
+                            run.vars.insert(step.id.clone(), Value::String(output));
+                        }
+                        Err(e) if step.continue_on_error => {
+                            run.steps.push(record(false, None, Some(e.to_string()), attempts));
+                            run.vars.insert(step.id.clone(), Value::String(String::new()));
+                        }
+                        Err(e) => {
+                            run.steps.push(record(false, None, Some(e.to_string()), attempts));
+                            return self.fail(run, format!("step '{}': {}", step.id, e));
+                        }
+                    }
+                    run.next_step += 1;
+                }
+            }
+            self.touch(&mut run);
+        }
+
+        let output = match workflow.output {
+            Some(ref template) => render(template, &run.vars),
+            None => workflow
+                .steps
+                .iter()
+                .rev()
+                .find_map(|s| run.vars.get(&s.id).filter(|v| v.is_string()).map(value_text))
+                .unwrap_or_default(),
+        };
+        if let Some(ref target) = run.reply_to {
+            if let Err(e) = host.send(&target.channel, &target.chat_id, &output).await {
+                warn!("Workflow {} reply failed: {}", run.id, e);
+            }
+        }
+        info!("Workflow '{}' run {} completed", run.workflow, run.id);
+        run.output = Some(output);
+        run.status = RunStatus::Completed;
+        run.finished_at_ms = Some(now_ms());
+        self.touch(&mut run);
+        run
+    }
+
+    async fn perform(&self, host: &dyn WorkflowHost, run: &WorkflowRun, action: &StepAction) -> anyhow::Result<String> {
+        let vars = &run.vars;
+        match action {
+            StepAction::Agent { prompt, session } => {
+                let session = match session {
+                    Some(s) => render(s, vars),
+                    None => format!("{}:{}", run.workflow, run.id),
+                };
+                host.prompt(&session, &render(prompt, vars)).await
+            }
+            StepAction::Tool { tool, args } => {
+                let args = args.iter().map(|(k, v)| (k.clone(), render_value(v, vars))).collect();
+                host.call_tool(tool, args).await
+            }
+            StepAction::Send { channel, to, text } => {
+                let text = render(text, vars);
+                host.send(&render(channel, vars), &render(to, vars), &text).await?;
+                Ok(text)
+            }
+            StepAction::Condition { .. } | StepAction::Delay { .. } => Ok(String::new()),
+        }
+    }
+
+    fn touch(&self, run: &mut WorkflowRun) {
+        run.updated_at_ms = now_ms();
+        self.save_run(run);
+    }
+
+    fn fail(&self, mut run: WorkflowRun, error: String) -> WorkflowRun {
+        warn!("Workflow '{}' run {} failed: {}", run.workflow, run.id, error);
+        run.status = RunStatus::Failed;
+        run.error = Some(error);
+        run.finished_at_ms = Some(now_ms());
+        self.touch(&mut run);
+        run
+    }
+}
+
+/// `/workflow` — list chat-triggerable workflows, or queue `/workflow <name> [input]`.
+/// The output is sent back to the chat when the run completes.
+pub fn handle_command(engine: &WorkflowEngine, args: &str, channel: &str, chat_id: &str, locale: Locale) -> String {
+    let (name, input) = match args.trim().split_once(char::is_whitespace) {
+        Some((name, input)) => (name, input.trim()),
+        None => (args.trim(), ""),
+    };
+    if name.is_empty() {
+        let (workflows, _) = engine.load_all();
+        let available: Vec<_> = workflows.iter().filter(|w| w.trigger.command).collect();
+        if available.is_empty() {
+            return locale.pick("実行できるワークフローはありません。", "No workflows available.").to_string();
+        }
+        let mut out = locale.pick("ワークフロー一覧:\n", "Workflows:\n").to_string();
+        for w in available {
+            out.push_str(&format!("・{} {}\n", w.name, w.description));
+        }
+        out.push_str(locale.pick("実行: /workflow <名前> [入力]", "Run: /workflow <name> [input]"));
+        return out;
+    }
+    if !engine.load(name).is_ok_and(|w| w.trigger.command) {
+        return if locale.is_english() {
+            format!("Workflow '{}' not found.", name)
+        } else {
+            format!("ワークフロー「{}」が見つかりません。", name)
+        };
+    }
+    let reply_to = ReplyTarget {
+        channel: channel.to_string(),
+        chat_id: chat_id.to_string(),
+    };
+    match engine.enqueue(name, TRIGGER_COMMAND, Value::String(input.to_string()), Some(reply_to)) {
+        Ok(run) if locale.is_english() => format!("Started workflow '{}' (run {}). I'll send the result when it's done.", name, run.id),
+        Ok(run) => format!("ワークフロー「{}」を開始しました（ID: {}）。完了したら結果をお送りします。", name, run.id),
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct FakeHost {
+        calls: Mutex<Vec<String>>,
+        fail_tool: std::sync::atomic::AtomicBool,
+    }
+
+    #[async_trait]
+    impl WorkflowHost for FakeHost {
+        async fn prompt(&self, session: &str, prompt: &str) -> anyhow::Result<String> {
+            self.calls.lock().unwrap().push(format!("prompt {} {}", session, prompt));
+            Ok(format!("summary of {}", prompt))
+        }
+
+        async fn call_tool(&self, name: &str, args: HashMap<String, Value>) -> anyhow::Result<String> {
+            self.calls.lock().unwrap().push(format!("tool {} {}", name, args["query"]));
+            if self.fail_tool.load(std::sync::atomic::Ordering::SeqCst) {
+                anyhow::bail!("search down");
+            }
+            Ok(r#"{"hits": 3, "top": "rust 2.0"}"#.to_string())
+        }
+
+        async fn send(&self, channel: &str, to: &str, text: &str) -> anyhow::Result<()> {
+            self.calls.lock().unwrap().push(format!("send {}:{} {}", channel, to, text));
+            Ok(())
+        }
+    }
+
+    const DIGEST: &str = r#"
+trigger:
+  cron: "0 8 * * *"
+  command: true
+vars:
+  topic: rust
+steps:
+  - id: news
+    type: tool
+    tool: web_search
+    args: { query: "{{topic}} {{input}}" }
+  - type: condition
+    if: "{{news.hits}} > 0"
+  - id: summary
+    type: agent
+    prompt: "{{news.top}}"
+  - type: delay
+    seconds: 0
+  - type: send
+    channel: telegram
+    to: "42"
+    text: "{{summary}}"
+"#;
+
+    fn engine_with(files: &[(&str, &str)]) -> (tempfile::TempDir, WorkflowEngine) {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("workflows");
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, content) in files {
+            std::fs::write(dir.join(name), content).unwrap();
+        }
+        let engine = WorkflowEngine::new(dir, tmp.path().join("runs.json"));
+        (tmp, engine)
+    }
+
+    #[test]
+    fn test_parse_and_validate() {
+        let w = Workflow::parse(DIGEST, false, "digest").unwrap();
+        assert_eq!(w.name, "digest");
+        assert_eq!(w.steps[1].id, "step2");
+        assert!(matches!(w.steps[0].action, StepAction::Tool { ref tool, .. } if tool == "web_search"));
+
+        let json = r#"{"name": "j", "steps": [{"type": "delay", "seconds": 5}]}"#;
+        assert!(Workflow::parse(json, true, "x").is_ok());
+
+        let bad_jump = "steps:\n  - type: condition\n    if: x\n    then: nowhere\n";
+        assert!(Workflow::parse(bad_jump, false, "b").is_err());
+        let bad_cron = "trigger: { cron: 'not cron' }\nsteps:\n  - type: delay\n    seconds: 1\n";
+        assert!(Workflow::parse(bad_cron, false, "c").is_err());
+    }
+
+    #[test]
+    fn test_render_and_evaluate() {
+        let mut vars = Map::new();
+        vars.insert("name".into(), Value::String("nanobot".into()));
+        vars.insert("data".into(), Value::String(r#"{"items": [{"n": 7}]}"#.into()));
+        assert_eq!(render("hi {{ name }} {{data.items.0.n}} {{missing}}!", &vars), "hi nanobot 7 !");
+
+        assert!(evaluate("{{data.items.0.n}} >= 7", &vars));
+        assert!(evaluate("{{name}} contains bot", &vars));
+        assert!(evaluate("{{name}} != 'other'", &vars));
+        assert!(!evaluate("{{missing}}", &vars));
+        assert!(evaluate("{{name}}", &vars));
+        assert!(evaluate("10 > 9", &vars));
+    }
+
+    #[tokio::test]
+    async fn test_run_passes_variables_between_steps() {
+        let (_tmp, engine) = engine_with(&[("digest.yaml", DIGEST)]);
+        let host = FakeHost::default();
+        let run = engine.start(&host, "digest", TRIGGER_MANUAL, Value::String("today".into())).await.unwrap();
+
+        assert_eq!(run.status, RunStatus::Completed, "{:?}", run.error);
+        assert_eq!(run.output.as_deref(), Some("summary of rust 2.0"));
+        let calls = host.calls.lock().unwrap();
+        assert_eq!(calls[0], "tool web_search \"rust today\"");
+        assert_eq!(calls[1], format!("prompt digest:{} rust 2.0", run.id));
+        assert_eq!(calls[2], "send telegram:42 summary of rust 2.0");
+        assert_eq!(engine.runs(Some("digest"), None, 10).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_delay_waits_and_failed_run_resumes() {
+        let flow = r#"
+steps:
+  - id: first
+    type: tool
+    tool: web_search
+    args: { query: "q" }
+  - type: delay
+    seconds: 3600
+  - type: send
+    channel: line
+    to: u1
+    text: "done {{first.hits}}"
+"#;
+        let (_tmp, engine) = engine_with(&[("flow.yml", flow)]);
+        let host = FakeHost::default();
+
+        host.fail_tool.store(true, std::sync::atomic::Ordering::SeqCst);
+        let run = engine.start(&host, "flow", TRIGGER_MANUAL, Value::Null).await.unwrap();
+        assert_eq!(run.status, RunStatus::Failed);
+        assert_eq!(run.next_step, 0);
+        assert!(run.error.as_deref().unwrap().contains("search down"));
+
+        host.fail_tool.store(false, std::sync::atomic::Ordering::SeqCst);
+        let run = engine.resume(&host, &run.id).await.unwrap();
+        assert_eq!(run.status, RunStatus::Waiting);
+        assert_eq!(run.next_step, 2);
+        // Not due yet
+        assert_eq!(engine.run_due(&host).await, 0);
+
+        let run = engine.resume(&host, &run.id).await.unwrap();
+        assert_eq!(run.status, RunStatus::Completed);
+        assert_eq!(run.output.as_deref(), Some("done 3"));
+        assert_eq!(run.steps.iter().filter(|s| !s.ok).count(), 1);
+    }
+
+    #[test]
+    fn test_command_and_cron_triggers() {
+        let (tmp, engine) = engine_with(&[("digest.yaml", DIGEST)]);
+        let reply = handle_command(&engine, "", "telegram", "42", Locale::Ja);
+        assert!(reply.contains("digest"));
+        let reply = handle_command(&engine, "digest hello world", "telegram", "42", Locale::En);
+        assert!(reply.starts_with("Started workflow 'digest'"));
+        let queued = engine.runs(None, Some(RunStatus::Queued), 10);
+        assert_eq!(queued[0].vars["input"], "hello world");
+        assert_eq!(queued[0].reply_to.as_ref().unwrap().chat_id, "42");
+
+        let mut cron = CronService::new(tmp.path().join("jobs.json"));
+        cron.init();
+        engine.sync_cron(&mut cron, Some("Asia/Tokyo"));
+        engine.sync_cron(&mut cron, Some("Asia/Tokyo"));
+        let jobs = cron.list_jobs(true);
+        assert_eq!(jobs.len(), 1);
+        assert_eq!((jobs[0].payload.kind.as_str(), jobs[0].payload.message.as_str()), (WORKFLOW_KIND, "digest"));
+    }
+}
@@ -96,7 +96,9 @@ async fn main() -> anyhow::Result<()> {
     // ---------------------------------------------------------------------------
     let cfg = config::load_config_from_env();
     let mut app_state = AppState::with_provider(cfg, Box::new(session_store));
-    app_state.db = Some(Arc::new(db));
+    let db: Arc<dyn nanobot_core::db::DbBackend> = Arc::new(db);
+    app_state.db = Some(db.clone());
+    app_state.set_expense_ledger(Arc::new(nanobot_core::expense::DbLedger(db)));
 
     // Load MCP tools from environment
     let mcp_tools = nanobot_core::mcp::client::load_mcp_tools_from_env().await;
@@ -83,8 +83,8 @@ async fn main() -> Result<(), Error> {
     let session_store = DynamoSessionStore::new(dynamo_client.clone(), table_name, tenant_id);
 
     let mut app_state = AppState::with_provider(cfg, Box::new(session_store));
-    app_state.dynamo_client = Some(dynamo_client);
-    app_state.config_table = Some(config_table);
+    app_state.dynamo_client = Some(dynamo_client.clone());
+    app_state.config_table = Some(config_table.clone());
 
     // Turso / libSQL backend (optional, takes priority over DynamoDB when set).
     // Set DATABASE_URL (and optionally DATABASE_TOKEN) to enable.
@@ -102,6 +102,11 @@ async fn main() -> Result<(), Error> {
         }
     }
 
+    match app_state.db.clone() {
+        Some(db) => app_state.set_expense_ledger(Arc::new(nanobot_core::expense::DbLedger(db))),
+        None => app_state.set_expense_ledger(Arc::new(nanobot_core::expense::DynamoLedger::new(dynamo_client, config_table))),
+    }
+
     // Load MCP tools from environment
     let mcp_tools = nanobot_core::mcp::client::load_mcp_tools_from_env().await;
     if !mcp_tools.is_empty() {
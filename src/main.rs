@@ -12,9 +12,14 @@ use crossterm::terminal::{self, ClearType};
 use crossterm::ExecutableCommand;
 use indicatif::{ProgressBar, ProgressStyle};
 
+use nanobot_core::agent::stream::StreamEvent;
 use nanobot_core::bus::MessageBus;
 use nanobot_core::config::{self, Config};
 use nanobot_core::provider;
+use nanobot_core::session::locale::Locale;
+use nanobot_core::termux::TermuxProfile;
+use nanobot_core::types::{ChatMode, ChatOptions, ModelTier};
+use nanobot_core::voice::tts::{Speech, TtsProvider};
 
 #[derive(Parser)]
 #[command(
@@ -23,6 +28,13 @@ use nanobot_core::provider;
     version = nanobot_core::VERSION,
 )]
 struct Cli {
+    /// Profile to use (config, workspace, sessions and auth under
+    /// ~/.nanobot/profiles/<name>; also NANOBOT_PROFILE)
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    /// Ignore a project-local .nanobot/ directory
+    #[arg(long, global = true)]
+    no_project: bool,
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -37,6 +49,18 @@ enum Commands {
         /// Sync with a Web/LINE/Telegram session ID
         #[arg(long)]
         sync: Option<String>,
+        /// Listen continuously and detect speech instead of push-to-talk
+        #[arg(long)]
+        hands_free: bool,
+        /// Phrase that must open each request in hands-free mode (e.g. "ねぇチャット")
+        #[arg(long)]
+        wake_word: Option<String>,
+        /// Keep speaking when the user talks over the reply
+        #[arg(long)]
+        no_barge_in: bool,
+        /// Print replies without reading them aloud
+        #[arg(long)]
+        no_tts: bool,
     },
     /// Chat with AI via chatweb.ai (no config needed)
     Chat {
@@ -48,11 +72,51 @@ enum Commands {
         /// Sync with a Web/LINE/Telegram session ID
         #[arg(long)]
         sync: Option<String>,
+        /// Inference mode: auto, local, cloud, race, explore or consensus
+        #[arg(long)]
+        mode: Option<ChatMode>,
+        /// Pick the model from a tier: economy, normal or powerful
+        #[arg(long)]
+        tier: Option<ModelTier>,
+        /// Maximum cost of one answer in USD
+        #[arg(long)]
+        max_cost: Option<f64>,
+        /// Answer without tools
+        #[arg(long)]
+        no_tools: bool,
+        /// Agent to answer with (as with `/agent <id>`)
+        #[arg(long)]
+        agent: Option<String>,
     },
     /// Link CLI with Web/LINE/Telegram session
     Link {
         /// Web session ID to link with (e.g. api:xxxx-xxxx)
         session_id: Option<String>,
+        /// Show a QR code to scan with your phone instead
+        #[arg(long, conflicts_with = "session_id")]
+        qr: bool,
+        /// API endpoint
+        #[arg(long, default_value = "https://chatweb.ai")]
+        api: String,
+    },
+    /// Sync a local agent session with its linked Web/LINE/Telegram session,
+    /// or settings and sessions between your devices (`sync now`)
+    #[command(args_conflicts_with_subcommands = true)]
+    Sync {
+        #[command(subcommand)]
+        command: Option<SyncCommands>,
+        /// Local session (as used by `chatweb agent`)
+        #[arg(short, long, default_value = "cli:default")]
+        session: String,
+        /// Remote session ID (defaults to the CLI session ID)
+        #[arg(long)]
+        remote: Option<String>,
+        /// API endpoint
+        #[arg(long, default_value = "https://chatweb.ai")]
+        api: String,
+        /// Keep syncing every N seconds
+        #[arg(long)]
+        interval: Option<u64>,
     },
     /// Initialize chatweb configuration and workspace
     Onboard,
@@ -82,6 +146,38 @@ enum Commands {
         /// Require Bearer token authentication
         #[arg(long)]
         auth: bool,
+        /// Also serve the gRPC API on this port (requires --http and the grpc feature)
+        #[arg(long)]
+        grpc_port: Option<u16>,
+        /// Serve HTTPS with a Let's Encrypt certificate for this domain (repeatable;
+        /// requires the acme feature and --http-port 443)
+        #[arg(long)]
+        domain: Vec<String>,
+        /// Contact email for the Let's Encrypt account
+        #[arg(long)]
+        acme_email: Option<String>,
+        /// Expose the HTTP API through a cloudflared (default) or ngrok tunnel
+        /// and register channel webhooks against its URL
+        #[arg(long, num_args = 0..=1, default_missing_value = "cloudflared", value_name = "cloudflared|ngrok")]
+        tunnel: Option<nanobot_core::service::tunnel::TunnelKind>,
+        /// Allow --tunnel without --auth and gateway.apiTokens, publishing the
+        /// whole API to anyone who has the URL
+        #[arg(long, requires = "tunnel")]
+        tunnel_insecure: bool,
+    },
+    /// Serve chatweb's tools to MCP clients (Claude Desktop, IDEs) over stdio
+    McpServe {
+        /// Tool groups to expose: fs, shell, web, cron (shell only when asked for)
+        #[arg(long, value_delimiter = ',', default_value = "fs,web,cron")]
+        tools: Vec<String>,
+        /// Serve Streamable HTTP (/mcp) and SSE (/sse) on this address instead,
+        /// e.g. 127.0.0.1:8765 (requires the http-api feature); other than
+        /// loopback addresses need gateway.apiTokens
+        #[arg(long, value_name = "ADDR")]
+        http: Option<String>,
+        /// Where the gateway delivers reminders made with the cron tool
+        #[arg(long, default_value = "cli:mcp", value_name = "CHANNEL:CHAT_ID")]
+        cron_to: String,
     },
     /// Show chatweb status
     Status,
@@ -90,6 +186,11 @@ enum Commands {
         #[command(subcommand)]
         command: ChannelCommands,
     },
+    /// Check the configuration file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
     /// Run background daemon (device monitoring + heartbeat)
     Daemon {
         /// Heartbeat interval in seconds
@@ -99,11 +200,181 @@ enum Commands {
         #[arg(long, default_value = "https://chatweb.ai")]
         api: String,
     },
+    /// Voice appliance mode (Raspberry Pi): always listening, status hooks,
+    /// offline fallback and remote commands
+    Kiosk {
+        /// API endpoint
+        #[arg(long, default_value = "https://chatweb.ai")]
+        api: String,
+        /// Sync with a Web/LINE/Telegram session ID
+        #[arg(long)]
+        sync: Option<String>,
+    },
+    /// Run the daemon at login (systemd, launchd, Windows Task Scheduler or Termux:Boot)
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommands,
+    },
     /// Manage scheduled tasks
     Cron {
         #[command(subcommand)]
         command: CronCommands,
     },
+    /// Show reminders, follow-ups, digests and cron jobs due next; cancel or move them
+    Upcoming {
+        #[command(subcommand)]
+        command: Option<UpcomingCommands>,
+    },
+    /// Back up or restore the workspace, sessions, cron jobs and config
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommands,
+    },
+    /// Export a session to a portable archive or import one
+    Sessions {
+        #[command(subcommand)]
+        command: SessionCommands,
+    },
+    /// List, run and inspect workflows in <workspace>/workflows
+    Workflow {
+        #[command(subcommand)]
+        command: WorkflowCommands,
+    },
+    /// Enroll household members' voices and manage what is kept about them
+    Speakers {
+        #[command(subcommand)]
+        command: SpeakerCommands,
+    },
+    /// Export routing logs and train or evaluate the learned agent router
+    Routing {
+        #[command(subcommand)]
+        command: RoutingCommands,
+    },
+    /// Consolidate daily memory notes into long-term memory
+    Memory {
+        #[command(subcommand)]
+        command: MemoryCommands,
+    },
+    /// Extract schema-validated JSON from texts, files or URLs (one JSON line per input)
+    Extract {
+        /// JSON Schema file describing the result
+        #[arg(short, long)]
+        schema: std::path::PathBuf,
+        /// Files or http(s) URLs; reads stdin when empty
+        inputs: Vec<String>,
+        /// Extra guidance for the model, e.g. units or date format
+        #[arg(short, long)]
+        instructions: Option<String>,
+        /// Model (default: agents.defaults.model)
+        #[arg(short, long)]
+        model: Option<String>,
+    },
+    /// Revert the last file edits made by the agent's file tools
+    Undo {
+        /// Number of edits to revert
+        #[arg(default_value_t = 1)]
+        count: usize,
+        /// Only edits of this file
+        #[arg(short, long)]
+        file: Option<std::path::PathBuf>,
+        /// List recent edits instead of reverting
+        #[arg(short, long)]
+        list: bool,
+        /// Revert even if the file was changed after the edit
+        #[arg(long)]
+        force: bool,
+    },
+    /// Run the linter and tests (optionally letting the agent fix failures); exits non-zero on failure
+    Verify {
+        /// Project language: rust, python, javascript, typescript, go (default: detected)
+        #[arg(short, long)]
+        language: Option<String>,
+        /// Project directory
+        #[arg(short, long, default_value = ".")]
+        path: std::path::PathBuf,
+        /// Let the agent fix lint/test failures and re-check
+        #[arg(long)]
+        fix: bool,
+        /// Fix rounds with --fix
+        #[arg(long, default_value_t = 3)]
+        max_iterations: u32,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Review a diff or GitHub pull request with the agent and print inline comments
+    Review {
+        /// Git range to review (e.g. main..HEAD); default: staged changes, else unstaged
+        #[arg(long)]
+        diff: Option<String>,
+        /// GitHub pull request URL to review instead of the local repository
+        #[arg(long, conflicts_with = "diff")]
+        pr: Option<String>,
+        /// Post the comments as a review on the pull request (needs GITHUB_TOKEN)
+        #[arg(long, requires = "pr")]
+        post: bool,
+        /// Print the review as JSON instead of markdown
+        #[arg(long)]
+        json: bool,
+        /// Exit with status 1 if any comment is at least this severe (info, warning, error)
+        #[arg(long)]
+        fail_on: Option<String>,
+        /// Install a git pre-push hook that reviews outgoing commits
+        #[arg(long)]
+        install_hook: bool,
+    },
+    /// Propose a conventional-commit message for the staged changes
+    CommitMsg {
+        /// Commit the staged changes with the proposed message
+        #[arg(long)]
+        commit: bool,
+    },
+    /// Generate a grouped changelog from git history
+    Changelog {
+        /// Start of the range (tag or commit); default: the latest tag
+        #[arg(long)]
+        since: Option<String>,
+        /// End of the range
+        #[arg(long, default_value = "HEAD")]
+        until: String,
+        /// Version heading (default: "Unreleased")
+        #[arg(long)]
+        version: Option<String>,
+        /// Print the grouped commits without asking the agent
+        #[arg(long)]
+        raw: bool,
+        /// Prepend the result to this changelog file instead of printing it
+        #[arg(long)]
+        write: Option<std::path::PathBuf>,
+    },
+    /// Import chat history exported from ChatGPT, Claude, or OpenAI-format logs
+    Import {
+        /// Export format: chatgpt, claude, openai
+        source: String,
+        /// Export file (conversations.json, .json or .jsonl)
+        file: std::path::PathBuf,
+        /// Show what would be imported without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Session key prefix (default: import:<source>)
+        #[arg(long)]
+        prefix: Option<String>,
+        /// Only conversations created on or after this date (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<String>,
+        /// Import at most N conversations (newest first)
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Keep system messages
+        #[arg(long)]
+        include_system: bool,
+        /// Don't add conversation summaries to long-term memory
+        #[arg(long)]
+        no_memory: bool,
+        /// Replace sessions that were already imported
+        #[arg(long)]
+        overwrite: bool,
+    },
     /// Earn credits by running local LLM inference for other users
     Earn {
         /// Model to serve (qwen3-0.6b, qwen3-1.7b, qwen3-4b)
@@ -113,13 +384,169 @@ enum Commands {
         #[arg(long, default_value = "https://chatweb.ai")]
         api: String,
     },
+    /// Show the credit balance and recent credit transactions
+    Credits {
+        /// Number of transactions to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+        /// API endpoint
+        #[arg(long, default_value = "https://chatweb.ai")]
+        api: String,
+    },
+    /// Load-test the HTTP chat API and report latency, throughput and errors
+    Bench {
+        /// Server to load (ignored with --mock)
+        #[arg(long, default_value = "http://localhost:3000")]
+        endpoint: String,
+        /// Requests kept in flight
+        #[arg(short, long, default_value_t = 10)]
+        concurrency: usize,
+        /// How long to run (e.g. 30s, 2m)
+        #[arg(short, long, default_value = "30s", value_parser = nanobot_core::bench::parse_duration)]
+        duration: std::time::Duration,
+        /// Message sent with every request
+        #[arg(short, long, default_value = "Hello")]
+        message: String,
+        /// Bearer token for servers that require authentication
+        #[arg(long)]
+        token: Option<String>,
+        /// Start a local server with a mock provider and load that instead
+        #[arg(long)]
+        mock: bool,
+        /// How long the mock provider takes to answer
+        #[arg(long, default_value = "0ms", value_parser = nanobot_core::bench::parse_duration)]
+        mock_latency: std::time::Duration,
+    },
+    /// Show local usage; `--tools` breaks tool calls down by tool
+    Usage {
+        /// Calls, failure rate, latency and downstream token cost per tool
+        #[arg(long)]
+        tools: bool,
+        /// Days to include, today included
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+        /// Only this session (e.g. line:U123)
+        #[arg(long)]
+        session: Option<String>,
+        /// Print JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Generate a new API token for Gateway authentication
     GenToken,
+    /// Manage profiles: separate config, workspace, sessions and auth per assistant
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommands,
+    },
 }
 
 #[derive(Subcommand)]
 enum ChannelCommands {
-    /// Show channel status
+    /// Probe each enabled channel's credentials and webhooks
+    Status {
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Report unknown keys, out-of-range values and missing channel credentials
+    Validate {
+        /// Config file (default: ~/.nanobot/config.json)
+        #[arg(long)]
+        path: Option<std::path::PathBuf>,
+        /// Treat unknown keys as errors
+        #[arg(long)]
+        strict: bool,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// List the environment variables that override config settings
+    EnvVars {
+        /// Also list the NANOBOT__<PATH> name of every setting
+        #[arg(long)]
+        all: bool,
+        /// Print Markdown tables
+        #[arg(long)]
+        markdown: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SessionCommands {
+    /// Write a session's messages and metadata to a JSONL (or .json) archive
+    Export {
+        /// Session key (cli:default, line:U123..., webchat:...)
+        session_id: String,
+        /// Archive file; a .json name writes one JSON document (default: JSONL to stdout)
+        #[arg(short, long)]
+        out: Option<std::path::PathBuf>,
+        /// Include the workspace's long-term memory
+        #[arg(long)]
+        memory: bool,
+        /// Session store to read: file or dynamodb
+        #[arg(long, default_value = "file")]
+        store: String,
+    },
+    /// Recreate a session from an archive
+    Import {
+        /// Archive written by `sessions export`
+        file: std::path::PathBuf,
+        /// Store under this key instead of the archived one
+        #[arg(long = "as")]
+        key: Option<String>,
+        /// Replace an existing session with the same key
+        #[arg(long)]
+        overwrite: bool,
+        /// Don't merge archived memory into the workspace's long-term memory
+        #[arg(long)]
+        no_memory: bool,
+        /// Session store to write: file or dynamodb
+        #[arg(long, default_value = "file")]
+        store: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileCommands {
+    /// List profiles, marking the active one
+    List,
+    /// Create a profile with its own config and workspace
+    Create {
+        /// Profile name (letters, digits, '-' and '_')
+        name: String,
+        /// Start from a copy of this profile's config
+        #[arg(long)]
+        from: Option<String>,
+    },
+    /// Use a profile by default (`default` goes back to ~/.nanobot)
+    Switch {
+        /// Profile name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ServiceCommands {
+    /// Register the daemon and start it now
+    Install {
+        /// Heartbeat interval in seconds
+        #[arg(long, default_value_t = 60)]
+        interval: u64,
+        /// API endpoint
+        #[arg(long, default_value = "https://chatweb.ai")]
+        api: String,
+        /// Run `chatweb kiosk` instead of the plain daemon
+        #[arg(long)]
+        kiosk: bool,
+    },
+    /// Stop the daemon and remove it
+    Uninstall,
+    /// Show whether the daemon is running
     Status,
 }
 
@@ -153,6 +580,260 @@ enum CronCommands {
     },
 }
 
+#[derive(Subcommand)]
+enum UpcomingCommands {
+    /// Cancel a scheduled item
+    Cancel {
+        /// Item ID
+        id: String,
+    },
+    /// Move a scheduled item: 30m, 18:30, "2026-03-14 09:00" or a cron expression
+    Move {
+        /// Item ID
+        id: String,
+        /// New time
+        #[arg(required = true)]
+        when: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SyncCommands {
+    /// Sync settings, named sessions and pins with sync.target (end-to-end
+    /// encrypted with a passphrase; NANOBOT_SYNC_PASSPHRASE or prompted)
+    Now {
+        /// API endpoint (for sync.target "chatweb")
+        #[arg(long, default_value = "https://chatweb.ai")]
+        api: String,
+    },
+    /// Show the last sync, unsynced local changes and conflicts
+    Status,
+}
+
+#[derive(Subcommand)]
+enum BackupCommands {
+    /// Create a backup archive
+    Create {
+        /// Directory, archive path, or s3://bucket/prefix (default: ~/.nanobot/backups)
+        #[arg(long)]
+        to: Option<String>,
+        /// Keep API keys and tokens in the archived config.json
+        #[arg(long)]
+        include_secrets: bool,
+    },
+    /// Verify and restore a backup archive
+    Restore {
+        /// Archive path or s3://bucket/key
+        from: String,
+        /// Only check the archive's integrity
+        #[arg(long)]
+        verify_only: bool,
+    },
+    /// Back up automatically while the gateway is running
+    Schedule {
+        /// Cron expression (default: daily at 03:00)
+        #[arg(short, long, default_value = "0 3 * * *")]
+        cron: String,
+        /// Directory or s3://bucket/prefix (default: ~/.nanobot/backups)
+        #[arg(long)]
+        to: Option<String>,
+        /// Local archives to keep (0 = all)
+        #[arg(long, default_value_t = 7)]
+        keep: usize,
+        /// Keep API keys and tokens in the archived config.json
+        #[arg(long)]
+        include_secrets: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorkflowCommands {
+    /// List workflow definitions
+    List,
+    /// Queue a run for the gateway (or run it here with --local)
+    Run {
+        /// Workflow name
+        name: String,
+        /// Input passed to the workflow as {{input}}
+        #[arg(short, long)]
+        input: Option<String>,
+        /// Execute in this process; channel sends are printed instead of delivered
+        #[arg(long)]
+        local: bool,
+    },
+    /// Show execution history
+    Runs {
+        /// Only runs of this workflow
+        #[arg(short, long)]
+        name: Option<String>,
+        /// Only runs with this status (queued, running, waiting, completed, failed)
+        #[arg(short, long)]
+        status: Option<String>,
+        /// Number of runs to show
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Resume a failed run from the step that failed
+    Resume {
+        /// Run ID
+        run_id: String,
+        /// Execute in this process instead of queueing for the gateway
+        #[arg(long)]
+        local: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum RoutingCommands {
+    /// Write routing log entries from DynamoDB as an anonymized JSONL dataset
+    Export {
+        /// Output file
+        #[arg(short, long, default_value = "routing.jsonl")]
+        out: std::path::PathBuf,
+        /// Days of logs to export, counting back from today
+        #[arg(long, default_value_t = 30)]
+        days: u32,
+        /// DynamoDB table (default: $DYNAMODB_CONFIG_TABLE)
+        #[arg(long)]
+        table: Option<String>,
+        /// Write the entries as logged, session hashes included
+        #[arg(long)]
+        raw: bool,
+        #[command(flatten)]
+        anonymize: AnonymizeArgs,
+    },
+    /// Anonymize raw entries written by `routing export --raw`
+    Anonymize {
+        /// Raw JSONL
+        input: std::path::PathBuf,
+        /// Output file
+        #[arg(short, long, default_value = "routing.jsonl")]
+        out: std::path::PathBuf,
+        #[command(flatten)]
+        anonymize: AnonymizeArgs,
+    },
+    /// Delete routing log entries older than agents.routing.logRetentionDays
+    Purge {
+        /// DynamoDB table (default: $DYNAMODB_CONFIG_TABLE)
+        #[arg(long)]
+        table: Option<String>,
+        /// Days before the retention window to check
+        #[arg(long, default_value_t = 30)]
+        lookback: u32,
+    },
+    /// Train a router on exported logs
+    Train {
+        /// Exported JSONL
+        input: std::path::PathBuf,
+        /// Model file (default: ~/.nanobot/routing/model.json)
+        #[arg(short, long)]
+        out: Option<std::path::PathBuf>,
+        /// Passes over the training set
+        #[arg(long, default_value_t = 20)]
+        epochs: usize,
+        /// Share of examples held back to report agreement (0-0.5)
+        #[arg(long, default_value_t = 0.2)]
+        holdout: f64,
+    },
+    /// Compare a trained router's picks with the logged ones
+    Eval {
+        /// Exported JSONL
+        input: std::path::PathBuf,
+        /// Model file (default: ~/.nanobot/routing/model.json)
+        #[arg(short, long)]
+        model: Option<std::path::PathBuf>,
+    },
+}
+
+/// Privacy settings of an anonymized routing dataset.
+#[derive(clap::Args)]
+struct AnonymizeArgs {
+    /// Sessions every released group and feature must cover
+    #[arg(long, default_value_t = 5)]
+    k: usize,
+    /// Requests counted per session
+    #[arg(long, default_value_t = 20)]
+    max_per_session: usize,
+    /// Privacy budget of the aggregate counts
+    #[arg(long, default_value_t = 1.0)]
+    epsilon: f64,
+    /// Also write noisy request counts per week, channel, language and agent
+    #[arg(long)]
+    aggregates: Option<std::path::PathBuf>,
+}
+
+impl AnonymizeArgs {
+    fn options(&self) -> nanobot_core::service::routing_dataset::AnonymizeOptions {
+        nanobot_core::service::routing_dataset::AnonymizeOptions {
+            k: self.k,
+            max_per_session: self.max_per_session,
+            epsilon: if self.aggregates.is_some() { self.epsilon } else { 0.0 },
+            seed: None,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum MemoryCommands {
+    /// Merge recent daily notes into MEMORY.md now (as agents.memory.schedule does)
+    Consolidate {
+        /// Model (default: agents.memory.model); `local-…` runs on the local model
+        #[arg(short, long)]
+        model: Option<String>,
+        /// Days of daily notes to read (default: agents.memory.days)
+        #[arg(short, long)]
+        days: Option<u32>,
+        /// Print the result without writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SpeakerCommands {
+    /// List enrolled speakers
+    List,
+    /// Record a few sentences from the microphone as a speaker's voiceprint
+    Enroll {
+        /// Name the assistant will call them by
+        name: String,
+        /// Utterances to record
+        #[arg(short, long, default_value_t = 3)]
+        utterances: u32,
+    },
+    /// Rename a speaker
+    Rename {
+        /// Speaker name or ID
+        speaker: String,
+        /// New name
+        name: String,
+    },
+    /// Add a note (e.g. a preference) the assistant keeps for a speaker
+    Remember {
+        /// Speaker name or ID
+        speaker: String,
+        /// Note text
+        note: String,
+    },
+    /// Show a speaker's notes
+    Notes {
+        /// Speaker name or ID
+        speaker: String,
+        /// Delete the notes, keeping the voiceprint
+        #[arg(long)]
+        clear: bool,
+    },
+    /// Delete a speaker's voiceprint and notes
+    Forget {
+        /// Speaker name or ID
+        #[arg(required_unless_present = "all")]
+        speaker: Option<String>,
+        /// Delete every enrolled speaker
+        #[arg(long)]
+        all: bool,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Default to warn level for cleaner CLI output, unless RUST_LOG is set
@@ -165,27 +846,93 @@ async fn main() -> Result<()> {
             .add_directive("nanobot_core::agent=info".parse().unwrap())
     };
 
-    tracing_subscriber::fmt()
-        .with_env_filter(default_filter)
-        .init();
-
     let cli = Cli::parse();
+    let logs = tracing_subscriber::fmt().with_env_filter(default_filter);
+    // MCP over stdio owns stdout
+    if matches!(cli.command, Some(Commands::McpServe { http: None, .. })) {
+        logs.with_writer(std::io::stderr).init();
+    } else {
+        logs.init();
+    }
+    config::profile::select(cli.profile.clone())?;
+    if cli.no_project {
+        config::project::disable();
+    } else if let Some(dir) = config::project::detect() {
+        tracing::info!("Using project workspace {}", dir.display());
+    }
 
     match cli.command {
         None => {
             // Default to Voice mode when no subcommand specified
-            cmd_voice("https://chatweb.ai/api/v1/chat".to_string(), None).await?
+            let config = config::load_config(None);
+            let voice = config.voice;
+            let api = "https://chatweb.ai/api/v1/chat".to_string();
+            if let Some(termux) = TermuxProfile::detect(&config.termux) {
+                cmd_voice_termux(api, None, termux).await?
+            } else if voice.hands_free {
+                cmd_voice_hands_free(api, None, voice, None).await?
+            } else {
+                cmd_voice(api, None, voice.tts).await?
+            }
+        }
+        Some(Commands::Voice {
+            api,
+            sync,
+            hands_free,
+            wake_word,
+            no_barge_in,
+            no_tts,
+        }) => {
+            let config = config::load_config(None);
+            let mut voice = config.voice;
+            voice.hands_free |= hands_free || wake_word.is_some();
+            if wake_word.is_some() {
+                voice.wake_word = wake_word;
+            }
+            if no_barge_in {
+                voice.barge_in = false;
+            }
+            if no_tts {
+                voice.tts = false;
+            }
+            if let Some(termux) = TermuxProfile::detect(&config.termux) {
+                cmd_voice_termux(api, sync, termux).await?
+            } else if voice.hands_free {
+                cmd_voice_hands_free(api, sync, voice, None).await?
+            } else {
+                cmd_voice(api, sync, voice.tts).await?
+            }
+        }
+        Some(Commands::Chat { message, api, sync, mode, tier, max_cost, no_tools, agent }) => {
+            let options = ChatOptions { tier, max_cost_usd: max_cost, tools: no_tools.then_some(false), agent };
+            options.validate().map_err(|e| anyhow::anyhow!(e))?;
+            cmd_chat(message, api, sync, mode, options).await?
         }
-        Some(Commands::Voice { api, sync }) => cmd_voice(api, sync).await?,
-        Some(Commands::Chat { message, api, sync }) => cmd_chat(message, api, sync).await?,
-        Some(Commands::Link { session_id }) => cmd_link(session_id).await?,
+        Some(Commands::Link { qr: true, api, .. }) => cmd_link_qr(api).await?,
+        Some(Commands::Link { session_id, api, .. }) => cmd_link(session_id, api).await?,
+        Some(Commands::Sync { command: Some(SyncCommands::Now { api }), .. }) => cmd_sync_now(api).await?,
+        Some(Commands::Sync { command: Some(SyncCommands::Status), .. }) => cmd_sync_status()?,
+        Some(Commands::Sync { session, remote, api, interval, .. }) => cmd_sync(session, remote, api, interval).await?,
         Some(Commands::Onboard) => cmd_onboard()?,
         Some(Commands::Agent { message, session }) => cmd_agent(message, session).await?,
-        Some(Commands::Gateway { port, verbose, http, http_port, auth }) => cmd_gateway(port, verbose, http, http_port, auth).await?,
+        Some(Commands::Gateway { port, verbose, http, http_port, auth, grpc_port, domain, acme_email, tunnel, tunnel_insecure }) => {
+            cmd_gateway(port, verbose, http, http_port, auth, grpc_port, domain, acme_email, tunnel, tunnel_insecure).await?
+        }
         Some(Commands::Daemon { interval, api }) => cmd_daemon(interval, api).await?,
+        Some(Commands::Kiosk { api, sync }) => cmd_kiosk(api, sync).await?,
+        Some(Commands::McpServe { tools, http, cron_to }) => cmd_mcp_serve(tools, http, cron_to).await?,
         Some(Commands::Status) => cmd_status()?,
+        Some(Commands::Service { command }) => match command {
+            ServiceCommands::Install { interval, api, kiosk } => cmd_service_install(interval, api, kiosk)?,
+            ServiceCommands::Uninstall => cmd_service_uninstall()?,
+            ServiceCommands::Status => cmd_service_status()?,
+        },
         Some(Commands::Channels { command }) => match command {
-            ChannelCommands::Status => cmd_channels_status()?,
+            ChannelCommands::Status { json } => cmd_channels_status(json).await?,
+        },
+        Some(Commands::Config { command }) => match command {
+            ConfigCommands::Validate { path, strict, json } => cmd_config_validate(path, strict, json)?,
+            ConfigCommands::EnvVars { all, markdown } => print!("{}", config::env::render(all, markdown)),
         },
         Some(Commands::Cron { command }) => match command {
             CronCommands::List { all } => cmd_cron_list(all)?,
@@ -197,8 +944,107 @@ async fn main() -> Result<()> {
             } => cmd_cron_add(name, message, every, cron)?,
             CronCommands::Remove { job_id } => cmd_cron_remove(job_id)?,
         },
+        Some(Commands::Upcoming { command }) => match command {
+            None => cmd_upcoming_list()?,
+            Some(UpcomingCommands::Cancel { id }) => cmd_upcoming_cancel(id)?,
+            Some(UpcomingCommands::Move { id, when }) => cmd_upcoming_move(id, when.join(" "))?,
+        },
+        Some(Commands::Sessions { command }) => match command {
+            SessionCommands::Export { session_id, out, memory, store } => cmd_sessions_export(session_id, out, memory, store).await?,
+            SessionCommands::Import { file, key, overwrite, no_memory, store } => {
+                cmd_sessions_import(file, key, overwrite, !no_memory, store).await?
+            }
+        },
+        Some(Commands::Backup { command }) => match command {
+            BackupCommands::Create { to, include_secrets } => cmd_backup_create(to, include_secrets).await?,
+            BackupCommands::Restore { from, verify_only } => cmd_backup_restore(from, verify_only).await?,
+            BackupCommands::Schedule {
+                cron,
+                to,
+                keep,
+                include_secrets,
+            } => cmd_backup_schedule(cron, to, keep, include_secrets)?,
+        },
+        Some(Commands::Workflow { command }) => match command {
+            WorkflowCommands::List => cmd_workflow_list()?,
+            WorkflowCommands::Run { name, input, local } => cmd_workflow_run(name, input, local).await?,
+            WorkflowCommands::Runs { name, status, limit } => cmd_workflow_runs(name, status, limit)?,
+            WorkflowCommands::Resume { run_id, local } => cmd_workflow_resume(run_id, local).await?,
+        },
+        Some(Commands::Speakers { command }) => match command {
+            SpeakerCommands::List => cmd_speakers_list(),
+            SpeakerCommands::Enroll { name, utterances } => cmd_speakers_enroll(name, utterances)?,
+            SpeakerCommands::Rename { speaker, name } => cmd_speakers_rename(speaker, name),
+            SpeakerCommands::Remember { speaker, note } => cmd_speakers_remember(speaker, note),
+            SpeakerCommands::Notes { speaker, clear } => cmd_speakers_notes(speaker, clear),
+            SpeakerCommands::Forget { speaker, all } => cmd_speakers_forget(speaker, all),
+        },
+        Some(Commands::Routing { command }) => match command {
+            RoutingCommands::Export { out, days, table, raw, anonymize } => {
+                cmd_routing_export(out, days, table, raw, anonymize).await?
+            }
+            RoutingCommands::Anonymize { input, out, anonymize } => cmd_routing_anonymize(input, out, anonymize)?,
+            RoutingCommands::Purge { table, lookback } => cmd_routing_purge(table, lookback).await?,
+            RoutingCommands::Train { input, out, epochs, holdout } => cmd_routing_train(input, out, epochs, holdout)?,
+            RoutingCommands::Eval { input, model } => cmd_routing_eval(input, model)?,
+        },
+        Some(Commands::Memory { command }) => match command {
+            MemoryCommands::Consolidate { model, days, dry_run } => cmd_memory_consolidate(model, days, dry_run).await?,
+        },
+        Some(Commands::Extract { schema, inputs, instructions, model }) => cmd_extract(schema, inputs, instructions, model).await?,
+        Some(Commands::Undo { count, file, list, force }) => cmd_undo(count, file, list, force)?,
+        Some(Commands::Review {
+            diff,
+            pr,
+            post,
+            json,
+            fail_on,
+            install_hook,
+        }) => {
+            if install_hook {
+                cmd_review_install_hook()?
+            } else {
+                cmd_review(diff, pr, post, json, fail_on).await?
+            }
+        }
+        Some(Commands::CommitMsg { commit }) => cmd_commit_msg(commit).await?,
+        Some(Commands::Changelog {
+            since,
+            until,
+            version,
+            raw,
+            write,
+        }) => cmd_changelog(since, until, version, raw, write).await?,
+        Some(Commands::Verify {
+            language,
+            path,
+            fix,
+            max_iterations,
+            json,
+        }) => cmd_verify(language, path, fix, max_iterations, json).await?,
+        Some(Commands::Import {
+            source,
+            file,
+            dry_run,
+            prefix,
+            since,
+            limit,
+            include_system,
+            no_memory,
+            overwrite,
+        }) => cmd_import(source, file, dry_run, prefix, since, limit, include_system, !no_memory, overwrite)?,
         Some(Commands::Earn { model, api }) => cmd_earn(model, api).await?,
+        Some(Commands::Credits { limit, api }) => cmd_credits(limit, api).await?,
+        Some(Commands::Bench { endpoint, concurrency, duration, message, token, mock, mock_latency }) => {
+            cmd_bench(endpoint, concurrency, duration, message, token, mock.then_some(mock_latency)).await?
+        }
+        Some(Commands::Usage { tools, days, session, json }) => cmd_usage(tools, days, session, json)?,
         Some(Commands::GenToken) => cmd_gen_token(),
+        Some(Commands::Profile { command }) => match command {
+            ProfileCommands::List => cmd_profile_list(),
+            ProfileCommands::Create { name, from } => cmd_profile_create(name, from)?,
+            ProfileCommands::Switch { name } => cmd_profile_switch(name)?,
+        },
     }
 
     Ok(())
@@ -221,7 +1067,7 @@ fn get_cli_session_id() -> Result<String> {
 }
 
 /// Display welcome banner in Claude Code style
-fn show_welcome_banner(session_id: &str, synced: bool, authenticated: bool) {
+fn show_welcome_banner(session_id: &str, synced: bool, authenticated: bool, locale: Locale) {
     // Get current directory
     let current_dir = std::env::current_dir()
         .ok()
@@ -250,11 +1096,12 @@ fn show_welcome_banner(session_id: &str, synced: bool, authenticated: bool) {
     println!();
 
     // Commands - Mobile-friendly
-    println!("\x1b[2m  📱 スマホ向けコマンド:\x1b[0m");
-    println!("\x1b[2m    ? or /q   クイックメニュー\x1b[0m");
-    println!("\x1b[2m    /h        ヘルプ\x1b[0m");
-    println!("\x1b[2m    /m        よく使うフレーズ\x1b[0m");
-    println!("\x1b[2m    1-5       数字でクイックアクション\x1b[0m");
+    println!("\x1b[2m  📱 {}:\x1b[0m", locale.pick("スマホ向けコマンド", "Quick commands"));
+    println!("\x1b[2m    ? or /q   {}\x1b[0m", locale.pick("クイックメニュー", "Quick menu"));
+    println!("\x1b[2m    /h        {}\x1b[0m", locale.pick("ヘルプ", "Help"));
+    println!("\x1b[2m    /m        {}\x1b[0m", locale.pick("よく使うフレーズ", "Frequent phrases"));
+    println!("\x1b[2m    /lang     {}\x1b[0m", locale.pick("言語切替 (ja/en)", "Switch language (ja/en)"));
+    println!("\x1b[2m    1-5       {}\x1b[0m", locale.pick("数字でクイックアクション", "Quick actions by number"));
 }
 
 
@@ -451,36 +1298,50 @@ fn show_konami_animation(credits_granted: i64, credits_remaining: i64) {
 }
 
 /// Show quick action menu (mobile-friendly)
-fn show_quick_menu() {
+fn show_quick_menu(locale: Locale) {
     println!();
-    println!("\x1b[1;36m📱 クイックメニュー\x1b[0m");
+    println!("\x1b[1;36m📱 {}\x1b[0m", locale.pick("クイックメニュー", "Quick menu"));
     println!("\x1b[2m─────────────────────────\x1b[0m");
-    println!("\x1b[1;32m1\x1b[0m. 📊 ステータス確認");
-    println!("\x1b[1;32m2\x1b[0m. 🎮 コナミコード実行");
-    println!("\x1b[1;32m3\x1b[0m. 🔗 セッション連携");
-    println!("\x1b[1;32m4\x1b[0m. 💬 よく使うフレーズ");
-    println!("\x1b[1;32m5\x1b[0m. 📋 ヘルプ表示");
+    println!("\x1b[1;32m1\x1b[0m. 📊 {}", locale.pick("ステータス確認", "Status"));
+    println!("\x1b[1;32m2\x1b[0m. 🎮 {}", locale.pick("コナミコード実行", "Konami code"));
+    println!("\x1b[1;32m3\x1b[0m. 🔗 {}", locale.pick("セッション連携", "Link session"));
+    println!("\x1b[1;32m4\x1b[0m. 💬 {}", locale.pick("よく使うフレーズ", "Frequent phrases"));
+    println!("\x1b[1;32m5\x1b[0m. 📋 {}", locale.pick("ヘルプ表示", "Help"));
     println!("\x1b[2m─────────────────────────\x1b[0m");
-    println!("\x1b[2m数字を入力して選択\x1b[0m");
+    println!("\x1b[2m{}\x1b[0m", locale.pick("数字を入力して選択", "Type a number to choose"));
+    println!();
+}
+
+/// Show session status (local view)
+fn show_status(session_id: &str, synced: bool, authenticated: bool, locale: Locale) {
+    println!();
+    println!("\x1b[1;36m📊 {}\x1b[0m", locale.pick("ステータス", "Status"));
+    println!("\x1b[2m  Session: {}\x1b[0m", session_id);
+    println!("\x1b[2m  {}: {}\x1b[0m", locale.pick("言語", "Language"), locale);
+    if synced {
+        println!("\x1b[32m  ✓ Synced\x1b[0m");
+    }
+    if authenticated {
+        println!("\x1b[32m  ✓ Authenticated\x1b[0m");
+    }
     println!();
 }
 
-/// Show mobile-friendly help
-fn show_mobile_help() {
+/// Show mobile-friendly help (commands from the shared registry)
+fn show_mobile_help(locale: Locale) {
     println!();
-    println!("\x1b[1;36m📱 スマホ向けコマンド\x1b[0m");
+    println!("\x1b[1;36m📱 {}\x1b[0m", locale.pick("コマンド一覧", "Commands"));
     println!("\x1b[2m─────────────────────────\x1b[0m");
-    println!("\x1b[1;33m/q\x1b[0m または \x1b[1;33m?\x1b[0m     クイックメニュー");
-    println!("\x1b[1;33m/s\x1b[0m            ステータス表示");
-    println!("\x1b[1;33m/h\x1b[0m            このヘルプ");
-    println!("\x1b[1;33m/c\x1b[0m            画面クリア");
-    println!("\x1b[1;33m/.\x1b[0m            前回メッセージ再送");
-    println!("\x1b[1;33m/m\x1b[0m            よく使うフレーズ");
-    println!("\x1b[1;33m/omikuji\x1b[0m      毎日おみくじ（運試し）");
+    for spec in nanobot_core::command::available_in("cli") {
+        println!("\x1b[1;33m{:<14}\x1b[0m {}", spec.usage, locale.pick(spec.help, spec.help_en));
+    }
     println!("\x1b[2m─────────────────────────\x1b[0m");
-    println!("\x1b[2m数字だけ: 1-5 でクイックアクション\x1b[0m");
+    println!("\x1b[2m{}\x1b[0m", locale.pick(
+        "? でクイックメニュー / 数字だけ: 1-5 でクイックアクション",
+        "? for the quick menu / digits 1-5 for quick actions",
+    ));
     println!();
-    println!("\x1b[2;90m💡 ヒント: 絵文字で気持ちを伝えると...\x1b[0m");
+    println!("\x1b[2;90m💡 {}\x1b[0m", locale.pick("ヒント: 絵文字で気持ちを伝えると...", "Tip: try telling me how you feel with emoji..."));
     println!();
 }
 
@@ -512,7 +1373,12 @@ fn get_phrase(num: &str) -> Option<String> {
 
 /// Voice-first interactive mode with animated character.
 /// Space key for push-to-talk, switch to chat mode with /chat command.
-async fn cmd_voice(api_url: String, sync: Option<String>) -> Result<()> {
+/// Speech is recorded while space is held and transcribed by the
+/// `voice.sttBackend` backend; without one the user types instead.
+async fn cmd_voice(api_url: String, sync: Option<String>, speak: bool) -> Result<()> {
+    use nanobot_core::voice::stt::{self, ChatwebStt};
+    use nanobot_core::voice::tts::{self, ChatwebTts};
+
     let session_id = if let Some(ref sid) = sync {
         sid.clone()
     } else {
@@ -521,7 +1387,15 @@ async fn cmd_voice(api_url: String, sync: Option<String>) -> Result<()> {
 
     let auth_token = load_auth_token();
     let stream_url = api_url.replace("/api/v1/chat", "/api/v1/chat/stream");
-
+    let base_url = api_url.trim_end_matches("/api/v1/chat").to_string();
+    let config = config::load_config(None);
+    let stt = stt::from_config(&config, auth_token.as_deref().map(|token| ChatwebStt::new(&base_url, token)));
+    let tts = if speak {
+        tts::from_config(&config, auth_token.as_deref().map(|token| ChatwebTts::new(&base_url, token, &session_id)))
+    } else {
+        None
+    };
+
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(90))
         .build()
@@ -532,14 +1406,25 @@ async fn cmd_voice(api_url: String, sync: Option<String>) -> Result<()> {
     let mut stdout = std::io::stdout();
     stdout.execute(terminal::Clear(ClearType::All))?;
 
+    // Holding space needs key release events; terminals without the
+    // keyboard enhancement protocol tap space to start and stop instead.
+    let hold_to_talk = terminal::supports_keyboard_enhancement().unwrap_or(false);
+    report_key_releases(hold_to_talk, true)?;
+
     // Show initial voice UI
     show_voice_ui(VoiceState::Idle, &session_id, sync.is_some(), auth_token.is_some())?;
 
     let mut mode = InteractionMode::Voice;
     let mut input_buffer = String::new();
     let mut listening_start = None;
+    let mut recording: Option<Recording> = None;
+    let mut playback: Option<Playback> = None;
 
     loop {
+        if playback.as_mut().is_some_and(|p| p.finished()) {
+            playback = None;
+        }
+
         // Poll for keyboard events
         if event::poll(std::time::Duration::from_millis(100))? {
             if let Event::Key(key) = event::read()? {
@@ -551,45 +1436,88 @@ async fn cmd_voice(api_url: String, sync: Option<String>) -> Result<()> {
                 match mode {
                     InteractionMode::Voice => {
                         match key.code {
-                            KeyCode::Char(' ') if key.kind == KeyEventKind::Press => {
-                                // Start listening
+                            KeyCode::Char(' ') if key.kind == KeyEventKind::Press && listening_start.is_none() => {
+                                // Start listening; talking over the previous reply stops it
+                                playback = None;
                                 listening_start = Some(std::time::Instant::now());
+                                if stt.is_some() {
+                                    recording = Recording::start().map_err(|e| tracing::warn!("Push-to-talk recording failed: {}", e)).ok();
+                                }
                                 show_voice_ui(VoiceState::Listening, &session_id, sync.is_some(), auth_token.is_some())?;
                             }
-                            KeyCode::Char(' ') if key.kind == KeyEventKind::Release => {
+                            KeyCode::Char(' ')
+                                if key.kind == KeyEventKind::Release || (!hold_to_talk && key.kind == KeyEventKind::Press) =>
+                            {
                                 // Stop listening and process
-                                if let Some(start) = listening_start {
+                                if let Some(start) = listening_start.take() {
                                     let duration = start.elapsed();
                                     show_voice_ui(VoiceState::Processing, &session_id, sync.is_some(), auth_token.is_some())?;
 
-                                    // For now, prompt user to type (real STT would go here)
+                                    let heard = match (&stt, recording.take().map(Recording::stop)) {
+                                        // Whisper makes words up from a stray tap's silence.
+                                        (Some(_), Some(samples)) if samples.len() < (CAPTURE_RATE / 4) as usize => Some(Ok(String::new())),
+                                        (Some(stt), Some(samples)) => Some(stt.transcribe(&samples, CAPTURE_RATE).await),
+                                        _ => None,
+                                    };
+
+                                    report_key_releases(hold_to_talk, false)?;
                                     terminal::disable_raw_mode()?;
                                     stdout.execute(terminal::Clear(ClearType::All))?;
                                     println!("\x1b[2m🎤 リスニング時間: {:.1}秒\x1b[0m", duration.as_secs_f32());
-                                    println!("\x1b[1;33m入力してください:\x1b[0m ");
 
                                     use std::io::BufRead;
-                                    let mut line = String::new();
-                                    std::io::stdin().lock().read_line(&mut line)?;
-
-                                    if !line.trim().is_empty() {
+                                    let text = match heard {
+                                        Some(Ok(text)) if text.is_empty() => {
+                                            println!("\x1b[2m聞き取れませんでした\x1b[0m");
+                                            text
+                                        }
+                                        Some(Ok(text)) => {
+                                            println!("\x1b[1;33m🎤\x1b[0m {}", text);
+                                            text
+                                        }
+                                        Some(Err(e)) => {
+                                            eprintln!("\x1b[31m{}\x1b[0m", e);
+                                            String::new()
+                                        }
+                                        // No recognizer or recorder: type the request instead
+                                        None => {
+                                            println!("\x1b[1;33m入力してください:\x1b[0m ");
+                                            let mut line = String::new();
+                                            std::io::stdin().lock().read_line(&mut line)?;
+                                            line.trim().to_string()
+                                        }
+                                    };
+
+                                    if !text.is_empty() {
                                         // Send to API
                                         println!();
-                                        chat_api_stream(&client, &stream_url, &api_url, line.trim(), &session_id, auth_token.as_deref()).await?;
+                                        let reply = chat_api_stream(&client, &stream_url, &api_url, &text, &session_id, auth_token.as_deref()).await?;
                                         println!();
+                                        let spoken = tts::speakable(&reply);
+                                        if let Some(tts) = tts.as_ref().filter(|_| !spoken.is_empty()) {
+                                            println!("\x1b[2m🔊 読み上げ中 ({})\x1b[0m", tts.name());
+                                            playback = match tts.synthesize(&spoken).await {
+                                                Ok(speech) => Playback::start(&speech).map_err(|e| eprintln!("\x1b[31m{}\x1b[0m", e)).ok(),
+                                                Err(e) => {
+                                                    eprintln!("\x1b[31m{}\x1b[0m", e);
+                                                    None
+                                                }
+                                            };
+                                        }
                                     }
 
                                     println!("\x1b[2m[スペースキー] で再度話す | [Ctrl+C] で終了 | [Enter] でチャットモードへ\x1b[0m");
                                     std::io::stdin().lock().read_line(&mut String::new())?;
 
                                     terminal::enable_raw_mode()?;
+                                    report_key_releases(hold_to_talk, true)?;
                                     show_voice_ui(VoiceState::Idle, &session_id, sync.is_some(), auth_token.is_some())?;
-                                    listening_start = None;
                                 }
                             }
-                            KeyCode::Enter => {
+                            KeyCode::Enter if key.kind == KeyEventKind::Press => {
                                 // Switch to chat mode
                                 mode = InteractionMode::Chat;
+                                report_key_releases(hold_to_talk, false)?;
                                 terminal::disable_raw_mode()?;
                                 stdout.execute(terminal::Clear(ClearType::All))?;
                                 println!("\x1b[1;36m💬 チャットモードに切り替えました\x1b[0m");
@@ -620,6 +1548,7 @@ async fn cmd_voice(api_url: String, sync: Option<String>) -> Result<()> {
                                     input_buffer.clear();
                                     mode = InteractionMode::Voice;
                                     terminal::enable_raw_mode()?;
+                                    report_key_releases(hold_to_talk, true)?;
                                     show_voice_ui(VoiceState::Idle, &session_id, sync.is_some(), auth_token.is_some())?;
                                 } else if !input_buffer.trim().is_empty() {
                                     // Send message
@@ -651,6 +1580,7 @@ async fn cmd_voice(api_url: String, sync: Option<String>) -> Result<()> {
         }
     }
 
+    report_key_releases(hold_to_talk && matches!(mode, InteractionMode::Voice), false)?;
     terminal::disable_raw_mode()?;
     stdout.execute(terminal::Clear(ClearType::All))?;
     println!("\x1b[2mVoice UIを終了しました\x1b[0m");
@@ -658,6 +1588,594 @@ async fn cmd_voice(api_url: String, sync: Option<String>) -> Result<()> {
     Ok(())
 }
 
+/// Capture rate for the microphone; the recorder resamples to it.
+const CAPTURE_RATE: u32 = 16_000;
+
+/// Turn key release reporting (kitty keyboard protocol) on or off where the
+/// terminal supports it. Keys then arrive as escape codes, so it must be
+/// off outside raw mode.
+fn report_key_releases(supported: bool, on: bool) -> Result<()> {
+    use crossterm::event::{KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags};
+    if !supported {
+        return Ok(());
+    }
+    let mut stdout = std::io::stdout();
+    if on {
+        stdout.execute(PushKeyboardEnhancementFlags(
+            KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                | KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES
+                | KeyboardEnhancementFlags::REPORT_EVENT_TYPES,
+        ))?;
+    } else {
+        stdout.execute(PopKeyboardEnhancementFlags)?;
+    }
+    Ok(())
+}
+
+/// A push-to-talk recording in progress.
+struct Recording {
+    child: std::process::Child,
+    reader: Option<std::thread::JoinHandle<Vec<u8>>>,
+}
+
+impl Recording {
+    fn start() -> Result<Self> {
+        use std::io::Read;
+        let mut child = spawn_recorder()?;
+        let mut mic = child.stdout.take().ok_or_else(|| anyhow::anyhow!("recorder has no output"))?;
+        let reader = std::thread::spawn(move || {
+            let mut pcm = Vec::new();
+            let _ = mic.read_to_end(&mut pcm);
+            pcm
+        });
+        Ok(Self { child, reader: Some(reader) })
+    }
+
+    /// Stop the recorder and return the captured samples.
+    fn stop(mut self) -> Vec<i16> {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let pcm = self.reader.take().and_then(|r| r.join().ok()).unwrap_or_default();
+        nanobot_core::voice::pcm_from_bytes(&pcm)
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Start a recorder writing raw 16 kHz mono 16-bit PCM to stdout.
+fn spawn_recorder() -> Result<std::process::Child> {
+    let rate = CAPTURE_RATE.to_string();
+    let recorders: [(&str, Vec<&str>); 2] = [
+        ("arecord", vec!["-q", "-t", "raw", "-f", "S16_LE", "-c", "1", "-r", &rate]),
+        ("rec", vec!["-q", "-t", "raw", "-b", "16", "-e", "signed-integer", "-c", "1", "-r", &rate, "-"]),
+    ];
+    for (program, args) in recorders {
+        if let Ok(child) = std::process::Command::new(program)
+            .args(&args)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+        {
+            return Ok(child);
+        }
+    }
+    anyhow::bail!("no audio recorder found; install alsa-utils (arecord) or sox (rec)")
+}
+
+/// TTS audio playing in an external player, paused with SIGSTOP on barge-in.
+struct Playback {
+    child: std::process::Child,
+    path: std::path::PathBuf,
+}
+
+impl Playback {
+    fn start(speech: &Speech) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("chatweb-tts-{}.{}", std::process::id(), speech.format));
+        std::fs::write(&path, &speech.audio)?;
+        let file = path.to_string_lossy().to_string();
+        let players: [(&str, Vec<&str>); 5] = [
+            ("afplay", vec![&file]),
+            ("mpv", vec!["--no-terminal", "--no-video", &file]),
+            ("ffplay", vec!["-nodisp", "-autoexit", "-loglevel", "quiet", &file]),
+            ("mpg123", vec!["-q", &file]),
+            ("aplay", vec!["-q", &file]),
+        ];
+        // mpg123 only decodes MP3 and aplay only plays WAV
+        let usable = |program: &str| match program {
+            "mpg123" => speech.format == "mp3",
+            "aplay" => speech.format == "wav",
+            _ => true,
+        };
+        for (program, args) in players.into_iter().filter(|(program, _)| usable(program)) {
+            if let Ok(child) = std::process::Command::new(program)
+                .args(&args)
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .spawn()
+            {
+                return Ok(Self { child, path });
+            }
+        }
+        anyhow::bail!("no audio player found; install mpv, ffmpeg (ffplay), mpg123 or alsa-utils (aplay)")
+    }
+
+    fn signal(&self, signal: &str) {
+        let _ = std::process::Command::new("kill")
+            .args([signal, &self.child.id().to_string()])
+            .status();
+    }
+
+    fn pause(&self) {
+        self.signal("-STOP");
+    }
+
+    fn resume(&self) {
+        self.signal("-CONT");
+    }
+
+    fn finished(&mut self) -> bool {
+        !matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+impl Drop for Playback {
+    fn drop(&mut self) {
+        // A stopped process must be continued before it can act on SIGKILL.
+        self.resume();
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Hands-free voice mode: the microphone stays open, voice activity detection
+/// ends each utterance, and talking over a reply pauses it (barge-in).
+/// `kiosk` adds the appliance extras of `chatweb kiosk`.
+async fn cmd_voice_hands_free(
+    api_url: String,
+    sync: Option<String>,
+    voice: config::VoiceConfig,
+    mut kiosk: Option<KioskRuntime>,
+) -> Result<()> {
+    use nanobot_core::kiosk::{DeviceCommand, KioskState};
+    use nanobot_core::voice::stt::ChatwebStt;
+    use nanobot_core::voice::tts::ChatwebTts;
+    use nanobot_core::voice::{self, Action, HandsFree, Turn, TurnState};
+    use std::io::Read;
+
+    let session_id = match sync {
+        Some(sid) => sid,
+        None => get_cli_session_id()?,
+    };
+    let Some(auth_token) = load_auth_token() else {
+        anyhow::bail!("hands-free mode uses the chatweb.ai speech API; run `chatweb link` to sign in first");
+    };
+    let base_url = api_url.trim_end_matches("/api/v1/chat").to_string();
+    let stream_url = format!("{}/api/v1/chat/stream", base_url);
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(90))
+        .build()
+        .unwrap_or_default();
+    let stt = ChatwebStt::new(&base_url, &auth_token);
+    let tts = ChatwebTts::new(&base_url, &auth_token, &session_id);
+
+    let mut recorder = spawn_recorder()?;
+    let mut mic = recorder.stdout.take().ok_or_else(|| anyhow::anyhow!("recorder has no output"))?;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Vec<i16>>();
+    std::thread::spawn(move || {
+        // 20 ms reads keep barge-in responsive.
+        let mut buf = vec![0u8; (CAPTURE_RATE / 50 * 2) as usize];
+        while let Ok(n) = mic.read(&mut buf) {
+            if n == 0 || tx.send(voice::pcm_from_bytes(&buf[..n])).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut hands_free = HandsFree::new(&voice, CAPTURE_RATE);
+    let mut playback: Option<Playback> = None;
+    println!("\x1b[1;36m{}\x1b[0m ハンズフリーモード", nanobot_core::LOGO);
+    match hands_free.wake_word() {
+        Some(wake) => println!("\x1b[2m「{}」と呼びかけてから話してください | Ctrl+C で終了\x1b[0m", wake),
+        None => println!("\x1b[2mそのまま話しかけてください | Ctrl+C で終了\x1b[0m"),
+    }
+    println!();
+    if let Some(k) = kiosk.as_mut() {
+        k.start_heartbeat(&client, &base_url, &session_id);
+        k.show(KioskState::Listening);
+    }
+
+    let mut tick = tokio::time::interval(std::time::Duration::from_millis(200));
+    let mut restart = false;
+    loop {
+        let samples = tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = tick.tick() => {
+                if playback.as_mut().is_some_and(|p| p.finished()) {
+                    playback = None;
+                    hands_free.set_state(TurnState::Listening);
+                    if let Some(k) = kiosk.as_mut() {
+                        k.show_idle();
+                    }
+                }
+                continue;
+            }
+            Some(command) = next_device_command(&mut kiosk) => {
+                let Some(k) = kiosk.as_mut() else { continue };
+                tracing::info!("Device command: {:?}", command);
+                match command {
+                    DeviceCommand::Say { text } => {
+                        drop(playback.take());
+                        playback = k.speak(&tts, &text).await;
+                        hands_free.set_state(if playback.is_some() { TurnState::Speaking } else { TurnState::Listening });
+                    }
+                    DeviceCommand::Volume { percent } => k.set_volume(percent),
+                    DeviceCommand::Mute => {
+                        playback = None;
+                        k.muted = true;
+                        k.show_idle();
+                    }
+                    DeviceCommand::Unmute => {
+                        k.muted = false;
+                        k.show_idle();
+                    }
+                    DeviceCommand::Restart => {
+                        restart = true;
+                        break;
+                    }
+                }
+                continue;
+            }
+            samples = rx.recv() => match samples {
+                Some(samples) => samples,
+                None => anyhow::bail!("the audio recorder stopped"),
+            },
+        };
+        if kiosk.as_ref().is_some_and(|k| k.muted) {
+            continue;
+        }
+
+        for action in hands_free.push_audio(&samples) {
+            let audio = match action {
+                Action::PauseTts => {
+                    if let Some(p) = &playback {
+                        p.pause();
+                    }
+                    continue;
+                }
+                Action::Transcribe(audio) => audio,
+            };
+            let wav = voice::pcm_to_wav(&audio, CAPTURE_RATE);
+            let text = match stt.transcribe_wav(wav.clone()).await {
+                Ok(text) => text,
+                Err(e) => match kiosk.as_mut() {
+                    Some(k) => k.transcribe_offline(&e, &wav),
+                    None => {
+                        eprintln!("\x1b[31m{}\x1b[0m", e);
+                        String::new()
+                    }
+                },
+            };
+            match hands_free.on_transcript(&text) {
+                Turn::Request(request) => {
+                    playback = None;
+                    println!("\x1b[1;33m🎤\x1b[0m {}", request);
+                    if let Some(k) = kiosk.as_mut() {
+                        k.show(KioskState::Thinking);
+                    }
+                    let reply = match chat_api_stream(&client, &stream_url, &api_url, &request, &session_id, Some(&auth_token)).await {
+                        Ok(reply) => {
+                            if let Some(k) = kiosk.as_mut() {
+                                k.offline = false;
+                            }
+                            reply
+                        }
+                        Err(e) => match kiosk.as_mut() {
+                            Some(k) => k.reply_offline(e, &request).await,
+                            None => {
+                                eprintln!("\x1b[31mError: {}\x1b[0m", e);
+                                String::new()
+                            }
+                        },
+                    };
+                    println!();
+                    // Audio captured while thinking belongs to the finished turn.
+                    while rx.try_recv().is_ok() {}
+                    let started = if reply.trim().is_empty() || !voice.tts {
+                        None
+                    } else if let Some(k) = kiosk.as_mut() {
+                        k.speak(&tts, &reply).await
+                    } else {
+                        match tts.synthesize(&reply).await {
+                            Ok(speech) => Playback::start(&speech).map_err(|e| eprintln!("\x1b[31m{}\x1b[0m", e)).ok(),
+                            Err(e) => {
+                                eprintln!("\x1b[31m{}\x1b[0m", e);
+                                None
+                            }
+                        }
+                    };
+                    playback = started;
+                    hands_free.set_state(if playback.is_some() { TurnState::Speaking } else { TurnState::Listening });
+                    if playback.is_none() {
+                        if let Some(k) = kiosk.as_mut() {
+                            k.show_idle();
+                        }
+                    }
+                }
+                Turn::Awake => println!("\x1b[2m👂 どうぞ\x1b[0m"),
+                Turn::ResumeTts => {
+                    if let Some(p) = &playback {
+                        p.resume();
+                    }
+                }
+                Turn::Ignore => {}
+            }
+        }
+    }
+
+    drop(playback);
+    let _ = recorder.kill();
+    let _ = recorder.wait();
+    if restart {
+        // Exit non-zero so the service manager starts a fresh process
+        anyhow::bail!("restart requested remotely");
+    }
+    println!("\x1b[2mVoice UIを終了しました\x1b[0m");
+    Ok(())
+}
+
+/// The next remote command for a kiosk; never resolves without one.
+async fn next_device_command(kiosk: &mut Option<KioskRuntime>) -> Option<nanobot_core::kiosk::DeviceCommand> {
+    match kiosk.as_mut() {
+        Some(k) => k.commands.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Appliance extras for hands-free mode (`chatweb kiosk`): status hooks,
+/// offline fallbacks and remote commands from the heartbeat.
+struct KioskRuntime {
+    config: config::KioskConfig,
+    status: nanobot_core::kiosk::StatusHooks,
+    fallback: Option<nanobot_core::kiosk::LocalFallback>,
+    commands_tx: tokio::sync::mpsc::UnboundedSender<nanobot_core::kiosk::DeviceCommand>,
+    commands: tokio::sync::mpsc::UnboundedReceiver<nanobot_core::kiosk::DeviceCommand>,
+    /// chatweb.ai was unreachable on the last request.
+    offline: bool,
+    muted: bool,
+}
+
+impl KioskRuntime {
+    fn new(config: config::KioskConfig) -> Self {
+        let (commands_tx, commands) = tokio::sync::mpsc::unbounded_channel();
+        Self {
+            status: nanobot_core::kiosk::StatusHooks::new(config.hooks.clone()),
+            fallback: nanobot_core::kiosk::LocalFallback::from_config(&config),
+            config,
+            commands_tx,
+            commands,
+            offline: false,
+            muted: false,
+        }
+    }
+
+    fn show(&mut self, state: nanobot_core::kiosk::KioskState) {
+        if self.status.set(state) {
+            println!("\x1b[2m● {}\x1b[0m", state.as_str());
+        }
+    }
+
+    /// Back to waiting for speech.
+    fn show_idle(&mut self) {
+        use nanobot_core::kiosk::KioskState;
+        self.show(if self.muted {
+            KioskState::Muted
+        } else if self.offline {
+            KioskState::Offline
+        } else {
+            KioskState::Listening
+        });
+    }
+
+    /// Send heartbeats in the background and pass on remote commands.
+    fn start_heartbeat(&self, client: &reqwest::Client, base_url: &str, session_id: &str) {
+        let (client, base_url, session_id) = (client.clone(), base_url.to_string(), session_id.to_string());
+        let tx = self.commands_tx.clone();
+        let interval = self.config.heartbeat_secs.max(10);
+        tokio::spawn(async move {
+            loop {
+                match send_heartbeat(&client, &base_url, &session_id).await {
+                    Ok(commands) => {
+                        for command in commands {
+                            if tx.send(command).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(e) => tracing::warn!("Heartbeat error: {}", e),
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+            }
+        });
+    }
+
+    /// Transcript from the offline recognizer when the speech API is
+    /// unreachable; empty otherwise.
+    fn transcribe_offline(&mut self, error: &anyhow::Error, wav: &[u8]) -> String {
+        let offline = nanobot_core::offline::is_offline_error(error);
+        match self.config.offline_stt_command.as_deref().filter(|_| offline) {
+            Some(command) => {
+                self.offline = true;
+                nanobot_core::kiosk::transcribe_locally(command, wav).unwrap_or_else(|e| {
+                    eprintln!("\x1b[31m{}\x1b[0m", e);
+                    String::new()
+                })
+            }
+            None => {
+                eprintln!("\x1b[31m{}\x1b[0m", error);
+                if !offline {
+                    self.show(nanobot_core::kiosk::KioskState::Error);
+                }
+                String::new()
+            }
+        }
+    }
+
+    /// Answer from the local model when chatweb.ai is unreachable.
+    async fn reply_offline(&mut self, error: anyhow::Error, request: &str) -> String {
+        let offline = nanobot_core::offline::is_offline_error(&error);
+        let Some(fallback) = self.fallback.as_mut().filter(|_| offline) else {
+            eprintln!("\x1b[31mError: {}\x1b[0m", error);
+            self.show(nanobot_core::kiosk::KioskState::Error);
+            return String::new();
+        };
+        self.offline = true;
+        match fallback.reply(request).await {
+            Ok(reply) => {
+                println!("\x1b[1;36m{}\x1b[0m {} \x1b[2m({})\x1b[0m", nanobot_core::LOGO, reply, fallback.model());
+                reply
+            }
+            Err(e) => {
+                eprintln!("\x1b[31mOffline model: {}\x1b[0m", e);
+                self.show(nanobot_core::kiosk::KioskState::Error);
+                String::new()
+            }
+        }
+    }
+
+    /// Speak `text`: through the speech API and a player that supports
+    /// barge-in, or with the offline synthesizer (blocking) when offline.
+    async fn speak(&mut self, tts: &dyn TtsProvider, text: &str) -> Option<Playback> {
+        use nanobot_core::kiosk::KioskState;
+
+        if !self.offline {
+            match tts.synthesize(text).await {
+                Ok(speech) => match Playback::start(&speech) {
+                    Ok(playback) => {
+                        self.show(KioskState::Speaking);
+                        return Some(playback);
+                    }
+                    Err(e) => eprintln!("\x1b[31m{}\x1b[0m", e),
+                },
+                Err(e) if nanobot_core::offline::is_offline_error(&e) => self.offline = true,
+                Err(e) => eprintln!("\x1b[31m{}\x1b[0m", e),
+            }
+        }
+        self.show(KioskState::Speaking);
+        let (command, text) = (self.config.offline_tts_command.clone(), text.to_string());
+        match tokio::task::spawn_blocking(move || nanobot_core::kiosk::speak_locally(&command, &text)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => eprintln!("\x1b[31m{}\x1b[0m", e),
+            Err(e) => eprintln!("\x1b[31m{}\x1b[0m", e),
+        }
+        self.show_idle();
+        None
+    }
+
+    fn set_volume(&self, percent: u8) {
+        let command = self.config.volume_command.replace("{percent}", &percent.min(100).to_string());
+        match std::process::Command::new("sh").args(["-c", &command]).status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => tracing::warn!("Volume command failed ({})", status),
+            Err(e) => tracing::warn!("Volume command failed: {}", e),
+        }
+    }
+}
+
+/// Voice appliance mode for a Raspberry Pi or similar: hands-free, always
+/// listening, with status hooks, offline fallbacks and remote management.
+async fn cmd_kiosk(api_base: String, sync: Option<String>) -> Result<()> {
+    let config = config::load_config(None);
+    let mut voice = config.voice;
+    voice.hands_free = true;
+    let kiosk = KioskRuntime::new(config.kiosk);
+    if let Some(ref fallback) = kiosk.fallback {
+        println!("\x1b[2mOffline model: {}\x1b[0m", fallback.model());
+    }
+    let api_url = format!("{}/api/v1/chat", api_base.trim_end_matches('/'));
+    cmd_voice_hands_free(api_url, sync, voice, Some(kiosk)).await
+}
+
+/// Voice mode for Termux: line input instead of raw-mode push-to-talk
+/// (Android terminals never send key releases). Enter on an empty line
+/// listens with Android speech recognition, and replies are read out with
+/// Android TTS when termux-api is installed.
+async fn cmd_voice_termux(api_url: String, sync: Option<String>, termux: TermuxProfile) -> Result<()> {
+    use std::io::Write;
+
+    let session_id = match sync {
+        Some(sid) => sid,
+        None => get_cli_session_id()?,
+    };
+    let auth_token = load_auth_token();
+    let stream_url = api_url.replace("/api/v1/chat", "/api/v1/chat/stream");
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(90))
+        .build()
+        .unwrap_or_default();
+    let locale = Locale::from_env().unwrap_or_default();
+
+    println!("\x1b[1;36m{}\x1b[0m chatweb.ai \x1b[2m(Termux: {})\x1b[0m", nanobot_core::LOGO, termux.summary());
+    if termux.microphone {
+        println!("\x1b[2m{}\x1b[0m", locale.pick("Enter で話す / 文字で入力して送信 / Ctrl+D で終了", "Enter to talk / type to send / Ctrl+D to quit"));
+    } else {
+        println!("\x1b[2m{}\x1b[0m", locale.pick("入力して送信 / Ctrl+D で終了（音声入力: pkg install termux-api）", "Type to send / Ctrl+D to quit (voice input: pkg install termux-api)"));
+    }
+    println!();
+    replay_offline_queue(&client, &stream_url, &api_url, &session_id, auth_token.as_deref(), locale).await;
+
+    loop {
+        print!("\x1b[1;33m{}\x1b[0m ", if termux.microphone { "🎤" } else { "You:" });
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            break;
+        }
+        let mut message = line.trim().to_string();
+        if message.is_empty() {
+            if !termux.microphone {
+                continue;
+            }
+            println!("\x1b[2m👂 {}\x1b[0m", locale.pick("どうぞ", "Listening"));
+            message = match tokio::task::spawn_blocking(nanobot_core::termux::listen).await? {
+                Ok(text) if !text.is_empty() => text,
+                Ok(_) => continue,
+                Err(e) => {
+                    eprintln!("\x1b[31m{}\x1b[0m", e);
+                    continue;
+                }
+            };
+            println!("\x1b[1;33mYou:\x1b[0m {}", message);
+        }
+
+        println!();
+        replay_offline_queue(&client, &stream_url, &api_url, &session_id, auth_token.as_deref(), locale).await;
+        let reply = match send_or_queue(&client, &stream_url, &api_url, &message, &session_id, auth_token.as_deref(), None, &ChatOptions::default(), locale).await {
+            Ok(reply) => reply,
+            Err(e) => {
+                eprintln!("\x1b[31mError: {}\x1b[0m\n", e);
+                continue;
+            }
+        };
+        println!();
+        if termux.tts && !reply.trim().is_empty() {
+            if let Err(e) = tokio::task::spawn_blocking(move || nanobot_core::termux::speak(&reply)).await? {
+                eprintln!("\x1b[31m{}\x1b[0m", e);
+            }
+        }
+    }
+
+    println!("\x1b[2mVoice UIを終了しました\x1b[0m");
+    Ok(())
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum InteractionMode {
     Voice,
@@ -730,7 +2248,7 @@ fn show_voice_ui(state: VoiceState, session_id: &str, synced: bool, authenticate
 
 /// Chat with chatweb.ai API directly — no config or API key needed.
 /// Uses SSE streaming for real-time responses with tool progress.
-async fn cmd_chat(message: Vec<String>, api_url: String, sync: Option<String>) -> Result<()> {
+async fn cmd_chat(message: Vec<String>, api_url: String, sync: Option<String>, mode: Option<ChatMode>, options: ChatOptions) -> Result<()> {
     let session_id = if let Some(ref sid) = sync {
         sid.clone()
     } else {
@@ -749,10 +2267,14 @@ async fn cmd_chat(message: Vec<String>, api_url: String, sync: Option<String>) -
         .unwrap_or_default();
 
     if message.is_empty() {
+        // UI language; follows /lang (the server keeps its own per-session copy)
+        let mut locale = Locale::from_env().unwrap_or_default();
+
         // Interactive mode with Claude Code-style banner
         println!();
-        show_welcome_banner(&session_id, sync.is_some(), auth_token.is_some());
+        show_welcome_banner(&session_id, sync.is_some(), auth_token.is_some(), locale);
         println!();
+        replay_offline_queue(&client, &stream_url, &api_url, &session_id, auth_token.as_deref(), locale).await;
 
         let mut last_message = String::new();
         let mut in_phrase_menu = false;
@@ -797,63 +2319,69 @@ async fn cmd_chat(message: Vec<String>, api_url: String, sync: Option<String>) -
                 continue;
             }
 
-            // Mobile-friendly shortcut commands
-            match input {
+            if let Some(inv) = nanobot_core::command::parse(input).filter(|inv| inv.name() == "lang") {
+                if let Some(l) = Locale::parse(inv.args) {
+                    locale = l;
+                }
+            }
+
+            // Slash commands from the shared registry: CLI screen commands are
+            // handled here, session commands (/reset, /pin, ...) go to the server.
+            let local_cmd = if input == "?" {
+                Some("q")
+            } else {
+                nanobot_core::command::parse(input)
+                    .filter(|inv| inv.name() == "help" || matches!(inv.spec.handler, nanobot_core::command::Handler::Frontend))
+                    .filter(|inv| inv.spec.is_available("cli"))
+                    .map(|inv| inv.name())
+            };
+            match (local_cmd, input) {
                 // Quick menu
-                "/q" | "?" => {
-                    show_quick_menu();
+                (Some("q"), _) => {
+                    show_quick_menu(locale);
                     continue;
                 }
                 // Status
-                "/s" => {
-                    println!();
-                    println!("\x1b[1;36m📊 ステータス\x1b[0m");
-                    println!("\x1b[2m  Session: {}\x1b[0m", session_id);
-                    if sync.is_some() {
-                        println!("\x1b[32m  ✓ Synced\x1b[0m");
-                    }
-                    if auth_token.is_some() {
-                        println!("\x1b[32m  ✓ Authenticated\x1b[0m");
-                    }
-                    println!();
+                (Some("status"), _) => {
+                    show_status(&session_id, sync.is_some(), auth_token.is_some(), locale);
                     continue;
                 }
-                // Mobile help
-                "/h" => {
-                    show_mobile_help();
+                // Help
+                (Some("help"), _) => {
+                    show_mobile_help(locale);
                     continue;
                 }
                 // Clear screen
-                "/c" => {
+                (Some("c"), _) => {
                     print!("\x1b[2J\x1b[H");
                     std::io::stdout().flush()?;
-                    show_welcome_banner(&session_id, sync.is_some(), auth_token.is_some());
+                    show_welcome_banner(&session_id, sync.is_some(), auth_token.is_some(), locale);
                     println!();
                     continue;
                 }
                 // Repeat last message
-                "/." => {
+                (Some("."), _) => {
                     if last_message.is_empty() {
-                        println!("\x1b[2m前回のメッセージがありません\x1b[0m");
+                        println!("\x1b[2m{}\x1b[0m", locale.pick("前回のメッセージがありません", "No previous message"));
                         println!();
                         continue;
                     }
-                    println!("\x1b[2m再送信: {}\x1b[0m", last_message);
+                    println!("\x1b[2m{}: {}\x1b[0m", locale.pick("再送信", "Resending"), last_message);
                     println!();
                     match chat_api_stream(&client, &stream_url, &api_url, &last_message, &session_id, auth_token.as_deref()).await {
-                        Ok(()) => println!(),
+                        Ok(_) => println!(),
                         Err(e) => eprintln!("\x1b[31mError: {}\x1b[0m\n", e),
                     }
                     continue;
                 }
                 // Phrases menu
-                "/m" => {
+                (Some("m"), _) => {
                     show_phrases_menu();
                     in_phrase_menu = true;
                     continue;
                 }
-                // Konami code
-                "/konami" => {
+                // Konami code (hidden)
+                (_, "/konami") => {
                     println!();
                     print!("\x1b[2mActivating Konami code...\x1b[0m");
                     std::io::stdout().flush()?;
@@ -877,7 +2405,7 @@ async fn cmd_chat(message: Vec<String>, api_url: String, sync: Option<String>) -
                     continue;
                 }
                 // Omikuji (fortune)
-                "/omikuji" | "/fortune" => {
+                (Some("omikuji"), _) => {
                     println!();
 
                     match draw_omikuji(&client, &api_url, &session_id, auth_token.as_deref()).await {
@@ -922,17 +2450,7 @@ async fn cmd_chat(message: Vec<String>, api_url: String, sync: Option<String>) -
                     // Quick action menu shortcuts
                     match num {
                         "1" => {
-                            // Status
-                            println!();
-                            println!("\x1b[1;36m📊 ステータス\x1b[0m");
-                            println!("\x1b[2m  Session: {}\x1b[0m", session_id);
-                            if sync.is_some() {
-                                println!("\x1b[32m  ✓ Synced\x1b[0m");
-                            }
-                            if auth_token.is_some() {
-                                println!("\x1b[32m  ✓ Authenticated\x1b[0m");
-                            }
-                            println!();
+                            show_status(&session_id, sync.is_some(), auth_token.is_some(), locale);
                             continue;
                         }
                         "2" => {
@@ -977,7 +2495,7 @@ async fn cmd_chat(message: Vec<String>, api_url: String, sync: Option<String>) -
                             continue;
                         }
                         "5" => {
-                            show_mobile_help();
+                            show_mobile_help(locale);
                             continue;
                         }
                         _ => {
@@ -991,43 +2509,271 @@ async fn cmd_chat(message: Vec<String>, api_url: String, sync: Option<String>) -
                 input.to_string()
             };
 
-            // Send message to API
+            // Send message to API (after anything still queued from offline, to keep the order)
             last_message = message_to_send.clone();
             println!();
-            match chat_api_stream(&client, &stream_url, &api_url, &message_to_send, &session_id, auth_token.as_deref()).await {
-                Ok(()) => println!(),
+            replay_offline_queue(&client, &stream_url, &api_url, &session_id, auth_token.as_deref(), locale).await;
+            match send_or_queue(&client, &stream_url, &api_url, &message_to_send, &session_id, auth_token.as_deref(), mode, &options, locale).await {
+                Ok(_) => println!(),
                 Err(e) => eprintln!("\x1b[31mError: {}\x1b[0m\n", e),
             }
         }
     } else {
         // Single message mode
+        let locale = Locale::from_env().unwrap_or_default();
         let msg = message.join(" ");
-        match chat_api_stream(&client, &stream_url, &api_url, &msg, &session_id, auth_token.as_deref()).await {
-            Ok(()) => {}
-            Err(e) => eprintln!("\x1b[31mError: {}\x1b[0m", e),
+        replay_offline_queue(&client, &stream_url, &api_url, &session_id, auth_token.as_deref(), locale).await;
+        if let Err(e) = send_or_queue(&client, &stream_url, &api_url, &msg, &session_id, auth_token.as_deref(), mode, &options, locale).await {
+            eprintln!("\x1b[31mError: {}\x1b[0m", e);
         }
     }
 
     Ok(())
 }
 
-/// Load auth token from ~/.nanobot/auth_token if available.
-fn load_auth_token() -> Option<String> {
-    let token_path = config::get_data_dir().join("auth_token");
-    std::fs::read_to_string(token_path).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+/// Send a chat message, or queue it when the server can't be reached.
+/// Returns the reply (empty when queued). An explicit mode or options go
+/// through the non-streaming endpoint, which supports all of them.
+#[allow(clippy::too_many_arguments)]
+async fn send_or_queue(
+    client: &reqwest::Client,
+    stream_url: &str,
+    api_url: &str,
+    message: &str,
+    session_id: &str,
+    auth_token: Option<&str>,
+    mode: Option<ChatMode>,
+    options: &ChatOptions,
+    locale: Locale,
+) -> Result<String> {
+    use nanobot_core::offline::{self, OfflineQueue};
+
+    let sent = if mode.is_some() || *options != ChatOptions::default() {
+        chat_api_fallback(client, api_url, message, session_id, auth_token, mode, options).await
+    } else {
+        chat_api_stream(client, stream_url, api_url, message, session_id, auth_token).await
+    };
+    match sent {
+        Ok(reply) => Ok(reply),
+        Err(e) if offline::is_offline_error(&e) => {
+            let queue = OfflineQueue::new(OfflineQueue::default_path());
+            queue.push(session_id, api_url, message);
+            let waiting = queue.pending(Some(session_id)).len();
+            if locale.is_english() {
+                println!("\x1b[33m📴 Offline: message saved ({} waiting). It will be sent when the connection is back.\x1b[0m", waiting);
+            } else {
+                println!("\x1b[33m📴 オフラインです。メッセージを保存しました（{}件待機中）。接続が戻ったら送信します。\x1b[0m", waiting);
+            }
+            Ok(String::new())
+        }
+        Err(e) => Err(e),
+    }
 }
 
-/// Link CLI session with a Web/LINE/Telegram session.
-async fn cmd_link(session_id: Option<String>) -> Result<()> {
-    let cli_session = get_cli_session_id()?;
-    let client = reqwest::Client::new();
-    let api_base = "https://chatweb.ai";
+/// Send messages queued while offline for `session_id`, oldest first.
+/// Stops at the first one that still can't be delivered.
+async fn replay_offline_queue(
+    client: &reqwest::Client,
+    stream_url: &str,
+    api_url: &str,
+    session_id: &str,
+    auth_token: Option<&str>,
+    locale: Locale,
+) {
+    use nanobot_core::offline::{self, OfflineQueue};
+
+    let queue = OfflineQueue::new(OfflineQueue::default_path());
+    let pending = queue.pending(Some(session_id));
+    if pending.is_empty() {
+        return;
+    }
+    println!(
+        "\x1b[2m↻ {} ({})\x1b[0m",
+        locale.pick("オフライン中のメッセージを送信します", "Sending messages queued while offline"),
+        pending.len()
+    );
+    for m in pending {
+        println!("\x1b[1;33mYou\x1b[0m \x1b[2m({})\x1b[0m: {}", m.queued_at_local(), m.message);
+        match chat_api_stream(client, stream_url, api_url, &m.message, session_id, auth_token).await {
+            Ok(_) => {
+                queue.remove(&m.id);
+                println!();
+            }
+            Err(e) if offline::is_offline_error(&e) => {
+                queue.mark_failed(&m.id, &e.to_string());
+                println!("\x1b[2m{}\x1b[0m\n", locale.pick("まだオフラインです。後で再送します。", "Still offline; will retry later."));
+                return;
+            }
+            Err(e) => {
+                // The server answered, so retrying won't help
+                queue.remove(&m.id);
+                eprintln!("\x1b[31mError: {}\x1b[0m\n", e);
+            }
+        }
+    }
+}
 
-    match session_id {
-        Some(web_sid) => {
-            // Link CLI with the given Web session by sending /link command
-            // First generate a code from CLI session
-            let resp = client
+/// Send queued messages in the background (`chatweb daemon`). Replies are
+/// not shown here; they land in the session like any other turn, and are
+/// posted as a notification when `notify` is set (Termux).
+async fn flush_offline_queue(client: &reqwest::Client, auth_token: Option<&str>, notify: bool) {
+    use nanobot_core::offline::OfflineQueue;
+
+    let queue = OfflineQueue::new(OfflineQueue::default_path());
+    for m in queue.pending(None) {
+        let body = serde_json::json!({
+            "message": m.message,
+            "session_id": m.session_id,
+            "channel": "cli",
+        });
+        let mut req = client.post(&m.api_url).json(&body);
+        if let Some(token) = auth_token {
+            req = req.header("Authorization", format!("Bearer {}", token));
+        }
+        match req.send().await {
+            Ok(resp) => {
+                if resp.status().is_success() {
+                    tracing::info!("Sent queued message {} to {}", m.id, m.session_id);
+                    let body: serde_json::Value = resp.json().await.unwrap_or_default();
+                    if let Some(reply) = body["response"].as_str().filter(|_| notify) {
+                        let title = format!("{} {}", nanobot_core::LOGO, truncate_str(&m.message, 40));
+                        if let Err(e) = nanobot_core::termux::notify(&title, reply) {
+                            tracing::warn!("Notification failed: {}", e);
+                        }
+                    }
+                } else {
+                    tracing::warn!("Queued message {} rejected: {}", m.id, resp.status());
+                }
+                queue.remove(&m.id);
+            }
+            Err(e) => {
+                // Keep the order: later messages wait for this one
+                queue.mark_failed(&m.id, &e.to_string());
+                tracing::debug!("Offline queue still blocked: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Load auth token from ~/.nanobot/auth_token if available.
+fn load_auth_token() -> Option<String> {
+    let token_path = config::get_data_dir().join("auth_token");
+    std::fs::read_to_string(token_path).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// Print the credit balance and recent transactions of the signed-in account.
+async fn cmd_credits(limit: usize, api_base: String) -> Result<()> {
+    let Some(auth_token) = load_auth_token() else {
+        anyhow::bail!("credits belong to your chatweb.ai account; run `chatweb link` to sign in first");
+    };
+    let resp = reqwest::Client::new()
+        .get(format!("{}/api/v1/credits/history", api_base.trim_end_matches('/')))
+        .query(&[("limit", limit)])
+        .header("Authorization", format!("Bearer {}", auth_token))
+        .timeout(std::time::Duration::from_secs(15))
+        .send()
+        .await?;
+    let status = resp.status();
+    let body: serde_json::Value = resp.json().await.unwrap_or_default();
+    if !status.is_success() {
+        let error = body["error"].as_str().unwrap_or("request failed");
+        anyhow::bail!("{} ({})", error, status);
+    }
+
+    println!("Balance: {} credits", body["balance"].as_i64().unwrap_or(0));
+    let transactions: Vec<nanobot_core::credits::CreditTransaction> =
+        serde_json::from_value(body["transactions"].clone()).unwrap_or_default();
+    if transactions.is_empty() {
+        println!("No transactions yet.");
+        return Ok(());
+    }
+    println!();
+    for tx in &transactions {
+        println!("  {}", tx.line());
+    }
+    Ok(())
+}
+
+/// Load-test the chat API; with `mock_latency`, a local server backed by a
+/// mock provider that answers after that delay.
+async fn cmd_bench(
+    endpoint: String,
+    concurrency: usize,
+    duration: std::time::Duration,
+    message: String,
+    token: Option<String>,
+    mock_latency: Option<std::time::Duration>,
+) -> Result<()> {
+    let endpoint = match mock_latency {
+        Some(latency) => start_mock_server(latency).await?,
+        None => endpoint,
+    };
+    println!(
+        "Benchmarking {} with {} concurrent requests for {}s...",
+        endpoint, concurrency, duration.as_secs()
+    );
+    let report = nanobot_core::bench::run(&nanobot_core::bench::BenchOptions {
+        endpoint,
+        concurrency,
+        duration,
+        message,
+        token,
+        timeout: std::time::Duration::from_secs(60),
+    })
+    .await?;
+    println!();
+    print!("{}", report.render());
+    Ok(())
+}
+
+/// Serve the HTTP API on a free local port with a mock provider and
+/// sessions in a temporary directory. Returns the server's base URL.
+#[cfg(feature = "http-api")]
+async fn start_mock_server(latency: std::time::Duration) -> Result<String> {
+    use nanobot_core::bench::MockProvider;
+    use nanobot_core::service::http::{serve, AppState};
+    use nanobot_core::session::file_store::FileSessionStore;
+
+    let sessions_dir = std::env::temp_dir().join(format!("chatweb-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&sessions_dir)?;
+    let mut state = AppState::with_provider(config::load_config_from_env(), Box::new(FileSessionStore::new(&sessions_dir)));
+    state.provider = Some(Arc::new(MockProvider { latency }));
+    *state.lb_provider.get_mut().unwrap() = None;
+    *state.lb_raw.get_mut().unwrap() = None;
+
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.to_string();
+    let serve_addr = addr.clone();
+    tokio::spawn(async move {
+        if let Err(e) = serve(&serve_addr, Arc::new(state)).await {
+            eprintln!("Mock server error: {}", e);
+        }
+    });
+    for _ in 0..50 {
+        if tokio::net::TcpStream::connect(&addr).await.is_ok() {
+            return Ok(format!("http://{}", addr));
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    anyhow::bail!("mock server didn't start on {}", addr)
+}
+
+#[cfg(not(feature = "http-api"))]
+async fn start_mock_server(_latency: std::time::Duration) -> Result<String> {
+    anyhow::bail!("--mock needs the HTTP API. Rebuild with: cargo build --features http-api")
+}
+
+/// Link CLI session with a Web/LINE/Telegram session.
+async fn cmd_link(session_id: Option<String>, api_base: String) -> Result<()> {
+    let cli_session = get_cli_session_id()?;
+    let client = reqwest::Client::new();
+    let api_base = api_base.trim_end_matches('/');
+
+    match session_id {
+        Some(web_sid) => {
+            // Link CLI with the given Web session by sending /link command
+            // First generate a code from CLI session
+            let resp = client
                 .post(format!("{}/api/v1/chat", api_base))
                 .json(&serde_json::json!({
                     "message": "/link",
@@ -1070,10 +2816,255 @@ async fn cmd_link(session_id: Option<String>) -> Result<()> {
             println!();
             println!("To sync with Web: paste this ID on the chatweb.ai sync section");
             println!("To sync with Web session: chatweb link <WEB_SESSION_ID>");
+            println!("To link your phone: chatweb link --qr");
             println!("To chat with synced session: chatweb chat --sync <SESSION_ID>");
+            println!("To keep the local agent session in sync: chatweb sync --interval 30");
+        }
+    }
+
+    Ok(())
+}
+
+/// Link the CLI session with a phone: show a QR code for a one-time link
+/// code, then wait until the phone confirms it or the code expires.
+async fn cmd_link_qr(api_base: String) -> Result<()> {
+    use qrcode::render::unicode::Dense1x2;
+
+    let cli_session = get_cli_session_id()?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .build()
+        .unwrap_or_default();
+    let api_base = api_base.trim_end_matches('/');
+
+    let body: serde_json::Value = client
+        .post(format!("{}/api/v1/link/generate", api_base))
+        .header("x-session-id", &cli_session)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let code = match body["code"].as_str() {
+        Some(code) => code.to_string(),
+        None => anyhow::bail!("Failed to generate link code: {}", body["error"].as_str().unwrap_or("no code returned")),
+    };
+    let expires_in = body["expires_in"].as_u64().unwrap_or(1800);
+
+    let url = format!("{}/link/{}", api_base, code);
+    let qr = qrcode::QrCode::new(url.as_bytes()).map_err(|e| anyhow::anyhow!("QR code: {}", e))?;
+    // Inverted so the code reads as dark-on-light on a dark terminal
+    let image = qr
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .quiet_zone(true)
+        .build();
+
+    println!("{} Scan with your phone to link this CLI session:", nanobot_core::LOGO);
+    println!();
+    println!("{}", image);
+    println!();
+    println!("  {}", url);
+    println!("  Code: {} (expires in {} min)", code, expires_in / 60);
+    println!();
+    println!("Waiting for confirmation on the phone... (Ctrl+C to cancel)");
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(expires_in);
+    let status_url = format!("{}/api/v1/link/status/{}", api_base, code);
+    while std::time::Instant::now() < deadline {
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        let status: serde_json::Value = match client.get(&status_url).send().await {
+            Ok(resp) => resp.json().await.unwrap_or_default(),
+            Err(e) => {
+                tracing::debug!("Link status poll failed: {}", e);
+                continue;
+            }
+        };
+        match status["status"].as_str() {
+            Some("linked") => {
+                let channel = status["channel"].as_str().filter(|c| !c.is_empty()).unwrap_or("your phone");
+                println!("✓ Linked with {}. This session now continues there too.", channel);
+                return Ok(());
+            }
+            Some("expired") => anyhow::bail!("Link code expired. Run `chatweb link --qr` again."),
+            _ => {}
+        }
+    }
+    anyhow::bail!("Link code expired. Run `chatweb link --qr` again.")
+}
+
+/// Pull and push message deltas between a local session and the server, once
+/// or every `interval` seconds.
+async fn cmd_sync(session: String, remote: Option<String>, api_base: String, interval: Option<u64>) -> Result<()> {
+    use nanobot_core::session::file_store::FileSessionStore;
+    use nanobot_core::session::store::SessionStore;
+    use nanobot_core::session::sync;
+
+    let remote = match remote {
+        Some(id) => id,
+        None => get_cli_session_id()?,
+    };
+    let auth_token = load_auth_token();
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .unwrap_or_default();
+    let url = format!("{}/api/v1/sessions/{}/sync", api_base.trim_end_matches('/'), remote);
+
+    let cfg = config::load_config(None);
+    let mut sessions = FileSessionStore::new(&cfg.workspace_path());
+    if interval.is_some() {
+        println!("{} Syncing {} <-> {} (Ctrl+C to stop)", nanobot_core::LOGO, session, remote);
+    }
+
+    loop {
+        // Pick up messages `chatweb agent` wrote since the last round
+        let local = sessions.refresh(&session);
+        match sync::sync_with(&client, &url, auth_token.as_deref(), local).await {
+            Ok(stats) => {
+                sessions.save_by_key(&session);
+                if stats != sync::SyncStats::default() || interval.is_none() {
+                    println!("✓ {}: pushed {}, pulled {}", session, stats.pushed, stats.pulled);
+                }
+            }
+            Err(e) if interval.is_some() => tracing::warn!("Sync failed: {}", e),
+            Err(e) => return Err(e),
+        }
+        match interval {
+            Some(secs) => tokio::time::sleep(tokio::time::Duration::from_secs(secs.max(1))).await,
+            None => return Ok(()),
+        }
+    }
+}
+
+/// This device's name for sync: `sync.device`, else the hostname.
+fn sync_device_name(settings: &config::SyncConfig) -> String {
+    if !settings.device.is_empty() {
+        return settings.device.clone();
+    }
+    hostname::get()
+        .map(|h| h.to_string_lossy().to_string())
+        .unwrap_or_else(|_| "device".to_string())
+}
+
+/// The sync passphrase from NANOBOT_SYNC_PASSPHRASE, else typed without
+/// echo (twice when `confirm`, so a typo can't lock out the other devices).
+fn read_sync_passphrase(confirm: bool) -> Result<String> {
+    use nanobot_core::service::config_sync::PASSPHRASE_ENV;
+
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        if !passphrase.is_empty() {
+            return Ok(passphrase);
+        }
+    }
+    if !std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+        anyhow::bail!("set {} to sync without a terminal", PASSPHRASE_ENV);
+    }
+
+    let passphrase = read_hidden("Sync passphrase (the same on every device): ")?;
+    if passphrase.is_empty() {
+        anyhow::bail!("the sync passphrase can't be empty");
+    }
+    if confirm && read_hidden("Repeat the passphrase: ")? != passphrase {
+        anyhow::bail!("the passphrases don't match");
+    }
+    Ok(passphrase)
+}
+
+/// Read a line from the terminal without echoing it.
+fn read_hidden(prompt: &str) -> Result<String> {
+    eprint!("{}", prompt);
+    std::io::Write::flush(&mut std::io::stderr())?;
+    terminal::enable_raw_mode()?;
+    let mut line = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(anyhow::anyhow!("cancelled"))
+                }
+                KeyCode::Backspace => {
+                    line.pop();
+                }
+                KeyCode::Char(c) => line.push(c),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e.into()),
         }
+    };
+    terminal::disable_raw_mode()?;
+    eprintln!();
+    result.map(|_| line)
+}
+
+/// Sync settings, named sessions and pins with `sync.target` once.
+async fn cmd_sync_now(api: String) -> Result<()> {
+    use nanobot_core::service::config_sync::{self, SyncSources, SyncState, Target};
+    use nanobot_core::session::file_store::FileSessionStore;
+
+    let cfg = config::load_config(None);
+    let target = Target::parse(&cfg.sync, &api, load_auth_token())?;
+    let sources = SyncSources::active();
+    let passphrase = read_sync_passphrase(SyncState::load(&sources).last_sync_at.is_none())?;
+    let device = sync_device_name(&cfg.sync);
+    let mut sessions = FileSessionStore::new(&cfg.workspace_path());
+
+    let report = config_sync::run(&target, &cfg.sync, &passphrase, &device, &sources, &mut sessions).await?;
+    for key in &report.pulled {
+        println!("↓ {}", key);
+    }
+    for key in &report.removed {
+        println!("✗ {} (deleted on another device)", key);
+    }
+    for (key, copy) in &report.conflicts {
+        println!("! {} changed on two devices; the other version is in {}", key, copy.display());
     }
+    if report.pushed {
+        println!("↑ Uploaded to {}", target.describe());
+    }
+    println!("✓ In sync with {}", target.describe());
+    Ok(())
+}
+
+/// Print the last sync, unsynced local changes and conflict copies.
+fn cmd_sync_status() -> Result<()> {
+    use nanobot_core::service::config_sync::{self, SyncSources, SyncState};
+    use nanobot_core::session::file_store::FileSessionStore;
 
+    let cfg = config::load_config(None);
+    if cfg.sync.target.is_empty() {
+        println!("Sync is off. Set sync.target in config.json to \"chatweb\", s3://bucket/key or a WebDAV URL, then run `chatweb sync now`.");
+        return Ok(());
+    }
+    let sources = SyncSources::active();
+    let state = SyncState::load(&sources);
+    let device = sync_device_name(&cfg.sync);
+    let mut sessions = FileSessionStore::new(&cfg.workspace_path());
+    let local = config_sync::collect(&sources, &cfg.sync, &device, &mut sessions)?;
+
+    println!("Target: {}", cfg.sync.target);
+    println!("Device: {}", device);
+    match state.last_sync_at {
+        Some(at) => println!("Last sync: {} ({} items)", at.format("%Y-%m-%d %H:%M UTC"), state.hashes.len()),
+        None => println!("Last sync: never"),
+    }
+    let pending = state.pending(&local);
+    if pending.is_empty() {
+        println!("No local changes since the last sync");
+    } else {
+        println!("Changed here since the last sync:");
+        for key in &pending {
+            println!("  {}", key);
+        }
+    }
+    if !state.conflicts.is_empty() {
+        println!("Conflicts in the last sync:");
+        for (key, copy) in &state.conflicts {
+            println!("  {} (other version: {})", key, copy);
+        }
+    }
     Ok(())
 }
 
@@ -1086,7 +3077,7 @@ async fn chat_api_stream(
     message: &str,
     session_id: &str,
     auth_token: Option<&str>,
-) -> Result<()> {
+) -> Result<String> {
     use std::io::Write;
 
     let body = serde_json::json!({
@@ -1107,21 +3098,57 @@ async fn chat_api_stream(
             // SSE failed, try non-streaming fallback
             let status = r.status();
             tracing::debug!("Stream returned {}, falling back to non-stream", status);
-            return chat_api_fallback(client, fallback_url, message, session_id, auth_token).await;
+            return chat_api_fallback(client, fallback_url, message, session_id, auth_token, None, &ChatOptions::default()).await;
         }
         Err(e) if e.is_timeout() => {
             println!("\x1b[2m考えすぎちゃった...もう一回聞いてくれる？\x1b[0m");
-            return Ok(());
+            return Ok(String::new());
         }
         Err(e) => return Err(e.into()),
     };
 
     let mut buf = String::new();
+    let mut reply = String::new();
     let mut got_content = false;
     let mut printed_prefix = false;
+    // The server's preliminary draft matched the final reply, already on screen
+    let mut draft_confirmed = false;
+    // Numbered sources sent before the final content event
+    let mut citations: Vec<String> = Vec::new();
     let mut tool_spinners: HashMap<String, ProgressBar> = HashMap::new();
+    // Id of the last event handled and of the one being read, for resuming
+    let (mut last_event_id, mut event_id): (Option<String>, Option<String>) = (None, None);
+    let mut finished = false;
+    let mut resumes = 0;
 
-    while let Some(chunk) = resp.chunk().await? {
+    loop {
+        let chunk = match resp.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) if finished => break,
+            ended => {
+                // The connection dropped before `done`: pick up after the last event
+                let resumed = match &last_event_id {
+                    Some(id) if resumes < MAX_STREAM_RESUMES => {
+                        resumes += 1;
+                        tokio::time::sleep(std::time::Duration::from_millis(500 * resumes)).await;
+                        resume_stream(client, stream_url, &body, id, auth_token).await
+                    }
+                    _ => None,
+                };
+                match resumed {
+                    Some(r) => {
+                        tracing::debug!("Resumed the reply stream after {}", last_event_id.as_deref().unwrap_or_default());
+                        resp = r;
+                        buf.clear();
+                        continue;
+                    }
+                    None => {
+                        ended?;
+                        break;
+                    }
+                }
+            }
+        };
         let text = String::from_utf8_lossy(&chunk);
         buf.push_str(&text);
 
@@ -1129,10 +3156,17 @@ async fn chat_api_stream(
             let line = buf[..newline_pos].to_string();
             buf = buf[newline_pos + 1..].to_string();
 
+            if let Some(id) = line.strip_prefix("id:") {
+                event_id = Some(id.trim().to_string());
+                continue;
+            }
             if !line.starts_with("data:") {
                 continue;
             }
             let data = line[5..].trim();
+            if let Some(id) = event_id.take() {
+                last_event_id = Some(id);
+            }
             if data.is_empty() {
                 continue;
             }
@@ -1201,19 +3235,50 @@ async fn chat_api_stream(
                         }
                         let chunk_text = evt["text"].as_str().unwrap_or("");
                         print!("{}", chunk_text);
+                        reply.push_str(chunk_text);
                         std::io::stdout().flush()?;
                         got_content = true;
                     }
+                    "draft" => {
+                        let draft = evt["text"].as_str().unwrap_or("");
+                        println!("\x1b[2m⏳ {}\x1b[0m", draft);
+                    }
+                    "draft_confirmed" => draft_confirmed = true,
+                    "queued" => {
+                        let position = evt["position"].as_u64().unwrap_or(0);
+                        println!("\x1b[2m⏳ 順番待ち: {}番目\x1b[0m", position);
+                    }
+                    "citation" => {
+                        let url = evt["url"].as_str().unwrap_or("");
+                        let index = evt["index"].as_u64().unwrap_or(0);
+                        citations.push(match evt["title"].as_str() {
+                            Some(title) => format!("[{}] {} — {}", index, title, url),
+                            None => format!("[{}] {}", index, url),
+                        });
+                    }
+                    "correction" => println!("\x1b[33m✏️ 訂正\x1b[0m"),
                     "content" => {
-                        if !got_content {
+                        if !got_content && draft_confirmed {
+                            reply = evt["content"].as_str().unwrap_or("").to_string();
+                            got_content = true;
+                        } else if !got_content {
                             let content = evt["content"].as_str().unwrap_or("");
                             if !content.is_empty() {
                                 println!("\x1b[1;36m{}\x1b[0m {}", nanobot_core::LOGO, content);
+                                reply = content.to_string();
                                 got_content = true;
                             }
                         } else {
                             // Streaming already printed content, just add newline
                             println!();
+                            // The streamed text has no source list; show it as footnotes
+                            for citation in &citations {
+                                println!("\x1b[2m  {}\x1b[0m", citation);
+                            }
+                        }
+                        // Which model answered; after a provider failover it may not be the one asked for
+                        if let Some(model) = evt["model"].as_str().or_else(|| evt["model_used"].as_str()) {
+                            println!("\x1b[2m  🤖 {}\x1b[0m", model);
                         }
                         // Show credits if available - more prominent
                         if let Some(remaining) = evt["credits_remaining"].as_i64() {
@@ -1234,7 +3299,8 @@ async fn chat_api_stream(
                             println!("\x1b[33m  → Upgrade at https://chatweb.ai/pricing\x1b[0m");
                         }
                     }
-                    "start" | "done" => {}
+                    "done" => finished = true,
+                    "start" => {}
                     _ => {}
                 }
             }
@@ -1250,23 +3316,49 @@ async fn chat_api_stream(
         println!("\x1b[2mレスポンスを受信できませんでした。\x1b[0m");
     }
 
-    Ok(())
+    Ok(reply)
+}
+
+/// Reconnects after a dropped reply stream before giving up.
+const MAX_STREAM_RESUMES: u64 = 3;
+
+/// Ask the server for the rest of a reply stream after event `last_event_id`.
+async fn resume_stream(
+    client: &reqwest::Client,
+    stream_url: &str,
+    body: &serde_json::Value,
+    last_event_id: &str,
+    auth_token: Option<&str>,
+) -> Option<reqwest::Response> {
+    let mut req = client.post(stream_url).json(body).header("Last-Event-ID", last_event_id);
+    if let Some(token) = auth_token {
+        req = req.header("Authorization", format!("Bearer {}", token));
+    }
+    req.send().await.ok().filter(|r| r.status().is_success())
 }
 
-/// Non-streaming fallback for when SSE is unavailable.
+/// Non-streaming request, used when SSE is unavailable and for explicit modes and options.
 async fn chat_api_fallback(
     client: &reqwest::Client,
     api_url: &str,
     message: &str,
     session_id: &str,
     auth_token: Option<&str>,
-) -> Result<()> {
-    let body = serde_json::json!({
+    mode: Option<ChatMode>,
+    options: &ChatOptions,
+) -> Result<String> {
+    let mut body = serde_json::json!({
         "message": message,
         "session_id": session_id,
         "channel": "cli",
         "language": "ja",
     });
+    if let Some(mode) = mode {
+        body["mode"] = serde_json::json!(mode);
+    }
+    if *options != ChatOptions::default() {
+        body["options"] = serde_json::to_value(options)?;
+    }
 
     let mut req = client.post(api_url).json(&body);
     if let Some(token) = auth_token {
@@ -1277,7 +3369,7 @@ async fn chat_api_fallback(
         Ok(r) => r,
         Err(e) if e.is_timeout() => {
             println!("\x1b[2m考えすぎちゃった...もう一回聞いてくれる？\x1b[0m");
-            return Ok(());
+            return Ok(String::new());
         }
         Err(e) => return Err(e.into()),
     };
@@ -1286,11 +3378,20 @@ async fn chat_api_fallback(
     let response = body["response"].as_str().unwrap_or("No response");
     println!("\x1b[1;36m{}\x1b[0m {}", nanobot_core::LOGO, response);
 
+    if let Some(models) = body["models_consulted"].as_array().filter(|m| m.len() > 1) {
+        let names: Vec<&str> = models.iter().filter_map(|m| m.as_str()).collect();
+        match (body["mode"].as_str(), body["model_used"].as_str()) {
+            (Some("consensus"), Some(judge)) => println!("\x1b[2m  Models: {} (judge: {})\x1b[0m", names.join(", "), judge),
+            _ => println!("\x1b[2m  Models: {}\x1b[0m", names.join(", ")),
+        }
+    } else if let Some(model) = body["model_used"].as_str() {
+        println!("\x1b[2m  🤖 {}\x1b[0m", model);
+    }
     if let Some(remaining) = body["credits_remaining"].as_i64() {
         println!("\x1b[2m  Credits: {}\x1b[0m", remaining);
     }
 
-    Ok(())
+    Ok(response.to_string())
 }
 
 /// Truncate a string to max length, adding "..." if truncated.
@@ -1307,6 +3408,8 @@ fn truncate_str(s: &str, max: usize) -> String {
 async fn cmd_daemon(interval: u64, api_base: String) -> Result<()> {
     let session_id = get_cli_session_id()?;
     let client = reqwest::Client::new();
+    let auth_token = load_auth_token();
+    let termux = nanobot_core::termux::TermuxProfile::detect(&config::load_config(None).termux);
     let hostname = hostname::get()
         .map(|h| h.to_string_lossy().to_string())
         .unwrap_or_else(|_| "unknown".to_string());
@@ -1318,25 +3421,14 @@ async fn cmd_daemon(interval: u64, api_base: String) -> Result<()> {
     println!();
 
     loop {
-        let heartbeat = serde_json::json!({
-            "session_id": session_id,
-            "hostname": hostname,
-            "os": std::env::consts::OS,
-            "arch": std::env::consts::ARCH,
-            "uptime_secs": get_system_uptime(),
-        });
-
-        match client
-            .post(format!("{}/api/v1/devices/heartbeat", api_base))
-            .json(&heartbeat)
-            .send()
-            .await
-        {
-            Ok(resp) => {
-                if resp.status().is_success() {
-                    tracing::debug!("Heartbeat sent successfully");
-                } else {
-                    tracing::warn!("Heartbeat failed: {}", resp.status());
+        match send_heartbeat(&client, &api_base, &session_id).await {
+            Ok(commands) => {
+                for command in commands {
+                    // Only the kiosk acts on audio commands
+                    if command == nanobot_core::kiosk::DeviceCommand::Restart {
+                        anyhow::bail!("restart requested remotely");
+                    }
+                    tracing::warn!("Ignoring device command {:?}; it needs `chatweb kiosk`", command);
                 }
             }
             Err(e) => {
@@ -1344,46 +3436,89 @@ async fn cmd_daemon(interval: u64, api_base: String) -> Result<()> {
             }
         }
 
+        flush_offline_queue(&client, auth_token.as_deref(), termux.is_some_and(|t| t.notifications)).await;
+
         tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
     }
 }
 
-/// Get system uptime in seconds (best effort).
-fn get_system_uptime() -> u64 {
-    #[cfg(target_os = "macos")]
-    {
-        use std::process::Command;
-        if let Ok(output) = Command::new("sysctl").arg("-n").arg("kern.boottime").output() {
-            let s = String::from_utf8_lossy(&output.stdout);
-            // Parse "{ sec = 1234567890, usec = 0 } ..."
-            if let Some(sec_start) = s.find("sec = ") {
-                let rest = &s[sec_start + 6..];
-                if let Some(comma) = rest.find(',') {
-                    if let Ok(boot_sec) = rest[..comma].trim().parse::<u64>() {
-                        let now = std::time::SystemTime::now()
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .map(|d| d.as_secs())
-                            .unwrap_or(0);
-                        return now.saturating_sub(boot_sec);
-                    }
-                }
-            }
-        }
-        0
+/// Report this device to chatweb.ai; returns the remote commands queued for it.
+async fn send_heartbeat(
+    client: &reqwest::Client,
+    api_base: &str,
+    session_id: &str,
+) -> Result<Vec<nanobot_core::kiosk::DeviceCommand>> {
+    let hostname = hostname::get()
+        .map(|h| h.to_string_lossy().to_string())
+        .unwrap_or_else(|_| "unknown".to_string());
+    let heartbeat = serde_json::json!({
+        "session_id": session_id,
+        "hostname": hostname,
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "uptime_secs": nanobot_core::service::autostart::system_uptime(),
+    });
+
+    let resp = client
+        .post(format!("{}/api/v1/devices/heartbeat", api_base))
+        .json(&heartbeat)
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("heartbeat failed: {}", resp.status());
     }
-    #[cfg(target_os = "linux")]
-    {
-        std::fs::read_to_string("/proc/uptime")
-            .ok()
-            .and_then(|s| s.split_whitespace().next().map(|s| s.to_string()))
-            .and_then(|s| s.parse::<f64>().ok())
-            .map(|f| f as u64)
-            .unwrap_or(0)
-    }
-    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
-    {
-        0
+    tracing::debug!("Heartbeat sent successfully");
+    let body: serde_json::Value = resp.json().await.unwrap_or_default();
+    // Older servers send no commands
+    Ok(body["commands"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|c| serde_json::from_value(c["command"].clone()).ok())
+        .collect())
+}
+
+/// The daemon service for this platform.
+fn daemon_service(args: Vec<String>) -> Result<nanobot_core::service::autostart::DaemonService> {
+    use nanobot_core::service::autostart::{DaemonService, ServiceManager};
+
+    let Some(manager) = ServiceManager::current() else {
+        anyhow::bail!("no supported service manager on this platform");
+    };
+    Ok(DaemonService::new(manager, std::env::current_exe()?, args))
+}
+
+fn cmd_service_install(interval: u64, api: String, kiosk: bool) -> Result<()> {
+    use nanobot_core::service::autostart::ServiceManager;
+
+    // The kiosk sends heartbeats itself (interval from kiosk.heartbeatSecs)
+    let args = if kiosk {
+        vec!["kiosk".to_string(), "--api".to_string(), api]
+    } else {
+        vec!["daemon".to_string(), "--interval".to_string(), interval.to_string(), "--api".to_string(), api]
+    };
+    let service = daemon_service(args)?;
+    let location = service.install()?;
+    println!("✓ {} installed with {}: {}", if kiosk { "Kiosk" } else { "Daemon" }, service.manager.name(), location);
+    if kiosk && service.manager == ServiceManager::Systemd {
+        println!("  To start it at boot without logging in: loginctl enable-linger");
     }
+    println!("  Remove it with: chatweb service uninstall");
+    Ok(())
+}
+
+fn cmd_service_uninstall() -> Result<()> {
+    let service = daemon_service(Vec::new())?;
+    service.uninstall()?;
+    println!("✓ Daemon removed from {}", service.manager.name());
+    Ok(())
+}
+
+fn cmd_service_status() -> Result<()> {
+    let service = daemon_service(Vec::new())?;
+    println!("{} daemon ({})", nanobot_core::LOGO, service.manager.name());
+    println!("{}", service.status()?);
+    Ok(())
 }
 
 fn cmd_onboard() -> Result<()> {
@@ -1443,6 +3578,27 @@ fn create_workspace_templates(workspace: &std::path::Path) -> Result<()> {
     Ok(())
 }
 
+/// Ask on the terminal before tool calls that `tools.policy` wants confirmed.
+fn terminal_approver() -> nanobot_core::tool::policy::Approver {
+    // Parallel tool calls take turns at the prompt
+    let prompt = Arc::new(std::sync::Mutex::new(()));
+    Arc::new(move |request| {
+        let prompt = prompt.clone();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                let _turn = prompt.lock().unwrap_or_else(|e| e.into_inner());
+                eprint!("\n\u{26a0} {} ({})\n  Allow? [y/N] ", request.summary(), request.reason);
+                let _ = std::io::Write::flush(&mut std::io::stderr());
+                let mut answer = String::new();
+                std::io::stdin().read_line(&mut answer).is_ok()
+                    && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+            })
+            .await
+            .unwrap_or(false)
+        })
+    })
+}
+
 async fn cmd_agent(message: Option<String>, session_id: String) -> Result<()> {
     let cfg = config::load_config(None);
 
@@ -1473,6 +3629,9 @@ async fn cmd_agent(message: Option<String>, session_id: String) -> Result<()> {
         Some(cfg.tools.web.search.api_key.clone())
     };
 
+    nanobot_core::util::http::configure_clients(&cfg.http);
+    nanobot_core::util::http::configure_web(&cfg.tools.web.fetch, &cfg.workspace_path());
+
     let mut agent = nanobot_core::agent::AgentLoop::new(
         bus,
         llm_provider,
@@ -1483,14 +3642,23 @@ async fn cmd_agent(message: Option<String>, session_id: String) -> Result<()> {
         cfg.tools.exec_config.clone(),
         cfg.tools.restrict_to_workspace,
         None,
-    );
+    )
+    .with_timezone(cfg.agents.defaults.timezone.clone())
+    .with_response_hooks(nanobot_core::hooks::ResponseHooks::from_config(&cfg.agents.hooks))
+    .with_web_search(&cfg.tools.web.search)
+    .with_ocr(&cfg.tools.ocr)
+    .with_file_policy(&cfg.tools.files)
+    .with_tool_policy(
+        &cfg.tools.policy,
+        std::io::IsTerminal::is_terminal(&std::io::stdin()).then(terminal_approver),
+    )
+    .with_tool_stats(Arc::new(nanobot_core::tool::stats::ToolStatsStore::new(
+        nanobot_core::tool::stats::ToolStatsStore::default_path(),
+    )));
 
     if let Some(msg) = message {
         // Single message mode
-        let response = agent
-            .process_direct(&msg, &session_id, "cli", "direct")
-            .await?;
-        println!("\n{} {}", nanobot_core::LOGO, response);
+        agent_turn(&mut agent, &msg, &session_id).await?;
     } else {
         // Interactive mode
         println!(
@@ -1513,56 +3681,193 @@ async fn cmd_agent(message: Option<String>, session_id: String) -> Result<()> {
                 continue;
             }
 
-            let response = agent
-                .process_direct(input, &session_id, "cli", "direct")
-                .await?;
-            println!("\n{} {}\n", nanobot_core::LOGO, response);
+            agent_turn(&mut agent, input, &session_id).await?;
+            println!();
         }
     }
 
     Ok(())
 }
 
+/// Run one message through the local agent, printing the reply as it streams in.
+async fn agent_turn(agent: &mut nanobot_core::agent::AgentLoop, input: &str, session_id: &str) -> Result<()> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let (response, streamed) = tokio::join!(
+        agent.process_direct_stream(input, session_id, "cli", "direct", tx),
+        render_agent_stream(rx),
+    );
+    let response = response?;
+    // The final reply replaces the streamed text when they differ (an
+    // escalated answer, a handoff notice)
+    if streamed.trim() == response.trim() {
+        println!();
+    } else {
+        println!("\n{} {}", nanobot_core::LOGO, response);
+    }
+    Ok(())
+}
+
+/// Print a local agent's stream events as they arrive; returns the reply
+/// text that was printed.
+async fn render_agent_stream(mut rx: tokio::sync::mpsc::UnboundedReceiver<StreamEvent>) -> String {
+    use std::io::Write;
+    let mut streamed = String::new();
+    while let Some(event) = rx.recv().await {
+        match event {
+            StreamEvent::ContentChunk { text } => {
+                if streamed.is_empty() {
+                    print!("\n\x1b[1;36m{}\x1b[0m ", nanobot_core::LOGO);
+                }
+                print!("{}", text);
+                let _ = std::io::stdout().flush();
+                streamed.push_str(&text);
+            }
+            StreamEvent::ToolStart { tool, args_preview, .. } => {
+                println!("\x1b[2m🔧 {}({})\x1b[0m", tool, truncate_str(&args_preview, 60));
+            }
+            StreamEvent::ToolResult { tool, duration_ms, is_error, .. } => {
+                let mark = if is_error { "\x1b[31m✗" } else { "\x1b[32m✓" };
+                println!("{} {}\x1b[0m \x1b[2m{:.1}s\x1b[0m", mark, tool, duration_ms as f64 / 1000.0);
+            }
+            StreamEvent::Thinking { iteration, max_iter, .. } => {
+                println!("\x1b[2;90m  💭 考え中… ({}/{})\x1b[0m", iteration + 1, max_iter);
+            }
+            StreamEvent::Content { .. } | StreamEvent::Error { .. } | StreamEvent::Done => {}
+        }
+    }
+    streamed
+}
+
 #[allow(unused_variables)]
-async fn cmd_gateway(port: u16, verbose: bool, http: bool, http_port: u16, auth: bool) -> Result<()> {
+#[allow(clippy::too_many_arguments)]
+async fn cmd_gateway(
+    port: u16,
+    verbose: bool,
+    http: bool,
+    http_port: u16,
+    auth: bool,
+    grpc_port: Option<u16>,
+    domains: Vec<String>,
+    acme_email: Option<String>,
+    tunnel: Option<nanobot_core::service::tunnel::TunnelKind>,
+    tunnel_insecure: bool,
+) -> Result<()> {
     if verbose {
         // Re-init with debug level
         // Already handled by env filter
     }
 
-    let cfg = config::load_config_from_env();
-
-    #[cfg(feature = "http-api")]
+    let mut cfg = config::load_config_from_env();
+    if !domains.is_empty() {
+        cfg.gateway.acme.domains = domains;
+    }
+    if acme_email.is_some() {
+        cfg.gateway.acme.email = acme_email;
+    }
+    if tunnel.is_some() && !http {
+        anyhow::bail!("--tunnel needs --http");
+    }
+    // Session routes are open without a token; don't hand them to the internet by accident
+    let authenticated = auth && !cfg.gateway.api_tokens.is_empty();
+    if tunnel.is_some() && !authenticated && !tunnel_insecure {
+        anyhow::bail!(
+            "--tunnel would publish the HTTP API without authentication. \
+             Use --auth with gateway.apiTokens set, or --tunnel-insecure to publish it anyway"
+        );
+    }
+
+    #[cfg(feature = "http-api")]
     if http {
-        use nanobot_core::service::http::{serve_with_auth, AppState};
+        use nanobot_core::service::http::{serve_on, AppState};
         use nanobot_core::session::file_store::FileSessionStore;
 
+        // Kept alive until the gateway exits; dropping it stops the tunnel
+        let tunnel = match tunnel {
+            Some(kind) => {
+                if !authenticated {
+                    eprintln!();
+                    eprintln!("  WARNING: --tunnel-insecure: the whole HTTP API (sessions, memory, files, cron)");
+                    eprintln!("  will be reachable WITHOUT AUTHENTICATION by anyone who has the tunnel URL.");
+                    eprintln!();
+                }
+                println!("  Starting {} tunnel...", kind.program());
+                let tunnel = nanobot_core::service::tunnel::Tunnel::start(kind, http_port).await?;
+                cfg.gateway.public_url = Some(tunnel.url.clone());
+                Some(tunnel)
+            }
+            None => None,
+        };
+
         let workspace = cfg.workspace_path();
         let state = std::sync::Arc::new(AppState::with_provider(
             cfg.clone(),
             Box::new(FileSessionStore::new(&workspace)),
         ));
 
-        let addr = format!("0.0.0.0:{}", http_port);
+        let addrs = cfg.gateway.listen_addrs(http_port)?;
         println!(
             "{} Starting chatweb HTTP API on {}...",
-            nanobot_core::LOGO, addr
+            nanobot_core::LOGO,
+            addrs.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ")
         );
+        if cfg.gateway.acme.enabled() {
+            println!("  HTTPS: Let's Encrypt for {}", cfg.gateway.acme.domains.join(", "));
+            if http_port != 443 {
+                println!("  Note: certificates are only issued when this is reachable on port 443");
+            }
+        }
         if auth {
             println!("  Authentication: ENABLED");
         } else {
             println!("  Authentication: disabled (use --auth to enable)");
         }
+        if cfg.gateway.webui.enabled {
+            #[cfg(feature = "webui")]
+            println!("  Web chat: http://localhost:{}/", http_port);
+            #[cfg(not(feature = "webui"))]
+            eprintln!("  Web chat (gateway.webui) not available. Rebuild with: cargo build --features webui");
+        }
+
+        // gRPC API shares the HTTP server's state
+        #[cfg(feature = "grpc")]
+        if let Some(grpc_port) = grpc_port {
+            let grpc_state = state.clone();
+            let grpc_addr = format!("0.0.0.0:{}", grpc_port);
+            println!("  gRPC API: {}", grpc_addr);
+            tokio::spawn(async move {
+                if let Err(e) = nanobot_core::service::grpc::serve(&grpc_addr, grpc_state, auth).await {
+                    eprintln!("gRPC server error: {}", e);
+                }
+            });
+        }
+        #[cfg(not(feature = "grpc"))]
+        if grpc_port.is_some() {
+            eprintln!("gRPC API not available. Rebuild with: cargo build --features grpc");
+            std::process::exit(1);
+        }
 
         // Run HTTP server and gateway concurrently
         let http_handle = tokio::spawn(async move {
-            if let Err(e) = serve_with_auth(&addr, state, auth).await {
+            if let Err(e) = serve_on(&addrs, state, auth).await {
                 eprintln!("HTTP server error: {}", e);
             }
         });
 
+        // With a public URL, Telegram updates arrive on the webhook, and
+        // long polling would conflict with it.
+        let public_url = cfg.gateway.public_base_url();
+        let mut registry = nanobot_core::channel::registry::ChannelRegistry::with_builtins();
+        if let Some(tunnel) = &tunnel {
+            println!("  Tunnel: {} ({})", tunnel.url, tunnel.kind.program());
+        }
+        if let Some(base) = &public_url {
+            println!("  Webhooks: {}/webhooks/...", base);
+            registry.unregister("telegram");
+        }
+        let unregister = (public_url.is_some() && cfg.gateway.unregister_webhooks).then(|| cfg.channels.clone());
+
         let gateway_handle = tokio::spawn(async move {
-            if let Err(e) = nanobot_core::service::gateway::run_gateway(cfg).await {
+            if let Err(e) = nanobot_core::service::gateway::run_gateway_with(cfg, registry).await {
                 eprintln!("Gateway error: {}", e);
             }
         });
@@ -1570,6 +3875,11 @@ async fn cmd_gateway(port: u16, verbose: bool, http: bool, http_port: u16, auth:
         tokio::select! {
             _ = http_handle => {},
             _ = gateway_handle => {},
+            _ = tokio::signal::ctrl_c() => {
+                if let Some(channels) = unregister {
+                    nanobot_core::channel::webhooks::unregister_all(&channels).await;
+                }
+            },
         }
         return Ok(());
     }
@@ -1606,6 +3916,9 @@ fn cmd_status() -> Result<()> {
         workspace.display(),
         if workspace.exists() { "✓" } else { "✗" }
     );
+    if let Some(termux) = TermuxProfile::detect(&cfg.termux) {
+        println!("Termux: ✓ {}", termux.summary());
+    }
 
     if config_exists {
         println!("Model: {}", cfg.agents.defaults.model);
@@ -1652,6 +3965,37 @@ fn cmd_status() -> Result<()> {
     Ok(())
 }
 
+async fn cmd_mcp_serve(groups: Vec<String>, http: Option<String>, cron_to: String) -> Result<()> {
+    use nanobot_core::mcp::server::{standard_tools, McpServer};
+
+    let cfg = config::load_config(None);
+    nanobot_core::util::http::configure_clients(&cfg.http);
+    nanobot_core::util::http::configure_web(&cfg.tools.web.fetch, &cfg.workspace_path());
+    let tools = standard_tools(&cfg, &groups, &cron_to).await?;
+    let server = Arc::new(McpServer::new(Arc::new(tools)));
+
+    let Some(addr) = http else {
+        server.serve_stdio().await?;
+        return Ok(());
+    };
+    #[cfg(feature = "http-api")]
+    {
+        println!("{} MCP server on http://{}/mcp (SSE: /sse)", nanobot_core::LOGO, addr);
+        println!("  Tools: {}", groups.join(", "));
+        if cfg.gateway.api_tokens.is_empty() {
+            println!("  Authentication: disabled, loopback only (add gateway.apiTokens to require a token)");
+        } else {
+            println!("  Authentication: gateway.apiTokens");
+        }
+        nanobot_core::mcp::server::serve_http(server, &addr, cfg.gateway.api_tokens.clone()).await
+    }
+    #[cfg(not(feature = "http-api"))]
+    {
+        let _ = (server, addr);
+        anyhow::bail!("--http needs the HTTP API. Rebuild with: cargo build --features http-api")
+    }
+}
+
 fn cmd_gen_token() {
     let token = uuid::Uuid::new_v4().to_string();
     println!("{} Generated Gateway API Token:\n", nanobot_core::LOGO);
@@ -1664,68 +4008,81 @@ fn cmd_gen_token() {
     println!("  GATEWAY_API_TOKENS=\"{}\" chatweb gateway --http --auth", token);
 }
 
-fn cmd_channels_status() -> Result<()> {
-    let cfg = config::load_config(None);
+/// Exit code 1 when an enabled channel fails a probe or lacks credentials.
+fn cmd_config_validate(path: Option<std::path::PathBuf>, strict: bool, json: bool) -> Result<()> {
+    let path = path.unwrap_or_else(config::get_config_path);
+    let content = std::fs::read_to_string(&path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+    let report = config::validate::validate_str(&content, strict || config::strict_from_env());
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", report.render(&path.display().to_string()));
+    }
 
-    println!("Channel Status\n");
-    println!(
-        "  WhatsApp:  {} ({})",
-        if cfg.channels.whatsapp.enabled {
-            "✓"
-        } else {
-            "✗"
-        },
-        cfg.channels.whatsapp.bridge_url
-    );
-    println!(
-        "  Telegram:  {} ({})",
-        if cfg.channels.telegram.enabled {
-            "✓"
-        } else {
-            "✗"
-        },
-        if cfg.channels.telegram.token.is_empty() {
-            "not configured"
-        } else {
-            "configured"
-        }
-    );
-    println!(
-        "  Discord:   {} ({})",
-        if cfg.channels.discord.enabled {
-            "✓"
-        } else {
-            "✗"
-        },
-        cfg.channels.discord.gateway_url
-    );
-    println!(
-        "  Feishu:    {} ({})",
-        if cfg.channels.feishu.enabled {
-            "✓"
-        } else {
-            "✗"
-        },
-        if cfg.channels.feishu.app_id.is_empty() {
-            "not configured"
-        } else {
-            "configured"
-        }
-    );
-    println!(
-        "  LINE:      {} ({})",
-        if cfg.channels.line.enabled {
-            "✓"
-        } else {
-            "✗"
-        },
-        if cfg.channels.line.channel_access_token.is_empty() {
-            "not configured"
-        } else {
-            "configured"
-        }
-    );
+    if !report.is_ok() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn cmd_usage(tools: bool, days: u32, session: Option<String>, json: bool) -> Result<()> {
+    use nanobot_core::tool::stats::{self, ToolStatsStore};
+
+    let store = ToolStatsStore::new(ToolStatsStore::default_path());
+    let rows = store.summary(session.as_deref(), days);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+    } else if tools {
+        println!("Tool usage, last {} day(s){}:\n", days, session.map(|s| format!(" for {}", s)).unwrap_or_default());
+        print!("{}", stats::render(&rows));
+    } else {
+        let calls: u64 = rows.iter().map(|r| r.stats.calls).sum();
+        let failures: u64 = rows.iter().map(|r| r.stats.failures).sum();
+        let cost: f64 = rows.iter().map(|r| r.stats.downstream_cost_usd).sum();
+        println!("Last {} day(s): {} tool calls ({} failed), ${:.4} in result tokens", days, calls, failures, cost);
+        println!("Run `chatweb usage --tools` for the breakdown per tool.");
+    }
+    Ok(())
+}
+
+fn cmd_profile_list() {
+    let active = config::profile::active();
+    for name in config::profile::list() {
+        let mark = if name == active { "*" } else { " " };
+        println!("{} {:<20} {}", mark, name, config::profile::dir(&name).display());
+    }
+}
 
+fn cmd_profile_create(name: String, from: Option<String>) -> Result<()> {
+    let dir = config::profile::create(&name, from.as_deref())?;
+    println!("Created profile '{}' in {}", name, dir.display());
+    println!("Use it with `chatweb --profile {} ...` or make it the default with `chatweb profile switch {}`.", name, name);
+    Ok(())
+}
+
+fn cmd_profile_switch(name: String) -> Result<()> {
+    config::profile::switch(&name)?;
+    println!("Switched to profile '{}' ({})", name, config::profile::dir(&name).display());
+    Ok(())
+}
+
+async fn cmd_channels_status(json: bool) -> Result<()> {
+    use nanobot_core::channel::health::{self, ChannelState};
+
+    let cfg = config::load_config(None);
+    let reports = health::probe_all(&cfg.channels).await;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        print!("{}", health::render(&reports));
+    }
+
+    if reports
+        .iter()
+        .any(|r| matches!(r.state, ChannelState::Failing | ChannelState::Unconfigured))
+    {
+        std::process::exit(1);
+    }
     Ok(())
 }
 
@@ -1753,6 +4110,23 @@ async fn cmd_earn(model: String, api_base: String) -> Result<()> {
     println!("  Hostname: {}", hostname);
     println!("  API: {}", api_base);
     println!();
+
+    // Load the model before taking work so the first request isn't slow
+    #[cfg(feature = "local-fallback")]
+    let local = match nanobot_core::provider::local::LocalProvider::for_model(&model) {
+        Some(local) => {
+            println!("Loading {}...", model);
+            let took = local.warm_up().await?;
+            println!("  Loaded in {:.1}s", took.as_secs_f64());
+            nanobot_core::provider::local::LocalProvider::spawn_keep_alive();
+            Some(local)
+        }
+        None => {
+            println!("  No local model configured for {} (LOCAL_MODEL_URL); answering with placeholders", model);
+            None
+        }
+    };
+
     println!("Registering worker...");
 
     // Register worker
@@ -1791,9 +4165,30 @@ async fn cmd_earn(model: String, api_base: String) -> Result<()> {
                     let prompt = body["prompt"].as_str().unwrap_or("");
                     println!("  Request: {} ({} chars)", request_id, prompt.len());
 
-                    // For now, return a placeholder — actual inference via candle
-                    // would go here when local-fallback feature is enabled
-                    let result = format!("Worker {} processed request (model: {})", worker_id, model);
+                    let placeholder = format!("Worker {} processed request (model: {})", worker_id, model);
+                    // A failed inference is skipped, not answered, and earns nothing
+                    #[cfg(feature = "local-fallback")]
+                    let result = match &local {
+                        Some(local) => {
+                            use nanobot_core::provider::LlmProvider;
+                            let messages = [nanobot_core::types::Message::user(prompt)];
+                            let started = std::time::Instant::now();
+                            match local.chat(&messages, None, local.name(), 512, 0.7).await {
+                                Ok(resp) => {
+                                    println!("  Answered in {:.1}s", started.elapsed().as_secs_f64());
+                                    Some(resp.content.unwrap_or_default())
+                                }
+                                Err(e) => {
+                                    println!("  Skipped: local inference failed: {}", e);
+                                    None
+                                }
+                            }
+                        }
+                        None => Some(placeholder),
+                    };
+                    #[cfg(not(feature = "local-fallback"))]
+                    let result = Some(placeholder);
+                    let Some(result) = result else { continue };
 
                     match client
                         .post(format!("{}/api/v1/workers/result", api_base))
@@ -1805,6 +4200,9 @@ async fn cmd_earn(model: String, api_base: String) -> Result<()> {
                         .send()
                         .await
                     {
+                        Ok(r) if !r.status().is_success() => {
+                            tracing::warn!("Result submission rejected: {}", r.status());
+                        }
                         Ok(r) => {
                             let d: serde_json::Value = r.json().await.unwrap_or_default();
                             let earned = d["credits_earned"].as_u64().unwrap_or(credits_per_req as u64);
@@ -1897,3 +4295,1061 @@ fn cmd_cron_remove(job_id: String) -> Result<()> {
 
     Ok(())
 }
+
+/// The gateway's cron store, read without recomputing next runs.
+fn upcoming_store() -> nanobot_core::service::cron::CronService {
+    nanobot_core::service::cron::CronService::open(config::get_data_dir().join("cron").join("jobs.json"))
+}
+
+/// The configured timezone (UTC when unset).
+fn upcoming_timezone() -> String {
+    config::load_config_from_env().agents.defaults.timezone.unwrap_or_else(|| "UTC".to_string())
+}
+
+fn cmd_upcoming_list() -> Result<()> {
+    let items = nanobot_core::service::upcoming::list(&upcoming_store(), None);
+    if items.is_empty() {
+        println!("Nothing scheduled.");
+        return Ok(());
+    }
+    let tz = upcoming_timezone();
+    let zone = nanobot_core::util::tz::parse_or_utc(&tz);
+    println!("Upcoming ({})\n", tz);
+    for item in &items {
+        let mut line = item.line(zone, Locale::En);
+        if let (Some(channel), Some(chat_id)) = (&item.channel, &item.chat_id) {
+            line.push_str(&format!("  → {}:{}", channel, chat_id));
+        }
+        println!("  {}", line);
+    }
+    println!("\nCancel: chatweb upcoming cancel <ID> · Move: chatweb upcoming move <ID> <when>");
+    Ok(())
+}
+
+fn cmd_upcoming_cancel(id: String) -> Result<()> {
+    nanobot_core::service::upcoming::cancel(&mut upcoming_store(), None, &id)?;
+    println!("✓ Cancelled {}", id);
+    Ok(())
+}
+
+fn cmd_upcoming_move(id: String, when: String) -> Result<()> {
+    let tz = upcoming_timezone();
+    let item = nanobot_core::service::upcoming::reschedule(&mut upcoming_store(), None, &id, &when, &tz)?;
+    println!("✓ Moved: {}", item.line(nanobot_core::util::tz::parse_or_utc(&tz), Locale::En));
+    Ok(())
+}
+
+fn routing_table(table: Option<String>) -> Result<String> {
+    table
+        .or_else(|| std::env::var("DYNAMODB_CONFIG_TABLE").ok())
+        .ok_or_else(|| anyhow::anyhow!("No table: pass --table or set DYNAMODB_CONFIG_TABLE"))
+}
+
+async fn cmd_routing_export(
+    out: std::path::PathBuf,
+    days: u32,
+    table: Option<String>,
+    raw: bool,
+    anonymize: AnonymizeArgs,
+) -> Result<()> {
+    use nanobot_core::service::router;
+
+    let table = routing_table(table)?;
+    let retention = config::load_config(None).agents.routing.log_retention_days;
+    if days > retention {
+        println!("  Entries are kept {} days (agents.routing.logRetentionDays); exporting those", retention);
+    }
+    let days = days.min(retention);
+    if raw {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(&out)?);
+        let count = router::export(&table, days, &mut file).await?;
+        std::io::Write::flush(&mut file)?;
+        println!("✓ Exported {} raw routing entries to {}", count, out.display());
+        println!("  They include session hashes; run `chatweb routing anonymize` before sharing them");
+        return Ok(());
+    }
+
+    let mut buffer = Vec::new();
+    router::export(&table, days, &mut buffer).await?;
+    let entries = parse_routing_entries(&buffer);
+    write_routing_dataset(&entries, &out, &anonymize)
+}
+
+fn cmd_routing_anonymize(input: std::path::PathBuf, out: std::path::PathBuf, anonymize: AnonymizeArgs) -> Result<()> {
+    let data = std::fs::read(&input).map_err(|e| anyhow::anyhow!("{}: {}", input.display(), e))?;
+    write_routing_dataset(&parse_routing_entries(&data), &out, &anonymize)
+}
+
+fn parse_routing_entries(jsonl: &[u8]) -> Vec<serde_json::Value> {
+    String::from_utf8_lossy(jsonl).lines().filter_map(|line| serde_json::from_str(line).ok()).collect()
+}
+
+fn write_routing_dataset(entries: &[serde_json::Value], out: &std::path::Path, anonymize: &AnonymizeArgs) -> Result<()> {
+    use nanobot_core::service::routing_dataset;
+    use std::io::Write;
+
+    let dataset = routing_dataset::anonymize(entries, &anonymize.options());
+    let mut file = std::io::BufWriter::new(std::fs::File::create(out)?);
+    for row in &dataset.rows {
+        writeln!(file, "{}", serde_json::to_string(row)?)?;
+    }
+    file.flush()?;
+    let r = &dataset.report;
+    println!("✓ Wrote {} anonymized examples ({} rows) to {}", r.released, dataset.rows.len(), out.display());
+    println!(
+        "  Read {}: {} unusable, {} over the per-session cap, {} generalized, {} suppressed; {} rare features removed",
+        r.read, r.unusable, r.over_cap, r.generalized, r.suppressed, r.rare_features
+    );
+    if let Some(path) = &anonymize.aggregates {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        for row in &dataset.aggregates {
+            writeln!(file, "{}", serde_json::to_string(row)?)?;
+        }
+        file.flush()?;
+        println!("✓ Wrote {} noisy counts (ε={}) to {}", dataset.aggregates.len(), anonymize.epsilon, path.display());
+    }
+
+    Ok(())
+}
+
+async fn cmd_routing_purge(table: Option<String>, lookback: u32) -> Result<()> {
+    let table = routing_table(table)?;
+    let retention = config::load_config(None).agents.routing.log_retention_days;
+    let deleted = nanobot_core::service::router::purge(&table, retention, lookback).await?;
+    println!("✓ Deleted {} routing entries older than {} days", deleted, retention);
+
+    Ok(())
+}
+
+fn cmd_routing_train(
+    input: std::path::PathBuf,
+    out: Option<std::path::PathBuf>,
+    epochs: usize,
+    holdout: f64,
+) -> Result<()> {
+    use nanobot_core::service::router::{self, RoutingModel, TrainOptions};
+
+    let examples = router::read_examples(&input)?;
+    if examples.is_empty() {
+        anyhow::bail!("No usable entries in {} (entries need route_features)", input.display());
+    }
+    // Every n-th example is held back, so the split is the same on every run
+    let every = match holdout.clamp(0.0, 0.5) {
+        h if h > 0.0 => (1.0 / h).round() as usize,
+        _ => 0,
+    };
+    let (mut train_set, mut test_set) = (Vec::new(), Vec::new());
+    for (i, example) in examples.into_iter().enumerate() {
+        if every > 0 && i % every == every - 1 {
+            test_set.push(example);
+        } else {
+            train_set.push(example);
+        }
+    }
+
+    let options = TrainOptions { epochs, ..Default::default() };
+    let model = router::train(&train_set, &options)?;
+    let path = out.unwrap_or_else(RoutingModel::default_path);
+    model.save(&path)?;
+    println!("✓ Trained on {} examples ({} agents): {}", train_set.len(), model.labels.len(), path.display());
+    if !test_set.is_empty() {
+        let eval = router::evaluate(&model, &test_set);
+        println!("  Held-out agreement: {:.1}% of {}", eval.agreement() * 100.0, eval.total);
+    }
+    println!("  Set agents.routing.mode to \"shadow\" to compare it with keyword routing live");
+
+    Ok(())
+}
+
+fn cmd_routing_eval(input: std::path::PathBuf, model: Option<std::path::PathBuf>) -> Result<()> {
+    use nanobot_core::service::router::{self, RoutingModel};
+
+    let path = model.unwrap_or_else(RoutingModel::default_path);
+    let model = RoutingModel::load(&path)?;
+    let examples = router::read_examples(&input)?;
+    let eval = router::evaluate(&model, &examples);
+    println!("Agreement: {:.1}% of {} ({} trained {})", eval.agreement() * 100.0, eval.total, path.display(), model.trained_at);
+    for (label, predicted) in &eval.confusion {
+        let row: Vec<String> = predicted.iter().map(|(agent, n)| format!("{}={}", agent, n)).collect();
+        println!("  {:<12} → {}", label, row.join(" "));
+    }
+
+    Ok(())
+}
+
+async fn cmd_memory_consolidate(model: Option<String>, days: Option<u32>, dry_run: bool) -> Result<()> {
+    use nanobot_core::memory::consolidate::Consolidator;
+    use nanobot_core::memory::MemoryStore;
+
+    let mut cfg = config::load_config(None);
+    if let Some(days) = days {
+        cfg.agents.memory.days = days;
+    }
+    let mut consolidator = Consolidator::from_config(&cfg.agents.memory);
+    if let Some(model) = model {
+        consolidator = consolidator.with_model(model);
+    }
+    let chat_model = cfg.agents.defaults.model.clone();
+    let api_key = cfg.get_api_key(None).unwrap_or("").to_string();
+    let api_base = cfg.get_api_base(None).map(|s| s.to_string());
+    let chat: Arc<dyn provider::LlmProvider> = Arc::from(provider::create_provider(&api_key, api_base.as_deref(), &chat_model));
+    let (llm_provider, model) = consolidator.provider_for(&cfg, chat)?;
+
+    let memory = MemoryStore::new(&cfg.workspace_path()).with_timezone(cfg.agents.defaults.timezone.clone());
+    match consolidator.run(llm_provider.as_ref(), &model, &memory, dry_run).await? {
+        Some(merged) if dry_run => println!("{}", merged),
+        Some(_) => println!("✓ Long-term memory consolidated with {}", model),
+        None => println!("No daily notes in the last {} days", consolidator.days()),
+    }
+
+    Ok(())
+}
+
+/// Run `extract` over each input and print `{"input", "data"|"error"}` lines.
+async fn cmd_extract(
+    schema: std::path::PathBuf,
+    inputs: Vec<String>,
+    instructions: Option<String>,
+    model: Option<String>,
+) -> Result<()> {
+    use nanobot_core::tool::extract;
+
+    let schema: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&schema)?)?;
+    let cfg = config::load_config(None);
+    let model = model.unwrap_or_else(|| cfg.agents.defaults.model.clone());
+    let api_key = cfg.get_api_key(None).unwrap_or("").to_string();
+    let api_base = cfg.get_api_base(None).map(|s| s.to_string());
+    let llm_provider = provider::create_provider(&api_key, api_base.as_deref(), &model);
+
+    let inputs = if inputs.is_empty() { vec!["-".to_string()] } else { inputs };
+    let mut failed = 0;
+    for input in &inputs {
+        let text = if input == "-" {
+            let mut buf = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut buf).map(|_| buf).map_err(|e| e.to_string())
+        } else if input.starts_with("http://") || input.starts_with("https://") {
+            extract::fetch_text(input).await
+        } else {
+            std::fs::read_to_string(input).map_err(|e| e.to_string())
+        };
+        let result = match text {
+            Ok(text) => extract::extract(
+                llm_provider.as_ref(), &model, &text, &schema, instructions.as_deref(), extract::DEFAULT_ATTEMPTS,
+            ).await.map_err(|e| e.to_string()),
+            Err(e) => Err(e),
+        };
+        let line = match result {
+            Ok(data) => serde_json::json!({"input": input, "data": data}),
+            Err(e) => {
+                failed += 1;
+                serde_json::json!({"input": input, "error": e})
+            }
+        };
+        println!("{}", line);
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} inputs failed", failed, inputs.len());
+    }
+    Ok(())
+}
+
+async fn cmd_backup_create(to: Option<String>, include_secrets: bool) -> Result<()> {
+    use nanobot_core::service::backup::{self, BackupPlan, BackupSources};
+
+    let cfg = config::load_config(None);
+    let plan = BackupPlan { to, include_secrets, keep: 0 };
+    let dest = backup::run_plan(BackupSources::from_config(&cfg), &plan).await?;
+    println!("✓ Backup created: {}", dest);
+    if !include_secrets {
+        println!("  API keys and tokens were excluded (use --include-secrets to keep them)");
+    }
+
+    Ok(())
+}
+
+async fn cmd_backup_restore(from: String, verify_only: bool) -> Result<()> {
+    use nanobot_core::service::backup::{self, BackupSources, Location};
+
+    let archive = match Location::parse(&from) {
+        Location::Local(path) => path,
+        Location::S3 { bucket, key } => {
+            let name = key.rsplit('/').next().unwrap_or("download.tar.gz");
+            let path = backup::default_dir().join("downloads").join(name);
+            backup::download(&bucket, &key, &path).await?;
+            path
+        }
+    };
+
+    let manifest = backup::verify(&archive)?;
+    println!(
+        "✓ Verified {} ({} files, {} bytes, created {} by v{})",
+        archive.display(),
+        manifest.files.len(),
+        manifest.total_size(),
+        manifest.created_at,
+        manifest.version
+    );
+    if verify_only {
+        return Ok(());
+    }
+
+    // Snapshot the current state first so a bad restore can be undone
+    let cfg = config::load_config(None);
+    let sources = BackupSources::from_config(&cfg);
+    let safety = backup::local_archive_path(&backup::default_dir());
+    backup::create(&sources, &safety, true)?;
+    println!("  Current state saved to {}", safety.display());
+
+    let manifest = backup::restore(&archive, &sources)?;
+    println!("✓ Restored {} files", manifest.files.len());
+
+    Ok(())
+}
+
+fn workflow_engine() -> nanobot_core::workflow::WorkflowEngine {
+    let cfg = config::load_config(None);
+    nanobot_core::workflow::WorkflowEngine::for_workspace(&cfg.workspace_path())
+}
+
+/// Host for `--local` runs: an agent built from the config, printing channel sends.
+fn workflow_local_host() -> Result<nanobot_core::workflow::AgentHost> {
+    let cfg = config::load_config(None);
+    let agent = nanobot_core::agent::builder::AgentBuilder::from_config(&cfg)?.build()?;
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<nanobot_core::types::OutboundMessage>(16);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            println!("  → {}:{} {}", msg.channel, msg.chat_id, msg.content);
+        }
+    });
+    Ok(nanobot_core::workflow::AgentHost::new(agent, tx))
+}
+
+fn print_workflow_run(run: &nanobot_core::workflow::WorkflowRun) {
+    println!("Run {} ({}): {:?}", run.id, run.workflow, run.status);
+    for step in &run.steps {
+        let mark = if step.ok { "✓" } else { "✗" };
+        let detail = step.error.as_deref().or(step.output.as_deref()).unwrap_or("");
+        println!("  {} {:<12} {:<9} {}", mark, step.id, step.kind, detail.lines().next().unwrap_or(""));
+    }
+    if let Some(ref output) = run.output {
+        println!("\n{}", output);
+    }
+    if let Some(ref error) = run.error {
+        println!("Error: {}", error);
+    }
+}
+
+fn cmd_workflow_list() -> Result<()> {
+    let engine = workflow_engine();
+    let (workflows, errors) = engine.load_all();
+    if workflows.is_empty() && errors.is_empty() {
+        println!("No workflows in {}", engine.dir().display());
+        return Ok(());
+    }
+
+    println!("Workflows\n");
+    println!("  {:<20} {:<6} {:<24} Description", "Name", "Steps", "Triggers");
+    println!("  {}", "-".repeat(70));
+    for w in &workflows {
+        let mut triggers = Vec::new();
+        if let Some(ref cron) = w.trigger.cron {
+            triggers.push(format!("cron({})", cron));
+        }
+        if w.trigger.webhook {
+            triggers.push("webhook".to_string());
+        }
+        if w.trigger.command {
+            triggers.push("command".to_string());
+        }
+        println!("  {:<20} {:<6} {:<24} {}", w.name, w.steps.len(), triggers.join(","), w.description);
+    }
+    for (path, error) in &errors {
+        println!("  ✗ {}: {}", path.display(), error);
+    }
+
+    Ok(())
+}
+
+async fn cmd_workflow_run(name: String, input: Option<String>, local: bool) -> Result<()> {
+    use nanobot_core::workflow::TRIGGER_MANUAL;
+
+    let engine = workflow_engine();
+    let input = input.map(serde_json::Value::String).unwrap_or_default();
+    if !local {
+        let run = engine.enqueue(&name, TRIGGER_MANUAL, input, None)?;
+        println!("✓ Queued run {} of '{}' (the gateway will execute it)", run.id, run.workflow);
+        return Ok(());
+    }
+    let host = workflow_local_host()?;
+    let run = engine.start(&host, &name, TRIGGER_MANUAL, input).await?;
+    print_workflow_run(&run);
+
+    Ok(())
+}
+
+fn cmd_workflow_runs(name: Option<String>, status: Option<String>, limit: usize) -> Result<()> {
+    use nanobot_core::workflow::RunStatus;
+
+    let status = match status {
+        Some(s) => Some(RunStatus::parse(&s).ok_or_else(|| anyhow::anyhow!("unknown status '{}'", s))?),
+        None => None,
+    };
+    let runs = workflow_engine().runs(name.as_deref(), status, limit);
+    if runs.is_empty() {
+        println!("No workflow runs.");
+        return Ok(());
+    }
+
+    println!("  {:<10} {:<20} {:<10} {:<10} {:<6} Error", "ID", "Workflow", "Trigger", "Status", "Step");
+    println!("  {}", "-".repeat(70));
+    for run in &runs {
+        println!(
+            "  {:<10} {:<20} {:<10} {:<10} {:<6} {}",
+            run.id,
+            run.workflow,
+            run.trigger,
+            format!("{:?}", run.status).to_lowercase(),
+            run.next_step + 1,
+            run.error.as_deref().unwrap_or("")
+        );
+    }
+
+    Ok(())
+}
+
+async fn cmd_workflow_resume(run_id: String, local: bool) -> Result<()> {
+    let engine = workflow_engine();
+    if !local {
+        match engine.requeue(&run_id) {
+            Some(run) => println!("✓ Run {} queued to resume at step {}", run.id, run.next_step + 1),
+            None => println!("No failed or waiting run with id {}", run_id),
+        }
+        return Ok(());
+    }
+    let host = workflow_local_host()?;
+    let run = engine.resume(&host, &run_id).await?;
+    print_workflow_run(&run);
+
+    Ok(())
+}
+
+fn speaker_registry() -> nanobot_core::voice::SpeakerRegistry {
+    let voice = config::load_config(None).voice;
+    if !voice.speaker_id {
+        println!("\x1b[2mSpeaker identification is off; set voice.speakerId in config.json to use these profiles.\x1b[0m");
+    }
+    nanobot_core::voice::SpeakerRegistry::new(
+        nanobot_core::voice::SpeakerRegistry::default_path(),
+        voice.speaker_threshold,
+    )
+}
+
+fn cmd_speakers_list() {
+    let speakers = speaker_registry().list();
+    if speakers.is_empty() {
+        println!("No speakers enrolled. Add one with `chatweb speakers enroll <name>`.");
+        return;
+    }
+    println!("Speakers\n");
+    println!("  {:<20} {:<18} {:<12} Notes", "Name", "ID", "Enrollments");
+    println!("  {}", "-".repeat(60));
+    for p in &speakers {
+        println!("  {:<20} {:<18} {:<12} {}", p.name, p.id, p.enrollments, p.notes.len());
+    }
+}
+
+fn cmd_speakers_enroll(name: String, utterances: u32) -> Result<()> {
+    use nanobot_core::voice::{self, speaker, Vad, VadConfig, VadEvent};
+    use std::io::Read;
+
+    let mut registry = speaker_registry();
+    let voice_config = config::load_config(None).voice;
+    let mut vad = Vad::new(VadConfig {
+        sample_rate: CAPTURE_RATE,
+        aggressiveness: voice_config.vad_aggressiveness,
+        silence_ms: voice_config.silence_ms,
+        ..Default::default()
+    });
+    let mut recorder = spawn_recorder()?;
+    let mut mic = recorder.stdout.take().ok_or_else(|| anyhow::anyhow!("recorder has no output"))?;
+
+    println!("Enrolling {}. Say {} sentences of a few seconds each, pausing between them.", name, utterances);
+    println!("Only a voiceprint is kept, never the recording.\n");
+    let mut enrolled = 0;
+    let mut buf = vec![0u8; (CAPTURE_RATE / 50 * 2) as usize];
+    while enrolled < utterances {
+        let n = mic.read(&mut buf)?;
+        if n == 0 {
+            anyhow::bail!("the audio recorder stopped");
+        }
+        for event in vad.push(&voice::pcm_from_bytes(&buf[..n])) {
+            let VadEvent::SpeechEnd(audio) = event else { continue };
+            match speaker::voiceprint(&audio, CAPTURE_RATE) {
+                Some(print) => {
+                    enrolled += 1;
+                    registry.enroll(&name, &print);
+                    println!("  ✓ {}/{}", enrolled, utterances);
+                }
+                None => println!("  ✗ Too short; try a longer sentence"),
+            }
+        }
+    }
+    let _ = recorder.kill();
+    let _ = recorder.wait();
+    println!("\n✓ Enrolled {}", name);
+    Ok(())
+}
+
+fn cmd_speakers_rename(speaker: String, name: String) {
+    if speaker_registry().rename(&speaker, &name) {
+        println!("✓ Renamed {} to {}", speaker, name);
+    } else {
+        println!("No speaker {}", speaker);
+    }
+}
+
+fn cmd_speakers_remember(speaker: String, note: String) {
+    if speaker_registry().remember(&speaker, &note) {
+        println!("✓ Noted for {}", speaker);
+    } else {
+        println!("No speaker {}", speaker);
+    }
+}
+
+fn cmd_speakers_notes(speaker: String, clear: bool) {
+    let mut registry = speaker_registry();
+    if clear {
+        if registry.clear_notes(&speaker) {
+            println!("✓ Cleared notes for {}", speaker);
+        } else {
+            println!("No speaker {}", speaker);
+        }
+        return;
+    }
+    match registry.find(&speaker) {
+        Some(p) if p.notes.is_empty() => println!("No notes for {}", p.name),
+        Some(p) => {
+            for note in &p.notes {
+                println!("- {}", note);
+            }
+        }
+        None => println!("No speaker {}", speaker),
+    }
+}
+
+fn cmd_speakers_forget(speaker: Option<String>, all: bool) {
+    let mut registry = speaker_registry();
+    if all {
+        println!("✓ Deleted {} speaker(s)", registry.forget_all());
+        return;
+    }
+    let speaker = speaker.unwrap_or_default();
+    if registry.forget(&speaker) {
+        println!("✓ Deleted {}'s voiceprint and notes", speaker);
+    } else {
+        println!("No speaker {}", speaker);
+    }
+}
+
+/// Exit codes for `review`: 0 ok, 1 a comment reached --fail-on, 2 the review could not be produced.
+async fn cmd_review(
+    range: Option<String>,
+    pr: Option<String>,
+    post: bool,
+    json: bool,
+    fail_on: Option<String>,
+) -> Result<()> {
+    use nanobot_core::review::{self, PullRequestRef, Severity};
+
+    let threshold = match fail_on.as_deref() {
+        Some(s) => Some(Severity::parse(s).ok_or_else(|| anyhow::anyhow!("unknown severity: {}", s))?),
+        None => None,
+    };
+    let token = std::env::var("GITHUB_TOKEN").ok().filter(|t| !t.is_empty());
+    let pr = pr.as_deref().map(PullRequestRef::parse).transpose()?;
+    let (diff, context) = match pr {
+        Some(ref pr) => (
+            review::fetch_pr_diff(pr, token.as_deref()).await?,
+            Some(format!("Pull request {}/{}#{}", pr.owner, pr.repo, pr.number)),
+        ),
+        None => (review::git_diff(&std::env::current_dir()?, range.as_deref())?, range.clone()),
+    };
+    if diff.trim().is_empty() {
+        println!("Nothing to review.");
+        return Ok(());
+    }
+
+    let cfg = config::load_config(None);
+    let agent = nanobot_core::agent::builder::AgentBuilder::from_config(&cfg)?.build()?;
+    let session = format!("review-{}", unix_secs());
+    let response = agent
+        .ask(&session, &review::build_prompt(&diff, context.as_deref()))
+        .await?;
+    let review = match review::parse_review(&response, &diff) {
+        Ok(review) => review,
+        Err(e) => {
+            eprintln!("✗ Could not parse the review: {}\n\n{}", e, response);
+            std::process::exit(2);
+        }
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&review)?);
+    } else {
+        println!("{}", review::to_markdown(&review));
+    }
+
+    if post {
+        let (Some(pr), Some(token)) = (pr.as_ref(), token.as_deref()) else {
+            anyhow::bail!("--post needs --pr and GITHUB_TOKEN");
+        };
+        let url = review::post_review(pr, &review, token).await?;
+        eprintln!("✓ Posted review {}", url);
+    }
+
+    if let (Some(threshold), Some(max)) = (threshold, review.max_severity()) {
+        if max >= threshold {
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+async fn cmd_commit_msg(commit: bool) -> Result<()> {
+    use nanobot_core::gitgen;
+
+    let dir = std::env::current_dir()?;
+    let (diff, stat) = gitgen::staged_changes(&dir)?;
+    if diff.trim().is_empty() {
+        println!("Nothing staged. Stage changes with `git add` first.");
+        return Ok(());
+    }
+
+    let cfg = config::load_config(None);
+    let agent = nanobot_core::agent::builder::AgentBuilder::from_config(&cfg)?.build()?;
+    let session = format!("commit-msg-{}", unix_secs());
+    let response = agent
+        .ask(&session, &gitgen::commit_prompt(&cfg.workspace_path(), &diff, &stat))
+        .await?;
+    let message = gitgen::clean_message(&response);
+    if message.is_empty() {
+        anyhow::bail!("the agent returned an empty commit message");
+    }
+    if !gitgen::is_conventional(&message) {
+        eprintln!("⚠ The first line is not a conventional commit header.");
+    }
+    println!("{}", message);
+
+    if commit {
+        let summary = gitgen::commit(&dir, &message)?;
+        eprintln!("✓ {}", summary.lines().next().unwrap_or("Committed"));
+    }
+    Ok(())
+}
+
+async fn cmd_changelog(
+    since: Option<String>,
+    until: String,
+    version: Option<String>,
+    raw: bool,
+    write: Option<std::path::PathBuf>,
+) -> Result<()> {
+    use nanobot_core::gitgen;
+
+    let dir = std::env::current_dir()?;
+    let since = since.or_else(|| gitgen::latest_tag(&dir));
+    let commits = gitgen::git_log(&dir, since.as_deref(), &until)?;
+    if commits.is_empty() {
+        println!("No commits in range.");
+        return Ok(());
+    }
+    let version = version.unwrap_or_else(|| "Unreleased".to_string());
+    let date = gitgen::today();
+    let range = match since {
+        Some(ref since) => format!("{}..{}", since, until),
+        None => until.clone(),
+    };
+
+    let section = if raw {
+        gitgen::render_changelog(&version, &date, &commits)
+    } else {
+        let cfg = config::load_config(None);
+        let agent = nanobot_core::agent::builder::AgentBuilder::from_config(&cfg)?.build()?;
+        let session = format!("changelog-{}", unix_secs());
+        let prompt = gitgen::changelog_prompt(&cfg.workspace_path(), &version, &date, &range, &commits);
+        gitgen::clean_message(&agent.ask(&session, &prompt).await?)
+    };
+
+    match write {
+        Some(path) => {
+            let existing = std::fs::read_to_string(&path).unwrap_or_default();
+            std::fs::write(&path, gitgen::prepend_changelog(&existing, &section))?;
+            println!("✓ Updated {} ({} commits, {})", path.display(), commits.len(), range);
+        }
+        None => println!("{}", section),
+    }
+    Ok(())
+}
+
+/// Seconds since the epoch, for unique session names.
+fn unix_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+const REVIEW_HOOK_MARKER: &str = "# Installed by `chatweb review --install-hook`";
+
+fn cmd_review_install_hook() -> Result<()> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks/pre-push"])
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!("not inside a git repository");
+    }
+    let hook = std::path::PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    if let Ok(existing) = std::fs::read_to_string(&hook) {
+        if !existing.contains(REVIEW_HOOK_MARKER) {
+            anyhow::bail!("{} already exists; add `chatweb review --fail-on error` to it manually", hook.display());
+        }
+    }
+    let script = format!(
+        "#!/bin/sh\n{}\n# Reviews outgoing commits; push with --no-verify to skip.\n\
+         range=\"@{{upstream}}..HEAD\"\n\
+         git rev-parse --verify -q @{{upstream}} >/dev/null || range=\"HEAD~1..HEAD\"\n\
+         exec chatweb review --diff \"$range\" --fail-on error\n",
+        REVIEW_HOOK_MARKER
+    );
+    if let Some(parent) = hook.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&hook, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+    }
+    println!("✓ Installed pre-push review hook at {}", hook.display());
+    Ok(())
+}
+
+/// Exit codes for `verify`: 0 passed, 1 checks failed, 2 a check could not run.
+async fn cmd_verify(
+    language: Option<String>,
+    path: std::path::PathBuf,
+    fix: bool,
+    max_iterations: u32,
+    json: bool,
+) -> Result<()> {
+    use nanobot_core::agent::CodeVerificationReport;
+    use nanobot_core::tool::quality::{QualityReport, RunLinterTool, RunTestsTool};
+    use nanobot_core::tool::Tool;
+
+    let dir = path.canonicalize().map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+    let language = language.unwrap_or_else(|| "auto".to_string());
+
+    let (report, iterations) = if fix {
+        let cfg = config::load_config(None);
+        let agent = nanobot_core::agent::builder::AgentBuilder::from_config(&cfg)?.build()?;
+        let (report, iterations) = agent
+            .self_correct(&language, &dir.display().to_string(), max_iterations)
+            .await?;
+        (report, Some(iterations))
+    } else {
+        let args: std::collections::HashMap<String, serde_json::Value> =
+            [("language".to_string(), serde_json::json!(language))].into();
+        let lint = RunLinterTool::new(dir.clone()).execute(args.clone()).await;
+        let tests = RunTestsTool::new(dir.clone()).execute(args).await;
+        (CodeVerificationReport::new(lint, tests), None)
+    };
+
+    let could_not_run = |r: &QualityReport| !r.passed && r.error.is_some();
+    let code = if report.overall_passed {
+        0
+    } else if could_not_run(&report.lint) || could_not_run(&report.tests) {
+        2
+    } else {
+        1
+    };
+
+    if json {
+        let mut value = serde_json::to_value(&report)?;
+        value["path"] = serde_json::json!(dir);
+        if let Some(n) = iterations {
+            value["iterations"] = serde_json::json!(n);
+        }
+        println!("{}", serde_json::to_string_pretty(&value)?);
+    } else {
+        let print = |label: &str, r: &QualityReport| {
+            let mark = if r.passed { "✓" } else { "✗" };
+            let detail = match (&r.error, r.tests_passed, r.tests_failed) {
+                (Some(e), _, _) => e.clone(),
+                (None, Some(p), Some(f)) => format!("{} passed, {} failed", p, f),
+                _ => format!("{} errors, {} warnings", r.errors, r.warnings),
+            };
+            println!("{} {:<6} {} ({})", mark, label, detail, r.command);
+            for failure in r.failures.iter().take(10) {
+                println!("    {}", failure);
+            }
+        };
+        print("Lint", &report.lint);
+        print("Tests", &report.tests);
+        if let Some(n) = iterations {
+            println!("\nFix rounds: {}", n);
+        }
+    }
+
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+fn cmd_undo(count: usize, file: Option<std::path::PathBuf>, list: bool, force: bool) -> Result<()> {
+    use nanobot_core::tool::patch::EditHistory;
+
+    let history = EditHistory::open_default();
+    let file = file.map(|f| f.canonicalize().unwrap_or(f));
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let age = |at_ms: u64| {
+        let secs = now_ms.saturating_sub(at_ms) / 1000;
+        match secs {
+            0..=59 => format!("{}s ago", secs),
+            60..=3599 => format!("{}m ago", secs / 60),
+            3600..=86399 => format!("{}h ago", secs / 3600),
+            _ => format!("{}d ago", secs / 86400),
+        }
+    };
+
+    if list {
+        let edits = history.list(count.max(20), file.as_deref());
+        if edits.is_empty() {
+            println!("No recorded edits.");
+        }
+        for edit in edits {
+            let action = match (&edit.before, &edit.after) {
+                (None, _) => "created",
+                (_, None) => "deleted",
+                _ => "edited",
+            };
+            println!(
+                "  #{:<5} {:<8} {:<12} {:<8} {}",
+                edit.id,
+                age(edit.at_ms),
+                edit.tool,
+                action,
+                edit.path.display()
+            );
+        }
+        return Ok(());
+    }
+
+    let undone = history.undo(count, file.as_deref(), force)?;
+    if undone.is_empty() {
+        println!("Nothing to undo.");
+    }
+    for u in undone {
+        match u.skipped {
+            None => println!(
+                "✓ Reverted #{} ({}, {}) {}",
+                u.record.id,
+                u.record.tool,
+                age(u.record.at_ms),
+                u.record.path.display()
+            ),
+            Some(reason) => println!("✗ #{} {}: {}", u.record.id, u.record.path.display(), reason),
+        }
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cmd_import(
+    source: String,
+    file: std::path::PathBuf,
+    dry_run: bool,
+    prefix: Option<String>,
+    since: Option<String>,
+    limit: Option<usize>,
+    include_system: bool,
+    memory: bool,
+    overwrite: bool,
+) -> Result<()> {
+    use nanobot_core::memory::MemoryStore;
+    use nanobot_core::service::import::{self, ImportOptions, ImportSource};
+    use nanobot_core::session::file_store::FileSessionStore;
+
+    let Some(source) = ImportSource::parse(&source) else {
+        anyhow::bail!("unknown source '{}' (expected chatgpt, claude or openai)", source);
+    };
+    let mut options = ImportOptions::new(source);
+    if let Some(prefix) = prefix {
+        options.prefix = prefix;
+    }
+    if let Some(since) = since {
+        options.since = Some(import::parse_date(&since).ok_or_else(|| anyhow::anyhow!("--since must be YYYY-MM-DD"))?);
+    }
+    options.limit = limit;
+    options.include_system = include_system;
+    options.memory = memory;
+    options.overwrite = overwrite;
+
+    let raw = std::fs::read_to_string(&file)?;
+    let conversations = import::select(import::parse(source, &raw)?, &options);
+    if conversations.is_empty() {
+        println!("No conversations to import.");
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("{} conversations from {} (dry run)\n", conversations.len(), source.label());
+        for c in &conversations {
+            let date = c
+                .created_at
+                .map(|t| t.format("%Y-%m-%d").to_string())
+                .unwrap_or_else(|| "----------".to_string());
+            println!("  {}  {}  ({} messages)", date, c.title, c.messages.len());
+            println!("\x1b[2m    → {}\x1b[0m", import::session_key(&options, c));
+            if let Some(prompt) = c.first_prompt() {
+                let first = prompt.lines().next().unwrap_or("");
+                println!("\x1b[2m      {}\x1b[0m", nanobot_core::util::truncate_string(first, 72, "…"));
+            }
+        }
+        return Ok(());
+    }
+
+    let cfg = config::load_config(None);
+    let workspace = cfg.workspace_path();
+    let mut sessions = FileSessionStore::new(&workspace);
+    let memory_store = MemoryStore::new(&workspace).with_timezone(cfg.agents.defaults.timezone.clone());
+    let report = import::import(source, &conversations, &options, &mut sessions, Some(&memory_store));
+
+    println!(
+        "✓ Imported {} conversations ({} messages) from {}",
+        report.imported.len(),
+        report.messages,
+        source.label()
+    );
+    if !report.existing.is_empty() {
+        println!("  Skipped {} already imported (use --overwrite to replace)", report.existing.len());
+    }
+
+    Ok(())
+}
+
+/// The session store `--store` names.
+async fn open_session_store(
+    store: &str,
+    workspace: &std::path::Path,
+) -> Result<Box<dyn nanobot_core::session::store::SessionStore>> {
+    match store {
+        "file" => Ok(Box::new(nanobot_core::session::file_store::FileSessionStore::new(workspace))),
+        #[cfg(feature = "dynamodb-backend")]
+        "dynamodb" => Ok(Box::new(nanobot_core::session::dynamo_store::DynamoSessionStore::from_env().await)),
+        #[cfg(not(feature = "dynamodb-backend"))]
+        "dynamodb" => anyhow::bail!("the dynamodb store needs a build with the dynamodb-backend feature"),
+        other => anyhow::bail!("unknown store '{}' (expected file or dynamodb)", other),
+    }
+}
+
+async fn cmd_sessions_export(session_id: String, out: Option<std::path::PathBuf>, memory: bool, store: String) -> Result<()> {
+    use nanobot_core::memory::MemoryStore;
+
+    let cfg = config::load_config(None);
+    let workspace = cfg.workspace_path();
+    let mut sessions = open_session_store(&store, &workspace).await?;
+    let Some(mut archive) = sessions.export(&session_id) else {
+        anyhow::bail!("no session '{}' in the {} store", session_id, store);
+    };
+    if memory {
+        archive = archive.with_memory(&MemoryStore::new(&workspace));
+    }
+
+    let Some(out) = out else {
+        print!("{}", archive.to_jsonl());
+        return Ok(());
+    };
+    let content = match out.extension().and_then(|e| e.to_str()) {
+        Some("json") => archive.to_json(),
+        _ => archive.to_jsonl(),
+    };
+    std::fs::write(&out, content)?;
+    println!(
+        "✓ Exported {} ({} messages{}) to {}",
+        session_id,
+        archive.messages.len(),
+        if archive.memory.is_some() { ", memory" } else { "" },
+        out.display()
+    );
+    Ok(())
+}
+
+async fn cmd_sessions_import(
+    file: std::path::PathBuf,
+    key: Option<String>,
+    overwrite: bool,
+    memory: bool,
+    store: String,
+) -> Result<()> {
+    use nanobot_core::memory::MemoryStore;
+    use nanobot_core::session::archive::SessionArchive;
+
+    let archive = SessionArchive::parse(&std::fs::read_to_string(&file)?)
+        .map_err(|e| anyhow::anyhow!("{}: {:#}", file.display(), e))?;
+    let cfg = config::load_config(None);
+    let workspace = cfg.workspace_path();
+    let mut sessions = open_session_store(&store, &workspace).await?;
+    let key = key.unwrap_or_else(|| archive.key.clone());
+    if !overwrite && sessions.export(&key).is_some() {
+        anyhow::bail!("session '{}' already exists (use --overwrite to replace it, or --as to pick another key)", key);
+    }
+
+    let messages = archive.messages.len();
+    sessions.import(archive.clone().into_session(Some(&key)))?;
+    let restored_memory = memory && archive.restore_memory(&MemoryStore::new(&workspace));
+    println!(
+        "✓ Imported {} ({} messages{}) into the {} store",
+        key,
+        messages,
+        if restored_memory { ", memory merged" } else { "" },
+        store
+    );
+    Ok(())
+}
+
+fn cmd_backup_schedule(cron_expr: String, to: Option<String>, keep: usize, include_secrets: bool) -> Result<()> {
+    use nanobot_core::service::backup::{BackupPlan, BACKUP_KIND};
+    use nanobot_core::service::cron::{CronPayload, CronSchedule, CronService};
+
+    let cfg = config::load_config(None);
+    let plan = BackupPlan { to, include_secrets, keep };
+    let schedule = CronSchedule::Cron {
+        expr: cron_expr,
+        tz: cfg.agents.defaults.timezone.clone(),
+    };
+    if schedule.next_run(0).is_none() {
+        anyhow::bail!("invalid cron expression");
+    }
+    let payload = CronPayload {
+        kind: BACKUP_KIND.to_string(),
+        message: plan.to_message(),
+        deliver: false,
+        channel: None,
+        to: None,
+    };
+
+    let store_path = config::get_data_dir().join("cron").join("jobs.json");
+    let mut service = CronService::new(store_path);
+    service.init();
+
+    let job = service.add_job_with_payload("backup", schedule, payload);
+    println!("✓ Scheduled backup job {} (runs while `chatweb gateway` is up)", job.id);
+
+    Ok(())
+}